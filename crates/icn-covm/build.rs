@@ -0,0 +1,15 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // The gRPC API is generated only for builds with the grpc feature, using
+    // a vendored protoc so no system install is needed
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/icn_covm.proto");
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc unavailable");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::configure()
+            .compile_protos(&["proto/icn_covm.proto"], &["proto"])
+            .expect("failed to compile proto/icn_covm.proto");
+    }
+}
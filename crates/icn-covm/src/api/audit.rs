@@ -0,0 +1,186 @@
+//! Audit logging of mutating API requests
+//!
+//! `audited` wraps the v1 routes and records every request that is not a
+//! `GET`, `HEAD`, or `OPTIONS` in the audit log, with the caller, the method
+//! and path, and the response status. Requests that are rejected are
+//! recorded too, with the status the rejection will be answered with.
+//!
+//! The caller is read from the bearer token, or from the ID of the API key,
+//! without another storage lookup; requests without valid credentials are
+//! recorded as `anonymous`. Entries are handed to a background writer, so
+//! requests never wait for the log, and the writer prunes the log once an
+//! hour according to the retention policy.
+
+use crate::api::auth::{validate_token, JwtConfig};
+use crate::api::keys::{self, API_KEY_HEADER};
+use crate::api::proposal_api::rejection_status;
+use crate::api::v1::tenant;
+use crate::audit::{self, AuditEntry, AuditOutcome, AuditSource, RetentionPolicy};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use warp::filters::path::FullPath;
+use warp::http::Method;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// How often entries outside the retention policy are removed
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Actor recorded for requests without valid credentials
+const ANONYMOUS: &str = "anonymous";
+
+/// Handle for recording entries in the audit log
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    tx: mpsc::UnboundedSender<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Start the writer that appends entries to the VM's storage
+    pub fn start<S>(vm: Arc<Mutex<VM<S>>>, policy: RetentionPolicy) -> Self
+    where
+        S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel::<AuditEntry>();
+        tokio::spawn(async move {
+            if let Some(storage) = vm.lock().await.get_storage_backend_mut() {
+                if let Err(e) = audit::prepare(storage) {
+                    tracing::error!("Failed to set up the audit log: {}", e);
+                }
+            }
+            let mut prune = tokio::time::interval(PRUNE_INTERVAL);
+            loop {
+                tokio::select! {
+                    entry = rx.recv() => {
+                        let Some(entry) = entry else { break };
+                        let mut vm = vm.lock().await;
+                        if let Some(storage) = vm.get_storage_backend_mut() {
+                            if let Err(e) = audit::append(storage, &entry) {
+                                tracing::error!("Failed to write audit entry {}: {}", entry.id, e);
+                            }
+                        }
+                    }
+                    _ = prune.tick() => {
+                        let mut vm = vm.lock().await;
+                        if let Some(storage) = vm.get_storage_backend_mut() {
+                            match audit::prune(storage, &policy, chrono::Utc::now()) {
+                                Ok(0) => {}
+                                Ok(removed) => tracing::info!("Pruned {} audit entries", removed),
+                                Err(e) => tracing::warn!("Failed to prune audit log: {}", e),
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// Queue an entry for writing
+    pub fn record(&self, entry: AuditEntry) {
+        if self.tx.send(entry).is_err() {
+            tracing::warn!("Audit log writer has stopped; entry dropped");
+        }
+    }
+}
+
+pub(crate) fn is_mutating(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Who made a request, as far as their credentials tell
+fn actor(jwt: &JwtConfig, authorization: Option<&str>, api_key: Option<&str>) -> String {
+    if let Some(api_key) = api_key {
+        return keys::key_id(api_key)
+            .map(|id| format!("api-key:{}", id))
+            .unwrap_or_else(|| ANONYMOUS.to_string());
+    }
+    authorization
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .and_then(|token| validate_token(jwt, token.trim()).ok())
+        .map(|claims| claims.sub)
+        .unwrap_or_else(|| ANONYMOUS.to_string())
+}
+
+/// Wrap `routes` so mutating requests are recorded in `log`
+pub fn audited<F, R>(
+    routes: F,
+    log: AuditLog,
+    jwt: JwtConfig,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    // Rejections are carried through as values so they can be recorded
+    let outcome = routes.map(|reply: R| Ok(reply.into_response())).or_else(
+        |rejection: Rejection| async move {
+            Ok::<_, Infallible>((Err::<Response, Rejection>(rejection),))
+        },
+    );
+
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<String>(API_KEY_HEADER))
+        .and(outcome)
+        .and_then(
+            move |method: Method,
+                  path: FullPath,
+                  authorization: Option<String>,
+                  api_key: Option<String>,
+                  result: Result<Response, Rejection>| {
+                let (log, jwt) = (log.clone(), jwt.clone());
+                async move {
+                    if is_mutating(&method) && path.as_str().starts_with("/api/v1/") {
+                        let status = match &result {
+                            Ok(response) => response.status(),
+                            Err(rejection) => rejection_status(rejection),
+                        };
+                        let outcome = if status.is_success() || status.is_redirection() {
+                            AuditOutcome::Success
+                        } else {
+                            AuditOutcome::Failure
+                        };
+                        let mut entry = AuditEntry::new(
+                            AuditSource::Api,
+                            actor(&jwt, authorization.as_deref(), api_key.as_deref()),
+                            format!("{} {}", method, path.as_str()),
+                            outcome,
+                        );
+                        entry.namespace = tenant::coop_namespace(path.as_str());
+                        entry.status = Some(status.as_u16());
+                        entry.detail = result.as_ref().err().map(|r| format!("{:?}", r));
+                        log.record(entry);
+                    }
+                    result
+                }
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::auth::issue_token;
+
+    #[test]
+    fn test_actor_from_credentials() {
+        let jwt = JwtConfig::new("test-secret");
+        let token = issue_token(&jwt, "did:key:alice", Vec::new(), Vec::new()).unwrap();
+        let bearer = format!("Bearer {}", token);
+
+        assert_eq!(actor(&jwt, Some(&bearer), None), "did:key:alice");
+        assert_eq!(actor(&jwt, Some("Bearer forged"), None), ANONYMOUS);
+        assert_eq!(actor(&jwt, None, None), ANONYMOUS);
+        assert_eq!(
+            actor(&jwt, None, Some("icn_k1_secret")),
+            "api-key:k1".to_string()
+        );
+    }
+}
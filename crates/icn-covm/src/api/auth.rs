@@ -0,0 +1,400 @@
+//! JWT authentication for the HTTP API
+//!
+//! Clients obtain a token from `POST /api/v1/auth/token` by signing a login
+//! challenge with the private key of a registered identity. Each challenge
+//! names the time it was signed and is accepted once: an identity's next
+//! login must sign a later time, so a captured challenge cannot be replayed.
+//! The token carries
+//! the identity's roles and memberships as claims, and every authenticated
+//! request has its claims turned back into an `AuthContext`, so storage
+//! permission checks apply to API callers exactly as they do in the CLI.
+//! Service integrations can use scoped API keys instead; see `api::keys`.
+//! Members of cooperatives running an OpenID Connect provider can log in
+//! through it; see `api::oidc`.
+
+use crate::api::keys::{validate_api_key, API_KEY_HEADER};
+use crate::identity::Identity;
+pub use crate::storage::auth::{memberships_key, roles_key};
+use crate::storage::auth::{AuthContext, RoleAssignment, IDENTITY_NAMESPACE};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::{Filter, Rejection, Reply};
+
+/// Environment variable holding the token signing secret
+pub const JWT_SECRET_ENV: &str = "ICN_JWT_SECRET";
+
+/// Issuer recorded in every token
+const ISSUER: &str = "icn-covm";
+
+/// How far a login challenge timestamp may drift from the server clock
+const MAX_CLOCK_SKEW_SECS: u64 = 5 * 60;
+
+/// Errors raised while issuing or validating tokens
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Missing or malformed Authorization header")]
+    MissingToken,
+
+    #[error("Invalid or revoked API key")]
+    InvalidApiKey,
+
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+
+    #[error("Unknown identity: {0}")]
+    UnknownIdentity(String),
+
+    #[error("No approved identity binding for {0}")]
+    UnboundAccount(String),
+
+    #[error("Login challenge expired")]
+    ExpiredChallenge,
+
+    #[error("Login challenge already used")]
+    ReusedChallenge,
+
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Failed to issue token: {0}")]
+    Issue(String),
+}
+
+impl warp::reject::Reject for AuthError {}
+
+/// Token signing configuration
+#[derive(Clone)]
+pub struct JwtConfig {
+    secret: Vec<u8>,
+    /// Lifetime of issued tokens in seconds
+    pub ttl_secs: u64,
+}
+
+// Never print the signing secret
+impl Debug for JwtConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtConfig")
+            .field("ttl_secs", &self.ttl_secs)
+            .finish()
+    }
+}
+
+impl JwtConfig {
+    /// Create a configuration with the given secret and a one hour token lifetime
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+            ttl_secs: 60 * 60,
+        }
+    }
+
+    /// Read the secret from `ICN_JWT_SECRET`, or generate a random one
+    ///
+    /// A generated secret invalidates all tokens when the server restarts.
+    pub fn from_env() -> Self {
+        match std::env::var(JWT_SECRET_ENV) {
+            Ok(secret) if !secret.is_empty() => Self::new(secret),
+            _ => {
+                tracing::warn!(
+                    "{} not set; using a random secret, tokens will not survive a restart",
+                    JWT_SECRET_ENV
+                );
+                let mut secret = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut secret);
+                Self::new(secret)
+            }
+        }
+    }
+}
+
+/// Claims carried by an API token
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Claims {
+    /// DID of the authenticated identity
+    pub sub: String,
+    /// Roles held by the identity
+    pub roles: Vec<RoleAssignment>,
+    /// Namespaces the identity is a member of
+    pub memberships: Vec<String>,
+    pub iss: String,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+impl Claims {
+    /// Build the `AuthContext` for a request made with these claims
+    pub fn to_auth_context(&self) -> AuthContext {
+        let mut auth = AuthContext::new(&self.sub);
+        for assignment in &self.roles {
+            auth.add_role(&assignment.namespace, &assignment.role);
+        }
+        for namespace in &self.memberships {
+            auth.add_membership(&self.sub, namespace);
+        }
+        auth
+    }
+}
+
+/// Context the server uses for its own lookups of identity records
+pub(crate) fn system_auth() -> AuthContext {
+    let mut auth = AuthContext::new("system");
+    auth.add_role("global", "admin");
+    auth
+}
+
+/// Storage key holding the timestamp of the last challenge an identity
+/// logged in with
+fn last_login_key(identity_did: &str) -> String {
+    format!("identities/{}/last_login", identity_did)
+}
+
+/// Bytes a client signs to log in as `identity_did` at `timestamp`
+pub fn login_challenge(identity_did: &str, timestamp: u64) -> Vec<u8> {
+    format!("icn-login|{}|{}", identity_did, timestamp).into_bytes()
+}
+
+/// Issue a token for an identity with the given roles and memberships
+pub fn issue_token(
+    config: &JwtConfig,
+    identity_did: &str,
+    roles: Vec<RoleAssignment>,
+    memberships: Vec<String>,
+) -> Result<String, AuthError> {
+    let now = crate::storage::utils::now_with_default();
+    let claims = Claims {
+        sub: identity_did.to_string(),
+        roles,
+        memberships,
+        iss: ISSUER.to_string(),
+        iat: now,
+        exp: now + config.ttl_secs,
+    };
+    encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(&config.secret),
+    )
+    .map_err(|e| AuthError::Issue(e.to_string()))
+}
+
+/// Validate a token's signature, issuer, and expiry, returning its claims
+pub fn validate_token(config: &JwtConfig, token: &str) -> Result<Claims, AuthError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[ISSUER]);
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&config.secret),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .map_err(|e| AuthError::InvalidToken(e.to_string()))
+}
+
+/// Filter extracting the caller's `AuthContext`
+///
+/// Callers authenticate with a bearer token in the `Authorization` header,
+/// or with an `access_token` query parameter for clients such as browser
+/// WebSockets that cannot set headers. Service integrations may instead send
+/// a scoped API key in the `x-api-key` header, which maps to a context
+/// holding only the key's roles. Requests without valid credentials are
+/// rejected with `AuthError`.
+pub fn with_auth<S>(
+    config: JwtConfig,
+    vm: Arc<Mutex<VM<S>>>,
+) -> impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    warp::header::optional::<String>("authorization")
+        .and(warp::header::optional::<String>(API_KEY_HEADER))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(
+            move |header: Option<String>,
+                  api_key: Option<String>,
+                  query: HashMap<String, String>| {
+                let config = config.clone();
+                let vm = vm.clone();
+                async move {
+                    let token = header
+                        .as_deref()
+                        .and_then(|h| h.strip_prefix("Bearer "))
+                        .or_else(|| query.get("access_token").map(String::as_str));
+                    authenticate(&config, &vm, token, api_key.as_deref())
+                        .await
+                        .map_err(warp::reject::custom)
+                }
+            },
+        )
+}
+
+/// Resolve the caller's `AuthContext` from an API key or a bearer token
+///
+/// An API key takes precedence when both are given. Shared by the HTTP and
+/// gRPC APIs so both accept the same credentials.
+pub async fn authenticate<S>(
+    config: &JwtConfig,
+    vm: &Mutex<VM<S>>,
+    token: Option<&str>,
+    api_key: Option<&str>,
+) -> Result<AuthContext, AuthError>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if let Some(api_key) = api_key {
+        let vm_lock = vm.lock().await;
+        return vm_lock
+            .get_storage_backend()
+            .and_then(|storage| validate_api_key(storage, api_key))
+            .map(|record| record.to_auth_context())
+            .ok_or(AuthError::InvalidApiKey);
+    }
+
+    let token = token.ok_or(AuthError::MissingToken)?;
+    validate_token(config, token.trim()).map(|claims| claims.to_auth_context())
+}
+
+/// Body of a token request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenRequest {
+    /// DID of the identity logging in
+    pub did: String,
+    /// Unix timestamp included in the signed challenge
+    pub timestamp: u64,
+    /// Multibase signature over `login_challenge(did, timestamp)`
+    pub signature: String,
+}
+
+/// Token response body
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub token: String,
+    pub expires_in: u64,
+}
+
+/// Route for POST /auth/token
+pub fn token_route<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    config: JwtConfig,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    warp::path!("auth" / "token")
+        .and(warp::post())
+        .and(warp::body::json::<TokenRequest>())
+        .and(warp::any().map(move || vm.clone()))
+        .and(warp::any().map(move || config.clone()))
+        .and_then(issue_token_handler)
+}
+
+/// Handler for POST /auth/token
+async fn issue_token_handler<S>(
+    request: TokenRequest,
+    vm: Arc<Mutex<VM<S>>>,
+    config: JwtConfig,
+) -> Result<impl Reply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let now = crate::storage::utils::now_with_default();
+    if now.abs_diff(request.timestamp) > MAX_CLOCK_SKEW_SECS {
+        return Err(warp::reject::custom(AuthError::ExpiredChallenge));
+    }
+
+    let mut vm_lock = vm.lock().await;
+    let storage = vm_lock
+        .get_storage_backend_mut()
+        .ok_or_else(|| warp::reject::custom(AuthError::UnknownIdentity(request.did.clone())))?;
+
+    let identity: Identity = storage
+        .get_identity(&request.did)
+        .map_err(|_| warp::reject::custom(AuthError::UnknownIdentity(request.did.clone())))?;
+    identity
+        .verify(
+            &login_challenge(&request.did, request.timestamp),
+            &request.signature,
+        )
+        .map_err(|e| warp::reject::custom(AuthError::InvalidSignature(e.to_string())))?;
+
+    let system = system_auth();
+    let key = last_login_key(&request.did);
+    let last_login: Option<u64> = storage
+        .get_json(Some(&system), IDENTITY_NAMESPACE, &key)
+        .ok();
+    if last_login.is_some_and(|last| request.timestamp <= last) {
+        return Err(warp::reject::custom(AuthError::ReusedChallenge));
+    }
+    storage
+        .set_json(Some(&system), IDENTITY_NAMESPACE, &key, &request.timestamp)
+        .map_err(|e| warp::reject::custom(AuthError::Issue(e.to_string())))?;
+
+    let response =
+        token_for_identity(&*storage, &config, &request.did).map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+/// Issue a token carrying the stored roles and memberships of `identity_did`
+///
+/// Used by every login route once the caller has proven who they are.
+pub(crate) fn token_for_identity<S>(
+    storage: &S,
+    config: &JwtConfig,
+    identity_did: &str,
+) -> Result<TokenResponse, AuthError>
+where
+    S: Storage + StorageExtensions,
+{
+    let roles: Vec<RoleAssignment> = storage
+        .get_json(Some(&system_auth()), "identity", &roles_key(identity_did))
+        .unwrap_or_default();
+    let memberships: Vec<String> = storage
+        .get_json(
+            Some(&system_auth()),
+            "identity",
+            &memberships_key(identity_did),
+        )
+        .unwrap_or_default();
+
+    let token = issue_token(config, identity_did, roles, memberships)?;
+    Ok(TokenResponse {
+        token,
+        expires_in: config.ttl_secs,
+    })
+}
+
+/// Map authentication rejections to 401 responses
+pub fn auth_rejection_status(err: &Rejection) -> Option<(warp::http::StatusCode, String)> {
+    err.find::<AuthError>()
+        .map(|e| (warp::http::StatusCode::UNAUTHORIZED, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_round_trip_builds_auth_context() {
+        let config = JwtConfig::new("test-secret");
+        let roles = vec![RoleAssignment {
+            namespace: "coops/alpha".to_string(),
+            role: "admin".to_string(),
+        }];
+        let token =
+            issue_token(&config, "did:key:alice", roles, vec!["coops/alpha".into()]).unwrap();
+
+        let claims = validate_token(&config, &token).unwrap();
+        let auth = claims.to_auth_context();
+        assert_eq!(auth.identity_did(), "did:key:alice");
+        assert!(auth.has_role("coops/alpha", "admin"));
+        assert!(auth.is_member("did:key:alice", "coops/alpha"));
+
+        assert!(validate_token(&JwtConfig::new("other-secret"), &token).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+//! Periodic materialization of demurrage
+//!
+//! Balances of resources with a demurrage policy decay lazily: reads show
+//! the decayed amount, but storage keeps the last written balance until the
+//! account is next used. The job started here writes the decay due on every
+//! account once an hour, so stored balances and total supplies stay current
+//! for holders that never transact.
+
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{apply_all_demurrage, Storage, StorageExtensions};
+use crate::vm::VM;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// How often demurrage is written to stored balances
+const APPLY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Start the job that applies demurrage to the VM's storage
+pub fn start<S>(vm: Arc<Mutex<VM<S>>>)
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut auth = AuthContext::new("system");
+    auth.add_role("global", "admin");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(APPLY_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut vm = vm.lock().await;
+            if let Some(storage) = vm.get_storage_backend_mut() {
+                match apply_all_demurrage(storage, Some(&auth)) {
+                    Ok(0) => {}
+                    Ok(charged) => tracing::info!("Applied demurrage to {} resources", charged),
+                    Err(e) => tracing::warn!("Failed to apply demurrage: {}", e),
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,458 @@
+//! gRPC API, served next to the HTTP API with `--features grpc`
+//!
+//! The services in `proto/icn_covm.proto` cover proposals, votes,
+//! executions, and the DAG ledger, for integrations that prefer protobuf
+//! contracts to JSON. They share the HTTP API's auth model:
+//!
+//! - callers send a bearer token in the `authorization` metadata, or a
+//!   scoped API key in `x-api-key`; anything else is `UNAUTHENTICATED`
+//! - `x-icn-coop: {coop}` binds a call to the namespace `coops/{coop}`, like
+//!   the `/api/v1/coops/{coop}` routes, and is `PERMISSION_DENIED` for
+//!   callers outside the cooperative
+//! - every call runs as the caller, so storage permission checks apply
+//!
+//! Handlers reuse the HTTP handlers' helpers, so both APIs answer alike.
+
+use crate::api::auth::{self, JwtConfig};
+use crate::api::keys::API_KEY_HEADER;
+use crate::api::v1::executions::Target;
+use crate::api::v1::models::{ExecuteProgramRequest, ProposalListItem, ProposalListQuery};
+use crate::api::v1::tenant::{self, ScopedVm, TenantAccessDenied};
+use crate::api::v1::{ledger, proposals};
+use crate::cli::proposal::{count_votes, handle_vote_command, VMProposalExtensions};
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::StorageError;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use icn_ledger::DagNode;
+use std::error::Error;
+use std::fmt::Debug;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tonic::{Request, Response, Status};
+
+/// Types and service stubs generated from `proto/icn_covm.proto`
+pub mod pb {
+    tonic::include_proto!("icn.covm.v1");
+}
+
+use pb::execute_request;
+use pb::executions_server::{Executions, ExecutionsServer};
+use pb::ledger_server::{Ledger, LedgerServer};
+use pb::proposals_server::{Proposals, ProposalsServer};
+use pb::votes_server::{Votes, VotesServer};
+
+/// Metadata key naming the cooperative a call is scoped to
+pub const COOP_METADATA: &str = "x-icn-coop";
+
+/// Serve the gRPC API on `port` until the server fails
+pub async fn serve<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    jwt: JwtConfig,
+    port: u16,
+) -> Result<(), tonic::transport::Error>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let api = GrpcApi { vm, jwt };
+    tonic::transport::Server::builder()
+        .add_service(ProposalsServer::new(api.clone()))
+        .add_service(VotesServer::new(api.clone()))
+        .add_service(ExecutionsServer::new(api.clone()))
+        .add_service(LedgerServer::new(api))
+        .serve(SocketAddr::from(([0, 0, 0, 0], port)))
+        .await
+}
+
+/// Implementation of every gRPC service over one shared VM
+#[derive(Debug, Clone)]
+pub struct GrpcApi<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    vm: Arc<Mutex<VM<S>>>,
+    jwt: JwtConfig,
+}
+
+/// Text of a metadata entry, if present and printable
+fn metadata_str<'a, T>(request: &'a Request<T>, key: &str) -> Option<&'a str> {
+    request
+        .metadata()
+        .get(key)
+        .and_then(|value| value.to_str().ok())
+}
+
+impl<S> GrpcApi<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    /// Authenticate a call and bind the VM to its cooperative, if any
+    async fn authorize<T>(
+        &self,
+        request: &Request<T>,
+    ) -> Result<(AuthContext, ScopedVm<S>), Status> {
+        let token =
+            metadata_str(request, "authorization").and_then(|h| h.strip_prefix("Bearer "));
+        let auth = auth::authenticate(
+            &self.jwt,
+            &self.vm,
+            token,
+            metadata_str(request, API_KEY_HEADER),
+        )
+        .await
+        .map_err(|e| Status::unauthenticated(e.to_string()))?;
+
+        let namespace = metadata_str(request, COOP_METADATA)
+            .filter(|coop| !coop.is_empty())
+            .map(|coop| format!("coops/{}", coop));
+        if let Some(namespace) = &namespace {
+            if !tenant::can_access(&auth, namespace) {
+                let denied = TenantAccessDenied {
+                    namespace: namespace.clone(),
+                };
+                return Err(Status::permission_denied(denied.to_string()));
+            }
+        }
+        Ok((auth, ScopedVm::new(self.vm.clone(), namespace)))
+    }
+}
+
+impl From<ProposalListItem> for pb::ProposalListItem {
+    fn from(item: ProposalListItem) -> Self {
+        Self {
+            id: item.id,
+            title: item.title,
+            creator: item.creator,
+            status: item.status,
+            labels: item.labels,
+            created_at: item.created_at,
+        }
+    }
+}
+
+impl From<&DagNode> for pb::LedgerNode {
+    fn from(node: &DagNode) -> Self {
+        Self {
+            id: node.id.clone(),
+            parent_ids: node.parent_ids.clone(),
+            timestamp: node.timestamp,
+            namespace: node.namespace.clone(),
+            data_json: serde_json::to_string(&node.data).unwrap_or_default(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<S> Proposals for GrpcApi<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    async fn list_proposals(
+        &self,
+        request: Request<pb::ListProposalsRequest>,
+    ) -> Result<Response<pb::ProposalPage>, Status> {
+        let (auth, vm) = self.authorize(&request).await?;
+        let request = request.into_inner();
+        let query = ProposalListQuery {
+            namespace: request.namespace,
+            status: request.status,
+            creator: request.creator,
+            label: request.label,
+            page: request.page.map(|page| page as usize),
+            per_page: request.per_page.map(|per_page| per_page as usize),
+            limit: None,
+            offset: None,
+            sort: request.sort,
+            cursor: request.cursor,
+        };
+
+        let vm_lock = vm.lock().await;
+        // Calls scoped to a cooperative cannot list another namespace
+        let namespace = match (vm.namespace(), &query.namespace) {
+            (None, Some(namespace)) => namespace.clone(),
+            _ => vm_lock.get_namespace().unwrap_or("default").to_string(),
+        };
+        let page =
+            proposals::list_page(&vm_lock, &auth, &namespace, &query).map_err(|e| match e {
+                proposals::ListError::Invalid(message) => Status::invalid_argument(message),
+                proposals::ListError::Unavailable => Status::internal("Storage not available"),
+                proposals::ListError::Storage(e @ StorageError::PermissionDenied { .. }) => {
+                    Status::permission_denied(e.to_string())
+                }
+                proposals::ListError::Storage(e @ StorageError::NotFound { .. }) => {
+                    Status::not_found(e.to_string())
+                }
+                proposals::ListError::Storage(e) => Status::internal(e.to_string()),
+            })?;
+
+        Ok(Response::new(pb::ProposalPage {
+            total: page.total as u64,
+            page: page.page as u32,
+            per_page: page.per_page as u32,
+            next_cursor: page.next_cursor,
+            proposals: page.proposals.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_proposal(
+        &self,
+        request: Request<pb::GetProposalRequest>,
+    ) -> Result<Response<pb::ProposalDetails>, Status> {
+        let (auth, vm) = self.authorize(&request).await?;
+        let id = request.into_inner().id;
+
+        let mut vm_lock = vm.lock().await;
+        let (proposal, (yes, no, abstain)) = vm_lock
+            .with_auth_context(auth, |vm| -> Result<_, Box<dyn Error>> {
+                let proposal = vm.get_proposal(&id)?;
+                let counts = count_votes(vm, &id)?;
+                Ok((proposal, counts))
+            })
+            .map_err(|e| Status::not_found(e.to_string()))?;
+
+        Ok(Response::new(pb::ProposalDetails {
+            id: proposal.id,
+            creator: proposal.creator,
+            status: format!("{:?}", proposal.status),
+            labels: proposal.labels,
+            created_at: proposal.created_at.to_rfc3339(),
+            votes: Some(pb::VoteCounts {
+                yes,
+                no,
+                abstain,
+                total: yes + no + abstain,
+            }),
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl<S> Votes for GrpcApi<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    async fn cast_vote(
+        &self,
+        request: Request<pb::CastVoteRequest>,
+    ) -> Result<Response<pb::CastVoteResponse>, Status> {
+        let (auth, vm) = self.authorize(&request).await?;
+        let pb::CastVoteRequest {
+            proposal_id,
+            choice,
+        } = request.into_inner();
+        let voter = auth.identity_did().to_string();
+
+        let mut vm_lock = vm.lock().await;
+        vm_lock
+            .with_auth_context(auth.clone(), |vm| {
+                handle_vote_command(vm, &proposal_id, &choice, None, &auth, None)
+            })
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+
+        Ok(Response::new(pb::CastVoteResponse {
+            proposal_id,
+            voter,
+            choice: choice.to_lowercase(),
+        }))
+    }
+
+    async fn list_votes(
+        &self,
+        request: Request<pb::ListVotesRequest>,
+    ) -> Result<Response<pb::ListVotesResponse>, Status> {
+        let (auth, vm) = self.authorize(&request).await?;
+        let proposal_id = request.into_inner().proposal_id;
+
+        let mut vm_lock = vm.lock().await;
+        let votes = vm_lock
+            .with_auth_context(auth, |vm| vm.get_proposal_votes(&proposal_id))
+            .map_err(|e| Status::not_found(e.to_string()))?;
+
+        Ok(Response::new(pb::ListVotesResponse {
+            votes: votes
+                .into_iter()
+                .map(|(voter, choice)| pb::Vote { voter, choice })
+                .collect(),
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl<S> Executions for GrpcApi<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    async fn execute(
+        &self,
+        request: Request<pb::ExecuteRequest>,
+    ) -> Result<Response<pb::ExecuteResponse>, Status> {
+        let (auth, vm) = self.authorize(&request).await?;
+        let request = request.into_inner();
+        let sandbox = Some(request.sandbox).filter(|sandbox| !sandbox.is_empty());
+        let request = match request.target {
+            Some(execute_request::Target::Program(program)) => ExecuteProgramRequest {
+                program: Some(program),
+                proposal_id: None,
+                sandbox,
+            },
+            Some(execute_request::Target::ProposalId(proposal_id)) => ExecuteProgramRequest {
+                program: None,
+                proposal_id: Some(proposal_id),
+                sandbox,
+            },
+            None => ExecuteProgramRequest {
+                program: None,
+                proposal_id: None,
+                sandbox,
+            },
+        };
+        let target = Target::from_request(request).map_err(Status::invalid_argument)?;
+        target.authorize(&auth).map_err(Status::permission_denied)?;
+
+        // Run on a blocking thread, as the HTTP API's execution jobs do
+        let mut guard = vm.lock().await;
+        let events = guard.subscribe_events();
+        let (guard, result) = tokio::task::spawn_blocking(move || {
+            let result = guard.with_auth_context(auth, |vm| target.run(vm));
+            (guard, result)
+        })
+        .await
+        .map_err(|e| Status::internal(format!("Execution aborted: {}", e)))?;
+
+        // Events are emitted synchronously, so all of them are queued by now
+        let output = events
+            .try_iter()
+            .map(|event| pb::ExecutionEvent {
+                category: event.category,
+                message: event.message,
+                severity: event.severity.to_string(),
+            })
+            .collect();
+        Ok(Response::new(pb::ExecuteResponse {
+            success: result.is_ok(),
+            error: result.err(),
+            output,
+            stack: guard.get_stack().iter().map(ToString::to_string).collect(),
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl<S> Ledger for GrpcApi<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    async fn get_node(
+        &self,
+        request: Request<pb::GetNodeRequest>,
+    ) -> Result<Response<pb::LedgerNode>, Status> {
+        let (auth, _) = self.authorize(&request).await?;
+        let id = request.into_inner().id;
+
+        let vm_lock = self.vm.lock().await;
+        let ledger = vm_lock
+            .get_dag()
+            .ok_or_else(|| Status::not_found("No DAG ledger configured"))?;
+        // Unreadable nodes are reported as missing so their existence is not leaked
+        match ledger.find_by_id(&id) {
+            Some(node) if ledger::can_read(&auth, &node.namespace) => {
+                Ok(Response::new(node.into()))
+            }
+            _ => Err(Status::not_found(format!("Node {} not found", id))),
+        }
+    }
+
+    async fn trace_proposal(
+        &self,
+        request: Request<pb::TraceProposalRequest>,
+    ) -> Result<Response<pb::ProposalTrace>, Status> {
+        let (auth, _) = self.authorize(&request).await?;
+        let proposal_id = request.into_inner().proposal_id;
+
+        let vm_lock = self.vm.lock().await;
+        let ledger = vm_lock
+            .get_dag()
+            .ok_or_else(|| Status::not_found("No DAG ledger configured"))?;
+        let start_ids: Vec<String> = ledger
+            .find_proposal_related_nodes(&proposal_id)
+            .into_iter()
+            .filter(|node| ledger::can_read(&auth, &node.namespace))
+            .map(|node| node.id)
+            .collect();
+        if start_ids.is_empty() {
+            return Err(Status::not_found(format!(
+                "No ledger nodes for proposal {}",
+                proposal_id
+            )));
+        }
+
+        let mut nodes: Vec<DagNode> = ledger
+            .export_selected(&start_ids)
+            .into_iter()
+            .filter(|node| ledger::can_read(&auth, &node.namespace))
+            .collect();
+        nodes.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.id.cmp(&b.id)));
+
+        Ok(Response::new(pb::ProposalTrace {
+            proposal_id,
+            nodes: nodes.iter().map(Into::into).collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use tonic::metadata::MetadataValue;
+
+    fn api() -> GrpcApi<InMemoryStorage> {
+        GrpcApi {
+            vm: Arc::new(Mutex::new(VM::with_storage_backend(InMemoryStorage::new()))),
+            jwt: JwtConfig::new("test-secret"),
+        }
+    }
+
+    fn request(token: Option<&str>, coop: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        if let Some(token) = token {
+            let value = MetadataValue::try_from(format!("Bearer {}", token)).unwrap();
+            request.metadata_mut().insert("authorization", value);
+        }
+        if let Some(coop) = coop {
+            request
+                .metadata_mut()
+                .insert(COOP_METADATA, MetadataValue::try_from(coop).unwrap());
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn test_authorize_checks_token_and_coop() {
+        let api = api();
+        let token = auth::issue_token(
+            &api.jwt,
+            "did:key:alice",
+            Vec::new(),
+            vec!["coops/alpha".to_string()],
+        )
+        .unwrap();
+
+        let err = api.authorize(&request(None, None)).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+
+        let (auth, vm) = api
+            .authorize(&request(Some(&token), Some("alpha")))
+            .await
+            .unwrap();
+        assert_eq!(auth.identity_did(), "did:key:alice");
+        assert_eq!(vm.namespace(), Some("coops/alpha"));
+
+        let err = api
+            .authorize(&request(Some(&token), Some("beta")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+}
@@ -0,0 +1,214 @@
+//! Liveness and readiness probes for orchestrators
+//!
+//! `GET /healthz` answers as long as the server can handle requests.
+//! `GET /readyz` checks each component the API depends on and answers 503
+//! if any of them is down:
+//!
+//! - `storage`: the storage backend answers a lookup
+//! - `ledger`: the DAG ledger is loaded
+//! - `federation` and `scheduler`: their `Heartbeat` was ticked recently
+//!
+//! Components that are not running in this process are reported as
+//! `disabled` and do not affect readiness.
+
+use crate::api::auth::system_auth;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// State of one component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentState {
+    Up,
+    Down,
+    Disabled,
+}
+
+/// Status of one component in a readiness report
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentStatus {
+    pub name: String,
+    pub state: ComponentState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ComponentStatus {
+    fn new(name: &str, state: ComponentState, detail: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            state,
+            detail,
+        }
+    }
+}
+
+/// Overall readiness with per-component statuses
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// `up` unless a component is down
+    pub status: ComponentState,
+    pub components: Vec<ComponentStatus>,
+}
+
+impl HealthReport {
+    fn from_components(components: Vec<ComponentStatus>) -> Self {
+        let status = if components.iter().any(|c| c.state == ComponentState::Down) {
+            ComponentState::Down
+        } else {
+            ComponentState::Up
+        };
+        Self { status, components }
+    }
+}
+
+/// Liveness signal ticked by a long-running background task
+///
+/// A component is considered down once `max_age_secs` pass without a beat.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    last_beat: Arc<AtomicU64>,
+    max_age_secs: u64,
+}
+
+impl Heartbeat {
+    /// Create a heartbeat that counts as alive from now
+    pub fn new(max_age_secs: u64) -> Self {
+        Self {
+            last_beat: Arc::new(AtomicU64::new(crate::storage::utils::now_with_default())),
+            max_age_secs,
+        }
+    }
+
+    /// Record that the component is alive
+    pub fn beat(&self) {
+        self.last_beat
+            .store(crate::storage::utils::now_with_default(), Ordering::Relaxed);
+    }
+
+    fn status(&self, name: &str, now: u64) -> ComponentStatus {
+        let age = now.saturating_sub(self.last_beat.load(Ordering::Relaxed));
+        let state = if age <= self.max_age_secs {
+            ComponentState::Up
+        } else {
+            ComponentState::Down
+        };
+        ComponentStatus::new(name, state, Some(format!("last heartbeat {}s ago", age)))
+    }
+}
+
+/// Heartbeats of background components running alongside the API
+#[derive(Debug, Clone, Default)]
+pub struct HealthMonitors {
+    pub federation: Option<Heartbeat>,
+    pub scheduler: Option<Heartbeat>,
+}
+
+impl HealthMonitors {
+    fn statuses(&self, now: u64) -> Vec<ComponentStatus> {
+        [
+            ("federation", &self.federation),
+            ("scheduler", &self.scheduler),
+        ]
+        .into_iter()
+        .map(|(name, heartbeat)| match heartbeat {
+            Some(heartbeat) => heartbeat.status(name, now),
+            None => ComponentStatus::new(name, ComponentState::Disabled, None),
+        })
+        .collect()
+    }
+}
+
+/// Routes for GET /healthz and GET /readyz
+pub fn health_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    monitors: HealthMonitors,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let healthz = warp::path!("healthz")
+        .and(warp::get())
+        .map(|| warp::reply::json(&HealthReport::from_components(Vec::new())));
+
+    let readyz = warp::path!("readyz")
+        .and(warp::get())
+        .and(warp::any().map(move || vm.clone()))
+        .and(warp::any().map(move || monitors.clone()))
+        .and_then(readyz_handler);
+
+    healthz.or(readyz)
+}
+
+async fn readyz_handler<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    monitors: HealthMonitors,
+) -> Result<impl Reply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut components = Vec::new();
+    {
+        let vm_lock = vm.lock().await;
+        components.push(match vm_lock.get_storage_backend() {
+            Some(storage) => match storage.contains(Some(&system_auth()), "identity", "healthz") {
+                Ok(_) => ComponentStatus::new("storage", ComponentState::Up, None),
+                Err(e) => {
+                    ComponentStatus::new("storage", ComponentState::Down, Some(e.to_string()))
+                }
+            },
+            None => ComponentStatus::new(
+                "storage",
+                ComponentState::Down,
+                Some("no storage backend configured".to_string()),
+            ),
+        });
+        components.push(match vm_lock.get_dag() {
+            Some(ledger) => ComponentStatus::new(
+                "ledger",
+                ComponentState::Up,
+                Some(format!("{} nodes", ledger.nodes().len())),
+            ),
+            None => ComponentStatus::new("ledger", ComponentState::Disabled, None),
+        });
+    }
+    components.extend(monitors.statuses(crate::storage::utils::now_with_default()));
+
+    let report = HealthReport::from_components(components);
+    let status = if report.status == ComponentState::Up {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&report), status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_heartbeat_marks_report_down() {
+        let heartbeat = Heartbeat::new(30);
+        let monitors = HealthMonitors {
+            federation: Some(heartbeat.clone()),
+            scheduler: None,
+        };
+        let now = crate::storage::utils::now_with_default();
+
+        let report = HealthReport::from_components(monitors.statuses(now));
+        assert_eq!(report.status, ComponentState::Up);
+        assert_eq!(report.components[1].state, ComponentState::Disabled);
+
+        let report = HealthReport::from_components(monitors.statuses(now + 60));
+        assert_eq!(report.status, ComponentState::Down);
+        assert_eq!(report.components[0].state, ComponentState::Down);
+    }
+}
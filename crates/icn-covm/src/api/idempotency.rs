@@ -0,0 +1,446 @@
+//! Idempotency keys for mutating API requests
+//!
+//! A client that may retry a `POST` or `PUT`, such as a mobile app on a flaky
+//! connection, sends an `Idempotency-Key` header with a value unique to the
+//! operation. The first request with a key runs as usual and its response is
+//! kept; a retry with the same key gets that response back, marked with
+//! `Idempotent-Replayed: true`, without running again. Keys are scoped to the
+//! caller's credentials, so two clients cannot see each other's responses.
+//!
+//! Each kept response holds a fingerprint of its request: the method, the
+//! path and query, and a hash of the body. Reusing a key for a different
+//! request is refused with 422, and a retry that arrives while the first
+//! request is still running on the same server is refused with 409. Server
+//! errors and 429 responses are not kept, so those requests may be retried
+//! with the same key.
+//!
+//! Responses are kept in the storage backend under `idempotency/` in the
+//! `system` namespace, so servers sharing a backend share them, and expire
+//! after `ICN_IDEMPOTENCY_TTL` seconds (a day by default). The server sets up
+//! the namespace with `prepare` at startup.
+
+use crate::api::auth::{system_auth, validate_token, JwtConfig};
+use crate::api::keys::API_KEY_HEADER;
+use crate::api::proposal_api::handle_rejection;
+use crate::api::v1::error_reply;
+use crate::storage::auth::ensure_system_namespace;
+use crate::storage::errors::StorageResult;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::storage::utils::now_with_default;
+use crate::vm::VM;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use warp::filters::path::FullPath;
+use warp::http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use warp::http::{Method, Request, StatusCode};
+use warp::hyper::body::{self, Body, Bytes};
+use warp::hyper::service::Service;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Request header naming the operation a request performs
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set on responses replayed from an earlier request
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Environment variable with how long responses are kept, in seconds
+pub const TTL_ENV: &str = "ICN_IDEMPOTENCY_TTL";
+
+/// How long responses are kept unless `ICN_IDEMPOTENCY_TTL` says otherwise
+pub const DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Namespace and key prefix of kept responses
+const NAMESPACE: &str = "system";
+const PREFIX: &str = "idempotency/";
+
+/// Longest key accepted
+const MAX_KEY_LEN: usize = 255;
+
+/// How often expired responses are removed
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// A response kept for replay
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StoredResponse {
+    /// Fingerprint of the request that produced it
+    pub fingerprint: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    /// Base64-encoded body
+    pub body: String,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+/// Fingerprint of a request: its method, path and query, and body
+pub fn fingerprint(method: &Method, path_and_query: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str());
+    hasher.update(b"\n");
+    hasher.update(path_and_query);
+    hasher.update(b"\n");
+    hasher.update(Sha256::digest(body));
+    hex::encode(hasher.finalize())
+}
+
+/// Storage key of the response kept for `key` sent with the credentials in
+/// `scope`
+fn storage_key(scope: &str, key: &str) -> String {
+    format!(
+        "{}{}",
+        PREFIX,
+        hex::encode(Sha256::digest(format!("{}|{}", scope, key)))
+    )
+}
+
+/// Whose keys a request's key is kept among: the identity of a valid bearer
+/// token, or a hash of the API key, which is checked by the route itself
+fn credential_scope(jwt: &JwtConfig, headers: &HeaderMap) -> String {
+    if let Some(api_key) = headers.get(API_KEY_HEADER) {
+        return format!(
+            "api-key:{}",
+            hex::encode(Sha256::digest(api_key.as_bytes()))
+        );
+    }
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .and_then(|token| validate_token(jwt, token.trim()).ok())
+        .map(|claims| claims.sub)
+        .unwrap_or_else(|| "anonymous".to_string())
+}
+
+/// Create the account responses are written as and their namespace, unless
+/// they already exist
+pub fn prepare<S: Storage>(storage: &mut S) -> StorageResult<()> {
+    ensure_system_namespace(storage, &system_auth(), NAMESPACE)
+}
+
+/// The unexpired response kept under `storage_key`, if any
+pub fn load_response<S>(
+    storage: &S,
+    storage_key: &str,
+    now: u64,
+) -> StorageResult<Option<StoredResponse>>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    if !storage.contains(Some(&system), NAMESPACE, storage_key)? {
+        return Ok(None);
+    }
+    let stored: StoredResponse = storage.get_json(Some(&system), NAMESPACE, storage_key)?;
+    Ok(Some(stored).filter(|stored| stored.expires_at > now))
+}
+
+/// Keep a response under `storage_key`
+pub fn save_response<S>(
+    storage: &mut S,
+    storage_key: &str,
+    stored: &StoredResponse,
+) -> StorageResult<()>
+where
+    S: Storage + StorageExtensions,
+{
+    storage.set_json(Some(&system_auth()), NAMESPACE, storage_key, stored)
+}
+
+/// Remove expired responses, returning how many were removed
+pub fn prune<S>(storage: &mut S, now: u64) -> StorageResult<usize>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    let mut removed = 0;
+    for key in storage.list_keys(Some(&system), NAMESPACE, Some(PREFIX))? {
+        let expired = storage
+            .get_json::<StoredResponse>(Some(&system), NAMESPACE, &key)
+            .map(|stored| stored.expires_at <= now)
+            .unwrap_or(true);
+        if expired {
+            storage.delete(Some(&system), NAMESPACE, &key)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// How long responses are kept, from `ICN_IDEMPOTENCY_TTL`
+pub fn ttl_from_env() -> u64 {
+    match std::env::var(TTL_ENV) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            tracing::warn!("Ignoring invalid {}={:?}", TTL_ENV, value);
+            DEFAULT_TTL_SECS
+        }),
+        Err(_) => DEFAULT_TTL_SECS,
+    }
+}
+
+/// Kept responses and the keys of requests still running
+#[derive(Debug, Clone)]
+pub struct IdempotencyCache<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    vm: Arc<Mutex<VM<S>>>,
+    ttl_secs: u64,
+    running: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+/// Marks a key as running until dropped
+struct Running {
+    keys: Arc<std::sync::Mutex<HashSet<String>>>,
+    key: String,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.keys
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.key);
+    }
+}
+
+impl<S> IdempotencyCache<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    /// Keep responses in the VM's storage for `ttl_secs`, removing expired
+    /// ones in the background
+    pub fn start(vm: Arc<Mutex<VM<S>>>, ttl_secs: u64) -> Self {
+        let pruned_vm = vm.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let mut vm = pruned_vm.lock().await;
+                if let Some(storage) = vm.get_storage_backend_mut() {
+                    match prune(storage, now_with_default()) {
+                        Ok(0) => {}
+                        Ok(removed) => tracing::info!("Pruned {} idempotency keys", removed),
+                        Err(e) => tracing::warn!("Failed to prune idempotency keys: {}", e),
+                    }
+                }
+            }
+        });
+        Self {
+            vm,
+            ttl_secs,
+            running: Arc::default(),
+        }
+    }
+
+    fn begin(&self, storage_key: &str) -> Option<Running> {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        running.insert(storage_key.to_string()).then(|| Running {
+            keys: self.running.clone(),
+            key: storage_key.to_string(),
+        })
+    }
+
+    async fn load(&self, storage_key: &str) -> Option<StoredResponse> {
+        let vm = self.vm.lock().await;
+        let storage = vm.get_storage_backend()?;
+        load_response(storage, storage_key, now_with_default()).unwrap_or_else(|e| {
+            tracing::warn!("Failed to read idempotency key: {}", e);
+            None
+        })
+    }
+
+    async fn save(&self, storage_key: &str, stored: &StoredResponse) {
+        let mut vm = self.vm.lock().await;
+        let Some(storage) = vm.get_storage_backend_mut() else {
+            return;
+        };
+        // The request has been applied; failing it now would invite a retry
+        if let Err(e) = save_response(storage, storage_key, stored) {
+            tracing::warn!("Failed to keep response for idempotency key: {}", e);
+        }
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    error_reply(message, status).into_response()
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let body = BASE64.decode(&stored.body).unwrap_or_default();
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let headers = response.headers_mut();
+    if let Some(value) = stored
+        .content_type
+        .and_then(|content_type| HeaderValue::from_str(&content_type).ok())
+    {
+        headers.insert(CONTENT_TYPE, value);
+    }
+    headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Wrap `routes`, mounted under `prefix`, so `POST` and `PUT` requests with
+/// an `Idempotency-Key` header are applied at most once
+///
+/// Keyed requests have their body read here, so they are run by passing the
+/// request again through `prefix` and `routes`; rejections are answered as
+/// the server answers them. The wrapper must itself sit behind `prefix`.
+pub fn idempotent<P, F, R, S>(
+    prefix: P,
+    routes: F,
+    cache: IdempotencyCache<S>,
+    jwt: JwtConfig,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+where
+    P: Filter<Extract = (), Error = Rejection> + Clone + Send + Sync + 'static,
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let service = warp::service(prefix.and(routes.clone()).recover(handle_rejection));
+    let query = warp::query::raw().or(warp::any().map(String::new)).unify();
+
+    let keyed = warp::header::<String>(IDEMPOTENCY_KEY_HEADER)
+        .and(warp::post().or(warp::put()).unify())
+        .and(warp::method())
+        .and(warp::path::full())
+        .and(query)
+        .and(warp::header::headers_cloned())
+        .and(warp::body::bytes())
+        .and_then(
+            move |key: String,
+                  method: Method,
+                  path: FullPath,
+                  query: String,
+                  headers: HeaderMap,
+                  request_body: Bytes| {
+                let (mut service, cache, jwt) = (service.clone(), cache.clone(), jwt.clone());
+                async move {
+                    if key.is_empty() || key.len() > MAX_KEY_LEN {
+                        return Ok::<_, Rejection>(error_response(
+                            StatusCode::BAD_REQUEST,
+                            "Idempotency-Key must be 1 to 255 characters",
+                        ));
+                    }
+                    let uri = match query.as_str() {
+                        "" => path.as_str().to_string(),
+                        query => format!("{}?{}", path.as_str(), query),
+                    };
+                    let fingerprint = fingerprint(&method, &uri, &request_body);
+                    let storage_key = storage_key(&credential_scope(&jwt, &headers), &key);
+
+                    let Some(_running) = cache.begin(&storage_key) else {
+                        return Ok(error_response(
+                            StatusCode::CONFLICT,
+                            "A request with this Idempotency-Key is still running",
+                        ));
+                    };
+                    match cache.load(&storage_key).await {
+                        Some(stored) if stored.fingerprint == fingerprint => {
+                            return Ok(replay(stored))
+                        }
+                        Some(_) => {
+                            return Ok(error_response(
+                                StatusCode::UNPROCESSABLE_ENTITY,
+                                "Idempotency-Key was already used for a different request",
+                            ))
+                        }
+                        None => {}
+                    }
+
+                    let mut request = Request::new(Body::from(request_body));
+                    *request.method_mut() = method;
+                    *request.uri_mut() = uri.parse().map_err(|_| warp::reject::not_found())?;
+                    *request.headers_mut() = headers;
+                    let response = match service.call(request).await {
+                        Ok(response) => response,
+                        Err(never) => match never {},
+                    };
+                    let status = response.status();
+                    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                        return Ok(response);
+                    }
+
+                    let (parts, response_body) = response.into_parts();
+                    let Ok(response_body) = body::to_bytes(response_body).await else {
+                        return Ok(error_response(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "Failed to read the response",
+                        ));
+                    };
+                    let now = now_with_default();
+                    let stored = StoredResponse {
+                        fingerprint,
+                        status: status.as_u16(),
+                        content_type: parts
+                            .headers
+                            .get(CONTENT_TYPE)
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string),
+                        body: BASE64.encode(&response_body),
+                        created_at: now,
+                        expires_at: now.saturating_add(cache.ttl_secs),
+                    };
+                    cache.save(&storage_key, &stored).await;
+                    Ok(Response::from_parts(parts, Body::from(response_body)))
+                }
+            },
+        );
+
+    keyed.or(routes.map(Reply::into_response)).unify()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_kept_responses_expire_and_are_scoped_to_credentials() {
+        let mut storage = InMemoryStorage::new();
+        prepare(&mut storage).unwrap();
+
+        let jwt = JwtConfig::new("test-secret");
+        let token =
+            crate::api::auth::issue_token(&jwt, "did:key:alice", Vec::new(), Vec::new()).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        );
+        assert_eq!(credential_scope(&jwt, &headers), "did:key:alice");
+        let alice = storage_key(&credential_scope(&jwt, &headers), "retry-1");
+        let anonymous = storage_key(&credential_scope(&jwt, &HeaderMap::new()), "retry-1");
+        assert_ne!(alice, anonymous);
+
+        let request = fingerprint(&Method::POST, "/api/v1/comments", b"{\"text\":\"hi\"}");
+        assert_ne!(
+            request,
+            fingerprint(&Method::POST, "/api/v1/comments", b"{\"text\":\"bye\"}")
+        );
+        let stored = StoredResponse {
+            fingerprint: request,
+            status: 201,
+            content_type: Some("application/json".to_string()),
+            body: BASE64.encode(b"{}"),
+            created_at: 100,
+            expires_at: 200,
+        };
+        save_response(&mut storage, &alice, &stored).unwrap();
+
+        assert_eq!(load_response(&storage, &alice, 150).unwrap(), Some(stored));
+        assert_eq!(load_response(&storage, &anonymous, 150).unwrap(), None);
+        assert_eq!(load_response(&storage, &alice, 200).unwrap(), None);
+        assert_eq!(prune(&mut storage, 150).unwrap(), 0);
+        assert_eq!(prune(&mut storage, 200).unwrap(), 1);
+    }
+}
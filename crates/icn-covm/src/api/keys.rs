@@ -0,0 +1,360 @@
+//! Scoped API keys for service integrations
+//!
+//! Bots and other services that cannot sign login challenges authenticate
+//! with an API key sent in the `x-api-key` header. Each key is limited to a
+//! set of namespaces and capabilities, and is mapped to an `AuthContext`
+//! holding only the matching roles, so a leaked key can never do more than
+//! it was issued for.
+//!
+//! Keys have the form `icn_<id>_<secret>`. Only a SHA-256 hash of the
+//! secret is stored, under `api_keys/<id>` in the `identity` namespace.
+//! Records are read and written by the server itself, after checking that
+//! the caller administers every namespace the key is scoped to.
+
+use crate::api::auth::system_auth;
+use crate::api::v1::storage_error_reply;
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// Header carrying an API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Prefix of every API key
+const KEY_PREFIX: &str = "icn";
+
+/// Storage namespace holding key records
+const KEYS_NAMESPACE: &str = "identity";
+
+/// Operation an API key may perform within its namespaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Read,
+    Write,
+}
+
+impl Capability {
+    /// Storage role granted by this capability
+    pub fn role(&self) -> &'static str {
+        match self {
+            Capability::Read => "reader",
+            Capability::Write => "writer",
+        }
+    }
+}
+
+/// Stored record of an API key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub id: String,
+    /// Human readable label, e.g. the name of the integration
+    pub name: String,
+    /// Hex SHA-256 of the key secret
+    key_hash: String,
+    /// DID of the identity that created the key
+    pub owner_did: String,
+    /// Namespaces the key may access
+    pub namespaces: Vec<String>,
+    /// Operations the key may perform in those namespaces
+    pub capabilities: Vec<Capability>,
+    pub created_at: u64,
+    pub revoked: bool,
+}
+
+impl ApiKeyRecord {
+    /// Build the restricted `AuthContext` for requests made with this key
+    pub fn to_auth_context(&self) -> AuthContext {
+        let mut auth = AuthContext::new(&format!("apikey:{}", self.id));
+        for namespace in &self.namespaces {
+            for capability in &self.capabilities {
+                auth.add_role(namespace, capability.role());
+            }
+        }
+        auth
+    }
+}
+
+/// Storage key of an API key record
+pub fn api_key_storage_key(id: &str) -> String {
+    format!("api_keys/{}", id)
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Split a presented key into its ID and secret
+fn parse_key(key: &str) -> Option<(&str, &str)> {
+    let rest = key.strip_prefix(KEY_PREFIX)?.strip_prefix('_')?;
+    let (id, secret) = rest.split_once('_')?;
+    if id.is_empty() || secret.is_empty() {
+        return None;
+    }
+    Some((id, secret))
+}
+
+/// ID of a presented key, without checking its secret
+pub(crate) fn key_id(key: &str) -> Option<&str> {
+    parse_key(key).map(|(id, _)| id)
+}
+
+/// Whether `auth` may issue or revoke keys scoped to `namespaces`
+///
+/// Requires the global admin role or the admin role in every namespace.
+pub fn can_manage(auth: &AuthContext, namespaces: &[String]) -> bool {
+    auth.has_role("global", "admin") || namespaces.iter().all(|ns| auth.has_role(ns, "admin"))
+}
+
+/// Create a key, returning its record and the plaintext key
+///
+/// The plaintext is only available here; it cannot be recovered later.
+pub fn create_api_key<S>(
+    storage: &mut S,
+    auth: &AuthContext,
+    name: &str,
+    namespaces: Vec<String>,
+    capabilities: Vec<Capability>,
+) -> StorageResult<(ApiKeyRecord, String)>
+where
+    S: Storage + StorageExtensions,
+{
+    if namespaces.is_empty() || !can_manage(auth, &namespaces) {
+        return Err(StorageError::PermissionDenied {
+            user_id: auth.identity_did().to_string(),
+            action: "create_api_key".to_string(),
+            key: namespaces.join(","),
+        });
+    }
+
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let secret = hex::encode(secret);
+
+    let record = ApiKeyRecord {
+        id: id.clone(),
+        name: name.to_string(),
+        key_hash: hash_secret(&secret),
+        owner_did: auth.identity_did().to_string(),
+        namespaces,
+        capabilities,
+        created_at: crate::storage::utils::now_with_default(),
+        revoked: false,
+    };
+    storage.set_json(
+        Some(&system_auth()),
+        KEYS_NAMESPACE,
+        &api_key_storage_key(&id),
+        &record,
+    )?;
+
+    Ok((record, format!("{}_{}_{}", KEY_PREFIX, id, secret)))
+}
+
+/// Revoke a key so it is no longer accepted
+pub fn revoke_api_key<S>(storage: &mut S, auth: &AuthContext, id: &str) -> StorageResult<()>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    let key = api_key_storage_key(id);
+    let mut record: ApiKeyRecord = storage.get_json(Some(&system), KEYS_NAMESPACE, &key)?;
+    if !can_manage(auth, &record.namespaces) {
+        return Err(StorageError::PermissionDenied {
+            user_id: auth.identity_did().to_string(),
+            action: "revoke_api_key".to_string(),
+            key,
+        });
+    }
+    record.revoked = true;
+    storage.set_json(Some(&system), KEYS_NAMESPACE, &key, &record)
+}
+
+/// List the keys `auth` is allowed to manage
+pub fn list_api_keys<S>(storage: &S, auth: &AuthContext) -> StorageResult<Vec<ApiKeyRecord>>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    let mut records = Vec::new();
+    for key in storage.list_keys(Some(&system), KEYS_NAMESPACE, Some("api_keys/"))? {
+        let record: ApiKeyRecord = storage.get_json(Some(&system), KEYS_NAMESPACE, &key)?;
+        if can_manage(auth, &record.namespaces) {
+            records.push(record);
+        }
+    }
+    records.sort_by_key(|r| r.created_at);
+    Ok(records)
+}
+
+/// Look up a presented key, returning its record if it is valid
+pub fn validate_api_key<S>(storage: &S, key: &str) -> Option<ApiKeyRecord>
+where
+    S: Storage + StorageExtensions,
+{
+    let (id, secret) = parse_key(key.trim())?;
+    let record: ApiKeyRecord = storage
+        .get_json(
+            Some(&system_auth()),
+            KEYS_NAMESPACE,
+            &api_key_storage_key(id),
+        )
+        .ok()?;
+    if record.revoked || record.key_hash != hash_secret(secret) {
+        return None;
+    }
+    Some(record)
+}
+
+/// Body of a key creation request
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub namespaces: Vec<String>,
+    pub capabilities: Vec<Capability>,
+}
+
+/// Response to a key creation request
+#[derive(Debug, Serialize)]
+pub struct CreateApiKeyResponse {
+    /// The plaintext key, shown only once
+    pub key: String,
+    pub record: ApiKeyRecord,
+}
+
+/// Routes for managing keys under `/api-keys`
+pub fn api_key_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_vm = warp::any().map(move || vm.clone());
+
+    let create = warp::path!("api-keys")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::body::json::<CreateApiKeyRequest>())
+        .and_then(create_handler);
+
+    let list = warp::path!("api-keys")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and_then(list_handler);
+
+    let revoke = warp::path!("api-keys" / String)
+        .and(warp::delete())
+        .and(auth)
+        .and(with_vm)
+        .and_then(revoke_handler);
+
+    create.or(list).or(revoke)
+}
+
+async fn create_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    request: CreateApiKeyRequest,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(
+        match create_api_key(
+            storage,
+            &auth,
+            &request.name,
+            request.namespaces,
+            request.capabilities,
+        ) {
+            Ok((record, key)) => warp::reply::with_status(
+                warp::reply::json(&CreateApiKeyResponse { key, record }),
+                StatusCode::CREATED,
+            ),
+            Err(e) => storage_error_reply(e),
+        },
+    )
+}
+
+async fn list_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(match list_api_keys(storage, &auth) {
+        Ok(records) => warp::reply::with_status(warp::reply::json(&records), StatusCode::OK),
+        Err(e) => storage_error_reply(e),
+    })
+}
+
+async fn revoke_handler<S>(
+    id: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(match revoke_api_key(storage, &auth, &id) {
+        Ok(()) => warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({ "revoked": id })),
+            StatusCode::OK,
+        ),
+        Err(e) => storage_error_reply(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_scopes_map_to_roles() {
+        assert_eq!(parse_key("icn_abc_s3cret"), Some(("abc", "s3cret")));
+        assert_eq!(parse_key("icn_abc_"), None);
+        assert_eq!(parse_key("other_abc_s3cret"), None);
+
+        let record = ApiKeyRecord {
+            id: "abc".to_string(),
+            name: "bot".to_string(),
+            key_hash: hash_secret("s3cret"),
+            owner_did: "did:key:alice".to_string(),
+            namespaces: vec!["coops/alpha".to_string()],
+            capabilities: vec![Capability::Read],
+            created_at: 0,
+            revoked: false,
+        };
+        let auth = record.to_auth_context();
+        assert_eq!(auth.identity_did(), "apikey:abc");
+        assert!(auth.has_role("coops/alpha", "reader"));
+        assert!(!auth.has_role("coops/alpha", "writer"));
+        assert!(!auth.has_role("coops/beta", "reader"));
+    }
+}
@@ -1,13 +1,51 @@
+pub mod audit;
+pub mod auth;
+pub mod demurrage;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
+pub mod idempotency;
+pub mod keys;
+pub mod oidc;
 pub mod proposal_api;
+pub mod rate_limit;
+pub mod replica;
+pub mod usage;
+pub mod v1;
 
+use crate::federation::NodeHandle;
 use crate::storage::traits::{Storage, StorageExtensions};
 use crate::vm::VM;
+use health::HealthMonitors;
+use oidc::OidcProvider;
+use replica::ReplicaConfig;
 use std::fmt::Debug;
 
 /// Initializes and runs the HTTP API server
-pub async fn start_api_server<S>(vm: VM<S>, port: u16) -> Result<(), Box<dyn std::error::Error>>
+///
+/// `node` is a running federation node to manage through the API, if any.
+/// With `grpc_port`, the gRPC API is served on that port as well. With
+/// `replica`, the server runs as a read replica of another API server. With
+/// `oidc`, members can log in through that OpenID Connect provider.
+pub async fn start_api_server<S>(
+    vm: VM<S>,
+    port: u16,
+    grpc_port: Option<u16>,
+    node: Option<NodeHandle>,
+    replica: Option<ReplicaConfig>,
+    oidc: Option<OidcProvider>,
+) -> Result<(), Box<dyn std::error::Error>>
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
-    proposal_api::start_api(vm, port).await
+    proposal_api::start_api(
+        vm,
+        port,
+        grpc_port,
+        HealthMonitors::default(),
+        node,
+        replica,
+        oidc,
+    )
+    .await
 }
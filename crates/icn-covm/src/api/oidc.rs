@@ -0,0 +1,590 @@
+//! OpenID Connect login for the HTTP API
+//!
+//! Cooperatives running an identity provider such as Keycloak or Authentik
+//! can let members log in through it instead of signing a challenge with
+//! their key. The client completes the provider's login flow itself and sends
+//! the ID token it receives to `POST /auth/oidc/token`, which answers with
+//! the same token a key login would issue, for the identity the provider
+//! account is bound to.
+//!
+//! A binding maps a provider account, by issuer and subject, to a registered
+//! identity, and is only used once that identity has approved it:
+//!
+//! 1. `POST /auth/oidc/bindings` with an ID token and the member's DID
+//!    requests a binding; the ID token proves control of the account
+//! 2. `POST /auth/oidc/bindings/{id}/approve`, authenticated as that DID,
+//!    approves it
+//!
+//! The approval is the only step that needs the member's key, so it can be
+//! done once during onboarding, after which the member only uses the
+//! provider. `GET /auth/oidc/bindings` lists the caller's bindings and
+//! `DELETE /auth/oidc/bindings/{id}` removes one, for the member or a global
+//! admin. Records are kept under `oidc_bindings/` in the `identity`
+//! namespace and written by the server itself.
+//!
+//! ID tokens are checked against the provider's JSON Web Key Set, read from
+//! a file or an `http://` URL and read again when a token is signed with a
+//! key it does not hold, so provider key rotation needs no restart.
+
+use crate::api::auth::{system_auth, token_for_identity, AuthError, JwtConfig};
+use crate::api::v1::{storage_error_reply, JsonReply};
+use crate::config::OidcConfig;
+use crate::http::Endpoint;
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// Storage namespace holding binding records
+const BINDINGS_NAMESPACE: &str = "identity";
+
+/// Key prefix of binding records
+const BINDINGS_PREFIX: &str = "oidc_bindings/";
+
+/// Timeout of each request for the provider's keys
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Signature algorithms accepted on ID tokens; shared-secret algorithms are
+/// refused since the keys come from a public key set
+const ALGORITHMS: [Algorithm; 8] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::EdDSA,
+];
+
+/// Claims of a provider ID token used to find its binding
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    /// The account's stable ID at the provider
+    pub sub: String,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// A provider account bound to a registered identity
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OidcBinding {
+    pub id: String,
+    pub issuer: String,
+    pub subject: String,
+    /// DID of the identity the account logs in as
+    pub did: String,
+    /// Email in the ID token that requested the binding, for display
+    pub email: Option<String>,
+    pub requested_at: u64,
+    /// When the identity approved the binding; pending until then
+    pub approved_at: Option<u64>,
+}
+
+impl OidcBinding {
+    pub fn is_approved(&self) -> bool {
+        self.approved_at.is_some()
+    }
+}
+
+/// ID of the binding for a provider account
+pub fn binding_id(issuer: &str, subject: &str) -> String {
+    hex::encode(Sha256::digest(format!("{}|{}", issuer, subject)))[..32].to_string()
+}
+
+fn binding_key(id: &str) -> String {
+    format!("{}{}", BINDINGS_PREFIX, id)
+}
+
+fn denied(auth: &AuthContext, action: &str, id: &str) -> StorageError {
+    StorageError::PermissionDenied {
+        user_id: auth.identity_did().to_string(),
+        action: action.to_string(),
+        key: binding_key(id),
+    }
+}
+
+/// Record a pending binding of the account in `claims` to `did`
+///
+/// A pending binding for the same account is replaced; an approved one must
+/// be removed first.
+pub fn request_binding<S>(
+    storage: &mut S,
+    claims: &IdTokenClaims,
+    did: &str,
+) -> StorageResult<OidcBinding>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    storage.get_identity(did)?;
+    let id = binding_id(&claims.iss, &claims.sub);
+    let key = binding_key(&id);
+    if let Ok(existing) = storage.get_json::<OidcBinding>(Some(&system), BINDINGS_NAMESPACE, &key) {
+        if existing.is_approved() {
+            return Err(StorageError::ConflictError {
+                resource: format!("oidc_binding:{}", existing.id),
+                details: format!("Account is already bound to {}", existing.did),
+            });
+        }
+    }
+    let binding = OidcBinding {
+        id,
+        issuer: claims.iss.clone(),
+        subject: claims.sub.clone(),
+        did: did.to_string(),
+        email: claims.email.clone(),
+        requested_at: crate::storage::utils::now_with_default(),
+        approved_at: None,
+    };
+    storage.set_json(Some(&system), BINDINGS_NAMESPACE, &key, &binding)?;
+    Ok(binding)
+}
+
+/// Approve a binding to the caller's own identity
+pub fn approve_binding<S>(
+    storage: &mut S,
+    auth: &AuthContext,
+    id: &str,
+) -> StorageResult<OidcBinding>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    let key = binding_key(id);
+    let mut binding: OidcBinding = storage.get_json(Some(&system), BINDINGS_NAMESPACE, &key)?;
+    if binding.did != auth.identity_did() {
+        return Err(denied(auth, "approve_oidc_binding", id));
+    }
+    binding
+        .approved_at
+        .get_or_insert_with(crate::storage::utils::now_with_default);
+    storage.set_json(Some(&system), BINDINGS_NAMESPACE, &key, &binding)?;
+    Ok(binding)
+}
+
+/// Remove a binding, as its identity or a global admin
+pub fn remove_binding<S>(storage: &mut S, auth: &AuthContext, id: &str) -> StorageResult<()>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    let key = binding_key(id);
+    let binding: OidcBinding = storage.get_json(Some(&system), BINDINGS_NAMESPACE, &key)?;
+    if binding.did != auth.identity_did() && !auth.has_role("global", "admin") {
+        return Err(denied(auth, "remove_oidc_binding", id));
+    }
+    storage.delete(Some(&system), BINDINGS_NAMESPACE, &key)
+}
+
+/// Bindings of the caller's identity, or every binding for a global admin
+pub fn list_bindings<S>(storage: &S, auth: &AuthContext) -> StorageResult<Vec<OidcBinding>>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    let mut bindings = Vec::new();
+    for key in storage.list_keys(Some(&system), BINDINGS_NAMESPACE, Some(BINDINGS_PREFIX))? {
+        let binding: OidcBinding = storage.get_json(Some(&system), BINDINGS_NAMESPACE, &key)?;
+        if binding.did == auth.identity_did() || auth.has_role("global", "admin") {
+            bindings.push(binding);
+        }
+    }
+    bindings.sort_by_key(|binding| binding.requested_at);
+    Ok(bindings)
+}
+
+/// DID the account in `claims` logs in as, if its binding is approved
+pub fn bound_identity<S>(storage: &S, claims: &IdTokenClaims) -> Option<String>
+where
+    S: Storage + StorageExtensions,
+{
+    let key = binding_key(&binding_id(&claims.iss, &claims.sub));
+    storage
+        .get_json::<OidcBinding>(Some(&system_auth()), BINDINGS_NAMESPACE, &key)
+        .ok()
+        .filter(OidcBinding::is_approved)
+        .map(|binding| binding.did)
+}
+
+/// Read a file, or GET an `http://` URL
+fn read_source(source: &str) -> Result<Vec<u8>, String> {
+    if source.starts_with("http://") {
+        let endpoint = Endpoint::parse(source).map_err(|e| e.to_string())?;
+        let path = if endpoint.base.is_empty() { "/" } else { "" };
+        endpoint
+            .request("GET", path, &[], b"", FETCH_TIMEOUT)
+            .map_err(|e| format!("Failed to fetch {}: {}", source, e))
+    } else if source.contains("://") {
+        Err(format!(
+            "Cannot fetch {}: only files and http:// URLs are supported",
+            source
+        ))
+    } else {
+        std::fs::read(source).map_err(|e| format!("Failed to read {}: {}", source, e))
+    }
+}
+
+/// Validates ID tokens from one provider
+#[derive(Clone)]
+pub struct OidcProvider {
+    config: OidcConfig,
+    keys: Arc<RwLock<JwkSet>>,
+}
+
+// The key set is long and public; the configuration says where it came from
+impl Debug for OidcProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcProvider")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl OidcProvider {
+    /// Check the configuration and read the provider's keys
+    pub fn new(config: OidcConfig) -> Result<Self, String> {
+        if config.issuer.is_empty() || config.client_id.is_empty() {
+            return Err("OIDC login needs both an issuer and a client ID".to_string());
+        }
+        let keys = Self::load_keys(&config)?;
+        Ok(Self {
+            config,
+            keys: Arc::new(RwLock::new(keys)),
+        })
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.config.issuer
+    }
+
+    fn load_keys(config: &OidcConfig) -> Result<JwkSet, String> {
+        let source = match &config.jwks {
+            Some(source) => source.clone(),
+            None => {
+                #[derive(Deserialize)]
+                struct Discovery {
+                    jwks_uri: String,
+                }
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    config.issuer.trim_end_matches('/')
+                );
+                serde_json::from_slice::<Discovery>(&read_source(&url)?)
+                    .map_err(|e| format!("Invalid discovery document at {}: {}", url, e))?
+                    .jwks_uri
+            }
+        };
+        serde_json::from_slice(&read_source(&source)?)
+            .map_err(|e| format!("Invalid JSON Web Key Set at {}: {}", source, e))
+    }
+
+    /// Key the token header names, or the only key when it names none
+    fn find_key(&self, kid: Option<&str>) -> Option<Jwk> {
+        let keys = self
+            .keys
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match kid {
+            Some(kid) => keys.find(kid).cloned(),
+            None if keys.keys.len() == 1 => keys.keys.first().cloned(),
+            None => None,
+        }
+    }
+
+    /// Check an ID token's signature, issuer, audience, and expiry
+    pub fn validate(&self, id_token: &str) -> Result<IdTokenClaims, AuthError> {
+        let invalid = |e: String| AuthError::InvalidToken(e);
+        let header = decode_header(id_token).map_err(|e| invalid(e.to_string()))?;
+        if !ALGORITHMS.contains(&header.alg) {
+            return Err(invalid(format!("{:?} is not accepted", header.alg)));
+        }
+        let jwk = match self.find_key(header.kid.as_deref()) {
+            Some(jwk) => jwk,
+            None => {
+                // The provider may have rotated its keys
+                let keys = Self::load_keys(&self.config).map_err(invalid)?;
+                *self
+                    .keys
+                    .write()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = keys;
+                self.find_key(header.kid.as_deref())
+                    .ok_or_else(|| invalid("signed with an unknown key".to_string()))?
+            }
+        };
+        let key = DecodingKey::from_jwk(&jwk).map_err(|e| invalid(e.to_string()))?;
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| invalid(e.to_string()))
+    }
+}
+
+/// Body of an OIDC login request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcTokenRequest {
+    /// ID token issued by the provider
+    pub id_token: String,
+}
+
+/// Body of a binding request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcBindingRequest {
+    /// ID token of the account to bind
+    pub id_token: String,
+    /// DID of the identity to bind it to
+    pub did: String,
+}
+
+/// Routes for OIDC login under `/auth/oidc`, rejected as not found when no
+/// provider is configured
+pub fn oidc_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    jwt: JwtConfig,
+    provider: Option<OidcProvider>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_provider = warp::any().and_then(move || {
+        let provider = provider.clone();
+        async move { provider.ok_or_else(warp::reject::not_found) }
+    });
+    let with_vm = warp::any().map(move || vm.clone());
+
+    let token = warp::path!("auth" / "oidc" / "token")
+        .and(warp::post())
+        .and(with_provider.clone())
+        .and(warp::body::json::<OidcTokenRequest>())
+        .and(with_vm.clone())
+        .and(warp::any().map(move || jwt.clone()))
+        .and_then(token_handler);
+
+    let request = warp::path!("auth" / "oidc" / "bindings")
+        .and(warp::post())
+        .and(with_provider.clone())
+        .and(warp::body::json::<OidcBindingRequest>())
+        .and(with_vm.clone())
+        .and_then(request_handler);
+
+    let list = warp::path!("auth" / "oidc" / "bindings")
+        .and(warp::get())
+        .and(with_provider.clone())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and_then(list_handler);
+
+    let approve = warp::path!("auth" / "oidc" / "bindings" / String / "approve")
+        .and(warp::post())
+        .and(with_provider.clone())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and_then(approve_handler);
+
+    let remove = warp::path!("auth" / "oidc" / "bindings" / String)
+        .and(warp::delete())
+        .and(with_provider)
+        .and(auth)
+        .and(with_vm)
+        .and_then(remove_handler);
+
+    token.or(request).or(list).or(approve).or(remove)
+}
+
+async fn token_handler<S>(
+    provider: OidcProvider,
+    request: OidcTokenRequest,
+    vm: Arc<Mutex<VM<S>>>,
+    jwt: JwtConfig,
+) -> Result<impl Reply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let claims = tokio::task::spawn_blocking(move || provider.validate(&request.id_token))
+        .await
+        .map_err(|e| warp::reject::custom(AuthError::InvalidToken(e.to_string())))?
+        .map_err(warp::reject::custom)?;
+    let vm_lock = vm.lock().await;
+    let account = format!("{} at {}", claims.sub, claims.iss);
+    let storage = vm_lock
+        .get_storage_backend()
+        .ok_or_else(|| warp::reject::custom(AuthError::UnboundAccount(account.clone())))?;
+    let did = bound_identity(storage, &claims)
+        .ok_or_else(|| warp::reject::custom(AuthError::UnboundAccount(account)))?;
+    let response = token_for_identity(storage, &jwt, &did).map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn request_handler<S>(
+    provider: OidcProvider,
+    request: OidcBindingRequest,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let id_token = request.id_token;
+    let claims = tokio::task::spawn_blocking(move || provider.validate(&id_token))
+        .await
+        .map_err(|e| warp::reject::custom(AuthError::InvalidToken(e.to_string())))?
+        .map_err(warp::reject::custom)?;
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(match request_binding(storage, &claims, &request.did) {
+        Ok(binding) => warp::reply::with_status(warp::reply::json(&binding), StatusCode::CREATED),
+        Err(e) => storage_error_reply(e),
+    })
+}
+
+async fn list_handler<S>(
+    _provider: OidcProvider,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(match list_bindings(storage, &auth) {
+        Ok(bindings) => warp::reply::with_status(warp::reply::json(&bindings), StatusCode::OK),
+        Err(e) => storage_error_reply(e),
+    })
+}
+
+async fn approve_handler<S>(
+    id: String,
+    _provider: OidcProvider,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(match approve_binding(storage, &auth, &id) {
+        Ok(binding) => warp::reply::with_status(warp::reply::json(&binding), StatusCode::OK),
+        Err(e) => storage_error_reply(e),
+    })
+}
+
+async fn remove_handler<S>(
+    id: String,
+    _provider: OidcProvider,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(match remove_binding(storage, &auth, &id) {
+        Ok(()) => warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({ "removed": id })),
+            StatusCode::OK,
+        ),
+        Err(e) => storage_error_reply(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use ed25519_dalek::SigningKey;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    #[test]
+    fn test_id_token_logs_in_only_through_approved_binding() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let jwks = serde_json::json!({ "keys": [{
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "kid": "k1",
+            "x": URL_SAFE_NO_PAD.encode(signing_key.verifying_key().to_bytes()),
+        }]});
+        let dir = tempfile::tempdir().unwrap();
+        let jwks_path = dir.path().join("jwks.json");
+        std::fs::write(&jwks_path, jwks.to_string()).unwrap();
+        let provider = OidcProvider::new(OidcConfig {
+            issuer: "https://auth.example.coop".to_string(),
+            client_id: "icn-covm".to_string(),
+            jwks: Some(jwks_path.to_string_lossy().into_owned()),
+        })
+        .unwrap();
+
+        // PKCS#8 v1 wrapping of the Ed25519 seed
+        let mut der = hex::decode("302e020100300506032b657004220420").unwrap();
+        der.extend_from_slice(&signing_key.to_bytes());
+        let id_token = |audience: &str| {
+            let mut header = Header::new(Algorithm::EdDSA);
+            header.kid = Some("k1".to_string());
+            let claims = serde_json::json!({
+                "iss": "https://auth.example.coop",
+                "sub": "user-42",
+                "aud": audience,
+                "email": "alice@example.coop",
+                "exp": crate::storage::utils::now_with_default() + 300,
+            });
+            encode(&header, &claims, &EncodingKey::from_ed_der(&der)).unwrap()
+        };
+        assert!(provider.validate(&id_token("other-client")).is_err());
+        let claims = provider.validate(&id_token("icn-covm")).unwrap();
+        assert_eq!(claims.sub, "user-42");
+
+        let mut storage = InMemoryStorage::new();
+        let system = system_auth();
+        storage
+            .create_account(Some(&system), "system", 1 << 20)
+            .unwrap();
+        let alice = Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        storage
+            .set_json(
+                Some(&system),
+                "identity",
+                &format!("identities/{}", alice.did()),
+                &alice,
+            )
+            .unwrap();
+        assert!(request_binding(&mut storage, &claims, "did:key:unknown").is_err());
+
+        let binding = request_binding(&mut storage, &claims, alice.did()).unwrap();
+        assert_eq!(bound_identity(&storage, &claims), None);
+        let mallory = AuthContext::new("did:key:mallory");
+        assert!(approve_binding(&mut storage, &mallory, &binding.id).is_err());
+        approve_binding(&mut storage, &AuthContext::new(alice.did()), &binding.id).unwrap();
+        assert_eq!(
+            bound_identity(&storage, &claims).as_deref(),
+            Some(alice.did())
+        );
+        assert!(request_binding(&mut storage, &claims, alice.did()).is_err());
+        assert!(remove_binding(&mut storage, &mallory, &binding.id).is_err());
+    }
+}
@@ -1,9 +1,26 @@
+use crate::api::audit::{self, AuditLog};
+use crate::api::auth::{self, with_auth, JwtConfig};
+use crate::api::{demurrage, usage};
+use crate::api::health::{self, HealthMonitors};
+use crate::api::idempotency::{self, IdempotencyCache};
+use crate::api::oidc::OidcProvider;
+use crate::api::rate_limit::{self, RateLimitConfig, RateLimiter};
+use crate::api::replica::{self, ReplicaConfig};
+use crate::api::v1::models::{
+    CommentResponse, CommentVersionResponse, ErrorResponse, Participant, ProposalResponse,
+    ProposalSummary, ShowHiddenQuery, VoteCounts,
+};
+use crate::api::v1::{self, events::EventHub};
+use crate::audit::RetentionPolicy;
 use crate::cli::proposal::{count_votes, fetch_comments_threaded, load_proposal_from_governance};
+use crate::federation::NodeHandle;
+use crate::governance::scheduler;
 use crate::governance::proposal::Proposal;
 use crate::storage::auth::AuthContext;
+use crate::storage::errors::StorageError;
 use crate::storage::traits::{Storage, StorageExtensions};
+use crate::telemetry::metrics;
 use crate::vm::VM;
-use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::Infallible;
 use std::fmt::Debug;
@@ -11,109 +28,125 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use warp::{Filter, Rejection, Reply};
 
-/// Represents a proposal with all of its metadata for API responses
-#[derive(Debug, Serialize, Deserialize)]
-struct ProposalResponse {
-    id: String,
-    title: String,
-    creator: String,
-    status: String,
-    created_at: String,
-    votes: VoteCounts,
-    quorum_percentage: f64,
-    threshold_percentage: f64,
-    execution_result: Option<String>,
-}
-
-/// Vote count information
-#[derive(Debug, Serialize, Deserialize)]
-struct VoteCounts {
-    yes: u32,
-    no: u32,
-    abstain: u32,
-    total: u32,
-}
-
-/// Comment metadata for API responses
-#[derive(Debug, Serialize, Deserialize)]
-struct CommentResponse {
-    id: String,
-    author: String,
-    timestamp: String,
-    content: String,
-    reply_to: Option<String>,
-    tags: Vec<String>,
-    reactions: HashMap<String, u32>,
-    hidden: bool,
-    edit_count: usize,
-}
-
-/// Comment version history for API
-#[derive(Debug, Serialize, Deserialize)]
-struct CommentVersionResponse {
-    content: String,
-    timestamp: String,
-}
-
-/// Proposal summary for API responses
-#[derive(Debug, Serialize, Deserialize)]
-struct ProposalSummary {
-    id: String,
-    title: String,
-    status: String,
-    comment_count: usize,
-    vote_count: u32,
-    vote_details: VoteCounts,
-    top_participants: Vec<Participant>,
-    last_activity: String,
-}
-
-/// Participant information for summaries
-#[derive(Debug, Serialize, Deserialize)]
-struct Participant {
-    id: String,
-    comment_count: u32,
-}
-
-/// API error response
-#[derive(Debug, Serialize)]
-struct ErrorResponse {
-    message: String,
-}
-
-/// Query parameters for filtering hidden comments
-#[derive(Debug, Serialize, Deserialize)]
-struct ShowHiddenQuery {
-    show_hidden: Option<bool>,
-}
-
 /// Initialize and start the API server with the given VM
-pub async fn start_api<S>(vm: VM<S>, port: u16) -> Result<(), Box<dyn std::error::Error>>
+///
+/// `monitors` holds the heartbeats of background components, such as a
+/// federation node, reported by the readiness probe. `node` is the federation
+/// node managed through `/api/v1/federation`, if one runs alongside the API.
+/// The gRPC API shares the VM and token configuration when `grpc_port` is set.
+/// With `replica`, storage follows the primary it names and mutating
+/// requests are forwarded there; see `replica`. `oidc` is the OpenID Connect
+/// provider members may log in with, if any.
+pub async fn start_api<S>(
+    mut vm: VM<S>,
+    port: u16,
+    grpc_port: Option<u16>,
+    monitors: HealthMonitors,
+    node: Option<NodeHandle>,
+    replica: Option<ReplicaConfig>,
+    oidc: Option<OidcProvider>,
+) -> Result<(), Box<dyn std::error::Error>>
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
+    if let Some(storage) = vm.get_storage_backend_mut() {
+        if replica.is_some() {
+            replica::create_account(storage);
+        }
+        let prepared = rate_limit::prepare(storage).and_then(|()| idempotency::prepare(storage));
+        if let Err(e) = prepared {
+            tracing::error!("Failed to set up the system namespace: {}", e);
+        }
+    }
+
+    // Subscribe to ledger and VM events before the VM is shared
+    let dag_events = vm.subscribe_dag();
+    let vm_events = vm.subscribe_namespaced_events();
     let vm = Arc::new(Mutex::new(vm));
 
+    let hub = EventHub::new();
+    if let Some(dag_events) = dag_events {
+        hub.forward_ledger(dag_events, vm.clone());
+    }
+    hub.forward_vm_events(vm_events, None);
+
+    let jwt = JwtConfig::from_env();
+    let limiter = RateLimiter::new(RateLimitConfig::from_env(), vm.clone());
+    let audit_log = AuditLog::start(vm.clone(), RetentionPolicy::from_env());
+    let idempotency = IdempotencyCache::start(vm.clone(), idempotency::ttl_from_env());
+    let primary = replica.as_ref().map(|config| config.primary.clone());
+    match replica {
+        // Writes happen on the primary, including demurrage and scheduled
+        // proposal transitions
+        Some(config) => {
+            println!(
+                "Following primary {}:{} as a read replica",
+                config.primary.host, config.primary.port
+            );
+            replica::start_follower(vm.clone(), jwt.clone(), config);
+        }
+        None => {
+            demurrage::start(vm.clone());
+            scheduler::start(vm.clone());
+        }
+    }
+    usage::start(vm.clone());
+    match grpc_port {
+        Some(grpc_port) if primary.is_some() => tracing::warn!(
+            grpc_port,
+            "Not serving gRPC: read replicas cannot forward gRPC calls to the primary"
+        ),
+        Some(grpc_port) => start_grpc(vm.clone(), jwt.clone(), grpc_port),
+        None => {}
+    }
+
     // Create routes for API endpoints
     let proposals_route = warp::path!("proposals" / String)
+        .and(with_auth(jwt.clone(), vm.clone()))
         .and(with_vm(vm.clone()))
         .and_then(get_proposal);
 
     let comments_route = warp::path!("proposals" / String / "comments")
+        .and(with_auth(jwt.clone(), vm.clone()))
         .and(with_vm(vm.clone()))
         .and(warp::query::<ShowHiddenQuery>())
         .and_then(get_proposal_comments);
 
     let summary_route = warp::path!("proposals" / String / "summary")
+        .and(with_auth(jwt.clone(), vm.clone()))
         .and(with_vm(vm.clone()))
         .and_then(get_proposal_summary);
 
     // Combine all routes
-    let routes = proposals_route
+    let routes = replica::forward_mutations(primary)
+        .or(health::health_routes(vm.clone(), monitors))
+        .or(audit::audited(
+            v1::routes(
+                vm.clone(),
+                hub,
+                jwt.clone(),
+                limiter,
+                node,
+                oidc,
+                idempotency,
+            ),
+            audit_log,
+            jwt,
+        ))
+        .or(proposals_route)
         .or(comments_route)
         .or(summary_route)
         .with(warp::cors().allow_any_origin())
-        .recover(handle_rejection);
+        .recover(handle_rejection)
+        .with(warp::log::custom(|info| {
+            metrics::record_api_request(
+                info.method().as_str(),
+                info.status().as_u16(),
+                info.elapsed(),
+            )
+        }))
+        // One span per request, exported with the spans of its handler
+        .with(warp::trace::request());
 
     println!("Starting API server on port {}", port);
     warp::serve(routes).run(([0, 0, 0, 0], port)).await;
@@ -121,6 +154,31 @@ where
     Ok(())
 }
 
+/// Serve the gRPC API in the background
+#[cfg(feature = "grpc")]
+fn start_grpc<S>(vm: Arc<Mutex<VM<S>>>, jwt: JwtConfig, port: u16)
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    println!("Starting gRPC server on port {}", port);
+    tokio::spawn(async move {
+        if let Err(e) = crate::api::grpc::serve(vm, jwt, port).await {
+            tracing::error!(port, error = %e, "gRPC server stopped");
+        }
+    });
+}
+
+#[cfg(not(feature = "grpc"))]
+fn start_grpc<S>(_vm: Arc<Mutex<VM<S>>>, _jwt: JwtConfig, port: u16)
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    tracing::warn!(
+        port,
+        "Not serving gRPC: icn-covm was built without the grpc feature"
+    );
+}
+
 /// Dependency injection helper for the VM
 fn with_vm<S>(
     vm: Arc<Mutex<VM<S>>>,
@@ -132,21 +190,24 @@ where
 }
 
 /// Handler for GET /proposals/{id}
-async fn get_proposal<S>(id: String, vm: Arc<Mutex<VM<S>>>) -> Result<impl Reply, Rejection>
+async fn get_proposal<S>(
+    id: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<impl Reply, Rejection>
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
-    let vm_lock = vm.lock().await;
+    let mut vm_lock = vm.lock().await;
 
-    // Load proposal
-    let proposal_result = load_proposal_from_governance(&vm_lock, &id);
+    // Read as the caller, so storage permission checks apply
+    let proposal_result = vm_lock.with_auth_context(auth, |vm| {
+        load_proposal_from_governance(vm, &id)
+            .map(|proposal| (proposal, count_votes(vm, &id).unwrap_or((0, 0, 0))))
+    });
 
     match proposal_result {
-        Ok(proposal) => {
-            // Get vote counts
-            let (yes_votes, no_votes, abstain_votes) =
-                count_votes(&vm_lock, &id).unwrap_or((0, 0, 0));
-
+        Ok((proposal, (yes_votes, no_votes, abstain_votes))) => {
             let total_votes = yes_votes + no_votes + abstain_votes;
 
             // Calculate percentages
@@ -175,13 +236,20 @@ where
                 execution_result: proposal.execution_result,
             };
 
-            Ok(warp::reply::json(&response))
+            Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                warp::http::StatusCode::OK,
+            ))
         }
         Err(e) => {
+            let status = match e.downcast_ref::<StorageError>() {
+                Some(StorageError::PermissionDenied { .. }) => warp::http::StatusCode::FORBIDDEN,
+                _ => warp::http::StatusCode::NOT_FOUND,
+            };
             let error = ErrorResponse {
                 message: format!("Failed to load proposal: {}", e),
             };
-            Ok(warp::reply::json(&error))
+            Ok(warp::reply::with_status(warp::reply::json(&error), status))
         }
     }
 }
@@ -189,6 +257,7 @@ where
 /// Handler for GET /proposals/{id}/comments
 async fn get_proposal_comments<S>(
     id: String,
+    auth: AuthContext,
     vm: Arc<Mutex<VM<S>>>,
     query: ShowHiddenQuery,
 ) -> Result<impl Reply, Rejection>
@@ -197,8 +266,7 @@ where
 {
     let vm_lock = vm.lock().await;
 
-    // Create a null auth context for read-only operations
-    let auth_context = None;
+    let auth_context = Some(&auth);
     let show_hidden = query.show_hidden.unwrap_or(false);
 
     // Pass the show_hidden parameter to control visibility of hidden comments
@@ -237,7 +305,11 @@ where
 }
 
 /// Handler for GET /proposals/{id}/summary
-async fn get_proposal_summary<S>(id: String, vm: Arc<Mutex<VM<S>>>) -> Result<impl Reply, Rejection>
+async fn get_proposal_summary<S>(
+    id: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<impl Reply, Rejection>
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
@@ -246,7 +318,7 @@ where
     // Load proposal and comments
     let proposal_result = load_proposal_from_governance(&vm_lock, &id);
     let comments_result =
-        crate::governance::comments::fetch_comments_threaded(&vm_lock, &id, None, false);
+        crate::governance::comments::fetch_comments_threaded(&vm_lock, &id, Some(&auth), false);
 
     if let (Ok(proposal), Ok(comments)) = (&proposal_result, &comments_result) {
         // Count votes
@@ -323,10 +395,42 @@ where
 }
 
 /// Error handler for API rejections
-async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
+pub(crate) async fn handle_rejection(err: Rejection) -> Result<warp::reply::Response, Infallible> {
+    if let Some((status, message, retry_after)) = rate_limit::rate_limit_rejection(&err) {
+        let error = ErrorResponse { message };
+        let reply = warp::reply::with_status(warp::reply::json(&error), status);
+        return Ok(
+            warp::reply::with_header(reply, "retry-after", retry_after.to_string()).into_response(),
+        );
+    }
+
+    if let Some((status, message)) =
+        auth::auth_rejection_status(&err).or_else(|| v1::tenant::tenant_rejection_status(&err))
+    {
+        let error = ErrorResponse { message };
+        return Ok(warp::reply::with_status(warp::reply::json(&error), status).into_response());
+    }
+
     let error = ErrorResponse {
         message: format!("API error: {:?}", err),
     };
 
-    Ok(warp::reply::json(&error))
+    Ok(warp::reply::with_status(warp::reply::json(&error), rejection_status(&err)).into_response())
+}
+
+/// Status a rejection is answered with
+pub(crate) fn rejection_status(err: &Rejection) -> warp::http::StatusCode {
+    if let Some((status, _, _)) = rate_limit::rate_limit_rejection(err) {
+        status
+    } else if let Some((status, _)) =
+        auth::auth_rejection_status(err).or_else(|| v1::tenant::tenant_rejection_status(err))
+    {
+        status
+    } else if err.is_not_found() {
+        warp::http::StatusCode::NOT_FOUND
+    } else if err.find::<warp::reject::PayloadTooLarge>().is_some() {
+        warp::http::StatusCode::PAYLOAD_TOO_LARGE
+    } else {
+        warp::http::StatusCode::BAD_REQUEST
+    }
 }
@@ -0,0 +1,331 @@
+//! Request rate limiting for the HTTP API
+//!
+//! Each client gets a token bucket that refills at a steady rate and holds
+//! at most a burst of requests. Buckets are kept per remote IP for every
+//! `/api/v1` request, and per identity once a request is authenticated, so
+//! one identity cannot spread load across addresses and one address cannot
+//! spread load across identities. Requests over either limit are rejected
+//! with `RateLimited`, which the server turns into a 429 response with a
+//! `Retry-After` header.
+//!
+//! Buckets live in memory by default. Setting `ICN_RATE_LIMIT_STORE=storage`
+//! keeps them in the storage backend instead, so servers sharing a backend
+//! share their limits; the server sets up their namespace with `prepare` at
+//! startup.
+
+use crate::api::auth::system_auth;
+use crate::storage::auth::{ensure_system_namespace, AuthContext};
+use crate::storage::errors::StorageResult;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::{Filter, Rejection};
+
+/// Environment variable with the per-IP limit in requests per minute
+pub const PER_IP_ENV: &str = "ICN_RATE_LIMIT_PER_IP";
+
+/// Environment variable with the per-identity limit in requests per minute
+pub const PER_IDENTITY_ENV: &str = "ICN_RATE_LIMIT_PER_IDENTITY";
+
+/// Environment variable selecting where buckets are kept: `memory` or `storage`
+pub const STORE_ENV: &str = "ICN_RATE_LIMIT_STORE";
+
+/// Namespace holding buckets when they are kept in storage
+const BUCKET_NAMESPACE: &str = "system";
+
+/// Number of in-memory buckets above which full buckets are dropped
+const MAX_IDLE_BUCKETS: usize = 10_000;
+
+/// Request was rejected because its bucket is empty
+#[derive(Debug, thiserror::Error)]
+#[error("Rate limit exceeded for {scope}; retry in {retry_after_secs}s")]
+pub struct RateLimited {
+    /// `ip` or `identity`
+    pub scope: &'static str,
+    pub retry_after_secs: u64,
+}
+
+impl warp::reject::Reject for RateLimited {}
+
+/// Size and refill rate of a bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Most requests allowed in a burst
+    pub capacity: u32,
+    /// Tokens added back per second
+    pub refill_per_sec: f64,
+}
+
+impl RateLimit {
+    /// Allow `requests` per minute, all of which may arrive at once
+    pub fn per_minute(requests: u32) -> Self {
+        Self {
+            capacity: requests,
+            refill_per_sec: requests as f64 / 60.0,
+        }
+    }
+}
+
+/// Limits applied by a `RateLimiter`; `None` disables a limit
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub per_ip: Option<RateLimit>,
+    pub per_identity: Option<RateLimit>,
+    /// Keep buckets in the storage backend instead of in memory
+    pub use_storage: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_ip: Some(RateLimit::per_minute(600)),
+            per_identity: Some(RateLimit::per_minute(300)),
+            use_storage: false,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Read limits from the environment, falling back to the defaults
+    ///
+    /// Limits are given in requests per minute; `0` disables the limit.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let limit = |var: &str, default: Option<RateLimit>| match std::env::var(var) {
+            Ok(value) => match value.trim().parse::<u32>() {
+                Ok(0) => None,
+                Ok(requests) => Some(RateLimit::per_minute(requests)),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid {}={:?}", var, value);
+                    default
+                }
+            },
+            Err(_) => default,
+        };
+        Self {
+            per_ip: limit(PER_IP_ENV, defaults.per_ip),
+            per_identity: limit(PER_IDENTITY_ENV, defaults.per_identity),
+            use_storage: std::env::var(STORE_ENV).is_ok_and(|v| v == "storage"),
+        }
+    }
+}
+
+/// Token bucket state, timestamps in milliseconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TokenBucket {
+    tokens: f64,
+    updated_ms: u64,
+}
+
+impl TokenBucket {
+    fn full(limit: &RateLimit, now_ms: u64) -> Self {
+        Self {
+            tokens: limit.capacity as f64,
+            updated_ms: now_ms,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now_ms: u64) {
+        let elapsed = now_ms.saturating_sub(self.updated_ms) as f64 / 1000.0;
+        self.tokens = (self.tokens + elapsed * limit.refill_per_sec).min(limit.capacity as f64);
+        self.updated_ms = now_ms;
+    }
+
+    /// Take one token, or return how many seconds until one is available
+    fn take(&mut self, limit: &RateLimit, now_ms: u64) -> Result<(), u64> {
+        self.refill(limit, now_ms);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else if limit.refill_per_sec > 0.0 {
+            Err(((1.0 - self.tokens) / limit.refill_per_sec).ceil() as u64)
+        } else {
+            Err(u64::MAX)
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis().max(0) as u64
+}
+
+/// Create the account buckets are written as and their namespace, unless
+/// they already exist
+pub fn prepare<S: Storage>(storage: &mut S) -> StorageResult<()> {
+    ensure_system_namespace(storage, &system_auth(), BUCKET_NAMESPACE)
+}
+
+/// Applies the configured limits to API requests
+#[derive(Debug, Clone)]
+pub struct RateLimiter<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    config: RateLimitConfig,
+    buckets: Arc<std::sync::Mutex<HashMap<String, TokenBucket>>>,
+    vm: Arc<Mutex<VM<S>>>,
+}
+
+impl<S> RateLimiter<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    /// Create a limiter; `vm` provides the storage backend when buckets are kept there
+    pub fn new(config: RateLimitConfig, vm: Arc<Mutex<VM<S>>>) -> Self {
+        Self {
+            config,
+            buckets: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            vm,
+        }
+    }
+
+    /// Take a token from the bucket of `client` in `scope`
+    async fn take(
+        &self,
+        scope: &'static str,
+        client: &str,
+        limit: &RateLimit,
+    ) -> Result<(), RateLimited> {
+        let now = now_ms();
+        let key = format!("{}/{}", scope, client);
+        let limited = |retry_after_secs| RateLimited {
+            scope,
+            retry_after_secs,
+        };
+        if !self.config.use_storage {
+            let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+            if buckets.len() > MAX_IDLE_BUCKETS {
+                // A full bucket behaves exactly like a missing one
+                buckets.retain(|key, bucket| {
+                    if !key.starts_with(scope) {
+                        return true;
+                    }
+                    bucket.refill(limit, now);
+                    bucket.tokens < limit.capacity as f64
+                });
+            }
+            return buckets
+                .entry(key)
+                .or_insert_with(|| TokenBucket::full(limit, now))
+                .take(limit, now)
+                .map_err(limited);
+        }
+
+        let mut vm_lock = self.vm.lock().await;
+        let Some(storage) = vm_lock.get_storage_backend_mut() else {
+            return Ok(());
+        };
+        let system = system_auth();
+        let storage_key = format!("rate_limits/{}", key);
+        let mut bucket = storage
+            .get_json::<TokenBucket>(Some(&system), BUCKET_NAMESPACE, &storage_key)
+            .unwrap_or_else(|_| TokenBucket::full(limit, now));
+        let result = bucket.take(limit, now).map_err(limited);
+        // A storage failure should not take the API down with it
+        if let Err(e) = storage.set_json(Some(&system), BUCKET_NAMESPACE, &storage_key, &bucket) {
+            tracing::warn!("Failed to persist rate limit bucket {}: {}", key, e);
+        }
+        result
+    }
+
+    /// Filter limiting requests per remote IP
+    pub fn per_ip(&self) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+        let limiter = self.clone();
+        warp::addr::remote()
+            .and_then(move |addr: Option<SocketAddr>| {
+                let limiter = limiter.clone();
+                async move {
+                    let (Some(limit), Some(addr)) = (limiter.config.per_ip, addr) else {
+                        return Ok(());
+                    };
+                    limiter
+                        .take("ip", &addr.ip().to_string(), &limit)
+                        .await
+                        .map_err(warp::reject::custom)
+                }
+            })
+            .untuple_one()
+    }
+
+    /// Wrap an authentication filter so each identity is limited separately
+    pub fn per_identity(
+        &self,
+        auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+    ) -> impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static
+    {
+        let limiter = self.clone();
+        auth.and_then(move |auth: AuthContext| {
+            let limiter = limiter.clone();
+            async move {
+                let Some(limit) = limiter.config.per_identity else {
+                    return Ok(auth);
+                };
+                limiter
+                    .take("identity", auth.identity_did(), &limit)
+                    .await
+                    .map(|_| auth)
+                    .map_err(warp::reject::custom)
+            }
+        })
+    }
+}
+
+/// Map rate limit rejections to 429 responses, with the `Retry-After` delay
+pub fn rate_limit_rejection(err: &Rejection) -> Option<(warp::http::StatusCode, String, u64)> {
+    err.find::<RateLimited>().map(|e| {
+        (
+            warp::http::StatusCode::TOO_MANY_REQUESTS,
+            e.to_string(),
+            e.retry_after_secs,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_token_bucket_allows_burst_then_refills() {
+        let limit = RateLimit::per_minute(2);
+        let mut bucket = TokenBucket::full(&limit, 0);
+
+        assert!(bucket.take(&limit, 0).is_ok());
+        assert!(bucket.take(&limit, 0).is_ok());
+        assert_eq!(bucket.take(&limit, 0), Err(30));
+
+        // One token is back after 30 seconds
+        assert!(bucket.take(&limit, 30_000).is_ok());
+        assert!(bucket.take(&limit, 30_000).is_err());
+
+        // Idle time never fills the bucket past its capacity
+        assert!(bucket.take(&limit, 3_600_000).is_ok());
+        assert!(bucket.take(&limit, 3_600_000).is_ok());
+        assert!(bucket.take(&limit, 3_600_000).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_buckets_in_storage_are_kept_between_requests() {
+        let mut storage = InMemoryStorage::new();
+        prepare(&mut storage).unwrap();
+        let vm = Arc::new(Mutex::new(VM::with_storage_backend(storage)));
+        let config = RateLimitConfig {
+            per_ip: Some(RateLimit::per_minute(2)),
+            per_identity: None,
+            use_storage: true,
+        };
+        let limiter = RateLimiter::new(config, vm);
+        let limit = RateLimit::per_minute(2);
+
+        assert!(limiter.take("ip", "10.0.0.1", &limit).await.is_ok());
+        assert!(limiter.take("ip", "10.0.0.1", &limit).await.is_ok());
+        assert!(limiter.take("ip", "10.0.0.1", &limit).await.is_err());
+        assert!(limiter.take("ip", "10.0.0.2", &limit).await.is_ok());
+    }
+}
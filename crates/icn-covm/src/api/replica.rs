@@ -0,0 +1,270 @@
+//! Read-replica mode for API nodes
+//!
+//! A replica serves reads from its own copy of a primary's storage, so
+//! dashboard and other read traffic can be spread over any number of
+//! stateless nodes. `start_follower` copies a snapshot from the primary's
+//! `/api/v1/replication` feed and then polls it for changes, and
+//! `forward_mutations` sends every request that is not a `GET`, `HEAD`, or
+//! `OPTIONS` on to the primary and relays its response, so writes only ever
+//! happen there and are audited there.
+//!
+//! Replicas call the feed with short-lived global admin tokens they issue
+//! themselves, so the primary and its replicas must share `ICN_JWT_SECRET`.
+//! That is needed anyway for replicas to accept the primary's tokens.
+
+use crate::api::audit::is_mutating;
+use crate::api::auth::{issue_token, system_auth, JwtConfig};
+use crate::api::idempotency::IDEMPOTENCY_KEY_HEADER;
+use crate::api::keys::API_KEY_HEADER;
+use crate::api::v1::error_reply;
+use crate::http::Endpoint;
+use crate::storage::auth::{AuthContext, RoleAssignment};
+use crate::storage::errors::StorageError;
+use crate::storage::replication::{apply_changes, apply_snapshot, ChangeBatch};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use std::fmt::Debug;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use warp::filters::path::FullPath;
+use warp::http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use warp::http::{Method, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// How long a replica waits between polls once it has caught up
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Timeout of each request to the primary
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Identity named in the tokens replicas issue for the feed
+const REPLICA_IDENTITY: &str = "replica";
+
+/// Storage quota of the account replicated writes are charged to; the
+/// primary has already enforced the real quotas
+const REPLICA_ACCOUNT_QUOTA: u64 = u64::MAX / 2;
+
+/// Request headers passed on to the primary
+const FORWARDED_HEADERS: [&str; 4] = [
+    "authorization",
+    API_KEY_HEADER,
+    "content-type",
+    IDEMPOTENCY_KEY_HEADER,
+];
+
+/// Where a replica follows its primary from
+#[derive(Debug, Clone)]
+pub struct ReplicaConfig {
+    pub primary: Endpoint,
+    pub poll_interval: Duration,
+}
+
+impl ReplicaConfig {
+    /// Follow the primary serving the API at `url`
+    pub fn new(url: &str) -> io::Result<Self> {
+        Ok(Self {
+            primary: Endpoint::parse(url)?,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        })
+    }
+}
+
+/// GET `path` from the primary's feed, returning `None` when the replica
+/// must take a snapshot again
+fn fetch(endpoint: &Endpoint, jwt: &JwtConfig, path: &str) -> Result<Option<ChangeBatch>, String> {
+    let admin = RoleAssignment {
+        namespace: "global".to_string(),
+        role: "admin".to_string(),
+    };
+    let token =
+        issue_token(jwt, REPLICA_IDENTITY, vec![admin], Vec::new()).map_err(|e| e.to_string())?;
+    let authorization = format!("Bearer {}", token);
+    let response = endpoint
+        .exchange(
+            "GET",
+            path,
+            &[("Authorization", &authorization)],
+            b"",
+            REQUEST_TIMEOUT,
+        )
+        .map_err(|e| e.to_string())?;
+    match response.status {
+        200 => serde_json::from_slice(&response.body)
+            .map(Some)
+            .map_err(|e| format!("Invalid change batch: {}", e)),
+        410 => Ok(None),
+        _ => Err(format!("primary responded with {:?}", response.status_line)),
+    }
+}
+
+/// Create the account replicated writes are charged to
+///
+/// This must run before anything else creates the `system` account with a
+/// smaller quota, such as the audit log writer.
+pub fn create_account<S: Storage>(storage: &mut S) {
+    let auth = system_auth();
+    match storage.create_account(Some(&auth), auth.user_id(), REPLICA_ACCOUNT_QUOTA) {
+        Ok(()) | Err(StorageError::TransactionError { .. }) => {}
+        Err(e) => tracing::warn!("Failed to create the replication account: {}", e),
+    }
+}
+
+/// Keep the VM's storage in step with the primary in the background
+pub fn start_follower<S>(vm: Arc<Mutex<VM<S>>>, jwt: JwtConfig, config: ReplicaConfig)
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    tokio::spawn(async move {
+        let auth = system_auth();
+
+        // Epoch and sequence number of the last change applied
+        let mut position: Option<(String, u64)> = None;
+        loop {
+            let path = match &position {
+                Some((epoch, head)) => {
+                    format!("/api/v1/replication/changes?epoch={}&since={}", epoch, head)
+                }
+                None => "/api/v1/replication/snapshot".to_string(),
+            };
+            let (endpoint, jwt) = (config.primary.clone(), jwt.clone());
+            let fetched = tokio::task::spawn_blocking(move || fetch(&endpoint, &jwt, &path))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()));
+
+            let more = match fetched {
+                Ok(Some(batch)) => {
+                    let applied = apply(&vm, &auth, position.is_none(), &batch).await;
+                    match applied {
+                        Ok(()) => {
+                            position = Some((batch.epoch, batch.head));
+                            batch.more
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to apply changes from the primary: {}", e);
+                            position = None;
+                            false
+                        }
+                    }
+                }
+                Ok(None) => {
+                    tracing::info!("Replica fell behind the primary; taking a new snapshot");
+                    position = None;
+                    true
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to poll primary {}:{}: {}",
+                        config.primary.host,
+                        config.primary.port,
+                        e
+                    );
+                    false
+                }
+            };
+            if !more {
+                tokio::time::sleep(config.poll_interval).await;
+            }
+        }
+    });
+}
+
+/// Apply a batch from the primary, replacing all of storage with a snapshot
+async fn apply<S>(
+    vm: &Arc<Mutex<VM<S>>>,
+    auth: &AuthContext,
+    snapshot: bool,
+    batch: &ChangeBatch,
+) -> Result<(), StorageError>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut vm = vm.lock().await;
+    let Some(storage) = vm.get_storage_backend_mut() else {
+        return Err(StorageError::TransactionError {
+            details: "Storage not available".to_string(),
+        });
+    };
+    if snapshot {
+        apply_snapshot(storage, Some(auth), &batch.changes)
+    } else {
+        apply_changes(storage, Some(auth), &batch.changes)
+    }
+}
+
+/// Filter answering mutating requests with the primary's response
+///
+/// Other requests, and every request when `primary` is `None`, are rejected
+/// as not found so they fall through to the local routes.
+pub fn forward_mutations(
+    primary: Option<Endpoint>,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::method()
+        .and_then(move |method: Method| {
+            let primary = primary.clone();
+            async move {
+                match primary {
+                    Some(primary) if is_mutating(&method) => Ok((primary, method)),
+                    _ => Err(warp::reject::not_found()),
+                }
+            }
+        })
+        .untuple_one()
+        .and(warp::path::full())
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::header::headers_cloned())
+        .and(warp::body::bytes())
+        .and_then(forward)
+}
+
+fn bad_gateway(message: String) -> Response {
+    error_reply(message, StatusCode::BAD_GATEWAY).into_response()
+}
+
+async fn forward(
+    primary: Endpoint,
+    method: Method,
+    path: FullPath,
+    query: String,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, Rejection> {
+    let target = if query.is_empty() {
+        path.as_str().to_string()
+    } else {
+        format!("{}?{}", path.as_str(), query)
+    };
+    let forwarded: Vec<(&str, String)> = FORWARDED_HEADERS
+        .iter()
+        .filter_map(|name| Some((*name, headers.get(*name)?.to_str().ok()?.to_string())))
+        .collect();
+
+    let exchanged = tokio::task::spawn_blocking(move || {
+        let headers: Vec<(&str, &str)> = forwarded
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        primary.exchange(method.as_str(), &target, &headers, &body, REQUEST_TIMEOUT)
+    })
+    .await;
+
+    Ok(match exchanged {
+        Ok(Ok(exchanged)) => {
+            let mut response = Response::new(exchanged.body.into());
+            *response.status_mut() =
+                StatusCode::from_u16(exchanged.status).unwrap_or(StatusCode::BAD_GATEWAY);
+            if let Some(content_type) = exchanged
+                .content_type
+                .and_then(|value| HeaderValue::from_str(&value).ok())
+            {
+                response.headers_mut().insert(CONTENT_TYPE, content_type);
+            }
+            response
+        }
+        Ok(Err(e)) => bad_gateway(format!("Failed to forward to the primary: {}", e)),
+        Err(e) => bad_gateway(format!("Failed to forward to the primary: {}", e)),
+    })
+}
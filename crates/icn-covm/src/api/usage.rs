@@ -0,0 +1,60 @@
+//! Periodic namespace usage reports
+//!
+//! The job started here measures every namespace (see
+//! [`crate::storage::usage`]) every few minutes, exports the figures as
+//! metrics, and delivers a `storage:quota` warning event to the event sinks
+//! when a namespace crosses one of the configured percentages of its quota.
+
+use crate::events;
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::storage::usage::{self, UsageAlerts};
+use crate::telemetry::metrics;
+use crate::vm::VM;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// How often namespace usage is measured
+const REPORT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Start the job that reports the usage of the VM's storage
+pub fn start<S>(vm: Arc<Mutex<VM<S>>>)
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut auth = AuthContext::new("system");
+    auth.add_role("global", "admin");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REPORT_INTERVAL);
+        let mut alerts = UsageAlerts::new();
+        loop {
+            interval.tick().await;
+            let usages = {
+                let vm = vm.lock().await;
+                let Some(storage) = vm.get_storage_backend() else {
+                    continue;
+                };
+                match usage::all_namespace_usage(storage, Some(&auth)) {
+                    Ok(usages) => usages,
+                    Err(e) => {
+                        tracing::warn!("Failed to measure namespace usage: {}", e);
+                        continue;
+                    }
+                }
+            };
+
+            let thresholds = usage::thresholds();
+            for usage in &usages {
+                metrics::record_namespace_usage(usage);
+                if let Some(event) = alerts.check(usage, &thresholds) {
+                    tracing::warn!(namespace = %usage.namespace, "{}", event.message);
+                    if let Err(e) = events::dispatch(&event) {
+                        tracing::warn!(error = %e, "Failed to deliver quota alert");
+                    }
+                }
+            }
+        }
+    });
+}
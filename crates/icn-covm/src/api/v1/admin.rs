@@ -0,0 +1,366 @@
+//! Administration endpoints under `/api/v1/admin`
+//!
+//! - `GET /admin/namespaces` lists namespaces, `POST` creates one
+//! - `POST /admin/accounts` creates a resource account with a quota
+//! - `GET /admin/identities/{did}/roles` lists an identity's roles, `POST`
+//!   grants one, and `DELETE ?namespace=&role=` revokes one
+//! - `GET /admin/audit` reads the storage audit log
+//!
+//! Every endpoint requires the global admin role. Role changes are stored
+//! where token issuance reads them, so they apply to tokens issued after the
+//! change.
+
+use super::models::{
+    AuditLogQuery, CreateAccountRequest, CreateNamespaceRequest, NamespaceListQuery, RoleRequest,
+};
+use super::{error_reply, storage_error_reply, JsonReply};
+use crate::api::auth::roles_key;
+use crate::storage::auth::{AuthContext, RoleAssignment};
+use crate::storage::errors::StorageError;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// Audit entries returned when no limit is given
+const DEFAULT_AUDIT_LIMIT: usize = 100;
+
+/// Most audit entries returned by one request
+const MAX_AUDIT_LIMIT: usize = 1000;
+
+/// Routes for administration
+pub fn admin_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_vm = warp::any().map(move || vm.clone());
+
+    let list_namespaces = warp::path!("admin" / "namespaces")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::query::<NamespaceListQuery>())
+        .and_then(list_namespaces_handler);
+
+    let create_namespace = warp::path!("admin" / "namespaces")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::body::json::<CreateNamespaceRequest>())
+        .and_then(create_namespace_handler);
+
+    let create_account = warp::path!("admin" / "accounts")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::body::json::<CreateAccountRequest>())
+        .and_then(create_account_handler);
+
+    let list_roles = warp::path!("admin" / "identities" / String / "roles")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and_then(list_roles_handler);
+
+    let grant_role = warp::path!("admin" / "identities" / String / "roles")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::body::json::<RoleRequest>())
+        .and_then(grant_role_handler);
+
+    let revoke_role = warp::path!("admin" / "identities" / String / "roles")
+        .and(warp::delete())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::query::<RoleRequest>())
+        .and_then(revoke_role_handler);
+
+    let audit = warp::path!("admin" / "audit")
+        .and(warp::get())
+        .and(auth)
+        .and(with_vm)
+        .and(warp::query::<AuditLogQuery>())
+        .and_then(audit_handler);
+
+    list_namespaces
+        .or(create_namespace)
+        .or(create_account)
+        .or(list_roles)
+        .or(grant_role)
+        .or(revoke_role)
+        .or(audit)
+}
+
+/// Reject callers without the global admin role
+fn require_admin(auth: &AuthContext) -> Result<(), JsonReply> {
+    if auth.has_role("global", "admin") {
+        Ok(())
+    } else {
+        Err(error_reply(
+            "Administration requires the global admin role",
+            StatusCode::FORBIDDEN,
+        ))
+    }
+}
+
+/// Add or remove `role` from a role list, returning whether it changed
+fn update_roles(roles: &mut Vec<RoleAssignment>, role: RoleAssignment, grant: bool) -> bool {
+    let held = roles.contains(&role);
+    if grant && !held {
+        roles.push(role);
+    } else if !grant && held {
+        roles.retain(|r| r != &role);
+    }
+    held != grant
+}
+
+async fn list_namespaces_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    query: NamespaceListQuery,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if let Err(reply) = require_admin(&auth) {
+        return Ok(reply);
+    }
+    let vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    Ok(match storage.list_namespaces(Some(&auth), &query.parent) {
+        Ok(namespaces) => warp::reply::with_status(warp::reply::json(&namespaces), StatusCode::OK),
+        Err(e) => storage_error_reply(e),
+    })
+}
+
+async fn create_namespace_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    request: CreateNamespaceRequest,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if let Err(reply) = require_admin(&auth) {
+        return Ok(reply);
+    }
+    if request.namespace.trim().is_empty() {
+        return Ok(error_reply(
+            "Namespace cannot be empty",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    Ok(
+        match storage.create_namespace(
+            Some(&auth),
+            &request.namespace,
+            request.quota_bytes,
+            request.parent.as_deref(),
+        ) {
+            Ok(()) => warp::reply::with_status(warp::reply::json(&request), StatusCode::CREATED),
+            Err(e) => storage_error_reply(e),
+        },
+    )
+}
+
+async fn create_account_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    request: CreateAccountRequest,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if let Err(reply) = require_admin(&auth) {
+        return Ok(reply);
+    }
+
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    Ok(
+        match storage.create_account(Some(&auth), &request.user_id, request.quota_bytes) {
+            Ok(()) => warp::reply::with_status(warp::reply::json(&request), StatusCode::CREATED),
+            Err(e) => storage_error_reply(e),
+        },
+    )
+}
+
+async fn list_roles_handler<S>(
+    did: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if let Err(reply) = require_admin(&auth) {
+        return Ok(reply);
+    }
+    let vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    Ok(
+        match storage.get_json::<Vec<RoleAssignment>>(Some(&auth), "identity", &roles_key(&did)) {
+            Ok(roles) => warp::reply::with_status(warp::reply::json(&roles), StatusCode::OK),
+            Err(StorageError::NotFound { .. }) => warp::reply::with_status(
+                warp::reply::json(&Vec::<RoleAssignment>::new()),
+                StatusCode::OK,
+            ),
+            Err(e) => storage_error_reply(e),
+        },
+    )
+}
+
+/// Grant or revoke a role and reply with the identity's roles
+async fn change_role<S>(
+    did: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    request: RoleRequest,
+    grant: bool,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if let Err(reply) = require_admin(&auth) {
+        return Ok(reply);
+    }
+
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    let key = roles_key(&did);
+    let mut roles = match storage.get_json::<Vec<RoleAssignment>>(Some(&auth), "identity", &key) {
+        Ok(roles) => roles,
+        Err(StorageError::NotFound { .. }) => Vec::new(),
+        Err(e) => return Ok(storage_error_reply(e)),
+    };
+
+    let role = RoleAssignment {
+        namespace: request.namespace,
+        role: request.role,
+    };
+    if update_roles(&mut roles, role, grant) {
+        if let Err(e) = storage.set_json(Some(&auth), "identity", &key, &roles) {
+            return Ok(storage_error_reply(e));
+        }
+    }
+    Ok(warp::reply::with_status(
+        warp::reply::json(&roles),
+        StatusCode::OK,
+    ))
+}
+
+async fn grant_role_handler<S>(
+    did: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    request: RoleRequest,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    change_role(did, auth, vm, request, true).await
+}
+
+async fn revoke_role_handler<S>(
+    did: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    request: RoleRequest,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    change_role(did, auth, vm, request, false).await
+}
+
+async fn audit_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    query: AuditLogQuery,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if let Err(reply) = require_admin(&auth) {
+        return Ok(reply);
+    }
+    let vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_LIMIT)
+        .clamp(1, MAX_AUDIT_LIMIT);
+    Ok(
+        match storage.get_audit_log(
+            Some(&auth),
+            query.namespace.as_deref(),
+            query.event_type.as_deref(),
+            limit,
+        ) {
+            Ok(events) => warp::reply::with_status(warp::reply::json(&events), StatusCode::OK),
+            Err(e) => storage_error_reply(e),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_roles_grants_and_revokes_once() {
+        let role = |name: &str| RoleAssignment {
+            namespace: "coops/alpha".to_string(),
+            role: name.to_string(),
+        };
+        let mut roles = vec![role("member")];
+
+        assert!(update_roles(&mut roles, role("admin"), true));
+        assert!(!update_roles(&mut roles, role("admin"), true));
+        assert_eq!(roles.len(), 2);
+
+        assert!(update_roles(&mut roles, role("member"), false));
+        assert!(!update_roles(&mut roles, role("member"), false));
+        assert_eq!(roles, vec![role("admin")]);
+    }
+}
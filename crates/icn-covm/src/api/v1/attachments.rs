@@ -0,0 +1,419 @@
+//! Proposal and comment attachments under `/api/v1/proposals/{id}/attachments`
+//!
+//! - `GET /proposals/{id}/attachments` lists the attachments of a proposal
+//! - `POST /proposals/{id}/attachments` uploads a file as `multipart/form-data`
+//!   with a `file` part and an optional `comment_id` part
+//! - `GET /proposals/{id}/attachments/{aid}` downloads a file, honouring a
+//!   single `Range: bytes=...` request
+//!
+//! Uploads are limited to `MAX_ATTACHMENT_BYTES` and to the content types in
+//! `ALLOWED_CONTENT_TYPES`. File contents are stored as content-addressed
+//! blobs, so the same file attached twice is stored once. Storage is accessed
+//! as the caller.
+
+use super::models::Attachment;
+use super::tenant::{self, ScopedVm};
+use super::{error_reply, storage_error_reply, JsonReply};
+use crate::governance::attachments;
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::StorageError;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use futures::TryStreamExt;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::{header, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::hyper::Body;
+use warp::multipart::{FormData, Part};
+use warp::reply::Response;
+use warp::{Buf, Filter, Rejection, Reply};
+
+/// Largest file that may be attached, in bytes
+pub const MAX_ATTACHMENT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Room left in a request body for the multipart framing and other parts
+const FORM_OVERHEAD_BYTES: u64 = 64 * 1024;
+
+/// Size of the chunks a download is streamed in
+const DOWNLOAD_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Content types accepted for attachments
+pub const ALLOWED_CONTENT_TYPES: &[&str] = &[
+    "application/json",
+    "application/pdf",
+    "image/gif",
+    "image/jpeg",
+    "image/png",
+    "image/svg+xml",
+    "text/csv",
+    "text/markdown",
+    "text/plain",
+];
+
+/// Routes for attachment upload and download
+pub fn attachment_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_vm = tenant::scoped_vm(vm);
+
+    let list = warp::path!("proposals" / String / "attachments")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and_then(list_handler);
+
+    let upload = warp::path!("proposals" / String / "attachments")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::multipart::form().max_length(MAX_ATTACHMENT_BYTES + FORM_OVERHEAD_BYTES))
+        .and_then(upload_handler);
+
+    let download = warp::path!("proposals" / String / "attachments" / String)
+        .and(warp::get())
+        .and(auth)
+        .and(with_vm)
+        .and(warp::header::optional::<String>("range"))
+        .and_then(download_handler);
+
+    list.or(upload).or(download)
+}
+
+fn json_reply<T: serde::Serialize>(body: &T, status: StatusCode) -> JsonReply {
+    warp::reply::with_status(warp::reply::json(body), status)
+}
+
+/// Map an error from `governance::attachments` to a response
+fn failure_reply(err: Box<dyn Error>) -> JsonReply {
+    let err = match err.downcast::<StorageError>() {
+        Ok(err) => {
+            return match *err {
+                err @ StorageError::QuotaExceeded { .. } => {
+                    error_reply(err.to_string(), StatusCode::INSUFFICIENT_STORAGE)
+                }
+                err => storage_error_reply(err),
+            }
+        }
+        Err(err) => err,
+    };
+    let message = err.to_string();
+    let status = if message.contains("does not exist") {
+        StatusCode::NOT_FOUND
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    error_reply(message, status)
+}
+
+/// Content type without parameters such as `charset`, lowercased
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Read a part into memory, failing once it grows past `limit` bytes
+async fn read_part(part: Part, limit: u64) -> Result<Option<Vec<u8>>, warp::Error> {
+    let mut data = Vec::new();
+    let mut stream = part.stream();
+    while let Some(mut chunk) = stream.try_next().await? {
+        if data.len() as u64 + chunk.remaining() as u64 > limit {
+            return Ok(None);
+        }
+        while chunk.has_remaining() {
+            let bytes = chunk.chunk();
+            data.extend_from_slice(bytes);
+            let read = bytes.len();
+            chunk.advance(read);
+        }
+    }
+    Ok(Some(data))
+}
+
+/// File and fields of an upload form
+struct Upload {
+    filename: String,
+    content_type: String,
+    data: Vec<u8>,
+    comment_id: Option<String>,
+}
+
+/// Collect an upload form, or the response rejecting it
+async fn read_upload(mut form: FormData) -> Result<Upload, JsonReply> {
+    let invalid = |e: warp::Error| {
+        error_reply(
+            format!("Invalid multipart body: {}", e),
+            StatusCode::BAD_REQUEST,
+        )
+    };
+    let too_large = || {
+        error_reply(
+            format!("Attachments are limited to {} bytes", MAX_ATTACHMENT_BYTES),
+            StatusCode::PAYLOAD_TOO_LARGE,
+        )
+    };
+
+    let mut file = None;
+    let mut comment_id = None;
+    while let Some(part) = form.try_next().await.map_err(invalid)? {
+        match part.name() {
+            "file" => {
+                let filename = part.filename().unwrap_or_default().to_string();
+                let content_type = essence(part.content_type().unwrap_or_default());
+                if !ALLOWED_CONTENT_TYPES.contains(&content_type.as_str()) {
+                    return Err(error_reply(
+                        format!(
+                            "Content type {:?} is not allowed; expected one of {}",
+                            content_type,
+                            ALLOWED_CONTENT_TYPES.join(", ")
+                        ),
+                        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    ));
+                }
+                let data = read_part(part, MAX_ATTACHMENT_BYTES)
+                    .await
+                    .map_err(invalid)?
+                    .ok_or_else(too_large)?;
+                file = Some((filename, content_type, data));
+            }
+            "comment_id" => {
+                let value = read_part(part, 1024)
+                    .await
+                    .map_err(invalid)?
+                    .ok_or_else(|| {
+                        error_reply("comment_id is too long", StatusCode::BAD_REQUEST)
+                    })?;
+                let value = String::from_utf8_lossy(&value).trim().to_string();
+                comment_id = Some(value).filter(|id| !id.is_empty());
+            }
+            // Unknown parts are ignored
+            _ => {}
+        }
+    }
+
+    let (filename, content_type, data) = file
+        .ok_or_else(|| error_reply("Missing multipart part \"file\"", StatusCode::BAD_REQUEST))?;
+    let filename = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    if filename.is_empty() {
+        return Err(error_reply(
+            "The file part must have a filename",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    Ok(Upload {
+        filename: filename.to_string(),
+        content_type,
+        data,
+        comment_id,
+    })
+}
+
+/// Byte range of a `Range` header within content of `len` bytes
+///
+/// Returns `Ok(None)` when the whole content should be sent: there is no
+/// header, it is not a byte range, or it asks for several ranges, which are
+/// served in full rather than as a multipart response. Returns `Err(())` when
+/// the range cannot be satisfied.
+fn parse_range(range: Option<&str>, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = range.and_then(|r| r.trim().strip_prefix("bytes=")) else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let (start, end) = spec.split_once('-').ok_or(())?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        // Suffix range: the last `n` bytes
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().map_err(|_| ())?;
+            if suffix == 0 {
+                return Err(());
+            }
+            (len.saturating_sub(suffix), len.checked_sub(1).ok_or(())?)
+        }
+        (start, "") => (
+            start.parse().map_err(|_| ())?,
+            len.checked_sub(1).ok_or(())?,
+        ),
+        (start, end) => {
+            let start: u64 = start.parse().map_err(|_| ())?;
+            let end: u64 = end.parse().map_err(|_| ())?;
+            (start, end.min(len.saturating_sub(1)))
+        }
+    };
+    if start >= len || start > end {
+        return Err(());
+    }
+    Ok(Some((start, end)))
+}
+
+/// Response body streaming `data` in chunks
+fn chunked_body(data: Bytes) -> Body {
+    Body::wrap_stream(futures::stream::unfold(data, |mut rest| async move {
+        if rest.is_empty() {
+            return None;
+        }
+        let chunk = rest.split_to(rest.len().min(DOWNLOAD_CHUNK_BYTES));
+        Some((Ok::<_, Infallible>(chunk), rest))
+    }))
+}
+
+async fn list_handler<S>(
+    proposal_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let vm_lock = vm.lock().await;
+    Ok(
+        match attachments::list_attachments(&vm_lock, &proposal_id, &auth) {
+            Ok(list) => json_reply(&list, StatusCode::OK),
+            Err(e) => failure_reply(e),
+        },
+    )
+}
+
+async fn upload_handler<S>(
+    proposal_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    form: FormData,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    // Read the body before taking the VM lock so slow uploads do not hold it
+    let upload = match read_upload(form).await {
+        Ok(upload) => upload,
+        Err(reply) => return Ok(reply),
+    };
+
+    let mut vm_lock = vm.lock().await;
+    Ok(
+        match attachments::add_attachment(
+            &mut vm_lock,
+            &proposal_id,
+            upload.comment_id.as_deref(),
+            &upload.filename,
+            &upload.content_type,
+            upload.data,
+            &auth,
+        ) {
+            Ok(attachment) => json_reply(&attachment, StatusCode::CREATED),
+            Err(e) => failure_reply(e),
+        },
+    )
+}
+
+async fn download_handler<S>(
+    proposal_id: String,
+    attachment_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    range: Option<String>,
+) -> Result<Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let (attachment, data): (Attachment, Vec<u8>) = {
+        let vm_lock = vm.lock().await;
+        let result = attachments::get_attachment(&vm_lock, &proposal_id, &attachment_id, &auth)
+            .and_then(|attachment| {
+                let data = attachments::read_attachment(&vm_lock, &attachment, &auth)?;
+                Ok((attachment, data))
+            });
+        match result {
+            Ok(found) => found,
+            Err(e) => return Ok(failure_reply(e).into_response()),
+        }
+    };
+
+    let len = data.len() as u64;
+    let (status, start, end) = match parse_range(range.as_deref(), len) {
+        Ok(Some((start, end))) => (StatusCode::PARTIAL_CONTENT, start, end),
+        Ok(None) => (StatusCode::OK, 0, len.saturating_sub(1)),
+        Err(()) => {
+            let mut response = error_reply(
+                format!("Range not satisfiable for {} bytes", len),
+                StatusCode::RANGE_NOT_SATISFIABLE,
+            )
+            .into_response();
+            if let Ok(value) = format!("bytes */{}", len).parse() {
+                response.headers_mut().insert(header::CONTENT_RANGE, value);
+            }
+            return Ok(response);
+        }
+    };
+
+    let body = if len == 0 {
+        Bytes::new()
+    } else {
+        Bytes::from(data).slice(start as usize..=end as usize)
+    };
+    let mut builder = warp::http::Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, &attachment.content_type)
+        .header(header::CONTENT_LENGTH, body.len())
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, format!("\"{}\"", attachment.sha256))
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{}\"",
+                attachment.filename.replace(['"', '\\'], "_")
+            ),
+        );
+    if status == StatusCode::PARTIAL_CONTENT {
+        builder = builder.header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", start, end, len),
+        );
+    }
+    Ok(match builder.body(chunked_body(body)) {
+        Ok(response) => response,
+        Err(e) => error_reply(
+            format!("Failed to build response: {}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .into_response(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 100), Ok(None));
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), Ok(Some((0, 9))));
+        assert_eq!(parse_range(Some("bytes=90-"), 100), Ok(Some((90, 99))));
+        assert_eq!(parse_range(Some("bytes=-10"), 100), Ok(Some((90, 99))));
+        assert_eq!(parse_range(Some("bytes=50-500"), 100), Ok(Some((50, 99))));
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), Ok(None));
+        assert_eq!(parse_range(Some("items=0-1"), 100), Ok(None));
+
+        assert_eq!(parse_range(Some("bytes=100-"), 100), Err(()));
+        assert_eq!(parse_range(Some("bytes=9-3"), 100), Err(()));
+        assert_eq!(parse_range(Some("bytes=-0"), 100), Err(()));
+        assert_eq!(parse_range(Some("bytes=0-"), 0), Err(()));
+        assert_eq!(parse_range(Some("bytes=x-y"), 100), Err(()));
+    }
+}
@@ -0,0 +1,110 @@
+//! Audit log of mutating actions under `/api/v1/audit`
+//!
+//! - `GET /audit` lists entries newest first, filtered by `actor`, `action`
+//!   (prefix), `namespace`, `source`, `outcome`, `since`, and `until`
+//!
+//! Global admins may read the whole log; a namespace admin may read the
+//! entries of their namespace by filtering on it. This is the log of API
+//! requests and CLI commands written by `crate::api::audit`, not the storage
+//! event log served at `/admin/audit`.
+
+use super::models::{AuditEntry, AuditQuery};
+use super::{error_reply, JsonReply};
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// Entries returned when no limit is given
+const DEFAULT_LIMIT: usize = 100;
+
+/// Most entries returned by one request
+const MAX_LIMIT: usize = 1000;
+
+/// Routes for reading the audit log
+pub fn audit_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    warp::path!("audit")
+        .and(warp::get())
+        .and(auth)
+        .and(warp::any().map(move || vm.clone()))
+        .and(warp::query::<AuditQuery>())
+        .and_then(list_handler)
+}
+
+/// Whether `auth` may read the entries selected by `query`
+fn can_read(auth: &AuthContext, query: &AuditQuery) -> bool {
+    auth.has_role("global", "admin")
+        || query
+            .namespace
+            .as_deref()
+            .is_some_and(|ns| auth.has_role(ns, "admin"))
+}
+
+async fn list_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    mut query: AuditQuery,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if !can_read(&auth, &query) {
+        return Ok(error_reply(
+            "Reading the audit log requires the global admin role, or the admin role in the requested namespace",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+    query.limit = Some(query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT));
+
+    let vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    Ok(match crate::audit::query(storage, &query) {
+        Ok(entries) => warp::reply::with_status(
+            warp::reply::json::<Vec<AuditEntry>>(&entries),
+            StatusCode::OK,
+        ),
+        Err(e) => error_reply(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespace_admin_reads_only_own_namespace() {
+        let mut auth = AuthContext::new("did:key:alice");
+        auth.add_role("coops/alpha", "admin");
+
+        let own = AuditQuery {
+            namespace: Some("coops/alpha".to_string()),
+            ..Default::default()
+        };
+        let other = AuditQuery {
+            namespace: Some("coops/beta".to_string()),
+            ..Default::default()
+        };
+        assert!(can_read(&auth, &own));
+        assert!(!can_read(&auth, &other));
+        assert!(!can_read(&auth, &AuditQuery::default()));
+
+        let mut admin = AuthContext::new("did:key:root");
+        admin.add_role("global", "admin");
+        assert!(can_read(&admin, &AuditQuery::default()));
+    }
+}
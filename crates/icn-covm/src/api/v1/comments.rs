@@ -0,0 +1,355 @@
+//! Threaded proposal comments under `/api/v1/proposals/{id}/comments`
+//!
+//! - `GET /proposals/{id}/comments` lists the comment tree
+//! - `POST /proposals/{id}/comments` adds a comment or reply
+//! - `PUT /proposals/{id}/comments/{cid}` edits the caller's own comment
+//! - `POST /proposals/{id}/comments/{cid}/hide` hides a comment
+//! - `POST /proposals/{id}/comments/{cid}/reactions` reacts to a comment
+//!
+//! Hidden comments are only listed for moderators, and only authors and
+//! moderators may hide a comment. Storage is accessed as the caller.
+
+use super::events::{ApiEvent, EventHub};
+use super::models::{
+    CommentListQuery, CommentResponse, CommentThread, CreateCommentRequest, EditCommentRequest,
+    ReactionRequest,
+};
+use super::tenant::{self, ScopedVm};
+use super::{error_reply, storage_error_reply, JsonReply};
+use crate::governance::comments::{self, ProposalComment};
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::StorageError;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+impl From<&ProposalComment> for CommentResponse {
+    fn from(comment: &ProposalComment) -> Self {
+        CommentResponse {
+            id: comment.id.clone(),
+            author: comment.author.clone(),
+            timestamp: comment.timestamp.to_rfc3339(),
+            content: comment.content.clone(),
+            reply_to: comment.reply_to.clone(),
+            tags: comment.tags.clone(),
+            reactions: comment.reactions.clone(),
+            hidden: comment.hidden,
+            // The first version is the original, not an edit
+            edit_count: comment.edit_history.len().saturating_sub(1),
+        }
+    }
+}
+
+/// Routes for comment management
+pub fn comment_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    hub: EventHub,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_vm = tenant::scoped_vm(vm);
+    let with_hub = warp::any().map(move || hub.clone());
+
+    let list = warp::path!("proposals" / String / "comments")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::query::<CommentListQuery>())
+        .and_then(list_handler);
+
+    let create = warp::path!("proposals" / String / "comments")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(with_hub)
+        .and(warp::body::json::<CreateCommentRequest>())
+        .and_then(create_handler);
+
+    let edit = warp::path!("proposals" / String / "comments" / String)
+        .and(warp::put())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::body::json::<EditCommentRequest>())
+        .and_then(edit_handler);
+
+    let hide = warp::path!("proposals" / String / "comments" / String / "hide")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and_then(hide_handler);
+
+    let react = warp::path!("proposals" / String / "comments" / String / "reactions")
+        .and(warp::post())
+        .and(auth)
+        .and(with_vm)
+        .and(warp::body::json::<ReactionRequest>())
+        .and_then(react_handler);
+
+    list.or(create).or(edit).or(hide).or(react)
+}
+
+fn json_reply<T: serde::Serialize>(body: &T, status: StatusCode) -> JsonReply {
+    warp::reply::with_status(warp::reply::json(body), status)
+}
+
+/// Map an error from `governance::comments` to a response
+fn failure_reply(err: Box<dyn Error>) -> JsonReply {
+    let err = match err.downcast::<StorageError>() {
+        Ok(err) => return storage_error_reply(*err),
+        Err(err) => err,
+    };
+    let message = err.to_string();
+    let status = if message.starts_with("Only the original author") {
+        StatusCode::FORBIDDEN
+    } else if message.contains("does not exist") {
+        StatusCode::NOT_FOUND
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    error_reply(message, status)
+}
+
+/// Arrange comments into reply trees, oldest first at every level
+fn build_threads(comments: &HashMap<String, ProposalComment>) -> Vec<CommentThread> {
+    let mut children: HashMap<Option<&str>, Vec<&ProposalComment>> = HashMap::new();
+    for comment in comments.values() {
+        // Replies to comments that are not listed are shown at the top level
+        let parent = comment
+            .reply_to
+            .as_deref()
+            .filter(|parent| comments.contains_key(*parent));
+        children.entry(parent).or_default().push(comment);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.id.cmp(&b.id)));
+    }
+
+    fn thread(
+        comment: &ProposalComment,
+        children: &HashMap<Option<&str>, Vec<&ProposalComment>>,
+    ) -> CommentThread {
+        CommentThread {
+            comment: comment.into(),
+            replies: children
+                .get(&Some(comment.id.as_str()))
+                .map(|replies| replies.iter().map(|r| thread(r, children)).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    children
+        .get(&None)
+        .map(|roots| roots.iter().map(|c| thread(c, &children)).collect())
+        .unwrap_or_default()
+}
+
+async fn list_handler<S>(
+    proposal_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    query: CommentListQuery,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let vm_lock = vm.lock().await;
+    let show_hidden = query.show_hidden.unwrap_or(false);
+    if show_hidden && !comments::is_moderator(&auth, &comments::comments_namespace(&vm_lock)) {
+        return Ok(error_reply(
+            "Only moderators can view hidden comments",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    let mut all =
+        match comments::fetch_comments_threaded(&vm_lock, &proposal_id, Some(&auth), show_hidden) {
+            Ok(all) => all,
+            Err(e) => return Ok(failure_reply(e)),
+        };
+
+    // A tag filter keeps matching comments plus the ancestors needed to thread them
+    if let Some(tag) = &query.tag {
+        let mut keep: Vec<String> = all
+            .values()
+            .filter(|c| c.tags.iter().any(|t| t == tag))
+            .map(|c| c.id.clone())
+            .collect();
+        let mut kept = std::collections::HashSet::new();
+        while let Some(id) = keep.pop() {
+            if kept.insert(id.clone()) {
+                if let Some(parent) = all.get(&id).and_then(|c| c.reply_to.clone()) {
+                    keep.push(parent);
+                }
+            }
+        }
+        all.retain(|id, _| kept.contains(id));
+    }
+
+    Ok(json_reply(&build_threads(&all), StatusCode::OK))
+}
+
+async fn create_handler<S>(
+    proposal_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    hub: EventHub,
+    request: CreateCommentRequest,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if request.content.trim().is_empty() {
+        return Ok(error_reply(
+            "Comment content cannot be empty",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let mut vm_lock = vm.lock().await;
+    if let Some(parent) = &request.reply_to {
+        if let Err(e) = comments::get_comment(&vm_lock, &proposal_id, parent, Some(&auth)) {
+            return Ok(failure_reply(e));
+        }
+    }
+
+    let author = auth.identity_did().to_string();
+    let namespace = vm_lock.get_namespace().unwrap_or("default").to_string();
+    match comments::create_comment(
+        &mut vm_lock,
+        &proposal_id,
+        &author,
+        &request.content,
+        request.reply_to.as_deref(),
+        request.tags,
+        &auth,
+    ) {
+        Ok(comment) => {
+            hub.publish(ApiEvent::CommentAdded {
+                proposal_id,
+                namespace,
+                comment_id: comment.id.clone(),
+                author,
+            });
+            Ok(json_reply(
+                &CommentResponse::from(&comment),
+                StatusCode::CREATED,
+            ))
+        }
+        Err(e) => Ok(failure_reply(e)),
+    }
+}
+
+async fn edit_handler<S>(
+    proposal_id: String,
+    comment_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    request: EditCommentRequest,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut vm_lock = vm.lock().await;
+    let result = comments::edit_comment(
+        &mut vm_lock,
+        &proposal_id,
+        &comment_id,
+        &request.content,
+        &auth,
+    )
+    .and_then(|_| comments::get_comment(&vm_lock, &proposal_id, &comment_id, Some(&auth)));
+    Ok(match result {
+        Ok(comment) => json_reply(&CommentResponse::from(&comment), StatusCode::OK),
+        Err(e) => failure_reply(e),
+    })
+}
+
+async fn hide_handler<S>(
+    proposal_id: String,
+    comment_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut vm_lock = vm.lock().await;
+    let result = comments::hide_comment(&mut vm_lock, &proposal_id, &comment_id, &auth)
+        .and_then(|_| comments::get_comment(&vm_lock, &proposal_id, &comment_id, Some(&auth)));
+    Ok(match result {
+        Ok(comment) => json_reply(&CommentResponse::from(&comment), StatusCode::OK),
+        Err(e) => failure_reply(e),
+    })
+}
+
+async fn react_handler<S>(
+    proposal_id: String,
+    comment_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    request: ReactionRequest,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if request.reaction.trim().is_empty() {
+        return Ok(error_reply(
+            "Reaction cannot be empty",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let mut vm_lock = vm.lock().await;
+    Ok(
+        match comments::react_to_comment(
+            &mut vm_lock,
+            &proposal_id,
+            &comment_id,
+            &request.reaction,
+            &auth,
+        ) {
+            Ok(comment) => json_reply(&CommentResponse::from(&comment), StatusCode::OK),
+            Err(e) => failure_reply(e),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_threads_nests_replies() {
+        let root = ProposalComment::new("did:key:alice".into(), "root".into(), None, vec![]);
+        let reply = ProposalComment::new(
+            "did:key:bob".into(),
+            "reply".into(),
+            Some(root.id.clone()),
+            vec![],
+        );
+        let orphan = ProposalComment::new(
+            "did:key:carol".into(),
+            "orphan".into(),
+            Some("missing".into()),
+            vec![],
+        );
+        let comments: HashMap<_, _> = [root.clone(), reply.clone(), orphan.clone()]
+            .into_iter()
+            .map(|c| (c.id.clone(), c))
+            .collect();
+
+        let threads = build_threads(&comments);
+        assert_eq!(threads.len(), 2);
+        let root_thread = threads.iter().find(|t| t.comment.id == root.id).unwrap();
+        assert_eq!(root_thread.replies.len(), 1);
+        assert_eq!(root_thread.replies[0].comment.id, reply.id);
+    }
+}
@@ -0,0 +1,53 @@
+//! DSL tooling under `/api/v1/dsl`
+//!
+//! - `POST /dsl/validate` checks a program and returns every error and
+//!   warning with its line and column, so editors can mark problems before
+//!   a proposal is submitted
+//!
+//! Validation only parses the source; nothing is executed or stored.
+
+use super::models::{ErrorResponse, ValidateDslRequest, ValidateDslResponse};
+use crate::compiler::{validate_dsl, Severity};
+use crate::storage::auth::AuthContext;
+use warp::http::StatusCode;
+use warp::reply::{Json, WithStatus};
+use warp::{Filter, Rejection, Reply};
+
+/// Largest program accepted for validation
+const MAX_SOURCE_BYTES: u64 = 1024 * 1024;
+
+/// Routes for DSL tooling
+pub fn dsl_routes(
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("dsl" / "validate")
+        .and(warp::post())
+        .and(auth)
+        .and(warp::body::content_length_limit(MAX_SOURCE_BYTES))
+        .and(warp::body::json::<ValidateDslRequest>())
+        .and_then(validate_handler)
+}
+
+async fn validate_handler(
+    _auth: AuthContext,
+    request: ValidateDslRequest,
+) -> Result<WithStatus<Json>, Rejection> {
+    if request.source.trim().is_empty() {
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&ErrorResponse {
+                message: "DSL source cannot be empty".to_string(),
+            }),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let diagnostics = validate_dsl(&request.source);
+    let response = ValidateDslResponse {
+        valid: !diagnostics.iter().any(|d| d.severity == Severity::Error),
+        diagnostics,
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&response),
+        StatusCode::OK,
+    ))
+}
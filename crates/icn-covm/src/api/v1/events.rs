@@ -0,0 +1,261 @@
+//! Live event fan-out for API clients
+//!
+//! The `EventHub` turns the ledger's append channel and the VM's event
+//! channel into `ApiEvent`s and broadcasts them to every connected client.
+//! Handlers that change state outside the ledger (e.g. new comments) publish
+//! to the hub directly.
+//!
+//! Proposal creation, votes and execution are published twice: as the
+//! summarised `ProposalState` and `VoteCounts` that proposal subscribers
+//! follow, and as lifecycle events for namespace subscribers. Every event
+//! records the namespace it happened in, so it is only delivered to clients
+//! who may access that namespace.
+
+use crate::cli::proposal::count_votes;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::types::VMEvent;
+use crate::vm::VM;
+use icn_ledger::{DagNode, NodeData};
+use serde::Serialize;
+use std::fmt::Debug;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+/// Number of events buffered per client before slow clients start missing events
+const EVENT_BUFFER: usize = 256;
+
+/// An event delivered to API subscribers
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApiEvent {
+    /// A proposal was created or changed state
+    ProposalState {
+        proposal_id: String,
+        namespace: String,
+        state: String,
+    },
+    /// A comment was added to a proposal
+    CommentAdded {
+        proposal_id: String,
+        namespace: String,
+        comment_id: String,
+        author: String,
+    },
+    /// The vote tally of a proposal changed
+    VoteCounts {
+        proposal_id: String,
+        namespace: String,
+        yes: u32,
+        no: u32,
+        abstain: u32,
+        total: u32,
+    },
+    /// A proposal was recorded on the ledger
+    ProposalCreated {
+        proposal_id: String,
+        namespace: String,
+        title: String,
+    },
+    /// A vote was recorded on the ledger
+    VoteCast {
+        proposal_id: String,
+        namespace: String,
+        voter: String,
+        vote: f64,
+    },
+    /// A proposal's logic was executed
+    ProposalExecuted {
+        proposal_id: String,
+        namespace: String,
+        success: bool,
+    },
+    /// Output or an event emitted by the VM
+    VmEvent {
+        proposal_id: Option<String>,
+        /// Namespace the VM was executing in
+        namespace: String,
+        category: String,
+        message: String,
+        timestamp: u64,
+    },
+}
+
+impl ApiEvent {
+    /// The proposal this event concerns, if any
+    pub fn proposal_id(&self) -> Option<&str> {
+        match self {
+            ApiEvent::ProposalState { proposal_id, .. }
+            | ApiEvent::CommentAdded { proposal_id, .. }
+            | ApiEvent::VoteCounts { proposal_id, .. }
+            | ApiEvent::ProposalCreated { proposal_id, .. }
+            | ApiEvent::VoteCast { proposal_id, .. }
+            | ApiEvent::ProposalExecuted { proposal_id, .. } => Some(proposal_id),
+            ApiEvent::VmEvent { proposal_id, .. } => proposal_id.as_deref(),
+        }
+    }
+
+    /// The namespace this event happened in, for VM and lifecycle events
+    pub fn namespace(&self) -> Option<&str> {
+        match self {
+            ApiEvent::ProposalCreated { namespace, .. }
+            | ApiEvent::VoteCast { namespace, .. }
+            | ApiEvent::ProposalExecuted { namespace, .. }
+            | ApiEvent::VmEvent { namespace, .. } => Some(namespace),
+            ApiEvent::ProposalState { .. }
+            | ApiEvent::CommentAdded { .. }
+            | ApiEvent::VoteCounts { .. } => None,
+        }
+    }
+
+    /// The namespace this event happened in, which decides who may see it
+    pub fn scope(&self) -> &str {
+        match self {
+            ApiEvent::ProposalState { namespace, .. }
+            | ApiEvent::CommentAdded { namespace, .. }
+            | ApiEvent::VoteCounts { namespace, .. }
+            | ApiEvent::ProposalCreated { namespace, .. }
+            | ApiEvent::VoteCast { namespace, .. }
+            | ApiEvent::ProposalExecuted { namespace, .. }
+            | ApiEvent::VmEvent { namespace, .. } => namespace,
+        }
+    }
+}
+
+/// Broadcasts `ApiEvent`s to all subscribed clients
+#[derive(Debug, Clone)]
+pub struct EventHub {
+    tx: broadcast::Sender<ApiEvent>,
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventHub {
+    /// Create a hub with no sources attached
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_BUFFER);
+        Self { tx }
+    }
+
+    /// Publish an event to all current subscribers
+    pub fn publish(&self, event: ApiEvent) {
+        // Sending only fails when nobody is listening
+        let _ = self.tx.send(event);
+    }
+
+    /// Subscribe to all events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ApiEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Forward ledger appends as lifecycle, proposal state and vote count
+    /// events
+    ///
+    /// Vote counts are recomputed from storage, so the VM is locked briefly
+    /// for each vote.
+    pub fn forward_ledger<S>(&self, nodes: Receiver<DagNode>, vm: Arc<Mutex<VM<S>>>)
+    where
+        S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        bridge(nodes, tx);
+
+        let hub = self.clone();
+        tokio::spawn(async move {
+            while let Some(node) = rx.recv().await {
+                let namespace = node.namespace;
+                match node.data {
+                    NodeData::ProposalCreated { proposal_id, title } => {
+                        hub.publish(ApiEvent::ProposalCreated {
+                            proposal_id: proposal_id.clone(),
+                            namespace: namespace.clone(),
+                            title,
+                        });
+                        hub.publish(ApiEvent::ProposalState {
+                            proposal_id,
+                            namespace,
+                            state: "Draft".to_string(),
+                        });
+                    }
+                    NodeData::ProposalExecuted {
+                        proposal_id,
+                        success,
+                    } => {
+                        hub.publish(ApiEvent::ProposalExecuted {
+                            proposal_id: proposal_id.clone(),
+                            namespace: namespace.clone(),
+                            success,
+                        });
+                        hub.publish(ApiEvent::ProposalState {
+                            proposal_id,
+                            namespace,
+                            state: if success { "Executed" } else { "Failed" }.to_string(),
+                        });
+                    }
+                    NodeData::VoteCast {
+                        proposal_id,
+                        voter,
+                        vote,
+                    } => {
+                        hub.publish(ApiEvent::VoteCast {
+                            proposal_id: proposal_id.clone(),
+                            namespace: namespace.clone(),
+                            voter,
+                            vote,
+                        });
+                        let vm_lock = vm.lock().await;
+                        if let Ok((yes, no, abstain)) = count_votes(&vm_lock, &proposal_id) {
+                            hub.publish(ApiEvent::VoteCounts {
+                                proposal_id,
+                                namespace,
+                                yes,
+                                no,
+                                abstain,
+                                total: yes + no + abstain,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    /// Forward VM output and events, tagged with the proposal they belong to
+    pub fn forward_vm_events(
+        &self,
+        events: Receiver<(String, VMEvent)>,
+        proposal_id: Option<String>,
+    ) {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        bridge(events, tx);
+
+        let hub = self.clone();
+        tokio::spawn(async move {
+            while let Some((namespace, event)) = rx.recv().await {
+                hub.publish(ApiEvent::VmEvent {
+                    proposal_id: proposal_id.clone(),
+                    namespace,
+                    category: event.category,
+                    message: event.message,
+                    timestamp: event.timestamp,
+                });
+            }
+        });
+    }
+}
+
+/// Move items from a blocking channel onto an async one on a dedicated thread
+fn bridge<T: Send + 'static>(from: Receiver<T>, to: mpsc::UnboundedSender<T>) {
+    std::thread::spawn(move || {
+        while let Ok(item) = from.recv() {
+            if to.send(item).is_err() {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,525 @@
+//! Program and proposal executions
+//!
+//! `POST /api/v1/executions` runs a submitted DSL program or a proposal's
+//! logic. It responds in one of two ways:
+//!
+//! - with `Accept: text/event-stream`, as an SSE stream: every `Emit` and
+//!   `EmitEvent` is sent as it is produced, followed by a final `finished`
+//!   event carrying the outcome
+//! - otherwise, by queueing the run as a background job and answering
+//!   `202 Accepted` with the job record, whose status, output, and final
+//!   stack are polled via `GET /api/v1/executions/{id}`
+//!
+//! `POST /api/v1/proposals/{id}/execute` always streams.
+//!
+//! Programs run as the caller, so storage permission checks apply. They also
+//! run in a sandbox (see [`crate::vm::sandbox`]): `deliberation-preview`
+//! unless the request's `sandbox` field names another profile, which only
+//! global admins may do. Proposal logic runs under `full-governance`.
+//!
+//! The VM is held for the whole run, so other executions wait until it
+//! finishes. Job records are kept in memory while the server runs and
+//! persisted to the `system` namespace when a job starts and finishes; only
+//! the submitter and global admins can read them.
+
+use super::error_reply;
+use super::models::{ExecuteProgramRequest, ExecutionJob, ExecutionResult, JobStatus};
+use super::tenant::{self, ScopedGuard, ScopedVm};
+use crate::api::auth::system_auth;
+use crate::cli::proposal::VMProposalExtensions;
+use crate::compiler::parse_dsl;
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::sandbox::SandboxProfile;
+use crate::vm::types::VMEvent;
+use crate::vm::{Op, VM};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::sse::Event;
+use warp::{Filter, Rejection, Reply};
+
+/// How often the forwarder checks whether execution has finished
+const FINISH_POLL: Duration = Duration::from_millis(50);
+
+/// Namespace job records are persisted in
+const JOB_NAMESPACE: &str = "system";
+
+/// Number of jobs kept in memory above which finished jobs are dropped
+const MAX_CACHED_JOBS: usize = 1000;
+/// An update sent to the client while a program runs
+enum ExecutionUpdate {
+    Output(VMEvent),
+    Finished(ExecutionResult),
+}
+
+impl ExecutionUpdate {
+    fn into_sse(self) -> Event {
+        let event = match self {
+            ExecutionUpdate::Output(event) => Event::default()
+                .event(if event.category == "output" {
+                    "output"
+                } else {
+                    "event"
+                })
+                .json_data(&event),
+            ExecutionUpdate::Finished(result) => {
+                Event::default().event("finished").json_data(&result)
+            }
+        };
+        event.unwrap_or_else(|e| Event::default().event("error").data(e.to_string()))
+    }
+}
+
+/// What an execution request asks to run
+pub(crate) enum Target {
+    /// A submitted program and the sandbox profile it runs under
+    Program(Vec<Op>, SandboxProfile),
+    Proposal(String),
+}
+
+impl Target {
+    pub(crate) fn from_request(request: ExecuteProgramRequest) -> Result<Self, String> {
+        match (request.program, request.proposal_id, request.sandbox) {
+            (Some(program), None, sandbox) => {
+                let sandbox = sandbox.as_deref().map_or(
+                    Ok(SandboxProfile::deliberation_preview()),
+                    SandboxProfile::named,
+                )?;
+                parse_dsl(&program)
+                    .map(|(ops, _)| Target::Program(ops, sandbox))
+                    .map_err(|e| format!("Failed to parse program: {}", e))
+            }
+            (None, Some(proposal_id), None) => Ok(Target::Proposal(proposal_id)),
+            (None, Some(_), Some(_)) => {
+                Err("A sandbox profile can only be chosen for programs".to_string())
+            }
+            _ => Err("Exactly one of program and proposal_id must be given".to_string()),
+        }
+    }
+
+    /// Check that `auth` may run the target under its sandbox profile
+    ///
+    /// Anyone may run a program under `deliberation-preview`; wider profiles
+    /// take the global admin role.
+    pub(crate) fn authorize(&self, auth: &AuthContext) -> Result<(), String> {
+        match self {
+            Target::Program(_, sandbox)
+                if !sandbox.is_within(&SandboxProfile::deliberation_preview())
+                    && !auth.has_role("global", "admin") =>
+            {
+                Err(format!(
+                    "Running programs under sandbox profile {} requires the global admin role",
+                    sandbox.name
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn run<S>(self, vm: &mut VM<S>) -> Result<(), String>
+    where
+        S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+    {
+        match self {
+            Target::Program(ops, sandbox) => vm
+                .with_sandbox(sandbox, |vm| vm.execute(&ops))
+                .map_err(|e| e.to_string()),
+            Target::Proposal(proposal_id) => vm
+                .with_sandbox(SandboxProfile::full_governance(), |vm| {
+                    vm.execute_proposal(&proposal_id, None)
+                })
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Execution jobs of this server, by ID
+///
+/// Jobs stay here after they finish so polling never waits for the VM; the
+/// oldest finished jobs are dropped once there are more than
+/// `MAX_CACHED_JOBS`, and are then read back from storage.
+#[derive(Debug, Clone, Default)]
+pub struct JobRegistry {
+    jobs: Arc<std::sync::Mutex<HashMap<String, ExecutionJob>>>,
+}
+
+impl JobRegistry {
+    fn get(&self, id: &str) -> Option<ExecutionJob> {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.get(id).cloned()
+    }
+
+    fn record(&self, job: &ExecutionJob) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.insert(job.id.clone(), job.clone());
+        if jobs.len() > MAX_CACHED_JOBS {
+            let mut finished: Vec<(String, String)> = jobs
+                .values()
+                .filter_map(|job| Some((job.finished_at.clone()?, job.id.clone())))
+                .collect();
+            finished.sort();
+            let excess = jobs.len() - MAX_CACHED_JOBS;
+            for (_, id) in finished.into_iter().take(excess) {
+                jobs.remove(&id);
+            }
+        }
+    }
+}
+
+fn job_key(id: &str) -> String {
+    format!("execution_jobs/{}", id)
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+/// Persist a job record, logging rather than failing the job on error
+fn save_job<S>(vm: &mut VM<S>, job: &ExecutionJob)
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let Some(storage) = vm.get_storage_backend_mut() else {
+        return;
+    };
+    if let Err(e) = storage.set_json(Some(&system_auth()), JOB_NAMESPACE, &job_key(&job.id), job) {
+        tracing::warn!("Failed to persist execution job {}: {}", job.id, e);
+    }
+}
+
+/// Routes for executions and execution jobs
+pub fn execution_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    jobs: JobRegistry,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_vm = tenant::scoped_vm(vm);
+    let with_jobs = warp::any().map(move || jobs.clone());
+
+    let program = warp::path!("executions")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(with_jobs.clone())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::body::json::<ExecuteProgramRequest>())
+        .and_then(execute_handler);
+
+    let job = warp::path!("executions" / String)
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(with_jobs)
+        .and_then(job_handler);
+
+    let proposal = warp::path!("proposals" / String / "execute")
+        .and(warp::post())
+        .and(auth)
+        .and(with_vm)
+        .and_then(execute_proposal_handler);
+
+    program.or(job).or(proposal)
+}
+
+async fn execute_handler<S>(
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    jobs: JobRegistry,
+    accept: Option<String>,
+    request: ExecuteProgramRequest,
+) -> Result<warp::reply::Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let target = match Target::from_request(request) {
+        Ok(target) => target,
+        Err(message) => return Ok(error_reply(message, StatusCode::BAD_REQUEST).into_response()),
+    };
+    if let Err(message) = target.authorize(&auth) {
+        return Ok(error_reply(message, StatusCode::FORBIDDEN).into_response());
+    }
+
+    if accept.is_some_and(|accept| accept.contains("text/event-stream")) {
+        let guard = vm.lock().await;
+        return Ok(stream_execution(guard, auth, move |vm| target.run(vm)));
+    }
+
+    let job = ExecutionJob {
+        id: Uuid::new_v4().to_string(),
+        status: JobStatus::Queued,
+        proposal_id: match &target {
+            Target::Proposal(proposal_id) => Some(proposal_id.clone()),
+            Target::Program(..) => None,
+        },
+        namespace: vm.namespace().unwrap_or_default().to_string(),
+        submitted_by: auth.identity_did().to_string(),
+        submitted_at: now(),
+        started_at: None,
+        finished_at: None,
+        output: Vec::new(),
+        stack: Vec::new(),
+        error: None,
+    };
+    jobs.record(&job);
+    tokio::spawn(run_job(vm, jobs, auth, target, job.clone()));
+
+    Ok(warp::reply::with_status(warp::reply::json(&job), StatusCode::ACCEPTED).into_response())
+}
+
+/// Run a queued job once the VM is free, recording its progress
+async fn run_job<S>(
+    vm: ScopedVm<S>,
+    jobs: JobRegistry,
+    auth: AuthContext,
+    target: Target,
+    mut job: ExecutionJob,
+) where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut guard = vm.lock().await;
+    job.status = JobStatus::Running;
+    job.started_at = Some(now());
+    job.namespace = guard.get_namespace().unwrap_or("default").to_string();
+    jobs.record(&job);
+    save_job(&mut guard, &job);
+
+    let events = guard.subscribe_events();
+    let (mut guard, result) = match tokio::task::spawn_blocking(move || {
+        let result = guard.with_auth_context(auth, |vm| target.run(vm));
+        (guard, result)
+    })
+    .await
+    {
+        Ok(finished) => finished,
+        Err(e) => (vm.lock().await, Err(format!("Execution aborted: {}", e))),
+    };
+
+    // Events are emitted synchronously, so all of them are queued by now
+    job.output = events.try_iter().collect();
+    job.stack = guard.get_stack();
+    job.finished_at = Some(now());
+    job.status = if result.is_ok() {
+        JobStatus::Succeeded
+    } else {
+        JobStatus::Failed
+    };
+    job.error = result.err();
+    jobs.record(&job);
+    save_job(&mut guard, &job);
+}
+
+async fn job_handler<S>(
+    job_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    jobs: JobRegistry,
+) -> Result<warp::reply::Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let job = match jobs.get(&job_id) {
+        Some(job) => Some(job),
+        None => {
+            let vm_lock = vm.lock().await;
+            vm_lock.get_storage_backend().and_then(|storage| {
+                storage
+                    .get_json::<ExecutionJob>(
+                        Some(&system_auth()),
+                        JOB_NAMESPACE,
+                        &job_key(&job_id),
+                    )
+                    .ok()
+            })
+        }
+    };
+
+    // Jobs of other callers, or of another cooperative, are reported as missing
+    let visible = job.filter(|job| {
+        (job.submitted_by == auth.identity_did() || auth.has_role("global", "admin"))
+            && vm
+                .namespace()
+                .is_none_or(|namespace| namespace == job.namespace)
+    });
+    Ok(match visible {
+        Some(job) => {
+            warp::reply::with_status(warp::reply::json(&job), StatusCode::OK).into_response()
+        }
+        None => error_reply(
+            format!("Execution job {} not found", job_id),
+            StatusCode::NOT_FOUND,
+        )
+        .into_response(),
+    })
+}
+
+async fn execute_proposal_handler<S>(
+    proposal_id: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+) -> Result<warp::reply::Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let guard = vm.lock().await;
+    Ok(stream_execution(guard, auth, move |vm| {
+        Target::Proposal(proposal_id).run(vm)
+    }))
+}
+
+/// Run `run` on a blocking thread and stream its output as SSE
+fn stream_execution<S, F>(
+    mut vm: ScopedGuard<S>,
+    auth: AuthContext,
+    run: F,
+) -> warp::reply::Response
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+    F: FnOnce(&mut VM<S>) -> Result<(), String> + Send + 'static,
+{
+    let events = vm.subscribe_events();
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::task::spawn_blocking(move || {
+        let result = vm.with_auth_context(auth, run);
+        // Release the VM before reporting, so the client can act on the result
+        drop(vm);
+        let _ = done_tx.send(result);
+    });
+    std::thread::spawn(move || forward_until_done(events, done_rx, tx));
+
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv()
+            .await
+            .map(|update| (Ok::<_, Infallible>(update.into_sse()), rx))
+    });
+    warp::sse::reply(warp::sse::keep_alive().stream(stream)).into_response()
+}
+
+/// Forward VM events until execution finishes, then send the outcome
+///
+/// Events are emitted synchronously, so once the run has returned every
+/// event it produced is already queued and is drained before `finished`.
+fn forward_until_done(
+    events: Receiver<VMEvent>,
+    done: Receiver<Result<(), String>>,
+    tx: mpsc::UnboundedSender<ExecutionUpdate>,
+) {
+    let result = loop {
+        match events.recv_timeout(FINISH_POLL) {
+            Ok(event) => {
+                let _ = tx.send(ExecutionUpdate::Output(event));
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if let Ok(result) = done.try_recv() {
+                    break result;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                break done
+                    .recv()
+                    .unwrap_or_else(|_| Err("Execution aborted".to_string()));
+            }
+        }
+    };
+
+    while let Ok(event) = events.try_recv() {
+        let _ = tx.send(ExecutionUpdate::Output(event));
+    }
+    let _ = tx.send(ExecutionUpdate::Finished(ExecutionResult {
+        success: result.is_ok(),
+        error: result.err(),
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    fn job(id: &str, finished_at: Option<&str>) -> ExecutionJob {
+        ExecutionJob {
+            id: id.to_string(),
+            status: if finished_at.is_some() {
+                JobStatus::Succeeded
+            } else {
+                JobStatus::Running
+            },
+            proposal_id: None,
+            namespace: "default".to_string(),
+            submitted_by: "did:key:alice".to_string(),
+            submitted_at: "2024-01-01T00:00:00+00:00".to_string(),
+            started_at: None,
+            finished_at: finished_at.map(str::to_string),
+            output: Vec::new(),
+            stack: Vec::new(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_registry_drops_oldest_finished_jobs() {
+        let jobs = JobRegistry::default();
+        jobs.record(&job("running", None));
+        jobs.record(&job("oldest", Some("2024-01-01T00:00:00+00:00")));
+        for i in 0..MAX_CACHED_JOBS - 1 {
+            jobs.record(&job(
+                &format!("job-{}", i),
+                Some("2024-06-01T00:00:00+00:00"),
+            ));
+        }
+
+        assert!(jobs.get("oldest").is_none());
+        assert!(jobs.get("running").is_some());
+        assert!(jobs.get("job-0").is_some());
+    }
+
+    #[test]
+    fn test_target_requires_exactly_one_field() {
+        let request = |program: Option<&str>, proposal_id: Option<&str>| ExecuteProgramRequest {
+            program: program.map(str::to_string),
+            proposal_id: proposal_id.map(str::to_string),
+            sandbox: None,
+        };
+        assert!(Target::from_request(request(Some("push 1"), None)).is_ok());
+        assert!(Target::from_request(request(None, Some("p1"))).is_ok());
+        assert!(Target::from_request(request(None, None)).is_err());
+        assert!(Target::from_request(request(Some("push 1"), Some("p1"))).is_err());
+        assert!(Target::from_request(request(Some("push"), None)).is_err());
+    }
+
+    #[test]
+    fn test_wider_sandbox_requires_global_admin() {
+        let request = |program: Option<&str>, sandbox: Option<&str>| ExecuteProgramRequest {
+            program: program.map(str::to_string),
+            proposal_id: None,
+            sandbox: sandbox.map(str::to_string),
+        };
+        let member = AuthContext::new("did:key:member");
+        let mut admin = AuthContext::new("did:key:admin");
+        admin.add_role("global", "admin");
+
+        let preview = Target::from_request(request(Some("push 1"), None)).unwrap();
+        assert!(preview.authorize(&member).is_ok());
+        let full = Target::from_request(request(Some("push 1"), Some("full-governance"))).unwrap();
+        assert!(full.authorize(&member).is_err());
+        assert!(full.authorize(&admin).is_ok());
+        assert!(Target::from_request(request(Some("push 1"), Some("root"))).is_err());
+
+        let mut vm = VM::<InMemoryStorage>::new();
+        let store = Target::from_request(request(Some("push 1\nstorep x"), None)).unwrap();
+        let error = store.run(&mut vm).unwrap_err();
+        assert!(error.contains("deliberation-preview"), "{}", error);
+        assert!(vm.sandbox.is_none());
+    }
+}
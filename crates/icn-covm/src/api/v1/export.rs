@@ -0,0 +1,123 @@
+//! Governance table export at `/api/v1/export/{table}`
+//!
+//! Serves the `proposals`, `votes`, or `comments` table of a namespace (see
+//! `governance::export`) as a file download:
+//!
+//! ```text
+//! GET /api/v1/export/votes?format=csv&namespace=coops/alpha
+//! GET /api/v1/coops/alpha/export/proposals?format=parquet
+//! ```
+//!
+//! Storage is read as the caller, so only members who can read a namespace
+//! can export it. Parquet requires a build with the `parquet` feature.
+
+use super::error_reply;
+use super::models::TableExportQuery;
+use super::tenant::{self, ScopedVm};
+use crate::governance::export::{self, ExportFormat, TABLE_NAMES};
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::{header, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Route for GET /export/{table}
+pub fn export_route<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    warp::path!("export" / String)
+        .and(warp::get())
+        .and(auth)
+        .and(tenant::scoped_vm(vm))
+        .and(warp::query::<TableExportQuery>())
+        .and_then(export_handler)
+}
+
+async fn export_handler<S>(
+    table: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    query: TableExportQuery,
+) -> Result<Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if !TABLE_NAMES.contains(&table.as_str()) {
+        return Ok(error_reply(
+            format!(
+                "Unknown table {}, expected one of {}",
+                table,
+                TABLE_NAMES.join(", ")
+            ),
+            StatusCode::NOT_FOUND,
+        )
+        .into_response());
+    }
+    let format = match query
+        .format
+        .as_deref()
+        .unwrap_or("csv")
+        .parse::<ExportFormat>()
+    {
+        Ok(format) => format,
+        Err(message) => return Ok(error_reply(message, StatusCode::BAD_REQUEST).into_response()),
+    };
+    if format == ExportFormat::Parquet && !cfg!(feature = "parquet") {
+        return Ok(error_reply(
+            "This server was built without Parquet support",
+            StatusCode::NOT_IMPLEMENTED,
+        )
+        .into_response());
+    }
+
+    let vm_lock = vm.lock().await;
+    // Requests scoped to a cooperative cannot export another namespace
+    let namespace = match (vm.namespace(), &query.namespace) {
+        (None, Some(namespace)) => namespace.clone(),
+        _ => vm_lock.get_namespace().unwrap_or("default").to_string(),
+    };
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Ok(
+            error_reply("Storage not available", StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+        );
+    };
+    let tables = match export::collect_tables(storage, Some(&auth), &namespace) {
+        Ok(tables) => tables,
+        Err(message) => return Ok(error_reply(message, StatusCode::FORBIDDEN).into_response()),
+    };
+    drop(vm_lock);
+
+    let Some(table) = tables.into_iter().find(|t| t.name == table) else {
+        return Ok(
+            error_reply("Table not produced", StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+        );
+    };
+    let body = match table.encode(format) {
+        Ok(body) => body,
+        Err(message) => {
+            return Ok(error_reply(message, StatusCode::INTERNAL_SERVER_ERROR).into_response())
+        }
+    };
+
+    let filename = format!(
+        "{}_{}.{}",
+        namespace.replace('/', "_"),
+        table.name,
+        format.extension()
+    );
+    let reply = warp::reply::with_header(body, header::CONTENT_TYPE, format.content_type());
+    Ok(warp::reply::with_header(
+        reply,
+        header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{}\"", filename),
+    )
+    .into_response())
+}
@@ -0,0 +1,236 @@
+//! Federation management under `/api/v1/federation`
+//!
+//! - `GET /federation` reports the local peer ID, bootstrap nodes, and peers
+//! - `GET /federation/peers` lists known peers with their liveness and reputation
+//! - `POST /federation/bootstrap` adds a bootstrap address and dials it
+//! - `POST /federation/broadcast` broadcasts a local proposal to the peers
+//! - `POST /federation/sync` asks the peers for missing ledger nodes and
+//!   refreshes the routing table
+//!
+//! Reads require any authenticated caller; changes require the global admin
+//! role. Changes are queued for the running node and answered with
+//! `202 Accepted`. All routes answer `503` when the API was started without
+//! a federation node.
+
+use super::models::{AddBootstrapNodeRequest, BroadcastProposalRequest, FederationStatus};
+use super::{error_reply, JsonReply};
+use crate::cli::federation::local_to_federated_proposal;
+use crate::cli::proposal::load_proposal_from_governance;
+use crate::federation::messages::{ProposalScope, VotingModel};
+use crate::federation::NodeHandle;
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use libp2p::Multiaddr;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// Routes for managing the federation node
+pub fn federation_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    node: Option<NodeHandle>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_node = warp::any().map(move || node.clone());
+    let with_vm = warp::any().map(move || vm.clone());
+
+    let status = warp::path!("federation")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_node.clone())
+        .and_then(status_handler);
+
+    let peers = warp::path!("federation" / "peers")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_node.clone())
+        .and_then(peers_handler);
+
+    let bootstrap = warp::path!("federation" / "bootstrap")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_node.clone())
+        .and(warp::body::json::<AddBootstrapNodeRequest>())
+        .and_then(bootstrap_handler);
+
+    let broadcast = warp::path!("federation" / "broadcast")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_node.clone())
+        .and(with_vm)
+        .and(warp::body::json::<BroadcastProposalRequest>())
+        .and_then(broadcast_handler);
+
+    let sync = warp::path!("federation" / "sync")
+        .and(warp::post())
+        .and(auth)
+        .and(with_node)
+        .and_then(sync_handler);
+
+    status.or(peers).or(bootstrap).or(broadcast).or(sync)
+}
+
+/// The node, or the reply to send when federation is not enabled
+fn require_node(node: Option<NodeHandle>) -> Result<NodeHandle, JsonReply> {
+    node.ok_or_else(|| {
+        error_reply(
+            "Federation is not enabled on this node",
+            StatusCode::SERVICE_UNAVAILABLE,
+        )
+    })
+}
+
+fn require_admin(auth: &AuthContext) -> Result<(), JsonReply> {
+    if auth.has_role("global", "admin") {
+        Ok(())
+    } else {
+        Err(error_reply(
+            "Managing federation requires the global admin role",
+            StatusCode::FORBIDDEN,
+        ))
+    }
+}
+
+async fn federation_status(node: &NodeHandle) -> FederationStatus {
+    FederationStatus {
+        local_peer_id: node.local_peer_id().to_string(),
+        bootstrap_nodes: node
+            .bootstrap_nodes()
+            .await
+            .iter()
+            .map(|addr| addr.to_string())
+            .collect(),
+        peers: node.peers().await,
+    }
+}
+
+async fn status_handler(
+    _auth: AuthContext,
+    node: Option<NodeHandle>,
+) -> Result<JsonReply, Rejection> {
+    Ok(match require_node(node) {
+        Ok(node) => warp::reply::with_status(
+            warp::reply::json(&federation_status(&node).await),
+            StatusCode::OK,
+        ),
+        Err(reply) => reply,
+    })
+}
+
+async fn peers_handler(
+    _auth: AuthContext,
+    node: Option<NodeHandle>,
+) -> Result<JsonReply, Rejection> {
+    Ok(match require_node(node) {
+        Ok(node) => {
+            warp::reply::with_status(warp::reply::json(&node.peers().await), StatusCode::OK)
+        }
+        Err(reply) => reply,
+    })
+}
+
+async fn bootstrap_handler(
+    auth: AuthContext,
+    node: Option<NodeHandle>,
+    request: AddBootstrapNodeRequest,
+) -> Result<JsonReply, Rejection> {
+    let node = match require_admin(&auth).and_then(|_| require_node(node)) {
+        Ok(node) => node,
+        Err(reply) => return Ok(reply),
+    };
+    let addr = match request.address.parse::<Multiaddr>() {
+        Ok(addr) => addr,
+        Err(e) => {
+            return Ok(error_reply(
+                format!("Invalid multiaddress: {}", e),
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+    };
+    Ok(match node.add_bootstrap_node(addr).await {
+        Ok(()) => warp::reply::with_status(warp::reply::json(&request), StatusCode::ACCEPTED),
+        Err(e) => error_reply(e.to_string(), StatusCode::SERVICE_UNAVAILABLE),
+    })
+}
+
+async fn broadcast_handler<S>(
+    auth: AuthContext,
+    node: Option<NodeHandle>,
+    vm: Arc<Mutex<VM<S>>>,
+    request: BroadcastProposalRequest,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let node = match require_admin(&auth).and_then(|_| require_node(node)) {
+        Ok(node) => node,
+        Err(reply) => return Ok(reply),
+    };
+    let proposal = {
+        let vm_lock = vm.lock().await;
+        match load_proposal_from_governance(&vm_lock, &request.proposal_id) {
+            Ok(proposal) => proposal,
+            Err(e) => {
+                return Ok(error_reply(
+                    format!("Proposal {} not found: {}", request.proposal_id, e),
+                    StatusCode::NOT_FOUND,
+                ))
+            }
+        }
+    };
+
+    let federated = local_to_federated_proposal(
+        &proposal,
+        request.scope.unwrap_or(ProposalScope::GlobalFederation),
+        request
+            .voting_model
+            .unwrap_or(VotingModel::OneMemberOneVote),
+        request.expires_in,
+    );
+    Ok(match node.broadcast_proposal(federated.clone()).await {
+        Ok(()) => warp::reply::with_status(warp::reply::json(&federated), StatusCode::ACCEPTED),
+        Err(e) => error_reply(e.to_string(), StatusCode::SERVICE_UNAVAILABLE),
+    })
+}
+
+async fn sync_handler(auth: AuthContext, node: Option<NodeHandle>) -> Result<JsonReply, Rejection> {
+    let node = match require_admin(&auth).and_then(|_| require_node(node)) {
+        Ok(node) => node,
+        Err(reply) => return Ok(reply),
+    };
+    Ok(match node.sync_ledger().await {
+        Ok(()) => warp::reply::with_status(
+            warp::reply::json(&federation_status(&node).await),
+            StatusCode::ACCEPTED,
+        ),
+        Err(e) => error_reply(e.to_string(), StatusCode::SERVICE_UNAVAILABLE),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_require_admin_and_node() {
+        let member = AuthContext::new("did:key:alice");
+        assert_eq!(
+            require_admin(&member).unwrap_err().into_response().status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let mut admin = AuthContext::new("did:key:root");
+        admin.add_role("global", "admin");
+        assert!(require_admin(&admin).is_ok());
+        assert_eq!(
+            require_node(None).unwrap_err().into_response().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
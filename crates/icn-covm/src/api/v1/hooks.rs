@@ -0,0 +1,479 @@
+//! Inbound webhooks that run DSL programs
+//!
+//! Outside tools can trigger a stored program by posting an event to
+//! `POST /api/v1/hooks/{name}`, for example an expense tool creating a
+//! reimbursement proposal when an expense is approved. Each hook holds:
+//!
+//! - the namespace its program runs in
+//! - the program's DSL source, checked when the hook is created
+//! - a mapping from program parameters to JSON pointers into the payload,
+//!   e.g. `amount` to `/expense/amount`; the program reads them with `load`
+//! - a secret the sender signs payloads with
+//!
+//! Deliveries carry an HMAC-SHA256 of the raw body, keyed with the secret
+//! and hex encoded, in the `x-icn-signature` header (optionally prefixed
+//! with `sha256=`). They need no other credentials. The program runs as
+//! `webhook:{name}`, holding only the reader and writer roles in the hook's
+//! namespace.
+//!
+//! Hooks are stored under `webhooks/{name}` in the `system` namespace. They
+//! are created, listed, and removed with `/api/v1/hooks` by admins of their
+//! namespace; the secret is returned once, when the hook is created.
+
+use super::models::ExecutionResult;
+use super::tenant::ScopedVm;
+use super::{error_reply, storage_error_reply};
+use crate::api::auth::system_auth;
+use crate::api::keys::can_manage;
+use crate::compiler::parse_dsl;
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::hyper::body::Bytes;
+use warp::{Filter, Rejection, Reply};
+
+/// Header carrying a delivery's signature
+pub const SIGNATURE_HEADER: &str = "x-icn-signature";
+
+/// Storage namespace holding hook records
+const HOOKS_NAMESPACE: &str = "system";
+
+/// Stored record of an inbound webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookRecord {
+    pub name: String,
+    /// Namespace the program runs in
+    pub namespace: String,
+    /// DSL source of the program run for each delivery
+    pub program: String,
+    /// Program parameters, by name, and the JSON pointers they are read from
+    pub params: BTreeMap<String, String>,
+    /// Key deliveries are signed with
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    secret: String,
+    /// DID of the identity that created the hook
+    pub created_by: String,
+    pub created_at: u64,
+}
+
+impl WebhookRecord {
+    /// Build the restricted `AuthContext` the hook's program runs as
+    pub fn to_auth_context(&self) -> AuthContext {
+        let mut auth = AuthContext::new(&format!("webhook:{}", self.name));
+        auth.add_role(&self.namespace, "reader");
+        auth.add_role(&self.namespace, "writer");
+        auth
+    }
+
+    /// Whether `signature` is the HMAC of `body` under the hook's secret
+    pub fn verify(&self, body: &[u8], signature: &str) -> bool {
+        let signature = signature.trim();
+        let hex_digest = signature.strip_prefix("sha256=").unwrap_or(signature);
+        let Ok(digest) = hex::decode(hex_digest) else {
+            return false;
+        };
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()) else {
+            return false;
+        };
+        mac.update(body);
+        mac.verify_slice(&digest).is_ok()
+    }
+
+    /// Program parameters read from a delivery's payload
+    pub fn map_params(&self, payload: &Value) -> Result<HashMap<String, String>, String> {
+        self.params
+            .iter()
+            .map(|(param, pointer)| {
+                let value = payload
+                    .pointer(pointer)
+                    .filter(|value| !value.is_null())
+                    .ok_or_else(|| format!("Payload has no {} for parameter {}", pointer, param))?;
+                let text = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                Ok((param.clone(), text))
+            })
+            .collect()
+    }
+
+    /// The record without its secret, as listed
+    fn redacted(&self) -> Self {
+        Self {
+            secret: String::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Storage key of a hook record
+pub fn webhook_storage_key(name: &str) -> String {
+    format!("webhooks/{}", name)
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Create a hook, returning its record and the secret deliveries are
+/// signed with
+///
+/// The secret is only returned here; listing hooks leaves it out.
+pub fn create_webhook<S>(
+    storage: &mut S,
+    auth: &AuthContext,
+    request: CreateWebhookRequest,
+) -> StorageResult<(WebhookRecord, String)>
+where
+    S: Storage + StorageExtensions,
+{
+    let key = webhook_storage_key(&request.name);
+    if !can_manage(auth, std::slice::from_ref(&request.namespace)) {
+        return Err(StorageError::PermissionDenied {
+            user_id: auth.identity_did().to_string(),
+            action: "create_webhook".to_string(),
+            key,
+        });
+    }
+    if !valid_name(&request.name) {
+        return Err(StorageError::ValidationError {
+            rule: "webhook_name".to_string(),
+            details: "Hook names may only hold letters, digits, '-' and '_'".to_string(),
+        });
+    }
+    parse_dsl(&request.program).map_err(|e| StorageError::ValidationError {
+        rule: "webhook_program".to_string(),
+        details: format!("Failed to parse program: {}", e),
+    })?;
+    let system = system_auth();
+    if storage.contains(Some(&system), HOOKS_NAMESPACE, &key)? {
+        return Err(StorageError::ValidationError {
+            rule: "webhook_name".to_string(),
+            details: format!("A hook named {} already exists", request.name),
+        });
+    }
+
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let secret = hex::encode(secret);
+    let record = WebhookRecord {
+        name: request.name,
+        namespace: request.namespace,
+        program: request.program,
+        params: request.params,
+        secret: secret.clone(),
+        created_by: auth.identity_did().to_string(),
+        created_at: crate::storage::utils::now_with_default(),
+    };
+    storage.set_json(Some(&system), HOOKS_NAMESPACE, &key, &record)?;
+    Ok((record.redacted(), secret))
+}
+
+/// Remove a hook so its deliveries are refused
+pub fn remove_webhook<S>(storage: &mut S, auth: &AuthContext, name: &str) -> StorageResult<()>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    let key = webhook_storage_key(name);
+    let record: WebhookRecord = storage.get_json(Some(&system), HOOKS_NAMESPACE, &key)?;
+    if !can_manage(auth, std::slice::from_ref(&record.namespace)) {
+        return Err(StorageError::PermissionDenied {
+            user_id: auth.identity_did().to_string(),
+            action: "remove_webhook".to_string(),
+            key,
+        });
+    }
+    storage.delete(Some(&system), HOOKS_NAMESPACE, &key)
+}
+
+/// List the hooks `auth` is allowed to manage, without their secrets
+pub fn list_webhooks<S>(storage: &S, auth: &AuthContext) -> StorageResult<Vec<WebhookRecord>>
+where
+    S: Storage + StorageExtensions,
+{
+    let system = system_auth();
+    let mut records = Vec::new();
+    for key in storage.list_keys(Some(&system), HOOKS_NAMESPACE, Some("webhooks/"))? {
+        let record: WebhookRecord = storage.get_json(Some(&system), HOOKS_NAMESPACE, &key)?;
+        if can_manage(auth, std::slice::from_ref(&record.namespace)) {
+            records.push(record.redacted());
+        }
+    }
+    records.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(records)
+}
+
+/// Body of a hook creation request
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub name: String,
+    pub namespace: String,
+    pub program: String,
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+}
+
+/// Response to a hook creation request
+#[derive(Debug, Serialize)]
+pub struct CreateWebhookResponse {
+    /// The signing secret, shown only once
+    pub secret: String,
+    pub record: WebhookRecord,
+}
+
+/// Routes for managing hooks and receiving deliveries under `/hooks`
+///
+/// Deliveries are authenticated by their signature rather than by `auth`.
+pub fn hook_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_vm = warp::any().map(move || vm.clone());
+
+    let create = warp::path!("hooks")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::body::json::<CreateWebhookRequest>())
+        .and_then(create_handler);
+
+    let list = warp::path!("hooks")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and_then(list_handler);
+
+    let remove = warp::path!("hooks" / String)
+        .and(warp::delete())
+        .and(auth)
+        .and(with_vm.clone())
+        .and_then(remove_handler);
+
+    let deliver = warp::path!("hooks" / String)
+        .and(warp::post())
+        .and(warp::header::optional::<String>(SIGNATURE_HEADER))
+        .and(with_vm)
+        .and(warp::body::bytes())
+        .and_then(deliver_handler);
+
+    create.or(list).or(remove).or(deliver)
+}
+
+async fn create_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    request: CreateWebhookRequest,
+) -> Result<warp::reply::Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(match create_webhook(storage, &auth, request) {
+        Ok((record, secret)) => warp::reply::with_status(
+            warp::reply::json(&CreateWebhookResponse { secret, record }),
+            StatusCode::CREATED,
+        )
+        .into_response(),
+        Err(e) => storage_error_reply(e).into_response(),
+    })
+}
+
+async fn list_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<warp::reply::Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(match list_webhooks(storage, &auth) {
+        Ok(records) => {
+            warp::reply::with_status(warp::reply::json(&records), StatusCode::OK).into_response()
+        }
+        Err(e) => storage_error_reply(e).into_response(),
+    })
+}
+
+async fn remove_handler<S>(
+    name: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<warp::reply::Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let mut vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend_mut() else {
+        return Err(warp::reject::not_found());
+    };
+    Ok(match remove_webhook(storage, &auth, &name) {
+        Ok(()) => warp::reply::with_status(warp::reply(), StatusCode::NO_CONTENT).into_response(),
+        Err(e) => storage_error_reply(e).into_response(),
+    })
+}
+
+/// Check a delivery and run its hook's program
+///
+/// Unknown hooks and bad signatures get the same answer, so senders cannot
+/// probe for hook names.
+async fn deliver_handler<S>(
+    name: String,
+    signature: Option<String>,
+    vm: Arc<Mutex<VM<S>>>,
+    body: Bytes,
+) -> Result<warp::reply::Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let record = {
+        let vm_lock = vm.lock().await;
+        vm_lock.get_storage_backend().and_then(|storage| {
+            storage
+                .get_json::<WebhookRecord>(
+                    Some(&system_auth()),
+                    HOOKS_NAMESPACE,
+                    &webhook_storage_key(&name),
+                )
+                .ok()
+        })
+    };
+    let Some(record) =
+        record.filter(|record| signature.is_some_and(|sig| record.verify(&body, &sig)))
+    else {
+        return Ok(error_reply(
+            "Unknown hook or invalid signature",
+            StatusCode::UNAUTHORIZED,
+        )
+        .into_response());
+    };
+
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return Ok(error_reply(
+                format!("Payload is not JSON: {}", e),
+                StatusCode::BAD_REQUEST,
+            )
+            .into_response())
+        }
+    };
+    let params = match record.map_params(&payload) {
+        Ok(params) => params,
+        Err(message) => {
+            return Ok(error_reply(message, StatusCode::UNPROCESSABLE_ENTITY).into_response())
+        }
+    };
+    let ops = match parse_dsl(&record.program) {
+        Ok((ops, _)) => ops,
+        Err(e) => {
+            return Ok(error_reply(
+                format!("Hook program no longer parses: {}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+            .into_response())
+        }
+    };
+
+    let mut guard = ScopedVm::new(vm, Some(record.namespace.clone()))
+        .lock()
+        .await;
+    let auth = record.to_auth_context();
+    let result = tokio::task::spawn_blocking(move || {
+        guard.with_auth_context(auth, |vm| {
+            vm.set_parameters(params)?;
+            vm.execute(&ops)
+        })
+    })
+    .await
+    .map_err(|e| format!("Execution aborted: {}", e))
+    .and_then(|result| result.map_err(|e| e.to_string()));
+    if let Err(e) = &result {
+        tracing::warn!(hook = %record.name, error = %e, "Webhook program failed");
+    }
+
+    let status = if result.is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ExecutionResult {
+            success: result.is_ok(),
+            error: result.err(),
+        }),
+        status,
+    )
+    .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deliveries_are_signed_and_mapped() {
+        let record = WebhookRecord {
+            name: "expenses".to_string(),
+            namespace: "coops/alpha".to_string(),
+            program: "load amount\nemit \"reimburse\"".to_string(),
+            params: BTreeMap::from([
+                ("amount".to_string(), "/expense/amount".to_string()),
+                ("member".to_string(), "/expense/submitter".to_string()),
+            ]),
+            secret: "s3cret".to_string(),
+            created_by: "did:key:alice".to_string(),
+            created_at: 0,
+        };
+        let body = br#"{"expense":{"amount":42.5,"submitter":"did:key:bob"}}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(record.verify(body, &signature));
+        assert!(record.verify(body, &format!("sha256={}", signature)));
+        assert!(!record.verify(b"{}", &signature));
+        assert!(!record.verify(body, "sha256=zz"));
+
+        let payload: Value = serde_json::from_slice(body).unwrap();
+        let params = record.map_params(&payload).unwrap();
+        assert_eq!(params["amount"], "42.5");
+        assert_eq!(params["member"], "did:key:bob");
+        assert!(record
+            .map_params(&json!({ "expense": { "amount": 1 } }))
+            .is_err());
+
+        let auth = record.to_auth_context();
+        assert_eq!(auth.identity_did(), "webhook:expenses");
+        assert!(auth.has_role("coops/alpha", "writer"));
+        assert!(!auth.has_role("default", "writer"));
+        assert!(serde_json::to_value(record.redacted())
+            .unwrap()
+            .get("secret")
+            .is_none());
+    }
+}
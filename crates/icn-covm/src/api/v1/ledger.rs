@@ -0,0 +1,228 @@
+//! Read-only DAG ledger endpoints under `/api/v1/ledger`
+//!
+//! - `GET /ledger/nodes/{id}` fetches a single node
+//! - `GET /ledger/proposals/{id}/trace` returns the nodes recording a
+//!   proposal together with every ancestor they depend on
+//! - `POST /ledger/diff` compares an uploaded JSONL ledger with this one
+//! - `GET /ledger/export?namespace=` downloads a namespace as JSONL
+//!
+//! Nodes are returned as stored, so sealed payloads stay sealed. Callers
+//! only see nodes from namespaces they can read: those where they hold the
+//! reader, writer, or admin role, or everything for global admins.
+
+use super::error_reply;
+use super::models::{LedgerDiffResponse, LedgerExportQuery, ProposalTrace};
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use icn_ledger::{DagLedger, DagNode};
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::{header, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Largest ledger accepted by the diff endpoint
+const MAX_UPLOAD_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Whether `auth` may read ledger nodes of `namespace`
+pub(crate) fn can_read(auth: &AuthContext, namespace: &str) -> bool {
+    auth.has_role("global", "admin")
+        || ["reader", "writer", "admin"]
+            .iter()
+            .any(|role| auth.has_role(namespace, role))
+}
+
+/// Routes for ledger inspection
+pub fn ledger_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_vm = warp::any().map(move || vm.clone());
+
+    let node = warp::path!("ledger" / "nodes" / String)
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and_then(node_handler);
+
+    let trace = warp::path!("ledger" / "proposals" / String / "trace")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and_then(trace_handler);
+
+    let diff = warp::path!("ledger" / "diff")
+        .and(warp::post())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(warp::body::content_length_limit(MAX_UPLOAD_BYTES))
+        .and(warp::body::bytes())
+        .and_then(diff_handler);
+
+    let export = warp::path!("ledger" / "export")
+        .and(warp::get())
+        .and(auth)
+        .and(with_vm)
+        .and(warp::query::<LedgerExportQuery>())
+        .and_then(export_handler);
+
+    node.or(trace).or(diff).or(export)
+}
+
+async fn node_handler<S>(
+    id: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let vm_lock = vm.lock().await;
+    let Some(ledger) = vm_lock.get_dag() else {
+        return Ok(error_reply("No DAG ledger configured", StatusCode::NOT_FOUND).into_response());
+    };
+    // Unreadable nodes are reported as missing so their existence is not leaked
+    Ok(match ledger.find_by_id(&id) {
+        Some(node) if can_read(&auth, &node.namespace) => warp::reply::json(node).into_response(),
+        _ => error_reply(format!("Node {} not found", id), StatusCode::NOT_FOUND).into_response(),
+    })
+}
+
+async fn trace_handler<S>(
+    proposal_id: String,
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+) -> Result<Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let vm_lock = vm.lock().await;
+    let Some(ledger) = vm_lock.get_dag() else {
+        return Ok(error_reply("No DAG ledger configured", StatusCode::NOT_FOUND).into_response());
+    };
+
+    let start_ids: Vec<String> = ledger
+        .find_proposal_related_nodes(&proposal_id)
+        .into_iter()
+        .filter(|node| can_read(&auth, &node.namespace))
+        .map(|node| node.id)
+        .collect();
+    if start_ids.is_empty() {
+        return Ok(error_reply(
+            format!("No ledger nodes for proposal {}", proposal_id),
+            StatusCode::NOT_FOUND,
+        )
+        .into_response());
+    }
+
+    let mut nodes: Vec<DagNode> = ledger
+        .export_selected(&start_ids)
+        .into_iter()
+        .filter(|node| can_read(&auth, &node.namespace))
+        .collect();
+    nodes.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.id.cmp(&b.id)));
+
+    Ok(warp::reply::json(&ProposalTrace { proposal_id, nodes }).into_response())
+}
+
+async fn diff_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    body: warp::hyper::body::Bytes,
+) -> Result<Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let text = match std::str::from_utf8(&body) {
+        Ok(text) => text,
+        Err(_) => {
+            return Ok(
+                error_reply("Ledger must be UTF-8 JSONL", StatusCode::BAD_REQUEST).into_response(),
+            )
+        }
+    };
+    let uploaded = match DagLedger::from_jsonl(text) {
+        Ok(ledger) => ledger,
+        Err(e) => return Ok(error_reply(e, StatusCode::BAD_REQUEST).into_response()),
+    };
+
+    let vm_lock = vm.lock().await;
+    let Some(ledger) = vm_lock.get_dag() else {
+        return Ok(error_reply("No DAG ledger configured", StatusCode::NOT_FOUND).into_response());
+    };
+    let diff = ledger.diff_with(&uploaded);
+
+    let readable = |nodes: Vec<DagNode>| -> Vec<DagNode> {
+        nodes
+            .into_iter()
+            .filter(|node| can_read(&auth, &node.namespace))
+            .collect()
+    };
+    Ok(warp::reply::json(&LedgerDiffResponse {
+        only_local: readable(diff.added),
+        only_uploaded: readable(diff.removed),
+        common: diff.common.len(),
+    })
+    .into_response())
+}
+
+async fn export_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    query: LedgerExportQuery,
+) -> Result<Response, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if !can_read(&auth, &query.namespace) {
+        return Ok(error_reply(
+            format!("Cannot read namespace {}", query.namespace),
+            StatusCode::FORBIDDEN,
+        )
+        .into_response());
+    }
+
+    let vm_lock = vm.lock().await;
+    let Some(ledger) = vm_lock.get_dag() else {
+        return Ok(error_reply("No DAG ledger configured", StatusCode::NOT_FOUND).into_response());
+    };
+    let nodes: Vec<DagNode> = ledger
+        .nodes_by_namespace(&query.namespace)
+        .into_iter()
+        .cloned()
+        .collect();
+    let body = match DagLedger::to_jsonl(&nodes) {
+        Ok(body) => body,
+        Err(e) => return Ok(error_reply(e, StatusCode::INTERNAL_SERVER_ERROR).into_response()),
+    };
+
+    let filename = format!("{}.jsonl", query.namespace.replace('/', "_"));
+    let reply = warp::reply::with_header(body, header::CONTENT_TYPE, "application/x-ndjson");
+    Ok(warp::reply::with_header(
+        reply,
+        header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{}\"", filename),
+    )
+    .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_can_read_requires_namespace_role() {
+        let mut auth = AuthContext::new("did:key:auditor");
+        auth.add_role("coops/alpha", "reader");
+        assert!(can_read(&auth, "coops/alpha"));
+        assert!(!can_read(&auth, "coops/beta"));
+
+        auth.add_role("global", "admin");
+        assert!(can_read(&auth, "coops/beta"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Version 1 of the HTTP API, served under `/api/v1`
+
+pub mod admin;
+pub mod attachments;
+pub mod audit;
+pub mod comments;
+pub mod dsl;
+pub mod events;
+pub mod executions;
+pub mod export;
+pub mod federation;
+pub mod hooks;
+pub mod ledger;
+pub mod models;
+pub mod openapi;
+pub mod proposals;
+pub mod replication;
+pub mod resources;
+pub mod tenant;
+pub mod ws;
+
+use crate::api::auth::{self, JwtConfig};
+use crate::api::idempotency::{self, IdempotencyCache};
+use crate::api::keys;
+use crate::api::oidc::{self, OidcProvider};
+use crate::api::rate_limit::RateLimiter;
+use crate::federation::NodeHandle;
+use crate::storage::errors::StorageError;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use events::EventHub;
+use models::ErrorResponse;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::reply::{Json, WithStatus};
+use warp::{Filter, Rejection, Reply};
+
+/// A JSON body with its status code, as the API handlers reply
+pub(crate) type JsonReply = WithStatus<Json>;
+
+/// All v1 routes, mounted under `/api/v1`
+///
+/// Every route except token issuance, OIDC login and binding requests, the
+/// OpenAPI document, and webhook deliveries requires a bearer token or API
+/// key; deliveries are signed instead (see `hooks`). All routes are limited
+/// per remote IP, and authenticated routes per identity as well. Proposal,
+/// comment, attachment, execution, resource, and export routes are also
+/// served under `/api/v1/coops/{coop}`, scoped to that cooperative's
+/// namespace; see `tenant`. Federation routes manage `node` when the server
+/// runs alongside a federation node. Replication routes serve the storage
+/// change feed followed by read replicas. OpenID Connect login is served
+/// when `oidc` names a provider; see `api::oidc`. `POST` and `PUT` requests
+/// with an `Idempotency-Key` are applied at most once; see `api::idempotency`.
+pub fn routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    hub: EventHub,
+    jwt: JwtConfig,
+    limiter: RateLimiter<S>,
+    node: Option<NodeHandle>,
+    oidc: Option<OidcProvider>,
+    idempotency: IdempotencyCache<S>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_auth = {
+        let (jwt, vm, limiter) = (jwt.clone(), vm.clone(), limiter.clone());
+        move || {
+            tenant::with_tenant_access(
+                limiter.per_identity(auth::with_auth(jwt.clone(), vm.clone())),
+            )
+        }
+    };
+
+    let jobs = executions::JobRegistry::default();
+    let change_log = replication::SharedChangeLog::default();
+
+    // Routes acting within a namespace, mounted again for each cooperative
+    let scoped_routes = {
+        let (vm, hub, with_auth) = (vm.clone(), hub.clone(), with_auth.clone());
+        move || {
+            executions::execution_routes(vm.clone(), jobs.clone(), with_auth())
+                .or(proposals::proposals_route(vm.clone(), with_auth()))
+                .or(comments::comment_routes(
+                    vm.clone(),
+                    hub.clone(),
+                    with_auth(),
+                ))
+                .or(attachments::attachment_routes(vm.clone(), with_auth()))
+                .or(resources::resource_routes(vm.clone(), with_auth()))
+                .or(export::export_route(vm.clone(), with_auth()))
+        }
+    };
+    let coop_routes = warp::path("coops")
+        .and(
+            warp::path::param::<String>()
+                .map(|_: String| ())
+                .untuple_one(),
+        )
+        .and(scoped_routes());
+
+    let api = || warp::path("api").and(warp::path("v1"));
+    let routes = openapi::openapi_route()
+        .or(auth::token_route(vm.clone(), jwt.clone()))
+        .or(oidc::oidc_routes(
+            vm.clone(),
+            jwt.clone(),
+            oidc,
+            with_auth(),
+        ))
+        .or(keys::api_key_routes(vm.clone(), with_auth()))
+        .or(scoped_routes())
+        .or(coop_routes)
+        .or(dsl::dsl_routes(with_auth()))
+        .or(ledger::ledger_routes(vm.clone(), with_auth()))
+        .or(admin::admin_routes(vm.clone(), with_auth()))
+        .or(audit::audit_routes(vm.clone(), with_auth()))
+        .or(federation::federation_routes(vm.clone(), node, with_auth()))
+        .or(hooks::hook_routes(vm.clone(), with_auth()))
+        .or(replication::replication_routes(
+            vm.clone(),
+            change_log,
+            with_auth(),
+        ))
+        .or(ws::events_ws_route(hub.clone(), jwt.clone(), vm.clone()))
+        .or(ws::ws_route(hub, jwt.clone(), vm));
+
+    api()
+        .and(limiter.per_ip())
+        .and(idempotency::idempotent(api(), routes, idempotency, jwt))
+}
+
+/// An `ErrorResponse` carrying `message`
+pub(crate) fn error_reply(message: impl Into<String>, status: StatusCode) -> JsonReply {
+    warp::reply::with_status(
+        warp::reply::json(&ErrorResponse {
+            message: message.into(),
+        }),
+        status,
+    )
+}
+
+/// An `ErrorResponse` for a storage error, with the status it maps to
+pub(crate) fn storage_error_reply(err: StorageError) -> JsonReply {
+    let status = match err {
+        StorageError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        StorageError::NotFound { .. } => StatusCode::NOT_FOUND,
+        StorageError::ConflictError { .. } => StatusCode::CONFLICT,
+        StorageError::QuotaExceeded { .. } | StorageError::ValidationError { .. } => {
+            StatusCode::BAD_REQUEST
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    error_reply(err.to_string(), status)
+}
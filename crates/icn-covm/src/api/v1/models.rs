@@ -0,0 +1,386 @@
+//! Request and response models of the HTTP API
+//!
+//! Models owned by other API modules are re-exported here so clients and the
+//! OpenAPI document have a single place to find every shape on the wire.
+
+use icn_ledger::DagNode;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub use super::events::ApiEvent;
+pub use crate::api::auth::{TokenRequest, TokenResponse};
+pub use crate::api::keys::{ApiKeyRecord, Capability, CreateApiKeyRequest, CreateApiKeyResponse};
+pub use crate::api::oidc::{OidcBinding, OidcBindingRequest, OidcTokenRequest};
+pub use crate::audit::{AuditEntry, AuditOutcome, AuditQuery, AuditSource};
+pub use crate::compiler::{Diagnostic, Severity};
+pub use crate::federation::messages::{FederatedProposal, ProposalScope, VotingModel};
+pub use crate::federation::PeerStatus;
+pub use crate::governance::attachments::Attachment;
+pub use crate::storage::auth::RoleAssignment;
+pub use crate::storage::events::StorageEvent;
+pub use crate::storage::namespaces::NamespaceMetadata;
+pub use crate::storage::resource::CreditLine;
+pub use crate::typed::TypedValue;
+pub use crate::vm::types::VMEvent;
+
+/// Represents a proposal with all of its metadata for API responses
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProposalResponse {
+    pub id: String,
+    pub title: String,
+    pub creator: String,
+    pub status: String,
+    pub created_at: String,
+    pub votes: VoteCounts,
+    pub quorum_percentage: f64,
+    pub threshold_percentage: f64,
+    pub execution_result: Option<String>,
+}
+
+/// Vote count information
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VoteCounts {
+    pub yes: u32,
+    pub no: u32,
+    pub abstain: u32,
+    pub total: u32,
+}
+
+/// Comment metadata for API responses
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommentResponse {
+    pub id: String,
+    pub author: String,
+    pub timestamp: String,
+    pub content: String,
+    pub reply_to: Option<String>,
+    pub tags: Vec<String>,
+    pub reactions: HashMap<String, u32>,
+    pub hidden: bool,
+    pub edit_count: usize,
+}
+
+/// Comment version history for API
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommentVersionResponse {
+    pub content: String,
+    pub timestamp: String,
+}
+
+/// Proposal summary for API responses
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProposalSummary {
+    pub id: String,
+    pub title: String,
+    pub status: String,
+    pub comment_count: usize,
+    pub vote_count: u32,
+    pub vote_details: VoteCounts,
+    pub top_participants: Vec<Participant>,
+    pub last_activity: String,
+}
+
+/// Participant information for summaries
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Participant {
+    pub id: String,
+    pub comment_count: u32,
+}
+
+/// API error response, returned with every non-2xx status
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub message: String,
+}
+
+/// Query parameters for filtering hidden comments
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShowHiddenQuery {
+    pub show_hidden: Option<bool>,
+}
+
+/// Body of an execution request; exactly one of the fields must be set
+#[derive(Debug, Deserialize)]
+pub struct ExecuteProgramRequest {
+    /// DSL source of the program
+    pub program: Option<String>,
+    /// Proposal whose logic to execute
+    pub proposal_id: Option<String>,
+    /// Sandbox profile the program runs under, `deliberation-preview` if
+    /// unset; only for programs
+    #[serde(default)]
+    pub sandbox: Option<String>,
+}
+
+/// Outcome of a streamed execution, sent as the final `finished` event
+#[derive(Debug, Serialize)]
+pub struct ExecutionResult {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Progress of an execution job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// An execution run in the background, polled via `GET /executions/{id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionJob {
+    pub id: String,
+    pub status: JobStatus,
+    /// Proposal executed, if the job runs a proposal rather than a program
+    pub proposal_id: Option<String>,
+    /// Namespace the job runs in
+    pub namespace: String,
+    /// DID of the caller who submitted the job
+    pub submitted_by: String,
+    pub submitted_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// `Emit` output and `EmitEvent` events, in order
+    pub output: Vec<VMEvent>,
+    /// Stack left when the job finished, bottom first
+    pub stack: Vec<TypedValue>,
+    pub error: Option<String>,
+}
+
+/// Query parameters of the proposal list
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProposalListQuery {
+    /// Namespace to list, defaulting to the server's namespace
+    pub namespace: Option<String>,
+    /// Only proposals in this status (case-insensitive)
+    pub status: Option<String>,
+    /// Only proposals created by this identity
+    pub creator: Option<String>,
+    /// Only proposals carrying this label
+    pub label: Option<String>,
+    /// One-based page number, ignored when `offset` or `cursor` is set
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    /// Page size, taking precedence over `per_page`
+    pub limit: Option<usize>,
+    /// Number of matching proposals to skip, ignored when `cursor` is set
+    pub offset: Option<usize>,
+    /// Sort field, prefixed with `-` for descending order
+    pub sort: Option<String>,
+    /// ID of the last proposal of the previous page
+    pub cursor: Option<String>,
+}
+
+/// A proposal in a list response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProposalListItem {
+    pub id: String,
+    pub title: String,
+    pub creator: String,
+    pub status: String,
+    pub labels: Vec<String>,
+    pub created_at: String,
+}
+
+/// One page of the proposal list
+#[derive(Debug, Serialize)]
+pub struct ProposalPage {
+    /// Number of proposals matching the filters, across all pages
+    pub total: usize,
+    pub page: usize,
+    pub per_page: usize,
+    /// Number of matching proposals before this page
+    pub offset: usize,
+    /// Cursor for the next page, absent on the last page
+    pub next_cursor: Option<String>,
+    pub proposals: Vec<ProposalListItem>,
+}
+
+/// Query parameters of the comment list
+#[derive(Debug, Default, Deserialize)]
+pub struct CommentListQuery {
+    /// Include hidden comments; requires a moderator role
+    pub show_hidden: Option<bool>,
+    /// Only comments with this tag, plus the comments they reply to
+    pub tag: Option<String>,
+}
+
+/// A comment with its replies
+#[derive(Debug, Serialize)]
+pub struct CommentThread {
+    #[serde(flatten)]
+    pub comment: CommentResponse,
+    pub replies: Vec<CommentThread>,
+}
+
+/// Body of a comment creation request
+#[derive(Debug, Deserialize)]
+pub struct CreateCommentRequest {
+    pub content: String,
+    /// ID of the comment being replied to
+    pub reply_to: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Body of a comment edit request
+#[derive(Debug, Deserialize)]
+pub struct EditCommentRequest {
+    pub content: String,
+}
+
+/// Body of a reaction request
+#[derive(Debug, Deserialize)]
+pub struct ReactionRequest {
+    /// Reaction to add, e.g. an emoji
+    pub reaction: String,
+}
+
+/// A proposal's ledger nodes and their ancestors, oldest first
+#[derive(Debug, Serialize)]
+pub struct ProposalTrace {
+    pub proposal_id: String,
+    pub nodes: Vec<DagNode>,
+}
+
+/// Difference between this server's ledger and an uploaded one
+#[derive(Debug, Serialize)]
+pub struct LedgerDiffResponse {
+    /// Nodes only present on this server
+    pub only_local: Vec<DagNode>,
+    /// Nodes only present in the upload
+    pub only_uploaded: Vec<DagNode>,
+    /// Number of nodes present in both
+    pub common: usize,
+}
+
+/// Query parameters of a governance table export
+#[derive(Debug, Deserialize)]
+pub struct TableExportQuery {
+    /// `csv` or `parquet`; CSV when not given
+    pub format: Option<String>,
+    /// Namespace to export, ignored for requests scoped to a cooperative
+    pub namespace: Option<String>,
+}
+
+/// Query parameters of the namespace export
+#[derive(Debug, Deserialize)]
+pub struct LedgerExportQuery {
+    pub namespace: String,
+}
+
+/// Body of a namespace creation request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateNamespaceRequest {
+    /// Full path of the namespace, e.g. `coops/alpha`
+    pub namespace: String,
+    pub quota_bytes: u64,
+    pub parent: Option<String>,
+}
+
+/// Query parameters of the namespace listing
+#[derive(Debug, Deserialize)]
+pub struct NamespaceListQuery {
+    /// Only namespaces under this one
+    #[serde(default)]
+    pub parent: String,
+}
+
+/// Body of an account creation request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAccountRequest {
+    /// DID of the identity the account belongs to
+    pub user_id: String,
+    pub quota_bytes: u64,
+}
+
+/// A role in a namespace, used to grant or revoke it
+#[derive(Debug, Deserialize)]
+pub struct RoleRequest {
+    pub namespace: String,
+    pub role: String,
+}
+
+/// Query parameters of the audit log
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub namespace: Option<String>,
+    /// e.g. `write`, `delete`, or `permission_change`
+    pub event_type: Option<String>,
+    /// Most entries to return; defaults to 100
+    pub limit: Option<usize>,
+}
+
+/// Query parameters of the replication change feed
+#[derive(Debug, Deserialize)]
+pub struct ReplicationChangesQuery {
+    /// Epoch of the replica's last snapshot
+    pub epoch: String,
+    /// Sequence number of the last change the replica applied
+    pub since: u64,
+    /// Most changes to return; defaults to 1000
+    pub limit: Option<usize>,
+}
+
+/// Body of a DSL validation request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidateDslRequest {
+    /// DSL source to check
+    pub source: String,
+}
+
+/// Outcome of validating DSL source
+#[derive(Debug, Serialize)]
+pub struct ValidateDslResponse {
+    /// Whether the source compiles; warnings do not make it invalid
+    pub valid: bool,
+    /// Errors and warnings, in line order
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// State of the federation node
+#[derive(Debug, Serialize)]
+pub struct FederationStatus {
+    pub local_peer_id: String,
+    /// Multiaddresses the node dials on start, including any added since
+    pub bootstrap_nodes: Vec<String>,
+    pub peers: Vec<PeerStatus>,
+}
+
+/// Body of a request adding a bootstrap node
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddBootstrapNodeRequest {
+    /// Multiaddress of the node, e.g. `/ip4/10.0.0.2/tcp/4001`
+    pub address: String,
+}
+
+/// Body of a request broadcasting a local proposal to the federation
+#[derive(Debug, Deserialize)]
+pub struct BroadcastProposalRequest {
+    pub proposal_id: String,
+    /// Who may vote; defaults to the whole federation
+    pub scope: Option<ProposalScope>,
+    /// How votes are counted; defaults to one member, one vote
+    pub voting_model: Option<VotingModel>,
+    /// Seconds until voting closes
+    pub expires_in: Option<u64>,
+}
+
+/// An account's standing in a resource
+#[derive(Debug, Serialize)]
+pub struct AccountStanding {
+    pub resource: String,
+    pub account: String,
+    /// Balance, negative while the account is drawing on credit
+    pub balance: Decimal,
+    /// How far below zero the account may go
+    pub credit_limit: Decimal,
+    /// What the account can still spend: its balance plus its credit limit
+    pub available: Decimal,
+    /// The credit lines making up the limit
+    pub credit_lines: Vec<CreditLine>,
+}
@@ -0,0 +1,1547 @@
+//! OpenAPI description of the HTTP API, served at `/api/v1/openapi.json`
+//!
+//! The document is built by hand from the route definitions, so any route or
+//! model added to the API must be described here too. Schemas mirror the
+//! types in `api::v1::models`.
+
+use serde_json::{json, Value};
+use warp::{Filter, Rejection, Reply};
+
+/// Version of the OpenAPI specification the document follows
+const OPENAPI_VERSION: &str = "3.0.3";
+
+/// Reference to a schema in `components/schemas`
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+/// JSON response with the given description and schema
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } }
+    })
+}
+
+/// Standard error responses shared by authenticated routes
+fn error_responses() -> Value {
+    json!({
+        "400": json_response("Malformed request", schema_ref("ErrorResponse")),
+        "401": json_response("Missing or invalid credentials", schema_ref("ErrorResponse")),
+        "403": json_response("Caller lacks the required role", schema_ref("ErrorResponse")),
+        "404": json_response("Resource not found", schema_ref("ErrorResponse")),
+        "429": {
+            "description": "Rate limit exceeded",
+            "headers": {
+                "Retry-After": {
+                    "description": "Seconds until the next request is allowed",
+                    "schema": { "type": "integer" }
+                }
+            },
+            "content": { "application/json": { "schema": schema_ref("ErrorResponse") } }
+        }
+    })
+}
+
+/// Merge the standard error responses into an operation's responses
+fn with_errors(mut responses: Value) -> Value {
+    if let (Some(map), Value::Object(errors)) = (responses.as_object_mut(), error_responses()) {
+        for (status, response) in errors {
+            map.entry(status).or_insert(response);
+        }
+    }
+    responses
+}
+
+/// Path parameter definition
+fn path_param(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "description": description,
+        "schema": { "type": "string" }
+    })
+}
+
+/// Add the entries of `extra` to the object `target`
+///
+/// Large sections are built in separate `json!` calls to stay under the
+/// macro's recursion limit, then merged.
+fn merge(target: &mut Value, extra: Value) {
+    if let (Some(map), Value::Object(extra)) = (target.as_object_mut(), extra) {
+        map.extend(extra);
+    }
+}
+
+fn paths() -> Value {
+    let mut paths = json!({
+        "/api/v1/openapi.json": {
+            "get": {
+                "summary": "This document",
+                "security": [],
+                "responses": {
+                    "200": { "description": "OpenAPI document", "content": { "application/json": {} } }
+                }
+            }
+        },
+        "/api/v1/auth/token": {
+            "post": {
+                "summary": "Exchange a signed login challenge for a bearer token",
+                "description": "Sign `icn-login|<did>|<timestamp>` with the identity's private key. Each timestamp must be later than the one of the identity's previous login.",
+                "security": [],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("TokenRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "200": json_response("Token issued", schema_ref("TokenResponse"))
+                }))
+            }
+        },
+        "/api/v1/api-keys": {
+            "get": {
+                "summary": "List API keys the caller administers",
+                "responses": with_errors(json!({
+                    "200": json_response(
+                        "Key records",
+                        json!({ "type": "array", "items": schema_ref("ApiKeyRecord") })
+                    )
+                }))
+            },
+            "post": {
+                "summary": "Create a scoped API key",
+                "description": "Requires the admin role in every requested namespace.",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("CreateApiKeyRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "201": json_response("Key created; the plaintext key is shown only once", schema_ref("CreateApiKeyResponse"))
+                }))
+            }
+        },
+        "/api/v1/api-keys/{id}": {
+            "delete": {
+                "summary": "Revoke an API key",
+                "parameters": [path_param("id", "Key ID")],
+                "responses": with_errors(json!({
+                    "200": json_response(
+                        "Key revoked",
+                        json!({ "type": "object", "properties": { "revoked": { "type": "string" } } })
+                    )
+                }))
+            }
+        },
+        "/api/v1/ws": {
+            "get": {
+                "summary": "WebSocket stream of proposal and VM events",
+                "description": "Send `{\"action\": \"subscribe\", \"proposal_ids\": [...]}` to follow proposals; `\"*\"` follows everything. Events are pushed as `ApiEvent` JSON frames.",
+                "parameters": [{
+                    "name": "access_token",
+                    "in": "query",
+                    "required": false,
+                    "description": "Bearer token, for clients that cannot set headers",
+                    "schema": { "type": "string" }
+                }],
+                "responses": with_errors(json!({
+                    "101": json_response("Switching protocols", schema_ref("ApiEvent"))
+                }))
+            }
+        },
+        "/api/v1/events/ws": {
+            "get": {
+                "summary": "WebSocket stream of VM and proposal lifecycle events by namespace",
+                "description": "Send `{\"action\": \"subscribe\", \"namespaces\": [...]}` to follow namespaces the caller can read; `\"*\"` follows every readable namespace. `vm_event`, `proposal_created`, `vote_cast` and `proposal_executed` events are pushed as `ApiEvent` JSON frames.",
+                "parameters": [{
+                    "name": "access_token",
+                    "in": "query",
+                    "required": false,
+                    "description": "Bearer token, for clients that cannot set headers",
+                    "schema": { "type": "string" }
+                }],
+                "responses": with_errors(json!({
+                    "101": json_response("Switching protocols", schema_ref("ApiEvent"))
+                }))
+            }
+        },
+        "/api/v1/executions": {
+            "post": {
+                "summary": "Run a DSL program or a proposal's logic",
+                "description": "With `Accept: text/event-stream`, responds with server-sent `output` and `event` events carrying `VMEvent`s, then a `finished` event carrying an `ExecutionResult`. Otherwise queues the run as a job and responds with its record; poll `/api/v1/executions/{id}` for the outcome.",
+                "parameters": [
+                    { "name": "Accept", "in": "header", "required": false, "description": "`text/event-stream` to stream the run instead of queueing it", "schema": { "type": "string" } }
+                ],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("ExecuteProgramRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "200": { "description": "Event stream", "content": { "text/event-stream": {} } },
+                    "202": json_response("Job queued", schema_ref("ExecutionJob"))
+                }))
+            }
+        },
+        "/api/v1/executions/{id}": {
+            "get": {
+                "summary": "Get the status, output, and final stack of an execution job",
+                "parameters": [path_param("id", "Job ID")],
+                "responses": with_errors(json!({
+                    "200": json_response("Job record", schema_ref("ExecutionJob"))
+                }))
+            }
+        },
+        "/api/v1/proposals": {
+            "get": {
+                "summary": "List proposals with filtering, sorting, and pagination",
+                "parameters": [
+                    { "name": "namespace", "in": "query", "required": false, "description": "Namespace to list", "schema": { "type": "string" } },
+                    { "name": "status", "in": "query", "required": false, "description": "Status filter, case-insensitive", "schema": { "type": "string" } },
+                    { "name": "creator", "in": "query", "required": false, "description": "Creator DID filter", "schema": { "type": "string" } },
+                    { "name": "label", "in": "query", "required": false, "description": "Label filter", "schema": { "type": "string" } },
+                    { "name": "page", "in": "query", "required": false, "description": "One-based page number", "schema": { "type": "integer" } },
+                    { "name": "per_page", "in": "query", "required": false, "description": "Page size, at most 100", "schema": { "type": "integer" } },
+                    { "name": "limit", "in": "query", "required": false, "description": "Page size, at most 100; overrides per_page", "schema": { "type": "integer" } },
+                    { "name": "offset", "in": "query", "required": false, "description": "Number of matching proposals to skip; overrides page", "schema": { "type": "integer" } },
+                    { "name": "sort", "in": "query", "required": false, "description": "created_at, title, status, creator, or id; prefix with - for descending", "schema": { "type": "string" } },
+                    { "name": "cursor", "in": "query", "required": false, "description": "next_cursor of the previous page", "schema": { "type": "string" } }
+                ],
+                "responses": with_errors(json!({
+                    "200": json_response("Page of proposals", schema_ref("ProposalPage"))
+                }))
+            }
+        },
+        "/api/v1/proposals/{id}/comments": {
+            "get": {
+                "summary": "List a proposal's comments as reply threads",
+                "parameters": [
+                    path_param("id", "Proposal ID"),
+                    { "name": "show_hidden", "in": "query", "required": false, "description": "Include hidden comments; moderators only", "schema": { "type": "boolean" } },
+                    { "name": "tag", "in": "query", "required": false, "description": "Only comments with this tag and their parents", "schema": { "type": "string" } }
+                ],
+                "responses": with_errors(json!({
+                    "200": json_response(
+                        "Comment threads",
+                        json!({ "type": "array", "items": schema_ref("CommentThread") })
+                    )
+                }))
+            },
+            "post": {
+                "summary": "Comment on a proposal or reply to a comment",
+                "parameters": [path_param("id", "Proposal ID")],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("CreateCommentRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "201": json_response("Comment created", schema_ref("CommentResponse"))
+                }))
+            }
+        },
+        "/api/v1/proposals/{id}/comments/{comment_id}": {
+            "put": {
+                "summary": "Edit a comment; authors only",
+                "parameters": [path_param("id", "Proposal ID"), path_param("comment_id", "Comment ID")],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("EditCommentRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "200": json_response("Edited comment", schema_ref("CommentResponse"))
+                }))
+            }
+        },
+        "/api/v1/proposals/{id}/comments/{comment_id}/hide": {
+            "post": {
+                "summary": "Hide a comment; authors and moderators only",
+                "parameters": [path_param("id", "Proposal ID"), path_param("comment_id", "Comment ID")],
+                "responses": with_errors(json!({
+                    "200": json_response("Hidden comment", schema_ref("CommentResponse"))
+                }))
+            }
+        },
+        "/api/v1/proposals/{id}/comments/{comment_id}/reactions": {
+            "post": {
+                "summary": "React to a comment",
+                "parameters": [path_param("id", "Proposal ID"), path_param("comment_id", "Comment ID")],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("ReactionRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "200": json_response("Updated comment", schema_ref("CommentResponse"))
+                }))
+            }
+        },
+        "/api/v1/proposals/{id}/execute": {
+            "post": {
+                "summary": "Execute a proposal and stream its output",
+                "description": "Streams the same events as `/api/v1/executions`.",
+                "parameters": [path_param("id", "Proposal ID")],
+                "responses": with_errors(json!({
+                    "200": { "description": "Event stream", "content": { "text/event-stream": {} } }
+                }))
+            }
+        },
+        "/api/v1/ledger/nodes/{id}": {
+            "get": {
+                "summary": "Fetch a ledger node",
+                "parameters": [path_param("id", "Node ID")],
+                "responses": with_errors(json!({
+                    "200": json_response("Node, sealed if its namespace is encrypted", schema_ref("DagNode"))
+                }))
+            }
+        },
+        "/api/v1/ledger/proposals/{id}/trace": {
+            "get": {
+                "summary": "Trace the ledger subgraph of a proposal",
+                "parameters": [path_param("id", "Proposal ID")],
+                "responses": with_errors(json!({
+                    "200": json_response("Proposal nodes and their ancestors", schema_ref("ProposalTrace"))
+                }))
+            }
+        },
+        "/api/v1/ledger/diff": {
+            "post": {
+                "summary": "Compare an uploaded JSONL ledger with this server's",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/x-ndjson": { "schema": { "type": "string" } } }
+                },
+                "responses": with_errors(json!({
+                    "200": json_response("Differences", schema_ref("LedgerDiffResponse"))
+                }))
+            }
+        },
+        "/api/v1/ledger/export": {
+            "get": {
+                "summary": "Download a namespace's ledger nodes as JSONL",
+                "parameters": [{
+                    "name": "namespace",
+                    "in": "query",
+                    "required": true,
+                    "schema": { "type": "string" }
+                }],
+                "responses": with_errors(json!({
+                    "200": { "description": "JSONL export", "content": { "application/x-ndjson": {} } }
+                }))
+            }
+        },
+        "/healthz": {
+            "get": {
+                "summary": "Liveness probe",
+                "security": [],
+                "responses": {
+                    "200": json_response("Server is alive", schema_ref("HealthReport"))
+                }
+            }
+        },
+        "/readyz": {
+            "get": {
+                "summary": "Readiness probe with per-component status",
+                "security": [],
+                "responses": {
+                    "200": json_response("All components are up or disabled", schema_ref("HealthReport")),
+                    "503": json_response("A component is down", schema_ref("HealthReport"))
+                }
+            }
+        },
+        "/proposals/{id}": {
+            "get": {
+                "summary": "Get a proposal with its vote counts",
+                "parameters": [path_param("id", "Proposal ID")],
+                "responses": with_errors(json!({
+                    "200": json_response("Proposal", schema_ref("ProposalResponse"))
+                }))
+            }
+        },
+        "/proposals/{id}/comments": {
+            "get": {
+                "summary": "List comments on a proposal",
+                "parameters": [
+                    path_param("id", "Proposal ID"),
+                    {
+                        "name": "show_hidden",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "boolean" }
+                    }
+                ],
+                "responses": with_errors(json!({
+                    "200": json_response(
+                        "Comments",
+                        json!({ "type": "array", "items": schema_ref("CommentResponse") })
+                    )
+                }))
+            }
+        },
+        "/proposals/{id}/summary": {
+            "get": {
+                "summary": "Summarize activity on a proposal",
+                "parameters": [path_param("id", "Proposal ID")],
+                "responses": with_errors(json!({
+                    "200": json_response("Summary", schema_ref("ProposalSummary"))
+                }))
+            }
+        }
+    });
+    merge(&mut paths, attachment_paths());
+    merge(
+        &mut paths,
+        json!({
+            "/api/v1/dsl/validate": {
+                "post": {
+                    "summary": "Check DSL source and report every error and warning",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": schema_ref("ValidateDslRequest") } }
+                    },
+                    "responses": with_errors(json!({
+                        "200": json_response("Validation result", schema_ref("ValidateDslResponse"))
+                    }))
+                }
+            }
+        }),
+    );
+    merge(&mut paths, admin_paths());
+    merge(&mut paths, federation_paths());
+    merge(&mut paths, hook_paths());
+    merge(&mut paths, replication_paths());
+    merge(&mut paths, oidc_paths());
+    merge(
+        &mut paths,
+        json!({
+            "/api/v1/resources/{resource}/accounts/{account}": {
+                "get": {
+                    "summary": "Get an account's balance, the credit extended to it, and what it can still spend",
+                    "parameters": [
+                        path_param("resource", "Resource ID"),
+                        path_param("account", "Account ID")
+                    ],
+                    "responses": with_errors(json!({
+                        "200": json_response("Account standing", schema_ref("AccountStanding"))
+                    }))
+                }
+            }
+        }),
+    );
+    merge(
+        &mut paths,
+        json!({
+            "/api/v1/audit": {
+                "get": {
+                    "summary": "Read the log of mutating API requests and CLI commands, newest first",
+                    "description": "Requires global admin, or admin of the namespace given in `namespace`.",
+                    "parameters": [
+                        { "name": "actor", "in": "query", "required": false, "schema": { "type": "string" } },
+                        { "name": "action", "in": "query", "required": false, "description": "Action prefix, e.g. `POST /api/v1/proposals`", "schema": { "type": "string" } },
+                        { "name": "namespace", "in": "query", "required": false, "schema": { "type": "string" } },
+                        { "name": "source", "in": "query", "required": false, "schema": schema_ref("AuditSource") },
+                        { "name": "outcome", "in": "query", "required": false, "schema": schema_ref("AuditOutcome") },
+                        { "name": "since", "in": "query", "required": false, "schema": { "type": "string", "format": "date-time" } },
+                        { "name": "until", "in": "query", "required": false, "schema": { "type": "string", "format": "date-time" } },
+                        { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "minimum": 1, "maximum": 1000 } }
+                    ],
+                    "responses": with_errors(json!({
+                        "200": json_response("Audit entries", json!({ "type": "array", "items": schema_ref("AuditEntry") }))
+                    }))
+                }
+            }
+        }),
+    );
+    merge(
+        &mut paths,
+        json!({
+            "/api/v1/export/{table}": {
+                "get": {
+                    "summary": "Download the proposals, votes, or comments of a namespace as a CSV or Parquet table",
+                    "parameters": [
+                        path_param("table", "proposals, votes, or comments"),
+                        { "name": "format", "in": "query", "required": false, "description": "csv (default) or parquet", "schema": { "type": "string", "enum": ["csv", "parquet"] } },
+                        { "name": "namespace", "in": "query", "required": false, "description": "Namespace to export", "schema": { "type": "string" } }
+                    ],
+                    "responses": with_errors(json!({
+                        "200": {
+                            "description": "Table file",
+                            "content": { "text/csv": {}, "application/vnd.apache.parquet": {} }
+                        }
+                    }))
+                }
+            }
+        }),
+    );
+    let coop = coop_paths(&paths);
+    merge(&mut paths, coop);
+    paths
+}
+
+/// Copies of the namespace-scoped paths under `/api/v1/coops/{coop}`
+fn coop_paths(paths: &Value) -> Value {
+    let mut scoped = serde_json::Map::new();
+    let Some(paths) = paths.as_object() else {
+        return Value::Object(scoped);
+    };
+    for (path, item) in paths {
+        let Some(rest) = path.strip_prefix("/api/v1").filter(|rest| {
+            ["/proposals", "/executions", "/resources", "/export"]
+                .iter()
+                .any(|prefix| rest.starts_with(prefix))
+        }) else {
+            continue;
+        };
+        let mut item = item.clone();
+        for operation in item
+            .as_object_mut()
+            .into_iter()
+            .flat_map(|ops| ops.values_mut())
+        {
+            let mut parameters = vec![path_param(
+                "coop",
+                "Cooperative ID; scopes the request to `coops/{coop}`",
+            )];
+            if let Some(Value::Array(existing)) = operation.get("parameters") {
+                parameters.extend(existing.iter().cloned());
+            }
+            operation["parameters"] = Value::Array(parameters);
+        }
+        scoped.insert(format!("/api/v1/coops/{{coop}}{}", rest), item);
+    }
+    Value::Object(scoped)
+}
+
+/// Paths of proposal and comment attachments
+fn attachment_paths() -> Value {
+    json!({
+        "/api/v1/proposals/{id}/attachments": {
+            "get": {
+                "summary": "List the files attached to a proposal and its comments",
+                "parameters": [path_param("id", "Proposal ID")],
+                "responses": with_errors(json!({
+                    "200": json_response(
+                        "Attachments, oldest first",
+                        json!({ "type": "array", "items": schema_ref("Attachment") })
+                    )
+                }))
+            },
+            "post": {
+                "summary": "Attach a file to a proposal or one of its comments",
+                "description": "Files are limited to 10 MiB and to JSON, PDF, GIF, JPEG, PNG, SVG, CSV, Markdown, and plain text.",
+                "parameters": [path_param("id", "Proposal ID")],
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "multipart/form-data": {
+                            "schema": {
+                                "type": "object",
+                                "required": ["file"],
+                                "properties": {
+                                    "file": { "type": "string", "format": "binary" },
+                                    "comment_id": { "type": "string", "description": "Attach to this comment instead of the proposal" }
+                                }
+                            }
+                        }
+                    }
+                },
+                "responses": with_errors(json!({
+                    "201": json_response("Attachment stored", schema_ref("Attachment")),
+                    "413": json_response("File too large", schema_ref("ErrorResponse")),
+                    "415": json_response("Content type not allowed", schema_ref("ErrorResponse"))
+                }))
+            }
+        },
+        "/api/v1/proposals/{id}/attachments/{attachment_id}": {
+            "get": {
+                "summary": "Download an attachment",
+                "description": "Supports a single `Range: bytes=start-end` request.",
+                "parameters": [
+                    path_param("id", "Proposal ID"),
+                    path_param("attachment_id", "Attachment ID"),
+                    { "name": "Range", "in": "header", "required": false, "description": "Byte range to download", "schema": { "type": "string" } }
+                ],
+                "responses": with_errors(json!({
+                    "200": {
+                        "description": "File content",
+                        "content": { "application/octet-stream": { "schema": { "type": "string", "format": "binary" } } }
+                    },
+                    "206": {
+                        "description": "Requested range of the file content",
+                        "content": { "application/octet-stream": { "schema": { "type": "string", "format": "binary" } } }
+                    },
+                    "416": json_response("Range not satisfiable", schema_ref("ErrorResponse"))
+                }))
+            }
+        }
+    })
+}
+
+/// Paths of the administration API
+fn admin_paths() -> Value {
+    json!({
+        "/api/v1/admin/namespaces": {
+            "get": {
+                "summary": "List namespaces; requires global admin",
+                "parameters": [{
+                    "name": "parent",
+                    "in": "query",
+                    "required": false,
+                    "schema": { "type": "string" }
+                }],
+                "responses": with_errors(json!({
+                    "200": json_response("Namespaces", json!({ "type": "array", "items": schema_ref("NamespaceMetadata") }))
+                }))
+            },
+            "post": {
+                "summary": "Create a namespace with a quota; requires global admin",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("CreateNamespaceRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "201": json_response("Namespace created", schema_ref("CreateNamespaceRequest"))
+                }))
+            }
+        },
+        "/api/v1/admin/accounts": {
+            "post": {
+                "summary": "Create a resource account with a quota; requires global admin",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("CreateAccountRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "201": json_response("Account created", schema_ref("CreateAccountRequest"))
+                }))
+            }
+        },
+        "/api/v1/admin/identities/{did}/roles": {
+            "get": {
+                "summary": "List an identity's roles; requires global admin",
+                "parameters": [path_param("did", "Identity DID")],
+                "responses": with_errors(json!({
+                    "200": json_response("Roles", json!({ "type": "array", "items": schema_ref("RoleAssignment") }))
+                }))
+            },
+            "post": {
+                "summary": "Grant a role; requires global admin",
+                "parameters": [path_param("did", "Identity DID")],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("RoleAssignment") } }
+                },
+                "responses": with_errors(json!({
+                    "200": json_response("Roles after the change", json!({ "type": "array", "items": schema_ref("RoleAssignment") }))
+                }))
+            },
+            "delete": {
+                "summary": "Revoke a role; requires global admin",
+                "parameters": [
+                    path_param("did", "Identity DID"),
+                    { "name": "namespace", "in": "query", "required": true, "schema": { "type": "string" } },
+                    { "name": "role", "in": "query", "required": true, "schema": { "type": "string" } }
+                ],
+                "responses": with_errors(json!({
+                    "200": json_response("Roles after the change", json!({ "type": "array", "items": schema_ref("RoleAssignment") }))
+                }))
+            }
+        },
+        "/api/v1/admin/audit": {
+            "get": {
+                "summary": "Read the storage audit log; requires global admin",
+                "parameters": [
+                    { "name": "namespace", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "event_type", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "minimum": 1, "maximum": 1000 } }
+                ],
+                "responses": with_errors(json!({
+                    "200": json_response("Audit entries", json!({ "type": "array", "items": schema_ref("StorageEvent") }))
+                }))
+            }
+        }
+    })
+}
+
+/// Paths of the federation management API
+fn hook_paths() -> Value {
+    json!({
+        "/api/v1/hooks": {
+            "get": {
+                "summary": "List inbound webhooks the caller administers, without their secrets",
+                "responses": with_errors(json!({
+                    "200": json_response(
+                        "Hook records",
+                        json!({ "type": "array", "items": schema_ref("WebhookRecord") })
+                    )
+                }))
+            },
+            "post": {
+                "summary": "Create an inbound webhook that runs a DSL program",
+                "description": "Requires the admin role in the hook's namespace. The program is checked when the hook is created.",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("CreateWebhookRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "201": json_response("Hook created; the signing secret is shown only once", schema_ref("CreateWebhookResponse"))
+                }))
+            }
+        },
+        "/api/v1/hooks/{name}": {
+            "post": {
+                "summary": "Deliver an event to a webhook, running its program",
+                "description": "Authenticated by `x-icn-signature`, the hex HMAC-SHA256 of the raw body keyed with the hook's secret, rather than by a token. Payload values are passed to the program as the hook's parameters.",
+                "security": [],
+                "parameters": [
+                    path_param("name", "Hook name"),
+                    { "name": "x-icn-signature", "in": "header", "required": true, "description": "`sha256=` followed by the hex HMAC of the body", "schema": { "type": "string" } }
+                ],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": { "type": "object" } } }
+                },
+                "responses": with_errors(json!({
+                    "200": json_response("Program ran", schema_ref("ExecutionResult")),
+                    "422": json_response("A parameter is missing from the payload, or the program failed", schema_ref("ErrorResponse"))
+                }))
+            },
+            "delete": {
+                "summary": "Remove an inbound webhook",
+                "parameters": [path_param("name", "Hook name")],
+                "responses": with_errors(json!({
+                    "204": { "description": "Hook removed" }
+                }))
+            }
+        }
+    })
+}
+
+/// Paths of the change feed followed by read replicas
+fn replication_paths() -> Value {
+    json!({
+        "/api/v1/replication/snapshot": {
+            "get": {
+                "summary": "Every key and value in storage, to start following from; requires global admin",
+                "responses": with_errors(json!({
+                    "200": json_response("Snapshot", schema_ref("ChangeBatch"))
+                }))
+            }
+        },
+        "/api/v1/replication/changes": {
+            "get": {
+                "summary": "Keys written or deleted after a sequence number; requires global admin",
+                "parameters": [
+                    { "name": "epoch", "in": "query", "required": true, "schema": { "type": "string" } },
+                    { "name": "since", "in": "query", "required": true, "schema": { "type": "integer", "minimum": 0 } },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "minimum": 1, "maximum": 1000 } }
+                ],
+                "responses": with_errors(json!({
+                    "200": json_response("Changes", schema_ref("ChangeBatch")),
+                    "410": json_response("Changes are no longer kept; take a snapshot", schema_ref("ErrorResponse"))
+                }))
+            }
+        }
+    })
+}
+
+fn replication_schemas() -> Value {
+    let uint = json!({ "type": "integer", "format": "int64", "minimum": 0 });
+
+    json!({
+        "ChangeRecord": {
+            "type": "object",
+            "required": ["seq", "namespace", "key"],
+            "properties": {
+                "seq": uint,
+                "namespace": { "type": "string" },
+                "key": { "type": "string" },
+                "value": { "type": "string", "format": "byte", "description": "New value; absent when the key was deleted" }
+            }
+        },
+        "ChangeBatch": {
+            "type": "object",
+            "required": ["epoch", "head", "more", "changes"],
+            "properties": {
+                "epoch": { "type": "string" },
+                "head": uint,
+                "more": { "type": "boolean" },
+                "changes": { "type": "array", "items": schema_ref("ChangeRecord") }
+            }
+        }
+    })
+}
+
+/// Paths of OpenID Connect login, served when a provider is configured
+fn oidc_paths() -> Value {
+    json!({
+        "/api/v1/auth/oidc/token": {
+            "post": {
+                "summary": "Exchange a provider ID token for a bearer token",
+                "description": "The account must be bound to an identity that approved the binding.",
+                "security": [],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("OidcTokenRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "200": json_response("Token issued", schema_ref("TokenResponse"))
+                }))
+            }
+        },
+        "/api/v1/auth/oidc/bindings": {
+            "get": {
+                "summary": "Bindings of the caller's identity; every binding for a global admin",
+                "responses": with_errors(json!({
+                    "200": json_response(
+                        "Bindings",
+                        json!({ "type": "array", "items": schema_ref("OidcBinding") })
+                    )
+                }))
+            },
+            "post": {
+                "summary": "Request a binding of a provider account to an identity",
+                "description": "The binding is pending until the identity approves it.",
+                "security": [],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("OidcBindingRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "201": json_response("Binding requested", schema_ref("OidcBinding")),
+                    "409": json_response("The account is already bound", schema_ref("ErrorResponse"))
+                }))
+            }
+        },
+        "/api/v1/auth/oidc/bindings/{id}/approve": {
+            "post": {
+                "summary": "Approve a binding to the caller's identity",
+                "parameters": [path_param("id", "Binding ID")],
+                "responses": with_errors(json!({
+                    "200": json_response("Binding approved", schema_ref("OidcBinding"))
+                }))
+            }
+        },
+        "/api/v1/auth/oidc/bindings/{id}": {
+            "delete": {
+                "summary": "Remove a binding of the caller's identity, or any binding as global admin",
+                "parameters": [path_param("id", "Binding ID")],
+                "responses": with_errors(json!({
+                    "200": json_response(
+                        "Binding removed",
+                        json!({ "type": "object", "properties": { "removed": { "type": "string" } } })
+                    )
+                }))
+            }
+        }
+    })
+}
+
+fn oidc_schemas() -> Value {
+    let string = json!({ "type": "string" });
+    let uint = json!({ "type": "integer", "format": "int64", "minimum": 0 });
+
+    json!({
+        "OidcTokenRequest": {
+            "type": "object",
+            "required": ["id_token"],
+            "properties": { "id_token": string }
+        },
+        "OidcBindingRequest": {
+            "type": "object",
+            "required": ["id_token", "did"],
+            "properties": { "id_token": string, "did": string }
+        },
+        "OidcBinding": {
+            "type": "object",
+            "required": ["id", "issuer", "subject", "did", "requested_at"],
+            "properties": {
+                "id": string,
+                "issuer": string,
+                "subject": string,
+                "did": string,
+                "email": { "type": "string", "nullable": true },
+                "requested_at": uint,
+                "approved_at": { "type": "integer", "format": "int64", "nullable": true, "description": "Absent while the binding is pending" }
+            }
+        }
+    })
+}
+
+fn hook_schemas() -> Value {
+    let string = json!({ "type": "string" });
+    let uint = json!({ "type": "integer", "format": "int64", "minimum": 0 });
+    let params = json!({
+        "type": "object",
+        "description": "JSON pointers into the payload, by program parameter name",
+        "additionalProperties": { "type": "string" }
+    });
+
+    json!({
+        "WebhookRecord": {
+            "type": "object",
+            "required": ["name", "namespace", "program", "params", "created_by", "created_at"],
+            "properties": {
+                "name": string,
+                "namespace": string,
+                "program": string,
+                "params": params,
+                "created_by": string,
+                "created_at": uint
+            }
+        },
+        "CreateWebhookRequest": {
+            "type": "object",
+            "required": ["name", "namespace", "program"],
+            "properties": {
+                "name": string,
+                "namespace": string,
+                "program": { "type": "string", "description": "DSL source run for each delivery" },
+                "params": params
+            }
+        },
+        "CreateWebhookResponse": {
+            "type": "object",
+            "required": ["secret", "record"],
+            "properties": { "secret": string, "record": schema_ref("WebhookRecord") }
+        }
+    })
+}
+
+fn federation_paths() -> Value {
+    json!({
+        "/api/v1/federation": {
+            "get": {
+                "summary": "Local peer ID, bootstrap nodes, and known peers of the federation node",
+                "responses": with_errors(json!({
+                    "200": json_response("Federation status", schema_ref("FederationStatus"))
+                }))
+            }
+        },
+        "/api/v1/federation/peers": {
+            "get": {
+                "summary": "Known peers with their liveness and reputation",
+                "responses": with_errors(json!({
+                    "200": json_response("Peers", json!({ "type": "array", "items": schema_ref("PeerStatus") }))
+                }))
+            }
+        },
+        "/api/v1/federation/bootstrap": {
+            "post": {
+                "summary": "Add a bootstrap node and dial it; requires global admin",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("AddBootstrapNodeRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "202": json_response("Bootstrap node queued", schema_ref("AddBootstrapNodeRequest"))
+                }))
+            }
+        },
+        "/api/v1/federation/broadcast": {
+            "post": {
+                "summary": "Broadcast a local proposal to the federation; requires global admin",
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref("BroadcastProposalRequest") } }
+                },
+                "responses": with_errors(json!({
+                    "202": json_response("Broadcast queued", schema_ref("FederatedProposal"))
+                }))
+            }
+        },
+        "/api/v1/federation/sync": {
+            "post": {
+                "summary": "Ask peers for missing ledger nodes; requires global admin",
+                "responses": with_errors(json!({
+                    "202": json_response("Sync queued", schema_ref("FederationStatus"))
+                }))
+            }
+        }
+    })
+}
+
+fn schemas() -> Value {
+    let string = json!({ "type": "string" });
+    let nullable_string = json!({ "type": "string", "nullable": true });
+    let uint = json!({ "type": "integer", "format": "int64", "minimum": 0 });
+    let strings = json!({ "type": "array", "items": { "type": "string" } });
+
+    let mut schemas = json!({
+        "ErrorResponse": {
+            "type": "object",
+            "required": ["message"],
+            "properties": { "message": string }
+        },
+        "TokenRequest": {
+            "type": "object",
+            "required": ["did", "timestamp", "signature"],
+            "properties": {
+                "did": string,
+                "timestamp": uint,
+                "signature": { "type": "string", "description": "Multibase signature of the login challenge" }
+            }
+        },
+        "TokenResponse": {
+            "type": "object",
+            "required": ["token", "expires_in"],
+            "properties": { "token": string, "expires_in": uint }
+        },
+        "Capability": {
+            "type": "string",
+            "enum": ["read", "write"]
+        },
+        "ApiKeyRecord": {
+            "type": "object",
+            "required": ["id", "name", "owner_did", "namespaces", "capabilities", "created_at", "revoked"],
+            "properties": {
+                "id": string,
+                "name": string,
+                "owner_did": string,
+                "namespaces": strings,
+                "capabilities": { "type": "array", "items": schema_ref("Capability") },
+                "created_at": uint,
+                "revoked": { "type": "boolean" }
+            }
+        },
+        "CreateApiKeyRequest": {
+            "type": "object",
+            "required": ["name", "namespaces", "capabilities"],
+            "properties": {
+                "name": string,
+                "namespaces": strings,
+                "capabilities": { "type": "array", "items": schema_ref("Capability") }
+            }
+        },
+        "CreateApiKeyResponse": {
+            "type": "object",
+            "required": ["key", "record"],
+            "properties": { "key": string, "record": schema_ref("ApiKeyRecord") }
+        },
+        "ApiEvent": {
+            "type": "object",
+            "required": ["type"],
+            "description": "Tagged by `type`: proposal_state, comment_added, vote_counts, proposal_created, vote_cast, proposal_executed, or vm_event",
+            "properties": {
+                "type": {
+                    "type": "string",
+                    "enum": [
+                        "proposal_state",
+                        "comment_added",
+                        "vote_counts",
+                        "proposal_created",
+                        "vote_cast",
+                        "proposal_executed",
+                        "vm_event"
+                    ]
+                },
+                "proposal_id": nullable_string,
+                "namespace": string
+            },
+            "additionalProperties": true
+        },
+        "ExecuteProgramRequest": {
+            "type": "object",
+            "description": "Exactly one of `program` and `proposal_id` must be given",
+            "properties": {
+                "program": { "type": "string", "description": "DSL source" },
+                "proposal_id": { "type": "string", "description": "Proposal whose logic to execute" },
+                "sandbox": {
+                    "type": "string",
+                    "enum": ["deliberation-preview", "full-governance"],
+                    "description": "Sandbox profile a program runs under; profiles wider than `deliberation-preview` require the global admin role"
+                }
+            }
+        },
+        "VMEvent": {
+            "type": "object",
+            "required": ["category", "message", "timestamp"],
+            "properties": {
+                "category": string,
+                "message": string,
+                "timestamp": uint,
+                "severity": { "type": "string", "enum": ["debug", "info", "warn", "error"] }
+            }
+        },
+        "ExecutionResult": {
+            "type": "object",
+            "required": ["success"],
+            "properties": { "success": { "type": "boolean" }, "error": nullable_string }
+        },
+        "ProposalListItem": {
+            "type": "object",
+            "required": ["id", "title", "creator", "status", "labels", "created_at"],
+            "properties": {
+                "id": string,
+                "title": string,
+                "creator": string,
+                "status": string,
+                "labels": strings,
+                "created_at": string
+            }
+        },
+        "ProposalPage": {
+            "type": "object",
+            "required": ["total", "page", "per_page", "offset", "proposals"],
+            "properties": {
+                "total": uint,
+                "page": uint,
+                "per_page": uint,
+                "offset": uint,
+                "next_cursor": nullable_string,
+                "proposals": { "type": "array", "items": schema_ref("ProposalListItem") }
+            }
+        },
+        "DagNode": {
+            "type": "object",
+            "required": ["id", "parent_ids", "timestamp", "namespace", "data"],
+            "properties": {
+                "id": string,
+                "parent_ids": strings,
+                "timestamp": uint,
+                "namespace": string,
+                "data": {
+                    "type": "object",
+                    "required": ["type"],
+                    "properties": { "type": string },
+                    "additionalProperties": true,
+                    "description": "Payload tagged by `type`, e.g. `VoteCast`; sealed nodes have type `Encrypted`"
+                }
+            }
+        },
+        "ProposalTrace": {
+            "type": "object",
+            "required": ["proposal_id", "nodes"],
+            "properties": {
+                "proposal_id": string,
+                "nodes": { "type": "array", "items": schema_ref("DagNode") }
+            }
+        },
+        "LedgerDiffResponse": {
+            "type": "object",
+            "required": ["only_local", "only_uploaded", "common"],
+            "properties": {
+                "only_local": { "type": "array", "items": schema_ref("DagNode") },
+                "only_uploaded": { "type": "array", "items": schema_ref("DagNode") },
+                "common": uint
+            }
+        },
+        "ComponentState": {
+            "type": "string",
+            "enum": ["up", "down", "disabled"]
+        },
+        "HealthReport": {
+            "type": "object",
+            "required": ["status", "components"],
+            "properties": {
+                "status": schema_ref("ComponentState"),
+                "components": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["name", "state"],
+                        "properties": {
+                            "name": string,
+                            "state": schema_ref("ComponentState"),
+                            "detail": string
+                        }
+                    }
+                }
+            }
+        },
+        "VoteCounts": {
+            "type": "object",
+            "required": ["yes", "no", "abstain", "total"],
+            "properties": { "yes": uint, "no": uint, "abstain": uint, "total": uint }
+        },
+        "ProposalResponse": {
+            "type": "object",
+            "required": ["id", "title", "creator", "status", "created_at", "votes", "quorum_percentage", "threshold_percentage"],
+            "properties": {
+                "id": string,
+                "title": string,
+                "creator": string,
+                "status": string,
+                "created_at": string,
+                "votes": schema_ref("VoteCounts"),
+                "quorum_percentage": { "type": "number" },
+                "threshold_percentage": { "type": "number" },
+                "execution_result": nullable_string
+            }
+        },
+        "CommentResponse": {
+            "type": "object",
+            "required": ["id", "author", "timestamp", "content", "tags", "reactions", "hidden", "edit_count"],
+            "properties": {
+                "id": string,
+                "author": string,
+                "timestamp": string,
+                "content": string,
+                "reply_to": nullable_string,
+                "tags": strings,
+                "reactions": { "type": "object", "additionalProperties": uint },
+                "hidden": { "type": "boolean" },
+                "edit_count": uint
+            }
+        },
+        "CommentThread": {
+            "allOf": [
+                schema_ref("CommentResponse"),
+                {
+                    "type": "object",
+                    "required": ["replies"],
+                    "properties": {
+                        "replies": { "type": "array", "items": schema_ref("CommentThread") }
+                    }
+                }
+            ]
+        },
+        "CreateCommentRequest": {
+            "type": "object",
+            "required": ["content"],
+            "properties": { "content": string, "reply_to": nullable_string, "tags": strings }
+        },
+        "EditCommentRequest": {
+            "type": "object",
+            "required": ["content"],
+            "properties": { "content": string }
+        },
+        "ReactionRequest": {
+            "type": "object",
+            "required": ["reaction"],
+            "properties": { "reaction": string }
+        },
+        "CommentVersionResponse": {
+            "type": "object",
+            "required": ["content", "timestamp"],
+            "properties": { "content": string, "timestamp": string }
+        },
+        "Participant": {
+            "type": "object",
+            "required": ["id", "comment_count"],
+            "properties": { "id": string, "comment_count": uint }
+        },
+        "ProposalSummary": {
+            "type": "object",
+            "required": ["id", "title", "status", "comment_count", "vote_count", "vote_details", "top_participants", "last_activity"],
+            "properties": {
+                "id": string,
+                "title": string,
+                "status": string,
+                "comment_count": uint,
+                "vote_count": uint,
+                "vote_details": schema_ref("VoteCounts"),
+                "top_participants": { "type": "array", "items": schema_ref("Participant") },
+                "last_activity": string
+            }
+        }
+    });
+    merge(
+        &mut schemas,
+        json!({
+            "Attachment": {
+                "type": "object",
+                "required": ["id", "proposal_id", "filename", "content_type", "size", "sha256", "uploaded_by", "uploaded_at"],
+                "properties": {
+                    "id": { "type": "string" },
+                    "proposal_id": { "type": "string" },
+                    "comment_id": { "type": "string", "nullable": true },
+                    "filename": { "type": "string" },
+                    "content_type": { "type": "string" },
+                    "size": { "type": "integer", "format": "int64", "minimum": 0 },
+                    "sha256": { "type": "string", "description": "Hex SHA-256 of the content" },
+                    "uploaded_by": { "type": "string" },
+                    "uploaded_at": { "type": "string", "format": "date-time" }
+                }
+            }
+        }),
+    );
+    merge(
+        &mut schemas,
+        json!({
+            "ValidateDslRequest": {
+                "type": "object",
+                "required": ["source"],
+                "properties": { "source": { "type": "string" } }
+            },
+            "Severity": { "type": "string", "enum": ["error", "warning"] },
+            "JobStatus": { "type": "string", "enum": ["queued", "running", "succeeded", "failed"] },
+            "TypedValue": {
+                "description": "A VM stack value: `{\"Number\": 1.0}`, `{\"Decimal\": \"12.50\"}`, `{\"Boolean\": true}`, `{\"String\": \"...\"}`, `{\"Bytes\": [222, 173]}`, `{\"Timestamp\": \"2025-03-01T12:00:00Z\"}`, or `\"Null\"`",
+                "oneOf": [
+                    { "type": "object", "required": ["Number"], "properties": { "Number": { "type": "number" } } },
+                    { "type": "object", "required": ["Decimal"], "properties": { "Decimal": { "type": "string", "description": "Exact decimal, e.g. a token balance" } } },
+                    { "type": "object", "required": ["Boolean"], "properties": { "Boolean": { "type": "boolean" } } },
+                    { "type": "object", "required": ["String"], "properties": { "String": { "type": "string" } } },
+                    { "type": "object", "required": ["Bytes"], "properties": { "Bytes": { "type": "array", "items": { "type": "integer", "minimum": 0, "maximum": 255 } } } },
+                    { "type": "object", "required": ["Timestamp"], "properties": { "Timestamp": { "type": "string", "format": "date-time" } } },
+                    { "type": "string", "enum": ["Null"] }
+                ]
+            },
+            "ExecutionJob": {
+                "type": "object",
+                "required": ["id", "status", "namespace", "submitted_by", "submitted_at", "output", "stack"],
+                "properties": {
+                    "id": { "type": "string" },
+                    "status": schema_ref("JobStatus"),
+                    "proposal_id": { "type": "string", "nullable": true },
+                    "namespace": { "type": "string" },
+                    "submitted_by": { "type": "string" },
+                    "submitted_at": { "type": "string", "format": "date-time" },
+                    "started_at": { "type": "string", "format": "date-time", "nullable": true },
+                    "finished_at": { "type": "string", "format": "date-time", "nullable": true },
+                    "output": { "type": "array", "items": schema_ref("VMEvent") },
+                    "stack": { "type": "array", "items": schema_ref("TypedValue") },
+                    "error": { "type": "string", "nullable": true }
+                }
+            },
+            "Diagnostic": {
+                "type": "object",
+                "required": ["severity", "line", "column", "message"],
+                "properties": {
+                    "severity": schema_ref("Severity"),
+                    "line": { "type": "integer", "minimum": 1 },
+                    "column": { "type": "integer", "minimum": 1 },
+                    "message": { "type": "string" }
+                }
+            },
+            "ValidateDslResponse": {
+                "type": "object",
+                "required": ["valid", "diagnostics"],
+                "properties": {
+                    "valid": { "type": "boolean" },
+                    "diagnostics": { "type": "array", "items": schema_ref("Diagnostic") }
+                }
+            }
+        }),
+    );
+    merge(&mut schemas, admin_schemas());
+    merge(&mut schemas, federation_schemas());
+    merge(&mut schemas, hook_schemas());
+    merge(&mut schemas, replication_schemas());
+    merge(&mut schemas, oidc_schemas());
+    merge(
+        &mut schemas,
+        json!({
+            "AuditSource": { "type": "string", "enum": ["api", "cli"] },
+            "AuditOutcome": { "type": "string", "enum": ["success", "failure"] },
+            "AuditEntry": {
+                "type": "object",
+                "required": ["id", "timestamp", "actor", "source", "action", "outcome"],
+                "properties": {
+                    "id": string,
+                    "timestamp": { "type": "string", "format": "date-time" },
+                    "actor": string,
+                    "source": schema_ref("AuditSource"),
+                    "action": string,
+                    "namespace": nullable_string,
+                    "outcome": schema_ref("AuditOutcome"),
+                    "status": { "type": "integer", "nullable": true },
+                    "detail": nullable_string
+                }
+            },
+            "CreditLine": {
+                "type": "object",
+                "required": ["resource", "creditor", "debtor", "limit"],
+                "properties": {
+                    "resource": string,
+                    "creditor": { "type": "string", "description": "Account extending the credit, or `commons`" },
+                    "debtor": string,
+                    "limit": { "type": "string", "description": "Exact decimal" },
+                    "proposal_id": nullable_string
+                }
+            },
+            "AccountStanding": {
+                "type": "object",
+                "required": ["resource", "account", "balance", "credit_limit", "available", "credit_lines"],
+                "properties": {
+                    "resource": string,
+                    "account": string,
+                    "balance": { "type": "string", "description": "Exact decimal; negative while drawing on credit" },
+                    "credit_limit": { "type": "string", "description": "Exact decimal" },
+                    "available": { "type": "string", "description": "Balance plus credit limit" },
+                    "credit_lines": { "type": "array", "items": schema_ref("CreditLine") }
+                }
+            }
+        }),
+    );
+    schemas
+}
+
+/// Schemas of the administration API
+fn admin_schemas() -> Value {
+    let string = json!({ "type": "string" });
+    let nullable_string = json!({ "type": "string", "nullable": true });
+    let uint = json!({ "type": "integer", "format": "int64", "minimum": 0 });
+
+    json!({
+        "CreateNamespaceRequest": {
+            "type": "object",
+            "required": ["namespace", "quota_bytes"],
+            "properties": { "namespace": string, "quota_bytes": uint, "parent": nullable_string }
+        },
+        "NamespaceMetadata": {
+            "type": "object",
+            "required": ["path", "owner", "quota_bytes", "used_bytes", "attributes"],
+            "properties": {
+                "path": string,
+                "owner": string,
+                "quota_bytes": uint,
+                "used_bytes": uint,
+                "parent": nullable_string,
+                "attributes": { "type": "object", "additionalProperties": { "type": "string" } }
+            }
+        },
+        "CreateAccountRequest": {
+            "type": "object",
+            "required": ["user_id", "quota_bytes"],
+            "properties": { "user_id": string, "quota_bytes": uint }
+        },
+        "RoleAssignment": {
+            "type": "object",
+            "required": ["namespace", "role"],
+            "properties": { "namespace": string, "role": string }
+        },
+        "StorageEvent": {
+            "type": "object",
+            "required": ["event_type", "user_id", "namespace", "key", "timestamp", "details"],
+            "properties": {
+                "event_type": string,
+                "user_id": string,
+                "namespace": string,
+                "key": string,
+                "timestamp": uint,
+                "details": string
+            }
+        }
+    })
+}
+
+/// Schemas of the federation management API
+fn federation_schemas() -> Value {
+    let string = json!({ "type": "string" });
+    let uint = json!({ "type": "integer", "format": "int64", "minimum": 0 });
+    let strings = json!({ "type": "array", "items": { "type": "string" } });
+
+    json!({
+        "PeerStatus": {
+            "type": "object",
+            "required": ["peer_id", "addresses", "connected", "last_seen", "ping_successes", "ping_failures", "reputation"],
+            "properties": {
+                "peer_id": string,
+                "addresses": strings,
+                "connected": { "type": "boolean" },
+                "last_seen": uint,
+                "rtt_ms": { "type": "integer", "format": "int64", "nullable": true },
+                "ping_successes": uint,
+                "ping_failures": uint,
+                "reputation": { "type": "number", "minimum": 0, "maximum": 1 }
+            }
+        },
+        "FederationStatus": {
+            "type": "object",
+            "required": ["local_peer_id", "bootstrap_nodes", "peers"],
+            "properties": {
+                "local_peer_id": string,
+                "bootstrap_nodes": strings,
+                "peers": { "type": "array", "items": schema_ref("PeerStatus") }
+            }
+        },
+        "AddBootstrapNodeRequest": {
+            "type": "object",
+            "required": ["address"],
+            "properties": { "address": { "type": "string", "example": "/ip4/10.0.0.2/tcp/4001" } }
+        },
+        "ProposalScope": {
+            "oneOf": [
+                { "type": "string", "enum": ["GlobalFederation"] },
+                { "type": "object", "required": ["SingleCoop"], "properties": { "SingleCoop": string } },
+                { "type": "object", "required": ["MultiCoop"], "properties": { "MultiCoop": strings } }
+            ]
+        },
+        "VotingModel": { "type": "string", "enum": ["OneMemberOneVote", "OneCoopOneVote"] },
+        "BroadcastProposalRequest": {
+            "type": "object",
+            "required": ["proposal_id"],
+            "properties": {
+                "proposal_id": string,
+                "scope": schema_ref("ProposalScope"),
+                "voting_model": schema_ref("VotingModel"),
+                "expires_in": { "type": "integer", "format": "int64", "minimum": 0, "nullable": true }
+            }
+        },
+        "FederatedProposal": {
+            "type": "object",
+            "required": ["proposal_id", "namespace", "options", "creator", "created_at", "scope", "voting_model", "status"],
+            "properties": {
+                "proposal_id": string,
+                "namespace": string,
+                "options": strings,
+                "creator": string,
+                "created_at": { "type": "integer", "format": "int64" },
+                "scope": schema_ref("ProposalScope"),
+                "voting_model": schema_ref("VotingModel"),
+                "expires_at": { "type": "integer", "format": "int64", "nullable": true },
+                "status": { "type": "string", "enum": ["Open", "Closed", "Executed", "Rejected", "Expired"] }
+            }
+        }
+    })
+}
+
+/// Build the OpenAPI document
+pub fn spec() -> Value {
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "ICN Cooperative VM API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Governance, ledger, and execution API of an icn-covm node. POST and PUT \
+                requests may carry an Idempotency-Key header: a retry with the same key gets the \
+                first response back, marked Idempotent-Replayed, instead of being applied again."
+        },
+        "paths": paths(),
+        "components": {
+            "schemas": schemas(),
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer", "bearerFormat": "JWT" },
+                "apiKey": { "type": "apiKey", "in": "header", "name": crate::api::keys::API_KEY_HEADER }
+            }
+        },
+        "security": [{ "bearerAuth": [] }, { "apiKey": [] }]
+    })
+}
+
+/// Route for GET /openapi.json
+pub fn openapi_route() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("openapi.json")
+        .and(warp::get())
+        .map(|| warp::reply::json(&spec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_references_resolve() {
+        let spec = spec();
+        let schemas = spec["components"]["schemas"].as_object().unwrap();
+
+        fn collect_refs(value: &Value, refs: &mut Vec<String>) {
+            match value {
+                Value::Object(map) => {
+                    if let Some(Value::String(r)) = map.get("$ref") {
+                        refs.push(r.trim_start_matches("#/components/schemas/").to_string());
+                    }
+                    map.values().for_each(|v| collect_refs(v, refs));
+                }
+                Value::Array(items) => items.iter().for_each(|v| collect_refs(v, refs)),
+                _ => {}
+            }
+        }
+
+        let mut refs = Vec::new();
+        collect_refs(&spec, &mut refs);
+        assert!(!refs.is_empty());
+        for name in refs {
+            assert!(schemas.contains_key(&name), "unresolved schema {}", name);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Proposal listing at `/api/v1/proposals`
+//!
+//! Supports filtering by status, creator, and label, sorting, and
+//! page-number, offset or cursor pagination:
+//!
+//! ```text
+//! GET /api/v1/proposals?status=voting&label=budget&sort=-created_at&per_page=20
+//! GET /api/v1/proposals?creator=did:key:alice&limit=10&offset=30
+//! GET /api/v1/proposals?cursor=<next_cursor from the previous page>
+//! ```
+//!
+//! Filtering, sorting and slicing happen in storage through
+//! `StorageExtensions::list_proposals_paged`, which scans the proposal key
+//! prefix of the namespace, so results always reflect storage without a
+//! separate index to keep in sync, and only the requested page is loaded.
+
+use super::models::{ProposalListItem, ProposalListQuery, ProposalPage};
+use super::tenant::{self, ScopedVm};
+use super::{error_reply, storage_error_reply, JsonReply};
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::StorageError;
+use crate::storage::proposal_list::{ProposalPageQuery, ProposalSort, ProposalSummary};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// Page size used when `per_page` is not given
+const DEFAULT_PER_PAGE: usize = 20;
+
+/// Largest page a client may request
+const MAX_PER_PAGE: usize = 100;
+
+/// Route for GET /proposals
+pub fn proposals_route<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    warp::path!("proposals")
+        .and(warp::get())
+        .and(auth)
+        .and(tenant::scoped_vm(vm))
+        .and(warp::query::<ProposalListQuery>())
+        .and_then(list_proposals_handler)
+}
+
+async fn list_proposals_handler<S>(
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+    query: ProposalListQuery,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let vm_lock = vm.lock().await;
+    // Requests scoped to a cooperative cannot list another namespace
+    let namespace = match (vm.namespace(), &query.namespace) {
+        (None, Some(namespace)) => namespace.clone(),
+        _ => vm_lock.get_namespace().unwrap_or("default").to_string(),
+    };
+
+    match list_page(&vm_lock, &auth, &namespace, &query) {
+        Ok(page) => Ok(warp::reply::with_status(
+            warp::reply::json(&page),
+            StatusCode::OK,
+        )),
+        Err(ListError::Invalid(message)) => Ok(error_reply(message, StatusCode::BAD_REQUEST)),
+        Err(ListError::Unavailable) => Ok(error_reply(
+            "Storage not available",
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )),
+        Err(ListError::Storage(err)) => Ok(storage_error_reply(err)),
+    }
+}
+
+/// Why a proposal list request failed
+#[derive(Debug)]
+pub(crate) enum ListError {
+    /// The query is malformed, e.g. an unknown sort field or cursor
+    Invalid(String),
+    /// The VM has no storage backend
+    Unavailable,
+    /// Storage failed the read
+    Storage(StorageError),
+}
+
+/// Translate list query parameters into a storage query and its page size
+///
+/// `limit` takes precedence over `per_page`, and `offset` over `page`; a
+/// `cursor` replaces both `offset` and `page`.
+pub(crate) fn page_query(query: &ProposalListQuery) -> Result<(ProposalPageQuery, usize), String> {
+    let sort = query.sort.as_deref().unwrap_or("-created_at");
+    let (field, descending) = match sort.strip_prefix('-') {
+        Some(field) => (field, true),
+        None => (sort, false),
+    };
+    let per_page = query
+        .limit
+        .or(query.per_page)
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let offset = match query.offset {
+        Some(offset) => offset,
+        None => {
+            let page = query.page.unwrap_or(1).max(1);
+            (page - 1)
+                .checked_mul(per_page)
+                .ok_or_else(|| format!("Page {} is out of range", page))?
+        }
+    };
+
+    let page_query = ProposalPageQuery {
+        status: query.status.clone(),
+        creator: query.creator.clone(),
+        label: query.label.clone(),
+        sort: field.parse::<ProposalSort>()?,
+        descending,
+        offset,
+        limit: per_page,
+        after: query.cursor.clone(),
+    };
+    Ok((page_query, per_page))
+}
+
+/// List the page of proposals in a namespace that `query` asks for
+pub(crate) fn list_page<S>(
+    vm: &VM<S>,
+    auth: &AuthContext,
+    namespace: &str,
+    query: &ProposalListQuery,
+) -> Result<ProposalPage, ListError>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let (page_query, per_page) = page_query(query).map_err(ListError::Invalid)?;
+    let storage = vm.get_storage_backend().ok_or(ListError::Unavailable)?;
+    let paged = storage
+        .list_proposals_paged(Some(auth), namespace, &page_query)
+        .map_err(|e| match e {
+            StorageError::ValidationError { details, .. } => ListError::Invalid(details),
+            e => ListError::Storage(e),
+        })?;
+
+    let end = paged.offset + paged.proposals.len();
+    let next_cursor = if end < paged.total {
+        paged.proposals.last().map(|item| item.id.clone())
+    } else {
+        None
+    };
+    Ok(ProposalPage {
+        total: paged.total,
+        page: paged.offset / per_page + 1,
+        per_page,
+        offset: paged.offset,
+        next_cursor,
+        proposals: paged.proposals.into_iter().map(list_item).collect(),
+    })
+}
+
+fn list_item(summary: ProposalSummary) -> ProposalListItem {
+    ProposalListItem {
+        id: summary.id,
+        title: summary.title,
+        creator: summary.creator,
+        status: summary.status,
+        labels: summary.labels,
+        created_at: summary.created_at.to_rfc3339(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_query_prefers_limit_offset_and_cursor() {
+        let query = ProposalListQuery {
+            status: Some("voting".to_string()),
+            page: Some(3),
+            per_page: Some(10),
+            ..Default::default()
+        };
+        let (storage_query, per_page) = page_query(&query).unwrap();
+        assert_eq!(
+            (storage_query.offset, storage_query.limit, per_page),
+            (20, 10, 10)
+        );
+        assert_eq!(storage_query.sort, ProposalSort::CreatedAt);
+        assert!(storage_query.descending);
+
+        let query = ProposalListQuery {
+            limit: Some(500),
+            offset: Some(7),
+            sort: Some("title".to_string()),
+            cursor: Some("prop-1".to_string()),
+            ..query
+        };
+        let (storage_query, per_page) = page_query(&query).unwrap();
+        assert_eq!((storage_query.offset, per_page), (7, MAX_PER_PAGE));
+        assert_eq!(storage_query.sort, ProposalSort::Title);
+        assert!(!storage_query.descending);
+        assert_eq!(storage_query.after.as_deref(), Some("prop-1"));
+
+        let bad_sort = ProposalListQuery {
+            sort: Some("votes".to_string()),
+            ..Default::default()
+        };
+        assert!(page_query(&bad_sort).is_err());
+
+        let far_page = ProposalListQuery {
+            page: Some(usize::MAX),
+            ..Default::default()
+        };
+        assert!(page_query(&far_page).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! Storage change feed for read replicas under `/api/v1/replication`
+//!
+//! - `GET /replication/snapshot` returns every key and value, with the epoch
+//!   and sequence number to follow on from
+//! - `GET /replication/changes?epoch=&since=&limit=` returns the changes
+//!   after `since`, or 410 Gone when they are no longer kept and the replica
+//!   must take a snapshot again
+//!
+//! Both endpoints require the global admin role. Each request first brings
+//! the change log up to date with storage; see `storage::replication`.
+
+use super::models::ReplicationChangesQuery;
+use super::{error_reply, JsonReply};
+use crate::storage::auth::AuthContext;
+use crate::storage::replication::{ChangeLog, MAX_BATCH};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// Change log shared by the replication routes
+pub type SharedChangeLog = Arc<std::sync::Mutex<ChangeLog>>;
+
+/// Routes serving the change feed
+pub fn replication_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    log: SharedChangeLog,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let with_vm = warp::any().map(move || vm.clone());
+    let with_log = warp::any().map(move || log.clone());
+
+    let snapshot = warp::path!("replication" / "snapshot")
+        .and(warp::get())
+        .and(auth.clone())
+        .and(with_vm.clone())
+        .and(with_log.clone())
+        .and_then(snapshot_handler);
+
+    let changes = warp::path!("replication" / "changes")
+        .and(warp::get())
+        .and(auth)
+        .and(with_vm)
+        .and(with_log)
+        .and(warp::query::<ReplicationChangesQuery>())
+        .and_then(changes_handler);
+
+    snapshot.or(changes)
+}
+
+async fn snapshot_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    log: SharedChangeLog,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if !auth.has_role("global", "admin") {
+        return Ok(error_reply(
+            "Replication requires the global admin role",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+    let vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    let mut log = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok(match log.snapshot(storage, Some(&auth)) {
+        Ok(batch) => warp::reply::with_status(warp::reply::json(&batch), StatusCode::OK),
+        Err(e) => error_reply(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR),
+    })
+}
+
+async fn changes_handler<S>(
+    auth: AuthContext,
+    vm: Arc<Mutex<VM<S>>>,
+    log: SharedChangeLog,
+    query: ReplicationChangesQuery,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    if !auth.has_role("global", "admin") {
+        return Ok(error_reply(
+            "Replication requires the global admin role",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+    let vm_lock = vm.lock().await;
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+    let mut log = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Err(e) = log.refresh(storage, Some(&auth)) {
+        return Ok(error_reply(
+            e.to_string(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ));
+    }
+    let limit = query.limit.unwrap_or(MAX_BATCH).clamp(1, MAX_BATCH);
+    Ok(match log.changes_since(&query.epoch, query.since, limit) {
+        Some(batch) => warp::reply::with_status(warp::reply::json(&batch), StatusCode::OK),
+        None => error_reply(
+            format!(
+                "Changes after {} in epoch {} are no longer kept; take a snapshot",
+                query.since, query.epoch
+            ),
+            StatusCode::GONE,
+        ),
+    })
+}
@@ -0,0 +1,155 @@
+//! Resource balances and mutual credit under `/api/v1/resources`
+//!
+//! - `GET /resources/{resource}/accounts/{account}` returns the account's
+//!   balance, the credit lines extended to it, and what it can still spend
+//!
+//! Credit limits themselves are only changed by executing a proposal whose
+//! logic runs `setcreditlimit`.
+
+use super::models::AccountStanding;
+use super::tenant::{self, ScopedVm};
+use super::{error_reply, JsonReply};
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use rust_decimal::Decimal;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// Route for GET /resources/{resource}/accounts/{account}
+pub fn resource_routes<S>(
+    vm: Arc<Mutex<VM<S>>>,
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    warp::path!("resources" / String / "accounts" / String)
+        .and(warp::get())
+        .and(auth)
+        .and(tenant::scoped_vm(vm))
+        .and_then(account_handler)
+}
+
+async fn account_handler<S>(
+    resource: String,
+    account: String,
+    auth: AuthContext,
+    vm: ScopedVm<S>,
+) -> Result<JsonReply, Rejection>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let vm_lock = vm.lock().await;
+    let namespace = vm_lock.get_namespace().unwrap_or("default").to_string();
+    let Some(storage) = vm_lock.get_storage_backend() else {
+        return Ok(error_reply(
+            "Storage not available",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    };
+
+    Ok(
+        match account_standing(storage, &auth, &namespace, &resource, &account) {
+            Ok(standing) => warp::reply::with_status(warp::reply::json(&standing), StatusCode::OK),
+            Err(StorageError::ResourceNotFound(_)) => error_reply(
+                format!("Resource '{}' not found", resource),
+                StatusCode::NOT_FOUND,
+            ),
+            Err(e @ StorageError::PermissionDenied { .. }) => {
+                error_reply(e.to_string(), StatusCode::FORBIDDEN)
+            }
+            Err(e) => error_reply(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR),
+        },
+    )
+}
+
+/// Read an account's balance and the credit extended to it
+fn account_standing<S: Storage>(
+    storage: &S,
+    auth: &AuthContext,
+    namespace: &str,
+    resource: &str,
+    account: &str,
+) -> StorageResult<AccountStanding> {
+    let (balance, _) = storage.get_balance(Some(auth), namespace, resource, account)?;
+    let credit_lines = storage.get_credit_lines(Some(auth), namespace, resource, account)?;
+    let credit_limit = credit_lines.iter().fold(Decimal::ZERO, |total, line| {
+        total.saturating_add(line.limit)
+    });
+
+    Ok(AccountStanding {
+        resource: resource.to_string(),
+        account: account.to_string(),
+        balance,
+        credit_limit,
+        available: balance.saturating_add(credit_limit),
+        credit_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use crate::storage::resource::{ResourcePolicy, COMMONS_CREDITOR};
+    use crate::storage::traits::EconomicOperations;
+
+    #[test]
+    fn test_transfers_draw_on_credit() {
+        let mut auth = AuthContext::new("did:key:admin");
+        auth.add_role("global", "admin");
+        let mut storage = InMemoryStorage::new();
+        let ns = "coops/alpha";
+        storage
+            .create_resource(Some(&auth), ns, "hours", &ResourcePolicy::default())
+            .unwrap();
+        storage
+            .set_credit_limit(
+                Some(&auth),
+                ns,
+                "hours",
+                COMMONS_CREDITOR,
+                "alice",
+                Decimal::from(20),
+                Some("p1"),
+            )
+            .unwrap();
+        storage
+            .set_credit_limit(
+                Some(&auth),
+                ns,
+                "hours",
+                "bob",
+                "alice",
+                Decimal::from(5),
+                Some("p2"),
+            )
+            .unwrap();
+
+        let transfer = |storage: &mut InMemoryStorage, amount: i64| {
+            storage.transfer(
+                Some(&auth),
+                ns,
+                "hours",
+                "alice",
+                "bob",
+                Decimal::from(amount),
+                "",
+            )
+        };
+        transfer(&mut storage, 15).unwrap();
+        assert!(transfer(&mut storage, 11).is_err());
+        transfer(&mut storage, 10).unwrap();
+
+        let standing = account_standing(&storage, &auth, ns, "hours", "alice").unwrap();
+        assert_eq!(standing.balance, Decimal::from(-25));
+        assert_eq!(standing.credit_limit, Decimal::from(25));
+        assert_eq!(standing.available, Decimal::ZERO);
+        assert_eq!(standing.credit_lines.len(), 2);
+    }
+}
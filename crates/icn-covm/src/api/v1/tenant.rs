@@ -0,0 +1,203 @@
+//! Per-cooperative routing under `/api/v1/coops/{coop}`
+//!
+//! Proposal, comment, and execution routes are mounted both at the API root
+//! and under `/api/v1/coops/{coop}/`. A request under a cooperative is bound
+//! to the namespace `coops/{coop}`:
+//!
+//! - its handler sees the VM switched to that namespace for as long as it
+//!   holds the lock, so proposals, comments, and programs read and write the
+//!   cooperative's data only
+//! - the caller must be a member of the cooperative, hold a role in it, or be
+//!   a global admin; anyone else is rejected with `TenantAccessDenied`
+//!
+//! Storage permission checks then apply the caller's roles in the
+//! cooperative's namespace, so one server can host several cooperatives.
+
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use warp::filters::path::FullPath;
+use warp::{Filter, Rejection};
+
+/// Path prefix of cooperative-scoped routes
+pub const COOP_PATH_PREFIX: &str = "/api/v1/coops/";
+
+/// Caller may not access the cooperative named in the path
+#[derive(Debug, thiserror::Error)]
+#[error("Not a member of namespace {namespace}")]
+pub struct TenantAccessDenied {
+    pub namespace: String,
+}
+
+impl warp::reject::Reject for TenantAccessDenied {}
+
+/// Storage namespace of the cooperative a request path is scoped to
+pub fn coop_namespace(path: &str) -> Option<String> {
+    path.strip_prefix(COOP_PATH_PREFIX)
+        .and_then(|rest| rest.split('/').next())
+        .filter(|coop| !coop.is_empty())
+        .map(|coop| format!("coops/{}", coop))
+}
+
+/// Whether `auth` may act within a cooperative's namespace
+pub(crate) fn can_access(auth: &AuthContext, namespace: &str) -> bool {
+    let did = auth.identity_did();
+    auth.has_role("global", "admin")
+        || auth.is_member(did, namespace)
+        || auth
+            .roles
+            .get(namespace)
+            .is_some_and(|roles| roles.values().any(|holders| holders.contains(did)))
+}
+
+/// VM handle bound to the namespace of one request
+#[derive(Debug)]
+pub struct ScopedVm<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    vm: Arc<Mutex<VM<S>>>,
+    namespace: Option<String>,
+}
+
+impl<S> Clone for ScopedVm<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            vm: self.vm.clone(),
+            namespace: self.namespace.clone(),
+        }
+    }
+}
+
+impl<S> ScopedVm<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    /// Bind `vm` to `namespace`, or leave it in its own namespace with `None`
+    pub fn new(vm: Arc<Mutex<VM<S>>>, namespace: Option<String>) -> Self {
+        Self { vm, namespace }
+    }
+
+    /// Namespace the request is bound to, if it is scoped to a cooperative
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Lock the VM, switching it to the request's namespace until the guard drops
+    pub async fn lock(&self) -> ScopedGuard<S> {
+        let mut guard = self.vm.clone().lock_owned().await;
+        let previous = self.namespace.as_ref().map(|namespace| {
+            let previous = guard.get_namespace().unwrap_or("default").to_string();
+            guard.set_namespace(namespace);
+            previous
+        });
+        ScopedGuard { guard, previous }
+    }
+}
+
+/// Locked VM that restores its namespace when dropped
+pub struct ScopedGuard<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    guard: OwnedMutexGuard<VM<S>>,
+    previous: Option<String>,
+}
+
+impl<S> Deref for ScopedGuard<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    type Target = VM<S>;
+
+    fn deref(&self) -> &VM<S> {
+        &self.guard
+    }
+}
+
+impl<S> DerefMut for ScopedGuard<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    fn deref_mut(&mut self) -> &mut VM<S> {
+        &mut self.guard
+    }
+}
+
+impl<S> Drop for ScopedGuard<S>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            self.guard.set_namespace(&previous);
+        }
+    }
+}
+
+/// Filter providing the VM bound to the cooperative in the request path
+pub fn scoped_vm<S>(
+    vm: Arc<Mutex<VM<S>>>,
+) -> impl Filter<Extract = (ScopedVm<S>,), Error = Infallible> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    warp::path::full().map(move |path: FullPath| ScopedVm {
+        vm: vm.clone(),
+        namespace: coop_namespace(path.as_str()),
+    })
+}
+
+/// Wrap an authentication filter so callers outside the cooperative in the
+/// request path are rejected
+pub fn with_tenant_access(
+    auth: impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+) -> impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone + Send + Sync + 'static {
+    auth.and(warp::path::full())
+        .and_then(|auth: AuthContext, path: FullPath| async move {
+            match coop_namespace(path.as_str()) {
+                Some(namespace) if !can_access(&auth, &namespace) => {
+                    Err(warp::reject::custom(TenantAccessDenied { namespace }))
+                }
+                _ => Ok(auth),
+            }
+        })
+}
+
+/// Map tenant rejections to 403 responses
+pub fn tenant_rejection_status(err: &Rejection) -> Option<(warp::http::StatusCode, String)> {
+    err.find::<TenantAccessDenied>()
+        .map(|e| (warp::http::StatusCode::FORBIDDEN, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coop_namespace_and_access() {
+        assert_eq!(
+            coop_namespace("/api/v1/coops/alpha/proposals").as_deref(),
+            Some("coops/alpha")
+        );
+        assert_eq!(coop_namespace("/api/v1/proposals"), None);
+        assert_eq!(coop_namespace("/api/v1/coops/"), None);
+
+        let mut auth = AuthContext::new("did:key:alice");
+        assert!(!can_access(&auth, "coops/alpha"));
+        auth.add_membership("did:key:alice", "coops/alpha");
+        assert!(can_access(&auth, "coops/alpha"));
+
+        auth.add_role("coops/beta", "reader");
+        assert!(can_access(&auth, "coops/beta"));
+        assert!(!can_access(&auth, "coops/gamma"));
+    }
+}
@@ -0,0 +1,351 @@
+//! WebSocket subscriptions at `/api/v1/ws` and `/api/v1/events/ws`
+//!
+//! On `/api/v1/ws`, clients send JSON control messages to choose which
+//! proposals they follow:
+//!
+//! ```json
+//! {"action": "subscribe", "proposal_ids": ["prop-1", "prop-2"]}
+//! {"action": "unsubscribe", "proposal_ids": ["prop-2"]}
+//! ```
+//!
+//! Subscribing to `"*"` follows every proposal, plus VM events that are not
+//! tied to one. Either way, clients only receive events from namespaces they
+//! can access as members or role holders.
+//!
+//! On `/api/v1/events/ws`, clients choose namespaces instead and receive the
+//! VM events and proposal lifecycle events (`proposal_created`, `vote_cast`
+//! and `proposal_executed`) that happen in them:
+//!
+//! ```json
+//! {"action": "subscribe", "namespaces": ["coops/alpha"]}
+//! ```
+//!
+//! Clients may only subscribe to namespaces they can read, in the sense of
+//! the ledger routes; `"*"` follows every namespace the client can read.
+//!
+//! Connections must authenticate with a bearer token or API key. Browser
+//! clients pass the token as the `access_token` query parameter. Matching
+//! `ApiEvent`s are pushed as JSON text frames, and refused control messages
+//! are answered with an `error` frame.
+
+use super::events::{ApiEvent, EventHub};
+use super::ledger::can_read;
+use super::tenant::can_access;
+use crate::api::auth::{with_auth, JwtConfig};
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use futures::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Mutex;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::{Filter, Rejection, Reply};
+
+/// Proposal ID that matches every event
+const ALL_PROPOSALS: &str = "*";
+
+/// Namespace that matches every namespace the client can read
+const ALL_NAMESPACES: &str = "*";
+
+/// Control message sent by a client of `/api/v1/ws`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe { proposal_ids: Vec<String> },
+    Unsubscribe { proposal_ids: Vec<String> },
+}
+
+/// Control message sent by a client of `/api/v1/events/ws`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum NamespaceMessage {
+    Subscribe { namespaces: Vec<String> },
+    Unsubscribe { namespaces: Vec<String> },
+}
+
+/// What a client has subscribed to, changed by its control messages
+trait Subscriptions: Send + 'static {
+    type Message: DeserializeOwned;
+
+    /// Apply a control message, or explain why it was refused
+    fn apply(&mut self, message: Self::Message) -> Result<(), String>;
+
+    /// Whether `event` should be delivered to the client
+    fn matches(&self, event: &ApiEvent) -> bool;
+}
+
+/// Proposals followed by a client of `/api/v1/ws`
+#[derive(Debug)]
+struct ProposalSubscriptions {
+    auth: AuthContext,
+    proposal_ids: HashSet<String>,
+}
+
+impl ProposalSubscriptions {
+    fn new(auth: AuthContext) -> Self {
+        Self {
+            auth,
+            proposal_ids: HashSet::new(),
+        }
+    }
+}
+
+impl Subscriptions for ProposalSubscriptions {
+    type Message = ClientMessage;
+
+    fn apply(&mut self, message: ClientMessage) -> Result<(), String> {
+        match message {
+            ClientMessage::Subscribe { proposal_ids } => self.proposal_ids.extend(proposal_ids),
+            ClientMessage::Unsubscribe { proposal_ids } => {
+                for id in &proposal_ids {
+                    self.proposal_ids.remove(id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn matches(&self, event: &ApiEvent) -> bool {
+        is_subscribed(&self.proposal_ids, event) && can_access(&self.auth, event.scope())
+    }
+}
+
+/// Namespaces followed by a client of `/api/v1/events/ws`
+#[derive(Debug)]
+struct NamespaceSubscriptions {
+    auth: AuthContext,
+    namespaces: HashSet<String>,
+}
+
+impl NamespaceSubscriptions {
+    fn new(auth: AuthContext) -> Self {
+        Self {
+            auth,
+            namespaces: HashSet::new(),
+        }
+    }
+}
+
+impl Subscriptions for NamespaceSubscriptions {
+    type Message = NamespaceMessage;
+
+    fn apply(&mut self, message: NamespaceMessage) -> Result<(), String> {
+        match message {
+            NamespaceMessage::Subscribe { namespaces } => {
+                // Refuse the whole message rather than subscribe to part of it
+                if let Some(namespace) = namespaces
+                    .iter()
+                    .find(|ns| *ns != ALL_NAMESPACES && !can_read(&self.auth, ns))
+                {
+                    return Err(format!("Not allowed to read namespace {}", namespace));
+                }
+                self.namespaces.extend(namespaces);
+            }
+            NamespaceMessage::Unsubscribe { namespaces } => {
+                for namespace in &namespaces {
+                    self.namespaces.remove(namespace);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn matches(&self, event: &ApiEvent) -> bool {
+        let Some(namespace) = event.namespace() else {
+            return false;
+        };
+        (self.namespaces.contains(ALL_NAMESPACES) || self.namespaces.contains(namespace))
+            && can_read(&self.auth, namespace)
+    }
+}
+
+/// Route for GET /api/v1/ws
+pub fn ws_route<S>(
+    hub: EventHub,
+    jwt: JwtConfig,
+    vm: Arc<Mutex<VM<S>>>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    warp::path("ws")
+        .and(warp::path::end())
+        .and(with_auth(jwt, vm))
+        .and(warp::ws())
+        .map(move |auth: AuthContext, ws: Ws| {
+            let hub = hub.clone();
+            ws.on_upgrade(move |socket| {
+                client_session(socket, hub, ProposalSubscriptions::new(auth))
+            })
+        })
+}
+
+/// Route for GET /api/v1/events/ws
+pub fn events_ws_route<S>(
+    hub: EventHub,
+    jwt: JwtConfig,
+    vm: Arc<Mutex<VM<S>>>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    warp::path("events")
+        .and(warp::path("ws"))
+        .and(warp::path::end())
+        .and(with_auth(jwt, vm))
+        .and(warp::ws())
+        .map(move |auth: AuthContext, ws: Ws| {
+            let hub = hub.clone();
+            ws.on_upgrade(move |socket| {
+                client_session(socket, hub, NamespaceSubscriptions::new(auth))
+            })
+        })
+}
+
+/// Whether an event should be delivered to a client with these subscriptions
+fn is_subscribed(subscriptions: &HashSet<String>, event: &ApiEvent) -> bool {
+    if subscriptions.contains(ALL_PROPOSALS) {
+        return true;
+    }
+    event
+        .proposal_id()
+        .map(|id| subscriptions.contains(id))
+        .unwrap_or(false)
+}
+
+/// Serve a single client until it disconnects
+async fn client_session<T: Subscriptions>(socket: WebSocket, hub: EventHub, mut subscriptions: T) {
+    let (mut outgoing, mut incoming) = socket.split();
+    let mut events = hub.subscribe();
+
+    loop {
+        tokio::select! {
+            message = incoming.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    _ => break,
+                };
+                if message.is_close() {
+                    break;
+                }
+                let Ok(text) = message.to_str() else {
+                    continue;
+                };
+                let refused = match serde_json::from_str::<T::Message>(text) {
+                    Ok(message) => subscriptions.apply(message).err(),
+                    Err(e) => Some(format!("Invalid message: {}", e)),
+                };
+                if let Some(message) = refused {
+                    let error = serde_json::json!({
+                        "type": "error",
+                        "message": message,
+                    });
+                    if outgoing.send(Message::text(error.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    // A slow client skips the events it missed rather than disconnecting
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                if !subscriptions.matches(&event) {
+                    continue;
+                }
+                let Ok(json) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if outgoing.send(Message::text(json)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote_cast(namespace: &str) -> ApiEvent {
+        ApiEvent::VoteCast {
+            proposal_id: "prop-1".to_string(),
+            namespace: namespace.to_string(),
+            voter: "alice".to_string(),
+            vote: 1.0,
+        }
+    }
+
+    fn subscribe(namespaces: &[&str]) -> NamespaceMessage {
+        NamespaceMessage::Subscribe {
+            namespaces: namespaces.iter().map(|ns| ns.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_namespace_subscriptions_only_cover_readable_namespaces() {
+        let mut auth = AuthContext::new("did:key:alice");
+        auth.add_role("coops/alpha", "reader");
+        auth.add_role("coops/gamma", "writer");
+        let mut subscriptions = NamespaceSubscriptions::new(auth);
+
+        assert!(subscriptions.apply(subscribe(&["coops/beta"])).is_err());
+        assert!(subscriptions
+            .apply(subscribe(&["coops/alpha", "coops/beta"]))
+            .is_err());
+        assert!(!subscriptions.matches(&vote_cast("coops/alpha")));
+
+        subscriptions.apply(subscribe(&["coops/alpha"])).unwrap();
+        assert!(subscriptions.matches(&vote_cast("coops/alpha")));
+        assert!(!subscriptions.matches(&vote_cast("coops/gamma")));
+        // Summaries without a namespace go to proposal subscribers only
+        assert!(!subscriptions.matches(&ApiEvent::ProposalState {
+            proposal_id: "prop-1".to_string(),
+            namespace: "coops/alpha".to_string(),
+            state: "Draft".to_string(),
+        }));
+
+        subscriptions.apply(subscribe(&["*"])).unwrap();
+        assert!(subscriptions.matches(&vote_cast("coops/gamma")));
+        assert!(!subscriptions.matches(&vote_cast("coops/beta")));
+
+        subscriptions
+            .apply(NamespaceMessage::Unsubscribe {
+                namespaces: vec!["*".to_string()],
+            })
+            .unwrap();
+        assert!(!subscriptions.matches(&vote_cast("coops/gamma")));
+        assert!(subscriptions.matches(&vote_cast("coops/alpha")));
+    }
+
+    #[test]
+    fn test_proposal_subscriptions_only_deliver_accessible_namespaces() {
+        let mut auth = AuthContext::new("did:key:alice");
+        auth.add_membership("did:key:alice", "coops/alpha");
+        let mut subscriptions = ProposalSubscriptions::new(auth);
+        subscriptions
+            .apply(ClientMessage::Subscribe {
+                proposal_ids: vec!["*".to_string()],
+            })
+            .unwrap();
+
+        assert!(subscriptions.matches(&vote_cast("coops/alpha")));
+        assert!(!subscriptions.matches(&vote_cast("coops/beta")));
+        assert!(!subscriptions.matches(&ApiEvent::VoteCounts {
+            proposal_id: "prop-1".to_string(),
+            namespace: "coops/beta".to_string(),
+            yes: 1,
+            no: 0,
+            abstain: 0,
+            total: 1,
+        }));
+    }
+}
@@ -0,0 +1,360 @@
+//! Append-only log of mutating actions
+//!
+//! Every mutating API request and CLI command is recorded as an
+//! `AuditEntry`: who did what, when, and whether it succeeded. Entries are
+//! written by the server itself to the `audit` namespace, keyed by time so
+//! they list in order, and are never changed afterwards. The only deletions
+//! are made by `prune`, which drops entries that fall outside the configured
+//! `RetentionPolicy`.
+//!
+//! [`chain`] keeps a separate, hash-chained log of the VM events raised
+//! while proposals execute.
+
+pub mod chain;
+
+use crate::storage::auth::{ensure_system_namespace, AuthContext};
+use crate::storage::errors::StorageResult;
+use crate::storage::traits::{StorageBackend, StorageExtensions};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Namespace holding the audit log
+pub const AUDIT_NAMESPACE: &str = "audit";
+
+/// Environment variable with the number of days entries are kept; `0` keeps them forever
+pub const RETENTION_DAYS_ENV: &str = "ICN_AUDIT_RETENTION_DAYS";
+
+/// Environment variable with the most entries kept; `0` keeps any number
+pub const MAX_ENTRIES_ENV: &str = "ICN_AUDIT_MAX_ENTRIES";
+
+/// Key prefix of log entries
+const ENTRY_PREFIX: &str = "entries/";
+
+/// Days entries are kept unless configured otherwise
+const DEFAULT_RETENTION_DAYS: i64 = 365;
+
+/// Where an audited action came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSource {
+    Api,
+    Cli,
+}
+
+/// Whether an audited action succeeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+/// One recorded action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    /// DID of the caller, or `anonymous` if they could not be identified
+    pub actor: String,
+    pub source: AuditSource,
+    /// e.g. `POST /api/v1/proposals` or `proposal vote`
+    pub action: String,
+    /// Namespace the action applied to, if known
+    pub namespace: Option<String>,
+    pub outcome: AuditOutcome,
+    /// HTTP status of an API request
+    pub status: Option<u16>,
+    /// Error message of a failed action
+    pub detail: Option<String>,
+}
+
+impl AuditEntry {
+    /// Entry for an action taken now
+    pub fn new(
+        source: AuditSource,
+        actor: impl Into<String>,
+        action: impl Into<String>,
+        outcome: AuditOutcome,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            actor: actor.into(),
+            source,
+            action: action.into(),
+            namespace: None,
+            outcome,
+            status: None,
+            detail: None,
+        }
+    }
+
+    fn key(&self) -> String {
+        format!(
+            "{}{:013}-{}",
+            ENTRY_PREFIX,
+            self.timestamp.timestamp_millis().max(0),
+            self.id
+        )
+    }
+}
+
+/// Filters for reading the audit log; unset fields match everything
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    /// Matches actions starting with this text
+    pub action: Option<String>,
+    pub namespace: Option<String>,
+    pub source: Option<AuditSource>,
+    pub outcome: Option<AuditOutcome>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Most entries to return; defaults to 100
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.actor.as_ref().is_none_or(|a| *a == entry.actor)
+            && self
+                .action
+                .as_ref()
+                .is_none_or(|a| entry.action.starts_with(a.as_str()))
+            && self
+                .namespace
+                .as_ref()
+                .is_none_or(|n| entry.namespace.as_ref() == Some(n))
+            && self.source.is_none_or(|s| s == entry.source)
+            && self.outcome.is_none_or(|o| o == entry.outcome)
+            && self.since.is_none_or(|t| entry.timestamp >= t)
+            && self.until.is_none_or(|t| entry.timestamp <= t)
+    }
+}
+
+/// How long entries are kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    /// Entries older than this are dropped
+    pub max_age: Option<Duration>,
+    /// Only this many of the newest entries are kept
+    pub max_entries: Option<usize>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age: Some(Duration::days(DEFAULT_RETENTION_DAYS)),
+            max_entries: None,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Read the policy from the environment, falling back to the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |var: &str| {
+            let value = std::env::var(var).ok()?;
+            let parsed = value.trim().parse::<u64>().ok();
+            if parsed.is_none() {
+                tracing::warn!("Ignoring invalid {}={:?}", var, value);
+            }
+            parsed
+        };
+        Self {
+            max_age: match read(RETENTION_DAYS_ENV) {
+                Some(0) => None,
+                Some(days) => Some(Duration::days(days as i64)),
+                None => defaults.max_age,
+            },
+            max_entries: match read(MAX_ENTRIES_ENV) {
+                Some(0) => None,
+                Some(entries) => Some(entries as usize),
+                None => defaults.max_entries,
+            },
+        }
+    }
+}
+
+/// Context the log is written and pruned with
+fn audit_auth() -> AuthContext {
+    let mut auth = AuthContext::new("system");
+    auth.add_role("global", "admin");
+    auth
+}
+
+/// Time in milliseconds encoded in an entry key
+fn key_millis(key: &str) -> Option<i64> {
+    key.strip_prefix(ENTRY_PREFIX)?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Entry keys, oldest first
+fn entry_keys<S: StorageBackend>(storage: &S) -> StorageResult<Vec<String>> {
+    let mut keys = storage.list_keys(Some(&audit_auth()), AUDIT_NAMESPACE, Some(ENTRY_PREFIX))?;
+    keys.sort();
+    Ok(keys)
+}
+
+/// Create the account the log is written as and the audit namespace,
+/// unless they already exist
+pub fn prepare<S: StorageBackend>(storage: &mut S) -> StorageResult<()> {
+    ensure_system_namespace(storage, &audit_auth(), AUDIT_NAMESPACE)
+}
+
+/// Add an entry to the log
+///
+/// The log must have been set up with `prepare` first.
+pub fn append<S: StorageBackend>(storage: &mut S, entry: &AuditEntry) -> StorageResult<()> {
+    storage.set_json(Some(&audit_auth()), AUDIT_NAMESPACE, &entry.key(), entry)
+}
+
+/// Entries matching `query`, newest first
+pub fn query<S: StorageBackend>(storage: &S, query: &AuditQuery) -> StorageResult<Vec<AuditEntry>> {
+    let limit = query.limit.unwrap_or(100);
+    let since = query.since.map(|t| t.timestamp_millis());
+    let auth = audit_auth();
+
+    let mut entries = Vec::new();
+    for key in entry_keys(storage)?.iter().rev() {
+        if entries.len() >= limit {
+            break;
+        }
+        // Keys are ordered by time, so nothing older can match
+        if since
+            .zip(key_millis(key))
+            .is_some_and(|(since, at)| at < since)
+        {
+            break;
+        }
+        match storage.get_json::<AuditEntry>(Some(&auth), AUDIT_NAMESPACE, key) {
+            Ok(entry) if query.matches(&entry) => entries.push(entry),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Skipping unreadable audit entry {}: {}", key, e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Drop entries outside `policy`, returning how many were removed
+pub fn prune<S: StorageBackend>(
+    storage: &mut S,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> StorageResult<usize> {
+    let keys = entry_keys(storage)?;
+    let cutoff = policy.max_age.map(|age| (now - age).timestamp_millis());
+    let excess = policy
+        .max_entries
+        .map_or(0, |max| keys.len().saturating_sub(max));
+
+    let auth = audit_auth();
+    let mut removed = 0;
+    for (i, key) in keys.iter().enumerate() {
+        let expired = cutoff
+            .zip(key_millis(key))
+            .is_some_and(|(cutoff, at)| at < cutoff);
+        if i >= excess && !expired {
+            // Later keys are newer, so they are within both limits
+            break;
+        }
+        storage.delete(Some(&auth), AUDIT_NAMESPACE, key)?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    fn storage() -> InMemoryStorage {
+        let mut storage = InMemoryStorage::new();
+        prepare(&mut storage).unwrap();
+        storage
+    }
+
+    fn entry(actor: &str, action: &str, age_days: i64) -> AuditEntry {
+        let mut entry = AuditEntry::new(AuditSource::Api, actor, action, AuditOutcome::Success);
+        entry.timestamp = Utc::now() - Duration::days(age_days);
+        entry
+    }
+
+    #[test]
+    fn test_query_filters_and_orders_newest_first() {
+        let mut storage = storage();
+        append(
+            &mut storage,
+            &entry("did:key:alice", "POST /api/v1/proposals", 3),
+        )
+        .unwrap();
+        append(
+            &mut storage,
+            &entry("did:key:bob", "POST /api/v1/proposals", 2),
+        )
+        .unwrap();
+        append(
+            &mut storage,
+            &entry("did:key:alice", "DELETE /api/v1/admin/x", 1),
+        )
+        .unwrap();
+
+        let all = query(&storage, &AuditQuery::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert!(all[0].timestamp > all[1].timestamp);
+
+        let alice = query(
+            &storage,
+            &AuditQuery {
+                actor: Some("did:key:alice".to_string()),
+                action: Some("POST".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(alice.len(), 1);
+        assert_eq!(alice[0].action, "POST /api/v1/proposals");
+    }
+
+    #[test]
+    fn test_prepare_sets_up_a_bare_backend() {
+        let mut storage = InMemoryStorage::new();
+        let entry = entry("did:key:alice", "POST /x", 0);
+        assert!(append(&mut storage, &entry).is_err());
+
+        prepare(&mut storage).unwrap();
+        prepare(&mut storage).unwrap();
+        append(&mut storage, &entry).unwrap();
+        let all = query(&storage, &AuditQuery::default()).unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].id, entry.id);
+    }
+
+    #[test]
+    fn test_prune_applies_age_and_count_limits() {
+        let mut storage = storage();
+        for age in [400, 30, 20, 10] {
+            append(&mut storage, &entry("did:key:alice", "POST /x", age)).unwrap();
+        }
+
+        let removed = prune(&mut storage, &RetentionPolicy::default(), Utc::now()).unwrap();
+        assert_eq!(removed, 1);
+
+        let policy = RetentionPolicy {
+            max_age: None,
+            max_entries: Some(2),
+        };
+        assert_eq!(prune(&mut storage, &policy, Utc::now()).unwrap(), 1);
+        let left = query(&storage, &AuditQuery::default()).unwrap();
+        assert_eq!(left.len(), 2);
+        assert!(left
+            .iter()
+            .all(|e| e.timestamp > Utc::now() - Duration::days(25)));
+    }
+}
@@ -0,0 +1,304 @@
+//! Hash-chained log of the VM events raised while proposals execute
+//!
+//! In audit mode, every `VMEvent` a proposal's logic emits is appended to a
+//! JSON-lines file as a [`ChainEntry`]. Each entry holds the hash of the one
+//! before it, and its own hash covers its contents and that link, so editing,
+//! removing or reordering any entry breaks every hash after it. [`verify`]
+//! walks the file and reports where the chain breaks.
+//!
+//! The log is kept apart from storage and the DAG ledger, so it can be
+//! checked, archived or shipped elsewhere on its own.
+
+use crate::events::Severity;
+use crate::vm::types::VMEvent;
+use fs2::FileExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// `prev_hash` of the first entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Errors reading or appending to an execution audit log
+#[derive(Debug, thiserror::Error)]
+pub enum ChainError {
+    #[error("Failed to access execution audit log {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Invalid entry on line {line} of execution audit log {path}: {details}")]
+    Format {
+        path: PathBuf,
+        line: usize,
+        details: String,
+    },
+}
+
+/// One VM event in the chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainEntry {
+    /// Position in the chain, from 0
+    pub seq: u64,
+    /// Proposal whose execution raised the event
+    pub proposal_id: String,
+    pub category: String,
+    pub message: String,
+    pub severity: Severity,
+    /// Unix time the VM raised the event
+    pub timestamp: u64,
+    /// `hash` of the previous entry, or [`GENESIS_HASH`]
+    pub prev_hash: String,
+    /// Hex SHA-256 over the fields above
+    pub hash: String,
+}
+
+/// The fields an entry's hash covers, in a fixed order
+#[derive(Serialize)]
+struct HashedFields<'a> {
+    seq: u64,
+    proposal_id: &'a str,
+    category: &'a str,
+    message: &'a str,
+    severity: Severity,
+    timestamp: u64,
+    prev_hash: &'a str,
+}
+
+impl ChainEntry {
+    fn new(seq: u64, proposal_id: &str, event: &VMEvent, prev_hash: &str) -> Self {
+        let mut entry = Self {
+            seq,
+            proposal_id: proposal_id.to_string(),
+            category: event.category.clone(),
+            message: event.message.clone(),
+            severity: event.severity,
+            timestamp: event.timestamp,
+            prev_hash: prev_hash.to_string(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+        entry
+    }
+
+    /// The hash the entry should have
+    pub fn compute_hash(&self) -> String {
+        let fields = HashedFields {
+            seq: self.seq,
+            proposal_id: &self.proposal_id,
+            category: &self.category,
+            message: &self.message,
+            severity: self.severity,
+            timestamp: self.timestamp,
+            prev_hash: &self.prev_hash,
+        };
+        let bytes = serde_json::to_vec(&fields).expect("hashed fields serialize");
+        hex::encode(Sha256::digest(bytes))
+    }
+}
+
+/// A place where the chain is broken
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChainIssue {
+    /// Line of the log, from 1
+    pub line: usize,
+    pub message: String,
+}
+
+/// Result of checking a log
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChainReport {
+    pub checked: usize,
+    /// Hash of the last entry; quoting it elsewhere pins the whole log
+    pub head: Option<String>,
+    pub issues: Vec<ChainIssue>,
+}
+
+impl ChainReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> ChainError + '_ {
+    move |source| ChainError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Read every entry, without checking the chain
+pub fn read_entries(path: &Path) -> Result<Vec<ChainEntry>, ChainError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_error(path)(e)),
+    };
+    let mut entries = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(io_error(path))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|e| ChainError::Format {
+            path: path.to_path_buf(),
+            line: index + 1,
+            details: e.to_string(),
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Append the events one execution of `proposal_id` raised, returning the
+/// new entries
+///
+/// The file is locked while the head is read and the entries written, so
+/// several processes can share one log.
+pub fn append(
+    path: &Path,
+    proposal_id: &str,
+    events: &[VMEvent],
+) -> Result<Vec<ChainEntry>, ChainError> {
+    if events.is_empty() {
+        return Ok(Vec::new());
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(io_error(parent))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(io_error(path))?;
+    // Held until the file is closed on return
+    file.lock_exclusive().map_err(io_error(path))?;
+
+    let (mut seq, mut prev_hash) = match read_entries(path)?.pop() {
+        Some(last) => (last.seq + 1, last.hash),
+        None => (0, GENESIS_HASH.to_string()),
+    };
+    let mut entries = Vec::with_capacity(events.len());
+    let mut lines = String::new();
+    for event in events {
+        let entry = ChainEntry::new(seq, proposal_id, event, &prev_hash);
+        lines.push_str(&serde_json::to_string(&entry).expect("chain entries serialize"));
+        lines.push('\n');
+        seq += 1;
+        prev_hash = entry.hash.clone();
+        entries.push(entry);
+    }
+    file.write_all(lines.as_bytes()).map_err(io_error(path))?;
+    file.sync_data().map_err(io_error(path))?;
+    Ok(entries)
+}
+
+/// Check every entry's hash, its link to the entry before it, and that
+/// sequence numbers run without gaps
+pub fn verify(path: &Path) -> Result<ChainReport, ChainError> {
+    let entries = read_entries(path)?;
+    let mut report = ChainReport::default();
+    let mut prev_hash = GENESIS_HASH.to_string();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let line = index + 1;
+        let mut issue = |message: String| report.issues.push(ChainIssue { line, message });
+        if entry.seq != index as u64 {
+            issue(format!(
+                "sequence number is {}, expected {}",
+                entry.seq, index
+            ));
+        }
+        if entry.prev_hash != prev_hash {
+            issue(format!(
+                "prev_hash {} does not match the hash of the previous entry",
+                entry.prev_hash
+            ));
+        }
+        if entry.compute_hash() != entry.hash {
+            issue("contents do not match the entry's hash".to_string());
+        }
+        prev_hash = entry.hash.clone();
+    }
+
+    report.checked = entries.len();
+    report.head = entries.last().map(|entry| entry.hash.clone());
+    Ok(report)
+}
+
+/// Log proposal executions are recorded to, if audit mode is on
+static EXECUTION_LOG: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+/// Turn audit mode on, recording to `path`, or off with `None`
+pub fn set_execution_log(path: Option<PathBuf>) {
+    if let Ok(mut log) = EXECUTION_LOG.lock() {
+        *log = path;
+    }
+}
+
+/// The log proposal executions are recorded to, if audit mode is on
+pub fn execution_log() -> Option<PathBuf> {
+    EXECUTION_LOG.lock().ok().and_then(|log| log.clone())
+}
+
+/// In audit mode, append the events one execution of `proposal_id` raised
+pub fn record_execution(proposal_id: &str, events: &[VMEvent]) -> Result<(), ChainError> {
+    match execution_log() {
+        Some(path) => append(&path, proposal_id, events).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(category: &str, message: &str) -> VMEvent {
+        VMEvent {
+            category: category.to_string(),
+            message: message.to_string(),
+            timestamp: 1_700_000_000,
+            severity: Severity::Info,
+        }
+    }
+
+    #[test]
+    fn test_chain_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("execution-audit.jsonl");
+
+        append(
+            &path,
+            "p1",
+            &[event("governance", "a"), event("economic", "b")],
+        )
+        .unwrap();
+        let appended = append(&path, "p2", &[event("governance", "c")]).unwrap();
+        assert_eq!(appended[0].seq, 2);
+
+        let report = verify(&path).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.head, Some(appended[0].hash.clone()));
+
+        // Rewriting a message breaks that entry's hash
+        let text = fs::read_to_string(&path).unwrap();
+        fs::write(
+            &path,
+            text.replacen("\"message\":\"b\"", "\"message\":\"B\"", 1),
+        )
+        .unwrap();
+        let report = verify(&path).unwrap();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].line, 2);
+
+        // Dropping an entry breaks the links after it
+        let lines: Vec<&str> = text.lines().collect();
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        let report = verify(&path).unwrap();
+        assert!(!report.is_valid());
+        assert!(report.issues.iter().all(|issue| issue.line == 2));
+    }
+}
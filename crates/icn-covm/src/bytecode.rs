@@ -12,6 +12,7 @@
 //! the nested AST representation into a flat, linear sequence of instructions.
 
 use crate::context::{OpExecutionContext, OpExecutor};
+use crate::events::Severity;
 use crate::federation::FederationName;
 use crate::identity::{IdentityId, IdentityName};
 use crate::resource::{ResourceId, ResourceName};
@@ -19,9 +20,14 @@ use crate::storage::auth::AuthContext;
 use crate::storage::error::{ResourceError, StorageError, VMError};
 use crate::storage::types::Key;
 use crate::storage::Storage;
+use crate::storage::resource::{
+    BountyVerification, EscrowOutcome, ResourcePolicy, SpendingAction, SpendingWindow,
+};
+use crate::typed::{TypedValueError, TypingMode};
 use crate::vm::types::{LoopControlType, OperandType, TypedValue};
 use crate::vm::vm::{LogLevel, VMStatus};
-use crate::vm::types::{CallFrame, LoopControl, Op, VMEvent};
+use crate::vm::types::{BountyStep, CallFrame, LoopControl, Op, VMEvent};
+use crate::vm::wasm::WasmCapability;
 use crate::vm::VM;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -58,8 +64,14 @@ pub enum BytecodeOp {
     /// Emit a message
     Emit(String),
 
-    /// Emit an event with category
-    EmitEvent(String, String),
+    /// Emit the top value as text
+    EmitTop,
+
+    /// Join the top two values into one string
+    Concat,
+
+    /// Emit an event with category and severity
+    EmitEvent(String, String, Severity),
 
     /// Call a function
     Call(String),
@@ -88,6 +100,9 @@ pub enum BytecodeOp {
     /// Ranked choice voting operation
     RankedVote(Vec<String>, Vec<Vec<usize>>),
 
+    /// Quadratic voting over ballots on the stack
+    QuadraticVote { candidates: usize, ballots: usize },
+
     /// Liquid democracy vote delegation
     LiquidDelegate(String, String),
 
@@ -130,6 +145,42 @@ pub enum BytecodeOp {
     /// Negate the top value on the stack
     Negate,
 
+    /// Encode the top value as a hex string
+    HexEncode,
+
+    /// Decode the hex string on top of the stack to bytes
+    HexDecode,
+
+    /// Encode the top value as a base64 string
+    Base64Encode,
+
+    /// Decode the base64 string on top of the stack to bytes
+    Base64Decode,
+
+    /// Replace the top value with its length
+    Length,
+
+    /// Replace the top value with whether it is Null
+    IsNull,
+
+    /// Pop a fallback, then replace the top value with it if the top value is Null
+    Coalesce,
+
+    /// Push the current time as a timestamp
+    Now,
+
+    /// Push the executing proposal's next random number below the operand
+    Random(u64),
+
+    /// Parse the top value as a timestamp
+    ParseTime,
+
+    /// Format the top timestamp as an RFC 3339 string
+    FormatTime,
+
+    /// Set how strictly the operations that follow check operand types
+    Typing(TypingMode),
+
     /// Logical AND of top two values on the stack
     And,
 
@@ -176,7 +227,10 @@ pub enum BytecodeOp {
     VerifySignature,
 
     /// Create a new economic resource
-    CreateResource(String),
+    CreateResource {
+        resource: String,
+        policy: ResourcePolicy,
+    },
 
     /// Mint new units of a resource and assign to an account
     Mint {
@@ -235,6 +289,231 @@ pub enum BytecodeOp {
         account: String,
     },
 
+    /// Set the credit one account, or the commons, extends to another
+    SetCreditLimit {
+        /// Resource identifier
+        resource: String,
+
+        /// Account extending the credit
+        creditor: String,
+
+        /// Account the credit is extended to
+        debtor: String,
+
+        /// New limit
+        limit: TypedValue,
+    },
+
+    /// Get how far below zero an account may go in a resource
+    CreditLimit {
+        /// Resource identifier
+        resource: String,
+
+        /// Account to check
+        account: String,
+    },
+
+    /// Let a spender transfer up to a limit out of the caller's account
+    Approve {
+        /// Resource identifier
+        resource: String,
+
+        /// Identity, role or proposal allowed to spend
+        spender: String,
+
+        /// Units the spender may transfer
+        limit: TypedValue,
+    },
+
+    /// Get how much a spender may still transfer out of an identity's account
+    Allowance {
+        /// Resource identifier
+        resource: String,
+
+        /// Identity that gave the allowance
+        owner: String,
+
+        /// Identity, role or proposal it was given to
+        spender: String,
+    },
+
+    /// Cap what an identity or role may transfer or mint within a window
+    SetSpendingLimit {
+        /// Resource identifier
+        resource: String,
+
+        /// Identity or role capped
+        subject: String,
+
+        /// Whether transfers or mints are capped
+        action: SpendingAction,
+
+        /// Window spending is counted over
+        window: SpendingWindow,
+
+        /// Most that may be spent within the window
+        limit: TypedValue,
+    },
+
+    /// Set the rate at which one resource exchanges into another
+    SetExchangeRate {
+        /// Resource paid in
+        from_resource: String,
+
+        /// Resource paid out
+        to_resource: String,
+
+        /// Units of `to_resource` paid for one of `from_resource`
+        rate: TypedValue,
+
+        /// Account exchanges go through
+        reserve: String,
+
+        /// Most of `from_resource` one exchange may convert
+        max_amount: Option<TypedValue>,
+    },
+
+    /// Convert units of one resource into another at the governed rate
+    Exchange {
+        /// Resource paid in
+        from_resource: String,
+
+        /// Resource paid out
+        to_resource: String,
+
+        /// Account exchanging
+        account: String,
+
+        /// Units of `from_resource` to convert
+        amount: TypedValue,
+
+        /// Fewest units of `to_resource` to accept
+        min_received: TypedValue,
+    },
+
+    /// Get the rate at which one resource exchanges into another
+    ExchangeRate {
+        /// Resource paid in
+        from_resource: String,
+
+        /// Resource paid out
+        to_resource: String,
+    },
+
+    /// Hold resource units under a proposal until it is decided
+    EscrowLock {
+        /// Proposal the amount is held under
+        proposal_id: String,
+
+        /// Resource identifier
+        resource: String,
+
+        /// Account the amount is taken from
+        from: String,
+
+        /// Account the amount is released to
+        to: String,
+
+        /// Amount to hold
+        amount: TypedValue,
+    },
+
+    /// Pay a proposal's locked escrows to their beneficiaries
+    EscrowRelease(String),
+
+    /// Return a proposal's locked escrows to their depositors
+    EscrowRefund(String),
+
+    /// Create a bounty funded from an account
+    CreateBounty {
+        /// Bounty identifier
+        id: String,
+
+        /// Resource the reward is paid in
+        resource: String,
+
+        /// Account the reward is taken from
+        funder: String,
+
+        /// Reward
+        amount: TypedValue,
+
+        /// Whether a verifier role or a vote accepts claims
+        verification: BountyVerification,
+
+        /// The work the bounty is for
+        description: String,
+    },
+
+    /// Claim an open bounty as the calling identity
+    ClaimBounty {
+        /// Bounty identifier
+        id: String,
+
+        /// Link to, or description of, the work done
+        evidence: String,
+    },
+
+    /// Approve or reject the pending claim on a bounty
+    VerifyBounty {
+        /// Bounty identifier
+        id: String,
+
+        /// Whether the claim is approved
+        approve: bool,
+    },
+
+    /// Withdraw an unclaimed bounty and refund its reward
+    CancelBounty(String),
+
+    /// Pop an amount and split it from a pool account by stored weights
+    Distribute {
+        /// Resource to distribute
+        resource: String,
+
+        /// Account the amount is paid from
+        pool_key: String,
+
+        /// Storage key prefix the weights are kept under
+        weight_key: String,
+    },
+
+    /// Approve a WASM module for calls
+    ApproveWasmModule {
+        /// SHA-256 hash of the module's blob
+        hash: String,
+
+        /// Host functions the module may call
+        capabilities: Vec<WasmCapability>,
+    },
+
+    /// Pop arguments and call a function of an approved WASM module
+    CallWasm {
+        /// SHA-256 hash of the module's blob
+        hash: String,
+
+        /// Exported function to call
+        function: String,
+
+        /// Number of arguments to pop
+        args: usize,
+    },
+
+    /// Admit a member to the cooperative
+    OnboardMember {
+        /// DID of the new member
+        did: String,
+
+        /// Public username recorded with the identity
+        username: String,
+
+        /// Roles granted in the namespace
+        roles: Vec<String>,
+    },
+
+    /// Retire a member of the cooperative
+    OffboardMember(String),
+
     /// Get identity operation
     GetIdentity(String),
 
@@ -470,6 +749,18 @@ impl BytecodeCompiler {
                 Op::Swap => self.program.instructions.push(BytecodeOp::Swap),
                 Op::Over => self.program.instructions.push(BytecodeOp::Return),
                 Op::Negate => self.program.instructions.push(BytecodeOp::Negate),
+                Op::HexEncode => self.program.instructions.push(BytecodeOp::HexEncode),
+                Op::HexDecode => self.program.instructions.push(BytecodeOp::HexDecode),
+                Op::Base64Encode => self.program.instructions.push(BytecodeOp::Base64Encode),
+                Op::Base64Decode => self.program.instructions.push(BytecodeOp::Base64Decode),
+                Op::Length => self.program.instructions.push(BytecodeOp::Length),
+                Op::IsNull => self.program.instructions.push(BytecodeOp::IsNull),
+                Op::Coalesce => self.program.instructions.push(BytecodeOp::Coalesce),
+                Op::Now => self.program.instructions.push(BytecodeOp::Now),
+                Op::Random { max } => self.program.instructions.push(BytecodeOp::Random(*max)),
+                Op::ParseTime => self.program.instructions.push(BytecodeOp::ParseTime),
+                Op::FormatTime => self.program.instructions.push(BytecodeOp::FormatTime),
+                Op::Typing(mode) => self.program.instructions.push(BytecodeOp::Typing(*mode)),
                 Op::Call(name) => self
                     .program
                     .instructions
@@ -482,10 +773,17 @@ impl BytecodeCompiler {
                     .program
                     .instructions
                     .push(BytecodeOp::Emit(msg.clone())),
-                Op::EmitEvent { category, message } => self
-                    .program
-                    .instructions
-                    .push(BytecodeOp::EmitEvent(category.clone(), message.clone())),
+                Op::EmitTop => self.program.instructions.push(BytecodeOp::EmitTop),
+                Op::Concat => self.program.instructions.push(BytecodeOp::Concat),
+                Op::EmitEvent {
+                    category,
+                    message,
+                    severity,
+                } => self.program.instructions.push(BytecodeOp::EmitEvent(
+                    category.clone(),
+                    message.clone(),
+                    *severity,
+                )),
                 Op::DumpStack => self.program.instructions.push(BytecodeOp::Return),
                 Op::DumpMemory => self.program.instructions.push(BytecodeOp::Return),
                 Op::DumpState => self.program.instructions.push(BytecodeOp::Return),
@@ -499,14 +797,18 @@ impl BytecodeCompiler {
                     .instructions
                     .push(BytecodeOp::AssertEqualStack(*depth)),
                 Op::Mod => self.program.instructions.push(BytecodeOp::Mod),
-                Op::RankedVote {
-                    candidates: _,
-                    ballots: _,
-                } => {
+                Op::RankedVote { .. } => {
                     // Skip for now until we implement RankedVote properly in BytecodeOp
                     // or convert the structure as needed
                     self.program.instructions.push(BytecodeOp::Return); // NOP for now
                 }
+                Op::QuadraticVote {
+                    candidates,
+                    ballots,
+                } => self.program.instructions.push(BytecodeOp::QuadraticVote {
+                    candidates: *candidates,
+                    ballots: *ballots,
+                }),
                 Op::StoreP(key) => self
                     .program
                     .instructions
@@ -586,10 +888,12 @@ impl BytecodeCompiler {
                 } => {
                     self.compile_match(value, cases, default);
                 }
-                Op::CreateResource(resource) => self
-                    .program
-                    .instructions
-                    .push(BytecodeOp::CreateResource(resource.clone())),
+                Op::CreateResource { resource, policy } => {
+                    self.program.instructions.push(BytecodeOp::CreateResource {
+                        resource: resource.clone(),
+                        policy: policy.clone(),
+                    })
+                }
                 Op::Mint {
                     resource,
                     account,
@@ -598,7 +902,7 @@ impl BytecodeCompiler {
                 } => self.program.instructions.push(BytecodeOp::Mint {
                     resource: resource.clone(),
                     account: account.clone(),
-                    amount: TypedValue::Number(*amount),
+                    amount: TypedValue::Decimal(*amount),
                     reason: reason.clone(),
                 }),
                 Op::Transfer {
@@ -611,7 +915,7 @@ impl BytecodeCompiler {
                     resource: resource.clone(),
                     from: from.clone(),
                     to: to.clone(),
-                    amount: TypedValue::Number(*amount),
+                    amount: TypedValue::Decimal(*amount),
                     reason: reason.clone(),
                 }),
                 Op::Burn {
@@ -622,7 +926,7 @@ impl BytecodeCompiler {
                 } => self.program.instructions.push(BytecodeOp::Burn {
                     resource: resource.clone(),
                     account: account.clone(),
-                    amount: TypedValue::Number(*amount),
+                    amount: TypedValue::Decimal(*amount),
                     reason: reason.clone(),
                 }),
                 Op::Balance { resource, account } => {
@@ -631,6 +935,175 @@ impl BytecodeCompiler {
                         account: account.clone(),
                     })
                 }
+                Op::SetCreditLimit {
+                    resource,
+                    creditor,
+                    debtor,
+                    limit,
+                } => self.program.instructions.push(BytecodeOp::SetCreditLimit {
+                    resource: resource.clone(),
+                    creditor: creditor.clone(),
+                    debtor: debtor.clone(),
+                    limit: TypedValue::Decimal(*limit),
+                }),
+                Op::CreditLimit { resource, account } => {
+                    self.program.instructions.push(BytecodeOp::CreditLimit {
+                        resource: resource.clone(),
+                        account: account.clone(),
+                    })
+                }
+                Op::Approve {
+                    resource,
+                    spender,
+                    limit,
+                } => self.program.instructions.push(BytecodeOp::Approve {
+                    resource: resource.clone(),
+                    spender: spender.clone(),
+                    limit: TypedValue::Decimal(*limit),
+                }),
+                Op::Allowance {
+                    resource,
+                    owner,
+                    spender,
+                } => self.program.instructions.push(BytecodeOp::Allowance {
+                    resource: resource.clone(),
+                    owner: owner.clone(),
+                    spender: spender.clone(),
+                }),
+                Op::SetSpendingLimit {
+                    resource,
+                    subject,
+                    action,
+                    window,
+                    limit,
+                } => self.program.instructions.push(BytecodeOp::SetSpendingLimit {
+                    resource: resource.clone(),
+                    subject: subject.clone(),
+                    action: *action,
+                    window: *window,
+                    limit: TypedValue::Decimal(*limit),
+                }),
+                Op::SetExchangeRate {
+                    from_resource,
+                    to_resource,
+                    rate,
+                    reserve,
+                    max_amount,
+                } => self.program.instructions.push(BytecodeOp::SetExchangeRate {
+                    from_resource: from_resource.clone(),
+                    to_resource: to_resource.clone(),
+                    rate: TypedValue::Decimal(*rate),
+                    reserve: reserve.clone(),
+                    max_amount: max_amount.map(TypedValue::Decimal),
+                }),
+                Op::Exchange {
+                    from_resource,
+                    to_resource,
+                    account,
+                    amount,
+                    min_received,
+                } => self.program.instructions.push(BytecodeOp::Exchange {
+                    from_resource: from_resource.clone(),
+                    to_resource: to_resource.clone(),
+                    account: account.clone(),
+                    amount: TypedValue::Decimal(*amount),
+                    min_received: TypedValue::Decimal(*min_received),
+                }),
+                Op::ExchangeRate {
+                    from_resource,
+                    to_resource,
+                } => self.program.instructions.push(BytecodeOp::ExchangeRate {
+                    from_resource: from_resource.clone(),
+                    to_resource: to_resource.clone(),
+                }),
+                Op::EscrowLock {
+                    proposal_id,
+                    resource,
+                    from,
+                    to,
+                    amount,
+                } => self.program.instructions.push(BytecodeOp::EscrowLock {
+                    proposal_id: proposal_id.clone(),
+                    resource: resource.clone(),
+                    from: from.clone(),
+                    to: to.clone(),
+                    amount: TypedValue::Decimal(*amount),
+                }),
+                Op::EscrowRelease(proposal_id) => self
+                    .program
+                    .instructions
+                    .push(BytecodeOp::EscrowRelease(proposal_id.clone())),
+                Op::EscrowRefund(proposal_id) => self
+                    .program
+                    .instructions
+                    .push(BytecodeOp::EscrowRefund(proposal_id.clone())),
+                Op::CreateBounty {
+                    id,
+                    resource,
+                    funder,
+                    amount,
+                    verification,
+                    description,
+                } => self.program.instructions.push(BytecodeOp::CreateBounty {
+                    id: id.clone(),
+                    resource: resource.clone(),
+                    funder: funder.clone(),
+                    amount: TypedValue::Decimal(*amount),
+                    verification: verification.clone(),
+                    description: description.clone(),
+                }),
+                Op::ClaimBounty { id, evidence } => {
+                    self.program.instructions.push(BytecodeOp::ClaimBounty {
+                        id: id.clone(),
+                        evidence: evidence.clone(),
+                    })
+                }
+                Op::VerifyBounty { id, approve } => {
+                    self.program.instructions.push(BytecodeOp::VerifyBounty {
+                        id: id.clone(),
+                        approve: *approve,
+                    })
+                }
+                Op::CancelBounty(id) => self
+                    .program
+                    .instructions
+                    .push(BytecodeOp::CancelBounty(id.clone())),
+                Op::Distribute {
+                    resource,
+                    pool_key,
+                    weight_key,
+                } => self.program.instructions.push(BytecodeOp::Distribute {
+                    resource: resource.clone(),
+                    pool_key: pool_key.clone(),
+                    weight_key: weight_key.clone(),
+                }),
+                Op::ApproveWasmModule { hash, capabilities } => {
+                    self.program.instructions.push(BytecodeOp::ApproveWasmModule {
+                        hash: hash.clone(),
+                        capabilities: capabilities.clone(),
+                    })
+                }
+                Op::CallWasm {
+                    hash,
+                    function,
+                    args,
+                } => self.program.instructions.push(BytecodeOp::CallWasm {
+                    hash: hash.clone(),
+                    function: function.clone(),
+                    args: *args,
+                }),
+                Op::OnboardMember {
+                    did,
+                    username,
+                    roles,
+                } => self.program.instructions.push(BytecodeOp::OnboardMember {
+                    did: did.clone(),
+                    username: username.clone(),
+                    roles: roles.clone(),
+                }),
+                Op::OffboardMember(did) => {
+                    self.program.instructions.push(BytecodeOp::OffboardMember(did.clone()))
+                }
                 Op::VerifySignature => self.program.instructions.push(BytecodeOp::VerifySignature),
                 Op::GetIdentity(identity_id) => {
                     self.program.instructions.push(BytecodeOp::GetIdentity(identity_id.clone()));
@@ -1046,8 +1519,22 @@ where
                 self.pc += 1;
                 Ok(())
             }
-            BytecodeOp::EmitEvent(category, message) => {
-                self.vm.executor.emit_event(category, message);
+            BytecodeOp::EmitTop => {
+                let value = self.vm.stack.pop("EmitTop")?;
+                self.vm.executor.emit(&value.as_string()?);
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::Concat => {
+                let (a, b) = self.vm.stack.pop_two("Concat")?;
+                self.vm.stack.push(a.concat(&b)?);
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::EmitEvent(category, message, severity) => {
+                self.vm
+                    .executor
+                    .emit_event_with_severity(category, message, *severity);
                 self.pc += 1;
                 Ok(())
             }
@@ -1134,6 +1621,42 @@ where
                 self.pc += 1;
                 Ok(())
             }
+            BytecodeOp::HexEncode => self.replace_top("HexEncode", TypedValue::hex_encode),
+            BytecodeOp::HexDecode => self.replace_top("HexDecode", TypedValue::hex_decode),
+            BytecodeOp::Base64Encode => self.replace_top("Base64Encode", TypedValue::base64_encode),
+            BytecodeOp::Base64Decode => self.replace_top("Base64Decode", TypedValue::base64_decode),
+            BytecodeOp::Length => self.replace_top("Length", TypedValue::length),
+            BytecodeOp::IsNull => {
+                let value = self.vm.stack.pop("IsNull")?;
+                self.vm.stack.push(TypedValue::Boolean(value.is_null()));
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::Coalesce => {
+                let (value, fallback) = self.vm.stack.pop_two("Coalesce")?;
+                self.vm.stack.push(if value.is_null() { fallback } else { value });
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::Now => {
+                let now = self.vm.clock().now();
+                self.vm.stack.push(TypedValue::Timestamp(now));
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::Random(max) => {
+                let value = self.vm.executor.draw_random(*max)?;
+                self.vm.stack.push(TypedValue::Number(value as f64));
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::ParseTime => self.replace_top("ParseTime", TypedValue::parse_time),
+            BytecodeOp::FormatTime => self.replace_top("FormatTime", TypedValue::format_time),
+            BytecodeOp::Typing(mode) => {
+                self.vm.executor.set_typing_mode(*mode);
+                self.pc += 1;
+                Ok(())
+            }
             BytecodeOp::And => {
                 let (a, b) = self.vm.stack.pop_two("And")?;
                 let result = self.vm.executor.execute_binary_logical(&a, &b, "and")?;
@@ -1162,8 +1685,8 @@ where
                 self.pc += 1;
                 Ok(())
             }
-            BytecodeOp::CreateResource(resource) => {
-                self.vm.executor.execute_create_resource(resource)?;
+            BytecodeOp::CreateResource { resource, policy } => {
+                self.vm.executor.execute_create_resource(resource, policy)?;
                 self.pc += 1;
                 Ok(())
             }
@@ -1206,7 +1729,232 @@ where
             }
             BytecodeOp::Balance { resource, account } => {
                 let balance = self.vm.executor.execute_balance(resource, account)?;
-                self.vm.stack.push(TypedValue::Number(balance));
+                self.vm.stack.push(balance);
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::SetCreditLimit {
+                resource,
+                creditor,
+                debtor,
+                limit,
+            } => {
+                self.vm
+                    .executor
+                    .execute_set_credit_limit(resource, creditor, debtor, limit)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::CreditLimit { resource, account } => {
+                let limit = self.vm.executor.execute_credit_limit(resource, account)?;
+                self.vm.stack.push(limit);
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::Approve {
+                resource,
+                spender,
+                limit,
+            } => {
+                self.vm.executor.execute_approve(resource, spender, limit)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::Allowance {
+                resource,
+                owner,
+                spender,
+            } => {
+                let allowance = self.vm.executor.execute_allowance(resource, owner, spender)?;
+                self.vm.stack.push(allowance);
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::SetSpendingLimit {
+                resource,
+                subject,
+                action,
+                window,
+                limit,
+            } => {
+                self.vm
+                    .executor
+                    .execute_set_spending_limit(resource, subject, *action, *window, limit)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::SetExchangeRate {
+                from_resource,
+                to_resource,
+                rate,
+                reserve,
+                max_amount,
+            } => {
+                self.vm.executor.execute_set_exchange_rate(
+                    from_resource,
+                    to_resource,
+                    rate,
+                    reserve,
+                    max_amount.as_ref(),
+                )?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::Exchange {
+                from_resource,
+                to_resource,
+                account,
+                amount,
+                min_received,
+            } => {
+                let received = self.vm.executor.execute_exchange(
+                    from_resource,
+                    to_resource,
+                    account,
+                    amount,
+                    min_received,
+                )?;
+                self.vm.stack.push(received);
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::ExchangeRate {
+                from_resource,
+                to_resource,
+            } => {
+                let rate = self
+                    .vm
+                    .executor
+                    .execute_exchange_rate(from_resource, to_resource)?;
+                self.vm.stack.push(rate);
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::EscrowLock {
+                proposal_id,
+                resource,
+                from,
+                to,
+                amount,
+            } => {
+                self.vm
+                    .executor
+                    .execute_escrow_lock(proposal_id, resource, from, to, amount)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::EscrowRelease(proposal_id) => {
+                self.vm
+                    .executor
+                    .execute_escrow_settle(proposal_id, EscrowOutcome::Release)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::EscrowRefund(proposal_id) => {
+                self.vm
+                    .executor
+                    .execute_escrow_settle(proposal_id, EscrowOutcome::Refund)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::CreateBounty {
+                id,
+                resource,
+                funder,
+                amount,
+                verification,
+                description,
+            } => {
+                let bounty = self.vm.executor.execute_create_bounty(
+                    id,
+                    resource,
+                    funder,
+                    amount,
+                    verification,
+                    description,
+                )?;
+                self.vm.record_bounty_step(BountyStep::Created, &bounty)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::ClaimBounty { id, evidence } => {
+                let bounty = self.vm.executor.execute_claim_bounty(id, evidence)?;
+                self.vm.record_bounty_step(BountyStep::Claimed, &bounty)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::VerifyBounty { id, approve } => {
+                let bounty = self.vm.executor.execute_verify_bounty(id, *approve)?;
+                let step = BountyStep::Verified { approved: *approve };
+                self.vm.record_bounty_step(step, &bounty)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::CancelBounty(id) => {
+                let bounty = self.vm.executor.execute_cancel_bounty(id)?;
+                self.vm.record_bounty_step(BountyStep::Cancelled, &bounty)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::Distribute {
+                resource,
+                pool_key,
+                weight_key,
+            } => {
+                let amount = self.vm.stack.pop("Distribute")?;
+                let distribution =
+                    self.vm.executor.execute_distribute(resource, pool_key, weight_key, &amount)?;
+                self.vm.record_distribution(&distribution)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::QuadraticVote {
+                candidates,
+                ballots,
+            } => {
+                let op = Op::QuadraticVote {
+                    candidates: *candidates,
+                    ballots: *ballots,
+                };
+                crate::governance::try_handle_governance_op(&mut self.vm, &op)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::ApproveWasmModule { hash, capabilities } => {
+                self.vm
+                    .executor
+                    .execute_approve_wasm_module(hash, capabilities)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::CallWasm {
+                hash,
+                function,
+                args,
+            } => {
+                let mut values = Vec::with_capacity(*args);
+                for _ in 0..*args {
+                    values.push(self.vm.stack.pop_number("CallWasm")?);
+                }
+                values.reverse();
+                let result = self.vm.executor.execute_call_wasm(hash, function, &values)?;
+                self.vm.stack.push(result);
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::OnboardMember {
+                did,
+                username,
+                roles,
+            } => {
+                self.vm
+                    .executor
+                    .execute_onboard_member(did, username, roles)?;
+                self.pc += 1;
+                Ok(())
+            }
+            BytecodeOp::OffboardMember(did) => {
+                self.vm.executor.execute_offboard_member(did)?;
                 self.pc += 1;
                 Ok(())
             }
@@ -1292,6 +2040,18 @@ where
         }
     }
 
+    /// Replace the top of the stack with `f` applied to it
+    fn replace_top(
+        &mut self,
+        op_name: &str,
+        f: fn(&TypedValue) -> Result<TypedValue, TypedValueError>,
+    ) -> Result<(), VMError> {
+        let value = self.vm.stack.pop(op_name)?;
+        self.vm.stack.push(f(&value)?);
+        self.pc += 1;
+        Ok(())
+    }
+
     /// Get the current VM
     pub fn get_vm(&self) -> &VM<S> {
         &self.vm
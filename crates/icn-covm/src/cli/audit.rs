@@ -0,0 +1,120 @@
+//! Execution audit log commands
+//!
+//! `audit verify` checks the hash chain of the log that audit mode
+//! (`audit.execution_log`) records proposal executions to, and
+//! `audit events` lists what it holds.
+
+use crate::audit::chain::{self, ChainEntry, ChainReport};
+use crate::cli::output::print_output;
+use crate::config::Config;
+use chrono::{DateTime, Utc};
+use clap::{Arg, ArgMatches, Command};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Create the audit command and its subcommands
+pub fn audit_command() -> Command {
+    Command::new("audit")
+        .about("Inspect the hash-chained log of proposal execution events")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("FILE")
+                .help("Execution audit log (default: audit.execution_log)")
+                .global(true),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check every entry's hash and its link to the entry before it"),
+        )
+        .subcommand(
+            Command::new("events")
+                .about("List the recorded events")
+                .arg(
+                    Arg::new("proposal")
+                        .long("proposal")
+                        .value_name("ID")
+                        .help("Only events raised while this proposal executed"),
+                ),
+        )
+}
+
+/// Handle `audit verify` and `audit events`
+pub fn handle_audit_command(matches: &ArgMatches, config: &Config) -> Result<(), Box<dyn Error>> {
+    let path = matches
+        .get_one::<String>("path")
+        .map(PathBuf::from)
+        .or_else(|| config.audit.execution_log.clone())
+        .ok_or("No execution audit log: pass --path or set audit.execution_log")?;
+
+    match matches.subcommand() {
+        Some(("verify", _)) => handle_verify_command(&path),
+        Some(("events", events_matches)) => {
+            let proposal = events_matches.get_one::<String>("proposal");
+            let entries: Vec<ChainEntry> = chain::read_entries(&path)?
+                .into_iter()
+                .filter(|entry| proposal.is_none_or(|id| &entry.proposal_id == id))
+                .collect();
+            print_output(&entries, |entries| print_entries(entries, &path))?;
+            Ok(())
+        }
+        _ => Err("Unknown audit subcommand".into()),
+    }
+}
+
+/// Check the log's hash chain
+///
+/// Fails when the chain is broken so scripts can detect tampering.
+pub fn handle_verify_command(path: &Path) -> Result<(), Box<dyn Error>> {
+    if !path.exists() {
+        return Err(format!("Execution audit log {} does not exist", path.display()).into());
+    }
+    let report = chain::verify(path)?;
+    print_output(&report, |report| print_report(report, path))?;
+
+    if report.is_valid() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Execution audit log has {} integrity issue(s)",
+            report.issues.len()
+        )
+        .into())
+    }
+}
+
+fn print_report(report: &ChainReport, path: &Path) {
+    println!("🔍 Execution Audit Verification: {}", path.display());
+    println!("   Entries checked: {}", report.checked);
+    if let Some(head) = &report.head {
+        println!("   Head hash: {}", head);
+    }
+    if report.is_valid() {
+        println!("   ✅ Chain intact");
+        return;
+    }
+
+    println!("\n⚠️  Issues:");
+    for issue in &report.issues {
+        println!("   Line {}: {}", issue.line, issue.message);
+    }
+}
+
+fn print_entries(entries: &[ChainEntry], path: &Path) {
+    println!("📜 Execution Audit Log: {}", path.display());
+    if entries.is_empty() {
+        println!("   No events recorded");
+        return;
+    }
+    for entry in entries {
+        let time = DateTime::<Utc>::from_timestamp(entry.timestamp as i64, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| entry.timestamp.to_string());
+        println!(
+            "   #{} {} [{}] [{}] {}: {}",
+            entry.seq, time, entry.severity, entry.category, entry.proposal_id, entry.message
+        );
+    }
+}
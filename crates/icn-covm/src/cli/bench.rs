@@ -0,0 +1,529 @@
+//! Execution benchmarks
+//!
+//! `icn-covm bench` runs a program through each execution mode, after a few
+//! warmup runs, for a fixed number of timed iterations, and reports the
+//! mean, median and 95th percentile of each with a 95% confidence interval
+//! for the mean:
+//!
+//! ```bash
+//! icn-covm bench --program demo/loop.dsl --iterations 50
+//! icn-covm --output json bench --program demo/loop.dsl > bench.json
+//! icn-covm bench --program demo/loop.dsl --baseline bench.json --max-regression 10
+//! ```
+//!
+//! Each iteration starts from a fresh VM, and only execution is timed; for
+//! bytecode, compilation is timed separately. With `--baseline`, the means
+//! are compared with an earlier JSON report and the command fails if a mode
+//! got slower by more than `--max-regression` percent.
+//!
+//! `--micro` runs the library's micro-benchmarks instead of a program, and
+//! `--profile` runs a DSL program once and reports the time spent in each op
+//! type and on each line; see [`crate::perf`]:
+//!
+//! ```bash
+//! icn-covm bench --micro --iterations 50
+//! icn-covm bench --program demo/loop.dsl --profile
+//! ```
+
+use crate::bytecode::{BytecodeCompiler, BytecodeInterpreter};
+use crate::cli::output::print_output;
+use crate::compiler::{parse_dsl, parse_dsl_with_stdlib};
+use crate::perf::{self, MicroBenchmark, MicroOptions, ProfileReport};
+use crate::storage::implementations::in_memory::InMemoryStorage;
+use crate::vm::{Op, VM};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub use crate::perf::BenchStats;
+
+/// A way of executing a program
+///
+/// New execution strategies, such as optimized bytecode, are benchmarked
+/// by adding a variant here and a case to [`run_once`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BenchMode {
+    /// The tree-walking interpreter
+    Ast,
+    /// Bytecode compilation and the bytecode interpreter
+    Bytecode,
+}
+
+impl BenchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BenchMode::Ast => "ast",
+            BenchMode::Bytecode => "bytecode",
+        }
+    }
+}
+
+/// Timings of one execution mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModeReport {
+    pub mode: String,
+    /// Compilation time, for modes that compile first
+    pub compile: Option<BenchStats>,
+    pub execute: BenchStats,
+}
+
+/// A mode's mean compared with a baseline report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineComparison {
+    pub mode: String,
+    pub baseline_mean_us: f64,
+    pub mean_us: f64,
+    /// Change in mean execution time, in percent; positive is slower
+    pub change_pct: f64,
+    pub regressed: bool,
+}
+
+/// Result of `icn-covm bench`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub program: String,
+    pub operations: usize,
+    pub warmup: usize,
+    pub iterations: usize,
+    pub modes: Vec<ModeReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub baseline: Vec<BaselineComparison>,
+}
+
+/// Settings for a benchmark run
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub modes: Vec<BenchMode>,
+    pub warmup: usize,
+    pub iterations: usize,
+    pub parameters: HashMap<String, String>,
+}
+
+pub fn bench_command() -> Command {
+    Command::new("bench")
+        .about("Benchmark a program in each execution mode")
+        .arg(
+            Arg::new("program")
+                .short('p')
+                .long("program")
+                .value_name("FILE")
+                .help("Program file to benchmark (.dsl or .json)")
+                .required_unless_present("micro"),
+        )
+        .arg(
+            Arg::new("micro")
+                .long("micro")
+                .help("Run the built-in micro-benchmarks instead of a program")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["program", "profile", "baseline"]),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .help("Run a .dsl program once and report time per op and per line")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["stdlib", "baseline", "mode"]),
+        )
+        .arg(
+            Arg::new("stdlib")
+                .long("stdlib")
+                .help("Include standard library functions")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("param")
+                .short('P')
+                .long("param")
+                .value_name("KEY=VALUE")
+                .help("Set a key-value parameter for the program (can be used multiple times)")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("mode")
+                .long("mode")
+                .value_name("MODE")
+                .help("Execution mode to benchmark (can be used multiple times; default: all)")
+                .value_parser(value_parser!(BenchMode))
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("warmup")
+                .long("warmup")
+                .value_name("COUNT")
+                .help("Untimed runs before measuring each mode")
+                .value_parser(value_parser!(usize))
+                .default_value("3"),
+        )
+        .arg(
+            Arg::new("iterations")
+                .short('n')
+                .long("iterations")
+                .value_name("COUNT")
+                .help("Timed runs of each mode")
+                .value_parser(value_parser!(u64).range(1..))
+                .default_value("20"),
+        )
+        .arg(
+            Arg::new("baseline")
+                .long("baseline")
+                .value_name("FILE")
+                .help("JSON report from an earlier run to compare against"),
+        )
+        .arg(
+            Arg::new("max-regression")
+                .long("max-regression")
+                .value_name("PERCENT")
+                .help("Fail if a mode's mean is this much slower than the baseline")
+                .value_parser(value_parser!(f64))
+                .default_value("10")
+                .requires("baseline"),
+        )
+}
+
+/// Parse a `.dsl` or `.json` program file
+pub fn load_program(path: &Path, use_stdlib: bool) -> Result<Vec<Op>, Box<dyn Error>> {
+    if !path.exists() {
+        return Err(format!("Program file not found: {}", path.display()).into());
+    }
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .ok_or("File has no extension")?;
+    let source = fs::read_to_string(path)?;
+    match extension.to_lowercase().as_str() {
+        "dsl" if use_stdlib => Ok(parse_dsl_with_stdlib(&source)?),
+        "dsl" => Ok(parse_dsl(&source)?.0),
+        "json" => Ok(serde_json::from_str(&source)?),
+        _ => Err(format!("Unsupported file extension: {}", extension).into()),
+    }
+}
+
+/// Time one run of `ops` in `mode`, returning compilation and execution time
+fn run_once(
+    mode: BenchMode,
+    ops: &[Op],
+    parameters: &HashMap<String, String>,
+) -> Result<(Option<Duration>, Duration), Box<dyn Error>> {
+    match mode {
+        BenchMode::Ast => {
+            let mut vm: VM<InMemoryStorage> = VM::new();
+            vm.set_parameters(parameters.clone())?;
+            let start = Instant::now();
+            vm.execute(ops)?;
+            Ok((None, start.elapsed()))
+        }
+        BenchMode::Bytecode => {
+            let start = Instant::now();
+            let program = BytecodeCompiler::new().compile(ops);
+            let compile = start.elapsed();
+
+            let mut interpreter = BytecodeInterpreter::new(VM::<InMemoryStorage>::new(), program);
+            interpreter
+                .get_vm_mut()
+                .set_parameters(parameters.clone())?;
+            let start = Instant::now();
+            interpreter.execute()?;
+            Ok((Some(compile), start.elapsed()))
+        }
+    }
+}
+
+/// Benchmark `ops` in each of the requested modes
+pub fn run_bench(
+    program: &str,
+    ops: &[Op],
+    options: &BenchOptions,
+) -> Result<BenchReport, Box<dyn Error>> {
+    let mut modes = Vec::new();
+    for &mode in &options.modes {
+        for _ in 0..options.warmup {
+            run_once(mode, ops, &options.parameters)
+                .map_err(|e| format!("{} run failed: {}", mode.as_str(), e))?;
+        }
+
+        let mut compile = Vec::new();
+        let mut execute = Vec::with_capacity(options.iterations);
+        for _ in 0..options.iterations {
+            let (compile_time, execute_time) = run_once(mode, ops, &options.parameters)
+                .map_err(|e| format!("{} run failed: {}", mode.as_str(), e))?;
+            compile.extend(compile_time);
+            execute.push(execute_time);
+        }
+
+        modes.push(ModeReport {
+            mode: mode.as_str().to_string(),
+            compile: (!compile.is_empty()).then(|| BenchStats::from_samples(&compile)),
+            execute: BenchStats::from_samples(&execute),
+        });
+    }
+
+    Ok(BenchReport {
+        program: program.to_string(),
+        operations: ops.len(),
+        warmup: options.warmup,
+        iterations: options.iterations,
+        modes,
+        baseline: Vec::new(),
+    })
+}
+
+/// Compare each mode's mean execution time with the same mode in `baseline`
+///
+/// A mode regresses when its mean is more than `max_regression` percent
+/// above the baseline mean.
+pub fn compare_with_baseline(
+    report: &BenchReport,
+    baseline: &BenchReport,
+    max_regression: f64,
+) -> Vec<BaselineComparison> {
+    report
+        .modes
+        .iter()
+        .filter_map(|current| {
+            let before = baseline.modes.iter().find(|m| m.mode == current.mode)?;
+            let change_pct = if before.execute.mean_us > 0.0 {
+                (current.execute.mean_us - before.execute.mean_us) / before.execute.mean_us * 100.0
+            } else {
+                0.0
+            };
+            Some(BaselineComparison {
+                mode: current.mode.clone(),
+                baseline_mean_us: before.execute.mean_us,
+                mean_us: current.execute.mean_us,
+                change_pct,
+                regressed: change_pct > max_regression,
+            })
+        })
+        .collect()
+}
+
+fn parse_params(matches: &ArgMatches) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let mut parameters = HashMap::new();
+    for param in matches.get_many::<String>("param").into_iter().flatten() {
+        let (key, value) = param
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid parameter format: {}. Expected KEY=VALUE", param))?;
+        parameters.insert(key.to_string(), value.to_string());
+    }
+    Ok(parameters)
+}
+
+fn format_us(us: f64) -> String {
+    if us >= 1_000_000.0 {
+        format!("{:.2}s", us / 1_000_000.0)
+    } else if us >= 1_000.0 {
+        format!("{:.2}ms", us / 1_000.0)
+    } else {
+        format!("{:.1}µs", us)
+    }
+}
+
+fn print_stats(label: &str, stats: &BenchStats) {
+    println!(
+        "   {:<9} mean {} (95% CI {} – {})  median {}  p95 {}  σ {}",
+        label,
+        format_us(stats.mean_us),
+        format_us(stats.ci95_low_us),
+        format_us(stats.ci95_high_us),
+        format_us(stats.median_us),
+        format_us(stats.p95_us),
+        format_us(stats.stddev_us),
+    );
+}
+
+pub fn print_report(report: &BenchReport) {
+    println!(
+        "⏱️  Benchmark: {} ({} operations, {} warmup, {} iterations)",
+        report.program, report.operations, report.warmup, report.iterations
+    );
+    for mode in &report.modes {
+        println!("\n{}", mode.mode);
+        if let Some(compile) = &mode.compile {
+            print_stats("compile", compile);
+        }
+        print_stats("execute", &mode.execute);
+    }
+
+    let ast = report
+        .modes
+        .iter()
+        .find(|m| m.mode == BenchMode::Ast.as_str());
+    if let Some(ast) = ast {
+        for mode in report.modes.iter().filter(|m| m.mode != ast.mode) {
+            if mode.execute.mean_us > 0.0 {
+                println!(
+                    "\n{} execution is {:.2}x the speed of ast",
+                    mode.mode,
+                    ast.execute.mean_us / mode.execute.mean_us
+                );
+            }
+        }
+    }
+
+    if !report.baseline.is_empty() {
+        println!("\nCompared with baseline:");
+        for comparison in &report.baseline {
+            println!(
+                "   {} {:<9} {} → {} ({:+.1}%)",
+                if comparison.regressed { "❌" } else { "✅" },
+                comparison.mode,
+                format_us(comparison.baseline_mean_us),
+                format_us(comparison.mean_us),
+                comparison.change_pct
+            );
+        }
+    }
+}
+
+/// Number of lines shown by [`print_profile`]
+const HOTTEST_LINES: usize = 10;
+
+pub fn print_micro(results: &[MicroBenchmark]) {
+    println!("⏱️  Micro-benchmarks");
+    for result in results {
+        println!(
+            "\n{} ({} {}s per run, {:.0} per second)",
+            result.name, result.units_per_run, result.unit, result.per_second
+        );
+        print_stats("run", &result.stats);
+    }
+}
+
+pub fn print_profile(report: &ProfileReport) {
+    println!("⏱️  Profile ({} total)", format_us(report.total_us));
+    println!(
+        "\n   {:<20} {:>8} {:>12} {:>12}",
+        "op", "count", "self", "total"
+    );
+    for op in &report.ops {
+        println!(
+            "   {:<20} {:>8} {:>12} {:>12}",
+            op.op,
+            op.count,
+            format_us(op.self_us),
+            format_us(op.total_us)
+        );
+    }
+    if !report.lines.is_empty() {
+        println!("\nHottest lines:");
+        for line in report.lines.iter().take(HOTTEST_LINES) {
+            println!(
+                "   {:>5}  {:>12}  {:>6}×  {}",
+                line.line,
+                format_us(line.total_us),
+                line.count,
+                line.source
+            );
+        }
+    }
+}
+
+pub fn handle_bench_command(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let iterations = matches.get_one::<u64>("iterations").copied().unwrap_or(20) as usize;
+    let warmup = matches.get_one::<usize>("warmup").copied().unwrap_or(3);
+    if matches.get_flag("micro") {
+        let results = perf::micro_benchmarks(&MicroOptions { warmup, iterations })?;
+        return print_output(&results, |results| print_micro(results));
+    }
+
+    let program = matches
+        .get_one::<String>("program")
+        .ok_or("Program file is required")?;
+    if matches.get_flag("profile") {
+        if !program.to_lowercase().ends_with(".dsl") {
+            return Err("--profile needs a .dsl program".into());
+        }
+        let source = fs::read_to_string(program)
+            .map_err(|e| format!("Failed to read {}: {}", program, e))?;
+        let (report, error) = perf::profile_dsl(&source, &parse_params(matches)?)?;
+        print_output(&report, print_profile)?;
+        return match error {
+            Some(e) => Err(format!("Program failed: {}", e).into()),
+            None => Ok(()),
+        };
+    }
+
+    let ops = load_program(Path::new(program), matches.get_flag("stdlib"))?;
+
+    let modes: Vec<BenchMode> = match matches.get_many::<BenchMode>("mode") {
+        Some(modes) => modes.copied().collect(),
+        None => BenchMode::value_variants().to_vec(),
+    };
+    let options = BenchOptions {
+        modes,
+        warmup,
+        iterations,
+        parameters: parse_params(matches)?,
+    };
+
+    let mut report = run_bench(program, &ops, &options)?;
+
+    if let Some(baseline_path) = matches.get_one::<String>("baseline") {
+        let baseline: BenchReport = serde_json::from_str(
+            &fs::read_to_string(baseline_path)
+                .map_err(|e| format!("Failed to read baseline {}: {}", baseline_path, e))?,
+        )
+        .map_err(|e| format!("Invalid baseline report {}: {}", baseline_path, e))?;
+        let max_regression = matches
+            .get_one::<f64>("max-regression")
+            .copied()
+            .unwrap_or(10.0);
+        report.baseline = compare_with_baseline(&report, &baseline, max_regression);
+    }
+
+    print_output(&report, print_report)?;
+
+    let regressed: Vec<&str> = report
+        .baseline
+        .iter()
+        .filter(|c| c.regressed)
+        .map(|c| c.mode.as_str())
+        .collect();
+    if !regressed.is_empty() {
+        return Err(format!("Performance regression in: {}", regressed.join(", ")).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_and_baseline_comparison() {
+        let samples: Vec<Duration> = (1..=20).map(Duration::from_micros).collect();
+        let stats = BenchStats::from_samples(&samples);
+        assert_eq!(stats.samples, 20);
+        assert!((stats.mean_us - 10.5).abs() < 1e-9);
+        assert_eq!(stats.median_us, 10.0);
+        assert_eq!(stats.p95_us, 19.0);
+        assert!(stats.ci95_low_us < 10.5 && stats.ci95_high_us > 10.5);
+        // t(19) * s / sqrt(n), with s = sqrt(35)
+        let margin = 2.093 * 35f64.sqrt() / 20f64.sqrt();
+        assert!((stats.ci95_high_us - 10.5 - margin).abs() < 1e-6);
+
+        let report = |mean_us: f64| BenchReport {
+            program: "loop.dsl".to_string(),
+            operations: 4,
+            warmup: 0,
+            iterations: 20,
+            modes: vec![ModeReport {
+                mode: "ast".to_string(),
+                compile: None,
+                execute: BenchStats {
+                    mean_us,
+                    ..stats.clone()
+                },
+            }],
+            baseline: Vec::new(),
+        };
+        let comparison = compare_with_baseline(&report(115.0), &report(100.0), 10.0);
+        assert!(comparison[0].regressed);
+        assert!((comparison[0].change_pct - 15.0).abs() < 1e-9);
+        assert!(!compare_with_baseline(&report(105.0), &report(100.0), 10.0)[0].regressed);
+    }
+}
@@ -0,0 +1,148 @@
+//! DSL coverage reports
+//!
+//! `icn-covm coverage` runs a program once per scenario, each a set of
+//! parameters, and prints its source annotated with how often each line ran
+//! and which branches were never taken; see [`crate::coverage`]:
+//!
+//! ```bash
+//! icn-covm coverage --program budget.dsl --scenario amount=500 --scenario amount=50
+//! ```
+
+use crate::cli::output::print_output;
+use crate::coverage::{self, CoverageReport};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+pub fn coverage_command() -> Command {
+    Command::new("coverage")
+        .about("Run a DSL program and report which lines and branches it exercised")
+        .arg(
+            Arg::new("program")
+                .short('p')
+                .long("program")
+                .value_name("FILE")
+                .help("DSL program to run")
+                .required(true),
+        )
+        .arg(
+            Arg::new("param")
+                .short('P')
+                .long("param")
+                .value_name("KEY=VALUE")
+                .help("Parameter for every run (can be used multiple times)")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("scenario")
+                .short('s')
+                .long("scenario")
+                .value_name("KEY=VALUE,...")
+                .help("Run the program once with these parameters (can be used multiple times)")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("require-branches")
+                .long("require-branches")
+                .help("Fail if any branch was never taken")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+/// Parse `KEY=VALUE` pairs into `parameters`
+fn parse_pairs<'a>(
+    pairs: impl Iterator<Item = &'a str>,
+    parameters: &mut HashMap<String, String>,
+) -> Result<(), Box<dyn Error>> {
+    for pair in pairs {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid parameter format: {}. Expected KEY=VALUE", pair))?;
+        parameters.insert(key.trim().to_string(), value.trim().to_string());
+    }
+    Ok(())
+}
+
+pub fn handle_coverage_command(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let program = matches
+        .get_one::<String>("program")
+        .ok_or("Program file is required")?;
+    let source =
+        fs::read_to_string(program).map_err(|e| format!("Failed to read {}: {}", program, e))?;
+
+    let mut common = HashMap::new();
+    parse_pairs(
+        matches
+            .get_many::<String>("param")
+            .into_iter()
+            .flatten()
+            .map(String::as_str),
+        &mut common,
+    )?;
+    let mut runs = Vec::new();
+    for scenario in matches.get_many::<String>("scenario").into_iter().flatten() {
+        let mut parameters = common.clone();
+        parse_pairs(
+            scenario.split(',').filter(|p| !p.is_empty()),
+            &mut parameters,
+        )?;
+        runs.push(parameters);
+    }
+    if runs.is_empty() {
+        runs.push(common);
+    }
+
+    let (report, errors) = coverage::coverage_dsl(&source, &runs)?;
+    print_output(&report, |report| print_report(report, program, runs.len()))?;
+    for (run, error) in errors.iter().enumerate() {
+        if let Some(error) = error {
+            eprintln!("⚠️  Run {} failed: {}", run + 1, error);
+        }
+    }
+
+    let uncovered = report.uncovered_branches().count();
+    if matches.get_flag("require-branches") && uncovered > 0 {
+        return Err(format!("{} branch(es) were never taken", uncovered).into());
+    }
+    Ok(())
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        covered as f64 * 100.0 / total as f64
+    }
+}
+
+/// Print the source with each line's hit count: `-` for lines with no ops,
+/// and `#####` for lines that never ran
+pub fn print_report(report: &CoverageReport, program: &str, runs: usize) {
+    println!("🧪 Coverage: {} ({} run(s))", program, runs);
+    println!(
+        "   Lines: {}/{} ({:.1}%)  Branches: {}/{} ({:.1}%)\n",
+        report.lines_covered,
+        report.lines_total,
+        percent(report.lines_covered, report.lines_total),
+        report.branches_covered,
+        report.branches_total,
+        percent(report.branches_covered, report.branches_total)
+    );
+    for line in &report.lines {
+        let hits = match line.hits {
+            None => "-".to_string(),
+            Some(0) => "#####".to_string(),
+            Some(hits) => hits.to_string(),
+        };
+        println!("{:>8} │{:>5} │ {}", hits, line.line, line.source);
+    }
+
+    let uncovered: Vec<_> = report.uncovered_branches().collect();
+    if !uncovered.is_empty() {
+        println!("\n⚠️  Branches never taken:");
+        for branch in uncovered {
+            println!("   Line {}: {}", branch.line, branch.branch);
+        }
+    }
+}
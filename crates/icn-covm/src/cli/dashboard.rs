@@ -0,0 +1,511 @@
+//! Terminal governance dashboard
+//!
+//! `icn-covm dashboard` shows the open proposals of a namespace with their
+//! vote tallies and next deadline, the most recent DAG ledger events, and the
+//! peers of a federation node, refreshing until `q` is pressed.
+//!
+//! Proposals are reloaded from storage on every refresh. Ledger events come
+//! from a subscription to an in-memory copy of the ledger file: each refresh
+//! imports the nodes other commands have written since, which the
+//! subscription then delivers.
+
+use crate::cli::ledger::describe_node;
+use crate::cli::proposal::{count_votes, VMProposalExtensions};
+use crate::federation::{NodeHandle, PeerStatus};
+use crate::governance::proposal_lifecycle::{ProposalLifecycle, ProposalState};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+
+use chrono::{DateTime, Duration, Utc};
+use clap::{value_parser, Arg, ArgMatches, Command};
+use icn_ledger::{DagLedger, DagNode};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::time::Instant;
+
+/// Ledger events kept for display
+const MAX_EVENTS: usize = 50;
+
+/// Create the dashboard command
+pub fn dashboard_command() -> Command {
+    Command::new("dashboard")
+        .about("Live terminal dashboard of proposals, ledger events, and federation peers")
+        .arg(
+            Arg::new("namespace")
+                .long("namespace")
+                .value_name("NAMESPACE")
+                .help("Namespace whose proposals are shown")
+                .default_value("default"),
+        )
+        .arg(
+            Arg::new("dag-path")
+                .long("dag-path")
+                .value_name("PATH")
+                .help("DAG ledger file to follow (defaults to the configured ledger)"),
+        )
+        .arg(
+            Arg::new("refresh")
+                .long("refresh")
+                .value_name("SECONDS")
+                .help("Seconds between refreshes")
+                .value_parser(value_parser!(u64).range(1..))
+                .default_value("2"),
+        )
+        .arg(
+            Arg::new("federation-port")
+                .long("federation-port")
+                .value_name("PORT")
+                .help("Run a federation node on this port to show peer status (default: federation.port when federation.enabled)")
+                .value_parser(value_parser!(u16)),
+        )
+}
+
+/// An open proposal as shown on the dashboard
+#[derive(Debug, Clone)]
+struct ProposalRow {
+    id: String,
+    title: String,
+    state: ProposalState,
+    yes: u32,
+    no: u32,
+    abstain: u32,
+    quorum: u64,
+    threshold: u64,
+    /// The next deadline and when it falls
+    deadline: Option<(&'static str, DateTime<Utc>)>,
+}
+
+/// Everything the dashboard draws
+#[derive(Debug)]
+struct Dashboard {
+    namespace: String,
+    dag_path: String,
+    proposals: Vec<ProposalRow>,
+    /// Newest first
+    events: VecDeque<DagNode>,
+    /// `None` when no federation node is running
+    peers: Option<Vec<PeerStatus>>,
+    error: Option<String>,
+    refreshed_at: DateTime<Utc>,
+}
+
+impl Dashboard {
+    fn push_event(&mut self, node: DagNode) {
+        self.events.push_front(node);
+        self.events.truncate(MAX_EVENTS);
+    }
+}
+
+/// Time left until a deadline, to the two largest units
+fn format_countdown(remaining: Duration) -> String {
+    if remaining <= Duration::zero() {
+        return "passed".to_string();
+    }
+    let (days, hours, minutes, seconds) = (
+        remaining.num_days(),
+        remaining.num_hours() % 24,
+        remaining.num_minutes() % 60,
+        remaining.num_seconds() % 60,
+    );
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m {}s", minutes, seconds)
+    }
+}
+
+/// Load the proposals of the VM's namespace that are still in progress
+fn load_open_proposals<S>(vm: &VM<S>) -> Result<Vec<ProposalRow>, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm.get_storage_backend().ok_or("Storage not available")?;
+    let namespace = vm.get_namespace().unwrap_or("default");
+    let prefix = VM::<S>::proposal_key_prefix("");
+
+    let mut rows = Vec::new();
+    for key in storage.list_keys(vm.get_auth_context(), namespace, Some(&prefix))? {
+        let Some(id) = key
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix("/lifecycle"))
+        else {
+            continue;
+        };
+        let lifecycle = vm.get_proposal_lifecycle(id)?;
+        if !matches!(
+            lifecycle.state,
+            ProposalState::Draft | ProposalState::OpenForFeedback | ProposalState::Voting
+        ) {
+            continue;
+        }
+        let (yes, no, abstain) = count_votes(vm, &id.to_string())?;
+        rows.push(ProposalRow {
+            id: id.to_string(),
+            deadline: lifecycle.next_deadline(),
+            title: lifecycle.title,
+            state: lifecycle.state,
+            yes,
+            no,
+            abstain,
+            quorum: lifecycle.quorum,
+            threshold: lifecycle.threshold,
+        });
+    }
+    rows.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(rows)
+}
+
+fn draw(frame: &mut Frame, dashboard: &Dashboard, now: DateTime<Utc>) {
+    let [proposals_area, lower_area, status_area] = Layout::vertical([
+        Constraint::Percentage(50),
+        Constraint::Min(6),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [events_area, peers_area] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .areas(lower_area);
+
+    let header = Row::new([
+        "ID",
+        "Title",
+        "State",
+        "Yes",
+        "No",
+        "Abstain",
+        "Q/T",
+        "Next deadline",
+    ])
+    .bold();
+    let rows = dashboard.proposals.iter().map(|p| {
+        let deadline = p.deadline.map_or_else(String::new, |(label, at)| {
+            format!("{} in {}", label, format_countdown(at - now))
+        });
+        Row::new([
+            p.id.clone(),
+            p.title.clone(),
+            p.state.to_string(),
+            p.yes.to_string(),
+            p.no.to_string(),
+            p.abstain.to_string(),
+            format!("{}%/{}%", p.quorum, p.threshold),
+            deadline,
+        ])
+    });
+    let widths = [
+        Constraint::Percentage(14),
+        Constraint::Percentage(24),
+        Constraint::Length(16),
+        Constraint::Length(5),
+        Constraint::Length(5),
+        Constraint::Length(8),
+        Constraint::Length(9),
+        Constraint::Fill(1),
+    ];
+    let title = format!(
+        " Open proposals in {} ({}) ",
+        dashboard.namespace,
+        dashboard.proposals.len()
+    );
+    frame.render_widget(
+        Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(title)),
+        proposals_area,
+    );
+
+    let events: Vec<ListItem> = dashboard
+        .events
+        .iter()
+        .map(|node| {
+            let time = DateTime::<Utc>::from_timestamp(node.timestamp as i64, 0)
+                .map(|t| t.format("%H:%M:%S").to_string())
+                .unwrap_or_else(|| node.timestamp.to_string());
+            ListItem::new(format!(
+                "{} {:<16} {}",
+                time,
+                node.data.type_name(),
+                describe_node(node)
+            ))
+        })
+        .collect();
+    frame.render_widget(
+        List::new(events)
+            .block(Block::bordered().title(format!(" Ledger {} ", dashboard.dag_path))),
+        events_area,
+    );
+
+    let peers_block = Block::bordered().title(" Federation peers ");
+    match &dashboard.peers {
+        None => frame.render_widget(
+            Paragraph::new("No federation node (use --federation-port)").block(peers_block),
+            peers_area,
+        ),
+        Some(peers) => {
+            let items: Vec<ListItem> = peers
+                .iter()
+                .map(|peer| {
+                    let short_id = &peer.peer_id[peer.peer_id.len().saturating_sub(8)..];
+                    let rtt = peer
+                        .rtt_ms
+                        .map_or_else(|| "-".to_string(), |ms| format!("{}ms", ms));
+                    let seen = now.timestamp() - peer.last_seen as i64;
+                    let line = format!(
+                        "{} …{} rtt {} seen {}s ago",
+                        if peer.connected { "●" } else { "○" },
+                        short_id,
+                        rtt,
+                        seen.max(0)
+                    );
+                    let color = if peer.connected {
+                        Color::Green
+                    } else {
+                        Color::DarkGray
+                    };
+                    ListItem::new(line).style(Style::new().fg(color))
+                })
+                .collect();
+            frame.render_widget(
+                List::new(items)
+                    .block(peers_block.title_bottom(format!(" {} known ", peers.len()))),
+                peers_area,
+            );
+        }
+    }
+
+    let status = match &dashboard.error {
+        Some(error) => Line::from(format!(" ❌ {}", error)).red(),
+        None => Line::from(format!(
+            " Refreshed {}  ·  q quit  ·  r refresh",
+            dashboard.refreshed_at.format("%H:%M:%S")
+        )),
+    };
+    frame.render_widget(status, status_area);
+}
+
+/// Reload proposals, new ledger events, and peers
+async fn refresh<S>(
+    dashboard: &mut Dashboard,
+    vm: &VM<S>,
+    ledger: &mut DagLedger,
+    updates: &Receiver<DagNode>,
+    dag_path: &Path,
+    node: Option<&NodeHandle>,
+) where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    dashboard.error = None;
+    match load_open_proposals(vm) {
+        Ok(proposals) => dashboard.proposals = proposals,
+        Err(e) => dashboard.error = Some(format!("Failed to load proposals: {}", e)),
+    }
+    if let Err(e) = ledger.import_from_file(dag_path) {
+        dashboard.error = Some(format!("Failed to read ledger: {}", e));
+    }
+    for event in updates.try_iter() {
+        dashboard.push_event(event);
+    }
+    if let Some(node) = node {
+        dashboard.peers = Some(node.peers().await);
+    }
+    dashboard.refreshed_at = Utc::now();
+}
+
+/// Run the dashboard until the user quits
+///
+/// `dag_path` is the ledger file to follow and `node` the federation node
+/// whose peers are shown, if one was started.
+pub async fn run_dashboard<S>(
+    vm: &mut VM<S>,
+    matches: &ArgMatches,
+    dag_path: &Path,
+    node: Option<NodeHandle>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let namespace = matches
+        .get_one::<String>("namespace")
+        .map(String::as_str)
+        .unwrap_or("default");
+    vm.set_namespace(namespace);
+    let refresh_every =
+        std::time::Duration::from_secs(matches.get_one::<u64>("refresh").copied().unwrap_or(2));
+
+    let mut ledger = DagLedger::load_from_file(dag_path)?;
+    let updates = ledger.subscribe();
+    let mut dashboard = Dashboard {
+        namespace: namespace.to_string(),
+        dag_path: dag_path.display().to_string(),
+        proposals: Vec::new(),
+        events: ledger
+            .nodes()
+            .iter()
+            .rev()
+            .take(MAX_EVENTS)
+            .cloned()
+            .collect(),
+        peers: None,
+        error: None,
+        refreshed_at: Utc::now(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(
+        &mut terminal,
+        &mut dashboard,
+        vm,
+        &mut ledger,
+        &updates,
+        dag_path,
+        node.as_ref(),
+        refresh_every,
+    )
+    .await;
+    ratatui::restore();
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn event_loop<S>(
+    terminal: &mut DefaultTerminal,
+    dashboard: &mut Dashboard,
+    vm: &VM<S>,
+    ledger: &mut DagLedger,
+    updates: &Receiver<DagNode>,
+    dag_path: &Path,
+    node: Option<&NodeHandle>,
+    refresh_every: std::time::Duration,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    loop {
+        refresh(dashboard, vm, ledger, updates, dag_path, node).await;
+        let next_refresh = Instant::now() + refresh_every;
+
+        // Redraw every second so countdowns tick between refreshes
+        while let Some(remaining) = next_refresh.checked_duration_since(Instant::now()) {
+            terminal.draw(|frame| draw(frame, dashboard, Utc::now()))?;
+            let timeout = remaining.min(std::time::Duration::from_secs(1));
+            if !tokio::task::block_in_place(|| event::poll(timeout))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return Ok(())
+                    }
+                    KeyCode::Char('r') => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::identity::Identity;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn test_next_deadline_follows_state() {
+        let creator = Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        let mut lifecycle = ProposalLifecycle::new(
+            "p1".to_string(),
+            creator,
+            "Budget".to_string(),
+            50,
+            60,
+            Some(Duration::hours(72)),
+            None,
+        );
+        assert_eq!(lifecycle.next_deadline(), None);
+
+        lifecycle.open_for_feedback(&SystemClock);
+        let (label, at) = lifecycle.next_deadline().unwrap();
+        assert_eq!(label, "Deliberation ends");
+        assert_eq!(
+            at,
+            lifecycle.history.last().unwrap().0 + Duration::hours(72)
+        );
+
+        lifecycle.start_voting(Duration::days(7), &SystemClock);
+        let (label, at) = lifecycle.next_deadline().unwrap();
+        assert_eq!(label, "Voting closes");
+        assert_eq!(Some(at), lifecycle.expires_at);
+
+        assert_eq!(format_countdown(Duration::minutes(-5)), "passed");
+        assert_eq!(format_countdown(Duration::minutes(90)), "1h 30m");
+        assert_eq!(format_countdown(Duration::hours(50)), "2d 2h");
+    }
+
+    #[test]
+    fn test_draw_shows_proposals_events_and_peers() {
+        let now = Utc::now();
+        let mut dashboard = Dashboard {
+            namespace: "coop".to_string(),
+            dag_path: "dag.jsonl".to_string(),
+            proposals: vec![ProposalRow {
+                id: "roof".to_string(),
+                title: "Fix the roof".to_string(),
+                state: ProposalState::Voting,
+                yes: 3,
+                no: 1,
+                abstain: 0,
+                quorum: 50,
+                threshold: 60,
+                deadline: Some(("Voting closes", now + Duration::hours(5))),
+            }],
+            events: VecDeque::new(),
+            peers: None,
+            error: None,
+            refreshed_at: now,
+        };
+        dashboard.push_event(DagNode::with_namespace(
+            vec![],
+            icn_ledger::NodeData::VoteCast {
+                proposal_id: "roof".to_string(),
+                voter: "bob".to_string(),
+                vote: 1.0,
+            },
+            now.timestamp() as u64,
+            "coop".to_string(),
+        ));
+
+        let mut terminal = Terminal::new(TestBackend::new(140, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &dashboard, now)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+
+        assert!(screen.contains("Open proposals in coop (1)"));
+        assert!(screen.contains("Fix the roof"));
+        assert!(screen.contains("Voting closes in 5h 0m"));
+        assert!(screen.contains("bob voted 1 on roof"));
+        assert!(screen.contains("No federation node"));
+    }
+}
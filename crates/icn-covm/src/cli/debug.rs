@@ -0,0 +1,201 @@
+//! The `run --debug` session
+//!
+//! The program is loaded but not run. Commands are read one per line:
+//!
+//! - `step` (`s`): run the next top-level op
+//! - `continue` (`c`): run until the next breakpoint or the end
+//! - `break <index|function>` (`b`): pause before that op, or before any op
+//!   that calls that function
+//! - `delete <index|function>`: remove a breakpoint
+//! - `breakpoints`: list the breakpoints
+//! - `print stack` and `print memory` (`p`): show the VM's state
+//! - `quit` (`q`): stop without running the rest of the program
+//!
+//! An empty line repeats the last command, so stepping is a matter of
+//! pressing enter.
+//!
+//! ```bash
+//! icn-covm run --program payroll.dsl --debug
+//! ```
+
+use crate::storage::traits::Storage;
+use crate::vm::{Breakpoint, DebugState, Op, VM};
+use std::error::Error;
+use std::fmt::Debug;
+use std::io::{self, BufRead, Write};
+
+/// Step through `ops` on `vm` with commands read from the terminal
+pub fn run_session<S>(vm: &mut VM<S>, ops: &[Op]) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    debug_program(vm, ops, io::stdin().lock(), io::stdout())
+}
+
+/// Step through `ops` on `vm` with commands read from `input`
+pub fn debug_program<S, R, W>(
+    vm: &mut VM<S>,
+    ops: &[Op],
+    input: R,
+    mut output: W,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+    R: BufRead,
+    W: Write,
+{
+    vm.start_debugging(ops);
+    writeln!(
+        output,
+        "Debugging {} ops; type `help` for the commands",
+        ops.len()
+    )?;
+    if let Some(op) = ops.first() {
+        writeln!(output, "Paused before op 0: {}", op)?;
+    }
+
+    let mut last = String::new();
+    let mut lines = input.lines();
+    loop {
+        write!(output, "(debug) ")?;
+        output.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(());
+        };
+        let line = match line.trim() {
+            "" => last.clone(),
+            line => line.to_string(),
+        };
+        last = line.clone();
+
+        let (command, argument) = line
+            .split_once(char::is_whitespace)
+            .map(|(command, argument)| (command, argument.trim()))
+            .unwrap_or((line.as_str(), ""));
+        let state = match command {
+            "step" | "s" => vm.step(),
+            "continue" | "c" => vm.resume(),
+            "break" | "b" | "delete" => {
+                match argument.parse::<Breakpoint>() {
+                    Err(e) => writeln!(output, "{}", e)?,
+                    Ok(breakpoint) if command == "delete" => {
+                        if vm.remove_breakpoint(&breakpoint) {
+                            writeln!(output, "Breakpoint at {} deleted", breakpoint)?;
+                        } else {
+                            writeln!(output, "No breakpoint at {}", breakpoint)?;
+                        }
+                    }
+                    Ok(breakpoint) => {
+                        writeln!(output, "Breakpoint set at {}", breakpoint)?;
+                        vm.add_breakpoint(breakpoint);
+                    }
+                }
+                continue;
+            }
+            "breakpoints" => {
+                for breakpoint in vm.breakpoints() {
+                    writeln!(output, "  {}", breakpoint)?;
+                }
+                continue;
+            }
+            "print" | "p" => {
+                print_state(vm, argument, &mut output)?;
+                continue;
+            }
+            "quit" | "q" => return Ok(()),
+            "help" | "h" => {
+                writeln!(
+                    output,
+                    "Commands: step, continue, break <index|function>, \
+                     delete <index|function>, breakpoints, print stack, print memory, quit"
+                )?;
+                continue;
+            }
+            _ => {
+                writeln!(output, "Unknown command: {}", command)?;
+                continue;
+            }
+        };
+
+        match state {
+            Ok(DebugState::Paused { next, breakpoint }) => {
+                if let Some(breakpoint) = breakpoint {
+                    writeln!(output, "Hit breakpoint at {}", breakpoint)?;
+                }
+                let op = vm.inspect().op.map(|op| op.to_string()).unwrap_or_default();
+                writeln!(output, "Paused before op {}: {}", next, op)?;
+            }
+            Ok(DebugState::Finished) => {
+                writeln!(output, "Program finished")?;
+                print_state(vm, "stack", &mut output)?;
+                return Ok(());
+            }
+            Err(e) => {
+                writeln!(output, "Execution failed: {}", e)?;
+                print_state(vm, "stack", &mut output)?;
+                return Err(e.into());
+            }
+        }
+    }
+}
+
+/// Print the stack or memory for `print`
+fn print_state<S, W>(vm: &VM<S>, what: &str, output: &mut W) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+    W: Write,
+{
+    let inspection = vm.inspect();
+    match what {
+        "stack" | "" => {
+            writeln!(
+                output,
+                "Stack ({} values, top last):",
+                inspection.stack.len()
+            )?;
+            for (depth, value) in inspection.stack.iter().enumerate() {
+                writeln!(output, "  {}: {}", depth, value)?;
+            }
+        }
+        "memory" => {
+            let mut memory: Vec<_> = inspection.memory.into_iter().collect();
+            memory.sort_by(|a, b| a.0.cmp(&b.0));
+            writeln!(output, "Memory ({} variables):", memory.len())?;
+            for (name, value) in memory {
+                writeln!(output, "  {} = {}", name, value)?;
+            }
+        }
+        _ => writeln!(output, "Print `stack` or `memory`, not {}", what)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use crate::typed::TypedValue;
+
+    #[test]
+    fn test_session_steps_breaks_and_prints() {
+        let ops = vec![
+            Op::Push(TypedValue::Number(2.0)),
+            Op::Store("x".to_string()),
+            Op::Push(TypedValue::Number(5.0)),
+            Op::Push(TypedValue::Number(1.0)),
+        ];
+        let commands = "step\nprint stack\nbreak 3\ncontinue\nprint memory\n\n";
+        let mut output = Vec::new();
+        let mut vm = VM::<InMemoryStorage>::new();
+        debug_program(&mut vm, &ops, commands.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Paused before op 1: "));
+        assert!(output.contains("  0: 2"));
+        assert!(output.contains("Hit breakpoint at op 3"));
+        assert!(output.contains("  x = 2"));
+        // The empty line repeated `print memory`; input ended with op 3 still to run
+        assert!(!output.contains("Program finished"));
+        assert_eq!(vm.get_stack(), vec![TypedValue::Number(5.0)]);
+    }
+}
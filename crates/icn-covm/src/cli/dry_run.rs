@@ -0,0 +1,354 @@
+//! Dry runs of state-changing commands
+//!
+//! `--dry-run` on `proposal create`, `vote`, `transition` and `execute`, and
+//! on `run`, goes through every check the real command makes, then prints
+//! what it would have changed instead of changing it:
+//!
+//! - the storage writes, with their namespace, key and value
+//! - the DAG nodes, with the parents they would be appended on
+//! - the events raised by any DSL the command runs, such as token mints,
+//!   transfers and burns, and the escrow payments a decision would settle,
+//!   which are reported rather than paid
+//!
+//! Nothing is committed: storage writes are made, with the caller's
+//! permissions, inside a transaction that is rolled back, DSL runs on a fork
+//! that is rolled back, and DAG nodes are never appended, so the ledger file
+//! is left as it was.
+//!
+//! A dry run is a [`DryRun`] value that the command passes down to each step
+//! that writes, so nothing outside that command can see it.
+//!
+//! ```bash
+//! icn-covm proposal vote --id budget-2024 --vote yes --dry-run
+//! icn-covm --output json proposal execute --id budget-2024 --dry-run
+//! icn-covm run --program payroll.dsl --dry-run
+//! ```
+
+use crate::cli::output::print_output;
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::StorageResult;
+use crate::storage::traits::{Storage, StorageBackend, StorageExtensions};
+use crate::vm::{Op, VMError, VMEvent, VM};
+use clap::{Arg, ArgAction, ArgMatches};
+use icn_ledger::{DagLedger, DagNode, NodeData};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::Debug;
+
+/// A storage write the command would have made
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedWrite {
+    pub namespace: String,
+    pub key: String,
+    /// The value as JSON, or as text for raw writes
+    pub value: serde_json::Value,
+}
+
+/// A DAG node the command would have appended
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedNode {
+    /// The node ID, unless the namespace is encrypted and the ID depends on
+    /// the sealed payload
+    pub id: Option<String>,
+    pub namespace: String,
+    pub parent_ids: Vec<String>,
+    pub data: NodeData,
+}
+
+/// An event raised by DSL the command ran, e.g. a token mint or transfer
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedEvent {
+    pub category: String,
+    pub message: String,
+}
+
+/// Everything a dry run would have changed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DryRunPlan {
+    pub writes: Vec<PlannedWrite>,
+    pub dag_nodes: Vec<PlannedNode>,
+    pub events: Vec<PlannedEvent>,
+}
+
+/// A dry run in progress, passed to every step of the command it rehearses
+#[derive(Debug, Default)]
+pub struct DryRun {
+    plan: DryRunPlan,
+}
+
+impl DryRun {
+    /// Start a dry run
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a dry run if `--dry-run` was given to this subcommand
+    pub fn requested(matches: &ArgMatches) -> Option<Self> {
+        (matches.try_get_one::<bool>("dry-run").ok().flatten() == Some(&true)).then(Self::new)
+    }
+
+    /// What the dry run has recorded so far
+    pub fn plan(&self) -> &DryRunPlan {
+        &self.plan
+    }
+
+    /// Record the events raised by a forked VM
+    pub fn record_events(&mut self, events: &[VMEvent]) {
+        self.plan
+            .events
+            .extend(events.iter().map(|event| PlannedEvent {
+                category: event.category.clone(),
+                message: event.message.clone(),
+            }));
+    }
+
+    /// End the dry run and print what it would have changed
+    pub fn finish(self) -> Result<(), Box<dyn Error>> {
+        print_plan(&self.plan)
+    }
+
+    fn record_write(&mut self, namespace: &str, key: &str, value: serde_json::Value) {
+        self.plan.writes.push(PlannedWrite {
+            namespace: namespace.to_string(),
+            key: key.to_string(),
+            value,
+        });
+    }
+}
+
+/// The `--dry-run` flag for a state-changing subcommand
+pub fn dry_run_arg() -> Arg {
+    Arg::new("dry-run")
+        .long("dry-run")
+        .help("Validate and print the storage writes and DAG nodes without committing them")
+        .action(ArgAction::SetTrue)
+}
+
+/// Make a command's storage writes with `make_writes`
+///
+/// During a dry run they are made inside a transaction on `storage` that is
+/// rolled back afterwards, whether or not `make_writes` succeeded.
+pub fn write<S, F>(
+    dry_run: Option<&mut DryRun>,
+    storage: &mut S,
+    make_writes: F,
+) -> Result<(), Box<dyn Error>>
+where
+    S: StorageBackend,
+    F: FnOnce(Option<&mut DryRun>, &mut S) -> Result<(), Box<dyn Error>>,
+{
+    let Some(dry_run) = dry_run else {
+        return make_writes(None, storage);
+    };
+    storage.begin_transaction()?;
+    let result = make_writes(Some(dry_run), storage);
+    storage.rollback_transaction()?;
+    result
+}
+
+/// Store `value` as JSON, recording the write during a dry run
+pub fn set_json<S, T>(
+    dry_run: Option<&mut DryRun>,
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    key: &str,
+    value: &T,
+) -> StorageResult<()>
+where
+    S: StorageExtensions,
+    T: Serialize,
+{
+    storage.set_json(auth, namespace, key, value)?;
+    if let Some(dry_run) = dry_run {
+        let value = serde_json::to_value(value).unwrap_or(serde_json::Value::Null);
+        dry_run.record_write(namespace, key, value);
+    }
+    Ok(())
+}
+
+/// Store raw bytes, recording the write during a dry run
+pub fn set<S: StorageBackend>(
+    dry_run: Option<&mut DryRun>,
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    key: &str,
+    value: Vec<u8>,
+) -> StorageResult<()> {
+    let shown = dry_run
+        .is_some()
+        .then(|| String::from_utf8_lossy(&value).into_owned());
+    storage.set(auth, namespace, key, value)?;
+    if let (Some(dry_run), Some(shown)) = (dry_run, shown) {
+        dry_run.record_write(namespace, key, serde_json::Value::String(shown));
+    }
+    Ok(())
+}
+
+/// Commit the VM's fork transaction, unless this is a dry run
+pub fn commit<S>(dry_run: Option<&DryRun>, vm: &mut VM<S>) -> Result<(), VMError>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    if dry_run.is_some() {
+        return Ok(());
+    }
+    vm.commit_fork_transaction()
+}
+
+/// Append `node` on the tips of its namespace, or only record it during a
+/// dry run
+///
+/// Returns the ID the node was, or would be, stored under.
+pub fn append_on_tips(
+    dry_run: Option<&mut DryRun>,
+    ledger: &mut DagLedger,
+    mut node: DagNode,
+) -> Result<String, String> {
+    let Some(dry_run) = dry_run else {
+        return ledger.append_on_tips(node);
+    };
+    for tip in ledger.current_tips(&node.namespace) {
+        if !node.parent_ids.contains(&tip) {
+            node.parent_ids.push(tip);
+        }
+    }
+    let id = if ledger.is_namespace_encrypted(&node.namespace) {
+        None
+    } else {
+        Some(node.compute_id())
+    };
+    let shown = id.clone().unwrap_or_else(|| "(sealed)".to_string());
+    dry_run.plan.dag_nodes.push(PlannedNode {
+        id,
+        namespace: node.namespace,
+        parent_ids: node.parent_ids,
+        data: node.data,
+    });
+    Ok(shown)
+}
+
+/// Run `ops` on a fork of the VM, record what they raised and roll it back
+pub fn rehearse<S>(dry_run: &mut DryRun, vm: &mut VM<S>, ops: &[Op]) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let mut fork = vm.fork()?;
+    let result = fork.execute(ops);
+    dry_run.record_events(fork.get_events());
+    fork.rollback_fork_transaction()?;
+    result?;
+    Ok(())
+}
+
+fn print_plan(plan: &DryRunPlan) -> Result<(), Box<dyn Error>> {
+    print_output(plan, |plan| {
+        println!("🧪 Dry run: nothing was committed");
+        println!("   Storage writes: {}", plan.writes.len());
+        for write in &plan.writes {
+            println!("     {}:{}", write.namespace, write.key);
+        }
+        println!("   DAG nodes: {}", plan.dag_nodes.len());
+        for node in &plan.dag_nodes {
+            let id = node.id.as_deref().unwrap_or("(sealed)");
+            println!(
+                "     {} {} in {}",
+                node.data.type_name(),
+                id,
+                node.namespace
+            );
+            if !node.parent_ids.is_empty() {
+                println!("       parents: {}", node.parent_ids.join(", "));
+            }
+        }
+        if !plan.events.is_empty() {
+            println!("   Events: {}", plan.events.len());
+            for event in &plan.events {
+                println!("     [{}] {}", event.category, event.message);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_dry_run_records_instead_of_appending() {
+        let mut ledger = DagLedger::new();
+        let first = ledger
+            .append_on_tips(DagNode::with_namespace(
+                vec![],
+                NodeData::ProposalCreated {
+                    proposal_id: "p1".to_string(),
+                    title: "First".to_string(),
+                },
+                1,
+                "coop".to_string(),
+            ))
+            .unwrap();
+
+        let mut dry_run = DryRun::new();
+        let node = DagNode::with_namespace(
+            vec![],
+            NodeData::VoteCast {
+                proposal_id: "p1".to_string(),
+                voter: "alice".to_string(),
+                vote: 1.0,
+            },
+            2,
+            "coop".to_string(),
+        );
+        let id = append_on_tips(Some(&mut dry_run), &mut ledger, node).unwrap();
+        let plan = dry_run.plan();
+
+        assert_eq!(ledger.nodes().len(), 1);
+        assert_eq!(plan.dag_nodes.len(), 1);
+        assert_eq!(plan.dag_nodes[0].parent_ids, vec![first]);
+        assert_eq!(plan.dag_nodes[0].id.as_deref(), Some(id.as_str()));
+    }
+
+    #[test]
+    fn test_dry_run_writes_are_checked_then_rolled_back() {
+        let mut storage = InMemoryStorage::new();
+        let mut admin = AuthContext::new("operator");
+        admin.add_role("global", "admin");
+        admin.add_role("coop", "admin");
+        storage
+            .create_namespace(Some(&admin), "coop", 1024 * 1024, None)
+            .unwrap();
+
+        let mut dry_run = DryRun::new();
+        write(Some(&mut dry_run), &mut storage, |dry_run, storage| {
+            Ok(set_json(
+                dry_run,
+                storage,
+                Some(&admin),
+                "coop",
+                "quorum",
+                &0.5,
+            )?)
+        })
+        .unwrap();
+        assert_eq!(dry_run.plan().writes.len(), 1);
+        assert!(!storage.contains(Some(&admin), "coop", "quorum").unwrap());
+
+        // A write the caller may not make fails the dry run too
+        let outsider = AuthContext::new("mallory");
+        let refused = write(Some(&mut dry_run), &mut storage, |dry_run, storage| {
+            Ok(set(
+                dry_run,
+                storage,
+                Some(&outsider),
+                "coop",
+                "quorum",
+                b"0.9".to_vec(),
+            )?)
+        });
+        assert!(refused.is_err());
+        assert_eq!(dry_run.plan().writes.len(), 1);
+    }
+}
@@ -1,10 +1,15 @@
+use crate::cli::output::print_output;
+use crate::config::Config;
+use crate::federation::execution::{self, AckReport};
 use crate::federation::messages::{
-    FederatedProposal, FederatedVote, ProposalScope, ProposalStatus, VotingModel,
+    ExecutionAck, FederatedProposal, FederatedVote, ProposalScope, ProposalStatus, VotingModel,
 };
+use crate::federation::peer_score::{PeerScore, PeerScores, Standing};
 use crate::federation::storage::{FederationStorage, FEDERATION_NAMESPACE, VOTES_NAMESPACE};
 use crate::federation::{NetworkNode, NodeConfig};
 use crate::governance::proposal::{Proposal, ProposalStatus as LocalProposalStatus};
 use crate::governance::proposal_lifecycle::VoteChoice;
+use crate::identity::signer::{self, Signer};
 use crate::storage::auth::AuthContext;
 use crate::storage::traits::{Storage, StorageExtensions};
 use crate::vm::VM;
@@ -12,8 +17,10 @@ use crate::vm::VM;
 use clap::{Arg, ArgAction, ArgMatches, Command};
 use libp2p::Multiaddr;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt::Debug;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
@@ -39,6 +46,19 @@ struct FederationSyncMetadata {
     vote_count: u32,
 }
 
+/// Overview of the federation state held in local storage
+#[derive(Debug, Default, Serialize)]
+struct FederationStatusReport {
+    /// Number of federated proposals stored locally
+    proposals: usize,
+    /// Federated proposals by status
+    by_status: BTreeMap<String, usize>,
+    /// Remote votes stored for those proposals
+    votes: usize,
+    /// Most recent proposal sync, if any
+    last_sync: Option<FederationSyncMetadata>,
+}
+
 /// Create the federation command and its subcommands
 pub fn federation_command() -> Command {
     Command::new("federation")
@@ -130,6 +150,12 @@ pub fn federation_command() -> Command {
                         .value_name("NODE_ADDRESS")
                         .help("Address of the node hosting the proposal")
                         .required(true),
+                )
+                .arg(
+                    Arg::new("identity")
+                        .long("identity")
+                        .value_name("FILE_PATH")
+                        .help("Identity or hardware token file to sign the vote with (default: the CLI identity)"),
                 ),
         )
         .subcommand(
@@ -166,6 +192,74 @@ pub fn federation_command() -> Command {
                         .help("Filter by status: open, closed, executed, rejected, expired"),
                 ),
         )
+        .subcommand(
+            Command::new("status")
+                .about("Summarize federated proposals, remote votes, and the last sync"),
+        )
+        .subcommand(
+            Command::new("peers")
+                .about("Show the scores and bans this node's peers earned by their messages")
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .value_name("FILE")
+                        .help("Peer scores file (default: federation.peer_scores_path)"),
+                ),
+        )
+        .subcommand(
+            Command::new("execute-proposal")
+                .about("Execute a federated proposal and report which cooperatives acknowledged it")
+                .arg(
+                    Arg::new("id")
+                        .long("id")
+                        .value_name("PROPOSAL_ID")
+                        .help("ID of the proposal to execute")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Execute even if the proposal has not expired yet")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("ack")
+                .about("Acknowledge that a cooperative applied an executed proposal's result")
+                .arg(
+                    Arg::new("id")
+                        .long("id")
+                        .value_name("PROPOSAL_ID")
+                        .help("ID of the executed proposal")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("coop")
+                        .long("coop")
+                        .value_name("COOP_ID")
+                        .help("Cooperative that applied the result")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("outcome")
+                        .long("outcome")
+                        .value_name("OPTION")
+                        .help("Result that was applied, e.g. the winning option")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("node")
+                        .long("node")
+                        .value_name("NODE_ADDRESS")
+                        .help("Address of a node to send the acknowledgment to"),
+                )
+                .arg(
+                    Arg::new("identity")
+                        .long("identity")
+                        .value_name("FILE_PATH")
+                        .help("Identity or hardware token file to sign with (default: the CLI identity)"),
+                ),
+        )
 }
 
 /// Handle federation commands
@@ -287,7 +381,28 @@ where
                 .parse::<Multiaddr>()
                 .map_err(|e| format!("Invalid multiaddress: {}", e))?;
 
-            submit_remote_vote(vm, proposal_id, vote_choice, &target_addr, auth_context).await
+            let signer: Box<dyn Signer> = match sub_matches.get_one::<String>("identity") {
+                Some(path) => signer::open_signer(Path::new(path))?,
+                None => Box::new(
+                    auth_context
+                        .get_identity(auth_context.identity_did())
+                        .cloned()
+                        .ok_or(
+                            "Votes are signed by the voter; pass --identity or set \
+                             identity.key_path",
+                        )?,
+                ),
+            };
+
+            submit_remote_vote(
+                vm,
+                proposal_id,
+                vote_choice,
+                &target_addr,
+                signer.as_ref(),
+                auth_context,
+            )
+            .await
         }
         Some(("sync", sub_matches)) => {
             let proposal_id = sub_matches
@@ -311,12 +426,62 @@ where
                 .map(|s| s.to_string());
             list_federated_proposals(vm, status_filter, auth_context)
         }
+        Some(("status", _)) => federation_status(vm, auth_context),
+        Some(("execute-proposal", sub_matches)) => {
+            let proposal_id = sub_matches
+                .get_one::<String>("id")
+                .ok_or_else(|| "Missing required argument: id")?;
+            let force = sub_matches.get_flag("force");
+
+            execute_federated_proposal(vm, proposal_id, force, auth_context)
+        }
+        Some(("ack", sub_matches)) => {
+            let proposal_id = sub_matches
+                .get_one::<String>("id")
+                .ok_or_else(|| "Missing required argument: id")?;
+            let coop_id = sub_matches
+                .get_one::<String>("coop")
+                .ok_or_else(|| "Missing required argument: coop")?;
+            let outcome = sub_matches
+                .get_one::<String>("outcome")
+                .ok_or_else(|| "Missing required argument: outcome")?;
+            let target_addr = sub_matches
+                .get_one::<String>("node")
+                .map(|node| {
+                    node.parse::<Multiaddr>()
+                        .map_err(|e| format!("Invalid multiaddress: {}", e))
+                })
+                .transpose()?;
+
+            let signer: Box<dyn Signer> = match sub_matches.get_one::<String>("identity") {
+                Some(path) => signer::open_signer(Path::new(path))?,
+                None => Box::new(
+                    auth_context
+                        .get_identity(auth_context.identity_did())
+                        .cloned()
+                        .ok_or(
+                            "Acknowledgments are signed by a member; pass --identity or set \
+                             identity.key_path",
+                        )?,
+                ),
+            };
+
+            acknowledge_execution(
+                vm,
+                proposal_id,
+                coop_id,
+                outcome,
+                target_addr.as_ref(),
+                signer.as_ref(),
+            )
+            .await
+        }
         _ => Err("Unknown federation subcommand".into()),
     }
 }
 
 /// Convert a local proposal to a federated proposal
-fn local_to_federated_proposal(
+pub(crate) fn local_to_federated_proposal(
     local_proposal: &Proposal,
     scope: ProposalScope,
     voting_model: VotingModel,
@@ -423,6 +588,7 @@ where
         name: Some(format!("proposal-sharer-{}", Uuid::new_v4())),
         capabilities: vec!["proposal-sharing".to_string()],
         protocol_version: "1.0.0".to_string(),
+        peer_scores_path: None,
     };
 
     // Create and start the network node
@@ -552,6 +718,7 @@ async fn submit_remote_vote<S>(
     proposal_id: &str,
     vote_choice: VoteChoice,
     target_addr: &Multiaddr,
+    signer: &dyn Signer,
     auth_context: &AuthContext,
 ) -> Result<(), Box<dyn Error>>
 where
@@ -582,19 +749,15 @@ where
         .into());
     }
 
-    // Create a vote object
-    let voter_id = auth_context.current_identity_did.clone();
+    // Create a vote object, signed by the voter
+    let voter_id = signer.did().to_string();
     let ranked_choices = vote_choice_to_ranked_choices(&vote_choice);
 
     // Create a message to sign
-    let message = format!(
-        "Vote for proposal {} by {} with choices {:?}",
-        proposal_id, voter_id, ranked_choices
-    );
-
-    // We'd normally sign this with the identity's private key
-    // For now, we'll use a placeholder signature
-    let signature = format!("placeholder_signature_for_{}", voter_id);
+    let message = FederatedVote::canonical_message(proposal_id, &voter_id, &ranked_choices);
+    let signature = signer
+        .sign(message.as_bytes())
+        .map_err(|e| format!("Failed to sign vote: {}", e))?;
 
     let federated_vote = FederatedVote {
         proposal_id: proposal_id.to_string(),
@@ -611,6 +774,7 @@ where
         name: Some(format!("vote-submitter-{}", Uuid::new_v4())),
         capabilities: vec!["vote-submission".to_string()],
         protocol_version: "1.0.0".to_string(),
+        peer_scores_path: None,
     };
 
     // Create and start the network node
@@ -875,3 +1039,355 @@ where
 
     Ok(())
 }
+
+/// A peer's score as `federation peers` lists it
+#[derive(Debug, Serialize)]
+struct PeerRow {
+    #[serde(flatten)]
+    score: PeerScore,
+    standing: Standing,
+}
+
+/// Handle `federation peers`, which reads the scores a node saved and
+/// needs no storage backend
+pub fn handle_peers_command(matches: &ArgMatches, config: &Config) -> Result<(), Box<dyn Error>> {
+    let path = matches
+        .get_one::<String>("path")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| config.federation.peer_scores_path_or_default());
+    let now = crate::storage::utils::now_with_default();
+    let mut rows: Vec<PeerRow> = PeerScores::load(&path)?
+        .all()
+        .into_iter()
+        .map(|score| PeerRow {
+            standing: score.standing(now),
+            score,
+        })
+        .collect();
+    // Worst first, so misbehaving peers are at the top
+    rows.sort_by(|a, b| a.score.score.total_cmp(&b.score.score));
+
+    print_output(&rows, |rows| {
+        if rows.is_empty() {
+            println!("No peer scores in {}", path.display());
+            return;
+        }
+        println!(
+            "{:<54} {:<14} {:>7} {:>7} {:>8} {:>9} {:>5}  BANNED UNTIL",
+            "PEER", "STANDING", "SCORE", "VALID", "BAD SIG", "MALFORMED", "SPAM"
+        );
+        for row in rows {
+            let peer = &row.score;
+            let banned_until = match (row.standing, peer.banned_until) {
+                (Standing::Banned, Some(until)) => {
+                    chrono::DateTime::from_timestamp(until as i64, 0)
+                        .map(|dt| dt.to_rfc3339())
+                        .unwrap_or_else(|| until.to_string())
+                }
+                _ => "-".to_string(),
+            };
+            println!(
+                "{:<54} {:<14} {:>7.1} {:>7} {:>8} {:>9} {:>5}  {}",
+                peer.peer_id,
+                row.standing.to_string(),
+                peer.score,
+                peer.valid_messages,
+                peer.bad_signatures,
+                peer.malformed,
+                peer.spam,
+                banned_until
+            );
+        }
+    })
+}
+
+/// Summarize the federation state held in local storage
+fn federation_status<S>(vm: &VM<S>, auth_context: &AuthContext) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm
+        .get_storage_backend()
+        .ok_or_else(|| "Storage backend not available")?;
+
+    let proposal_keys = storage
+        .list_keys(
+            Some(auth_context),
+            FEDERATION_NAMESPACE,
+            Some(FEDERATION_PROPOSALS_PATH),
+        )
+        .unwrap_or_default();
+
+    let mut report = FederationStatusReport::default();
+    for key in proposal_keys {
+        let proposal_id = key.split('/').last().unwrap_or("unknown");
+        let full_key = FederationStorage::make_proposal_key(proposal_id);
+        let Some(proposal) = storage
+            .get(Some(auth_context), FEDERATION_NAMESPACE, &full_key)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<FederatedProposal>(&data).ok())
+        else {
+            continue;
+        };
+
+        report.proposals += 1;
+        *report
+            .by_status
+            .entry(format!("{:?}", proposal.status))
+            .or_insert(0) += 1;
+
+        let votes_key = FederationStorage::make_votes_key(proposal_id);
+        report.votes += storage
+            .list_keys(Some(auth_context), VOTES_NAMESPACE, Some(&votes_key))
+            .map(|keys| keys.len())
+            .unwrap_or(0);
+
+        let sync_key = FederationStorage::make_sync_key(proposal_id);
+        let sync_info = storage
+            .get(Some(auth_context), FEDERATION_NAMESPACE, &sync_key)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<FederationSyncMetadata>(&data).ok());
+        if let Some(metadata) = sync_info {
+            if report
+                .last_sync
+                .as_ref()
+                .map_or(true, |last| metadata.last_synced > last.last_synced)
+            {
+                report.last_sync = Some(metadata);
+            }
+        }
+    }
+
+    print_output(&report, |report| {
+        println!("=== Federation Status ===");
+        println!("Proposals: {}", report.proposals);
+        for (status, count) in &report.by_status {
+            println!("  {:<10} {}", status, count);
+        }
+        println!("Votes:     {}", report.votes);
+        match &report.last_sync {
+            Some(metadata) => println!(
+                "Last Sync: {} ({} from {})",
+                chrono::DateTime::from_timestamp(metadata.last_synced as i64, 0)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_else(|| metadata.last_synced.to_string()),
+                metadata.proposal_id,
+                metadata.source_node
+            ),
+            None => println!("Last Sync: never"),
+        }
+    })
+}
+
+/// Count the votes on a federated proposal, returning the winning option
+fn tally_federated_proposal<S>(
+    storage: &S,
+    proposal: &FederatedProposal,
+    auth_context: &AuthContext,
+) -> Result<Option<String>, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions,
+{
+    let federation_storage = FederationStorage::new();
+    let votes = federation_storage
+        .get_votes(storage, &proposal.proposal_id)
+        .unwrap_or_default();
+
+    // Voters' cooperatives come from the identities known to this node
+    let voter_identities: HashMap<_, _> = votes
+        .iter()
+        .filter_map(|vote| {
+            auth_context
+                .get_identity(&vote.voter)
+                .map(|identity| (vote.voter.clone(), identity.clone()))
+        })
+        .collect();
+    let ballots = federation_storage.prepare_ranked_ballots(&votes, proposal, &voter_identities);
+
+    Ok(execution::instant_runoff(&ballots, proposal.options.len())
+        .and_then(|winner| proposal.options.get(winner).cloned()))
+}
+
+/// Execute a federated proposal if it has not been yet, and report which
+/// cooperatives have acknowledged the result
+fn execute_federated_proposal<S>(
+    vm: &mut VM<S>,
+    proposal_id: &str,
+    force: bool,
+    auth_context: &AuthContext,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm
+        .get_storage_backend()
+        .ok_or_else(|| "Storage backend not available")?;
+    let federation_storage = FederationStorage::new();
+    let mut proposal = federation_storage
+        .get_proposal(&*storage, proposal_id)
+        .map_err(|e| format!("Proposal not found locally: {}", e))?;
+    let outcome = tally_federated_proposal(&*storage, &proposal, auth_context)?;
+
+    match proposal.status {
+        ProposalStatus::Executed => {}
+        ProposalStatus::Open | ProposalStatus::Closed => {
+            let now = vm.clock().now().timestamp();
+            if let Some(expires_at) = proposal.expires_at {
+                if now < expires_at && !force {
+                    let remaining = expires_at - now;
+                    return Err(format!(
+                        "Proposal has not expired yet. {} hours {} minutes remaining. Use --force to override.",
+                        remaining / 3600,
+                        remaining % 3600 / 60
+                    )
+                    .into());
+                }
+            }
+            let success = outcome.is_some();
+
+            proposal.status = ProposalStatus::Executed;
+            let mut forked = vm.fork().map_err(|e| format!("Failed to fork VM: {}", e))?;
+            let storage = forked
+                .get_storage_backend_mut()
+                .ok_or_else(|| "Storage backend not available in forked VM")?;
+            federation_storage
+                .save_proposal_with_auth(storage, Some(auth_context), proposal.clone())
+                .map_err(|e| format!("Failed to store federated proposal: {}", e))?;
+            vm.commit_fork_transaction()?;
+
+            let timestamp = vm.clock().unix_seconds();
+            if let Some(ledger) = &mut vm.dag {
+                let node = icn_ledger::DagNode {
+                    id: String::new(),
+                    parent_ids: vec![],
+                    timestamp,
+                    namespace: FEDERATION_NAMESPACE.to_string(),
+                    data: icn_ledger::NodeData::ProposalExecuted {
+                        proposal_id: proposal_id.to_string(),
+                        success,
+                    },
+                };
+                let node_id = ledger.append_on_tips(node)?;
+                println!("⚙️ DAG: Execution recorded as node {}", node_id);
+            }
+        }
+        _ => {
+            return Err(format!(
+                "Proposal cannot be executed. Current status: {:?}",
+                proposal.status
+            )
+            .into())
+        }
+    }
+
+    let nodes = vm.dag.as_ref().map(|ledger| ledger.nodes().as_slice());
+    let report = execution::ack_report(&proposal, outcome.as_deref(), nodes.unwrap_or_default());
+    print_output(&report, print_ack_report)
+}
+
+fn print_ack_report(report: &AckReport) {
+    println!("=== Execution of {} ===", report.proposal_id);
+    match &report.outcome {
+        Some(outcome) => println!("Outcome: {}", outcome),
+        None => println!("Outcome: none (no votes expressed a preference)"),
+    }
+    println!("\nAcknowledged ({}):", report.acknowledged.len());
+    for ack in &report.acknowledged {
+        let mismatch = if ack.matches_outcome {
+            String::new()
+        } else {
+            format!(" ⚠️  applied \"{}\"", ack.outcome)
+        };
+        println!(
+            "  ✅ {} (signed by {}){}",
+            ack.coop_id, ack.signer, mismatch
+        );
+    }
+    if !report.missing.is_empty() {
+        println!("\nNot yet acknowledged ({}):", report.missing.len());
+        for coop in &report.missing {
+            println!("  ⏳ {}", coop);
+        }
+    }
+    if report.invalid > 0 {
+        println!(
+            "\n⚠️  Ignored {} acknowledgment(s) with invalid signatures",
+            report.invalid
+        );
+    }
+}
+
+/// Sign an acknowledgment that `coop_id` applied `outcome`, record it on
+/// the DAG ledger, and send it to `target_addr` if given
+async fn acknowledge_execution<S>(
+    vm: &mut VM<S>,
+    proposal_id: &str,
+    coop_id: &str,
+    outcome: &str,
+    target_addr: Option<&Multiaddr>,
+    signer: &dyn Signer,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm
+        .get_storage_backend()
+        .ok_or_else(|| "Storage backend not available")?;
+    FederationStorage::new()
+        .get_proposal(&*storage, proposal_id)
+        .map_err(|e| format!("Proposal not found locally: {}", e))?;
+
+    let message = ExecutionAck::canonical_message(proposal_id, coop_id, outcome);
+    let ack = ExecutionAck {
+        proposal_id: proposal_id.to_string(),
+        coop_id: coop_id.to_string(),
+        outcome: outcome.to_string(),
+        signer: signer.did().to_string(),
+        signature: signer
+            .sign(message.as_bytes())
+            .map_err(|e| format!("Failed to sign acknowledgment: {}", e))?,
+    };
+    ack.verify()
+        .map_err(|e| format!("Acknowledgment does not verify: {}", e))?;
+
+    let timestamp = vm.clock().unix_seconds();
+    let ledger = vm.dag.as_mut().ok_or("DAG ledger not available")?;
+    let node = icn_ledger::DagNode {
+        id: String::new(),
+        parent_ids: vec![],
+        timestamp,
+        namespace: FEDERATION_NAMESPACE.to_string(),
+        data: icn_ledger::NodeData::ExecutionAck {
+            proposal_id: ack.proposal_id.clone(),
+            coop_id: ack.coop_id.clone(),
+            signer: ack.signer.clone(),
+            outcome: ack.outcome.clone(),
+            signature: ack.signature.clone(),
+        },
+    };
+    let node_id = ledger.append_on_tips(node)?;
+
+    if let Some(target_addr) = target_addr {
+        let node_config = NodeConfig {
+            port: Some(0), // Use any available port
+            bootstrap_nodes: vec![target_addr.clone()],
+            name: Some(format!("ack-sender-{}", Uuid::new_v4())),
+            capabilities: vec!["execution-ack".to_string()],
+            protocol_version: "1.0.0".to_string(),
+            peer_scores_path: None,
+        };
+        let mut node = NetworkNode::new(node_config)
+            .await
+            .map_err(|e| format!("Failed to create network node: {}", e))?;
+        node.send_execution_ack(ack)
+            .await
+            .map_err(|e| format!("Failed to send acknowledgment: {}", e))?;
+        node.stop().await;
+    }
+
+    println!(
+        "✅ {} acknowledged proposal {} ({}), recorded as node {}",
+        coop_id, proposal_id, outcome, node_id
+    );
+    Ok(())
+}
@@ -47,6 +47,9 @@ mod tests {
             execution_result: None,
             deliberation_started_at: Some(Utc::now()),
             min_deliberation_hours: Some(24),
+            labels: vec![],
+            imported_from: None,
+            cloned_from: None,
         }
     }
     
@@ -212,4 +215,4 @@ mod tests {
         assert_eq!(retrieved_proposal.status, federated_proposal.status);
         assert_eq!(retrieved_proposal.namespace, federated_proposal.namespace);
     }
-} 
\ No newline at end of file
+} 
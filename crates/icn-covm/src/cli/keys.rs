@@ -0,0 +1,614 @@
+//! Identity keypair CLI functionality.
+//!
+//! This module manages the identity file that the node or operator acts as:
+//! the file named by `identity.key_path` in the configuration, which holds an
+//! Ed25519 keypair and its `did:key` DID. The same key signs federation votes
+//! and genesis nodes and logs in to the API. The file may instead be a token
+//! file naming a key on a hardware token (see `identity::signer`).
+//!
+//! The module includes functionality for:
+//! - Generating a keypair, optionally sealed with a passphrase
+//! - Enrolling a key on a PKCS#11 token or FIDO2 security key
+//! - Showing the DID and public key of a key file
+//! - Rotating the keypair, with the old key vouching for the new one
+//! - Signing messages and API login challenges
+//! - Verifying signatures against a key file, public key, or DID
+
+use crate::api::auth::{login_challenge, TokenRequest};
+use crate::cli::output::print_output;
+use crate::config::Config;
+use crate::identity::keystore::{self, KeyFileSummary, KeystoreError, PASSPHRASE_ENV};
+use crate::identity::signer::{self, Enrollment, TOKEN_PIN_ENV};
+use crate::identity::{self, Identity};
+use chrono::Utc;
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+/// Key file used when neither `--path` nor `identity.key_path` is set
+pub const DEFAULT_KEY_PATH: &str = "./identity.json";
+
+/// Create the keys command and its subcommands
+pub fn keys_command() -> Command {
+    Command::new("keys")
+        .about("Manage the node or operator identity keypair")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .value_name("FILE_PATH")
+                .help("Identity key file (default: identity.key_path, or ./identity.json)")
+                .global(true),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Generate a new identity keypair")
+                .arg(
+                    Arg::new("username")
+                        .long("username")
+                        .value_name("NAME")
+                        .help("Public username for the identity profile")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("full-name")
+                        .long("full-name")
+                        .value_name("NAME")
+                        .help("Optional full name for the identity profile"),
+                )
+                .arg(
+                    Arg::new("type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .help("Identity type, e.g. member, cooperative, or service")
+                        .default_value("member"),
+                )
+                .arg(
+                    Arg::new("encrypt")
+                        .long("encrypt")
+                        .action(ArgAction::SetTrue)
+                        .help(format!(
+                            "Seal the key file with a passphrase (from {} or prompted)",
+                            PASSPHRASE_ENV
+                        )),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .action(ArgAction::SetTrue)
+                        .help("Overwrite an existing key file"),
+                ),
+        )
+        .subcommand(
+            Command::new("enroll")
+                .about("Write a token file for a key on a hardware token")
+                .after_help(format!(
+                    "The token PIN is read from {} or prompted for.",
+                    TOKEN_PIN_ENV
+                ))
+                .arg(
+                    Arg::new("username")
+                        .long("username")
+                        .value_name("NAME")
+                        .help("Public username for the identity profile")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .help("Identity type, e.g. member, cooperative, or service")
+                        .default_value("member"),
+                )
+                .arg(
+                    Arg::new("pkcs11-module")
+                        .long("pkcs11-module")
+                        .value_name("FILE_PATH")
+                        .help("PKCS#11 module of the token holding an Ed25519 key pair")
+                        .requires_all(["token", "key-label"]),
+                )
+                .arg(
+                    Arg::new("token")
+                        .long("token")
+                        .value_name("LABEL")
+                        .help("Label of the PKCS#11 token")
+                        .requires("pkcs11-module"),
+                )
+                .arg(
+                    Arg::new("key-label")
+                        .long("key-label")
+                        .value_name("LABEL")
+                        .help("Label of the key pair on the PKCS#11 token")
+                        .requires("pkcs11-module"),
+                )
+                .arg(
+                    Arg::new("fido2")
+                        .long("fido2")
+                        .action(ArgAction::SetTrue)
+                        .help("Derive the key from a new credential on a FIDO2 security key"),
+                )
+                .arg(
+                    Arg::new("rp-id")
+                        .long("rp-id")
+                        .value_name("ID")
+                        .help("Relying party ID of the FIDO2 credential")
+                        .default_value("icn-covm"),
+                )
+                .group(
+                    ArgGroup::new("device")
+                        .args(["pkcs11-module", "fido2"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .action(ArgAction::SetTrue)
+                        .help("Overwrite an existing key file"),
+                ),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("Show the DID and public key of a key file")
+                .arg(
+                    Arg::new("pem")
+                        .long("pem")
+                        .action(ArgAction::SetTrue)
+                        .help("Print only the public key, as PEM (e.g. for verify-vote)"),
+                ),
+        )
+        .subcommand(
+            Command::new("rotate")
+                .about("Replace the keypair, keeping the profile and archiving the old file"),
+        )
+        .subcommand(
+            Command::new("sign")
+                .about("Sign a message, a file, or an API login challenge")
+                .arg(
+                    Arg::new("message")
+                        .long("message")
+                        .value_name("TEXT")
+                        .help("Message to sign"),
+                )
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .value_name("FILE_PATH")
+                        .help("File whose contents to sign"),
+                )
+                .arg(
+                    Arg::new("login")
+                        .long("login")
+                        .action(ArgAction::SetTrue)
+                        .help("Print a signed request body for POST /api/v1/auth/token"),
+                )
+                .arg(
+                    Arg::new("timestamp")
+                        .long("timestamp")
+                        .value_name("UNIX_SECONDS")
+                        .help("Timestamp of the login challenge (default: now)")
+                        .value_parser(clap::value_parser!(u64))
+                        .requires("login"),
+                )
+                .group(
+                    ArgGroup::new("input")
+                        .args(["message", "file", "login"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Verify a signature over a message or file")
+                .arg(
+                    Arg::new("signature")
+                        .long("signature")
+                        .value_name("MULTIBASE")
+                        .help("Signature to verify")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("message")
+                        .long("message")
+                        .value_name("TEXT")
+                        .help("Signed message"),
+                )
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .value_name("FILE_PATH")
+                        .help("File whose contents were signed"),
+                )
+                .group(
+                    ArgGroup::new("input")
+                        .args(["message", "file"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("public-key")
+                        .long("public-key")
+                        .value_name("MULTIBASE")
+                        .help("Public key of the signer (default: the key file's)"),
+                )
+                .arg(
+                    Arg::new("did")
+                        .long("did")
+                        .value_name("DID")
+                        .help("did:key DID of the signer (default: the key file's)")
+                        .conflicts_with("public-key"),
+                ),
+        )
+}
+
+/// Handle keys subcommands
+pub fn handle_keys_command(matches: &ArgMatches, config: &Config) -> Result<(), Box<dyn Error>> {
+    let key_path = matches
+        .get_one::<String>("path")
+        .map(PathBuf::from)
+        .or_else(|| config.identity.key_path.clone())
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_KEY_PATH));
+
+    match matches.subcommand() {
+        Some(("generate", generate_matches)) => {
+            let username = generate_matches
+                .get_one::<String>("username")
+                .ok_or("Username is required")?;
+            let identity_type = generate_matches
+                .get_one::<String>("type")
+                .map(String::as_str)
+                .unwrap_or("member");
+            handle_generate_command(
+                &key_path,
+                username,
+                generate_matches
+                    .get_one::<String>("full-name")
+                    .map(String::as_str),
+                identity_type,
+                generate_matches.get_flag("encrypt"),
+                generate_matches.get_flag("force"),
+            )
+        }
+        Some(("enroll", enroll_matches)) => {
+            let username = enroll_matches
+                .get_one::<String>("username")
+                .ok_or("Username is required")?;
+            let identity_type = enroll_matches
+                .get_one::<String>("type")
+                .map(String::as_str)
+                .unwrap_or("member");
+            let enrollment = match enroll_matches.get_one::<String>("pkcs11-module") {
+                Some(module) => Enrollment::Pkcs11 {
+                    module: Path::new(module),
+                    token_label: enroll_matches
+                        .get_one::<String>("token")
+                        .ok_or("--token is required")?,
+                    key_label: enroll_matches
+                        .get_one::<String>("key-label")
+                        .ok_or("--key-label is required")?,
+                },
+                None => Enrollment::Fido2 {
+                    rp_id: enroll_matches
+                        .get_one::<String>("rp-id")
+                        .map(String::as_str)
+                        .unwrap_or("icn-covm"),
+                },
+            };
+            handle_enroll_command(
+                &key_path,
+                &enrollment,
+                username,
+                identity_type,
+                enroll_matches.get_flag("force"),
+            )
+        }
+        Some(("show", show_matches)) if show_matches.get_flag("pem") => {
+            handle_show_pem_command(&key_path)
+        }
+        Some(("show", _)) => handle_show_command(&key_path),
+        Some(("rotate", _)) => handle_rotate_command(&key_path),
+        Some(("sign", sign_matches)) => {
+            if sign_matches.get_flag("login") {
+                let timestamp = sign_matches
+                    .get_one::<u64>("timestamp")
+                    .copied()
+                    .unwrap_or_else(|| Utc::now().timestamp() as u64);
+                return handle_login_command(&key_path, timestamp);
+            }
+            let message = message_bytes(sign_matches)?;
+            handle_sign_command(&key_path, &message)
+        }
+        Some(("verify", verify_matches)) => {
+            let signature = verify_matches
+                .get_one::<String>("signature")
+                .ok_or("Signature is required")?;
+            let public_key = match (
+                verify_matches.get_one::<String>("public-key"),
+                verify_matches.get_one::<String>("did"),
+            ) {
+                (Some(public_key), _) => public_key.clone(),
+                (None, Some(did)) => did
+                    .strip_prefix("did:key:")
+                    .ok_or_else(|| format!("Not a did:key DID: {}", did))?
+                    .to_string(),
+                (None, None) => keystore::inspect_identity(&key_path)?.public_key_multibase,
+            };
+            let message = message_bytes(verify_matches)?;
+            handle_verify_command(&public_key, &message, signature)
+        }
+        _ => unreachable!("Subcommand should be required"),
+    }
+}
+
+/// The bytes given with `--message` or `--file`
+fn message_bytes(matches: &ArgMatches) -> Result<Vec<u8>, Box<dyn Error>> {
+    if let Some(message) = matches.get_one::<String>("message") {
+        return Ok(message.as_bytes().to_vec());
+    }
+    let file = matches
+        .get_one::<String>("file")
+        .ok_or("Either --message or --file is required")?;
+    Ok(fs::read(file)?)
+}
+
+/// Get a passphrase for a new sealed file, asking twice on the terminal
+fn new_passphrase() -> Result<String, Box<dyn Error>> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    if !io::stdin().is_terminal() {
+        return Err(format!(
+            "Set {} to encrypt the key file non-interactively",
+            PASSPHRASE_ENV
+        )
+        .into());
+    }
+    let passphrase = rpassword::prompt_password("New passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err("Passphrase must not be empty".into());
+    }
+    if rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+        return Err("Passphrases do not match".into());
+    }
+    Ok(passphrase)
+}
+
+/// Load a key file, returning the passphrase it was sealed with, if any
+fn unlock(path: &Path) -> Result<(Identity, Option<String>), Box<dyn Error>> {
+    let passphrase = if keystore::is_encrypted(path)? {
+        let prompt = format!("Passphrase for {}: ", path.display());
+        let passphrase = keystore::passphrase_from_env_or_prompt(&prompt)?
+            .ok_or_else(|| KeystoreError::PassphraseRequired(path.to_path_buf()))?;
+        Some(passphrase)
+    } else {
+        None
+    };
+    let identity = keystore::load_identity(path, passphrase.as_deref())?;
+    Ok((identity, passphrase))
+}
+
+/// Handle the generate command to create a new key file
+pub fn handle_generate_command(
+    key_path: &Path,
+    username: &str,
+    full_name: Option<&str>,
+    identity_type: &str,
+    encrypt: bool,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    if key_path.exists() && !force {
+        return Err(format!(
+            "Key file {} already exists; use --force to overwrite it or `keys rotate` to replace the keypair",
+            key_path.display()
+        )
+        .into());
+    }
+
+    let passphrase = if encrypt {
+        Some(new_passphrase()?)
+    } else {
+        None
+    };
+    let identity = Identity::new(
+        username.to_string(),
+        full_name.map(str::to_string),
+        identity_type.to_string(),
+        None,
+    )?;
+    keystore::save_identity(key_path, &identity, passphrase.as_deref())?;
+
+    let summary = keystore::inspect_identity(key_path)?;
+    print_output(&summary, |summary| {
+        println!("🔑 Generated identity key: {}", key_path.display());
+        print_summary(summary);
+    })
+}
+
+/// Handle the enroll command to write a token file for a hardware key
+pub fn handle_enroll_command(
+    key_path: &Path,
+    enrollment: &Enrollment,
+    username: &str,
+    identity_type: &str,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    if key_path.exists() && !force {
+        return Err(format!(
+            "Key file {} already exists; use --force to overwrite it",
+            key_path.display()
+        )
+        .into());
+    }
+
+    let pin = signer::token_pin("Token PIN: ")?;
+    if let Enrollment::Fido2 { .. } = enrollment {
+        eprintln!("👆 Touch the security key when it blinks (twice)");
+    }
+    let file = signer::enroll(enrollment, pin.as_deref(), username, identity_type)?;
+    signer::save_token_file(key_path, &file)?;
+
+    let summary = keystore::inspect_identity(key_path)?;
+    print_output(&summary, |summary| {
+        println!("🔑 Enrolled hardware key: {}", key_path.display());
+        print_summary(summary);
+    })
+}
+
+/// Handle the show command to print a key file's public details
+pub fn handle_show_command(key_path: &Path) -> Result<(), Box<dyn Error>> {
+    let summary = keystore::inspect_identity(key_path)?;
+    print_output(&summary, |summary| {
+        println!("🔑 Identity key: {}", key_path.display());
+        print_summary(summary);
+    })
+}
+
+/// Print the public key as PEM, for tools that take a key file
+pub fn handle_show_pem_command(key_path: &Path) -> Result<(), Box<dyn Error>> {
+    let summary = keystore::inspect_identity(key_path)?;
+    let (_, public_key) = multibase::decode(&summary.public_key_multibase)
+        .map_err(|e| format!("Invalid public key in {}: {}", key_path.display(), e))?;
+    print!("{}", identity::public_key_to_pem(&public_key));
+    Ok(())
+}
+
+fn print_summary(summary: &KeyFileSummary) {
+    println!("   DID: {}", summary.did);
+    println!("   Public key: {}", summary.public_key_multibase);
+    match (&summary.public_username, &summary.identity_type) {
+        (Some(username), Some(identity_type)) => {
+            println!("   Username: {}", username);
+            println!("   Type: {}", identity_type);
+        }
+        _ => println!("   Profile: sealed"),
+    }
+    println!(
+        "   Encrypted: {}",
+        if summary.encrypted { "yes" } else { "no" }
+    );
+    if let Some(token) = &summary.token {
+        println!("   Hardware token: {}", token);
+    }
+}
+
+/// Result of rotating a key file
+#[derive(Debug, Serialize)]
+pub struct RotationReport {
+    pub old_did: String,
+    pub new_did: String,
+    pub timestamp: u64,
+    /// Signature by the old key over `rotation_statement(old, new, timestamp)`
+    pub signature: String,
+    /// Where the old key file was moved
+    pub archived_to: PathBuf,
+}
+
+/// The message an old key signs to vouch for its replacement
+pub fn rotation_statement(old_did: &str, new_did: &str, timestamp: u64) -> Vec<u8> {
+    format!("icn-key-rotation|{}|{}|{}", old_did, new_did, timestamp).into_bytes()
+}
+
+/// Handle the rotate command to replace a key file's keypair
+pub fn handle_rotate_command(key_path: &Path) -> Result<(), Box<dyn Error>> {
+    let (old, passphrase) = unlock(key_path)?;
+    let new = Identity::new(
+        old.profile.public_username.clone(),
+        old.profile.full_name.clone(),
+        old.identity_type.clone(),
+        Some(old.profile.other_fields.clone()),
+    )?;
+    let timestamp = Utc::now().timestamp() as u64;
+    let signature = old.sign(&rotation_statement(&old.did, &new.did, timestamp))?;
+
+    let mut archived_to = key_path.as_os_str().to_owned();
+    archived_to.push(format!(".{}.old", timestamp));
+    let archived_to = PathBuf::from(archived_to);
+    fs::copy(key_path, &archived_to)?;
+    keystore::save_identity(key_path, &new, passphrase.as_deref())?;
+
+    let report = RotationReport {
+        old_did: old.did,
+        new_did: new.did,
+        timestamp,
+        signature,
+        archived_to,
+    };
+    print_output(&report, |report| {
+        println!("🔄 Rotated identity key: {}", key_path.display());
+        println!("   Old DID: {}", report.old_did);
+        println!("   New DID: {}", report.new_did);
+        println!("   Rotation signature: {}", report.signature);
+        println!("   Old key archived to: {}", report.archived_to.display());
+    })
+}
+
+/// Handle the sign command for a message or file
+pub fn handle_sign_command(key_path: &Path, message: &[u8]) -> Result<(), Box<dyn Error>> {
+    #[derive(Serialize)]
+    struct SignedMessage {
+        did: String,
+        signature: String,
+    }
+
+    let signer = signer::open_signer(key_path)?;
+    let signed = SignedMessage {
+        signature: signer.sign(message)?,
+        did: signer.did().to_string(),
+    };
+    print_output(&signed, |signed| println!("{}", signed.signature))
+}
+
+/// Handle `sign --login`, printing a request body for POST /api/v1/auth/token
+pub fn handle_login_command(key_path: &Path, timestamp: u64) -> Result<(), Box<dyn Error>> {
+    let signer = signer::open_signer(key_path)?;
+    let request = TokenRequest {
+        signature: signer.sign(&login_challenge(signer.did(), timestamp))?,
+        did: signer.did().to_string(),
+        timestamp,
+    };
+    println!("{}", serde_json::to_string_pretty(&request)?);
+    Ok(())
+}
+
+/// Handle the verify command, failing if the signature is not valid
+pub fn handle_verify_command(
+    public_key_multibase: &str,
+    message: &[u8],
+    signature: &str,
+) -> Result<(), Box<dyn Error>> {
+    identity::verify_signature(public_key_multibase, message, signature)
+        .map_err(|e| format!("Signature is not valid: {}", e))?;
+    println!("✅ Signature is valid");
+    println!("   Signer: did:key:{}", public_key_multibase);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_archives_old_key_and_keeps_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("identity.json");
+        handle_generate_command(&key_path, "alice", None, "member", false, false).unwrap();
+        let old = keystore::load_identity(&key_path, None).unwrap();
+        assert!(handle_generate_command(&key_path, "alice", None, "member", false, false).is_err());
+
+        handle_rotate_command(&key_path).unwrap();
+        let new = keystore::load_identity(&key_path, None).unwrap();
+        assert_ne!(new.did, old.did);
+        assert_eq!(new.profile, old.profile);
+        assert_eq!(new.identity_type, old.identity_type);
+
+        let archived: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".old"))
+            .collect();
+        assert_eq!(archived.len(), 1);
+        let archived = keystore::load_identity(&archived[0].path(), None).unwrap();
+        assert_eq!(archived.did, old.did);
+    }
+}
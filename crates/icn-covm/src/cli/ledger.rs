@@ -0,0 +1,957 @@
+//! DAG ledger CLI functionality.
+//!
+//! This module provides commands that operate directly on a DAG ledger file,
+//! independently of the proposal commands that append to it as a side effect.
+//!
+//! The module includes functionality for:
+//! - Merging a divergent ledger with a structured conflict report
+//! - Generating keys for private namespaces
+//! - Creating signed genesis nodes and epoch markers
+//! - Reporting node counts, activity over time, and top voters
+//! - Tracing a proposal or node back through its ancestors
+//! - Verifying node hashes, links, and genesis signatures, and computing
+//!   the ledger's Merkle root
+//! - Diffing, exporting, and importing nodes as JSONL
+//! - Compacting the ledger file after merges and imports
+//! - Rendering the DAG as a Graphviz graph
+
+use crate::cli::output::{print_as, with_json_flag};
+use crate::config::Config;
+use crate::identity::{self, signer};
+use chrono::{Datelike, Utc};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use icn_ledger::{
+    genesis_signing_bytes, DagDiff, DagLedger, DagNode, IssueKind, LedgerStats, MergeReport,
+    NamespaceKey, NodeData, VerifyIssue, VerifyReport,
+};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub use crate::config::DEFAULT_DAG_PATH;
+
+/// Create the ledger command and its subcommands
+pub fn ledger_command() -> Command {
+    Command::new("ledger")
+        .visible_alias("dag")
+        .about("Inspect and maintain the DAG ledger")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("dag-path")
+                .long("dag-path")
+                .value_name("PATH")
+                .help("Path to the DAG ledger file (default: ledger.dag_path, or ./dag_ledger.jsonl)")
+                .global(true),
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("Merge nodes from a divergent ledger, reporting conflicting events")
+                .arg(
+                    Arg::new("other")
+                        .long("other")
+                        .value_name("FILE_PATH")
+                        .help("Path to the ledger file to merge in")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Report what would be merged without writing the ledger"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the merge report as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("init")
+                .about("Create the signed genesis node for a new cooperative's namespace")
+                .arg(
+                    Arg::new("namespace")
+                        .long("namespace")
+                        .value_name("NAMESPACE")
+                        .help("Namespace of the cooperative")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("identity")
+                        .long("identity")
+                        .value_name("FILE_PATH")
+                        .help("Founder identity or hardware token file (default: identity.key_path)"),
+                ),
+        )
+        .subcommand(
+            Command::new("epoch")
+                .about("Append an epoch marker checkpointing a namespace")
+                .arg(
+                    Arg::new("namespace")
+                        .long("namespace")
+                        .value_name("NAMESPACE")
+                        .help("Namespace to checkpoint")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("epoch")
+                        .long("epoch")
+                        .value_name("LABEL")
+                        .help("Epoch label (default: current year)"),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Show node counts, activity over time, and top voters")
+                .arg(
+                    Arg::new("bucket")
+                        .long("bucket")
+                        .value_name("PERIOD")
+                        .help("Activity histogram bucket: hour, day, week, or month")
+                        .default_value("day"),
+                )
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .value_name("COUNT")
+                        .help("Number of top voters to list")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the statistics as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("trace")
+                .about("Show a proposal or node together with all of its ancestors")
+                .arg(
+                    Arg::new("proposal")
+                        .long("proposal")
+                        .value_name("PROPOSAL_ID")
+                        .help("Trace the nodes recorded for a proposal")
+                        .conflicts_with("node"),
+                )
+                .arg(
+                    Arg::new("node")
+                        .long("node")
+                        .value_name("NODE_ID")
+                        .help("Trace a single node"),
+                )
+                .arg(
+                    Arg::new("namespace")
+                        .long("namespace")
+                        .value_name("NAMESPACE")
+                        .help("Only show nodes in this namespace"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the nodes as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check node hashes, links, and genesis signatures, and report the Merkle root")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the verification report as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("compact")
+                .about("Drop repeated nodes and rewrite the ledger file with every node after its parents")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the compaction report as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Compare the ledger with another ledger file")
+                .arg(
+                    Arg::new("other")
+                        .long("other")
+                        .value_name("FILE_PATH")
+                        .help("Path to the ledger file to compare against")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("FILE_PATH")
+                        .help("Write the nodes missing from the other ledger to this file"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the diff as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export nodes to a JSONL file")
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("FILE_PATH")
+                        .help("File to write the nodes to")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("namespace")
+                        .long("namespace")
+                        .value_name("NAMESPACE")
+                        .help("Only export nodes in this namespace"),
+                )
+                .arg(
+                    Arg::new("proposal")
+                        .long("proposal")
+                        .value_name("PROPOSAL_ID")
+                        .help("Only export a proposal's nodes and their ancestors"),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import the nodes missing from the ledger from another ledger file")
+                .arg(
+                    Arg::new("input")
+                        .long("input")
+                        .value_name("FILE_PATH")
+                        .help("Path to the ledger file to import from")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("graph")
+                .about("Render the DAG in Graphviz DOT format")
+                .arg(
+                    Arg::new("namespace")
+                        .long("namespace")
+                        .value_name("NAMESPACE")
+                        .help("Only render nodes in this namespace"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("FILE_PATH")
+                        .help("File to write the graph to (default: stdout)"),
+                ),
+        )
+        .subcommand(
+            Command::new("keygen")
+                .about("Generate a key for encrypting a private namespace's payloads")
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("FILE_PATH")
+                        .help("File to write the hex-encoded key to")
+                        .required(true),
+                ),
+        )
+}
+
+/// Handle the ledger command and its subcommands
+///
+/// `config` supplies the ledger file and founder identity when they are
+/// not given as flags.
+pub fn handle_ledger_command(matches: &ArgMatches, config: &Config) -> Result<(), Box<dyn Error>> {
+    let dag_path = matches
+        .get_one::<String>("dag-path")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| config.ledger.dag_path_or_default());
+
+    match matches.subcommand() {
+        Some(("merge", merge_matches)) => {
+            let other_path = merge_matches
+                .get_one::<String>("other")
+                .ok_or("Other ledger path is required")?;
+            handle_merge_command(
+                &dag_path,
+                Path::new(other_path),
+                merge_matches.get_flag("dry-run"),
+                merge_matches.get_flag("json"),
+            )
+        }
+        Some(("init", init_matches)) => {
+            let namespace = init_matches
+                .get_one::<String>("namespace")
+                .ok_or("Namespace is required")?;
+            let identity_path = init_matches
+                .get_one::<String>("identity")
+                .map(PathBuf::from)
+                .or_else(|| config.identity.key_path.clone())
+                .ok_or("Identity file is required: pass --identity or set identity.key_path")?;
+            handle_init_command(&dag_path, namespace, &identity_path)
+        }
+        Some(("epoch", epoch_matches)) => {
+            let namespace = epoch_matches
+                .get_one::<String>("namespace")
+                .ok_or("Namespace is required")?;
+            let epoch = epoch_matches
+                .get_one::<String>("epoch")
+                .cloned()
+                .unwrap_or_else(|| Utc::now().year().to_string());
+            handle_epoch_command(&dag_path, namespace, &epoch)
+        }
+        Some(("stats", stats_matches)) => {
+            let bucket = stats_matches
+                .get_one::<String>("bucket")
+                .map(String::as_str)
+                .unwrap_or("day");
+            let top = stats_matches.get_one::<usize>("top").copied().unwrap_or(10);
+            handle_stats_command(&dag_path, bucket, top, stats_matches.get_flag("json"))
+        }
+        Some(("trace", trace_matches)) => handle_trace_command(
+            &dag_path,
+            trace_matches
+                .get_one::<String>("proposal")
+                .map(String::as_str),
+            trace_matches.get_one::<String>("node").map(String::as_str),
+            trace_matches
+                .get_one::<String>("namespace")
+                .map(String::as_str),
+            trace_matches.get_flag("json"),
+        ),
+        Some(("verify", verify_matches)) => {
+            handle_verify_command(&dag_path, verify_matches.get_flag("json"))
+        }
+        Some(("compact", compact_matches)) => {
+            handle_compact_command(&dag_path, compact_matches.get_flag("json"))
+        }
+        Some(("diff", diff_matches)) => {
+            let other_path = diff_matches
+                .get_one::<String>("other")
+                .ok_or("Other ledger path is required")?;
+            handle_diff_command(
+                &dag_path,
+                Path::new(other_path),
+                diff_matches.get_one::<String>("output").map(Path::new),
+                diff_matches.get_flag("json"),
+            )
+        }
+        Some(("export", export_matches)) => {
+            let output_path = export_matches
+                .get_one::<String>("output")
+                .ok_or("Output path is required")?;
+            handle_export_command(
+                &dag_path,
+                Path::new(output_path),
+                export_matches
+                    .get_one::<String>("namespace")
+                    .map(String::as_str),
+                export_matches
+                    .get_one::<String>("proposal")
+                    .map(String::as_str),
+            )
+        }
+        Some(("import", import_matches)) => {
+            let input_path = import_matches
+                .get_one::<String>("input")
+                .ok_or("Input path is required")?;
+            handle_import_command(&dag_path, Path::new(input_path))
+        }
+        Some(("graph", graph_matches)) => handle_graph_command(
+            &dag_path,
+            graph_matches
+                .get_one::<String>("namespace")
+                .map(String::as_str),
+            graph_matches.get_one::<String>("output").map(Path::new),
+        ),
+        Some(("keygen", keygen_matches)) => {
+            let output_path = keygen_matches
+                .get_one::<String>("output")
+                .ok_or("Output path is required")?;
+            handle_keygen_command(Path::new(output_path))
+        }
+        _ => unreachable!("Subcommand should be required"),
+    }
+}
+
+/// Load the ledger at `dag_path`, keeping the path so it can be written back
+fn open_ledger(dag_path: &Path) -> Result<DagLedger, Box<dyn Error>> {
+    let mut ledger = DagLedger::load_from_file(dag_path)?;
+    ledger.set_path(dag_path.to_path_buf());
+    Ok(ledger)
+}
+
+/// Handle the init command to create a namespace's signed genesis node
+pub fn handle_init_command(
+    dag_path: &Path,
+    namespace: &str,
+    identity_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let founder = signer::open_signer(identity_path)?;
+    let timestamp = Utc::now().timestamp() as u64;
+    let signature = founder.sign(&genesis_signing_bytes(namespace, founder.did(), timestamp))?;
+
+    let mut ledger = open_ledger(dag_path)?;
+    let genesis_id = ledger.create_genesis(
+        namespace,
+        founder.did(),
+        &founder.public_identity().public_key_multibase,
+        &signature,
+        timestamp,
+    )?;
+    ledger.persist_appended()?;
+
+    println!("🌱 Created genesis for namespace '{}'", namespace);
+    println!("   Node: {}", genesis_id);
+    println!("   Founder: {}", founder.did());
+    Ok(())
+}
+
+/// Handle the epoch command to checkpoint a namespace
+pub fn handle_epoch_command(
+    dag_path: &Path,
+    namespace: &str,
+    epoch: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut ledger = open_ledger(dag_path)?;
+    let marker_id = ledger.append_epoch_marker(namespace, epoch, Utc::now().timestamp() as u64)?;
+    ledger.persist_appended()?;
+
+    println!("📍 Marked epoch '{}' for namespace '{}'", epoch, namespace);
+    println!("   Node: {}", marker_id);
+    Ok(())
+}
+
+/// Convert a histogram bucket name to its width in seconds
+fn bucket_secs(bucket: &str) -> Result<u64, Box<dyn Error>> {
+    match bucket {
+        "hour" => Ok(60 * 60),
+        "day" => Ok(24 * 60 * 60),
+        "week" => Ok(7 * 24 * 60 * 60),
+        "month" => Ok(30 * 24 * 60 * 60),
+        other => Err(format!("Unknown bucket '{}': use hour, day, week, or month", other).into()),
+    }
+}
+
+/// Handle the stats command to report ledger activity
+pub fn handle_stats_command(
+    dag_path: &Path,
+    bucket: &str,
+    top: usize,
+    json: bool,
+) -> Result<(), Box<dyn Error>> {
+    let ledger = open_ledger(dag_path)?;
+    let stats = ledger.stats(bucket_secs(bucket)?, top);
+
+    print_as(with_json_flag(json), &stats, |stats| {
+        print_stats(stats, dag_path)
+    })
+}
+
+fn print_stats(stats: &LedgerStats, dag_path: &Path) {
+    println!("📊 Ledger Statistics: {}", dag_path.display());
+    println!("   Total nodes: {}", stats.total_nodes);
+
+    println!("\n   {:<20} {:>8}", "Node type", "Count");
+    for (node_type, count) in &stats.by_type {
+        println!("   {:<20} {:>8}", node_type, count);
+    }
+
+    println!("\n   {:<20} {:>8}", "Namespace", "Count");
+    for (namespace, count) in &stats.by_namespace {
+        println!("   {:<20} {:>8}", namespace, count);
+    }
+
+    if !stats.activity.is_empty() {
+        let max = stats.activity.values().copied().max().unwrap_or(1).max(1);
+        println!("\n   Activity:");
+        for (start, count) in &stats.activity {
+            let label = chrono::DateTime::<Utc>::from_timestamp(*start as i64, 0)
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| start.to_string());
+            let bar = "█".repeat((count * 40).div_ceil(max));
+            println!("   {} {:>6} {}", label, count, bar);
+        }
+    }
+
+    if !stats.top_voters.is_empty() {
+        println!("\n   {:<40} {:>8}", "Top voters", "Votes");
+        for (voter, count) in &stats.top_voters {
+            println!("   {:<40} {:>8}", voter, count);
+        }
+    }
+}
+
+/// Format a node timestamp for display
+fn format_timestamp(timestamp: u64) -> String {
+    chrono::DateTime::<Utc>::from_timestamp(timestamp as i64, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// One-line summary of a node's payload
+pub(crate) fn describe_node(node: &DagNode) -> String {
+    match &node.data {
+        NodeData::ProposalCreated { proposal_id, title } => {
+            format!("proposal {} \"{}\"", proposal_id, title)
+        }
+        NodeData::VoteCast {
+            proposal_id,
+            voter,
+            vote,
+        } => format!("{} voted {} on {}", voter, vote, proposal_id),
+        NodeData::ProposalExecuted {
+            proposal_id,
+            success,
+        } => format!(
+            "proposal {} {}",
+            proposal_id,
+            if *success { "succeeded" } else { "failed" }
+        ),
+        NodeData::TokenMinted {
+            resource,
+            recipient,
+            amount,
+        } => format!("{} {} to {}", amount, resource, recipient),
+        NodeData::Genesis { founder, .. } => format!("founded by {}", founder),
+        NodeData::EpochMarker { epoch, node_count } => {
+            format!("epoch {} over {} node(s)", epoch, node_count)
+        }
+        NodeData::Encrypted { .. } => "sealed payload".to_string(),
+        NodeData::BountyCreated {
+            bounty_id,
+            resource,
+            funder,
+            amount,
+        } => format!(
+            "bounty {} of {} {} from {}",
+            bounty_id, amount, resource, funder
+        ),
+        NodeData::BountyClaimed {
+            bounty_id,
+            claimant,
+        } => format!("{} claimed bounty {}", claimant, bounty_id),
+        NodeData::BountyVerified {
+            bounty_id,
+            verifier,
+            approved,
+        } => format!(
+            "{} {} the claim on bounty {}",
+            verifier,
+            if *approved { "approved" } else { "rejected" },
+            bounty_id
+        ),
+        NodeData::BountyPaid {
+            bounty_id,
+            recipient,
+            amount,
+        } => format!("bounty {} paid {} to {}", bounty_id, amount, recipient),
+        NodeData::BountyCancelled { bounty_id } => format!("bounty {} cancelled", bounty_id),
+        NodeData::Distributed {
+            resource,
+            pool,
+            weight_key,
+            amount,
+            shares,
+        } => format!(
+            "{} {} from {} to {} accounts by {}",
+            amount,
+            resource,
+            pool,
+            shares.len(),
+            weight_key
+        ),
+        NodeData::ExecutionAck {
+            proposal_id,
+            coop_id,
+            signer,
+            outcome,
+            ..
+        } => format!(
+            "{} applied proposal {} ({}), signed by {}",
+            coop_id, proposal_id, outcome, signer
+        ),
+    }
+}
+
+fn print_node(node: &DagNode) {
+    println!(
+        "   {} {:<16} {}",
+        format_timestamp(node.timestamp),
+        node.data.type_name(),
+        describe_node(node)
+    );
+    println!("     id: {}  namespace: {}", node.id, node.namespace);
+    if !node.parent_ids.is_empty() {
+        println!("     parents: {}", node.parent_ids.join(", "));
+    }
+}
+
+/// Handle the trace command to show nodes with their ancestors, oldest first
+///
+/// Without a proposal or node, the whole ledger (or namespace) is listed.
+pub fn handle_trace_command(
+    dag_path: &Path,
+    proposal_id: Option<&str>,
+    node_id: Option<&str>,
+    namespace: Option<&str>,
+    json: bool,
+) -> Result<(), Box<dyn Error>> {
+    let ledger = open_ledger(dag_path)?;
+
+    let mut nodes = if let Some(proposal_id) = proposal_id {
+        let start_ids: Vec<String> = ledger
+            .find_proposal_related_nodes(proposal_id)
+            .into_iter()
+            .map(|node| node.id)
+            .collect();
+        if start_ids.is_empty() {
+            return Err(format!("No ledger nodes for proposal {}", proposal_id).into());
+        }
+        ledger.export_selected(&start_ids)
+    } else if let Some(node_id) = node_id {
+        if ledger.find_by_id(node_id).is_none() {
+            return Err(format!("Node {} not found", node_id).into());
+        }
+        ledger.export_selected(&[node_id.to_string()])
+    } else {
+        ledger.export_all()
+    };
+    nodes.retain(|node| namespace.is_none_or(|ns| node.namespace == ns));
+    nodes.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.id.cmp(&b.id)));
+
+    print_as(with_json_flag(json), &nodes, |nodes| {
+        let subject = match (proposal_id, node_id) {
+            (Some(proposal_id), _) => format!("proposal '{}'", proposal_id),
+            (_, Some(node_id)) => format!("node {}", node_id),
+            _ => dag_path.display().to_string(),
+        };
+        println!("📜 Ledger Trace: {}", subject);
+        println!("   Nodes: {}\n", nodes.len());
+        nodes.iter().for_each(print_node);
+    })
+}
+
+/// Check a genesis node's signature against the founder's public key
+fn verify_genesis_signature(node: &DagNode) -> Option<VerifyIssue> {
+    let NodeData::Genesis {
+        founder,
+        public_key,
+        signature,
+    } = &node.data
+    else {
+        return None;
+    };
+    let message = genesis_signing_bytes(&node.namespace, founder, node.timestamp);
+    identity::verify_signature(public_key, &message, signature)
+        .err()
+        .map(|e| VerifyIssue {
+            node_id: node.id.clone(),
+            kind: IssueKind::InvalidSignature,
+            message: format!("Genesis signature by {} does not verify: {}", founder, e),
+            line: None,
+        })
+}
+
+/// Handle the verify command to check the ledger's integrity
+///
+/// The file is read line by line, so lines that are not nodes are reported
+/// too, and the first corrupted node is named with its line. Fails when any
+/// problem is found so scripts can detect a damaged ledger.
+pub fn handle_verify_command(dag_path: &Path, json: bool) -> Result<(), Box<dyn Error>> {
+    let report = DagLedger::verify_file_integrity(dag_path, verify_genesis_signature)?;
+
+    print_as(with_json_flag(json), &report, |report| {
+        print_verify_report(report, dag_path)
+    })?;
+
+    if report.is_valid() {
+        Ok(())
+    } else {
+        Err(format!("Ledger has {} integrity issue(s)", report.issues.len()).into())
+    }
+}
+
+fn print_verify_report(report: &VerifyReport, dag_path: &Path) {
+    println!("🔍 Ledger Verification: {}", dag_path.display());
+    println!("   Nodes checked: {}", report.checked);
+    if let Some(root) = &report.merkle_root {
+        println!("   Merkle root: {}", root);
+    }
+    let Some(first) = report.first_issue() else {
+        println!("   ✅ No issues found");
+        return;
+    };
+    println!("   ❌ First corrupted node: {}", issue_location(first));
+
+    println!("\n⚠️  Issues:");
+    for issue in &report.issues {
+        println!("   {} [{:?}]", issue_location(issue), issue.kind);
+        println!("     {}", issue.message);
+    }
+}
+
+/// The node an issue is about and its line in the ledger file, if known
+fn issue_location(issue: &VerifyIssue) -> String {
+    match (issue.node_id.is_empty(), issue.line) {
+        (true, Some(line)) => format!("line {}", line),
+        (false, Some(line)) => format!("{} (line {})", issue.node_id, line),
+        _ => issue.node_id.clone(),
+    }
+}
+
+/// Handle the compact command to deduplicate and reorder the ledger file
+///
+/// Appends only add lines to the file, so run this after merges, imports or
+/// hand edits have left repeated or out-of-order nodes behind.
+pub fn handle_compact_command(dag_path: &Path, json: bool) -> Result<(), Box<dyn Error>> {
+    if !dag_path.exists() {
+        return Err(format!("Ledger not found: {}", dag_path.display()).into());
+    }
+    let mut ledger = open_ledger(dag_path)?;
+    let report = ledger.compact()?;
+
+    print_as(with_json_flag(json), &report, |report| {
+        println!("🧹 Compacted ledger: {}", dag_path.display());
+        println!("   Nodes kept: {}", report.nodes);
+        println!("   Duplicates removed: {}", report.duplicates_removed);
+        println!("   Nodes reordered: {}", report.reordered);
+    })
+}
+
+/// Handle the diff command to compare the ledger with another file
+///
+/// "Added" nodes are in the ledger but not the other file; "removed" nodes
+/// are only in the other file. With `output_path`, the added nodes are
+/// written there so they can be imported on the other side.
+pub fn handle_diff_command(
+    dag_path: &Path,
+    other_path: &Path,
+    output_path: Option<&Path>,
+    json: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !other_path.exists() {
+        return Err(format!("File not found: {}", other_path.display()).into());
+    }
+    let ledger = open_ledger(dag_path)?;
+    let diff = ledger.diff_with_file(other_path)?;
+
+    if let Some(output_path) = output_path {
+        ledger.export_diff_to_file(&diff, output_path)?;
+    }
+
+    print_as(with_json_flag(json), &diff, |diff| {
+        print_diff(diff, dag_path, other_path, output_path)
+    })
+}
+
+fn print_diff(diff: &DagDiff, dag_path: &Path, other_path: &Path, output_path: Option<&Path>) {
+    println!("🔎 Ledger Diff:");
+    println!("   Base: {}", dag_path.display());
+    println!("   Other: {}", other_path.display());
+    println!("   Common: {}", diff.common.len());
+    println!("   Only in base: {}", diff.added.len());
+    println!("   Only in other: {}", diff.removed.len());
+
+    if !diff.added.is_empty() {
+        println!("\n➕ Only in base:");
+        diff.added.iter().for_each(print_node);
+    }
+    if !diff.removed.is_empty() {
+        println!("\n➖ Only in other:");
+        diff.removed.iter().for_each(print_node);
+    }
+    if let Some(output_path) = output_path {
+        println!(
+            "\n📤 Wrote {} node(s) to {}",
+            diff.added.len(),
+            output_path.display()
+        );
+    }
+}
+
+/// Handle the export command to write nodes to a JSONL file
+///
+/// A proposal export includes every ancestor of the proposal's nodes, so the
+/// file can be imported and verified on its own.
+pub fn handle_export_command(
+    dag_path: &Path,
+    output_path: &Path,
+    namespace: Option<&str>,
+    proposal_id: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let ledger = open_ledger(dag_path)?;
+
+    let mut nodes = match proposal_id {
+        Some(proposal_id) => {
+            let start_ids: Vec<String> = ledger
+                .find_proposal_related_nodes(proposal_id)
+                .into_iter()
+                .map(|node| node.id)
+                .collect();
+            ledger.export_selected(&start_ids)
+        }
+        None => ledger.export_all(),
+    };
+    nodes.retain(|node| namespace.is_none_or(|ns| node.namespace == ns));
+    nodes.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.id.cmp(&b.id)));
+
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(output_path, DagLedger::to_jsonl(&nodes)?)?;
+
+    println!(
+        "📤 Exported {} node(s) to {}",
+        nodes.len(),
+        output_path.display()
+    );
+    Ok(())
+}
+
+/// Handle the import command to add nodes missing from the ledger
+///
+/// Nodes whose ID does not match their contents are refused, since they
+/// were altered after being written.
+pub fn handle_import_command(dag_path: &Path, input_path: &Path) -> Result<(), Box<dyn Error>> {
+    if !input_path.exists() {
+        return Err(format!("File not found: {}", input_path.display()).into());
+    }
+    let tampered: Vec<String> = DagLedger::load_from_file(input_path)?
+        .nodes()
+        .iter()
+        .filter(|node| !node.has_valid_id())
+        .map(|node| node.id.clone())
+        .collect();
+    if !tampered.is_empty() {
+        return Err(format!(
+            "Refusing to import: {} node(s) do not match their IDs: {}",
+            tampered.len(),
+            tampered.join(", ")
+        )
+        .into());
+    }
+
+    let mut ledger = open_ledger(dag_path)?;
+    let added = ledger.import_from_file(input_path)?;
+    ledger.persist_appended()?;
+
+    println!(
+        "📥 Imported {} new node(s) from {}",
+        added,
+        input_path.display()
+    );
+    Ok(())
+}
+
+/// Handle the graph command to render the DAG for Graphviz
+pub fn handle_graph_command(
+    dag_path: &Path,
+    namespace: Option<&str>,
+    output_path: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let dot = open_ledger(dag_path)?.to_dot(namespace);
+
+    match output_path {
+        Some(output_path) => {
+            fs::write(output_path, dot)?;
+            println!("🕸️  Wrote ledger graph to {}", output_path.display());
+            println!(
+                "   Render it with: dot -Tsvg {} -o ledger.svg",
+                output_path.display()
+            );
+        }
+        None => print!("{}", dot),
+    }
+    Ok(())
+}
+
+/// Handle the keygen command to create a namespace encryption key
+///
+/// The key must be shared out of band with every member who should be able
+/// to read the namespace; peers without it can still sync and verify nodes.
+pub fn handle_keygen_command(output_path: &Path) -> Result<(), Box<dyn Error>> {
+    if output_path.exists() {
+        return Err(format!(
+            "Refusing to overwrite existing key file: {}",
+            output_path.display()
+        )
+        .into());
+    }
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(output_path, NamespaceKey::generate().to_hex())?;
+
+    println!("🔑 Wrote namespace key to {}", output_path.display());
+    println!("   Use it with --namespace-key-file when creating proposals");
+    Ok(())
+}
+
+/// Handle the merge command to import nodes from a divergent ledger
+///
+/// Non-conflicting nodes are written back to the ledger file; conflicting
+/// nodes and their descendants are left out and listed in the report. The
+/// command fails when conflicts are found so scripts can detect them.
+pub fn handle_merge_command(
+    dag_path: &Path,
+    other_path: &Path,
+    dry_run: bool,
+    json: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut ledger = open_ledger(dag_path)?;
+
+    let report = ledger.merge_from_file(other_path)?;
+
+    if !dry_run {
+        ledger.persist_appended()?;
+    }
+
+    print_as(with_json_flag(json), &report, |report| {
+        print_merge_report(report, dag_path, other_path, dry_run)
+    })?;
+
+    if report.conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Merge found {} conflicting event(s)",
+            report.conflicts.len()
+        )
+        .into())
+    }
+}
+
+fn print_merge_report(report: &MergeReport, dag_path: &Path, other_path: &Path, dry_run: bool) {
+    println!(
+        "🔀 Ledger Merge{}:",
+        if dry_run { " (dry run)" } else { "" }
+    );
+    println!("   Base: {}", dag_path.display());
+    println!("   Other: {}", other_path.display());
+    println!("   Already present: {}", report.already_present);
+    println!("   Imported: {}", report.imported.len());
+    println!("   Conflicts: {}", report.conflicts.len());
+    println!("   Blocked: {}", report.blocked.len());
+
+    if !report.conflicts.is_empty() {
+        println!("\n⚠️  Conflicting events:");
+        for conflict in &report.conflicts {
+            println!("   {}", conflict.event);
+            println!("     local:    {}", conflict.local_id);
+            println!("     incoming: {}", conflict.incoming_id);
+        }
+    }
+
+    if !report.blocked.is_empty() {
+        println!("\n⏸️  Held back (ancestor rejected or missing):");
+        for id in &report.blocked {
+            println!("   {}", id);
+        }
+    }
+}
@@ -1,8 +1,28 @@
+pub mod audit;
+pub mod bench;
+pub mod coverage;
+pub mod dashboard;
+pub mod debug;
+pub mod dry_run;
 pub mod federation;
+pub mod keys;
+pub mod ledger;
+pub mod notifications;
+pub mod output;
 pub mod proposal;
 pub mod proposal_demo;
+pub mod proposal_digest;
+pub mod proposal_import;
+pub mod proposal_watch;
+pub mod proposal_wizard;
+pub mod repl;
+pub mod session;
+pub mod storage;
+pub mod template;
 pub mod utils;
+pub mod verify_vote;
 
 // Re-export key components
 pub use federation::federation_command;
+pub use ledger::ledger_command;
 pub use proposal::proposal_command;
@@ -0,0 +1,240 @@
+//! Chat notification CLI functionality.
+//!
+//! Manages the chat channels of a namespace, which are notified when its
+//! proposals open for voting, reach quorum, or are executed (see
+//! `governance::notifications`). The mapping lives in storage, so every
+//! node sharing the storage posts to the same channels.
+//!
+//! The module includes functionality for:
+//! - Adding Slack, Discord, and Matrix channels, optionally limited to some
+//!   milestones
+//! - Listing and removing channels
+//! - Sending a test message to every channel
+
+use crate::cli::output::print_output;
+use crate::governance::notifications::{
+    load_channels, save_channels, ChatChannel, ChatTarget, Milestone,
+};
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::Storage;
+use crate::vm::VM;
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::Debug;
+
+fn namespace_arg() -> Arg {
+    Arg::new("namespace")
+        .long("namespace")
+        .value_name("NAMESPACE")
+        .help("Namespace whose proposals are announced")
+        .default_value("default")
+}
+
+/// Create the notifications command and its subcommands
+pub fn notifications_command() -> Command {
+    Command::new("notifications")
+        .about("Post proposal milestones to Matrix rooms or Slack/Discord webhooks")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add a chat channel to a namespace")
+                .arg(namespace_arg())
+                .arg(
+                    Arg::new("slack")
+                        .long("slack")
+                        .value_name("WEBHOOK_URL")
+                        .help("Slack incoming webhook URL"),
+                )
+                .arg(
+                    Arg::new("discord")
+                        .long("discord")
+                        .value_name("WEBHOOK_URL")
+                        .help("Discord channel webhook URL"),
+                )
+                .arg(
+                    Arg::new("matrix-room")
+                        .long("matrix-room")
+                        .value_name("ROOM_ID")
+                        .help("Matrix room ID, e.g. !abc123:example.org")
+                        .requires_all(["matrix-homeserver", "matrix-token"]),
+                )
+                .arg(
+                    Arg::new("matrix-homeserver")
+                        .long("matrix-homeserver")
+                        .value_name("URL")
+                        .help("Matrix homeserver URL")
+                        .requires("matrix-room"),
+                )
+                .arg(
+                    Arg::new("matrix-token")
+                        .long("matrix-token")
+                        .value_name("TOKEN")
+                        .help("Access token of the Matrix user that posts")
+                        .requires("matrix-room"),
+                )
+                .group(
+                    ArgGroup::new("target")
+                        .args(["slack", "discord", "matrix-room"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("on")
+                        .long("on")
+                        .value_name("MILESTONE")
+                        .help("Milestones to post: voting_opened, quorum_reached, executed, digest")
+                        .value_delimiter(',')
+                        .value_parser(|value: &str| value.parse::<Milestone>())
+                        .action(ArgAction::Append),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List the chat channels of a namespace")
+                .arg(namespace_arg()),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove a chat channel from a namespace")
+                .arg(namespace_arg())
+                .arg(
+                    Arg::new("index")
+                        .long("index")
+                        .value_name("INDEX")
+                        .help("Index of the channel, as shown by `notifications list`")
+                        .value_parser(clap::value_parser!(usize))
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("test")
+                .about("Send a test message to every chat channel of a namespace")
+                .arg(namespace_arg()),
+        )
+}
+
+/// A channel as listed, without webhook paths or tokens
+#[derive(Debug, Serialize)]
+struct ChannelView {
+    index: usize,
+    target: String,
+    milestones: Vec<Milestone>,
+}
+
+/// Handle the notifications command and its subcommands
+pub fn handle_notifications_command<S>(
+    vm: &mut VM<S>,
+    matches: &ArgMatches,
+    auth_context: &AuthContext,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let (name, sub_matches) = matches
+        .subcommand()
+        .ok_or("A notifications subcommand is required")?;
+    let namespace = sub_matches
+        .get_one::<String>("namespace")
+        .map(String::as_str)
+        .unwrap_or("default");
+    let auth = Some(auth_context);
+    let storage = vm
+        .get_storage_backend_mut()
+        .ok_or("Storage backend not configured for notifications")?;
+    let mut channels = load_channels(storage, auth, namespace)?;
+
+    match name {
+        "add" => {
+            let text = |arg: &str| sub_matches.get_one::<String>(arg).cloned();
+            let target = if let Some(webhook_url) = text("slack") {
+                ChatTarget::Slack { webhook_url }
+            } else if let Some(webhook_url) = text("discord") {
+                ChatTarget::Discord { webhook_url }
+            } else {
+                ChatTarget::Matrix {
+                    homeserver: text("matrix-homeserver")
+                        .ok_or("--matrix-homeserver is required")?,
+                    room_id: text("matrix-room").ok_or("--matrix-room is required")?,
+                    access_token: text("matrix-token").ok_or("--matrix-token is required")?,
+                }
+            };
+            let milestones = sub_matches
+                .get_many::<Milestone>("on")
+                .map(|milestones| milestones.copied().collect())
+                .unwrap_or_default();
+            println!("✅ Added {} to {}", target.describe(), namespace);
+            channels.push(ChatChannel { target, milestones });
+            save_channels(storage, auth, namespace, &channels)?;
+            Ok(())
+        }
+        "list" => {
+            let views: Vec<ChannelView> = channels
+                .iter()
+                .enumerate()
+                .map(|(index, channel)| ChannelView {
+                    index,
+                    target: channel.target.describe(),
+                    milestones: channel.milestones.clone(),
+                })
+                .collect();
+            print_output(&views, |views| {
+                if views.is_empty() {
+                    println!("No chat channels for {}", namespace);
+                    return;
+                }
+                println!("💬 Chat channels for {} ({}):", namespace, views.len());
+                for view in views {
+                    let milestones = if view.milestones.is_empty() {
+                        "all milestones".to_string()
+                    } else {
+                        view.milestones
+                            .iter()
+                            .map(Milestone::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    println!("   [{}] {} — {}", view.index, view.target, milestones);
+                }
+            })
+        }
+        "remove" => {
+            let index = *sub_matches
+                .get_one::<usize>("index")
+                .ok_or("Channel index is required")?;
+            if index >= channels.len() {
+                return Err(format!("No chat channel {} in {}", index, namespace).into());
+            }
+            let removed = channels.remove(index);
+            save_channels(storage, auth, namespace, &channels)?;
+            println!(
+                "🗑️ Removed {} from {}",
+                removed.target.describe(),
+                namespace
+            );
+            Ok(())
+        }
+        "test" => {
+            if channels.is_empty() {
+                println!("No chat channels for {}", namespace);
+                return Ok(());
+            }
+            let text = format!("👋 Test message from icn-covm for {}", namespace);
+            let mut failed = 0;
+            for (index, channel) in channels.iter().enumerate() {
+                match channel.target.send(&text) {
+                    Ok(()) => println!("   ✅ [{}] {}", index, channel.target.describe()),
+                    Err(e) => {
+                        failed += 1;
+                        println!("   ❌ [{}] {}: {}", index, channel.target.describe(), e);
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(format!("{} of {} channels failed", failed, channels.len()).into());
+            }
+            Ok(())
+        }
+        _ => Err(format!("Unknown notifications subcommand: {}", name).into()),
+    }
+}
@@ -0,0 +1,141 @@
+//! Structured output for read commands
+//!
+//! `--output` selects how read commands print their results: `table`, the
+//! default human-readable text, or `json` or `yaml` for scripts and
+//! dashboards. It is given before the subcommand and applies to every
+//! command, since several subcommands already use `--output` for a file:
+//!
+//! ```bash
+//! icn-covm --output json proposal list
+//! icn-covm --output yaml ledger stats
+//! ```
+//!
+//! Only the result goes to stdout in the structured formats; progress
+//! messages and warnings go to stderr.
+
+use clap::{Arg, ValueEnum};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::error::Error;
+use std::sync::Mutex;
+
+/// How read commands print their results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Table,
+    /// Pretty-printed JSON
+    Json,
+    /// YAML
+    Yaml,
+}
+
+static OUTPUT_FORMAT: Lazy<Mutex<OutputFormat>> = Lazy::new(|| Mutex::new(OutputFormat::Table));
+
+/// The top-level `--output` argument
+pub fn output_arg() -> Arg {
+    Arg::new("output-format")
+        .long("output")
+        .value_name("FORMAT")
+        .help("Output format for read commands; give it before the subcommand")
+        .value_parser(clap::value_parser!(OutputFormat))
+        .default_value("table")
+}
+
+/// Set the format used by read commands for the rest of the process
+pub fn set_output_format(format: OutputFormat) {
+    *OUTPUT_FORMAT.lock().unwrap_or_else(|e| e.into_inner()) = format;
+}
+
+/// The format selected with `--output`
+pub fn output_format() -> OutputFormat {
+    *OUTPUT_FORMAT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The selected format, or JSON when a command's own `--json` flag is set
+pub fn with_json_flag(json: bool) -> OutputFormat {
+    if json {
+        OutputFormat::Json
+    } else {
+        output_format()
+    }
+}
+
+/// Whether results are printed as text, so progress messages may go to stdout
+pub fn is_table() -> bool {
+    output_format() == OutputFormat::Table
+}
+
+/// Serialize `value` in a structured format, or `None` for `Table`
+pub fn render<T: Serialize>(
+    format: OutputFormat,
+    value: &T,
+) -> Result<Option<String>, Box<dyn Error>> {
+    Ok(match format {
+        OutputFormat::Table => None,
+        OutputFormat::Json => Some(serde_json::to_string_pretty(value)?),
+        OutputFormat::Yaml => Some(serde_yaml::to_string(value)?),
+    })
+}
+
+/// Print `value` in `format`, calling `table` to print it as text
+pub fn print_as<T: Serialize>(
+    format: OutputFormat,
+    value: &T,
+    table: impl FnOnce(&T),
+) -> Result<(), Box<dyn Error>> {
+    match render(format, value)? {
+        Some(text) => println!("{}", text.trim_end()),
+        None => table(value),
+    }
+    Ok(())
+}
+
+/// Print `value` in the format selected with `--output`
+pub fn print_output<T: Serialize>(value: &T, table: impl FnOnce(&T)) -> Result<(), Box<dyn Error>> {
+    print_as(output_format(), value, table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Row {
+        id: String,
+        votes: u32,
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let rows = vec![Row {
+            id: "p1".to_string(),
+            votes: 3,
+        }];
+
+        assert!(render(OutputFormat::Table, &rows).unwrap().is_none());
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(OutputFormat::Json, &rows).unwrap().unwrap()).unwrap();
+        assert_eq!(json[0]["votes"], 3);
+
+        let yaml = render(OutputFormat::Yaml, &rows).unwrap().unwrap();
+        assert_eq!(yaml, "- id: p1\n  votes: 3\n");
+    }
+
+    #[test]
+    fn test_output_arg_parses_formats() {
+        let cmd = clap::Command::new("icn-covm").arg(output_arg());
+        let matches = cmd
+            .clone()
+            .get_matches_from(["icn-covm", "--output", "yaml"]);
+        assert_eq!(
+            matches.get_one::<OutputFormat>("output-format"),
+            Some(&OutputFormat::Yaml)
+        );
+        assert!(cmd
+            .try_get_matches_from(["icn-covm", "--output", "xml"])
+            .is_err());
+    }
+}
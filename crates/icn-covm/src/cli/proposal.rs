@@ -15,22 +15,33 @@
 
 use crate::compiler::parse_dsl;
 use crate::compiler::parse_dsl::LifecycleConfig;
+use crate::governance::archive;
 use crate::governance::comments::{self as comments};
+use crate::governance::eligibility::{self, VoterEligibility};
+use crate::governance::export::{self, ExportFormat};
+use crate::governance::notifications::{self, quorum_met, Notice};
 use crate::governance::proposal::{
     Proposal, ProposalStatus, ProposalStatus as LocalProposalStatus,
 };
 use crate::governance::proposal_lifecycle::ExecutionStatus;
 use crate::governance::proposal_lifecycle::VoteChoice;
 use crate::governance::proposal_lifecycle::{Comment, ProposalLifecycle, ProposalState};
+use crate::governance::role_classes::{self, ClassTally};
+use crate::governance::scheduler::ScheduledProposals;
+use crate::governance::snapshot::{self, VoteWeight, WeightedTally};
 use crate::identity::Identity;
+use crate::import::ImportSource;
 use crate::storage::auth::AuthContext;
 use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::resource::{EscrowOutcome, EscrowStatus};
 use crate::storage::traits::{Storage, StorageBackend, StorageExtensions};
 use crate::vm::Op;
+use crate::vm::VMEvent;
+use crate::events::Severity;
 use crate::vm::VMError;
 use crate::vm::VM;
 use chrono::{DateTime, Duration, Utc};
-use clap::{arg, value_parser, Arg, ArgAction, ArgMatches, Command, Subcommand};
+use clap::{arg, value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command, Subcommand};
 use hex;
 use serde::{Deserialize, Serialize};
 use serde_json;
@@ -46,10 +57,17 @@ use std::str::FromStr;
 use std::time::Duration as StdDuration;
 use uuid;
 use regex::Regex;
+use rust_decimal::prelude::ToPrimitive;
 use icn_ledger;
 use icn_ledger::{DagLedger, DagNode, NodeData};
 use icn_ledger::TypedValue;
 use crate::cli::utils::{f64_to_typed, safe_f64_to_u64, safe_percentage};
+use crate::cli::dry_run::{self, dry_run_arg, DryRun};
+use crate::cli::output::{self, print_output};
+use crate::cli::proposal_wizard::{run_wizard, Prompter, ProposalDraft};
+use crate::cli::proposal_watch::run_watch;
+use crate::cli::proposal_digest::{parse_time_of_day, run_digest};
+use crate::cli::proposal_import::run_import;
 
 /// Extension trait that provides proposal storage operations for VM
 ///
@@ -62,7 +80,7 @@ use crate::cli::utils::{f64_to_typed, safe_f64_to_u64, safe_percentage};
 /// - Proper fork/mutation patterns for all data-changing operations
 /// - Accessor methods that avoid direct field access
 /// - Type-safe state transitions and error handling
-trait VMProposalExtensions<S: StorageExtensions + Clone + Debug> {
+pub(crate) trait VMProposalExtensions<S: StorageExtensions + Clone + Debug> {
     /// Get the proposal lifecycle by ID
     fn get_proposal_lifecycle(
         &self,
@@ -72,29 +90,32 @@ trait VMProposalExtensions<S: StorageExtensions + Clone + Debug> {
     /// Get the proposal metadata by ID
     fn get_proposal(&self, proposal_id: &str) -> Result<Proposal, Box<dyn Error>>;
 
-    /// Create a proposal in storage
+    /// Create a proposal in storage, or rehearse it during a dry run
     fn create_proposal(
         &mut self,
         proposal: Proposal,
         lifecycle: ProposalLifecycle,
         description: &str,
         logic: &str,
+        dry_run: Option<&mut DryRun>,
     ) -> Result<(), Box<dyn Error>>;
 
-    /// Update a proposal's state
+    /// Update a proposal's state, or rehearse it during a dry run
     fn update_proposal_state(
         &mut self,
         proposal_id: &str,
         new_state: ProposalState,
+        dry_run: Option<&mut DryRun>,
     ) -> Result<(), Box<dyn Error>>;
 
-    /// Cast a vote on a proposal
+    /// Cast a vote on a proposal, or rehearse it during a dry run
     fn cast_vote(
         &mut self,
         proposal_id: &str,
         voter_id: &str,
         vote_value: &str,
         delegated_by: Option<&str>,
+        dry_run: Option<&mut DryRun>,
     ) -> Result<(), Box<dyn Error>>;
 
     /// Get all votes for a proposal
@@ -103,8 +124,12 @@ trait VMProposalExtensions<S: StorageExtensions + Clone + Debug> {
         proposal_id: &str,
     ) -> Result<Vec<(String, String)>, Box<dyn Error>>;
 
-    /// Execute a proposal
-    fn execute_proposal(&mut self, proposal_id: &str) -> Result<(), Box<dyn Error>>;
+    /// Execute a proposal, or rehearse it during a dry run
+    fn execute_proposal(
+        &mut self,
+        proposal_id: &str,
+        dry_run: Option<&mut DryRun>,
+    ) -> Result<(), Box<dyn Error>>;
 
     /// Add a comment to a proposal
     fn add_proposal_comment(
@@ -179,9 +204,10 @@ where
     fn create_proposal(
         &mut self,
         proposal: Proposal,
-        lifecycle: ProposalLifecycle,
+        mut lifecycle: ProposalLifecycle,
         description: &str,
         logic: &str,
+        mut dry_run: Option<&mut DryRun>,
     ) -> Result<(), Box<dyn Error>> {
         let proposal_id = proposal.id.clone();
         let title = lifecycle.title.clone();
@@ -193,42 +219,74 @@ where
         let auth_context_opt = forked.get_auth_context();
         let namespace = forked.get_namespace().unwrap_or("default");
 
-        // Store the proposal metadata
-        let proposal_key = Self::proposal_key_prefix(&proposal_id);
-        storage
-            .set_json(auth_context_opt, &namespace, &proposal_key, &proposal)
-            .map_err(|e| format!("Failed to store proposal: {}", e))?;
-
-        // Store lifecycle data
-        let lifecycle_key = Self::proposal_lifecycle_key(&proposal_id);
-        storage
-            .set_json(auth_context_opt, &namespace, &lifecycle_key, &lifecycle)
-            .map_err(|e| format!("Failed to store proposal lifecycle: {}", e))?;
-
-        // Store description
-        let description_key = Self::proposal_description_key(&proposal_id);
-        storage
-            .set(
+        // Fix the voter roll now if only members at creation may vote
+        if lifecycle.voter_eligibility == VoterEligibility::AtCreation
+            && lifecycle.voter_roll.is_none()
+        {
+            lifecycle.voter_roll = Some(eligibility::take_roll(
+                &storage,
                 auth_context_opt,
                 &namespace,
-                &description_key,
-                description.as_bytes().to_vec(),
-            )
-            .map_err(|e| format!("Failed to store proposal description: {}", e))?;
+            )?);
+        }
 
-        // Store logic
-        let logic_key = Self::proposal_logic_key(&proposal_id);
-        storage
-            .set(
-                auth_context_opt,
-                &namespace,
-                &logic_key,
-                logic.as_bytes().to_vec(),
-            )
-            .map_err(|e| format!("Failed to store proposal logic: {}", e))?;
+        dry_run::write(
+            dry_run.as_deref_mut(),
+            &mut storage,
+            |mut dry_run, storage| {
+                // Store the proposal metadata
+                let proposal_key = Self::proposal_key_prefix(&proposal_id);
+                dry_run::set_json(
+                    dry_run.as_deref_mut(),
+                    storage,
+                    auth_context_opt,
+                    &namespace,
+                    &proposal_key,
+                    &proposal,
+                )
+                .map_err(|e| format!("Failed to store proposal: {}", e))?;
+
+                // Store lifecycle data
+                let lifecycle_key = Self::proposal_lifecycle_key(&proposal_id);
+                dry_run::set_json(
+                    dry_run.as_deref_mut(),
+                    storage,
+                    auth_context_opt,
+                    &namespace,
+                    &lifecycle_key,
+                    &lifecycle,
+                )
+                .map_err(|e| format!("Failed to store proposal lifecycle: {}", e))?;
+
+                // Store description
+                let description_key = Self::proposal_description_key(&proposal_id);
+                dry_run::set(
+                    dry_run.as_deref_mut(),
+                    storage,
+                    auth_context_opt,
+                    &namespace,
+                    &description_key,
+                    description.as_bytes().to_vec(),
+                )
+                .map_err(|e| format!("Failed to store proposal description: {}", e))?;
+
+                // Store logic
+                let logic_key = Self::proposal_logic_key(&proposal_id);
+                dry_run::set(
+                    dry_run,
+                    storage,
+                    auth_context_opt,
+                    &namespace,
+                    &logic_key,
+                    logic.as_bytes().to_vec(),
+                )
+                .map_err(|e| format!("Failed to store proposal logic: {}", e))?;
+                Ok(())
+            },
+        )?;
 
         // Commit the transaction
-        self.commit_fork_transaction()?;
+        dry_run::commit(dry_run.as_deref(), self)?;
 
         // Get the namespace for the DAG node - do this outside the borrow block
         let dag_namespace = self.get_namespace().unwrap_or("default").to_string();
@@ -237,7 +295,7 @@ where
         if let Some(ledger) = &mut self.dag {
             let node = icn_ledger::DagNode {
                 id: String::new(), // Will be computed by the ledger
-                parent_ids: vec![],
+                parent_ids: vec![], // Parented on the namespace tips by the ledger
                 timestamp: TypedValue::Number(chrono::Utc::now().timestamp() as f64)
                     .as_u64_safe("timestamp conversion")
                     .map_err(|e| format!("Failed to convert timestamp: {}", e))?,
@@ -247,8 +305,11 @@ where
                     title,
                 },
             };
-            let node_id = ledger.append(node).unwrap();
-            println!("🧾 DAG: Proposal {} recorded as node {}", proposal_id, node_id);
+            let rehearsed = dry_run.is_some();
+            let node_id = dry_run::append_on_tips(dry_run, ledger, node).unwrap();
+            if !rehearsed {
+                println!("🧾 DAG: Proposal {} recorded as node {}", proposal_id, node_id);
+            }
         }
 
         Ok(())
@@ -258,6 +319,7 @@ where
         &mut self,
         proposal_id: &str,
         new_state: ProposalState,
+        mut dry_run: Option<&mut DryRun>,
     ) -> Result<(), Box<dyn Error>> {
         // Create a fork for the state update transaction
         let mut forked = self.fork()?;
@@ -275,16 +337,63 @@ where
             .map_err(|e| format!("Failed to load proposal lifecycle: {}", e))?;
 
         // Update the state and add to history
+        let opened_voting =
+            new_state == ProposalState::Voting && lifecycle.state != ProposalState::Voting;
         lifecycle.state = new_state.clone();
-        lifecycle.history.push((chrono::Utc::now(), new_state));
+        lifecycle.history.push((self.clock().now(), new_state));
+        if opened_voting
+            && lifecycle.voter_eligibility == VoterEligibility::AtVotingOpen
+            && lifecycle.voter_roll.is_none()
+        {
+            lifecycle.voter_roll = Some(eligibility::take_roll(
+                &storage,
+                auth_context_opt.as_ref(),
+                &namespace,
+            )?);
+        }
 
-        // Save the updated lifecycle
-        storage
-            .set_json(auth_context_opt.as_ref(), &namespace, &lifecycle_key, &lifecycle)
-            .map_err(|e| format!("Failed to update proposal state: {}", e))?;
+        dry_run::write(
+            dry_run.as_deref_mut(),
+            &mut storage,
+            |mut dry_run, storage| {
+                // Save the updated lifecycle
+                dry_run::set_json(
+                    dry_run.as_deref_mut(),
+                    storage,
+                    auth_context_opt.as_ref(),
+                    &namespace,
+                    &lifecycle_key,
+                    &lifecycle,
+                )
+                .map_err(|e| format!("Failed to update proposal state: {}", e))?;
+                snapshot_vote_weights(
+                    dry_run.as_deref_mut(),
+                    storage,
+                    auth_context_opt.as_ref(),
+                    &namespace,
+                    &lifecycle,
+                )?;
+                settle_proposal_escrows(
+                    dry_run,
+                    storage,
+                    auth_context_opt.as_ref(),
+                    &namespace,
+                    proposal_id,
+                    &lifecycle.state,
+                )
+            },
+        )?;
 
         // Commit the transaction
-        self.commit_fork_transaction()?;
+        dry_run::commit(dry_run.as_deref(), self)?;
+
+        if opened_voting && dry_run.is_none() {
+            let namespace = self.get_namespace().unwrap_or("default").to_string();
+            notify_chat(
+                self,
+                Notice::voting_opened(&namespace, proposal_id, &lifecycle.title),
+            );
+        }
 
         Ok(())
     }
@@ -295,6 +404,7 @@ where
         voter_id: &str,
         vote_value: &str,
         delegated_by: Option<&str>,
+        mut dry_run: Option<&mut DryRun>,
     ) -> Result<(), Box<dyn Error>> {
         // Create a fork for the vote transaction
         let mut forked = self.fork()?;
@@ -312,6 +422,24 @@ where
             return Err(format!("Proposal with ID '{}' not found", proposal_id).into());
         }
 
+        // Check the voter against the proposal's eligibility rule
+        let lifecycle: ProposalLifecycle = storage
+            .get_json(
+                auth_context_opt,
+                &namespace,
+                &Self::proposal_lifecycle_key(proposal_id),
+            )
+            .map_err(|e| format!("Failed to load proposal lifecycle: {}", e))?;
+        eligibility::check_voter(
+            &storage,
+            auth_context_opt,
+            &namespace,
+            lifecycle.voter_eligibility,
+            lifecycle.voter_roll.as_ref(),
+            voter_id,
+        )
+        .map_err(|e| format!("Vote refused: {}", e))?;
+
         // Create the vote data structure
         let vote_data = serde_json::json!({
             "voter": voter_id,
@@ -322,14 +450,24 @@ where
 
         // Create the vote key
         let vote_key = format!("{}/{}", Self::proposal_votes_prefix(proposal_id), voter_id);
+        let first_vote = !storage.contains(auth_context_opt, &namespace, &vote_key)?;
 
         // Store the vote
-        storage
-            .set_json(auth_context_opt, &namespace, &vote_key, &vote_data)
-            .map_err(|e| format!("Failed to store vote: {}", e))?;
+        dry_run::write(dry_run.as_deref_mut(), &mut storage, |dry_run, storage| {
+            dry_run::set_json(
+                dry_run,
+                storage,
+                auth_context_opt,
+                &namespace,
+                &vote_key,
+                &vote_data,
+            )
+            .map_err(|e| format!("Failed to store vote: {}", e).into())
+        })?;
 
         // Commit the transaction
-        self.commit_fork_transaction()?;
+        dry_run::commit(dry_run.as_deref(), self)?;
+        let rehearsed = dry_run.is_some();
 
         // Get the namespace for the DAG node - do this outside the borrow block
         let dag_namespace = self.get_namespace().unwrap_or("default").to_string();
@@ -344,14 +482,9 @@ where
                 _ => -1.0, // Invalid vote
             };
 
-            // Find the proposal node to create the parent reference
-            let parent_ids = ledger.find_proposal_node_id(proposal_id)
-                .map(|id| vec![id])
-                .unwrap_or_default();
-
             let node = icn_ledger::DagNode {
                 id: String::new(), // Will be computed by the ledger
-                parent_ids,
+                parent_ids: vec![], // Parented on the namespace tips by the ledger
                 timestamp: TypedValue::Number(chrono::Utc::now().timestamp() as f64)
                     .as_u64_safe("timestamp conversion")
                     .map_err(|e| format!("Failed to convert timestamp: {}", e))?,
@@ -362,8 +495,28 @@ where
                     vote: vote_numeric,
                 },
             };
-            let node_id = ledger.append(node).unwrap();
-            println!("🗳️ DAG: Vote recorded as node {}", node_id);
+            let node_id = dry_run::append_on_tips(dry_run, ledger, node).unwrap();
+            if !rehearsed {
+                println!("🗳️ DAG: Vote recorded as node {}", node_id);
+            }
+        }
+
+        // Announce the vote that brings the proposal to its quorum
+        if first_vote && !rehearsed {
+            if let (Ok(lifecycle), Ok(votes)) = (
+                self.get_proposal_lifecycle(proposal_id),
+                self.get_proposal_votes(proposal_id),
+            ) {
+                let total = votes.len();
+                if quorum_met(&lifecycle, total) && !quorum_met(&lifecycle, total.saturating_sub(1))
+                {
+                    let namespace = self.get_namespace().unwrap_or("default").to_string();
+                    notify_chat(
+                        self,
+                        Notice::quorum_reached(&namespace, proposal_id, &lifecycle.title, total),
+                    );
+                }
+            }
         }
 
         Ok(())
@@ -407,9 +560,14 @@ where
         Ok(votes)
     }
 
-    fn execute_proposal(&mut self, proposal_id: &str) -> Result<(), Box<dyn Error>> {
-        // Create a fork for mutations
+    fn execute_proposal(
+        &mut self,
+        proposal_id: &str,
+        mut dry_run: Option<&mut DryRun>,
+    ) -> Result<(), Box<dyn Error>> {
+        // Create a fork for mutations, running as the proposal's logic
         let mut forked = self.fork()?;
+        forked.set_executing_proposal(Some(proposal_id.to_string()));
         
         // Get and capture the auth context and namespace
         let maybe_auth_context = forked.get_auth_context().cloned();
@@ -458,32 +616,55 @@ where
             false
         };
         
+        // A dry run reports what the logic did, then discards it
+        if let Some(dry_run) = dry_run.as_deref_mut() {
+            dry_run.record_events(forked.get_events());
+            forked.rollback_fork_transaction()?;
+        }
+
         // Update the proposal state
         proposal_lifecycle.state = ProposalState::Executed;
-        proposal_lifecycle.history.push((Utc::now(), ProposalState::Executed));
+        proposal_lifecycle
+            .history
+            .push((self.clock().now(), ProposalState::Executed));
         
         // Save updated lifecycle data
-        storage
-            .set_json(maybe_auth_context.as_ref(), &namespace, &lifecycle_key, &proposal_lifecycle)
-            .map_err(|e| format!("Failed to update proposal lifecycle: {}", e))?;
+        dry_run::write(
+            dry_run.as_deref_mut(),
+            &mut storage,
+            |mut dry_run, storage| {
+                dry_run::set_json(
+                    dry_run.as_deref_mut(),
+                    storage,
+                    maybe_auth_context.as_ref(),
+                    &namespace,
+                    &lifecycle_key,
+                    &proposal_lifecycle,
+                )
+                .map_err(|e| format!("Failed to update proposal lifecycle: {}", e))?;
+                settle_proposal_escrows(
+                    dry_run,
+                    storage,
+                    maybe_auth_context.as_ref(),
+                    &namespace,
+                    proposal_id,
+                    &proposal_lifecycle.state,
+                )
+            },
+        )?;
         
         // Commit the transaction
-        self.commit_fork_transaction()?;
+        dry_run::commit(dry_run.as_deref(), self)?;
+        let rehearsed = dry_run.is_some();
 
         // Get the namespace for the DAG node - do this outside the borrow block
         let dag_namespace = self.get_namespace().unwrap_or("default").to_string();
         
         // Log to DAG if available
         if let Some(ledger) = &mut self.dag {
-            // Collect parent IDs from vote nodes
-            let vote_nodes = ledger.find_vote_nodes_for(proposal_id);
-            let parent_ids: Vec<String> = vote_nodes.iter()
-                .map(|node| node.id.clone())
-                .collect();
-
             let node = icn_ledger::DagNode {
                 id: String::new(), // Will be computed by the ledger
-                parent_ids,
+                parent_ids: vec![], // Parented on the namespace tips by the ledger
                 timestamp: TypedValue::Number(chrono::Utc::now().timestamp() as f64)
                     .as_u64_safe("timestamp conversion")
                     .map_err(|e| format!("Failed to convert timestamp: {}", e))?,
@@ -493,10 +674,19 @@ where
                     success,
                 },
             };
-            let node_id = ledger.append(node).unwrap();
-            println!("⚙️ DAG: Execution recorded as node {}", node_id);
+            let node_id = dry_run::append_on_tips(dry_run, ledger, node).unwrap();
+            if !rehearsed {
+                println!("⚙️ DAG: Execution recorded as node {}", node_id);
+            }
         }
-        
+
+        if !rehearsed {
+            notify_chat(
+                self,
+                Notice::executed(&namespace, proposal_id, &proposal_lifecycle.title, success),
+            );
+        }
+
         Ok(())
     }
 
@@ -585,11 +775,13 @@ pub struct ProposalComment {
 /// - attach: Attach a file to a proposal
 /// - comment: Add a comment to a proposal
 /// - edit: Edit an existing proposal
+/// - clone: Copy a proposal into a new Draft
 /// - publish: Move a proposal from Draft to OpenForFeedback state
 /// - vote: Cast a vote on a proposal
 /// - transition: Manually change a proposal's state
 /// - view: View proposal details
 /// - list: List and filter proposals
+/// - watch: Print votes, comments, and state changes as they happen
 /// - comments: View all comments for a proposal
 /// - comment-react: Add a reaction to a comment
 /// - comment-tag: Add tags to an existing comment
@@ -598,6 +790,9 @@ pub struct ProposalComment {
 /// - execute: Execute the logic of a passed proposal
 /// - view-comments: View all comments for a proposal
 /// - export: Export a complete proposal and its lifecycle data to a JSON file
+/// - export-all: Export all proposals, votes, and comments as CSV or Parquet tables
+/// - archive: Export closed proposals to a signed cold-storage archive
+/// - verify-archive: Check an archive against its manifest and the DAG ledger
 /// - dag-export-all: Export all DAG nodes to a file
 /// - dag-import: Import DAG nodes from a file
 /// - dag-export-selected: Export selected DAG nodes and their ancestor nodes to a file
@@ -611,6 +806,20 @@ pub fn proposal_command() -> Command {
         .about("Governance proposal operations")
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .arg(
+            Arg::new("storage-backend")
+                .long("storage-backend")
+                .value_name("TYPE")
+                .help("Storage backend type, memory or sled (default: storage.backend)")
+                .global(true)
+        )
+        .arg(
+            Arg::new("storage-path")
+                .long("storage-path")
+                .value_name("PATH")
+                .help("Path for the sled storage database (default: storage.path)")
+                .global(true)
+        )
         .arg(
             Arg::new("dag-path")
                 .long("dag-path")
@@ -626,29 +835,42 @@ pub fn proposal_command() -> Command {
                 .default_value("default")
                 .global(true)
         )
+        .arg(
+            Arg::new("namespace-key-file")
+                .long("namespace-key-file")
+                .value_name("FILE_PATH")
+                .help("Hex key file used to encrypt DAG payloads for a private namespace")
+                .global(true)
+        )
         .subcommand(
             Command::new("create")
                 .about("Create a new governance proposal")
+                .arg(
+                    Arg::new("interactive")
+                        .long("interactive")
+                        .help("Prompt for each field, preview the compiled logic, then save")
+                        .action(ArgAction::SetTrue),
+                )
                 .arg(
                     Arg::new("id")
                         .long("id")
                         .value_name("ID")
                         .help("Unique identifier for the proposal")
-                        .required(true),
+                        .required_unless_present("interactive"),
                 )
                 .arg(
                     Arg::new("title")
                         .long("title")
                         .value_name("STRING")
                         .help("Title of the proposal")
-                        .required(true),
+                        .required_unless_present("interactive"),
                 )
                 .arg(
                     Arg::new("description")
                         .long("description")
                         .value_name("STRING")
                         .help("Description of the proposal")
-                        .required(true),
+                        .required_unless_present("interactive"),
                 )
                 .arg(
                     Arg::new("quorum")
@@ -656,7 +878,7 @@ pub fn proposal_command() -> Command {
                         .value_name("FLOAT")
                         .help("Quorum required for the proposal to pass (value between 0.0 and 1.0)")
                         .value_parser(value_parser!(f64))
-                        .required(true),
+                        .required_unless_present("interactive"),
                 )
                 .arg(
                     Arg::new("threshold")
@@ -664,14 +886,14 @@ pub fn proposal_command() -> Command {
                         .value_name("FLOAT")
                         .help("Threshold required for the proposal to pass (value between 0.0 and 1.0)")
                         .value_parser(value_parser!(f64))
-                        .required(true),
+                        .required_unless_present("interactive"),
                 )
                 .arg(
                     Arg::new("logic")
                         .long("logic")
                         .value_name("PATH")
                         .help("Path to the DSL logic file")
-                        .required(true),
+                        .required_unless_present("interactive"),
                 )
                 .arg(
                     Arg::new("expires-in")
@@ -704,6 +926,13 @@ pub fn proposal_command() -> Command {
                         .value_name("ATTACHMENTS")
                         .help("Comma-separated list of attachment references"),
                 )
+                .arg(
+                    Arg::new("label")
+                        .long("label")
+                        .value_name("LABEL")
+                        .help("Label used to categorize the proposal (can be repeated)")
+                        .action(ArgAction::Append),
+                )
                 .arg(
                     Arg::new("min-deliberation")
                         .long("min-deliberation")
@@ -724,6 +953,15 @@ pub fn proposal_command() -> Command {
                         .help("Minimum number of participants required for the proposal to be valid")
                         .value_parser(value_parser!(u64)),
                 )
+                .arg(
+                    Arg::new("vote-weight")
+                        .long("vote-weight")
+                        .value_name("WEIGHT")
+                        .help("Weight votes by member (default), reputation or resource:<name>, as held when voting opens")
+                        .value_parser(VoteWeight::from_str),
+                )
+                .arg(voter_eligibility_arg())
+                .arg(dry_run_arg())
         )
         .subcommand(
             Command::new("attach")
@@ -938,6 +1176,36 @@ pub fn proposal_command() -> Command {
                 )
                 // TODO: Add options for changing title, quorum, threshold? Depends on rules.
         )
+        .subcommand(
+            Command::new("clone")
+                .about("Copy a proposal's logic, description and voting parameters into a new Draft")
+                .arg(
+                    Arg::new("id")
+                        .long("id")
+                        .value_name("PROPOSAL_ID")
+                        .help("ID of the proposal to clone")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("new-id")
+                        .long("new-id")
+                        .value_name("PROPOSAL_ID")
+                        .help("ID of the new proposal (default: the source ID with a numeric suffix)")
+                )
+                .arg(
+                    Arg::new("title")
+                        .long("title")
+                        .value_name("STRING")
+                        .help("Title of the new proposal (default: the source title)")
+                )
+                .arg(
+                    Arg::new("creator")
+                        .long("creator")
+                        .value_name("ID")
+                        .help("Identity ID of the new proposal's creator")
+                )
+                .arg(dry_run_arg())
+        )
         .subcommand(
             Command::new("publish")
                 .about("Publish a proposal draft to make it open for feedback")
@@ -973,6 +1241,7 @@ pub fn proposal_command() -> Command {
                         .value_name("IDENTITY")
                         .help("Optional identity to vote as (for delegated voting)")
                 )
+                .arg(dry_run_arg())
         )
         .subcommand(
             Command::new("transition")
@@ -1003,6 +1272,7 @@ pub fn proposal_command() -> Command {
                         .help("Force status transition ignoring state transition rules")
                         .action(ArgAction::SetTrue)
                 )
+                .arg(dry_run_arg())
         )
         .subcommand(
             Command::new("view")
@@ -1015,6 +1285,66 @@ pub fn proposal_command() -> Command {
                         .required(true)
                 )
         )
+        .subcommand(
+            Command::new("watch")
+                .about("Print new votes, comments, and state changes as they happen")
+                .arg(
+                    Arg::new("id")
+                        .long("id")
+                        .value_name("PROPOSAL_ID")
+                        .help("ID of the proposal to watch")
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Watch every proposal in the namespace")
+                        .action(ArgAction::SetTrue)
+                )
+                .group(
+                    ArgGroup::new("target")
+                        .args(["id", "all"])
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("interval")
+                        .long("interval")
+                        .value_name("SECONDS")
+                        .help("Seconds between checks for new activity")
+                        .value_parser(value_parser!(u64).range(1..))
+                        .default_value("1")
+                )
+        )
+        .subcommand(
+            Command::new("digest")
+                .about("Summarize recent proposals, votes, comments, and upcoming deadlines")
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .value_name("DURATION")
+                        .help("Period to summarize, ending now (e.g. 24h, 7d)")
+                        .default_value("24h")
+                )
+                .arg(
+                    Arg::new("ahead")
+                        .long("ahead")
+                        .value_name("DURATION")
+                        .help("How far past the period to list deadlines (e.g. 7d)")
+                        .default_value("7d")
+                )
+                .arg(
+                    Arg::new("post")
+                        .long("post")
+                        .help("Post the digest to the namespace's chat channels")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("daily")
+                        .long("daily")
+                        .value_name("HH:MM")
+                        .help("Keep running, building a digest every day at this time (UTC)")
+                        .value_parser(parse_time_of_day)
+                )
+        )
         .subcommand(
             Command::new("list")
                 .about("List all proposals")
@@ -1098,6 +1428,35 @@ pub fn proposal_command() -> Command {
                         .help("ID of the proposal to execute")
                         .required(true)
                 )
+                .arg(dry_run_arg())
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import proposals, votes, and comments from CSV or JSON files in one transaction")
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .value_name("FILE")
+                        .help("CSV file with a header row, or JSON records; may be given more than once")
+                        .action(ArgAction::Append)
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("File format; taken from the file extension by default")
+                        .value_parser(["csv", "json"])
+                )
+                .arg(
+                    Arg::new("source")
+                        .long("source")
+                        .value_name("SOURCE")
+                        .help("Format of the records: covm, or a Loomio or Decidim export")
+                        .value_parser(["covm", "loomio", "decidim"])
+                        .default_value("covm")
+                )
+                .arg(dry_run_arg())
         )
         .subcommand(
             Command::new("view-comments")
@@ -1133,6 +1492,59 @@ pub fn proposal_command() -> Command {
                         .help("File path for the exported JSON (default: proposal_<id>.json)")
                 )
         )
+        .subcommand(
+            Command::new("export-all")
+                .about("Export all proposals, votes, and comments as CSV or Parquet tables")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Table file format")
+                        .value_parser(["csv", "parquet"])
+                        .default_value("csv")
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("DIR")
+                        .help("Directory for proposals, votes, and comments files")
+                        .default_value("governance_export")
+                )
+        )
+        .subcommand(
+            Command::new("archive")
+                .about("Export closed proposals, their votes, attachments, and ledger nodes to a signed archive")
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("DIR")
+                        .help("Empty or new directory to write the archive to")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("id")
+                        .long("id")
+                        .value_name("PROPOSAL_ID")
+                        .help("Proposal to archive; may be repeated (default: every closed proposal)")
+                        .action(ArgAction::Append)
+                )
+        )
+        .subcommand(
+            Command::new("verify-archive")
+                .about("Check an archive's signature and hashes, and that it matches the DAG ledger")
+                .arg(
+                    Arg::new("archive")
+                        .value_name("DIR")
+                        .help("Directory holding the archive")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("no-ledger")
+                        .long("no-ledger")
+                        .action(ArgAction::SetTrue)
+                        .help("Only check the archive itself, not that it matches the ledger")
+                )
+        )
         .subcommand(
             Command::new("dag-export-all")
                 .about("Export all DAG nodes to a file")
@@ -1343,8 +1755,184 @@ fn extract_lifecycle_config(content: &str) -> Result<LifecycleConfig, Box<dyn Er
     Ok(LifecycleConfig::default())
 }
 
+/// `--voter-eligibility`, shared by `proposal create` and
+/// `template instantiate`
+pub(crate) fn voter_eligibility_arg() -> Arg {
+    Arg::new("voter-eligibility")
+        .long("voter-eligibility")
+        .value_name("RULE")
+        .help("Who may vote: any (default), or members at creation, at voting-open, or live")
+        .value_parser(VoterEligibility::from_str)
+}
+
+/// Store a new proposal from answers given as flags or to the wizard
+///
+/// The creator, labels and required participants are always taken from the
+/// `create` flags.
+pub(crate) fn create_from_draft<S>(
+    vm: &mut VM<S>,
+    draft: ProposalDraft,
+    matches: &ArgMatches,
+    auth_context: &AuthContext,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let creator = matches
+        .get_one::<String>("creator")
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| auth_context.identity_did().to_string());
+    let required_participants = matches.get_one::<u64>("required-participants");
+
+    let mut proposal = Proposal::new(
+        draft.id.clone(),
+        creator.clone(),
+        draft.logic_path,
+        Some(Utc::now() + draft.expires_in),
+        None,       // discussion_path
+        Vec::new(), // attachments
+    );
+    proposal.labels = matches
+        .get_many::<String>("label")
+        .map(|labels| labels.cloned().collect())
+        .unwrap_or_default();
+
+    let mut lifecycle = ProposalLifecycle::new(
+        draft.id.clone(),
+        did_to_identity(&creator)?,
+        draft.title,
+        safe_f64_to_u64(draft.quorum * 100.0, "quorum percentage conversion")
+            .map_err(|e| format!("Failed to convert quorum: {}", e))?,
+        safe_f64_to_u64(draft.threshold * 100.0, "threshold percentage conversion")
+            .map_err(|e| format!("Failed to convert threshold: {}", e))?,
+        Some(draft.min_deliberation),
+        required_participants.copied(),
+    );
+    if let Some(weight) = matches.get_one::<VoteWeight>("vote-weight") {
+        lifecycle.vote_weight = weight.clone();
+    }
+    if let Some(rule) = matches.get_one::<VoterEligibility>("voter-eligibility") {
+        lifecycle.voter_eligibility = *rule;
+    }
+    lifecycle.role_classes = draft.role_classes;
+    lifecycle.execution_delay = draft.execution_delay;
+
+    let mut dry_run = DryRun::requested(matches);
+    vm.create_proposal(
+        proposal,
+        lifecycle,
+        &draft.description,
+        &draft.logic,
+        dry_run.as_mut(),
+    )?;
+    if let Some(dry_run) = dry_run {
+        return dry_run.finish();
+    }
+
+    println!("✅ Proposal '{}' created successfully", draft.id);
+    Ok(())
+}
+
+/// Copy a proposal's logic, description and voting parameters into a new
+/// Draft, recording the source in its `cloned_from`
+///
+/// Without `new_id`, the clone is named after the source with the first free
+/// numeric suffix, e.g. `budget-2`. The clone expires as long after its
+/// creation as the source did; votes, comments and attachments are not
+/// copied. Returns the ID of the clone.
+pub fn clone_proposal<S>(
+    vm: &mut VM<S>,
+    source_id: &str,
+    new_id: Option<&str>,
+    title: Option<&str>,
+    creator: &str,
+    dry_run: Option<&mut DryRun>,
+) -> Result<String, Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let source = vm.get_proposal(source_id)?;
+    let source_lifecycle = vm.get_proposal_lifecycle(source_id)?;
+
+    let storage = vm.get_storage_backend().ok_or("Storage not available")?;
+    let auth_context = vm.get_auth_context();
+    let namespace = vm.get_namespace().unwrap_or("default");
+    let read_text = |key: String| -> Result<String, Box<dyn Error>> {
+        match storage.get(auth_context, namespace, &key) {
+            Ok(bytes) => Ok(String::from_utf8(bytes)?),
+            Err(StorageError::NotFound { .. }) => Ok(String::new()),
+            Err(e) => Err(e.into()),
+        }
+    };
+    let description = read_text(VM::<S>::proposal_description_key(source_id))?;
+    let logic = read_text(VM::<S>::proposal_logic_key(source_id))?;
+
+    let exists =
+        |id: &str| storage.contains(auth_context, namespace, &VM::<S>::proposal_key_prefix(id));
+    let clone_id = match new_id {
+        Some(id) => {
+            if exists(id)? {
+                return Err(format!("Proposal with ID '{}' already exists", id).into());
+            }
+            id.to_string()
+        }
+        None => {
+            let mut suffix = 2;
+            while exists(&format!("{}-{}", source_id, suffix))? {
+                suffix += 1;
+            }
+            format!("{}-{}", source_id, suffix)
+        }
+    };
+
+    let now = Utc::now();
+    let mut proposal = Proposal::new(
+        clone_id.clone(),
+        creator.to_string(),
+        source.logic_path.clone(),
+        source
+            .expires_at
+            .map(|expires_at| now + (expires_at - source.created_at)),
+        None,       // discussion_path
+        Vec::new(), // attachments
+    );
+    proposal.labels = source.labels.clone();
+    proposal.min_deliberation_hours = source.min_deliberation_hours;
+    proposal.cloned_from = Some(source_id.to_string());
+
+    let mut lifecycle = ProposalLifecycle::new(
+        clone_id.clone(),
+        did_to_identity(creator)?,
+        title.map_or_else(|| source_lifecycle.title.clone(), str::to_string),
+        source_lifecycle.quorum,
+        source_lifecycle.threshold,
+        source_lifecycle.discussion_duration,
+        source_lifecycle.required_participants,
+    );
+    lifecycle.vote_weight = source_lifecycle.vote_weight;
+    lifecycle.role_classes = source_lifecycle.role_classes;
+    lifecycle.voter_eligibility = source_lifecycle.voter_eligibility;
+    lifecycle.execution_delay = source_lifecycle.execution_delay;
+
+    vm.create_proposal(proposal, lifecycle, &description, &logic, dry_run)?;
+    Ok(clone_id)
+}
+
+/// Parse a proposal state as given to `transition --state`
+pub(crate) fn parse_proposal_state(state: &str) -> Result<ProposalState, Box<dyn Error>> {
+    match state.to_lowercase().as_str() {
+        "draft" => Ok(ProposalState::Draft),
+        "feedback" | "open_for_feedback" | "deliberation" => Ok(ProposalState::OpenForFeedback),
+        "voting" => Ok(ProposalState::Voting),
+        "executed" => Ok(ProposalState::Executed),
+        "rejected" => Ok(ProposalState::Rejected),
+        "expired" => Ok(ProposalState::Expired),
+        _ => Err(format!("Invalid state: {}", state).into()),
+    }
+}
+
 // Let's also fix the parse_duration_string function
-fn parse_duration_string(duration_str: &str) -> Result<chrono::Duration, Box<dyn Error>> {
+pub(crate) fn parse_duration_string(duration_str: &str) -> Result<chrono::Duration, Box<dyn Error>> {
     let re = Regex::new(r"^(\d+)([dhm])$")
         .map_err(|e| format!("Regex error: {}", e))?;
 
@@ -1363,6 +1951,17 @@ fn parse_duration_string(duration_str: &str) -> Result<chrono::Duration, Box<dyn
     }
 }
 
+/// The proposal a subcommand acts on, if it names one
+fn proposal_id_arg(matches: &ArgMatches) -> Option<&str> {
+    ["proposal-id", "id"].into_iter().find_map(|name| {
+        matches
+            .try_get_one::<String>(name)
+            .ok()
+            .flatten()
+            .map(String::as_str)
+    })
+}
+
 /// Main handler for proposal commands
 ///
 /// Processes all proposal subcommands based on the CLI arguments.
@@ -1388,19 +1987,53 @@ where
 {
     let user_did = auth_context.identity_did(); // Get DID from auth_context parameter
 
+    // Diagnostics from the subcommand carry the proposal, namespace and caller
+    let span = match matches.subcommand() {
+        Some((subcommand, sub_matches)) => tracing::info_span!(
+            "proposal",
+            command = subcommand,
+            proposal_id = proposal_id_arg(sub_matches),
+            namespace = matches.get_one::<String>("namespace").map(String::as_str),
+            identity = user_did,
+        ),
+        None => tracing::Span::none(),
+    };
+    let _entered = span.enter();
+
     // Check for DAG path option
     if let Some(dag_path) = matches.get_one::<String>("dag-path") {
         vm.set_dag_path(PathBuf::from(dag_path));
-        println!("📒 Using DAG ledger at: {}", dag_path);
+        if output::is_table() {
+            println!("📒 Using DAG ledger at: {}", dag_path);
+        }
     }
     
     // Set namespace if provided
     if let Some(namespace) = matches.get_one::<String>("namespace") {
         vm.set_namespace(namespace);
         println!("🏷️ Using namespace: {}", namespace);
+
+        // Register the namespace key so private payloads are sealed on append
+        if let Some(key_path) = matches.get_one::<String>("namespace-key-file") {
+            let key = icn_ledger::NamespaceKey::from_hex(&fs::read_to_string(key_path)?)?;
+            if let Some(ledger) = &mut vm.dag {
+                ledger.set_namespace_key(namespace, key);
+                println!("🔒 Encrypting DAG payloads for namespace: {}", namespace);
+            }
+        }
     }
 
     match matches.subcommand() {
+        Some(("create", sub_matches)) if sub_matches.get_flag("interactive") => {
+            let id = sub_matches.get_one::<String>("id").map(String::as_str);
+            match run_wizard(&mut Prompter::stdio(), id)? {
+                Some(draft) => return create_from_draft(vm, draft, sub_matches, auth_context),
+                None => {
+                    println!("Proposal not saved");
+                    return Ok(());
+                }
+            }
+        }
         Some(("create", sub_matches)) => {
             let proposal_id = sub_matches.get_one::<String>("id")
                 .ok_or("Proposal ID is required")?;
@@ -1416,18 +2049,9 @@ where
                 .get_one::<String>("logic")
                 .or_else(|| sub_matches.get_one::<String>("logic-path"))
                 .ok_or_else(|| "No logic path provided")?;
-            let discussion_path = sub_matches.get_one::<String>("discussion-path");
-            let attachments = sub_matches.get_one::<String>("attachments");
             let expires_in = sub_matches.get_one::<String>("expires-in");
             let min_deliberation = sub_matches.get_one::<i64>("min-deliberation");
             let discussion_duration = sub_matches.get_one::<String>("discussion-duration");
-            let required_participants = sub_matches.get_one::<u64>("required-participants");
-
-            // Special case for creator identity
-            let creator = sub_matches
-                .get_one::<String>("creator")
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| auth_context.identity_did().to_string());
 
             // Read and parse the DSL content
             let (logic_ops, lifecycle_config) = match parse_dsl_from_file(vm, logic_path) {
@@ -1438,10 +2062,10 @@ where
                 }
             };
 
-            // Calculate expiry date
-            let expires_at = if let Some(expires_str) = expires_in {
+            // Calculate time until expiry
+            let expires_in = if let Some(expires_str) = expires_in {
                 match parse_duration_string(expires_str) {
-                    Ok(duration) => Some(chrono::Utc::now() + duration),
+                    Ok(duration) => duration,
                     Err(e) => {
                         println!("❌ Invalid expires-in format: {}", e);
                         return Err(e);
@@ -1449,7 +2073,7 @@ where
                 }
             } else {
                 // Default expiry of 30 days
-                Some(chrono::Utc::now() + chrono::Duration::days(30))
+                chrono::Duration::days(30)
             };
 
             // Calculate minimum deliberation period
@@ -1468,42 +2092,26 @@ where
                 chrono::Duration::hours(MIN_DELIBERATION_HOURS)
             };
 
-            // Create the proposal metadata
-            let proposal = Proposal::new(
-                proposal_id.to_string(),
-                creator.clone(),
-                Some(logic_path.to_string()),
-                expires_at,
-                None,       // discussion_path
-                Vec::new(), // attachments
-            );
-
-            // Create identity from creator string
-            let creator_identity = did_to_identity(&creator)?;
-
-            // Create the proposal lifecycle data
-            let lifecycle = ProposalLifecycle::new(
-                proposal_id.to_string(),
-                creator_identity,
-                title.to_string(),
-                safe_f64_to_u64(quorum * 100.0, "quorum percentage conversion")
-                    .map_err(|e| format!("Failed to convert quorum: {}", e))?,
-                safe_f64_to_u64(threshold * 100.0, "threshold percentage conversion")
-                    .map_err(|e| format!("Failed to convert threshold: {}", e))?,
-                Some(min_delib_duration),
-                required_participants.copied(),
-            );
-
             // Read the DSL file content for storage
             let logic_content = fs::read_to_string(logic_path)
                 .map_err(|e| format!("Failed to read DSL file: {}", e))?;
 
-            // Store everything using the trait method
-            vm.create_proposal(proposal, lifecycle, description, &logic_content)?;
-
-            println!("✅ Proposal '{}' created successfully", proposal_id);
-
-            return Ok(());
+            let draft = ProposalDraft {
+                id: proposal_id.to_string(),
+                title: title.to_string(),
+                description: description.to_string(),
+                template: None,
+                quorum,
+                threshold,
+                role_classes: Vec::new(),
+                min_deliberation: min_delib_duration,
+                expires_in,
+                execution_delay: None,
+                logic_path: Some(logic_path.to_string()),
+                logic: logic_content,
+                ops: logic_ops,
+            };
+            return create_from_draft(vm, draft, sub_matches, auth_context);
         }
         Some(("attach", attach_matches)) => {
             println!("Handling proposal attach...");
@@ -1673,6 +2281,31 @@ where
 
             return Ok(());
         }
+        Some(("clone", clone_matches)) => {
+            let source_id = clone_matches
+                .get_one::<String>("id")
+                .ok_or("Proposal ID is required")?;
+            let creator = clone_matches
+                .get_one::<String>("creator")
+                .map(String::as_str)
+                .unwrap_or_else(|| auth_context.identity_did());
+
+            let mut dry_run = DryRun::requested(clone_matches);
+            let clone_id = clone_proposal(
+                vm,
+                source_id,
+                clone_matches.get_one::<String>("new-id").map(String::as_str),
+                clone_matches.get_one::<String>("title").map(String::as_str),
+                creator,
+                dry_run.as_mut(),
+            )?;
+            if let Some(dry_run) = dry_run {
+                return dry_run.finish();
+            }
+
+            println!("✅ Cloned proposal '{}' as Draft '{}'", source_id, clone_id);
+            return Ok(());
+        }
         Some(("publish", publish_matches)) => {
             let proposal_id = publish_matches
                 .get_one::<String>("id")
@@ -1682,7 +2315,7 @@ where
             let mut forked = vm.fork()?;
 
             // We'll use the update_proposal_state method from the trait to change the state
-            vm.update_proposal_state(proposal_id, ProposalState::OpenForFeedback)?;
+            vm.update_proposal_state(proposal_id, ProposalState::OpenForFeedback, None)?;
 
             println!("✅ Proposal '{}' published for feedback", proposal_id);
 
@@ -1696,13 +2329,16 @@ where
                 .ok_or("Vote choice is required")?.clone();
             let delegate_identity = vote_matches.get_one::<String>("as").map(|s| s.as_str());
 
-            return handle_vote_command(
+            let mut dry_run = DryRun::requested(vote_matches);
+            handle_vote_command(
                 vm,
                 &proposal_id,
                 &vote_choice,
                 delegate_identity,
                 auth_context,
-            );
+                dry_run.as_mut(),
+            )?;
+            return dry_run.map_or(Ok(()), DryRun::finish);
         }
         Some(("transition", transition_matches)) => {
             let proposal_id = transition_matches
@@ -1714,18 +2350,14 @@ where
                 .ok_or("State is required")?;
 
             // Parse the new state
-            let new_state = match state_str.to_lowercase().as_str() {
-                "draft" => ProposalState::Draft,
-                "feedback" | "open_for_feedback" | "deliberation" => ProposalState::OpenForFeedback,
-                "voting" => ProposalState::Voting,
-                "executed" => ProposalState::Executed,
-                "rejected" => ProposalState::Rejected,
-                "expired" => ProposalState::Expired,
-                _ => return Err(format!("Invalid state: {}", state_str).into()),
-            };
+            let new_state = parse_proposal_state(state_str)?;
 
             // Use the update_proposal_state method from the trait
-            vm.update_proposal_state(proposal_id, new_state.clone())?;
+            let mut dry_run = DryRun::requested(transition_matches);
+            vm.update_proposal_state(proposal_id, new_state.clone(), dry_run.as_mut())?;
+            if let Some(dry_run) = dry_run {
+                return dry_run.finish();
+            }
 
             println!(
                 "✅ Proposal '{}' transitioned to '{:?}'",
@@ -1739,6 +2371,21 @@ where
                 .ok_or("Proposal ID is required")?;
             return handle_view_command(vm, proposal_id);
         }
+        Some(("watch", watch_matches)) => {
+            let proposal_id = watch_matches.get_one::<String>("id").map(String::as_str);
+            let interval = watch_matches.get_one::<u64>("interval").copied().unwrap_or(1);
+            return run_watch(vm, proposal_id, std::time::Duration::from_secs(interval));
+        }
+        Some(("digest", digest_matches)) => {
+            let since = parse_duration_string(
+                digest_matches.get_one::<String>("since").map_or("24h", String::as_str),
+            )?;
+            let ahead = parse_duration_string(
+                digest_matches.get_one::<String>("ahead").map_or("7d", String::as_str),
+            )?;
+            let daily = digest_matches.get_one::<chrono::NaiveTime>("daily").copied();
+            return run_digest(vm, since, ahead, digest_matches.get_flag("post"), daily);
+        }
         Some(("list", list_matches)) => {
             // Optional status filter
             let status_filter = list_matches
@@ -1754,11 +2401,7 @@ where
             let prefix = VM::<S>::proposal_key_prefix("");
             let keys = storage.list_keys(auth_context_opt, namespace, Some(&prefix))?;
 
-            // Keep track of count
-            let mut count = 0;
-
-            println!("Proposals:");
-            println!("----------");
+            let mut entries = Vec::new();
 
             for key in keys {
                 // Skip non-proposal keys (like comment keys, etc.)
@@ -1802,33 +2445,42 @@ where
                         ) {
                             Ok(lifecycle) => lifecycle,
                             Err(_) => {
-                                println!("Warning: Could not load lifecycle for proposal {}", id);
+                                tracing::warn!(
+                                    proposal_id = %id,
+                                    "Could not load proposal lifecycle"
+                                );
                                 continue;
                             }
                         };
 
-                        println!(
-                            "{}: {} - {:?}",
-                            id, lifecycle.title, proposal.status
-                        );
-                        count += 1;
+                        entries.push(ProposalListEntry {
+                            id: id.to_string(),
+                            title: lifecycle.title,
+                            status: proposal.status,
+                        });
                     }
                     Err(e) => {
-                        eprintln!("Error loading proposal {}: {}", id, e);
+                        tracing::error!(proposal_id = %id, error = %e, "Failed to load proposal");
                     }
                 }
             }
 
-            if count == 0 {
-                println!("No proposals found");
-                if let Some(status_filter_value) = status_filter {
-                    println!("(Filter: {})", status_filter_value);
+            return print_output(&entries, |entries| {
+                println!("Proposals:");
+                println!("----------");
+                for entry in entries {
+                    println!("{}: {} - {:?}", entry.id, entry.title, entry.status);
                 }
-            } else {
-                println!("\nTotal: {} proposal(s)", count);
-            }
 
-            return Ok(());
+                if entries.is_empty() {
+                    println!("No proposals found");
+                    if let Some(status_filter_value) = &status_filter {
+                        println!("(Filter: {})", status_filter_value);
+                    }
+                } else {
+                    println!("\nTotal: {} proposal(s)", entries.len());
+                }
+            });
         }
         Some(("comments", comments_matches)) => {
             println!("Fetching comments for proposal...");
@@ -1891,7 +2543,25 @@ where
                 .get_one::<String>("id")
                 .ok_or("Proposal ID is required")?
                 .clone();
-            return handle_execute_command(vm, &proposal_id, auth_context);
+            let mut dry_run = DryRun::requested(execute_matches);
+            handle_execute_command(vm, &proposal_id, auth_context, dry_run.as_mut())?;
+            return dry_run.map_or(Ok(()), DryRun::finish);
+        }
+        Some(("import", import_matches)) => {
+            let paths: Vec<&Path> = import_matches
+                .get_many::<String>("file")
+                .ok_or("Import file is required")?
+                .map(Path::new)
+                .collect();
+            let format = import_matches.get_one::<String>("format").map(String::as_str);
+            let source: ImportSource = import_matches
+                .get_one::<String>("source")
+                .map(String::as_str)
+                .unwrap_or("covm")
+                .parse()?;
+            let mut dry_run = DryRun::requested(import_matches);
+            run_import(vm, &paths, format, source, auth_context, dry_run.as_mut())?;
+            return dry_run.map_or(Ok(()), DryRun::finish);
         }
         Some(("view-comments", view_comments_matches)) => {
             let proposal_id = view_comments_matches
@@ -1912,6 +2582,37 @@ where
 
             return handle_export_command(vm, &proposal_id, output_path, auth_context);
         }
+        Some(("export-all", export_matches)) => {
+            let format: ExportFormat = export_matches
+                .get_one::<String>("format")
+                .map(String::as_str)
+                .unwrap_or("csv")
+                .parse()?;
+            let output_dir = export_matches
+                .get_one::<String>("output")
+                .ok_or("Output directory is required")?;
+
+            return handle_export_all_command(vm, format, Path::new(output_dir), auth_context);
+        }
+        Some(("archive", archive_matches)) => {
+            let output_dir = archive_matches
+                .get_one::<String>("output")
+                .ok_or("Output directory is required")?;
+            let proposal_ids: Vec<String> = archive_matches
+                .get_many::<String>("id")
+                .map(|ids| ids.cloned().collect())
+                .unwrap_or_default();
+
+            return handle_archive_command(vm, &proposal_ids, Path::new(output_dir), auth_context);
+        }
+        Some(("verify-archive", verify_matches)) => {
+            let archive_dir = verify_matches
+                .get_one::<String>("archive")
+                .ok_or("Archive directory is required")?;
+            let check_ledger = !verify_matches.get_flag("no-ledger");
+
+            return handle_verify_archive_command(vm, Path::new(archive_dir), check_ledger);
+        }
         Some(("comment-react", react_matches)) => {
             let comment_id = react_matches
                 .get_one::<String>("id")
@@ -2229,6 +2930,54 @@ where
     Ok((yes_votes, no_votes, abstain_votes))
 }
 
+/// Count the votes for a weighted proposal at its snapshot weights
+///
+/// Returns `None` for proposals with one vote per member, and for weighted
+/// proposals that have not opened for voting yet.
+pub fn count_weighted_votes<S>(
+    vm: &VM<S>,
+    proposal_id: &ProposalId,
+) -> Result<Option<WeightedTally>, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm.get_storage_backend().ok_or("Storage not available")?;
+    let namespace = vm.get_namespace().unwrap_or("default");
+    let Some(snapshot) =
+        snapshot::load_snapshot(storage, vm.get_auth_context(), namespace, proposal_id)?
+    else {
+        return Ok(None);
+    };
+    let votes = vm.get_proposal_votes(proposal_id)?;
+    Ok(Some(WeightedTally::count(&snapshot, &votes)))
+}
+
+/// Count the votes of each role class a proposal sets requirements for,
+/// against the member registry of the VM's namespace
+fn tally_role_classes<S>(
+    vm: &VM<S>,
+    proposal_id: &ProposalId,
+    lifecycle: &ProposalLifecycle,
+) -> Result<Vec<ClassTally>, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm.get_storage_backend().ok_or("Storage not available")?;
+    let namespace = vm.get_namespace().unwrap_or("default");
+    let ballots = vm
+        .get_proposal_votes(proposal_id)?
+        .into_iter()
+        .filter_map(|(voter, choice)| Some((voter, VoteChoice::from_str(&choice).ok()?)))
+        .collect();
+    Ok(role_classes::tally_role_classes(
+        storage,
+        vm.get_auth_context(),
+        namespace,
+        &lifecycle.role_classes,
+        &ballots,
+    )?)
+}
+
 /// Handle the view command to display proposal details
 fn handle_view_command<S>(vm: &VM<S>, proposal_id: &str) -> Result<(), Box<dyn Error>>
 where
@@ -2241,73 +2990,180 @@ where
     // Count votes
     let (yes_votes, no_votes, abstain_votes) = count_votes(vm, &proposal_id_string)?;
     let total_votes = yes_votes + no_votes + abstain_votes;
+    let lifecycle = load_proposal(vm, &proposal_id_string).ok();
+    let weighted_votes = count_weighted_votes(vm, &proposal_id_string)?;
+    let voter_eligibility = lifecycle.as_ref().map(|l| l.voter_eligibility);
+    let role_classes = match &lifecycle {
+        Some(lifecycle) if !lifecycle.role_classes.is_empty() => {
+            tally_role_classes(vm, &proposal_id_string, lifecycle)?
+        }
+        _ => Vec::new(),
+    };
 
     // Calculate participation percentage for quorum
-    let quorum_percentage = if let Ok(lifecycle) = load_proposal(vm, &proposal_id_string) {
-        if lifecycle.quorum > 0 {
+    let quorum_percent = lifecycle
+        .as_ref()
+        .filter(|l| l.quorum > 0)
+        .map(|lifecycle| {
             let total_typed = f64_to_typed(total_votes as f64);
             let quorum_typed = f64_to_typed(lifecycle.quorum as f64);
-            let quorum_value = safe_percentage(&total_typed, &quorum_typed).unwrap_or(0.0);
-            format!("{:.1}%", quorum_value)
-        } else {
-            "N/A".to_string()
-        }
-    } else {
-        "Unknown".to_string()
+            safe_percentage(&total_typed, &quorum_typed).unwrap_or(0.0)
+        });
+
+    // Calculate threshold percentage, by weight for weighted proposals
+    let (yes_share, total_share) = match &weighted_votes {
+        Some(tally) => (
+            tally.yes.to_f64().unwrap_or(0.0),
+            (tally.yes + tally.no + tally.abstain).to_f64().unwrap_or(0.0),
+        ),
+        None => (yes_votes as f64, total_votes as f64),
     };
+    let threshold_percent = lifecycle
+        .as_ref()
+        .filter(|l| l.threshold > 0 && total_share > 0.0)
+        .map(|_| {
+            let yes_typed = f64_to_typed(yes_share);
+            let total_typed = f64_to_typed(total_share);
+            safe_percentage(&yes_typed, &total_typed).unwrap_or(0.0)
+        });
 
-    // Calculate threshold percentage
-    let threshold_percentage = if let Ok(lifecycle) = load_proposal(vm, &proposal_id_string) {
-        if lifecycle.threshold > 0 && total_votes > 0 {
-            let yes_typed = f64_to_typed(yes_votes as f64);
-            let total_typed = f64_to_typed(total_votes as f64);
-            let threshold_value = safe_percentage(&yes_typed, &total_typed).unwrap_or(0.0);
-            format!("{:.1}%", threshold_value)
-        } else {
-            "N/A".to_string()
-        }
-    } else {
-        "Unknown".to_string()
+    let view = ProposalView {
+        id: proposal_id.to_string(),
+        title: lifecycle.map(|l| l.title),
+        creator: proposal.creator,
+        status: proposal.status,
+        created_at: proposal.created_at,
+        votes: VoteTally {
+            yes: yes_votes,
+            no: no_votes,
+            abstain: abstain_votes,
+            total: total_votes,
+        },
+        weighted_votes,
+        quorum_percent,
+        threshold_percent,
+        role_classes,
+        cloned_from: proposal.cloned_from,
+        voter_eligibility,
+        execution_result: proposal.execution_result,
+        expires_at: proposal.expires_at,
+        logic_path: proposal.logic_path,
+    };
+
+    print_output(&view, print_proposal_view)
+}
+
+/// Proposal row printed by `proposal list`
+#[derive(Debug, Serialize)]
+struct ProposalListEntry {
+    id: String,
+    title: String,
+    status: ProposalStatus,
+}
+
+/// Vote counts for a proposal
+#[derive(Debug, Serialize)]
+struct VoteTally {
+    yes: u32,
+    no: u32,
+    abstain: u32,
+    total: u32,
+}
+
+/// Proposal details printed by `proposal view`
+#[derive(Debug, Serialize)]
+struct ProposalView {
+    id: String,
+    /// `None` when the proposal's lifecycle could not be loaded
+    title: Option<String>,
+    creator: String,
+    status: ProposalStatus,
+    created_at: DateTime<Utc>,
+    votes: VoteTally,
+    /// Vote totals at the snapshot weights, for weighted proposals
+    weighted_votes: Option<WeightedTally>,
+    /// Participation as a percentage of the quorum, if one is set
+    quorum_percent: Option<f64>,
+    /// Share of yes votes, once a threshold is set and votes were cast
+    threshold_percent: Option<f64>,
+    /// Votes of each role class the proposal sets requirements for
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    role_classes: Vec<ClassTally>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cloned_from: Option<String>,
+    /// Eligibility rule, when the lifecycle could be loaded
+    voter_eligibility: Option<VoterEligibility>,
+    execution_result: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    logic_path: Option<String>,
+}
+
+fn print_proposal_view(view: &ProposalView) {
+    // Without a lifecycle, the percentages are unknown rather than not applicable
+    let percentage = |value: Option<f64>| match (value, &view.title) {
+        (Some(value), _) => format!("{:.1}%", value),
+        (None, Some(_)) => "N/A".to_string(),
+        (None, None) => "Unknown".to_string(),
     };
 
     // Print formatted output
-    println!("\n=== Proposal Details: {} ===", proposal_id);
-    println!(
-        "Title:     {}",
-        load_proposal(vm, &proposal_id_string)
-            .map(|p| p.title)
-            .unwrap_or_else(|_| "N/A".to_string())
-    );
-    println!("Creator:   {}", proposal.creator);
-    println!("Status:    {:?}", proposal.status);
-    println!("Created:   {}", proposal.created_at);
+    println!("\n=== Proposal Details: {} ===", view.id);
+    println!("Title:     {}", view.title.as_deref().unwrap_or("N/A"));
+    println!("Creator:   {}", view.creator);
+    println!("Status:    {:?}", view.status);
+    println!("Created:   {}", view.created_at);
+    if let Some(source) = &view.cloned_from {
+        println!("Cloned from: {}", source);
+    }
 
     // Print vote counts
     println!("\n=== Voting Information ===");
-    println!("Yes votes:      {}", yes_votes);
-    println!("No votes:       {}", no_votes);
-    println!("Abstain votes:  {}", abstain_votes);
-    println!("Total votes:    {}", total_votes);
-    println!("Quorum:         {}", quorum_percentage);
-    println!("Threshold:      {}", threshold_percentage);
+    println!("Yes votes:      {}", view.votes.yes);
+    println!("No votes:       {}", view.votes.no);
+    println!("Abstain votes:  {}", view.votes.abstain);
+    println!("Total votes:    {}", view.votes.total);
+    if let Some(weighted) = &view.weighted_votes {
+        println!(
+            "Weighted:       {} yes, {} no, {} abstain",
+            weighted.yes, weighted.no, weighted.abstain
+        );
+    }
+    println!("Quorum:         {}", percentage(view.quorum_percent));
+    println!("Threshold:      {}", percentage(view.threshold_percent));
+    if let Some(rule) = view.voter_eligibility {
+        println!("Eligible:       {}", rule);
+    }
+    for class in &view.role_classes {
+        println!(
+            "Class {}: {} yes, {} no of {} \
+             (quorum {:.1}%/{:.0}%, threshold {:.1}%/{:.0}%) {}",
+            class.role,
+            class.yes,
+            class.no,
+            class.eligible,
+            class.participation() * 100.0,
+            class.quorum * 100.0,
+            class.approval() * 100.0,
+            class.threshold * 100.0,
+            if class.passed() { "✅" } else { "❌" }
+        );
+    }
 
     // Print execution result if any
-    if let Some(result) = &proposal.execution_result {
+    if let Some(result) = &view.execution_result {
         println!("\n=== Execution Result ===");
         println!("{}", result);
     }
 
     // Print other metadata
     println!("\n=== Additional Information ===");
-    if let Some(expires) = &proposal.expires_at {
+    if let Some(expires) = &view.expires_at {
         println!("Expires at: {}", expires);
     }
 
-    if let Some(logic_path) = &proposal.logic_path {
+    if let Some(logic_path) = &view.logic_path {
         println!("Logic path: {}", logic_path);
     }
-
-    Ok(())
 }
 
 /// Load a ProposalLifecycle for more information
@@ -2326,13 +3182,13 @@ where
         .ok_or_else(|| VMError::StorageUnavailable)?
         .get(None, "proposals", &storage_key)
         .map_err(|e| {
-            eprintln!("Failed to read proposal lifecycle: {}", e);
+            tracing::error!(proposal_id, error = %e, "Failed to read proposal lifecycle");
             Box::new(e) as Box<dyn Error>
         })?;
 
     // Deserialize the proposal
     serde_json::from_slice::<ProposalLifecycle>(&proposal_data).map_err(|e| {
-        eprintln!("Failed to deserialize proposal lifecycle: {}", e);
+        tracing::error!(proposal_id, error = %e, "Failed to deserialize proposal lifecycle");
         Box::new(e) as Box<dyn Error>
     })
 }
@@ -2354,19 +3210,19 @@ where
     // Count comments
     let auth_context = None; // No auth needed for summary
     let comments = fetch_comments_threaded(vm, proposal_id, auth_context, false)?;
-    let comment_count = comments.len();
 
-    // Calculate some statistics
-    let top_commenters: Vec<(&String, usize)> = comments
-        .iter()
-        .map(|(_, comment)| &comment.author)
-        .fold(HashMap::new(), |mut map, author| {
-            *map.entry(author).or_insert(0) += 1;
+    // Rank commenters by number of comments
+    let mut top_commenters: Vec<CommenterCount> = comments
+        .values()
+        .fold(HashMap::new(), |mut map, comment| {
+            *map.entry(comment.author.clone()).or_insert(0) += 1;
             map
         })
-        .iter()
-        .map(|(author, count)| (*author, *count))
+        .into_iter()
+        .map(|(author, comments)| CommenterCount { author, comments })
         .collect();
+    top_commenters.sort_by(|a, b| b.comments.cmp(&a.comments).then(a.author.cmp(&b.author)));
+    top_commenters.truncate(5);
 
     // Find the last activity timestamp
     let last_activity = comments
@@ -2375,64 +3231,97 @@ where
         .max()
         .unwrap_or(proposal.created_at);
 
+    let summary = ProposalSummary {
+        id: proposal_id.to_string(),
+        title: load_proposal_lifecycle(vm, proposal_id)
+            .ok()
+            .map(|l| l.title),
+        status: proposal.status,
+        created_at: proposal.created_at,
+        last_activity,
+        votes: VoteTally {
+            yes: yes_votes,
+            no: no_votes,
+            abstain: abstain_votes,
+            total: total_votes,
+        },
+        comments: comments.len(),
+        top_commenters,
+    };
+
+    print_output(&summary, print_proposal_summary)
+}
+
+/// Number of comments by one author
+#[derive(Debug, Serialize)]
+struct CommenterCount {
+    author: String,
+    comments: usize,
+}
+
+/// Condensed overview printed by `proposal summary`
+#[derive(Debug, Serialize)]
+struct ProposalSummary {
+    id: String,
+    title: Option<String>,
+    status: ProposalStatus,
+    created_at: DateTime<Utc>,
+    last_activity: DateTime<Utc>,
+    votes: VoteTally,
+    comments: usize,
+    /// Most active commenters, at most five
+    top_commenters: Vec<CommenterCount>,
+}
+
+fn print_proposal_summary(summary: &ProposalSummary) {
+    let share = |count: u32| {
+        if summary.votes.total > 0 {
+            let count_typed = f64_to_typed(count as f64);
+            let total_typed = f64_to_typed(summary.votes.total as f64);
+            safe_percentage(&count_typed, &total_typed).unwrap_or(0.0)
+        } else {
+            0.0
+        }
+    };
+
     // Print summary
-    println!("\n=== Proposal Summary: {} ===", proposal_id);
-    if let Ok(lifecycle) = load_proposal_lifecycle(vm, proposal_id) {
-        println!("Title:      {}", lifecycle.title);
+    println!("\n=== Proposal Summary: {} ===", summary.id);
+    if let Some(title) = &summary.title {
+        println!("Title:      {}", title);
     }
-    println!("Status:     {:?}", proposal.status);
-    println!("Created:    {}", proposal.created_at);
-    println!("Last activity: {}", last_activity);
+    println!("Status:     {:?}", summary.status);
+    println!("Created:    {}", summary.created_at);
+    println!("Last activity: {}", summary.last_activity);
 
     // Print vote summary
     println!("\n=== Vote Summary ===");
     println!(
         "Yes:     {} ({:.1}%)",
-        yes_votes,
-        if total_votes > 0 {
-            let yes_typed = f64_to_typed(yes_votes as f64);
-            let total_typed = f64_to_typed(total_votes as f64);
-            safe_percentage(&yes_typed, &total_typed).unwrap_or(0.0)
-        } else {
-            0.0
-        }
+        summary.votes.yes,
+        share(summary.votes.yes)
     );
     println!(
         "No:      {} ({:.1}%)",
-        no_votes,
-        if total_votes > 0 {
-            let no_typed = f64_to_typed(no_votes as f64);
-            let total_typed = f64_to_typed(total_votes as f64);
-            safe_percentage(&no_typed, &total_typed).unwrap_or(0.0)
-        } else {
-            0.0
-        }
+        summary.votes.no,
+        share(summary.votes.no)
     );
     println!(
         "Abstain: {} ({:.1}%)",
-        abstain_votes,
-        if total_votes > 0 {
-            let abstain_typed = f64_to_typed(abstain_votes as f64);
-            let total_typed = f64_to_typed(total_votes as f64);
-            safe_percentage(&abstain_typed, &total_typed).unwrap_or(0.0)
-        } else {
-            0.0
-        }
+        summary.votes.abstain,
+        share(summary.votes.abstain)
     );
-    println!("Total:   {}", total_votes);
+    println!("Total:   {}", summary.votes.total);
 
     // Print comment summary
     println!("\n=== Comment Summary ===");
-    println!("Total comments: {}", comment_count);
+    println!("Total comments: {}", summary.comments);
 
-    if !top_commenters.is_empty() {
+    if !summary.top_commenters.is_empty() {
         println!("\nTop commenters:");
-        for (author, count) in top_commenters.iter().take(5) {
-            println!("  {}: {} comments", author, count);
+        for commenter in &summary.top_commenters {
+            println!("  {}: {} comments", commenter.author, commenter.comments);
         }
     }
-
-    Ok(())
 }
 
 /// Handle the simulate command to test execution of a proposal without making persistent changes
@@ -2447,7 +3336,6 @@ where
 }
 
 /// Handle the comment-react command to add reactions to comments
-#[allow(unused)]
 pub fn handle_comment_react_command<S>(
     vm: &mut VM<S>,
     comment_id: &str,
@@ -2456,9 +3344,13 @@ pub fn handle_comment_react_command<S>(
     auth_context: &AuthContext,
 ) -> Result<(), Box<dyn Error>>
 where
-    S: Storage + Send + Sync + Clone + Debug + 'static,
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
-    unimplemented!("Stub implementation")
+    comments::react_to_comment(vm, proposal_id, comment_id, reaction, auth_context)?;
+
+    println!("Reacted {} to comment {}.", reaction, comment_id);
+
+    Ok(())
 }
 
 /// Handle the comment-tag command to add tags to comments
@@ -2676,6 +3568,7 @@ pub fn handle_vote_command<S>(
     vote_choice: &str,
     delegate_identity: Option<&str>,
     auth_context: &AuthContext,
+    mut dry_run: Option<&mut DryRun>,
 ) -> Result<(), Box<dyn Error>>
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
@@ -2710,7 +3603,7 @@ where
 
     // Check if the minimum deliberation period has passed
     if let Some(min_deliberation) = proposal_lifecycle.discussion_duration {
-        let now = Utc::now();
+        let now = vm.clock().now();
         let elapsed = now.signed_duration_since(proposal_lifecycle.created_at);
 
         if elapsed < min_deliberation {
@@ -2740,12 +3633,20 @@ where
     };
 
     // Cast the vote using the trait method
-    vm.cast_vote(proposal_id, &voter_id, vote_value, delegate_identity)?;
+    vm.cast_vote(
+        proposal_id,
+        &voter_id,
+        vote_value,
+        delegate_identity,
+        dry_run.as_deref_mut(),
+    )?;
 
-    println!(
-        "✅ Vote '{}' recorded for proposal '{}' by '{}'",
-        vote_value, proposal_id, voter_id
-    );
+    if dry_run.is_none() {
+        println!(
+            "✅ Vote '{}' recorded for proposal '{}' by '{}'",
+            vote_value, proposal_id, voter_id
+        );
+    }
 
     // Award reputation for participation
     let rep_dsl = format!(
@@ -2753,40 +3654,57 @@ where
         voter_id, proposal_id
     );
     let (ops, _) = parse_dsl(&rep_dsl)?;
-    vm.execute(&ops)?;
+    if let Some(dry_run) = dry_run {
+        dry_run::rehearse(dry_run, vm, &ops)?;
+    } else {
+        vm.execute(&ops)?;
+    }
 
     Ok(())
 }
 
-/// Handle the execute command to run proposal logic if it passed
-pub fn handle_execute_command<S>(
-    vm: &mut VM<S>,
-    proposal_id: &str,
-    auth_context: &AuthContext,
-) -> Result<(), Box<dyn Error>>
+/// Votes cast on a proposal, measured against its quorum and threshold
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct VoteOutcome {
+    pub yes_votes: u64,
+    pub no_votes: u64,
+    pub abstain_votes: u64,
+    /// Share of the votes cast that are yes
+    pub yes_ratio: f64,
+    /// Votes cast as a share of the required participants
+    pub participation_rate: f64,
+    pub quorum_ratio: f64,
+    pub threshold_ratio: f64,
+}
+
+impl VoteOutcome {
+    pub fn quorum_met(&self) -> bool {
+        self.participation_rate >= self.quorum_ratio
+    }
+
+    pub fn threshold_met(&self) -> bool {
+        self.yes_ratio >= self.threshold_ratio
+    }
+
+    /// Whether the proposal passed and its logic may run
+    pub fn passed(&self) -> bool {
+        self.quorum_met() && self.threshold_met()
+    }
+}
+
+/// Tally the votes on a proposal against its quorum and threshold
+pub(crate) fn vote_outcome<S>(
+    vm: &VM<S>,
+    lifecycle: &ProposalLifecycle,
+) -> Result<VoteOutcome, Box<dyn Error>>
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
-    // First check if proposal exists
-    if !vm
-        .get_storage_backend()
-        .ok_or_else(|| "Storage backend not configured for proposal execution")?
-        .contains(
-            Some(auth_context),
-            &vm.get_namespace().unwrap_or("default"),
-            &VM::<S>::proposal_key_prefix(proposal_id),
-        )?
-    {
-        return Err(format!("Proposal with ID '{}' not found", proposal_id).into());
-    }
-
-    // Tally votes
-    let votes = vm.get_proposal_votes(proposal_id)?;
+    let votes = vm.get_proposal_votes(&lifecycle.id)?;
 
     let mut yes_votes = 0;
     let mut no_votes = 0;
     let mut abstain_votes = 0;
-
     for (_, vote) in &votes {
         match vote.to_lowercase().as_str() {
             "yes" => yes_votes += 1,
@@ -2804,6 +3722,108 @@ where
         0.0
     };
 
+    // Calculate participation rate
+    let required_participants = lifecycle.required_participants.unwrap_or(1);
+    let participation_rate = if required_participants > 0 {
+        total_votes as f64 / required_participants as f64
+    } else {
+        1.0 // Avoid division by zero
+    };
+
+    // Convert stored percentages to ratios (they're stored as integers 0-100)
+    Ok(VoteOutcome {
+        yes_votes,
+        no_votes,
+        abstain_votes,
+        yes_ratio,
+        participation_rate,
+        quorum_ratio: lifecycle.quorum as f64 / 100.0,
+        threshold_ratio: lifecycle.threshold as f64 / 100.0,
+    })
+}
+
+/// Scheduled transitions run through the same storage operations as the
+/// proposal commands, in the VM's namespace
+impl<S> ScheduledProposals for VM<S>
+where
+    S: StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    fn lifecycles(&self) -> Result<Vec<ProposalLifecycle>, Box<dyn Error>> {
+        let namespace = self.get_namespace().unwrap_or("default");
+        let keys = self
+            .get_storage_backend()
+            .ok_or("Storage not available")?
+            .list_keys(
+                self.get_auth_context(),
+                namespace,
+                Some("governance_proposals/"),
+            )?;
+        keys.iter()
+            .filter_map(|key| {
+                key.strip_prefix("governance_proposals/")?
+                    .strip_suffix("/lifecycle")
+            })
+            .map(|id| self.get_proposal_lifecycle(id))
+            .collect()
+    }
+
+    fn voting_closes(
+        &self,
+        lifecycle: &ProposalLifecycle,
+    ) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+        match lifecycle.expires_at {
+            Some(closes) => Ok(Some(closes)),
+            None => Ok(self.get_proposal(&lifecycle.id)?.expires_at),
+        }
+    }
+
+    fn passed(&self, lifecycle: &ProposalLifecycle) -> Result<bool, Box<dyn Error>> {
+        Ok(vote_outcome(self, lifecycle)?.passed())
+    }
+
+    fn transition(&mut self, id: &str, state: ProposalState) -> Result<(), Box<dyn Error>> {
+        self.update_proposal_state(id, state, None)
+    }
+
+    fn save_lifecycle(&mut self, lifecycle: &ProposalLifecycle) -> Result<(), Box<dyn Error>> {
+        let namespace = self.get_namespace().unwrap_or("default").to_string();
+        let auth_context = self.get_auth_context().cloned();
+        let lifecycle_key = Self::proposal_lifecycle_key(&lifecycle.id);
+        self.get_storage_backend_mut()
+            .ok_or("Storage not available")?
+            .set_json(auth_context.as_ref(), &namespace, &lifecycle_key, lifecycle)
+            .map_err(|e| format!("Failed to update proposal lifecycle: {}", e))?;
+        Ok(())
+    }
+
+    fn execute(&mut self, id: &str) -> Result<(), Box<dyn Error>> {
+        self.execute_proposal(id, None)
+    }
+}
+
+/// Handle the execute command to run proposal logic if it passed
+pub fn handle_execute_command<S>(
+    vm: &mut VM<S>,
+    proposal_id: &str,
+    auth_context: &AuthContext,
+    dry_run: Option<&mut DryRun>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    // First check if proposal exists
+    if !vm
+        .get_storage_backend()
+        .ok_or_else(|| "Storage backend not configured for proposal execution")?
+        .contains(
+            Some(auth_context),
+            &vm.get_namespace().unwrap_or("default"),
+            &VM::<S>::proposal_key_prefix(proposal_id),
+        )?
+    {
+        return Err(format!("Proposal with ID '{}' not found", proposal_id).into());
+    }
+
     // Load the proposal metadata to get quorum and threshold
     let proposal_lifecycle = vm.get_proposal_lifecycle(proposal_id)?;
 
@@ -2812,21 +3832,18 @@ where
         return Err(format!("Proposal '{}' has already been executed", proposal_id).into());
     }
 
-    // Convert stored percentages to ratios (they're stored as integers 0-100)
-    let quorum_ratio = proposal_lifecycle.quorum as f64 / 100.0;
-    let threshold_ratio = proposal_lifecycle.threshold as f64 / 100.0;
-
-    // Calculate participation rate
-    let required_participants = proposal_lifecycle.required_participants.unwrap_or(1);
-    let participation_rate = if required_participants > 0 {
-        total_votes as f64 / required_participants as f64
-    } else {
-        1.0 // Avoid division by zero
-    };
-
-    // Check if proposal passed
-    let quorum_met = participation_rate >= quorum_ratio;
-    let threshold_met = yes_ratio >= threshold_ratio;
+    let outcome = vote_outcome(vm, &proposal_lifecycle)?;
+    let VoteOutcome {
+        yes_votes,
+        no_votes,
+        abstain_votes,
+        yes_ratio,
+        participation_rate,
+        quorum_ratio,
+        threshold_ratio,
+    } = outcome;
+    let quorum_met = outcome.quorum_met();
+    let threshold_met = outcome.threshold_met();
 
     // If proposal did not pass, return with message
     if !quorum_met {
@@ -2863,7 +3880,7 @@ where
     );
 
     // Use the execute_proposal method from our trait
-    match vm.execute_proposal(proposal_id) {
+    match vm.execute_proposal(proposal_id, dry_run) {
         Ok(_) => {
             println!("✅ Logic executed successfully.");
             Ok(())
@@ -2918,7 +3935,7 @@ where
                 comments.push(comment);
             }
             Err(e) => {
-                eprintln!("Warning: Failed to parse comment at {}: {}", key, e);
+                tracing::warn!(key = %key, error = %e, "Failed to parse comment");
                 // Continue with other comments
             }
         }
@@ -3007,6 +4024,119 @@ fn display_comment(
     }
 }
 
+/// Post a proposal milestone to the chat channels of the VM's namespace
+fn notify_chat<S>(vm: &VM<S>, notice: Notice)
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    if let Some(storage) = vm.get_storage_backend() {
+        let namespace = vm.get_namespace().unwrap_or("default");
+        notifications::notify(storage, vm.get_auth_context(), namespace, &notice);
+    }
+}
+
+/// Record the vote weights of a weighted proposal that has just opened for
+/// voting, unless a snapshot was already taken
+fn snapshot_vote_weights<S>(
+    dry_run: Option<&mut DryRun>,
+    storage: &mut S,
+    auth_context: Option<&AuthContext>,
+    namespace: &str,
+    lifecycle: &ProposalLifecycle,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage,
+{
+    if lifecycle.state != ProposalState::Voting || !lifecycle.vote_weight.is_weighted() {
+        return Ok(());
+    }
+    if snapshot::load_snapshot(storage, auth_context, namespace, &lifecycle.id)?.is_some() {
+        return Ok(());
+    }
+
+    let taken = snapshot::snapshot_weights(
+        storage,
+        auth_context,
+        namespace,
+        &lifecycle.id,
+        &lifecycle.vote_weight,
+    )?;
+    let rehearsed = dry_run.is_some();
+    dry_run::set_json(
+        dry_run,
+        storage,
+        auth_context,
+        namespace,
+        &snapshot::snapshot_key(&lifecycle.id),
+        &taken,
+    )
+    .map_err(|e| format!("Failed to snapshot vote weights: {}", e))?;
+    if !rehearsed {
+        println!(
+            "📸 Vote weights ({}) recorded for {} members",
+            lifecycle.vote_weight,
+            taken.weights.len()
+        );
+    }
+    Ok(())
+}
+
+/// Settle the escrows held under a proposal that has just been decided:
+/// released once it is executed, refunded once it is rejected or expired
+///
+/// A dry run reports what each escrow would pay instead.
+fn settle_proposal_escrows<S>(
+    dry_run: Option<&mut DryRun>,
+    storage: &mut S,
+    auth_context: Option<&AuthContext>,
+    namespace: &str,
+    proposal_id: &str,
+    state: &ProposalState,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage,
+{
+    let outcome = match state {
+        ProposalState::Executed => EscrowOutcome::Release,
+        ProposalState::Rejected | ProposalState::Expired => EscrowOutcome::Refund,
+        _ => return Ok(()),
+    };
+
+    if let Some(dry_run) = dry_run {
+        let events: Vec<VMEvent> = storage
+            .get_escrows(auth_context, namespace, proposal_id)?
+            .into_iter()
+            .filter(|(_, escrow)| escrow.status == EscrowStatus::Locked)
+            .map(|(_, escrow)| VMEvent {
+                category: "economic".to_string(),
+                message: format!(
+                    "escrow: {} of {} to {}",
+                    escrow.amount,
+                    escrow.resource,
+                    escrow.payee(outcome)
+                ),
+                timestamp: Utc::now().timestamp() as u64,
+                severity: Severity::Info,
+            })
+            .collect();
+        dry_run.record_events(&events);
+        return Ok(());
+    }
+
+    let (settled, _) = storage
+        .settle_escrows(auth_context, namespace, proposal_id, outcome)
+        .map_err(|e| format!("Failed to settle escrows: {}", e))?;
+    for escrow in &settled {
+        println!(
+            "💰 Escrow: {} of {} paid to {}",
+            escrow.amount,
+            escrow.resource,
+            escrow.payee(outcome)
+        );
+    }
+    Ok(())
+}
+
 /// Helper function to shorten DIDs for display
 fn shorten_did(did: &str) -> String {
     if did.starts_with("did:") {
@@ -3055,6 +4185,120 @@ struct CommentExport {
     parent: Option<String>,
 }
 
+/// Handle the export-all command to write the governance tables of the
+/// current namespace to `output_dir`
+pub fn handle_export_all_command<S>(
+    vm: &VM<S>,
+    format: ExportFormat,
+    output_dir: &Path,
+    auth_context: &AuthContext,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm
+        .get_storage_backend()
+        .ok_or("Storage backend not configured for proposal export")?;
+    let namespace = vm.get_namespace().unwrap_or("default");
+    let tables = export::collect_tables(storage, Some(auth_context), namespace)?;
+
+    // Encode every table before writing so an unsupported format leaves no
+    // partial export behind
+    let files = tables
+        .iter()
+        .map(|table| Ok((table, table.encode(format)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    std::fs::create_dir_all(output_dir)?;
+    for (table, bytes) in files {
+        let path = output_dir.join(format!("{}.{}", table.name, format.extension()));
+        std::fs::write(&path, bytes)?;
+        println!(
+            "✅ Wrote {} {} rows to {}",
+            table.rows.len(),
+            table.name,
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Handle the archive command to write closed proposals of the current
+/// namespace to a signed archive in `output_dir`
+pub fn handle_archive_command<S>(
+    vm: &VM<S>,
+    proposal_ids: &[String],
+    output_dir: &Path,
+    auth_context: &AuthContext,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let signer = auth_context
+        .get_identity(auth_context.identity_did())
+        .ok_or(
+            "Archives are signed by their archiver; set identity.key_path \
+             (see `icn-covm keys generate`)",
+        )?;
+    let archive = archive::build_archive(vm, auth_context, signer, proposal_ids)?;
+    archive.write_to(output_dir)?;
+
+    print_output(&archive.manifest, |manifest| {
+        println!(
+            "📦 Archived {} proposals and {} ledger nodes to {}",
+            manifest.proposals.len(),
+            manifest.ledger_nodes.len(),
+            output_dir.display()
+        );
+        println!("   Files: {}", manifest.files.len() + 1);
+        println!("   Signed by: {}", manifest.created_by);
+    })
+}
+
+/// Handle the verify-archive command, failing if the archive does not check out
+pub fn handle_verify_archive_command<S>(
+    vm: &VM<S>,
+    archive_dir: &Path,
+    check_ledger: bool,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let ledger = if check_ledger {
+        Some(
+            vm.dag
+                .as_ref()
+                .ok_or("DAG ledger not available; use --no-ledger to skip it")?,
+        )
+    } else {
+        None
+    };
+    let report = archive::verify_archive(archive_dir, ledger)?;
+
+    print_output(&report, |report| {
+        println!(
+            "🔍 Archive of {} proposals from {}, made {} by {}",
+            report.manifest.proposals.len(),
+            report.manifest.namespace,
+            report.manifest.created_at.to_rfc3339(),
+            report.manifest.created_by
+        );
+        if !report.ledger_checked {
+            println!("   Ledger not checked");
+        }
+        for issue in &report.issues {
+            println!("❌ {}", issue);
+        }
+        if report.is_valid() {
+            println!("✅ Archive verified");
+        }
+    })?;
+    if !report.is_valid() {
+        return Err(format!("Archive has {} issues", report.issues.len()).into());
+    }
+    Ok(())
+}
+
 /// Handle the export command to export proposal data to a JSON file
 pub fn handle_export_command<S>(
     vm: &mut VM<S>,
@@ -3120,7 +4364,7 @@ where
                 });
             }
             Err(e) => {
-                eprintln!("Warning: Failed to parse vote at {}: {}", key, e);
+                tracing::warn!(key = %key, error = %e, "Failed to parse vote");
                 // Continue with other votes
             }
         }
@@ -3142,7 +4386,7 @@ where
                 });
             }
             Err(e) => {
-                eprintln!("Warning: Failed to parse comment at {}: {}", key, e);
+                tracing::warn!(key = %key, error = %e, "Failed to parse comment");
                 // Continue with other comments
             }
         }
@@ -3265,8 +4509,68 @@ mod tests {
 
         Ok(())
     }
-    
-    // ...rest of test methods...
+
+    #[test]
+    fn test_clone_proposal_copies_logic_and_parameters() {
+        let mut vm = setup_test_vm();
+        let mut auth = setup_test_auth();
+        auth.add_role("global", "admin");
+        vm.set_auth_context(auth);
+
+        let mut source = Proposal::new(
+            "budget".to_string(),
+            "alice".to_string(),
+            None,
+            Some(Utc::now() + Duration::days(7)),
+            None,
+            Vec::new(),
+        );
+        source.labels = vec!["finance".to_string()];
+        let mut lifecycle = ProposalLifecycle::new(
+            "budget".to_string(),
+            did_to_identity("alice").unwrap(),
+            "Q3 budget".to_string(),
+            50,
+            60,
+            None,
+            None,
+        );
+        lifecycle.vote_weight = VoteWeight::Reputation;
+        vm.create_proposal(source, lifecycle, "Fund the garden", "push 1.0", None)
+            .unwrap();
+
+        let clone_id = clone_proposal(&mut vm, "budget", None, None, "bob", None).unwrap();
+        assert_eq!(clone_id, "budget-2");
+        let clone = vm.get_proposal(&clone_id).unwrap();
+        assert_eq!(clone.cloned_from.as_deref(), Some("budget"));
+        assert_eq!(clone.creator, "bob");
+        assert!(matches!(clone.status, ProposalStatus::Draft));
+        assert_eq!(clone.labels, vec!["finance".to_string()]);
+
+        let cloned = vm.get_proposal_lifecycle(&clone_id).unwrap();
+        assert_eq!(cloned.title, "Q3 budget");
+        assert_eq!((cloned.quorum, cloned.threshold), (50, 60));
+        assert_eq!(cloned.vote_weight, VoteWeight::Reputation);
+        assert_eq!(cloned.state, ProposalState::Draft);
+
+        let storage = vm.get_storage_backend().unwrap();
+        let auth = vm.get_auth_context();
+        let logic_key = VM::<InMemoryStorage>::proposal_logic_key(&clone_id);
+        assert_eq!(
+            storage.get(auth, "test_ns", &logic_key).unwrap(),
+            b"push 1.0"
+        );
+        let description_key = VM::<InMemoryStorage>::proposal_description_key(&clone_id);
+        assert_eq!(
+            storage.get(auth, "test_ns", &description_key).unwrap(),
+            b"Fund the garden"
+        );
+
+        // Further clones take the next free suffix; a taken ID is refused
+        let next = clone_proposal(&mut vm, "budget", None, Some("Q4 budget"), "bob", None).unwrap();
+        assert_eq!(next, "budget-3");
+        assert!(clone_proposal(&mut vm, "budget", Some("budget-2"), None, "bob", None).is_err());
+    }
 }
 
 /// Simple comment structure for storage
@@ -3590,12 +4894,7 @@ where
     // Display node counts by type
     let mut node_summary = HashMap::new();
     for node in &nodes {
-        let type_name = match &node.data {
-            icn_ledger::NodeData::ProposalCreated { .. } => "ProposalCreated".to_string(),
-            icn_ledger::NodeData::VoteCast { .. } => "VoteCast".to_string(),
-            icn_ledger::NodeData::ProposalExecuted { .. } => "ProposalExecuted".to_string(),
-            icn_ledger::NodeData::TokenMinted { .. } => "TokenMinted".to_string(),
-        };
+        let type_name = node.data.type_name().to_string();
         *node_summary.entry(type_name).or_insert(0) += 1;
     }
     
@@ -139,7 +139,7 @@ pub fn run_proposal_demo() -> Result<(), Box<dyn Error>> {
         storage.get_json::<Proposal>(Some(&auth), "governance", &proposal.storage_key())
     })??;
 
-    proposal.mark_deliberation();
+    proposal.mark_deliberation(vm.clock());
 
     vm.with_storage_mut(|storage| {
         storage.set_json(
@@ -0,0 +1,122 @@
+//! Activity digests of proposals
+//!
+//! `proposal digest` prints what happened in the namespace over the last
+//! period (a day unless `--since` says otherwise), see
+//! [`crate::governance::digest`]. `--post` also sends it to the namespace's
+//! chat channels, and `--daily HH:MM` keeps running, building and posting a
+//! digest every day at that time (UTC) until interrupted:
+//!
+//! ```bash
+//! icn-covm proposal digest --since 7d --output json
+//! icn-covm proposal digest --daily 07:00 --post
+//! ```
+
+use crate::cli::output::{self, print_output};
+use crate::governance::digest::{build_digest, Digest};
+use crate::governance::notifications;
+use crate::storage::traits::Storage;
+use crate::vm::VM;
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use std::error::Error;
+use std::fmt::Debug;
+
+/// Parse a `HH:MM` time of day
+pub fn parse_time_of_day(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| format!("Invalid time of day: {}. Expected HH:MM", value))
+}
+
+/// The first time after `now` that the clock reads `at` (UTC)
+fn next_run(now: DateTime<Utc>, at: NaiveTime) -> DateTime<Utc> {
+    let today = now.date_naive().and_time(at).and_utc();
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+/// Build the digest of the period ending now, print it, and post it if asked
+fn digest_once<S>(
+    vm: &VM<S>,
+    since: Duration,
+    ahead: Duration,
+    post: bool,
+) -> Result<Digest, Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm.get_storage_backend().ok_or("Storage not available")?;
+    let auth = vm.get_auth_context();
+    let namespace = vm.get_namespace().unwrap_or("default");
+    let to = vm.clock().now();
+    let digest = build_digest(storage, auth, namespace, to - since, to, ahead)?;
+
+    print_output(&digest, |digest| print!("{}", digest.to_markdown()))?;
+    if post {
+        if digest.is_empty() {
+            tracing::info!(namespace, "Nothing to report; digest not posted");
+        } else {
+            let delivered = notifications::notify(storage, auth, namespace, &digest.notice());
+            if output::is_table() {
+                println!("\n📨 Posted to {} channel(s)", delivered);
+            }
+        }
+    }
+    Ok(digest)
+}
+
+/// Handle `proposal digest`
+pub fn run_digest<S>(
+    vm: &VM<S>,
+    since: Duration,
+    ahead: Duration,
+    post: bool,
+    daily: Option<NaiveTime>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let Some(at) = daily else {
+        digest_once(vm, since, ahead, post)?;
+        return Ok(());
+    };
+
+    eprintln!(
+        "📰 Building a digest every day at {} UTC (Ctrl-C to stop)",
+        at.format("%H:%M")
+    );
+    loop {
+        let now = vm.clock().now();
+        let wait = (next_run(now, at) - now).to_std().unwrap_or_default();
+        std::thread::sleep(wait);
+        // A failed digest is retried the next day rather than ending the job
+        if let Err(e) = digest_once(vm, since, ahead, post) {
+            tracing::error!(error = %e, "Failed to build digest");
+            eprintln!("⚠️  Failed to build digest: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_next_run_is_the_next_occurrence() {
+        let at = parse_time_of_day("07:00").unwrap();
+        let before = Utc.with_ymd_and_hms(2025, 3, 1, 6, 30, 0).unwrap();
+        assert_eq!(
+            next_run(before, at),
+            Utc.with_ymd_and_hms(2025, 3, 1, 7, 0, 0).unwrap()
+        );
+        let exactly = Utc.with_ymd_and_hms(2025, 3, 1, 7, 0, 0).unwrap();
+        assert_eq!(
+            next_run(exactly, at),
+            Utc.with_ymd_and_hms(2025, 3, 2, 7, 0, 0).unwrap()
+        );
+        assert!(parse_time_of_day("7am").is_err());
+    }
+}
@@ -0,0 +1,834 @@
+//! Bulk import of proposals, votes, and comments
+//!
+//! `proposal import --file <FILE>` brings an existing cooperative's decision
+//! history into icn-covm. The file is CSV with a header row, or a JSON array
+//! of objects, and each record is a proposal, a vote, or a comment, chosen by
+//! its `kind` column:
+//!
+//! ```text
+//! kind,id,title,quorum,threshold,state,created_at,proposal_id,voter,vote
+//! proposal,budget-2023,2023 budget,0.5,0.6,executed,2023-01-10,,,
+//! vote,,,,,,2023-01-12,budget-2023,did:key:z6Mk...,yes
+//! ```
+//!
+//! With `--source loomio` or `--source decidim`, the files are instead
+//! exports of those platforms, turned into records by `crate::import`.
+//! Imported records are flagged with the platform they came from.
+//!
+//! Every record is checked before anything is written, including against the
+//! other records and the proposals, votes, and comments already stored. If
+//! any record is invalid, the per-row report is printed and nothing is
+//! imported; otherwise all records are written in one transaction.
+
+use crate::cli::dry_run::{self, DryRun};
+use crate::cli::output::print_output;
+use crate::cli::proposal::{parse_proposal_state, VMProposalExtensions};
+use crate::cli::utils::safe_f64_to_u64;
+use crate::compiler::parse_dsl;
+use crate::governance::comments::{comments_namespace_for, CommentVersion, ProposalComment};
+use crate::governance::proposal::Proposal;
+use crate::governance::proposal_lifecycle::{ProposalLifecycle, ProposalState, VoteChoice};
+use crate::identity::Identity;
+use crate::import::{ImportSource, Row, SOURCE_REF};
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::Storage;
+use crate::vm::VM;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A proposal taken from an import file
+#[derive(Debug, Clone)]
+pub struct ImportedProposal {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// Fraction of participants required, between 0.0 and 1.0
+    pub quorum: f64,
+    /// Fraction of yes votes required, between 0.0 and 1.0
+    pub threshold: f64,
+    /// DSL source stored with the proposal
+    pub logic: String,
+    /// Creator DID, or the acting identity when not given
+    pub creator: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub state: ProposalState,
+    pub labels: Vec<String>,
+    /// Platform the proposal came from
+    pub imported_from: String,
+}
+
+/// A vote taken from an import file
+#[derive(Debug, Clone)]
+pub struct ImportedVote {
+    pub proposal_id: String,
+    pub voter: String,
+    /// `yes`, `no` or `abstain`
+    pub vote: String,
+    pub cast_at: Option<DateTime<Utc>>,
+    /// Platform the vote came from
+    pub imported_from: String,
+}
+
+/// A comment taken from an import file
+#[derive(Debug, Clone)]
+pub struct ImportedComment {
+    /// Comment ID, generated when not given
+    pub id: Option<String>,
+    pub proposal_id: String,
+    pub author: String,
+    pub content: String,
+    pub created_at: Option<DateTime<Utc>>,
+    /// ID of the comment this one replies to
+    pub reply_to: Option<String>,
+    pub tags: Vec<String>,
+    /// Platform the comment came from
+    pub imported_from: String,
+}
+
+/// One record of an import file
+#[derive(Debug, Clone)]
+pub enum ImportRecord {
+    Proposal(ImportedProposal),
+    Vote(ImportedVote),
+    Comment(ImportedComment),
+}
+
+/// A record that cannot be imported
+#[derive(Debug, Clone, Serialize)]
+pub struct RowError {
+    /// 1-based record number; the CSV header is not counted
+    pub row: usize,
+    pub message: String,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub rows: usize,
+    pub proposals: usize,
+    pub votes: usize,
+    pub comments: usize,
+    pub errors: Vec<RowError>,
+    /// Records of a platform export that have no icn-covm equivalent
+    pub skipped: Vec<String>,
+}
+
+/// Split CSV text into records, following RFC 4180 quoting
+///
+/// Blank lines are skipped. Quoted fields may contain commas, newlines and
+/// doubled quotes.
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    parse_csv_with(text, ',')
+}
+
+/// Split CSV text separated by `delimiter`, as for `parse_csv`
+pub fn parse_csv_with(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quoted field".to_string());
+    }
+    record.push(field);
+    if record.iter().any(|f| !f.is_empty()) {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Read the records of an import file as JSON objects
+///
+/// The format is taken from `format` (`csv` or `json`), or else from the
+/// file extension. JSON is an array of objects or one object per line. CSV
+/// is separated by commas, or by semicolons when the header has no comma;
+/// cells become strings, and empty cells are left out.
+pub fn read_rows(path: &Path, format: Option<&str>) -> Result<Vec<Row>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read import file {}: {}", path.display(), e))?;
+    let format = format
+        .map(str::to_lowercase)
+        .or_else(|| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_lowercase)
+        })
+        .unwrap_or_default();
+
+    match format.as_str() {
+        "json" | "jsonl" | "ndjson" => {
+            let rows: Vec<Value> = if text.trim_start().starts_with('[') {
+                serde_json::from_str(&text)
+                    .map_err(|e| format!("Invalid JSON import file: {}", e))?
+            } else {
+                text.lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .enumerate()
+                    .map(|(i, line)| {
+                        serde_json::from_str(line)
+                            .map_err(|e| format!("Invalid JSON on line {}: {}", i + 1, e))
+                    })
+                    .collect::<Result<_, _>>()?
+            };
+            rows.into_iter()
+                .enumerate()
+                .map(|(i, row)| match row {
+                    Value::Object(map) => Ok(map),
+                    _ => Err(format!("Entry {} of the import file is not an object", i + 1).into()),
+                })
+                .collect()
+        }
+        "csv" => {
+            let header_line = text.lines().next().unwrap_or_default();
+            let delimiter = if header_line.contains(';') && !header_line.contains(',') {
+                ';'
+            } else {
+                ','
+            };
+            let mut records = parse_csv_with(&text, delimiter)
+                .map_err(|e| format!("Invalid CSV import file: {}", e))?;
+            if records.is_empty() {
+                return Ok(Vec::new());
+            }
+            let header: Vec<String> = records
+                .remove(0)
+                .iter()
+                .map(|h| h.trim().to_lowercase())
+                .collect();
+            Ok(records
+                .into_iter()
+                .map(|record| {
+                    header
+                        .iter()
+                        .zip(record)
+                        .filter(|(_, cell)| !cell.trim().is_empty())
+                        .map(|(name, cell)| (name.clone(), Value::String(cell)))
+                        .collect()
+                })
+                .collect())
+        }
+        other => Err(format!(
+            "Cannot tell the format of {}{}; use --format csv or --format json",
+            path.display(),
+            if other.is_empty() {
+                String::new()
+            } else {
+                format!(" ('{}')", other)
+            }
+        )
+        .into()),
+    }
+}
+
+fn text(row: &Map<String, Value>, name: &str) -> Option<String> {
+    match row.get(name)? {
+        Value::Null => None,
+        Value::String(s) if s.trim().is_empty() => None,
+        Value::String(s) => Some(s.trim().to_string()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn required(row: &Map<String, Value>, name: &str) -> Result<String, String> {
+    text(row, name).ok_or_else(|| format!("missing {}", name))
+}
+
+fn fraction(row: &Map<String, Value>, name: &str) -> Result<f64, String> {
+    let value = required(row, name)?;
+    let number = value
+        .parse::<f64>()
+        .map_err(|_| format!("{} must be a number, got '{}'", name, value))?;
+    if !(0.0..=1.0).contains(&number) {
+        return Err(format!(
+            "{} must be between 0.0 and 1.0, got {}",
+            name, number
+        ));
+    }
+    Ok(number)
+}
+
+/// An RFC 3339 timestamp, or a date taken as midnight UTC
+fn timestamp(row: &Map<String, Value>, name: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let Some(value) = text(row, name) else {
+        return Ok(None);
+    };
+    if let Ok(time) = DateTime::parse_from_rfc3339(&value) {
+        return Ok(Some(time.with_timezone(&Utc)));
+    }
+    NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| Some(time.and_utc()))
+        .ok_or_else(|| {
+            format!(
+                "{} must be an RFC 3339 timestamp or YYYY-MM-DD, got '{}'",
+                name, value
+            )
+        })
+}
+
+/// A list as a JSON array, or separated by `;` in a CSV cell
+fn list(row: &Map<String, Value>, name: &str) -> Vec<String> {
+    match row.get(name) {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        _ => text(row, name)
+            .map(|labels| {
+                labels
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Platform a record came from, `file` for records written by hand
+fn imported_from(row: &Map<String, Value>) -> String {
+    text(row, "imported_from").unwrap_or_else(|| "file".to_string())
+}
+
+/// Check one record on its own
+pub fn parse_record(row: &Map<String, Value>) -> Result<ImportRecord, String> {
+    match required(row, "kind")?.to_lowercase().as_str() {
+        "proposal" => {
+            let logic = text(row, "logic").unwrap_or_default();
+            if !logic.is_empty() {
+                parse_dsl(&logic).map_err(|e| format!("logic does not compile: {}", e))?;
+            }
+            let state = match text(row, "state") {
+                Some(state) => parse_proposal_state(&state).map_err(|e| e.to_string())?,
+                None => ProposalState::Draft,
+            };
+            Ok(ImportRecord::Proposal(ImportedProposal {
+                id: required(row, "id")?,
+                title: required(row, "title")?,
+                description: text(row, "description").unwrap_or_default(),
+                quorum: fraction(row, "quorum")?,
+                threshold: fraction(row, "threshold")?,
+                logic,
+                creator: text(row, "creator"),
+                created_at: timestamp(row, "created_at")?,
+                state,
+                labels: list(row, "labels"),
+                imported_from: imported_from(row),
+            }))
+        }
+        "vote" | "ballot" => {
+            let vote = required(row, "vote")?;
+            VoteChoice::from_str(&vote).map_err(|e| e.to_string())?;
+            Ok(ImportRecord::Vote(ImportedVote {
+                proposal_id: required(row, "proposal_id")?,
+                voter: required(row, "voter")?,
+                vote: vote.to_lowercase(),
+                cast_at: timestamp(row, "cast_at").or_else(|_| timestamp(row, "created_at"))?,
+                imported_from: imported_from(row),
+            }))
+        }
+        "comment" => Ok(ImportRecord::Comment(ImportedComment {
+            id: text(row, "id"),
+            proposal_id: required(row, "proposal_id")?,
+            author: required(row, "author")?,
+            content: required(row, "content")?,
+            created_at: timestamp(row, "created_at")?,
+            reply_to: text(row, "reply_to"),
+            tags: list(row, "tags"),
+            imported_from: imported_from(row),
+        })),
+        other => Err(format!(
+            "kind must be 'proposal', 'vote' or 'comment', got '{}'",
+            other
+        )),
+    }
+}
+
+/// Check every record, on its own and against the others
+///
+/// `proposal_exists`, `vote_exists` and `comment_exists` tell whether a
+/// proposal, a voter's vote on a proposal, or a comment on a proposal is
+/// already stored. Returns the records when all are valid, or one error per
+/// invalid record.
+pub fn validate_rows(
+    rows: &[Row],
+    proposal_exists: impl Fn(&str) -> bool,
+    vote_exists: impl Fn(&str, &str) -> bool,
+    comment_exists: impl Fn(&str, &str) -> bool,
+) -> Result<Vec<ImportRecord>, Vec<RowError>> {
+    let parsed: Vec<Result<ImportRecord, String>> = rows.iter().map(parse_record).collect();
+    let imported: HashSet<&str> = parsed
+        .iter()
+        .filter_map(|record| match record {
+            Ok(ImportRecord::Proposal(p)) => Some(p.id.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let mut seen_proposals = HashSet::new();
+    let mut seen_votes = HashSet::new();
+    let mut seen_comments = HashSet::new();
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+    for (i, record) in parsed.iter().enumerate() {
+        let checked = record.clone().and_then(|record| {
+            match &record {
+                ImportRecord::Proposal(p) => {
+                    if !seen_proposals.insert(p.id.clone()) {
+                        return Err(format!("proposal '{}' appears more than once", p.id));
+                    }
+                    if proposal_exists(&p.id) {
+                        return Err(format!("proposal '{}' already exists", p.id));
+                    }
+                }
+                ImportRecord::Vote(v) => {
+                    if !imported.contains(v.proposal_id.as_str())
+                        && !proposal_exists(&v.proposal_id)
+                    {
+                        return Err(format!("proposal '{}' not found", v.proposal_id));
+                    }
+                    if !seen_votes.insert((v.proposal_id.clone(), v.voter.clone())) {
+                        return Err(format!(
+                            "'{}' votes on '{}' more than once",
+                            v.voter, v.proposal_id
+                        ));
+                    }
+                    if vote_exists(&v.proposal_id, &v.voter) {
+                        return Err(format!(
+                            "'{}' has already voted on '{}'",
+                            v.voter, v.proposal_id
+                        ));
+                    }
+                }
+                ImportRecord::Comment(c) => {
+                    if !imported.contains(c.proposal_id.as_str())
+                        && !proposal_exists(&c.proposal_id)
+                    {
+                        return Err(format!("proposal '{}' not found", c.proposal_id));
+                    }
+                    if let Some(id) = &c.id {
+                        if !seen_comments.insert(id.clone()) {
+                            return Err(format!("comment '{}' appears more than once", id));
+                        }
+                        if comment_exists(&c.proposal_id, id) {
+                            return Err(format!("comment '{}' already exists", id));
+                        }
+                    }
+                }
+            }
+            Ok(record)
+        });
+        match checked {
+            Ok(record) => records.push(record),
+            Err(message) => errors.push(RowError {
+                row: i + 1,
+                // Name the platform record a mapped record came from
+                message: match text(&rows[i], SOURCE_REF) {
+                    Some(source) => format!("{}: {}", source, message),
+                    None => message,
+                },
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(records)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Value recorded for a vote in the DAG, as in `proposal vote`
+fn dag_vote_value(vote: &str) -> f64 {
+    match vote {
+        "yes" => 1.0,
+        "no" => 0.0,
+        _ => 0.5,
+    }
+}
+
+/// Import proposals, votes, and comments from files in one transaction
+///
+/// The records of all `paths` are mapped from `source` and imported
+/// together. Prints the report. Fails without importing anything if any
+/// record is invalid.
+pub fn run_import<S>(
+    vm: &mut VM<S>,
+    paths: &[&Path],
+    format: Option<&str>,
+    source: ImportSource,
+    auth_context: &AuthContext,
+    mut dry_run: Option<&mut DryRun>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let mut source_rows = Vec::new();
+    for path in paths {
+        source_rows.extend(read_rows(path, format)?);
+    }
+    let mapped = source
+        .map_rows(source_rows)
+        .map_err(|e| format!("Invalid {} export: {}", source, e))?;
+    let rows = mapped.rows;
+    let namespace = vm.get_namespace().unwrap_or("default").to_string();
+    let comments_namespace = comments_namespace_for(&namespace);
+    let auth = Some(auth_context);
+
+    let validated = {
+        let storage = vm.get_storage_backend().ok_or("Storage not available")?;
+        validate_rows(
+            &rows,
+            |id| {
+                storage
+                    .contains(auth, &namespace, &VM::<S>::proposal_key_prefix(id))
+                    .unwrap_or(false)
+            },
+            |id, voter| {
+                let key = format!("{}/{}", VM::<S>::proposal_votes_prefix(id), voter);
+                storage.contains(auth, &namespace, &key).unwrap_or(false)
+            },
+            |id, comment| {
+                let key = comment_key(id, comment);
+                storage
+                    .contains(auth, &comments_namespace, &key)
+                    .unwrap_or(false)
+            },
+        )
+    };
+    let records = match validated {
+        Ok(records) => records,
+        Err(errors) => {
+            let report = ImportReport {
+                rows: rows.len(),
+                errors,
+                skipped: mapped.skipped,
+                ..Default::default()
+            };
+            print_report(&report)?;
+            return Err(format!(
+                "{} of {} rows are invalid; nothing was imported",
+                report.errors.len(),
+                report.rows
+            )
+            .into());
+        }
+    };
+
+    let forked = vm.fork()?;
+    let mut storage = forked
+        .get_storage_backend()
+        .ok_or("Storage not available")?
+        .clone();
+    let mut report = ImportReport {
+        rows: rows.len(),
+        skipped: mapped.skipped,
+        ..Default::default()
+    };
+    let mut dag_nodes = Vec::new();
+
+    dry_run::write(
+        dry_run.as_deref_mut(),
+        &mut storage,
+        |mut dry_run, storage| {
+            for record in &records {
+                match record {
+                    ImportRecord::Proposal(p) => {
+                        let created_at = p.created_at.unwrap_or_else(Utc::now);
+                        let creator = p
+                            .creator
+                            .clone()
+                            .unwrap_or_else(|| auth_context.identity_did().to_string());
+
+                        let mut proposal = Proposal::new(
+                            p.id.clone(),
+                            creator.clone(),
+                            None,
+                            None,
+                            None,
+                            Vec::new(),
+                        );
+                        proposal.created_at = created_at;
+                        proposal.labels = p.labels.clone();
+                        proposal.imported_from = Some(p.imported_from.clone());
+
+                        let mut lifecycle = ProposalLifecycle::new(
+                            p.id.clone(),
+                            Identity::new(creator, None, "member".to_string(), None).map_err(
+                                |e| format!("Failed to create identity for {}: {}", p.id, e),
+                            )?,
+                            p.title.clone(),
+                            safe_f64_to_u64(p.quorum * 100.0, "quorum percentage conversion")?,
+                            safe_f64_to_u64(
+                                p.threshold * 100.0,
+                                "threshold percentage conversion",
+                            )?,
+                            None,
+                            None,
+                        );
+                        lifecycle.created_at = created_at;
+                        lifecycle.state = p.state.clone();
+                        lifecycle.history = vec![(created_at, ProposalState::Draft)];
+                        if p.state != ProposalState::Draft {
+                            lifecycle.history.push((created_at, p.state.clone()));
+                        }
+
+                        dry_run::set_json(
+                            dry_run.as_deref_mut(),
+                            storage,
+                            auth,
+                            &namespace,
+                            &VM::<S>::proposal_key_prefix(&p.id),
+                            &proposal,
+                        )?;
+                        dry_run::set_json(
+                            dry_run.as_deref_mut(),
+                            storage,
+                            auth,
+                            &namespace,
+                            &VM::<S>::proposal_lifecycle_key(&p.id),
+                            &lifecycle,
+                        )?;
+                        dry_run::set(
+                            dry_run.as_deref_mut(),
+                            storage,
+                            auth,
+                            &namespace,
+                            &VM::<S>::proposal_description_key(&p.id),
+                            p.description.as_bytes().to_vec(),
+                        )?;
+                        dry_run::set(
+                            dry_run.as_deref_mut(),
+                            storage,
+                            auth,
+                            &namespace,
+                            &VM::<S>::proposal_logic_key(&p.id),
+                            p.logic.as_bytes().to_vec(),
+                        )?;
+                        // Comments are only accepted on proposals recorded next to them
+                        dry_run::set_json(
+                            dry_run.as_deref_mut(),
+                            storage,
+                            auth,
+                            &comments_namespace,
+                            &proposal.storage_key(),
+                            &proposal,
+                        )?;
+
+                        report.proposals += 1;
+                        dag_nodes.push((
+                            created_at,
+                            icn_ledger::NodeData::ProposalCreated {
+                                proposal_id: p.id.clone(),
+                                title: p.title.clone(),
+                            },
+                        ));
+                    }
+                    ImportRecord::Vote(v) => {
+                        let cast_at = v.cast_at.unwrap_or_else(Utc::now);
+                        let vote_data = serde_json::json!({
+                            "voter": v.voter,
+                            "vote": v.vote,
+                            "timestamp": cast_at.to_rfc3339(),
+                            "delegated_by": Value::Null,
+                            "imported_from": v.imported_from,
+                        });
+                        let vote_key = format!(
+                            "{}/{}",
+                            VM::<S>::proposal_votes_prefix(&v.proposal_id),
+                            v.voter
+                        );
+                        dry_run::set_json(
+                            dry_run.as_deref_mut(),
+                            storage,
+                            auth,
+                            &namespace,
+                            &vote_key,
+                            &vote_data,
+                        )?;
+
+                        report.votes += 1;
+                        dag_nodes.push((
+                            cast_at,
+                            icn_ledger::NodeData::VoteCast {
+                                proposal_id: v.proposal_id.clone(),
+                                voter: v.voter.clone(),
+                                vote: dag_vote_value(&v.vote),
+                            },
+                        ));
+                    }
+                    ImportRecord::Comment(c) => {
+                        let created_at = c.created_at.unwrap_or_else(Utc::now);
+                        let mut comment = ProposalComment::new(
+                            c.author.clone(),
+                            c.content.clone(),
+                            c.reply_to.clone(),
+                            c.tags.clone(),
+                        );
+                        if let Some(id) = &c.id {
+                            comment.id = id.clone();
+                        }
+                        comment.timestamp = created_at;
+                        comment.edit_history = vec![CommentVersion {
+                            content: c.content.clone(),
+                            timestamp: created_at,
+                        }];
+                        comment.imported_from = Some(c.imported_from.clone());
+                        dry_run::set_json(
+                            dry_run.as_deref_mut(),
+                            storage,
+                            auth,
+                            &comments_namespace,
+                            &comment_key(&c.proposal_id, &comment.id),
+                            &comment,
+                        )?;
+                        report.comments += 1;
+                    }
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    dry_run::commit(dry_run.as_deref(), vm)?;
+
+    // Record the history in the DAG in the order it happened; a stable sort
+    // keeps proposals ahead of votes cast at the same time
+    dag_nodes.sort_by_key(|(time, _)| *time);
+    if let Some(ledger) = &mut vm.dag {
+        for (time, data) in dag_nodes {
+            let node = icn_ledger::DagNode {
+                id: String::new(),
+                parent_ids: vec![],
+                timestamp: time.timestamp().max(0) as u64,
+                namespace: namespace.clone(),
+                data,
+            };
+            dry_run::append_on_tips(dry_run.as_deref_mut(), ledger, node)?;
+        }
+    }
+
+    if dry_run.is_some() {
+        return Ok(());
+    }
+    print_report(&report)
+}
+
+/// Key of a comment, as written by `governance::comments`
+fn comment_key(proposal_id: &str, comment_id: &str) -> String {
+    format!(
+        "governance/proposals/{}/comments/{}",
+        proposal_id, comment_id
+    )
+}
+
+fn print_report(report: &ImportReport) -> Result<(), Box<dyn Error>> {
+    print_output(report, |report| {
+        for skipped in &report.skipped {
+            println!("⚠️ Skipped {}", skipped);
+        }
+        if report.errors.is_empty() {
+            println!(
+                "📥 Imported {} proposal(s), {} vote(s) and {} comment(s) from {} row(s)",
+                report.proposals, report.votes, report.comments, report.rows
+            );
+            return;
+        }
+        println!(
+            "❌ {} of {} row(s) are invalid; nothing was imported",
+            report.errors.len(),
+            report.rows
+        );
+        for error in &report.errors {
+            println!("   row {}: {}", error.row, error.message);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_quoting() {
+        let records =
+            parse_csv("a,b,c\r\n\"x, y\",\"say \"\"hi\"\"\",\"two\nlines\"\n\n1,,3").unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1], vec!["x, y", "say \"hi\"", "two\nlines"]);
+        assert_eq!(records[2], vec!["1", "", "3"]);
+        assert!(parse_csv("\"open").is_err());
+    }
+
+    #[test]
+    fn test_validate_rows_reports_each_bad_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.csv");
+        fs::write(
+            &path,
+            "kind,id,title,quorum,threshold,state,created_at,proposal_id,voter,vote\n\
+             proposal,p1,First,0.5,0.6,executed,2023-01-10,,,\n\
+             vote,,,,,,2023-01-12,p1,alice,yes\n\
+             vote,,,,,,,p1,alice,no\n\
+             proposal,p2,Second,1.5,0.6,,,,,\n\
+             vote,,,,,,,missing,bob,yes\n\
+             vote,,,,,,,old,carol,maybe\n",
+        )
+        .unwrap();
+        let rows = read_rows(&path, None).unwrap();
+
+        let errors =
+            validate_rows(&rows, |id| id == "old", |_, _| false, |_, _| false).unwrap_err();
+        let bad_rows: Vec<usize> = errors.iter().map(|e| e.row).collect();
+        assert_eq!(bad_rows, vec![3, 4, 5, 6]);
+        assert!(errors[1].message.contains("quorum"));
+
+        let records = validate_rows(&rows[..2], |_| false, |_, _| false, |_, _| false).unwrap();
+        match &records[0] {
+            ImportRecord::Proposal(p) => {
+                assert_eq!(p.state, ProposalState::Executed);
+                assert_eq!(
+                    p.created_at.unwrap().to_rfc3339(),
+                    "2023-01-10T00:00:00+00:00"
+                );
+            }
+            other => panic!("expected a proposal, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! Watch mode for proposal activity
+//!
+//! `proposal watch` prints new votes, comments, and state changes as they
+//! happen, for one proposal (`--id`) or every proposal in the namespace
+//! (`--all`), until interrupted.
+//!
+//! Storage is polled: each poll snapshots the watched proposals and reports
+//! what changed since the previous one. When the VM has a ledger file, nodes
+//! that other commands append to it are followed as well, as the dashboard
+//! does. An event seen in both storage and the ledger is printed once.
+
+use crate::cli::output::{self, OutputFormat};
+use crate::cli::proposal::VMProposalExtensions;
+use crate::governance::comments::fetch_comments_threaded;
+use crate::governance::proposal_lifecycle::ProposalState;
+use crate::governance::replay::vote_choice;
+use crate::storage::traits::Storage;
+use crate::vm::VM;
+
+use chrono::{DateTime, Utc};
+use icn_ledger::{DagNode, NodeData};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Something that happened to a watched proposal
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WatchEvent {
+    ProposalCreated {
+        proposal_id: String,
+        title: String,
+    },
+    StateChanged {
+        proposal_id: String,
+        from: Option<ProposalState>,
+        to: ProposalState,
+    },
+    VoteCast {
+        proposal_id: String,
+        voter: String,
+        choice: String,
+    },
+    CommentAdded {
+        proposal_id: String,
+        comment_id: String,
+        author: String,
+        content: String,
+    },
+    ProposalExecuted {
+        proposal_id: String,
+        success: bool,
+    },
+}
+
+impl WatchEvent {
+    /// What the event reports and its new value, so the same change seen
+    /// through storage and the ledger is only printed once
+    fn report_key(&self) -> (String, String) {
+        match self {
+            WatchEvent::ProposalCreated { proposal_id, .. } => {
+                (format!("created/{}", proposal_id), String::new())
+            }
+            WatchEvent::StateChanged {
+                proposal_id, to, ..
+            } => (format!("state/{}", proposal_id), format!("{:?}", to)),
+            WatchEvent::ProposalExecuted { proposal_id, .. } => (
+                format!("state/{}", proposal_id),
+                format!("{:?}", ProposalState::Executed),
+            ),
+            WatchEvent::VoteCast {
+                proposal_id,
+                voter,
+                choice,
+            } => (format!("vote/{}/{}", proposal_id, voter), choice.clone()),
+            WatchEvent::CommentAdded {
+                proposal_id,
+                comment_id,
+                ..
+            } => (
+                format!("comment/{}/{}", proposal_id, comment_id),
+                String::new(),
+            ),
+        }
+    }
+}
+
+/// A watched proposal as of the last poll
+#[derive(Debug, Clone)]
+struct ProposalSnapshot {
+    title: String,
+    state: ProposalState,
+    /// Choice of each voter
+    votes: BTreeMap<String, String>,
+    /// Comments by ID, with their author and content
+    comments: BTreeMap<String, (DateTime<Utc>, String, String)>,
+}
+
+/// Tracks watched proposals and turns changes into events
+#[derive(Debug)]
+pub struct Watcher {
+    /// Proposal to watch, or `None` for all of them
+    proposal_id: Option<String>,
+    snapshots: HashMap<String, ProposalSnapshot>,
+    /// Last value reported for each report key
+    reported: HashMap<String, String>,
+}
+
+impl Watcher {
+    /// Start watching, taking the current state as already seen
+    pub fn new<S>(vm: &VM<S>, proposal_id: Option<&str>) -> Result<Self, Box<dyn Error>>
+    where
+        S: Storage + Send + Sync + Clone + Debug + 'static,
+    {
+        let mut watcher = Self {
+            proposal_id: proposal_id.map(str::to_string),
+            snapshots: HashMap::new(),
+            reported: HashMap::new(),
+        };
+        watcher.poll(vm)?;
+        Ok(watcher)
+    }
+
+    /// Snapshot the watched proposals and return what changed since the last poll
+    pub fn poll<S>(&mut self, vm: &VM<S>) -> Result<Vec<WatchEvent>, Box<dyn Error>>
+    where
+        S: Storage + Send + Sync + Clone + Debug + 'static,
+    {
+        let mut events = Vec::new();
+        for id in self.watched_ids(vm)? {
+            let Some(current) = snapshot(vm, &id)? else {
+                continue;
+            };
+            let previous = self.snapshots.insert(id.clone(), current.clone());
+            events.extend(diff(&id, previous.as_ref(), &current));
+        }
+        Ok(events
+            .into_iter()
+            .filter(|event| self.report(event))
+            .collect())
+    }
+
+    /// The event a ledger node records for a watched proposal, unless it was
+    /// already reported from storage
+    pub fn ledger_event(&mut self, node: &DagNode) -> Option<WatchEvent> {
+        let event = match &node.data {
+            NodeData::ProposalCreated { proposal_id, title } => WatchEvent::ProposalCreated {
+                proposal_id: proposal_id.clone(),
+                title: title.clone(),
+            },
+            NodeData::VoteCast {
+                proposal_id,
+                voter,
+                vote,
+            } => WatchEvent::VoteCast {
+                proposal_id: proposal_id.clone(),
+                voter: voter.clone(),
+                choice: vote_choice(*vote)?.to_string(),
+            },
+            NodeData::ProposalExecuted {
+                proposal_id,
+                success,
+            } => WatchEvent::ProposalExecuted {
+                proposal_id: proposal_id.clone(),
+                success: *success,
+            },
+            _ => return None,
+        };
+        let watched = match (&self.proposal_id, &event) {
+            (None, _) => true,
+            (Some(id), WatchEvent::ProposalCreated { proposal_id, .. })
+            | (Some(id), WatchEvent::VoteCast { proposal_id, .. })
+            | (Some(id), WatchEvent::ProposalExecuted { proposal_id, .. }) => id == proposal_id,
+            _ => false,
+        };
+        (watched && self.report(&event)).then_some(event)
+    }
+
+    /// Record an event as reported, returning whether it is news
+    fn report(&mut self, event: &WatchEvent) -> bool {
+        let (key, value) = event.report_key();
+        self.reported.insert(key, value.clone()).as_ref() != Some(&value)
+    }
+
+    fn watched_ids<S>(&self, vm: &VM<S>) -> Result<Vec<String>, Box<dyn Error>>
+    where
+        S: Storage + Send + Sync + Clone + Debug + 'static,
+    {
+        if let Some(id) = &self.proposal_id {
+            return Ok(vec![id.clone()]);
+        }
+        let storage = vm.get_storage_backend().ok_or("Storage not available")?;
+        let namespace = vm.get_namespace().unwrap_or("default");
+        let prefix = VM::<S>::proposal_key_prefix("");
+        Ok(storage
+            .list_keys(vm.get_auth_context(), namespace, Some(&prefix))?
+            .iter()
+            .filter_map(|key| {
+                key.strip_prefix(&prefix)
+                    .and_then(|rest| rest.strip_suffix("/lifecycle"))
+                    .map(str::to_string)
+            })
+            .collect())
+    }
+}
+
+/// Load a proposal's state, votes, and comments, or `None` if it does not exist yet
+fn snapshot<S>(vm: &VM<S>, proposal_id: &str) -> Result<Option<ProposalSnapshot>, Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let Ok(lifecycle) = vm.get_proposal_lifecycle(proposal_id) else {
+        return Ok(None);
+    };
+    let votes = vm.get_proposal_votes(proposal_id)?.into_iter().collect();
+    // Proposals without a discussion thread have no comments to fetch
+    let comments = fetch_comments_threaded(vm, proposal_id, vm.get_auth_context(), false)
+        .unwrap_or_default()
+        .into_values()
+        .map(|comment| {
+            (
+                comment.id,
+                (comment.timestamp, comment.author, comment.content),
+            )
+        })
+        .collect();
+    Ok(Some(ProposalSnapshot {
+        title: lifecycle.title,
+        state: lifecycle.state,
+        votes,
+        comments,
+    }))
+}
+
+/// Events turning `previous` into `current`, oldest comments first
+fn diff(
+    proposal_id: &str,
+    previous: Option<&ProposalSnapshot>,
+    current: &ProposalSnapshot,
+) -> Vec<WatchEvent> {
+    let mut events = Vec::new();
+    if previous.is_none() {
+        events.push(WatchEvent::ProposalCreated {
+            proposal_id: proposal_id.to_string(),
+            title: current.title.clone(),
+        });
+    }
+    let previous_state = previous.map(|p| p.state.clone());
+    if previous_state.as_ref() != Some(&current.state) {
+        events.push(WatchEvent::StateChanged {
+            proposal_id: proposal_id.to_string(),
+            from: previous_state,
+            to: current.state.clone(),
+        });
+    }
+    for (voter, choice) in &current.votes {
+        if previous.and_then(|p| p.votes.get(voter)) != Some(choice) {
+            events.push(WatchEvent::VoteCast {
+                proposal_id: proposal_id.to_string(),
+                voter: voter.clone(),
+                choice: choice.clone(),
+            });
+        }
+    }
+    let mut comments: Vec<_> = current
+        .comments
+        .iter()
+        .filter(|(id, _)| previous.is_none_or(|p| !p.comments.contains_key(*id)))
+        .collect();
+    comments.sort_by_key(|(_, (timestamp, _, _))| *timestamp);
+    for (comment_id, (_, author, content)) in comments {
+        events.push(WatchEvent::CommentAdded {
+            proposal_id: proposal_id.to_string(),
+            comment_id: comment_id.clone(),
+            author: author.clone(),
+            content: content.clone(),
+        });
+    }
+    events
+}
+
+/// Print an event as a line of text, or as one JSON or YAML document
+fn print_event(event: &WatchEvent, at: DateTime<Utc>) -> Result<(), Box<dyn Error>> {
+    #[derive(Serialize)]
+    struct TimedEvent<'a> {
+        timestamp: DateTime<Utc>,
+        #[serde(flatten)]
+        event: &'a WatchEvent,
+    }
+
+    let timed = TimedEvent {
+        timestamp: at,
+        event,
+    };
+    match output::output_format() {
+        OutputFormat::Json => println!("{}", serde_json::to_string(&timed)?),
+        OutputFormat::Yaml => print!("---\n{}", serde_yaml::to_string(&timed)?),
+        OutputFormat::Table => println!("[{}] {}", at.format("%H:%M:%S"), describe(event)),
+    }
+    Ok(())
+}
+
+fn describe(event: &WatchEvent) -> String {
+    match event {
+        WatchEvent::ProposalCreated { proposal_id, title } => {
+            format!("📝 {} created: {}", proposal_id, title)
+        }
+        WatchEvent::StateChanged {
+            proposal_id,
+            from: Some(from),
+            to,
+        } => format!("🔄 {}: {:?} → {:?}", proposal_id, from, to),
+        WatchEvent::StateChanged {
+            proposal_id,
+            from: None,
+            to,
+        } => format!("🔄 {}: {:?}", proposal_id, to),
+        WatchEvent::VoteCast {
+            proposal_id,
+            voter,
+            choice,
+        } => format!("🗳️ {}: {} voted {}", proposal_id, voter, choice),
+        WatchEvent::CommentAdded {
+            proposal_id,
+            author,
+            content,
+            ..
+        } => format!("💬 {}: {}: {}", proposal_id, author, content),
+        WatchEvent::ProposalExecuted {
+            proposal_id,
+            success: true,
+        } => format!("✅ {} executed", proposal_id),
+        WatchEvent::ProposalExecuted {
+            proposal_id,
+            success: false,
+        } => format!("❌ {} execution failed", proposal_id),
+    }
+}
+
+/// Print proposal activity as it happens, until the process is interrupted
+///
+/// Storage is polled every `interval`; the VM's ledger file, if it has one,
+/// is re-read at the same time.
+pub fn run_watch<S>(
+    vm: &mut VM<S>,
+    proposal_id: Option<&str>,
+    interval: Duration,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    // Catch up with the ledger file first so only later nodes are reported
+    let dag_path = vm
+        .get_dag()
+        .and_then(|dag| dag.path())
+        .map(|p| p.to_path_buf());
+    if let (Some(dag), Some(path)) = (vm.dag.as_mut(), &dag_path) {
+        dag.import_from_file(path)?;
+    }
+    let updates = vm.subscribe_dag();
+    let mut watcher = Watcher::new(vm, proposal_id)?;
+
+    let target = proposal_id
+        .map(|id| format!("proposal {}", id))
+        .unwrap_or_else(|| "all proposals".to_string());
+    if output::is_table() {
+        println!("👀 Watching {} (Ctrl-C to stop)", target);
+    } else {
+        eprintln!("👀 Watching {} (Ctrl-C to stop)", target);
+    }
+
+    loop {
+        std::thread::sleep(interval);
+        let mut events = watcher.poll(vm)?;
+        if let (Some(dag), Some(path), Some(updates)) = (vm.dag.as_mut(), &dag_path, &updates) {
+            dag.import_from_file(path)?;
+            for node in updates.try_iter() {
+                events.extend(watcher.ledger_event(&dag.readable(&node)));
+            }
+        }
+        let now = Utc::now();
+        for event in &events {
+            print_event(event, now)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot_with(state: ProposalState, votes: &[(&str, &str)]) -> ProposalSnapshot {
+        ProposalSnapshot {
+            title: "Budget".to_string(),
+            state,
+            votes: votes
+                .iter()
+                .map(|(voter, choice)| (voter.to_string(), choice.to_string()))
+                .collect(),
+            comments: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_diff_reports_state_changes_and_new_votes() {
+        let before = snapshot_with(ProposalState::OpenForFeedback, &[("alice", "yes")]);
+        let after = snapshot_with(ProposalState::Voting, &[("alice", "yes"), ("bob", "no")]);
+
+        assert_eq!(
+            diff("p1", Some(&before), &after),
+            vec![
+                WatchEvent::StateChanged {
+                    proposal_id: "p1".to_string(),
+                    from: Some(ProposalState::OpenForFeedback),
+                    to: ProposalState::Voting,
+                },
+                WatchEvent::VoteCast {
+                    proposal_id: "p1".to_string(),
+                    voter: "bob".to_string(),
+                    choice: "no".to_string(),
+                },
+            ]
+        );
+        assert!(diff("p1", Some(&after), &after).is_empty());
+    }
+
+    #[test]
+    fn test_ledger_events_already_seen_in_storage_are_skipped() {
+        let mut watcher = Watcher {
+            proposal_id: Some("p1".to_string()),
+            snapshots: HashMap::new(),
+            reported: HashMap::new(),
+        };
+        let vote = |proposal_id: &str, vote: f64| {
+            DagNode::with_default_namespace(
+                Vec::new(),
+                NodeData::VoteCast {
+                    proposal_id: proposal_id.to_string(),
+                    voter: "alice".to_string(),
+                    vote,
+                },
+                0,
+            )
+        };
+
+        assert!(watcher.report(&WatchEvent::VoteCast {
+            proposal_id: "p1".to_string(),
+            voter: "alice".to_string(),
+            choice: "yes".to_string(),
+        }));
+        assert_eq!(watcher.ledger_event(&vote("p1", 1.0)), None);
+        assert_eq!(watcher.ledger_event(&vote("p2", 0.0)), None);
+        assert_eq!(
+            watcher.ledger_event(&vote("p1", 0.0)),
+            Some(WatchEvent::VoteCast {
+                proposal_id: "p1".to_string(),
+                voter: "alice".to_string(),
+                choice: "no".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,463 @@
+//! Interactive proposal creation
+//!
+//! `proposal create --interactive` asks for each field of a new proposal in
+//! turn, re-asking until the answer is valid. A template supplies default
+//! voting rules and, optionally, logic filled in from its parameters; a
+//! logic file can be given instead. The logic is compiled and its ops shown
+//! before anything is saved.
+
+use crate::cli::proposal::parse_duration_string;
+use crate::compiler::parse_dsl::parse_dsl;
+use crate::governance::role_classes::RoleClassRequirement;
+use crate::vm::Op;
+use chrono::Duration;
+use rust_decimal::Decimal;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// Kinds of template parameter, which decide how answers are validated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    /// Free text, without quotes or line breaks
+    Text,
+    /// A single word such as an account or resource name
+    Word,
+    /// A positive number
+    Amount,
+}
+
+/// A value asked for when a template is used
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateParam {
+    /// Name used in `{{name}}` placeholders in the template logic
+    pub name: &'static str,
+    pub prompt: &'static str,
+    pub kind: ParamKind,
+    pub default: Option<&'static str>,
+}
+
+/// Built-in proposal template
+#[derive(Debug, Clone, Copy)]
+pub struct ProposalTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub quorum: f64,
+    pub threshold: f64,
+    /// Minimum deliberation, as accepted by `--discussion-duration`
+    pub min_deliberation: &'static str,
+    /// Time until expiry, as accepted by `--expires-in`
+    pub expires_in: &'static str,
+    pub parameters: &'static [TemplateParam],
+    /// DSL logic with `{{name}}` placeholders for the parameters
+    pub logic: &'static str,
+}
+
+/// Templates offered by the wizard, matching the rules of the templates in
+/// `examples/governance_templates.dsl`
+pub const TEMPLATES: &[ProposalTemplate] = &[
+    ProposalTemplate {
+        name: "standard",
+        description: "Regular decision",
+        quorum: 0.5,
+        threshold: 0.6,
+        min_deliberation: "72h",
+        expires_in: "14d",
+        parameters: &[TemplateParam {
+            name: "decision",
+            prompt: "Decision to record when approved",
+            kind: ParamKind::Text,
+            default: None,
+        }],
+        logic: "emit \"Approved: {{decision}}\"\n",
+    },
+    ProposalTemplate {
+        name: "budget",
+        description: "Transfer of funds from a treasury",
+        quorum: 0.6,
+        threshold: 0.7,
+        min_deliberation: "96h",
+        expires_in: "10d",
+        parameters: &[
+            TemplateParam {
+                name: "resource",
+                prompt: "Resource to transfer",
+                kind: ParamKind::Word,
+                default: Some("funds"),
+            },
+            TemplateParam {
+                name: "from",
+                prompt: "Account paying",
+                kind: ParamKind::Word,
+                default: Some("treasury"),
+            },
+            TemplateParam {
+                name: "to",
+                prompt: "Account receiving",
+                kind: ParamKind::Word,
+                default: None,
+            },
+            TemplateParam {
+                name: "amount",
+                prompt: "Amount",
+                kind: ParamKind::Amount,
+                default: None,
+            },
+        ],
+        logic: "transfer {{resource}} {{from}} {{to}} {{amount}} \"Approved budget\"\n",
+    },
+    ProposalTemplate {
+        name: "emergency",
+        description: "Urgent action with a short vote",
+        quorum: 0.3,
+        threshold: 0.8,
+        min_deliberation: "1h",
+        expires_in: "24h",
+        parameters: &[TemplateParam {
+            name: "action",
+            prompt: "Action to take when approved",
+            kind: ParamKind::Text,
+            default: None,
+        }],
+        logic: "emit \"Emergency action approved: {{action}}\"\n",
+    },
+];
+
+/// Answers collected by the wizard
+#[derive(Debug, Clone)]
+pub struct ProposalDraft {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// Name of the template used, if any
+    pub template: Option<String>,
+    pub quorum: f64,
+    pub threshold: f64,
+    /// Quorum and threshold per role class, from the template
+    pub role_classes: Vec<RoleClassRequirement>,
+    pub min_deliberation: Duration,
+    pub expires_in: Duration,
+    /// Seconds to wait after the proposal passes before the scheduler runs
+    /// its logic, from the template
+    pub execution_delay: Option<u64>,
+    /// Logic file, or `None` when the template logic is used
+    pub logic_path: Option<String>,
+    /// DSL source stored with the proposal
+    pub logic: String,
+    /// Compiled logic, shown in the preview
+    pub ops: Vec<Op>,
+}
+
+/// Line-based prompts over any reader and writer
+pub struct Prompter<R, W> {
+    input: R,
+    output: W,
+}
+
+impl Prompter<io::StdinLock<'static>, io::Stdout> {
+    /// Prompt on the terminal
+    pub fn stdio() -> Self {
+        Self::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    /// Print a line between questions
+    pub fn say(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        writeln!(self.output, "{}", text)?;
+        Ok(())
+    }
+
+    /// Ask until `parse` accepts the answer; an empty answer takes `default`
+    pub fn ask<T>(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<T, Box<dyn Error>> {
+        loop {
+            match default {
+                Some(default) => write!(self.output, "{} [{}]: ", question, default)?,
+                None => write!(self.output, "{}: ", question)?,
+            }
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Err("Input ended before the wizard finished".into());
+            }
+            let answer = match (line.trim(), default) {
+                ("", Some(default)) => default,
+                (answer, _) => answer,
+            };
+            match parse(answer) {
+                Ok(value) => return Ok(value),
+                Err(e) => writeln!(self.output, "   ❌ {}", e)?,
+            }
+        }
+    }
+
+    /// Ask a yes/no question
+    pub fn confirm(&mut self, question: &str, default: bool) -> Result<bool, Box<dyn Error>> {
+        let default = if default { "y" } else { "n" };
+        self.ask(question, Some(default), |answer| {
+            match answer.to_lowercase().as_str() {
+                "y" | "yes" => Ok(true),
+                "n" | "no" => Ok(false),
+                _ => Err("Answer y or n".to_string()),
+            }
+        })
+    }
+}
+
+fn non_empty(answer: &str) -> Result<String, String> {
+    if answer.is_empty() {
+        Err("A value is required".to_string())
+    } else {
+        Ok(answer.to_string())
+    }
+}
+
+fn fraction(answer: &str) -> Result<f64, String> {
+    match answer.parse::<f64>() {
+        Ok(value) if value > 0.0 && value <= 1.0 => Ok(value),
+        _ => Err("Enter a number greater than 0 and at most 1, e.g. 0.6".to_string()),
+    }
+}
+
+fn duration(answer: &str) -> Result<Duration, String> {
+    parse_duration_string(answer).map_err(|e| e.to_string())
+}
+
+fn param_value(kind: ParamKind, answer: &str) -> Result<String, String> {
+    let answer = non_empty(answer)?;
+    match kind {
+        ParamKind::Text if answer.contains('"') => Err("Quotes are not allowed".to_string()),
+        ParamKind::Word if answer.contains(char::is_whitespace) || answer.contains('"') => {
+            Err("Enter a single word".to_string())
+        }
+        ParamKind::Amount => match answer.parse::<Decimal>() {
+            Ok(amount) if amount > Decimal::ZERO => Ok(answer),
+            _ => Err("Enter a positive number".to_string()),
+        },
+        _ => Ok(answer),
+    }
+}
+
+/// Fill the `{{name}}` placeholders of a template's logic
+pub fn render_logic(template: &ProposalTemplate, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.logic.to_string(), |logic, (name, value)| {
+            logic.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+fn compile(logic: &str) -> Result<Vec<Op>, String> {
+    parse_dsl(logic)
+        .map(|(ops, _)| ops)
+        .map_err(|e| format!("Logic does not compile: {}", e))
+}
+
+/// Walk through the fields of a new proposal
+///
+/// `id` skips the ID question when it was given on the command line.
+/// Returns `None` if the user declines to save after the preview.
+pub fn run_wizard<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    id: Option<&str>,
+) -> Result<Option<ProposalDraft>, Box<dyn Error>> {
+    prompter.say("📝 New proposal")?;
+    let id = match id {
+        Some(id) => id.to_string(),
+        None => prompter.ask("Proposal ID", None, |answer| {
+            if answer.is_empty() || answer.contains(|c: char| c.is_whitespace() || c == '/') {
+                Err("Enter an ID without spaces or slashes".to_string())
+            } else {
+                Ok(answer.to_string())
+            }
+        })?,
+    };
+    let title = prompter.ask("Title", None, non_empty)?;
+    let description = prompter.ask("Description", None, non_empty)?;
+
+    prompter.say("Templates:")?;
+    for (i, template) in TEMPLATES.iter().enumerate() {
+        prompter.say(&format!(
+            "   {}. {} - {}",
+            i + 1,
+            template.name,
+            template.description
+        ))?;
+    }
+    prompter.say("   0. none - Custom rules and logic file")?;
+    let template = prompter.ask("Template", Some("0"), |answer| {
+        if answer == "0" || answer == "none" {
+            return Ok(None);
+        }
+        answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| TEMPLATES.get(i))
+            .or_else(|| TEMPLATES.iter().find(|t| t.name == answer))
+            .map(Some)
+            .ok_or_else(|| format!("Unknown template: {}", answer))
+    })?;
+
+    let mut values = Vec::new();
+    for param in template.map(|t| t.parameters).unwrap_or_default() {
+        let value = prompter.ask(param.prompt, param.default, |answer| {
+            param_value(param.kind, answer)
+        })?;
+        values.push((param.name, value));
+    }
+
+    let quorum_default = template.map(|t| t.quorum.to_string());
+    let quorum = prompter.ask(
+        "Quorum (fraction of members who must vote)",
+        quorum_default.as_deref(),
+        fraction,
+    )?;
+    let threshold_default = template.map(|t| t.threshold.to_string());
+    let threshold = prompter.ask(
+        "Threshold (fraction of votes in favor to pass)",
+        threshold_default.as_deref(),
+        fraction,
+    )?;
+    let min_deliberation = prompter.ask(
+        "Deliberation window (e.g. 72h)",
+        Some(template.map_or("24h", |t| t.min_deliberation)),
+        duration,
+    )?;
+    let expires_in = prompter.ask(
+        "Expires in (e.g. 14d)",
+        Some(template.map_or("30d", |t| t.expires_in)),
+        duration,
+    )?;
+
+    let template_logic = template.map(|t| render_logic(t, &values));
+    let question = if template_logic.is_some() {
+        "Logic file (empty to use the template logic)"
+    } else {
+        "Logic file"
+    };
+    let (logic_path, logic, ops) = prompter.ask(question, None, |answer| {
+        if answer.is_empty() {
+            let logic = template_logic
+                .clone()
+                .ok_or_else(|| "A logic file is required without a template".to_string())?;
+            let ops = compile(&logic)?;
+            return Ok((None, logic, ops));
+        }
+        if !Path::new(answer).is_file() {
+            return Err(format!("File not found: {}", answer));
+        }
+        let logic = fs::read_to_string(answer).map_err(|e| e.to_string())?;
+        let ops = compile(&logic)?;
+        Ok((Some(answer.to_string()), logic, ops))
+    })?;
+
+    prompter.say("")?;
+    prompter.say(&format!("📋 Proposal '{}': {}", id, title))?;
+    prompter.say(&format!(
+        "   Template: {}",
+        template.map_or("none", |t| t.name)
+    ))?;
+    prompter.say(&format!(
+        "   Quorum: {}%  Threshold: {}%",
+        quorum * 100.0,
+        threshold * 100.0
+    ))?;
+    prompter.say(&format!(
+        "   Deliberation: {}h  Expires in: {}h",
+        min_deliberation.num_hours(),
+        expires_in.num_hours()
+    ))?;
+    prompter.say(&format!("⚙️ Compiled logic ({} ops):", ops.len()))?;
+    for (i, op) in ops.iter().enumerate() {
+        prompter.say(&format!("   {:>3}: {:?}", i, op))?;
+    }
+
+    if !prompter.confirm("Save this proposal?", true)? {
+        return Ok(None);
+    }
+
+    Ok(Some(ProposalDraft {
+        id,
+        title,
+        description,
+        template: template.map(|t| t.name.to_string()),
+        quorum,
+        threshold,
+        role_classes: Vec::new(),
+        min_deliberation,
+        expires_in,
+        execution_delay: None,
+        logic_path,
+        logic,
+        ops,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wizard_uses_template_defaults_and_logic() {
+        // Invalid answers are re-asked: an empty title, an unknown template,
+        // a non-numeric amount and an out-of-range quorum
+        let answers =
+            "\nFund repairs\nRoof work\n9\nbudget\n\n\nbob\nlots\n250\n1.5\n\n\n\n\n\ny\n";
+        let mut output = Vec::new();
+        let mut prompter = Prompter::new(answers.as_bytes(), &mut output);
+
+        let draft = run_wizard(&mut prompter, Some("repairs")).unwrap().unwrap();
+        assert_eq!(draft.id, "repairs");
+        assert_eq!(draft.title, "Fund repairs");
+        assert_eq!(draft.template.as_deref(), Some("budget"));
+        assert_eq!(draft.quorum, 0.6);
+        assert_eq!(draft.threshold, 0.7);
+        assert_eq!(draft.min_deliberation, Duration::hours(96));
+        assert_eq!(draft.expires_in, Duration::days(10));
+        assert_eq!(draft.logic_path, None);
+        assert_eq!(
+            draft.logic,
+            "transfer funds treasury bob 250 \"Approved budget\"\n"
+        );
+        assert!(
+            matches!(draft.ops[..], [Op::Transfer { amount, .. }] if amount == Decimal::from(250))
+        );
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("A value is required"));
+        assert!(output.contains("Unknown template: 9"));
+        assert!(output.contains("Enter a positive number"));
+        assert!(output.contains("Compiled logic (1 ops)"));
+    }
+
+    #[test]
+    fn test_wizard_requires_logic_file_without_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let logic_path = dir.path().join("logic.dsl");
+        fs::write(&logic_path, "push 1\npush 2\nadd\n").unwrap();
+
+        let answers = format!(
+            "p1\nTitle\nDescription\n\n0.5\n0.5\n48h\n7d\n\n{}\nn\n",
+            logic_path.display()
+        );
+        let mut output = Vec::new();
+        let mut prompter = Prompter::new(answers.as_bytes(), &mut output);
+
+        assert!(run_wizard(&mut prompter, None).unwrap().is_none());
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("A logic file is required without a template"));
+        assert!(output.contains("Compiled logic (3 ops)"));
+    }
+}
@@ -0,0 +1,221 @@
+//! Input and session handling for the interactive REPL
+//!
+//! Block statements such as `if:`, `while:` and `def name(args):` can be
+//! typed over several lines. A line ending in `:` opens a block, and the
+//! following lines are collected until a blank line, or a line that is not
+//! indented, ends it:
+//!
+//! ```text
+//! > push 1
+//! Result: 1
+//! > if:
+//! ...     push 1
+//! ... else:
+//! ...     push 0
+//! ...
+//! Result: 1
+//! ```
+//!
+//! Each input that runs successfully is kept in the session, so `save <file>`
+//! can write it out as a program and `load <file>` can run it again later.
+
+use crate::vm::Op;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Prompt for a new input
+pub const PROMPT: &str = "> ";
+
+/// Prompt for a line inside a block
+pub const CONTINUATION_PROMPT: &str = "... ";
+
+/// Lines that continue a block at the same indentation as its header
+const CONTINUATION_HEADERS: &[&str] = &["else:"];
+
+/// Collects lines into complete inputs
+#[derive(Debug, Default)]
+pub struct BlockReader {
+    lines: Vec<String>,
+}
+
+impl BlockReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The prompt for the next line
+    pub fn prompt(&self) -> &'static str {
+        if self.is_pending() {
+            CONTINUATION_PROMPT
+        } else {
+            PROMPT
+        }
+    }
+
+    /// Whether a block has been started but not finished
+    pub fn is_pending(&self) -> bool {
+        !self.lines.is_empty()
+    }
+
+    /// Drop a partly typed block
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Add a line, returning the inputs it completes, in order
+    ///
+    /// A line that ends a block by not being indented is returned as an input
+    /// of its own after the block, or starts the next block.
+    pub fn push(&mut self, line: &str) -> Vec<String> {
+        let trimmed = line.trim();
+        if !self.is_pending() {
+            if trimmed.ends_with(':') {
+                self.lines.push(line.trim_end().to_string());
+                return Vec::new();
+            }
+            return vec![line.to_string()];
+        }
+
+        if trimmed.is_empty() {
+            return vec![self.take()];
+        }
+        if line.starts_with(char::is_whitespace) || CONTINUATION_HEADERS.contains(&trimmed) {
+            self.lines.push(line.trim_end().to_string());
+            return Vec::new();
+        }
+
+        let mut inputs = vec![self.take()];
+        inputs.extend(self.push(line));
+        inputs
+    }
+
+    fn take(&mut self) -> String {
+        std::mem::take(&mut self.lines).join("\n")
+    }
+}
+
+/// An input that ran in the session
+#[derive(Debug, Clone)]
+struct SessionEntry {
+    /// DSL source, unless the ops were loaded from a JSON program
+    source: Option<String>,
+    ops: Vec<Op>,
+}
+
+/// The inputs run so far in a REPL session
+#[derive(Debug, Default)]
+pub struct ReplSession {
+    entries: Vec<SessionEntry>,
+}
+
+impl ReplSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember an input that ran successfully
+    pub fn record(&mut self, source: Option<String>, ops: Vec<Op>) {
+        self.entries.push(SessionEntry { source, ops });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The session as a DSL program
+    ///
+    /// Fails if part of the session was loaded from a JSON program, which
+    /// has no DSL source.
+    pub fn to_dsl(&self) -> Result<String, Box<dyn Error>> {
+        let mut sources = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            let source = entry
+                .source
+                .as_deref()
+                .ok_or("The session includes a JSON program; save it to a .json file instead")?;
+            sources.push(source.trim_end());
+        }
+        Ok(sources.join("\n") + "\n")
+    }
+
+    /// All ops run in the session
+    pub fn ops(&self) -> Vec<Op> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.ops.iter().cloned())
+            .collect()
+    }
+
+    /// Write the session to `path`, as JSON ops for a `.json` file and as
+    /// DSL otherwise
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let is_json = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let contents = if is_json {
+            serde_json::to_string_pretty(&self.ops())?
+        } else {
+            self.to_dsl()?
+        };
+        fs::write(path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parse_dsl;
+
+    fn feed(reader: &mut BlockReader, lines: &[&str]) -> Vec<String> {
+        lines.iter().flat_map(|line| reader.push(line)).collect()
+    }
+
+    #[test]
+    fn test_blocks_end_on_blank_line_or_dedent() {
+        let mut reader = BlockReader::new();
+        assert_eq!(feed(&mut reader, &["push 1"]), vec!["push 1"]);
+
+        let inputs = feed(&mut reader, &["if:", "    push 1", "else:", "    push 0"]);
+        assert!(inputs.is_empty());
+        assert_eq!(reader.prompt(), CONTINUATION_PROMPT);
+        let inputs = reader.push("");
+        assert_eq!(inputs, vec!["if:\n    push 1\nelse:\n    push 0"]);
+        assert_eq!(reader.prompt(), PROMPT);
+        assert!(parse_dsl(&inputs[0]).is_ok());
+
+        let inputs = feed(&mut reader, &["while:", "    push 0", "stack"]);
+        assert_eq!(inputs, vec!["while:\n    push 0", "stack"]);
+        assert!(!reader.is_pending());
+    }
+
+    #[test]
+    fn test_session_saves_dsl_and_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = ReplSession::new();
+        for source in ["push 1", "if:\n    push 2"] {
+            let (ops, _) = parse_dsl(source).unwrap();
+            session.record(Some(source.to_string()), ops);
+        }
+
+        let dsl_path = dir.path().join("session.dsl");
+        session.save(&dsl_path).unwrap();
+        let saved = fs::read_to_string(&dsl_path).unwrap();
+        assert_eq!(saved, "push 1\nif:\n    push 2\n");
+        assert_eq!(parse_dsl(&saved).unwrap().0, session.ops());
+
+        let json_path = dir.path().join("session.json");
+        session.save(&json_path).unwrap();
+        let ops: Vec<Op> = serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(ops, session.ops());
+
+        session.record(None, vec![Op::Push(crate::typed::TypedValue::Number(3.0))]);
+        assert!(session.save(&dsl_path).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Identity session commands
+//!
+//! `identity login` opens a key file, asking for its passphrase if it is
+//! sealed, and caches a signed session token in the session file
+//! (`ICN_SESSION`, or `./.icn-session.json`). Until it expires or
+//! `identity logout` removes it, proposal, template, federation and other
+//! commands act as the logged-in identity. `identity whoami` shows which
+//! identity that is.
+
+use crate::cli::keys::DEFAULT_KEY_PATH;
+use crate::cli::output::print_output;
+use crate::config::Config;
+use crate::identity::keystore;
+use crate::identity::session::{self, Session, DEFAULT_SESSION_TTL_SECS};
+use crate::storage::auth::AuthContext;
+use crate::storage::utils::now_with_default;
+use chrono::Utc;
+use clap::{value_parser, Arg, ArgMatches, Command};
+use serde::Serialize;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Subcommands of `identity` handled by [`handle_session_command`]
+pub const SESSION_COMMANDS: &[&str] = &["login", "logout", "whoami"];
+
+/// The `identity` subcommands that manage the session
+pub fn session_subcommands() -> Vec<Command> {
+    vec![
+        Command::new("login")
+            .about("Act as the identity in a key file until logout or expiry")
+            .arg(
+                Arg::new("path")
+                    .long("path")
+                    .value_name("FILE")
+                    .help("Identity key file (default: identity.key_path, or ./identity.json)"),
+            )
+            .arg(
+                Arg::new("ttl")
+                    .long("ttl")
+                    .value_name("HOURS")
+                    .value_parser(value_parser!(u64).range(1..))
+                    .help("Hours until the session expires (default: 12)"),
+            ),
+        Command::new("logout").about("End the current session"),
+        Command::new("whoami").about("Show the identity commands act as"),
+    ]
+}
+
+/// What `whoami` reports
+#[derive(Debug, Serialize)]
+struct WhoAmI {
+    did: String,
+    /// "session", "config" or "demo"
+    source: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    identity_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+}
+
+impl WhoAmI {
+    fn from_session(session: &Session) -> Self {
+        Self {
+            did: session.did().to_string(),
+            source: "session",
+            username: Some(session.identity.public_username().to_string()),
+            identity_type: Some(session.identity.identity_type.clone()),
+            key_path: Some(session.key_path.clone()),
+            expires_at: Some(session.expires_at),
+        }
+    }
+}
+
+fn format_time(timestamp: u64) -> String {
+    chrono::DateTime::<Utc>::from_timestamp(timestamp as i64, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn print_session(heading: &str, session: &Session) -> Result<(), Box<dyn Error>> {
+    print_output(&WhoAmI::from_session(session), |who| {
+        println!("🔑 {}", heading);
+        println!("   DID: {}", who.did);
+        if let Some(username) = &who.username {
+            println!("   Username: {}", username);
+        }
+        if let Some(key_path) = &who.key_path {
+            println!("   Key file: {}", key_path.display());
+        }
+        println!("   Expires: {}", format_time(session.expires_at));
+    })?;
+    Ok(())
+}
+
+/// Auth context of the active session, if someone is logged in
+///
+/// Fails if the session file exists but has expired or been tampered with,
+/// rather than quietly acting as someone else.
+pub fn session_auth_context() -> Result<Option<AuthContext>, Box<dyn Error>> {
+    let session = session::active_session(&session::session_path(), now_with_default())?;
+    Ok(session.map(|session| session.to_auth_context()))
+}
+
+/// Handle `identity login`, `logout` and `whoami`
+pub fn handle_session_command(
+    subcommand: &str,
+    matches: &ArgMatches,
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    let session_path = session::session_path();
+    match subcommand {
+        "login" => {
+            let key_path = matches
+                .get_one::<String>("path")
+                .map(PathBuf::from)
+                .or_else(|| config.identity.key_path.clone())
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_KEY_PATH));
+            let ttl_secs = matches
+                .get_one::<u64>("ttl")
+                .map(|hours| hours * 60 * 60)
+                .unwrap_or(DEFAULT_SESSION_TTL_SECS);
+            let session = session::login(&key_path, &session_path, now_with_default(), ttl_secs)?;
+            print_session("Logged in", &session)
+        }
+        "logout" => {
+            match session::logout(&session_path)? {
+                Some(session) => println!("👋 Logged out {}", session.did()),
+                None => println!("Not logged in"),
+            }
+            Ok(())
+        }
+        "whoami" => {
+            if let Some(session) = session::active_session(&session_path, now_with_default())? {
+                return print_session("Logged in", &session);
+            }
+            let who = match &config.identity.key_path {
+                Some(key_path) => {
+                    let summary = keystore::inspect_identity(key_path)?;
+                    WhoAmI {
+                        did: summary.did,
+                        source: "config",
+                        username: summary.public_username,
+                        identity_type: summary.identity_type,
+                        key_path: Some(key_path.clone()),
+                        expires_at: None,
+                    }
+                }
+                None => WhoAmI {
+                    did: "demo_user".to_string(),
+                    source: "demo",
+                    username: None,
+                    identity_type: None,
+                    key_path: None,
+                    expires_at: None,
+                },
+            };
+            print_output(&who, |who| match who.source {
+                "config" => {
+                    println!("🔑 Not logged in; using the configured identity");
+                    println!("   DID: {}", who.did);
+                    if let Some(key_path) = &who.key_path {
+                        println!("   Key file: {}", key_path.display());
+                    }
+                }
+                _ => println!(
+                    "Not logged in; commands act as demo_user. Run `icn-covm identity login`."
+                ),
+            })?;
+            Ok(())
+        }
+        _ => Err(format!("Unknown identity subcommand: {}", subcommand).into()),
+    }
+}
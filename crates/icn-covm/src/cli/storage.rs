@@ -0,0 +1,760 @@
+//! Storage repair commands
+//!
+//! `storage set-value`, `set-json`, `delete`, `copy` and `export-namespace`
+//! let operators fix or back up stored data from the command line, and
+//! `grant` and `revoke-grant` manage the federation grants that open one
+//! cooperative's namespaces to another (see `storage::partition`). They act
+//! as the configured operator identity (`identity.key_path`), which is given
+//! the role the command needs on the namespaces it touches; the backend
+//! still makes its own permission check before each read or write.
+//!
+//! Commands that change a value ask for confirmation, showing what will be
+//! replaced, unless `--force` is given. Without a terminal, `--force` is
+//! required. Each change is recorded in the audit log.
+//!
+//! `storage snapshot` writes everything the backend holds to a file, and
+//! `storage restore` rolls the backend back to such a file (see
+//! `storage::snapshot`). A restore keeps the audit namespace as it stands,
+//! so the log still shows what was rolled back and who did it.
+//!
+//! `storage usage` reports how much each namespace holds and how close it is
+//! to its quota; see [`crate::storage::usage`].
+
+use crate::audit::{self, AuditEntry, AuditOutcome, AuditSource};
+use crate::cli::output::print_output;
+use crate::cli::proposal_wizard::Prompter;
+use crate::storage::auth::AuthContext;
+use crate::storage::partition::{
+    coop_root, grant_access, grant_key, partition_of, revoke_access, GrantAccess,
+};
+use crate::storage::snapshot::StorageSnapshot;
+use crate::storage::traits::StorageBackend;
+use crate::storage::usage::{self, NamespaceUsage};
+use chrono::{DateTime, Utc};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+/// Subcommands of `storage` handled by [`handle_storage_command`]
+pub const STORAGE_WRITE_COMMANDS: &[&str] = &[
+    "set-value",
+    "set-json",
+    "delete",
+    "copy",
+    "export-namespace",
+    "grant",
+    "revoke-grant",
+    "snapshot",
+    "restore",
+];
+
+fn force_arg() -> Arg {
+    Arg::new("force")
+        .long("force")
+        .action(ArgAction::SetTrue)
+        .help("Make the change without asking for confirmation")
+}
+
+fn namespace_arg(help: &'static str) -> Arg {
+    Arg::new("namespace").help(help).required(true).index(1)
+}
+
+fn key_arg(help: &'static str) -> Arg {
+    Arg::new("key").help(help).required(true).index(2)
+}
+
+/// The `storage` subcommands that change or export data
+pub fn storage_write_subcommands() -> Vec<Command> {
+    vec![
+        Command::new("set-value")
+            .about("Store a text value, or the contents of a file, under a key")
+            .arg(namespace_arg("Namespace to write to"))
+            .arg(key_arg("Key to set"))
+            .arg(
+                Arg::new("value")
+                    .help("Value to store")
+                    .index(3)
+                    .required_unless_present("file"),
+            )
+            .arg(
+                Arg::new("file")
+                    .long("file")
+                    .value_name("FILE")
+                    .help("Store the bytes of this file instead")
+                    .conflicts_with("value"),
+            )
+            .arg(force_arg()),
+        Command::new("set-json")
+            .about("Store a JSON value under a key, checking that it parses")
+            .arg(namespace_arg("Namespace to write to"))
+            .arg(key_arg("Key to set"))
+            .arg(
+                Arg::new("json")
+                    .help("JSON value to store")
+                    .index(3)
+                    .required_unless_present("file"),
+            )
+            .arg(
+                Arg::new("file")
+                    .long("file")
+                    .value_name("FILE")
+                    .help("Read the JSON value from this file instead")
+                    .conflicts_with("json"),
+            )
+            .arg(force_arg()),
+        Command::new("delete")
+            .about("Delete a key")
+            .arg(namespace_arg("Namespace to delete from"))
+            .arg(key_arg("Key to delete"))
+            .arg(force_arg()),
+        Command::new("copy")
+            .about("Copy a value to another key or namespace")
+            .arg(namespace_arg("Namespace to copy from"))
+            .arg(key_arg("Key to copy"))
+            .arg(
+                Arg::new("to-namespace")
+                    .long("to-namespace")
+                    .value_name("NAMESPACE")
+                    .help("Namespace to copy to (default: the source namespace)"),
+            )
+            .arg(
+                Arg::new("to-key")
+                    .long("to-key")
+                    .value_name("KEY")
+                    .help("Key to copy to (default: the source key)"),
+            )
+            .arg(force_arg()),
+        Command::new("export-namespace")
+            .about("Export every key in a namespace as JSON")
+            .arg(namespace_arg("Namespace to export"))
+            .arg(
+                Arg::new("prefix")
+                    .short('p')
+                    .long("prefix")
+                    .value_name("PREFIX")
+                    .help("Only export keys with this prefix"),
+            )
+            .arg(
+                Arg::new("file")
+                    .long("file")
+                    .value_name("FILE")
+                    .help("Write the export to this file instead of printing it"),
+            ),
+        Command::new("grant")
+            .about("Let another cooperative's members access a cooperative namespace")
+            .arg(namespace_arg(
+                "Namespace to share, under coops/{coop}; namespaces below it are shared too",
+            ))
+            .arg(
+                Arg::new("to")
+                    .long("to")
+                    .value_name("COOP")
+                    .required(true)
+                    .help("Cooperative whose members get access"),
+            )
+            .arg(
+                Arg::new("write")
+                    .long("write")
+                    .action(ArgAction::SetTrue)
+                    .help("Allow writing as well as reading"),
+            )
+            .arg(force_arg()),
+        Command::new("revoke-grant")
+            .about("Withdraw a cooperative's grant to another")
+            .arg(namespace_arg("Any namespace of the granting cooperative"))
+            .arg(
+                Arg::new("from")
+                    .long("from")
+                    .value_name("COOP")
+                    .required(true)
+                    .help("Cooperative to withdraw access from"),
+            )
+            .arg(force_arg()),
+        Command::new("snapshot")
+            .about("Write everything in storage to a snapshot file")
+            .arg(
+                Arg::new("file")
+                    .help("File to write the snapshot to")
+                    .required(true)
+                    .index(1),
+            ),
+        Command::new("restore")
+            .about("Replace everything in storage with a snapshot, keeping the audit log")
+            .arg(
+                Arg::new("file")
+                    .help("Snapshot file written by `storage snapshot`")
+                    .required(true)
+                    .index(1),
+            )
+            .arg(force_arg()),
+    ]
+}
+
+/// A stored value as JSON
+///
+/// JSON values are embedded as-is, other text as a string, and binary data
+/// as a hex string.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedValue {
+    pub key: String,
+    pub encoding: &'static str,
+    pub size: usize,
+    pub value: serde_json::Value,
+}
+
+impl ExportedValue {
+    pub fn new(key: &str, data: &[u8]) -> Self {
+        let (encoding, value) = match std::str::from_utf8(data) {
+            Ok(text) => match serde_json::from_str::<serde_json::Value>(text) {
+                Ok(json) => ("json", json),
+                Err(_) => ("text", serde_json::Value::String(text.to_string())),
+            },
+            Err(_) => ("binary", serde_json::Value::String(hex::encode(data))),
+        };
+        Self {
+            key: key.to_string(),
+            encoding,
+            size: data.len(),
+            value,
+        }
+    }
+}
+
+/// Result of `storage export-namespace`
+#[derive(Debug, Clone, Serialize)]
+pub struct NamespaceExport {
+    pub namespace: String,
+    pub prefix: Option<String>,
+    pub entries: Vec<ExportedValue>,
+}
+
+/// A change made by a storage command
+#[derive(Debug, Clone, Serialize)]
+struct StorageChange {
+    action: &'static str,
+    namespace: String,
+    key: String,
+    /// Size of the value written, or of the value deleted
+    size: usize,
+    /// Whether an existing value was replaced
+    replaced: bool,
+}
+
+/// Ask before changing data, unless `--force` was given
+fn confirm(question: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    if force {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err("Refusing to change storage without a terminal to confirm; use --force".into());
+    }
+    if Prompter::stdio().confirm(question, false)? {
+        Ok(())
+    } else {
+        Err("Cancelled".into())
+    }
+}
+
+fn required<'a>(matches: &'a ArgMatches, name: &str) -> Result<&'a str, Box<dyn Error>> {
+    matches
+        .get_one::<String>(name)
+        .map(String::as_str)
+        .ok_or_else(|| format!("Missing required argument: {}", name).into())
+}
+
+/// The current value of a key, if it has one
+fn existing<S: StorageBackend>(
+    storage: &S,
+    auth: &AuthContext,
+    namespace: &str,
+    key: &str,
+) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    if !storage.contains(Some(auth), namespace, key)? {
+        return Ok(None);
+    }
+    Ok(Some(storage.get(Some(auth), namespace, key)?))
+}
+
+/// Write `value`, after confirming any overwrite
+fn write_value<S: StorageBackend>(
+    storage: &mut S,
+    auth: &AuthContext,
+    namespace: &str,
+    key: &str,
+    value: Vec<u8>,
+    force: bool,
+) -> Result<StorageChange, Box<dyn Error>> {
+    storage.check_permission(Some(auth), "write", namespace)?;
+    let current = existing(storage, auth, namespace, key)?;
+    let question = match &current {
+        Some(current) => format!(
+            "Replace {}:{} ({} bytes) with {} bytes?",
+            namespace,
+            key,
+            current.len(),
+            value.len()
+        ),
+        None => format!("Write {} bytes to {}:{}?", value.len(), namespace, key),
+    };
+    confirm(&question, force)?;
+
+    let size = value.len();
+    storage.set(Some(auth), namespace, key, value)?;
+    Ok(StorageChange {
+        action: "set",
+        namespace: namespace.to_string(),
+        key: key.to_string(),
+        size,
+        replaced: current.is_some(),
+    })
+}
+
+/// Cooperative whose partition `namespace` belongs to
+fn owning_coop(namespace: &str) -> Result<&str, Box<dyn Error>> {
+    partition_of(namespace)
+        .ok_or_else(|| format!("{} is not a cooperative namespace under coops/", namespace).into())
+}
+
+fn export_namespace<S: StorageBackend>(
+    storage: &S,
+    auth: &AuthContext,
+    namespace: &str,
+    prefix: Option<&str>,
+) -> Result<NamespaceExport, Box<dyn Error>> {
+    let mut keys = storage.list_keys(Some(auth), namespace, prefix)?;
+    keys.sort();
+    let mut entries = Vec::with_capacity(keys.len());
+    for key in keys {
+        let data = storage.get(Some(auth), namespace, &key)?;
+        entries.push(ExportedValue::new(&key, &data));
+    }
+    Ok(NamespaceExport {
+        namespace: namespace.to_string(),
+        prefix: prefix.map(str::to_string),
+        entries,
+    })
+}
+
+/// Write a snapshot of the whole backend to `path`
+fn take_snapshot<S: StorageBackend>(storage: &S, path: &str) -> Result<(), Box<dyn Error>> {
+    let snapshot = storage.snapshot()?;
+    snapshot.save(Path::new(path))?;
+    println!(
+        "📸 Saved {} keys in {} namespaces to {}",
+        snapshot.entries.len(),
+        snapshot.namespaces.len(),
+        path
+    );
+    Ok(())
+}
+
+/// Roll the backend back to the snapshot in `path`, after confirming
+///
+/// The audit namespace is carried over from the current state rather than
+/// the snapshot.
+fn restore_snapshot<S: StorageBackend>(
+    storage: &mut S,
+    path: &str,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut snapshot = StorageSnapshot::load(Path::new(path))?;
+    let current = storage.snapshot()?;
+    let taken_at = DateTime::<Utc>::from_timestamp(snapshot.taken_at as i64, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| snapshot.taken_at.to_string());
+    confirm(
+        &format!(
+            "Replace the {} keys in storage with the {} keys of the snapshot taken at {}?",
+            current.entries.len(),
+            snapshot.entries.len(),
+            taken_at
+        ),
+        force,
+    )?;
+
+    snapshot.keep_namespace(&current, audit::AUDIT_NAMESPACE);
+    let keys = snapshot.entries.len();
+    storage.restore(snapshot)?;
+    println!("⏪ Restored {} keys from {}", keys, path);
+    Ok(())
+}
+
+fn run_command<S: StorageBackend>(
+    storage: &mut S,
+    auth: &mut AuthContext,
+    subcommand: &str,
+    matches: &ArgMatches,
+) -> Result<Option<StorageChange>, Box<dyn Error>> {
+    let force = matches.try_get_one::<bool>("force").ok().flatten() == Some(&true);
+    match subcommand {
+        "snapshot" => return take_snapshot(storage, required(matches, "file")?).map(|_| None),
+        "restore" => {
+            return restore_snapshot(storage, required(matches, "file")?, force).map(|_| None)
+        }
+        _ => {}
+    }
+    let namespace = required(matches, "namespace")?;
+
+    match subcommand {
+        "set-value" => {
+            let key = required(matches, "key")?;
+            let value = match matches.get_one::<String>("file") {
+                Some(path) => {
+                    fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?
+                }
+                None => required(matches, "value")?.as_bytes().to_vec(),
+            };
+            auth.add_role(namespace, "admin");
+            write_value(storage, auth, namespace, key, value, force).map(Some)
+        }
+        "set-json" => {
+            let key = required(matches, "key")?;
+            let text = match matches.get_one::<String>("file") {
+                Some(path) => fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path, e))?,
+                None => required(matches, "json")?.to_string(),
+            };
+            let json: serde_json::Value =
+                serde_json::from_str(&text).map_err(|e| format!("Invalid JSON: {}", e))?;
+            auth.add_role(namespace, "admin");
+            write_value(
+                storage,
+                auth,
+                namespace,
+                key,
+                serde_json::to_vec(&json)?,
+                force,
+            )
+            .map(Some)
+        }
+        "delete" => {
+            let key = required(matches, "key")?;
+            auth.add_role(namespace, "admin");
+            storage.check_permission(Some(auth), "write", namespace)?;
+            let current = existing(storage, auth, namespace, key)?
+                .ok_or_else(|| format!("Key not found: {}:{}", namespace, key))?;
+            confirm(
+                &format!("Delete {}:{} ({} bytes)?", namespace, key, current.len()),
+                force,
+            )?;
+            storage.delete(Some(auth), namespace, key)?;
+            Ok(Some(StorageChange {
+                action: "delete",
+                namespace: namespace.to_string(),
+                key: key.to_string(),
+                size: current.len(),
+                replaced: true,
+            }))
+        }
+        "copy" => {
+            let key = required(matches, "key")?;
+            let to_namespace = matches
+                .get_one::<String>("to-namespace")
+                .map(String::as_str)
+                .unwrap_or(namespace);
+            let to_key = matches
+                .get_one::<String>("to-key")
+                .map(String::as_str)
+                .unwrap_or(key);
+            if (to_namespace, to_key) == (namespace, key) {
+                return Err(
+                    "Copy needs --to-namespace or --to-key to differ from the source".into(),
+                );
+            }
+            auth.add_role(namespace, "reader");
+            auth.add_role(to_namespace, "admin");
+            let value = storage.get(Some(auth), namespace, key)?;
+            let mut change = write_value(storage, auth, to_namespace, to_key, value, force)?;
+            change.action = "copy";
+            Ok(Some(change))
+        }
+        "export-namespace" => {
+            let prefix = matches.get_one::<String>("prefix").map(String::as_str);
+            auth.add_role(namespace, "reader");
+            let export = export_namespace(storage, auth, namespace, prefix)?;
+            match matches.get_one::<String>("file") {
+                Some(path) => {
+                    fs::write(path, serde_json::to_string_pretty(&export)?)
+                        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+                    println!(
+                        "📦 Exported {} keys from '{}' to {}",
+                        export.entries.len(),
+                        namespace,
+                        path
+                    );
+                }
+                // Exports are data, so they are JSON unless YAML was asked for
+                None if crate::cli::output::is_table() => {
+                    println!("{}", serde_json::to_string_pretty(&export)?)
+                }
+                None => print_output(&export, |_| {})?,
+            }
+            Ok(None)
+        }
+        "grant" => {
+            let grantee = required(matches, "to")?;
+            let owner = owning_coop(namespace)?;
+            let (access, allowed) = if matches.get_flag("write") {
+                (GrantAccess::Write, "read and write")
+            } else {
+                (GrantAccess::Read, "read")
+            };
+            let root = coop_root(owner);
+            auth.add_role(&root, "admin");
+            let key = grant_key(grantee);
+            let replaced = existing(storage, auth, &root, &key)?.is_some();
+            confirm(
+                &format!(
+                    "Let members of {} {} {}{}?",
+                    grantee,
+                    allowed,
+                    namespace,
+                    if replaced {
+                        ", replacing their current grant"
+                    } else {
+                        ""
+                    }
+                ),
+                force,
+            )?;
+            let grant = grant_access(storage, auth, namespace, grantee, access)?;
+            Ok(Some(StorageChange {
+                action: "grant",
+                namespace: root,
+                key,
+                size: serde_json::to_vec(&grant)?.len(),
+                replaced,
+            }))
+        }
+        "revoke-grant" => {
+            let grantee = required(matches, "from")?;
+            let owner = owning_coop(namespace)?;
+            let root = coop_root(owner);
+            auth.add_role(&root, "admin");
+            let key = grant_key(grantee);
+            let current = existing(storage, auth, &root, &key)?
+                .ok_or_else(|| format!("{} has no grant to {}", owner, grantee))?;
+            confirm(
+                &format!("Withdraw the access of {} to {}?", grantee, owner),
+                force,
+            )?;
+            revoke_access(storage, auth, owner, grantee)?;
+            Ok(Some(StorageChange {
+                action: "revoke-grant",
+                namespace: root,
+                key,
+                size: current.len(),
+                replaced: true,
+            }))
+        }
+        other => Err(format!("Unknown storage subcommand: {}", other).into()),
+    }
+}
+
+/// The `storage usage` subcommand
+pub fn storage_usage_subcommand() -> Command {
+    Command::new("usage")
+        .about("Show the storage used by namespaces and their quota headroom")
+        .arg(
+            Arg::new("namespace")
+                .long("namespace")
+                .value_name("NAMESPACE")
+                .help("Only this namespace (default: every namespace)"),
+        )
+}
+
+/// Handle `storage usage`, flagging namespaces that have reached one of the
+/// configured alert percentages of their quota
+pub fn handle_usage_command<S: StorageBackend + ?Sized>(
+    storage: &S,
+    auth: &AuthContext,
+    namespace: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let usages = match namespace {
+        Some(namespace) => vec![usage::namespace_usage(storage, Some(auth), namespace)?],
+        None => usage::all_namespace_usage(storage, Some(auth))?,
+    };
+    let thresholds = usage::thresholds();
+    print_output(&usages, |usages| print_usage(usages, &thresholds))
+}
+
+fn print_usage(usages: &[NamespaceUsage], thresholds: &[u8]) {
+    if usages.is_empty() {
+        println!("No namespaces found");
+        return;
+    }
+    println!(
+        "{:<24} {:>6} {:>12} {:>12} {:>7} {:>9} {:>6} {:>8}",
+        "NAMESPACE", "KEYS", "BYTES", "QUOTA", "USED", "PROPOSALS", "VOTES", "COMMENTS"
+    );
+    for usage in usages {
+        let quota = usage
+            .quota_bytes
+            .map_or_else(|| "-".to_string(), |quota| quota.to_string());
+        let used = usage
+            .percent_used()
+            .map_or_else(|| "-".to_string(), |percent| format!("{:.1}%", percent));
+        let alert = match usage.threshold_reached(thresholds) {
+            Some(threshold) => format!("  ⚠️  over {}%", threshold),
+            None => String::new(),
+        };
+        println!(
+            "{:<24} {:>6} {:>12} {:>12} {:>7} {:>9} {:>6} {:>8}{}",
+            usage.namespace,
+            usage.keys,
+            usage.bytes,
+            quota,
+            used,
+            usage.proposals,
+            usage.votes,
+            usage.comments,
+            alert
+        );
+    }
+}
+
+/// Run one of [`STORAGE_WRITE_COMMANDS`] as the operator in `auth`
+///
+/// Changes are recorded in the audit log, whether they succeed or fail.
+pub fn handle_storage_command<S: StorageBackend>(
+    storage: &mut S,
+    auth: &AuthContext,
+    subcommand: &str,
+    matches: &ArgMatches,
+) -> Result<(), Box<dyn Error>> {
+    // Snapshots cover every namespace, so they take none
+    let namespace = matches.try_get_one::<String>("namespace").ok().flatten();
+    let span = tracing::info_span!(
+        "storage",
+        command = subcommand,
+        namespace = namespace.map(String::as_str),
+        identity = auth.identity_did(),
+    );
+    let _entered = span.enter();
+    let mut operator = auth.clone();
+    let result = run_command(storage, &mut operator, subcommand, matches);
+
+    // Exports and snapshots only read
+    if !matches!(subcommand, "export-namespace" | "snapshot") {
+        let outcome = if result.is_ok() {
+            AuditOutcome::Success
+        } else {
+            AuditOutcome::Failure
+        };
+        let mut entry = AuditEntry::new(
+            AuditSource::Cli,
+            auth.identity_did(),
+            format!("storage {}", subcommand),
+            outcome,
+        );
+        entry.namespace = namespace.cloned();
+        entry.detail = match &result {
+            Ok(Some(change)) => Some(format!("{}:{}", change.namespace, change.key)),
+            Ok(None) => matches.try_get_one::<String>("file").ok().flatten().cloned(),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Err(e) = audit::prepare(storage).and_then(|()| audit::append(storage, &entry)) {
+            tracing::warn!(error = %e, "Failed to write audit entry");
+        }
+    }
+
+    if let Some(change) = result? {
+        print_output(&change, |change| {
+            let verb = match change.action {
+                "delete" => "Deleted",
+                "copy" => "Copied to",
+                "grant" => "Granted",
+                "revoke-grant" => "Revoked",
+                _ if change.replaced => "Replaced",
+                _ => "Wrote",
+            };
+            println!(
+                "✅ {} {}:{} ({} bytes)",
+                verb, change.namespace, change.key, change.size
+            );
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    fn run(
+        storage: &mut InMemoryStorage,
+        auth: &AuthContext,
+        args: &[&str],
+    ) -> Result<(), Box<dyn Error>> {
+        let matches = Command::new("storage")
+            .subcommands(storage_write_subcommands())
+            .try_get_matches_from(std::iter::once("storage").chain(args.iter().copied()))?;
+        let (subcommand, sub_matches) = matches.subcommand().unwrap();
+        handle_storage_command(storage, auth, subcommand, sub_matches)
+    }
+
+    #[test]
+    fn test_write_copy_delete_and_export() {
+        let mut storage = InMemoryStorage::new();
+        let mut auth = AuthContext::new("did:key:operator");
+        auth.add_role("global", "admin");
+        storage
+            .create_namespace(Some(&auth), "coop", 1024 * 1024, None)
+            .unwrap();
+        storage
+            .create_namespace(Some(&auth), "backup", 1024 * 1024, None)
+            .unwrap();
+        let operator = AuthContext::new("did:key:operator");
+
+        run(
+            &mut storage,
+            &operator,
+            &["set-json", "coop", "config", "{\"quorum\": 0.5}", "--force"],
+        )
+        .unwrap();
+        assert!(run(
+            &mut storage,
+            &operator,
+            &["set-json", "coop", "bad", "{oops", "--force"]
+        )
+        .is_err());
+        run(
+            &mut storage,
+            &operator,
+            &[
+                "copy",
+                "coop",
+                "config",
+                "--to-namespace",
+                "backup",
+                "--force",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            storage.get(Some(&auth), "backup", "config").unwrap(),
+            b"{\"quorum\":0.5}".to_vec()
+        );
+
+        run(
+            &mut storage,
+            &operator,
+            &["delete", "coop", "config", "--force"],
+        )
+        .unwrap();
+        assert!(!storage.contains(Some(&auth), "coop", "config").unwrap());
+
+        let export = export_namespace(&storage, &auth, "backup", None).unwrap();
+        assert_eq!(export.entries.len(), 1);
+        assert_eq!(export.entries[0].encoding, "json");
+        assert_eq!(export.entries[0].value["quorum"], 0.5);
+
+        let audited = audit::query(&storage, &Default::default()).unwrap();
+        assert_eq!(audited.len(), 4);
+        assert!(audited.iter().any(|e| e.outcome == AuditOutcome::Failure));
+    }
+}
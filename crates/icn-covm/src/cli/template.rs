@@ -0,0 +1,473 @@
+//! Governance template CLI functionality.
+//!
+//! Templates are reusable proposal definitions kept in the
+//! `governance::templates::TemplateRegistry`: voting rules, eligibility,
+//! typed parameters, and the DSL to run when a proposal passes. Instantiating
+//! a template fills in its parameters and creates a proposal from it.
+//!
+//! The module includes functionality for:
+//! - Creating and updating templates from JSON definitions, or creating the
+//!   built-in `onboard-member` and `offboard-member` templates
+//! - Listing templates and showing one in full, with its version history
+//! - Deleting templates
+//! - Creating proposals from templates
+
+use crate::cli::dry_run::dry_run_arg;
+use crate::cli::output::print_output;
+use crate::cli::proposal::{create_from_draft, voter_eligibility_arg};
+use crate::cli::proposal_wizard::ProposalDraft;
+use crate::compiler::parse_dsl;
+use crate::governance::templates::{
+    builtin_template, builtin_template_ids, Template, TemplateRegistry,
+};
+use crate::identity::Identity;
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::Storage;
+use crate::vm::VM;
+use chrono::{DateTime, Duration};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fs;
+
+fn id_arg(help: &'static str) -> Arg {
+    Arg::new("id")
+        .long("id")
+        .value_name("TEMPLATE_ID")
+        .help(help)
+        .required(true)
+}
+
+fn file_arg() -> Arg {
+    Arg::new("file")
+        .long("file")
+        .value_name("FILE_PATH")
+        .help("Template definition in JSON")
+        .required(true)
+}
+
+/// Create the template command and its subcommands
+pub fn template_command() -> Command {
+    Command::new("template")
+        .about("Manage governance templates and create proposals from them")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("create")
+                .about("Create a template from a JSON definition or a built-in template")
+                .arg(file_arg().required(false).required_unless_present("builtin"))
+                .arg(
+                    Arg::new("builtin")
+                        .long("builtin")
+                        .value_name("TEMPLATE")
+                        .help("Create a built-in template instead of reading a definition")
+                        .value_parser(builtin_template_ids().collect::<Vec<_>>())
+                        .conflicts_with("file"),
+                )
+                .arg(
+                    Arg::new("id")
+                        .long("id")
+                        .value_name("TEMPLATE_ID")
+                        .help("Template ID (default: the definition's id, or a generated one)"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Template name (default: the definition's name)"),
+                ),
+        )
+        .subcommand(Command::new("list").about("List templates"))
+        .subcommand(
+            Command::new("show")
+                .about("Show a template, its parameters and version history")
+                .arg(id_arg("ID of the template to show")),
+        )
+        .subcommand(
+            Command::new("update")
+                .about("Replace a template's definition, keeping the old version in its history")
+                .arg(id_arg("ID of the template to update"))
+                .arg(file_arg()),
+        )
+        .subcommand(
+            Command::new("delete")
+                .about("Delete a template")
+                .arg(id_arg("ID of the template to delete")),
+        )
+        .subcommand(
+            Command::new("instantiate")
+                .about("Create a proposal from a template")
+                .arg(id_arg("ID of the template to use"))
+                .arg(
+                    Arg::new("proposal-id")
+                        .long("proposal-id")
+                        .value_name("ID")
+                        .help("Unique identifier for the new proposal")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("title")
+                        .long("title")
+                        .value_name("STRING")
+                        .help("Title of the proposal (default: the template name)"),
+                )
+                .arg(
+                    Arg::new("description")
+                        .long("description")
+                        .value_name("STRING")
+                        .help("Description of the proposal"),
+                )
+                .arg(
+                    Arg::new("param")
+                        .short('P')
+                        .long("param")
+                        .value_name("KEY=VALUE")
+                        .help("Value for a template parameter (can be used multiple times)")
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("creator")
+                        .long("creator")
+                        .value_name("ID")
+                        .help("Identity ID of the proposal creator"),
+                )
+                .arg(
+                    Arg::new("label")
+                        .long("label")
+                        .value_name("LABEL")
+                        .help("Label used to categorize the proposal (can be repeated)")
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("required-participants")
+                        .long("required-participants")
+                        .value_name("NUMBER")
+                        .help(
+                            "Minimum number of participants required for the proposal to be valid",
+                        )
+                        .value_parser(value_parser!(u64)),
+                )
+                .arg(voter_eligibility_arg())
+                .arg(dry_run_arg()),
+        )
+}
+
+/// Handle template commands
+///
+/// Templates are read from and written to the VM's storage backend, in the
+/// `governance` namespace.
+pub fn handle_template_command<S>(
+    vm: &mut VM<S>,
+    matches: &ArgMatches,
+    auth_context: &AuthContext,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm
+        .get_storage_backend()
+        .ok_or("Storage backend not configured for templates")?
+        .clone();
+    let mut registry = TemplateRegistry::new(storage);
+    let auth = Some(auth_context);
+
+    match matches.subcommand() {
+        Some(("create", create_matches)) => {
+            let mut definition = match create_matches.get_one::<String>("builtin") {
+                Some(builtin) => {
+                    builtin_template(builtin).ok_or("Unknown built-in template")?
+                }
+                None => read_definition(create_matches)?,
+            };
+            if let Some(id) = create_matches.get_one::<String>("id") {
+                definition.id = id.clone();
+            }
+            let name = create_matches
+                .get_one::<String>("name")
+                .cloned()
+                .unwrap_or_else(|| definition.name.clone());
+            let id = registry.create_template(&name, &definition, author(auth_context)?, auth)?;
+            println!("✅ Template '{}' created with ID: {}", name, id);
+            Ok(())
+        }
+        Some(("list", _)) => {
+            let templates = registry.list_templates(auth)?;
+            print_output(&templates, |templates| {
+                if templates.is_empty() {
+                    println!("No templates found");
+                    return;
+                }
+                println!("📋 Templates ({}):", templates.len());
+                for template in templates {
+                    println!(
+                        "   {} — {} (v{})",
+                        template.id, template.name, template.version.version
+                    );
+                    println!(
+                        "      {:?}, quorum {:.0}%, threshold {:.0}%, {} parameter(s)",
+                        template.voting.method,
+                        template.voting.quorum * 100.0,
+                        template.voting.threshold * 100.0,
+                        template.parameters.len()
+                    );
+                }
+            })
+        }
+        Some(("show", show_matches)) => {
+            let id = show_matches
+                .get_one::<String>("id")
+                .ok_or("Template ID is required")?;
+            let template = registry.get_template(id, auth)?;
+            print_output(&template, print_template)
+        }
+        Some(("update", update_matches)) => {
+            let id = update_matches
+                .get_one::<String>("id")
+                .ok_or("Template ID is required")?;
+            let definition = read_definition(update_matches)?;
+            let template =
+                registry.update_template(id, &definition, author(auth_context)?, auth)?;
+            println!(
+                "✅ Template '{}' updated to version {}",
+                id, template.version.version
+            );
+            Ok(())
+        }
+        Some(("delete", delete_matches)) => {
+            let id = delete_matches
+                .get_one::<String>("id")
+                .ok_or("Template ID is required")?;
+            registry.delete_template(id, auth)?;
+            println!("🗑️ Template '{}' deleted", id);
+            Ok(())
+        }
+        Some(("instantiate", instantiate_matches)) => {
+            handle_instantiate_command(vm, &registry, instantiate_matches, auth_context)
+        }
+        _ => Err("Unknown template subcommand".into()),
+    }
+}
+
+/// The identity templates are attributed to
+fn author(auth_context: &AuthContext) -> Result<&Identity, Box<dyn Error>> {
+    auth_context
+        .get_identity(auth_context.identity_did())
+        .ok_or_else(|| {
+            "Templates are attributed to their author; set identity.key_path \
+             (see `icn-covm keys generate`)"
+                .into()
+        })
+}
+
+fn read_definition(matches: &ArgMatches) -> Result<Template, Box<dyn Error>> {
+    let path = matches
+        .get_one::<String>("file")
+        .ok_or("Template file is required")?;
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read template file {}: {}", path, e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Invalid template definition in {}: {}", path, e).into())
+}
+
+/// Parse `--param KEY=VALUE` arguments
+fn parse_params(matches: &ArgMatches) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    matches
+        .get_many::<String>("param")
+        .unwrap_or_default()
+        .map(|param| match param.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => Err(format!("Invalid parameter '{}', expected KEY=VALUE", param).into()),
+        })
+        .collect()
+}
+
+/// Format a period in seconds the way `--expires-in` accepts it
+fn format_period(seconds: u64) -> String {
+    if seconds % 86400 == 0 {
+        format!("{}d", seconds / 86400)
+    } else if seconds % 3600 == 0 {
+        format!("{}h", seconds / 3600)
+    } else {
+        format!("{}m", seconds / 60)
+    }
+}
+
+fn format_timestamp(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn print_template(template: &Template) {
+    println!("📋 Template {}: {}", template.id, template.name);
+    println!(
+        "   Version: {} by {} ({})",
+        template.version.version,
+        template.version.author,
+        format_timestamp(template.version.created_at)
+    );
+    println!(
+        "   Voting: {:?}, quorum {:.0}%, threshold {:.0}%",
+        template.voting.method,
+        template.voting.quorum * 100.0,
+        template.voting.threshold * 100.0
+    );
+    for class in &template.voting.role_classes {
+        println!(
+            "   Role class {}: quorum {:.0}%, threshold {:.0}%",
+            class.role,
+            class.quorum * 100.0,
+            class.threshold * 100.0
+        );
+    }
+    println!(
+        "   Deliberation: {}, voting: {}",
+        format_period(template.voting.deliberation_period),
+        format_period(template.voting.voting_period)
+    );
+    if let Some(role) = &template.eligibility.required_role {
+        println!("   Required role: {}", role);
+    }
+    if let Some(reputation) = template.eligibility.minimum_reputation {
+        println!("   Minimum reputation: {}", reputation);
+    }
+
+    let mut parameters: Vec<_> = template.parameters.values().collect();
+    parameters.sort_by(|a, b| a.name.cmp(&b.name));
+    println!("   Parameters ({}):", parameters.len());
+    for param in parameters {
+        let requirement = match (&param.default_value, param.required) {
+            (Some(default), _) => format!("default {}", default),
+            (None, true) => "required".to_string(),
+            (None, false) => "optional".to_string(),
+        };
+        println!(
+            "      {} ({:?}, {}): {}",
+            param.name, param.param_type, requirement, param.description
+        );
+    }
+
+    println!("   On approve:");
+    for line in &template.execution.on_approve {
+        println!("      {}", line);
+    }
+    if let Some(on_reject) = &template.execution.on_reject {
+        println!("   On reject:");
+        for line in on_reject {
+            println!("      {}", line);
+        }
+    }
+    if let Some(delay) = template.execution.execution_delay {
+        println!("   Execution delay: {}", format_period(delay));
+    }
+
+    if !template.previous_versions.is_empty() {
+        println!("   History:");
+        for version in template.previous_versions.iter().rev() {
+            println!(
+                "      {} by {} ({}): {}",
+                version.version,
+                version.author,
+                format_timestamp(version.created_at),
+                version.description
+            );
+        }
+    }
+}
+
+/// Create a proposal from a template
+///
+/// The proposal takes its quorum, threshold and deliberation period from
+/// the template, expires when the template's voting period ends, and stores
+/// the template's approval logic with the parameters filled in.
+fn handle_instantiate_command<S>(
+    vm: &mut VM<S>,
+    registry: &TemplateRegistry<S>,
+    matches: &ArgMatches,
+    auth_context: &AuthContext,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    let template_id = matches
+        .get_one::<String>("id")
+        .ok_or("Template ID is required")?;
+    let proposal_id = matches
+        .get_one::<String>("proposal-id")
+        .ok_or("Proposal ID is required")?;
+
+    let template = registry.get_template(template_id, Some(auth_context))?;
+    let values = template.resolve_parameters(&parse_params(matches)?)?;
+    let logic = template.render_logic(&values);
+    let (ops, _) = parse_dsl(&logic).map_err(|e| {
+        format!(
+            "Logic of template '{}' does not compile: {}",
+            template.id, e
+        )
+    })?;
+
+    let deliberation = template.voting.deliberation_period;
+    let draft = ProposalDraft {
+        id: proposal_id.clone(),
+        title: matches
+            .get_one::<String>("title")
+            .cloned()
+            .unwrap_or_else(|| template.name.clone()),
+        description: matches
+            .get_one::<String>("description")
+            .cloned()
+            .unwrap_or_else(|| format!("Created from template {}", template.id)),
+        template: Some(template.id.clone()),
+        quorum: template.voting.quorum,
+        threshold: template.voting.threshold,
+        role_classes: template.voting.role_classes.clone(),
+        min_deliberation: Duration::seconds(deliberation as i64),
+        expires_in: Duration::seconds((deliberation + template.voting.voting_period) as i64),
+        execution_delay: template.execution.execution_delay,
+        logic_path: None,
+        logic,
+        ops,
+    };
+    create_from_draft(vm, draft, matches, auth_context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_params() {
+        let matches = template_command().get_matches_from([
+            "template",
+            "instantiate",
+            "--id",
+            "budget",
+            "--proposal-id",
+            "q3",
+            "-P",
+            "amount=250",
+            "--param",
+            "note=a=b",
+        ]);
+        let (_, instantiate) = matches.subcommand().unwrap();
+        let params = parse_params(instantiate).unwrap();
+        assert_eq!(params["amount"], "250");
+        assert_eq!(params["note"], "a=b");
+
+        let matches = template_command().get_matches_from([
+            "template",
+            "instantiate",
+            "--id",
+            "budget",
+            "--proposal-id",
+            "q3",
+            "-P",
+            "amount",
+        ]);
+        let (_, instantiate) = matches.subcommand().unwrap();
+        assert!(parse_params(instantiate).is_err());
+        assert_eq!(format_period(86400 * 7), "7d");
+        assert_eq!(format_period(5400), "90m");
+    }
+}
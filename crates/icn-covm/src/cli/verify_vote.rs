@@ -0,0 +1,222 @@
+//! Offline verification of federated votes
+//!
+//! `verify-vote` lets a scrutineer check a vote exported as JSON against
+//! the voter's public key without a node, storage, or network: the signed
+//! message must be the canonical construction of the vote's fields and
+//! the signature must verify with the key.
+
+use crate::cli::output::print_output;
+use crate::federation::messages::FederatedVote;
+use crate::identity::{self, IdentityError};
+use clap::{Arg, ArgMatches, Command};
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Create the verify-vote command
+pub fn verify_vote_command() -> Command {
+    Command::new("verify-vote")
+        .about("Check a federated vote's canonical message and signature offline")
+        .arg(
+            Arg::new("file")
+                .long("file")
+                .short('f')
+                .value_name("FILE")
+                .help("Vote as JSON (proposal_id, voter, ranked_choices, message, signature)")
+                .required(true),
+        )
+        .arg(
+            Arg::new("key")
+                .long("key")
+                .short('k')
+                .value_name("FILE")
+                .help("Voter's Ed25519 public key, as PEM or a multibase string")
+                .required(true),
+        )
+}
+
+/// Result of checking one vote
+#[derive(Debug, Serialize)]
+pub struct VoteCheck {
+    pub proposal_id: String,
+    pub voter: String,
+    /// DID derived from the supplied key
+    pub key_did: String,
+    /// The message the vote should have signed
+    pub expected_message: String,
+    /// The vote's message is the canonical construction of its fields
+    pub canonical_message: bool,
+    /// The signature verifies over the vote's message with the key
+    pub signature_valid: bool,
+    /// The key is the voter's, when the voter is a did:key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_matches_voter: Option<bool>,
+    pub issues: Vec<String>,
+}
+
+impl VoteCheck {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Check a vote against the voter's public key
+pub fn check_vote(vote: &FederatedVote, public_key: &[u8]) -> VoteCheck {
+    let mut issues = Vec::new();
+
+    let expected_message =
+        FederatedVote::canonical_message(&vote.proposal_id, &vote.voter, &vote.ranked_choices);
+    let canonical_message = vote.message == expected_message;
+    if !canonical_message {
+        issues.push(format!(
+            "Signed message {:?} is not the canonical message for this vote",
+            vote.message
+        ));
+    }
+
+    let key_multibase = multibase::encode(multibase::Base::Base58Btc, public_key);
+    let signature_valid = match identity::verify_signature(
+        &key_multibase,
+        vote.message.as_bytes(),
+        &vote.signature,
+    ) {
+        Ok(()) => true,
+        Err(e) => {
+            issues.push(format!("Signature does not verify: {}", e));
+            false
+        }
+    };
+
+    let key_did = format!("did:key:{}", key_multibase);
+    let key_matches_voter = vote
+        .voter
+        .starts_with("did:key:")
+        .then(|| vote.voter == key_did);
+    if key_matches_voter == Some(false) {
+        issues.push(format!("Key belongs to {}, not the voter", key_did));
+    }
+
+    VoteCheck {
+        proposal_id: vote.proposal_id.clone(),
+        voter: vote.voter.clone(),
+        key_did,
+        expected_message,
+        canonical_message,
+        signature_valid,
+        key_matches_voter,
+        issues,
+    }
+}
+
+/// Read a public key file holding a PEM block or a multibase key
+pub fn read_public_key(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read key {}: {}", path.display(), e))?;
+    let content = content.trim();
+
+    let key = if content.starts_with("-----BEGIN") {
+        identity::public_key_from_pem(content)?
+    } else {
+        let (_, key) = multibase::decode(content)
+            .map_err(|e| IdentityError::MultibaseError(format!("Invalid public key: {}", e)))?;
+        key
+    };
+    if key.len() != 32 {
+        return Err(format!("{} does not hold an Ed25519 public key", path.display()).into());
+    }
+    Ok(key)
+}
+
+/// Handle `verify-vote`
+///
+/// Fails when any check fails so scripts can count rejected votes.
+pub fn handle_verify_vote_command(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let file = Path::new(
+        matches
+            .get_one::<String>("file")
+            .ok_or("--file is required")?,
+    );
+    let key = Path::new(
+        matches
+            .get_one::<String>("key")
+            .ok_or("--key is required")?,
+    );
+
+    let content = fs::read_to_string(file)
+        .map_err(|e| format!("Failed to read vote {}: {}", file.display(), e))?;
+    let vote: FederatedVote = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid vote {}: {}", file.display(), e))?;
+    let public_key = read_public_key(key)?;
+
+    let check = check_vote(&vote, &public_key);
+    print_output(&check, print_check)?;
+
+    if check.is_valid() {
+        Ok(())
+    } else {
+        Err(format!("Vote failed {} check(s)", check.issues.len()).into())
+    }
+}
+
+fn print_check(check: &VoteCheck) {
+    let mark = |ok: bool| if ok { "✅" } else { "❌" };
+    println!("🗳️  Vote on proposal {}", check.proposal_id);
+    println!("   Voter: {}", check.voter);
+    println!("   Key: {}", check.key_did);
+    println!(
+        "   {} Canonical message: {}",
+        mark(check.canonical_message),
+        check.expected_message
+    );
+    println!("   {} Signature", mark(check.signature_valid));
+    if let Some(matches) = check.key_matches_voter {
+        println!("   {} Key matches voter DID", mark(matches));
+    }
+    if check.is_valid() {
+        return;
+    }
+
+    println!("\n⚠️  Issues:");
+    for issue in &check.issues {
+        println!("   {}", issue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+
+    #[test]
+    fn test_check_vote() {
+        let voter = Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        let ranked_choices = vec![1.0, 0.0];
+        let message = FederatedVote::canonical_message("prop-1", voter.did(), &ranked_choices);
+        let mut vote = FederatedVote {
+            proposal_id: "prop-1".to_string(),
+            voter: voter.did().to_string(),
+            ranked_choices,
+            signature: voter.sign(message.as_bytes()).unwrap(),
+            message,
+        };
+
+        let pem = identity::public_key_to_pem(voter.public_key().unwrap());
+        let public_key = identity::public_key_from_pem(&pem).unwrap();
+        let check = check_vote(&vote, &public_key);
+        assert!(check.is_valid(), "{:?}", check.issues);
+        assert_eq!(check.key_matches_voter, Some(true));
+
+        // Signed, but the choices no longer match what was signed
+        vote.ranked_choices = vec![0.0, 1.0];
+        let check = check_vote(&vote, &public_key);
+        assert!(!check.canonical_message);
+        assert!(check.signature_valid);
+
+        // Another member's key
+        let other = Identity::new("bob".to_string(), None, "member".to_string(), None).unwrap();
+        let check = check_vote(&vote, other.public_key().unwrap());
+        assert!(!check.signature_valid);
+        assert_eq!(check.key_matches_voter, Some(false));
+    }
+}
@@ -0,0 +1,109 @@
+//! Time as governance sees it
+//!
+//! Deliberation windows, expiry and timelocks all compare against "now".
+//! Code that makes those checks asks a [`Clock`] instead of calling
+//! `Utc::now()`, so tests can put a [`MockClock`] in its place and move time
+//! forward exactly as far as they need:
+//!
+//! ```
+//! use chrono::{Duration, TimeZone, Utc};
+//! use icn_covm::clock::{Clock, MockClock};
+//!
+//! let clock = MockClock::new(Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap());
+//! let deadline = clock.now() + Duration::days(7);
+//! clock.advance(Duration::days(7) + Duration::seconds(1));
+//! assert!(clock.now() > deadline);
+//! ```
+//!
+//! The VM carries a [`SharedClock`] (see `VM::set_clock`), which the `now`
+//! operation, event timestamps and proposal lifecycle checks read.
+
+use chrono::{DateTime, Duration, Utc};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+/// A source of the current time
+pub trait Clock: Debug + Send + Sync {
+    /// The current time
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The current time as seconds since the Unix epoch, as storage and
+    /// events record it
+    fn unix_seconds(&self) -> u64 {
+        self.now().timestamp().max(0) as u64
+    }
+}
+
+/// A clock shared between a VM, its forks and whatever drives them
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system's wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// The clock used unless another is set
+pub fn system() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when told to
+///
+/// Clones share the same time, so a test can keep one and hand another to
+/// the VM.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Jump to `now`, which may be earlier than the current time
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    /// Move the time forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_mock_clock_is_shared_between_clones() {
+        let start = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let shared: SharedClock = Arc::new(clock.clone());
+
+        clock.advance(Duration::hours(48));
+        assert_eq!(shared.now(), start + Duration::hours(48));
+        assert_eq!(
+            shared.unix_seconds(),
+            (start + Duration::hours(48)).timestamp() as u64
+        );
+
+        clock.set(start);
+        assert_eq!(shared.now(), start);
+    }
+}
@@ -24,6 +24,33 @@ pub fn extract_quoted_text(line: &str) -> Option<(String, String)> {
     }
 }
 
+/// Parse a double-quoted string literal that makes up all of `text`
+///
+/// The literal may contain spaces and the escapes `\"`, `\\`, `\n` and `\t`.
+/// Returns `None` if it is unterminated, uses another escape or is followed
+/// by more text than a `#` comment.
+pub fn parse_string_literal(text: &str) -> Option<String> {
+    let mut chars = text.strip_prefix('"')?.chars();
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let rest = chars.as_str().trim_start();
+                return (rest.is_empty() || rest.starts_with('#')).then_some(value);
+            }
+            '\\' => value.push(match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                'n' => '\n',
+                't' => '\t',
+                _ => return None,
+            }),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
 /// Create a source position with column adjusted for specific part of line
 pub fn adjusted_position(pos: SourcePosition, line: &str, part: &str) -> SourcePosition {
     if let Some(idx) = line.find(part) {
@@ -0,0 +1,235 @@
+//! Collect every problem in a DSL program instead of stopping at the first
+//!
+//! The parser returns on its first error. `validate_dsl` reports that error,
+//! blanks out the offending statement, and parses again, until the program
+//! parses or no position is left to skip. A program that parses is then
+//! linted for mistakes the parser accepts, which are reported as warnings.
+
+use super::common::get_indent;
+use super::parse_dsl::{parse_dsl, LifecycleConfig};
+use super::stdlib::get_stdlib_code;
+use super::CompilerError;
+use crate::vm::Op;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Most diagnostics reported for one program
+const MAX_DIAGNOSTICS: usize = 100;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The program does not compile
+    Error,
+    /// The program compiles but probably does not do what was meant
+    Warning,
+}
+
+/// A problem found in DSL source
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Line number (1-indexed)
+    pub line: usize,
+    /// Column number (1-indexed)
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(err: &CompilerError) -> Self {
+        let pos = err.position();
+        Self {
+            severity: Severity::Error,
+            line: pos.map_or(1, |p| p.line),
+            column: pos.map_or(1, |p| p.column),
+            message: err.to_string(),
+        }
+    }
+
+    fn warning(line: usize, column: usize, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+/// Check DSL source, returning every error and warning found, in line order
+///
+/// An empty result means the program compiles cleanly.
+pub fn validate_dsl(source: &str) -> Vec<Diagnostic> {
+    let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+    let mut diagnostics = Vec::new();
+
+    loop {
+        let err = match parse_dsl(&lines.join("\n")) {
+            Ok((ops, config)) => {
+                if diagnostics.is_empty() {
+                    diagnostics.extend(lint(source, &ops, &config));
+                }
+                break;
+            }
+            Err(err) => err,
+        };
+        diagnostics.push(Diagnostic::error(&err));
+        if diagnostics.len() >= MAX_DIAGNOSTICS {
+            break;
+        }
+        // Without a fresh line to skip, parsing again would find the same error
+        let Some(index) = err
+            .position()
+            .map(|p| p.line.saturating_sub(1))
+            .filter(|&i| lines.get(i).is_some_and(|l| !l.trim().is_empty()))
+        else {
+            break;
+        };
+        skip_statement(&mut lines, index);
+    }
+
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+/// Blank out the statement at `index`
+///
+/// A braced block is skipped up to its closing brace, since its directives
+/// mean nothing outside it. The body of an indented block is kept and checked
+/// as top-level statements.
+fn skip_statement(lines: &mut [String], index: usize) {
+    let braced = lines[index].trim_end().ends_with('{');
+    lines[index].clear();
+    if !braced {
+        return;
+    }
+    for line in lines.iter_mut().skip(index + 1) {
+        let closed = line.trim() == "}";
+        line.clear();
+        if closed {
+            break;
+        }
+    }
+}
+
+/// Warnings for a program that parses
+fn lint(source: &str, ops: &[Op], config: &LifecycleConfig) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    let lines: Vec<&str> = source.lines().collect();
+    // First line whose first two words are `keyword` and `arg`
+    let find = |keyword: &str, arg: Option<&str>| {
+        lines
+            .iter()
+            .enumerate()
+            .find(|(_, line)| {
+                let mut words = line.split_whitespace();
+                words.next() == Some(keyword) && arg.is_none_or(|arg| words.next() == Some(arg))
+            })
+            .map_or((1, 1), |(i, line)| (i + 1, get_indent(line) + 1))
+    };
+
+    for (keyword, value) in [
+        ("quorumthreshold", config.quorum),
+        ("votethreshold", config.threshold),
+    ] {
+        if let Some(value) = value.filter(|v| !(0.0..=1.0).contains(v)) {
+            let (line, column) = find(keyword, None);
+            warnings.push(Diagnostic::warning(
+                line,
+                column,
+                format!("{} {} is outside 0.0 to 1.0", keyword, value),
+            ));
+        }
+    }
+
+    let mut defined = HashSet::new();
+    let mut called = Vec::new();
+    collect_functions(ops, &mut defined, &mut called);
+    if let Ok((stdlib, _)) = parse_dsl(&get_stdlib_code()) {
+        collect_functions(&stdlib, &mut defined, &mut Vec::new());
+    }
+    let mut reported = HashSet::new();
+    for name in called {
+        if !defined.contains(&name) && reported.insert(name.clone()) {
+            let (line, column) = find("call", Some(&name));
+            warnings.push(Diagnostic::warning(
+                line,
+                column,
+                format!("Call to undefined function '{}'", name),
+            ));
+        }
+    }
+
+    warnings
+}
+
+/// Record the functions defined and called anywhere in `ops`
+fn collect_functions(ops: &[Op], defined: &mut HashSet<String>, called: &mut Vec<String>) {
+    for op in ops {
+        match op {
+            Op::Def { name, body, .. } => {
+                defined.insert(name.clone());
+                collect_functions(body, defined, called);
+            }
+            Op::Call(name) => called.push(name.clone()),
+            Op::If {
+                condition,
+                then,
+                else_,
+            } => {
+                collect_functions(condition, defined, called);
+                collect_functions(then, defined, called);
+                if let Some(else_) = else_ {
+                    collect_functions(else_, defined, called);
+                }
+            }
+            Op::Loop { body, .. } | Op::IfPassed(body) | Op::Else(body) => {
+                collect_functions(body, defined, called)
+            }
+            Op::While { condition, body } => {
+                collect_functions(condition, defined, called);
+                collect_functions(body, defined, called);
+            }
+            Op::Match {
+                value,
+                cases,
+                default,
+            } => {
+                collect_functions(value, defined, called);
+                for (_, case) in cases {
+                    collect_functions(case, defined, called);
+                }
+                if let Some(default) = default {
+                    collect_functions(default, defined, called);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_reports_every_error() {
+        let source = "push 1\npush\nfrobnicate\npush 2\nemit missing quotes\n";
+        let diagnostics = validate_dsl(source);
+        let lines: Vec<usize> = diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, vec![2, 3, 5]);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_validate_warns_about_undefined_calls() {
+        assert!(validate_dsl("push 1\ncall abs\n").is_empty());
+
+        let diagnostics = validate_dsl("push 1\ncall nowhere\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 1));
+    }
+}
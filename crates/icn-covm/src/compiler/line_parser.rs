@@ -1,7 +1,15 @@
 use super::{common, macros::ProposalLifecycleMacro, CompilerError, SourcePosition};
+use crate::events::Severity;
+use crate::storage::resource::{
+    BountyVerification, DemurragePolicy, IssuancePolicy, ResourcePolicy, SpendingAction,
+    SpendingWindow,
+};
 use crate::typed::TypedValue;
+use crate::vm::random::MAX_RANGE;
+use crate::vm::wasm::WasmCapability;
 use crate::vm::Op;
 use chrono;
+use rust_decimal::Decimal;
 
 /// Parse a single line of DSL code
 pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError> {
@@ -23,16 +31,29 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
                 .ok_or(CompilerError::MissingPushValue(pos.line, pos.column))?;
 
             // Try to parse as different types
-            let value = if val_str == "true" {
+            let value = if val_str.starts_with('"') {
+                // String literal, which may contain spaces
+                let literal = line.trim_start()[command.len()..].trim();
+                let text = common::parse_string_literal(literal).ok_or_else(|| {
+                    CompilerError::InvalidPushValue(
+                        literal.to_string(),
+                        pos.line,
+                        common::adjusted_position(pos, line, val_str).column,
+                    )
+                })?;
+                TypedValue::String(text)
+            } else if val_str == "true" {
                 TypedValue::Boolean(true)
             } else if val_str == "false" {
                 TypedValue::Boolean(false)
             } else if val_str == "null" {
                 TypedValue::Null
-            } else if val_str.starts_with('"') && val_str.ends_with('"') {
-                // String literal (strip quotes)
-                let str_content = &val_str[1..val_str.len() - 1];
-                TypedValue::String(str_content.to_string())
+            } else if let Some(bytes) = val_str
+                .strip_prefix("0x")
+                .and_then(|digits| hex::decode(digits).ok())
+            {
+                // Bytes literal
+                TypedValue::Bytes(bytes)
             } else {
                 // Try to parse as number
                 match val_str.parse::<f64>() {
@@ -47,6 +68,8 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
 
             Ok(Op::Push(value))
         }
+        // Without a message, emit the value on top of the stack
+        "emit" if parts.clone().next().is_none() => Ok(Op::EmitTop),
         "emit" => {
             if let Some(inner) = line.find('"') {
                 let inner = &line[inner + 1..line.rfind('"').unwrap_or(line.len())];
@@ -56,7 +79,7 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
             }
         }
         "emitevent" => {
-            // Format: emitevent "category" "message"
+            // Format: emitevent "category" "message" [debug|info|warn|error]
             let line_str = line.to_string();
             let parts: Vec<&str> = line_str.split('"').collect();
             if parts.len() < 5 {
@@ -65,8 +88,18 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
 
             let category = parts[1].trim().to_string();
             let message = parts[3].trim().to_string();
+            let severity = match parts[4].trim() {
+                "" => Severity::Info,
+                severity => severity
+                    .parse()
+                    .map_err(|_| CompilerError::InvalidEmitEventFormat(pos.line, pos.column))?,
+            };
 
-            Ok(Op::EmitEvent { category, message })
+            Ok(Op::EmitEvent {
+                category,
+                message,
+                severity,
+            })
         }
         "assertequalstack" => {
             let depth_str = parts
@@ -116,6 +149,46 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
         "and" => Ok(Op::And),
         "or" => Ok(Op::Or),
         "negate" => Ok(Op::Negate),
+        "hex_encode" => Ok(Op::HexEncode),
+        "hex_decode" => Ok(Op::HexDecode),
+        "base64_encode" => Ok(Op::Base64Encode),
+        "base64_decode" => Ok(Op::Base64Decode),
+        "length" => Ok(Op::Length),
+        "concat" => Ok(Op::Concat),
+        "is_null" => Ok(Op::IsNull),
+        "coalesce" => Ok(Op::Coalesce),
+        "now" => Ok(Op::Now),
+        "random" => {
+            let max_str = parts.next().ok_or(CompilerError::MissingParameter(
+                "random".to_string(),
+                pos.line,
+                pos.column,
+            ))?;
+            let max = max_str
+                .parse::<u64>()
+                .ok()
+                .filter(|max| (1..=MAX_RANGE).contains(max))
+                .ok_or_else(|| {
+                    CompilerError::InvalidParameterValue(
+                        "random".to_string(),
+                        pos.line,
+                        common::adjusted_position(pos, line, max_str).column,
+                    )
+                })?;
+            Ok(Op::Random { max })
+        }
+        "parse_time" => Ok(Op::ParseTime),
+        "format_time" => Ok(Op::FormatTime),
+        "typing" => {
+            let mode = parts.next().ok_or(CompilerError::MissingParameter(
+                "typing".to_string(),
+                pos.line,
+                pos.column,
+            ))?;
+            mode.parse().map(Op::Typing).map_err(|_| {
+                CompilerError::InvalidParameterValue("typing".to_string(), pos.line, pos.column)
+            })
+        }
         "dup" => Ok(Op::Dup),
         "swap" => Ok(Op::Swap),
         "over" => Ok(Op::Over),
@@ -163,7 +236,8 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
         "dumpmemory" => Ok(Op::DumpMemory),
         "dumpstate" => Ok(Op::DumpState), // Debug/introspection opcode
         "rankedvote" => {
-            // Parse rankedvote command with required parameters: candidates and ballots
+            // Parse rankedvote command with required parameters: candidates and ballots,
+            // then an optional `delegated` flag
             let candidates_str = parts.next().ok_or(CompilerError::InvalidFunctionFormat(
                 "rankedvote requires 'candidates' parameter".to_string(),
                 pos.line,
@@ -194,10 +268,46 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
                 )
             })?;
 
+            let delegated = match parts.next() {
+                None => false,
+                Some("delegated") => true,
+                Some(other) => {
+                    return Err(CompilerError::InvalidFunctionFormat(
+                        format!("Unknown rankedvote option: {}", other),
+                        pos.line,
+                        pos.column,
+                    ))
+                }
+            };
+
             // Create RankedVote operation
             Ok(Op::RankedVote {
                 candidates,
                 ballots,
+                delegated,
+            })
+        }
+        "quadraticvote" => {
+            // Same parameters as rankedvote: candidates and ballots
+            let mut count = |name: &str| {
+                let value = parts.next().ok_or(CompilerError::InvalidFunctionFormat(
+                    format!("quadraticvote requires '{}' parameter", name),
+                    pos.line,
+                    pos.column,
+                ))?;
+                value.parse::<usize>().map_err(|_| {
+                    CompilerError::InvalidFunctionFormat(
+                        format!("Invalid {} count: {}", name, value),
+                        pos.line,
+                        pos.column,
+                    )
+                })
+            };
+            let candidates = count("candidates")?;
+            let ballots = count("ballots")?;
+            Ok(Op::QuadraticVote {
+                candidates,
+                ballots,
             })
         }
         "liquiddelegate" => {
@@ -447,7 +557,10 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
                 pos.line,
                 pos.column,
             ))?;
-            Ok(Op::CreateResource(resource_id.to_string()))
+            Ok(Op::CreateResource {
+                resource: resource_id.to_string(),
+                policy: parse_resource_policy(parts, pos)?,
+            })
         }
         "mint" => {
             let resource = parts.next().ok_or(CompilerError::MissingVariable(
@@ -468,7 +581,7 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
                 pos.column,
             ))?;
 
-            let amount = amount_str.parse::<f64>().map_err(|_| {
+            let amount = amount_str.parse::<Decimal>().map_err(|_| {
                 CompilerError::InvalidFunctionFormat(
                     format!("Invalid mint amount: {}", amount_str),
                     pos.line,
@@ -516,7 +629,7 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
                 pos.column,
             ))?;
 
-            let amount = amount_str.parse::<f64>().map_err(|_| {
+            let amount = amount_str.parse::<Decimal>().map_err(|_| {
                 CompilerError::InvalidFunctionFormat(
                     format!("Invalid transfer amount: {}", amount_str),
                     pos.line,
@@ -559,7 +672,7 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
                 pos.column,
             ))?;
 
-            let amount = amount_str.parse::<f64>().map_err(|_| {
+            let amount = amount_str.parse::<Decimal>().map_err(|_| {
                 CompilerError::InvalidFunctionFormat(
                     format!("Invalid burn amount: {}", amount_str),
                     pos.line,
@@ -600,6 +713,470 @@ pub fn parse_line(line: &str, pos: SourcePosition) -> Result<Op, CompilerError>
                 account: account.to_string(),
             })
         }
+        "setcreditlimit" => {
+            // Format: setcreditlimit <resource> <creditor> <debtor> <limit>
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("setcreditlimit ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let resource = next("resource")?.to_string();
+            let creditor = next("creditor")?.to_string();
+            let debtor = next("debtor")?.to_string();
+            let limit_str = next("limit")?;
+            let limit = limit_str.parse::<Decimal>().map_err(|_| {
+                CompilerError::InvalidFunctionFormat(
+                    format!("Invalid setcreditlimit limit: {}", limit_str),
+                    pos.line,
+                    pos.column,
+                )
+            })?;
+
+            Ok(Op::SetCreditLimit {
+                resource,
+                creditor,
+                debtor,
+                limit,
+            })
+        }
+        "creditlimit" => {
+            let resource = parts.next().ok_or(CompilerError::MissingVariable(
+                "creditlimit (resource)".to_string(),
+                pos.line,
+                pos.column,
+            ))?;
+
+            let account = parts.next().ok_or(CompilerError::MissingVariable(
+                "creditlimit (account)".to_string(),
+                pos.line,
+                pos.column,
+            ))?;
+
+            Ok(Op::CreditLimit {
+                resource: resource.to_string(),
+                account: account.to_string(),
+            })
+        }
+        "approve" => {
+            // Format: approve <resource> <spender> <limit>
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("approve ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let resource = next("resource")?.to_string();
+            let spender = next("spender")?.to_string();
+            let limit_str = next("limit")?;
+            let limit = limit_str.parse::<Decimal>().map_err(|_| {
+                CompilerError::InvalidFunctionFormat(
+                    format!("Invalid approve limit: {}", limit_str),
+                    pos.line,
+                    pos.column,
+                )
+            })?;
+
+            Ok(Op::Approve {
+                resource,
+                spender,
+                limit,
+            })
+        }
+        "allowance" => {
+            // Format: allowance <resource> <owner> <spender>
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("allowance ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let resource = next("resource")?.to_string();
+            let owner = next("owner")?.to_string();
+            let spender = next("spender")?.to_string();
+
+            Ok(Op::Allowance {
+                resource,
+                owner,
+                spender,
+            })
+        }
+        "setexchangerate" => {
+            // Format: setexchangerate <from_resource> <to_resource> <rate> <reserve> [max_amount]
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("setexchangerate ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let from_resource = next("from_resource")?.to_string();
+            let to_resource = next("to_resource")?.to_string();
+            let rate_str = next("rate")?;
+            let reserve = next("reserve")?.to_string();
+            let decimal = |what: &str, value: &str| {
+                value.parse::<Decimal>().map_err(|_| {
+                    CompilerError::InvalidFunctionFormat(
+                        format!("Invalid setexchangerate {}: {}", what, value),
+                        pos.line,
+                        pos.column,
+                    )
+                })
+            };
+            let rate = decimal("rate", rate_str)?;
+            let max_amount = parts
+                .next()
+                .map(|max| decimal("max_amount", max))
+                .transpose()?;
+
+            Ok(Op::SetExchangeRate {
+                from_resource,
+                to_resource,
+                rate,
+                reserve,
+                max_amount,
+            })
+        }
+        "exchange" => {
+            // Format: exchange <from_resource> <to_resource> <account> <amount> <min_received>
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("exchange ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let from_resource = next("from_resource")?.to_string();
+            let to_resource = next("to_resource")?.to_string();
+            let account = next("account")?.to_string();
+            let amount_str = next("amount")?;
+            let min_received_str = next("min_received")?;
+            let decimal = |what: &str, value: &str| {
+                value.parse::<Decimal>().map_err(|_| {
+                    CompilerError::InvalidFunctionFormat(
+                        format!("Invalid exchange {}: {}", what, value),
+                        pos.line,
+                        pos.column,
+                    )
+                })
+            };
+
+            Ok(Op::Exchange {
+                from_resource,
+                to_resource,
+                account,
+                amount: decimal("amount", amount_str)?,
+                min_received: decimal("min_received", min_received_str)?,
+            })
+        }
+        "exchangerate" => {
+            // Format: exchangerate <from_resource> <to_resource>
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("exchangerate ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let from_resource = next("from_resource")?.to_string();
+            let to_resource = next("to_resource")?.to_string();
+
+            Ok(Op::ExchangeRate {
+                from_resource,
+                to_resource,
+            })
+        }
+        "approvewasmmodule" => {
+            // Format: approvewasmmodule <hash> [capability ...]
+            let hash = parts
+                .next()
+                .ok_or(CompilerError::MissingVariable(
+                    "approvewasmmodule (hash)".to_string(),
+                    pos.line,
+                    pos.column,
+                ))?
+                .to_string();
+            let capabilities = parts
+                .by_ref()
+                .map(|capability| {
+                    capability.parse::<WasmCapability>().map_err(|_| {
+                        CompilerError::InvalidParameterValue(
+                            format!("approvewasmmodule {}", capability),
+                            pos.line,
+                            pos.column,
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(Op::ApproveWasmModule { hash, capabilities })
+        }
+        "callwasm" => {
+            // Format: callwasm <hash> <function> [args]
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("callwasm ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let hash = next("hash")?.to_string();
+            let function = next("function")?.to_string();
+            let args = match parts.next() {
+                Some(args_str) => args_str.parse::<usize>().map_err(|_| {
+                    CompilerError::InvalidFunctionFormat(
+                        format!("Invalid callwasm argument count: {}", args_str),
+                        pos.line,
+                        pos.column,
+                    )
+                })?,
+                None => 0,
+            };
+
+            Ok(Op::CallWasm {
+                hash,
+                function,
+                args,
+            })
+        }
+        "onboardmember" => {
+            // Format: onboardmember <did> <username> [roles...]
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("onboardmember ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let did = next("did")?.to_string();
+            let username = next("username")?.to_string();
+            let roles = parts.by_ref().map(str::to_string).collect();
+
+            Ok(Op::OnboardMember {
+                did,
+                username,
+                roles,
+            })
+        }
+        "offboardmember" => {
+            // Format: offboardmember <did>
+            let did = parts.next().ok_or(CompilerError::MissingVariable(
+                "offboardmember (did)".to_string(),
+                pos.line,
+                pos.column,
+            ))?;
+            Ok(Op::OffboardMember(did.to_string()))
+        }
+        "setspendinglimit" => {
+            // Format: setspendinglimit <resource> <subject> transfer|mint day|week <limit>
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("setspendinglimit ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let resource = next("resource")?.to_string();
+            let subject = next("subject")?.to_string();
+            let action_str = next("action")?;
+            let window_str = next("window")?;
+            let limit_str = next("limit")?;
+            let invalid = |value: &str| {
+                CompilerError::InvalidParameterValue(
+                    format!("setspendinglimit {}", value),
+                    pos.line,
+                    pos.column,
+                )
+            };
+            let action = match action_str {
+                "transfer" => SpendingAction::Transfer,
+                "mint" => SpendingAction::Mint,
+                _ => return Err(invalid(action_str)),
+            };
+            let window = match window_str {
+                "day" => SpendingWindow::Day,
+                "week" => SpendingWindow::Week,
+                _ => return Err(invalid(window_str)),
+            };
+            let limit = limit_str.parse::<Decimal>().map_err(|_| {
+                CompilerError::InvalidFunctionFormat(
+                    format!("Invalid setspendinglimit limit: {}", limit_str),
+                    pos.line,
+                    pos.column,
+                )
+            })?;
+
+            Ok(Op::SetSpendingLimit {
+                resource,
+                subject,
+                action,
+                window,
+                limit,
+            })
+        }
+        "escrowlock" => {
+            // Format: escrowlock <proposal_id> <resource> <from> <to> <amount>
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("escrowlock ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let proposal_id = next("proposal_id")?.trim_matches('"').to_string();
+            let resource = next("resource")?.to_string();
+            let from = next("from")?.to_string();
+            let to = next("to")?.to_string();
+            let amount_str = next("amount")?;
+            let amount = amount_str.parse::<Decimal>().map_err(|_| {
+                CompilerError::InvalidFunctionFormat(
+                    format!("Invalid escrowlock amount: {}", amount_str),
+                    pos.line,
+                    pos.column,
+                )
+            })?;
+
+            Ok(Op::EscrowLock {
+                proposal_id,
+                resource,
+                from,
+                to,
+                amount,
+            })
+        }
+        "escrowrelease" | "escrowrefund" => {
+            let proposal_id = parts
+                .next()
+                .ok_or(CompilerError::MissingProposalId(pos.line, pos.column))?
+                .trim_matches('"')
+                .to_string();
+            Ok(if command == "escrowrelease" {
+                Op::EscrowRelease(proposal_id)
+            } else {
+                Op::EscrowRefund(proposal_id)
+            })
+        }
+        "createbounty" => {
+            // Format: createbounty <id> <resource> <funder> <amount> verifier:<role>|votes:<n> ["description"]
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("createbounty ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let id = next("id")?.trim_matches('"').to_string();
+            let resource = next("resource")?.to_string();
+            let funder = next("funder")?.to_string();
+            let amount_str = next("amount")?;
+            let amount = amount_str.parse::<Decimal>().map_err(|_| {
+                CompilerError::InvalidFunctionFormat(
+                    format!("Invalid createbounty amount: {}", amount_str),
+                    pos.line,
+                    pos.column,
+                )
+            })?;
+            let verification_str = next("verification")?;
+            let invalid = || {
+                CompilerError::InvalidParameterValue(
+                    format!("createbounty {}", verification_str),
+                    pos.line,
+                    pos.column,
+                )
+            };
+            let verification = match verification_str.split_once(':') {
+                Some(("verifier", role)) if !role.is_empty() => BountyVerification::Verifier {
+                    role: role.to_string(),
+                },
+                Some(("votes", threshold)) => BountyVerification::Votes {
+                    threshold: threshold
+                        .parse()
+                        .ok()
+                        .filter(|t| *t > 0)
+                        .ok_or_else(invalid)?,
+                },
+                _ => return Err(invalid()),
+            };
+            let description = parts.collect::<Vec<_>>().join(" ");
+            let description = if description.is_empty() {
+                String::new()
+            } else {
+                parse_quoted_string(&description)?
+            };
+
+            Ok(Op::CreateBounty {
+                id,
+                resource,
+                funder,
+                amount,
+                verification,
+                description,
+            })
+        }
+        "claimbounty" => {
+            // Format: claimbounty <id> "evidence"
+            let id = parts
+                .next()
+                .ok_or(CompilerError::MissingVariable(
+                    "claimbounty (id)".to_string(),
+                    pos.line,
+                    pos.column,
+                ))?
+                .trim_matches('"')
+                .to_string();
+            let evidence = parts.collect::<Vec<_>>().join(" ");
+            if evidence.is_empty() {
+                return Err(CompilerError::MissingVariable(
+                    "claimbounty (evidence)".to_string(),
+                    pos.line,
+                    pos.column,
+                ));
+            }
+            Ok(Op::ClaimBounty {
+                id,
+                evidence: parse_quoted_string(&evidence)?,
+            })
+        }
+        "approvebounty" | "rejectbounty" | "cancelbounty" => {
+            let id = parts
+                .next()
+                .ok_or(CompilerError::MissingVariable(
+                    format!("{} (id)", command),
+                    pos.line,
+                    pos.column,
+                ))?
+                .trim_matches('"')
+                .to_string();
+            Ok(match command {
+                "cancelbounty" => Op::CancelBounty(id),
+                _ => Op::VerifyBounty {
+                    id,
+                    approve: command == "approvebounty",
+                },
+            })
+        }
+        "distribute" => {
+            // Format: distribute <resource> <pool> <weight_key>, amount from the stack
+            let mut next = |what: &str| {
+                parts.next().ok_or(CompilerError::MissingVariable(
+                    format!("distribute ({})", what),
+                    pos.line,
+                    pos.column,
+                ))
+            };
+            let resource = next("resource")?.to_string();
+            let pool_key = next("pool")?.to_string();
+            let weight_key = next("weight_key")?.to_string();
+
+            Ok(Op::Distribute {
+                resource,
+                pool_key,
+                weight_key,
+            })
+        }
         "proposal_lifecycle" => {
             // Format: proposal_lifecycle "id" quorum=X threshold=Y title="Title" author="Author" { ... }
             let proposal_id = parts
@@ -783,6 +1360,70 @@ pub fn parse_block(
     Ok(block_ops)
 }
 
+/// Parse the `key=value` options after `createresource <id>`:
+/// `symbol=HRS decimals=2 transferable=false issuance=capped:1000 minters=did:a,did:b`
+fn parse_resource_policy<'a>(
+    options: impl Iterator<Item = &'a str>,
+    pos: SourcePosition,
+) -> Result<ResourcePolicy, CompilerError> {
+    let mut policy = ResourcePolicy::default();
+    for option in options {
+        let invalid = || {
+            CompilerError::InvalidParameterValue(
+                format!("createresource {}", option),
+                pos.line,
+                pos.column,
+            )
+        };
+        let (key, value) = option.split_once('=').ok_or_else(invalid)?;
+        match key {
+            "symbol" => policy.symbol = Some(value.to_string()),
+            "decimals" => policy.decimals = Some(value.parse().map_err(|_| invalid())?),
+            "transferable" => policy.transferable = value.parse().map_err(|_| invalid())?,
+            "issuance" => {
+                policy.issuance = match value.split_once(':') {
+                    Some(("capped", max)) => IssuancePolicy::Capped {
+                        max_supply: max.parse().map_err(|_| invalid())?,
+                    },
+                    None if value == "open" => IssuancePolicy::Open,
+                    None if value == "fixed" => IssuancePolicy::Fixed,
+                    _ => return Err(invalid()),
+                }
+            }
+            "minters" => {
+                policy.minters = value
+                    .split(',')
+                    .filter(|m| !m.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            "demurrage" => {
+                let mut parts = value.split('/');
+                let demurrage = DemurragePolicy {
+                    rate: parts
+                        .next()
+                        .and_then(|r| r.parse().ok())
+                        .ok_or_else(invalid)?,
+                    period_secs: parts
+                        .next()
+                        .and_then(|p| p.parse().ok())
+                        .ok_or_else(invalid)?,
+                    grace_secs: match parts.next() {
+                        Some(grace) => grace.parse().map_err(|_| invalid())?,
+                        None => 0,
+                    },
+                };
+                if parts.next().is_some() || demurrage.validate().is_err() {
+                    return Err(invalid());
+                }
+                policy.demurrage = Some(demurrage);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(policy)
+}
+
 // Helper to parse quoted strings (handles both single and double quotes)
 fn parse_quoted_string(input: &str) -> Result<String, CompilerError> {
     let trimmed = input.trim();
@@ -821,8 +1462,126 @@ mod tests {
         let op = parse_line("push \"hello\"", SourcePosition::new(1, 1)).unwrap();
         assert_eq!(op, Op::Push(TypedValue::String("hello".to_string())));
 
+        let op = parse_line(
+            r#"  push "say \"hi\"\tto all"  # greeting"#,
+            SourcePosition::new(1, 1),
+        )
+        .unwrap();
+        assert_eq!(
+            op,
+            Op::Push(TypedValue::String("say \"hi\"\tto all".to_string()))
+        );
+        for invalid in [r#"push "hello"#, r#"push "a" b"#, r#"push "bad \q""#] {
+            assert!(parse_line(invalid, SourcePosition::new(1, 1)).is_err());
+        }
+
         // Null
         let op = parse_line("push null", SourcePosition::new(1, 1)).unwrap();
         assert_eq!(op, Op::Push(TypedValue::Null));
     }
+
+    #[test]
+    fn test_parse_createresource_policy() {
+        let op = parse_line(
+            "createresource hours symbol=HRS decimals=2 transferable=false issuance=capped:1000 minters=did:key:a,did:key:b",
+            SourcePosition::new(1, 1),
+        )
+        .unwrap();
+        assert_eq!(
+            op,
+            Op::CreateResource {
+                resource: "hours".to_string(),
+                policy: ResourcePolicy {
+                    symbol: Some("HRS".to_string()),
+                    decimals: Some(2),
+                    transferable: false,
+                    issuance: IssuancePolicy::Capped {
+                        max_supply: Decimal::from(1000),
+                    },
+                    minters: vec!["did:key:a".to_string(), "did:key:b".to_string()],
+                    demurrage: None,
+                },
+            }
+        );
+
+        let op = parse_line("createresource token", SourcePosition::new(1, 1)).unwrap();
+        assert_eq!(
+            op,
+            Op::CreateResource {
+                resource: "token".to_string(),
+                policy: ResourcePolicy::default(),
+            }
+        );
+
+        assert!(parse_line(
+            "createresource token issuance=sometimes",
+            SourcePosition::new(1, 1)
+        )
+        .is_err());
+
+        let op = parse_line(
+            "createresource credits demurrage=0.02/2592000/604800",
+            SourcePosition::new(1, 1),
+        )
+        .unwrap();
+        assert_eq!(
+            op,
+            Op::CreateResource {
+                resource: "credits".to_string(),
+                policy: ResourcePolicy {
+                    demurrage: Some(DemurragePolicy {
+                        rate: Decimal::new(2, 2),
+                        period_secs: 2_592_000,
+                        grace_secs: 604_800,
+                    }),
+                    ..ResourcePolicy::default()
+                },
+            }
+        );
+        assert!(parse_line(
+            "createresource credits demurrage=1.5/86400",
+            SourcePosition::new(1, 1)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_createbounty() {
+        let op = parse_line(
+            "createbounty docs hours treasury 40 verifier:reviewer \"Write the guide\"",
+            SourcePosition::new(1, 1),
+        )
+        .unwrap();
+        assert_eq!(
+            op,
+            Op::CreateBounty {
+                id: "docs".to_string(),
+                resource: "hours".to_string(),
+                funder: "treasury".to_string(),
+                amount: Decimal::from(40),
+                verification: BountyVerification::Verifier {
+                    role: "reviewer".to_string(),
+                },
+                description: "Write the guide".to_string(),
+            }
+        );
+
+        let op = parse_line(
+            "createbounty audit hours treasury 10 votes:3",
+            SourcePosition::new(1, 1),
+        )
+        .unwrap();
+        assert!(matches!(
+            op,
+            Op::CreateBounty {
+                verification: BountyVerification::Votes { threshold: 3 },
+                ..
+            }
+        ));
+        assert!(parse_line(
+            "createbounty audit hours treasury 10 votes:0",
+            SourcePosition::new(1, 1)
+        )
+        .is_err());
+    }
 }
@@ -1,4 +1,5 @@
 use crate::compiler::parse_dsl; // Use the correct path from parent module
+use crate::events::Severity;
 use crate::governance::proposal_lifecycle::{ProposalLifecycle, ProposalState}; // Import necessary structs
 use crate::vm::Op;
 use chrono::{DateTime, Duration, Utc};
@@ -203,6 +204,7 @@ pub fn expand_proposal_lifecycle(lines: &[&str]) -> Result<Vec<Op>, String> {
         ops.push(Op::EmitEvent {
             category: "governance".to_string(),
             message: format!("Stored execution logic for proposal {}", proposal_id),
+            severity: Severity::Info,
         });
     } else {
         println!(
@@ -216,6 +218,7 @@ pub fn expand_proposal_lifecycle(lines: &[&str]) -> Result<Vec<Op>, String> {
     ops.push(Op::EmitEvent {
         category: "governance".to_string(),
         message: event_msg,
+        severity: Severity::Info,
     });
 
     // TODO: Add lifecycle validation Ops?
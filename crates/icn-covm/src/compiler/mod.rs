@@ -5,6 +5,7 @@ use thiserror::Error;
 
 // Sub-modules
 pub mod common;
+pub mod diagnostics;
 pub mod function_block;
 pub mod if_block;
 pub mod line_parser;
@@ -16,12 +17,13 @@ pub mod proposal_block;
 pub mod while_block;
 
 // Re-export the parser functions
+pub use diagnostics::{validate_dsl, Diagnostic, Severity};
 pub use function_block::parse_function_block;
 pub use if_block::parse_if_block;
 pub use line_parser::parse_line;
 pub use loop_block::parse_loop_block;
 pub use match_block::parse_match_block;
-pub use parse_dsl::parse_dsl;
+pub use parse_dsl::{parse_dsl, parse_dsl_with_lines};
 pub use parse_dsl::LifecycleConfig;
 pub use while_block::parse_while_block;
 
@@ -185,6 +187,54 @@ pub enum CompilerError {
     InvalidParameterValue(String, usize, usize),
 }
 
+impl CompilerError {
+    /// Position in the source the error refers to, if it names one
+    ///
+    /// Errors that only know their line report column 1. Syntax errors carry
+    /// their position in the message, when they have one.
+    pub fn position(&self) -> Option<SourcePosition> {
+        use CompilerError::*;
+        match self {
+            UnknownCommand(_, line, column)
+            | UnknownBlockType(_, line, column)
+            | InvalidFunctionDefinition(_, line, column)
+            | InvalidFunctionFormat(_, line, column)
+            | InvalidFunctionStart(_, line, column)
+            | InvalidPushValue(_, line, column)
+            | MissingVariable(_, line, column)
+            | InvalidAssertDepth(_, line, column)
+            | InvalidCaseValue(_, line, column)
+            | InvalidLoopFormat(_, line, column)
+            | InvalidLoopCount(_, line, column)
+            | MissingParameter(_, line, column)
+            | InvalidParameterValue(_, line, column)
+            | MissingPushValue(line, column)
+            | MissingEmitQuotes(line, column)
+            | InvalidEmitEventFormat(line, column)
+            | MissingFunctionName(line, column)
+            | MissingAssertDepth(line, column)
+            | InsufficientAssertDepth(line, column)
+            | MissingMatchValue(line, column)
+            | MissingProposalId(line, column)
+            | InvalidQuorumValue(line, column)
+            | InvalidThresholdValue(line, column)
+            | DuplicateIfPassedBlock(line, column)
+            | DuplicateElseBlock(line, column)
+            | ElseWithoutIfPassed(line, column) => Some(SourcePosition::new(*line, *column)),
+            UnexpectedEOF(line) | InvalidIndentation(line) => Some(SourcePosition::new(*line, 1)),
+            SyntaxError { details } => details
+                .split("line ")
+                .nth(1)
+                .and_then(|rest| {
+                    rest.split(|c: char| !c.is_ascii_digit())
+                        .next()
+                        .and_then(|line| line.parse().ok())
+                })
+                .map(|line| SourcePosition::new(line, 1)),
+        }
+    }
+}
+
 /// Source position information for error reporting
 ///
 /// Contains line and column information to pinpoint errors in the source code.
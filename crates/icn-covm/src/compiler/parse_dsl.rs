@@ -110,9 +110,21 @@ fn parse_duration(duration_str: &str) -> Result<Duration, CompilerError> {
 /// let (ops, config) = parse_dsl(source).unwrap();
 /// ```
 pub fn parse_dsl(source: &str) -> Result<(Vec<Op>, LifecycleConfig), CompilerError> {
+    parse_dsl_with_lines(source).map(|(ops, _, config)| (ops, config))
+}
+
+/// Parse DSL source like [`parse_dsl`], also returning the 1-indexed line on
+/// which each top-level operation starts
+///
+/// Blocks such as `if:` and `def` are a single top-level operation, so they
+/// map to the line that opens them.
+pub fn parse_dsl_with_lines(
+    source: &str,
+) -> Result<(Vec<Op>, Vec<usize>, LifecycleConfig), CompilerError> {
     let lines: Vec<String> = source.lines().map(|s| s.to_string()).collect();
     let mut current_line = 0;
     let mut ops = Vec::new();
+    let mut op_lines = Vec::new();
     let mut config = LifecycleConfig::default();
     let mut in_governance_block = false;
     let mut in_template_block = false;
@@ -324,6 +336,7 @@ pub fn parse_dsl(source: &str) -> Result<(Vec<Op>, LifecycleConfig), CompilerErr
 
             if !matches!(op, Op::Nop) {
                 ops.push(op);
+                op_lines.push(pos.line);
             }
             // current_line is already incremented by the block parser
         } else {
@@ -331,12 +344,13 @@ pub fn parse_dsl(source: &str) -> Result<(Vec<Op>, LifecycleConfig), CompilerErr
             let op = parse_line(line, pos)?;
             if !matches!(op, Op::Nop) {
                 ops.push(op);
+                op_lines.push(pos.line);
             }
             current_line += 1;
         }
     }
 
-    Ok((ops, config))
+    Ok((ops, op_lines, config))
 }
 
 #[cfg(test)]
@@ -0,0 +1,727 @@
+//! Layered node configuration
+//!
+//! Settings are resolved from, in increasing order of precedence:
+//!
+//! 1. Built-in defaults
+//! 2. A TOML file: the one named by `--config` or `ICN_CONFIG`, otherwise
+//!    `icn-covm.toml` in the working directory if it exists
+//! 3. `ICN_*` environment variables
+//! 4. Command-line flags, which callers apply over the loaded [`Config`]
+//!
+//! ```toml
+//! [storage]
+//! backend = "file"
+//! path = "./storage"
+//! usage_alerts = [80, 95]
+//!
+//! [api]
+//! port = 3030
+//! grpc_port = 50051
+//!
+//! [federation]
+//! enabled = true
+//! port = 8000
+//! node_name = "coop-node"
+//! bootstrap_nodes = ["/ip4/10.0.0.2/tcp/8000"]
+//!
+//! [ledger]
+//! dag_path = "./dag_ledger.jsonl"
+//!
+//! [identity]
+//! key_path = "./identity.json"
+//!
+//! [audit]
+//! execution_log = "./execution-audit.jsonl"
+//!
+//! [logging]
+//! level = "info,icn_covm::federation=debug"
+//! format = "json"
+//! file = "./icn-covm.log"
+//!
+//! [telemetry]
+//! otlp_endpoint = "http://localhost:4318"
+//! service_name = "coop-node"
+//!
+//! [events]
+//! min_severity = "warn"
+//! filters = [{ categories = ["governance*"], min_severity = "info" }]
+//!
+//! [[events.sinks]]
+//! type = "webhook"
+//! url = "http://monitor.local:9000/icn"
+//! categories = ["governance*"]
+//!
+//! [attachments.ipfs]
+//! api_url = "http://127.0.0.1:5001"
+//! gateways = ["http://127.0.0.1:8080"]
+//! ```
+
+use crate::events::Severity;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Config file read from the working directory when none is named
+pub const DEFAULT_CONFIG_FILE: &str = "icn-covm.toml";
+
+/// Environment variable naming the config file
+pub const CONFIG_ENV: &str = "ICN_CONFIG";
+
+/// Default location of the DAG ledger file
+pub const DEFAULT_DAG_PATH: &str = "./dag_ledger.jsonl";
+
+/// Default location of the federation peer scores file
+pub const DEFAULT_PEER_SCORES_PATH: &str = "./federation_peers.json";
+
+/// Environment variables and the settings they override
+pub const STORAGE_BACKEND_ENV: &str = "ICN_STORAGE_BACKEND";
+pub const STORAGE_PATH_ENV: &str = "ICN_STORAGE_PATH";
+/// Comma-separated, e.g. `80,95`
+pub const STORAGE_USAGE_ALERTS_ENV: &str = "ICN_STORAGE_USAGE_ALERTS";
+pub const API_PORT_ENV: &str = "ICN_API_PORT";
+pub const GRPC_PORT_ENV: &str = "ICN_GRPC_PORT";
+pub const REPLICA_OF_ENV: &str = "ICN_REPLICA_OF";
+pub const OIDC_ISSUER_ENV: &str = "ICN_OIDC_ISSUER";
+pub const OIDC_CLIENT_ID_ENV: &str = "ICN_OIDC_CLIENT_ID";
+pub const OIDC_JWKS_ENV: &str = "ICN_OIDC_JWKS";
+pub const FEDERATION_ENABLED_ENV: &str = "ICN_FEDERATION_ENABLED";
+pub const FEDERATION_PORT_ENV: &str = "ICN_FEDERATION_PORT";
+pub const NODE_NAME_ENV: &str = "ICN_NODE_NAME";
+/// Comma-separated multiaddresses
+pub const BOOTSTRAP_NODES_ENV: &str = "ICN_BOOTSTRAP_NODES";
+pub const DAG_PATH_ENV: &str = "ICN_DAG_PATH";
+pub const IDENTITY_KEY_ENV: &str = "ICN_IDENTITY_KEY";
+pub const EXECUTION_AUDIT_LOG_ENV: &str = "ICN_EXECUTION_AUDIT_LOG";
+pub const LOG_LEVEL_ENV: &str = "ICN_LOG_LEVEL";
+pub const LOG_FORMAT_ENV: &str = "ICN_LOG_FORMAT";
+pub const LOG_FILE_ENV: &str = "ICN_LOG_FILE";
+pub const OTLP_ENDPOINT_ENV: &str = "ICN_OTLP_ENDPOINT";
+/// Turns on IPFS attachment storage with the other settings at their defaults
+pub const IPFS_API_ENV: &str = "ICN_IPFS_API";
+
+/// Standard OpenTelemetry variable, used when `ICN_OTLP_ENDPOINT` is unset
+pub const OTEL_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Errors loading the configuration
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Invalid value for {var}: {value:?}")]
+    InvalidEnv { var: &'static str, value: String },
+}
+
+/// Storage backend settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Backend type: `memory`, `file` or `sled`
+    pub backend: String,
+    /// Directory used by the file and sled backends
+    pub path: PathBuf,
+    /// Percentages of a namespace's quota at which a warning is raised
+    pub usage_alerts: Vec<u8>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: "memory".to_string(),
+            path: PathBuf::from("./storage"),
+            usage_alerts: vec![80, 95],
+        }
+    }
+}
+
+/// API server settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub port: u16,
+    /// Serve the gRPC API on this port too; needs the `grpc` feature
+    pub grpc_port: Option<u16>,
+    /// Run as a read replica of the API server at this `http://` URL
+    pub replica_of: Option<String>,
+    /// OpenID Connect provider members may log in with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oidc: Option<OidcConfig>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            port: 3030,
+            grpc_port: None,
+            replica_of: None,
+            oidc: None,
+        }
+    }
+}
+
+/// OpenID Connect provider, such as Keycloak or Authentik
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OidcConfig {
+    /// Issuer URL, as in the `iss` claim of the provider's ID tokens
+    pub issuer: String,
+    /// Client ID the API is registered with, expected in the `aud` claim
+    pub client_id: String,
+    /// File or `http://` URL of the provider's JSON Web Key Set; taken from
+    /// the issuer's discovery document when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks: Option<String>,
+}
+
+/// Federation node settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FederationConfig {
+    /// Run a federation node alongside `run` and `api`
+    pub enabled: bool,
+    /// Port to listen on; 0 picks a free port
+    pub port: u16,
+    pub node_name: String,
+    /// Multiaddresses of nodes to dial on startup
+    pub bootstrap_nodes: Vec<String>,
+    /// Capabilities advertised to peers
+    pub capabilities: Vec<String>,
+    /// File peer scores and bans are kept in; when unset,
+    /// [`DEFAULT_PEER_SCORES_PATH`]
+    pub peer_scores_path: Option<PathBuf>,
+}
+
+impl Default for FederationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 0,
+            node_name: "icn-covm-node".to_string(),
+            bootstrap_nodes: Vec::new(),
+            capabilities: Vec::new(),
+            peer_scores_path: None,
+        }
+    }
+}
+
+impl FederationConfig {
+    /// The configured peer scores file, or the default one
+    pub fn peer_scores_path_or_default(&self) -> PathBuf {
+        self.peer_scores_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_PEER_SCORES_PATH))
+    }
+}
+
+/// DAG ledger settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LedgerConfig {
+    /// Ledger file; when unset, proposal commands keep their ledger in
+    /// memory and ledger commands use [`DEFAULT_DAG_PATH`]
+    pub dag_path: Option<PathBuf>,
+}
+
+impl LedgerConfig {
+    /// The configured ledger file, or the default one
+    pub fn dag_path_or_default(&self) -> PathBuf {
+        self.dag_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DAG_PATH))
+    }
+}
+
+/// Identity settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdentityConfig {
+    /// Identity JSON file, including its private key, that commands act as
+    pub key_path: Option<PathBuf>,
+}
+
+/// Audit settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Hash-chained log that every VM event raised while a proposal executes
+    /// is appended to; audit mode is off when unset
+    pub execution_log: Option<PathBuf>,
+}
+
+/// How diagnostic log lines are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoggingFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, with span fields such as `proposal_id`
+    Json,
+}
+
+impl std::str::FromStr for LoggingFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown log format: {}", s)),
+        }
+    }
+}
+
+/// Diagnostic logging settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Filter directives, such as `info` or `warn,icn_covm::federation=debug`;
+    /// `RUST_LOG` takes precedence when set
+    pub level: String,
+    pub format: LoggingFormat,
+    /// File to append log lines to instead of stderr
+    pub file: Option<PathBuf>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "warn".to_string(),
+            format: LoggingFormat::Text,
+            file: None,
+        }
+    }
+}
+
+/// OpenTelemetry export settings, used when built with the `otel` feature
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector, such as `http://localhost:4318`; nothing is
+    /// exported when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
+    /// `service.name` of the exported spans and metrics
+    pub service_name: String,
+    /// Filter directives choosing the spans exported, like `logging.level`
+    pub level: String,
+    /// Seconds between metric exports
+    pub metrics_interval_secs: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: "icn-covm".to_string(),
+            level: "info".to_string(),
+            metrics_interval_secs: 60,
+        }
+    }
+}
+
+/// Where one event sink sends events
+///
+/// `categories` lists the event tags the sink receives; a trailing `*`
+/// matches any tag with that prefix, and an empty list matches all tags.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum EventSinkConfig {
+    /// JSON lines appended to a file
+    File {
+        path: PathBuf,
+        #[serde(default)]
+        categories: Vec<String>,
+        /// Rotate the file before it grows past this size
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_bytes: Option<u64>,
+        /// Rotated files to keep
+        #[serde(default = "default_max_files")]
+        max_files: usize,
+    },
+    /// RFC 5424 messages to syslog
+    Syslog {
+        /// Unix socket path or UDP `host:port`; defaults to `/dev/log`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        address: Option<String>,
+        #[serde(default)]
+        categories: Vec<String>,
+    },
+    /// JSON POSTed to an `http://` URL
+    Webhook {
+        url: String,
+        #[serde(default)]
+        categories: Vec<String>,
+    },
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+impl EventSinkConfig {
+    pub fn categories(&self) -> &[String] {
+        match self {
+            Self::File { categories, .. }
+            | Self::Syslog { categories, .. }
+            | Self::Webhook { categories, .. } => categories,
+        }
+    }
+}
+
+/// The lowest severity kept for some event categories
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventFilterConfig {
+    /// Categories the rule applies to, with the same patterns as a sink's
+    pub categories: Vec<String>,
+    pub min_severity: Severity,
+}
+
+/// Event filter and sink settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventsConfig {
+    /// Lowest severity sent to sinks for categories no filter matches
+    pub min_severity: Severity,
+    /// Per-category minimum severities; the first matching one applies
+    pub filters: Vec<EventFilterConfig>,
+    /// Sinks that all receive events at the same time
+    pub sinks: Vec<EventSinkConfig>,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            min_severity: Severity::Debug,
+            filters: Vec::new(),
+            sinks: Vec::new(),
+        }
+    }
+}
+
+/// IPFS node that large attachments are stored on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IpfsConfig {
+    /// `http://` URL of the node's RPC API, such as `http://127.0.0.1:5001`
+    pub api_url: String,
+    /// `http://` gateways tried in order when the API cannot return a file
+    pub gateways: Vec<String>,
+    /// Files smaller than this stay in the storage backend
+    pub min_bytes: u64,
+    /// Pin files on the node so its garbage collection keeps them
+    pub pin: bool,
+    /// Remote pinning service, as named on the node, to pin files with too
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_pin_service: Option<String>,
+    /// Seconds a request to the node or a gateway may take
+    pub timeout_secs: u64,
+}
+
+impl Default for IpfsConfig {
+    fn default() -> Self {
+        Self {
+            api_url: "http://127.0.0.1:5001".to_string(),
+            gateways: Vec::new(),
+            min_bytes: 1024 * 1024,
+            pin: true,
+            remote_pin_service: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// Attachment settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentsConfig {
+    /// IPFS node for large files; every file stays in the storage backend
+    /// when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipfs: Option<IpfsConfig>,
+}
+
+/// Resolved node configuration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub storage: StorageConfig,
+    pub api: ApiConfig,
+    pub federation: FederationConfig,
+    pub ledger: LedgerConfig,
+    pub identity: IdentityConfig,
+    pub audit: AuditConfig,
+    pub logging: LoggingConfig,
+    pub telemetry: TelemetryConfig,
+    pub events: EventsConfig,
+    pub attachments: AttachmentsConfig,
+}
+
+impl Config {
+    /// Load the file and environment layers
+    ///
+    /// `path` is the file named on the command line, if any. A named file
+    /// must exist; the default `icn-covm.toml` is skipped when missing.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let named = path
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os(CONFIG_ENV).map(PathBuf::from));
+        let mut config = match named {
+            Some(path) => Self::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => Self::default(),
+        };
+        config.apply_env(|var| std::env::var(var).ok())?;
+        Ok(config)
+    }
+
+    /// Read a TOML config file over the defaults
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Override settings from environment variables read through `lookup`
+    pub fn apply_env(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        fn parse<T: std::str::FromStr>(var: &'static str, value: String) -> Result<T, ConfigError> {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| ConfigError::InvalidEnv { var, value })
+        }
+
+        if let Some(value) = lookup(STORAGE_BACKEND_ENV) {
+            self.storage.backend = value;
+        }
+        if let Some(value) = lookup(STORAGE_PATH_ENV) {
+            self.storage.path = PathBuf::from(value);
+        }
+        if let Some(value) = lookup(STORAGE_USAGE_ALERTS_ENV) {
+            self.storage.usage_alerts = value
+                .split(',')
+                .filter(|percent| !percent.trim().is_empty())
+                .map(|percent| parse(STORAGE_USAGE_ALERTS_ENV, percent.to_string()))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(value) = lookup(API_PORT_ENV) {
+            self.api.port = parse(API_PORT_ENV, value)?;
+        }
+        if let Some(value) = lookup(GRPC_PORT_ENV) {
+            self.api.grpc_port = Some(parse(GRPC_PORT_ENV, value)?);
+        }
+        if let Some(value) = lookup(REPLICA_OF_ENV) {
+            self.api.replica_of = Some(value);
+        }
+        if let Some(value) = lookup(OIDC_ISSUER_ENV) {
+            self.api.oidc.get_or_insert_with(OidcConfig::default).issuer = value;
+        }
+        if let Some(value) = lookup(OIDC_CLIENT_ID_ENV) {
+            self.api
+                .oidc
+                .get_or_insert_with(OidcConfig::default)
+                .client_id = value;
+        }
+        if let Some(value) = lookup(OIDC_JWKS_ENV) {
+            self.api.oidc.get_or_insert_with(OidcConfig::default).jwks = Some(value);
+        }
+        if let Some(value) = lookup(FEDERATION_ENABLED_ENV) {
+            self.federation.enabled = parse(FEDERATION_ENABLED_ENV, value)?;
+        }
+        if let Some(value) = lookup(FEDERATION_PORT_ENV) {
+            self.federation.port = parse(FEDERATION_PORT_ENV, value)?;
+        }
+        if let Some(value) = lookup(NODE_NAME_ENV) {
+            self.federation.node_name = value;
+        }
+        if let Some(value) = lookup(BOOTSTRAP_NODES_ENV) {
+            self.federation.bootstrap_nodes = value
+                .split(',')
+                .map(str::trim)
+                .filter(|addr| !addr.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(value) = lookup(DAG_PATH_ENV) {
+            self.ledger.dag_path = Some(PathBuf::from(value));
+        }
+        if let Some(value) = lookup(IDENTITY_KEY_ENV) {
+            self.identity.key_path = Some(PathBuf::from(value));
+        }
+        if let Some(value) = lookup(EXECUTION_AUDIT_LOG_ENV) {
+            self.audit.execution_log = Some(PathBuf::from(value));
+        }
+        if let Some(value) = lookup(LOG_LEVEL_ENV) {
+            self.logging.level = value;
+        }
+        if let Some(value) = lookup(LOG_FORMAT_ENV) {
+            self.logging.format = parse(LOG_FORMAT_ENV, value)?;
+        }
+        if let Some(value) = lookup(LOG_FILE_ENV) {
+            self.logging.file = Some(PathBuf::from(value));
+        }
+        if let Some(value) = lookup(OTLP_ENDPOINT_ENV).or_else(|| lookup(OTEL_ENDPOINT_ENV)) {
+            self.telemetry.otlp_endpoint = Some(value);
+        }
+        if let Some(value) = lookup(IPFS_API_ENV) {
+            self.attachments
+                .ipfs
+                .get_or_insert_with(IpfsConfig::default)
+                .api_url = value;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_file_layers_over_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_CONFIG_FILE);
+        std::fs::write(
+            &path,
+            "[api]\nport = 8080\n\n[federation]\nenabled = true\nbootstrap_nodes = [\"/ip4/10.0.0.2/tcp/8000\"]\n",
+        )
+        .unwrap();
+
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.api.port, 8080);
+        assert!(config.federation.enabled);
+        assert_eq!(config.federation.bootstrap_nodes.len(), 1);
+        assert_eq!(config.federation.node_name, "icn-covm-node");
+        assert_eq!(config.storage, StorageConfig::default());
+        assert_eq!(
+            config.ledger.dag_path_or_default(),
+            PathBuf::from(DEFAULT_DAG_PATH)
+        );
+
+        std::fs::write(
+            &path,
+            "[[events.sinks]]\ntype = \"file\"\npath = \"events.log\"\nmax_bytes = 1048576\n\n[[events.sinks]]\ntype = \"webhook\"\nurl = \"http://localhost:9000/\"\ncategories = [\"governance*\"]\n",
+        )
+        .unwrap();
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.events.sinks.len(), 2);
+        assert!(matches!(
+            &config.events.sinks[0],
+            EventSinkConfig::File {
+                max_bytes: Some(1048576),
+                max_files: 5,
+                ..
+            }
+        ));
+        assert_eq!(config.events.sinks[1].categories(), ["governance*"]);
+        assert_eq!(config.events.min_severity, Severity::Debug);
+
+        std::fs::write(
+            &path,
+            "[events]\nmin_severity = \"warn\"\nfilters = [{ categories = [\"governance:*\"], min_severity = \"info\" }]\n",
+        )
+        .unwrap();
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.events.min_severity, Severity::Warn);
+        assert_eq!(config.events.filters[0].min_severity, Severity::Info);
+        assert_eq!(config.attachments.ipfs, None);
+
+        std::fs::write(
+            &path,
+            "[attachments.ipfs]\napi_url = \"http://ipfs.local:5001\"\nmin_bytes = 0\n",
+        )
+        .unwrap();
+        let ipfs = Config::from_file(&path).unwrap().attachments.ipfs.unwrap();
+        assert_eq!(ipfs.api_url, "http://ipfs.local:5001");
+        assert_eq!(ipfs.min_bytes, 0);
+        assert!(ipfs.pin);
+
+        std::fs::write(&path, "[api]\nprot = 8080\n").unwrap();
+        assert!(matches!(
+            Config::from_file(&path),
+            Err(ConfigError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let env: HashMap<&str, &str> = [
+            (STORAGE_USAGE_ALERTS_ENV, "90, 99"),
+            (API_PORT_ENV, "9000"),
+            (GRPC_PORT_ENV, "50051"),
+            (REPLICA_OF_ENV, "http://primary:3030"),
+            (OIDC_ISSUER_ENV, "https://auth.example.coop/realms/alpha"),
+            (
+                BOOTSTRAP_NODES_ENV,
+                "/ip4/10.0.0.2/tcp/8000, /ip4/10.0.0.3/tcp/8000",
+            ),
+            (DAG_PATH_ENV, "/var/lib/icn/dag.jsonl"),
+            (LOG_FORMAT_ENV, "JSON"),
+            (OTEL_ENDPOINT_ENV, "http://collector:4318"),
+            (IPFS_API_ENV, "http://ipfs:5001"),
+        ]
+        .into_iter()
+        .collect();
+        let mut config = Config::default();
+        config
+            .apply_env(|var| env.get(var).map(|v| v.to_string()))
+            .unwrap();
+
+        assert_eq!(config.storage.usage_alerts, vec![90, 99]);
+        assert_eq!(config.api.port, 9000);
+        assert_eq!(config.api.grpc_port, Some(50051));
+        assert_eq!(
+            config.api.replica_of.as_deref(),
+            Some("http://primary:3030")
+        );
+        let oidc = config.api.oidc.as_ref().unwrap();
+        assert_eq!(oidc.issuer, "https://auth.example.coop/realms/alpha");
+        assert!(oidc.client_id.is_empty());
+        assert_eq!(config.federation.bootstrap_nodes.len(), 2);
+        assert_eq!(
+            config.ledger.dag_path,
+            Some(PathBuf::from("/var/lib/icn/dag.jsonl"))
+        );
+        assert_eq!(config.logging.format, LoggingFormat::Json);
+        assert_eq!(config.logging.level, "warn");
+        assert_eq!(
+            config.telemetry.otlp_endpoint.as_deref(),
+            Some("http://collector:4318")
+        );
+        let ipfs = config.attachments.ipfs.as_ref().unwrap();
+        assert_eq!(ipfs.api_url, "http://ipfs:5001");
+        assert_eq!(ipfs.timeout_secs, IpfsConfig::default().timeout_secs);
+
+        let err = config
+            .apply_env(|var| (var == FEDERATION_PORT_ENV).then(|| "many".to_string()))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::InvalidEnv {
+                var: FEDERATION_PORT_ENV,
+                ..
+            }
+        ));
+    }
+}
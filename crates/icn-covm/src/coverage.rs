@@ -0,0 +1,466 @@
+//! Execution coverage of DSL programs
+//!
+//! A [`Coverage`] attached to a VM with [`VM::set_coverage`] counts how often
+//! each op runs and which branches of `if`, `match`, `loop` and `while`
+//! blocks are taken. [`Coverage::report`] maps those counts back onto the
+//! program's source lines, so an author can confirm that both the passing
+//! and the failing paths of a proposal's logic ran before it goes to a vote:
+//!
+//! ```
+//! use icn_covm::coverage::coverage_dsl;
+//! use std::collections::HashMap;
+//!
+//! let source = "load approved\nif:\n    emit \"pass\"\nelse:\n    emit \"fail\"\n";
+//! let runs = vec![HashMap::from([("approved".to_string(), "true".to_string())])];
+//! let (report, _) = coverage_dsl(source, &runs).unwrap();
+//! assert_eq!(report.branches_covered, 1);
+//! assert_eq!(report.uncovered_branches().next().unwrap().branch, "else");
+//! ```
+//!
+//! Counts accumulate until [`Coverage::reset`], so one coverage can record
+//! several runs of the same program, such as one per test scenario. A
+//! function's body is counted on the lines of its `def`, whichever line
+//! called it.
+
+use crate::compiler::{parse_dsl_with_lines, CompilerError};
+use crate::storage::auth::AuthContext;
+use crate::storage::implementations::in_memory::InMemoryStorage;
+use crate::vm::{MemoryScope, Op, VMError, VM};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// Namespace that [`coverage_dsl`] runs programs in
+pub const COVERAGE_NAMESPACE: &str = "coverage";
+
+/// Where an op or block sits: the function whose body holds it (`None` for
+/// the program itself), and the alternating op and block indices that lead
+/// to it from there
+type Node = (Option<String>, Vec<usize>);
+
+/// Position within the block being executed
+#[derive(Debug)]
+struct Cursor {
+    function: Option<String>,
+    block: Vec<usize>,
+    next: usize,
+    /// Path of the op last entered in this block
+    current: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+struct CoverageState {
+    cursors: Vec<Cursor>,
+    hits: HashMap<Node, u64>,
+}
+
+/// Records which ops and branches run while attached to a VM
+///
+/// Clones share their recordings, like a [`crate::perf::Profiler`].
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    state: Arc<Mutex<CoverageState>>,
+}
+
+/// Leaves the block, function or program it was created for when dropped
+pub(crate) struct CoverageGuard {
+    state: Arc<Mutex<CoverageState>>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CoverageState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, cursor: Cursor) -> CoverageGuard {
+        self.lock().cursors.push(cursor);
+        CoverageGuard {
+            state: Arc::clone(&self.state),
+        }
+    }
+
+    /// Start executing a program from its first op
+    pub(crate) fn enter_program(&self) -> CoverageGuard {
+        self.push(Cursor {
+            function: None,
+            block: Vec::new(),
+            next: 0,
+            current: Vec::new(),
+        })
+    }
+
+    /// Start executing the body of the function `name`
+    pub(crate) fn enter_function(&self, name: &str) -> CoverageGuard {
+        self.push(Cursor {
+            function: Some(name.to_string()),
+            block: Vec::new(),
+            next: 0,
+            current: Vec::new(),
+        })
+    }
+
+    /// Start executing block `block` of the current op, in the order given by
+    /// [`blocks`]
+    pub(crate) fn enter_block(&self, block: usize) -> CoverageGuard {
+        let cursor = {
+            let mut state = self.lock();
+            let (function, mut path) = state
+                .cursors
+                .last()
+                .map(|cursor| (cursor.function.clone(), cursor.current.clone()))
+                .unwrap_or_default();
+            path.push(block);
+            *state
+                .hits
+                .entry((function.clone(), path.clone()))
+                .or_default() += 1;
+            Cursor {
+                function,
+                block: path,
+                next: 0,
+                current: Vec::new(),
+            }
+        };
+        self.push(cursor)
+    }
+
+    /// Count the next op of the current block
+    pub(crate) fn enter_op(&self) {
+        let mut state = self.lock();
+        let Some(cursor) = state.cursors.last_mut() else {
+            return;
+        };
+        let mut path = cursor.block.clone();
+        path.push(cursor.next);
+        cursor.next += 1;
+        cursor.current = path.clone();
+        let node = (cursor.function.clone(), path);
+        *state.hits.entry(node).or_default() += 1;
+    }
+
+    /// Forget everything recorded so far
+    pub fn reset(&self) {
+        *self.lock() = CoverageState::default();
+    }
+
+    /// Map what was recorded onto the lines of `source`, the program it was
+    /// recorded for
+    pub fn report(&self, source: &str) -> Result<CoverageReport, CompilerError> {
+        let map = LineMap::build(source)?;
+        let state = self.lock();
+        let hits = |node: &Node| state.hits.get(node).copied().unwrap_or(0);
+
+        let mut line_hits: Vec<Option<u64>> = vec![None; source.lines().count()];
+        for (node, &line) in &map.ops {
+            if let Some(slot) = line_hits.get_mut(line - 1) {
+                *slot = Some(slot.unwrap_or(0) + hits(node));
+            }
+        }
+        let lines: Vec<LineCoverage> = source
+            .lines()
+            .zip(line_hits)
+            .enumerate()
+            .map(|(index, (text, hits))| LineCoverage {
+                line: index + 1,
+                source: text.to_string(),
+                hits,
+            })
+            .collect();
+
+        let mut branches: Vec<BranchCoverage> = map
+            .branches
+            .iter()
+            .map(|(node, line, branch)| BranchCoverage {
+                line: *line,
+                branch: branch.clone(),
+                hits: hits(node),
+            })
+            .collect();
+        branches.sort_by_key(|branch| branch.line);
+
+        Ok(CoverageReport {
+            lines_total: lines.iter().filter(|l| l.hits.is_some()).count(),
+            lines_covered: lines
+                .iter()
+                .filter(|l| l.hits.is_some_and(|h| h > 0))
+                .count(),
+            branches_total: branches.len(),
+            branches_covered: branches.iter().filter(|b| b.hits > 0).count(),
+            lines,
+            branches,
+        })
+    }
+}
+
+impl Drop for CoverageGuard {
+    fn drop(&mut self) {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .cursors
+            .pop();
+    }
+}
+
+/// How often one source line ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineCoverage {
+    /// 1-indexed line number
+    pub line: usize,
+    pub source: String,
+    /// Times the ops starting on this line ran, or `None` if none start on it
+    pub hits: Option<u64>,
+}
+
+/// How often one branch of a block was taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchCoverage {
+    /// Line of the block the branch belongs to
+    pub line: usize,
+    /// `then` or `else` for `if:`, `case <value>` or `default` for `match:`,
+    /// and `body` for loops
+    pub branch: String,
+    pub hits: u64,
+}
+
+/// Coverage of a program, line by line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Lines that ops start on
+    pub lines_total: usize,
+    pub lines_covered: usize,
+    pub branches_total: usize,
+    pub branches_covered: usize,
+    /// Every line of the source, in order
+    pub lines: Vec<LineCoverage>,
+    pub branches: Vec<BranchCoverage>,
+}
+
+impl CoverageReport {
+    /// Branches that never ran
+    pub fn uncovered_branches(&self) -> impl Iterator<Item = &BranchCoverage> {
+        self.branches.iter().filter(|branch| branch.hits == 0)
+    }
+}
+
+/// The blocks nested in `op` with their index, as passed to
+/// [`Coverage::enter_block`], and a name for those that are branches
+///
+/// A missing `else` or `default` is an empty block, so that falling through
+/// it counts as taking it.
+pub(crate) fn blocks(op: &Op) -> Vec<(usize, &[Op], Option<String>)> {
+    match op {
+        Op::If {
+            condition,
+            then,
+            else_,
+        } => vec![
+            (0, condition.as_slice(), None),
+            (1, then.as_slice(), Some("then".to_string())),
+            (
+                2,
+                else_.as_deref().unwrap_or_default(),
+                Some("else".to_string()),
+            ),
+        ],
+        Op::Loop { body, .. } => vec![(0, body.as_slice(), Some("body".to_string()))],
+        Op::While { condition, body } => vec![
+            (0, condition.as_slice(), None),
+            (1, body.as_slice(), Some("body".to_string())),
+        ],
+        Op::Match {
+            value,
+            cases,
+            default,
+        } => {
+            let mut blocks = vec![(0, value.as_slice(), None)];
+            for (index, (case, body)) in cases.iter().enumerate() {
+                blocks.push((index + 1, body.as_slice(), Some(format!("case {}", case))));
+            }
+            blocks.push((
+                cases.len() + 1,
+                default.as_deref().unwrap_or_default(),
+                Some("default".to_string()),
+            ));
+            blocks
+        }
+        Op::Def { body, .. } | Op::IfPassed(body) | Op::Else(body) => {
+            vec![(0, body.as_slice(), None)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Source lines of every op and branch of a program
+#[derive(Debug, Default)]
+struct LineMap {
+    ops: HashMap<Node, usize>,
+    branches: Vec<(Node, usize, String)>,
+}
+
+impl LineMap {
+    /// The parser only reports the line of each top-level op, so nested ops
+    /// are matched, in order, with the statement lines between their block's
+    /// line and the next top-level op's
+    fn build(source: &str) -> Result<Self, CompilerError> {
+        let (ops, op_lines, _) = parse_dsl_with_lines(source)?;
+        let lines: Vec<&str> = source.lines().collect();
+        let mut map = Self::default();
+        for (index, (op, &line)) in ops.iter().zip(&op_lines).enumerate() {
+            let end = op_lines.get(index + 1).copied().unwrap_or(lines.len() + 1);
+            let mut statements = (line + 1..end).filter(|&n| {
+                let text = lines[n - 1].trim();
+                !text.is_empty() && !text.starts_with('#')
+            });
+            map.add(&None, vec![index], op, line, &lines, &mut statements);
+        }
+        Ok(map)
+    }
+
+    fn add(
+        &mut self,
+        function: &Option<String>,
+        path: Vec<usize>,
+        op: &Op,
+        line: usize,
+        lines: &[&str],
+        statements: &mut impl Iterator<Item = usize>,
+    ) {
+        for (block, body, branch) in blocks(op) {
+            let mut block_path = path.clone();
+            block_path.push(block);
+            if let Some(branch) = branch {
+                self.branches
+                    .push(((function.clone(), block_path.clone()), line, branch));
+            }
+            for (index, child) in body.iter().enumerate() {
+                let child_line = statements
+                    .find(|&n| !is_block_label(lines[n - 1].trim(), child))
+                    .unwrap_or(line);
+                let mut child_path = block_path.clone();
+                child_path.push(index);
+                self.add(function, child_path, child, child_line, lines, statements);
+            }
+        }
+
+        // The body runs when the function is called, so it is counted under
+        // the function's name rather than where it is defined
+        if let Op::Def { name, .. } = op {
+            let mut prefix = path.clone();
+            prefix.push(0);
+            let in_body = |node: &Node| &node.0 == function && node.1.starts_with(&prefix);
+            let moved = |node: &Node| (Some(name.clone()), node.1[prefix.len()..].to_vec());
+
+            let body_ops: Vec<(Node, usize)> = self
+                .ops
+                .iter()
+                .filter(|(node, _)| in_body(node))
+                .map(|(node, &line)| (moved(node), line))
+                .collect();
+            self.ops.retain(|node, _| !in_body(node));
+            self.ops.extend(body_ops);
+            for (node, _, _) in self
+                .branches
+                .iter_mut()
+                .filter(|(node, _, _)| in_body(node))
+            {
+                *node = moved(node);
+            }
+        }
+        self.ops.insert((function.clone(), path), line);
+    }
+}
+
+/// Whether `text` only labels a block of the op before `child`, rather than
+/// being the statement `child` comes from
+fn is_block_label(text: &str, child: &Op) -> bool {
+    match text {
+        "else:" => !matches!(child, Op::Else(_)),
+        "condition:" | "value:" | "default:" => true,
+        _ => text.starts_with("case ") && text.ends_with(':'),
+    }
+}
+
+/// Run a DSL program once for each set of parameters in `runs`, or once
+/// without parameters if there are none, and report their combined coverage
+///
+/// Each run is an admin of [`COVERAGE_NAMESPACE`] over fresh in-memory
+/// storage, with its parameters as variables. A run that fails still counts towards coverage, so its error is
+/// returned alongside the report.
+pub fn coverage_dsl(
+    source: &str,
+    runs: &[HashMap<String, String>],
+) -> Result<(CoverageReport, Vec<Option<VMError>>), Box<dyn Error>> {
+    let (ops, _, _) = parse_dsl_with_lines(source)?;
+    let coverage = Coverage::new();
+    let no_parameters = [HashMap::new()];
+    let runs = if runs.is_empty() {
+        &no_parameters[..]
+    } else {
+        runs
+    };
+
+    let mut errors = Vec::new();
+    for parameters in runs {
+        let mut auth = AuthContext::new("did:icn:coverage");
+        auth.add_role(COVERAGE_NAMESPACE, "admin");
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(auth);
+        vm.set_namespace(COVERAGE_NAMESPACE);
+        // Through memory, so the program can `load` them
+        vm.memory.set_parameters(parameters.clone());
+        vm.set_coverage(Some(coverage.clone()));
+        errors.push(vm.execute(&ops).err());
+    }
+    Ok((coverage.report(source)?, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_of_branches_loops_and_functions() {
+        let source = "def bump(x):\n    load x\n    push 1\n    add\n    return\n\n\
+                      # Approve big enough budgets\nload budget\npush 100\ngt\nif:\n    \
+                      push 1\n    call bump\n    emit \"approved\"\nelse:\n    \
+                      emit \"rejected\"\nloop 2:\n    push 0\n    pop\n";
+        let run = |budget: &str| HashMap::from([("budget".to_string(), budget.to_string())]);
+
+        let (report, errors) = coverage_dsl(source, &[run("500")]).unwrap();
+        assert!(errors.iter().all(Option::is_none), "{:?}", errors);
+        let hits = |line: usize| report.lines[line - 1].hits;
+        assert_eq!(hits(6), None);
+        assert_eq!(hits(7), None);
+        assert_eq!(hits(8), Some(1));
+        // Function bodies count when called, on the lines of the def
+        assert_eq!(hits(1), Some(1));
+        assert_eq!(hits(2), Some(1));
+        assert_eq!(hits(13), Some(1));
+        assert_eq!(hits(15), None);
+        assert_eq!(hits(16), Some(0));
+        assert_eq!(hits(18), Some(2));
+
+        let taken: Vec<(usize, &str, u64)> = report
+            .branches
+            .iter()
+            .map(|b| (b.line, b.branch.as_str(), b.hits))
+            .collect();
+        assert_eq!(
+            taken,
+            vec![(11, "then", 1), (11, "else", 0), (17, "body", 2)]
+        );
+        assert_eq!(report.lines_total, report.lines_covered + 1);
+
+        // A second scenario covers the other branch
+        let (report, _) = coverage_dsl(source, &[run("500"), run("50")]).unwrap();
+        assert_eq!(report.branches_covered, report.branches_total);
+        assert_eq!(report.lines_covered, report.lines_total);
+        assert_eq!(report.uncovered_branches().count(), 0);
+    }
+}
@@ -1,17 +1,90 @@
 #![allow(dead_code)] // Allow dead code during development
 
+//! Structured events and the sinks that receive them
+//!
+//! [`Event::emit`] prints an event to stdout and hands it to every
+//! registered [`EventSink`]. Events raised by `EmitEvent` ops are handed to
+//! the sinks as well, through [`dispatch_vm_event`], so governance activity
+//! can feed external monitoring.
+//!
+//! Every event has a [`Severity`] and a category. Before an event reaches
+//! any sink it must pass the global [`EventFilter`], which sets the lowest
+//! severity kept per category, so that chatty debug events can be dropped
+//! while governance events still get through. Several sinks can then be
+//! active at once, each with a [`CategoryFilter`] choosing the categories
+//! it receives:
+//!
+//! - [`FileSink`] appends to a file, rotating it once it reaches a size
+//! - [`SyslogSink`] sends RFC 5424 messages to a local or remote syslog
+//! - [`WebhookSink`] POSTs each event as JSON to an HTTP endpoint
+//!
+//! Filters and sinks are usually set up from the `[events]` config section
+//! with [`configure`]. A sink that fails does not stop delivery to the
+//! others.
+
+use crate::config::{EventSinkConfig, EventsConfig};
+use crate::vm::types::VMEvent;
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::fs::OpenOptions;
-use std::io::{self, Write};
-use std::path::Path;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use std::time::Duration;
+
+/// How much an event matters, from least to most
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Debug => "debug",
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "debug" => Ok(Severity::Debug),
+            "info" => Ok(Severity::Info),
+            "warn" | "warning" => Ok(Severity::Warn),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("unknown severity: {}", s)),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
-    pub level: String, // "info", "warn", "error"
-    pub tag: String,   // e.g., "emit", "stack", "memory", "governance"
+    #[serde(alias = "level")]
+    pub severity: Severity,
+    /// e.g., "emit", "stack", "memory", "governance:vote"
+    #[serde(alias = "tag")]
+    pub category: String,
     pub message: String,
     pub timestamp: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -25,19 +98,36 @@ pub enum LogFormat {
 }
 
 static LOG_FORMAT: Lazy<Mutex<LogFormat>> = Lazy::new(|| Mutex::new(LogFormat::Pretty));
-static LOG_FILE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+static SINKS: Lazy<Mutex<Vec<RegisteredSink>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Name of the sink registered by [`set_log_file`]
+pub const LOG_FILE_SINK: &str = "log-file";
+
+fn lock_error<T>(what: &str, e: T) -> io::Error
+where
+    T: std::fmt::Debug,
+{
+    io::Error::other(format!("Failed to lock {}: {:?}", what, e))
+}
+
+fn log_format() -> io::Result<LogFormat> {
+    LOG_FORMAT
+        .lock()
+        .map(|format| *format)
+        .map_err(|e| lock_error("LOG_FORMAT", e))
+}
 
 impl Event {
-    pub fn new<S1: Into<String>, S2: Into<String>, S3: Into<String>>(
-        level: S1,
-        tag: S2,
-        message: S3,
+    pub fn new<S1: Into<String>, S2: Into<String>>(
+        severity: Severity,
+        category: S1,
+        message: S2,
     ) -> Self {
         let now: DateTime<Utc> = Utc::now();
 
         Self {
-            level: level.into(),
-            tag: tag.into(),
+            severity,
+            category: category.into(),
             message: message.into(),
             timestamp: now.to_rfc3339(),
             data: None,
@@ -49,41 +139,42 @@ impl Event {
         self
     }
 
-    pub fn info<S1: Into<String>, S2: Into<String>>(tag: S1, message: S2) -> Self {
-        Self::new("info", tag, message)
+    pub fn debug<S1: Into<String>, S2: Into<String>>(category: S1, message: S2) -> Self {
+        Self::new(Severity::Debug, category, message)
     }
 
-    pub fn warn<S1: Into<String>, S2: Into<String>>(tag: S1, message: S2) -> Self {
-        Self::new("warn", tag, message)
+    pub fn info<S1: Into<String>, S2: Into<String>>(category: S1, message: S2) -> Self {
+        Self::new(Severity::Info, category, message)
     }
 
-    pub fn error<S1: Into<String>, S2: Into<String>>(tag: S1, message: S2) -> Self {
-        Self::new("error", tag, message)
+    pub fn warn<S1: Into<String>, S2: Into<String>>(category: S1, message: S2) -> Self {
+        Self::new(Severity::Warn, category, message)
     }
 
-    pub fn emit(&self) -> io::Result<()> {
-        let format = LOG_FORMAT.lock()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to lock LOG_FORMAT: {:?}", e)))?;
-        let log_file = LOG_FILE.lock()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to lock LOG_FILE: {:?}", e)))?
-            .clone();
+    pub fn error<S1: Into<String>, S2: Into<String>>(category: S1, message: S2) -> Self {
+        Self::new(Severity::Error, category, message)
+    }
 
-        match *format {
-            LogFormat::Pretty => self.emit_pretty(log_file),
-            LogFormat::Json => self.emit_json(log_file),
+    /// Print the event to stdout and deliver it to the registered sinks
+    pub fn emit(&self) -> io::Result<()> {
+        match log_format()? {
+            LogFormat::Pretty => println!("{}", self.to_pretty_line()),
+            LogFormat::Json => println!("{}", self.to_json_line()?),
         }
+        dispatch(self)
     }
 
-    fn emit_pretty(&self, log_file: Option<String>) -> io::Result<()> {
-        let level_color = match self.level.as_str() {
-            "info" => "\x1b[32m",  // Green
-            "warn" => "\x1b[33m",  // Yellow
-            "error" => "\x1b[31m", // Red
-            _ => "\x1b[0m",        // Default
+    fn to_pretty_line(&self) -> String {
+        let level_color = match self.severity {
+            Severity::Info => "\x1b[32m",  // Green
+            Severity::Warn => "\x1b[33m",  // Yellow
+            Severity::Error => "\x1b[31m", // Red
+            Severity::Debug => "\x1b[0m",  // Default
         };
 
         // Safely extract the time portion from the timestamp
-        let time_str = self.timestamp
+        let time_str = self
+            .timestamp
             .split('T')
             .nth(1)
             .unwrap_or(&self.timestamp)
@@ -91,79 +182,657 @@ impl Event {
             .next()
             .unwrap_or("");
 
-        let pretty_line = format!(
+        format!(
             "{}{} [{}] [{}] {}\x1b[0m",
             level_color,
             time_str,
-            self.level.to_uppercase(),
-            self.tag,
+            self.severity.as_str().to_uppercase(),
+            self.category,
             self.message
-        );
+        )
+    }
 
-        // Always print to stdout
-        println!("{}", pretty_line);
+    fn to_plain_line(&self) -> String {
+        format!(
+            "{} [{}] [{}] {}",
+            self.timestamp,
+            self.severity.as_str().to_uppercase(),
+            self.category,
+            self.message
+        )
+    }
 
-        // If log file is specified, write to it
-        if let Some(file_path) = log_file {
-            let plain_line = format!(
-                "{} [{}] [{}] {}",
-                self.timestamp,
-                self.level.to_uppercase(),
-                self.tag,
-                self.message
-            );
+    fn to_json_line(&self) -> io::Result<String> {
+        serde_json::to_string(&self)
+            .map_err(|e| io::Error::other(format!("Failed to serialize event: {}", e)))
+    }
+}
 
-            append_to_file(&file_path, &plain_line)?;
+impl From<&VMEvent> for Event {
+    fn from(event: &VMEvent) -> Self {
+        let timestamp = DateTime::<Utc>::from_timestamp(event.timestamp as i64, 0)
+            .unwrap_or_else(Utc::now)
+            .to_rfc3339();
+        Self {
+            severity: event.severity,
+            category: event.category.clone(),
+            message: event.message.clone(),
+            timestamp,
+            data: None,
         }
+    }
+}
 
-        Ok(())
+/// Set the format of events printed to stdout and of the [`set_log_file`]
+/// file
+pub fn set_log_format(format: LogFormat) -> io::Result<()> {
+    let mut log_format = LOG_FORMAT.lock().map_err(|e| lock_error("LOG_FORMAT", e))?;
+    *log_format = format;
+    Ok(())
+}
+
+/// Also write every event to `file_path`, or stop doing so with `None`
+pub fn set_log_file(file_path: Option<String>) -> io::Result<()> {
+    match file_path {
+        Some(path) => register_sink(
+            LOG_FILE_SINK,
+            CategoryFilter::all(),
+            Box::new(FileSink::new(path)),
+        ),
+        None => remove_sink(LOG_FILE_SINK).map(|_| ()),
     }
+}
 
-    fn emit_json(&self, log_file: Option<String>) -> io::Result<()> {
-        let json = serde_json::to_string(&self)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to serialize event: {}", e)))?;
+/// A destination for events
+pub trait EventSink: Send {
+    /// Deliver one event
+    fn write(&mut self, event: &Event) -> io::Result<()>;
+}
 
-        // Always print to stdout
-        println!("{}", json);
+/// A set of event categories
+///
+/// A pattern ending in `*` matches categories starting with the rest of it, so
+/// `governance*` matches `governance` and `governance:vote`. A filter with
+/// no patterns matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CategoryFilter {
+    patterns: Vec<String>,
+}
+
+impl CategoryFilter {
+    /// A filter that matches every event
+    pub fn all() -> Self {
+        Self::default()
+    }
 
-        // If log file is specified, write to it
-        if let Some(file_path) = log_file {
-            append_to_file(&file_path, &json)?;
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            patterns: patterns.into_iter().map(Into::into).collect(),
         }
+    }
 
-        Ok(())
+    pub fn matches(&self, category: &str) -> bool {
+        self.patterns.is_empty()
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => category.starts_with(prefix),
+                    None => pattern == category,
+                })
     }
 }
 
-pub fn set_log_format(format: LogFormat) -> io::Result<()> {
-    let mut log_format = LOG_FORMAT.lock()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to lock LOG_FORMAT: {:?}", e)))?;
-    *log_format = format;
+/// The lowest severity kept for each category, checked before any sink
+///
+/// The first rule whose categories match an event's category decides;
+/// events no rule matches are kept from `default_min` up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFilter {
+    rules: Vec<(CategoryFilter, Severity)>,
+    default_min: Severity,
+}
+
+impl Default for EventFilter {
+    /// Keep everything
+    fn default() -> Self {
+        Self::new(Severity::Debug)
+    }
+}
+
+impl EventFilter {
+    pub fn new(default_min: Severity) -> Self {
+        Self {
+            rules: Vec::new(),
+            default_min,
+        }
+    }
+
+    /// Keep events in `categories` from `min` up
+    pub fn with_rule(mut self, categories: CategoryFilter, min: Severity) -> Self {
+        self.rules.push((categories, min));
+        self
+    }
+
+    /// The lowest severity kept for `category`
+    pub fn min_severity(&self, category: &str) -> Severity {
+        self.rules
+            .iter()
+            .find(|(categories, _)| categories.matches(category))
+            .map_or(self.default_min, |(_, min)| *min)
+    }
+
+    pub fn allows(&self, event: &Event) -> bool {
+        event.severity >= self.min_severity(&event.category)
+    }
+}
+
+static FILTER: Lazy<Mutex<EventFilter>> = Lazy::new(|| Mutex::new(EventFilter::default()));
+
+/// Replace the filter events must pass before reaching any sink
+pub fn set_filter(filter: EventFilter) -> io::Result<()> {
+    let mut current = FILTER.lock().map_err(|e| lock_error("FILTER", e))?;
+    *current = filter;
     Ok(())
 }
 
-pub fn set_log_file(file_path: Option<String>) -> io::Result<()> {
-    let mut log_file = LOG_FILE.lock()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("Failed to lock LOG_FILE: {:?}", e)))?;
-    *log_file = file_path;
+struct RegisteredSink {
+    name: String,
+    filter: CategoryFilter,
+    sink: Box<dyn EventSink>,
+}
+
+/// Deliver events matching `filter` to `sink`, replacing any sink already
+/// registered as `name`
+pub fn register_sink(
+    name: &str,
+    filter: CategoryFilter,
+    sink: Box<dyn EventSink>,
+) -> io::Result<()> {
+    let mut sinks = SINKS.lock().map_err(|e| lock_error("SINKS", e))?;
+    sinks.retain(|registered| registered.name != name);
+    sinks.push(RegisteredSink {
+        name: name.to_string(),
+        filter,
+        sink,
+    });
     Ok(())
 }
 
-fn append_to_file(file_path: &str, content: &str) -> io::Result<()> {
-    let path = Path::new(file_path);
+/// Stop delivering events to the sink registered as `name`, returning
+/// whether there was one
+pub fn remove_sink(name: &str) -> io::Result<bool> {
+    let mut sinks = SINKS.lock().map_err(|e| lock_error("SINKS", e))?;
+    let before = sinks.len();
+    sinks.retain(|registered| registered.name != name);
+    Ok(sinks.len() != before)
+}
 
+/// Names of the registered sinks, in registration order
+pub fn sink_names() -> io::Result<Vec<String>> {
+    let sinks = SINKS.lock().map_err(|e| lock_error("SINKS", e))?;
+    Ok(sinks
+        .iter()
+        .map(|registered| registered.name.clone())
+        .collect())
+}
+
+/// Deliver `event`, if it passes the [`EventFilter`], to every registered
+/// sink whose filter matches its category
+///
+/// Every matching sink is tried; the first failure is returned.
+pub fn dispatch(event: &Event) -> io::Result<()> {
+    let allowed = FILTER
+        .lock()
+        .map_err(|e| lock_error("FILTER", e))?
+        .allows(event);
+    if !allowed {
+        return Ok(());
+    }
+    let mut sinks = SINKS.lock().map_err(|e| lock_error("SINKS", e))?;
+    let mut result = Ok(());
+    for registered in sinks.iter_mut() {
+        if !registered.filter.matches(&event.category) {
+            continue;
+        }
+        if let Err(e) = registered.sink.write(event) {
+            if result.is_ok() {
+                result = Err(io::Error::new(
+                    e.kind(),
+                    format!("Event sink {} failed: {}", registered.name, e),
+                ));
+            }
+        }
+    }
+    result
+}
+
+/// Deliver an event raised by an `EmitEvent` op to the registered sinks
+///
+/// Failures are logged rather than returned, so a sink that is down does
+/// not stop the program.
+pub fn dispatch_vm_event(event: &VMEvent) {
+    if let Err(e) = dispatch(&Event::from(event)) {
+        tracing::warn!(category = %event.category, error = %e, "Failed to deliver event");
+    }
+}
+
+/// Set the filter and register the sinks described by the `[events]`
+/// config section
+///
+/// Sinks are named `config-0`, `config-1`, ... in order, replacing the
+/// sinks of an earlier call.
+pub fn configure(config: &EventsConfig) -> io::Result<()> {
+    let filter =
+        config
+            .filters
+            .iter()
+            .fold(EventFilter::new(config.min_severity), |filter, rule| {
+                filter.with_rule(
+                    CategoryFilter::new(rule.categories.iter().cloned()),
+                    rule.min_severity,
+                )
+            });
+    set_filter(filter)?;
+
+    for (index, config) in config.sinks.iter().enumerate() {
+        let sink: Box<dyn EventSink> = match config {
+            EventSinkConfig::File {
+                path,
+                max_bytes,
+                max_files,
+                ..
+            } => {
+                let mut sink = FileSink::new(path).with_format(LogFormat::Json);
+                if let Some(max_bytes) = max_bytes {
+                    sink = sink.with_rotation(*max_bytes, *max_files);
+                }
+                Box::new(sink)
+            }
+            EventSinkConfig::Syslog { address, .. } => {
+                Box::new(SyslogSink::connect(address.as_deref())?)
+            }
+            EventSinkConfig::Webhook { url, .. } => Box::new(WebhookSink::new(url)?),
+        };
+        let filter = CategoryFilter::new(config.categories().iter().cloned());
+        register_sink(&format!("config-{}", index), filter, sink)?;
+    }
+    Ok(())
+}
+
+/// Appends events to a file, one per line
+pub struct FileSink {
+    path: PathBuf,
+    /// Line format; follows [`set_log_format`] when unset
+    format: Option<LogFormat>,
+    rotation: Option<Rotation>,
+}
+
+/// When and how far a [`FileSink`] rotates its file
+#[derive(Debug, Clone, Copy)]
+struct Rotation {
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl FileSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: None,
+            rotation: None,
+        }
+    }
+
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Before a write would take the file past `max_bytes`, rename it to
+    /// `<path>.1`, shifting older files up to `<path>.<max_files>` and
+    /// dropping the oldest
+    pub fn with_rotation(mut self, max_bytes: u64, max_files: usize) -> Self {
+        self.rotation = Some(Rotation {
+            max_bytes,
+            max_files,
+        });
+        self
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&self, rotation: Rotation) -> io::Result<()> {
+        if rotation.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        let oldest = self.rotated_path(rotation.max_files);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for index in (1..rotation.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+}
+
+impl EventSink for FileSink {
+    fn write(&mut self, event: &Event) -> io::Result<()> {
+        let line = match self.format.map_or_else(log_format, Ok)? {
+            LogFormat::Pretty => event.to_plain_line(),
+            LogFormat::Json => event.to_json_line()?,
+        };
+        if let Some(rotation) = self.rotation {
+            let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+            if size > 0 && size + line.len() as u64 + 1 > rotation.max_bytes {
+                self.rotate(rotation)?;
+            }
+        }
+        append_to_file(&self.path, &line)
+    }
+}
+
+/// Sends events to syslog as RFC 5424 messages from the `user` facility
+pub struct SyslogSink {
+    transport: SyslogTransport,
+    hostname: String,
+}
+
+enum SyslogTransport {
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixDatagram),
+}
+
+/// Syslog socket used when no address is configured
+pub const DEFAULT_SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Syslog address used when there is no local socket
+pub const DEFAULT_SYSLOG_ADDRESS: &str = "127.0.0.1:514";
+
+impl SyslogSink {
+    /// Connect to `address`: a Unix socket path, or a `host:port` reached
+    /// over UDP. Without one, the local `/dev/log` socket is used if it
+    /// exists, and UDP to `127.0.0.1:514` otherwise.
+    pub fn connect(address: Option<&str>) -> io::Result<Self> {
+        let address = match address {
+            Some(address) => address,
+            None if Path::new(DEFAULT_SYSLOG_SOCKET).exists() => DEFAULT_SYSLOG_SOCKET,
+            None => DEFAULT_SYSLOG_ADDRESS,
+        };
+        let transport = if address.starts_with('/') {
+            Self::unix_transport(address)?
+        } else {
+            let socket = UdpSocket::bind("0.0.0.0:0")?;
+            socket.connect(address)?;
+            SyslogTransport::Udp(socket)
+        };
+        Ok(Self {
+            transport,
+            hostname: std::env::var("HOSTNAME").unwrap_or_else(|_| "-".to_string()),
+        })
+    }
+
+    #[cfg(unix)]
+    fn unix_transport(path: &str) -> io::Result<SyslogTransport> {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(SyslogTransport::Unix(socket))
+    }
+
+    #[cfg(not(unix))]
+    fn unix_transport(path: &str) -> io::Result<SyslogTransport> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unix syslog sockets are not supported here: {}", path),
+        ))
+    }
+
+    fn format_message(&self, event: &Event) -> String {
+        // Facility 1 (user) with the event's severity
+        let severity = match event.severity {
+            Severity::Error => 3,
+            Severity::Warn => 4,
+            Severity::Info => 6,
+            Severity::Debug => 7,
+        };
+        format!(
+            "<{}>1 {} {} icn-covm {} - - [{}] {}",
+            8 + severity,
+            event.timestamp,
+            self.hostname,
+            std::process::id(),
+            event.category,
+            event.message
+        )
+    }
+}
+
+impl EventSink for SyslogSink {
+    fn write(&mut self, event: &Event) -> io::Result<()> {
+        let message = self.format_message(event);
+        match &self.transport {
+            SyslogTransport::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            #[cfg(unix)]
+            SyslogTransport::Unix(socket) => socket.send(message.as_bytes()).map(|_| ()),
+        }
+    }
+}
+
+/// POSTs each event as JSON to a plain `http://` URL
+///
+/// Delivery is synchronous with a short timeout; anything but a 2xx
+/// response counts as a failure. For HTTPS endpoints, point the sink at a
+/// local relay that terminates TLS.
+pub struct WebhookSink {
+    host: String,
+    port: u16,
+    path: String,
+    timeout: Duration,
+}
+
+/// How long a webhook may take to connect and respond
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(2);
+
+impl WebhookSink {
+    pub fn new(url: &str) -> io::Result<Self> {
+        let invalid = |details: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid webhook URL {}: {}", url, details),
+            )
+        };
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("only http:// URLs are supported"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid("bad port"))?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            timeout: WEBHOOK_TIMEOUT,
+        })
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut last_error = None;
+        for addr in (self.host.as_str(), self.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No address for {}", self.host),
+            )
+        }))
+    }
+}
+
+impl EventSink for WebhookSink {
+    fn write(&mut self, event: &Event) -> io::Result<()> {
+        let body = event.to_json_line()?;
+        let mut stream = self.connect()?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            self.port,
+            body.len(),
+            body
+        )?;
+        stream.flush()?;
+
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line)?;
+        let status = status_line.split_whitespace().nth(1).unwrap_or("");
+        if status.starts_with('2') {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "Webhook responded with {:?}",
+                status_line.trim()
+            )))
+        }
+    }
+}
+
+fn append_to_file(file_path: &Path, content: &str) -> io::Result<()> {
     // Create parent directories if they don't exist
-    if let Some(parent) = path.parent() {
-        if !parent.exists() {
-            std::fs::create_dir_all(parent)?;
+    if let Some(parent) = file_path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent)?;
         }
     }
 
     // Open file with append mode
-    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file_path)?;
 
     // Write content with newline
     writeln!(file, "{}", content)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_category_filter() {
+        let filter = CategoryFilter::new(["governance*", "audit"]);
+        assert!(filter.matches("governance"));
+        assert!(filter.matches("governance:vote"));
+        assert!(filter.matches("audit"));
+        assert!(!filter.matches("audit:read"));
+        assert!(!filter.matches("emit"));
+        assert!(CategoryFilter::all().matches("emit"));
+    }
+
+    #[test]
+    fn test_event_filter_by_severity() {
+        let filter = EventFilter::new(Severity::Warn)
+            .with_rule(CategoryFilter::new(["governance:*"]), Severity::Info)
+            .with_rule(CategoryFilter::new(["governance*"]), Severity::Error);
+        assert!(filter.allows(&Event::info("governance:vote", "vote cast")));
+        assert!(!filter.allows(&Event::debug("governance:vote", "tally step")));
+        assert!(!filter.allows(&Event::warn("governance", "quorum low")));
+        assert!(!filter.allows(&Event::info("economic", "minted")));
+        assert!(filter.allows(&Event::warn("economic", "overdraft")));
+        assert!(EventFilter::default().allows(&Event::debug("stack", "push")));
+
+        assert_eq!("WARNING".parse::<Severity>(), Ok(Severity::Warn));
+        let old: Event = serde_json::from_str(
+            r#"{"level":"error","tag":"audit","message":"m","timestamp":"t"}"#,
+        )
+        .unwrap();
+        assert_eq!(old.severity, Severity::Error);
+        assert_eq!(old.category, "audit");
+    }
+
+    #[test]
+    fn test_file_sink_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        let mut sink = FileSink::new(&path)
+            .with_format(LogFormat::Json)
+            .with_rotation(200, 2);
+        for i in 0..6 {
+            sink.write(&Event::info("governance", format!("event {}", i)))
+                .unwrap();
+        }
+
+        let current = fs::read_to_string(&path).unwrap();
+        assert!(current.contains("event 5"));
+        assert!(dir.path().join("events.log.1").exists());
+        assert!(dir.path().join("events.log.2").exists());
+        assert!(!dir.path().join("events.log.3").exists());
+        for line in current.lines() {
+            let event: Event = serde_json::from_str(line).unwrap();
+            assert_eq!(event.category, "governance");
+        }
+    }
+
+    #[test]
+    fn test_webhook_sink_posts_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains("\"governance\"") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let mut sink = WebhookSink::new(&format!("http://127.0.0.1:{}/hooks/icn", port)).unwrap();
+        sink.write(&Event::info("governance", "proposal passed"))
+            .unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /hooks/icn HTTP/1.1"));
+        assert!(request.contains("proposal passed"));
+
+        assert!(WebhookSink::new("https://example.com").is_err());
+    }
+}
@@ -43,6 +43,15 @@ pub enum NetworkEvent {
     /// A vote was received from the network
     VoteReceived,
 
+    /// An execution acknowledgment was sent to peers
+    ExecutionAckSent,
+
+    /// A valid execution acknowledgment was received from the network
+    ExecutionAckReceived,
+
+    /// Connected peers were asked for missing ledger nodes
+    LedgerSyncRequested,
+
     /// Error occurred in the network layer
     Error(String),
 }
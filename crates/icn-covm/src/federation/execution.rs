@@ -0,0 +1,233 @@
+//! Executing federated proposals and tracking who applied the result
+//!
+//! `federation execute-proposal` counts the votes on a proposal by instant
+//! runoff and records the execution on the DAG ledger. Each cooperative in
+//! the proposal's scope then applies the result on its own node, and one of
+//! its members signs an [`ExecutionAck`] (`federation ack`) that is recorded
+//! as an `ExecutionAck` ledger node and sent to peers. Running
+//! `execute-proposal` again reports which cooperatives have acknowledged the
+//! result and which have not.
+
+use crate::federation::messages::{ExecutionAck, FederatedProposal, ProposalScope};
+use icn_ledger::{DagNode, NodeData};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The winning option of ranked ballots by instant runoff
+///
+/// Each ballot holds one preference value per option, higher meaning more
+/// preferred, and counts for its most preferred option still in the running.
+/// A ballot that prefers none of those over the others abstains for the
+/// round. The option with the fewest votes is dropped (the later option on a
+/// tie) until one holds a majority. Returns `None` when no ballot expresses a
+/// preference.
+pub fn instant_runoff(ballots: &[Vec<f64>], options: usize) -> Option<usize> {
+    let mut running: Vec<usize> = (0..options).collect();
+    loop {
+        let mut counts = vec![0usize; options];
+        for ballot in ballots {
+            let preference = |option: &usize| ballot.get(*option).copied().unwrap_or(0.0);
+            let Some(top) = running
+                .iter()
+                .copied()
+                .max_by(|a, b| preference(a).total_cmp(&preference(b)).then(b.cmp(a)))
+            else {
+                break;
+            };
+            if running
+                .iter()
+                .any(|option| preference(option) < preference(&top))
+            {
+                counts[top] += 1;
+            }
+        }
+
+        let total: usize = running.iter().map(|&option| counts[option]).sum();
+        if total == 0 {
+            return None;
+        }
+        let leader = *running.iter().max_by_key(|&&option| counts[option])?;
+        if counts[leader] * 2 > total || running.len() <= 2 {
+            // Two options left without a majority means a tie; the earlier wins
+            return running
+                .iter()
+                .copied()
+                .find(|&option| counts[option] == counts[leader]);
+        }
+        let last = *running.iter().rev().min_by_key(|&&option| counts[option])?;
+        running.retain(|&option| option != last);
+    }
+}
+
+/// The cooperatives expected to apply a proposal's result, or `None` when
+/// its scope is the whole federation
+pub fn expected_coops(scope: &ProposalScope) -> Option<Vec<String>> {
+    match scope {
+        ProposalScope::SingleCoop(coop) => Some(vec![coop.clone()]),
+        ProposalScope::MultiCoop(coops) => Some(coops.clone()),
+        ProposalScope::GlobalFederation => None,
+    }
+}
+
+/// A cooperative's latest valid acknowledgment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Acknowledgment {
+    pub coop_id: String,
+    pub signer: String,
+    pub outcome: String,
+    /// Whether the cooperative applied the outcome the votes decided
+    pub matches_outcome: bool,
+}
+
+/// Which cooperatives have acknowledged a proposal's execution
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AckReport {
+    pub proposal_id: String,
+    /// The winning option, if any ballot expressed a preference
+    pub outcome: Option<String>,
+    pub acknowledged: Vec<Acknowledgment>,
+    /// Cooperatives in scope without an acknowledgment; always empty for
+    /// federation-wide proposals, which have no fixed list
+    pub missing: Vec<String>,
+    /// Acknowledgments on the ledger whose signature did not verify
+    pub invalid: usize,
+}
+
+/// Collect the acknowledgments of `proposal` from ledger `nodes`
+///
+/// Nodes are taken in order, so a cooperative's later acknowledgment
+/// replaces an earlier one. Acknowledgments that fail to verify are counted
+/// but otherwise ignored.
+pub fn ack_report(
+    proposal: &FederatedProposal,
+    outcome: Option<&str>,
+    nodes: &[DagNode],
+) -> AckReport {
+    let mut acknowledged = BTreeMap::new();
+    let mut invalid = 0;
+    for node in nodes {
+        let NodeData::ExecutionAck {
+            proposal_id,
+            coop_id,
+            signer,
+            outcome: applied,
+            signature,
+        } = &node.data
+        else {
+            continue;
+        };
+        if proposal_id != &proposal.proposal_id {
+            continue;
+        }
+        let ack = ExecutionAck {
+            proposal_id: proposal_id.clone(),
+            coop_id: coop_id.clone(),
+            outcome: applied.clone(),
+            signer: signer.clone(),
+            signature: signature.clone(),
+        };
+        if let Err(e) = ack.verify() {
+            tracing::warn!(node = %node.id, error = %e, "Ignoring invalid execution acknowledgment");
+            invalid += 1;
+            continue;
+        }
+        acknowledged.insert(
+            coop_id.clone(),
+            Acknowledgment {
+                coop_id: ack.coop_id,
+                signer: ack.signer,
+                matches_outcome: outcome == Some(ack.outcome.as_str()),
+                outcome: ack.outcome,
+            },
+        );
+    }
+
+    let missing = expected_coops(&proposal.scope)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|coop| !acknowledged.contains_key(coop))
+        .collect();
+    AckReport {
+        proposal_id: proposal.proposal_id.clone(),
+        outcome: outcome.map(str::to_string),
+        acknowledged: acknowledged.into_values().collect(),
+        missing,
+        invalid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::federation::messages::VotingModel;
+    use crate::identity::signer::Signer;
+    use crate::identity::Identity;
+
+    fn ack_node(ack: &ExecutionAck) -> DagNode {
+        DagNode {
+            id: String::new(),
+            parent_ids: Vec::new(),
+            timestamp: 0,
+            namespace: "federation".to_string(),
+            data: NodeData::ExecutionAck {
+                proposal_id: ack.proposal_id.clone(),
+                coop_id: ack.coop_id.clone(),
+                signer: ack.signer.clone(),
+                outcome: ack.outcome.clone(),
+                signature: ack.signature.clone(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_ack_report_lists_missing_coops() {
+        let ballots = vec![
+            vec![1.0, 0.0],
+            vec![1.0, 0.0],
+            vec![0.0, 1.0],
+            vec![0.0, 0.0],
+        ];
+        assert_eq!(instant_runoff(&ballots, 2), Some(0));
+        assert_eq!(instant_runoff(&[vec![0.0, 0.0]], 2), None);
+        // Option 2 is dropped and its ballot moves to option 1
+        let ballots = vec![
+            vec![3.0, 2.0, 1.0],
+            vec![3.0, 2.0, 1.0],
+            vec![1.0, 3.0, 2.0],
+            vec![1.0, 3.0, 2.0],
+            vec![1.0, 2.0, 3.0],
+        ];
+        assert_eq!(instant_runoff(&ballots, 3), Some(1));
+
+        let proposal = FederatedProposal::new(
+            "prop-1".to_string(),
+            "federation".to_string(),
+            vec!["Yes".to_string(), "No".to_string()],
+            "alice".to_string(),
+            ProposalScope::MultiCoop(vec!["coop-a".to_string(), "coop-b".to_string()]),
+            VotingModel::OneCoopOneVote,
+        );
+        let member = Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        let message = ExecutionAck::canonical_message("prop-1", "coop-a", "Yes");
+        let ack = ExecutionAck {
+            proposal_id: "prop-1".to_string(),
+            coop_id: "coop-a".to_string(),
+            outcome: "Yes".to_string(),
+            signer: member.did().to_string(),
+            signature: member.sign(message.as_bytes()).unwrap(),
+        };
+        assert!(ack.verify().is_ok());
+        let forged = ExecutionAck {
+            coop_id: "coop-b".to_string(),
+            ..ack.clone()
+        };
+        assert!(forged.verify().is_err());
+
+        let report = ack_report(&proposal, Some("Yes"), &[ack_node(&ack), ack_node(&forged)]);
+        assert_eq!(report.acknowledged.len(), 1);
+        assert_eq!(report.acknowledged[0].coop_id, "coop-a");
+        assert!(report.acknowledged[0].matches_outcome);
+        assert_eq!(report.missing, vec!["coop-b".to_string()]);
+        assert_eq!(report.invalid, 1);
+    }
+}
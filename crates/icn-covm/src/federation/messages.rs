@@ -1,3 +1,4 @@
+use crate::identity::{self, IdentityError};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
@@ -18,6 +19,12 @@ pub enum NetworkMessage {
 
     /// Submit a vote for a federated proposal
     VoteSubmission(FederatedVote),
+
+    /// Ask peers for the ledger nodes the sender is missing
+    LedgerSyncRequest(LedgerSyncRequest),
+
+    /// Confirm that a cooperative applied an executed proposal's result
+    ExecutionAck(ExecutionAck),
 }
 
 /// Message announcing a node's presence and capabilities on the network
@@ -36,6 +43,13 @@ pub struct NodeAnnouncement {
     pub name: Option<String>,
 }
 
+/// Request for the ledger nodes a node is missing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerSyncRequest {
+    /// Identifier of the requesting node
+    pub node_id: String,
+}
+
 /// Ping message used to verify node connectivity
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ping {
@@ -190,3 +204,52 @@ pub struct FederatedVote {
     /// Signature to verify the vote's authenticity
     pub signature: String,
 }
+
+impl FederatedVote {
+    /// The message a voter signs for their ranked choices on a proposal
+    pub fn canonical_message(proposal_id: &str, voter: &str, ranked_choices: &[f64]) -> String {
+        format!(
+            "Vote for proposal {} by {} with choices {:?}",
+            proposal_id, voter, ranked_choices
+        )
+    }
+}
+
+/// A cooperative's confirmation that it applied the result of an executed
+/// federated proposal, signed by one of its members
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExecutionAck {
+    /// Proposal whose result was applied
+    pub proposal_id: String,
+
+    /// Cooperative that applied it
+    pub coop_id: String,
+
+    /// Result that was applied, e.g. the winning option
+    pub outcome: String,
+
+    /// DID of the signing member, a `did:key`
+    pub signer: String,
+
+    /// Signature over the canonical message
+    pub signature: String,
+}
+
+impl ExecutionAck {
+    /// The message a member signs to acknowledge `outcome` for their cooperative
+    pub fn canonical_message(proposal_id: &str, coop_id: &str, outcome: &str) -> String {
+        format!(
+            "Cooperative {} applied proposal {} with outcome {}",
+            coop_id, proposal_id, outcome
+        )
+    }
+
+    /// Check the signature against the key the signer's DID names
+    pub fn verify(&self) -> Result<(), IdentityError> {
+        let public_key = self.signer.strip_prefix("did:key:").ok_or_else(|| {
+            IdentityError::VerificationError(format!("{} is not a did:key", self.signer))
+        })?;
+        let message = Self::canonical_message(&self.proposal_id, &self.coop_id, &self.outcome);
+        identity::verify_signature(public_key, message.as_bytes(), &self.signature)
+    }
+}
@@ -6,8 +6,10 @@
 mod behaviour;
 mod error;
 mod events;
+pub mod execution;
 pub mod messages;
 mod node;
+pub mod peer_score;
 pub mod storage;
 #[cfg(test)]
 mod tests;
@@ -15,9 +17,11 @@ mod tests;
 pub use error::FederationError;
 pub use events::NetworkEvent;
 pub use messages::{
-    FederatedProposal, FederatedVote, NetworkMessage, NodeAnnouncement, Ping, Pong,
+    ExecutionAck, FederatedProposal, FederatedVote, LedgerSyncRequest, NetworkMessage,
+    NodeAnnouncement, Ping, Pong,
 };
-pub use node::{NetworkNode, NodeConfig};
+pub use node::{NetworkNode, NodeConfig, NodeHandle, PeerStatus};
+pub use peer_score::{Misbehavior, PeerScore, PeerScores, Standing};
 pub use storage::{FederationStorage, VoteTallyResult, FEDERATION_NAMESPACE, VOTES_NAMESPACE};
 
 /// Protocol name/ID used for ICN-COVM federation
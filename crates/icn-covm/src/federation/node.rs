@@ -2,9 +2,14 @@ use crate::federation::{
     behaviour::{create_behaviour, IcnBehaviour, IcnBehaviourEvent},
     error::FederationError,
     events::NetworkEvent,
-    messages::{FederatedProposal, FederatedVote, NetworkMessage, NodeAnnouncement},
+    messages::{
+        ExecutionAck, FederatedProposal, FederatedVote, LedgerSyncRequest, NetworkMessage,
+        NodeAnnouncement,
+    },
+    peer_score::{PeerScore, PeerScores, Standing},
     storage::FederationStorage,
 };
+use crate::telemetry::metrics;
 
 use futures::{channel::mpsc, stream::StreamExt, SinkExt};
 use libp2p::{
@@ -18,9 +23,10 @@ use libp2p::kad;
 use libp2p::mdns;
 use libp2p::ping;
 
-use log::{debug, error, info, warn};
+use serde::Serialize;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -28,6 +34,7 @@ use std::{
     time::Duration,
 };
 use tokio::sync::Mutex;
+use tracing::{debug, error, info, instrument, warn};
 
 /// Configuration options for a network node
 #[derive(Debug, Clone)]
@@ -46,6 +53,10 @@ pub struct NodeConfig {
 
     /// Protocol version
     pub protocol_version: String,
+
+    /// File peer scores and bans are kept in, so they outlast a restart;
+    /// kept in memory only when `None`
+    pub peer_scores_path: Option<PathBuf>,
 }
 
 impl Default for NodeConfig {
@@ -56,7 +67,146 @@ impl Default for NodeConfig {
             name: None,
             capabilities: Vec::new(),
             protocol_version: "1.0.0".to_string(),
+            peer_scores_path: None,
+        }
+    }
+}
+
+/// Liveness and reputation of a peer, as observed by this node
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerStatus {
+    /// Peer ID of the remote node
+    pub peer_id: String,
+
+    /// Addresses the peer was seen at
+    pub addresses: Vec<String>,
+
+    /// Whether a connection to the peer is open
+    pub connected: bool,
+
+    /// Unix time (seconds) the peer was last heard from
+    pub last_seen: u64,
+
+    /// Round-trip time of the last successful ping
+    pub rtt_ms: Option<u64>,
+
+    /// Number of successful pings
+    pub ping_successes: u64,
+
+    /// Number of failed pings
+    pub ping_failures: u64,
+
+    /// Share of pings answered, between 0 and 1; starts at 0.5 for a new peer
+    pub reputation: f64,
+}
+
+impl PeerStatus {
+    fn new(peer_id: &PeerId) -> Self {
+        Self {
+            peer_id: peer_id.to_string(),
+            addresses: Vec::new(),
+            connected: false,
+            last_seen: crate::storage::utils::now_with_default(),
+            rtt_ms: None,
+            ping_successes: 0,
+            ping_failures: 0,
+            reputation: 0.5,
+        }
+    }
+
+    fn add_address(&mut self, addr: &Multiaddr) {
+        let addr = addr.to_string();
+        if !self.addresses.contains(&addr) {
+            self.addresses.push(addr);
+        }
+    }
+
+    fn record_ping(&mut self, rtt: Option<Duration>) {
+        match rtt {
+            Some(rtt) => {
+                self.ping_successes += 1;
+                self.rtt_ms = Some(rtt.as_millis() as u64);
+                self.last_seen = crate::storage::utils::now_with_default();
+            }
+            None => self.ping_failures += 1,
         }
+        // Smoothed so a single ping does not swing a new peer to 0 or 1
+        self.reputation = (self.ping_successes as f64 + 1.0)
+            / ((self.ping_successes + self.ping_failures) as f64 + 2.0);
+    }
+}
+
+type PeerTable = Arc<Mutex<HashMap<PeerId, PeerStatus>>>;
+
+/// Requests sent to a running node through a `NodeHandle`
+#[derive(Debug)]
+enum NodeCommand {
+    AddBootstrapNode(Multiaddr),
+    BroadcastProposal(FederatedProposal),
+    SyncLedger,
+}
+
+/// Handle for inspecting and controlling a running `NetworkNode`
+///
+/// Commands are queued for the node's event loop, so they take effect once
+/// the node is started and do not wait for the network.
+#[derive(Debug, Clone)]
+pub struct NodeHandle {
+    local_peer_id: PeerId,
+    commands: mpsc::Sender<NodeCommand>,
+    peers: PeerTable,
+    peer_scores: Arc<Mutex<PeerScores>>,
+    bootstrap_nodes: Arc<Mutex<Vec<Multiaddr>>>,
+}
+
+impl NodeHandle {
+    /// Get the local peer ID
+    pub fn local_peer_id(&self) -> &PeerId {
+        &self.local_peer_id
+    }
+
+    /// Peers this node has discovered or connected to, ordered by peer ID
+    pub async fn peers(&self) -> Vec<PeerStatus> {
+        let mut peers: Vec<PeerStatus> = self.peers.lock().await.values().cloned().collect();
+        peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        peers
+    }
+
+    /// Scores of the peers that have sent this node messages, ordered by
+    /// peer ID
+    pub async fn peer_scores(&self) -> Vec<PeerScore> {
+        self.peer_scores.lock().await.all()
+    }
+
+    /// Bootstrap nodes the node dials, including any added since it started
+    pub async fn bootstrap_nodes(&self) -> Vec<Multiaddr> {
+        self.bootstrap_nodes.lock().await.clone()
+    }
+
+    /// Add a bootstrap node and dial it
+    pub async fn add_bootstrap_node(&self, addr: Multiaddr) -> Result<(), FederationError> {
+        self.send(NodeCommand::AddBootstrapNode(addr)).await
+    }
+
+    /// Broadcast a proposal to the connected peers
+    pub async fn broadcast_proposal(
+        &self,
+        proposal: FederatedProposal,
+    ) -> Result<(), FederationError> {
+        self.send(NodeCommand::BroadcastProposal(proposal)).await
+    }
+
+    /// Ask the connected peers for the ledger nodes this node is missing
+    pub async fn sync_ledger(&self) -> Result<(), FederationError> {
+        self.send(NodeCommand::SyncLedger).await
+    }
+
+    async fn send(&self, command: NodeCommand) -> Result<(), FederationError> {
+        self.commands
+            .clone()
+            .send(command)
+            .await
+            .map_err(|_| FederationError::NetworkError("Network node has stopped".to_string()))
     }
 }
 
@@ -83,6 +233,21 @@ pub struct NetworkNode {
     /// Store tracking known peers
     known_peers: Arc<Mutex<HashSet<PeerId>>>,
 
+    /// Liveness and reputation of discovered peers
+    peer_status: PeerTable,
+
+    /// Scores peers earned by the messages they sent
+    peer_scores: Arc<Mutex<PeerScores>>,
+
+    /// Bootstrap nodes, starting with those in the configuration
+    bootstrap_nodes: Arc<Mutex<Vec<Multiaddr>>>,
+
+    /// Channel for receiving commands from `NodeHandle`s
+    command_receiver: mpsc::Receiver<NodeCommand>,
+
+    /// Channel handed to `NodeHandle`s; held so the receiver never closes
+    command_sender: mpsc::Sender<NodeCommand>,
+
     /// Storage for federation proposals and votes
     federation_storage: Arc<FederationStorage>,
 }
@@ -106,6 +271,12 @@ impl NetworkNode {
 
         // Create a channel for network events
         let (event_sender, event_receiver) = mpsc::channel::<NetworkEvent>(32);
+        let (command_sender, command_receiver) = mpsc::channel::<NodeCommand>(32);
+        let bootstrap_nodes = Arc::new(Mutex::new(config.bootstrap_nodes.clone()));
+        let peer_scores = match &config.peer_scores_path {
+            Some(path) => PeerScores::load(path)?,
+            None => PeerScores::default(),
+        };
 
         Ok(Self {
             swarm,
@@ -115,11 +286,17 @@ impl NetworkNode {
             event_receiver,
             event_sender,
             known_peers: Arc::new(Mutex::new(HashSet::new())),
+            peer_status: Arc::new(Mutex::new(HashMap::new())),
+            peer_scores: Arc::new(Mutex::new(peer_scores)),
+            bootstrap_nodes,
+            command_receiver,
+            command_sender,
             federation_storage: Arc::new(FederationStorage::new()),
         })
     }
 
     /// Start the network node and begin processing events
+    #[instrument(skip_all, fields(peer_id = %self.local_peer_id, node = ?self.config.name))]
     pub async fn start(&mut self) -> Result<(), FederationError> {
         if self.running.load(Ordering::SeqCst) {
             return Ok(());
@@ -148,14 +325,9 @@ impl NetworkNode {
         }
 
         // Connect to bootstrap nodes
-        for addr in &self.config.bootstrap_nodes {
-            debug!("Dialing bootstrap node: {}", addr);
-            match self.swarm.dial(addr.clone()) {
-                Ok(_) => {}
-                Err(e) => {
-                    warn!("Failed to dial bootstrap node {}: {}", addr, e);
-                }
-            }
+        let bootstrap_nodes = self.bootstrap_nodes.lock().await.clone();
+        for addr in &bootstrap_nodes {
+            self.dial_bootstrap_node(addr);
         }
 
         // Create node announcement
@@ -172,6 +344,9 @@ impl NetworkNode {
     pub async fn stop(&mut self) {
         info!("Stopping network node");
         self.running.store(false, Ordering::SeqCst);
+        if let Err(e) = self.save_peer_scores().await {
+            warn!("Failed to save peer scores: {}", e);
+        }
     }
 
     /// Get the local peer ID
@@ -179,6 +354,130 @@ impl NetworkNode {
         &self.local_peer_id
     }
 
+    /// Get a handle for inspecting and controlling this node while it runs
+    pub fn handle(&self) -> NodeHandle {
+        NodeHandle {
+            local_peer_id: self.local_peer_id,
+            commands: self.command_sender.clone(),
+            peers: self.peer_status.clone(),
+            peer_scores: self.peer_scores.clone(),
+            bootstrap_nodes: self.bootstrap_nodes.clone(),
+        }
+    }
+
+    fn dial_bootstrap_node(&mut self, addr: &Multiaddr) {
+        debug!("Dialing bootstrap node: {}", addr);
+        if let Err(e) = self.swarm.dial(addr.clone()) {
+            warn!("Failed to dial bootstrap node {}: {}", addr, e);
+        }
+    }
+
+    /// Look up a peer's status, creating it on first sight
+    async fn update_peer(&self, peer_id: &PeerId, update: impl FnOnce(&mut PeerStatus)) {
+        let mut peers = self.peer_status.lock().await;
+        update(
+            peers
+                .entry(*peer_id)
+                .or_insert_with(|| PeerStatus::new(peer_id)),
+        );
+    }
+
+    /// Write the peer scores to the configured file, if any
+    async fn save_peer_scores(&self) -> Result<(), FederationError> {
+        match &self.config.peer_scores_path {
+            Some(path) => self.peer_scores.lock().await.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `peer_id` is banned
+    async fn is_banned(&self, peer_id: &PeerId) -> bool {
+        let now = crate::storage::utils::now_with_default();
+        self.peer_scores
+            .lock()
+            .await
+            .standing(&peer_id.to_string(), now)
+            == Standing::Banned
+    }
+
+    /// Peers to send to: the known peers that are not banned, best first
+    async fn recipients(&self) -> Vec<PeerId> {
+        let peers: Vec<PeerId> = self.known_peers.lock().await.iter().cloned().collect();
+        let now = crate::storage::utils::now_with_default();
+        self.peer_scores
+            .lock()
+            .await
+            .prioritize(peers, PeerId::to_string, now)
+    }
+
+    /// Act on a message `from` sent, once it is scored
+    ///
+    /// Messages from banned peers, and any that fail their checks, are
+    /// dropped. A peer banned by the message is disconnected.
+    #[instrument(skip_all, fields(peer = %from))]
+    pub async fn receive_message(
+        &mut self,
+        from: PeerId,
+        message: NetworkMessage,
+    ) -> Result<(), FederationError> {
+        let now = crate::storage::utils::now_with_default();
+        let (standing, misbehavior) =
+            self.peer_scores
+                .lock()
+                .await
+                .observe(&from.to_string(), &message, now);
+        if let Some(misbehavior) = misbehavior {
+            warn!(%misbehavior, %standing, "Dropping message from misbehaving peer");
+            if standing == Standing::Banned {
+                warn!("Banning peer");
+                let _ = self.swarm.disconnect_peer_id(from);
+            }
+            return self.save_peer_scores().await;
+        }
+        if standing == Standing::Banned {
+            debug!("Dropping message from banned peer");
+            return Ok(());
+        }
+
+        let _ = self
+            .event_sender
+            .send(NetworkEvent::MessageReceived {
+                peer: from,
+                message: message.clone(),
+            })
+            .await;
+        match message {
+            NetworkMessage::ProposalBroadcast(proposal) => {
+                self.handle_proposal_broadcast(proposal).await
+            }
+            NetworkMessage::VoteSubmission(vote) => self.handle_vote_submission(vote).await,
+            NetworkMessage::ExecutionAck(ack) => self.handle_execution_ack(ack).await,
+            other => {
+                debug!("Received {:?}", other);
+                Ok(())
+            }
+        }
+    }
+
+    /// Carry out a command from a `NodeHandle`
+    async fn handle_command(&mut self, command: NodeCommand) -> Result<(), FederationError> {
+        match command {
+            NodeCommand::AddBootstrapNode(addr) => {
+                info!("Adding bootstrap node: {}", addr);
+                {
+                    let mut bootstrap_nodes = self.bootstrap_nodes.lock().await;
+                    if !bootstrap_nodes.contains(&addr) {
+                        bootstrap_nodes.push(addr.clone());
+                    }
+                }
+                self.dial_bootstrap_node(&addr);
+                Ok(())
+            }
+            NodeCommand::BroadcastProposal(proposal) => self.broadcast_proposal(proposal).await,
+            NodeCommand::SyncLedger => self.request_ledger_sync().await,
+        }
+    }
+
     /// Create a node announcement message
     fn create_node_announcement(&self) -> NodeAnnouncement {
         NodeAnnouncement {
@@ -202,6 +501,12 @@ impl NetworkNode {
                         let _ = self.event_sender.send(NetworkEvent::Error(e.to_string())).await;
                     }
                 }
+                command = self.command_receiver.select_next_some() => {
+                    if let Err(e) = self.handle_command(command).await {
+                        error!("Error handling node command: {}", e);
+                        let _ = self.event_sender.send(NetworkEvent::Error(e.to_string())).await;
+                    }
+                }
             }
         }
 
@@ -222,6 +527,11 @@ impl NetworkNode {
             SwarmEvent::ConnectionEstablished {
                 peer_id, endpoint, ..
             } => {
+                if self.is_banned(&peer_id).await {
+                    info!("Closing connection to banned peer {}", peer_id);
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                    return Ok(());
+                }
                 info!("Connected to {}", peer_id);
 
                 // Add peer to Kademlia routing table if using discovered address
@@ -236,8 +546,13 @@ impl NetworkNode {
                     .add_address(&peer_id, remote_addr.clone());
 
                 // Add peer to known peers
-                let mut peers = self.known_peers.lock().await;
-                peers.insert(peer_id);
+                self.known_peers.lock().await.insert(peer_id);
+                self.update_peer(&peer_id, |status| {
+                    status.connected = true;
+                    status.last_seen = crate::storage::utils::now_with_default();
+                    status.add_address(remote_addr);
+                })
+                .await;
 
                 // Notify about new connection
                 let _ = self
@@ -252,6 +567,8 @@ impl NetworkNode {
                 } else {
                     info!("Disconnected from {}", peer_id);
                 }
+                self.update_peer(&peer_id, |status| status.connected = false)
+                    .await;
 
                 // Notify about disconnection
                 let _ = self
@@ -341,6 +658,9 @@ impl NetworkNode {
                 ..
             } => {
                 info!("Ping success from {}: RTT = {:?}", peer, rtt);
+                metrics::record_federation_round_trip("ping", rtt);
+                self.update_peer(&peer, |status| status.record_ping(Some(rtt)))
+                    .await;
             }
 
             ping::Event {
@@ -349,6 +669,8 @@ impl NetworkNode {
                 ..
             } => {
                 warn!("Ping failure with {}: {}", peer, error);
+                self.update_peer(&peer, |status| status.record_ping(None))
+                    .await;
             }
         }
 
@@ -361,10 +683,13 @@ impl NetworkNode {
             kad::Event::OutboundQueryProgressed {
                 id,
                 result: kad::QueryResult::GetClosestPeers(Ok(peers)),
-                stats: _,
+                stats,
                 ..
             } => {
                 info!("Kademlia query {:?} found {} peers", id, peers.peers.len());
+                if let Some(duration) = stats.duration() {
+                    metrics::record_federation_round_trip("kademlia", duration);
+                }
 
                 let _ = self
                     .event_sender
@@ -450,6 +775,8 @@ impl NetworkNode {
             mdns::Event::Discovered(list) => {
                 for (peer, addr) in list {
                     info!("mDNS discovered peer {} at {}", peer, addr);
+                    self.update_peer(&peer, |status| status.add_address(&addr))
+                        .await;
 
                     // Add address to Kademlia
                     self.swarm
@@ -465,7 +792,7 @@ impl NetworkNode {
 
                     // Optionally, dial the peer if not already connected
                     let is_known = self.known_peers.lock().await.contains(&peer);
-                    if !is_known {
+                    if !is_known && !self.is_banned(&peer).await {
                         debug!("Dialing newly discovered peer: {}", peer);
                         if let Err(e) = self.swarm.dial(addr.clone()) {
                             warn!("Failed to dial discovered peer {}: {}", peer, e);
@@ -498,6 +825,14 @@ impl NetworkNode {
 
                 debug!("Protocols supported by {}: {:?}", peer_id, info.protocols);
 
+                self.update_peer(&peer_id, |status| {
+                    status.last_seen = crate::storage::utils::now_with_default();
+                    info.listen_addrs
+                        .iter()
+                        .for_each(|addr| status.add_address(addr));
+                })
+                .await;
+
                 // Add all listen addresses to Kademlia
                 for addr in info.listen_addrs {
                     debug!("Adding address {} for peer {}", addr, peer_id);
@@ -530,24 +865,22 @@ impl NetworkNode {
     }
 
     /// Broadcast a proposal to the network
+    #[instrument(
+        skip_all,
+        fields(proposal_id = %proposal.proposal_id, namespace = %proposal.namespace)
+    )]
     pub async fn broadcast_proposal(
         &mut self,
         proposal: FederatedProposal,
     ) -> Result<(), FederationError> {
-        info!("Broadcasting proposal: {}", proposal.proposal_id);
+        info!("Broadcasting proposal");
 
         // Create the proposal broadcast message
         let _message = NetworkMessage::ProposalBroadcast(proposal);
 
-        // Get all connected peers
-        let peer_ids = {
-            let peers = self.known_peers.lock().await;
-            peers.iter().cloned().collect::<Vec<_>>()
-        };
-
-        // Broadcast to all peers
-        for peer_id in peer_ids {
-            debug!("Sending proposal to peer: {}", peer_id);
+        // Broadcast to every peer that is not banned, best scored first
+        for peer_id in self.recipients().await {
+            debug!(peer = %peer_id, "Sending proposal to peer");
             // In a real implementation, we would use a proper broadcast mechanism
             // For now, we're just simulating by sending to each peer individually
         }
@@ -560,9 +893,37 @@ impl NetworkNode {
         Ok(())
     }
 
+    /// Ask connected peers for the ledger nodes this node is missing
+    ///
+    /// Also refreshes the Kademlia routing table, so peers that joined since
+    /// the node started are found.
+    pub async fn request_ledger_sync(&mut self) -> Result<(), FederationError> {
+        info!("Requesting ledger sync from peers");
+
+        let _message = NetworkMessage::LedgerSyncRequest(LedgerSyncRequest {
+            node_id: self.local_peer_id.to_string(),
+        });
+
+        if let Err(e) = self.swarm.behaviour_mut().kademlia.bootstrap() {
+            debug!("Kademlia bootstrap not started: {:?}", e);
+        }
+
+        for peer_id in self.recipients().await {
+            debug!("Sending ledger sync request to peer: {}", peer_id);
+            // Sent the same way as proposal broadcasts
+        }
+
+        self.event_sender
+            .try_send(NetworkEvent::LedgerSyncRequested)
+            .map_err(|e| FederationError::NetworkError(format!("Failed to emit event: {}", e)))?;
+
+        Ok(())
+    }
+
     /// Submit a vote to the network
+    #[instrument(skip_all, fields(proposal_id = %vote.proposal_id, identity = %vote.voter))]
     pub async fn submit_vote(&mut self, vote: FederatedVote) -> Result<(), FederationError> {
-        info!("Submitting vote from {}", vote.voter);
+        info!("Submitting vote");
 
         // Create the vote submission message
         let _message = NetworkMessage::VoteSubmission(vote);
@@ -576,12 +937,33 @@ impl NetworkNode {
         Ok(())
     }
 
+    /// Tell peers that a cooperative applied an executed proposal's result
+    #[instrument(skip_all, fields(proposal_id = %ack.proposal_id, coop_id = %ack.coop_id))]
+    pub async fn send_execution_ack(&mut self, ack: ExecutionAck) -> Result<(), FederationError> {
+        info!("Sending execution acknowledgment");
+
+        // Create the acknowledgment message
+        let _message = NetworkMessage::ExecutionAck(ack);
+
+        // In a real implementation, we would send this to peers who have the proposal
+        // For now, we just emit an event
+        self.event_sender
+            .try_send(NetworkEvent::ExecutionAckSent)
+            .map_err(|e| FederationError::NetworkError(format!("Failed to emit event: {}", e)))?;
+
+        Ok(())
+    }
+
     /// Handle proposal broadcast message
+    #[instrument(
+        skip_all,
+        fields(proposal_id = %proposal.proposal_id, namespace = %proposal.namespace)
+    )]
     async fn handle_proposal_broadcast(
         &mut self,
         proposal: FederatedProposal,
     ) -> Result<(), FederationError> {
-        info!("Received proposal broadcast: {}", proposal.proposal_id);
+        info!("Received proposal broadcast");
 
         // Store the proposal
         // In a real implementation, we would have access to the storage backend
@@ -596,8 +978,9 @@ impl NetworkNode {
     }
 
     /// Handle vote submission message
+    #[instrument(skip_all, fields(proposal_id = %vote.proposal_id, identity = %vote.voter))]
     async fn handle_vote_submission(&mut self, vote: FederatedVote) -> Result<(), FederationError> {
-        info!("Received vote from {}", vote.voter);
+        info!("Received vote");
 
         // Store the vote
         // In a real implementation, we would have access to the storage backend
@@ -610,6 +993,23 @@ impl NetworkNode {
 
         Ok(())
     }
+
+    /// Handle execution acknowledgment message
+    #[instrument(skip_all, fields(proposal_id = %ack.proposal_id, coop_id = %ack.coop_id))]
+    async fn handle_execution_ack(&mut self, ack: ExecutionAck) -> Result<(), FederationError> {
+        // An acknowledgment that fails to verify is dropped
+        if let Err(e) = ack.verify() {
+            warn!(signer = %ack.signer, error = %e, "Dropping execution acknowledgment");
+            return Ok(());
+        }
+        info!("Received execution acknowledgment");
+
+        self.event_sender
+            .try_send(NetworkEvent::ExecutionAckReceived)
+            .map_err(|e| FederationError::NetworkError(format!("Failed to emit event: {}", e)))?;
+
+        Ok(())
+    }
 }
 
 /// Create a new Swarm with the provided identity
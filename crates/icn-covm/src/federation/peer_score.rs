@@ -0,0 +1,384 @@
+//! Reputation of peers by the messages they send
+//!
+//! Every message a peer sends is checked before the node acts on it. A valid
+//! message raises the peer's score a little; a bad signature, a malformed
+//! proposal or vote, or more messages than the rate limit allows lowers it.
+//! A peer whose score drops below zero is deprioritized: the node sends to
+//! it after every other peer. One whose score reaches `BAN_SCORE` is banned
+//! for `BAN_SECS`: its messages are dropped and its connections closed.
+//!
+//! Scores are kept in a JSON file, when the node is given one, so a ban
+//! outlasts a restart and `federation peers` can show them.
+
+use crate::federation::error::FederationError;
+use crate::federation::messages::{FederatedProposal, FederatedVote, NetworkMessage};
+use crate::identity;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Score a peer cannot rise above, so a long history of valid messages
+/// does not excuse a burst of bad ones
+pub const MAX_SCORE: f64 = 20.0;
+
+/// Score at or below which a peer is banned
+pub const BAN_SCORE: f64 = -50.0;
+
+/// How long a ban lasts, in seconds
+pub const BAN_SECS: u64 = 60 * 60;
+
+/// Messages a peer may send within `RATE_WINDOW_SECS` before the rest count
+/// as spam
+pub const RATE_LIMIT: usize = 100;
+
+/// Length of the window `RATE_LIMIT` applies to, in seconds
+pub const RATE_WINDOW_SECS: u64 = 10;
+
+/// Score gained for each valid message
+const VALID_MESSAGE_SCORE: f64 = 1.0;
+
+/// A way a peer broke the protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Misbehavior {
+    /// A signed message whose signature does not verify
+    BadSignature,
+    /// A proposal or vote missing what it needs to be acted on
+    Malformed,
+    /// A message beyond the rate limit
+    Spam,
+}
+
+impl Misbehavior {
+    /// Score lost for the misbehavior
+    pub fn penalty(self) -> f64 {
+        match self {
+            Misbehavior::BadSignature => 25.0,
+            Misbehavior::Malformed => 10.0,
+            Misbehavior::Spam => 5.0,
+        }
+    }
+}
+
+impl fmt::Display for Misbehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Misbehavior::BadSignature => write!(f, "bad signature"),
+            Misbehavior::Malformed => write!(f, "malformed message"),
+            Misbehavior::Spam => write!(f, "spam"),
+        }
+    }
+}
+
+/// How the node treats a peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Standing {
+    Good,
+    /// Sent to after every peer in good standing
+    Deprioritized,
+    /// Messages dropped and connections closed until the ban ends
+    Banned,
+}
+
+impl fmt::Display for Standing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Standing::Good => write!(f, "good"),
+            Standing::Deprioritized => write!(f, "deprioritized"),
+            Standing::Banned => write!(f, "banned"),
+        }
+    }
+}
+
+/// What a peer's messages have earned it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PeerScore {
+    pub peer_id: String,
+    pub score: f64,
+    pub valid_messages: u64,
+    pub bad_signatures: u64,
+    pub malformed: u64,
+    pub spam: u64,
+    /// Unix time (seconds) the peer's ban ends, if it was ever banned
+    pub banned_until: Option<u64>,
+    /// Arrival times of the messages within the rate window
+    #[serde(skip)]
+    recent: VecDeque<u64>,
+}
+
+impl PeerScore {
+    fn new(peer_id: &str) -> Self {
+        Self {
+            peer_id: peer_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The peer's standing at `now`
+    pub fn standing(&self, now: u64) -> Standing {
+        if self.banned_until.is_some_and(|until| until > now) {
+            Standing::Banned
+        } else if self.score < 0.0 {
+            Standing::Deprioritized
+        } else {
+            Standing::Good
+        }
+    }
+}
+
+/// Scores of every peer that has sent this node a message
+#[derive(Debug, Clone, Default)]
+pub struct PeerScores {
+    peers: BTreeMap<String, PeerScore>,
+}
+
+impl PeerScores {
+    /// Read scores from `path`; a missing file holds none
+    pub fn load(path: &Path) -> Result<Self, FederationError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let peers: Vec<PeerScore> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(Self {
+            peers: peers
+                .into_iter()
+                .map(|peer| (peer.peer_id.clone(), peer))
+                .collect(),
+        })
+    }
+
+    /// Write the scores to `path`
+    pub fn save(&self, path: &Path) -> Result<(), FederationError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&self.all())?)?;
+        Ok(())
+    }
+
+    /// Every peer's score, ordered by peer ID
+    pub fn all(&self) -> Vec<PeerScore> {
+        self.peers.values().cloned().collect()
+    }
+
+    pub fn get(&self, peer_id: &str) -> Option<&PeerScore> {
+        self.peers.get(peer_id)
+    }
+
+    /// A peer's standing at `now`; peers never heard from are in good standing
+    pub fn standing(&self, peer_id: &str, now: u64) -> Standing {
+        self.get(peer_id)
+            .map_or(Standing::Good, |peer| peer.standing(now))
+    }
+
+    fn entry(&mut self, peer_id: &str) -> &mut PeerScore {
+        self.peers
+            .entry(peer_id.to_string())
+            .or_insert_with(|| PeerScore::new(peer_id))
+    }
+
+    /// Score a message `peer_id` sent at `now`
+    ///
+    /// Returns the peer's standing afterwards, and the misbehavior if the
+    /// message should be dropped. Messages from a banned peer are dropped
+    /// without changing its score.
+    pub fn observe(
+        &mut self,
+        peer_id: &str,
+        message: &NetworkMessage,
+        now: u64,
+    ) -> (Standing, Option<Misbehavior>) {
+        let peer = self.entry(peer_id);
+        if peer.standing(now) == Standing::Banned {
+            return (Standing::Banned, None);
+        }
+
+        while peer
+            .recent
+            .front()
+            .is_some_and(|&at| at + RATE_WINDOW_SECS <= now)
+        {
+            peer.recent.pop_front();
+        }
+        peer.recent.push_back(now);
+        let misbehavior = if peer.recent.len() > RATE_LIMIT {
+            Some(Misbehavior::Spam)
+        } else {
+            check(message).err()
+        };
+
+        let standing = match misbehavior {
+            Some(misbehavior) => self.penalize(peer_id, misbehavior, now),
+            None => {
+                peer.valid_messages += 1;
+                peer.score = (peer.score + VALID_MESSAGE_SCORE).min(MAX_SCORE);
+                peer.standing(now)
+            }
+        };
+        (standing, misbehavior)
+    }
+
+    /// Lower `peer_id`'s score for `misbehavior`, banning it if the score
+    /// reaches `BAN_SCORE`
+    pub fn penalize(&mut self, peer_id: &str, misbehavior: Misbehavior, now: u64) -> Standing {
+        let peer = self.entry(peer_id);
+        match misbehavior {
+            Misbehavior::BadSignature => peer.bad_signatures += 1,
+            Misbehavior::Malformed => peer.malformed += 1,
+            Misbehavior::Spam => peer.spam += 1,
+        }
+        peer.score -= misbehavior.penalty();
+        if peer.score <= BAN_SCORE && peer.standing(now) != Standing::Banned {
+            peer.banned_until = Some(now + BAN_SECS);
+        }
+        peer.standing(now)
+    }
+
+    /// `peers` without the banned ones, highest score first
+    pub fn prioritize<T, F>(&self, mut peers: Vec<T>, peer_id: F, now: u64) -> Vec<T>
+    where
+        F: Fn(&T) -> String,
+    {
+        peers.retain(|peer| self.standing(&peer_id(peer), now) != Standing::Banned);
+        let score = |peer: &T| self.get(&peer_id(peer)).map_or(0.0, |peer| peer.score);
+        peers.sort_by(|a, b| score(b).total_cmp(&score(a)));
+        peers
+    }
+}
+
+/// Check that a message can be acted on
+pub fn check(message: &NetworkMessage) -> Result<(), Misbehavior> {
+    match message {
+        NetworkMessage::ProposalBroadcast(proposal) => check_proposal(proposal),
+        NetworkMessage::VoteSubmission(vote) => check_vote(vote),
+        NetworkMessage::ExecutionAck(ack) => ack.verify().map_err(|_| Misbehavior::BadSignature),
+        _ => Ok(()),
+    }
+}
+
+fn check_proposal(proposal: &FederatedProposal) -> Result<(), Misbehavior> {
+    let malformed = proposal.proposal_id.trim().is_empty()
+        || proposal.creator.trim().is_empty()
+        || proposal.options.len() < 2
+        || proposal
+            .expires_at
+            .is_some_and(|expires| expires <= proposal.created_at);
+    if malformed {
+        Err(Misbehavior::Malformed)
+    } else {
+        Ok(())
+    }
+}
+
+/// A vote must sign the canonical message for its choices; one cast by a
+/// `did:key` is verified against the key, others when stored
+fn check_vote(vote: &FederatedVote) -> Result<(), Misbehavior> {
+    let canonical =
+        FederatedVote::canonical_message(&vote.proposal_id, &vote.voter, &vote.ranked_choices);
+    if vote.proposal_id.trim().is_empty()
+        || vote.voter.trim().is_empty()
+        || vote.ranked_choices.iter().any(|choice| !choice.is_finite())
+        || vote.message != canonical
+    {
+        return Err(Misbehavior::Malformed);
+    }
+    match vote.voter.strip_prefix("did:key:") {
+        Some(key) => identity::verify_signature(key, canonical.as_bytes(), &vote.signature)
+            .map_err(|_| Misbehavior::BadSignature),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::federation::messages::{Ping, ProposalScope, VotingModel};
+
+    fn proposal(options: usize) -> NetworkMessage {
+        NetworkMessage::ProposalBroadcast(FederatedProposal::new(
+            "budget".to_string(),
+            "coop".to_string(),
+            (0..options).map(|option| option.to_string()).collect(),
+            "did:key:alice".to_string(),
+            ProposalScope::GlobalFederation,
+            VotingModel::OneMemberOneVote,
+        ))
+    }
+
+    #[test]
+    fn test_misbehaving_peers_are_deprioritized_then_banned() {
+        let mut scores = PeerScores::default();
+        let now = 1_700_000_000;
+        assert_eq!(
+            scores.observe("good", &proposal(2), now),
+            (Standing::Good, None)
+        );
+
+        assert_eq!(
+            scores.observe("bad", &proposal(1), now),
+            (Standing::Deprioritized, Some(Misbehavior::Malformed))
+        );
+        let vote = NetworkMessage::VoteSubmission(FederatedVote {
+            proposal_id: "budget".to_string(),
+            voter: "did:key:z6Mkbogus".to_string(),
+            ranked_choices: vec![1.0, 0.0],
+            message: FederatedVote::canonical_message("budget", "did:key:z6Mkbogus", &[1.0, 0.0]),
+            signature: "zbogus".to_string(),
+        });
+        assert_eq!(
+            scores.observe("bad", &vote, now).1,
+            Some(Misbehavior::BadSignature)
+        );
+        assert_eq!(
+            scores.observe("bad", &vote, now),
+            (Standing::Banned, Some(Misbehavior::BadSignature))
+        );
+        // Banned peers are dropped without further scoring, until the ban ends
+        assert_eq!(
+            scores.observe("bad", &proposal(2), now),
+            (Standing::Banned, None)
+        );
+        assert_eq!(scores.get("bad").unwrap().valid_messages, 0);
+        assert_eq!(
+            scores.standing("bad", now + BAN_SECS),
+            Standing::Deprioritized
+        );
+
+        let peers = vec!["bad", "new", "good"];
+        assert_eq!(
+            scores.prioritize(peers.clone(), |peer| peer.to_string(), now),
+            vec!["good", "new"]
+        );
+        assert_eq!(
+            scores.prioritize(peers, |peer| peer.to_string(), now + BAN_SECS),
+            vec!["good", "new", "bad"]
+        );
+
+        // Flooding counts as spam once the rate limit is passed
+        let ping = NetworkMessage::Ping(Ping {
+            nonce: 7,
+            timestamp_ms: now * 1000,
+        });
+        for _ in 0..RATE_LIMIT {
+            assert_eq!(scores.observe("flood", &ping, now).1, None);
+        }
+        assert_eq!(
+            scores.observe("flood", &ping, now).1,
+            Some(Misbehavior::Spam)
+        );
+        assert_eq!(
+            scores.observe("flood", &ping, now + RATE_WINDOW_SECS).1,
+            None
+        );
+
+        let path = std::env::temp_dir().join(format!("icn-peers-{}.json", std::process::id()));
+        scores.save(&path).unwrap();
+        let loaded = PeerScores::load(&path).unwrap();
+        assert_eq!(loaded.standing("bad", now), Standing::Banned);
+        assert_eq!(loaded.get("flood").unwrap().spam, 1);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -7,11 +7,11 @@ use crate::storage::auth::AuthContext;
 use crate::storage::errors::{StorageError, StorageResult};
 use crate::storage::traits::StorageExtensions;
 use crate::storage::utils;
-use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, instrument, warn};
 
 // Storage namespace constants
 pub const FEDERATION_NAMESPACE: &str = "federation";
@@ -83,6 +83,10 @@ impl FederationStorage {
     }
 
     /// Save a proposal to storage and cache
+    #[instrument(
+        skip_all,
+        fields(proposal_id = %proposal.proposal_id, namespace = %proposal.namespace)
+    )]
     pub fn save_proposal<S: StorageExtensions>(
         &self,
         storage: &mut S,
@@ -112,6 +116,14 @@ impl FederationStorage {
     }
 
     /// Save a proposal to storage and cache with explicit auth
+    #[instrument(
+        skip_all,
+        fields(
+            proposal_id = %proposal.proposal_id,
+            namespace = %proposal.namespace,
+            identity = auth.map(|auth| auth.identity_did()),
+        )
+    )]
     pub fn save_proposal_with_auth<S: StorageExtensions>(
         &self,
         storage: &mut S,
@@ -151,6 +163,7 @@ impl FederationStorage {
     }
 
     /// Save a vote to storage, checking eligibility first
+    #[instrument(skip_all, fields(proposal_id = %vote.proposal_id, identity = %vote.voter))]
     pub fn save_vote<S: StorageExtensions>(
         &self,
         storage: &mut S,
@@ -162,10 +175,7 @@ impl FederationStorage {
 
         // Check if voting is still open
         if proposal.status != ProposalStatus::Open {
-            warn!(
-                "Vote rejected: Proposal {} is not open for voting",
-                vote.proposal_id
-            );
+            warn!("Vote rejected: proposal is not open for voting");
             return Err(StorageError::Other {
                 details: format!("Proposal {} is not open for voting", vote.proposal_id),
             });
@@ -179,7 +189,7 @@ impl FederationStorage {
             match self.load_identity_from_storage(storage, &vote.voter) {
                 Ok(loaded_identity) => loaded_identity,
                 Err(e) => {
-                    warn!("Failed to load identity for voter {}: {}", vote.voter, e);
+                    warn!(error = %e, "Failed to load voter identity");
                     return Err(StorageError::NotFound {
                         key: format!("Identity for voter {} not found", vote.voter),
                     });
@@ -199,27 +209,21 @@ impl FederationStorage {
                     &scheme,
                     pub_key,
                 ) {
-                    warn!("Vote rejected: Invalid signature from voter {}", vote.voter);
+                    warn!("Vote rejected: invalid signature");
                     return Err(StorageError::Other {
                         details: format!("Invalid signature for vote from {}", vote.voter),
                     });
                 }
 
-                debug!("Signature verification passed for voter {}", vote.voter);
+                debug!("Signature verification passed");
             } else {
-                warn!(
-                    "Cannot verify vote: No crypto scheme specified for voter {}",
-                    vote.voter
-                );
+                warn!("Cannot verify vote: no crypto scheme specified for voter");
                 return Err(StorageError::Other {
                     details: format!("No crypto scheme specified for voter {}", vote.voter),
                 });
             }
         } else {
-            warn!(
-                "Cannot verify vote: No public key available for voter {}",
-                vote.voter
-            );
+            warn!("Cannot verify vote: no public key available for voter");
             return Err(StorageError::Other {
                 details: format!("No public key available for voter {}", vote.voter),
             });
@@ -230,10 +234,7 @@ impl FederationStorage {
             ProposalScope::SingleCoop(coop_id) => {
                 // Only members of this specific coop can vote
                 if !identity.belongs_to(coop_id) {
-                    warn!(
-                        "Vote rejected: Voter {} not a member of cooperative {}",
-                        vote.voter, coop_id
-                    );
+                    warn!(coop_id = %coop_id, "Vote rejected: voter not a member of cooperative");
                     return Err(StorageError::Other {
                         details: format!("Voter not a member of eligible cooperative {}", coop_id),
                     });
@@ -244,10 +245,7 @@ impl FederationStorage {
                 // Check if the voter belongs to any of the eligible coops
                 let belongs = coop_ids.iter().any(|coop_id| identity.belongs_to(coop_id));
                 if !belongs {
-                    warn!(
-                        "Vote rejected: Voter {} not a member of any eligible cooperatives",
-                        vote.voter
-                    );
+                    warn!("Vote rejected: voter not a member of any eligible cooperative");
                     return Err(StorageError::Other {
                         details: "Voter not a member of any eligible cooperatives".to_string(),
                     });
@@ -276,16 +274,13 @@ impl FederationStorage {
                 Ok(existing_votes) => {
                     // Check if this voter already voted
                     if existing_votes.iter().any(|v| v.voter == vote.voter) {
-                        warn!(
-                            "Vote overwritten: Voter {} already voted on {}",
-                            vote.voter, vote.proposal_id
-                        );
+                        warn!("Vote overwritten: voter already voted");
                     }
                     existing_votes
                 }
                 Err(StorageError::NotFound { .. }) => Vec::new(),
                 Err(e) => {
-                    warn!("Error retrieving existing votes: {}", e);
+                    warn!(error = %e, "Error retrieving existing votes");
                     return Err(StorageError::Other {
                         details: format!("Failed to retrieve existing votes: {}", e),
                     });
@@ -355,7 +350,7 @@ impl FederationStorage {
                 !signature.is_empty() && !message.is_empty() && !public_key.is_empty()
             }
             _ => {
-                warn!("Unsupported crypto scheme: {}", scheme);
+                warn!(scheme, "Unsupported crypto scheme");
                 false
             }
         }
@@ -493,7 +488,7 @@ fn current_timestamp_or_default(default: i64) -> i64 {
         Ok(timestamp) => timestamp,
         Err(e) => {
             // First log the error
-            warn!("Error getting timestamp, using default: {}", e);
+            warn!(error = %e, "Error getting timestamp, using default");
 
             // If storage::utils::now_with_default() would also fail,
             // use the provided default instead of the hardcoded one
@@ -0,0 +1,520 @@
+//! Cold-storage archives of closed proposals
+//!
+//! [`build_archive`] bundles closed proposals into a directory that can be
+//! moved off the node and kept for as long as the cooperative needs:
+//!
+//! - `proposals/{id}.json`: every record stored under
+//!   `governance_proposals/{id}/`, including votes and attachment metadata
+//! - `attachments/{sha256}`: the content of each attachment, named by its hash
+//! - `ledger.jsonl`: the ledger nodes recording the proposals' creation,
+//!   votes, and execution, in ledger order
+//! - `manifest.json`: the SHA-256 and size of every other file, signed by the
+//!   identity that made the archive
+//!
+//! [`verify_archive`] needs nothing but the archive, the archiver's `did:key`
+//! DID, and optionally the ledger. It checks the manifest signature and every
+//! file hash, and, given the ledger, that every archived node is still in it
+//! unchanged and that the ledger records no event for an archived proposal
+//! that the archive lacks.
+
+use crate::governance::attachments::{list_attachments, read_attachment};
+use crate::governance::membership::identity_from_did;
+use crate::governance::proposal_lifecycle::{ProposalLifecycle, ProposalState};
+use crate::identity::Signer;
+use crate::storage::auth::AuthContext;
+use crate::storage::blobs::blob_hash;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use icn_ledger::{DagLedger, DagNode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+
+/// Version of the archive layout written by this build
+pub const ARCHIVE_FORMAT: u32 = 1;
+
+/// File holding the signed manifest
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// File holding the archived ledger nodes
+pub const LEDGER_FILE: &str = "ledger.jsonl";
+
+/// Key prefix proposals are stored under
+const PROPOSALS_PREFIX: &str = "governance_proposals/";
+
+/// A file of the archive, as recorded in the manifest
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ArchivedFile {
+    /// Path relative to the archive directory, with `/` separators
+    pub path: String,
+    /// Hex SHA-256 of the content
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// Signed description of an archive
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ArchiveManifest {
+    pub format: u32,
+    /// Namespace the proposals were archived from
+    pub namespace: String,
+    pub created_at: DateTime<Utc>,
+    /// DID of the archiver, whose key signs the manifest
+    pub created_by: String,
+    pub proposals: Vec<String>,
+    /// IDs of the archived ledger nodes, in ledger order
+    pub ledger_nodes: Vec<String>,
+    pub files: Vec<ArchivedFile>,
+    /// Multibase Ed25519 signature over the manifest with this field empty
+    pub signature: String,
+}
+
+impl ArchiveManifest {
+    /// Bytes the archiver signs
+    pub fn signing_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        let mut unsigned = self.clone();
+        unsigned.signature = String::new();
+        serde_json::to_vec(&unsigned)
+    }
+}
+
+/// A proposal as stored in `proposals/{id}.json`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ArchivedProposal {
+    pub id: String,
+    pub title: String,
+    pub state: ProposalState,
+    /// Base64 of every value under `governance_proposals/{id}/`, by the rest
+    /// of its key
+    pub records: BTreeMap<String, String>,
+}
+
+/// An archive built in memory, ready to be written out
+#[derive(Debug, Clone)]
+pub struct Archive {
+    pub manifest: ArchiveManifest,
+    /// Content of every file but the manifest, by path
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+impl Archive {
+    /// Write the archive into `dir`, which must be empty or not yet exist
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        if dir.exists() && fs::read_dir(dir)?.next().is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is not empty", dir.display()),
+            ));
+        }
+        for (path, data) in &self.files {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, data)?;
+        }
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join(MANIFEST_FILE),
+            serde_json::to_vec_pretty(&self.manifest)?,
+        )
+    }
+}
+
+/// Whether a proposal in `state` can no longer change
+pub fn is_closed(state: &ProposalState) -> bool {
+    matches!(
+        state,
+        ProposalState::Executed | ProposalState::Rejected | ProposalState::Expired
+    )
+}
+
+/// Archive the given proposals of the VM's namespace, or every closed one
+/// when `proposal_ids` is empty, signing the manifest as `signer`
+///
+/// Fails if a proposal is still open, or if an attachment cannot be read,
+/// so an archive is never missing data it claims to hold.
+pub fn build_archive<S>(
+    vm: &VM<S>,
+    auth: &AuthContext,
+    signer: &dyn Signer,
+    proposal_ids: &[String],
+) -> Result<Archive, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let namespace = vm.get_namespace().unwrap_or("default").to_string();
+    let storage = vm
+        .get_storage_backend()
+        .ok_or("Storage backend not available")?;
+    let ledger = vm.dag.as_ref().ok_or("DAG ledger not available")?;
+
+    let mut lifecycles: BTreeMap<String, ProposalLifecycle> = storage
+        .list_keys(Some(auth), &namespace, Some(PROPOSALS_PREFIX))?
+        .iter()
+        .filter(|key| key.ends_with("/lifecycle"))
+        .filter_map(|key| {
+            storage
+                .get_json::<ProposalLifecycle>(Some(auth), &namespace, key)
+                .ok()
+        })
+        .map(|lifecycle| (lifecycle.id.clone(), lifecycle))
+        .collect();
+    if proposal_ids.is_empty() {
+        lifecycles.retain(|_, lifecycle| is_closed(&lifecycle.state));
+    } else {
+        for id in proposal_ids {
+            match lifecycles.get(id) {
+                None => return Err(format!("Proposal {} not found", id).into()),
+                Some(lifecycle) if !is_closed(&lifecycle.state) => {
+                    return Err(format!(
+                        "Proposal {} is still {:?}; only closed proposals are archived",
+                        id, lifecycle.state
+                    )
+                    .into())
+                }
+                Some(_) => {}
+            }
+        }
+        lifecycles.retain(|id, _| proposal_ids.contains(id));
+    }
+    if lifecycles.is_empty() {
+        return Err(format!("No closed proposals to archive in {}", namespace).into());
+    }
+
+    let mut files = BTreeMap::new();
+    for (id, lifecycle) in &lifecycles {
+        let prefix = format!("{}{}/", PROPOSALS_PREFIX, id);
+        let mut records = BTreeMap::new();
+        for key in storage.list_keys(Some(auth), &namespace, Some(&prefix))? {
+            let value = storage.get(Some(auth), &namespace, &key)?;
+            records.insert(key[prefix.len()..].to_string(), BASE64.encode(value));
+        }
+        let proposal = ArchivedProposal {
+            id: id.clone(),
+            title: lifecycle.title.clone(),
+            state: lifecycle.state.clone(),
+            records,
+        };
+        files.insert(
+            format!("proposals/{}.json", id),
+            serde_json::to_vec_pretty(&proposal)?,
+        );
+        for attachment in list_attachments(vm, id, auth)? {
+            let data = read_attachment(vm, &attachment, auth)?;
+            files.insert(format!("attachments/{}", attachment.sha256), data);
+        }
+    }
+
+    let related: BTreeSet<String> = lifecycles
+        .keys()
+        .flat_map(|id| ledger.find_proposal_related_nodes(id))
+        .map(|node| node.id)
+        .collect();
+    let nodes: Vec<DagNode> = ledger
+        .nodes()
+        .iter()
+        .filter(|node| related.contains(&node.id))
+        .cloned()
+        .collect();
+    files.insert(
+        LEDGER_FILE.to_string(),
+        DagLedger::to_jsonl(&nodes)?.into_bytes(),
+    );
+
+    let mut manifest = ArchiveManifest {
+        format: ARCHIVE_FORMAT,
+        namespace,
+        created_at: Utc::now(),
+        created_by: signer.did().to_string(),
+        proposals: lifecycles.into_keys().collect(),
+        ledger_nodes: nodes.into_iter().map(|node| node.id).collect(),
+        files: files
+            .iter()
+            .map(|(path, data)| ArchivedFile {
+                path: path.clone(),
+                sha256: blob_hash(data),
+                size: data.len() as u64,
+            })
+            .collect(),
+        signature: String::new(),
+    };
+    manifest.signature = signer.sign(&manifest.signing_bytes()?)?;
+    Ok(Archive { manifest, files })
+}
+
+/// Outcome of checking an archive
+#[derive(Serialize, Debug, Clone)]
+pub struct ArchiveReport {
+    pub manifest: ArchiveManifest,
+    /// Whether the archived nodes were compared with a ledger
+    pub ledger_checked: bool,
+    /// Problems found, empty when the archive is intact
+    pub issues: Vec<String>,
+}
+
+impl ArchiveReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Paths of every file under `dir`, relative to `root`
+fn walk(root: &Path, dir: &Path, paths: &mut BTreeSet<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(root, &path, paths)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let parts: Vec<_> = relative.iter().map(|part| part.to_string_lossy()).collect();
+            paths.insert(parts.join("/"));
+        }
+    }
+    Ok(())
+}
+
+/// Check the archive in `dir`, and that it matches `ledger` if given
+///
+/// Errors only when the manifest cannot be read; everything else that does
+/// not match is reported as an issue.
+pub fn verify_archive(
+    dir: &Path,
+    ledger: Option<&DagLedger>,
+) -> Result<ArchiveReport, Box<dyn Error>> {
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let manifest: ArchiveManifest = serde_json::from_slice(
+        &fs::read(&manifest_path)
+            .map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))?,
+    )
+    .map_err(|e| format!("Invalid manifest {}: {}", manifest_path.display(), e))?;
+    let mut issues = Vec::new();
+
+    if manifest.format > ARCHIVE_FORMAT {
+        issues.push(format!(
+            "Archive format {} is newer than this build supports ({})",
+            manifest.format, ARCHIVE_FORMAT
+        ));
+    }
+    match identity_from_did(&manifest.created_by, "archiver") {
+        Ok(archiver) => {
+            if archiver
+                .verify(&manifest.signing_bytes()?, &manifest.signature)
+                .is_err()
+            {
+                issues.push(format!(
+                    "Manifest signature does not match {}",
+                    manifest.created_by
+                ));
+            }
+        }
+        Err(e) => issues.push(format!("Cannot check the manifest signature: {}", e)),
+    }
+
+    let mut contents = BTreeMap::new();
+    for file in &manifest.files {
+        let safe = Path::new(&file.path)
+            .components()
+            .all(|part| matches!(part, Component::Normal(_)));
+        if !safe || file.path == MANIFEST_FILE {
+            issues.push(format!("Manifest lists an invalid path {}", file.path));
+            continue;
+        }
+        match fs::read(dir.join(&file.path)) {
+            Ok(data) if blob_hash(&data) != file.sha256 || data.len() as u64 != file.size => {
+                issues.push(format!(
+                    "{} does not match its hash in the manifest",
+                    file.path
+                ));
+            }
+            Ok(data) => {
+                contents.insert(file.path.as_str(), data);
+            }
+            Err(e) => issues.push(format!("Failed to read {}: {}", file.path, e)),
+        }
+    }
+    let mut present = BTreeSet::new();
+    walk(dir, dir, &mut present)?;
+    let listed: BTreeSet<&str> = manifest
+        .files
+        .iter()
+        .map(|file| file.path.as_str())
+        .collect();
+    for path in &present {
+        if path != MANIFEST_FILE && !listed.contains(path.as_str()) {
+            issues.push(format!("{} is not listed in the manifest", path));
+        }
+    }
+    for id in &manifest.proposals {
+        let path = format!("proposals/{}.json", id);
+        if !listed.contains(path.as_str()) {
+            issues.push(format!("Proposal {} has no {}", id, path));
+        }
+    }
+
+    let nodes = match contents.get(LEDGER_FILE) {
+        Some(data) => match DagLedger::from_jsonl(&String::from_utf8_lossy(data)) {
+            Ok(archived) => archived.export_all(),
+            Err(e) => {
+                issues.push(format!("Invalid {}: {}", LEDGER_FILE, e));
+                Vec::new()
+            }
+        },
+        None => {
+            if !listed.contains(LEDGER_FILE) {
+                issues.push(format!("{} is not listed in the manifest", LEDGER_FILE));
+            }
+            Vec::new()
+        }
+    };
+    let ids: Vec<String> = nodes.iter().map(|node| node.id.clone()).collect();
+    if contents.contains_key(LEDGER_FILE) && ids != manifest.ledger_nodes {
+        issues.push(format!(
+            "{} does not hold the nodes listed in the manifest",
+            LEDGER_FILE
+        ));
+    }
+    for node in nodes.iter().filter(|node| !node.has_valid_id()) {
+        issues.push(format!("Ledger node {} does not match its hash", node.id));
+    }
+
+    if let Some(ledger) = ledger {
+        for node in &nodes {
+            match ledger.find_by_id(&node.id) {
+                None => issues.push(format!(
+                    "Ledger node {} is no longer in the ledger",
+                    node.id
+                )),
+                Some(current) if serde_json::to_value(current)? != serde_json::to_value(node)? => {
+                    issues.push(format!("Ledger node {} differs from the ledger", node.id));
+                }
+                Some(_) => {}
+            }
+        }
+        let archived: BTreeSet<&str> = ids.iter().map(String::as_str).collect();
+        for id in &manifest.proposals {
+            for node in ledger.find_proposal_related_nodes(id) {
+                if !archived.contains(node.id.as_str()) {
+                    issues.push(format!(
+                        "Ledger records {} {} for proposal {}, which the archive lacks",
+                        node.data.type_name(),
+                        node.id,
+                        id
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(ArchiveReport {
+        manifest,
+        ledger_checked: ledger.is_some(),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use icn_ledger::NodeData;
+
+    #[test]
+    fn test_archive_verifies_against_ledger_and_detects_tampering() {
+        let mut auth = AuthContext::new("did:key:alice");
+        auth.add_role("global", "admin");
+        let mut storage = InMemoryStorage::new();
+        storage
+            .create_account(Some(&auth), "did:key:alice", 1 << 20)
+            .unwrap();
+        let signer = Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        for (id, state) in [
+            ("budget", ProposalState::Executed),
+            ("open", ProposalState::Voting),
+        ] {
+            let mut lifecycle = ProposalLifecycle::new(
+                id.to_string(),
+                signer.clone(),
+                id.to_string(),
+                50,
+                60,
+                None,
+                None,
+            );
+            lifecycle.state = state;
+            let key = format!("governance_proposals/{}/lifecycle", id);
+            storage
+                .set_json(Some(&auth), "default", &key, &lifecycle)
+                .unwrap();
+        }
+        storage
+            .set(
+                Some(&auth),
+                "default",
+                "governance_proposals/budget/votes/bob",
+                b"yes".to_vec(),
+            )
+            .unwrap();
+
+        let mut vm = VM::with_storage_backend(storage);
+        let mut ledger = DagLedger::new();
+        for data in [
+            NodeData::ProposalCreated {
+                proposal_id: "budget".to_string(),
+                title: "Budget".to_string(),
+            },
+            NodeData::VoteCast {
+                proposal_id: "budget".to_string(),
+                voter: "bob".to_string(),
+                vote: 1.0,
+            },
+        ] {
+            ledger
+                .append_on_tips(DagNode::with_default_namespace(vec![], data, 1))
+                .unwrap();
+        }
+        vm.dag = Some(ledger.clone());
+
+        assert!(build_archive(&vm, &auth, &signer, &["open".to_string()]).is_err());
+        let archive = build_archive(&vm, &auth, &signer, &[]).unwrap();
+        assert_eq!(archive.manifest.proposals, vec!["budget".to_string()]);
+        assert_eq!(archive.manifest.ledger_nodes.len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        archive.write_to(dir.path()).unwrap();
+        let report = verify_archive(dir.path(), Some(&ledger)).unwrap();
+        assert!(report.is_valid(), "{:?}", report.issues);
+
+        // A vote recorded after archiving makes the archive incomplete
+        ledger
+            .append_on_tips(DagNode::with_default_namespace(
+                vec![],
+                NodeData::VoteCast {
+                    proposal_id: "budget".to_string(),
+                    voter: "carol".to_string(),
+                    vote: 0.0,
+                },
+                2,
+            ))
+            .unwrap();
+        assert_eq!(
+            verify_archive(dir.path(), Some(&ledger))
+                .unwrap()
+                .issues
+                .len(),
+            1
+        );
+
+        fs::write(dir.path().join("proposals/budget.json"), b"{}").unwrap();
+        let report = verify_archive(dir.path(), None).unwrap();
+        assert_eq!(report.issues.len(), 1);
+        assert!(report.issues[0].contains("proposals/budget.json"));
+    }
+}
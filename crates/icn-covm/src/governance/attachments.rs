@@ -0,0 +1,226 @@
+//! Files attached to proposals and their comments
+//!
+//! Each attachment is a metadata record stored with its proposal under
+//! `governance_proposals/{id}/uploads/{attachment_id}`, pointing at a
+//! content-addressed blob that holds the file's bytes. Attachments live in
+//! the VM's namespace, next to the proposal they belong to, and are read and
+//! written as the caller so storage permission checks apply.
+//!
+//! With IPFS configured, large files go to the IPFS node instead of a blob
+//! and the record keeps their CID next to the hash. A file the node cannot
+//! take is stored as a blob, and reading one falls back to a blob with the
+//! same hash when neither the node nor a gateway can return it.
+
+use crate::governance::comments;
+use crate::storage::auth::AuthContext;
+use crate::storage::blobs::{blob_hash, blob_key, get_blob, put_blob};
+use crate::storage::ipfs;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::vm::VM;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Debug;
+use uuid::Uuid;
+
+/// Metadata of a file attached to a proposal or one of its comments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    pub proposal_id: String,
+    /// Comment the file is attached to, if not the proposal itself
+    pub comment_id: Option<String>,
+    pub filename: String,
+    pub content_type: String,
+    pub size: u64,
+    /// Address of the blob holding the content, and the hash IPFS content
+    /// is checked against
+    pub sha256: String,
+    /// CID of the content on IPFS, if it is stored there rather than in a
+    /// blob
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipfs_cid: Option<String>,
+    /// DID of the uploader
+    pub uploaded_by: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+fn proposal_key(proposal_id: &str) -> String {
+    format!("governance_proposals/{}", proposal_id)
+}
+
+fn uploads_prefix(proposal_id: &str) -> String {
+    format!("{}/uploads/", proposal_key(proposal_id))
+}
+
+fn namespace<S>(vm: &VM<S>) -> String
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    vm.get_namespace().unwrap_or("default").to_string()
+}
+
+/// Attach a file to a proposal, or to one of its comments
+pub fn add_attachment<S>(
+    vm: &mut VM<S>,
+    proposal_id: &str,
+    comment_id: Option<&str>,
+    filename: &str,
+    content_type: &str,
+    data: Vec<u8>,
+    auth_context: &AuthContext,
+) -> Result<Attachment, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let namespace = namespace(vm);
+    let proposal_key = format!("{}/proposal", proposal_key(proposal_id));
+    let storage = vm
+        .get_storage_backend()
+        .ok_or("Storage backend not available")?;
+    if !storage.contains(Some(auth_context), &namespace, &proposal_key)? {
+        return Err(format!("Proposal {} does not exist", proposal_id).into());
+    }
+    if let Some(comment_id) = comment_id {
+        comments::get_comment(vm, proposal_id, comment_id, Some(auth_context))?;
+    }
+
+    let size = data.len() as u64;
+    let ipfs_cid = match ipfs::client().filter(|client| client.wants(size)) {
+        Some(client) => match client.add(&data) {
+            Ok(cid) => Some(cid),
+            Err(e) => {
+                tracing::warn!(proposal_id, error = %e, "IPFS add failed; storing attachment as a blob");
+                None
+            }
+        },
+        None => None,
+    };
+    let storage = vm
+        .get_storage_backend_mut()
+        .ok_or("Storage backend not available")?;
+    let sha256 = match ipfs_cid {
+        Some(_) => blob_hash(&data),
+        None => put_blob(storage, Some(auth_context), &namespace, data)?,
+    };
+
+    let attachment = Attachment {
+        id: Uuid::new_v4().to_string(),
+        proposal_id: proposal_id.to_string(),
+        comment_id: comment_id.map(str::to_string),
+        filename: filename.to_string(),
+        content_type: content_type.to_string(),
+        size,
+        sha256,
+        ipfs_cid,
+        uploaded_by: auth_context.identity_did().to_string(),
+        uploaded_at: Utc::now(),
+    };
+    storage.set_json(
+        Some(auth_context),
+        &namespace,
+        &format!("{}{}", uploads_prefix(proposal_id), attachment.id),
+        &attachment,
+    )?;
+
+    Ok(attachment)
+}
+
+/// List the attachments of a proposal and its comments, oldest first
+pub fn list_attachments<S>(
+    vm: &VM<S>,
+    proposal_id: &str,
+    auth_context: &AuthContext,
+) -> Result<Vec<Attachment>, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let namespace = namespace(vm);
+    let storage = vm
+        .get_storage_backend()
+        .ok_or("Storage backend not available")?;
+    let keys = storage.list_keys(
+        Some(auth_context),
+        &namespace,
+        Some(&uploads_prefix(proposal_id)),
+    )?;
+
+    let mut attachments: Vec<Attachment> = keys
+        .iter()
+        .filter_map(|key| storage.get_json(Some(auth_context), &namespace, key).ok())
+        .collect();
+    attachments.sort_by(|a, b| a.uploaded_at.cmp(&b.uploaded_at));
+    Ok(attachments)
+}
+
+/// Get the metadata of one attachment
+pub fn get_attachment<S>(
+    vm: &VM<S>,
+    proposal_id: &str,
+    attachment_id: &str,
+    auth_context: &AuthContext,
+) -> Result<Attachment, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let storage = vm
+        .get_storage_backend()
+        .ok_or("Storage backend not available")?;
+    Ok(storage.get_json(
+        Some(auth_context),
+        &namespace(vm),
+        &format!("{}{}", uploads_prefix(proposal_id), attachment_id),
+    )?)
+}
+
+/// Read the content of an attachment
+pub fn read_attachment<S>(
+    vm: &VM<S>,
+    attachment: &Attachment,
+    auth_context: &AuthContext,
+) -> Result<Vec<u8>, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let namespace = namespace(vm);
+    let storage = vm
+        .get_storage_backend()
+        .ok_or("Storage backend not available")?;
+    let Some(cid) = &attachment.ipfs_cid else {
+        return Ok(get_blob(
+            storage,
+            Some(auth_context),
+            &namespace,
+            &attachment.sha256,
+        )?);
+    };
+
+    let fetched = match ipfs::client() {
+        Some(client) => client
+            .get(cid, &attachment.sha256)
+            .map_err(|e| e.to_string()),
+        None => Err("IPFS is not configured".to_string()),
+    };
+    match fetched {
+        Ok(data) => Ok(data),
+        Err(_)
+            if storage.contains(
+                Some(auth_context),
+                &namespace,
+                &blob_key(&attachment.sha256),
+            )? =>
+        {
+            Ok(get_blob(
+                storage,
+                Some(auth_context),
+                &namespace,
+                &attachment.sha256,
+            )?)
+        }
+        Err(e) => Err(format!(
+            "Attachment {} is unavailable from IPFS: {}",
+            attachment.id, e
+        )
+        .into()),
+    }
+}
@@ -44,6 +44,9 @@ pub struct ProposalComment {
     pub hidden: bool,
     /// History of versions of this comment
     pub edit_history: Vec<CommentVersion>,
+    /// Platform a historical comment was imported from, e.g. `loomio`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported_from: Option<String>,
 }
 
 impl ProposalComment {
@@ -70,6 +73,7 @@ impl ProposalComment {
                 content: content.clone(),
                 timestamp: now,
             }],
+            imported_from: None,
         }
     }
 
@@ -115,6 +119,26 @@ impl ProposalComment {
     }
 }
 
+/// Namespace the comments of `vm` are stored in
+///
+/// Comments live in the VM's namespace, next to the proposals they discuss.
+/// VMs left on the `default` namespace keep using `governance`, where
+/// comments have always been stored.
+pub fn comments_namespace<S>(vm: &VM<S>) -> String
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    comments_namespace_for(vm.get_namespace().unwrap_or("default"))
+}
+
+/// Namespace the comments on proposals in `namespace` are stored in
+pub fn comments_namespace_for(namespace: &str) -> String {
+    match namespace {
+        "default" => "governance".to_string(),
+        namespace => namespace.to_string(),
+    }
+}
+
 /// Fetch all comments for a proposal, organized in a thread structure
 pub fn fetch_comments_threaded<S>(
     vm: &VM<S>,
@@ -125,6 +149,8 @@ pub fn fetch_comments_threaded<S>(
 where
     S: Storage + Send + Sync + Clone + Debug + 'static,
 {
+    let namespace = comments_namespace(vm);
+
     // Check that the proposal exists
     let proposal_path = format!("governance/proposals/{}", proposal_id);
 
@@ -133,17 +159,17 @@ where
         .get_storage_backend()
         .ok_or_else(|| format!("Storage backend not available"))?;
     let _ = storage
-        .get(auth, "governance", &proposal_path)
+        .get(auth, &namespace, &proposal_path)
         .map_err(|_| format!("Proposal {} does not exist", proposal_id))?;
 
     // Fetch all comments stored under governance/proposals/{proposal_id}/comments/
     let comment_path = format!("governance/proposals/{}/comments", proposal_id);
-    let comments_refs = storage.list_keys(auth, "governance", Some(&comment_path))?;
+    let comments_refs = storage.list_keys(auth, &namespace, Some(&comment_path))?;
 
     let mut comments = HashMap::new();
 
     for comment_ref in comments_refs {
-        match storage.get_json::<ProposalComment>(auth, "governance", &comment_ref) {
+        match storage.get_json::<ProposalComment>(auth, &namespace, &comment_ref) {
             Ok(comment) => {
                 // Only include non-hidden comments unless show_hidden is true
                 if !comment.hidden || show_hidden {
@@ -170,6 +196,8 @@ pub fn create_comment<S>(
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
+    let namespace = comments_namespace(vm);
+
     // Check that the proposal exists
     let proposal_path = format!("governance/proposals/{}", proposal_id);
 
@@ -178,7 +206,7 @@ where
         .get_storage_backend()
         .ok_or_else(|| "Storage backend not available")?;
     let _ = storage
-        .get(Some(auth_context), "governance", &proposal_path)
+        .get(Some(auth_context), &namespace, &proposal_path)
         .map_err(|_| format!("Proposal {} does not exist", proposal_id))?;
 
     // Create the comment
@@ -195,12 +223,10 @@ where
         proposal_id, comment.id
     );
 
-    // Clone the storage to get a mutable version
-    let mut storage = vm
-        .get_storage_backend()
-        .ok_or_else(|| "Storage backend not available")?
-        .clone();
-    storage.set_json(Some(auth_context), "governance", &comment_path, &comment)?;
+    let storage = vm
+        .get_storage_backend_mut()
+        .ok_or_else(|| "Storage backend not available")?;
+    storage.set_json(Some(auth_context), &namespace, &comment_path, &comment)?;
 
     Ok(comment)
 }
@@ -215,6 +241,7 @@ pub fn get_comment<S>(
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
+    let namespace = comments_namespace(vm);
     let comment_path = format!(
         "governance/proposals/{}/comments/{}",
         proposal_id, comment_id
@@ -223,7 +250,7 @@ where
     let storage = vm
         .get_storage_backend()
         .ok_or_else(|| "Storage backend not available")?;
-    let comment_data = storage.get(auth_context, "governance", &comment_path)?;
+    let comment_data = storage.get(auth_context, &namespace, &comment_path)?;
 
     // Try to deserialize as the new format
     match serde_json::from_slice::<ProposalComment>(&comment_data) {
@@ -263,6 +290,7 @@ where
                     content: legacy_comment.content,
                     timestamp: legacy_comment.timestamp, // Use original timestamp
                 }],
+                imported_from: None,
             };
 
             // Save the migrated comment back to storage with the new format
@@ -271,7 +299,7 @@ where
                 if let Some(mut storage_mut) = vm_clone.get_storage_backend().cloned() {
                     let _ = storage_mut.set_json(
                         auth_context,
-                        "governance",
+                        &namespace,
                         &comment_path,
                         &migrated_comment,
                     );
@@ -294,17 +322,19 @@ pub fn edit_comment<S>(
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
+    let namespace = comments_namespace(vm);
+
     // Get the comment
     let comment_path = format!(
         "governance/proposals/{}/comments/{}",
         proposal_id, comment_id
     );
 
-    let storage = vm
-        .get_storage_backend()
+    let storage_mut = vm
+        .get_storage_backend_mut()
         .ok_or_else(|| "Storage backend not available")?;
     let mut comment =
-        storage.get_json::<ProposalComment>(Some(auth_context), "governance", &comment_path)?;
+        storage_mut.get_json::<ProposalComment>(Some(auth_context), &namespace, &comment_path)?;
 
     // Verify the author is the same as the current user
     if comment.author != auth_context.current_identity_did {
@@ -315,8 +345,7 @@ where
     comment.add_version(new_content.to_string());
 
     // Save the updated comment
-    let mut storage_mut = storage.clone();
-    storage_mut.set_json(Some(auth_context), "governance", &comment_path, &comment)?;
+    storage_mut.set_json(Some(auth_context), &namespace, &comment_path, &comment)?;
 
     // Also save the version history
     let version_id = comment.edit_history.len() - 1;
@@ -327,7 +356,7 @@ where
 
     storage_mut.set_json(
         Some(auth_context),
-        "governance",
+        &namespace,
         &version_path,
         &comment
             .edit_history
@@ -348,6 +377,8 @@ pub fn hide_comment<S>(
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
+    let namespace = comments_namespace(vm);
+
     // Get the comment
     let comment_path = format!(
         "governance/proposals/{}/comments/{}",
@@ -355,26 +386,62 @@ where
     );
 
     let storage = vm
-        .get_storage_backend()
+        .get_storage_backend_mut()
         .ok_or_else(|| "Storage backend not available")?;
     let mut comment =
-        storage.get_json::<ProposalComment>(Some(auth_context), "governance", &comment_path)?;
+        storage.get_json::<ProposalComment>(Some(auth_context), &namespace, &comment_path)?;
 
-    // Verify the author is the same as the current user
-    if comment.author != auth_context.current_identity_did {
-        return Err(format!("Only the original author can hide a comment").into());
+    // Only the author or a moderator may hide a comment
+    if comment.author != auth_context.current_identity_did && !is_moderator(auth_context, &namespace) {
+        return Err(format!("Only the original author or a moderator can hide a comment").into());
     }
 
     // Hide the comment
     comment.hide();
 
     // Save the updated comment
-    let mut storage_mut = storage.clone();
-    storage_mut.set_json(Some(auth_context), "governance", &comment_path, &comment)?;
+    storage.set_json(Some(auth_context), &namespace, &comment_path, &comment)?;
 
     Ok(())
 }
 
+/// Add a reaction to a comment
+pub fn react_to_comment<S>(
+    vm: &mut VM<S>,
+    proposal_id: &str,
+    comment_id: &str,
+    reaction: &str,
+    auth_context: &AuthContext,
+) -> Result<ProposalComment, Box<dyn Error>>
+where
+    S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
+{
+    let namespace = comments_namespace(vm);
+    let comment_path = format!(
+        "governance/proposals/{}/comments/{}",
+        proposal_id, comment_id
+    );
+
+    let storage = vm
+        .get_storage_backend_mut()
+        .ok_or_else(|| "Storage backend not available")?;
+    let mut comment =
+        storage.get_json::<ProposalComment>(Some(auth_context), &namespace, &comment_path)?;
+
+    comment.add_reaction(reaction);
+    storage.set_json(Some(auth_context), &namespace, &comment_path, &comment)?;
+
+    Ok(comment)
+}
+
+/// Whether the caller may moderate comments in `namespace` (hide others'
+/// comments, view hidden ones)
+pub fn is_moderator(auth_context: &AuthContext, namespace: &str) -> bool {
+    auth_context.has_role(namespace, "moderator")
+        || auth_context.has_role(namespace, "admin")
+        || auth_context.has_role("global", "admin")
+}
+
 /// Get the version history of a comment
 pub fn get_comment_history<S>(
     vm: &VM<S>,
@@ -385,6 +452,8 @@ pub fn get_comment_history<S>(
 where
     S: Storage + StorageExtensions + Send + Sync + Clone + Debug + 'static,
 {
+    let namespace = comments_namespace(vm);
+
     // Get the comment
     let comment_path = format!(
         "governance/proposals/{}/comments/{}",
@@ -394,7 +463,7 @@ where
     let storage = vm
         .get_storage_backend()
         .ok_or_else(|| "Storage backend not available")?;
-    let comment = storage.get_json::<ProposalComment>(auth_context, "governance", &comment_path)?;
+    let comment = storage.get_json::<ProposalComment>(auth_context, &namespace, &comment_path)?;
 
     Ok(comment.edit_history.clone())
 }
@@ -0,0 +1,347 @@
+//! Activity digests for proposals
+//!
+//! A digest summarizes what happened in a namespace over a period: the
+//! proposals created, the votes cast and comments posted on each proposal,
+//! and the deadlines coming up after the period ends. `proposal digest`
+//! prints one as Markdown, JSON or YAML, and with `--post` sends it to the
+//! namespace's chat channels as a [`Milestone::Digest`] notice, once or every
+//! day at a set time.
+//!
+//! Votes and comments are placed in the period by their own timestamps, so a
+//! digest can be rebuilt for any past period from storage alone.
+
+use crate::governance::comments::{comments_namespace_for, ProposalComment};
+use crate::governance::export::PROPOSALS_PREFIX;
+use crate::governance::notifications::{Milestone, Notice};
+use crate::governance::proposal_lifecycle::{ProposalLifecycle, ProposalState};
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Write;
+
+/// A proposal created during the period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NewProposal {
+    pub proposal_id: String,
+    pub title: String,
+    pub creator: String,
+    pub state: ProposalState,
+}
+
+/// Votes and comments on one proposal during the period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProposalActivity {
+    pub proposal_id: String,
+    pub title: String,
+    pub votes: usize,
+    pub comments: usize,
+}
+
+/// A deadline falling after the period, within the look-ahead
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpcomingDeadline {
+    pub proposal_id: String,
+    pub title: String,
+    /// What the deadline ends, e.g. "Voting closes"
+    pub label: String,
+    pub at: DateTime<Utc>,
+}
+
+/// What happened in a namespace from `from` up to `to`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Digest {
+    pub namespace: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub new_proposals: Vec<NewProposal>,
+    pub votes_cast: usize,
+    pub comments_posted: usize,
+    /// Proposals that received votes or comments, most active first
+    pub activity: Vec<ProposalActivity>,
+    /// Soonest first
+    pub upcoming_deadlines: Vec<UpcomingDeadline>,
+}
+
+impl Digest {
+    /// Whether nothing happened and nothing is coming up
+    pub fn is_empty(&self) -> bool {
+        self.new_proposals.is_empty()
+            && self.activity.is_empty()
+            && self.upcoming_deadlines.is_empty()
+    }
+
+    /// The digest as a Markdown document
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let _ = writeln!(md, "# Governance digest: {}", self.namespace);
+        let _ = writeln!(
+            md,
+            "\n{} to {}\n",
+            self.from.format("%Y-%m-%d %H:%M UTC"),
+            self.to.format("%Y-%m-%d %H:%M UTC")
+        );
+        let _ = writeln!(
+            md,
+            "{} new proposal(s), {} vote(s) cast, {} comment(s) posted.",
+            self.new_proposals.len(),
+            self.votes_cast,
+            self.comments_posted
+        );
+
+        if !self.new_proposals.is_empty() {
+            md.push_str("\n## New proposals\n\n");
+            for proposal in &self.new_proposals {
+                let _ = writeln!(
+                    md,
+                    "- **{}** ({}) by {}, {:?}",
+                    proposal.title, proposal.proposal_id, proposal.creator, proposal.state
+                );
+            }
+        }
+        if !self.activity.is_empty() {
+            md.push_str("\n## Activity\n\n");
+            for activity in &self.activity {
+                let _ = writeln!(
+                    md,
+                    "- **{}** ({}): {} vote(s), {} comment(s)",
+                    activity.title, activity.proposal_id, activity.votes, activity.comments
+                );
+            }
+        }
+        if !self.upcoming_deadlines.is_empty() {
+            md.push_str("\n## Upcoming deadlines\n\n");
+            for deadline in &self.upcoming_deadlines {
+                let _ = writeln!(
+                    md,
+                    "- {}: {} on **{}** ({})",
+                    deadline.at.format("%Y-%m-%d %H:%M UTC"),
+                    deadline.label,
+                    deadline.title,
+                    deadline.proposal_id
+                );
+            }
+        }
+        md
+    }
+
+    /// The digest as a chat notice
+    pub fn notice(&self) -> Notice {
+        Notice {
+            milestone: Milestone::Digest,
+            text: self.to_markdown(),
+        }
+    }
+}
+
+/// Whether `at` falls in the period from `from` up to, but not including, `to`
+fn in_period(at: DateTime<Utc>, from: DateTime<Utc>, to: DateTime<Utc>) -> bool {
+    from <= at && at < to
+}
+
+/// Summarize the activity of `namespace` from `from` up to `to`, with the
+/// deadlines falling up to `look_ahead` after `to`
+///
+/// Storage is read as `auth`. Records that fail to parse are skipped with a
+/// warning, and hidden comments are not counted.
+pub fn build_digest<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    look_ahead: Duration,
+) -> Result<Digest, String>
+where
+    S: Storage + StorageExtensions,
+{
+    let comments_namespace = comments_namespace_for(namespace);
+    let keys = storage
+        .list_keys(auth, namespace, Some(PROPOSALS_PREFIX))
+        .map_err(|e| e.to_string())?;
+    let mut lifecycle_keys: Vec<&String> = keys
+        .iter()
+        .filter(|key| key.ends_with("/lifecycle"))
+        .collect();
+    lifecycle_keys.sort();
+
+    let mut digest = Digest {
+        namespace: namespace.to_string(),
+        from,
+        to,
+        new_proposals: Vec::new(),
+        votes_cast: 0,
+        comments_posted: 0,
+        activity: Vec::new(),
+        upcoming_deadlines: Vec::new(),
+    };
+    for key in lifecycle_keys {
+        let lifecycle: ProposalLifecycle = match storage.get_json(auth, namespace, key) {
+            Ok(lifecycle) => lifecycle,
+            Err(e) => {
+                tracing::warn!(key = %key, error = %e, "Skipping unreadable proposal");
+                continue;
+            }
+        };
+
+        if in_period(lifecycle.created_at, from, to) {
+            digest.new_proposals.push(NewProposal {
+                proposal_id: lifecycle.id.clone(),
+                title: lifecycle.title.clone(),
+                creator: lifecycle.creator.did().to_string(),
+                state: lifecycle.state.clone(),
+            });
+        }
+
+        let vote_prefix = format!("{}{}/votes/", PROPOSALS_PREFIX, lifecycle.id);
+        let vote_keys = storage
+            .list_keys(auth, namespace, Some(&vote_prefix))
+            .map_err(|e| e.to_string())?;
+        let mut votes = 0;
+        for vote_key in vote_keys {
+            match storage.get_json::<Value>(auth, namespace, &vote_key) {
+                Ok(vote) => {
+                    let cast_at = vote["timestamp"]
+                        .as_str()
+                        .and_then(|at| DateTime::parse_from_rfc3339(at).ok());
+                    if cast_at.map_or(false, |at| in_period(at.with_timezone(&Utc), from, to)) {
+                        votes += 1;
+                    }
+                }
+                Err(e) => tracing::warn!(key = %vote_key, error = %e, "Skipping unreadable vote"),
+            }
+        }
+
+        let comment_prefix = format!("governance/proposals/{}/comments/", lifecycle.id);
+        let comments = storage
+            .list_keys(auth, &comments_namespace, Some(&comment_prefix))
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|comment_key| {
+                storage
+                    .get_json::<ProposalComment>(auth, &comments_namespace, comment_key)
+                    .ok()
+            })
+            .filter(|comment| !comment.hidden && in_period(comment.timestamp, from, to))
+            .count();
+
+        if votes > 0 || comments > 0 {
+            digest.votes_cast += votes;
+            digest.comments_posted += comments;
+            digest.activity.push(ProposalActivity {
+                proposal_id: lifecycle.id.clone(),
+                title: lifecycle.title.clone(),
+                votes,
+                comments,
+            });
+        }
+
+        if let Some((label, at)) = lifecycle.next_deadline() {
+            if at >= to && at < to + look_ahead {
+                digest.upcoming_deadlines.push(UpcomingDeadline {
+                    proposal_id: lifecycle.id.clone(),
+                    title: lifecycle.title.clone(),
+                    label: label.to_string(),
+                    at,
+                });
+            }
+        }
+    }
+
+    digest
+        .activity
+        .sort_by(|a, b| (b.votes + b.comments).cmp(&(a.votes + a.comments)));
+    digest
+        .upcoming_deadlines
+        .sort_by_key(|deadline| deadline.at);
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::identity::Identity;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_digest_counts_only_the_period() {
+        let start = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        let clock = MockClock::new(start + Duration::hours(6));
+        let creator = Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        let mut lifecycle = ProposalLifecycle::new(
+            "p1".to_string(),
+            creator,
+            "Budget".to_string(),
+            50,
+            60,
+            None,
+            None,
+        );
+        lifecycle.created_at = clock.now();
+        lifecycle.open_for_feedback(&clock);
+        lifecycle.start_voting(Duration::days(2), &clock);
+
+        let mut auth = AuthContext::new("did:key:alice");
+        auth.add_role("global", "admin");
+        auth.add_role("default", "admin");
+        let mut storage = InMemoryStorage::new();
+        let prefix = format!("{}p1", PROPOSALS_PREFIX);
+        storage
+            .set_json(
+                Some(&auth),
+                "default",
+                &format!("{}/lifecycle", prefix),
+                &lifecycle,
+            )
+            .unwrap();
+        for (voter, at) in [
+            ("bob", start + Duration::hours(8)),
+            ("carol", start - Duration::hours(1)),
+        ] {
+            let vote = serde_json::json!({
+                "voter": voter,
+                "vote": "yes",
+                "timestamp": at.to_rfc3339(),
+            });
+            storage
+                .set_json(
+                    Some(&auth),
+                    "default",
+                    &format!("{}/votes/{}", prefix, voter),
+                    &vote,
+                )
+                .unwrap();
+        }
+
+        let to = start + Duration::days(1);
+        let digest = build_digest(
+            &storage,
+            Some(&auth),
+            "default",
+            start,
+            to,
+            Duration::days(7),
+        )
+        .unwrap();
+        assert_eq!(digest.new_proposals.len(), 1);
+        assert_eq!(digest.votes_cast, 1);
+        assert_eq!(digest.activity[0].votes, 1);
+        assert_eq!(digest.upcoming_deadlines[0].label, "Voting closes");
+        assert!(digest.to_markdown().contains("## Upcoming deadlines"));
+
+        // The next day nothing new happened and voting closes after the look-ahead
+        let digest = build_digest(
+            &storage,
+            Some(&auth),
+            "default",
+            to,
+            to + Duration::days(1),
+            Duration::hours(1),
+        )
+        .unwrap();
+        assert!(digest.is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+//! Voter eligibility snapshots
+//!
+//! Who may vote on a proposal is decided by its [`VoterEligibility`] rule,
+//! so nobody has to argue afterwards about whether a member admitted or
+//! retired mid-vote was allowed to take part. Except for `any`, voters must
+//! be active members in the registry of the proposal's namespace (see
+//! [`membership`](super::membership)):
+//!
+//! - `creation`: members when the proposal was created
+//! - `voting-open`: members when it opened for voting
+//! - `live`: members at the moment they vote
+//!
+//! The first two rules fix a [`VoterRoll`] in the proposal's lifecycle when
+//! the proposal reaches that point; a roll is never retaken.
+
+use crate::governance::membership::{self, MemberStatus};
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{StorageBackend, StorageExtensions};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Which members may vote on a proposal
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VoterEligibility {
+    /// Anyone may vote
+    #[default]
+    Any,
+    /// Members when the proposal was created
+    AtCreation,
+    /// Members when the proposal opened for voting
+    AtVotingOpen,
+    /// Members at the time of voting
+    Live,
+}
+
+impl fmt::Display for VoterEligibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoterEligibility::Any => write!(f, "any"),
+            VoterEligibility::AtCreation => write!(f, "creation"),
+            VoterEligibility::AtVotingOpen => write!(f, "voting-open"),
+            VoterEligibility::Live => write!(f, "live"),
+        }
+    }
+}
+
+impl FromStr for VoterEligibility {
+    type Err = String;
+
+    /// Parse `any`, `creation`, `voting-open` or `live`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(VoterEligibility::Any),
+            "creation" => Ok(VoterEligibility::AtCreation),
+            "voting-open" => Ok(VoterEligibility::AtVotingOpen),
+            "live" => Ok(VoterEligibility::Live),
+            _ => Err(format!(
+                "Invalid voter eligibility '{}': expected any, creation, voting-open or live",
+                s
+            )),
+        }
+    }
+}
+
+/// The members entitled to vote, as fixed at one point in a proposal's life
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VoterRoll {
+    pub taken_at: DateTime<Utc>,
+    /// DIDs of the active members at `taken_at`
+    pub voters: BTreeSet<String>,
+}
+
+/// The active members of a namespace now
+pub fn take_roll<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+) -> StorageResult<VoterRoll>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    let voters = membership::list_members(storage, auth, namespace)?
+        .into_iter()
+        .filter(|member| member.status == MemberStatus::Active)
+        .map(|member| member.did)
+        .collect();
+    Ok(VoterRoll {
+        taken_at: Utc::now(),
+        voters,
+    })
+}
+
+/// Check that `voter` may vote under `eligibility`, given the proposal's
+/// `roll` if one was fixed
+pub fn check_voter<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    eligibility: VoterEligibility,
+    roll: Option<&VoterRoll>,
+    voter: &str,
+) -> StorageResult<()>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    let refuse = |details: String| {
+        Err(StorageError::ValidationError {
+            rule: "voter_eligibility".to_string(),
+            details,
+        })
+    };
+    let when = match eligibility {
+        VoterEligibility::Any => return Ok(()),
+        VoterEligibility::Live => {
+            return match membership::load_member(storage, auth, namespace, voter)? {
+                Some(member) if member.status == MemberStatus::Active => Ok(()),
+                _ => refuse(format!("{} is not a member of {}", voter, namespace)),
+            };
+        }
+        VoterEligibility::AtCreation => "the proposal was created",
+        VoterEligibility::AtVotingOpen => "voting opened",
+    };
+    match roll {
+        Some(roll) if roll.voters.contains(voter) => Ok(()),
+        Some(_) => refuse(format!("{} was not a member when {}", voter, when)),
+        None => refuse(format!("eligible voters are fixed when {}", when)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_roll_ignores_later_members() {
+        let mut storage = InMemoryStorage::new();
+        let mut auth = AuthContext::new("admin");
+        auth.add_role("global", "admin");
+        let auth = Some(&auth);
+        let add_member = |storage: &mut InMemoryStorage, name: &str| {
+            let record = membership::MemberRecord {
+                did: name.to_string(),
+                username: name.to_string(),
+                roles: vec!["member".to_string()],
+                status: MemberStatus::Active,
+                joined_at: 0,
+                admitted_by: "charter".to_string(),
+                retired_at: None,
+                retired_by: None,
+            };
+            storage
+                .set_json(auth, "coop", &membership::member_key(name), &record)
+                .unwrap();
+        };
+
+        add_member(&mut storage, "alice");
+        let roll = take_roll(&storage, auth, "coop").unwrap();
+        add_member(&mut storage, "bob");
+
+        let check = |eligibility, roll, voter| {
+            check_voter(&storage, auth, "coop", eligibility, roll, voter).is_ok()
+        };
+        assert!(check(VoterEligibility::AtCreation, Some(&roll), "alice"));
+        assert!(!check(VoterEligibility::AtCreation, Some(&roll), "bob"));
+        assert!(!check(VoterEligibility::AtVotingOpen, None, "alice"));
+        assert!(check(VoterEligibility::Live, None, "bob"));
+        assert!(!check(VoterEligibility::Live, None, "carol"));
+        assert!(check(VoterEligibility::Any, None, "carol"));
+    }
+}
@@ -0,0 +1,510 @@
+//! Governance records as analytic tables
+//!
+//! Flattens the proposals of a namespace, their votes, and their comments
+//! into three tables that load directly into spreadsheets, dataframes, or
+//! SQL engines:
+//!
+//! - `proposals`: one row per proposal, with vote and comment counts
+//! - `votes`: one row per vote, keyed by `proposal_id`
+//! - `comments`: one row per visible comment, keyed by `proposal_id`
+//!
+//! Tables are encoded as CSV, or as Parquet in builds with the `parquet`
+//! feature. `proposal export-all` writes all three to a directory and
+//! `GET /api/v1/export/{table}` serves one at a time.
+
+use crate::governance::comments::{comments_namespace_for, ProposalComment};
+use crate::governance::proposal::Proposal;
+use crate::governance::proposal_lifecycle::{ExecutionStatus, ProposalLifecycle};
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::{Storage, StorageExtensions};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// Key prefix proposals are stored under
+pub(crate) const PROPOSALS_PREFIX: &str = "governance_proposals/";
+
+/// File format a table is encoded in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    /// File extension, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "parquet" => Ok(ExportFormat::Parquet),
+            other => Err(format!(
+                "Unknown export format '{}', expected csv or parquet",
+                other
+            )),
+        }
+    }
+}
+
+/// Type of the values in a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Text,
+    Float,
+    Integer,
+    Boolean,
+}
+
+/// One value of a row; any column may hold `Null`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Float(f64),
+    Integer(i64),
+    Boolean(bool),
+    Null,
+}
+
+impl From<Option<String>> for Cell {
+    fn from(value: Option<String>) -> Self {
+        value.map(Cell::Text).unwrap_or(Cell::Null)
+    }
+}
+
+/// A named table with typed columns
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: &'static str,
+    pub columns: &'static [(&'static str, ColumnKind)],
+    pub rows: Vec<Vec<Cell>>,
+}
+
+const PROPOSAL_COLUMNS: &[(&str, ColumnKind)] = &[
+    ("id", ColumnKind::Text),
+    ("title", ColumnKind::Text),
+    ("creator", ColumnKind::Text),
+    ("state", ColumnKind::Text),
+    ("created_at", ColumnKind::Text),
+    ("expires_at", ColumnKind::Text),
+    ("quorum", ColumnKind::Float),
+    ("threshold", ColumnKind::Float),
+    ("execution_status", ColumnKind::Text),
+    ("labels", ColumnKind::Text),
+    ("vote_count", ColumnKind::Integer),
+    ("comment_count", ColumnKind::Integer),
+    ("imported_from", ColumnKind::Text),
+];
+
+const VOTE_COLUMNS: &[(&str, ColumnKind)] = &[
+    ("proposal_id", ColumnKind::Text),
+    ("voter", ColumnKind::Text),
+    ("vote", ColumnKind::Text),
+    ("timestamp", ColumnKind::Text),
+    ("delegated_by", ColumnKind::Text),
+    ("imported_from", ColumnKind::Text),
+];
+
+const COMMENT_COLUMNS: &[(&str, ColumnKind)] = &[
+    ("proposal_id", ColumnKind::Text),
+    ("id", ColumnKind::Text),
+    ("author", ColumnKind::Text),
+    ("timestamp", ColumnKind::Text),
+    ("reply_to", ColumnKind::Text),
+    ("edited", ColumnKind::Boolean),
+    ("content", ColumnKind::Text),
+    ("imported_from", ColumnKind::Text),
+];
+
+/// Names of the tables produced by `collect_tables`, in order
+pub const TABLE_NAMES: [&str; 3] = ["proposals", "votes", "comments"];
+
+impl Table {
+    /// Encode the table as a CSV or Parquet file
+    pub fn encode(&self, format: ExportFormat) -> Result<Vec<u8>, String> {
+        match format {
+            ExportFormat::Csv => Ok(self.to_csv().into_bytes()),
+            ExportFormat::Parquet => self.to_parquet(),
+        }
+    }
+
+    /// CSV with a header row, quoted as in RFC 4180; nulls are empty fields
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        let header: Vec<String> = self
+            .columns
+            .iter()
+            .map(|(name, _)| csv_field(name))
+            .collect();
+        out.push_str(&header.join(","));
+        out.push_str("\r\n");
+        for row in &self.rows {
+            let fields: Vec<String> = row
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Text(text) => csv_field(text),
+                    Cell::Float(value) => value.to_string(),
+                    Cell::Integer(value) => value.to_string(),
+                    Cell::Boolean(value) => value.to_string(),
+                    Cell::Null => String::new(),
+                })
+                .collect();
+            out.push_str(&fields.join(","));
+            out.push_str("\r\n");
+        }
+        out
+    }
+
+    #[cfg(feature = "parquet")]
+    fn to_parquet(&self) -> Result<Vec<u8>, String> {
+        use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+        use std::sync::Arc;
+
+        let fields: Vec<String> = self
+            .columns
+            .iter()
+            .map(|(name, kind)| match kind {
+                ColumnKind::Text => format!("OPTIONAL BYTE_ARRAY {} (UTF8);", name),
+                ColumnKind::Float => format!("OPTIONAL DOUBLE {};", name),
+                ColumnKind::Integer => format!("OPTIONAL INT64 {};", name),
+                ColumnKind::Boolean => format!("OPTIONAL BOOLEAN {};", name),
+            })
+            .collect();
+        let message = format!("message {} {{ {} }}", self.name, fields.join(" "));
+        let schema = Arc::new(parse_message_type(&message).map_err(|e| e.to_string())?);
+
+        let mut buffer = Vec::new();
+        let properties = Arc::new(WriterProperties::builder().build());
+        let mut writer = SerializedFileWriter::new(&mut buffer, schema, properties)
+            .map_err(|e| e.to_string())?;
+        let mut row_group = writer.next_row_group().map_err(|e| e.to_string())?;
+        for index in 0..self.columns.len() {
+            let mut column = row_group
+                .next_column()
+                .map_err(|e| e.to_string())?
+                .ok_or("Parquet schema has fewer columns than the table")?;
+            // Nulls are written as a definition level of 0 and no value
+            let cells: Vec<&Cell> = self.rows.iter().map(|row| &row[index]).collect();
+            let levels: Vec<i16> = cells
+                .iter()
+                .map(|cell| i16::from(**cell != Cell::Null))
+                .collect();
+            let written = match self.columns[index].1 {
+                ColumnKind::Text => {
+                    let values: Vec<ByteArray> = cells
+                        .iter()
+                        .filter_map(|cell| match cell {
+                            Cell::Text(text) => Some(ByteArray::from(text.as_str())),
+                            _ => None,
+                        })
+                        .collect();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+                ColumnKind::Float => {
+                    let values: Vec<f64> = cells
+                        .iter()
+                        .filter_map(|cell| match cell {
+                            Cell::Float(value) => Some(*value),
+                            _ => None,
+                        })
+                        .collect();
+                    column
+                        .typed::<DoubleType>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+                ColumnKind::Integer => {
+                    let values: Vec<i64> = cells
+                        .iter()
+                        .filter_map(|cell| match cell {
+                            Cell::Integer(value) => Some(*value),
+                            _ => None,
+                        })
+                        .collect();
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+                ColumnKind::Boolean => {
+                    let values: Vec<bool> = cells
+                        .iter()
+                        .filter_map(|cell| match cell {
+                            Cell::Boolean(value) => Some(*value),
+                            _ => None,
+                        })
+                        .collect();
+                    column
+                        .typed::<BoolType>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+            };
+            written.map_err(|e| e.to_string())?;
+            column.close().map_err(|e| e.to_string())?;
+        }
+        row_group.close().map_err(|e| e.to_string())?;
+        writer.close().map_err(|e| e.to_string())?;
+        Ok(buffer)
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn to_parquet(&self) -> Result<Vec<u8>, String> {
+        Err("Parquet export requires a build with the `parquet` feature".to_string())
+    }
+}
+
+/// Quote a CSV field when it contains a delimiter, quote, or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render a stored vote value, which is usually a string
+fn vote_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Load every proposal in `namespace` with its votes and comments as the
+/// `proposals`, `votes`, and `comments` tables
+///
+/// Storage is read as `auth`. Hidden comments are left out, and records
+/// that fail to parse are skipped with a warning.
+pub fn collect_tables<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+) -> Result<Vec<Table>, String>
+where
+    S: Storage + StorageExtensions,
+{
+    let comments_namespace = comments_namespace_for(namespace);
+    let keys = storage
+        .list_keys(auth, namespace, Some(PROPOSALS_PREFIX))
+        .map_err(|e| e.to_string())?;
+    let mut lifecycle_keys: Vec<&String> = keys
+        .iter()
+        .filter(|key| key.ends_with("/lifecycle"))
+        .collect();
+    lifecycle_keys.sort();
+
+    let mut proposals = Vec::new();
+    let mut votes = Vec::new();
+    let mut comments = Vec::new();
+    for key in lifecycle_keys {
+        let lifecycle: ProposalLifecycle = match storage.get_json(auth, namespace, key) {
+            Ok(lifecycle) => lifecycle,
+            Err(e) => {
+                tracing::warn!(key = %key, error = %e, "Skipping unreadable proposal");
+                continue;
+            }
+        };
+        let prefix = format!("{}{}", PROPOSALS_PREFIX, lifecycle.id);
+        let proposal = storage
+            .get_json::<Proposal>(auth, namespace, &format!("{}/proposal", prefix))
+            .ok();
+        let labels = proposal
+            .as_ref()
+            .map(|proposal| proposal.labels.join(";"))
+            .unwrap_or_default();
+
+        let mut vote_keys = storage
+            .list_keys(auth, namespace, Some(&format!("{}/votes/", prefix)))
+            .map_err(|e| e.to_string())?;
+        vote_keys.sort();
+        let votes_before = votes.len();
+        for vote_key in vote_keys {
+            match storage.get_json::<Value>(auth, namespace, &vote_key) {
+                Ok(vote) => votes.push(vec![
+                    Cell::Text(lifecycle.id.clone()),
+                    vote["voter"].as_str().map(str::to_string).into(),
+                    vote_text(&vote["vote"]).into(),
+                    vote["timestamp"].as_str().map(str::to_string).into(),
+                    vote["delegated_by"].as_str().map(str::to_string).into(),
+                    vote["imported_from"].as_str().map(str::to_string).into(),
+                ]),
+                Err(e) => tracing::warn!(key = %vote_key, error = %e, "Skipping unreadable vote"),
+            }
+        }
+
+        let comment_prefix = format!("governance/proposals/{}/comments/", lifecycle.id);
+        let comment_keys = storage
+            .list_keys(auth, &comments_namespace, Some(&comment_prefix))
+            .unwrap_or_default();
+        let mut proposal_comments: Vec<ProposalComment> = comment_keys
+            .iter()
+            .filter_map(|comment_key| {
+                storage
+                    .get_json::<ProposalComment>(auth, &comments_namespace, comment_key)
+                    .ok()
+            })
+            .filter(|comment| !comment.hidden)
+            .collect();
+        proposal_comments.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        let comment_count = proposal_comments.len();
+        for comment in proposal_comments {
+            comments.push(vec![
+                Cell::Text(lifecycle.id.clone()),
+                Cell::Text(comment.id),
+                Cell::Text(comment.author),
+                Cell::Text(comment.timestamp.to_rfc3339()),
+                comment.reply_to.into(),
+                Cell::Boolean(comment.edit_history.len() > 1),
+                Cell::Text(comment.content),
+                comment.imported_from.into(),
+            ]);
+        }
+
+        proposals.push(vec![
+            Cell::Text(lifecycle.id.clone()),
+            Cell::Text(lifecycle.title.clone()),
+            Cell::Text(lifecycle.creator.did().to_string()),
+            Cell::Text(format!("{:?}", lifecycle.state)),
+            Cell::Text(lifecycle.created_at.to_rfc3339()),
+            lifecycle.expires_at.map(|at| at.to_rfc3339()).into(),
+            // Stored as percentages
+            Cell::Float(lifecycle.quorum as f64 / 100.0),
+            Cell::Float(lifecycle.threshold as f64 / 100.0),
+            lifecycle
+                .execution_status
+                .as_ref()
+                .map(|status| match status {
+                    ExecutionStatus::Success => "success".to_string(),
+                    ExecutionStatus::Failure(_) => "failure".to_string(),
+                })
+                .into(),
+            Cell::Text(labels),
+            Cell::Integer((votes.len() - votes_before) as i64),
+            Cell::Integer(comment_count as i64),
+            proposal.and_then(|proposal| proposal.imported_from).into(),
+        ]);
+    }
+
+    Ok(vec![
+        Table {
+            name: "proposals",
+            columns: PROPOSAL_COLUMNS,
+            rows: proposals,
+        },
+        Table {
+            name: "votes",
+            columns: VOTE_COLUMNS,
+            rows: votes,
+        },
+        Table {
+            name: "comments",
+            columns: COMMENT_COLUMNS,
+            rows: comments,
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_collect_tables_flattens_votes_and_comments() {
+        let mut auth = AuthContext::new("did:key:alice");
+        auth.add_role("global", "admin");
+        auth.add_role("default", "admin");
+        auth.add_role("governance", "admin");
+        let auth = Some(&auth);
+        let mut storage = InMemoryStorage::new();
+        let creator = Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        let lifecycle = ProposalLifecycle::new(
+            "budget".to_string(),
+            creator,
+            "Budget, 2024".to_string(),
+            50,
+            60,
+            None,
+            None,
+        );
+        storage
+            .set_json(
+                auth,
+                "default",
+                "governance_proposals/budget/lifecycle",
+                &lifecycle,
+            )
+            .unwrap();
+        storage
+            .set_json(
+                auth,
+                "default",
+                "governance_proposals/budget/votes/bob",
+                &serde_json::json!({
+                    "voter": "bob",
+                    "vote": "yes",
+                    "timestamp": "2024-01-02T00:00:00Z",
+                    "delegated_by": null,
+                }),
+            )
+            .unwrap();
+        let comment = ProposalComment::new(
+            "carol".to_string(),
+            "Line one\n\"quoted\"".to_string(),
+            None,
+            Vec::new(),
+        );
+        let mut hidden = ProposalComment::new("dan".to_string(), "spam".to_string(), None, vec![]);
+        hidden.hide();
+        for comment in [&comment, &hidden] {
+            let key = format!("governance/proposals/budget/comments/{}", comment.id);
+            storage.set_json(auth, "governance", &key, comment).unwrap();
+        }
+
+        let tables = collect_tables(&storage, auth, "default").unwrap();
+        let names: Vec<&str> = tables.iter().map(|table| table.name).collect();
+        assert_eq!(names, TABLE_NAMES);
+        let (proposals, votes, comments) = (&tables[0], &tables[1], &tables[2]);
+        assert_eq!(proposals.rows.len(), 1);
+        assert_eq!(proposals.rows[0][6], Cell::Float(0.5));
+        assert_eq!(proposals.rows[0][10], Cell::Integer(1));
+        assert_eq!(proposals.rows[0][11], Cell::Integer(1));
+        assert_eq!(votes.rows[0][2], Cell::Text("yes".to_string()));
+        assert_eq!(votes.rows[0][4], Cell::Null);
+        assert_eq!(comments.rows.len(), 1);
+
+        let csv = comments.to_csv();
+        assert!(csv.starts_with("proposal_id,id,author,timestamp,reply_to,edited,content,"));
+        assert!(csv.ends_with(",,false,\"Line one\n\"\"quoted\"\"\",\r\n"));
+        assert!(proposals.to_csv().contains(",\"Budget, 2024\","));
+        assert_eq!("CSV".parse::<ExportFormat>(), Ok(ExportFormat::Csv));
+        assert!("xlsx".parse::<ExportFormat>().is_err());
+    }
+}
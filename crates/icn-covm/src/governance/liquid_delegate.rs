@@ -1,13 +1,157 @@
 use crate::governance::traits::GovernanceOpHandler;
-use crate::storage::traits::Storage;
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::typed::TypedValue;
 use crate::vm::execution::ExecutorOps;
 use crate::vm::memory::MemoryScope;
 use crate::vm::types::Op;
 use crate::vm::{VMError, VM};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
 use std::marker::{Send, Sync};
 
+/// Memory key holding the delegation count, with the graph as metadata
+const DELEGATIONS_MEMORY_KEY: &str = "governance_delegations";
+
+/// Storage key of the delegation graph within the VM's namespace
+pub const DELEGATIONS_STORAGE_KEY: &str = "governance/delegations";
+
+/// Who each member has delegated their vote to
+///
+/// Delegations chain: a member's vote follows their delegate's delegation
+/// in turn, so it is cast by the first member along the chain who votes.
+/// Cycles are rejected when a delegation is made, so every chain ends.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DelegationGraph {
+    delegations: BTreeMap<String, String>,
+}
+
+impl DelegationGraph {
+    /// Load the graph the VM keeps for its namespace
+    ///
+    /// With a storage backend and an identity to act as, the graph lives in
+    /// storage under `DELEGATIONS_STORAGE_KEY` and outlasts the run;
+    /// otherwise it lives in VM memory for the run only.
+    pub fn load<S>(vm: &mut VM<S>) -> Result<Self, VMError>
+    where
+        S: Storage + Send + Sync + Clone + Debug + 'static,
+    {
+        if Self::is_persistent(vm) {
+            let delegations =
+                vm.executor
+                    .storage_operation("load_delegations", |backend, auth, namespace| {
+                        if !backend.contains(auth, namespace, DELEGATIONS_STORAGE_KEY)? {
+                            return Ok(BTreeMap::new());
+                        }
+                        backend.get_json(auth, namespace, DELEGATIONS_STORAGE_KEY)
+                    })?;
+            return Ok(Self { delegations });
+        }
+
+        let delegations = vm
+            .memory
+            .get_string_metadata(DELEGATIONS_MEMORY_KEY)
+            .and_then(|metadata| serde_json::from_str(&metadata).ok())
+            .unwrap_or_default();
+        Ok(Self { delegations })
+    }
+
+    /// Save the graph where `load` reads it from
+    ///
+    /// The delegation count is kept in VM memory either way.
+    pub fn save<S>(&self, vm: &mut VM<S>) -> Result<(), VMError>
+    where
+        S: Storage + Send + Sync + Clone + Debug + 'static,
+    {
+        if Self::is_persistent(vm) {
+            vm.executor
+                .storage_operation("save_delegations", |backend, auth, namespace| {
+                    backend.set_json(auth, namespace, DELEGATIONS_STORAGE_KEY, &self.delegations)
+                })?;
+        } else {
+            let serialized = serde_json::to_string(&self.delegations).map_err(|e| {
+                VMError::Deserialization(format!("Failed to serialize delegations: {}", e))
+            })?;
+            vm.memory
+                .set_string_metadata(DELEGATIONS_MEMORY_KEY, serialized);
+        }
+        vm.memory.store(
+            DELEGATIONS_MEMORY_KEY,
+            TypedValue::Number(self.delegations.len() as f64),
+        );
+        Ok(())
+    }
+
+    fn is_persistent<S>(vm: &VM<S>) -> bool
+    where
+        S: Storage + Send + Sync + Clone + Debug + 'static,
+    {
+        vm.get_storage_backend().is_some() && vm.get_auth_context().is_some()
+    }
+
+    /// The member `from` has delegated to directly, if any
+    pub fn delegate_of(&self, from: &str) -> Option<&str> {
+        self.delegations.get(from).map(String::as_str)
+    }
+
+    /// Delegate `from`'s vote to `to`, replacing any earlier delegation
+    ///
+    /// Fails without changing the graph if `to`'s chain leads back to
+    /// `from`, including a member delegating to themselves.
+    pub fn delegate(&mut self, from: &str, to: &str) -> Result<(), VMError> {
+        let mut current = Some(to);
+        while let Some(member) = current {
+            if member == from {
+                return Err(VMError::GovernanceError(format!(
+                    "Delegation from {} to {} would create a cycle",
+                    from, to
+                )));
+            }
+            current = self.delegate_of(member);
+        }
+        self.delegations.insert(from.to_string(), to.to_string());
+        Ok(())
+    }
+
+    /// Revoke `from`'s delegation, returning whether there was one
+    pub fn revoke(&mut self, from: &str) -> bool {
+        self.delegations.remove(from).is_some()
+    }
+
+    /// The member at the end of `member`'s delegation chain
+    pub fn resolve<'a>(&'a self, member: &'a str) -> &'a str {
+        let mut current = member;
+        while let Some(next) = self.delegate_of(current) {
+            current = next;
+        }
+        current
+    }
+
+    /// Voting weight of each of `voters`
+    ///
+    /// Every voter counts once for themselves, whatever they delegated, and
+    /// once more for each member who did not vote and whose chain reaches
+    /// them before any other voter.
+    pub fn weights(&self, voters: &[String]) -> HashMap<String, f64> {
+        let voting: HashSet<&str> = voters.iter().map(String::as_str).collect();
+        let mut weights: HashMap<String, f64> =
+            voters.iter().map(|voter| (voter.clone(), 1.0)).collect();
+        for member in self.delegations.keys() {
+            if voting.contains(member.as_str()) {
+                continue;
+            }
+            let mut current = self.delegate_of(member);
+            while let Some(delegate) = current {
+                if voting.contains(delegate) {
+                    *weights.entry(delegate.to_string()).or_default() += 1.0;
+                    break;
+                }
+                current = self.delegate_of(delegate);
+            }
+        }
+        weights
+    }
+}
+
 /// Handler for LiquidDelegate operations
 pub struct LiquidDelegateHandler;
 
@@ -24,29 +168,11 @@ impl GovernanceOpHandler for LiquidDelegateHandler {
                 ));
             }
 
-            // Get current delegations from memory or initialize a new map
-            let delegations_key = "governance_delegations";
-            let mut delegations: HashMap<String, String> = match vm.memory.load(delegations_key) {
-                Ok(_) => {
-                    // Try to retrieve from VM metadata
-                    if let Some(metadata) = vm.memory.get_string_metadata(delegations_key) {
-                        match serde_json::from_str(&metadata) {
-                            Ok(map) => map,
-                            Err(_) => HashMap::new(),
-                        }
-                    } else {
-                        HashMap::new()
-                    }
-                }
-                Err(_) => {
-                    // Initialize an empty delegation map
-                    HashMap::new()
-                }
-            };
+            let mut graph = DelegationGraph::load(vm)?;
 
             if to.is_empty() {
                 // If 'to' is empty, it's a revocation
-                if delegations.remove(from).is_some() {
+                if graph.revoke(from) {
                     vm.executor
                         .emit_event("governance", &format!("Delegation revoked for {}", from));
                 } else {
@@ -56,49 +182,21 @@ impl GovernanceOpHandler for LiquidDelegateHandler {
                     );
                 }
             } else {
-                // Check for cycles in the delegation graph
-                let mut visited = HashMap::new();
-                visited.insert(from.clone(), true);
-
-                // Start with the immediate delegation target
-                let mut current = to.clone();
-
-                // Follow the delegation chain to detect cycles
-                while !current.is_empty() {
-                    // If we've seen this node before, we have a cycle
-                    if visited.contains_key(&current) {
-                        return Err(VMError::GovernanceError(format!(
-                            "Delegation from {} to {} would create a cycle",
-                            from, to
-                        )));
-                    }
-
-                    // Mark this node as visited
-                    visited.insert(current.clone(), true);
-
-                    // Move to the next node in the chain, if any
-                    current = delegations.get(&current).cloned().unwrap_or_default();
-                }
-
-                // No cycles found, add the delegation
-                delegations.insert(from.clone(), to.clone());
+                graph.delegate(from, to)?;
                 vm.executor.emit_event(
                     "governance",
                     &format!("Delegation created from {} to {}", from, to),
                 );
+                let end = graph.resolve(to);
+                if end != to {
+                    vm.executor.emit_event(
+                        "governance",
+                        &format!("Votes delegated by {} are cast by {}", from, end),
+                    );
+                }
             }
 
-            // Store the updated delegations map in memory
-            let serialized = serde_json::to_string(&delegations).map_err(|e| {
-                VMError::Deserialization(format!("Failed to serialize delegations: {}", e))
-            })?;
-
-            vm.memory.set_string_metadata(delegations_key, serialized);
-
-            // Also store a numeric value to indicate the delegation count
-            vm.memory.store(delegations_key, delegations.len() as f64);
-
-            Ok(())
+            graph.save(vm)
         } else {
             Err(VMError::UndefinedOperation(
                 "Expected LiquidDelegate operation".into(),
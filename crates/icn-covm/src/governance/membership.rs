@@ -0,0 +1,293 @@
+//! Membership changes made by governance
+//!
+//! Members join and leave a cooperative through proposals: the
+//! `onboardmember` and `offboardmember` ops run only in the logic of an
+//! approved proposal, so every change to the roster is a recorded governance
+//! act. The `onboard-member` and `offboard-member` templates create such
+//! proposals.
+//!
+//! Onboarding a `did:key` identity:
+//! - registers its public identity at `identities/{did}` in the `identity`
+//!   namespace, if it is not registered already
+//! - grants its roles in the cooperative's namespace and adds the namespace
+//!   to its memberships, where token issuance reads them
+//! - creates its storage account, if it has none
+//! - adds an active [`MemberRecord`] at `members/{did}` in the namespace
+//!
+//! Offboarding revokes the member's roles and membership in the namespace
+//! and marks the record retired. The identity, the account and the record
+//! are kept, so past votes and transfers still resolve. A retired member can
+//! be onboarded again.
+//!
+//! Identity, role and account records are written on the cooperative's
+//! behalf: the approved proposal is the authority for the change, so the
+//! executor needs no rights over the `identity` namespace.
+
+use crate::identity::{Identity, Profile};
+use crate::storage::auth::{
+    memberships_key, roles_key, AuthContext, RoleAssignment, IDENTITY_NAMESPACE,
+};
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{StorageBackend, StorageExtensions};
+use crate::storage::utils::{now_with_default, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Prefix of member records in a cooperative's namespace
+pub const MEMBER_PREFIX: &str = "members/";
+
+/// Storage quota of the account created for a new member
+pub const MEMBER_ACCOUNT_QUOTA: u64 = 1024 * 1024;
+
+/// Role granted when an onboarding proposal names none
+pub const DEFAULT_MEMBER_ROLE: &str = "member";
+
+/// Whether a member currently belongs to the cooperative
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberStatus {
+    Active,
+    Retired,
+}
+
+/// A member's entry on a cooperative's roster
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MemberRecord {
+    pub did: String,
+    pub username: String,
+    /// Roles granted in the cooperative's namespace
+    pub roles: Vec<String>,
+    pub status: MemberStatus,
+    pub joined_at: Timestamp,
+    /// Proposal that admitted the member most recently
+    pub admitted_by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retired_at: Option<Timestamp>,
+    /// Proposal that retired the member
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retired_by: Option<String>,
+}
+
+/// Key of a member's record
+pub fn member_key(did: &str) -> String {
+    format!("{}{}", MEMBER_PREFIX, did)
+}
+
+/// Context for the records written on the cooperative's behalf
+fn registry_auth() -> AuthContext {
+    let mut auth = AuthContext::new("governance");
+    auth.add_role("global", "admin");
+    auth
+}
+
+fn invalid(details: String) -> StorageError {
+    StorageError::ValidationError {
+        rule: "membership".to_string(),
+        details,
+    }
+}
+
+/// The public identity of a `did:key` DID, whose key is encoded in the DID
+pub fn identity_from_did(did: &str, username: &str) -> StorageResult<Identity> {
+    let public_key_multibase = did
+        .strip_prefix("did:key:")
+        .ok_or_else(|| invalid(format!("'{}' is not a did:key DID", did)))?;
+    let (_, public_key_bytes) = multibase::decode(public_key_multibase)
+        .map_err(|e| invalid(format!("'{}' does not encode a key: {}", did, e)))?;
+    if public_key_bytes.len() != 32 {
+        return Err(invalid(format!(
+            "'{}' does not encode an Ed25519 public key",
+            did
+        )));
+    }
+    Ok(Identity {
+        did: did.to_string(),
+        public_key_bytes,
+        private_key_bytes: None,
+        public_key_multibase: public_key_multibase.to_string(),
+        profile: Profile {
+            public_username: username.to_string(),
+            full_name: None,
+            other_fields: HashMap::new(),
+        },
+        identity_type: "member".to_string(),
+    })
+}
+
+/// A member's record, if they have ever been onboarded
+pub fn load_member<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    did: &str,
+) -> StorageResult<Option<MemberRecord>>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    match storage.get_json(auth, namespace, &member_key(did)) {
+        Ok(record) => Ok(Some(record)),
+        Err(StorageError::NotFound { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Every member record in a namespace, active and retired
+pub fn list_members<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+) -> StorageResult<Vec<MemberRecord>>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    storage
+        .list_keys(auth, namespace, Some(MEMBER_PREFIX))?
+        .iter()
+        .map(|key| storage.get_json(auth, namespace, key))
+        .collect()
+}
+
+/// Read a JSON list from the identity namespace, empty if it is missing
+fn identity_list<S, T>(storage: &S, key: &str) -> StorageResult<Vec<T>>
+where
+    S: StorageBackend + StorageExtensions,
+    T: serde::de::DeserializeOwned,
+{
+    match storage.get_json(Some(&registry_auth()), IDENTITY_NAMESPACE, key) {
+        Ok(list) => Ok(list),
+        Err(StorageError::NotFound { .. }) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Admit `did` to the cooperative in `namespace` with `roles`, as decided by
+/// `proposal_id`
+pub fn onboard_member<S>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    proposal_id: &str,
+    did: &str,
+    username: &str,
+    roles: &[String],
+) -> StorageResult<MemberRecord>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    let previous = load_member(storage, auth, namespace, did)?;
+    if previous
+        .as_ref()
+        .is_some_and(|record| record.status == MemberStatus::Active)
+    {
+        return Err(invalid(format!(
+            "{} is already a member of {}",
+            did, namespace
+        )));
+    }
+    let roles = if roles.is_empty() {
+        vec![DEFAULT_MEMBER_ROLE.to_string()]
+    } else {
+        roles.to_vec()
+    };
+
+    let registry = registry_auth();
+    let identity_key = format!("identities/{}", did);
+    if !storage.contains(Some(&registry), IDENTITY_NAMESPACE, &identity_key)? {
+        let identity = identity_from_did(did, username)?;
+        storage.set_json(
+            Some(&registry),
+            IDENTITY_NAMESPACE,
+            &identity_key,
+            &identity,
+        )?;
+    }
+
+    let mut assignments: Vec<RoleAssignment> = identity_list(storage, &roles_key(did))?;
+    for role in &roles {
+        let assignment = RoleAssignment {
+            namespace: namespace.to_string(),
+            role: role.clone(),
+        };
+        if !assignments.contains(&assignment) {
+            assignments.push(assignment);
+        }
+    }
+    storage.set_json(
+        Some(&registry),
+        IDENTITY_NAMESPACE,
+        &roles_key(did),
+        &assignments,
+    )?;
+
+    let mut memberships: Vec<String> = identity_list(storage, &memberships_key(did))?;
+    if !memberships.iter().any(|m| m == namespace) {
+        memberships.push(namespace.to_string());
+        storage.set_json(
+            Some(&registry),
+            IDENTITY_NAMESPACE,
+            &memberships_key(did),
+            &memberships,
+        )?;
+    }
+
+    match storage.create_account(Some(&registry), did, MEMBER_ACCOUNT_QUOTA) {
+        // A returning member keeps the account they had
+        Ok(()) | Err(StorageError::TransactionError { .. }) => {}
+        Err(e) => return Err(e),
+    }
+
+    let record = MemberRecord {
+        did: did.to_string(),
+        username: username.to_string(),
+        roles,
+        status: MemberStatus::Active,
+        joined_at: now_with_default(),
+        admitted_by: proposal_id.to_string(),
+        retired_at: None,
+        retired_by: None,
+    };
+    storage.set_json(auth, namespace, &member_key(did), &record)?;
+    Ok(record)
+}
+
+/// Retire `did` from the cooperative in `namespace`, as decided by
+/// `proposal_id`
+pub fn offboard_member<S>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    proposal_id: &str,
+    did: &str,
+) -> StorageResult<MemberRecord>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    let mut record = load_member(storage, auth, namespace, did)?
+        .filter(|record| record.status == MemberStatus::Active)
+        .ok_or_else(|| invalid(format!("{} is not a member of {}", did, namespace)))?;
+
+    let registry = registry_auth();
+    let mut assignments: Vec<RoleAssignment> = identity_list(storage, &roles_key(did))?;
+    assignments.retain(|assignment| assignment.namespace != namespace);
+    storage.set_json(
+        Some(&registry),
+        IDENTITY_NAMESPACE,
+        &roles_key(did),
+        &assignments,
+    )?;
+
+    let mut memberships: Vec<String> = identity_list(storage, &memberships_key(did))?;
+    memberships.retain(|m| m != namespace);
+    storage.set_json(
+        Some(&registry),
+        IDENTITY_NAMESPACE,
+        &memberships_key(did),
+        &memberships,
+    )?;
+
+    record.status = MemberStatus::Retired;
+    record.retired_at = Some(now_with_default());
+    record.retired_by = Some(proposal_id.to_string());
+    storage.set_json(auth, namespace, &member_key(did), &record)?;
+    Ok(record)
+}
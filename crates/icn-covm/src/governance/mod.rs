@@ -2,6 +2,7 @@
 //!
 //! This module contains implementations of governance operations:
 //! - RankedVote: Ranked-choice voting implementation
+//! - QuadraticVote: Voting where votes cost their square in voice credits
 //! - LiquidDelegate: Delegate voting power to another account
 //! - QuorumThreshold: Check if voting participation meets a threshold
 //! - VoteThreshold: Check if vote approval meets a threshold
@@ -12,15 +13,28 @@
 //! - Improves maintainability of governance-specific code
 //! - Sets up for future plugin-style governance logic
 
+pub mod archive;
+pub mod attachments;
 pub mod comments;
+pub mod digest;
+pub mod eligibility;
+pub mod export;
+pub mod membership;
+pub mod notifications;
 pub mod proposal;
 pub mod proposal_lifecycle;
+pub mod replay;
+pub mod role_classes;
+pub mod scheduler;
+pub mod snapshot;
+pub mod templates;
 // Make contents public for use in tests/CLI
 pub use comments::{CommentVersion, ProposalComment};
 pub use proposal::{Proposal, ProposalStatus};
 pub use proposal_lifecycle::{Comment, ExecutionStatus, ProposalLifecycle, ProposalState};
 
 mod liquid_delegate;
+mod quadratic_vote;
 mod quorum_threshold;
 mod ranked_vote;
 pub mod traits;
@@ -45,6 +59,10 @@ where
             ranked_vote::RankedVoteHandler::handle(vm, op)?;
             Ok(Some(()))
         }
+        Op::QuadraticVote { .. } => {
+            quadratic_vote::QuadraticVoteHandler::handle(vm, op)?;
+            Ok(Some(()))
+        }
         Op::LiquidDelegate { .. } => {
             liquid_delegate::LiquidDelegateHandler::handle(vm, op)?;
             Ok(Some(()))
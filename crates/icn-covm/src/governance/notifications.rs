@@ -0,0 +1,418 @@
+//! Chat notifications for proposal milestones
+//!
+//! Each namespace keeps its chat channels in storage under
+//! [`CHANNELS_KEY`]. When a proposal in the namespace opens for voting,
+//! reaches its quorum, or is executed, a short message is posted to every
+//! channel subscribed to that [`Milestone`]:
+//!
+//! - Slack and Discord channels through their incoming webhooks
+//! - Matrix rooms through the client-server API, as `m.notice` messages
+//!
+//! Activity digests from `proposal digest --post` are posted the same way,
+//! to channels subscribed to [`Milestone::Digest`].
+//!
+//! Messages go through [`crate::http`], so only `http://` URLs work; reach
+//! hosted services through a local relay that terminates TLS. Delivery is
+//! synchronous with a short timeout, and a channel that fails is logged and
+//! skipped, so notifications never hold up or fail a governance action.
+
+use crate::governance::proposal_lifecycle::ProposalLifecycle;
+use crate::http::{encode_path_segment, Endpoint};
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{Storage, StorageExtensions};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Key, in each namespace, of the list of chat channels to notify
+pub const CHANNELS_KEY: &str = "notifications/channels";
+
+/// How long a chat service may take to connect and respond
+pub const NOTIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A point in a proposal's life that channels can be notified of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Milestone {
+    VotingOpened,
+    QuorumReached,
+    Executed,
+    /// A periodic summary of the namespace's activity, see
+    /// [`crate::governance::digest`]
+    Digest,
+}
+
+impl Milestone {
+    pub const ALL: [Milestone; 4] = [
+        Milestone::VotingOpened,
+        Milestone::QuorumReached,
+        Milestone::Executed,
+        Milestone::Digest,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Milestone::VotingOpened => "voting_opened",
+            Milestone::QuorumReached => "quorum_reached",
+            Milestone::Executed => "executed",
+            Milestone::Digest => "digest",
+        }
+    }
+}
+
+impl fmt::Display for Milestone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Milestone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Milestone::ALL
+            .into_iter()
+            .find(|milestone| milestone.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| {
+                format!(
+                    "Unknown milestone: {} (expected voting_opened, quorum_reached, executed, \
+                     or digest)",
+                    s
+                )
+            })
+    }
+}
+
+/// Where a channel's messages are posted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatTarget {
+    /// A Slack incoming webhook
+    Slack { webhook_url: String },
+    /// A Discord channel webhook
+    Discord { webhook_url: String },
+    /// A Matrix room, posted to as the user owning `access_token`
+    Matrix {
+        homeserver: String,
+        room_id: String,
+        access_token: String,
+    },
+}
+
+impl ChatTarget {
+    /// Short description that leaves out webhook paths and tokens
+    pub fn describe(&self) -> String {
+        let host = |url: &str| {
+            Endpoint::parse(url)
+                .map(|endpoint| endpoint.host)
+                .unwrap_or_else(|_| "invalid URL".to_string())
+        };
+        match self {
+            ChatTarget::Slack { webhook_url } => format!("slack via {}", host(webhook_url)),
+            ChatTarget::Discord { webhook_url } => format!("discord via {}", host(webhook_url)),
+            ChatTarget::Matrix {
+                homeserver,
+                room_id,
+                ..
+            } => format!("matrix {} on {}", room_id, host(homeserver)),
+        }
+    }
+
+    /// Post `text` to the target
+    pub fn send(&self, text: &str) -> io::Result<()> {
+        let json = ("Content-Type", "application/json");
+        match self {
+            ChatTarget::Slack { webhook_url } => {
+                let body = serde_json::json!({ "text": text }).to_string();
+                Endpoint::parse(webhook_url)?.request(
+                    "POST",
+                    "",
+                    &[json],
+                    body.as_bytes(),
+                    NOTIFY_TIMEOUT,
+                )?;
+            }
+            ChatTarget::Discord { webhook_url } => {
+                let body = serde_json::json!({ "content": text }).to_string();
+                Endpoint::parse(webhook_url)?.request(
+                    "POST",
+                    "",
+                    &[json],
+                    body.as_bytes(),
+                    NOTIFY_TIMEOUT,
+                )?;
+            }
+            ChatTarget::Matrix {
+                homeserver,
+                room_id,
+                access_token,
+            } => {
+                let body = serde_json::json!({ "msgtype": "m.notice", "body": text }).to_string();
+                // A fresh transaction ID per message, so retries by the
+                // homeserver are deduplicated but messages never are
+                let path = format!(
+                    "/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+                    encode_path_segment(room_id),
+                    uuid::Uuid::new_v4().simple()
+                );
+                let authorization = format!("Bearer {}", access_token);
+                Endpoint::parse(homeserver)?.request(
+                    "PUT",
+                    &path,
+                    &[json, ("Authorization", authorization.as_str())],
+                    body.as_bytes(),
+                    NOTIFY_TIMEOUT,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A chat channel and the milestones it is notified of
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatChannel {
+    pub target: ChatTarget,
+    /// Milestones to post about; empty means all of them
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+}
+
+impl ChatChannel {
+    pub fn wants(&self, milestone: Milestone) -> bool {
+        self.milestones.is_empty() || self.milestones.contains(&milestone)
+    }
+}
+
+/// A message about one proposal milestone
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notice {
+    pub milestone: Milestone,
+    pub text: String,
+}
+
+impl Notice {
+    pub fn voting_opened(namespace: &str, proposal_id: &str, title: &str) -> Self {
+        Self {
+            milestone: Milestone::VotingOpened,
+            text: format!(
+                "🗳️ Voting is open on \"{}\" ({}) in {}",
+                title, proposal_id, namespace
+            ),
+        }
+    }
+
+    pub fn quorum_reached(namespace: &str, proposal_id: &str, title: &str, votes: usize) -> Self {
+        Self {
+            milestone: Milestone::QuorumReached,
+            text: format!(
+                "📊 \"{}\" ({}) in {} reached quorum with {} vote(s)",
+                title, proposal_id, namespace, votes
+            ),
+        }
+    }
+
+    pub fn executed(namespace: &str, proposal_id: &str, title: &str, success: bool) -> Self {
+        let (icon, outcome) = if success {
+            ("✅", "was executed")
+        } else {
+            ("⚠️", "failed to execute")
+        };
+        Self {
+            milestone: Milestone::Executed,
+            text: format!(
+                "{} \"{}\" ({}) in {} {}",
+                icon, title, proposal_id, namespace, outcome
+            ),
+        }
+    }
+}
+
+/// Whether `total_votes` meets the quorum of a proposal
+///
+/// Participation counts against `required_participants`, as when the
+/// proposal is executed.
+pub fn quorum_met(lifecycle: &ProposalLifecycle, total_votes: usize) -> bool {
+    let required = lifecycle.required_participants.unwrap_or(1).max(1);
+    total_votes as f64 / required as f64 >= lifecycle.quorum as f64 / 100.0
+}
+
+/// The chat channels of a namespace, or none if it has no mapping
+pub fn load_channels<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+) -> StorageResult<Vec<ChatChannel>>
+where
+    S: Storage,
+{
+    match storage.get_json(auth, namespace, CHANNELS_KEY) {
+        Ok(channels) => Ok(channels),
+        Err(StorageError::NotFound { .. }) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Replace the chat channels of a namespace
+pub fn save_channels<S>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    channels: &[ChatChannel],
+) -> StorageResult<()>
+where
+    S: Storage,
+{
+    storage.set_json(auth, namespace, CHANNELS_KEY, &channels)
+}
+
+/// Post `notice` to every channel of `namespace` subscribed to its milestone
+///
+/// Returns how many channels received it. Failures, including a mapping
+/// that cannot be read, are logged rather than returned.
+pub fn notify<S>(storage: &S, auth: Option<&AuthContext>, namespace: &str, notice: &Notice) -> usize
+where
+    S: Storage,
+{
+    let channels = match load_channels(storage, auth, namespace) {
+        Ok(channels) => channels,
+        Err(e) => {
+            tracing::warn!(namespace, error = %e, "Failed to load chat channels");
+            return 0;
+        }
+    };
+
+    let mut delivered = 0;
+    for channel in channels.iter().filter(|c| c.wants(notice.milestone)) {
+        match channel.target.send(&notice.text) {
+            Ok(()) => delivered += 1,
+            Err(e) => tracing::warn!(
+                namespace,
+                channel = %channel.target.describe(),
+                milestone = %notice.milestone,
+                error = %e,
+                "Failed to post chat notification"
+            ),
+        }
+    }
+    delivered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Accept one request, answer 200, and return what was sent
+    fn serve_once() -> (u16, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Content-Length: "))
+                        .and_then(|len| len.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n{}").unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+        (port, server)
+    }
+
+    #[test]
+    fn test_notify_posts_to_subscribed_channels() {
+        let mut auth = AuthContext::new("did:key:alice");
+        auth.add_role("global", "admin");
+        auth.add_role("coops/alpha", "admin");
+        let mut storage = InMemoryStorage::new();
+        assert!(load_channels(&storage, Some(&auth), "coops/alpha")
+            .unwrap()
+            .is_empty());
+
+        let (slack_port, slack) = serve_once();
+        let (matrix_port, matrix) = serve_once();
+        let channels = vec![
+            ChatChannel {
+                target: ChatTarget::Slack {
+                    webhook_url: format!("http://127.0.0.1:{}/services/T0/B0", slack_port),
+                },
+                milestones: vec![Milestone::VotingOpened],
+            },
+            ChatChannel {
+                target: ChatTarget::Matrix {
+                    homeserver: format!("http://127.0.0.1:{}", matrix_port),
+                    room_id: "!votes:example.org".to_string(),
+                    access_token: "secret".to_string(),
+                },
+                milestones: Vec::new(),
+            },
+            ChatChannel {
+                target: ChatTarget::Discord {
+                    webhook_url: "http://127.0.0.1:1/unused".to_string(),
+                },
+                milestones: vec![Milestone::Executed],
+            },
+        ];
+        save_channels(&mut storage, Some(&auth), "coops/alpha", &channels).unwrap();
+
+        let notice = Notice::voting_opened("coops/alpha", "p1", "Buy a van");
+        assert_eq!(notify(&storage, Some(&auth), "coops/alpha", &notice), 2);
+
+        let slack_request = slack.join().unwrap();
+        assert!(slack_request.starts_with("POST /services/T0/B0 HTTP/1.0"));
+        assert!(slack_request.contains("\"text\":\"🗳️ Voting is open on \\\"Buy a van\\\""));
+
+        let matrix_request = matrix.join().unwrap();
+        assert!(matrix_request.starts_with(
+            "PUT /_matrix/client/v3/rooms/%21votes%3Aexample.org/send/m.room.message/"
+        ));
+        assert!(matrix_request.contains("Authorization: Bearer secret"));
+        assert!(matrix_request.contains("\"msgtype\":\"m.notice\""));
+    }
+
+    #[test]
+    fn test_quorum_met_and_milestone_parsing() {
+        let creator = Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        let mut lifecycle = ProposalLifecycle::new(
+            "p1".to_string(),
+            creator,
+            "Buy a van".to_string(),
+            60,
+            50,
+            None,
+            Some(5),
+        );
+        assert!(!quorum_met(&lifecycle, 2));
+        assert!(quorum_met(&lifecycle, 3));
+        lifecycle.required_participants = None;
+        assert!(quorum_met(&lifecycle, 1));
+
+        assert_eq!(
+            "Quorum_Reached".parse::<Milestone>(),
+            Ok(Milestone::QuorumReached)
+        );
+        assert!("closed".parse::<Milestone>().is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::clock::Clock;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +16,15 @@ pub struct Proposal {
     pub execution_result: Option<String>,
     pub deliberation_started_at: Option<DateTime<Utc>>,
     pub min_deliberation_hours: Option<i64>,
+    /// Free-form labels used to categorize and filter proposals
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Platform a historical proposal was imported from, e.g. `loomio`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported_from: Option<String>,
+    /// Proposal this one was cloned from by `proposal clone`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloned_from: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -51,6 +61,9 @@ impl Proposal {
             execution_result: None,
             deliberation_started_at: None,
             min_deliberation_hours: None,
+            labels: Vec::new(),
+            imported_from: None,
+            cloned_from: None,
         }
     }
 
@@ -63,9 +76,9 @@ impl Proposal {
         self.status = ProposalStatus::Active;
     }
 
-    pub fn mark_deliberation(&mut self) {
+    pub fn mark_deliberation(&mut self, clock: &dyn Clock) {
         self.status = ProposalStatus::Deliberation;
-        self.deliberation_started_at = Some(Utc::now());
+        self.deliberation_started_at = Some(clock.now());
     }
 
     pub fn mark_voting(&mut self) {
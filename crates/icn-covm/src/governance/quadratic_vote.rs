@@ -0,0 +1,110 @@
+use crate::governance::traits::GovernanceOpHandler;
+use crate::storage::traits::Storage;
+use crate::typed::TypedValue;
+use crate::vm::execution::ExecutorOps;
+use crate::vm::stack::StackOps;
+use crate::vm::types::Op;
+use crate::vm::{VMError, VM};
+use std::fmt::Debug;
+use std::marker::{Send, Sync};
+
+/// Handler for QuadraticVote operations
+///
+/// Each ballot spends voice credits on the candidates, and casting `n` votes
+/// for a candidate costs `n²` credits, so a ballot's credits count as their
+/// square root. Spreading credits over several candidates therefore counts
+/// for more than spending them all on one, which keeps a few members with
+/// strong preferences from outweighing a broad majority.
+pub struct QuadraticVoteHandler;
+
+/// Votes each candidate received, where a ballot's credits for a candidate
+/// count as their square root
+fn quadratic_tally(ballots: &[Vec<f64>], candidates: usize) -> Vec<f64> {
+    let mut votes = vec![0.0; candidates];
+    for ballot in ballots {
+        for (candidate, credits) in ballot.iter().enumerate().take(candidates) {
+            votes[candidate] += credits.sqrt();
+        }
+    }
+    votes
+}
+
+impl GovernanceOpHandler for QuadraticVoteHandler {
+    fn handle<S>(vm: &mut VM<S>, op: &Op) -> Result<(), VMError>
+    where
+        S: Storage + Send + Sync + Clone + Debug + 'static,
+    {
+        let Op::QuadraticVote {
+            candidates,
+            ballots,
+        } = op
+        else {
+            return Err(VMError::UndefinedOperation(
+                "Expected QuadraticVote operation".into(),
+            ));
+        };
+
+        if *candidates < 2 {
+            return Err(VMError::GovernanceError(
+                "QuadraticVote requires at least 2 candidates".into(),
+            ));
+        }
+        if *ballots < 1 {
+            return Err(VMError::GovernanceError(
+                "QuadraticVote requires at least 1 ballot".into(),
+            ));
+        }
+
+        // Each ballot is the credits for candidate 0 first, then 1, and so on
+        let mut all_ballots = Vec::with_capacity(*ballots);
+        for ballot_index in 0..*ballots {
+            let mut ballot = Vec::with_capacity(*candidates);
+            for candidate in 0..*candidates {
+                let credits = vm.stack.pop_number("QuadraticVote")?;
+                if !credits.is_finite() || credits < 0.0 {
+                    return Err(VMError::GovernanceError(format!(
+                        "QuadraticVote ballot {} spends {} credits on candidate {}",
+                        ballot_index + 1,
+                        credits,
+                        candidate
+                    )));
+                }
+                ballot.push(credits);
+            }
+            all_ballots.push(ballot);
+        }
+
+        vm.executor.emit_event(
+            "governance",
+            &format!(
+                "Running quadratic vote with {} candidates and {} ballots",
+                candidates, ballots
+            ),
+        );
+
+        let votes = quadratic_tally(&all_ballots, *candidates);
+        for (candidate, count) in votes.iter().enumerate() {
+            vm.executor.emit_event(
+                "governance",
+                &format!("Candidate {} received {:.3} votes", candidate, count),
+            );
+        }
+
+        // Ties go to the lowest-numbered candidate
+        let winner = (0..votes.len()).fold(0, |best, candidate| {
+            if votes[candidate] > votes[best] {
+                candidate
+            } else {
+                best
+            }
+        });
+
+        vm.executor.emit_event(
+            "governance",
+            &format!("Winner of quadratic vote: candidate {}", winner),
+        );
+
+        vm.stack.push(TypedValue::Number(winner as f64));
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
+use crate::governance::liquid_delegate::DelegationGraph;
 use crate::governance::traits::GovernanceOpHandler;
 use crate::storage::traits::Storage;
+use crate::typed::TypedValue;
 use crate::vm::execution::ExecutorOps;
 use crate::vm::stack::StackOps;
 use crate::vm::types::Op;
@@ -18,6 +20,7 @@ impl GovernanceOpHandler for RankedVoteHandler {
         if let Op::RankedVote {
             candidates,
             ballots,
+            delegated,
         } = op
         {
             // Validate parameters
@@ -33,18 +36,44 @@ impl GovernanceOpHandler for RankedVoteHandler {
                 ));
             }
 
-            // Collect all ballots from the stack
+            // Collect all ballots from the stack, each after its voter when
+            // delegated
             let mut all_ballots = Vec::new();
+            let mut voters = Vec::new();
 
             for _ in 0..*ballots {
+                if *delegated {
+                    voters.push(vm.stack.pop_string("RankedVote")?);
+                }
                 let mut ballot = Vec::new();
                 for _ in 0..*candidates {
-                    let choice = vm.pop_one("RankedVote")?;
+                    let choice = vm.stack.pop_number("RankedVote")?;
                     ballot.push(choice);
                 }
                 all_ballots.push(ballot);
             }
 
+            // Each ballot counts once, or with its voter's delegated weight
+            let weights: Vec<f64> = if *delegated {
+                let graph = DelegationGraph::load(vm)?;
+                let by_voter = graph.weights(&voters);
+                for voter in &voters {
+                    if by_voter[voter] > 1.0 {
+                        vm.executor.emit_event(
+                            "governance",
+                            &format!(
+                                "Ballot of {} carries {} delegated votes",
+                                voter,
+                                by_voter[voter] - 1.0
+                            ),
+                        );
+                    }
+                }
+                voters.iter().map(|voter| by_voter[voter]).collect()
+            } else {
+                vec![1.0; *ballots]
+            };
+
             // Perform ranked choice voting calculation
             vm.executor.emit_event(
                 "governance",
@@ -60,24 +89,24 @@ impl GovernanceOpHandler for RankedVoteHandler {
 
             while remaining_candidates > 1 {
                 // Count first-choice votes for each candidate
-                let mut votes = vec![0; *candidates];
+                let mut votes = vec![0.0; *candidates];
 
-                for ballot in &all_ballots {
-                    for (i, &choice) in ballot.iter().enumerate() {
+                for (ballot, weight) in all_ballots.iter().zip(&weights) {
+                    for &choice in ballot {
                         let candidate = choice as usize;
                         if candidate < *candidates && !eliminated[candidate] {
-                            votes[candidate] += 1;
+                            votes[candidate] += weight;
                             break;
                         }
                     }
                 }
 
                 // Find candidate with fewest votes
-                let mut min_votes = *ballots + 1;
+                let mut min_votes = f64::INFINITY;
                 let mut min_candidate = 0;
 
                 for (candidate, &vote_count) in votes.iter().enumerate() {
-                    if !eliminated[candidate] && vote_count < min_votes && vote_count > 0 {
+                    if !eliminated[candidate] && vote_count < min_votes && vote_count > 0.0 {
                         min_votes = vote_count;
                         min_candidate = candidate;
                     }
@@ -105,7 +134,7 @@ impl GovernanceOpHandler for RankedVoteHandler {
             );
 
             // Push the winner to the stack
-            vm.stack.push(winner as f64);
+            vm.stack.push(TypedValue::Number(winner as f64));
             Ok(())
         } else {
             Err(VMError::UndefinedOperation(
@@ -0,0 +1,624 @@
+//! Ledger replay for rebuilding governance state
+//!
+//! The DAG ledger records every proposal creation, vote, execution, and token
+//! mint. This module folds those events back into proposal, vote, and balance
+//! state so that storage can be rebuilt after data loss, or compared against
+//! the live backend to detect drift between the two.
+//!
+//! The ledger does not carry creator identities or proposal logic, so replay
+//! restores proposal metadata records and state, and votes only. Proposals
+//! restored from scratch are attributed to the `dag-replay` creator.
+//!
+//! Balances are never written back: the ledger records mints but not
+//! transfers or burns, so the minted totals it yields are not balances.
+//! Replay reports where the live balances differ from them instead.
+
+use crate::governance::proposal::{Proposal, ProposalStatus};
+use crate::governance::proposal_lifecycle::{ProposalLifecycle, ProposalState};
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{StorageBackend, StorageExtensions};
+use chrono::{TimeZone, Utc};
+use icn_ledger::{DagLedger, NodeData};
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Proposal state reconstructed from ledger events
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayedProposal {
+    pub namespace: String,
+    pub title: String,
+    pub created_at: u64,
+    /// Latest vote per voter ("yes", "no", or "abstain")
+    pub votes: BTreeMap<String, String>,
+    /// Execution outcome, if an execution event was recorded
+    pub executed: Option<bool>,
+}
+
+impl ReplayedProposal {
+    /// The lifecycle state implied by the replayed events
+    pub fn state(&self) -> ProposalState {
+        match self.executed {
+            Some(_) => ProposalState::Executed,
+            None if !self.votes.is_empty() => ProposalState::Voting,
+            None => ProposalState::Draft,
+        }
+    }
+}
+
+/// Complete state reconstructed from a ledger
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayedState {
+    /// Proposals keyed by proposal ID
+    pub proposals: BTreeMap<String, ReplayedProposal>,
+    /// Minted balances keyed by (namespace, resource, account)
+    pub balances: BTreeMap<(String, String, String), Decimal>,
+    /// Votes whose recorded value is not a known choice, as (proposal ID,
+    /// voter, value); they are left out of `proposals`
+    pub invalid_votes: Vec<(String, String, f64)>,
+}
+
+/// Summary of the writes performed by `replay`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    pub proposals_restored: usize,
+    pub votes_restored: usize,
+    /// Votes skipped because the ledger records no valid choice for them
+    pub invalid_votes: usize,
+    /// Live balances that differ from the minted totals, left as they are
+    pub balance_drift: Vec<StateDrift>,
+}
+
+/// A difference between replayed state and live storage
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateDrift {
+    /// A proposal recorded in the ledger has no record in storage
+    MissingProposal { proposal_id: String },
+    /// The stored lifecycle state disagrees with the ledger
+    ProposalStateMismatch {
+        proposal_id: String,
+        expected: ProposalState,
+        actual: ProposalState,
+    },
+    /// A vote recorded in the ledger has no record in storage
+    MissingVote { proposal_id: String, voter: String },
+    /// The stored vote disagrees with the ledger
+    VoteMismatch {
+        proposal_id: String,
+        voter: String,
+        expected: String,
+        actual: String,
+    },
+    /// A minted balance disagrees with the ledger
+    BalanceMismatch {
+        namespace: String,
+        resource: String,
+        account: String,
+        expected: Decimal,
+        actual: Decimal,
+    },
+}
+
+fn proposal_key(proposal_id: &str) -> String {
+    format!("governance_proposals/{}", proposal_id)
+}
+
+fn lifecycle_key(proposal_id: &str) -> String {
+    format!("{}/lifecycle", proposal_key(proposal_id))
+}
+
+fn vote_key(proposal_id: &str, voter: &str) -> String {
+    format!("{}/votes/{}", proposal_key(proposal_id), voter)
+}
+
+fn balance_key(resource: &str, account: &str) -> String {
+    format!("resources/{}/accounts/{}", resource, account)
+}
+
+/// Convert the numeric vote stored in a `VoteCast` node back to its choice
+///
+/// Returns `None` for any other value, such as the -1.0 recorded for a vote
+/// the CLI did not recognise.
+pub(crate) fn vote_choice(vote: f64) -> Option<&'static str> {
+    if vote == 1.0 {
+        Some("yes")
+    } else if vote == 0.5 {
+        Some("abstain")
+    } else if vote == 0.0 {
+        Some("no")
+    } else {
+        None
+    }
+}
+
+/// Whether a stored lifecycle state is at or past the state the ledger implies
+///
+/// Stored records may legitimately be further along than the ledger (e.g.
+/// rejected or expired), so only regressions and missing executions count.
+fn reached(expected: &ProposalState, actual: &ProposalState) -> bool {
+    match expected {
+        ProposalState::Executed => *actual == ProposalState::Executed,
+        ProposalState::Voting => *actual != ProposalState::Draft,
+        _ => true,
+    }
+}
+
+/// Fold the ledger's events into governance and token state
+///
+/// Nodes are visited in ledger order, which always places parents before
+/// their children, so later votes by the same voter override earlier ones.
+/// Sealed nodes from namespaces whose key the ledger does not hold are skipped.
+pub fn rebuild_state(ledger: &DagLedger) -> ReplayedState {
+    let mut state = ReplayedState::default();
+
+    for node in ledger.readable_nodes() {
+        match &node.data {
+            NodeData::ProposalCreated { proposal_id, title } => {
+                state
+                    .proposals
+                    .entry(proposal_id.clone())
+                    .or_insert_with(|| ReplayedProposal {
+                        namespace: node.namespace.clone(),
+                        title: title.clone(),
+                        created_at: node.timestamp,
+                        votes: BTreeMap::new(),
+                        executed: None,
+                    });
+            }
+            NodeData::VoteCast {
+                proposal_id,
+                voter,
+                vote,
+            } => match (state.proposals.get_mut(proposal_id), vote_choice(*vote)) {
+                (Some(proposal), Some(choice)) => {
+                    proposal.votes.insert(voter.clone(), choice.to_string());
+                }
+                (Some(_), None) => {
+                    state
+                        .invalid_votes
+                        .push((proposal_id.clone(), voter.clone(), *vote));
+                }
+                (None, _) => {}
+            },
+            NodeData::ProposalExecuted {
+                proposal_id,
+                success,
+            } => {
+                if let Some(proposal) = state.proposals.get_mut(proposal_id) {
+                    proposal.executed = Some(*success);
+                }
+            }
+            NodeData::TokenMinted {
+                resource,
+                recipient,
+                amount,
+            } => {
+                *state
+                    .balances
+                    .entry((node.namespace.clone(), resource.clone(), recipient.clone()))
+                    .or_default() += Decimal::try_from(amount.max(0.0)).unwrap_or_default();
+            }
+            NodeData::Genesis { .. }
+            | NodeData::EpochMarker { .. }
+            | NodeData::Encrypted { .. }
+            | NodeData::BountyCreated { .. }
+            | NodeData::BountyClaimed { .. }
+            | NodeData::BountyVerified { .. }
+            | NodeData::BountyPaid { .. }
+            | NodeData::BountyCancelled { .. }
+            | NodeData::Distributed { .. }
+            | NodeData::ExecutionAck { .. } => {}
+        }
+    }
+
+    state
+}
+
+/// Rebuild storage from the ledger's events
+///
+/// Existing lifecycle records are only moved forward, to the state the ledger
+/// implies, when they have not reached it yet; proposals missing from storage
+/// are restored as metadata records, and votes missing from storage are
+/// restored with the replayed choice. Stored votes are left as they are.
+/// Balances are only compared, and any drift is returned in the report.
+pub fn replay<S>(
+    ledger: &DagLedger,
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+) -> StorageResult<ReplayReport>
+where
+    S: StorageBackend,
+{
+    let state = rebuild_state(ledger);
+    let mut report = ReplayReport {
+        invalid_votes: state.invalid_votes.len(),
+        ..ReplayReport::default()
+    };
+
+    storage.begin_transaction()?;
+    let result = apply_state(&state, storage, auth, &mut report);
+    match result {
+        Ok(()) => storage.commit_transaction()?,
+        Err(e) => {
+            storage.rollback_transaction()?;
+            return Err(e);
+        }
+    }
+
+    Ok(report)
+}
+
+fn apply_state<S>(
+    state: &ReplayedState,
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    report: &mut ReplayReport,
+) -> StorageResult<()>
+where
+    S: StorageBackend,
+{
+    for (proposal_id, replayed) in &state.proposals {
+        let namespace = replayed.namespace.as_str();
+        let lifecycle_key = lifecycle_key(proposal_id);
+
+        if storage.contains(auth, namespace, &lifecycle_key)? {
+            let mut lifecycle: ProposalLifecycle =
+                storage.get_json(auth, namespace, &lifecycle_key)?;
+            let state = replayed.state();
+            if !reached(&state, &lifecycle.state) {
+                lifecycle.state = state.clone();
+                lifecycle.history.push((Utc::now(), state));
+                storage.set_json(auth, namespace, &lifecycle_key, &lifecycle)?;
+                report.proposals_restored += 1;
+            }
+        } else if !storage.contains(auth, namespace, &proposal_key(proposal_id))? {
+            let mut proposal = Proposal::new(
+                proposal_id.clone(),
+                "dag-replay".to_string(),
+                None,
+                None,
+                None,
+                vec![],
+            );
+            if let Some(created) = Utc.timestamp_opt(replayed.created_at as i64, 0).single() {
+                proposal.created_at = created;
+            }
+            match replayed.executed {
+                Some(success) => proposal.mark_executed(format!("success: {}", success)),
+                None if !replayed.votes.is_empty() => proposal.mark_voting(),
+                None => {}
+            }
+            storage.set_json(auth, namespace, &proposal_key(proposal_id), &proposal)?;
+            report.proposals_restored += 1;
+        }
+
+        for (voter, choice) in &replayed.votes {
+            let key = vote_key(proposal_id, voter);
+            if storage.contains(auth, namespace, &key)? {
+                continue;
+            }
+            let vote_data = json!({
+                "voter": voter,
+                "vote": choice,
+                "timestamp": Utc::now().to_rfc3339(),
+                "delegated_by": null,
+                "replayed": true,
+            });
+            storage.set_json(auth, namespace, &key, &vote_data)?;
+            report.votes_restored += 1;
+        }
+    }
+
+    report.balance_drift = balance_drift(state, storage, auth)?;
+
+    Ok(())
+}
+
+/// Compare the minted totals against the live balances
+fn balance_drift<S>(
+    state: &ReplayedState,
+    storage: &S,
+    auth: Option<&AuthContext>,
+) -> StorageResult<Vec<StateDrift>>
+where
+    S: StorageBackend,
+{
+    let mut drift = Vec::new();
+    for ((namespace, resource, account), expected) in &state.balances {
+        let actual = match storage.get(auth, namespace, &balance_key(resource, account)) {
+            Ok(bytes) => std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.parse::<Decimal>().ok())
+                .unwrap_or_default(),
+            Err(StorageError::NotFound { .. }) => Decimal::ZERO,
+            Err(e) => return Err(e),
+        };
+        if actual != *expected {
+            drift.push(StateDrift::BalanceMismatch {
+                namespace: namespace.clone(),
+                resource: resource.clone(),
+                account: account.clone(),
+                expected: *expected,
+                actual,
+            });
+        }
+    }
+    Ok(drift)
+}
+
+/// Compare replayed ledger state against live storage without writing anything
+///
+/// Balances are only reported when the live value differs from the total
+/// minted in the ledger; transfers and burns are not recorded as DAG events,
+/// so a drift report for balances is a prompt to investigate, not proof of
+/// tampering.
+pub fn check<S>(
+    ledger: &DagLedger,
+    storage: &S,
+    auth: Option<&AuthContext>,
+) -> StorageResult<Vec<StateDrift>>
+where
+    S: StorageBackend,
+{
+    let state = rebuild_state(ledger);
+    let mut drift = Vec::new();
+
+    for (proposal_id, replayed) in &state.proposals {
+        let namespace = replayed.namespace.as_str();
+        let lifecycle_key = lifecycle_key(proposal_id);
+
+        if storage.contains(auth, namespace, &lifecycle_key)? {
+            let lifecycle: ProposalLifecycle = storage.get_json(auth, namespace, &lifecycle_key)?;
+            let expected = replayed.state();
+            if !reached(&expected, &lifecycle.state) {
+                drift.push(StateDrift::ProposalStateMismatch {
+                    proposal_id: proposal_id.clone(),
+                    expected,
+                    actual: lifecycle.state,
+                });
+            }
+        } else if storage.contains(auth, namespace, &proposal_key(proposal_id))? {
+            let proposal: Proposal =
+                storage.get_json(auth, namespace, &proposal_key(proposal_id))?;
+            if replayed.executed.is_some() && !matches!(proposal.status, ProposalStatus::Executed) {
+                drift.push(StateDrift::ProposalStateMismatch {
+                    proposal_id: proposal_id.clone(),
+                    expected: ProposalState::Executed,
+                    actual: ProposalState::Voting,
+                });
+            }
+        } else {
+            drift.push(StateDrift::MissingProposal {
+                proposal_id: proposal_id.clone(),
+            });
+            continue;
+        }
+
+        for (voter, expected) in &replayed.votes {
+            let key = vote_key(proposal_id, voter);
+            match storage.get_json::<serde_json::Value>(auth, namespace, &key) {
+                Ok(vote_data) => {
+                    let actual = vote_data
+                        .get("vote")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_lowercase();
+                    if &actual != expected {
+                        drift.push(StateDrift::VoteMismatch {
+                            proposal_id: proposal_id.clone(),
+                            voter: voter.clone(),
+                            expected: expected.clone(),
+                            actual,
+                        });
+                    }
+                }
+                Err(StorageError::NotFound { .. }) => drift.push(StateDrift::MissingVote {
+                    proposal_id: proposal_id.clone(),
+                    voter: voter.clone(),
+                }),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    drift.extend(balance_drift(&state, storage, auth)?);
+
+    Ok(drift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use icn_ledger::DagNode;
+
+    fn node(data: NodeData) -> DagNode {
+        DagNode::with_namespace(vec![], data, 1_700_000_000, "coop".to_string())
+    }
+
+    #[test]
+    fn test_rebuild_state_folds_events() {
+        let mut ledger = DagLedger::new();
+        ledger
+            .append_on_tips(node(NodeData::ProposalCreated {
+                proposal_id: "p1".to_string(),
+                title: "Buy a van".to_string(),
+            }))
+            .unwrap();
+        for (voter, vote) in [("alice", 1.0), ("bob", 0.0), ("alice", 0.5)] {
+            ledger
+                .append_on_tips(node(NodeData::VoteCast {
+                    proposal_id: "p1".to_string(),
+                    voter: voter.to_string(),
+                    vote,
+                }))
+                .unwrap();
+        }
+        ledger
+            .append_on_tips(node(NodeData::TokenMinted {
+                resource: "hours".to_string(),
+                recipient: "alice".to_string(),
+                amount: 10.0,
+            }))
+            .unwrap();
+
+        let state = rebuild_state(&ledger);
+        let proposal = &state.proposals["p1"];
+        assert_eq!(proposal.title, "Buy a van");
+        assert_eq!(proposal.votes["alice"], "abstain");
+        assert_eq!(proposal.votes["bob"], "no");
+        assert_eq!(proposal.state(), ProposalState::Voting);
+        assert_eq!(
+            state.balances[&("coop".to_string(), "hours".to_string(), "alice".to_string())],
+            Decimal::from(10)
+        );
+    }
+
+    #[test]
+    fn test_execution_marks_proposal_executed() {
+        let mut ledger = DagLedger::new();
+        ledger
+            .append_on_tips(node(NodeData::ProposalCreated {
+                proposal_id: "p1".to_string(),
+                title: "t".to_string(),
+            }))
+            .unwrap();
+        ledger
+            .append_on_tips(node(NodeData::ProposalExecuted {
+                proposal_id: "p1".to_string(),
+                success: true,
+            }))
+            .unwrap();
+
+        let state = rebuild_state(&ledger);
+        assert_eq!(state.proposals["p1"].state(), ProposalState::Executed);
+    }
+
+    #[test]
+    fn test_replay_only_moves_state_forward_and_keeps_stored_votes() {
+        let mut auth = AuthContext::new("operator");
+        auth.add_role("global", "admin");
+        auth.add_role("coop", "admin");
+        let mut storage = InMemoryStorage::new();
+        storage
+            .create_account(Some(&auth), "operator", 1 << 20)
+            .unwrap();
+        storage
+            .create_namespace(Some(&auth), "coop", 1 << 20, None)
+            .unwrap();
+        let creator = Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        for (id, state) in [
+            ("rejected", ProposalState::Rejected),
+            ("quiet", ProposalState::Voting),
+        ] {
+            let mut lifecycle = ProposalLifecycle::new(
+                id.to_string(),
+                creator.clone(),
+                id.to_string(),
+                1,
+                1,
+                None,
+                None,
+            );
+            lifecycle.state = state;
+            storage
+                .set_json(Some(&auth), "coop", &lifecycle_key(id), &lifecycle)
+                .unwrap();
+        }
+        let bob_vote = json!({
+            "voter": "bob",
+            "vote": "no",
+            "timestamp": "2023-11-14T22:13:20+00:00",
+            "delegated_by": "carol",
+        });
+        storage
+            .set_json(Some(&auth), "coop", &vote_key("rejected", "bob"), &bob_vote)
+            .unwrap();
+
+        let mut ledger = DagLedger::new();
+        for id in ["rejected", "quiet"] {
+            ledger
+                .append_on_tips(node(NodeData::ProposalCreated {
+                    proposal_id: id.to_string(),
+                    title: id.to_string(),
+                }))
+                .unwrap();
+        }
+        for (voter, vote) in [("alice", 1.0), ("bob", 0.0), ("dave", -1.0)] {
+            ledger
+                .append_on_tips(node(NodeData::VoteCast {
+                    proposal_id: "rejected".to_string(),
+                    voter: voter.to_string(),
+                    vote,
+                }))
+                .unwrap();
+        }
+
+        let report = replay(&ledger, &mut storage, Some(&auth)).unwrap();
+        assert_eq!(report.proposals_restored, 0);
+        assert_eq!(report.votes_restored, 1);
+        assert_eq!(report.invalid_votes, 1);
+        for (id, state) in [
+            ("rejected", ProposalState::Rejected),
+            ("quiet", ProposalState::Voting),
+        ] {
+            let lifecycle: ProposalLifecycle = storage
+                .get_json(Some(&auth), "coop", &lifecycle_key(id))
+                .unwrap();
+            assert_eq!(lifecycle.state, state);
+        }
+        let stored: serde_json::Value = storage
+            .get_json(Some(&auth), "coop", &vote_key("rejected", "bob"))
+            .unwrap();
+        assert_eq!(stored, bob_vote);
+        assert!(storage
+            .contains(Some(&auth), "coop", &vote_key("rejected", "alice"))
+            .unwrap());
+        assert!(!storage
+            .contains(Some(&auth), "coop", &vote_key("rejected", "dave"))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_replay_reports_balance_drift_without_overwriting() {
+        let mut auth = AuthContext::new("operator");
+        auth.add_role("global", "admin");
+        auth.add_role("coop", "admin");
+        let mut storage = InMemoryStorage::new();
+        storage
+            .create_account(Some(&auth), "operator", 1 << 20)
+            .unwrap();
+        storage
+            .create_namespace(Some(&auth), "coop", 1 << 20, None)
+            .unwrap();
+        // Alice was minted 10 hours and has since transferred 4 away
+        let key = balance_key("hours", "alice");
+        storage
+            .set(Some(&auth), "coop", &key, b"6".to_vec())
+            .unwrap();
+
+        let mut ledger = DagLedger::new();
+        ledger
+            .append_on_tips(node(NodeData::TokenMinted {
+                resource: "hours".to_string(),
+                recipient: "alice".to_string(),
+                amount: 10.0,
+            }))
+            .unwrap();
+
+        let report = replay(&ledger, &mut storage, Some(&auth)).unwrap();
+        assert_eq!(storage.get(Some(&auth), "coop", &key).unwrap(), b"6");
+        assert_eq!(
+            report.balance_drift,
+            vec![StateDrift::BalanceMismatch {
+                namespace: "coop".to_string(),
+                resource: "hours".to_string(),
+                account: "alice".to_string(),
+                expected: Decimal::from(10),
+                actual: Decimal::from(6),
+            }]
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Quorum and threshold per role class
+//!
+//! A template can require more than an overall majority: each role class
+//! listed in its `role_classes` must reach its own quorum and threshold, e.g.
+//! 50% of workers AND 30% of consumer-members. Classes are counted from the
+//! member registry (see [`membership`](super::membership)): the electorate of
+//! a class is every active member holding its role, and a member holding
+//! several roles counts in each of their classes.
+//!
+//! As in the overall tally, participation counts yes and no votes, and the
+//! threshold is the share of yes among them. A class with no active members
+//! only passes if it requires nothing.
+
+use crate::governance::membership::{self, MemberStatus};
+use crate::governance::proposal_lifecycle::VoteChoice;
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::StorageResult;
+use crate::storage::traits::{StorageBackend, StorageExtensions};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Quorum and threshold one role class must reach
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RoleClassRequirement {
+    /// Role that defines the class, as granted in the cooperative's namespace
+    pub role: String,
+
+    /// Fraction of the class that must vote yes or no
+    pub quorum: f64,
+
+    /// Fraction of the class's yes and no votes that must be yes
+    pub threshold: f64,
+}
+
+/// How one role class voted on a proposal
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ClassTally {
+    pub role: String,
+    /// Active members holding the role
+    pub eligible: u64,
+    pub yes: u64,
+    pub no: u64,
+    pub abstain: u64,
+    pub quorum: f64,
+    pub threshold: f64,
+}
+
+impl ClassTally {
+    /// Fraction of the class that voted yes or no
+    pub fn participation(&self) -> f64 {
+        if self.eligible == 0 {
+            return 0.0;
+        }
+        (self.yes + self.no) as f64 / self.eligible as f64
+    }
+
+    /// Fraction of the class's yes and no votes that were yes
+    pub fn approval(&self) -> f64 {
+        if self.yes + self.no == 0 {
+            return 0.0;
+        }
+        self.yes as f64 / (self.yes + self.no) as f64
+    }
+
+    pub fn quorum_met(&self) -> bool {
+        self.participation() >= self.quorum
+    }
+
+    pub fn threshold_met(&self) -> bool {
+        self.approval() >= self.threshold
+    }
+
+    pub fn passed(&self) -> bool {
+        self.quorum_met() && self.threshold_met()
+    }
+}
+
+/// Tally `ballots`, keyed by voter DID, for each class in `requirements`
+/// against the member registry of `namespace`
+///
+/// Votes from voters outside a class, including retired members, do not
+/// count towards it.
+pub fn tally_role_classes<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    requirements: &[RoleClassRequirement],
+    ballots: &HashMap<String, VoteChoice>,
+) -> StorageResult<Vec<ClassTally>>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    let members: Vec<_> = membership::list_members(storage, auth, namespace)?
+        .into_iter()
+        .filter(|member| member.status == MemberStatus::Active)
+        .collect();
+
+    Ok(requirements
+        .iter()
+        .map(|requirement| {
+            let mut tally = ClassTally {
+                role: requirement.role.clone(),
+                eligible: 0,
+                yes: 0,
+                no: 0,
+                abstain: 0,
+                quorum: requirement.quorum,
+                threshold: requirement.threshold,
+            };
+            for member in members
+                .iter()
+                .filter(|member| member.roles.contains(&requirement.role))
+            {
+                tally.eligible += 1;
+                match ballots.get(&member.did) {
+                    Some(VoteChoice::Yes) => tally.yes += 1,
+                    Some(VoteChoice::No) => tally.no += 1,
+                    Some(VoteChoice::Abstain) => tally.abstain += 1,
+                    None => {}
+                }
+            }
+            tally
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_each_class_is_tallied_separately() {
+        let mut storage = InMemoryStorage::new();
+        let mut auth = AuthContext::new("admin");
+        auth.add_role("global", "admin");
+
+        // Three workers and three consumers; carol is both
+        let roster: [(&str, &[&str]); 5] = [
+            ("alice", &["worker"]),
+            ("bob", &["worker"]),
+            ("carol", &["worker", "consumer"]),
+            ("dave", &["consumer"]),
+            ("erin", &["consumer"]),
+        ];
+        for (name, roles) in roster {
+            let record = membership::MemberRecord {
+                did: format!("did:example:{}", name),
+                username: name.to_string(),
+                roles: roles.iter().map(|r| r.to_string()).collect(),
+                status: MemberStatus::Active,
+                joined_at: 0,
+                admitted_by: "charter".to_string(),
+                retired_at: None,
+                retired_by: None,
+            };
+            storage
+                .set_json(
+                    Some(&auth),
+                    "coop",
+                    &membership::member_key(&record.did),
+                    &record,
+                )
+                .unwrap();
+        }
+
+        let ballots = HashMap::from([
+            ("did:example:alice".to_string(), VoteChoice::Yes),
+            ("did:example:carol".to_string(), VoteChoice::Yes),
+            ("did:example:dave".to_string(), VoteChoice::No),
+            ("did:example:outsider".to_string(), VoteChoice::Yes),
+        ]);
+        let requirements = [
+            RoleClassRequirement {
+                role: "worker".to_string(),
+                quorum: 0.5,
+                threshold: 0.5,
+            },
+            RoleClassRequirement {
+                role: "consumer".to_string(),
+                quorum: 0.3,
+                threshold: 0.6,
+            },
+        ];
+        let tallies =
+            tally_role_classes(&storage, Some(&auth), "coop", &requirements, &ballots).unwrap();
+
+        let workers = &tallies[0];
+        assert_eq!((workers.eligible, workers.yes, workers.no), (3, 2, 0));
+        assert!(workers.passed());
+
+        // Two of three consumers voted, but only one of them in favour
+        let consumers = &tallies[1];
+        assert_eq!((consumers.eligible, consumers.yes, consumers.no), (3, 1, 1));
+        assert!(consumers.quorum_met());
+        assert!(!consumers.threshold_met());
+    }
+}
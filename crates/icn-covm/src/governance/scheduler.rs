@@ -0,0 +1,278 @@
+//! Moving proposals along their timeline without anyone running a command
+//!
+//! Proposals record when deliberation ends, when voting closes and how long
+//! a passed proposal waits before its logic runs. The job started here checks
+//! those times every minute against the VM's clock:
+//!
+//! - a proposal open for feedback whose deliberation period has ended moves
+//!   to Voting;
+//! - once voting closes, a proposal that met its quorum and threshold is
+//!   approved, and any other moves to Expired;
+//! - an approved proposal is executed once its execution delay has passed,
+//!   as `proposal execute` would.
+//!
+//! Voting closes at the lifecycle's `expires_at`, or failing that the
+//! proposal's own. Each pass is `run_due`, which tests call directly.
+
+use crate::governance::proposal_lifecycle::{ProposalLifecycle, ProposalState};
+use crate::storage::auth::AuthContext;
+use crate::storage::traits::Storage;
+use crate::vm::VM;
+use chrono::{DateTime, Duration, Utc};
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// How often proposals are checked for due transitions
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// A transition the scheduler made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduledAction {
+    /// Deliberation ended and voting opened
+    VotingOpened(String),
+    /// Voting closed with the proposal passed; its execution delay started
+    Approved(String),
+    /// Voting closed without the proposal passing
+    Expired(String),
+    /// The execution delay passed and the proposal's logic ran
+    Executed(String),
+}
+
+/// The proposals of a namespace, as the scheduler reads and changes them
+///
+/// The proposal commands implement this for `VM`, so scheduled transitions
+/// go through the same code as `proposal transition` and `proposal execute`.
+pub trait ScheduledProposals {
+    /// Lifecycles of every proposal in the namespace
+    fn lifecycles(&self) -> Result<Vec<ProposalLifecycle>, Box<dyn Error>>;
+
+    /// When voting on a proposal closes, if it has a deadline at all
+    fn voting_closes(
+        &self,
+        lifecycle: &ProposalLifecycle,
+    ) -> Result<Option<DateTime<Utc>>, Box<dyn Error>>;
+
+    /// Whether the votes cast meet the proposal's quorum and threshold
+    fn passed(&self, lifecycle: &ProposalLifecycle) -> Result<bool, Box<dyn Error>>;
+
+    /// Move a proposal to `state`
+    fn transition(&mut self, id: &str, state: ProposalState) -> Result<(), Box<dyn Error>>;
+
+    /// Store a lifecycle changed by the scheduler
+    fn save_lifecycle(&mut self, lifecycle: &ProposalLifecycle) -> Result<(), Box<dyn Error>>;
+
+    /// Run a proposal's logic and mark it executed
+    fn execute(&mut self, id: &str) -> Result<(), Box<dyn Error>>;
+}
+
+/// Start the job that moves the VM's proposals along
+pub fn start<S>(vm: Arc<Mutex<VM<S>>>)
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+    VM<S>: ScheduledProposals,
+{
+    let mut auth = AuthContext::new("system");
+    auth.add_role("global", "admin");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut vm = vm.clone().lock_owned().await;
+            let auth = auth.clone();
+            // Proposal logic may run, so keep it off the async workers
+            let pass = tokio::task::spawn_blocking(move || {
+                vm.with_auth_context(auth, |vm| {
+                    let now = vm.clock().now();
+                    run_due(vm, now).map_err(|e| e.to_string())
+                })
+            });
+            match pass.await {
+                Ok(Ok(actions)) => {
+                    for action in actions {
+                        tracing::info!(?action, "Scheduled proposal transition");
+                    }
+                }
+                Ok(Err(e)) => tracing::warn!("Failed to check proposal schedules: {}", e),
+                Err(e) => tracing::warn!("Proposal scheduler pass panicked: {}", e),
+            }
+        }
+    });
+}
+
+/// Make every transition that is due at `now`
+///
+/// A proposal whose transition fails is logged and left for the next pass,
+/// so one broken proposal does not hold up the others.
+pub fn run_due<P: ScheduledProposals>(
+    proposals: &mut P,
+    now: DateTime<Utc>,
+) -> Result<Vec<ScheduledAction>, Box<dyn Error>> {
+    let mut actions = Vec::new();
+    for lifecycle in proposals.lifecycles()? {
+        let id = lifecycle.id.clone();
+        if let Err(e) = advance(proposals, lifecycle, now, &mut actions) {
+            tracing::warn!(proposal_id = %id, error = %e, "Scheduled transition failed");
+        }
+    }
+    Ok(actions)
+}
+
+/// Make the transitions due for one proposal, in order
+fn advance<P: ScheduledProposals>(
+    proposals: &mut P,
+    mut lifecycle: ProposalLifecycle,
+    now: DateTime<Utc>,
+    actions: &mut Vec<ScheduledAction>,
+) -> Result<(), Box<dyn Error>> {
+    let id = lifecycle.id.clone();
+
+    if lifecycle.state == ProposalState::OpenForFeedback {
+        match lifecycle.next_deadline() {
+            Some((_, ends)) if ends <= now => {
+                proposals.transition(&id, ProposalState::Voting)?;
+                actions.push(ScheduledAction::VotingOpened(id.clone()));
+                lifecycle.state = ProposalState::Voting;
+            }
+            _ => return Ok(()),
+        }
+    }
+    if lifecycle.state != ProposalState::Voting {
+        return Ok(());
+    }
+
+    if lifecycle.approved_at.is_none() {
+        match proposals.voting_closes(&lifecycle)? {
+            Some(closes) if closes <= now => {}
+            _ => return Ok(()),
+        }
+        if !proposals.passed(&lifecycle)? {
+            proposals.transition(&id, ProposalState::Expired)?;
+            actions.push(ScheduledAction::Expired(id));
+            return Ok(());
+        }
+        lifecycle.approved_at = Some(now);
+        proposals.save_lifecycle(&lifecycle)?;
+        actions.push(ScheduledAction::Approved(id.clone()));
+    }
+
+    let delay = Duration::seconds(lifecycle.execution_delay.unwrap_or(0) as i64);
+    if lifecycle
+        .approved_at
+        .is_some_and(|approved| approved + delay <= now)
+    {
+        proposals.execute(&id)?;
+        actions.push(ScheduledAction::Executed(id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, MockClock};
+    use crate::identity::Identity;
+    use chrono::TimeZone;
+    use std::collections::{BTreeMap, HashSet};
+
+    /// Proposals kept in memory, with the outcome of each vote fixed
+    #[derive(Default)]
+    struct Board {
+        lifecycles: BTreeMap<String, ProposalLifecycle>,
+        passing: HashSet<String>,
+    }
+
+    impl Board {
+        fn propose(&mut self, clock: &dyn Clock, id: &str, delay: Option<u64>, passes: bool) {
+            let creator =
+                Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+            let mut lifecycle = ProposalLifecycle::new(
+                id.to_string(),
+                creator,
+                id.to_string(),
+                50,
+                50,
+                Some(Duration::hours(1)),
+                None,
+            );
+            lifecycle.open_for_feedback(clock);
+            lifecycle.expires_at = Some(clock.now() + Duration::days(1));
+            lifecycle.execution_delay = delay;
+            self.lifecycles.insert(id.to_string(), lifecycle);
+            if passes {
+                self.passing.insert(id.to_string());
+            }
+        }
+    }
+
+    impl ScheduledProposals for Board {
+        fn lifecycles(&self) -> Result<Vec<ProposalLifecycle>, Box<dyn Error>> {
+            Ok(self.lifecycles.values().cloned().collect())
+        }
+
+        fn voting_closes(
+            &self,
+            lifecycle: &ProposalLifecycle,
+        ) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+            Ok(lifecycle.expires_at)
+        }
+
+        fn passed(&self, lifecycle: &ProposalLifecycle) -> Result<bool, Box<dyn Error>> {
+            Ok(self.passing.contains(&lifecycle.id))
+        }
+
+        fn transition(&mut self, id: &str, state: ProposalState) -> Result<(), Box<dyn Error>> {
+            self.lifecycles.get_mut(id).ok_or("unknown proposal")?.state = state;
+            Ok(())
+        }
+
+        fn save_lifecycle(&mut self, lifecycle: &ProposalLifecycle) -> Result<(), Box<dyn Error>> {
+            self.lifecycles
+                .insert(lifecycle.id.clone(), lifecycle.clone());
+            Ok(())
+        }
+
+        fn execute(&mut self, id: &str) -> Result<(), Box<dyn Error>> {
+            self.transition(id, ProposalState::Executed)
+        }
+    }
+
+    #[test]
+    fn test_proposals_open_close_and_execute_on_schedule() {
+        use ScheduledAction::*;
+        let clock = MockClock::new(Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap());
+        let mut board = Board::default();
+        board.propose(&clock, "fence", None, false);
+        board.propose(&clock, "garden", Some(3600), true);
+        board.propose(&clock, "shed", None, true);
+        assert!(run_due(&mut board, clock.now()).unwrap().is_empty());
+
+        clock.advance(Duration::hours(2));
+        let actions = run_due(&mut board, clock.now()).unwrap();
+        assert_eq!(actions.len(), 3);
+        assert!(actions.iter().all(|a| matches!(a, VotingOpened(_))));
+
+        // Voting closes; shed has no delay, so it runs in the same pass
+        clock.advance(Duration::days(1));
+        assert_eq!(
+            run_due(&mut board, clock.now()).unwrap(),
+            vec![
+                Expired("fence".to_string()),
+                Approved("garden".to_string()),
+                Approved("shed".to_string()),
+                Executed("shed".to_string()),
+            ]
+        );
+
+        clock.advance(Duration::minutes(59));
+        assert!(run_due(&mut board, clock.now()).unwrap().is_empty());
+        clock.advance(Duration::minutes(1));
+        assert_eq!(
+            run_due(&mut board, clock.now()).unwrap(),
+            vec![Executed("garden".to_string())]
+        );
+        assert_eq!(board.lifecycles["garden"].state, ProposalState::Executed);
+        assert!(run_due(&mut board, clock.now()).unwrap().is_empty());
+    }
+}
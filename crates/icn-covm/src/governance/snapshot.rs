@@ -0,0 +1,276 @@
+//! Vote weight snapshots
+//!
+//! A proposal weighted by a resource or by reputation is tallied against the
+//! weights members held when it opened for voting, not their live balances,
+//! so moving stake between accounts after voting opens changes nothing.
+//! [`take_snapshot`] records those weights once, at
+//! `governance_proposals/{id}/snapshot`; a snapshot is never overwritten.
+
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::StorageResult;
+use crate::storage::traits::{EconomicOperations, StorageBackend, StorageExtensions};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// How much each vote on a proposal counts for
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteWeight {
+    /// One vote per member
+    #[default]
+    OnePerMember,
+    /// The voter's balance of a resource
+    Resource(String),
+    /// The voter's reputation
+    Reputation,
+}
+
+impl VoteWeight {
+    /// Whether tallies need a snapshot
+    pub fn is_weighted(&self) -> bool {
+        !matches!(self, VoteWeight::OnePerMember)
+    }
+}
+
+impl fmt::Display for VoteWeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoteWeight::OnePerMember => write!(f, "one per member"),
+            VoteWeight::Resource(resource) => write!(f, "resource:{}", resource),
+            VoteWeight::Reputation => write!(f, "reputation"),
+        }
+    }
+}
+
+impl FromStr for VoteWeight {
+    type Err = String;
+
+    /// Parse `member`, `reputation` or `resource:<name>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("resource", resource)) if !resource.is_empty() => {
+                Ok(VoteWeight::Resource(resource.to_string()))
+            }
+            None if s == "member" => Ok(VoteWeight::OnePerMember),
+            None if s == "reputation" => Ok(VoteWeight::Reputation),
+            _ => Err(format!(
+                "Invalid vote weight '{}': expected member, reputation or resource:<name>",
+                s
+            )),
+        }
+    }
+}
+
+/// The weights members held when a proposal opened for voting
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WeightSnapshot {
+    pub proposal_id: String,
+    pub weighting: VoteWeight,
+    pub taken_at: DateTime<Utc>,
+    /// Weight by account; accounts holding nothing are left out
+    pub weights: BTreeMap<String, Decimal>,
+}
+
+impl WeightSnapshot {
+    /// The weight of a voter's ballot, zero if they held nothing
+    pub fn weight_of(&self, voter: &str) -> Decimal {
+        self.weights.get(voter).copied().unwrap_or(Decimal::ZERO)
+    }
+
+    /// Sum of every recorded weight
+    pub fn total(&self) -> Decimal {
+        self.weights.values().sum()
+    }
+}
+
+/// Weighted vote totals
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct WeightedTally {
+    pub yes: Decimal,
+    pub no: Decimal,
+    pub abstain: Decimal,
+}
+
+impl WeightedTally {
+    /// Count `(voter, vote)` ballots at their snapshot weights
+    ///
+    /// Voters missing from the snapshot, including accounts funded after it
+    /// was taken, count for nothing.
+    pub fn count(snapshot: &WeightSnapshot, votes: &[(String, String)]) -> Self {
+        let mut tally = WeightedTally::default();
+        for (voter, vote) in votes {
+            let weight = snapshot.weight_of(voter);
+            match vote.to_lowercase().as_str() {
+                "yes" => tally.yes += weight,
+                "no" => tally.no += weight,
+                "abstain" => tally.abstain += weight,
+                _ => {}
+            }
+        }
+        tally
+    }
+}
+
+/// Storage key of a proposal's snapshot
+pub fn snapshot_key(proposal_id: &str) -> String {
+    format!("governance_proposals/{}/snapshot", proposal_id)
+}
+
+/// Load a proposal's snapshot, if one was taken
+pub fn load_snapshot<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    proposal_id: &str,
+) -> StorageResult<Option<WeightSnapshot>>
+where
+    S: StorageBackend,
+{
+    let key = snapshot_key(proposal_id);
+    if !storage.contains(auth, namespace, &key)? {
+        return Ok(None);
+    }
+    storage.get_json(auth, namespace, &key).map(Some)
+}
+
+/// Read the weights members hold now, without storing them
+///
+/// Resource balances are read after any demurrage due.
+pub fn snapshot_weights<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    proposal_id: &str,
+    weighting: &VoteWeight,
+) -> StorageResult<WeightSnapshot>
+where
+    S: StorageBackend + EconomicOperations,
+{
+    let mut weights = BTreeMap::new();
+    match weighting {
+        VoteWeight::OnePerMember => {}
+        VoteWeight::Resource(resource) => {
+            let prefix = format!("resources/{}/accounts/", resource);
+            for key in storage.list_keys(auth, namespace, Some(&prefix))? {
+                let account = &key[prefix.len()..];
+                let (balance, _) = storage.get_balance(auth, namespace, resource, account)?;
+                if balance > Decimal::ZERO {
+                    weights.insert(account.to_string(), balance);
+                }
+            }
+        }
+        VoteWeight::Reputation => {
+            for key in storage.list_keys(auth, namespace, Some("identities/"))? {
+                let Some(identity) = key
+                    .strip_prefix("identities/")
+                    .and_then(|rest| rest.strip_suffix("/reputation"))
+                else {
+                    continue;
+                };
+                let (reputation, _) = storage.get_reputation(auth, namespace, identity)?;
+                if reputation > 0 {
+                    weights.insert(identity.to_string(), Decimal::from(reputation));
+                }
+            }
+        }
+    }
+
+    Ok(WeightSnapshot {
+        proposal_id: proposal_id.to_string(),
+        weighting: weighting.clone(),
+        taken_at: Utc::now(),
+        weights,
+    })
+}
+
+/// Record the weights a proposal is tallied against
+///
+/// If the proposal already has a snapshot it is returned unchanged, so
+/// reopening voting cannot refresh the weights.
+pub fn take_snapshot<S>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    proposal_id: &str,
+    weighting: &VoteWeight,
+) -> StorageResult<WeightSnapshot>
+where
+    S: StorageBackend + EconomicOperations,
+{
+    if let Some(existing) = load_snapshot(storage, auth, namespace, proposal_id)? {
+        return Ok(existing);
+    }
+    let snapshot = snapshot_weights(storage, auth, namespace, proposal_id, weighting)?;
+    storage.set_json(auth, namespace, &snapshot_key(proposal_id), &snapshot)?;
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use crate::storage::resource::ResourcePolicy;
+
+    #[test]
+    fn test_snapshot_ignores_later_transfers() {
+        let mut storage = InMemoryStorage::new();
+        let mut auth = AuthContext::new("admin");
+        auth.add_role("global", "admin");
+        let auth = Some(&auth);
+        let ns = "coop";
+
+        storage
+            .create_resource(auth, ns, "shares", &ResourcePolicy::default())
+            .unwrap();
+        for (member, amount) in [("alice", 60), ("bob", 40)] {
+            storage
+                .mint(auth, ns, "shares", member, Decimal::from(amount), "issue")
+                .unwrap();
+        }
+
+        let weighting: VoteWeight = "resource:shares".parse().unwrap();
+        let snapshot = take_snapshot(&mut storage, auth, ns, "p1", &weighting).unwrap();
+        assert_eq!(snapshot.total(), Decimal::from(100));
+
+        // Stake moved to a fresh account after voting opened buys no votes
+        storage
+            .transfer(
+                auth,
+                ns,
+                "shares",
+                "bob",
+                "mallory",
+                Decimal::from(40),
+                "move",
+            )
+            .unwrap();
+        let votes = vec![
+            ("alice".to_string(), "no".to_string()),
+            ("bob".to_string(), "yes".to_string()),
+            ("mallory".to_string(), "yes".to_string()),
+        ];
+        let stored = load_snapshot(&storage, auth, ns, "p1").unwrap().unwrap();
+        let tally = WeightedTally::count(&stored, &votes);
+        assert_eq!(tally.yes, Decimal::from(40));
+        assert_eq!(tally.no, Decimal::from(60));
+
+        // A second snapshot keeps the first weights
+        let again = take_snapshot(&mut storage, auth, ns, "p1", &weighting).unwrap();
+        assert_eq!(again, snapshot);
+    }
+
+    #[test]
+    fn test_parse_vote_weight() {
+        assert_eq!("member".parse(), Ok(VoteWeight::OnePerMember));
+        assert_eq!("reputation".parse(), Ok(VoteWeight::Reputation));
+        assert_eq!(
+            "resource:hours".parse(),
+            Ok(VoteWeight::Resource("hours".to_string()))
+        );
+        assert!("resource:".parse::<VoteWeight>().is_err());
+    }
+}
@@ -6,13 +6,15 @@
 //! Templates provide consistent governance patterns that can be reused across
 //! multiple proposals, ensuring procedural fairness and transparency.
 
-use crate::storage::traits::Storage;
-use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{Storage, StorageExtensions};
+use crate::storage::errors::StorageError;
 use crate::storage::auth::AuthContext;
 use crate::identity::Identity;
+use crate::governance::role_classes::RoleClassRequirement;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fmt::{self, Debug};
+use std::fmt::Debug;
 use std::marker::{Send, Sync};
 use std::path::PathBuf;
 use std::fs;
@@ -117,6 +119,10 @@ pub struct VotingConfig {
     
     /// Voting period in seconds
     pub voting_period: u64,
+    
+    /// Quorum and threshold each role class must also reach
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub role_classes: Vec<RoleClassRequirement>,
 }
 
 /// Methods for vote counting
@@ -124,12 +130,15 @@ pub struct VotingConfig {
 pub enum VotingMethod {
     /// One member, one vote
     SimpleMajority,
-    
+
     /// Weighted by reputation
     ReputationWeighted,
-    
+
     /// Ranked choice voting
     RankedChoice,
+
+    /// Votes cost their square in voice credits, as `quadraticvote` tallies
+    Quadratic,
 }
 
 /// Configuration for who can participate in voting
@@ -164,6 +173,10 @@ pub enum TemplateError {
     /// Template not found
     #[error("Template not found: {id}")]
     TemplateNotFound { id: String },
+
+    /// A template with this ID already exists
+    #[error("Template already exists: {id}")]
+    TemplateExists { id: String },
     
     /// Invalid template format
     #[error("Invalid template format: {details}")]
@@ -185,9 +198,9 @@ pub enum TemplateError {
 impl From<StorageError> for TemplateError {
     fn from(error: StorageError) -> Self {
         match error {
-            StorageError::ResourceNotFound { key, .. } => {
-                TemplateError::TemplateNotFound { id: key }
-            }
+            StorageError::NotFound { key } => TemplateError::TemplateNotFound {
+                id: key.trim_start_matches(TEMPLATE_KEY_PREFIX).to_string(),
+            },
             StorageError::PermissionDenied { action, .. } => {
                 TemplateError::PermissionDenied { details: action }
             }
@@ -209,6 +222,155 @@ impl From<io::Error> for TemplateError {
 /// Result type for template operations
 pub type TemplateResult<T> = Result<T, TemplateError>;
 
+impl Template {
+    /// Check a definition before it is stored
+    pub fn validate(&self) -> TemplateResult<()> {
+        let invalid = |details: String| Err(TemplateError::InvalidFormat { details });
+        if self.name.trim().is_empty() {
+            return invalid("template name is empty".to_string());
+        }
+        for (label, value) in [
+            ("quorum", self.voting.quorum),
+            ("threshold", self.voting.threshold),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return invalid(format!("{} must be between 0.0 and 1.0, got {}", label, value));
+            }
+        }
+        for (i, class) in self.voting.role_classes.iter().enumerate() {
+            if class.role.trim().is_empty() {
+                return invalid("role class has no role".to_string());
+            }
+            if self.voting.role_classes[..i]
+                .iter()
+                .any(|c| c.role == class.role)
+            {
+                return invalid(format!("role class '{}' is listed twice", class.role));
+            }
+            for (label, value) in [("quorum", class.quorum), ("threshold", class.threshold)] {
+                if !(0.0..=1.0).contains(&value) {
+                    return invalid(format!(
+                        "{} of role class '{}' must be between 0.0 and 1.0, got {}",
+                        label, class.role, value
+                    ));
+                }
+            }
+        }
+        if self.execution.on_approve.is_empty() {
+            return invalid("execution.on_approve has no operations".to_string());
+        }
+        for (key, param) in &self.parameters {
+            if key != &param.name {
+                return invalid(format!(
+                    "parameter '{}' is declared under the key '{}'",
+                    param.name, key
+                ));
+            }
+            if let Some(default) = &param.default_value {
+                param.check(default)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve the values of a template's parameters
+    ///
+    /// Missing values are taken from the parameter defaults. Fails if a
+    /// required parameter has no value, a value is not of the declared type,
+    /// or a value is given for a parameter the template does not declare.
+    pub fn resolve_parameters(
+        &self,
+        values: &HashMap<String, String>,
+    ) -> TemplateResult<HashMap<String, String>> {
+        if let Some(unknown) = values.keys().find(|k| !self.parameters.contains_key(*k)) {
+            return Err(TemplateError::InvalidFormat {
+                details: format!("template '{}' has no parameter '{}'", self.id, unknown),
+            });
+        }
+
+        let mut resolved = HashMap::new();
+        for (name, param) in &self.parameters {
+            match values.get(name).or(param.default_value.as_ref()) {
+                Some(value) => {
+                    param.check(value)?;
+                    resolved.insert(name.clone(), value.clone());
+                }
+                None if param.required => {
+                    return Err(TemplateError::InvalidFormat {
+                        details: format!("missing required parameter '{}'", name),
+                    });
+                }
+                None => {}
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// The approval logic as DSL, with `{{name}}` placeholders filled in
+    pub fn render_logic(&self, values: &HashMap<String, String>) -> String {
+        values.iter().fold(
+            self.execution.on_approve.join("\n"),
+            |logic, (name, value)| logic.replace(&format!("{{{{{}}}}}", name), value),
+        )
+    }
+}
+
+impl ParameterDefinition {
+    /// Check that `value` is of this parameter's type
+    fn check(&self, value: &str) -> TemplateResult<()> {
+        let valid = match self.param_type {
+            ParameterType::String => true,
+            ParameterType::Number => value.parse::<f64>().is_ok(),
+            ParameterType::Boolean => value.parse::<bool>().is_ok(),
+            ParameterType::Identity | ParameterType::Resource => !value.trim().is_empty(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(TemplateError::InvalidFormat {
+                details: format!(
+                    "parameter '{}' expects a {:?}, got '{}'",
+                    self.name, self.param_type, value
+                ),
+            })
+        }
+    }
+}
+
+/// Namespace templates are stored in
+pub const TEMPLATES_NAMESPACE: &str = "governance";
+
+const TEMPLATE_KEY_PREFIX: &str = "templates:";
+
+fn template_key(id: &str) -> String {
+    format!("{}{}", TEMPLATE_KEY_PREFIX, id)
+}
+
+/// Templates shipped with icn-covm, by ID
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "onboard-member",
+        include_str!("builtin/onboard_member.json"),
+    ),
+    (
+        "offboard-member",
+        include_str!("builtin/offboard_member.json"),
+    ),
+];
+
+/// IDs of the templates shipped with icn-covm
+pub fn builtin_template_ids() -> impl Iterator<Item = &'static str> {
+    BUILTIN_TEMPLATES.iter().map(|(id, _)| *id)
+}
+
+/// A template shipped with icn-covm, ready to be created in a registry
+pub fn builtin_template(id: &str) -> Option<Template> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin, _)| *builtin == id)
+        .map(|(_, json)| serde_json::from_str(json).expect("built-in templates are valid JSON"))
+}
+
 /// Registry for governance templates
 pub struct TemplateRegistry<S>
 where
@@ -248,8 +410,32 @@ where
         }
         Ok(())
     }
+
+    /// Write a template to storage, and to the templates directory if set
+    fn store(&mut self, template: &Template, auth_context: Option<&AuthContext>) -> TemplateResult<()> {
+        self.storage
+            .set_json(auth_context, TEMPLATES_NAMESPACE, &template_key(&template.id), template)?;
+
+        if let Some(path) = &self.templates_path {
+            self.ensure_templates_dir()?;
+            let value = serde_json::to_string_pretty(template)
+                .map_err(|e| TemplateError::InvalidFormat { details: e.to_string() })?;
+            fs::write(path.join(format!("{}.json", template.id)), value)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a template with this ID exists
+    pub fn template_exists(&self, id: &str, auth_context: Option<&AuthContext>) -> TemplateResult<bool> {
+        Ok(self
+            .storage
+            .contains(auth_context, TEMPLATES_NAMESPACE, &template_key(id))?)
+    }
     
     /// Create a new template
+    ///
+    /// The definition's ID is kept if set, otherwise one is generated. The
+    /// template starts at version 1.0, authored by `author`.
     pub fn create_template(
         &mut self,
         name: &str,
@@ -257,25 +443,26 @@ where
         author: &Identity,
         auth_context: Option<&AuthContext>,
     ) -> TemplateResult<String> {
-        // Generate a unique ID
-        let id = format!("template:{}", uuid::Uuid::new_v4());
-        
-        // Store in storage backend
-        let key = format!("templates:{}", id);
-        let value = serde_json::to_string(definition)
-            .map_err(|e| TemplateError::InvalidFormat { details: e.to_string() })?;
-        
-        self.storage.store_string(&key, &value, auth_context, "governance")
-            .map_err(TemplateError::from)?;
-        
-        // If file storage is enabled, also store there
-        if let Some(path) = &self.templates_path {
-            self.ensure_templates_dir()?;
-            let file_path = path.join(format!("{}.json", id));
-            fs::write(file_path, value)?;
+        let mut template = definition.clone();
+        if template.id.is_empty() {
+            template.id = format!("template:{}", uuid::Uuid::new_v4());
         }
-        
-        Ok(id)
+        if self.template_exists(&template.id, auth_context)? {
+            return Err(TemplateError::TemplateExists { id: template.id });
+        }
+
+        template.name = name.to_string();
+        template.version = TemplateVersion {
+            version: "1.0".to_string(),
+            author: author.did().to_string(),
+            created_at: Utc::now().timestamp() as u64,
+            description: format!("Initial version of {}", name),
+        };
+        template.previous_versions = Vec::new();
+        template.validate()?;
+
+        self.store(&template, auth_context)?;
+        Ok(template.id)
     }
     
     /// Get a template by ID
@@ -284,71 +471,66 @@ where
         id: &str,
         auth_context: Option<&AuthContext>,
     ) -> TemplateResult<Template> {
-        // Try to get from storage backend
-        let key = format!("templates:{}", id);
-        let value = self.storage.load_string(&key, auth_context, "governance")
-            .map_err(TemplateError::from)?;
-        
-        // Deserialize the template
-        serde_json::from_str(&value)
+        if !self.template_exists(id, auth_context)? {
+            return Err(TemplateError::TemplateNotFound { id: id.to_string() });
+        }
+        self.storage
+            .get_json(auth_context, TEMPLATES_NAMESPACE, &template_key(id))
             .map_err(|e| TemplateError::InvalidFormat { details: e.to_string() })
     }
     
-    /// List all templates
+    /// List all templates, ordered by ID
     pub fn list_templates(
         &self,
         auth_context: Option<&AuthContext>,
     ) -> TemplateResult<Vec<Template>> {
-        // Get all keys matching the template pattern
-        let prefix = "templates:";
-        let keys = self.storage.keys_with_prefix(prefix, auth_context, "governance")
-            .map_err(TemplateError::from)?;
-        
-        // Load each template
-        let mut templates = Vec::new();
-        for key in keys {
-            let value = self.storage.load_string(&key, auth_context, "governance")
-                .map_err(TemplateError::from)?;
-            
-            let template = serde_json::from_str(&value)
-                .map_err(|e| TemplateError::InvalidFormat { details: e.to_string() })?;
-            
-            templates.push(template);
-        }
-        
+        let keys = self
+            .storage
+            .list_keys(auth_context, TEMPLATES_NAMESPACE, Some(TEMPLATE_KEY_PREFIX))?;
+
+        let mut templates = keys
+            .iter()
+            .map(|key| {
+                self.storage
+                    .get_json::<Template>(auth_context, TEMPLATES_NAMESPACE, key)
+                    .map_err(|e| TemplateError::InvalidFormat { details: e.to_string() })
+            })
+            .collect::<TemplateResult<Vec<_>>>()?;
+        templates.sort_by(|a, b| a.id.cmp(&b.id));
         Ok(templates)
     }
     
     /// Update an existing template
+    ///
+    /// The current version moves to the template's history and the update
+    /// becomes the next minor version, authored by `author`.
     pub fn update_template(
         &mut self,
         id: &str,
         updated_definition: &Template,
         author: &Identity,
         auth_context: Option<&AuthContext>,
-    ) -> TemplateResult<()> {
-        // Get the existing template
-        let mut template = self.get_template(id, auth_context)?;
-        
-        // Store the current version in previous versions
-        template.previous_versions.push(template.version.clone());
-        
-        // Update with new definition
-        let key = format!("templates:{}", id);
-        let value = serde_json::to_string(updated_definition)
-            .map_err(|e| TemplateError::InvalidFormat { details: e.to_string() })?;
-        
-        self.storage.store_string(&key, &value, auth_context, "governance")
-            .map_err(TemplateError::from)?;
-        
-        // If file storage is enabled, also update there
-        if let Some(path) = &self.templates_path {
-            self.ensure_templates_dir()?;
-            let file_path = path.join(format!("{}.json", id));
-            fs::write(file_path, value)?;
-        }
-        
-        Ok(())
+    ) -> TemplateResult<Template> {
+        let current = self.get_template(id, auth_context)?;
+
+        let mut template = updated_definition.clone();
+        template.id = id.to_string();
+        template.previous_versions = current.previous_versions;
+        template.previous_versions.push(current.version.clone());
+        template.version = TemplateVersion {
+            version: format!(
+                "{}.{}",
+                current.version.version.split('.').next().unwrap_or("1"),
+                template.previous_versions.len()
+            ),
+            author: author.did().to_string(),
+            created_at: Utc::now().timestamp() as u64,
+            description: format!("Updated version of {}", template.name),
+        };
+        template.validate()?;
+
+        self.store(&template, auth_context)?;
+        Ok(template)
     }
     
     /// Delete a template
@@ -357,10 +539,11 @@ where
         id: &str,
         auth_context: Option<&AuthContext>,
     ) -> TemplateResult<()> {
-        // Delete from storage backend
-        let key = format!("templates:{}", id);
-        self.storage.delete(&key, auth_context, "governance")
-            .map_err(TemplateError::from)?;
+        if !self.template_exists(id, auth_context)? {
+            return Err(TemplateError::TemplateNotFound { id: id.to_string() });
+        }
+        self.storage
+            .delete(auth_context, TEMPLATES_NAMESPACE, &template_key(id))?;
         
         // If file storage is enabled, also delete there
         if let Some(path) = &self.templates_path {
@@ -378,4 +561,100 @@ where
 pub use self::registry::FileBackedTemplateRegistry;
 
 // Sub-modules
-mod registry; 
\ No newline at end of file
+mod registry; 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget_template() -> Template {
+        let amount = ParameterDefinition {
+            name: "amount".to_string(),
+            description: "Amount to allocate".to_string(),
+            param_type: ParameterType::Number,
+            required: true,
+            default_value: None,
+        };
+        let account = ParameterDefinition {
+            name: "account".to_string(),
+            description: "Receiving account".to_string(),
+            param_type: ParameterType::Identity,
+            required: false,
+            default_value: Some("treasury".to_string()),
+        };
+        Template {
+            id: "budget".to_string(),
+            name: "Budget".to_string(),
+            version: TemplateVersion {
+                version: "1.0".to_string(),
+                author: "did:key:test".to_string(),
+                created_at: 0,
+                description: "Initial version".to_string(),
+            },
+            previous_versions: Vec::new(),
+            parameters: HashMap::from([
+                ("amount".to_string(), amount),
+                ("account".to_string(), account),
+            ]),
+            voting: VotingConfig {
+                quorum: 0.5,
+                threshold: 0.6,
+                method: VotingMethod::SimpleMajority,
+                deliberation_period: 86400,
+                voting_period: 604800,
+                role_classes: Vec::new(),
+            },
+            eligibility: EligibilityConfig {
+                required_role: None,
+                minimum_reputation: None,
+                custom_logic: None,
+            },
+            execution: ExecutionConfig {
+                on_approve: vec!["mint budget {{account}} {{amount}}".to_string()],
+                on_reject: None,
+                execution_delay: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_resolve_parameters_and_render_logic() {
+        let template = budget_template();
+        template.validate().unwrap();
+
+        let values = HashMap::from([("amount".to_string(), "250".to_string())]);
+        let resolved = template.resolve_parameters(&values).unwrap();
+        assert_eq!(resolved["account"], "treasury");
+        assert_eq!(template.render_logic(&resolved), "mint budget treasury 250");
+
+        assert!(template.resolve_parameters(&HashMap::new()).is_err());
+        let wrong_type = HashMap::from([("amount".to_string(), "lots".to_string())]);
+        assert!(template.resolve_parameters(&wrong_type).is_err());
+        let unknown = HashMap::from([
+            ("amount".to_string(), "1".to_string()),
+            ("color".to_string(), "red".to_string()),
+        ]);
+        assert!(template.resolve_parameters(&unknown).is_err());
+
+        let mut by_class = budget_template();
+        let workers = RoleClassRequirement {
+            role: "worker".to_string(),
+            quorum: 0.5,
+            threshold: 0.5,
+        };
+        by_class.voting.role_classes = vec![workers.clone()];
+        by_class.validate().unwrap();
+        by_class.voting.role_classes.push(workers);
+        assert!(by_class.validate().is_err());
+
+        for id in builtin_template_ids() {
+            builtin_template(id).unwrap().validate().unwrap();
+        }
+        let onboard = builtin_template("onboard-member").unwrap();
+        let values = HashMap::from([
+            ("did".to_string(), "did:key:z6Mk".to_string()),
+            ("username".to_string(), "alice".to_string()),
+        ]);
+        let logic = onboard.render_logic(&onboard.resolve_parameters(&values).unwrap());
+        assert_eq!(logic, "onboardmember did:key:z6Mk alice member");
+    }
+}
@@ -5,7 +5,6 @@
 
 use super::{Template, TemplateError, TemplateResult, TemplateVersion};
 use crate::identity::Identity;
-use crate::storage::auth::AuthContext;
 use chrono::Utc;
 use std::path::{Path, PathBuf};
 use std::fs::{self, File};
@@ -53,7 +52,7 @@ impl FileBackedTemplateRegistry {
         let now = Utc::now().timestamp() as u64;
         let version = TemplateVersion {
             version: "1.0".to_string(),
-            author: author.id().to_string(),
+            author: author.did().to_string(),
             created_at: now,
             description: format!("Initial version of {}", name),
         };
@@ -97,14 +96,18 @@ impl FileBackedTemplateRegistry {
             
             // Only process JSON files
             if path.extension().and_then(|e| e.to_str()) == Some("json") {
-                let mut file = File::open(path)?;
+                let mut file = File::open(&path)?;
                 let mut contents = String::new();
                 file.read_to_string(&mut contents)?;
                 
                 match serde_json::from_str(&contents) {
                     Ok(template) => templates.push(template),
                     Err(e) => {
-                        eprintln!("Error parsing template file: {}", e);
+                        tracing::warn!(
+                            path = %path.display(),
+                            error = %e,
+                            "Failed to parse template file"
+                        );
                         // Continue with other templates, don't fail the whole operation
                     }
                 }
@@ -135,7 +138,7 @@ impl FileBackedTemplateRegistry {
                 template.version.version.split('.').next().unwrap_or("1"),
                 template.previous_versions.len() + 1
             ),
-            author: author.id().to_string(),
+            author: author.did().to_string(),
             created_at: now,
             description: format!("Updated version of {}", template.name),
         };
@@ -241,6 +244,7 @@ mod tests {
                 method: super::super::VotingMethod::SimpleMajority,
                 deliberation_period: 86400, // 1 day
                 voting_period: 604800,      // 1 week
+                role_classes: Vec::new(),
             },
             eligibility: super::super::EligibilityConfig {
                 required_role: None,
@@ -259,21 +263,21 @@ mod tests {
     fn test_create_and_get_template() {
         let temp_dir = tempdir().unwrap();
         let registry = FileBackedTemplateRegistry::new(temp_dir.path()).unwrap();
-        let identity = Identity::new("test_author".to_string());
+        let identity = Identity::new("test_author".to_string(), None, "member".to_string(), None).unwrap();
         
         let template = create_test_template();
         let id = registry.create_template("Test Template", template, &identity).unwrap();
         
         let retrieved = registry.get_template(&id).unwrap();
         assert_eq!(retrieved.name, "Test Template");
-        assert_eq!(retrieved.version.author, "test_author");
+        assert_eq!(retrieved.version.author, identity.did());
     }
     
     #[test]
     fn test_list_templates() {
         let temp_dir = tempdir().unwrap();
         let registry = FileBackedTemplateRegistry::new(temp_dir.path()).unwrap();
-        let identity = Identity::new("test_author".to_string());
+        let identity = Identity::new("test_author".to_string(), None, "member".to_string(), None).unwrap();
         
         // Create a few templates
         let template1 = create_test_template();
@@ -290,7 +294,7 @@ mod tests {
     fn test_update_template() {
         let temp_dir = tempdir().unwrap();
         let registry = FileBackedTemplateRegistry::new(temp_dir.path()).unwrap();
-        let identity = Identity::new("test_author".to_string());
+        let identity = Identity::new("test_author".to_string(), None, "member".to_string(), None).unwrap();
         
         // Create a template
         let mut template = create_test_template();
@@ -310,7 +314,7 @@ mod tests {
     fn test_delete_template() {
         let temp_dir = tempdir().unwrap();
         let registry = FileBackedTemplateRegistry::new(temp_dir.path()).unwrap();
-        let identity = Identity::new("test_author".to_string());
+        let identity = Identity::new("test_author".to_string(), None, "member".to_string(), None).unwrap();
         
         // Create a template
         let template = create_test_template();
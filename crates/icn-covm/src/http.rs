@@ -0,0 +1,199 @@
+//! Minimal HTTP client for outbound integrations
+//!
+//! IPFS attachment storage, chat notifications and read replicas talk to
+//! other services with a handful of plain requests, so they share this small
+//! HTTP/1.0 client rather than pulling in a full one. Only `http://` URLs are
+//! supported, as for the webhook event sink; reach HTTPS services through a
+//! local relay that terminates TLS.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Host, port and base path of an `http://` URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    /// Path prefix without a trailing `/`
+    pub base: String,
+}
+
+impl Endpoint {
+    pub fn parse(url: &str) -> io::Result<Self> {
+        let invalid = |details: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid URL {}: {}", url, details),
+            )
+        };
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("only http:// URLs are supported"))?;
+        let (authority, base) = match rest.find('/') {
+            Some(index) => (&rest[..index], rest[index..].trim_end_matches('/')),
+            None => (rest, ""),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid("bad port"))?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            base: base.to_string(),
+        })
+    }
+
+    /// Send a request to `path` under the base path and return the body of
+    /// a 2xx response
+    pub fn request(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        timeout: Duration,
+    ) -> io::Result<Vec<u8>> {
+        let response = self.exchange(method, path, headers, body, timeout)?;
+        if !(200..300).contains(&response.status) {
+            return Err(io::Error::other(format!(
+                "{}:{} responded with {:?}",
+                self.host, self.port, response.status_line
+            )));
+        }
+        Ok(response.body)
+    }
+
+    /// Send a request to `path` under the base path and return the response,
+    /// whatever its status
+    pub fn exchange(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        timeout: Duration,
+    ) -> io::Result<Response> {
+        let mut last_error = None;
+        let mut stream = None;
+        for addr in (self.host.as_str(), self.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(connected) => {
+                    stream = Some(connected);
+                    break;
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        let mut stream = stream.ok_or_else(|| {
+            last_error.unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No address for {}", self.host),
+                )
+            })
+        })?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        // HTTP/1.0 so the response is not chunked and ends when the
+        // connection closes
+        let mut head = format!(
+            "{} {}{} HTTP/1.0\r\nHost: {}:{}\r\nContent-Length: {}\r\n",
+            method,
+            self.base,
+            path,
+            self.host,
+            self.port,
+            body.len()
+        );
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        Response::parse(response)
+    }
+}
+
+/// Status, content type and body of an HTTP response
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    /// First line of the response, e.g. `HTTP/1.1 404 Not Found`
+    pub status_line: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn parse(mut raw: Vec<u8>) -> io::Result<Self> {
+        let split = raw
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(|| io::Error::other("Malformed HTTP response"))?;
+        let head = String::from_utf8_lossy(&raw[..split]).to_string();
+        let mut lines = head.lines();
+        let status_line = lines.next().unwrap_or("").trim().to_string();
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| io::Error::other(format!("Malformed status line {:?}", status_line)))?;
+        let content_type = lines.find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-type")
+                .then(|| value.trim().to_string())
+        });
+        Ok(Self {
+            status,
+            status_line,
+            content_type,
+            body: raw.split_off(split + 4),
+        })
+    }
+}
+
+/// Percent-encode `value` for use as one path segment
+pub fn encode_path_segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_encode() {
+        let endpoint = Endpoint::parse("http://relay.local:8080/hooks/").unwrap();
+        assert_eq!(endpoint.host, "relay.local");
+        assert_eq!(endpoint.port, 8080);
+        assert_eq!(endpoint.base, "/hooks");
+        assert_eq!(Endpoint::parse("http://relay.local").unwrap().port, 80);
+        assert!(Endpoint::parse("https://relay.local").is_err());
+        assert!(Endpoint::parse("http://:80").is_err());
+
+        assert_eq!(
+            encode_path_segment("!room:example.org"),
+            "%21room%3Aexample.org"
+        );
+    }
+}
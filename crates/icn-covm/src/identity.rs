@@ -1,4 +1,12 @@
-use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+pub mod keystore;
+pub mod session;
+pub mod signer;
+
+pub use signer::Signer;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer as _, SigningKey, Verifier, VerifyingKey};
 use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -94,6 +102,28 @@ impl Identity {
         })
     }
 
+    /// Creates a public Identity for an existing Ed25519 key, such as one
+    /// held on a hardware token.
+    pub fn from_public_key(
+        public_key_bytes: &[u8],
+        public_username: &str,
+        identity_type: &str,
+    ) -> Self {
+        let public_key_multibase = multibase::encode(multibase::Base::Base58Btc, public_key_bytes);
+        Self {
+            did: format!("did:key:{}", public_key_multibase),
+            public_key_bytes: public_key_bytes.to_vec(),
+            private_key_bytes: None,
+            public_key_multibase,
+            profile: Profile {
+                public_username: public_username.to_string(),
+                full_name: None,
+                other_fields: HashMap::new(),
+            },
+            identity_type: identity_type.to_string(),
+        }
+    }
+
     /// Returns the DID string.
     pub fn did(&self) -> &str {
         &self.did
@@ -123,30 +153,7 @@ impl Identity {
 
     /// Verifies a multibase-encoded signature against the identity's public key.
     pub fn verify(&self, message: &[u8], signature_multibase: &str) -> Result<(), IdentityError> {
-        let verifying_key = VerifyingKey::from_bytes(
-            &self
-                .public_key_bytes
-                .as_slice()
-                .try_into()
-                .map_err(|_| IdentityError::InvalidKeyMaterial)?,
-        )
-        .map_err(|e| IdentityError::VerificationError(e.to_string()))?;
-
-        // Decode the multibase signature
-        let (_, sig_bytes) = multibase::decode(signature_multibase).map_err(|e| {
-            IdentityError::MultibaseError(format!("Invalid signature format: {}", e))
-        })?;
-
-        // Convert to ed25519 Signature
-        let signature = Signature::from_bytes(
-            &sig_bytes
-                .try_into()
-                .map_err(|_| IdentityError::InvalidKeyMaterial)?,
-        );
-
-        verifying_key
-            .verify(message, &signature)
-            .map_err(|e| IdentityError::VerificationError(e.to_string()))
+        verify_with_key_bytes(&self.public_key_bytes, message, signature_multibase)
     }
 
     /// Returns the public username.
@@ -215,6 +222,85 @@ impl Identity {
     // Add methods to load from storage, update profile etc. as needed
 }
 
+/// Verifies a multibase-encoded signature against a multibase-encoded public key,
+/// for signers known only by their key, such as a ledger genesis founder.
+pub fn verify_signature(
+    public_key_multibase: &str,
+    message: &[u8],
+    signature_multibase: &str,
+) -> Result<(), IdentityError> {
+    let (_, key_bytes) = multibase::decode(public_key_multibase)
+        .map_err(|e| IdentityError::MultibaseError(format!("Invalid public key format: {}", e)))?;
+    verify_with_key_bytes(&key_bytes, message, signature_multibase)
+}
+
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo (RFC 8410); the 32 key
+/// bytes follow it.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Encodes an Ed25519 public key as a PEM `PUBLIC KEY` block.
+pub fn public_key_to_pem(public_key_bytes: &[u8]) -> String {
+    let mut der = ED25519_SPKI_PREFIX.to_vec();
+    der.extend_from_slice(public_key_bytes);
+    let encoded = BASE64.encode(der);
+
+    let mut pem = String::from("-----BEGIN PUBLIC KEY-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str("-----END PUBLIC KEY-----\n");
+    pem
+}
+
+/// Decodes an Ed25519 public key from a PEM `PUBLIC KEY` block.
+pub fn public_key_from_pem(pem: &str) -> Result<Vec<u8>, IdentityError> {
+    let body = pem
+        .trim()
+        .strip_prefix("-----BEGIN PUBLIC KEY-----")
+        .and_then(|rest| rest.strip_suffix("-----END PUBLIC KEY-----"))
+        .ok_or(IdentityError::InvalidKeyMaterial)?;
+    let encoded: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+    let der = BASE64
+        .decode(encoded)
+        .map_err(|_| IdentityError::InvalidKeyMaterial)?;
+
+    match der.strip_prefix(&ED25519_SPKI_PREFIX[..]) {
+        Some(key) if key.len() == 32 => Ok(key.to_vec()),
+        _ => Err(IdentityError::InvalidKeyMaterial),
+    }
+}
+
+fn verify_with_key_bytes(
+    public_key_bytes: &[u8],
+    message: &[u8],
+    signature_multibase: &str,
+) -> Result<(), IdentityError> {
+    let verifying_key = VerifyingKey::from_bytes(
+        public_key_bytes
+            .try_into()
+            .map_err(|_| IdentityError::InvalidKeyMaterial)?,
+    )
+    .map_err(|e| IdentityError::VerificationError(e.to_string()))?;
+
+    // Decode the multibase signature
+    let (_, sig_bytes) = multibase::decode(signature_multibase)
+        .map_err(|e| IdentityError::MultibaseError(format!("Invalid signature format: {}", e)))?;
+
+    // Convert to ed25519 Signature
+    let signature = Signature::from_bytes(
+        &sig_bytes
+            .try_into()
+            .map_err(|_| IdentityError::InvalidKeyMaterial)?,
+    );
+
+    verifying_key
+        .verify(message, &signature)
+        .map_err(|e| IdentityError::VerificationError(e.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*; // Import everything from the parent module (Identity, etc.)
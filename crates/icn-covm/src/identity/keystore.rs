@@ -0,0 +1,340 @@
+//! Identity key files
+//!
+//! An identity file holds an `Identity` as JSON, including its private key.
+//! It may be stored in the clear, as written by earlier versions, or sealed
+//! with a passphrase: the identity JSON is then encrypted with
+//! ChaCha20-Poly1305 under a key derived from the passphrase with Argon2id,
+//! and only the DID and public key stay readable.
+//!
+//! Commands that act as the node or operator identity read the passphrase
+//! of a sealed file from `ICN_KEY_PASSPHRASE`, or prompt for it.
+//!
+//! A key file may instead be a token file naming a key on a hardware token
+//! (see [`signer`](super::signer)). It can be inspected but not loaded, since
+//! its private key never leaves the token.
+
+use super::signer::TokenFile;
+use super::Identity;
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the passphrase of a sealed identity file
+pub const PASSPHRASE_ENV: &str = "ICN_KEY_PASSPHRASE";
+
+const CIPHER: &str = "chacha20poly1305";
+const KDF: &str = "argon2id";
+const SALT_LEN: usize = 16;
+
+/// Errors reading or writing identity files
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    #[error("Failed to access key file {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Invalid key file {path}: {details}")]
+    Format { path: PathBuf, details: String },
+
+    #[error("Key file {0} is encrypted; set {PASSPHRASE_ENV} or run interactively")]
+    PassphraseRequired(PathBuf),
+
+    #[error("Wrong passphrase for key file {0}")]
+    WrongPassphrase(PathBuf),
+
+    #[error("Key derivation failed: {0}")]
+    Kdf(String),
+
+    #[error("Key file {0} names a key on a hardware token, which cannot be read")]
+    HardwareKey(PathBuf),
+}
+
+/// How a sealed file was encrypted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Encryption {
+    cipher: String,
+    kdf: String,
+    /// Hex-encoded KDF salt
+    salt: String,
+    /// Hex-encoded nonce
+    nonce: String,
+}
+
+/// On-disk form of a passphrase-protected identity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedIdentity {
+    did: String,
+    public_key_multibase: String,
+    encryption: Encryption,
+    /// Hex-encoded ciphertext of the identity JSON
+    ciphertext: String,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> KeystoreError + '_ {
+    move |source| KeystoreError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn format_error(path: &Path, details: impl ToString) -> KeystoreError {
+    KeystoreError::Format {
+        path: path.to_path_buf(),
+        details: details.to_string(),
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, KeystoreError> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| KeystoreError::Kdf(e.to_string()))?;
+    Ok(key)
+}
+
+/// Read a key file without decrypting it
+fn read_key_file(path: &Path) -> Result<serde_json::Value, KeystoreError> {
+    let text = fs::read_to_string(path).map_err(io_error(path))?;
+    serde_json::from_str(&text).map_err(|e| format_error(path, e))
+}
+
+/// Whether the identity file at `path` is sealed with a passphrase
+pub fn is_encrypted(path: &Path) -> Result<bool, KeystoreError> {
+    Ok(read_key_file(path)?.get("ciphertext").is_some())
+}
+
+/// Whether the key file at `path` is a token file for a hardware key
+pub fn is_token_file(path: &Path) -> Result<bool, KeystoreError> {
+    Ok(read_key_file(path)?.get("token").is_some())
+}
+
+/// The public part of an identity file, readable without its passphrase
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyFileSummary {
+    pub did: String,
+    pub public_key_multibase: String,
+    pub encrypted: bool,
+    /// Profile username, unless the file is sealed
+    pub public_username: Option<String>,
+    /// Identity type, unless the file is sealed
+    pub identity_type: Option<String>,
+    /// Kind of hardware token holding the key, for a token file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// Describe an identity file without decrypting it
+pub fn inspect_identity(path: &Path) -> Result<KeyFileSummary, KeystoreError> {
+    let value = read_key_file(path)?;
+    if value.get("ciphertext").is_some() {
+        let sealed: SealedIdentity =
+            serde_json::from_value(value).map_err(|e| format_error(path, e))?;
+        return Ok(KeyFileSummary {
+            did: sealed.did,
+            public_key_multibase: sealed.public_key_multibase,
+            encrypted: true,
+            public_username: None,
+            identity_type: None,
+            token: None,
+        });
+    }
+    let (identity, token) = if value.get("token").is_some() {
+        let file: TokenFile = serde_json::from_value(value).map_err(|e| format_error(path, e))?;
+        (file.identity, Some(file.token.kind().to_string()))
+    } else {
+        let identity = serde_json::from_value(value).map_err(|e| format_error(path, e))?;
+        (identity, None)
+    };
+    Ok(KeyFileSummary {
+        did: identity.did,
+        public_key_multibase: identity.public_key_multibase,
+        encrypted: false,
+        public_username: Some(identity.profile.public_username),
+        identity_type: Some(identity.identity_type),
+        token,
+    })
+}
+
+/// Load an identity file, decrypting it with `passphrase` if it is sealed
+pub fn load_identity(path: &Path, passphrase: Option<&str>) -> Result<Identity, KeystoreError> {
+    let value = read_key_file(path)?;
+    if value.get("token").is_some() {
+        return Err(KeystoreError::HardwareKey(path.to_path_buf()));
+    }
+    if value.get("ciphertext").is_none() {
+        return serde_json::from_value(value).map_err(|e| format_error(path, e));
+    }
+
+    let sealed: SealedIdentity =
+        serde_json::from_value(value).map_err(|e| format_error(path, e))?;
+    if sealed.encryption.cipher != CIPHER || sealed.encryption.kdf != KDF {
+        return Err(format_error(
+            path,
+            format!(
+                "unsupported encryption {}/{}",
+                sealed.encryption.cipher, sealed.encryption.kdf
+            ),
+        ));
+    }
+    let passphrase =
+        passphrase.ok_or_else(|| KeystoreError::PassphraseRequired(path.to_path_buf()))?;
+
+    let salt = hex::decode(&sealed.encryption.salt).map_err(|e| format_error(path, e))?;
+    let nonce = hex::decode(&sealed.encryption.nonce).map_err(|e| format_error(path, e))?;
+    if nonce.len() != 12 {
+        return Err(format_error(path, "invalid nonce length"));
+    }
+    let ciphertext = hex::decode(&sealed.ciphertext).map_err(|e| format_error(path, e))?;
+
+    let key = derive_key(passphrase, &salt)?;
+    let plaintext = ChaCha20Poly1305::new(&key)
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: sealed.did.as_bytes(),
+            },
+        )
+        .map_err(|_| KeystoreError::WrongPassphrase(path.to_path_buf()))?;
+    let identity: Identity =
+        serde_json::from_slice(&plaintext).map_err(|e| format_error(path, e))?;
+    if identity.did != sealed.did {
+        return Err(format_error(path, "DID does not match the sealed identity"));
+    }
+    Ok(identity)
+}
+
+/// Write an identity file, sealing it if a passphrase is given
+///
+/// The file is replaced atomically and, on Unix, readable only by its owner.
+pub fn save_identity(
+    path: &Path,
+    identity: &Identity,
+    passphrase: Option<&str>,
+) -> Result<(), KeystoreError> {
+    let json = match passphrase {
+        None => serde_json::to_string_pretty(identity),
+        Some(passphrase) => {
+            let plaintext = serde_json::to_vec(identity).map_err(|e| format_error(path, e))?;
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+            let ciphertext = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?)
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: &plaintext,
+                        aad: identity.did.as_bytes(),
+                    },
+                )
+                .map_err(|_| format_error(path, "encryption failed"))?;
+            serde_json::to_string_pretty(&SealedIdentity {
+                did: identity.did.clone(),
+                public_key_multibase: identity.public_key_multibase.clone(),
+                encryption: Encryption {
+                    cipher: CIPHER.to_string(),
+                    kdf: KDF.to_string(),
+                    salt: hex::encode(salt),
+                    nonce: hex::encode(nonce),
+                },
+                ciphertext: hex::encode(ciphertext),
+            })
+        }
+    }
+    .map_err(|e| format_error(path, e))?;
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(io_error(parent))?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&tmp_path).map_err(io_error(&tmp_path))?;
+        file.write_all(json.as_bytes())
+            .and_then(|_| file.sync_all())
+            .map_err(io_error(&tmp_path))?;
+    }
+    fs::rename(&tmp_path, path).map_err(io_error(path))
+}
+
+/// Ask for a passphrase on the terminal, or take it from `ICN_KEY_PASSPHRASE`
+///
+/// Returns `None` when neither is available.
+pub fn passphrase_from_env_or_prompt(prompt: &str) -> io::Result<Option<String>> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(Some(passphrase));
+    }
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    rpassword::prompt_password(prompt).map(Some)
+}
+
+/// Load the identity file at `path`, getting the passphrase of a sealed file
+/// from `ICN_KEY_PASSPHRASE` or the terminal
+pub fn open_identity(path: &Path) -> Result<Identity, KeystoreError> {
+    let passphrase = if is_encrypted(path)? {
+        passphrase_from_env_or_prompt(&format!("Passphrase for {}: ", path.display()))
+            .map_err(io_error(path))?
+    } else {
+        None
+    };
+    load_identity(path, passphrase.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_identity_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.json");
+        let identity =
+            Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+
+        save_identity(&path, &identity, Some("correct horse")).unwrap();
+        assert!(is_encrypted(&path).unwrap());
+        let summary = inspect_identity(&path).unwrap();
+        assert_eq!(summary.did, identity.did);
+        assert_eq!(summary.public_username, None);
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains(&identity.did));
+        assert!(!text.contains("alice"));
+
+        let loaded = load_identity(&path, Some("correct horse")).unwrap();
+        assert_eq!(loaded.did, identity.did);
+        assert_eq!(loaded.private_key_bytes, identity.private_key_bytes);
+
+        assert!(matches!(
+            load_identity(&path, Some("wrong")),
+            Err(KeystoreError::WrongPassphrase(_))
+        ));
+        assert!(matches!(
+            load_identity(&path, None),
+            Err(KeystoreError::PassphraseRequired(_))
+        ));
+    }
+
+    #[test]
+    fn test_plain_identity_files_still_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.json");
+        let identity = Identity::new("bob".to_string(), None, "member".to_string(), None).unwrap();
+        fs::write(&path, serde_json::to_string(&identity).unwrap()).unwrap();
+
+        assert!(!is_encrypted(&path).unwrap());
+        let loaded = load_identity(&path, Some("ignored")).unwrap();
+        assert_eq!(loaded.did, identity.did);
+
+        save_identity(&path, &identity, None).unwrap();
+        assert_eq!(load_identity(&path, None).unwrap().did, identity.did);
+    }
+}
@@ -0,0 +1,257 @@
+//! Local CLI sessions
+//!
+//! `identity login` opens an identity key file once, decrypting it if it is
+//! sealed or signing on its token if it names a hardware key, and caches a
+//! session: the public part of the identity and a token, signed with its
+//! private key, that states who is logged in and until when. Later commands
+//! act as that identity without reopening the key file or asking for its
+//! passphrase. The private key is never written to the session file.
+//!
+//! The token is checked on every use against the public key in the DID, so
+//! a session file that was edited to name another identity, or that has
+//! expired, is refused.
+
+use super::keystore::KeystoreError;
+use super::signer::{self, Signer, SignerError};
+use super::{verify_signature, Identity, IdentityError};
+use crate::storage::auth::AuthContext;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the session file
+pub const SESSION_ENV: &str = "ICN_SESSION";
+
+/// Session file used when `ICN_SESSION` is not set
+pub const DEFAULT_SESSION_PATH: &str = "./.icn-session.json";
+
+/// How long a session lasts unless another lifetime is requested
+pub const DEFAULT_SESSION_TTL_SECS: u64 = 12 * 60 * 60;
+
+/// Errors reading, writing or checking a session
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("Failed to access session file {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Invalid session file {path}: {details}")]
+    Format { path: PathBuf, details: String },
+
+    #[error("Session for {did} expired; run `icn-covm identity login` again")]
+    Expired { did: String },
+
+    #[error("Session for {did} is not validly signed; run `icn-covm identity login` again")]
+    InvalidSignature { did: String },
+
+    #[error(transparent)]
+    Keystore(#[from] KeystoreError),
+
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+
+    #[error("Failed to sign session: {0}")]
+    Identity(#[from] IdentityError),
+}
+
+/// A logged-in identity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    /// The identity, without its private key
+    pub identity: Identity,
+    /// Key file the session was opened from
+    pub key_path: PathBuf,
+    /// Unix time the session started
+    pub issued_at: u64,
+    /// Unix time after which the session is refused
+    pub expires_at: u64,
+    /// Multibase signature over [`session_statement`]
+    pub token: String,
+}
+
+/// Bytes an identity signs to open a session
+pub fn session_statement(did: &str, issued_at: u64, expires_at: u64) -> Vec<u8> {
+    format!("icn-session|{}|{}|{}", did, issued_at, expires_at).into_bytes()
+}
+
+/// The session file named by `ICN_SESSION`, or the default one
+pub fn session_path() -> PathBuf {
+    std::env::var_os(SESSION_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SESSION_PATH))
+}
+
+impl Session {
+    /// Open a session for the identity `signer` signs as
+    pub fn open(
+        signer: &dyn Signer,
+        key_path: &Path,
+        issued_at: u64,
+        ttl_secs: u64,
+    ) -> Result<Self, SessionError> {
+        let expires_at = issued_at + ttl_secs;
+        let token = signer.sign(&session_statement(signer.did(), issued_at, expires_at))?;
+        Ok(Self {
+            identity: signer.public_identity(),
+            key_path: key_path.to_path_buf(),
+            issued_at,
+            expires_at,
+            token,
+        })
+    }
+
+    pub fn did(&self) -> &str {
+        self.identity.did()
+    }
+
+    /// Check that the session has not expired and that its token was signed
+    /// by the key its DID names
+    pub fn verify(&self, now: u64) -> Result<(), SessionError> {
+        let did = self.did();
+        if now >= self.expires_at {
+            return Err(SessionError::Expired {
+                did: did.to_string(),
+            });
+        }
+        let invalid = || SessionError::InvalidSignature {
+            did: did.to_string(),
+        };
+        let public_key = did.strip_prefix("did:key:").ok_or_else(invalid)?;
+        if public_key != self.identity.public_key_multibase {
+            return Err(invalid());
+        }
+        verify_signature(
+            public_key,
+            &session_statement(did, self.issued_at, self.expires_at),
+            &self.token,
+        )
+        .map_err(|_| invalid())
+    }
+
+    /// Auth context acting as the session's identity
+    pub fn to_auth_context(&self) -> AuthContext {
+        let mut auth_context = AuthContext::new(self.did());
+        auth_context.register_identity(self.identity.clone());
+        auth_context
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> SessionError + '_ {
+    move |source| SessionError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Log in as the identity in `key_path`, asking for its passphrase if the
+/// file is sealed or its PIN if it names a hardware key, and save the
+/// session to `session_path`
+pub fn login(
+    key_path: &Path,
+    session_path: &Path,
+    now: u64,
+    ttl_secs: u64,
+) -> Result<Session, SessionError> {
+    let signer = signer::open_signer(key_path)?;
+    let session = Session::open(signer.as_ref(), key_path, now, ttl_secs)?;
+    save_session(session_path, &session)?;
+    Ok(session)
+}
+
+/// Write a session file, readable only by its owner on Unix
+pub fn save_session(path: &Path, session: &Session) -> Result<(), SessionError> {
+    let json = serde_json::to_string_pretty(session).map_err(|e| SessionError::Format {
+        path: path.to_path_buf(),
+        details: e.to_string(),
+    })?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(io_error(parent))?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(io_error(path))?;
+    file.write_all(json.as_bytes()).map_err(io_error(path))
+}
+
+/// Read the session file, if there is one, without checking it
+pub fn load_session(path: &Path) -> Result<Option<Session>, SessionError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(io_error(path)(e)),
+    };
+    serde_json::from_str(&text)
+        .map(Some)
+        .map_err(|e| SessionError::Format {
+            path: path.to_path_buf(),
+            details: e.to_string(),
+        })
+}
+
+/// The checked session in `path`, if someone is logged in
+pub fn active_session(path: &Path, now: u64) -> Result<Option<Session>, SessionError> {
+    match load_session(path)? {
+        Some(session) => {
+            session.verify(now)?;
+            Ok(Some(session))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Remove the session file, returning the session it held
+pub fn logout(path: &Path) -> Result<Option<Session>, SessionError> {
+    // An unreadable session is still removed
+    let session = load_session(path).unwrap_or(None);
+    match fs::remove_file(path) {
+        Ok(()) => Ok(session),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(io_error(path)(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::keystore;
+
+    #[test]
+    fn test_session_round_trip_and_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("identity.json");
+        let session_path = dir.path().join("session.json");
+        let identity =
+            Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        keystore::save_identity(&key_path, &identity, None).unwrap();
+
+        let session = login(&key_path, &session_path, 1_000, 3_600).unwrap();
+        assert!(!fs::read_to_string(&session_path)
+            .unwrap()
+            .contains("private_key_bytes"));
+
+        let active = active_session(&session_path, 2_000).unwrap().unwrap();
+        assert_eq!(active.did(), identity.did());
+        assert_eq!(active.to_auth_context().identity_did(), identity.did());
+        assert!(matches!(
+            active_session(&session_path, 4_600),
+            Err(SessionError::Expired { .. })
+        ));
+
+        // A session edited to last longer no longer matches its token
+        let mut tampered = session.clone();
+        tampered.expires_at += 3_600;
+        assert!(matches!(
+            tampered.verify(2_000),
+            Err(SessionError::InvalidSignature { .. })
+        ));
+
+        assert_eq!(
+            logout(&session_path).unwrap().unwrap().did(),
+            identity.did()
+        );
+        assert!(active_session(&session_path, 2_000).unwrap().is_none());
+        assert!(logout(&session_path).unwrap().is_none());
+    }
+}
@@ -0,0 +1,552 @@
+//! Signing keys kept off disk
+//!
+//! Signatures by the node or operator identity go through [`Signer`], so the
+//! key may sit in an identity file (see [`keystore`]) or on a hardware token.
+//! A hardware key is named by a token file written by `keys enroll`, holding
+//! the public identity and where to find its key; commands that take an
+//! identity file accept a token file in its place.
+//!
+//! Two kinds of token are supported, each behind a feature:
+//!
+//! - `pkcs11`: an Ed25519 key on a PKCS#11 token, such as a smart card, an
+//!   HSM, or a YubiKey through its PKCS#11 module. The token signs each
+//!   message itself and never releases the key.
+//! - `fido2`: a FIDO2 security key with the `hmac-secret` extension. FIDO2
+//!   keys only sign WebAuthn assertions, which no `did:key` verifier can
+//!   check, so the Ed25519 key is instead derived from the credential's HMAC
+//!   secret each time it is needed, with a touch of the key, and dropped
+//!   after signing. It is never written anywhere.
+//!
+//! The token PIN is read from `ICN_TOKEN_PIN`, or prompted for.
+
+use super::keystore::{self, KeystoreError};
+use super::{Identity, IdentityError};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the PIN of a hardware token
+pub const TOKEN_PIN_ENV: &str = "ICN_TOKEN_PIN";
+
+/// Something that signs messages as an identity
+pub trait Signer {
+    /// DID of the signing identity
+    fn did(&self) -> &str;
+
+    /// The signing identity, without its private key
+    fn public_identity(&self) -> Identity;
+
+    /// Sign `message`, returning a multibase-encoded Ed25519 signature
+    fn sign(&self, message: &[u8]) -> Result<String, IdentityError>;
+}
+
+impl Signer for Identity {
+    fn did(&self) -> &str {
+        &self.did
+    }
+
+    fn public_identity(&self) -> Identity {
+        let mut public = self.clone();
+        public.private_key_bytes = None;
+        public
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<String, IdentityError> {
+        Identity::sign(self, message)
+    }
+}
+
+/// Errors opening or enrolling a signer
+#[derive(Debug, thiserror::Error)]
+pub enum SignerError {
+    #[error(transparent)]
+    Keystore(#[from] KeystoreError),
+
+    #[error("Failed to access token file {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Invalid token file {path}: {details}")]
+    Format { path: PathBuf, details: String },
+
+    #[error("Token file {path} names a {kind} token, but this build lacks the `{kind}` feature")]
+    Unsupported { path: PathBuf, kind: &'static str },
+
+    #[error("Hardware token error: {0}")]
+    Token(String),
+}
+
+/// Where a hardware key is found
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum TokenKey {
+    /// An Ed25519 private key on a PKCS#11 token
+    Pkcs11 {
+        /// Path of the token's PKCS#11 module
+        module: PathBuf,
+        token_label: String,
+        /// Label of the key pair on the token
+        key_label: String,
+    },
+    /// A FIDO2 credential whose HMAC secret seeds the key
+    Fido2 {
+        rp_id: String,
+        /// Hex-encoded credential ID
+        credential_id: String,
+        /// Hex-encoded `hmac-secret` salt
+        salt: String,
+    },
+}
+
+impl TokenKey {
+    /// Feature name of the token kind
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TokenKey::Pkcs11 { .. } => "pkcs11",
+            TokenKey::Fido2 { .. } => "fido2",
+        }
+    }
+}
+
+/// On-disk form of a hardware key: the public identity and its token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenFile {
+    pub identity: Identity,
+    pub token: TokenKey,
+}
+
+/// Read the token file at `path`
+pub fn read_token_file(path: &Path) -> Result<TokenFile, SignerError> {
+    let text = fs::read_to_string(path).map_err(|source| SignerError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    serde_json::from_str(&text).map_err(|e| SignerError::Format {
+        path: path.to_path_buf(),
+        details: e.to_string(),
+    })
+}
+
+/// Write a token file; it holds no secret, so it keeps the default mode
+pub fn save_token_file(path: &Path, file: &TokenFile) -> Result<(), SignerError> {
+    let io_error = |source| SignerError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let json = serde_json::to_string_pretty(file).map_err(|e| SignerError::Format {
+        path: path.to_path_buf(),
+        details: e.to_string(),
+    })?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    fs::write(path, json).map_err(io_error)
+}
+
+/// Ask for the token PIN on the terminal, or take it from `ICN_TOKEN_PIN`
+///
+/// Returns `None` when neither is available, for tokens without a PIN.
+pub fn token_pin(prompt: &str) -> io::Result<Option<String>> {
+    if let Ok(pin) = std::env::var(TOKEN_PIN_ENV) {
+        return Ok(Some(pin));
+    }
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    rpassword::prompt_password(prompt).map(|pin| Some(pin).filter(|pin| !pin.is_empty()))
+}
+
+/// A key held on a hardware token
+pub struct TokenSigner {
+    identity: Identity,
+    token: TokenKey,
+    pin: Option<String>,
+}
+
+impl TokenSigner {
+    /// Signer for a token file's key, failing if this build cannot reach the
+    /// token
+    pub fn new(path: &Path, file: TokenFile, pin: Option<String>) -> Result<Self, SignerError> {
+        let supported = match file.token {
+            TokenKey::Pkcs11 { .. } => cfg!(feature = "pkcs11"),
+            TokenKey::Fido2 { .. } => cfg!(feature = "fido2"),
+        };
+        if !supported {
+            return Err(SignerError::Unsupported {
+                path: path.to_path_buf(),
+                kind: file.token.kind(),
+            });
+        }
+        Ok(Self {
+            identity: file.identity,
+            token: file.token,
+            pin,
+        })
+    }
+}
+
+impl Signer for TokenSigner {
+    fn did(&self) -> &str {
+        &self.identity.did
+    }
+
+    fn public_identity(&self) -> Identity {
+        self.identity.clone()
+    }
+
+    /// Sign on the token, checking the signature against the identity so a
+    /// swapped token or key is caught here rather than by a verifier
+    fn sign(&self, message: &[u8]) -> Result<String, IdentityError> {
+        let signature: Result<String, String> = match &self.token {
+            #[cfg(feature = "pkcs11")]
+            TokenKey::Pkcs11 {
+                module,
+                token_label,
+                key_label,
+            } => pkcs11::sign(module, token_label, key_label, self.pin.as_deref(), message),
+            #[cfg(feature = "fido2")]
+            TokenKey::Fido2 {
+                rp_id,
+                credential_id,
+                salt,
+            } => fido2::sign(rp_id, credential_id, salt, self.pin.as_deref(), message),
+            #[allow(unreachable_patterns)]
+            other => Err(format!("{} tokens are not supported", other.kind())),
+        };
+        let signature = signature.map_err(IdentityError::SigningError)?;
+        self.identity.verify(message, &signature).map_err(|_| {
+            IdentityError::SigningError(format!("Token key does not match {}", self.identity.did))
+        })?;
+        Ok(signature)
+    }
+}
+
+/// Open the signer for an identity file or a token file, asking for the
+/// passphrase or PIN it needs
+pub fn open_signer(path: &Path) -> Result<Box<dyn Signer>, SignerError> {
+    if !keystore::is_token_file(path)? {
+        return Ok(Box::new(keystore::open_identity(path)?));
+    }
+    let file = read_token_file(path)?;
+    let pin =
+        token_pin(&format!("PIN for {}: ", path.display())).map_err(|source| SignerError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    Ok(Box::new(TokenSigner::new(path, file, pin)?))
+}
+
+/// A hardware key to write a token file for
+#[derive(Debug, Clone)]
+pub enum Enrollment<'a> {
+    /// An existing Ed25519 key pair on a PKCS#11 token
+    Pkcs11 {
+        module: &'a Path,
+        token_label: &'a str,
+        key_label: &'a str,
+    },
+    /// A new credential on a FIDO2 security key
+    Fido2 { rp_id: &'a str },
+}
+
+impl Enrollment<'_> {
+    /// Feature name of the token kind
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Enrollment::Pkcs11 { .. } => "pkcs11",
+            Enrollment::Fido2 { .. } => "fido2",
+        }
+    }
+}
+
+/// Describe a hardware key as a token file for a new identity
+///
+/// A PKCS#11 key pair must already exist on the token. A FIDO2 key gets a
+/// new credential, and must be touched twice: once to create it and once to
+/// derive the public key.
+#[cfg_attr(
+    not(any(feature = "pkcs11", feature = "fido2")),
+    allow(unused_variables)
+)]
+pub fn enroll(
+    enrollment: &Enrollment,
+    pin: Option<&str>,
+    username: &str,
+    identity_type: &str,
+) -> Result<TokenFile, SignerError> {
+    let enrolled: Result<(Vec<u8>, TokenKey), SignerError> = match enrollment {
+        #[cfg(feature = "pkcs11")]
+        Enrollment::Pkcs11 {
+            module,
+            token_label,
+            key_label,
+        } => {
+            let public_key = pkcs11::public_key(module, token_label, key_label, pin)
+                .map_err(SignerError::Token)?;
+            let token = TokenKey::Pkcs11 {
+                module: module.to_path_buf(),
+                token_label: token_label.to_string(),
+                key_label: key_label.to_string(),
+            };
+            Ok((public_key, token))
+        }
+        #[cfg(feature = "fido2")]
+        Enrollment::Fido2 { rp_id } => {
+            use rand::RngCore;
+
+            let credential_id = fido2::make_credential(rp_id, pin).map_err(SignerError::Token)?;
+            let mut salt = [0u8; 32];
+            rand::rngs::OsRng.fill_bytes(&mut salt);
+            let key = fido2::signing_key(rp_id, &credential_id, &salt, pin)
+                .map_err(SignerError::Token)?;
+            let token = TokenKey::Fido2 {
+                rp_id: rp_id.to_string(),
+                credential_id: hex::encode(credential_id),
+                salt: hex::encode(salt),
+            };
+            Ok((key.verifying_key().to_bytes().to_vec(), token))
+        }
+        #[allow(unreachable_patterns)]
+        other => Err(SignerError::Token(format!(
+            "This build lacks the `{}` feature",
+            other.kind()
+        ))),
+    };
+    let (public_key, token) = enrolled?;
+    Ok(TokenFile {
+        identity: Identity::from_public_key(&public_key, username, identity_type),
+        token,
+    })
+}
+
+#[cfg(feature = "pkcs11")]
+mod pkcs11 {
+    use cryptoki::context::{CInitializeArgs, Pkcs11};
+    use cryptoki::mechanism::Mechanism;
+    use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+    use cryptoki::session::{Session, UserType};
+    use cryptoki::types::AuthPin;
+    use std::path::Path;
+
+    /// Open a session on the token labelled `token_label`, logged in if a
+    /// PIN is given
+    fn open_session(
+        module: &Path,
+        token_label: &str,
+        pin: Option<&str>,
+    ) -> Result<Session, String> {
+        let context = Pkcs11::new(module).map_err(|e| e.to_string())?;
+        context
+            .initialize(CInitializeArgs::OsThreads)
+            .map_err(|e| e.to_string())?;
+        let slot = context
+            .get_slots_with_token()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|slot| {
+                context
+                    .get_token_info(*slot)
+                    .map(|info| info.label() == token_label)
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| format!("No token labelled '{}'", token_label))?;
+        let session = context.open_ro_session(slot).map_err(|e| e.to_string())?;
+        if let Some(pin) = pin {
+            session
+                .login(UserType::User, Some(&AuthPin::new(pin.to_string())))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(session)
+    }
+
+    fn find_key(
+        session: &Session,
+        class: ObjectClass,
+        key_label: &str,
+    ) -> Result<ObjectHandle, String> {
+        session
+            .find_objects(&[
+                Attribute::Class(class),
+                Attribute::KeyType(KeyType::EC_EDWARDS),
+                Attribute::Label(key_label.as_bytes().to_vec()),
+            ])
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No Ed25519 key labelled '{}' on the token", key_label))
+    }
+
+    /// The raw public key of the key pair `key_label`
+    pub fn public_key(
+        module: &Path,
+        token_label: &str,
+        key_label: &str,
+        pin: Option<&str>,
+    ) -> Result<Vec<u8>, String> {
+        let session = open_session(module, token_label, pin)?;
+        let key = find_key(&session, ObjectClass::PUBLIC_KEY, key_label)?;
+        let point = session
+            .get_attributes(key, &[AttributeType::EcPoint])
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find_map(|attribute| match attribute {
+                Attribute::EcPoint(point) => Some(point),
+                _ => None,
+            })
+            .ok_or("Public key has no EC point")?;
+        // Tokens return the point as a DER OCTET STRING or as the bare key
+        match point.as_slice() {
+            [0x04, 32, key @ ..] if key.len() == 32 => Ok(key.to_vec()),
+            key if key.len() == 32 => Ok(key.to_vec()),
+            _ => Err("Public key is not an Ed25519 key".to_string()),
+        }
+    }
+
+    pub fn sign(
+        module: &Path,
+        token_label: &str,
+        key_label: &str,
+        pin: Option<&str>,
+        message: &[u8],
+    ) -> Result<String, String> {
+        let session = open_session(module, token_label, pin)?;
+        let key = find_key(&session, ObjectClass::PRIVATE_KEY, key_label)?;
+        let signature = session
+            .sign(&Mechanism::Eddsa, key, message)
+            .map_err(|e| e.to_string())?;
+        Ok(multibase::encode(multibase::Base::Base58Btc, signature))
+    }
+}
+
+#[cfg(feature = "fido2")]
+mod fido2 {
+    use ctap_hid_fido2::fidokey::get_assertion::get_assertion_params::Extension as AssertionExtension;
+    use ctap_hid_fido2::fidokey::make_credential::make_credential_params::Extension as CredentialExtension;
+    use ctap_hid_fido2::fidokey::{GetAssertionArgsBuilder, MakeCredentialArgsBuilder};
+    use ctap_hid_fido2::{Cfg, FidoKeyHid, FidoKeyHidFactory};
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use rand::RngCore;
+
+    fn device() -> Result<FidoKeyHid, String> {
+        FidoKeyHidFactory::create(&Cfg::init()).map_err(|e| e.to_string())
+    }
+
+    fn challenge() -> [u8; 32] {
+        let mut challenge = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut challenge);
+        challenge
+    }
+
+    /// Create a credential with `hmac-secret` enabled, returning its ID
+    pub fn make_credential(rp_id: &str, pin: Option<&str>) -> Result<Vec<u8>, String> {
+        let challenge = challenge();
+        let extensions = [CredentialExtension::HmacSecret(Some(true))];
+        let builder = MakeCredentialArgsBuilder::new(rp_id, &challenge).extensions(&extensions);
+        let args = match pin {
+            Some(pin) => builder.pin(pin),
+            None => builder.without_pin_and_uv(),
+        }
+        .build();
+        let attestation = device()?
+            .make_credential_with_args(&args)
+            .map_err(|e| e.to_string())?;
+        Ok(attestation.credential_descriptor.id)
+    }
+
+    /// Derive the Ed25519 key seeded by the credential's HMAC secret
+    pub fn signing_key(
+        rp_id: &str,
+        credential_id: &[u8],
+        salt: &[u8; 32],
+        pin: Option<&str>,
+    ) -> Result<SigningKey, String> {
+        let challenge = challenge();
+        let extensions = [AssertionExtension::HmacSecret(Some(*salt))];
+        let builder = GetAssertionArgsBuilder::new(rp_id, &challenge)
+            .credential_id(credential_id)
+            .extensions(&extensions);
+        let args = match pin {
+            Some(pin) => builder.pin(pin),
+            None => builder.without_pin_and_uv(),
+        }
+        .build();
+        device()?
+            .get_assertion_with_args(&args)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .flat_map(|assertion| assertion.extensions)
+            .find_map(|extension| match extension {
+                AssertionExtension::HmacSecret(Some(secret)) => {
+                    Some(SigningKey::from_bytes(&secret))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| "Security key returned no hmac-secret".to_string())
+    }
+
+    pub fn sign(
+        rp_id: &str,
+        credential_id: &str,
+        salt: &str,
+        pin: Option<&str>,
+        message: &[u8],
+    ) -> Result<String, String> {
+        let credential_id = hex::decode(credential_id).map_err(|e| e.to_string())?;
+        let salt: [u8; 32] = hex::decode(salt)
+            .map_err(|e| e.to_string())?
+            .try_into()
+            .map_err(|_| "Salt must be 32 bytes".to_string())?;
+        let key = signing_key(rp_id, &credential_id, &salt, pin)?;
+        Ok(multibase::encode(
+            multibase::Base::Base58Btc,
+            key.sign(message).to_bytes(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_files_and_token_files_open_as_signers() {
+        let dir = tempfile::tempdir().unwrap();
+        let identity_path = dir.path().join("identity.json");
+        let identity =
+            Identity::new("alice".to_string(), None, "member".to_string(), None).unwrap();
+        keystore::save_identity(&identity_path, &identity, None).unwrap();
+
+        let signer = open_signer(&identity_path).unwrap();
+        assert_eq!(signer.did(), identity.did);
+        assert!(signer.public_identity().private_key_bytes.is_none());
+        let signature = signer.sign(b"hello").unwrap();
+        identity.verify(b"hello", &signature).unwrap();
+
+        let token_path = dir.path().join("token.json");
+        let file = TokenFile {
+            identity: Identity::from_public_key(&identity.public_key_bytes, "alice", "member"),
+            token: TokenKey::Fido2 {
+                rp_id: "icn-covm".to_string(),
+                credential_id: "00".to_string(),
+                salt: "00".to_string(),
+            },
+        };
+        assert_eq!(file.identity.did, identity.did);
+        save_token_file(&token_path, &file).unwrap();
+        assert!(keystore::is_token_file(&token_path).unwrap());
+        assert_eq!(
+            keystore::inspect_identity(&token_path).unwrap().token,
+            Some("fido2".to_string())
+        );
+        assert!(matches!(
+            keystore::load_identity(&token_path, None),
+            Err(KeystoreError::HardwareKey(_))
+        ));
+        if !cfg!(feature = "fido2") {
+            assert!(matches!(
+                TokenSigner::new(&token_path, file, None),
+                Err(SignerError::Unsupported { kind: "fido2", .. })
+            ));
+        }
+    }
+}
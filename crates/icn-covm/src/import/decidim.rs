@@ -0,0 +1,209 @@
+//! Decidim proposal and comment exports
+//!
+//! Decidim exports a component's proposals and their comments as separate
+//! JSON or CSV files; pass both to one import. CSV exports flatten nested
+//! fields into columns such as `title/en` and `author/id`, and translated
+//! fields use the English text when there is one.
+//!
+//! - Proposals become proposals `decidim-proposal-{id}`. Accepted proposals
+//!   are executed, rejected ones rejected, withdrawn ones expired, those
+//!   being evaluated in voting, and unanswered ones open for feedback. The
+//!   category and scope names become labels.
+//! - Comments on proposals, and replies to them, become comments. Their
+//!   alignment becomes an `in_favor` or `against` tag.
+//! - Authors are named `decidim:user-{id}`.
+//!
+//! Decidim exports only count the supports a proposal received, not who
+//! gave them, so no votes are imported. Comments on other kinds of
+//! resources are skipped.
+
+use super::{lookup, record, text, Mapped, Row, SOURCE_REF};
+use std::collections::HashMap;
+
+const SOURCE: &str = "decidim";
+
+fn author(row: &Row) -> Option<String> {
+    lookup(row, &["author", "id"]).map(|id| format!("decidim:user-{}", id))
+}
+
+/// Whether a `commentable_type` names a Decidim proposal
+fn is_proposal(commentable_type: &str) -> bool {
+    commentable_type.ends_with("::Proposal")
+}
+
+/// Proposal ID at the end of a proposal URL, e.g. `.../proposals/12`
+fn proposal_in_url(url: &str) -> Option<String> {
+    let (rest, id) = url.trim_end_matches('/').rsplit_once('/')?;
+    (rest.ends_with("/proposals") && id.chars().all(|c| c.is_ascii_digit())).then(|| id.to_string())
+}
+
+/// Map the records of Decidim proposal and comment exports
+pub fn map_rows(rows: &[Row]) -> Result<Mapped, String> {
+    let mut mapped = Mapped::default();
+    let mut comments = Vec::new();
+    for (i, proposal) in rows.iter().enumerate() {
+        if lookup(proposal, &["commentable_type"]).is_some() {
+            comments.push(proposal);
+            continue;
+        }
+        let (Some(id), Some(title)) = (lookup(proposal, &["id"]), lookup(proposal, &["title"]))
+        else {
+            return Err(format!(
+                "Entry {} is neither a Decidim proposal with an id and title nor a comment",
+                i + 1
+            ));
+        };
+        let state = match lookup(proposal, &["state"]).as_deref() {
+            Some("accepted") => "executed",
+            Some("rejected") => "rejected",
+            Some("withdrawn") => "expired",
+            Some("evaluating") => "voting",
+            _ => "feedback",
+        };
+        let labels: Vec<String> = [
+            lookup(proposal, &["category", "name"]),
+            lookup(proposal, &["scope", "name"]),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        mapped.rows.push(record(vec![
+            ("kind", Some("proposal".to_string())),
+            ("id", Some(format!("decidim-proposal-{}", id))),
+            ("title", Some(title)),
+            ("description", lookup(proposal, &["body"])),
+            ("quorum", Some("0".to_string())),
+            ("threshold", Some("0.5".to_string())),
+            ("state", Some(state.to_string())),
+            ("creator", author(proposal)),
+            (
+                "created_at",
+                lookup(proposal, &["published_at"]).or_else(|| lookup(proposal, &["created_at"])),
+            ),
+            ("labels", Some(labels.join(";")).filter(|l| !l.is_empty())),
+            ("imported_from", Some(SOURCE.to_string())),
+            (SOURCE_REF, Some(format!("Decidim proposal {}", id))),
+        ]));
+    }
+
+    // Replies name their parent comment rather than the proposal, so find
+    // each comment's proposal through its parents
+    let parents: HashMap<String, (String, String)> = comments
+        .iter()
+        .filter_map(|comment| {
+            Some((
+                lookup(comment, &["id"])?,
+                (
+                    lookup(comment, &["commentable_type"])?,
+                    lookup(comment, &["commentable_id"])?,
+                ),
+            ))
+        })
+        .collect();
+    let proposal_of = |comment: &Row| -> Option<String> {
+        let mut parent = (
+            lookup(comment, &["commentable_type"])?,
+            lookup(comment, &["commentable_id"])?,
+        );
+        for _ in 0..=parents.len() {
+            if is_proposal(&parent.0) {
+                return Some(parent.1);
+            }
+            parent = parents.get(&parent.1)?.clone();
+        }
+        None
+    };
+
+    for comment in comments {
+        let id = lookup(comment, &["id"]).unwrap_or_default();
+        let Some(proposal_id) = proposal_of(comment).or_else(|| {
+            lookup(comment, &["root_commentable_url"]).and_then(|url| proposal_in_url(&url))
+        }) else {
+            mapped
+                .skipped
+                .push(format!("Decidim comment {}: not on a proposal", id));
+            continue;
+        };
+        let commentable_type = lookup(comment, &["commentable_type"]).unwrap_or_default();
+        let reply_to = (!is_proposal(&commentable_type))
+            .then(|| lookup(comment, &["commentable_id"]))
+            .flatten()
+            .filter(|parent| parents.contains_key(parent))
+            .map(|parent| format!("decidim-comment-{}", parent));
+        let tag = match text(comment, "alignment").as_deref() {
+            Some("1") => Some("in_favor".to_string()),
+            Some("-1") => Some("against".to_string()),
+            _ => None,
+        };
+        mapped.rows.push(record(vec![
+            ("kind", Some("comment".to_string())),
+            ("id", Some(format!("decidim-comment-{}", id))),
+            (
+                "proposal_id",
+                Some(format!("decidim-proposal-{}", proposal_id)),
+            ),
+            ("author", author(comment)),
+            ("content", lookup(comment, &["body"])),
+            ("created_at", lookup(comment, &["created_at"])),
+            ("reply_to", reply_to),
+            ("tags", tag),
+            ("imported_from", Some(SOURCE.to_string())),
+            (SOURCE_REF, Some(format!("Decidim comment {}", id))),
+        ]));
+    }
+
+    Ok(mapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_map_proposals_and_threaded_comments() {
+        let export: Vec<Row> = [
+            json!({ "id": 12, "title": { "en": "Solar roof", "ca": "Teulada solar" },
+                    "body": { "en": "Install panels" }, "state": "accepted",
+                    "published_at": "2022-05-01T09:00:00Z", "author": { "id": 3 },
+                    "category": { "name": { "en": "Energy" } } }),
+            // A CSV export row, flattened and read as strings
+            json!({ "id": "13", "title/en": "Bike shed", "state": "", "author/id": "4" }),
+            json!({ "id": 40, "commentable_type": "Decidim::Proposals::Proposal",
+                    "commentable_id": 12, "body": { "en": "Yes please" }, "alignment": 1,
+                    "author": { "id": 4 }, "created_at": "2022-05-02T09:00:00Z" }),
+            json!({ "id": 41, "commentable_type": "Decidim::Comments::Comment",
+                    "commentable_id": 40, "body": { "en": "Agreed" }, "alignment": 0 }),
+            json!({ "id": 42, "commentable_type": "Decidim::Debates::Debate",
+                    "commentable_id": 1, "body": { "en": "Off topic" } }),
+        ]
+        .iter()
+        .map(|row| row.as_object().unwrap().clone())
+        .collect();
+
+        let mapped = map_rows(&export).unwrap();
+        assert_eq!(mapped.rows.len(), 4);
+        let solar = &mapped.rows[0];
+        assert_eq!(text(solar, "title").as_deref(), Some("Solar roof"));
+        assert_eq!(text(solar, "state").as_deref(), Some("executed"));
+        assert_eq!(text(solar, "labels").as_deref(), Some("Energy"));
+        assert_eq!(text(solar, "creator").as_deref(), Some("decidim:user-3"));
+        assert_eq!(text(&mapped.rows[1], "state").as_deref(), Some("feedback"));
+
+        let reply = &mapped.rows[3];
+        assert_eq!(
+            text(reply, "proposal_id").as_deref(),
+            Some("decidim-proposal-12")
+        );
+        assert_eq!(
+            text(reply, "reply_to").as_deref(),
+            Some("decidim-comment-40")
+        );
+        assert_eq!(text(&mapped.rows[2], "tags").as_deref(), Some("in_favor"));
+        assert_eq!(mapped.skipped, ["Decidim comment 42: not on a proposal"]);
+        assert_eq!(
+            proposal_in_url("https://decidim.example/processes/p/f/3/proposals/12"),
+            Some("12".to_string())
+        );
+    }
+}
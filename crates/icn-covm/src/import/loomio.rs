@@ -0,0 +1,293 @@
+//! Loomio group exports
+//!
+//! A Loomio group export has one `{"table": ..., "record": ...}` object per
+//! line. These tables are read:
+//!
+//! - `polls` become proposals `loomio-poll-{id}`. Closed polls are executed
+//!   when more stances agreed than disagreed, and rejected otherwise; open
+//!   polls are left in voting. `quorum_pct` is kept when set.
+//! - `stances`, with `poll_options` and `stance_choices` (or the stance's
+//!   `option_scores`), become votes when the single option chosen reads as
+//!   yes, no, or abstain. Only the latest, unrevoked stance counts.
+//! - `comments` become comments on the first poll of their discussion.
+//! - `users` name participants `loomio:{username}`.
+//!
+//! Deleted polls and comments, anonymous stances, and comments in
+//! discussions without a poll are skipped.
+
+use super::{record, text, vote_for_option, Mapped, Row, SOURCE_REF};
+use serde_json::Value;
+use std::collections::HashMap;
+
+const SOURCE: &str = "loomio";
+
+/// Map the records of a Loomio group export
+pub fn map_rows(rows: &[Row]) -> Result<Mapped, String> {
+    let mut tables: HashMap<String, Vec<&Row>> = HashMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        match (
+            text(row, "table"),
+            row.get("record").and_then(Value::as_object),
+        ) {
+            (Some(table), Some(record)) => tables.entry(table).or_default().push(record),
+            _ => {
+                return Err(format!(
+                    "Entry {} is not a Loomio export record with a table and a record",
+                    i + 1
+                ))
+            }
+        }
+    }
+    let table = |name: &str| tables.get(name).map(Vec::as_slice).unwrap_or_default();
+
+    let users: HashMap<String, String> = table("users")
+        .iter()
+        .filter_map(|user| {
+            let id = text(user, "id")?;
+            let name = text(user, "username").unwrap_or_else(|| format!("user-{}", id));
+            Some((id, format!("loomio:{}", name)))
+        })
+        .collect();
+    let member = |id: Option<String>| {
+        id.map(|id| {
+            users
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| format!("loomio:user-{}", id))
+        })
+    };
+    let options: HashMap<String, String> = table("poll_options")
+        .iter()
+        .filter_map(|option| Some((text(option, "id")?, text(option, "name")?)))
+        .collect();
+    let mut choices: HashMap<String, Vec<String>> = HashMap::new();
+    for choice in table("stance_choices") {
+        let score = choice.get("score").and_then(Value::as_f64).unwrap_or(1.0);
+        if let (Some(stance), Some(option)) =
+            (text(choice, "stance_id"), text(choice, "poll_option_id"))
+        {
+            if score > 0.0 {
+                choices.entry(stance).or_default().push(option);
+            }
+        }
+    }
+    let mut stances: HashMap<String, Vec<&Row>> = HashMap::new();
+    for stance in table("stances") {
+        if let Some(poll) = text(stance, "poll_id") {
+            stances.entry(poll).or_default().push(stance);
+        }
+    }
+
+    let mut mapped = Mapped::default();
+    let mut discussion_polls: HashMap<String, String> = HashMap::new();
+    for poll in table("polls") {
+        let Some(poll_id) = text(poll, "id") else {
+            mapped.skipped.push("Loomio poll without an id".to_string());
+            continue;
+        };
+        if text(poll, "discarded_at").is_some() {
+            mapped
+                .skipped
+                .push(format!("Loomio poll {}: deleted", poll_id));
+            continue;
+        }
+        let proposal_id = format!("loomio-poll-{}", poll_id);
+        if let Some(discussion) = text(poll, "discussion_id") {
+            discussion_polls
+                .entry(discussion)
+                .or_insert_with(|| proposal_id.clone());
+        }
+
+        let (mut agreed, mut disagreed) = (0, 0);
+        let mut votes = Vec::new();
+        for stance in stances.get(&poll_id).map(Vec::as_slice).unwrap_or_default() {
+            let stance_id = text(stance, "id").unwrap_or_default();
+            if stance.get("latest") == Some(&Value::Bool(false))
+                || text(stance, "revoked_at").is_some()
+                || text(stance, "cast_at").is_none()
+            {
+                continue;
+            }
+            let Some(voter) = member(text(stance, "participant_id")) else {
+                mapped
+                    .skipped
+                    .push(format!("Loomio stance {}: anonymous", stance_id));
+                continue;
+            };
+            let mut chosen = choices.get(&stance_id).cloned().unwrap_or_default();
+            if let Some(Value::Object(scores)) = stance.get("option_scores") {
+                chosen.extend(
+                    scores
+                        .iter()
+                        .filter(|(_, score)| score.as_f64().unwrap_or(0.0) > 0.0)
+                        .map(|(option, _)| option.clone()),
+                );
+            }
+            let names: Vec<&str> = chosen
+                .iter()
+                .filter_map(|option| options.get(option))
+                .map(String::as_str)
+                .collect();
+            let Some(vote) = (match names.as_slice() {
+                [name] => vote_for_option(name),
+                _ => None,
+            }) else {
+                mapped.skipped.push(format!(
+                    "Loomio stance {}: {:?} is not a single yes, no, or abstain",
+                    stance_id, names
+                ));
+                continue;
+            };
+            match vote {
+                "yes" => agreed += 1,
+                "no" => disagreed += 1,
+                _ => {}
+            }
+            votes.push(record(vec![
+                ("kind", Some("vote".to_string())),
+                ("proposal_id", Some(proposal_id.clone())),
+                ("voter", Some(voter)),
+                ("vote", Some(vote.to_string())),
+                ("cast_at", text(stance, "cast_at")),
+                ("imported_from", Some(SOURCE.to_string())),
+                (SOURCE_REF, Some(format!("Loomio stance {}", stance_id))),
+            ]));
+        }
+
+        let state = if text(poll, "closed_at").is_none() {
+            "voting"
+        } else if agreed > disagreed {
+            "executed"
+        } else {
+            "rejected"
+        };
+        let quorum = poll
+            .get("quorum_pct")
+            .and_then(Value::as_f64)
+            .map(|pct| (pct / 100.0).clamp(0.0, 1.0))
+            .unwrap_or(0.0);
+        mapped.rows.push(record(vec![
+            ("kind", Some("proposal".to_string())),
+            ("id", Some(proposal_id)),
+            ("title", text(poll, "title")),
+            ("description", text(poll, "details")),
+            ("quorum", Some(quorum.to_string())),
+            ("threshold", Some("0.5".to_string())),
+            ("state", Some(state.to_string())),
+            ("creator", member(text(poll, "author_id"))),
+            ("created_at", text(poll, "created_at")),
+            ("labels", text(poll, "poll_type")),
+            ("imported_from", Some(SOURCE.to_string())),
+            (SOURCE_REF, Some(format!("Loomio poll {}", poll_id))),
+        ]));
+        mapped.rows.extend(votes);
+    }
+
+    for comment in table("comments") {
+        let comment_id = text(comment, "id").unwrap_or_default();
+        if text(comment, "discarded_at").is_some() {
+            mapped
+                .skipped
+                .push(format!("Loomio comment {}: deleted", comment_id));
+            continue;
+        }
+        let Some(proposal_id) = text(comment, "discussion_id")
+            .and_then(|discussion| discussion_polls.get(&discussion).cloned())
+        else {
+            mapped.skipped.push(format!(
+                "Loomio comment {}: its discussion has no poll",
+                comment_id
+            ));
+            continue;
+        };
+        let reply_to = match text(comment, "parent_type").as_deref() {
+            Some("Comment") => {
+                text(comment, "parent_id").map(|id| format!("loomio-comment-{}", id))
+            }
+            _ => None,
+        };
+        mapped.rows.push(record(vec![
+            ("kind", Some("comment".to_string())),
+            ("id", Some(format!("loomio-comment-{}", comment_id))),
+            ("proposal_id", Some(proposal_id)),
+            (
+                "author",
+                member(text(comment, "user_id").or_else(|| text(comment, "author_id"))),
+            ),
+            ("content", text(comment, "body")),
+            ("created_at", text(comment, "created_at")),
+            ("reply_to", reply_to),
+            ("imported_from", Some(SOURCE.to_string())),
+            (SOURCE_REF, Some(format!("Loomio comment {}", comment_id))),
+        ]));
+    }
+
+    Ok(mapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows(records: Value) -> Vec<Row> {
+        records
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r.as_object().unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_map_group_export() {
+        let export = rows(json!([
+            { "table": "users", "record": { "id": 1, "username": "ana" } },
+            { "table": "polls", "record": { "id": 10, "title": "Buy a van", "poll_type": "proposal",
+                "discussion_id": 5, "author_id": 1, "created_at": "2023-03-01T10:00:00Z",
+                "closed_at": "2023-03-08T10:00:00Z" } },
+            { "table": "poll_options", "record": { "id": 100, "poll_id": 10, "name": "agree" } },
+            { "table": "poll_options", "record": { "id": 101, "poll_id": 10, "name": "block" } },
+            { "table": "stances", "record": { "id": 20, "poll_id": 10, "participant_id": 1,
+                "cast_at": "2023-03-02T10:00:00Z", "latest": true } },
+            { "table": "stances", "record": { "id": 21, "poll_id": 10, "participant_id": 2,
+                "cast_at": "2023-03-02T11:00:00Z", "option_scores": { "101": 1 } } },
+            { "table": "stances", "record": { "id": 22, "poll_id": 10, "participant_id": 1,
+                "cast_at": "2023-03-01T11:00:00Z", "latest": false } },
+            { "table": "stance_choices", "record": { "stance_id": 20, "poll_option_id": 100 } },
+            { "table": "comments", "record": { "id": 30, "discussion_id": 5, "user_id": 1,
+                "body": "Let's do it", "created_at": "2023-03-01T12:00:00Z" } },
+            { "table": "comments", "record": { "id": 31, "discussion_id": 5, "user_id": 2,
+                "body": "Too pricey", "parent_type": "Comment", "parent_id": 30 } },
+            { "table": "comments", "record": { "id": 32, "discussion_id": 6, "user_id": 2,
+                "body": "Hi" } }
+        ]));
+
+        let mapped = map_rows(&export).unwrap();
+        let kinds: Vec<String> = mapped
+            .rows
+            .iter()
+            .map(|r| text(r, "kind").unwrap())
+            .collect();
+        assert_eq!(kinds, ["proposal", "vote", "vote", "comment", "comment"]);
+
+        let proposal = &mapped.rows[0];
+        assert_eq!(text(proposal, "id").as_deref(), Some("loomio-poll-10"));
+        assert_eq!(text(proposal, "state").as_deref(), Some("rejected"));
+        assert_eq!(text(proposal, "creator").as_deref(), Some("loomio:ana"));
+        assert_eq!(text(&mapped.rows[1], "vote").as_deref(), Some("yes"));
+        assert_eq!(
+            text(&mapped.rows[2], "voter").as_deref(),
+            Some("loomio:user-2")
+        );
+        assert_eq!(text(&mapped.rows[2], "vote").as_deref(), Some("no"));
+        assert_eq!(
+            text(&mapped.rows[4], "reply_to").as_deref(),
+            Some("loomio-comment-30")
+        );
+        assert_eq!(mapped.skipped.len(), 1);
+        assert!(mapped.skipped[0].contains("comment 32"));
+
+        assert!(map_rows(&rows(json!([{ "kind": "proposal" }]))).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Mappers from other governance platforms' exports
+//!
+//! Cooperatives moving to icn-covm usually have years of decisions in
+//! Loomio or Decidim. These mappers turn those platforms' exports into the
+//! records read by `proposal import` (proposals, votes, and comments, see
+//! `cli::proposal_import`), so they are checked and stored exactly like a
+//! hand-written import file:
+//!
+//! - `loomio`: a Loomio group export (`{"table": ..., "record": ...}` lines)
+//! - `decidim`: Decidim proposal and comment exports, as JSON or CSV
+//!
+//! Every mapped record carries `imported_from` with the platform name, which
+//! the importer stores on the proposal, vote, or comment to flag it as
+//! historical, and `source_ref` naming the record it came from, which is
+//! used in error reports. Records that have no icn-covm equivalent are left
+//! out and listed in `Mapped::skipped`.
+
+pub mod decidim;
+pub mod loomio;
+
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// One record, as read from an import file
+pub type Row = Map<String, Value>;
+
+/// Column naming the source record a mapped record came from
+pub const SOURCE_REF: &str = "source_ref";
+
+/// Format of the records being imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    /// icn-covm's own import format
+    Covm,
+    Loomio,
+    Decidim,
+}
+
+impl ImportSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportSource::Covm => "covm",
+            ImportSource::Loomio => "loomio",
+            ImportSource::Decidim => "decidim",
+        }
+    }
+
+    /// Turn records of this source into `proposal import` records
+    pub fn map_rows(&self, rows: Vec<Row>) -> Result<Mapped, String> {
+        match self {
+            ImportSource::Covm => Ok(Mapped {
+                rows,
+                skipped: Vec::new(),
+            }),
+            ImportSource::Loomio => loomio::map_rows(&rows),
+            ImportSource::Decidim => decidim::map_rows(&rows),
+        }
+    }
+}
+
+impl fmt::Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ImportSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "covm" | "icn-covm" => Ok(ImportSource::Covm),
+            "loomio" => Ok(ImportSource::Loomio),
+            "decidim" => Ok(ImportSource::Decidim),
+            other => Err(format!(
+                "Unknown import source '{}', expected covm, loomio or decidim",
+                other
+            )),
+        }
+    }
+}
+
+/// Records mapped from a platform export
+#[derive(Debug, Default)]
+pub struct Mapped {
+    /// Records in the format read by `proposal import`
+    pub rows: Vec<Row>,
+    /// Source records left out, with the reason
+    pub skipped: Vec<String>,
+}
+
+/// Text of a string or number field; empty strings count as missing
+pub(crate) fn text(row: &Row, name: &str) -> Option<String> {
+    value_text(row.get(name)?)
+}
+
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if s.trim().is_empty() => None,
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        // Translated fields: prefer English, else the first translation
+        Value::Object(translations) => translations
+            .get("en")
+            .and_then(value_text)
+            .or_else(|| translations.values().find_map(value_text)),
+        _ => None,
+    }
+}
+
+/// Text at a nested `path`, in a JSON record or a CSV record whose header
+/// flattens the path with `/`, e.g. `author/name` or `title/en`
+pub(crate) fn lookup(row: &Row, path: &[&str]) -> Option<String> {
+    let (first, rest) = path.split_first()?;
+    let mut value = row.get(*first);
+    for key in rest {
+        value = value.and_then(|v| v.get(*key));
+    }
+    if let Some(found) = value.and_then(value_text) {
+        return Some(found);
+    }
+
+    let flat = path.join("/");
+    if let Some(value) = text(row, &flat) {
+        return Some(value);
+    }
+    // A translated field flattened into one column per locale
+    let prefix = format!("{}/", flat);
+    text(row, &format!("{}en", prefix)).or_else(|| {
+        let mut keys: Vec<&String> = row.keys().filter(|k| k.starts_with(&prefix)).collect();
+        keys.sort();
+        keys.into_iter().find_map(|key| text(row, key))
+    })
+}
+
+/// Build an import record from `(column, value)` pairs, leaving out
+/// missing values
+pub(crate) fn record(fields: Vec<(&str, Option<String>)>) -> Row {
+    fields
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|v| (name.to_string(), Value::String(v))))
+        .collect()
+}
+
+/// Vote for an option name, when it reads as yes, no, or abstain
+pub(crate) fn vote_for_option(name: &str) -> Option<&'static str> {
+    match name.trim().to_lowercase().as_str() {
+        "yes" | "agree" | "consent" | "approve" | "in favour" | "in favor" => Some("yes"),
+        "no" | "disagree" | "block" | "object" | "objection" | "reject" | "against" => Some("no"),
+        "abstain" | "stand aside" | "stand_aside" => Some("abstain"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_lookup_reads_nested_and_flattened_fields() {
+        let nested =
+            json!({ "title": { "ca": "Pressupost", "en": "Budget" }, "author": { "id": 7 } });
+        let nested = nested.as_object().unwrap();
+        assert_eq!(lookup(nested, &["title"]).as_deref(), Some("Budget"));
+        assert_eq!(lookup(nested, &["author", "id"]).as_deref(), Some("7"));
+
+        let flat = json!({ "title/ca": "Pressupost", "author/id": "7" });
+        let flat = flat.as_object().unwrap();
+        assert_eq!(lookup(flat, &["title"]).as_deref(), Some("Pressupost"));
+        assert_eq!(lookup(flat, &["author", "id"]).as_deref(), Some("7"));
+        assert_eq!(lookup(flat, &["body"]), None);
+
+        assert_eq!("Loomio".parse::<ImportSource>(), Ok(ImportSource::Loomio));
+        assert!("slack".parse::<ImportSource>().is_err());
+    }
+}
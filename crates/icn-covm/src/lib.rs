@@ -14,12 +14,21 @@
 //! This crate is intended to be used in contexts where multiple parties
 //! need to cooperatively manage resources using programmatic governance.
 
+pub mod audit;
 pub mod bytecode;
+pub mod clock;
 pub mod compiler;
+pub mod config;
+pub mod coverage;
+pub mod events;
 pub mod federation;
 pub mod governance;
+pub mod http;
 pub mod identity;
+pub mod import;
+pub mod perf;
 pub mod storage;
+pub mod telemetry;
 pub mod typed;
 pub mod vm;
 
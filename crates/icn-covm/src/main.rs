@@ -1,31 +1,60 @@
 // pub mod storage;
 
 use icn_covm::api;
+use icn_covm::api::oidc::OidcProvider;
+use icn_covm::api::replica::ReplicaConfig;
+use icn_covm::audit::{self, AuditEntry, AuditOutcome, AuditSource};
 use icn_covm::bytecode::{BytecodeCompiler, BytecodeInterpreter};
-use icn_covm::cli::federation::{federation_command, handle_federation_command};
+use icn_covm::cli::audit::{audit_command, handle_audit_command};
+use icn_covm::cli::bench::{self, bench_command, handle_bench_command, BenchMode, BenchOptions};
+use icn_covm::cli::coverage::{coverage_command, handle_coverage_command};
+use icn_covm::cli::dashboard::{dashboard_command, run_dashboard};
+use icn_covm::cli::debug;
+use icn_covm::cli::dry_run::{self, dry_run_arg, DryRun};
+use icn_covm::cli::federation::{
+    federation_command, handle_federation_command, handle_peers_command,
+};
+use icn_covm::cli::keys::{handle_keys_command, keys_command};
+use icn_covm::cli::ledger::{handle_ledger_command, ledger_command};
+use icn_covm::cli::notifications::{handle_notifications_command, notifications_command};
+use icn_covm::cli::output::{self, output_arg, print_output, OutputFormat};
 use icn_covm::cli::proposal::{handle_proposal_command, proposal_command};
 use icn_covm::cli::proposal_demo::run_proposal_demo;
+use icn_covm::cli::repl::{BlockReader, ReplSession};
+use icn_covm::cli::session::{
+    handle_session_command, session_auth_context, session_subcommands, SESSION_COMMANDS,
+};
+use icn_covm::cli::storage::{
+    handle_storage_command, handle_usage_command, storage_usage_subcommand,
+    storage_write_subcommands, STORAGE_WRITE_COMMANDS,
+};
+use icn_covm::cli::template::{handle_template_command, template_command};
+use icn_covm::cli::verify_vote::{handle_verify_vote_command, verify_vote_command};
 use icn_covm::compiler::{parse_dsl, parse_dsl_with_stdlib, CompilerError, LifecycleConfig};
-use icn_covm::events::LogFormat;
+use icn_covm::config::{Config, ConfigError, IDENTITY_KEY_ENV};
+use icn_covm::events::{self, LogFormat};
 use icn_covm::federation::messages::{ProposalScope, ProposalStatus, VotingModel};
-use icn_covm::federation::{NetworkNode, NodeConfig};
-use icn_covm::identity::Identity;
+use icn_covm::federation::{NetworkNode, NodeConfig, NodeHandle};
+use icn_covm::identity::{keystore, signer, Identity};
 use icn_covm::storage::auth::AuthContext;
 use icn_covm::storage::implementations::file_storage::FileStorage;
 use icn_covm::storage::implementations::in_memory::InMemoryStorage;
-use icn_covm::storage::traits::StorageBackend;
+use icn_covm::storage::implementations::sled_storage::SledStorage;
+use icn_covm::storage::traits::{Storage, StorageBackend};
 use icn_covm::storage::utils::now_with_default;
-use icn_covm::vm::{MemoryScope, StackOps, VMError, VM};
+use icn_covm::telemetry;
+use icn_covm::vm::{MemoryScope, Op, StackOps, VMError, VM};
 
 use clap::{Arg, ArgAction, Command};
-use log::{debug, error, info, warn};
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::time::Instant;
 use thiserror::Error;
+use tracing::{debug, error, info, info_span, instrument, warn, Instrument};
 
 #[derive(Debug, Error)]
 enum AppError {
@@ -44,6 +73,9 @@ enum AppError {
     #[error("Federation error: {0}")]
     Federation(String),
 
+    #[error("Config error: {0}")]
+    Config(#[from] ConfigError),
+
     #[error("{0}")]
     Other(String),
 }
@@ -68,13 +100,6 @@ impl From<Box<dyn Error>> for AppError {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    // Initialize logging
-    env_logger::init();
-
-    // Default storage settings
-    let default_storage_backend = "memory";
-    let default_storage_path = "./storage";
-
     // Parse command line arguments
     let api_cmd = Command::new("api")
         .about("Start the API server for web/mobile access")
@@ -83,15 +108,68 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .long("port")
                 .short('p')
                 .value_name("PORT")
-                .help("Port to listen on (default: 3030)")
-                .value_parser(clap::value_parser!(u16))
-                .default_value("3030"),
+                .help("Port to listen on (default: api.port, or 3030)")
+                .value_parser(clap::value_parser!(u16)),
+        )
+        .arg(
+            Arg::new("grpc-port")
+                .long("grpc-port")
+                .value_name("PORT")
+                .help("Also serve the gRPC API on this port; needs a build with the grpc feature (default: api.grpc_port)")
+                .value_parser(clap::value_parser!(u16)),
+        )
+        .arg(
+            Arg::new("federation-port")
+                .long("federation-port")
+                .value_name("PORT")
+                .help("Run a federation node on this port, managed through /api/v1/federation (default: federation.port when federation.enabled)")
+                .value_parser(clap::value_parser!(u16)),
+        )
+        .arg(
+            Arg::new("bootstrap-nodes")
+                .long("bootstrap-nodes")
+                .value_name("MULTIADDR")
+                .help("Multiaddresses of bootstrap nodes (can be used multiple times)")
+                .value_parser(clap::value_parser!(libp2p::Multiaddr))
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("node-name")
+                .long("node-name")
+                .value_name("NAME")
+                .help("Human-readable name for the federation node (default: federation.node_name)"),
+        )
+        .arg(
+            Arg::new("replica-of")
+                .long("replica-of")
+                .value_name("URL")
+                .help("Serve reads from a copy of the API server at this http:// URL and forward writes to it; both need the same ICN_JWT_SECRET (default: api.replica_of)"),
+        )
+        .arg(
+            Arg::new("storage-backend")
+                .long("storage-backend")
+                .value_name("TYPE")
+                .help("Storage backend type, memory or sled (default: storage.backend)"),
+        )
+        .arg(
+            Arg::new("storage-path")
+                .long("storage-path")
+                .value_name("PATH")
+                .help("Path for the sled storage database (default: storage.path)"),
         );
 
     let matches = Command::new("icn-covm")
         .version("0.7.0")
         .author("Intercooperative Network")
         .about("Secure stack-based virtual machine with governance-inspired opcodes")
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .help("Config file; settings are overridden by ICN_* environment variables and flags (default: ./icn-covm.toml if present, or $ICN_CONFIG)")
+                .global(true),
+        )
+        .arg(output_arg())
         .subcommand(
             Command::new("run")
                 .about("Run a program")
@@ -147,36 +225,33 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .arg(
                     Arg::new("benchmark")
                         .long("benchmark")
-                        .help("Run both AST and bytecode execution and compare performance")
+                        .help("Compare AST and bytecode execution times; see `icn-covm bench` for more options")
                         .action(ArgAction::SetTrue),
                 )
                 .arg(
                     Arg::new("storage-backend")
                         .long("storage-backend")
                         .value_name("TYPE")
-                        .help("Storage backend type (memory or file)")
-                        .default_value("memory"),
+                        .help("Storage backend type, memory, file or sled (default: storage.backend)"),
                 )
                 .arg(
                     Arg::new("storage-path")
                         .long("storage-path")
                         .value_name("PATH")
-                        .help("Path for file storage backend")
-                        .default_value("./storage"),
+                        .help("Path for the file or sled storage backend (default: storage.path)"),
                 )
                 // Federation-related options
                 .arg(
                     Arg::new("enable-federation")
                         .long("enable-federation")
-                        .help("Enable federation support")
+                        .help("Enable federation support (default: federation.enabled)")
                         .action(ArgAction::SetTrue),
                 )
                 .arg(
                     Arg::new("federation-port")
                         .long("federation-port")
                         .value_name("PORT")
-                        .help("Port number for federation listening")
-                        .default_value("0"),
+                        .help("Port number for federation listening (default: federation.port)"),
                 )
                 .arg(
                     Arg::new("bootstrap-nodes")
@@ -189,8 +264,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     Arg::new("node-name")
                         .long("node-name")
                         .value_name("NAME")
-                        .help("Human-readable name for this node")
-                        .default_value("icn-covm-node"),
+                        .help("Human-readable name for this node (default: federation.node_name)"),
                 )
                 .arg(
                     Arg::new("capabilities")
@@ -205,6 +279,18 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         .help("Run the program without modifying persistent storage")
                         .action(ArgAction::SetTrue),
                 )
+                .arg(
+                    dry_run_arg()
+                        .help("Run the program on a fork and print the token operations and storage events it would commit")
+                        .conflicts_with_all(["bytecode", "benchmark", "interactive"]),
+                )
+                .arg(
+                    Arg::new("debug")
+                        .long("debug")
+                        .help("Step through the program, with breakpoints and stack and memory inspection")
+                        .conflicts_with_all(["bytecode", "benchmark", "interactive", "dry-run"])
+                        .action(ArgAction::SetTrue),
+                )
                 .arg(
                     Arg::new("trace")
                         .long("trace")
@@ -254,29 +340,37 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                 .help("Output file to save the registered identity to"),
                         ),
                 )
+                .subcommands(session_subcommands())
         )
         .subcommand(proposal_command())
+        .subcommand(template_command())
+        .subcommand(notifications_command())
+        .subcommand(bench_command())
+        .subcommand(coverage_command())
         .subcommand(federation_command())
+        .subcommand(ledger_command())
+        .subcommand(keys_command())
+        .subcommand(audit_command())
+        .subcommand(verify_vote_command())
+        .subcommand(dashboard_command())
         .subcommand(
             Command::new("proposal-demo")
                 .about("Run a demo of the proposal lifecycle")
         )
         .subcommand(
             Command::new("storage")
-                .about("Storage inspection commands")
+                .about("Storage inspection and repair commands")
                 .arg(
                     Arg::new("storage-backend")
                         .long("storage-backend")
                         .value_name("TYPE")
-                        .help("Storage backend type (memory or file)")
-                        .default_value("file"),
+                        .help("Storage backend type, memory, file or sled (default: storage.backend)"),
                 )
                 .arg(
                     Arg::new("storage-path")
                         .long("storage-path")
                         .value_name("PATH")
-                        .help("Path for file storage backend")
-                        .default_value("./storage"),
+                        .help("Path for the file or sled storage backend (default: storage.path)"),
                 )
                 .subcommand(
                     Command::new("list-keys")
@@ -311,14 +405,57 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                 .index(2),
                         )
                 )
+                .subcommand(storage_usage_subcommand())
+                .subcommands(storage_write_subcommands())
         )
         .subcommand(
             Command::new("dag-trace")
                 .about("View the DAG ledger trace of proposal events")
         )
         .subcommand(api_cmd)
+        .subcommand(
+            Command::new("config")
+                .about("Print the configuration resolved from the config file and environment")
+        )
         .get_matches();
 
+    // Resolve the config file and environment layers; flags are applied per command
+    let config = match Config::load(matches.get_one::<String>("config").map(Path::new)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+    // Diagnostics go through tracing, set up by the [logging] section
+    // Held until main returns, so the last exported spans are flushed
+    let telemetry_guard = match telemetry::init(&config.logging, &config.telemetry) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = events::configure(&config.events) {
+        eprintln!("Error: Failed to set up event sinks: {}", e);
+        process::exit(1);
+    }
+    audit::chain::set_execution_log(config.audit.execution_log.clone());
+    icn_covm::storage::usage::configure(&config.storage.usage_alerts);
+    if let Err(e) = icn_covm::storage::ipfs::configure(config.attachments.ipfs.as_ref()) {
+        eprintln!("Error: Failed to set up IPFS attachment storage: {}", e);
+        process::exit(1);
+    }
+    output::set_output_format(
+        matches
+            .get_one::<OutputFormat>("output-format")
+            .copied()
+            .unwrap_or_default(),
+    );
+    let default_storage_backend = config.storage.backend.as_str();
+    let default_storage_path = config.storage.path.to_string_lossy().into_owned();
+    let default_storage_path = default_storage_path.as_str();
+
     // Handle subcommands
     let result: Result<(), AppError> = match matches.subcommand() {
         Some(("run", run_matches)) => {
@@ -344,28 +481,28 @@ async fn main() -> Result<(), Box<dyn Error>> {
             let use_stdlib = run_matches.get_flag("stdlib");
             let use_bytecode = run_matches.get_flag("bytecode");
 
-            // Use let bindings for default values to ensure they live long enough
-            let default_storage_backend = "memory".to_string();
-            let default_storage_path = "./storage".to_string();
-
             let storage_backend = run_matches
                 .get_one::<String>("storage-backend")
-                .unwrap_or(&default_storage_backend);
+                .map(String::as_str)
+                .unwrap_or(default_storage_backend);
             let storage_path = run_matches
                 .get_one::<String>("storage-path")
-                .unwrap_or(&default_storage_path);
+                .map(String::as_str)
+                .unwrap_or(default_storage_path);
 
             // Get federation configuration
-            let enable_federation = run_matches.get_flag("enable-federation");
-            let federation_port = run_matches
-                .get_one::<String>("federation-port")
-                .unwrap_or(&"0".to_string())
-                .parse::<u16>()
-                .map_err(|e| format!("Invalid federation port: {}", e))?;
+            let enable_federation =
+                run_matches.get_flag("enable-federation") || config.federation.enabled;
+            let federation_port = match run_matches.get_one::<String>("federation-port") {
+                Some(port) => port
+                    .parse::<u16>()
+                    .map_err(|e| format!("Invalid federation port: {}", e))?,
+                None => config.federation.port,
+            };
             let bootstrap_nodes = run_matches
                 .get_many::<String>("bootstrap-nodes")
                 .map(|values| values.map(|s| s.to_string()).collect::<Vec<String>>())
-                .unwrap_or_default()
+                .unwrap_or_else(|| config.federation.bootstrap_nodes.clone())
                 .iter()
                 .filter_map(|addr| match addr.parse::<libp2p::Multiaddr>() {
                     Ok(addr) => Some(addr),
@@ -377,28 +514,31 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .collect();
             let node_name = run_matches
                 .get_one::<String>("node-name")
-                .unwrap_or(&"unknown-node".to_string())
-                .to_string();
+                .cloned()
+                .unwrap_or_else(|| config.federation.node_name.clone());
             let capabilities = run_matches
                 .get_many::<String>("capabilities")
-                .unwrap_or_default()
-                .cloned()
-                .collect::<Vec<String>>();
+                .map(|values| values.cloned().collect::<Vec<String>>())
+                .unwrap_or_else(|| config.federation.capabilities.clone());
 
             let simulate = run_matches.get_flag("simulate");
             let trace = run_matches.get_flag("trace");
             let explain = run_matches.get_flag("explain");
             let verbose_storage_trace = run_matches.get_flag("verbose-storage-trace");
+            let debug = run_matches.get_flag("debug");
+            let dry_run = DryRun::requested(run_matches);
 
             if run_matches.get_flag("benchmark") {
-                run_benchmark(
-                    program_path,
-                    verbose,
-                    use_stdlib,
-                    params,
-                    storage_backend,
-                    storage_path,
-                )
+                let options = BenchOptions {
+                    modes: vec![BenchMode::Ast, BenchMode::Bytecode],
+                    warmup: 3,
+                    iterations: 20,
+                    parameters: params,
+                };
+                bench::load_program(Path::new(program_path), use_stdlib)
+                    .and_then(|ops| bench::run_bench(program_path, &ops, &options))
+                    .and_then(|report| print_output(&report, bench::print_report))
+                    .map_err(|e| e.to_string().into())
             } else if run_matches.get_flag("interactive") {
                 run_interactive(
                     verbose,
@@ -425,6 +565,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     bootstrap_nodes,
                     node_name,
                     capabilities,
+                    config.federation.peer_scores_path_or_default(),
                     simulate,
                     trace,
                     explain,
@@ -445,6 +586,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     trace,
                     explain,
                     verbose_storage_trace,
+                    debug,
+                    dry_run,
                 )
             }
         }
@@ -459,23 +602,76 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 let output_file = register_matches.get_one::<String>("output");
                 register_identity(id_file, id_type, output_file)
             }
+            Some((subcommand, session_matches)) if SESSION_COMMANDS.contains(&subcommand) => {
+                handle_session_command(subcommand, session_matches, &config)
+                    .map_err(|e| e.to_string().into())
+            }
             _ => Err("Unknown identity subcommand".into()),
         },
         Some(("proposal", sub_matches)) => {
-            let auth_context =
-                get_or_create_auth_context(default_storage_backend, default_storage_path)?;
+            let auth_context = get_or_create_auth_context(&config)?;
+            let storage_backend = sub_matches
+                .get_one::<String>("storage-backend")
+                .map(|s| s.as_str())
+                .unwrap_or(default_storage_backend);
+            let storage_path = sub_matches
+                .get_one::<String>("storage-path")
+                .map(|s| s.as_str())
+                .unwrap_or(default_storage_path);
+            if storage_backend == "sled" {
+                let vm = VM::with_storage_backend(open_sled_storage(storage_path)?);
+                run_proposal_command(vm, &config, sub_matches, &auth_context)
+            } else {
+                let storage = setup_storage(storage_backend, storage_path)?;
+                let vm = VM::with_storage_backend(storage);
+                run_proposal_command(vm, &config, sub_matches, &auth_context)
+            }
+        }
+        Some(("template", template_matches)) => {
+            let auth_context = get_or_create_auth_context(&config)?;
             let storage = setup_storage(default_storage_backend, default_storage_path)?;
             let mut vm = VM::with_storage_backend(storage);
-            handle_proposal_command(&mut vm, sub_matches, &auth_context).map_err(|e| e.into())
+            if let Some(dag_path) = &config.ledger.dag_path {
+                vm = vm.with_dag_path(dag_path.clone());
+            }
+            let result = handle_template_command(&mut vm, template_matches, &auth_context);
+            record_cli_audit(
+                &mut vm,
+                &auth_context,
+                "template",
+                template_matches,
+                &result,
+            );
+            result.map_err(|e| e.into())
+        }
+        Some(("notifications", notifications_matches)) => {
+            let auth_context = get_or_create_auth_context(&config)?;
+            let storage = setup_storage(default_storage_backend, default_storage_path)?;
+            let mut vm = VM::with_storage_backend(storage);
+            let result =
+                handle_notifications_command(&mut vm, notifications_matches, &auth_context);
+            record_cli_audit(
+                &mut vm,
+                &auth_context,
+                "notifications",
+                notifications_matches,
+                &result,
+            );
+            result.map_err(|e| e.into())
+        }
+        Some(("bench", bench_matches)) => {
+            handle_bench_command(bench_matches).map_err(|e| e.to_string().into())
         }
         Some(("proposal-demo", _)) => run_proposal_demo().map_err(|e| e.to_string().into()),
         Some(("storage", storage_matches)) => {
             let storage_backend = storage_matches
                 .get_one::<String>("storage-backend")
-                .ok_or_else(|| "Missing required argument: storage-backend")?;
+                .map(String::as_str)
+                .unwrap_or(default_storage_backend);
             let storage_path = storage_matches
                 .get_one::<String>("storage-path")
-                .ok_or_else(|| "Missing required argument: storage-path")?;
+                .map(String::as_str)
+                .unwrap_or(default_storage_path);
 
             match storage_matches.subcommand() {
                 Some(("list-keys", list_keys_matches)) => {
@@ -494,23 +690,86 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         .ok_or_else(|| "Missing required argument: key")?;
                     get_value_command(namespace, key, storage_backend, storage_path)
                 }
+                Some(("usage", usage_matches)) => {
+                    let namespace = usage_matches.get_one::<String>("namespace");
+                    let auth_context = create_admin_auth_context()?;
+                    let result = if storage_backend == "file" {
+                        let storage = FileStorage::new(storage_path).map_err(|e| {
+                            AppError::Other(format!("Failed to initialize file storage: {}", e))
+                        })?;
+                        handle_usage_command(&storage, &auth_context, namespace.map(String::as_str))
+                    } else {
+                        handle_usage_command(
+                            &InMemoryStorage::new(),
+                            &auth_context,
+                            namespace.map(String::as_str),
+                        )
+                    };
+                    result.map_err(|e| e.to_string().into())
+                }
+                Some((subcommand, sub_matches)) if STORAGE_WRITE_COMMANDS.contains(&subcommand) => {
+                    let auth_context = operator_auth_context(&config)?;
+                    let result = if storage_backend == "file" {
+                        let mut storage = FileStorage::new(storage_path).map_err(|e| {
+                            AppError::Other(format!("Failed to initialize file storage: {}", e))
+                        })?;
+                        handle_storage_command(&mut storage, &auth_context, subcommand, sub_matches)
+                    } else if storage_backend == "sled" {
+                        let mut storage = open_sled_storage(storage_path)?;
+                        handle_storage_command(&mut storage, &auth_context, subcommand, sub_matches)
+                    } else {
+                        warn!("The memory backend discards changes when the command exits; use --storage-backend file");
+                        handle_storage_command(
+                            &mut InMemoryStorage::new(),
+                            &auth_context,
+                            subcommand,
+                            sub_matches,
+                        )
+                    };
+                    result.map_err(|e| e.to_string().into())
+                }
                 _ => Err("Unknown storage subcommand".into()),
             }
         }
+        Some(("federation", sub_matches)) if sub_matches.subcommand_name() == Some("peers") => {
+            let peers_matches = sub_matches
+                .subcommand_matches("peers")
+                .ok_or("Missing federation subcommand")?;
+            handle_peers_command(peers_matches, &config).map_err(|e| e.into())
+        }
         Some(("federation", sub_matches)) => {
-            let auth_context =
-                get_or_create_auth_context(default_storage_backend, default_storage_path)?;
+            let auth_context = get_or_create_auth_context(&config)?;
             let storage = setup_storage(default_storage_backend, default_storage_path)?;
             let mut vm = VM::with_storage_backend(storage);
-            handle_federation_command(&mut vm, sub_matches, &auth_context)
-                .await
-                .map_err(|e| e.into())
+            let span = info_span!("federation", identity = auth_context.identity_did());
+            let result = handle_federation_command(&mut vm, sub_matches, &auth_context)
+                .instrument(span)
+                .await;
+            record_cli_audit(&mut vm, &auth_context, "federation", sub_matches, &result);
+            result.map_err(|e| e.into())
+        }
+        Some(("ledger", ledger_matches)) => {
+            handle_ledger_command(ledger_matches, &config).map_err(|e| e.into())
+        }
+        Some(("keys", keys_matches)) => {
+            handle_keys_command(keys_matches, &config).map_err(|e| e.into())
+        }
+        Some(("coverage", coverage_matches)) => {
+            handle_coverage_command(coverage_matches).map_err(|e| e.into())
+        }
+        Some(("audit", audit_matches)) => {
+            handle_audit_command(audit_matches, &config).map_err(|e| e.into())
+        }
+        Some(("verify-vote", verify_matches)) => {
+            handle_verify_vote_command(verify_matches).map_err(|e| e.into())
         }
         Some(("dag-trace", _)) => {
             let storage = setup_storage(default_storage_backend, default_storage_path)?;
-            let auth_context =
-                get_or_create_auth_context(default_storage_backend, default_storage_path)?;
+            let auth_context = get_or_create_auth_context(&config)?;
             let mut vm = VM::with_storage_backend(storage);
+            if let Some(dag_path) = &config.ledger.dag_path {
+                vm = vm.with_dag_path(dag_path.clone());
+            }
             vm.set_auth_context(auth_context);
             if let Some(dag) = &vm.dag {
                 println!("📜 DAG Trace:");
@@ -523,30 +782,156 @@ async fn main() -> Result<(), Box<dyn Error>> {
             Ok(())
         }
         Some(("api", api_matches)) => {
-            let port = api_matches.get_one::<u16>("port").copied().unwrap_or(3030);
+            let port = api_matches
+                .get_one::<u16>("port")
+                .copied()
+                .unwrap_or(config.api.port);
+            let grpc_port = api_matches
+                .get_one::<u16>("grpc-port")
+                .copied()
+                .or(config.api.grpc_port);
+            let replica = api_matches
+                .get_one::<String>("replica-of")
+                .or(config.api.replica_of.as_ref())
+                .map(|url| ReplicaConfig::new(url))
+                .transpose()?;
+            let oidc = config
+                .api
+                .oidc
+                .clone()
+                .map(OidcProvider::new)
+                .transpose()
+                .map_err(AppError::Other)?;
             println!("Starting API server on port {}...", port);
 
-            // Initialize VM with storage
+            let storage_backend = api_matches
+                .get_one::<String>("storage-backend")
+                .map(|s| s.as_str())
+                .unwrap_or(default_storage_backend);
+            let storage_path = api_matches
+                .get_one::<String>("storage-path")
+                .map(|s| s.as_str())
+                .unwrap_or(default_storage_path);
+
+            // Run a federation node alongside the API if requested
+            let federation_port = api_matches
+                .get_one::<u16>("federation-port")
+                .copied()
+                .or(config.federation.enabled.then_some(config.federation.port));
+            let node = match federation_port {
+                Some(federation_port) => {
+                    let bootstrap_nodes =
+                        match api_matches.get_many::<libp2p::Multiaddr>("bootstrap-nodes") {
+                            Some(addrs) => addrs.cloned().collect(),
+                            None => configured_bootstrap_nodes(&config)?,
+                        };
+                    Some(
+                        start_federation_node(NodeConfig {
+                            port: Some(federation_port),
+                            bootstrap_nodes,
+                            name: Some(
+                                api_matches
+                                    .get_one::<String>("node-name")
+                                    .cloned()
+                                    .unwrap_or_else(|| config.federation.node_name.clone()),
+                            ),
+                            capabilities: config.federation.capabilities.clone(),
+                            protocol_version: "1.0.0".to_string(),
+                            peer_scores_path: Some(config.federation.peer_scores_path_or_default()),
+                        })
+                        .await?,
+                    )
+                }
+                None => None,
+            };
+
+            // Initialize VM with storage and start the API server
+            let result = if storage_backend == "sled" {
+                let vm = VM::with_storage_backend(open_sled_storage(storage_path)?);
+                api::start_api_server(vm, port, grpc_port, node, replica, oidc).await
+            } else {
+                let storage = setup_storage(storage_backend, storage_path)?;
+                let vm = VM::with_storage_backend(storage);
+                api::start_api_server(vm, port, grpc_port, node, replica, oidc).await
+            };
+            result.map_err(|e| AppError::Other(format!("API server error: {}", e)))
+        }
+        Some(("dashboard", dashboard_matches)) => {
+            let auth_context = get_or_create_auth_context(&config)?;
             let storage = setup_storage(default_storage_backend, default_storage_path)?;
             let mut vm = VM::with_storage_backend(storage);
-
-            // Start the API server
-            api::start_api_server(vm, port)
+            vm.set_auth_context(auth_context);
+            let dag_path = dashboard_matches
+                .get_one::<String>("dag-path")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| config.ledger.dag_path_or_default());
+
+            let federation_port = dashboard_matches
+                .get_one::<u16>("federation-port")
+                .copied()
+                .or(config.federation.enabled.then_some(config.federation.port));
+            let node = match federation_port {
+                Some(federation_port) => Some(
+                    start_federation_node(NodeConfig {
+                        port: Some(federation_port),
+                        bootstrap_nodes: configured_bootstrap_nodes(&config)?,
+                        name: Some(config.federation.node_name.clone()),
+                        capabilities: config.federation.capabilities.clone(),
+                        protocol_version: "1.0.0".to_string(),
+                        peer_scores_path: Some(config.federation.peer_scores_path_or_default()),
+                    })
+                    .await?,
+                ),
+                None => None,
+            };
+
+            run_dashboard(&mut vm, dashboard_matches, &dag_path, node)
                 .await
-                .map_err(|e| AppError::Other(format!("API server error: {}", e)))
+                .map_err(|e| e.into())
         }
+        Some(("config", _)) => print_config(&config),
         _ => Err("Unknown command".into()),
     };
 
     // Handle errors
     if let Err(e) = result {
         eprintln!("Error: {}", e);
+        drop(telemetry_guard);
         process::exit(1);
     }
 
     Ok(())
 }
 
+/// Start a federation node in the background, returning a handle to it
+/// Parse the bootstrap nodes from the config file and environment
+fn configured_bootstrap_nodes(config: &Config) -> Result<Vec<libp2p::Multiaddr>, AppError> {
+    config
+        .federation
+        .bootstrap_nodes
+        .iter()
+        .map(|addr| {
+            addr.parse::<libp2p::Multiaddr>()
+                .map_err(|e| format!("Invalid bootstrap node {}: {}", addr, e).into())
+        })
+        .collect()
+}
+
+async fn start_federation_node(config: NodeConfig) -> Result<NodeHandle, AppError> {
+    let mut node = NetworkNode::new(config)
+        .await
+        .map_err(|e| AppError::Federation(format!("Failed to create network node: {}", e)))?;
+    info!(peer_id = %node.local_peer_id(), "Federation node created");
+
+    let handle = node.handle();
+    tokio::spawn(async move {
+        if let Err(e) = node.start().await {
+            error!(error = %e, "Federation node stopped");
+        }
+    });
+    Ok(handle)
+}
+
 /// Run the virtual machine with federation enabled
 async fn run_with_federation(
     program_path: &str,
@@ -560,16 +945,20 @@ async fn run_with_federation(
     bootstrap_nodes: Vec<libp2p::Multiaddr>,
     node_name: String,
     capabilities: Vec<String>,
+    peer_scores_path: PathBuf,
     simulate: bool,
     trace: bool,
     explain: bool,
     verbose_storage_trace: bool,
 ) -> Result<(), AppError> {
     info!("Starting ICN-COVM with federation enabled");
-    debug!("Federation port: {}", federation_port);
-    debug!("Bootstrap nodes: {:?}", bootstrap_nodes);
-    debug!("Node name: {}", node_name);
-    debug!("Capabilities: {:?}", capabilities);
+    debug!(
+        port = federation_port,
+        bootstrap_nodes = ?bootstrap_nodes,
+        node = %node_name,
+        capabilities = ?capabilities,
+        "Federation settings"
+    );
 
     // Configure federation
     let node_config = NodeConfig {
@@ -578,6 +967,7 @@ async fn run_with_federation(
         name: Some(node_name),
         capabilities,
         protocol_version: "1.0.0".to_string(),
+        peer_scores_path: Some(peer_scores_path),
     };
 
     // Create and start network node
@@ -591,7 +981,7 @@ async fn run_with_federation(
         }
     };
 
-    info!("Local peer ID: {}", network_node.local_peer_id());
+    info!(peer_id = %network_node.local_peer_id(), "Network node created");
 
     // Start the network node
     if let Err(e) = network_node.start().await {
@@ -615,6 +1005,8 @@ async fn run_with_federation(
             trace,
             explain,
             verbose_storage_trace,
+            false,
+            None,
         )?;
     } else {
         info!("No program specified, running in network-only mode");
@@ -640,6 +1032,8 @@ fn run_program(
     trace: bool,
     explain: bool,
     verbose_storage_trace: bool,
+    debug: bool,
+    dry_run: Option<DryRun>,
 ) -> Result<(), AppError> {
     let path = Path::new(program_path);
 
@@ -703,19 +1097,70 @@ fn run_program(
     let auth_context = create_demo_auth_context()?;
 
     // Select the appropriate storage backend
-    let storage = create_storage_backend(storage_backend, storage_path)?;
+    if storage_backend == "sled" {
+        let storage = open_sled_storage(storage_path)?;
+        execute_program(
+            storage,
+            &ops,
+            auth_context,
+            parameters,
+            use_bytecode,
+            verbose,
+            simulate,
+            trace,
+            explain,
+            verbose_storage_trace,
+            debug,
+            dry_run,
+        )
+    } else {
+        let storage = create_storage_backend(storage_backend, storage_path)?;
+        execute_program(
+            storage,
+            &ops,
+            auth_context,
+            parameters,
+            use_bytecode,
+            verbose,
+            simulate,
+            trace,
+            explain,
+            verbose_storage_trace,
+            debug,
+            dry_run,
+        )
+    }
+}
 
+/// Run `ops` as the demo identity against `storage`
+fn execute_program<S>(
+    storage: S,
+    ops: &[Op],
+    auth_context: AuthContext,
+    parameters: HashMap<String, String>,
+    use_bytecode: bool,
+    verbose: bool,
+    simulate: bool,
+    trace: bool,
+    explain: bool,
+    verbose_storage_trace: bool,
+    debug: bool,
+    mut dry_run: Option<DryRun>,
+) -> Result<(), AppError>
+where
+    S: Storage + Send + Sync + Clone + std::fmt::Debug + 'static,
+{
     if use_bytecode {
         // Bytecode execution with FileStorage
         let mut compiler = BytecodeCompiler::new();
-        let program = compiler.compile(&ops);
+        let program = compiler.compile(ops);
 
         if verbose {
             println!("Compiled bytecode program:\n{}", program.dump());
         }
 
         // Create bytecode interpreter with proper auth context and storage
-        let mut vm: VM<InMemoryStorage> = VM::new()
+        let mut vm: VM<S> = VM::new()
             .set_simulation_mode(simulate)
             .set_tracing(trace)
             .set_explanation(explain)
@@ -758,7 +1203,7 @@ fn run_program(
         }
     } else {
         // AST execution with FileStorage
-        let mut vm: VM<InMemoryStorage> = VM::new();
+        let mut vm: VM<S> = VM::new();
 
         // Set the new flags
         vm.set_simulation_mode(simulate);
@@ -778,7 +1223,13 @@ fn run_program(
             println!("-----------------------------------");
         }
 
-        vm.execute(&ops)?;
+        if debug {
+            debug::run_session(&mut vm, ops)?;
+        } else if let Some(dry_run) = dry_run.as_mut() {
+            dry_run::rehearse(dry_run, &mut vm, ops)?;
+        } else {
+            vm.execute(ops)?;
+        }
 
         if verbose {
             println!("-----------------------------------");
@@ -799,9 +1250,37 @@ fn run_program(
         }
     }
 
+    if let Some(dry_run) = dry_run {
+        dry_run.finish()?;
+    }
     Ok(())
 }
 
+/// Run a `proposal` subcommand against `vm` and record it in the audit log
+fn run_proposal_command<S>(
+    mut vm: VM<S>,
+    config: &Config,
+    matches: &clap::ArgMatches,
+    auth_context: &AuthContext,
+) -> Result<(), AppError>
+where
+    S: Storage + Send + Sync + Clone + std::fmt::Debug + 'static,
+{
+    // An explicit --dag-path still takes precedence in the handler
+    if let Some(dag_path) = &config.ledger.dag_path {
+        vm = vm.with_dag_path(dag_path.clone());
+    }
+    let result = handle_proposal_command(&mut vm, matches, auth_context);
+    record_cli_audit(&mut vm, auth_context, "proposal", matches, &result);
+    result.map_err(|e| e.into())
+}
+
+/// Open, or create, the sled database in `path`
+fn open_sled_storage(path: &str) -> Result<SledStorage, AppError> {
+    SledStorage::open(path)
+        .map_err(|e| AppError::Other(format!("Failed to open sled storage at {}: {}", path, e)))
+}
+
 /// Helper to create the appropriate storage backend
 fn create_storage_backend(backend_type: &str, path: &str) -> Result<InMemoryStorage, AppError> {
     match backend_type {
@@ -847,149 +1326,6 @@ fn create_demo_auth_context() -> Result<AuthContext, AppError> {
     Ok(auth_context)
 }
 
-// Helper function to create a demo auth context and initialize storage
-fn setup_storage_for_demo() -> (AuthContext, InMemoryStorage) {
-    let auth = create_demo_auth_context().unwrap();
-
-    // Create storage backend
-    let mut storage = InMemoryStorage::new();
-
-    // Create user account
-    if let Err(e) = storage.create_account(Some(&auth), &auth.user_id(), 1024 * 1024) {
-        println!("Warning: Failed to create account: {:?}", e);
-    }
-
-    // Create namespace
-    if let Err(e) = storage.create_namespace(Some(&auth), "demo", 1024 * 1024, None) {
-        println!("Warning: Failed to create namespace: {:?}", e);
-    }
-
-    (auth, storage)
-}
-
-fn run_benchmark(
-    program_path: &str,
-    _verbose: bool,
-    use_stdlib: bool,
-    parameters: HashMap<String, String>,
-    _storage_backend: &str,
-    _storage_path: &str,
-) -> Result<(), AppError> {
-    let path = Path::new(program_path);
-
-    // Check if file exists
-    if !path.exists() {
-        return Err(format!("Program file not found: {}", program_path).into());
-    }
-
-    // Parse operations based on file extension
-    let ops = if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
-        match extension.to_lowercase().as_str() {
-            "dsl" => {
-                println!("Parsing DSL program from {}", program_path);
-                let program_source = fs::read_to_string(path)?;
-
-                if use_stdlib {
-                    parse_dsl_with_stdlib(&program_source)?
-                } else {
-                    let (ops, _lifecycle) = parse_dsl(&program_source)?;
-                    ops
-                }
-            }
-            "json" => {
-                println!("Parsing JSON program from {}", program_path);
-                let program_json = fs::read_to_string(path)?;
-                serde_json::from_str(&program_json)?
-            }
-            _ => return Err(format!("Unsupported file extension: {}", extension).into()),
-        }
-    } else {
-        return Err("File has no extension".into());
-    };
-
-    println!("Program loaded with {} operations", ops.len());
-    println!("\nBenchmarking execution modes...");
-
-    // Run AST interpreter
-    println!("\n1. Running AST interpreter...");
-
-    let mut vm: VM<InMemoryStorage> = VM::new();
-
-    // Set up auth context and namespace
-    let auth_context = setup_storage_for_demo().0;
-    vm.set_auth_context(auth_context.clone());
-    vm.set_namespace("demo");
-
-    vm.set_parameters(parameters.clone())?;
-
-    let ast_start = Instant::now();
-    vm.execute(&ops)?;
-    let ast_duration = ast_start.elapsed();
-
-    println!("AST execution time: {:?}", ast_duration);
-
-    // Run bytecode compilation and execution
-    println!("\n2. Running bytecode compiler and interpreter...");
-
-    let compiler_start = Instant::now();
-    let mut compiler = BytecodeCompiler::new();
-    let program = compiler.compile(&ops);
-    let compiler_duration = compiler_start.elapsed();
-
-    println!("Bytecode compilation time: {:?}", compiler_duration);
-    println!("Bytecode size: {} instructions", program.instructions.len());
-
-    let mut vm: VM<InMemoryStorage> = VM::new();
-    vm.set_auth_context(auth_context);
-    vm.set_namespace("demo");
-
-    let mut interpreter = BytecodeInterpreter::new(VM::<InMemoryStorage>::new(), program);
-    interpreter.get_vm_mut().set_parameters(parameters)?;
-
-    let bytecode_start = Instant::now();
-    interpreter.execute()?;
-    let bytecode_duration = bytecode_start.elapsed();
-
-    println!("Bytecode execution time: {:?}", bytecode_duration);
-
-    // Calculate speedup
-    let total_bytecode_time = compiler_duration + bytecode_duration;
-    println!(
-        "\nTotal bytecode time (compilation + execution): {:?}",
-        total_bytecode_time
-    );
-
-    if ast_duration > bytecode_duration {
-        let speedup = ast_duration.as_secs_f64() / bytecode_duration.as_secs_f64();
-        println!(
-            "Bytecode execution is {:.2}x faster than AST interpretation",
-            speedup
-        );
-    } else {
-        let slowdown = bytecode_duration.as_secs_f64() / ast_duration.as_secs_f64();
-        println!(
-            "Bytecode execution is {:.2}x slower than AST interpretation",
-            slowdown
-        );
-    }
-
-    if ast_duration > total_bytecode_time {
-        let speedup = ast_duration.as_secs_f64() / total_bytecode_time.as_secs_f64();
-        println!(
-            "Bytecode (including compilation) is {:.2}x faster than AST interpretation",
-            speedup
-        );
-    } else {
-        let slowdown = total_bytecode_time.as_secs_f64() / ast_duration.as_secs_f64();
-        println!(
-            "Bytecode (including compilation) is {:.2}x slower than AST interpretation",
-            slowdown
-        );
-    }
-
-    Ok(())
-}
-
 fn run_interactive(
     verbose: bool,
     parameters: HashMap<String, String>,
@@ -1046,10 +1382,18 @@ fn run_interactive(
     // Create an editor for interactive input
     let mut rl = rustyline::DefaultEditor::new().map_err(|e| AppError::Other(e.to_string()))?;
 
-    loop {
+    let mut reader = BlockReader::new();
+    let mut session = ReplSession::new();
+
+    'repl: loop {
         // Read a line of input
-        let line = match rl.readline("> ") {
+        let line = match rl.readline(reader.prompt()) {
             Ok(line) => line,
+            Err(rustyline::error::ReadlineError::Interrupted) if reader.is_pending() => {
+                reader.clear();
+                println!("Block discarded");
+                continue;
+            }
             Err(rustyline::error::ReadlineError::Interrupted) => {
                 println!("Interrupted (Ctrl+C)");
                 break;
@@ -1068,198 +1412,197 @@ fn run_interactive(
             return Err(AppError::Other(format!("Error adding to history: {}", e)));
         }
 
-        // Process the line
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-
-        match trimmed {
-            "exit" | "quit" => {
-                println!("Exiting REPL");
-                break;
-            }
-            "help" => {
-                println!("Available commands:");
-                println!("  help         - Show this help message");
-                println!("  exit, quit   - Exit the REPL");
-                println!("  stack        - Display the current stack");
-                println!("  memory       - Display memory contents");
-                println!("  reset        - Reset the VM");
-                println!("  mode ast     - Switch to AST interpreter mode");
-                println!("  mode bytecode - Switch to bytecode execution mode");
-                println!("  trace on/off - Toggle tracing mode");
-                println!("  explain on/off - Toggle explanation mode");
-                println!("  simulate on/off - Toggle simulation mode");
-                println!("  storage-trace on/off - Toggle verbose storage tracing");
-                println!("  save <file>  - Save current program to a file");
-                println!("  load <file>  - Load program from a file");
-                println!();
-                println!("Any other input will be interpreted as DSL code and executed.");
+        // Process each input the line completes
+        for input in reader.push(&line) {
+            let trimmed = input.trim();
+            if trimmed.is_empty() {
+                continue;
             }
-            "stack" => {
-                println!("Stack:");
-                let stack = vm.get_stack();
-                for (i, &value) in stack.iter().enumerate() {
-                    println!("  {}: {}", i, value);
+
+            match trimmed {
+                "exit" | "quit" => {
+                    println!("Exiting REPL");
+                    break 'repl;
                 }
-                if stack.is_empty() {
-                    println!("  (empty)");
+                "help" => {
+                    println!("Available commands:");
+                    println!("  help         - Show this help message");
+                    println!("  exit, quit   - Exit the REPL");
+                    println!("  stack        - Display the current stack");
+                    println!("  memory       - Display memory contents");
+                    println!("  reset        - Reset the VM");
+                    println!("  mode ast     - Switch to AST interpreter mode");
+                    println!("  mode bytecode - Switch to bytecode execution mode");
+                    println!("  trace on/off - Toggle tracing mode");
+                    println!("  explain on/off - Toggle explanation mode");
+                    println!("  simulate on/off - Toggle simulation mode");
+                    println!("  storage-trace on/off - Toggle verbose storage tracing");
+                    println!("  save <file>  - Save the session's program (.json saves ops, otherwise DSL)");
+                    println!("  load <file>  - Load and run a .dsl or .json program");
+                    println!();
+                    println!("Any other input will be interpreted as DSL code and executed.");
+                    println!("A line ending in ':' starts a block; end it with a blank line.");
                 }
-            }
-            "memory" => {
-                println!("Memory:");
-                let memory_map = vm.memory.get_memory_map();
-                for (key, value) in memory_map {
-                    println!("  {}: {}", key, value);
+                "stack" => {
+                    println!("Stack:");
+                    let stack = vm.get_stack();
+                    for (i, &value) in stack.iter().enumerate() {
+                        println!("  {}: {}", i, value);
+                    }
+                    if stack.is_empty() {
+                        println!("  (empty)");
+                    }
                 }
-                if vm.memory.get_memory_map().is_empty() {
-                    println!("  (empty)");
+                "memory" => {
+                    println!("Memory:");
+                    let memory_map = vm.memory.get_memory_map();
+                    for (key, value) in memory_map {
+                        println!("  {}: {}", key, value);
+                    }
+                    if vm.memory.get_memory_map().is_empty() {
+                        println!("  (empty)");
+                    }
                 }
-            }
-            "reset" => {
-                vm = VM::<InMemoryStorage>::new();
-                vm.set_simulation_mode(simulate);
-                vm.set_tracing(trace);
-                vm.set_explanation(explain);
-                vm.set_auth_context(auth_context.clone());
-                vm.set_namespace("demo");
-                println!("VM reset");
-            }
-            "trace on" => {
-                vm.set_tracing(true);
-                println!("Tracing enabled");
-            }
-            "trace off" => {
-                vm.set_tracing(false);
-                println!("Tracing disabled");
-            }
-            "explain on" => {
-                vm.set_explanation(true);
-                println!("Explanation enabled");
-            }
-            "explain off" => {
-                vm.set_explanation(false);
-                println!("Explanation disabled");
-            }
-            "simulate on" => {
-                vm.set_simulation_mode(true);
-                println!("Simulation mode enabled (no persistent storage changes)");
-            }
-            "simulate off" => {
-                vm.set_simulation_mode(false);
-                println!("Simulation mode disabled (storage changes will be committed)");
-            }
-            "storage-trace on" => {
-                vm.set_verbose_storage_trace(true);
-                println!("Verbose storage tracing enabled");
-            }
-            "storage-trace off" => {
-                vm.set_verbose_storage_trace(false);
-                println!("Verbose storage tracing disabled");
-            }
-            "mode ast" => {
-                return run_interactive(
-                    verbose,
-                    vm.get_memory_map()
-                        .iter()
-                        .map(|(k, v)| (k.clone(), v.to_string()))
-                        .collect(),
-                    false,
-                    storage_backend,
-                    storage_path,
-                    vm.is_simulation_mode(),
-                    vm.is_tracing(),
-                    vm.is_explaining(),
-                    verbose_storage_trace,
-                );
-            }
-            "mode bytecode" => {
-                return run_interactive(
-                    verbose,
-                    vm.get_memory_map()
-                        .iter()
-                        .map(|(k, v)| (k.clone(), v.to_string()))
-                        .collect(),
-                    true,
-                    storage_backend,
-                    storage_path,
-                    vm.is_simulation_mode(),
-                    vm.is_tracing(),
-                    vm.is_explaining(),
-                    verbose_storage_trace,
-                );
-            }
-            _ if trimmed.starts_with("save ") => {
-                let file_name = trimmed[5..].trim();
-                if file_name.is_empty() {
-                    println!("Usage: save <file>");
-                    continue;
+                "reset" => {
+                    vm = VM::<InMemoryStorage>::new();
+                    vm.set_simulation_mode(simulate);
+                    vm.set_tracing(trace);
+                    vm.set_explanation(explain);
+                    vm.set_auth_context(auth_context.clone());
+                    vm.set_namespace("demo");
+                    session.clear();
+                    println!("VM reset");
                 }
-                // Not implemented yet
-                println!("Save functionality not yet implemented");
-            }
-            _ if trimmed.starts_with("load ") => {
-                let file_name = trimmed[5..].trim();
-                if file_name.is_empty() {
-                    println!("Usage: load <file>");
-                    continue;
+                "trace on" => {
+                    vm.set_tracing(true);
+                    println!("Tracing enabled");
                 }
-                // Not implemented yet
-                println!("Load functionality not yet implemented");
-            }
-            _ => {
-                // Parse and execute the input as DSL code
-                match parse_dsl(trimmed) {
-                    Ok((ops, _lifecycle_config)) => {
-                        if use_bytecode {
-                            // Compile to bytecode and execute
-                            let mut compiler = BytecodeCompiler::new();
-                            let program = compiler.compile(&ops);
-
-                            if verbose {
-                                println!("Compiled to bytecode:");
-                                println!("{}", program.dump());
-                            }
-
-                            // Configure a new VM with our flags
-                            let mut base_vm = VM::<InMemoryStorage>::new();
-                            base_vm.set_simulation_mode(vm.is_simulation_mode());
-                            base_vm.set_tracing(vm.is_tracing());
-                            base_vm.set_explanation(vm.is_explaining());
-                            base_vm.set_auth_context(auth_context.clone());
-                            base_vm.set_namespace("demo");
-
-                            let mut interpreter = BytecodeInterpreter::new(base_vm, program);
-
-                            // Execute with bytecode
-                            let bytecode_start = Instant::now();
-                            interpreter.execute()?;
-                            let bytecode_duration = bytecode_start.elapsed();
-
-                            println!("Bytecode: {:?}", bytecode_duration);
-
-                            // Copy results back to REPL VM
-                            vm.stack = interpreter.get_vm().stack.clone();
-                            vm.memory = interpreter.get_vm().memory.clone();
-
-                            // Print result (if any)
-                            if let Some(result) = interpreter.get_vm().top() {
-                                println!("Result: {}", result);
+                "trace off" => {
+                    vm.set_tracing(false);
+                    println!("Tracing disabled");
+                }
+                "explain on" => {
+                    vm.set_explanation(true);
+                    println!("Explanation enabled");
+                }
+                "explain off" => {
+                    vm.set_explanation(false);
+                    println!("Explanation disabled");
+                }
+                "simulate on" => {
+                    vm.set_simulation_mode(true);
+                    println!("Simulation mode enabled (no persistent storage changes)");
+                }
+                "simulate off" => {
+                    vm.set_simulation_mode(false);
+                    println!("Simulation mode disabled (storage changes will be committed)");
+                }
+                "storage-trace on" => {
+                    vm.set_verbose_storage_trace(true);
+                    println!("Verbose storage tracing enabled");
+                }
+                "storage-trace off" => {
+                    vm.set_verbose_storage_trace(false);
+                    println!("Verbose storage tracing disabled");
+                }
+                "mode ast" => {
+                    return run_interactive(
+                        verbose,
+                        vm.get_memory_map()
+                            .iter()
+                            .map(|(k, v)| (k.clone(), v.to_string()))
+                            .collect(),
+                        false,
+                        storage_backend,
+                        storage_path,
+                        vm.is_simulation_mode(),
+                        vm.is_tracing(),
+                        vm.is_explaining(),
+                        verbose_storage_trace,
+                    );
+                }
+                "mode bytecode" => {
+                    return run_interactive(
+                        verbose,
+                        vm.get_memory_map()
+                            .iter()
+                            .map(|(k, v)| (k.clone(), v.to_string()))
+                            .collect(),
+                        true,
+                        storage_backend,
+                        storage_path,
+                        vm.is_simulation_mode(),
+                        vm.is_tracing(),
+                        vm.is_explaining(),
+                        verbose_storage_trace,
+                    );
+                }
+                _ if trimmed.starts_with("save ") => {
+                    let file_name = trimmed[5..].trim();
+                    if file_name.is_empty() {
+                        println!("Usage: save <file>");
+                        continue;
+                    }
+                    if session.is_empty() {
+                        println!("Nothing to save yet");
+                        continue;
+                    }
+                    match session.save(Path::new(file_name)) {
+                        Ok(()) => println!("Session saved to {}", file_name),
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+                _ if trimmed.starts_with("load ") => {
+                    let file_name = trimmed[5..].trim();
+                    if file_name.is_empty() {
+                        println!("Usage: load <file>");
+                        continue;
+                    }
+                    let path = Path::new(file_name);
+                    let source = match fs::read_to_string(path) {
+                        Ok(source) => source,
+                        Err(e) => {
+                            println!("Error: Failed to read {}: {}", file_name, e);
+                            continue;
+                        }
+                    };
+                    let is_json = path
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+                    let loaded = if is_json {
+                        serde_json::from_str::<Vec<Op>>(&source)
+                            .map(|ops| (None, ops))
+                            .map_err(|e| e.to_string())
+                    } else {
+                        parse_dsl(&source)
+                            .map(|(ops, _lifecycle_config)| (Some(source), ops))
+                            .map_err(|e| e.to_string())
+                    };
+                    match loaded {
+                        Ok((source, ops)) => {
+                            println!("Loaded {} operations from {}", ops.len(), file_name);
+                            match run_repl_ops(&mut vm, &ops, use_bytecode, verbose, &auth_context)
+                            {
+                                Ok(()) => session.record(source, ops),
+                                Err(e) => println!("Error: {}", e),
                             }
-                        } else {
-                            // Execute directly with AST interpreter
-                            match vm.execute(&ops) {
-                                Ok(()) => {
-                                    if let Some(result) = vm.top() {
-                                        println!("Result: {}", result);
-                                    }
-                                }
+                        }
+                        Err(e) => println!("Parse error: {}", e),
+                    }
+                }
+                _ => {
+                    // Parse and execute the input as DSL code
+                    match parse_dsl(trimmed) {
+                        Ok((ops, _lifecycle_config)) => {
+                            match run_repl_ops(&mut vm, &ops, use_bytecode, verbose, &auth_context)
+                            {
+                                Ok(()) => session.record(Some(trimmed.to_string()), ops),
                                 Err(e) => println!("Error: {}", e),
                             }
                         }
+                        Err(e) => println!("Parse error: {}", e),
                     }
-                    Err(e) => println!("Parse error: {}", e),
                 }
             }
         }
@@ -1268,6 +1611,60 @@ fn run_interactive(
     Ok(())
 }
 
+/// Run ops typed or loaded in the REPL, keeping the results in `vm`
+fn run_repl_ops(
+    vm: &mut VM<InMemoryStorage>,
+    ops: &[Op],
+    use_bytecode: bool,
+    verbose: bool,
+    auth_context: &AuthContext,
+) -> Result<(), AppError> {
+    if !use_bytecode {
+        // Execute directly with AST interpreter
+        vm.execute(ops)?;
+        if let Some(result) = vm.top() {
+            println!("Result: {}", result);
+        }
+        return Ok(());
+    }
+
+    // Compile to bytecode and execute
+    let mut compiler = BytecodeCompiler::new();
+    let program = compiler.compile(ops);
+
+    if verbose {
+        println!("Compiled to bytecode:");
+        println!("{}", program.dump());
+    }
+
+    // Configure a new VM with our flags
+    let mut base_vm = VM::<InMemoryStorage>::new();
+    base_vm.set_simulation_mode(vm.is_simulation_mode());
+    base_vm.set_tracing(vm.is_tracing());
+    base_vm.set_explanation(vm.is_explaining());
+    base_vm.set_auth_context(auth_context.clone());
+    base_vm.set_namespace("demo");
+
+    let mut interpreter = BytecodeInterpreter::new(base_vm, program);
+
+    // Execute with bytecode
+    let bytecode_start = Instant::now();
+    interpreter.execute()?;
+    let bytecode_duration = bytecode_start.elapsed();
+
+    println!("Bytecode: {:?}", bytecode_duration);
+
+    // Copy results back to REPL VM
+    vm.stack = interpreter.get_vm().stack.clone();
+    vm.memory = interpreter.get_vm().memory.clone();
+
+    // Print result (if any)
+    if let Some(result) = interpreter.get_vm().top() {
+        println!("Result: {}", result);
+    }
+    Ok(())
+}
+
 /// Register a new identity using the information in the provided JSON file
 fn register_identity(
     id_file: &str,
@@ -1345,7 +1742,7 @@ fn list_keys_command(
         // Create the storage directory if it doesn't exist
         let storage_dir = Path::new(storage_path);
         if !storage_dir.exists() {
-            println!("Creating storage directory: {}", storage_path);
+            info!(path = storage_path, "Creating storage directory");
             fs::create_dir_all(storage_dir).map_err(|e| {
                 AppError::Other(format!("Failed to create storage directory: {}", e))
             })?;
@@ -1364,30 +1761,55 @@ fn list_keys_command(
     let prefix_str = prefix.map(|s| s.as_str());
 
     // List keys from the storage backend
-    match storage.list_keys(Some(&auth_context), namespace, prefix_str) {
-        Ok(keys) => {
-            if keys.is_empty() {
-                println!(
-                    "No keys found in namespace '{}'{}",
-                    namespace,
-                    prefix.map_or(String::new(), |p| format!(" with prefix '{}'", p))
-                );
-            } else {
-                println!(
-                    "Keys in namespace '{}'{}",
-                    namespace,
-                    prefix.map_or(String::new(), |p| format!(" with prefix '{}'", p))
-                );
-                let keys_count = keys.len();
-                for key in keys {
-                    println!("  - {}", key);
-                }
-                println!("Total: {} keys", keys_count);
+    let keys = storage
+        .list_keys(Some(&auth_context), namespace, prefix_str)
+        .map_err(|e| AppError::Other(format!("Failed to list keys: {}", e)))?;
+    let listing = KeyListing {
+        namespace: namespace.to_string(),
+        prefix: prefix.cloned(),
+        keys,
+    };
+
+    print_output(&listing, |listing| {
+        let filter = listing
+            .prefix
+            .as_ref()
+            .map_or(String::new(), |p| format!(" with prefix '{}'", p));
+        if listing.keys.is_empty() {
+            println!(
+                "No keys found in namespace '{}'{}",
+                listing.namespace, filter
+            );
+        } else {
+            println!("Keys in namespace '{}'{}", listing.namespace, filter);
+            for key in &listing.keys {
+                println!("  - {}", key);
             }
-            Ok(())
+            println!("Total: {} keys", listing.keys.len());
         }
-        Err(e) => Err(AppError::Other(format!("Failed to list keys: {}", e))),
-    }
+    })?;
+    Ok(())
+}
+
+/// Keys listed by `storage list-keys`
+#[derive(Serialize)]
+struct KeyListing {
+    namespace: String,
+    prefix: Option<String>,
+    keys: Vec<String>,
+}
+
+/// A value read by `storage get-value`
+///
+/// JSON values are embedded as-is, other text as a string, and binary data
+/// as a hex string.
+#[derive(Serialize)]
+struct StoredValue {
+    namespace: String,
+    key: String,
+    encoding: &'static str,
+    size: usize,
+    value: serde_json::Value,
 }
 
 /// Command to get a value from storage
@@ -1405,7 +1827,7 @@ fn get_value_command(
         // Create the storage directory if it doesn't exist
         let storage_dir = Path::new(storage_path);
         if !storage_dir.exists() {
-            println!("Creating storage directory: {}", storage_path);
+            info!(path = storage_path, "Creating storage directory");
             fs::create_dir_all(storage_dir).map_err(|e| {
                 AppError::Other(format!("Failed to create storage directory: {}", e))
             })?;
@@ -1422,6 +1844,24 @@ fn get_value_command(
 
     // Get the value from storage
     match storage.get(Some(&auth_context), namespace, key) {
+        Ok(data) if !output::is_table() => {
+            let (encoding, value) = match std::str::from_utf8(&data) {
+                Ok(text) => match serde_json::from_str::<serde_json::Value>(text) {
+                    Ok(json) => ("json", json),
+                    Err(_) => ("text", serde_json::Value::String(text.to_string())),
+                },
+                Err(_) => ("binary", serde_json::Value::String(hex::encode(&data))),
+            };
+            let stored = StoredValue {
+                namespace: namespace.to_string(),
+                key: key.to_string(),
+                encoding,
+                size: data.len(),
+                value,
+            };
+            print_output(&stored, |_| {})?;
+            Ok(())
+        }
         Ok(data) => {
             // Try to decode as UTF-8 string
             match std::str::from_utf8(&data) {
@@ -1480,7 +1920,7 @@ async fn broadcast_proposal(
     coops: &str,
     expires_in: Option<u64>,
 ) -> Result<(), AppError> {
-    info!("Broadcasting proposal from file: {}", proposal_file);
+    info!(file = proposal_file, "Broadcasting proposal from file");
 
     // Read and parse the proposal file
     let proposal_content = fs::read_to_string(proposal_file).map_err(|e| AppError::IO(e))?;
@@ -1545,6 +1985,7 @@ async fn broadcast_proposal(
         name: Some(node_name),
         capabilities: vec!["voting".to_string()],
         protocol_version: "1.0.0".to_string(),
+        peer_scores_path: None,
     };
 
     // Create and start network node
@@ -1558,7 +1999,7 @@ async fn broadcast_proposal(
         }
     };
 
-    info!("Local peer ID: {}", network_node.local_peer_id());
+    info!(peer_id = %network_node.local_peer_id(), "Network node created");
 
     // Start the network node
     if let Err(e) = network_node.start().await {
@@ -1605,7 +2046,7 @@ async fn submit_vote(
     bootstrap_nodes: Vec<libp2p::Multiaddr>,
     node_name: String,
 ) -> Result<(), AppError> {
-    info!("Submitting vote from file: {}", vote_file);
+    info!(file = vote_file, "Submitting vote from file");
 
     // Read and parse the vote file
     let vote_content = fs::read_to_string(vote_file).map_err(|e| AppError::IO(e))?;
@@ -1636,11 +2077,10 @@ async fn submit_vote(
         lines[3].trim().to_string()
     } else {
         // Generate a canonical message for signing if none was provided
-        format!(
-            "Vote from {} on proposal {} with choices {}",
-            voter,
-            proposal_id,
-            lines[2].trim()
+        icn_covm::federation::FederatedVote::canonical_message(
+            &proposal_id,
+            &voter,
+            &ranked_choices,
         )
     };
 
@@ -1653,10 +2093,10 @@ async fn submit_vote(
     };
 
     info!(
-        "Parsed vote for proposal {} by {} with {} ranked choices",
-        proposal_id,
-        voter,
-        ranked_choices.len()
+        proposal_id = %proposal_id,
+        identity = %voter,
+        choices = ranked_choices.len(),
+        "Parsed vote"
     );
 
     // Create the vote object
@@ -1675,6 +2115,7 @@ async fn submit_vote(
         name: Some(node_name),
         capabilities: vec!["voting".to_string()],
         protocol_version: "1.0.0".to_string(),
+        peer_scores_path: None,
     };
 
     // Create and start network node
@@ -1688,7 +2129,7 @@ async fn submit_vote(
         }
     };
 
-    info!("Local peer ID: {}", network_node.local_peer_id());
+    info!(peer_id = %network_node.local_peer_id(), "Network node created");
 
     // Start the network node
     if let Err(e) = network_node.start().await {
@@ -1724,6 +2165,7 @@ async fn submit_vote(
 }
 
 /// Handle the execute-proposal federation command
+#[instrument(skip_all, fields(proposal_id = %proposal_id, identity = tracing::field::Empty))]
 async fn execute_proposal(
     proposal_id: &str,
     storage_backend: &str,
@@ -1733,11 +2175,12 @@ async fn execute_proposal(
     node_name: String,
     force: bool,
 ) -> Result<(), AppError> {
-    info!("Executing proposal: {}", proposal_id);
+    info!("Executing proposal");
 
     // Create a network node for federation operations
     let storage = setup_storage(storage_backend, storage_path)?;
-    let auth_context = get_or_create_auth_context(storage_backend, storage_path)?;
+    let auth_context = get_or_create_auth_context(&Config::load(None)?)?;
+    tracing::Span::current().record("identity", auth_context.identity_did());
 
     // Setup the network node
     let node_config = NodeConfig {
@@ -1746,6 +2189,7 @@ async fn execute_proposal(
         name: Some(node_name),
         capabilities: vec!["voting".to_string()],
         protocol_version: "1.0.0".to_string(),
+        peer_scores_path: None,
     };
 
     let mut network_node = NetworkNode::new(node_config)
@@ -1836,7 +2280,7 @@ async fn execute_proposal(
                 id
             }
             Err(e) => {
-                warn!("Error creating identity for {}: {}", vote.voter, e);
+                warn!(identity = %vote.voter, error = %e, "Error creating voter identity");
                 continue;
             }
         };
@@ -1896,6 +2340,7 @@ async fn execute_proposal(
     let result = vm.execute(&[icn_covm::vm::Op::RankedVote {
         candidates: proposal.options.len(),
         ballots: ballots.len(),
+        delegated: false,
     }]);
 
     match result {
@@ -1907,11 +2352,10 @@ async fn execute_proposal(
                     AppError::Federation(format!("Invalid winner index: {}", winner_index))
                 })?;
 
-                info!("Proposal voting complete!");
                 info!(
-                    "Winning option ({}/{}): {}",
-                    winner_index + 1,
-                    proposal.options.len(),
+                    winner = winner_index + 1,
+                    options = proposal.options.len(),
+                    "Proposal voting complete: {}",
                     winner_option
                 );
 
@@ -1944,12 +2388,88 @@ async fn execute_proposal(
     Ok(())
 }
 
-fn get_or_create_auth_context(
-    storage_backend: &str,
-    storage_path: &str,
-) -> Result<AuthContext, AppError> {
-    // For now, just create a simple auth context for demo purposes
-    Ok(AuthContext::new("demo_user"))
+/// Record a mutating CLI command in the audit log
+fn record_cli_audit<S>(
+    vm: &mut VM<S>,
+    auth_context: &AuthContext,
+    command: &str,
+    matches: &clap::ArgMatches,
+    result: &Result<(), Box<dyn Error>>,
+) where
+    S: Storage + Send + Sync + Clone + std::fmt::Debug + 'static,
+{
+    let action = match matches.subcommand_name() {
+        Some(subcommand) => format!("{} {}", command, subcommand),
+        None => command.to_string(),
+    };
+    let outcome = if result.is_ok() {
+        AuditOutcome::Success
+    } else {
+        AuditOutcome::Failure
+    };
+    let mut entry = AuditEntry::new(
+        AuditSource::Cli,
+        auth_context.identity_did(),
+        action,
+        outcome,
+    );
+    entry.detail = result.as_ref().err().map(|e| e.to_string());
+    if let Some(storage) = vm.get_storage_backend_mut() {
+        if let Err(e) = audit::prepare(storage).and_then(|()| audit::append(storage, &entry)) {
+            warn!(error = %e, "Failed to write audit entry");
+        }
+    }
+}
+
+/// Print the resolved configuration, as TOML unless another format was requested
+fn print_config(config: &Config) -> Result<(), AppError> {
+    let text = toml::to_string_pretty(config).map_err(|e| AppError::Other(e.to_string()))?;
+    print_output(config, |_| print!("{}", text))?;
+    Ok(())
+}
+
+/// Auth context for CLI commands, acting as the logged-in identity, or else
+/// the configured one, if any
+fn get_or_create_auth_context(config: &Config) -> Result<AuthContext, AppError> {
+    if let Some(auth_context) =
+        session_auth_context().map_err(|e| AppError::Other(e.to_string()))?
+    {
+        return Ok(auth_context);
+    }
+    let Some(key_path) = &config.identity.key_path else {
+        // For now, just create a simple auth context for demo purposes
+        return Ok(AuthContext::new("demo_user"));
+    };
+    // A hardware key signs through its token; the auth context only needs
+    // the public identity
+    let identity =
+        if keystore::is_token_file(key_path).map_err(|e| AppError::Other(e.to_string()))? {
+            signer::read_token_file(key_path)
+                .map_err(|e| AppError::Other(e.to_string()))?
+                .identity
+        } else {
+            keystore::open_identity(key_path).map_err(|e| AppError::Other(e.to_string()))?
+        };
+    let mut auth_context = AuthContext::new(identity.did());
+    auth_context.register_identity(identity);
+    Ok(auth_context)
+}
+
+/// Auth context of the operator identity, for commands that must not run as
+/// the demo user
+fn operator_auth_context(config: &Config) -> Result<AuthContext, AppError> {
+    if let Some(auth_context) =
+        session_auth_context().map_err(|e| AppError::Other(e.to_string()))?
+    {
+        return Ok(auth_context);
+    }
+    if config.identity.key_path.is_none() {
+        return Err(AppError::Other(format!(
+            "This command needs an operator identity; run `identity login`, or set identity.key_path or {}",
+            IDENTITY_KEY_ENV
+        )));
+    }
+    get_or_create_auth_context(config)
 }
 
 fn setup_storage(storage_backend: &str, storage_path: &str) -> Result<InMemoryStorage, AppError> {
@@ -0,0 +1,527 @@
+//! Micro-benchmarks and an execution profiler
+//!
+//! The micro-benchmarks time the VM's building blocks in isolation: op
+//! dispatch, storage round trips through `StoreP` and `LoadP`, and DSL
+//! parsing. Each returns [`BenchStats`] along with a throughput, so they can
+//! be printed by `icn-covm bench --micro` or asserted on in tests:
+//!
+//! ```
+//! use icn_covm::perf::{bench_op_dispatch, MicroOptions};
+//!
+//! let result = bench_op_dispatch(&MicroOptions { warmup: 0, iterations: 3 }).unwrap();
+//! assert_eq!(result.stats.samples, 3);
+//! ```
+//!
+//! A [`Profiler`] attached to a VM with [`VM::set_profiler`] records the time
+//! spent in each op. Time in ops nested in blocks and function calls counts
+//! towards their own type and is left out of the enclosing op's self time.
+//! [`profile_dsl`] runs a program once with a profiler and also reports the
+//! hottest lines; a block's time, and the time of any function it calls,
+//! belongs to the top-level line it starts on.
+
+use crate::compiler::{parse_dsl, parse_dsl_with_lines};
+use crate::storage::auth::AuthContext;
+use crate::storage::implementations::in_memory::InMemoryStorage;
+use crate::typed::TypedValue;
+use crate::vm::{Op, VMError, VM};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::mem::Discriminant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Summary statistics of a set of timings, in microseconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchStats {
+    pub samples: usize,
+    pub mean_us: f64,
+    pub stddev_us: f64,
+    pub min_us: f64,
+    pub median_us: f64,
+    pub p95_us: f64,
+    pub max_us: f64,
+    /// Lower bound of the 95% confidence interval for the mean
+    pub ci95_low_us: f64,
+    /// Upper bound of the 95% confidence interval for the mean
+    pub ci95_high_us: f64,
+}
+
+/// Two-sided 95% critical value of Student's t distribution
+fn t_critical_95(df: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match df {
+        0 => f64::INFINITY,
+        1..=30 => TABLE[df - 1],
+        31..=60 => 2.000,
+        61..=120 => 1.980,
+        _ => 1.960,
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl BenchStats {
+    /// Summarize `samples`, which must not be empty
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        let mut values: Vec<f64> = samples
+            .iter()
+            .map(|d| d.as_secs_f64() * 1_000_000.0)
+            .collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+
+        let n = values.len();
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let stddev = if n > 1 {
+            (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let margin = if n > 1 {
+            t_critical_95(n - 1) * stddev / (n as f64).sqrt()
+        } else {
+            0.0
+        };
+
+        Self {
+            samples: n,
+            mean_us: mean,
+            stddev_us: stddev,
+            min_us: values[0],
+            median_us: percentile(&values, 50.0),
+            p95_us: percentile(&values, 95.0),
+            max_us: values[n - 1],
+            ci95_low_us: (mean - margin).max(0.0),
+            ci95_high_us: mean + margin,
+        }
+    }
+}
+
+/// How many times to run a micro-benchmark
+#[derive(Debug, Clone, Copy)]
+pub struct MicroOptions {
+    /// Untimed runs before measuring
+    pub warmup: usize,
+    /// Timed runs, at least one
+    pub iterations: usize,
+}
+
+impl Default for MicroOptions {
+    fn default() -> Self {
+        Self {
+            warmup: 3,
+            iterations: 20,
+        }
+    }
+}
+
+/// Result of one micro-benchmark
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MicroBenchmark {
+    pub name: String,
+    /// What one unit of work is, such as `"op"` or `"line"`
+    pub unit: String,
+    /// Units of work in each timed run
+    pub units_per_run: usize,
+    pub stats: BenchStats,
+    /// Units of work per second at the mean run time
+    pub per_second: f64,
+}
+
+impl MicroBenchmark {
+    fn new(name: &str, unit: &str, units_per_run: usize, stats: BenchStats) -> Self {
+        let per_second = if stats.mean_us > 0.0 {
+            units_per_run as f64 / (stats.mean_us / 1_000_000.0)
+        } else {
+            0.0
+        };
+        Self {
+            name: name.to_string(),
+            unit: unit.to_string(),
+            units_per_run,
+            stats,
+            per_second,
+        }
+    }
+}
+
+/// Time `run`, which returns how long its measured part took, after
+/// `options.warmup` untimed runs
+///
+/// Letting `run` time itself keeps setup such as building a fresh VM out of
+/// the measurement.
+pub fn measure<E>(
+    options: &MicroOptions,
+    mut run: impl FnMut() -> Result<Duration, E>,
+) -> Result<BenchStats, E> {
+    for _ in 0..options.warmup {
+        run()?;
+    }
+    let mut samples = Vec::with_capacity(options.iterations.max(1));
+    for _ in 0..options.iterations.max(1) {
+        samples.push(run()?);
+    }
+    Ok(BenchStats::from_samples(&samples))
+}
+
+/// Ops in each run of [`bench_op_dispatch`]
+const DISPATCH_OPS: usize = 1_000;
+/// Keys written and read back in each run of [`bench_storage_round_trip`]
+const ROUND_TRIP_KEYS: usize = 100;
+/// Lines of DSL in each run of [`bench_parser`]
+const PARSER_LINES: usize = 1_000;
+
+/// Namespace the storage benchmarks and [`profile_dsl`] run in
+const BENCH_NAMESPACE: &str = "bench";
+
+/// A VM over fresh in-memory storage that may read and write
+/// [`BENCH_NAMESPACE`]
+fn bench_vm() -> VM<InMemoryStorage> {
+    let mut auth = AuthContext::new("did:icn:bench");
+    auth.add_role(BENCH_NAMESPACE, "admin");
+    let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+    vm.set_auth_context(auth);
+    vm.set_namespace(BENCH_NAMESPACE);
+    vm
+}
+
+/// Dispatch of cheap stack ops, which is mostly the interpreter's overhead
+pub fn bench_op_dispatch(options: &MicroOptions) -> Result<MicroBenchmark, VMError> {
+    let ops: Vec<Op> = (0..DISPATCH_OPS / 2)
+        .flat_map(|_| [Op::Push(TypedValue::Number(1.0)), Op::Pop])
+        .collect();
+    let stats = measure::<VMError>(options, || {
+        let mut vm = VM::<InMemoryStorage>::new();
+        let started = Instant::now();
+        vm.execute(&ops)?;
+        Ok(started.elapsed())
+    })?;
+    Ok(MicroBenchmark::new("op_dispatch", "op", ops.len(), stats))
+}
+
+/// `StoreP` of a value followed by `LoadP` of it, against in-memory storage
+pub fn bench_storage_round_trip(options: &MicroOptions) -> Result<MicroBenchmark, VMError> {
+    let ops: Vec<Op> = (0..ROUND_TRIP_KEYS)
+        .flat_map(|i| {
+            let key = format!("bench/key{}", i);
+            [
+                Op::Push(TypedValue::Number(i as f64)),
+                Op::StoreP(key.clone()),
+                Op::LoadP(key),
+                Op::Pop,
+            ]
+        })
+        .collect();
+    let stats = measure::<VMError>(options, || {
+        let mut vm = bench_vm();
+        let started = Instant::now();
+        vm.execute(&ops)?;
+        Ok(started.elapsed())
+    })?;
+    Ok(MicroBenchmark::new(
+        "storage_round_trip",
+        "round trip",
+        ROUND_TRIP_KEYS,
+        stats,
+    ))
+}
+
+/// Parsing of DSL with a mix of plain lines and blocks
+pub fn bench_parser(options: &MicroOptions) -> Result<MicroBenchmark, Box<dyn Error>> {
+    let mut source = String::new();
+    let mut lines = 0;
+    while lines < PARSER_LINES {
+        source.push_str(
+            "push 1\npush 2\nadd\nstore total\nloop 2:\n    load total\n    push 1\n    \
+             add\n",
+        );
+        lines += 8;
+    }
+    let stats = measure(options, || -> Result<Duration, Box<dyn Error>> {
+        let started = Instant::now();
+        parse_dsl(&source)?;
+        Ok(started.elapsed())
+    })?;
+    Ok(MicroBenchmark::new("parser", "line", lines, stats))
+}
+
+/// Run every micro-benchmark
+pub fn micro_benchmarks(options: &MicroOptions) -> Result<Vec<MicroBenchmark>, Box<dyn Error>> {
+    Ok(vec![
+        bench_op_dispatch(options)?,
+        bench_storage_round_trip(options)?,
+        bench_parser(options)?,
+    ])
+}
+
+/// Time and count of one type of op
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpProfile {
+    /// Name of the op, such as `"LoadP"`
+    pub op: String,
+    pub count: u64,
+    /// Time in these ops, not counting ops nested in them
+    pub self_us: f64,
+    /// Time in these ops including nested ops
+    pub total_us: f64,
+}
+
+/// Time spent on one top-level line of a DSL program
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineProfile {
+    /// 1-indexed line number
+    pub line: usize,
+    pub source: String,
+    /// Times the line's op ran
+    pub count: u64,
+    pub total_us: f64,
+}
+
+/// What a [`Profiler`] recorded, hottest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileReport {
+    /// Time in top-level ops
+    pub total_us: f64,
+    pub ops: Vec<OpProfile>,
+    /// Empty unless the program's source lines are known
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<LineProfile>,
+}
+
+#[derive(Debug)]
+struct OpTotals {
+    name: String,
+    count: u64,
+    self_time: Duration,
+    total_time: Duration,
+}
+
+#[derive(Debug)]
+struct Frame {
+    kind: Discriminant<Op>,
+    started: Instant,
+    nested: Duration,
+    top_level: usize,
+}
+
+#[derive(Debug, Default)]
+struct ProfileState {
+    frames: Vec<Frame>,
+    ops: HashMap<Discriminant<Op>, OpTotals>,
+    /// Count and time of each top-level op, by its position in the program
+    top_level: Vec<(u64, Duration)>,
+    next_top_level: usize,
+}
+
+/// Records time per op while attached to a VM
+///
+/// Clones share their recordings, so a profiler can be attached to a VM and
+/// read from elsewhere, and forks of a profiled VM report to the same one.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    state: Arc<Mutex<ProfileState>>,
+}
+
+/// Times an op from its creation until it is dropped
+pub(crate) struct ProfileGuard {
+    state: Arc<Mutex<ProfileState>>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start timing `op`; it is recorded when the guard is dropped, however
+    /// the op finishes
+    pub(crate) fn enter(&self, op: &Op) -> ProfileGuard {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let kind = std::mem::discriminant(op);
+        state.ops.entry(kind).or_insert_with(|| OpTotals {
+            name: op_name(op),
+            count: 0,
+            self_time: Duration::ZERO,
+            total_time: Duration::ZERO,
+        });
+        let top_level = match state.frames.last() {
+            Some(parent) => parent.top_level,
+            None => {
+                state.next_top_level += 1;
+                state.next_top_level - 1
+            }
+        };
+        state.frames.push(Frame {
+            kind,
+            started: Instant::now(),
+            nested: Duration::ZERO,
+            top_level,
+        });
+        ProfileGuard {
+            state: Arc::clone(&self.state),
+        }
+    }
+
+    /// Forget everything recorded so far
+    pub fn reset(&self) {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) = ProfileState::default();
+    }
+
+    /// Time per op type, without line information
+    pub fn report(&self) -> ProfileReport {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut ops: Vec<OpProfile> = state
+            .ops
+            .values()
+            .filter(|totals| totals.count > 0)
+            .map(|totals| OpProfile {
+                op: totals.name.clone(),
+                count: totals.count,
+                self_us: micros(totals.self_time),
+                total_us: micros(totals.total_time),
+            })
+            .collect();
+        ops.sort_by(|a, b| b.self_us.total_cmp(&a.self_us));
+        ProfileReport {
+            total_us: state.top_level.iter().map(|(_, time)| micros(*time)).sum(),
+            ops,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Time per op type and per line of `source`, where `op_lines` gives the
+    /// line of each top-level op, as from [`parse_dsl_with_lines`]
+    pub fn report_with_lines(&self, source: &str, op_lines: &[usize]) -> ProfileReport {
+        let mut report = self.report();
+        let source_lines: Vec<&str> = source.lines().collect();
+        let mut by_line: HashMap<usize, (u64, Duration)> = HashMap::new();
+        {
+            let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            for (index, (count, time)) in state.top_level.iter().enumerate() {
+                if let Some(&line) = op_lines.get(index) {
+                    let entry = by_line.entry(line).or_default();
+                    entry.0 += count;
+                    entry.1 += *time;
+                }
+            }
+        }
+        report.lines = by_line
+            .into_iter()
+            .map(|(line, (count, time))| LineProfile {
+                line,
+                source: source_lines
+                    .get(line - 1)
+                    .map(|text| text.trim().to_string())
+                    .unwrap_or_default(),
+                count,
+                total_us: micros(time),
+            })
+            .collect();
+        report
+            .lines
+            .sort_by(|a, b| b.total_us.total_cmp(&a.total_us).then(a.line.cmp(&b.line)));
+        report
+    }
+}
+
+impl Drop for ProfileGuard {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(frame) = state.frames.pop() else {
+            return;
+        };
+        let elapsed = frame.started.elapsed();
+        if let Some(parent) = state.frames.last_mut() {
+            parent.nested += elapsed;
+        } else {
+            if state.top_level.len() <= frame.top_level {
+                state
+                    .top_level
+                    .resize(frame.top_level + 1, (0, Duration::ZERO));
+            }
+            let entry = &mut state.top_level[frame.top_level];
+            entry.0 += 1;
+            entry.1 += elapsed;
+        }
+        if let Some(totals) = state.ops.get_mut(&frame.kind) {
+            totals.count += 1;
+            totals.self_time += elapsed.saturating_sub(frame.nested);
+            totals.total_time += elapsed;
+        }
+    }
+}
+
+/// The variant name of `op`, such as `"LoadP"` for `LoadP("votes")`
+fn op_name(op: &Op) -> String {
+    let debug = format!("{:?}", op);
+    let end = debug
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(debug.len());
+    debug[..end].to_string()
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+/// Run a DSL program once with a profiler attached, reporting time per op
+/// type and the hottest lines
+///
+/// The program runs as an admin of its own namespace over fresh in-memory
+/// storage. It is profiled even if it fails, so the error is returned
+/// alongside the report.
+pub fn profile_dsl(
+    source: &str,
+    parameters: &HashMap<String, String>,
+) -> Result<(ProfileReport, Option<VMError>), Box<dyn Error>> {
+    let (ops, op_lines, _) = parse_dsl_with_lines(source)?;
+    let mut vm = bench_vm();
+    vm.set_parameters(parameters.clone())?;
+    let profiler = Profiler::new();
+    vm.set_profiler(Some(profiler.clone()));
+    let error = vm.execute(&ops).err();
+    Ok((profiler.report_with_lines(source, &op_lines), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_attributes_time_to_ops_and_lines() {
+        let source = "push 0\nstore total\nloop 20:\n    load total\n    push 1\n    add\n    \
+                      store total\nemit \"done\"\n";
+        let (report, error) = profile_dsl(source, &HashMap::new()).unwrap();
+        assert!(error.is_none());
+
+        let count = |name: &str| report.ops.iter().find(|o| o.op == name).unwrap().count;
+        assert_eq!(count("Loop"), 1);
+        assert_eq!(count("Add"), 20);
+        assert_eq!(count("Emit"), 1);
+        for op in &report.ops {
+            assert!(op.self_us <= op.total_us);
+        }
+
+        // The loop's body counts towards the line that opens it
+        assert_eq!(report.lines.len(), 4);
+        assert_eq!(report.lines[0].line, 3);
+        assert_eq!(report.lines[0].source, "loop 20:");
+        let line_total: f64 = report.lines.iter().map(|l| l.total_us).sum();
+        assert!((line_total - report.total_us).abs() < 1e-6);
+
+        let micro = MicroOptions {
+            warmup: 0,
+            iterations: 2,
+        };
+        let round_trip = bench_storage_round_trip(&micro).unwrap();
+        assert_eq!(round_trip.stats.samples, 2);
+        assert_eq!(round_trip.units_per_run, ROUND_TRIP_KEYS);
+    }
+}
@@ -1,4 +1,6 @@
 use crate::identity::Identity;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::StorageBackend;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display};
@@ -12,6 +14,42 @@ pub struct RoleAssignment {
     pub role: String,
 }
 
+/// Namespace holding identity records and the roles and memberships token
+/// issuance reads
+pub const IDENTITY_NAMESPACE: &str = "identity";
+
+/// Storage key holding the role assignments of an identity
+pub fn roles_key(identity_did: &str) -> String {
+    format!("identities/{}/roles", identity_did)
+}
+
+/// Storage key holding the membership namespaces of an identity
+pub fn memberships_key(identity_did: &str) -> String {
+    format!("identities/{}/memberships", identity_did)
+}
+
+/// Quota of the `system` account and of the namespaces holding the records
+/// the server writes as it, such as the audit log
+pub const SYSTEM_ACCOUNT_QUOTA: u64 = 1024 * 1024 * 1024;
+
+/// Create the account of `auth` and `namespace` for records the server
+/// writes itself, keeping either if it already exists
+pub fn ensure_system_namespace<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: &AuthContext,
+    namespace: &str,
+) -> StorageResult<()> {
+    // Backends report an account or namespace that exists as a transaction error
+    match storage.create_account(Some(auth), auth.user_id(), SYSTEM_ACCOUNT_QUOTA) {
+        Ok(()) | Err(StorageError::TransactionError { .. }) => {}
+        Err(e) => return Err(e),
+    }
+    match storage.create_namespace(Some(auth), namespace, SYSTEM_ACCOUNT_QUOTA, None) {
+        Ok(()) | Err(StorageError::TransactionError { .. }) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
 /// Represents a membership relationship between an identity and a namespace (typically a cooperative)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Membership {
@@ -0,0 +1,93 @@
+//! Content-addressed blob storage
+//!
+//! Blobs are stored in a namespace under `blobs/{sha256}`, so identical
+//! content is stored once no matter how many records refer to it, and the
+//! key of a blob verifies its content when it is read back. Records that
+//! refer to a blob keep its hash rather than a copy of its bytes.
+
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::StorageBackend;
+use sha2::{Digest, Sha256};
+
+/// Key prefix under which blobs are stored
+pub const BLOB_PREFIX: &str = "blobs/";
+
+/// Hex-encoded SHA-256 hash addressing `data`
+pub fn blob_hash(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Storage key of the blob with the given hash
+pub fn blob_key(hash: &str) -> String {
+    format!("{}{}", BLOB_PREFIX, hash)
+}
+
+/// Store `data` as a blob and return its hash
+///
+/// Content that is already stored is not written again.
+pub fn put_blob<S>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    data: Vec<u8>,
+) -> StorageResult<String>
+where
+    S: StorageBackend + ?Sized,
+{
+    let hash = blob_hash(&data);
+    let key = blob_key(&hash);
+    if !storage.contains(auth, namespace, &key)? {
+        storage.set(auth, namespace, &key, data)?;
+    }
+    Ok(hash)
+}
+
+/// Read the blob with the given hash, checking that its content matches
+pub fn get_blob<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    hash: &str,
+) -> StorageResult<Vec<u8>>
+where
+    S: StorageBackend + ?Sized,
+{
+    let data = storage.get(auth, namespace, &blob_key(hash))?;
+    let actual = blob_hash(&data);
+    if actual != hash {
+        return Err(StorageError::InvalidDataFormat {
+            expected: format!("blob with SHA-256 {}", hash),
+            received: format!("blob with SHA-256 {}", actual),
+            details: "Blob content does not match its address".to_string(),
+        });
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_blobs_are_deduplicated_and_verified() {
+        let mut storage = InMemoryStorage::new();
+        let mut admin = AuthContext::new("admin");
+        admin.add_role("global", "admin");
+        storage.create_account(Some(&admin), "admin", 1000).unwrap();
+
+        let hash = put_blob(&mut storage, Some(&admin), "docs", b"minutes".to_vec()).unwrap();
+        let again = put_blob(&mut storage, Some(&admin), "docs", b"minutes".to_vec()).unwrap();
+        assert_eq!(hash, again);
+        assert_eq!(
+            get_blob(&storage, Some(&admin), "docs", &hash).unwrap(),
+            b"minutes"
+        );
+
+        storage
+            .set(Some(&admin), "docs", &blob_key(&hash), b"tampered".to_vec())
+            .unwrap();
+        assert!(get_blob(&storage, Some(&admin), "docs", &hash).is_err());
+    }
+}
@@ -2,6 +2,9 @@ use crate::storage::auth::AuthContext;
 use crate::storage::errors::{StorageError, StorageResult};
 use crate::storage::events::StorageEvent;
 use crate::storage::namespaces::NamespaceMetadata;
+use crate::storage::partition::{self, PartitionAccess};
+use crate::storage::resource::ResourceAccount;
+use crate::storage::snapshot::{SnapshotEntry, StorageSnapshot};
 use crate::storage::traits::StorageBackend;
 use crate::storage::utils::{now, now_with_default, Timestamp};
 use crate::storage::versioning::{DiffChange, VersionDiff, VersionInfo};
@@ -13,6 +16,13 @@ use std::fs::{self, create_dir_all, File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 
+/// The directories `restore` replaces
+const RESTORE_DIRS: [&str; 2] = ["namespaces", "accounts"];
+
+/// Where `restore` keeps the replaced directories until the new ones are
+/// written
+const RESTORE_PREVIOUS_DIR: &str = "restore-previous";
+
 /// Represents a file-based persistent storage implementation.
 ///
 /// The FileStorage organizes data in a hierarchical directory structure:
@@ -26,6 +36,7 @@ use std::path::{Path, PathBuf};
 /// - accounts/ - User account information
 /// - audit_logs/ - Append-only logs of all operations
 /// - transactions/ - Transaction logs and rollback information
+/// - restore-previous/ - The namespaces and accounts a restore is replacing
 pub struct FileStorage {
     /// Root path for all storage
     root_path: PathBuf,
@@ -502,6 +513,70 @@ impl FileStorage {
         Ok(data)
     }
 
+    /// Moves the directories a restore replaces from `from` to `to`
+    fn swap_restore_dirs(&self, from: &Path, to: &Path) -> StorageResult<()> {
+        for dir in RESTORE_DIRS {
+            let path = from.join(dir);
+            if path.exists() {
+                fs::rename(&path, to.join(dir))
+                    .map_err(|e| self.map_io_error(e, dir, None, "moving for restore"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the namespaces, keys and accounts of `snapshot` into empty
+    /// namespace and account directories
+    fn write_snapshot(&self, snapshot: &StorageSnapshot) -> StorageResult<()> {
+        for dir in RESTORE_DIRS {
+            create_dir_all(self.root_path.join(dir))
+                .map_err(|e| self.map_io_error(e, dir, None, "creating for restore"))?;
+        }
+        for metadata in snapshot.all_namespaces() {
+            create_dir_all(self.namespace_path(&metadata.path).join("keys")).map_err(|e| {
+                self.map_io_error(e, &metadata.path, None, "creating namespace keys directory")
+            })?;
+            self.write_namespace_metadata(&metadata)?;
+        }
+        for entry in &snapshot.entries {
+            let metadata = KeyMetadata {
+                key: entry.key.clone(),
+                created_by: entry.version.created_by.clone(),
+                created_at: entry.version.timestamp,
+                versions: vec![entry.version.clone()],
+            };
+            self.write_key_metadata(&entry.namespace, &entry.key, &metadata)?;
+            self.write_version_data(
+                &entry.namespace,
+                &entry.key,
+                entry.version.version,
+                &entry.value,
+            )?;
+        }
+        for account in &snapshot.accounts {
+            let account = FileResourceAccount {
+                user_id: account.owner_id.clone(),
+                quota_bytes: account.storage_quota_bytes,
+                used_bytes: account.storage_used_bytes,
+                created_at: account.last_updated,
+                last_updated: account.last_updated,
+            };
+            let account_path = self
+                .root_path
+                .join("accounts")
+                .join(format!("{}.json", account.user_id()));
+            let account_json = serde_json::to_string_pretty(&account).map_err(|e| {
+                StorageError::SerializationError {
+                    data_type: "FileResourceAccount".to_string(),
+                    details: e.to_string(),
+                }
+            })?;
+            fs::write(account_path, account_json)?;
+        }
+
+        Ok(())
+    }
+
     /// Writes a namespace metadata file
     fn write_namespace_metadata(&self, metadata: &NamespaceMetadata) -> StorageResult<()> {
         let path = self.namespace_metadata_path(&metadata.path);
@@ -625,6 +700,31 @@ impl FileStorage {
         self.namespace_cache.contains_key(namespace) || self.namespace_path(namespace).exists()
     }
 
+    /// Collects the metadata of every key under a namespace's keys
+    /// directory; keys containing `/` are nested a directory per segment
+    fn collect_key_metadata(&self, dir: &Path, found: &mut Vec<KeyMetadata>) -> StorageResult<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        let metadata_path = dir.join("metadata.json");
+        if metadata_path.is_file() {
+            let metadata_str = fs::read_to_string(&metadata_path)?;
+            found.push(serde_json::from_str(&metadata_str).map_err(|e| {
+                StorageError::SerializationError {
+                    data_type: "KeyMetadata".to_string(),
+                    details: format!("{}: {}", metadata_path.display(), e),
+                }
+            })?);
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.collect_key_metadata(&path, found)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Records an operation for potential rollback
     fn record_for_rollback(&mut self, op: TransactionOp) -> StorageResult<()> {
         if let Some(tx) = self.transactions.last_mut() {
@@ -648,6 +748,43 @@ impl FileStorage {
             return Ok(());
         }
 
+        // Other cooperatives' namespaces are reached only through grants
+        let lookup = |ns: &str, key: &str| {
+            let metadata = self.read_key_metadata(ns, key).ok()?;
+            let latest = metadata.versions.last()?;
+            self.read_version_data(ns, key, latest.version).ok()
+        };
+        match partition::check(auth, action, namespace, lookup) {
+            PartitionAccess::Open => {}
+            PartitionAccess::Granted(grant) => {
+                self.record_audit_log(
+                    auth,
+                    "partition_grant",
+                    namespace,
+                    None,
+                    &format!("{} granted to {} by {}", action, grant.grantee, grant.owner),
+                )?;
+                return Ok(());
+            }
+            PartitionAccess::Denied { partition } => {
+                self.record_audit_log(
+                    auth,
+                    "partition_denied",
+                    namespace,
+                    None,
+                    &format!(
+                        "{} outside the caller's partitions, in {}",
+                        action, partition
+                    ),
+                )?;
+                return Err(StorageError::PermissionDenied {
+                    user_id: auth.user_id_cloneable(),
+                    action: action.to_string(),
+                    key: namespace.to_string(),
+                });
+            }
+        }
+
         // Check namespace admin
         if auth.has_role(namespace, "admin") {
             return Ok(());
@@ -795,6 +932,7 @@ impl StorageBackend for FileStorage {
     ) -> StorageResult<()> {
         // Check permissions
         self.check_permission(auth, "write", namespace)?;
+        partition::check_grant_change(auth, namespace, key)?;
 
         // Check if namespace exists
         if !self.namespace_exists(namespace) {
@@ -944,6 +1082,7 @@ impl StorageBackend for FileStorage {
     ) -> StorageResult<()> {
         // Check write permission
         self.check_permission(auth, "write", namespace)?;
+        partition::check_grant_change(auth, namespace, key)?;
 
         // Check if the namespace exists
         if !self.namespace_exists(namespace) {
@@ -1689,4 +1828,90 @@ impl StorageBackend for FileStorage {
 
         Ok(metadata_path.exists())
     }
+
+    fn snapshot(&self) -> StorageResult<StorageSnapshot> {
+        let mut snapshot = StorageSnapshot::new();
+        for (namespace, metadata) in &self.namespace_cache {
+            snapshot.namespaces.push(metadata.clone());
+
+            let mut keys = Vec::new();
+            self.collect_key_metadata(&self.namespace_path(namespace).join("keys"), &mut keys)?;
+            for key_metadata in keys {
+                let Some(version) = key_metadata.versions.last().cloned() else {
+                    continue;
+                };
+                let value =
+                    self.read_version_data(namespace, &key_metadata.key, version.version)?;
+                snapshot.entries.push(SnapshotEntry {
+                    namespace: namespace.clone(),
+                    key: key_metadata.key,
+                    value,
+                    version,
+                });
+            }
+        }
+        snapshot.accounts = self
+            .account_cache
+            .values()
+            .map(|account| ResourceAccount {
+                owner_id: account.user_id_cloneable(),
+                storage_quota_bytes: account.quota_bytes,
+                storage_used_bytes: account.used_bytes,
+                last_updated: account.last_updated,
+            })
+            .collect();
+        Ok(snapshot.sorted())
+    }
+
+    fn restore(&mut self, snapshot: StorageSnapshot) -> StorageResult<()> {
+        if !self.transactions.is_empty() {
+            return Err(StorageError::TransactionError {
+                details: "Cannot restore a snapshot inside a transaction".to_string(),
+            });
+        }
+        snapshot.validate()?;
+
+        // Namespaces nest inside each other's directories, so the whole tree
+        // is replaced rather than one namespace at a time. The old tree is
+        // moved aside first and put back if writing the new one fails.
+        let previous = self.root_path.join(RESTORE_PREVIOUS_DIR);
+        fs::create_dir(&previous).map_err(|e| StorageError::IoError {
+            operation: "restore".to_string(),
+            details: format!(
+                "Cannot keep the current data in {}, which a failed restore may have \
+                 left behind: {}",
+                previous.display(),
+                e
+            ),
+        })?;
+        let moved = self.swap_restore_dirs(&self.root_path, &previous);
+        let moved_all = moved.is_ok();
+        let result = moved
+            .and_then(|()| self.write_snapshot(&snapshot))
+            .and_then(|()| self.load_namespace_cache())
+            .and_then(|()| self.load_account_cache());
+        match result {
+            Ok(()) => {
+                // The restore has taken effect; a leftover copy only stops
+                // the next restore, which reports where it is
+                let _ = fs::remove_dir_all(&previous);
+                Ok(())
+            }
+            Err(e) => {
+                // Best effort: the error that stopped the restore is the one
+                // worth reporting
+                if moved_all {
+                    for dir in RESTORE_DIRS {
+                        let _ = fs::remove_dir_all(self.root_path.join(dir));
+                    }
+                }
+                if self.swap_restore_dirs(&previous, &self.root_path).is_ok() {
+                    let _ = fs::remove_dir(&previous);
+                }
+                let _ = self.load_namespace_cache();
+                let _ = self.load_account_cache();
+                Err(e)
+            }
+        }
+    }
 }
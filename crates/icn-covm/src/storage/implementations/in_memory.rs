@@ -18,12 +18,15 @@
 use serde::{de::DeserializeOwned, Serialize};
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::{Arc, Mutex};
 
 use crate::storage::auth::AuthContext;
 use crate::storage::errors::{StorageError, StorageResult};
 use crate::storage::events::StorageEvent;
 use crate::storage::namespaces::NamespaceMetadata;
+use crate::storage::partition::{self, PartitionAccess};
 use crate::storage::resource::ResourceAccount;
+use crate::storage::snapshot::{implicit_namespace, SnapshotEntry, StorageSnapshot};
 use crate::storage::traits::StorageBackend;
 use crate::storage::utils::now;
 use crate::storage::utils::now_with_default;
@@ -58,8 +61,9 @@ pub struct InMemoryStorage {
     versions: HashMap<String, HashMap<String, VersionInfo>>,
     /// User accounts: User ID -> ResourceAccount
     accounts: HashMap<String, ResourceAccount>,
-    /// Audit log of all operations
-    audit_log: Vec<StorageEvent>,
+    /// Audit log of all operations, shared with clones; locked so that
+    /// permission checks, which only borrow the storage, can record to it
+    audit_log: Arc<Mutex<Vec<StorageEvent>>>,
     /// Transaction support: Stack of operations to rollback
     /// Each operation is (namespace, key, Option<old_value>)
     /// None means the key didn't exist before the transaction started.
@@ -88,7 +92,7 @@ impl InMemoryStorage {
             data: HashMap::new(),
             versions: HashMap::new(),
             accounts: HashMap::new(),
-            audit_log: Vec::new(),
+            audit_log: Arc::new(Mutex::new(Vec::new())),
             transaction_stack: Vec::new(),
         }
     }
@@ -133,7 +137,7 @@ impl InMemoryStorage {
     /// * `key` - Key that was affected
     /// * `details` - Additional information about the operation
     fn emit_event(
-        &mut self,
+        &self,
         event_type: &str,
         auth: &AuthContext,
         namespace: &str,
@@ -141,7 +145,11 @@ impl InMemoryStorage {
         details: &str,
     ) {
         // TODO: Consider making event emission configurable or optional
-        self.audit_log.push(StorageEvent {
+        let mut audit_log = self
+            .audit_log
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        audit_log.push(StorageEvent {
             event_type: event_type.to_string(),
             user_id: auth.user_id_cloneable(),
             namespace: namespace.to_string(),
@@ -278,6 +286,7 @@ impl StorageBackend for InMemoryStorage {
         value: Vec<u8>,
     ) -> StorageResult<()> {
         self.check_permission(auth, "write", namespace)?;
+        partition::check_grant_change(auth, namespace, key)?;
 
         let value_size = value.len() as u64;
         let internal_key = Self::make_internal_key(namespace, key);
@@ -419,6 +428,39 @@ impl StorageBackend for InMemoryStorage {
             return Ok(());
         }
 
+        // Other cooperatives' namespaces are reached only through grants
+        let lookup = |ns: &str, key: &str| self.data.get(ns)?.get(key).cloned();
+        match partition::check(auth, action, namespace, lookup) {
+            PartitionAccess::Open => {}
+            PartitionAccess::Granted(grant) => {
+                self.emit_event(
+                    "partition_grant",
+                    auth,
+                    namespace,
+                    "",
+                    &format!("{} granted to {} by {}", action, grant.grantee, grant.owner),
+                );
+                return Ok(());
+            }
+            PartitionAccess::Denied { partition } => {
+                self.emit_event(
+                    "partition_denied",
+                    auth,
+                    namespace,
+                    "",
+                    &format!(
+                        "{} outside the caller's partitions, in {}",
+                        action, partition
+                    ),
+                );
+                return Err(StorageError::PermissionDenied {
+                    user_id: auth.user_id_cloneable(),
+                    action: action.to_string(),
+                    key: namespace.to_string(),
+                });
+            }
+        }
+
         // Check namespace admin
         if auth.has_role(namespace, "admin") {
             return Ok(());
@@ -524,8 +566,11 @@ impl StorageBackend for InMemoryStorage {
         }
 
         // Filter logic
-        let results: Vec<StorageEvent> = self
+        let audit_log = self
             .audit_log
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let results: Vec<StorageEvent> = audit_log
             .iter()
             .filter(|event| {
                 // Namespace filter: If namespace is Some, event must match.
@@ -775,6 +820,7 @@ impl StorageBackend for InMemoryStorage {
     ) -> StorageResult<()> {
         // Check write permission
         self.check_permission(auth, "write", namespace)?;
+        partition::check_grant_change(auth, namespace, key)?;
 
         // Check if key exists
         if !self
@@ -844,6 +890,63 @@ impl StorageBackend for InMemoryStorage {
             .map(|ns_data| ns_data.contains_key(key))
             .unwrap_or(false))
     }
+
+    fn snapshot(&self) -> StorageResult<StorageSnapshot> {
+        let mut snapshot = StorageSnapshot::new();
+        for (namespace, ns_data) in &self.data {
+            snapshot.namespaces.push(implicit_namespace(namespace));
+            for (key, value) in ns_data {
+                let version = self
+                    .versions
+                    .get(namespace)
+                    .and_then(|ns_versions| ns_versions.get(key))
+                    .cloned()
+                    .ok_or_else(|| StorageError::TransactionError {
+                        details: format!("No version info for existing key {}", key),
+                    })?;
+                snapshot.entries.push(SnapshotEntry {
+                    namespace: namespace.clone(),
+                    key: key.clone(),
+                    value: value.clone(),
+                    version,
+                });
+            }
+        }
+        snapshot.accounts = self.accounts.values().cloned().collect();
+        Ok(snapshot.sorted())
+    }
+
+    fn restore(&mut self, snapshot: StorageSnapshot) -> StorageResult<()> {
+        if !self.transaction_stack.is_empty() {
+            return Err(StorageError::TransactionError {
+                details: "Cannot restore a snapshot inside a transaction".to_string(),
+            });
+        }
+        snapshot.validate()?;
+
+        self.data.clear();
+        self.versions.clear();
+        for metadata in snapshot.all_namespaces() {
+            self.data.insert(metadata.path.clone(), HashMap::new());
+            self.versions.insert(metadata.path, HashMap::new());
+        }
+        for entry in snapshot.entries {
+            self.data
+                .entry(entry.namespace.clone())
+                .or_default()
+                .insert(entry.key.clone(), entry.value);
+            self.versions
+                .entry(entry.namespace)
+                .or_default()
+                .insert(entry.key, entry.version);
+        }
+        self.accounts = snapshot
+            .accounts
+            .into_iter()
+            .map(|account| (account.owner_id.clone(), account))
+            .collect();
+        Ok(())
+    }
 }
 
 #[cfg(test)]
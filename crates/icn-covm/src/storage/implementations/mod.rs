@@ -1,4 +1,5 @@
 // Declare the submodules within the implementations directory
 pub mod file_storage;
 pub mod in_memory;
+pub mod sled_storage;
 // pub mod file_storage; // Add this when file_storage.rs is implemented
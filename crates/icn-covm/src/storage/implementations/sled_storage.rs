@@ -0,0 +1,877 @@
+//! Storage backed by an embedded sled database
+//!
+//! `SledStorage` keeps namespaces, keys, every version of every value,
+//! resource accounts and the audit log in a single
+//! [sled](https://docs.rs/sled) database directory, so a node's state
+//! survives restarts without the thousands of small files `FileStorage`
+//! writes. Select it with `--storage-backend sled` (or `storage.backend =
+//! "sled"`) and point `--storage-path` at the database directory.
+//!
+//! All records live in the default tree under a one-letter prefix, so the
+//! records a write touches are updated together in one atomic batch:
+//!
+//! - `d` `{namespace}` `{key}`: the latest value
+//! - `v` `{namespace}` `{key}`: the latest `VersionInfo`, with its history
+//! - `h` `{namespace}` `{key}` `{version}`: the value of each version
+//! - `n` `{namespace}`: `NamespaceMetadata`
+//! - `a` `{user}`: `ResourceAccount`
+//! - `l` `{id}`: audit log `StorageEvent`s, oldest first
+//!
+//! Parts are separated by a zero byte, and versions and log ids are written
+//! as big-endian integers so they sort in order. Clones share the database;
+//! each clone keeps its own transaction log, which rollback replays to
+//! restore the values and versions a transaction overwrote.
+
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::events::StorageEvent;
+use crate::storage::namespaces::NamespaceMetadata;
+use crate::storage::partition::{self, PartitionAccess};
+use crate::storage::resource::ResourceAccount;
+use crate::storage::snapshot::{SnapshotEntry, StorageSnapshot};
+use crate::storage::traits::StorageBackend;
+use crate::storage::utils::now_with_default;
+use crate::storage::versioning::{DiffChange, VersionDiff, VersionInfo};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Quota of namespaces created implicitly by their first write
+const DEFAULT_NAMESPACE_QUOTA: u64 = 1024 * 1024 * 1024;
+
+/// What a transaction overwrote, so rollback can put it back
+#[derive(Clone)]
+struct UndoEntry {
+    namespace: String,
+    key: String,
+    value: Option<Vec<u8>>,
+    version: Option<VersionInfo>,
+}
+
+/// A `StorageBackend` persisted in an embedded sled database
+#[derive(Clone)]
+pub struct SledStorage {
+    db: sled::Db,
+    /// Undo logs of the open transactions, innermost last
+    transactions: Vec<Vec<UndoEntry>>,
+}
+
+impl fmt::Debug for SledStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SledStorage")
+            .field("size_on_disk", &self.db.size_on_disk().ok())
+            .field("transactions", &self.transactions.len())
+            .finish()
+    }
+}
+
+fn sled_error(operation: &str, error: sled::Error) -> StorageError {
+    StorageError::IoError {
+        operation: operation.to_string(),
+        details: error.to_string(),
+    }
+}
+
+fn record_key(kind: u8, parts: &[&str]) -> Vec<u8> {
+    let mut record = vec![kind];
+    for part in parts {
+        record.push(0);
+        record.extend_from_slice(part.as_bytes());
+    }
+    record
+}
+
+fn history_key(namespace: &str, key: &str, version: u64) -> Vec<u8> {
+    let mut record = record_key(b'h', &[namespace, key]);
+    record.push(0);
+    record.extend_from_slice(&version.to_be_bytes());
+    record
+}
+
+/// Prefix of every record of `kind` in `namespace` whose key starts with
+/// `prefix`
+fn scan_prefix(kind: u8, namespace: &str, prefix: &str) -> Vec<u8> {
+    let mut record = record_key(kind, &[namespace]);
+    record.push(0);
+    record.extend_from_slice(prefix.as_bytes());
+    record
+}
+
+impl SledStorage {
+    /// Open the database in `path`, creating it if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> StorageResult<Self> {
+        let db = sled::open(path.as_ref()).map_err(|e| sled_error("open", e))?;
+        Ok(Self {
+            db,
+            transactions: Vec::new(),
+        })
+    }
+
+    /// Write everything to disk before returning
+    pub fn flush(&self) -> StorageResult<()> {
+        self.db.flush().map_err(|e| sled_error("flush", e))?;
+        Ok(())
+    }
+
+    fn read_raw(&self, record: &[u8]) -> StorageResult<Option<Vec<u8>>> {
+        self.db
+            .get(record)
+            .map(|value| value.map(|value| value.to_vec()))
+            .map_err(|e| sled_error("read", e))
+    }
+
+    fn read_json<T: DeserializeOwned>(&self, record: &[u8]) -> StorageResult<Option<T>> {
+        match self.read_raw(record)? {
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn write_json<T: Serialize>(&self, record: &[u8], value: &T) -> StorageResult<()> {
+        self.db
+            .insert(record, serde_json::to_vec(value)?)
+            .map_err(|e| sled_error("write", e))?;
+        Ok(())
+    }
+
+    fn value(&self, namespace: &str, key: &str) -> StorageResult<Option<Vec<u8>>> {
+        self.read_raw(&record_key(b'd', &[namespace, key]))
+    }
+
+    fn version(&self, namespace: &str, key: &str) -> StorageResult<Option<VersionInfo>> {
+        self.read_json(&record_key(b'v', &[namespace, key]))
+    }
+
+    fn namespace(&self, namespace: &str) -> StorageResult<Option<NamespaceMetadata>> {
+        self.read_json(&record_key(b'n', &[namespace]))
+    }
+
+    fn account(&self, user_id: &str) -> StorageResult<Option<ResourceAccount>> {
+        self.read_json(&record_key(b'a', &[user_id]))
+    }
+
+    /// Bytes held by the latest values in `namespace`
+    fn namespace_bytes(&self, namespace: &str) -> StorageResult<u64> {
+        let mut total = 0;
+        for entry in self.db.scan_prefix(scan_prefix(b'd', namespace, "")) {
+            let (_, value) = entry.map_err(|e| sled_error("scan", e))?;
+            total += value.len() as u64;
+        }
+        Ok(total)
+    }
+
+    /// Append an event to the audit log
+    ///
+    /// Failing to record is logged rather than failing the operation, as the
+    /// in-memory backend cannot fail to record either.
+    fn emit_event(
+        &self,
+        event_type: &str,
+        auth: &AuthContext,
+        namespace: &str,
+        key: &str,
+        details: &str,
+    ) {
+        let event = StorageEvent {
+            event_type: event_type.to_string(),
+            user_id: auth.user_id_cloneable(),
+            namespace: namespace.to_string(),
+            key: key.to_string(),
+            timestamp: now_with_default(),
+            details: details.to_string(),
+        };
+        let recorded = self
+            .db
+            .generate_id()
+            .map_err(|e| sled_error("audit", e))
+            .and_then(|id| {
+                let mut record = vec![b'l', 0];
+                record.extend_from_slice(&id.to_be_bytes());
+                self.write_json(&record, &event)
+            });
+        if let Err(e) = recorded {
+            tracing::warn!(error = %e, event_type, namespace, "Failed to record storage event");
+        }
+    }
+
+    fn record_for_rollback(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        value: Option<Vec<u8>>,
+        version: Option<VersionInfo>,
+    ) {
+        if let Some(transaction) = self.transactions.last_mut() {
+            transaction.push(UndoEntry {
+                namespace: namespace.to_string(),
+                key: key.to_string(),
+                value,
+                version,
+            });
+        }
+    }
+
+    /// Charge or refund the difference between an old and a new value to
+    /// the writer's account, if they have one
+    fn account_for(&self, auth: &AuthContext, old_size: u64, new_size: u64) -> StorageResult<()> {
+        let user_id = auth.user_id_cloneable();
+        let Some(mut account) = self.account(&user_id)? else {
+            return Ok(());
+        };
+        if new_size > old_size {
+            account.add_usage(new_size - old_size)?;
+        } else {
+            account.reduce_usage(old_size - new_size);
+        }
+        self.write_json(&record_key(b'a', &[&user_id]), &account)
+    }
+
+    /// Make the latest value of `namespace`/`key` `value` with `version`,
+    /// dropping the history of later versions; `None` removes the key
+    fn restore_key(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: Option<Vec<u8>>,
+        version: Option<VersionInfo>,
+    ) -> StorageResult<()> {
+        let kept = version.as_ref().map_or(0, |version| version.version);
+        let mut batch = sled::Batch::default();
+        for entry in self.db.scan_prefix(scan_prefix(b'h', namespace, key)) {
+            let (record, _) = entry.map_err(|e| sled_error("scan", e))?;
+            let prefix_len = history_key(namespace, key, 0).len() - 8;
+            if record.len() != prefix_len + 8 {
+                continue; // A longer key that shares this one as a prefix
+            }
+            let mut number = [0; 8];
+            number.copy_from_slice(&record[prefix_len..]);
+            if u64::from_be_bytes(number) > kept {
+                batch.remove(record);
+            }
+        }
+        match (value, version) {
+            (Some(value), Some(version)) => {
+                batch.insert(record_key(b'd', &[namespace, key]), value);
+                batch.insert(
+                    record_key(b'v', &[namespace, key]),
+                    serde_json::to_vec(&version)?,
+                );
+            }
+            _ => {
+                batch.remove(record_key(b'd', &[namespace, key]));
+                batch.remove(record_key(b'v', &[namespace, key]));
+            }
+        }
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| sled_error("rollback", e))
+    }
+}
+
+impl StorageBackend for SledStorage {
+    fn get(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+    ) -> StorageResult<Vec<u8>> {
+        self.check_permission(auth, "read", namespace)?;
+        self.value(namespace, key)?
+            .ok_or_else(|| StorageError::NotFound {
+                key: format!("{}:{}", namespace, key),
+            })
+    }
+
+    fn get_versioned(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+    ) -> StorageResult<(Vec<u8>, VersionInfo)> {
+        let data = self.get(auth, namespace, key)?;
+        let version =
+            self.version(namespace, key)?
+                .ok_or_else(|| StorageError::TransactionError {
+                    details: format!("No version info for existing key {}", key),
+                })?;
+        Ok((data, version))
+    }
+
+    fn get_version(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+        version: u64,
+    ) -> StorageResult<(Vec<u8>, VersionInfo)> {
+        self.check_permission(auth, "read", namespace)?;
+        let not_found = || StorageError::NotFound {
+            key: format!("{} (version {})", key, version),
+        };
+        let latest = self.version(namespace, key)?.ok_or_else(not_found)?;
+        let info = latest.get_version(version).cloned().ok_or_else(not_found)?;
+        let data = self
+            .read_raw(&history_key(namespace, key, version))?
+            .ok_or_else(not_found)?;
+        Ok((data, info))
+    }
+
+    fn list_versions(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+    ) -> StorageResult<Vec<VersionInfo>> {
+        self.check_permission(auth, "read", namespace)?;
+        let latest = self
+            .version(namespace, key)?
+            .ok_or_else(|| StorageError::NotFound {
+                key: key.to_string(),
+            })?;
+        Ok(latest.get_version_history().into_iter().cloned().collect())
+    }
+
+    fn diff_versions(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+        v1: u64,
+        v2: u64,
+    ) -> StorageResult<VersionDiff<Vec<u8>>> {
+        let (old_value, _) = self.get_version(auth, namespace, key, v1)?;
+        let (new_value, _) = self.get_version(auth, namespace, key, v2)?;
+        let mut changes = Vec::new();
+        if old_value != new_value {
+            changes.push(DiffChange::ValueChanged {
+                path: "data".to_string(),
+                old_value,
+                new_value,
+            });
+        }
+        Ok(VersionDiff {
+            old_version: v1,
+            new_version: v2,
+            created_by: auth
+                .map(|a| a.user_id_cloneable())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: now_with_default(),
+            changes,
+        })
+    }
+
+    fn set(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+        value: Vec<u8>,
+    ) -> StorageResult<()> {
+        self.check_permission(auth, "write", namespace)?;
+        partition::check_grant_change(auth, namespace, key)?;
+        let auth = auth.ok_or_else(|| StorageError::PermissionDenied {
+            user_id: "anonymous".to_string(),
+            action: "write".to_string(),
+            key: format!("{}:{}", namespace, key),
+        })?;
+
+        let existing = self.value(namespace, key)?;
+        let current_version = self.version(namespace, key)?;
+        let existing_size = existing.as_ref().map_or(0, |v| v.len() as u64);
+        let value_size = value.len() as u64;
+        self.account_for(auth, existing_size, value_size)?;
+        self.record_for_rollback(namespace, key, existing, current_version.clone());
+
+        let user_id = auth.user_id_cloneable();
+        let version = match &current_version {
+            Some(current) => current.next_version(&user_id),
+            None => VersionInfo::new(&user_id),
+        };
+        let mut batch = sled::Batch::default();
+        if self.namespace(namespace)?.is_none() {
+            let metadata = NamespaceMetadata {
+                path: namespace.to_string(),
+                owner: user_id.clone(),
+                quota_bytes: DEFAULT_NAMESPACE_QUOTA,
+                used_bytes: 0,
+                parent: None,
+                attributes: HashMap::new(),
+            };
+            batch.insert(
+                record_key(b'n', &[namespace]),
+                serde_json::to_vec(&metadata)?,
+            );
+        }
+        batch.insert(history_key(namespace, key, version.version), value.clone());
+        batch.insert(
+            record_key(b'v', &[namespace, key]),
+            serde_json::to_vec(&version)?,
+        );
+        batch.insert(record_key(b'd', &[namespace, key]), value);
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| sled_error("write", e))?;
+
+        self.emit_event(
+            "write",
+            auth,
+            namespace,
+            key,
+            &format!("Set v{} ({} bytes)", version.version, value_size),
+        );
+        Ok(())
+    }
+
+    fn contains(
+        &self,
+        _auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+    ) -> StorageResult<bool> {
+        self.db
+            .contains_key(record_key(b'd', &[namespace, key]))
+            .map_err(|e| sled_error("read", e))
+    }
+
+    fn list_keys(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        prefix: Option<&str>,
+    ) -> StorageResult<Vec<String>> {
+        self.check_permission(auth, "read", namespace)?;
+        let skip = scan_prefix(b'd', namespace, "").len();
+        let mut keys = Vec::new();
+        for entry in self
+            .db
+            .scan_prefix(scan_prefix(b'd', namespace, prefix.unwrap_or("")))
+        {
+            let (record, _) = entry.map_err(|e| sled_error("scan", e))?;
+            keys.push(String::from_utf8_lossy(&record[skip..]).into_owned());
+        }
+        Ok(keys)
+    }
+
+    fn list_namespaces(
+        &self,
+        auth: Option<&AuthContext>,
+        parent_namespace: &str,
+    ) -> StorageResult<Vec<NamespaceMetadata>> {
+        self.check_permission(auth, "read", "global")?;
+        let mut namespaces = Vec::new();
+        for entry in self.db.scan_prefix([b'n', 0]) {
+            let (_, value) = entry.map_err(|e| sled_error("scan", e))?;
+            let mut metadata: NamespaceMetadata = serde_json::from_slice(&value)?;
+            if metadata.path.starts_with(parent_namespace) && metadata.path != parent_namespace {
+                metadata.used_bytes = self.namespace_bytes(&metadata.path)?;
+                namespaces.push(metadata);
+            }
+        }
+        Ok(namespaces)
+    }
+
+    fn create_account(
+        &mut self,
+        auth: Option<&AuthContext>,
+        user_id: &str,
+        quota_bytes: u64,
+    ) -> StorageResult<()> {
+        let auth = match auth {
+            Some(auth) if auth.has_role("global", "admin") => auth,
+            _ => {
+                return Err(StorageError::PermissionDenied {
+                    user_id: auth.map_or("anonymous".to_string(), |a| a.user_id_cloneable()),
+                    action: "create_account".to_string(),
+                    key: user_id.to_string(),
+                })
+            }
+        };
+        if self.account(user_id)?.is_some() {
+            return Err(StorageError::TransactionError {
+                details: format!("Account already exists for user {}", user_id),
+            });
+        }
+        self.write_json(
+            &record_key(b'a', &[user_id]),
+            &ResourceAccount::new(user_id, quota_bytes),
+        )?;
+        self.emit_event(
+            "account_created",
+            auth,
+            "global",
+            user_id,
+            &format!("Account created with quota {} bytes", quota_bytes),
+        );
+        Ok(())
+    }
+
+    fn create_namespace(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        quota_bytes: u64,
+        parent: Option<&str>,
+    ) -> StorageResult<()> {
+        let auth = match auth {
+            Some(auth) if auth.has_role("global", "admin") => auth,
+            _ => {
+                return Err(StorageError::PermissionDenied {
+                    user_id: auth.map_or("anonymous".to_string(), |a| a.user_id_cloneable()),
+                    action: "create_namespace".to_string(),
+                    key: namespace.to_string(),
+                })
+            }
+        };
+        if let Some(parent) = parent {
+            if self.namespace(parent)?.is_none() {
+                return Err(StorageError::NotFound {
+                    key: parent.to_string(),
+                });
+            }
+        }
+        if self.namespace(namespace)?.is_some() {
+            return Ok(());
+        }
+        let metadata = NamespaceMetadata {
+            path: namespace.to_string(),
+            owner: auth.user_id_cloneable(),
+            quota_bytes,
+            used_bytes: 0,
+            parent: parent.map(str::to_string),
+            attributes: HashMap::new(),
+        };
+        self.write_json(&record_key(b'n', &[namespace]), &metadata)?;
+        self.emit_event(
+            "namespace_created",
+            auth,
+            "global",
+            namespace,
+            &format!("Namespace created with quota {} bytes", quota_bytes),
+        );
+        Ok(())
+    }
+
+    fn check_permission(
+        &self,
+        auth: Option<&AuthContext>,
+        action: &str,
+        namespace: &str,
+    ) -> StorageResult<()> {
+        let auth = auth.ok_or_else(|| StorageError::PermissionDenied {
+            user_id: "anonymous".to_string(),
+            action: action.to_string(),
+            key: namespace.to_string(),
+        })?;
+        if auth.has_role("global", "admin") {
+            return Ok(());
+        }
+
+        // Other cooperatives' namespaces are reached only through grants
+        let lookup = |ns: &str, key: &str| self.value(ns, key).ok().flatten();
+        match partition::check(auth, action, namespace, lookup) {
+            PartitionAccess::Open => {}
+            PartitionAccess::Granted(grant) => {
+                self.emit_event(
+                    "partition_grant",
+                    auth,
+                    namespace,
+                    "",
+                    &format!("{} granted to {} by {}", action, grant.grantee, grant.owner),
+                );
+                return Ok(());
+            }
+            PartitionAccess::Denied { partition } => {
+                self.emit_event(
+                    "partition_denied",
+                    auth,
+                    namespace,
+                    "",
+                    &format!(
+                        "{} outside the caller's partitions, in {}",
+                        action, partition
+                    ),
+                );
+                return Err(StorageError::PermissionDenied {
+                    user_id: auth.user_id_cloneable(),
+                    action: action.to_string(),
+                    key: namespace.to_string(),
+                });
+            }
+        }
+
+        let allowed: &[&str] = match action {
+            "read" => &["reader", "writer", "admin"],
+            "write" => &["writer", "admin"],
+            _ => &[],
+        };
+        if allowed.iter().any(|role| auth.has_role(namespace, role)) {
+            Ok(())
+        } else {
+            Err(StorageError::PermissionDenied {
+                user_id: auth.user_id_cloneable(),
+                action: action.to_string(),
+                key: namespace.to_string(),
+            })
+        }
+    }
+
+    fn begin_transaction(&mut self) -> StorageResult<()> {
+        self.transactions.push(Vec::new());
+        Ok(())
+    }
+
+    fn commit_transaction(&mut self) -> StorageResult<()> {
+        let undo = self
+            .transactions
+            .pop()
+            .ok_or_else(|| StorageError::TransactionError {
+                details: "No active transaction to commit".to_string(),
+            })?;
+        // An enclosing transaction must still be able to undo these writes
+        if let Some(outer) = self.transactions.last_mut() {
+            outer.extend(undo);
+        }
+        self.flush()
+    }
+
+    fn rollback_transaction(&mut self) -> StorageResult<()> {
+        let undo = self
+            .transactions
+            .pop()
+            .ok_or_else(|| StorageError::TransactionError {
+                details: "No active transaction to rollback".to_string(),
+            })?;
+        for entry in undo.into_iter().rev() {
+            self.restore_key(&entry.namespace, &entry.key, entry.value, entry.version)?;
+        }
+        Ok(())
+    }
+
+    fn get_audit_log(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: Option<&str>,
+        event_type: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<StorageEvent>> {
+        let effective_ns = namespace.unwrap_or("global");
+        let auth = auth.ok_or_else(|| StorageError::AuthenticationError {
+            details: format!(
+                "Authentication required for view_audit_log on {}",
+                effective_ns
+            ),
+        })?;
+        if !auth.has_role("global", "admin") && !auth.has_role(effective_ns, "admin") {
+            return Err(StorageError::PermissionDenied {
+                user_id: auth.user_id_cloneable(),
+                action: "view_audit_log".to_string(),
+                key: effective_ns.to_string(),
+            });
+        }
+
+        // Latest first, as the in-memory backend returns them
+        let mut events = Vec::new();
+        for entry in self.db.scan_prefix([b'l', 0]).rev() {
+            if events.len() >= limit {
+                break;
+            }
+            let (_, value) = entry.map_err(|e| sled_error("scan", e))?;
+            let event: StorageEvent = serde_json::from_slice(&value)?;
+            if namespace.map_or(true, |ns| event.namespace == ns)
+                && event_type.map_or(true, |et| event.event_type == et)
+            {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+
+    fn delete(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+    ) -> StorageResult<()> {
+        self.check_permission(auth, "write", namespace)?;
+        partition::check_grant_change(auth, namespace, key)?;
+        let auth = auth.ok_or_else(|| StorageError::PermissionDenied {
+            user_id: "anonymous".to_string(),
+            action: "write".to_string(),
+            key: format!("{}:{}", namespace, key),
+        })?;
+        let existing = self
+            .value(namespace, key)?
+            .ok_or_else(|| StorageError::NotFound {
+                key: format!("{}:{}", namespace, key),
+            })?;
+        let version = self.version(namespace, key)?;
+
+        self.account_for(auth, existing.len() as u64, 0)?;
+        if self.transactions.is_empty() {
+            self.restore_key(namespace, key, None, None)?;
+        } else {
+            // Keep the history, so rollback can bring the key back
+            self.record_for_rollback(namespace, key, Some(existing), version);
+            let mut batch = sled::Batch::default();
+            batch.remove(record_key(b'd', &[namespace, key]));
+            batch.remove(record_key(b'v', &[namespace, key]));
+            self.db
+                .apply_batch(batch)
+                .map_err(|e| sled_error("delete", e))?;
+        }
+        self.emit_event("delete", auth, namespace, key, "Key deleted");
+        Ok(())
+    }
+
+    fn get_usage(&self, auth: Option<&AuthContext>, namespace: &str) -> StorageResult<u64> {
+        self.check_permission(auth, "read", namespace)?;
+        self.namespace_bytes(namespace)
+    }
+
+    fn snapshot(&self) -> StorageResult<StorageSnapshot> {
+        let mut snapshot = StorageSnapshot::new();
+        for entry in self.db.scan_prefix([b'd', 0]) {
+            let (record, value) = entry.map_err(|e| sled_error("scan", e))?;
+            let parts = &record[2..];
+            let Some(split) = parts.iter().position(|byte| *byte == 0) else {
+                continue;
+            };
+            let namespace = String::from_utf8_lossy(&parts[..split]).into_owned();
+            let key = String::from_utf8_lossy(&parts[split + 1..]).into_owned();
+            let version =
+                self.version(&namespace, &key)?
+                    .ok_or_else(|| StorageError::TransactionError {
+                        details: format!("No version info for existing key {}", key),
+                    })?;
+            snapshot.entries.push(SnapshotEntry {
+                namespace,
+                key,
+                value: value.to_vec(),
+                version,
+            });
+        }
+        for entry in self.db.scan_prefix([b'n', 0]) {
+            let (_, value) = entry.map_err(|e| sled_error("scan", e))?;
+            snapshot.namespaces.push(serde_json::from_slice(&value)?);
+        }
+        for entry in self.db.scan_prefix([b'a', 0]) {
+            let (_, value) = entry.map_err(|e| sled_error("scan", e))?;
+            snapshot.accounts.push(serde_json::from_slice(&value)?);
+        }
+        Ok(snapshot.sorted())
+    }
+
+    fn restore(&mut self, snapshot: StorageSnapshot) -> StorageResult<()> {
+        if !self.transactions.is_empty() {
+            return Err(StorageError::TransactionError {
+                details: "Cannot restore a snapshot inside a transaction".to_string(),
+            });
+        }
+        snapshot.validate()?;
+
+        // One batch, so a failed restore leaves the database as it was
+        let mut batch = sled::Batch::default();
+        for kind in [b'd', b'v', b'h', b'n', b'a'] {
+            for entry in self.db.scan_prefix([kind, 0]) {
+                let (record, _) = entry.map_err(|e| sled_error("scan", e))?;
+                batch.remove(record);
+            }
+        }
+        for metadata in snapshot.all_namespaces() {
+            batch.insert(
+                record_key(b'n', &[&metadata.path]),
+                serde_json::to_vec(&metadata)?,
+            );
+        }
+        for entry in &snapshot.entries {
+            let (namespace, key) = (entry.namespace.as_str(), entry.key.as_str());
+            batch.insert(record_key(b'd', &[namespace, key]), entry.value.clone());
+            batch.insert(
+                record_key(b'v', &[namespace, key]),
+                serde_json::to_vec(&entry.version)?,
+            );
+            batch.insert(
+                history_key(namespace, key, entry.version.version),
+                entry.value.clone(),
+            );
+        }
+        for account in &snapshot.accounts {
+            batch.insert(
+                record_key(b'a', &[&account.owner_id]),
+                serde_json::to_vec(account)?,
+            );
+        }
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| sled_error("restore", e))?;
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_persist_and_rollback_restores() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut auth = AuthContext::new("did:key:admin");
+        auth.add_role("global", "admin");
+
+        let mut storage = SledStorage::open(dir.path()).unwrap();
+        storage
+            .create_namespace(Some(&auth), "coop", 1_000, None)
+            .unwrap();
+        storage
+            .set(Some(&auth), "coop", "budget", b"100".to_vec())
+            .unwrap();
+        storage
+            .set(Some(&auth), "coop", "budget", b"250".to_vec())
+            .unwrap();
+        storage.flush().unwrap();
+        drop(storage);
+
+        let mut storage = SledStorage::open(dir.path()).unwrap();
+        assert_eq!(storage.get(Some(&auth), "coop", "budget").unwrap(), b"250");
+        let (first, info) = storage
+            .get_version(Some(&auth), "coop", "budget", 1)
+            .unwrap();
+        assert_eq!((first.as_slice(), info.version), (&b"100"[..], 1));
+        assert_eq!(
+            storage
+                .list_versions(Some(&auth), "coop", "budget")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            storage.list_keys(Some(&auth), "coop", Some("bud")).unwrap(),
+            vec!["budget"]
+        );
+        assert_eq!(storage.get_usage(Some(&auth), "coop").unwrap(), 3);
+
+        storage.begin_transaction().unwrap();
+        storage
+            .set(Some(&auth), "coop", "budget", b"999".to_vec())
+            .unwrap();
+        storage
+            .set(Some(&auth), "coop", "new", b"x".to_vec())
+            .unwrap();
+        storage.delete(Some(&auth), "coop", "budget").unwrap();
+        storage.rollback_transaction().unwrap();
+        assert_eq!(storage.get(Some(&auth), "coop", "budget").unwrap(), b"250");
+        assert!(!storage.contains(Some(&auth), "coop", "new").unwrap());
+        assert!(storage
+            .get_version(Some(&auth), "coop", "budget", 3)
+            .is_err());
+
+        let reader = AuthContext::new("did:key:reader");
+        assert!(storage.get(Some(&reader), "coop", "budget").is_err());
+        let log = storage
+            .get_audit_log(Some(&auth), Some("coop"), Some("write"), 10)
+            .unwrap();
+        assert!(!log.is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+//! IPFS storage for large attachment files
+//!
+//! When `[attachments.ipfs]` is configured, files at least `min_bytes` long
+//! are added to an IPFS node through its RPC API instead of being stored as
+//! blobs, and their records keep only the CID and SHA-256 hash. Reads try
+//! the node first and then each configured gateway, checking every answer
+//! against the hash, so a gateway cannot hand back altered content.
+//!
+//! Requests go through [`crate::http`], so only `http://` URLs work; reach a
+//! remote node or an HTTPS gateway through a local relay.
+
+use crate::config::IpfsConfig;
+use crate::http::Endpoint;
+use crate::storage::blobs::blob_hash;
+use once_cell::sync::Lazy;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Client for the IPFS node and gateways attachments are stored on
+#[derive(Debug, Clone)]
+pub struct IpfsClient {
+    api: Endpoint,
+    gateways: Vec<Endpoint>,
+    min_bytes: u64,
+    pin: bool,
+    remote_pin_service: Option<String>,
+    timeout: Duration,
+}
+
+impl IpfsClient {
+    pub fn new(config: &IpfsConfig) -> io::Result<Self> {
+        Ok(Self {
+            api: Endpoint::parse(&config.api_url)?,
+            gateways: config
+                .gateways
+                .iter()
+                .map(|url| Endpoint::parse(url))
+                .collect::<io::Result<_>>()?,
+            min_bytes: config.min_bytes,
+            pin: config.pin,
+            remote_pin_service: config.remote_pin_service.clone(),
+            timeout: Duration::from_secs(config.timeout_secs.max(1)),
+        })
+    }
+
+    /// Whether a file of `size` bytes belongs on IPFS
+    pub fn wants(&self, size: u64) -> bool {
+        size >= self.min_bytes
+    }
+
+    /// Add `data` to the node, pinning it as configured, and return its CID
+    pub fn add(&self, data: &[u8]) -> io::Result<String> {
+        let boundary = format!("icn-covm-{}", uuid::Uuid::new_v4().simple());
+        let mut body = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"attachment\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+            boundary
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        let content_type = format!("multipart/form-data; boundary={}", boundary);
+        let response = self.api.request(
+            "POST",
+            &format!("/api/v0/add?cid-version=1&pin={}", self.pin),
+            &[("Content-Type", content_type.as_str())],
+            &body,
+            self.timeout,
+        )?;
+        // One JSON object per line; the last one describes the file added
+        let cid = String::from_utf8_lossy(&response)
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter_map(|value| value.get("Hash")?.as_str().map(str::to_string))
+            .last()
+            .ok_or_else(|| io::Error::other("IPFS add returned no CID"))?;
+
+        if let Some(service) = &self.remote_pin_service {
+            self.api.request(
+                "POST",
+                &format!(
+                    "/api/v0/pin/remote/add?arg={}&service={}&background=true",
+                    cid, service
+                ),
+                &[],
+                &[],
+                self.timeout,
+            )?;
+        }
+        Ok(cid)
+    }
+
+    /// Fetch the content of `cid`, which must hash to `sha256`
+    ///
+    /// The node is asked first, then each gateway in turn; an answer whose
+    /// hash does not match counts as a failure.
+    pub fn get(&self, cid: &str, sha256: &str) -> io::Result<Vec<u8>> {
+        let verified = |data: Vec<u8>| {
+            if blob_hash(&data) == sha256 {
+                Ok(data)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Content of {} does not match SHA-256 {}", cid, sha256),
+                ))
+            }
+        };
+
+        let mut last_error = match self
+            .api
+            .request(
+                "POST",
+                &format!("/api/v0/cat?arg={}", cid),
+                &[],
+                &[],
+                self.timeout,
+            )
+            .and_then(verified)
+        {
+            Ok(data) => return Ok(data),
+            Err(e) => e,
+        };
+        for gateway in &self.gateways {
+            match gateway
+                .request("GET", &format!("/ipfs/{}", cid), &[], &[], self.timeout)
+                .and_then(verified)
+            {
+                Ok(data) => return Ok(data),
+                Err(e) => {
+                    tracing::warn!(cid, gateway = %gateway.host, error = %e, "IPFS gateway failed");
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+/// Client attachments are stored with, if IPFS is configured
+static CLIENT: Lazy<Mutex<Option<Arc<IpfsClient>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Store large attachments on IPFS as `config` describes, or stop with `None`
+pub fn configure(config: Option<&IpfsConfig>) -> io::Result<()> {
+    let client = config.map(IpfsClient::new).transpose()?.map(Arc::new);
+    if let Ok(mut current) = CLIENT.lock() {
+        *current = client;
+    }
+    Ok(())
+}
+
+/// The client attachments are stored with, if IPFS is configured
+pub fn client() -> Option<Arc<IpfsClient>> {
+    CLIENT.lock().ok().and_then(|client| client.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Answer `responses.len()` requests in turn, returning the request lines
+    fn serve(responses: Vec<Vec<u8>>) -> (u16, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|response| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    // Read the head and as much of the body as it declares
+                    loop {
+                        let n = stream.read(&mut buf).unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request).to_string();
+                        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                            let length = head
+                                .lines()
+                                .find_map(|line| line.strip_prefix("Content-Length: "))
+                                .and_then(|len| len.trim().parse::<usize>().ok())
+                                .unwrap_or(0);
+                            if body.len() >= length {
+                                break;
+                            }
+                        }
+                        if n == 0 {
+                            break;
+                        }
+                    }
+                    stream.write_all(&response).unwrap();
+                    String::from_utf8_lossy(&request)
+                        .lines()
+                        .next()
+                        .unwrap_or("")
+                        .to_string()
+                })
+                .collect()
+        });
+        (port, server)
+    }
+
+    fn ok(body: &[u8]) -> Vec<u8> {
+        let mut response = b"HTTP/1.0 200 OK\r\n\r\n".to_vec();
+        response.extend_from_slice(body);
+        response
+    }
+
+    #[test]
+    fn test_add_and_get_with_gateway_fallback() {
+        let data = b"minutes of the general assembly".to_vec();
+        let cid = "bafkreiexample";
+        let (api_port, api) = serve(vec![
+            ok(format!(
+                "{{\"Name\":\"attachment\",\"Hash\":\"{}\",\"Size\":\"31\"}}\n",
+                cid
+            )
+            .as_bytes()),
+            b"HTTP/1.0 500 Internal Server Error\r\n\r\n".to_vec(),
+        ]);
+        let (bad_port, bad_gateway) = serve(vec![ok(b"tampered")]);
+        let (good_port, good_gateway) = serve(vec![ok(&data)]);
+
+        let client = IpfsClient::new(&IpfsConfig {
+            api_url: format!("http://127.0.0.1:{}", api_port),
+            gateways: vec![
+                format!("http://127.0.0.1:{}", bad_port),
+                format!("http://127.0.0.1:{}/", good_port),
+            ],
+            min_bytes: 16,
+            ..IpfsConfig::default()
+        })
+        .unwrap();
+        assert!(client.wants(16));
+        assert!(!client.wants(15));
+
+        assert_eq!(client.add(&data).unwrap(), cid);
+        assert_eq!(client.get(cid, &blob_hash(&data)).unwrap(), data);
+
+        let api_requests = api.join().unwrap();
+        assert!(api_requests[0].starts_with("POST /api/v0/add?cid-version=1&pin=true"));
+        assert!(api_requests[1].starts_with("POST /api/v0/cat?arg=bafkreiexample"));
+        assert!(bad_gateway.join().unwrap()[0].starts_with("GET /ipfs/bafkreiexample"));
+        good_gateway.join().unwrap();
+
+        assert!(IpfsClient::new(&IpfsConfig {
+            api_url: "https://ipfs.example".to_string(),
+            ..IpfsConfig::default()
+        })
+        .is_err());
+    }
+}
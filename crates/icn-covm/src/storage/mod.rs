@@ -1,10 +1,17 @@
 pub mod auth;
+pub mod blobs;
 pub mod errors;
 pub mod events;
 pub mod implementations;
+pub mod ipfs;
 pub mod namespaces;
+pub mod partition;
+pub mod proposal_list;
+pub mod replication;
 pub mod resource;
+pub mod snapshot;
 pub mod traits;
+pub mod usage;
 pub mod utils;
 pub mod versioning;
 
@@ -0,0 +1,319 @@
+//! Per-cooperative storage partitions
+//!
+//! Every namespace under `coops/{coop}` belongs to that cooperative's
+//! partition; other namespaces, such as `identity` or `global`, are shared.
+//! A caller belongs to a cooperative when they are a member of, or hold a
+//! role in, any namespace of its partition. Within their own cooperatives,
+//! and in shared namespaces, the usual role checks apply.
+//!
+//! Namespaces of any other cooperative can only be reached through a
+//! [`FederationGrant`], by which the owning cooperative lets the members of
+//! another read, or read and write, one of its namespaces and everything
+//! under it. Grants are kept at `federation_grants/{grantee}` in the owner's
+//! root namespace, and only its admins may change them.
+//!
+//! Backends call [`check`] from `check_permission`, after the global admin
+//! bypass, and record every use of a grant and every refusal in their audit
+//! log as `partition_grant` and `partition_denied` events.
+
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{StorageBackend, StorageExtensions};
+use crate::storage::utils::{now_with_default, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Prefix of the namespaces partitioned by cooperative
+pub const COOP_PREFIX: &str = "coops/";
+
+/// Prefix of grant records in a cooperative's root namespace
+pub const GRANT_PREFIX: &str = "federation_grants/";
+
+/// What a grant allows
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantAccess {
+    Read,
+    /// Read and write
+    Write,
+}
+
+/// Access to part of one cooperative's partition for another's members
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FederationGrant {
+    /// Cooperative whose data is shared
+    pub owner: String,
+    /// Cooperative whose members may access it
+    pub grantee: String,
+    /// Namespace shared, together with the namespaces under it
+    pub namespace: String,
+    pub access: GrantAccess,
+    pub granted_by: String,
+    pub granted_at: Timestamp,
+}
+
+impl FederationGrant {
+    /// Whether the grant allows `action` on `namespace`
+    pub fn covers(&self, action: &str, namespace: &str) -> bool {
+        let allowed = match action {
+            "read" => true,
+            "write" => self.access == GrantAccess::Write,
+            _ => false,
+        };
+        allowed && within(namespace, &self.namespace)
+    }
+}
+
+/// How a partition check was decided
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartitionAccess {
+    /// Shared namespace, or one of the caller's cooperatives; roles decide
+    Open,
+    /// Allowed by a grant to one of the caller's cooperatives
+    Granted(FederationGrant),
+    /// Another cooperative's namespace, with no grant covering it
+    Denied { partition: String },
+}
+
+/// Whether `namespace` is `parent` or below it
+fn within(namespace: &str, parent: &str) -> bool {
+    namespace == parent
+        || namespace
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Cooperative whose partition `namespace` belongs to, if any
+pub fn partition_of(namespace: &str) -> Option<&str> {
+    namespace
+        .strip_prefix(COOP_PREFIX)
+        .and_then(|rest| rest.split('/').next())
+        .filter(|coop| !coop.is_empty())
+}
+
+/// Root namespace of a cooperative's partition
+pub fn coop_root(coop: &str) -> String {
+    format!("{}{}", COOP_PREFIX, coop)
+}
+
+/// Key of the grant from a cooperative to `grantee`
+pub fn grant_key(grantee: &str) -> String {
+    format!("{}{}", GRANT_PREFIX, grantee)
+}
+
+/// Cooperatives the caller belongs to
+pub fn home_partitions(auth: &AuthContext) -> BTreeSet<String> {
+    let did = auth.identity_did();
+    let memberships = auth
+        .memberships
+        .iter()
+        .filter(|membership| membership.identity_did == did)
+        .map(|membership| membership.namespace.as_str());
+    let roles = auth
+        .roles
+        .iter()
+        .filter(|(_, roles)| roles.values().any(|holders| holders.contains(did)))
+        .map(|(namespace, _)| namespace.as_str());
+    memberships
+        .chain(roles)
+        .filter_map(partition_of)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Decide whether partitions allow `auth` to take `action` on `namespace`
+///
+/// `lookup` reads a raw value from the backend without permission checks,
+/// and is used to find grants.
+pub fn check<F>(auth: &AuthContext, action: &str, namespace: &str, lookup: F) -> PartitionAccess
+where
+    F: Fn(&str, &str) -> Option<Vec<u8>>,
+{
+    let Some(partition) = partition_of(namespace) else {
+        return PartitionAccess::Open;
+    };
+    let homes = home_partitions(auth);
+    if homes.contains(partition) {
+        return PartitionAccess::Open;
+    }
+    let root = coop_root(partition);
+    homes
+        .iter()
+        .filter_map(|home| lookup(&root, &grant_key(home)))
+        .filter_map(|raw| serde_json::from_slice::<FederationGrant>(&raw).ok())
+        .find(|grant| grant.owner == partition && grant.covers(action, namespace))
+        .map(PartitionAccess::Granted)
+        .unwrap_or_else(|| PartitionAccess::Denied {
+            partition: partition.to_string(),
+        })
+}
+
+/// Refuse changes to grant records by anyone but an admin of the owner
+///
+/// Backends call this before writing or deleting `key` in `namespace`.
+pub fn check_grant_change(
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    key: &str,
+) -> StorageResult<()> {
+    let Some(coop) = partition_of(namespace) else {
+        return Ok(());
+    };
+    if namespace != coop_root(coop) || !key.starts_with(GRANT_PREFIX) {
+        return Ok(());
+    }
+    match auth {
+        Some(auth) if auth.has_role("global", "admin") || auth.has_role(namespace, "admin") => {
+            Ok(())
+        }
+        _ => Err(StorageError::PermissionDenied {
+            user_id: auth.map_or_else(|| "anonymous".to_string(), |a| a.user_id_cloneable()),
+            action: "change federation grant".to_string(),
+            key: format!("{}:{}", namespace, key),
+        }),
+    }
+}
+
+fn invalid(details: String) -> StorageError {
+    StorageError::ValidationError {
+        rule: "federation_grant".to_string(),
+        details,
+    }
+}
+
+/// Let members of `grantee` access `namespace` of the cooperative owning it,
+/// replacing any earlier grant between the two
+pub fn grant_access<S>(
+    storage: &mut S,
+    auth: &AuthContext,
+    namespace: &str,
+    grantee: &str,
+    access: GrantAccess,
+) -> StorageResult<FederationGrant>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    let owner = partition_of(namespace).ok_or_else(|| {
+        invalid(format!(
+            "{} is not a cooperative namespace under {}",
+            namespace, COOP_PREFIX
+        ))
+    })?;
+    if grantee.is_empty() || grantee.contains('/') {
+        return Err(invalid(format!("Invalid cooperative '{}'", grantee)));
+    }
+    if grantee == owner {
+        return Err(invalid(format!("{} cannot grant access to itself", owner)));
+    }
+    let grant = FederationGrant {
+        owner: owner.to_string(),
+        grantee: grantee.to_string(),
+        namespace: namespace.to_string(),
+        access,
+        granted_by: auth.identity_did().to_string(),
+        granted_at: now_with_default(),
+    };
+    storage.set_json(Some(auth), &coop_root(owner), &grant_key(grantee), &grant)?;
+    Ok(grant)
+}
+
+/// Withdraw the grant from `owner` to `grantee`
+pub fn revoke_access<S>(
+    storage: &mut S,
+    auth: &AuthContext,
+    owner: &str,
+    grantee: &str,
+) -> StorageResult<()>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    storage.delete(Some(auth), &coop_root(owner), &grant_key(grantee))
+}
+
+/// Grants made by a cooperative
+pub fn list_grants<S>(
+    storage: &S,
+    auth: &AuthContext,
+    owner: &str,
+) -> StorageResult<Vec<FederationGrant>>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    let root = coop_root(owner);
+    storage
+        .list_keys(Some(auth), &root, Some(GRANT_PREFIX))?
+        .iter()
+        .map(|key| storage.get_json(Some(auth), &root, key))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_coops_reach_each_other_only_through_grants() {
+        let mut storage = InMemoryStorage::new();
+        let mut root = AuthContext::new("root");
+        root.add_role("global", "admin");
+        for user in ["root", "did:key:alice", "did:key:bob"] {
+            storage.create_account(Some(&root), user, 1 << 20).unwrap();
+        }
+        storage
+            .set(Some(&root), "coops/alpha/reports", "q1", b"42".to_vec())
+            .unwrap();
+        storage
+            .set(Some(&root), "coops/alpha/private", "payroll", b"7".to_vec())
+            .unwrap();
+
+        // Alice administers alpha; Bob writes in beta only
+        let mut alice = AuthContext::new("did:key:alice");
+        alice.add_role("coops/alpha", "admin");
+        let mut bob = AuthContext::new("did:key:bob");
+        bob.add_role("coops/beta", "writer");
+
+        let read = |auth: &AuthContext, namespace: &str, key: &str| {
+            storage.get(Some(auth), namespace, key).is_ok()
+        };
+        assert!(!read(&bob, "coops/alpha/reports", "q1"));
+
+        // A writer of beta cannot grant itself access to alpha
+        let forged = grant_access(
+            &mut storage,
+            &bob,
+            "coops/alpha/reports",
+            "beta",
+            GrantAccess::Write,
+        );
+        assert!(forged.is_err());
+
+        grant_access(
+            &mut storage,
+            &alice,
+            "coops/alpha/reports",
+            "beta",
+            GrantAccess::Read,
+        )
+        .unwrap();
+        let read = |auth: &AuthContext, namespace: &str, key: &str| {
+            storage.get(Some(auth), namespace, key).is_ok()
+        };
+        assert!(read(&bob, "coops/alpha/reports", "q1"));
+        assert!(!read(&bob, "coops/alpha/private", "payroll"));
+        assert!(storage
+            .set(Some(&bob), "coops/alpha/reports", "q2", b"0".to_vec())
+            .is_err());
+        assert_eq!(list_grants(&storage, &alice, "alpha").unwrap().len(), 1);
+
+        let events = storage.get_audit_log(Some(&root), None, None, 100).unwrap();
+        let count = |event_type: &str| events.iter().filter(|e| e.event_type == event_type).count();
+        assert_eq!(count("partition_grant"), 1);
+        assert!(count("partition_denied") >= 3);
+
+        revoke_access(&mut storage, &alice, "alpha", "beta").unwrap();
+        assert!(storage
+            .get(Some(&bob), "coops/alpha/reports", "q1")
+            .is_err());
+    }
+}
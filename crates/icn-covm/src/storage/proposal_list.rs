@@ -0,0 +1,334 @@
+//! Filtered, sorted pages of the proposals in a namespace
+//!
+//! `StorageExtensions::list_proposals_paged` scans the lifecycle keys of a
+//! namespace for proposal IDs, keeping only the ID and sort value of each
+//! proposal that matches the filters. Once they are sorted, only the proposals on the
+//! requested page are read in full, so listing a large namespace does not
+//! hold every proposal in memory at once.
+
+use crate::governance::proposal::Proposal;
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::StorageBackend;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// Key prefix the proposal commands store proposals under
+const PROPOSALS_PREFIX: &str = "governance_proposals/";
+
+/// Field proposals are sorted by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProposalSort {
+    #[default]
+    CreatedAt,
+    /// Case-insensitive
+    Title,
+    Status,
+    Creator,
+    Id,
+}
+
+impl FromStr for ProposalSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created_at" => Ok(ProposalSort::CreatedAt),
+            "title" => Ok(ProposalSort::Title),
+            "status" => Ok(ProposalSort::Status),
+            "creator" => Ok(ProposalSort::Creator),
+            "id" => Ok(ProposalSort::Id),
+            _ => Err(format!("Unknown sort field: {}", s)),
+        }
+    }
+}
+
+/// Which proposals to list, in what order, and which slice of them
+#[derive(Debug, Clone)]
+pub struct ProposalPageQuery {
+    /// Only proposals in this status (case-insensitive)
+    pub status: Option<String>,
+    /// Only proposals created by this identity
+    pub creator: Option<String>,
+    /// Only proposals carrying this label
+    pub label: Option<String>,
+    pub sort: ProposalSort,
+    pub descending: bool,
+    /// Number of matching proposals to skip
+    pub offset: usize,
+    /// Largest number of proposals to return
+    pub limit: usize,
+    /// ID of the last proposal of the previous page; replaces `offset`
+    pub after: Option<String>,
+}
+
+impl Default for ProposalPageQuery {
+    fn default() -> Self {
+        Self {
+            status: None,
+            creator: None,
+            label: None,
+            sort: ProposalSort::default(),
+            descending: false,
+            offset: 0,
+            limit: usize::MAX,
+            after: None,
+        }
+    }
+}
+
+/// A proposal as listed, with its title from the lifecycle record
+#[derive(Debug, Clone)]
+pub struct ProposalSummary {
+    pub id: String,
+    pub title: String,
+    pub creator: String,
+    pub status: String,
+    pub labels: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One slice of the proposals matching a query
+#[derive(Debug, Clone)]
+pub struct PagedProposals {
+    /// Number of proposals matching the filters, across all pages
+    pub total: usize,
+    /// Position of the first returned proposal among the matches
+    pub offset: usize,
+    pub proposals: Vec<ProposalSummary>,
+}
+
+/// The part of a stored lifecycle the list needs
+#[derive(Deserialize)]
+struct LifecycleTitle {
+    #[serde(default)]
+    title: String,
+}
+
+/// A matching proposal, kept until the page is known
+struct Match {
+    id: String,
+    sort_value: String,
+}
+
+/// Read a proposal, `None` if it is missing or does not parse
+fn read_proposal<S: StorageBackend + ?Sized>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    id: &str,
+) -> StorageResult<Option<Proposal>> {
+    let key = format!("{}{}", PROPOSALS_PREFIX, id);
+    let bytes = match storage.get(auth, namespace, &key) {
+        Ok(bytes) => bytes,
+        Err(StorageError::NotFound { .. }) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    Ok(serde_json::from_slice(&bytes).ok())
+}
+
+fn read_title<S: StorageBackend + ?Sized>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    id: &str,
+) -> String {
+    let key = format!("{}{}/lifecycle", PROPOSALS_PREFIX, id);
+    storage
+        .get(auth, namespace, &key)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<LifecycleTitle>(&bytes).ok())
+        .map(|lifecycle| lifecycle.title)
+        .unwrap_or_default()
+}
+
+fn status_name(proposal: &Proposal) -> String {
+    format!("{:?}", proposal.status)
+}
+
+/// See `StorageExtensions::list_proposals_paged`
+pub(crate) fn list_proposals_paged<S: StorageBackend + ?Sized>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    query: &ProposalPageQuery,
+) -> StorageResult<PagedProposals> {
+    let keys = storage.list_keys(auth, namespace, Some(PROPOSALS_PREFIX))?;
+    let mut matches = Vec::new();
+    for key in &keys {
+        let Some(id) = key
+            .strip_prefix(PROPOSALS_PREFIX)
+            .and_then(|rest| rest.strip_suffix("/lifecycle"))
+        else {
+            continue;
+        };
+        let Some(proposal) = read_proposal(storage, auth, namespace, id)? else {
+            continue;
+        };
+        let wanted = query
+            .status
+            .as_ref()
+            .map_or(true, |s| status_name(&proposal).eq_ignore_ascii_case(s))
+            && query
+                .creator
+                .as_ref()
+                .map_or(true, |c| &proposal.creator == c)
+            && query
+                .label
+                .as_ref()
+                .map_or(true, |l| proposal.labels.iter().any(|label| label == l));
+        if !wanted {
+            continue;
+        }
+        let sort_value = match query.sort {
+            // RFC 3339 timestamps in UTC sort lexically
+            ProposalSort::CreatedAt => proposal.created_at.to_rfc3339(),
+            ProposalSort::Title => read_title(storage, auth, namespace, id).to_lowercase(),
+            ProposalSort::Status => status_name(&proposal),
+            ProposalSort::Creator => proposal.creator,
+            ProposalSort::Id => String::new(),
+        };
+        matches.push(Match {
+            id: id.to_string(),
+            sort_value,
+        });
+    }
+
+    matches.sort_by(|a, b| {
+        // Break ties by ID so cursors are stable
+        let ordering: Ordering = a
+            .sort_value
+            .cmp(&b.sort_value)
+            .then_with(|| a.id.cmp(&b.id));
+        if query.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    let total = matches.len();
+    let offset = match &query.after {
+        Some(cursor) => matches
+            .iter()
+            .position(|m| &m.id == cursor)
+            .map(|i| i + 1)
+            .ok_or_else(|| StorageError::ValidationError {
+                rule: "proposal_cursor".to_string(),
+                details: format!("Unknown cursor: {}", cursor),
+            })?,
+        None => query.offset,
+    };
+
+    let mut proposals = Vec::new();
+    for m in matches.iter().skip(offset).take(query.limit) {
+        // A proposal deleted since the scan is left out of the page
+        if let Some(proposal) = read_proposal(storage, auth, namespace, &m.id)? {
+            proposals.push(ProposalSummary {
+                title: read_title(storage, auth, namespace, &m.id),
+                status: status_name(&proposal),
+                id: proposal.id,
+                creator: proposal.creator,
+                labels: proposal.labels,
+                created_at: proposal.created_at,
+            });
+        }
+    }
+
+    Ok(PagedProposals {
+        total,
+        offset,
+        proposals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::governance::proposal::ProposalStatus;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use crate::storage::traits::StorageExtensions;
+    use chrono::TimeZone;
+
+    fn store(storage: &mut InMemoryStorage, auth: &AuthContext, id: &str, day: u32, label: &str) {
+        let mut proposal = Proposal::new(
+            id.to_string(),
+            "did:key:alice".to_string(),
+            None,
+            None,
+            None,
+            vec![],
+        );
+        proposal.status = ProposalStatus::Voting;
+        proposal.created_at = Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+        proposal.labels = vec![label.to_string()];
+        let key = format!("{}{}", PROPOSALS_PREFIX, id);
+        storage
+            .set_json(Some(auth), "coop", &key, &proposal)
+            .unwrap();
+        let lifecycle = serde_json::json!({ "title": format!("Proposal {}", id) });
+        storage
+            .set_json(
+                Some(auth),
+                "coop",
+                &format!("{}/lifecycle", key),
+                &lifecycle,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_pages_are_filtered_sorted_and_sliced() {
+        let mut auth = AuthContext::new("did:key:admin");
+        auth.add_role("global", "admin");
+        let mut storage = InMemoryStorage::new();
+        for (id, day, label) in [("a", 1, "budget"), ("b", 2, "events"), ("c", 3, "budget")] {
+            store(&mut storage, &auth, id, day, label);
+        }
+
+        let query = ProposalPageQuery {
+            label: Some("budget".to_string()),
+            descending: true,
+            limit: 1,
+            ..Default::default()
+        };
+        let page = list_proposals_paged(&storage, Some(&auth), "coop", &query).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.proposals[0].id, "c");
+        assert_eq!(page.proposals[0].title, "Proposal c");
+
+        let page = storage
+            .list_proposals_paged(
+                Some(&auth),
+                "coop",
+                &ProposalPageQuery { offset: 1, ..query },
+            )
+            .unwrap();
+        assert_eq!((page.offset, page.proposals[0].id.as_str()), (1, "a"));
+
+        let after = ProposalPageQuery {
+            after: Some("a".to_string()),
+            status: Some("voting".to_string()),
+            ..Default::default()
+        };
+        let page = storage
+            .list_proposals_paged(Some(&auth), "coop", &after)
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.offset, 1);
+        assert_eq!(page.proposals.len(), 2);
+
+        let unknown = ProposalPageQuery {
+            after: Some("z".to_string()),
+            ..Default::default()
+        };
+        assert!(storage
+            .list_proposals_paged(Some(&auth), "coop", &unknown)
+            .is_err());
+        assert!(storage
+            .list_proposals_paged(None, "coop", &unknown)
+            .is_err());
+    }
+}
@@ -0,0 +1,381 @@
+//! Storage change stream for read replicas
+//!
+//! A primary API node keeps a [`ChangeLog`] of its storage. Each refresh
+//! compares every key with the previous scan and records a numbered
+//! [`ChangeRecord`] for each value written or deleted since. A replica
+//! copies a full [`snapshot`](ChangeLog::snapshot) once, then polls for the
+//! changes after the last sequence number it applied (see `api::replica`).
+//!
+//! The log is built by comparison rather than by hooking writes, so it works
+//! with any backend and sees writes from every path into storage. Only the
+//! net change between two refreshes is recorded, and only keys and values
+//! are replicated, not accounts or namespace metadata.
+//!
+//! The log keeps a bounded number of changes, and each log has its own
+//! epoch, so sequence numbers from before a primary restarted are never
+//! mistaken for current ones. A replica whose position is older than the
+//! log, or from another epoch, must take a snapshot again.
+
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::StorageBackend;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Changes kept for replicas that fall behind
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// Most changes returned in one batch
+pub const MAX_BATCH: usize = 1_000;
+
+/// Every key and value in storage, by namespace and key
+type Entries = BTreeMap<(String, String), Vec<u8>>;
+
+/// One key written or deleted on the primary
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangeRecord {
+    pub seq: u64,
+    pub namespace: String,
+    pub key: String,
+    /// Base64 of the new value; absent when the key was deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// Changes that bring a replica up to `head`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangeBatch {
+    /// Log the sequence numbers belong to
+    pub epoch: String,
+    /// Sequence number of the last change included
+    pub head: u64,
+    /// Whether changes after `head` are waiting
+    pub more: bool,
+    pub changes: Vec<ChangeRecord>,
+}
+
+/// Numbered changes to a primary's storage
+#[derive(Debug)]
+pub struct ChangeLog {
+    epoch: String,
+    head: u64,
+    /// Sequence number of the newest change no longer kept
+    floor: u64,
+    capacity: usize,
+    /// SHA-256 of every value at the last refresh
+    digests: HashMap<(String, String), [u8; 32]>,
+    changes: VecDeque<ChangeRecord>,
+    scanned: bool,
+}
+
+impl Default for ChangeLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ChangeLog {
+    /// Create a log keeping at most `capacity` changes, in a new epoch
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            epoch: uuid::Uuid::new_v4().to_string(),
+            head: 0,
+            floor: 0,
+            capacity: capacity.max(1),
+            digests: HashMap::new(),
+            changes: VecDeque::new(),
+            scanned: false,
+        }
+    }
+
+    pub fn epoch(&self) -> &str {
+        &self.epoch
+    }
+
+    /// Sequence number of the latest change
+    pub fn head(&self) -> u64 {
+        self.head
+    }
+
+    /// Record what changed in `storage` since the last refresh, returning
+    /// the number of changes
+    ///
+    /// The first refresh only notes the current state; replicas copy it
+    /// with a snapshot.
+    pub fn refresh<S: StorageBackend>(
+        &mut self,
+        storage: &S,
+        auth: Option<&AuthContext>,
+    ) -> StorageResult<usize> {
+        let entries = read_all(storage, auth)?;
+        Ok(self.record(&entries))
+    }
+
+    /// Refresh, then return every key and value as of the new head
+    pub fn snapshot<S: StorageBackend>(
+        &mut self,
+        storage: &S,
+        auth: Option<&AuthContext>,
+    ) -> StorageResult<ChangeBatch> {
+        let entries = read_all(storage, auth)?;
+        self.record(&entries);
+        let changes = entries
+            .into_iter()
+            .map(|((namespace, key), value)| ChangeRecord {
+                seq: self.head,
+                namespace,
+                key,
+                value: Some(BASE64.encode(value)),
+            })
+            .collect();
+        Ok(ChangeBatch {
+            epoch: self.epoch.clone(),
+            head: self.head,
+            more: false,
+            changes,
+        })
+    }
+
+    /// At most `limit` changes after `since` in `epoch`, or `None` if the
+    /// replica must take a snapshot instead
+    pub fn changes_since(&self, epoch: &str, since: u64, limit: usize) -> Option<ChangeBatch> {
+        if epoch != self.epoch || since < self.floor || since > self.head {
+            return None;
+        }
+        let changes: Vec<ChangeRecord> = self
+            .changes
+            .iter()
+            .filter(|change| change.seq > since)
+            .take(limit.max(1))
+            .cloned()
+            .collect();
+        let head = changes.last().map_or(since, |change| change.seq);
+        Some(ChangeBatch {
+            epoch: self.epoch.clone(),
+            head,
+            more: head < self.head,
+            changes,
+        })
+    }
+
+    /// Compare `entries` with the last refresh and append the differences
+    fn record(&mut self, entries: &Entries) -> usize {
+        let scanned = std::mem::replace(&mut self.scanned, true);
+        let digests: HashMap<_, [u8; 32]> = entries
+            .iter()
+            .map(|(entry, value)| (entry.clone(), Sha256::digest(value).into()))
+            .collect();
+        if !scanned {
+            self.digests = digests;
+            return 0;
+        }
+
+        let mut count = 0;
+        for ((namespace, key), value) in entries {
+            let entry = (namespace.clone(), key.clone());
+            if self.digests.get(&entry) != digests.get(&entry) {
+                self.push(entry, Some(BASE64.encode(value)));
+                count += 1;
+            }
+        }
+        let mut deleted: Vec<_> = self
+            .digests
+            .keys()
+            .filter(|entry| !digests.contains_key(*entry))
+            .cloned()
+            .collect();
+        deleted.sort();
+        for entry in deleted {
+            self.push(entry, None);
+            count += 1;
+        }
+        self.digests = digests;
+        count
+    }
+
+    fn push(&mut self, (namespace, key): (String, String), value: Option<String>) {
+        self.head += 1;
+        self.changes.push_back(ChangeRecord {
+            seq: self.head,
+            namespace,
+            key,
+            value,
+        });
+        while self.changes.len() > self.capacity {
+            if let Some(dropped) = self.changes.pop_front() {
+                self.floor = dropped.seq;
+            }
+        }
+    }
+}
+
+/// Every namespace and key in storage
+fn list_all<S: StorageBackend>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+) -> StorageResult<Vec<(String, String)>> {
+    let mut entries = Vec::new();
+    for namespace in storage.list_namespaces(auth, "")? {
+        for key in storage.list_keys(auth, &namespace.path, None)? {
+            entries.push((namespace.path.clone(), key));
+        }
+    }
+    Ok(entries)
+}
+
+fn read_all<S: StorageBackend>(storage: &S, auth: Option<&AuthContext>) -> StorageResult<Entries> {
+    let mut entries = Entries::new();
+    for (namespace, key) in list_all(storage, auth)? {
+        match storage.get(auth, &namespace, &key) {
+            Ok(value) => {
+                entries.insert((namespace, key), value);
+            }
+            // Deleted between listing and reading
+            Err(StorageError::NotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Run `apply` in a storage transaction, rolling back if it fails
+fn in_transaction<S, F>(storage: &mut S, apply: F) -> StorageResult<()>
+where
+    S: StorageBackend,
+    F: FnOnce(&mut S) -> StorageResult<()>,
+{
+    storage.begin_transaction()?;
+    match apply(storage) {
+        Ok(()) => storage.commit_transaction(),
+        Err(e) => {
+            if let Err(rollback) = storage.rollback_transaction() {
+                tracing::error!("Failed to roll back replicated changes: {}", rollback);
+            }
+            Err(e)
+        }
+    }
+}
+
+fn apply_change<S: StorageBackend>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    change: &ChangeRecord,
+) -> StorageResult<()> {
+    match &change.value {
+        Some(encoded) => {
+            let value = BASE64
+                .decode(encoded)
+                .map_err(|e| StorageError::ValidationError {
+                    rule: "replication".to_string(),
+                    details: format!(
+                        "Invalid value for {}/{}: {}",
+                        change.namespace, change.key, e
+                    ),
+                })?;
+            storage.set(auth, &change.namespace, &change.key, value)
+        }
+        None => match storage.delete(auth, &change.namespace, &change.key) {
+            Ok(()) | Err(StorageError::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+/// Apply changes from the primary to a replica's storage, all or none
+pub fn apply_changes<S: StorageBackend>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    changes: &[ChangeRecord],
+) -> StorageResult<()> {
+    in_transaction(storage, |storage| {
+        changes
+            .iter()
+            .try_for_each(|change| apply_change(storage, auth, change))
+    })
+}
+
+/// Make a replica's storage match a snapshot of the primary, deleting keys
+/// the primary does not have
+pub fn apply_snapshot<S: StorageBackend>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    snapshot: &[ChangeRecord],
+) -> StorageResult<()> {
+    let kept: HashSet<(&str, &str)> = snapshot
+        .iter()
+        .map(|change| (change.namespace.as_str(), change.key.as_str()))
+        .collect();
+    let stale: Vec<ChangeRecord> = list_all(storage, auth)?
+        .into_iter()
+        .filter(|(namespace, key)| !kept.contains(&(namespace.as_str(), key.as_str())))
+        .map(|(namespace, key)| ChangeRecord {
+            seq: 0,
+            namespace,
+            key,
+            value: None,
+        })
+        .collect();
+    in_transaction(storage, |storage| {
+        stale
+            .iter()
+            .chain(snapshot)
+            .try_for_each(|change| apply_change(storage, auth, change))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_replica_follows_snapshot_and_changes() {
+        let mut auth = AuthContext::new("admin");
+        auth.add_role("global", "admin");
+        let auth = Some(&auth);
+        let storage_with_account = || {
+            let mut storage = InMemoryStorage::new();
+            storage.create_account(auth, "admin", 1 << 20).unwrap();
+            storage
+        };
+        let mut primary = storage_with_account();
+        let mut replica = storage_with_account();
+        primary.set(auth, "coop", "a", b"1".to_vec()).unwrap();
+        primary.set(auth, "coop", "b", b"2".to_vec()).unwrap();
+        replica.set(auth, "coop", "stale", b"x".to_vec()).unwrap();
+
+        let mut log = ChangeLog::new(2);
+        assert_eq!(log.refresh(&primary, auth).unwrap(), 0);
+        let snapshot = log.snapshot(&primary, auth).unwrap();
+        apply_snapshot(&mut replica, auth, &snapshot.changes).unwrap();
+        assert_eq!(
+            read_all(&replica, auth).unwrap(),
+            read_all(&primary, auth).unwrap()
+        );
+
+        primary.set(auth, "coop", "a", b"3".to_vec()).unwrap();
+        primary.delete(auth, "coop", "b").unwrap();
+        assert_eq!(log.refresh(&primary, auth).unwrap(), 2);
+        let batch = log
+            .changes_since(&snapshot.epoch, snapshot.head, MAX_BATCH)
+            .unwrap();
+        assert_eq!((batch.head, batch.more), (2, false));
+        apply_changes(&mut replica, auth, &batch.changes).unwrap();
+        assert_eq!(
+            read_all(&replica, auth).unwrap(),
+            read_all(&primary, auth).unwrap()
+        );
+
+        // Only two changes are kept, so a replica further behind resyncs
+        primary.set(auth, "coop", "c", b"4".to_vec()).unwrap();
+        log.refresh(&primary, auth).unwrap();
+        assert!(log
+            .changes_since(&batch.epoch, batch.head, MAX_BATCH)
+            .is_some());
+        assert!(log.changes_since(&batch.epoch, 0, MAX_BATCH).is_none());
+        assert!(log.changes_since("other", batch.head, MAX_BATCH).is_none());
+    }
+}
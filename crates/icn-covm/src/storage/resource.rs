@@ -1,5 +1,6 @@
 use crate::storage::errors::StorageError;
 use crate::storage::utils::{now_with_default, Timestamp};
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Serialize};
 
 // Resource accounting
@@ -46,3 +47,740 @@ impl ResourceAccount {
         self.last_updated = now_with_default();
     }
 }
+
+// Economic resource registry
+
+/// How new units of an economic resource may be issued
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IssuancePolicy {
+    /// Any amount may be minted
+    #[default]
+    Open,
+    /// Minting may not take the total supply above `max_supply`
+    Capped { max_supply: Decimal },
+    /// Nothing may be minted
+    Fixed,
+}
+
+/// Terms an economic resource is created with
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResourcePolicy {
+    /// Short name shown with amounts, such as `HRS`
+    #[serde(default)]
+    pub symbol: Option<String>,
+    /// Most decimal places an amount may have; any if unset
+    #[serde(default)]
+    pub decimals: Option<u32>,
+    /// Whether units may be transferred between accounts
+    #[serde(default = "default_transferable")]
+    pub transferable: bool,
+    #[serde(default)]
+    pub issuance: IssuancePolicy,
+    /// Identities allowed to mint; anyone if empty
+    #[serde(default)]
+    pub minters: Vec<String>,
+    /// Decay charged on balances left idle; none if unset
+    #[serde(default)]
+    pub demurrage: Option<DemurragePolicy>,
+}
+
+fn default_transferable() -> bool {
+    true
+}
+
+impl Default for ResourcePolicy {
+    fn default() -> Self {
+        Self {
+            symbol: None,
+            decimals: None,
+            transferable: true,
+            issuance: IssuancePolicy::Open,
+            minters: Vec::new(),
+            demurrage: None,
+        }
+    }
+}
+
+/// Demurrage on a resource: positive balances lose a fraction of their
+/// value for every period they sit unchanged, so units circulate rather
+/// than accumulate
+///
+/// A rate of 1 makes units expire outright once the grace period and one
+/// period have passed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DemurragePolicy {
+    /// Fraction of the balance lost each period, above 0 and at most 1
+    pub rate: Decimal,
+    /// Length of a period in seconds
+    pub period_secs: u64,
+    /// Seconds a balance may sit unchanged before it starts to decay
+    #[serde(default)]
+    pub grace_secs: u64,
+}
+
+impl DemurragePolicy {
+    /// Check that the rate and period are usable
+    pub fn validate(&self) -> Result<(), StorageError> {
+        if self.rate <= Decimal::ZERO || self.rate > Decimal::ONE {
+            return Err(StorageError::ValidationError {
+                rule: "demurrage_rate".to_string(),
+                details: format!("Demurrage rate {} must be above 0 and at most 1", self.rate),
+            });
+        }
+        if self.period_secs == 0 {
+            return Err(StorageError::ValidationError {
+                rule: "demurrage_period".to_string(),
+                details: "Demurrage period must be at least one second".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whole periods a balance unchanged since `since` has decayed for at `now`
+    pub fn periods(&self, since: Timestamp, now: Timestamp) -> u64 {
+        now.saturating_sub(since).saturating_sub(self.grace_secs) / self.period_secs.max(1)
+    }
+
+    /// `balance` after `periods` periods of decay, rounded down to
+    /// `decimals` places if the resource limits them
+    ///
+    /// Zero and negative balances, such as those drawn on credit, do not
+    /// decay.
+    pub fn decay(&self, balance: Decimal, periods: u64, decimals: Option<u32>) -> Decimal {
+        if periods == 0 || balance <= Decimal::ZERO {
+            return balance;
+        }
+        // (1 - rate)^periods by repeated squaring; every factor is at most
+        // one, so nothing can overflow
+        let mut factor = Decimal::ONE;
+        let mut base = Decimal::ONE - self.rate;
+        let mut remaining = periods;
+        while remaining > 0 && !factor.is_zero() {
+            if remaining & 1 == 1 {
+                factor *= base;
+            }
+            base *= base;
+            remaining >>= 1;
+        }
+        let decayed = balance * factor;
+        match decimals {
+            Some(decimals) => decayed.round_dp_with_strategy(decimals, RoundingStrategy::ToZero),
+            None => decayed,
+        }
+        .normalize()
+    }
+}
+
+/// Where an account stands against its resource's demurrage, kept at
+/// `resources/<id>/demurrage/<account>`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DemurrageState {
+    /// When the balance last changed through a mint, transfer, burn or
+    /// escrow
+    pub since: Timestamp,
+    /// Periods of decay already written to the balance
+    #[serde(default)]
+    pub decayed_periods: u64,
+}
+
+impl DemurrageState {
+    pub fn new(since: Timestamp) -> Self {
+        Self {
+            since,
+            decayed_periods: 0,
+        }
+    }
+}
+
+/// A resource's entry in the registry, kept at `resources/<id>/metadata`
+///
+/// Entries written before resources had policies hold only `id` and
+/// `namespace`, and read back with the default policy.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResourceMetadata {
+    pub id: String,
+    pub namespace: String,
+    #[serde(flatten)]
+    pub policy: ResourcePolicy,
+    /// Units minted and not yet burned
+    #[serde(default)]
+    pub total_supply: Decimal,
+    /// Identity that created the resource
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+impl ResourceMetadata {
+    pub fn new(
+        id: &str,
+        namespace: &str,
+        policy: ResourcePolicy,
+        created_by: Option<String>,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            namespace: namespace.to_string(),
+            policy,
+            total_supply: Decimal::ZERO,
+            created_by,
+        }
+    }
+
+    /// Check that an amount has no more decimal places than the resource allows
+    pub fn check_precision(&self, amount: Decimal) -> Result<(), StorageError> {
+        match self.policy.decimals {
+            Some(decimals) if amount.normalize().scale() > decimals => {
+                Err(StorageError::ValidationError {
+                    rule: "resource_decimals".to_string(),
+                    details: format!(
+                        "Amount {} has more than the {} decimal places resource {} allows",
+                        amount, decimals, self.id
+                    ),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that `minter` may mint `amount`, returning the new total supply
+    pub fn check_mint(
+        &self,
+        minter: Option<&str>,
+        amount: Decimal,
+    ) -> Result<Decimal, StorageError> {
+        self.check_precision(amount)?;
+        if !self.policy.minters.is_empty()
+            && !minter.is_some_and(|minter| self.policy.minters.iter().any(|m| m == minter))
+        {
+            return Err(StorageError::PermissionDenied {
+                user_id: minter.unwrap_or("anonymous").to_string(),
+                action: "mint".to_string(),
+                key: format!("resources/{}", self.id),
+            });
+        }
+
+        let supply_error = |details: String| StorageError::ValidationError {
+            rule: "issuance_policy".to_string(),
+            details,
+        };
+        let total_supply = self.total_supply.checked_add(amount).ok_or_else(|| {
+            supply_error(format!(
+                "Total supply of resource {} would overflow",
+                self.id
+            ))
+        })?;
+        match &self.policy.issuance {
+            IssuancePolicy::Open => Ok(total_supply),
+            IssuancePolicy::Capped { max_supply } if total_supply <= *max_supply => {
+                Ok(total_supply)
+            }
+            IssuancePolicy::Capped { max_supply } => Err(supply_error(format!(
+                "Minting {} would take the supply of resource {} above its cap of {}",
+                amount, self.id, max_supply
+            ))),
+            IssuancePolicy::Fixed => Err(supply_error(format!(
+                "Resource {} has a fixed supply",
+                self.id
+            ))),
+        }
+    }
+
+    /// Check that `amount` may be transferred between accounts
+    pub fn check_transfer(&self, amount: Decimal) -> Result<(), StorageError> {
+        self.check_precision(amount)?;
+        if !self.policy.transferable {
+            return Err(StorageError::ValidationError {
+                rule: "non_transferable".to_string(),
+                details: format!("Resource {} is not transferable", self.id),
+            });
+        }
+        Ok(())
+    }
+}
+
+// Escrow
+
+/// Where an escrowed amount stands
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscrowStatus {
+    /// Held until the proposal is decided
+    Locked,
+    /// Paid to the beneficiary after the proposal was executed
+    Released,
+    /// Returned to the depositor after the proposal was rejected or expired
+    Refunded,
+}
+
+/// How the escrows of a decided proposal are settled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowOutcome {
+    /// Pay each escrow to its beneficiary
+    Release,
+    /// Return each escrow to its depositor
+    Refund,
+}
+
+impl EscrowOutcome {
+    /// The settlement a proposal's lifecycle state allows, if it is decided
+    pub fn for_proposal_state(state: &str) -> Option<Self> {
+        match state {
+            "Executed" => Some(Self::Release),
+            "Rejected" | "Expired" => Some(Self::Refund),
+            _ => None,
+        }
+    }
+
+    pub fn status(self) -> EscrowStatus {
+        match self {
+            Self::Release => EscrowStatus::Released,
+            Self::Refund => EscrowStatus::Refunded,
+        }
+    }
+}
+
+/// A resource amount held under a proposal
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Escrow {
+    pub proposal_id: String,
+    pub resource: String,
+    /// Account the amount was taken from, and is refunded to
+    pub from: String,
+    /// Account the amount is released to
+    pub to: String,
+    pub amount: Decimal,
+    pub status: EscrowStatus,
+}
+
+impl Escrow {
+    /// Account the amount goes to when settled with `outcome`
+    pub fn payee(&self, outcome: EscrowOutcome) -> &str {
+        match outcome {
+            EscrowOutcome::Release => &self.to,
+            EscrowOutcome::Refund => &self.from,
+        }
+    }
+}
+
+// Mutual credit
+
+/// Creditor standing for the cooperative as a whole in a credit line
+pub const COMMONS_CREDITOR: &str = "commons";
+
+/// Credit a member, or the commons, extends to an account in a resource
+///
+/// Transfers may take an account below zero, down to minus the sum of the
+/// credit lines extended to it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreditLine {
+    pub resource: String,
+    /// Account extending the credit, or [`COMMONS_CREDITOR`]
+    pub creditor: String,
+    /// Account the credit is extended to
+    pub debtor: String,
+    pub limit: Decimal,
+    /// Proposal whose execution set the limit
+    pub proposal_id: Option<String>,
+}
+
+// Allowances
+
+/// Prefix of an allowance spender standing for everyone holding a role in
+/// the namespace
+pub const ROLE_SPENDER_PREFIX: &str = "role:";
+
+/// Prefix of an allowance spender standing for a proposal's logic
+pub const PROPOSAL_SPENDER_PREFIX: &str = "proposal:";
+
+/// Units an identity lets a spender transfer out of its account on its
+/// behalf, kept at `resources/<id>/allowances/<owner>/<spender>`
+///
+/// The spender is an identity's DID, `role:<role>` for anyone holding the
+/// role in the namespace, or `proposal:<id>` for the logic of a proposal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Allowance {
+    pub resource: String,
+    /// Identity whose account the allowance is spent from
+    pub owner: String,
+    pub spender: String,
+    /// Units still to be spent
+    pub remaining: Decimal,
+}
+
+impl Allowance {
+    /// Check that a spender names an identity, a role or a proposal
+    pub fn validate_spender(spender: &str) -> Result<(), StorageError> {
+        let name = spender
+            .strip_prefix(ROLE_SPENDER_PREFIX)
+            .or_else(|| spender.strip_prefix(PROPOSAL_SPENDER_PREFIX))
+            .unwrap_or(spender);
+        if name.is_empty() {
+            return Err(StorageError::ValidationError {
+                rule: "allowance_spender".to_string(),
+                details: format!("Allowance spender '{}' names no one", spender),
+            });
+        }
+        Ok(())
+    }
+}
+
+// Bounties
+
+/// How a claim on a bounty is accepted
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BountyVerification {
+    /// Any one identity holding `role` in the bounty's namespace decides
+    Verifier { role: String },
+    /// Members other than the claimant vote; `threshold` approvals accept
+    /// the claim, and as many rejections turn it down
+    Votes { threshold: u32 },
+}
+
+/// Where a bounty stands
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BountyStatus {
+    /// Funded and waiting for a claim
+    Open,
+    /// A claim is waiting to be verified
+    Claimed,
+    /// The claim was accepted and the reward paid out
+    Paid,
+    /// Withdrawn before it was claimed, and the reward refunded
+    Cancelled,
+}
+
+/// Work submitted against a bounty, with the verifications recorded so far
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BountyClaim {
+    pub claimant: String,
+    /// Link to, or description of, the work done
+    pub evidence: String,
+    #[serde(default)]
+    pub approvals: Vec<String>,
+    #[serde(default)]
+    pub rejections: Vec<String>,
+}
+
+/// A reward for a piece of work, kept at `bounties/<id>`
+///
+/// The reward is taken from the funding account when the bounty is
+/// created, and held until it is paid to a claimant or refunded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bounty {
+    pub id: String,
+    pub resource: String,
+    /// Account the reward was taken from, such as the treasury
+    pub funder: String,
+    pub amount: Decimal,
+    pub description: String,
+    pub verification: BountyVerification,
+    pub status: BountyStatus,
+    /// Claim waiting to be verified, or the one that was paid
+    #[serde(default)]
+    pub claim: Option<BountyClaim>,
+    /// Identity that created the bounty
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+impl Bounty {
+    /// Record `verifier`'s approval or rejection of the pending claim,
+    /// returning whether the claim is now accepted (`Some(true)`) or turned
+    /// down (`Some(false)`)
+    ///
+    /// The caller checks that `verifier` may verify the bounty at all.
+    pub fn record_verification(
+        &mut self,
+        verifier: &str,
+        approve: bool,
+    ) -> Result<Option<bool>, StorageError> {
+        let Some(claim) = self
+            .claim
+            .as_mut()
+            .filter(|_| self.status == BountyStatus::Claimed)
+        else {
+            return Err(StorageError::ConflictError {
+                resource: format!("bounties/{}", self.id),
+                details: "Bounty has no claim waiting to be verified".to_string(),
+            });
+        };
+        let threshold = match &self.verification {
+            BountyVerification::Verifier { .. } => 1,
+            BountyVerification::Votes { threshold } => {
+                if claim.claimant == verifier {
+                    return Err(StorageError::PermissionDenied {
+                        user_id: verifier.to_string(),
+                        action: "verify own claim".to_string(),
+                        key: format!("bounties/{}", self.id),
+                    });
+                }
+                (*threshold).max(1) as usize
+            }
+        };
+        if claim
+            .approvals
+            .iter()
+            .chain(&claim.rejections)
+            .any(|v| v == verifier)
+        {
+            return Err(StorageError::ConflictError {
+                resource: format!("bounties/{}", self.id),
+                details: format!("{} has already verified this claim", verifier),
+            });
+        }
+
+        if approve {
+            claim.approvals.push(verifier.to_string());
+        } else {
+            claim.rejections.push(verifier.to_string());
+        }
+        Ok(if claim.approvals.len() >= threshold {
+            Some(true)
+        } else if claim.rejections.len() >= threshold {
+            Some(false)
+        } else {
+            None
+        })
+    }
+}
+
+// Distributions
+
+/// One account's part of a distribution
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DistributionShare {
+    pub account: String,
+    pub weight: Decimal,
+    pub amount: Decimal,
+}
+
+/// An amount split from a pool account across members in proportion to
+/// their weights, such as hours worked or patronage points
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub resource: String,
+    pub pool: String,
+    /// Storage key prefix the weights were read from
+    pub weight_key: String,
+    /// Amount that was to be split
+    pub amount: Decimal,
+    pub shares: Vec<DistributionShare>,
+}
+
+impl Distribution {
+    /// Split `amount` in proportion to `weights`
+    ///
+    /// Each share is rounded down to `decimals` places if the resource limits
+    /// them, so whatever rounding leaves over stays in the pool. Accounts
+    /// with a weight of zero get no share.
+    pub fn proportional(
+        resource: &str,
+        pool: &str,
+        weight_key: &str,
+        amount: Decimal,
+        weights: Vec<(String, Decimal)>,
+        decimals: Option<u32>,
+    ) -> Result<Self, StorageError> {
+        let weight_error = |details: String| StorageError::ValidationError {
+            rule: "distribution_weight".to_string(),
+            details,
+        };
+        if let Some((account, weight)) = weights.iter().find(|(_, w)| w.is_sign_negative()) {
+            return Err(weight_error(format!(
+                "Weight {} of account {} is negative",
+                weight, account
+            )));
+        }
+        let total = weights
+            .iter()
+            .try_fold(Decimal::ZERO, |total, (_, w)| total.checked_add(*w))
+            .ok_or_else(|| weight_error(format!("Weights under {} overflow", weight_key)))?;
+        if total.is_zero() {
+            return Err(weight_error(format!(
+                "No account has a weight under {}",
+                weight_key
+            )));
+        }
+
+        let mut shares = Vec::with_capacity(weights.len());
+        for (account, weight) in weights {
+            if weight.is_zero() {
+                continue;
+            }
+            let share = amount
+                .checked_mul(weight)
+                .and_then(|scaled| scaled.checked_div(total))
+                .ok_or_else(|| weight_error(format!("Share of account {} overflows", account)))?;
+            let share = match decimals {
+                Some(decimals) => share.round_dp_with_strategy(decimals, RoundingStrategy::ToZero),
+                None => share,
+            }
+            .normalize();
+            shares.push(DistributionShare {
+                account,
+                weight,
+                amount: share,
+            });
+        }
+
+        Ok(Self {
+            resource: resource.to_string(),
+            pool: pool.to_string(),
+            weight_key: weight_key.to_string(),
+            amount,
+            shares,
+        })
+    }
+
+    /// Total paid out, which rounding may leave below `amount`
+    pub fn paid(&self) -> Decimal {
+        self.shares.iter().map(|share| share.amount).sum()
+    }
+}
+
+// Exchange
+
+/// Rate at which one internal resource converts into another, set by a
+/// passed proposal
+///
+/// Exchanges pay into and out of a reserve account held in both resources,
+/// so neither supply changes and no more is paid out than the reserve holds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub from_resource: String,
+    pub to_resource: String,
+    /// Units of `to_resource` paid for one unit of `from_resource`
+    pub rate: Decimal,
+    /// Account exchanges pay into and out of
+    pub reserve: String,
+    /// Most of `from_resource` one exchange may convert; any if unset
+    pub max_amount: Option<Decimal>,
+    /// Proposal whose execution set the rate
+    pub proposal_id: Option<String>,
+}
+
+impl ExchangeRate {
+    /// What `amount` of `from_resource` converts to, rounded down to
+    /// `decimals` places if `to_resource` limits them
+    pub fn quote(&self, amount: Decimal, decimals: Option<u32>) -> Result<Decimal, StorageError> {
+        if let Some(max) = self.max_amount.filter(|max| amount > *max) {
+            return Err(StorageError::ValidationError {
+                rule: "exchange_limit".to_string(),
+                details: format!(
+                    "At most {} of {} may be exchanged at once, got {}",
+                    max, self.from_resource, amount
+                ),
+            });
+        }
+        let converted =
+            amount
+                .checked_mul(self.rate)
+                .ok_or_else(|| StorageError::ValidationError {
+                    rule: "exchange_limit".to_string(),
+                    details: format!("Exchanging {} of {} overflows", amount, self.from_resource),
+                })?;
+        Ok(match decimals {
+            Some(decimals) => converted.round_dp_with_strategy(decimals, RoundingStrategy::ToZero),
+            None => converted,
+        }
+        .normalize())
+    }
+}
+
+// Spending limits
+
+/// Economic action a spending limit caps
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendingAction {
+    Transfer,
+    Mint,
+}
+
+impl SpendingAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpendingAction::Transfer => "transfer",
+            SpendingAction::Mint => "mint",
+        }
+    }
+}
+
+/// Rolling window a spending limit is counted over
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendingWindow {
+    Day,
+    Week,
+}
+
+impl SpendingWindow {
+    /// Length of the window in seconds
+    pub fn secs(&self) -> u64 {
+        match self {
+            SpendingWindow::Day => 24 * 60 * 60,
+            SpendingWindow::Week => 7 * 24 * 60 * 60,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpendingWindow::Day => "day",
+            SpendingWindow::Week => "week",
+        }
+    }
+}
+
+/// Most of a resource an identity may transfer or mint within a rolling
+/// window, set by a passed proposal and kept at
+/// `resources/<id>/spending_limits/<subject>/<action>`
+///
+/// The subject is an identity's DID, or `role:<role>` to cap each identity
+/// holding the role in the namespace separately.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpendingLimit {
+    pub resource: String,
+    pub subject: String,
+    pub action: SpendingAction,
+    pub window: SpendingWindow,
+    pub limit: Decimal,
+    /// Proposal whose execution set the limit
+    pub proposal_id: Option<String>,
+}
+
+/// An amount transferred or minted, and when
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpendingEntry {
+    pub at: Timestamp,
+    pub amount: Decimal,
+}
+
+/// What an identity has recently transferred or minted of a resource,
+/// kept at `resources/<id>/spending/<identity>/<action>`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpendingLog {
+    pub entries: Vec<SpendingEntry>,
+}
+
+impl SpendingLog {
+    /// Total spent within `window` of `now`
+    pub fn spent_within(&self, window: SpendingWindow, now: Timestamp) -> Decimal {
+        let since = now.saturating_sub(window.secs());
+        self.entries
+            .iter()
+            .filter(|entry| entry.at > since)
+            .fold(Decimal::ZERO, |total, entry| {
+                total.saturating_add(entry.amount)
+            })
+    }
+
+    /// Add an amount spent at `now`, dropping entries no window still counts
+    pub fn record(&mut self, amount: Decimal, now: Timestamp) {
+        let since = now.saturating_sub(SpendingWindow::Week.secs());
+        self.entries.retain(|entry| entry.at > since);
+        self.entries.push(SpendingEntry { at: now, amount });
+    }
+}
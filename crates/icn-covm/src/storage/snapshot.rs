@@ -0,0 +1,320 @@
+//! Point-in-time copies of a storage backend
+//!
+//! [`StorageBackend::snapshot`] captures the namespaces, the resource
+//! accounts, and the latest value and version of every key;
+//! [`StorageBackend::restore`] replaces everything the backend holds with
+//! them. A `StorageSnapshot` does not depend on the backend it was taken
+//! from, so `storage snapshot` writes one to a JSON file and `storage
+//! restore` reads it back, into the same backend or another.
+//!
+//! Earlier versions of a value are not captured: after a restore, each key
+//! carries the version it had when the snapshot was taken, and its history
+//! continues from there. The backend's own event log is not captured either,
+//! and a restore leaves it as it is.
+//!
+//! [`StorageBackend::snapshot`]: crate::storage::traits::StorageBackend::snapshot
+//! [`StorageBackend::restore`]: crate::storage::traits::StorageBackend::restore
+
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::namespaces::NamespaceMetadata;
+use crate::storage::resource::ResourceAccount;
+use crate::storage::utils::{now_with_default, Timestamp};
+use crate::storage::versioning::VersionInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Version of the snapshot layout written by this build
+pub const SNAPSHOT_FORMAT: u32 = 1;
+
+/// Quota given to namespaces that hold keys in a snapshot but have no
+/// metadata of their own, as the in-memory backend keeps none
+const IMPLICIT_NAMESPACE_QUOTA: u64 = 1024 * 1024 * 1024;
+
+/// The latest value of one key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub namespace: String,
+    pub key: String,
+    /// The value, as standard padded base64 in the JSON form
+    #[serde(with = "base64_bytes")]
+    pub value: Vec<u8>,
+    pub version: VersionInfo,
+}
+
+/// Everything a storage backend holds, as of `taken_at`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageSnapshot {
+    pub format: u32,
+    pub taken_at: Timestamp,
+    pub namespaces: Vec<NamespaceMetadata>,
+    pub entries: Vec<SnapshotEntry>,
+    pub accounts: Vec<ResourceAccount>,
+}
+
+impl Default for StorageSnapshot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StorageSnapshot {
+    /// An empty snapshot taken now
+    pub fn new() -> Self {
+        Self {
+            format: SNAPSHOT_FORMAT,
+            taken_at: now_with_default(),
+            namespaces: Vec::new(),
+            entries: Vec::new(),
+            accounts: Vec::new(),
+        }
+    }
+
+    /// Put namespaces, entries and accounts in order, so the same contents
+    /// always give the same file
+    pub fn sorted(mut self) -> Self {
+        self.namespaces.sort_by(|a, b| a.path.cmp(&b.path));
+        self.entries
+            .sort_by(|a, b| (&a.namespace, &a.key).cmp(&(&b.namespace, &b.key)));
+        self.accounts.sort_by(|a, b| a.owner_id.cmp(&b.owner_id));
+        self
+    }
+
+    /// Metadata of every namespace to restore, including those that only
+    /// appear in entries
+    pub fn all_namespaces(&self) -> Vec<NamespaceMetadata> {
+        let mut namespaces: BTreeMap<String, NamespaceMetadata> = self
+            .namespaces
+            .iter()
+            .map(|metadata| (metadata.path.clone(), metadata.clone()))
+            .collect();
+        for entry in &self.entries {
+            namespaces
+                .entry(entry.namespace.clone())
+                .or_insert_with(|| implicit_namespace(&entry.namespace));
+        }
+        namespaces.into_values().collect()
+    }
+
+    /// Replace the entries of `namespace` with those in `current`
+    ///
+    /// `storage restore` uses this to keep the audit namespace as it is, so
+    /// rolling back does not erase the record of what was rolled back.
+    pub fn keep_namespace(&mut self, current: &StorageSnapshot, namespace: &str) {
+        self.entries.retain(|entry| entry.namespace != namespace);
+        self.entries.extend(
+            current
+                .entries
+                .iter()
+                .filter(|entry| entry.namespace == namespace)
+                .cloned(),
+        );
+        if !self.namespaces.iter().any(|ns| ns.path == namespace) {
+            self.namespaces.extend(
+                current
+                    .namespaces
+                    .iter()
+                    .filter(|ns| ns.path == namespace)
+                    .cloned(),
+            );
+        }
+    }
+
+    /// Check the snapshot can be restored as it stands
+    ///
+    /// Fails on a layout newer than this build reads, or on a key, namespace
+    /// or account that appears twice.
+    pub fn validate(&self) -> StorageResult<()> {
+        if self.format > SNAPSHOT_FORMAT {
+            return Err(StorageError::SchemaVersionError {
+                current_version: SNAPSHOT_FORMAT.to_string(),
+                required_version: self.format.to_string(),
+                details: "The snapshot was written by a newer version".to_string(),
+            });
+        }
+        let duplicate = |what: &str, name: String| StorageError::ValidationError {
+            rule: "snapshot".to_string(),
+            details: format!("The snapshot holds {} {} twice", what, name),
+        };
+        let mut namespaces = HashSet::new();
+        for metadata in &self.namespaces {
+            if !namespaces.insert(metadata.path.as_str()) {
+                return Err(duplicate("namespace", metadata.path.clone()));
+            }
+        }
+        let mut keys = HashSet::new();
+        for entry in &self.entries {
+            if !keys.insert((entry.namespace.as_str(), entry.key.as_str())) {
+                return Err(duplicate(
+                    "key",
+                    format!("{}:{}", entry.namespace, entry.key),
+                ));
+            }
+        }
+        let mut accounts = HashSet::new();
+        for account in &self.accounts {
+            if !accounts.insert(account.owner_id.as_str()) {
+                return Err(duplicate("account", account.owner_id.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the snapshot to `path` as JSON
+    pub fn save(&self, path: &Path) -> StorageResult<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?).map_err(|e| StorageError::IoError {
+            operation: "write snapshot".to_string(),
+            details: format!("Failed to write {}: {}", path.display(), e),
+        })
+    }
+
+    /// Read a snapshot written by `save`
+    pub fn load(path: &Path) -> StorageResult<Self> {
+        let data = fs::read(path).map_err(|e| StorageError::IoError {
+            operation: "read snapshot".to_string(),
+            details: format!("Failed to read {}: {}", path.display(), e),
+        })?;
+        let snapshot: Self = serde_json::from_slice(&data)?;
+        snapshot.validate()?;
+        Ok(snapshot)
+    }
+}
+
+/// Metadata for a namespace that exists only because it holds keys
+pub(crate) fn implicit_namespace(path: &str) -> NamespaceMetadata {
+    NamespaceMetadata {
+        path: path.to_string(),
+        owner: "system".to_string(),
+        quota_bytes: IMPLICIT_NAMESPACE_QUOTA,
+        used_bytes: 0,
+        parent: None,
+        attributes: HashMap::new(),
+    }
+}
+
+mod base64_bytes {
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::auth::AuthContext;
+    use crate::storage::implementations::file_storage::FileStorage;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use crate::storage::implementations::sled_storage::SledStorage;
+    use crate::storage::traits::StorageBackend;
+
+    fn admin() -> AuthContext {
+        let mut auth = AuthContext::new("operator");
+        auth.add_role("global", "admin");
+        auth.add_role("coop", "admin");
+        auth
+    }
+
+    /// Write a value, snapshot, change and add values, then restore and
+    /// check only the snapshotted state is left
+    fn roll_back<S: StorageBackend>(storage: &mut S) {
+        let auth = admin();
+        storage
+            .create_account(Some(&auth), "operator", 1024 * 1024)
+            .unwrap();
+        storage
+            .create_namespace(Some(&auth), "coop", 1024 * 1024, None)
+            .unwrap();
+        storage
+            .set(Some(&auth), "coop", "quorum", b"0.5".to_vec())
+            .unwrap();
+        storage
+            .set(Some(&auth), "coop", "quorum", b"0.6".to_vec())
+            .unwrap();
+        storage
+            .set(Some(&auth), "coop", "members/alice", vec![0, 159, 146])
+            .unwrap();
+
+        let snapshot = storage.snapshot().unwrap();
+        assert_eq!(snapshot.entries.len(), 2);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: StorageSnapshot = serde_json::from_str(&json).unwrap();
+
+        storage
+            .set(Some(&auth), "coop", "quorum", b"0.9".to_vec())
+            .unwrap();
+        storage
+            .set(Some(&auth), "coop", "treasury", b"100".to_vec())
+            .unwrap();
+        storage.restore(snapshot).unwrap();
+
+        assert_eq!(storage.get(Some(&auth), "coop", "quorum").unwrap(), b"0.6");
+        let (_, version) = storage
+            .get_versioned(Some(&auth), "coop", "quorum")
+            .unwrap();
+        assert_eq!(version.version, 2);
+        assert_eq!(
+            storage.get(Some(&auth), "coop", "members/alice").unwrap(),
+            vec![0, 159, 146]
+        );
+        assert!(!storage.contains(Some(&auth), "coop", "treasury").unwrap());
+
+        // Writes carry on from the restored version
+        storage
+            .set(Some(&auth), "coop", "quorum", b"0.7".to_vec())
+            .unwrap();
+        let (_, version) = storage
+            .get_versioned(Some(&auth), "coop", "quorum")
+            .unwrap();
+        assert_eq!(version.version, 3);
+    }
+
+    #[test]
+    fn test_snapshot_and_restore_each_backend() {
+        roll_back(&mut InMemoryStorage::new());
+        let dir = tempfile::tempdir().unwrap();
+        roll_back(&mut FileStorage::new(dir.path().join("file")).unwrap());
+        roll_back(&mut SledStorage::open(dir.path().join("sled")).unwrap());
+
+        // A snapshot from one backend restores into another
+        let mut memory = InMemoryStorage::new();
+        let auth = admin();
+        memory
+            .create_namespace(Some(&auth), "coop", 1024, None)
+            .unwrap();
+        memory
+            .create_account(Some(&auth), "operator", 1024)
+            .unwrap();
+        memory
+            .set(Some(&auth), "coop", "quorum", b"0.5".to_vec())
+            .unwrap();
+        let mut file = FileStorage::new(dir.path().join("copy")).unwrap();
+        file.restore(memory.snapshot().unwrap()).unwrap();
+        assert_eq!(file.get(Some(&auth), "coop", "quorum").unwrap(), b"0.5");
+
+        let mut duplicated = memory.snapshot().unwrap();
+        duplicated.entries.push(duplicated.entries[0].clone());
+        assert!(matches!(
+            file.restore(duplicated),
+            Err(StorageError::ValidationError { .. })
+        ));
+
+        // A restore that fails to write puts the old data back
+        let mut unwritable = memory.snapshot().unwrap();
+        unwritable.entries[0].value = b"0.9".to_vec();
+        unwritable.accounts[0].owner_id = "missing/operator".to_string();
+        assert!(file.restore(unwritable).is_err());
+        assert_eq!(file.get(Some(&auth), "coop", "quorum").unwrap(), b"0.5");
+        assert_eq!(file.snapshot().unwrap().accounts[0].owner_id, "operator");
+        file.restore(memory.snapshot().unwrap()).unwrap();
+    }
+}
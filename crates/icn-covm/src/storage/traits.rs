@@ -2,7 +2,17 @@ use crate::storage::auth::AuthContext;
 use crate::storage::errors::{StorageError, StorageResult};
 use crate::storage::events::StorageEvent;
 use crate::storage::namespaces::NamespaceMetadata;
+use crate::storage::proposal_list::{self, PagedProposals, ProposalPageQuery};
+use crate::storage::resource::{
+    Allowance, Bounty, BountyClaim, BountyStatus, BountyVerification, CreditLine, DemurrageState,
+    Distribution, Escrow, EscrowOutcome, EscrowStatus, ExchangeRate, ResourceMetadata,
+    ResourcePolicy, SpendingAction, SpendingLimit, SpendingLog, PROPOSAL_SPENDER_PREFIX,
+    ROLE_SPENDER_PREFIX,
+};
+use crate::storage::snapshot::StorageSnapshot;
+use crate::storage::utils::{now_with_default, Timestamp};
 use crate::storage::versioning::{VersionDiff, VersionInfo};
+use rust_decimal::Decimal;
 use serde::{de::DeserializeOwned, Serialize};
 
 /// Defines the core operations for a cooperative storage backend.
@@ -140,6 +150,17 @@ pub trait StorageBackend {
 
     /// Get storage usage for a namespace
     fn get_usage(&self, auth: Option<&AuthContext>, namespace: &str) -> StorageResult<u64>;
+
+    /// Captures the namespaces, accounts and latest value of every key.
+    /// Not permission-checked: this is for operators backing up the whole
+    /// backend, so callers decide who may take one.
+    fn snapshot(&self) -> StorageResult<StorageSnapshot>;
+
+    /// Replaces everything the backend holds with `snapshot`, leaving the
+    /// event log as it is. Fails, without changing anything, inside a
+    /// transaction, if the snapshot does not validate or if it cannot be
+    /// written.
+    fn restore(&mut self, snapshot: StorageSnapshot) -> StorageResult<()>;
 }
 
 // Convenience extension trait - with methods that depend on StorageBackend
@@ -224,6 +245,19 @@ pub trait StorageExtensions: StorageBackend {
         self.delete(Some(auth_context), namespace, key)
     }
 
+    /// List one page of the namespace's proposals matching `query`
+    ///
+    /// Only the proposals on the page are read in full; see
+    /// `storage::proposal_list`.
+    fn list_proposals_paged(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        query: &ProposalPageQuery,
+    ) -> StorageResult<PagedProposals> {
+        proposal_list::list_proposals_paged(self, auth, namespace, query)
+    }
+
     /// Store versioning-aware JSON data with built-in conflict detection
     fn set_json_versioned<T: Serialize + DeserializeOwned>(
         &mut self,
@@ -323,23 +357,442 @@ impl<S: StorageBackend> StorageExtensions for S {
     }
 }
 
+/// Reject negative token amounts
+fn check_amount(amount: Decimal) -> StorageResult<()> {
+    if amount.is_sign_negative() {
+        return Err(StorageError::ValidationError {
+            rule: "non_negative_amount".to_string(),
+            details: format!("Amount {} is negative", amount),
+        });
+    }
+    Ok(())
+}
+
+/// Add to a balance, failing rather than overflowing
+fn add_to_balance(
+    balance: Decimal,
+    amount: Decimal,
+    account: &str,
+    resource: &str,
+) -> StorageResult<Decimal> {
+    balance
+        .checked_add(amount)
+        .ok_or_else(|| StorageError::ValidationError {
+            rule: "balance_overflow".to_string(),
+            details: format!(
+                "Balance of account {} for resource {} would overflow",
+                account, resource
+            ),
+        })
+}
+
+/// Read a resource's registry entry
+fn read_resource<S: StorageBackend + ?Sized>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    resource: &str,
+) -> StorageResult<ResourceMetadata> {
+    let key = format!("resources/{}/metadata", resource);
+    if !storage.contains(auth, namespace, &key)? {
+        return Err(StorageError::ResourceNotFound(resource.to_string()));
+    }
+    serde_json::from_slice(&storage.get(auth, namespace, &key)?).map_err(|e| {
+        StorageError::SerializationError {
+            data_type: "ResourceMetadata".to_string(),
+            details: e.to_string(),
+        }
+    })
+}
+
+/// Write a resource's registry entry
+fn write_resource<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    metadata: &ResourceMetadata,
+) -> StorageResult<()> {
+    let bytes = serde_json::to_vec(metadata).map_err(|e| StorageError::SerializationError {
+        data_type: "ResourceMetadata".to_string(),
+        details: e.to_string(),
+    })?;
+    let key = format!("resources/{}/metadata", metadata.id);
+    storage.set(auth, namespace, &key, bytes)
+}
+
+/// Read an account's balance of a resource, zero if it has none
+fn read_balance<S: StorageBackend + ?Sized>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    resource: &str,
+    account: &str,
+) -> StorageResult<Decimal> {
+    let key = format!("resources/{}/accounts/{}", resource, account);
+    if !storage.contains(auth, namespace, &key)? {
+        return Ok(Decimal::ZERO);
+    }
+    Ok(std::str::from_utf8(&storage.get(auth, namespace, &key)?)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_default())
+}
+
+/// Write an account's balance of a resource
+fn write_balance<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    resource: &str,
+    account: &str,
+    balance: Decimal,
+) -> StorageResult<()> {
+    let key = format!("resources/{}/accounts/{}", resource, account);
+    storage.set(auth, namespace, &key, balance.to_string().into_bytes())
+}
+
+/// Key of an account's standing against its resource's demurrage
+fn demurrage_key(resource: &str, account: &str) -> String {
+    format!("resources/{}/demurrage/{}", resource, account)
+}
+
+/// An account's stored balance and its balance after the demurrage due at
+/// `now`, with the standing to write once that decay is materialized
+///
+/// The standing is `None` for resources without demurrage. Accounts with no
+/// standing yet have nothing due, and are tracked from `now`.
+fn decayed_balance<S: StorageBackend + ?Sized>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    metadata: &ResourceMetadata,
+    account: &str,
+    now: Timestamp,
+) -> StorageResult<(Decimal, Decimal, Option<DemurrageState>)> {
+    let balance = read_balance(storage, auth, namespace, &metadata.id, account)?;
+    let Some(policy) = &metadata.policy.demurrage else {
+        return Ok((balance, balance, None));
+    };
+    let key = demurrage_key(&metadata.id, account);
+    if !storage.contains(auth, namespace, &key)? {
+        return Ok((balance, balance, Some(DemurrageState::new(now))));
+    }
+    let mut state: DemurrageState = serde_json::from_slice(&storage.get(auth, namespace, &key)?)
+        .map_err(|e| StorageError::SerializationError {
+            data_type: "DemurrageState".to_string(),
+            details: e.to_string(),
+        })?;
+    let periods = policy.periods(state.since, now);
+    let decayed = policy.decay(
+        balance,
+        periods.saturating_sub(state.decayed_periods),
+        metadata.policy.decimals,
+    );
+    state.decayed_periods = state.decayed_periods.max(periods);
+    Ok((balance, decayed, Some(state)))
+}
+
+/// Write an account's standing against its resource's demurrage
+fn write_demurrage_state<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    resource: &str,
+    account: &str,
+    state: &DemurrageState,
+) -> StorageResult<()> {
+    let bytes = serde_json::to_vec(state).map_err(|e| StorageError::SerializationError {
+        data_type: "DemurrageState".to_string(),
+        details: e.to_string(),
+    })?;
+    storage.set(auth, namespace, &demurrage_key(resource, account), bytes)
+}
+
+/// Write the demurrage due on an account at `now` to its balance, returning
+/// the balance and the amount charged
+///
+/// The charge is taken off `metadata.total_supply`; the caller writes the
+/// metadata back when the charge is not zero.
+fn materialize_demurrage<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    metadata: &mut ResourceMetadata,
+    account: &str,
+    now: Timestamp,
+) -> StorageResult<(Decimal, Decimal)> {
+    let (balance, decayed, state) =
+        decayed_balance(storage, auth, namespace, metadata, account, now)?;
+    let Some(state) = state else {
+        return Ok((balance, Decimal::ZERO));
+    };
+    let charged = balance - decayed;
+    if !charged.is_zero() {
+        write_balance(storage, auth, namespace, &metadata.id, account, decayed)?;
+        metadata.total_supply = (metadata.total_supply - charged).max(Decimal::ZERO);
+    }
+    write_demurrage_state(storage, auth, namespace, &metadata.id, account, &state)?;
+    Ok((decayed, charged))
+}
+
+/// Start an account's grace period again after its balance changes
+fn restart_demurrage<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    metadata: &ResourceMetadata,
+    account: &str,
+    now: Timestamp,
+) -> StorageResult<()> {
+    if metadata.policy.demurrage.is_none() {
+        return Ok(());
+    }
+    write_demurrage_state(
+        storage,
+        auth,
+        namespace,
+        &metadata.id,
+        account,
+        &DemurrageState::new(now),
+    )
+}
+
+/// Take an amount out of an account to be held by an escrow or bounty
+///
+/// Held amounts cannot be drawn on credit.
+fn hold_funds<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    metadata: &mut ResourceMetadata,
+    account: &str,
+    amount: Decimal,
+) -> StorageResult<()> {
+    let now = now_with_default();
+    let (balance, charged) =
+        materialize_demurrage(storage, auth, namespace, metadata, account, now)?;
+    if !charged.is_zero() {
+        write_resource(storage, auth, namespace, metadata)?;
+    }
+    if balance < amount {
+        return Err(StorageError::InsufficientBalance(format!(
+            "Account {} has insufficient balance for resource {}",
+            account, metadata.id
+        )));
+    }
+    write_balance(
+        storage,
+        auth,
+        namespace,
+        &metadata.id,
+        account,
+        balance - amount,
+    )?;
+    restart_demurrage(storage, auth, namespace, metadata, account, now)
+}
+
+/// Pay an amount held by an escrow or bounty into an account
+fn pay_held_funds<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    resource: &str,
+    account: &str,
+    amount: Decimal,
+) -> StorageResult<()> {
+    let now = now_with_default();
+    let mut metadata = read_resource(storage, auth, namespace, resource)?;
+    let (balance, charged) =
+        materialize_demurrage(storage, auth, namespace, &mut metadata, account, now)?;
+    if !charged.is_zero() {
+        write_resource(storage, auth, namespace, &metadata)?;
+    }
+    let balance = add_to_balance(balance, amount, account, resource)?;
+    write_balance(storage, auth, namespace, resource, account, balance)?;
+    restart_demurrage(storage, auth, namespace, &metadata, account, now)
+}
+
+/// Key of a bounty's record
+fn bounty_key(id: &str) -> String {
+    format!("bounties/{}", id)
+}
+
+/// Write a bounty's record
+fn write_bounty<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    bounty: &Bounty,
+) -> StorageResult<()> {
+    let bytes = serde_json::to_vec(bounty).map_err(|e| StorageError::SerializationError {
+        data_type: "Bounty".to_string(),
+        details: e.to_string(),
+    })?;
+    storage.set(auth, namespace, &bounty_key(&bounty.id), bytes)
+}
+
+/// Identity acting through `auth`, which bounty steps other than creation
+/// require
+fn acting_identity(auth: Option<&AuthContext>, action: &str, key: &str) -> StorageResult<String> {
+    auth.map(|a| a.user_id_string())
+        .ok_or_else(|| StorageError::PermissionDenied {
+            user_id: "anonymous".to_string(),
+            action: action.to_string(),
+            key: key.to_string(),
+        })
+}
+
+/// Key prefix of the credit lines extended to an account
+fn credit_prefix(resource: &str, debtor: &str) -> String {
+    format!("resources/{}/credit/{}/", resource, debtor)
+}
+
+/// Key prefix of the allowances an identity has given in a resource
+fn allowance_prefix(resource: &str, owner: &str) -> String {
+    format!("resources/{}/allowances/{}/", resource, owner)
+}
+
+/// Write an allowance, removing it once nothing is left to spend
+fn write_allowance<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    allowance: &Allowance,
+) -> StorageResult<()> {
+    let key = format!(
+        "{}{}",
+        allowance_prefix(&allowance.resource, &allowance.owner),
+        allowance.spender
+    );
+    if allowance.remaining.is_zero() {
+        if storage.contains(auth, namespace, &key)? {
+            storage.delete(auth, namespace, &key)?;
+        }
+        return Ok(());
+    }
+    let bytes = serde_json::to_vec(allowance).map_err(|e| StorageError::SerializationError {
+        data_type: "Allowance".to_string(),
+        details: e.to_string(),
+    })?;
+    storage.set(auth, namespace, &key, bytes)
+}
+
+/// Whether the caller may spend an allowance given to `spender`, ranked so
+/// allowances given to the caller itself are spent before those given to
+/// the running proposal, and those before ones given to a role
+fn spender_rank(
+    spender: &str,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    proposal: Option<&str>,
+) -> Option<u8> {
+    if let Some(role) = spender.strip_prefix(ROLE_SPENDER_PREFIX) {
+        auth.is_some_and(|a| a.has_role(namespace, role))
+            .then_some(2)
+    } else if let Some(proposal_id) = spender.strip_prefix(PROPOSAL_SPENDER_PREFIX) {
+        (proposal == Some(proposal_id)).then_some(1)
+    } else {
+        auth.is_some_and(|a| a.user_id() == spender).then_some(0)
+    }
+}
+
+/// Key of the rate at which one resource exchanges into another
+fn exchange_key(from_resource: &str, to_resource: &str) -> String {
+    format!("resources/{}/exchange/{}", from_resource, to_resource)
+}
+
+/// Key prefix of the spending limits set on a resource
+fn spending_limit_prefix(resource: &str) -> String {
+    format!("resources/{}/spending_limits/", resource)
+}
+
+/// Key of what an identity has recently spent of a resource
+fn spending_log_key(resource: &str, identity: &str, action: SpendingAction) -> String {
+    format!(
+        "resources/{}/spending/{}/{}",
+        resource,
+        identity,
+        action.as_str()
+    )
+}
+
+/// Whether a spending limit's subject covers the caller: its own identity,
+/// or a role it holds in the namespace
+fn limit_applies(subject: &str, auth: &AuthContext, namespace: &str) -> bool {
+    match subject.strip_prefix(ROLE_SPENDER_PREFIX) {
+        Some(role) => auth.has_role(namespace, role),
+        None => auth.user_id() == subject,
+    }
+}
+
+/// Key prefix of the escrows held under a proposal
+fn escrow_prefix(proposal_id: &str) -> String {
+    format!("escrow/{}/", proposal_id)
+}
+
+/// How a proposal's escrows may be settled, given its stored lifecycle
+/// state: `None` while it is undecided
+///
+/// The lifecycle is read from `governance_proposals/{id}/lifecycle`, where
+/// the proposal commands keep it.
+pub fn proposal_escrow_outcome<S: StorageBackend + ?Sized>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    proposal_id: &str,
+) -> StorageResult<Option<EscrowOutcome>> {
+    let key = format!("governance_proposals/{}/lifecycle", proposal_id);
+    if !storage.contains(auth, namespace, &key)? {
+        return Err(StorageError::NotFound { key });
+    }
+    let lifecycle: serde_json::Value = serde_json::from_slice(&storage.get(auth, namespace, &key)?)
+        .map_err(|e| StorageError::SerializationError {
+            data_type: "ProposalLifecycle".to_string(),
+            details: e.to_string(),
+        })?;
+    Ok(lifecycle["state"]
+        .as_str()
+        .and_then(EscrowOutcome::for_proposal_state))
+}
+
 /// EconomicOperations provides operations for managing resources and accounts
 pub trait EconomicOperations: StorageBackend {
-    /// Create a new economic resource
+    /// Create a new economic resource, registering the policy that mints,
+    /// transfers and burns of it are checked against
     fn create_resource(
         &mut self,
         auth: Option<&AuthContext>,
         namespace: &str,
         resource: &str,
+        policy: &ResourcePolicy,
     ) -> StorageResult<()> {
-        // Default implementation creates a resource metadata entry
+        if let Some(demurrage) = &policy.demurrage {
+            demurrage.validate()?;
+        }
         let key = format!("resources/{}/metadata", resource);
-        let metadata = format!(
-            "{{\"id\": \"{}\", \"namespace\": \"{}\"}}",
-            resource, namespace
+        if self.contains(auth, namespace, &key)? {
+            return Err(StorageError::ConflictError {
+                resource: resource.to_string(),
+                details: "Resource already exists".to_string(),
+            });
+        }
+        let metadata = ResourceMetadata::new(
+            resource,
+            namespace,
+            policy.clone(),
+            auth.map(|a| a.user_id_string()),
         );
-        self.set(auth, namespace, &key, metadata.as_bytes().to_vec())?;
-        Ok(())
+        write_resource(self, auth, namespace, &metadata)
+    }
+
+    /// Get a resource's registry entry
+    fn get_resource(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+    ) -> StorageResult<ResourceMetadata> {
+        read_resource(self, auth, namespace, resource)
     }
 
     /// Mint new units of a resource for an account
@@ -349,34 +802,32 @@ pub trait EconomicOperations: StorageBackend {
         namespace: &str,
         resource: &str,
         account: &str,
-        amount: u64,
+        amount: Decimal,
         reason: &str,
     ) -> StorageResult<((), Option<StorageEvent>)> {
-        // Check if resource exists
-        let resource_key = format!("resources/{}/metadata", resource);
-        if !self.contains(auth, namespace, &resource_key)? {
-            return Err(StorageError::ResourceNotFound(resource.to_string()));
-        }
+        check_amount(amount)?;
 
-        // Get current balance
-        let balance_key = format!("resources/{}/accounts/{}", resource, account);
-        let current_balance = if self.contains(auth, namespace, &balance_key)? {
-            match std::str::from_utf8(&self.get(auth, namespace, &balance_key)?) {
-                Ok(s) => s.parse::<u64>().unwrap_or(0),
-                Err(_) => 0,
-            }
-        } else {
-            0
-        };
+        // Get current balance, after any demurrage due
+        let now = now_with_default();
+        let mut metadata = read_resource(self, auth, namespace, resource)?;
+        let (current_balance, _) =
+            materialize_demurrage(self, auth, namespace, &mut metadata, account, now)?;
+
+        // Check the resource's issuance policy
+        let minter = auth.map(|a| a.user_id_string());
+        metadata.total_supply = metadata.check_mint(minter.as_deref(), amount)?;
 
-        // Update balance
-        let new_balance = current_balance + amount;
+        // Update balance and supply
+        let balance_key = format!("resources/{}/accounts/{}", resource, account);
+        let new_balance = add_to_balance(current_balance, amount, account, resource)?;
         self.set(
             auth,
             namespace,
             &balance_key,
             new_balance.to_string().as_bytes().to_vec(),
         )?;
+        restart_demurrage(self, auth, namespace, &metadata, account, now)?;
+        write_resource(self, auth, namespace, &metadata)?;
 
         // Create event
         let event = StorageEvent {
@@ -407,48 +858,41 @@ pub trait EconomicOperations: StorageBackend {
         resource: &str,
         from: &str,
         to: &str,
-        amount: u64,
+        amount: Decimal,
         reason: &str,
     ) -> StorageResult<((), Option<StorageEvent>)> {
-        // Check if resource exists
-        let resource_key = format!("resources/{}/metadata", resource);
-        if !self.contains(auth, namespace, &resource_key)? {
-            return Err(StorageError::ResourceNotFound(resource.to_string()));
-        }
+        check_amount(amount)?;
 
-        // Get from balance
-        let from_key = format!("resources/{}/accounts/{}", resource, from);
-        let from_balance = if self.contains(auth, namespace, &from_key)? {
-            match std::str::from_utf8(&self.get(auth, namespace, &from_key)?) {
-                Ok(s) => s.parse::<u64>().unwrap_or(0),
-                Err(_) => 0,
-            }
-        } else {
-            0
-        };
+        // Check the resource allows the transfer
+        let mut metadata = read_resource(self, auth, namespace, resource)?;
+        metadata.check_transfer(amount)?;
 
-        // Check if sufficient balance
-        if from_balance < amount {
-            return Err(StorageError::InsufficientBalance(format!(
-                "Account {} has insufficient balance for resource {}",
-                from, resource
-            )));
+        // Get balances, after any demurrage due
+        let now = now_with_default();
+        let from_key = format!("resources/{}/accounts/{}", resource, from);
+        let to_key = format!("resources/{}/accounts/{}", resource, to);
+        let (from_balance, from_charged) =
+            materialize_demurrage(self, auth, namespace, &mut metadata, from, now)?;
+        let (_, to_charged) = materialize_demurrage(self, auth, namespace, &mut metadata, to, now)?;
+        if !(from_charged + to_charged).is_zero() {
+            write_resource(self, auth, namespace, &metadata)?;
         }
 
-        // Get to balance
-        let to_key = format!("resources/{}/accounts/{}", resource, to);
-        let to_balance = if self.contains(auth, namespace, &to_key)? {
-            match std::str::from_utf8(&self.get(auth, namespace, &to_key)?) {
-                Ok(s) => s.parse::<u64>().unwrap_or(0),
-                Err(_) => 0,
+        // Check if sufficient balance, counting credit extended to the account
+        if from_balance < amount {
+            let limit = self.get_credit_limit(auth, namespace, resource, from)?;
+            if from_balance.checked_sub(amount).is_none_or(|b| b < -limit) {
+                return Err(StorageError::InsufficientBalance(format!(
+                    "Account {} has insufficient balance and credit for resource {}",
+                    from, resource
+                )));
             }
-        } else {
-            0
-        };
+        }
 
         // Update balances
+        let to_balance = read_balance(self, auth, namespace, resource, to)?;
         let new_from_balance = from_balance - amount;
-        let new_to_balance = to_balance + amount;
+        let new_to_balance = add_to_balance(to_balance, amount, to, resource)?;
 
         self.set(
             auth,
@@ -462,6 +906,8 @@ pub trait EconomicOperations: StorageBackend {
             &to_key,
             new_to_balance.to_string().as_bytes().to_vec(),
         )?;
+        restart_demurrage(self, auth, namespace, &metadata, from, now)?;
+        restart_demurrage(self, auth, namespace, &metadata, to, now)?;
 
         // Create event
         let event = StorageEvent {
@@ -491,35 +937,34 @@ pub trait EconomicOperations: StorageBackend {
         namespace: &str,
         resource: &str,
         account: &str,
-        amount: u64,
+        amount: Decimal,
         reason: &str,
     ) -> StorageResult<((), Option<StorageEvent>)> {
-        // Check if resource exists
-        let resource_key = format!("resources/{}/metadata", resource);
-        if !self.contains(auth, namespace, &resource_key)? {
-            return Err(StorageError::ResourceNotFound(resource.to_string()));
-        }
+        check_amount(amount)?;
+
+        // Check the amount against the resource
+        let mut metadata = read_resource(self, auth, namespace, resource)?;
+        metadata.check_precision(amount)?;
 
-        // Get current balance
+        // Get current balance, after any demurrage due
+        let now = now_with_default();
         let balance_key = format!("resources/{}/accounts/{}", resource, account);
-        let current_balance = if self.contains(auth, namespace, &balance_key)? {
-            match std::str::from_utf8(&self.get(auth, namespace, &balance_key)?) {
-                Ok(s) => s.parse::<u64>().unwrap_or(0),
-                Err(_) => 0,
-            }
-        } else {
-            0
-        };
+        let (current_balance, charged) =
+            materialize_demurrage(self, auth, namespace, &mut metadata, account, now)?;
 
         // Check if sufficient balance
         if current_balance < amount {
+            if !charged.is_zero() {
+                write_resource(self, auth, namespace, &metadata)?;
+            }
             return Err(StorageError::InsufficientBalance(format!(
                 "Account {} has insufficient balance for resource {}",
                 account, resource
             )));
         }
 
-        // Update balance
+        // Update balance and supply; resources created before supply was
+        // tracked start from zero
         let new_balance = current_balance - amount;
         self.set(
             auth,
@@ -527,6 +972,9 @@ pub trait EconomicOperations: StorageBackend {
             &balance_key,
             new_balance.to_string().as_bytes().to_vec(),
         )?;
+        metadata.total_supply = (metadata.total_supply - amount).max(Decimal::ZERO);
+        restart_demurrage(self, auth, namespace, &metadata, account, now)?;
+        write_resource(self, auth, namespace, &metadata)?;
 
         // Create event
         let event = StorageEvent {
@@ -550,29 +998,27 @@ pub trait EconomicOperations: StorageBackend {
     }
 
     /// Get the balance of a resource for an account
+    ///
+    /// Demurrage due since the balance was last written is deducted from
+    /// the amount returned, without writing anything.
     fn get_balance(
         &self,
         auth: Option<&AuthContext>,
         namespace: &str,
         resource: &str,
         account: &str,
-    ) -> StorageResult<(u64, Option<StorageEvent>)> {
-        // Check if resource exists
-        let resource_key = format!("resources/{}/metadata", resource);
-        if !self.contains(auth, namespace, &resource_key)? {
-            return Err(StorageError::ResourceNotFound(resource.to_string()));
-        }
-
-        // Get balance
+    ) -> StorageResult<(Decimal, Option<StorageEvent>)> {
+        // Get balance, after any demurrage due
+        let metadata = read_resource(self, auth, namespace, resource)?;
         let balance_key = format!("resources/{}/accounts/{}", resource, account);
-        let balance = if self.contains(auth, namespace, &balance_key)? {
-            match std::str::from_utf8(&self.get(auth, namespace, &balance_key)?) {
-                Ok(s) => s.parse::<u64>().unwrap_or(0),
-                Err(_) => 0,
-            }
-        } else {
-            0
-        };
+        let (_, balance, _) = decayed_balance(
+            self,
+            auth,
+            namespace,
+            &metadata,
+            account,
+            now_with_default(),
+        )?;
 
         // Create event
         let event = StorageEvent {
@@ -595,46 +1041,42 @@ pub trait EconomicOperations: StorageBackend {
         Ok((balance, Some(event)))
     }
 
-    /// Get reputation for an identity
-    fn get_reputation(
-        &self,
-        auth: Option<&AuthContext>,
-        namespace: &str,
-        identity_id: &str,
-    ) -> StorageResult<(u64, Option<StorageEvent>)> {
-        // Get reputation
-        let rep_key = format!("identities/{}/reputation", identity_id);
-        let reputation = if self.contains(auth, namespace, &rep_key)? {
-            match std::str::from_utf8(&self.get(auth, namespace, &rep_key)?) {
-                Ok(s) => s.parse::<u64>().unwrap_or(0),
-                Err(_) => 0,
-            }
-        } else {
-            0
-        };
-
-        // No event for reading reputation
-        Ok((reputation, None))
-    }
-
-    /// Set reputation for an identity
-    fn set_reputation(
+    /// Set the credit `creditor` extends to `debtor` in a resource; a limit
+    /// of zero removes the line
+    fn set_credit_limit(
         &mut self,
         auth: Option<&AuthContext>,
         namespace: &str,
-        identity_id: &str,
-        value: u64,
+        resource: &str,
+        creditor: &str,
+        debtor: &str,
+        limit: Decimal,
+        proposal_id: Option<&str>,
     ) -> StorageResult<((), Option<StorageEvent>)> {
-        // Set reputation
-        let rep_key = format!("identities/{}/reputation", identity_id);
-        self.set(
-            auth,
-            namespace,
-            &rep_key,
-            value.to_string().as_bytes().to_vec(),
-        )?;
+        check_amount(limit)?;
+        read_resource(self, auth, namespace, resource)?.check_precision(limit)?;
+
+        let key = format!("{}{}", credit_prefix(resource, debtor), creditor);
+        if limit.is_zero() {
+            if self.contains(auth, namespace, &key)? {
+                self.delete(auth, namespace, &key)?;
+            }
+        } else {
+            let line = CreditLine {
+                resource: resource.to_string(),
+                creditor: creditor.to_string(),
+                debtor: debtor.to_string(),
+                limit,
+                proposal_id: proposal_id.map(str::to_string),
+            };
+            let bytes =
+                serde_json::to_vec(&line).map_err(|e| StorageError::SerializationError {
+                    data_type: "CreditLine".to_string(),
+                    details: e.to_string(),
+                })?;
+            self.set(auth, namespace, &key, bytes)?;
+        }
 
-        // Create event
         let event = StorageEvent {
             user_id: auth
                 .map(|a| a.user_id_string())
@@ -644,66 +1086,1212 @@ pub trait EconomicOperations: StorageBackend {
                 .unwrap_or_default()
                 .as_secs(),
             namespace: namespace.to_string(),
-            key: rep_key,
-            event_type: "set_reputation".to_string(),
-            details: format!("Set reputation for {} to {}", identity_id, value),
+            key,
+            event_type: "set_credit_limit".to_string(),
+            details: format!(
+                "Set credit from {} to {} in {} to {}",
+                creditor, debtor, resource, limit
+            ),
         };
 
         Ok(((), Some(event)))
     }
 
-    /// Store custom data
-    fn store(
+    /// List the credit lines extended to an account in a resource
+    fn get_credit_lines(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+        debtor: &str,
+    ) -> StorageResult<Vec<CreditLine>> {
+        let mut keys = self.list_keys(auth, namespace, Some(&credit_prefix(resource, debtor)))?;
+        keys.sort();
+        keys.into_iter()
+            .map(|key| {
+                serde_json::from_slice(&self.get(auth, namespace, &key)?).map_err(|e| {
+                    StorageError::SerializationError {
+                        data_type: "CreditLine".to_string(),
+                        details: e.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// How far below zero an account may go in a resource: the sum of the
+    /// credit lines extended to it
+    fn get_credit_limit(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+        debtor: &str,
+    ) -> StorageResult<Decimal> {
+        Ok(self
+            .get_credit_lines(auth, namespace, resource, debtor)?
+            .iter()
+            .fold(Decimal::ZERO, |total, line| {
+                total.saturating_add(line.limit)
+            }))
+    }
+
+    /// Let `spender` transfer up to `limit` units out of the caller's
+    /// account on its behalf, replacing any allowance given it before; a
+    /// limit of zero revokes the allowance
+    fn approve(
         &mut self,
         auth: Option<&AuthContext>,
         namespace: &str,
-        key: &str,
-        value: Vec<u8>,
-    ) -> StorageResult<((), Option<StorageEvent>)> {
-        // Set the value
-        self.set(auth, namespace, key, value)?;
+        resource: &str,
+        spender: &str,
+        limit: Decimal,
+    ) -> StorageResult<(Allowance, Option<StorageEvent>)> {
+        check_amount(limit)?;
+        Allowance::validate_spender(spender)?;
+        read_resource(self, auth, namespace, resource)?.check_precision(limit)?;
+
+        let owner = acting_identity(auth, "approve", &allowance_prefix(resource, ""))?;
+        let allowance = Allowance {
+            resource: resource.to_string(),
+            owner: owner.clone(),
+            spender: spender.to_string(),
+            remaining: limit,
+        };
+        write_allowance(self, auth, namespace, &allowance)?;
 
-        // Create event
         let event = StorageEvent {
-            user_id: auth
-                .map(|a| a.user_id_string())
-                .unwrap_or_else(|| "system".to_string()),
+            user_id: owner.clone(),
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
             namespace: namespace.to_string(),
-            key: key.to_string(),
-            event_type: "store".to_string(),
-            details: format!("Stored data for key {}", key),
+            key: format!("{}{}", allowance_prefix(resource, &owner), spender),
+            event_type: "approve".to_string(),
+            details: format!(
+                "{} allowed {} to spend {} of {} on its behalf",
+                owner, spender, limit, resource
+            ),
         };
 
-        Ok(((), Some(event)))
+        Ok((allowance, Some(event)))
     }
 
-    /// Load custom data
-    fn load(
+    /// List the allowances an identity has given in a resource
+    fn get_allowances(
         &self,
         auth: Option<&AuthContext>,
         namespace: &str,
-        key: &str,
-    ) -> StorageResult<(Vec<u8>, Option<StorageEvent>)> {
-        // Get the data
-        let data = self.get(auth, namespace, key)?;
+        resource: &str,
+        owner: &str,
+    ) -> StorageResult<Vec<Allowance>> {
+        let mut keys = self.list_keys(auth, namespace, Some(&allowance_prefix(resource, owner)))?;
+        keys.sort();
+        keys.into_iter()
+            .map(|key| {
+                serde_json::from_slice(&self.get(auth, namespace, &key)?).map_err(|e| {
+                    StorageError::SerializationError {
+                        data_type: "Allowance".to_string(),
+                        details: e.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Units `spender` may still transfer out of `owner`'s account, zero
+    /// if it has no allowance
+    fn get_allowance(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+        owner: &str,
+        spender: &str,
+    ) -> StorageResult<Decimal> {
+        Ok(self
+            .get_allowances(auth, namespace, resource, owner)?
+            .into_iter()
+            .find(|allowance| allowance.spender == spender)
+            .map_or(Decimal::ZERO, |allowance| allowance.remaining))
+    }
+
+    /// Transfer out of `owner`'s account on its behalf, spending an
+    /// allowance it gave the caller, returning that allowance's spender
+    ///
+    /// The caller may spend allowances given to its own identity, to a
+    /// role it holds in the namespace, or to `proposal`, the proposal whose
+    /// logic is running. The first of those, in that order, that covers
+    /// the amount is spent, together with the transfer or not at all.
+    fn transfer_from(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+        owner: &str,
+        to: &str,
+        amount: Decimal,
+        reason: &str,
+        proposal: Option<&str>,
+    ) -> StorageResult<(String, Option<StorageEvent>)> {
+        check_amount(amount)?;
+        let account_key = format!("resources/{}/accounts/{}", resource, owner);
+        let caller = acting_identity(auth, "transfer from", &account_key)?;
+
+        let mut usable: Vec<(u8, Allowance)> = self
+            .get_allowances(auth, namespace, resource, owner)?
+            .into_iter()
+            .filter_map(|allowance| {
+                spender_rank(&allowance.spender, auth, namespace, proposal)
+                    .map(|rank| (rank, allowance))
+            })
+            .collect();
+        if usable.is_empty() {
+            return Err(StorageError::PermissionDenied {
+                user_id: caller,
+                action: "transfer from".to_string(),
+                key: account_key,
+            });
+        }
+        usable.sort_by_key(|(rank, _)| *rank);
+        let Some((_, mut allowance)) = usable
+            .into_iter()
+            .find(|(_, allowance)| allowance.remaining >= amount)
+        else {
+            return Err(StorageError::ValidationError {
+                rule: "allowance".to_string(),
+                details: format!(
+                    "No allowance {} has given {} covers {} of {}",
+                    owner, caller, amount, resource
+                ),
+            });
+        };
+        allowance.remaining -= amount;
+
+        self.begin_transaction()?;
+        let result = self
+            .transfer(auth, namespace, resource, owner, to, amount, reason)
+            .and_then(|_| write_allowance(self, auth, namespace, &allowance));
+        match result {
+            Ok(()) => self.commit_transaction()?,
+            Err(e) => {
+                self.rollback_transaction()?;
+                return Err(e);
+            }
+        }
 
-        // Create event
         let event = StorageEvent {
-            user_id: auth
-                .map(|a| a.user_id_string())
-                .unwrap_or_else(|| "system".to_string()),
+            user_id: caller,
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
             namespace: namespace.to_string(),
-            key: key.to_string(),
-            event_type: "load".to_string(),
-            details: format!("Loaded data for key {}", key),
+            key: account_key,
+            event_type: "transfer_from".to_string(),
+            details: format!(
+                "Transferred {} of {} from {} to {} under the allowance given {}: {}",
+                amount, resource, owner, to, allowance.spender, reason
+            ),
+        };
+
+        Ok((allowance.spender, Some(event)))
+    }
+
+    /// Set the rate at which `rate.from_resource` exchanges into
+    /// `rate.to_resource`; a rate of zero stops exchanges between them
+    fn set_exchange_rate(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        rate: &ExchangeRate,
+    ) -> StorageResult<((), Option<StorageEvent>)> {
+        check_amount(rate.rate)?;
+        if rate.from_resource == rate.to_resource {
+            return Err(StorageError::ValidationError {
+                rule: "exchange_rate".to_string(),
+                details: format!("{} cannot be exchanged into itself", rate.from_resource),
+            });
+        }
+        let from = read_resource(self, auth, namespace, &rate.from_resource)?;
+        read_resource(self, auth, namespace, &rate.to_resource)?;
+        if let Some(max) = rate.max_amount {
+            check_amount(max)?;
+            from.check_precision(max)?;
+        }
+
+        let key = exchange_key(&rate.from_resource, &rate.to_resource);
+        if rate.rate.is_zero() {
+            if self.contains(auth, namespace, &key)? {
+                self.delete(auth, namespace, &key)?;
+            }
+        } else {
+            let bytes = serde_json::to_vec(rate).map_err(|e| StorageError::SerializationError {
+                data_type: "ExchangeRate".to_string(),
+                details: e.to_string(),
+            })?;
+            self.set(auth, namespace, &key, bytes)?;
+        }
+
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key,
+            event_type: "set_exchange_rate".to_string(),
+            details: format!(
+                "Set exchange from {} to {} at {} through {}",
+                rate.from_resource, rate.to_resource, rate.rate, rate.reserve
+            ),
+        };
+
+        Ok(((), Some(event)))
+    }
+
+    /// Get the rate at which one resource exchanges into another, if one
+    /// has been set
+    fn get_exchange_rate(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        from_resource: &str,
+        to_resource: &str,
+    ) -> StorageResult<Option<ExchangeRate>> {
+        let key = exchange_key(from_resource, to_resource);
+        if !self.contains(auth, namespace, &key)? {
+            return Ok(None);
+        }
+        serde_json::from_slice(&self.get(auth, namespace, &key)?)
+            .map(Some)
+            .map_err(|e| StorageError::SerializationError {
+                data_type: "ExchangeRate".to_string(),
+                details: e.to_string(),
+            })
+    }
+
+    /// Convert `amount` of one resource in `account` into another at the
+    /// governed rate, returning what was received
+    ///
+    /// The amount is paid into the rate's reserve and the converted amount
+    /// paid out of it, together or not at all. The exchange fails if it
+    /// would pay out less than `min_received`, more than the reserve holds,
+    /// or more than the rate's limit allows.
+    fn exchange(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        from_resource: &str,
+        to_resource: &str,
+        account: &str,
+        amount: Decimal,
+        min_received: Decimal,
+    ) -> StorageResult<(Decimal, Option<StorageEvent>)> {
+        check_amount(amount)?;
+        check_amount(min_received)?;
+        let Some(rate) = self.get_exchange_rate(auth, namespace, from_resource, to_resource)?
+        else {
+            return Err(StorageError::ValidationError {
+                rule: "exchange_rate".to_string(),
+                details: format!(
+                    "No rate has been set for exchanging {} into {}",
+                    from_resource, to_resource
+                ),
+            });
+        };
+        read_resource(self, auth, namespace, from_resource)?.check_precision(amount)?;
+        let to = read_resource(self, auth, namespace, to_resource)?;
+
+        let received = rate.quote(amount, to.policy.decimals)?;
+        if received < min_received || received.is_zero() {
+            return Err(StorageError::ValidationError {
+                rule: "exchange_slippage".to_string(),
+                details: format!(
+                    "Exchanging {} of {} pays {} of {}, less than the {} asked for",
+                    amount, from_resource, received, to_resource, min_received
+                ),
+            });
+        }
+        let (reserve_balance, _) = self.get_balance(auth, namespace, to_resource, &rate.reserve)?;
+        if reserve_balance < received {
+            return Err(StorageError::InsufficientBalance(format!(
+                "Exchange reserve {} holds {} of {}, not the {} due",
+                rate.reserve, reserve_balance, to_resource, received
+            )));
+        }
+
+        let reason = format!("Exchange into {}", to_resource);
+        self.begin_transaction()?;
+        let result = self
+            .transfer(
+                auth,
+                namespace,
+                from_resource,
+                account,
+                &rate.reserve,
+                amount,
+                &reason,
+            )
+            .and_then(|_| {
+                self.transfer(
+                    auth,
+                    namespace,
+                    to_resource,
+                    &rate.reserve,
+                    account,
+                    received,
+                    &reason,
+                )
+            });
+        match result {
+            Ok(_) => self.commit_transaction()?,
+            Err(e) => {
+                self.rollback_transaction()?;
+                return Err(e);
+            }
+        }
+
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key: exchange_key(from_resource, to_resource),
+            event_type: "exchange".to_string(),
+            details: format!(
+                "Exchanged {} of {} for {} of {} in {}",
+                amount, from_resource, received, to_resource, account
+            ),
+        };
+
+        Ok((received, Some(event)))
+    }
+
+    /// Cap what an identity, or each holder of a role, may transfer or
+    /// mint of a resource within a rolling window; a limit of zero removes
+    /// the cap
+    fn set_spending_limit(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        limit: &SpendingLimit,
+    ) -> StorageResult<((), Option<StorageEvent>)> {
+        check_amount(limit.limit)?;
+        Allowance::validate_spender(&limit.subject)?;
+        read_resource(self, auth, namespace, &limit.resource)?.check_precision(limit.limit)?;
+
+        let key = format!(
+            "{}{}/{}",
+            spending_limit_prefix(&limit.resource),
+            limit.subject,
+            limit.action.as_str()
+        );
+        if limit.limit.is_zero() {
+            if self.contains(auth, namespace, &key)? {
+                self.delete(auth, namespace, &key)?;
+            }
+        } else {
+            let bytes =
+                serde_json::to_vec(limit).map_err(|e| StorageError::SerializationError {
+                    data_type: "SpendingLimit".to_string(),
+                    details: e.to_string(),
+                })?;
+            self.set(auth, namespace, &key, bytes)?;
+        }
+
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key,
+            event_type: "set_spending_limit".to_string(),
+            details: format!(
+                "Limited {} to {} {} of {} a {}",
+                limit.subject,
+                limit.action.as_str(),
+                limit.limit,
+                limit.resource,
+                limit.window.as_str()
+            ),
+        };
+
+        Ok(((), Some(event)))
+    }
+
+    /// List the spending limits set on a resource
+    fn get_spending_limits(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+    ) -> StorageResult<Vec<SpendingLimit>> {
+        let mut keys = self.list_keys(auth, namespace, Some(&spending_limit_prefix(resource)))?;
+        keys.sort();
+        keys.into_iter()
+            .map(|key| {
+                serde_json::from_slice(&self.get(auth, namespace, &key)?).map_err(|e| {
+                    StorageError::SerializationError {
+                        data_type: "SpendingLimit".to_string(),
+                        details: e.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Read what an identity has recently spent of a resource
+    fn get_spending_log(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+        identity: &str,
+        action: SpendingAction,
+    ) -> StorageResult<SpendingLog> {
+        let key = spending_log_key(resource, identity, action);
+        if !self.contains(auth, namespace, &key)? {
+            return Ok(SpendingLog::default());
+        }
+        serde_json::from_slice(&self.get(auth, namespace, &key)?).map_err(|e| {
+            StorageError::SerializationError {
+                data_type: "SpendingLog".to_string(),
+                details: e.to_string(),
+            }
+        })
+    }
+
+    /// The spending limits the caller would go over by spending `amount`
+    /// more, empty if it stays within them all
+    ///
+    /// Limits set for the caller's identity and for each role it holds in
+    /// the namespace all apply, each counted against what the caller has
+    /// spent within its window. Code running without an identity is not
+    /// limited.
+    fn check_spending_limits(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+        action: SpendingAction,
+        amount: Decimal,
+    ) -> StorageResult<Vec<SpendingLimit>> {
+        let Some(caller) = auth else {
+            return Ok(Vec::new());
+        };
+        let limits: Vec<SpendingLimit> = self
+            .get_spending_limits(auth, namespace, resource)?
+            .into_iter()
+            .filter(|limit| {
+                limit.action == action && limit_applies(&limit.subject, caller, namespace)
+            })
+            .collect();
+        if limits.is_empty() {
+            return Ok(limits);
+        }
+
+        let log = self.get_spending_log(auth, namespace, resource, caller.user_id(), action)?;
+        let now = now_with_default();
+        Ok(limits
+            .into_iter()
+            .filter(|limit| {
+                log.spent_within(limit.window, now)
+                    .checked_add(amount)
+                    .is_none_or(|spent| spent > limit.limit)
+            })
+            .collect())
+    }
+
+    /// Count `amount` against the caller's spending limits, if any apply
+    fn record_spending(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+        action: SpendingAction,
+        amount: Decimal,
+    ) -> StorageResult<()> {
+        let Some(caller) = auth else {
+            return Ok(());
+        };
+        let limited = self
+            .get_spending_limits(auth, namespace, resource)?
+            .iter()
+            .any(|limit| {
+                limit.action == action && limit_applies(&limit.subject, caller, namespace)
+            });
+        if !limited {
+            return Ok(());
+        }
+
+        let mut log = self.get_spending_log(auth, namespace, resource, caller.user_id(), action)?;
+        log.record(amount, now_with_default());
+        let bytes = serde_json::to_vec(&log).map_err(|e| StorageError::SerializationError {
+            data_type: "SpendingLog".to_string(),
+            details: e.to_string(),
+        })?;
+        self.set(
+            auth,
+            namespace,
+            &spending_log_key(resource, caller.user_id(), action),
+            bytes,
+        )
+    }
+
+    /// Move an amount out of `from` and hold it under a proposal that has
+    /// not yet been decided, to be paid to `to` if it is executed
+    fn lock_escrow(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        proposal_id: &str,
+        resource: &str,
+        from: &str,
+        to: &str,
+        amount: Decimal,
+    ) -> StorageResult<((), Option<StorageEvent>)> {
+        check_amount(amount)?;
+        let mut metadata = read_resource(self, auth, namespace, resource)?;
+        metadata.check_transfer(amount)?;
+        if proposal_escrow_outcome(self, auth, namespace, proposal_id)?.is_some() {
+            return Err(StorageError::ConflictError {
+                resource: proposal_id.to_string(),
+                details: "Proposal has already been decided".to_string(),
+            });
+        }
+
+        hold_funds(self, auth, namespace, &mut metadata, from, amount)?;
+
+        let prefix = escrow_prefix(proposal_id);
+        let index = self.list_keys(auth, namespace, Some(&prefix))?.len();
+        let key = format!("{}{}", prefix, index);
+        let escrow = Escrow {
+            proposal_id: proposal_id.to_string(),
+            resource: resource.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            amount,
+            status: EscrowStatus::Locked,
+        };
+        let bytes = serde_json::to_vec(&escrow).map_err(|e| StorageError::SerializationError {
+            data_type: "Escrow".to_string(),
+            details: e.to_string(),
+        })?;
+        self.set(auth, namespace, &key, bytes)?;
+
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key,
+            event_type: "escrow_lock".to_string(),
+            details: format!(
+                "Locked {} of {} from {} for {} under proposal {}",
+                amount, resource, from, to, proposal_id
+            ),
+        };
+
+        Ok(((), Some(event)))
+    }
+
+    /// List the escrows held, or once held, under a proposal
+    fn get_escrows(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        proposal_id: &str,
+    ) -> StorageResult<Vec<(String, Escrow)>> {
+        let mut keys = self.list_keys(auth, namespace, Some(&escrow_prefix(proposal_id)))?;
+        keys.sort();
+        keys.into_iter()
+            .map(|key| {
+                let escrow =
+                    serde_json::from_slice(&self.get(auth, namespace, &key)?).map_err(|e| {
+                        StorageError::SerializationError {
+                            data_type: "Escrow".to_string(),
+                            details: e.to_string(),
+                        }
+                    })?;
+                Ok((key, escrow))
+            })
+            .collect()
+    }
+
+    /// Pay out every escrow still locked under a proposal, to its
+    /// beneficiary or back to its depositor, returning those settled
+    ///
+    /// The caller is responsible for checking that the proposal's outcome
+    /// allows the settlement; see [`proposal_escrow_outcome`].
+    fn settle_escrows(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        proposal_id: &str,
+        outcome: EscrowOutcome,
+    ) -> StorageResult<(Vec<Escrow>, Option<StorageEvent>)> {
+        let mut settled = Vec::new();
+        for (key, mut escrow) in self.get_escrows(auth, namespace, proposal_id)? {
+            if escrow.status != EscrowStatus::Locked {
+                continue;
+            }
+            let payee = escrow.payee(outcome);
+            pay_held_funds(
+                self,
+                auth,
+                namespace,
+                &escrow.resource,
+                payee,
+                escrow.amount,
+            )?;
+
+            escrow.status = outcome.status();
+            let bytes =
+                serde_json::to_vec(&escrow).map_err(|e| StorageError::SerializationError {
+                    data_type: "Escrow".to_string(),
+                    details: e.to_string(),
+                })?;
+            self.set(auth, namespace, &key, bytes)?;
+            settled.push(escrow);
+        }
+
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key: escrow_prefix(proposal_id),
+            event_type: match outcome {
+                EscrowOutcome::Release => "escrow_release",
+                EscrowOutcome::Refund => "escrow_refund",
+            }
+            .to_string(),
+            details: format!(
+                "Settled {} escrow(s) under proposal {}",
+                settled.len(),
+                proposal_id
+            ),
+        };
+
+        Ok((settled, Some(event)))
+    }
+
+    /// Create a bounty, taking its reward out of the funding account to
+    /// hold until a claim on it is accepted
+    fn create_bounty(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        id: &str,
+        resource: &str,
+        funder: &str,
+        amount: Decimal,
+        verification: BountyVerification,
+        description: &str,
+    ) -> StorageResult<(Bounty, Option<StorageEvent>)> {
+        check_amount(amount)?;
+        let key = bounty_key(id);
+        if self.contains(auth, namespace, &key)? {
+            return Err(StorageError::ConflictError {
+                resource: key,
+                details: "Bounty already exists".to_string(),
+            });
+        }
+        let mut metadata = read_resource(self, auth, namespace, resource)?;
+        metadata.check_transfer(amount)?;
+        hold_funds(self, auth, namespace, &mut metadata, funder, amount)?;
+
+        let bounty = Bounty {
+            id: id.to_string(),
+            resource: resource.to_string(),
+            funder: funder.to_string(),
+            amount,
+            description: description.to_string(),
+            verification,
+            status: BountyStatus::Open,
+            claim: None,
+            created_by: auth.map(|a| a.user_id_string()),
+        };
+        write_bounty(self, auth, namespace, &bounty)?;
+
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key,
+            event_type: "create_bounty".to_string(),
+            details: format!(
+                "Created bounty {} of {} {} funded by {}",
+                id, amount, resource, funder
+            ),
+        };
+
+        Ok((bounty, Some(event)))
+    }
+
+    /// Get a bounty's record
+    fn get_bounty(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        id: &str,
+    ) -> StorageResult<Bounty> {
+        let key = bounty_key(id);
+        if !self.contains(auth, namespace, &key)? {
+            return Err(StorageError::NotFound { key });
+        }
+        serde_json::from_slice(&self.get(auth, namespace, &key)?).map_err(|e| {
+            StorageError::SerializationError {
+                data_type: "Bounty".to_string(),
+                details: e.to_string(),
+            }
+        })
+    }
+
+    /// Claim an open bounty as the acting identity, submitting the work
+    /// done for verification
+    fn claim_bounty(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        id: &str,
+        evidence: &str,
+    ) -> StorageResult<(Bounty, Option<StorageEvent>)> {
+        let key = bounty_key(id);
+        let claimant = acting_identity(auth, "claim bounty", &key)?;
+        let mut bounty = self.get_bounty(auth, namespace, id)?;
+        if bounty.status != BountyStatus::Open {
+            return Err(StorageError::ConflictError {
+                resource: key,
+                details: "Bounty is not open for claims".to_string(),
+            });
+        }
+        bounty.status = BountyStatus::Claimed;
+        bounty.claim = Some(BountyClaim {
+            claimant: claimant.clone(),
+            evidence: evidence.to_string(),
+            approvals: Vec::new(),
+            rejections: Vec::new(),
+        });
+        write_bounty(self, auth, namespace, &bounty)?;
+
+        let event = StorageEvent {
+            user_id: claimant.clone(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key,
+            event_type: "claim_bounty".to_string(),
+            details: format!("{} claimed bounty {}", claimant, id),
+        };
+
+        Ok((bounty, Some(event)))
+    }
+
+    /// Approve or reject the pending claim on a bounty as the acting
+    /// identity
+    ///
+    /// Once the claim is accepted the reward is paid to the claimant; once
+    /// it is turned down the bounty opens for claims again.
+    fn verify_bounty(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        id: &str,
+        approve: bool,
+    ) -> StorageResult<(Bounty, Option<StorageEvent>)> {
+        let key = bounty_key(id);
+        let verifier = acting_identity(auth, "verify bounty", &key)?;
+        let mut bounty = self.get_bounty(auth, namespace, id)?;
+        if let BountyVerification::Verifier { role } = &bounty.verification {
+            if !auth.is_some_and(|a| a.has_role(namespace, role)) {
+                return Err(StorageError::PermissionDenied {
+                    user_id: verifier,
+                    action: "verify bounty".to_string(),
+                    key,
+                });
+            }
+        }
+
+        let outcome = bounty.record_verification(&verifier, approve)?;
+        match outcome {
+            Some(true) => {
+                let claimant = bounty.claim.as_ref().map(|c| c.claimant.clone());
+                let claimant = claimant.unwrap_or_default();
+                pay_held_funds(
+                    self,
+                    auth,
+                    namespace,
+                    &bounty.resource,
+                    &claimant,
+                    bounty.amount,
+                )?;
+                bounty.status = BountyStatus::Paid;
+            }
+            Some(false) => {
+                bounty.status = BountyStatus::Open;
+                bounty.claim = None;
+            }
+            None => {}
+        }
+        write_bounty(self, auth, namespace, &bounty)?;
+
+        let event = StorageEvent {
+            user_id: verifier.clone(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key,
+            event_type: "verify_bounty".to_string(),
+            details: format!(
+                "{} {} the claim on bounty {}{}",
+                verifier,
+                if approve { "approved" } else { "rejected" },
+                id,
+                match outcome {
+                    Some(true) => "; reward paid",
+                    Some(false) => "; claim turned down",
+                    None => "",
+                }
+            ),
+        };
+
+        Ok((bounty, Some(event)))
+    }
+
+    /// Withdraw a bounty that has not been claimed, returning its reward to
+    /// the funding account
+    ///
+    /// Only the identity that created the bounty may cancel it.
+    fn cancel_bounty(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        id: &str,
+    ) -> StorageResult<(Bounty, Option<StorageEvent>)> {
+        let key = bounty_key(id);
+        let caller = acting_identity(auth, "cancel bounty", &key)?;
+        let mut bounty = self.get_bounty(auth, namespace, id)?;
+        if bounty
+            .created_by
+            .as_ref()
+            .is_some_and(|creator| *creator != caller)
+        {
+            return Err(StorageError::PermissionDenied {
+                user_id: caller,
+                action: "cancel bounty".to_string(),
+                key,
+            });
+        }
+        if bounty.status != BountyStatus::Open {
+            return Err(StorageError::ConflictError {
+                resource: key,
+                details: "Only an open bounty can be cancelled".to_string(),
+            });
+        }
+        pay_held_funds(
+            self,
+            auth,
+            namespace,
+            &bounty.resource,
+            &bounty.funder,
+            bounty.amount,
+        )?;
+        bounty.status = BountyStatus::Cancelled;
+        write_bounty(self, auth, namespace, &bounty)?;
+
+        let event = StorageEvent {
+            user_id: caller,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key,
+            event_type: "cancel_bounty".to_string(),
+            details: format!(
+                "Cancelled bounty {}, returning {} {} to {}",
+                id, bounty.amount, bounty.resource, bounty.funder
+            ),
+        };
+
+        Ok((bounty, Some(event)))
+    }
+
+    /// Split `amount` from the `pool` account across members in proportion
+    /// to their weights, as one batch of transfers
+    ///
+    /// Each member's weight is the number stored at `{weight_key}/{account}`
+    /// in the namespace. Either every share is paid or, if any transfer
+    /// fails, none are.
+    fn distribute(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+        pool: &str,
+        weight_key: &str,
+        amount: Decimal,
+    ) -> StorageResult<(Distribution, Option<StorageEvent>)> {
+        check_amount(amount)?;
+        let metadata = read_resource(self, auth, namespace, resource)?;
+        metadata.check_transfer(amount)?;
+
+        let prefix = format!("{}/", weight_key);
+        let mut keys = self.list_keys(auth, namespace, Some(&prefix))?;
+        keys.sort();
+        let mut weights = Vec::with_capacity(keys.len());
+        for key in keys {
+            let data = self.get(auth, namespace, &key)?;
+            let weight = std::str::from_utf8(&data)
+                .ok()
+                .and_then(|s| s.trim().parse::<Decimal>().ok())
+                .ok_or_else(|| StorageError::ValidationError {
+                    rule: "distribution_weight".to_string(),
+                    details: format!("Weight at {} is not a number", key),
+                })?;
+            weights.push((key[prefix.len()..].to_string(), weight));
+        }
+        let distribution = Distribution::proportional(
+            resource,
+            pool,
+            weight_key,
+            amount,
+            weights,
+            metadata.policy.decimals,
+        )?;
+
+        let reason = format!("Distribution by {}", weight_key);
+        self.begin_transaction()?;
+        let result = distribution.shares.iter().try_for_each(|share| {
+            self.transfer(
+                auth,
+                namespace,
+                resource,
+                pool,
+                &share.account,
+                share.amount,
+                &reason,
+            )
+            .map(|_| ())
+        });
+        match result {
+            Ok(()) => self.commit_transaction()?,
+            Err(e) => {
+                self.rollback_transaction()?;
+                return Err(e);
+            }
+        }
+
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key: format!("resources/{}/accounts/{}", resource, pool),
+            event_type: "distribute".to_string(),
+            details: format!(
+                "Distributed {} of {} from {} to {} accounts by {}",
+                distribution.paid(),
+                resource,
+                pool,
+                distribution.shares.len(),
+                weight_key
+            ),
+        };
+
+        Ok((distribution, Some(event)))
+    }
+
+    /// Write the demurrage due on every account holding a resource to its
+    /// balance, returning the total charged
+    ///
+    /// Balances read through [`get_balance`](Self::get_balance) already
+    /// show the decay; this brings the stored balances and the total supply
+    /// up to date for accounts that have not been used since.
+    fn apply_demurrage(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        resource: &str,
+    ) -> StorageResult<(Decimal, Option<StorageEvent>)> {
+        let mut metadata = read_resource(self, auth, namespace, resource)?;
+        if metadata.policy.demurrage.is_none() {
+            return Ok((Decimal::ZERO, None));
+        }
+
+        let now = now_with_default();
+        let prefix = format!("resources/{}/accounts/", resource);
+        let mut keys = self.list_keys(auth, namespace, Some(&prefix))?;
+        keys.sort();
+        let mut total = Decimal::ZERO;
+        for key in keys {
+            let account = &key[prefix.len()..];
+            let (_, charged) =
+                materialize_demurrage(self, auth, namespace, &mut metadata, account, now)?;
+            total += charged;
+        }
+        if !total.is_zero() {
+            write_resource(self, auth, namespace, &metadata)?;
+        }
+
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: now,
+            namespace: namespace.to_string(),
+            key: prefix,
+            event_type: "apply_demurrage".to_string(),
+            details: format!("Charged {} of {} in demurrage", total, resource),
+        };
+
+        Ok((total, Some(event)))
+    }
+
+    /// Get reputation for an identity
+    fn get_reputation(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        identity_id: &str,
+    ) -> StorageResult<(u64, Option<StorageEvent>)> {
+        // Get reputation
+        let rep_key = format!("identities/{}/reputation", identity_id);
+        let reputation = if self.contains(auth, namespace, &rep_key)? {
+            match std::str::from_utf8(&self.get(auth, namespace, &rep_key)?) {
+                Ok(s) => s.parse::<u64>().unwrap_or(0),
+                Err(_) => 0,
+            }
+        } else {
+            0
+        };
+
+        // No event for reading reputation
+        Ok((reputation, None))
+    }
+
+    /// Set reputation for an identity
+    fn set_reputation(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        identity_id: &str,
+        value: u64,
+    ) -> StorageResult<((), Option<StorageEvent>)> {
+        // Set reputation
+        let rep_key = format!("identities/{}/reputation", identity_id);
+        self.set(
+            auth,
+            namespace,
+            &rep_key,
+            value.to_string().as_bytes().to_vec(),
+        )?;
+
+        // Create event
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key: rep_key,
+            event_type: "set_reputation".to_string(),
+            details: format!("Set reputation for {} to {}", identity_id, value),
+        };
+
+        Ok(((), Some(event)))
+    }
+
+    /// Store custom data
+    fn store(
+        &mut self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+        value: Vec<u8>,
+    ) -> StorageResult<((), Option<StorageEvent>)> {
+        // Set the value
+        self.set(auth, namespace, key, value)?;
+
+        // Create event
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key: key.to_string(),
+            event_type: "store".to_string(),
+            details: format!("Stored data for key {}", key),
+        };
+
+        Ok(((), Some(event)))
+    }
+
+    /// Load custom data
+    fn load(
+        &self,
+        auth: Option<&AuthContext>,
+        namespace: &str,
+        key: &str,
+    ) -> StorageResult<(Vec<u8>, Option<StorageEvent>)> {
+        // Get the data
+        let data = self.get(auth, namespace, key)?;
+
+        // Create event
+        let event = StorageEvent {
+            user_id: auth
+                .map(|a| a.user_id_string())
+                .unwrap_or_else(|| "system".to_string()),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            namespace: namespace.to_string(),
+            key: key.to_string(),
+            event_type: "load".to_string(),
+            details: format!("Loaded data for key {}", key),
         };
 
         Ok((data, Some(event)))
@@ -713,6 +2301,46 @@ pub trait EconomicOperations: StorageBackend {
 // Automatically implement EconomicOperations for all StorageBackend implementors
 impl<T: StorageBackend> EconomicOperations for T {}
 
+/// Apply the demurrage due on every resource in every namespace, returning
+/// the number of resources charged
+///
+/// Run periodically so idle balances are written down even when nobody
+/// reads or moves them. A namespace that cannot be processed is logged and
+/// skipped.
+pub fn apply_all_demurrage<S: EconomicOperations + ?Sized>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+) -> StorageResult<usize> {
+    let mut charged = 0;
+    for namespace in storage.list_namespaces(auth, "")? {
+        let keys = match storage.list_keys(auth, &namespace.path, Some("resources/")) {
+            Ok(keys) => keys,
+            Err(e) => {
+                tracing::warn!("Skipping demurrage in namespace {}: {}", namespace.path, e);
+                continue;
+            }
+        };
+        let resources = keys.iter().filter_map(|key| {
+            key.strip_prefix("resources/")?
+                .strip_suffix("/metadata")
+                .filter(|id| !id.contains('/'))
+        });
+        for resource in resources {
+            match storage.apply_demurrage(auth, &namespace.path, resource) {
+                Ok((total, _)) if !total.is_zero() => charged += 1,
+                Ok(_) => {}
+                Err(e) => tracing::warn!(
+                    "Failed to apply demurrage to {} in {}: {}",
+                    resource,
+                    namespace.path,
+                    e
+                ),
+            }
+        }
+    }
+    Ok(charged)
+}
+
 /// Define a standard Storage type that includes all trait bounds
 pub trait Storage: StorageBackend + EconomicOperations + Clone + Send + Sync {}
 
@@ -0,0 +1,239 @@
+//! Namespace usage and quota alerts
+//!
+//! [`namespace_usage`] measures what a namespace holds: its keys and bytes,
+//! the headroom left under its quota, and how many proposals, votes and
+//! comments it contains. `storage usage` prints these figures, and the API
+//! server computes them for every namespace every few minutes, exporting
+//! them as metrics and raising a `storage:quota` warning event through
+//! [`UsageAlerts`] when a namespace crosses one of the configured
+//! percentages of its quota (`storage.usage_alerts`, 80% and 95% by
+//! default).
+
+use crate::events::Event;
+use crate::governance::comments::comments_namespace_for;
+use crate::governance::export::PROPOSALS_PREFIX;
+use crate::storage::auth::AuthContext;
+use crate::storage::errors::StorageResult;
+use crate::storage::traits::StorageBackend;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Percentages used until [`configure`] is called
+pub const DEFAULT_THRESHOLDS: [u8; 2] = [80, 95];
+
+static THRESHOLDS: Lazy<Mutex<Vec<u8>>> = Lazy::new(|| Mutex::new(DEFAULT_THRESHOLDS.to_vec()));
+
+/// Raise alerts at these percentages of a namespace's quota
+pub fn configure(thresholds: &[u8]) {
+    let mut thresholds = thresholds.to_vec();
+    thresholds.sort_unstable();
+    thresholds.dedup();
+    if let Ok(mut current) = THRESHOLDS.lock() {
+        *current = thresholds;
+    }
+}
+
+/// The configured alert percentages, lowest first
+pub fn thresholds() -> Vec<u8> {
+    THRESHOLDS
+        .lock()
+        .map(|thresholds| thresholds.clone())
+        .unwrap_or_else(|_| DEFAULT_THRESHOLDS.to_vec())
+}
+
+/// What a namespace holds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NamespaceUsage {
+    pub namespace: String,
+    pub keys: usize,
+    pub bytes: u64,
+    /// `None` when the backend has no quota for the namespace
+    pub quota_bytes: Option<u64>,
+    pub headroom_bytes: Option<u64>,
+    pub proposals: usize,
+    pub votes: usize,
+    pub comments: usize,
+}
+
+impl NamespaceUsage {
+    /// How much of the quota is used, as a percentage
+    pub fn percent_used(&self) -> Option<f64> {
+        self.quota_bytes
+            .filter(|&quota| quota > 0)
+            .map(|quota| self.bytes as f64 * 100.0 / quota as f64)
+    }
+
+    /// The highest of `thresholds` the usage has reached
+    pub fn threshold_reached(&self, thresholds: &[u8]) -> Option<u8> {
+        let percent = self.percent_used()?;
+        thresholds
+            .iter()
+            .copied()
+            .filter(|&threshold| percent >= f64::from(threshold))
+            .max()
+    }
+}
+
+/// Measure `namespace` as `auth`
+pub fn namespace_usage<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+) -> StorageResult<NamespaceUsage>
+where
+    S: StorageBackend + ?Sized,
+{
+    let keys = storage.list_keys(auth, namespace, None)?;
+    let bytes = storage.get_usage(auth, namespace)?;
+    let quota_bytes = storage
+        .list_namespaces(auth, "")
+        .unwrap_or_default()
+        .into_iter()
+        .find(|metadata| metadata.path == namespace)
+        .map(|metadata| metadata.quota_bytes);
+
+    let proposal_keys = keys.iter().filter(|key| key.starts_with(PROPOSALS_PREFIX));
+    let proposals = proposal_keys
+        .clone()
+        .filter(|key| key.ends_with("/lifecycle"))
+        .count();
+    let votes = proposal_keys.filter(|key| key.contains("/votes/")).count();
+    let comments = storage
+        .list_keys(
+            auth,
+            &comments_namespace_for(namespace),
+            Some("governance/proposals/"),
+        )
+        .unwrap_or_default()
+        .iter()
+        .filter(|key| key.contains("/comments/"))
+        .count();
+
+    Ok(NamespaceUsage {
+        namespace: namespace.to_string(),
+        keys: keys.len(),
+        bytes,
+        quota_bytes,
+        headroom_bytes: quota_bytes.map(|quota| quota.saturating_sub(bytes)),
+        proposals,
+        votes,
+        comments,
+    })
+}
+
+/// Measure every namespace `auth` can list, skipping those it cannot read
+pub fn all_namespace_usage<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+) -> StorageResult<Vec<NamespaceUsage>>
+where
+    S: StorageBackend + ?Sized,
+{
+    let mut namespaces: Vec<String> = storage
+        .list_namespaces(auth, "")?
+        .into_iter()
+        .map(|metadata| metadata.path)
+        .collect();
+    namespaces.sort();
+
+    let mut usages = Vec::new();
+    for namespace in &namespaces {
+        match namespace_usage(storage, auth, namespace) {
+            Ok(usage) => usages.push(usage),
+            Err(e) => tracing::debug!(namespace = %namespace, error = %e, "Skipping namespace"),
+        }
+    }
+    Ok(usages)
+}
+
+/// Remembers the threshold each namespace last reached, so a warning is
+/// raised once per crossing rather than on every check
+#[derive(Debug, Default)]
+pub struct UsageAlerts {
+    reached: HashMap<String, u8>,
+}
+
+impl UsageAlerts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The warning to raise for `usage`, if it crossed a higher threshold
+    /// than at the last check
+    ///
+    /// Falling back below a threshold re-arms it.
+    pub fn check(&mut self, usage: &NamespaceUsage, thresholds: &[u8]) -> Option<Event> {
+        let Some(threshold) = usage.threshold_reached(thresholds) else {
+            self.reached.remove(&usage.namespace);
+            return None;
+        };
+        let previous = self.reached.insert(usage.namespace.clone(), threshold);
+        if previous.map_or(false, |previous| previous >= threshold) {
+            return None;
+        }
+
+        let message = format!(
+            "Namespace {} has used {:.1}% of its quota ({} of {} bytes)",
+            usage.namespace,
+            usage.percent_used().unwrap_or_default(),
+            usage.bytes,
+            usage.quota_bytes.unwrap_or_default()
+        );
+        Some(
+            Event::warn("storage:quota", message).with_data(serde_json::json!({
+                "namespace": usage.namespace,
+                "threshold": threshold,
+                "bytes": usage.bytes,
+                "quota_bytes": usage.quota_bytes,
+                "headroom_bytes": usage.headroom_bytes,
+            })),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+
+    #[test]
+    fn test_usage_counts_and_alerts_once_per_crossing() {
+        let mut auth = AuthContext::new("did:key:operator");
+        auth.add_role("global", "admin");
+        auth.add_role("coop", "admin");
+        let mut storage = InMemoryStorage::new();
+        storage
+            .create_namespace(Some(&auth), "coop", 1_000, None)
+            .unwrap();
+        let lifecycle = format!("{}p1/lifecycle", PROPOSALS_PREFIX);
+        let vote = format!("{}p1/votes/alice", PROPOSALS_PREFIX);
+        let comment = "governance/proposals/p1/comments/c1";
+        for key in [lifecycle.as_str(), vote.as_str(), comment] {
+            storage.set(Some(&auth), "coop", key, vec![0; 10]).unwrap();
+        }
+
+        let mut usage = namespace_usage(&storage, Some(&auth), "coop").unwrap();
+        assert_eq!(usage.keys, 3);
+        assert_eq!(usage.bytes, 30);
+        assert_eq!((usage.proposals, usage.votes, usage.comments), (1, 1, 1));
+        // The in-memory backend reports a fixed quota for every namespace
+        let quota = usage.quota_bytes.unwrap();
+        assert_eq!(usage.headroom_bytes, Some(quota - 30));
+
+        let mut alerts = UsageAlerts::new();
+        assert!(alerts.check(&usage, &DEFAULT_THRESHOLDS).is_none());
+        usage.bytes = quota * 85 / 100;
+        let event = alerts.check(&usage, &DEFAULT_THRESHOLDS).unwrap();
+        assert_eq!(event.category, "storage:quota");
+        assert_eq!(event.data.unwrap()["threshold"], 80);
+        assert!(alerts.check(&usage, &DEFAULT_THRESHOLDS).is_none());
+        usage.bytes = quota;
+        assert!(alerts.check(&usage, &DEFAULT_THRESHOLDS).is_some());
+        usage.bytes = 0;
+        assert!(alerts.check(&usage, &DEFAULT_THRESHOLDS).is_none());
+        usage.bytes = quota * 85 / 100;
+        assert!(alerts.check(&usage, &DEFAULT_THRESHOLDS).is_some());
+    }
+}
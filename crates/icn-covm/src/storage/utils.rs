@@ -24,7 +24,7 @@ pub fn now_with_fallback(fallback: Timestamp) -> Timestamp {
         Ok(ts) => ts,
         Err(e) => {
             // Log the error when time appears to have gone backwards
-            log::warn!("Clock error when getting timestamp: {}", e);
+            tracing::warn!("Clock error when getting timestamp: {}", e);
             fallback
         }
     }
@@ -58,7 +58,7 @@ pub fn now_with_default() -> Timestamp {
         Ok(duration) => duration.as_secs(),
         Err(e) => {
             // Log the error
-            log::warn!("Clock error when getting timestamp, using default: {}", e);
+            tracing::warn!("Clock error when getting timestamp, using default: {}", e);
             // Use January 1, 2022 as the default timestamp
             1640995200
         }
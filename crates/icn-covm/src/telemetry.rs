@@ -0,0 +1,394 @@
+//! Diagnostic logging and OpenTelemetry export
+//!
+//! Modules report what they are doing through `tracing` events and spans.
+//! Spans carry the context an operator filters on: `proposal_id`,
+//! `namespace` and `identity` (the acting DID). [`init`] installs the
+//! subscriber described by the `[logging]` config section, and forwards
+//! records from dependencies that still use the `log` crate.
+//!
+//! Command output meant for the user is still printed directly; only
+//! diagnostics go through here, to stderr or the configured file.
+//!
+//! Built with the `otel` feature, and with `telemetry.otlp_endpoint` set,
+//! spans and the [`metrics`] for API requests, VM executions and federation
+//! round trips are also exported over OTLP/HTTP to a collector, from which
+//! they can reach any standard observability stack.
+
+use crate::config::{LoggingConfig, LoggingFormat, TelemetryConfig};
+use std::fs::OpenOptions;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::Registry;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Errors setting up logging
+#[derive(Debug, thiserror::Error)]
+pub enum TelemetryError {
+    #[error("Invalid log level {level:?}: {details}")]
+    Level { level: String, details: String },
+
+    #[error("Failed to open log file {path}: {source}")]
+    File { path: PathBuf, source: io::Error },
+
+    #[error("Failed to set up OTLP export to {endpoint}: {details}")]
+    Otlp { endpoint: String, details: String },
+
+    #[error("Failed to install logger: {0}")]
+    Install(String),
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// The filter for `level`, unless `RUST_LOG` overrides it
+fn filter(level: &str) -> Result<EnvFilter, TelemetryError> {
+    if let Ok(filter) = EnvFilter::try_from_default_env() {
+        return Ok(filter);
+    }
+    EnvFilter::try_new(level).map_err(|e| TelemetryError::Level {
+        level: level.to_string(),
+        details: e.to_string(),
+    })
+}
+
+/// The layer writing log lines for `config`
+fn fmt_layer(config: &LoggingConfig) -> Result<BoxedLayer, TelemetryError> {
+    let layer = tracing_subscriber::fmt::layer();
+    let layer = match &config.file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|source| TelemetryError::File {
+                    path: path.clone(),
+                    source,
+                })?;
+            let layer = layer.with_writer(Mutex::new(file)).with_ansi(false);
+            match config.format {
+                LoggingFormat::Text => layer.boxed(),
+                LoggingFormat::Json => layer.json().boxed(),
+            }
+        }
+        None => {
+            let layer = layer.with_writer(io::stderr);
+            match config.format {
+                LoggingFormat::Text => layer.boxed(),
+                LoggingFormat::Json => layer.json().boxed(),
+            }
+        }
+    };
+    Ok(layer.with_filter(filter(&config.level)?).boxed())
+}
+
+/// Keeps OTLP export running; flushes what is left when dropped
+#[must_use = "dropping the guard stops OTLP export"]
+#[derive(Default)]
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    otlp: Option<otlp::Providers>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(providers) = self.otlp.take() {
+            providers.shutdown();
+        }
+    }
+}
+
+/// Install the global subscriber for `logging`, and start OTLP export if
+/// `telemetry` names an endpoint
+///
+/// Keep the returned guard until the program ends, so that the last spans
+/// and metrics are sent.
+pub fn init(
+    logging: &LoggingConfig,
+    telemetry: &TelemetryConfig,
+) -> Result<TelemetryGuard, TelemetryError> {
+    let mut layers = vec![fmt_layer(logging)?];
+    let guard = match &telemetry.otlp_endpoint {
+        Some(endpoint) => {
+            let (layer, guard) = otlp_layer(endpoint, telemetry)?;
+            layers.extend(layer);
+            guard
+        }
+        None => TelemetryGuard::default(),
+    };
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .try_init()
+        .map_err(|e| TelemetryError::Install(e.to_string()))?;
+
+    if cfg!(not(feature = "otel")) {
+        if let Some(endpoint) = &telemetry.otlp_endpoint {
+            tracing::warn!(
+                endpoint = %endpoint,
+                "Not exporting telemetry: icn-covm was built without the otel feature"
+            );
+        }
+    }
+    Ok(guard)
+}
+
+#[cfg(feature = "otel")]
+fn otlp_layer(
+    endpoint: &str,
+    telemetry: &TelemetryConfig,
+) -> Result<(Option<BoxedLayer>, TelemetryGuard), TelemetryError> {
+    let providers = otlp::Providers::install(endpoint, telemetry)?;
+    let layer = providers
+        .layer()
+        .with_filter(filter(&telemetry.level)?)
+        .boxed();
+    let guard = TelemetryGuard {
+        otlp: Some(providers),
+    };
+    Ok((Some(layer), guard))
+}
+
+#[cfg(not(feature = "otel"))]
+fn otlp_layer(
+    _endpoint: &str,
+    _telemetry: &TelemetryConfig,
+) -> Result<(Option<BoxedLayer>, TelemetryGuard), TelemetryError> {
+    Ok((None, TelemetryGuard::default()))
+}
+
+/// OTLP/HTTP exporters for spans and metrics
+#[cfg(feature = "otel")]
+mod otlp {
+    use super::TelemetryError;
+    use crate::config::TelemetryConfig;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+    use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+    use opentelemetry_sdk::Resource;
+    use std::time::Duration;
+    use tracing_opentelemetry::OpenTelemetryLayer;
+    use tracing_subscriber::registry::Registry;
+
+    pub(super) struct Providers {
+        tracer: SdkTracerProvider,
+        meter: SdkMeterProvider,
+    }
+
+    impl Providers {
+        /// Start exporting to the collector at `endpoint`, such as
+        /// `http://localhost:4318`, and make the providers global
+        pub(super) fn install(
+            endpoint: &str,
+            config: &TelemetryConfig,
+        ) -> Result<Self, TelemetryError> {
+            let otlp_error = |details: String| TelemetryError::Otlp {
+                endpoint: endpoint.to_string(),
+                details,
+            };
+            let base = endpoint.trim_end_matches('/');
+            let resource = Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build();
+
+            let spans = SpanExporter::builder()
+                .with_http()
+                .with_endpoint(format!("{}/v1/traces", base))
+                .build()
+                .map_err(|e| otlp_error(e.to_string()))?;
+            let tracer = SdkTracerProvider::builder()
+                .with_batch_exporter(spans)
+                .with_resource(resource.clone())
+                .build();
+
+            let metrics = MetricExporter::builder()
+                .with_http()
+                .with_endpoint(format!("{}/v1/metrics", base))
+                .build()
+                .map_err(|e| otlp_error(e.to_string()))?;
+            let reader = PeriodicReader::builder(metrics)
+                .with_interval(Duration::from_secs(config.metrics_interval_secs.max(1)))
+                .build();
+            let meter = SdkMeterProvider::builder()
+                .with_reader(reader)
+                .with_resource(resource)
+                .build();
+
+            opentelemetry::global::set_tracer_provider(tracer.clone());
+            opentelemetry::global::set_meter_provider(meter.clone());
+            Ok(Self { tracer, meter })
+        }
+
+        /// The layer turning `tracing` spans into exported spans
+        pub(super) fn layer(&self) -> OpenTelemetryLayer<Registry, Tracer> {
+            tracing_opentelemetry::layer().with_tracer(self.tracer.tracer("icn-covm"))
+        }
+
+        /// Flush and stop both exporters
+        pub(super) fn shutdown(self) {
+            if let Err(e) = self.tracer.shutdown() {
+                tracing::warn!(error = %e, "Failed to flush exported spans");
+            }
+            if let Err(e) = self.meter.shutdown() {
+                tracing::warn!(error = %e, "Failed to flush exported metrics");
+            }
+        }
+    }
+}
+
+/// Metrics exported over OTLP
+///
+/// Recording is a no-op unless icn-covm was built with the `otel` feature
+/// and [`init`] started export before the first measurement.
+pub mod metrics {
+    use std::time::Duration;
+
+    #[cfg(feature = "otel")]
+    use opentelemetry::{
+        metrics::{Counter, Gauge, Histogram},
+        KeyValue,
+    };
+
+    #[cfg(feature = "otel")]
+    struct Instruments {
+        api_request_duration: Histogram<f64>,
+        vm_execution_duration: Histogram<f64>,
+        vm_ops: Counter<u64>,
+        federation_round_trip: Histogram<f64>,
+        namespace_usage: Gauge<u64>,
+        namespace_quota: Gauge<u64>,
+        namespace_records: Gauge<u64>,
+    }
+
+    #[cfg(feature = "otel")]
+    static INSTRUMENTS: once_cell::sync::Lazy<Instruments> = once_cell::sync::Lazy::new(|| {
+        let meter = opentelemetry::global::meter("icn-covm");
+        Instruments {
+            api_request_duration: meter
+                .f64_histogram("http.server.request.duration")
+                .with_unit("s")
+                .with_description("Duration of HTTP API requests")
+                .build(),
+            vm_execution_duration: meter
+                .f64_histogram("icn.vm.execution.duration")
+                .with_unit("s")
+                .with_description("Duration of VM program executions")
+                .build(),
+            vm_ops: meter
+                .u64_counter("icn.vm.ops")
+                .with_description("Ops executed by the VM")
+                .build(),
+            federation_round_trip: meter
+                .f64_histogram("icn.federation.round_trip.duration")
+                .with_unit("s")
+                .with_description("Round trips to federation peers")
+                .build(),
+            namespace_usage: meter
+                .u64_gauge("icn.storage.namespace.usage")
+                .with_unit("By")
+                .with_description("Bytes stored in a namespace")
+                .build(),
+            namespace_quota: meter
+                .u64_gauge("icn.storage.namespace.quota")
+                .with_unit("By")
+                .with_description("Storage quota of a namespace")
+                .build(),
+            namespace_records: meter
+                .u64_gauge("icn.storage.namespace.records")
+                .with_description("Keys, proposals, votes and comments in a namespace")
+                .build(),
+        }
+    });
+
+    /// An API request answered with `status` after `elapsed`
+    pub fn record_api_request(method: &str, status: u16, elapsed: Duration) {
+        #[cfg(feature = "otel")]
+        INSTRUMENTS.api_request_duration.record(
+            elapsed.as_secs_f64(),
+            &[
+                KeyValue::new("http.request.method", method.to_string()),
+                KeyValue::new("http.response.status_code", i64::from(status)),
+            ],
+        );
+        #[cfg(not(feature = "otel"))]
+        let _ = (method, status, elapsed);
+    }
+
+    /// A VM run of `ops` top-level ops that took `elapsed`
+    pub fn record_vm_execution(ops: usize, success: bool, elapsed: Duration) {
+        #[cfg(feature = "otel")]
+        {
+            let attributes = [KeyValue::new("success", success)];
+            INSTRUMENTS
+                .vm_execution_duration
+                .record(elapsed.as_secs_f64(), &attributes);
+            INSTRUMENTS.vm_ops.add(ops as u64, &attributes);
+        }
+        #[cfg(not(feature = "otel"))]
+        let _ = (ops, success, elapsed);
+    }
+
+    /// A round trip to a federation peer, such as a `"ping"` or a
+    /// `"kademlia"` query, that took `elapsed`
+    pub fn record_federation_round_trip(kind: &'static str, elapsed: Duration) {
+        #[cfg(feature = "otel")]
+        INSTRUMENTS
+            .federation_round_trip
+            .record(elapsed.as_secs_f64(), &[KeyValue::new("kind", kind)]);
+        #[cfg(not(feature = "otel"))]
+        let _ = (kind, elapsed);
+    }
+
+    /// The figures of one namespace, as measured by
+    /// [`crate::storage::usage::namespace_usage`]
+    pub fn record_namespace_usage(usage: &crate::storage::usage::NamespaceUsage) {
+        #[cfg(feature = "otel")]
+        {
+            let namespace = KeyValue::new("namespace", usage.namespace.clone());
+            INSTRUMENTS
+                .namespace_usage
+                .record(usage.bytes, &[namespace.clone()]);
+            if let Some(quota) = usage.quota_bytes {
+                INSTRUMENTS
+                    .namespace_quota
+                    .record(quota, &[namespace.clone()]);
+            }
+            for (kind, count) in [
+                ("keys", usage.keys),
+                ("proposals", usage.proposals),
+                ("votes", usage.votes),
+                ("comments", usage.comments),
+            ] {
+                INSTRUMENTS.namespace_records.record(
+                    count as u64,
+                    &[namespace.clone(), KeyValue::new("kind", kind)],
+                );
+            }
+        }
+        #[cfg(not(feature = "otel"))]
+        let _ = usage;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_level_is_reported() {
+        let config = LoggingConfig {
+            level: "info,federation=loud".to_string(),
+            ..LoggingConfig::default()
+        };
+        if std::env::var_os("RUST_LOG").is_none() {
+            assert!(matches!(
+                filter(&config.level),
+                Err(TelemetryError::Level { .. })
+            ));
+        }
+        assert!(filter(&LoggingConfig::default().level).is_ok());
+    }
+}
@@ -1,3 +1,8 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
@@ -17,14 +22,24 @@ pub enum TypedValueError {
 
     #[error("Value out of bounds")]
     ValueOutOfBounds,
+
+    #[error("Invalid {encoding} data: {details}")]
+    InvalidEncoding { encoding: String, details: String },
 }
 
 /// A typed value that can be stored on the VM stack
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TypedValue {
     Number(f64),
+    /// Exact decimal, used for token amounts so that economic operations
+    /// are not subject to floating point rounding
+    Decimal(Decimal),
     Boolean(bool),
     String(String),
+    /// Raw bytes, such as signatures, hashes and attachment digests
+    Bytes(Vec<u8>),
+    /// A point in time, such as a deliberation deadline or timelock
+    Timestamp(DateTime<Utc>),
     Null,
 }
 
@@ -33,30 +48,46 @@ impl TypedValue {
     pub fn type_name(&self) -> &'static str {
         match self {
             TypedValue::Number(_) => "Number",
+            TypedValue::Decimal(_) => "Decimal",
             TypedValue::Boolean(_) => "Boolean",
             TypedValue::String(_) => "String",
+            TypedValue::Bytes(_) => "Bytes",
+            TypedValue::Timestamp(_) => "Timestamp",
             TypedValue::Null => "Null",
         }
     }
 
     /// Check if a value is considered falsey in boolean context
-    /// - Numbers: 0.0 is falsey, any other number is truthy
+    /// - Numbers and decimals: zero is falsey, any other value is truthy
     /// - Booleans: false is falsey, true is truthy
-    /// - Strings: empty string is falsey, any other string is truthy
+    /// - Strings and bytes: empty is falsey, anything else is truthy
+    /// - Timestamps: always truthy
     /// - Null: always falsey
     pub fn is_falsey(&self) -> bool {
         match self {
             TypedValue::Number(n) => *n == 0.0,
+            TypedValue::Decimal(d) => d.is_zero(),
             TypedValue::Boolean(b) => !b,
             TypedValue::String(s) => s.is_empty(),
+            TypedValue::Bytes(b) => b.is_empty(),
+            TypedValue::Timestamp(_) => false,
             TypedValue::Null => true,
         }
     }
 
-    /// Try to convert the value to a number
+    /// Check if the value is Null, e.g. a storage key that does not exist
+    pub fn is_null(&self) -> bool {
+        matches!(self, TypedValue::Null)
+    }
+
+    /// Try to convert the value to a number; timestamps give Unix seconds
     pub fn as_number(&self) -> Result<f64, TypedValueError> {
         match self {
             TypedValue::Number(n) => Ok(*n),
+            TypedValue::Decimal(d) => d.to_f64().ok_or_else(|| TypedValueError::CoercionError {
+                from: "Decimal".to_string(),
+                to: "Number".to_string(),
+            }),
             TypedValue::Boolean(b) => Ok(if *b { 1.0 } else { 0.0 }),
             TypedValue::String(s) => s
                 .parse::<f64>()
@@ -64,16 +95,60 @@ impl TypedValue {
                     from: "String".to_string(),
                     to: "Number".to_string(),
                 }),
+            TypedValue::Bytes(_) => Err(TypedValueError::CoercionError {
+                from: "Bytes".to_string(),
+                to: "Number".to_string(),
+            }),
+            TypedValue::Timestamp(t) => Ok(t.timestamp_millis() as f64 / 1000.0),
             TypedValue::Null => Ok(0.0),
         }
     }
 
+    /// Try to convert the value to an exact decimal
+    ///
+    /// Numbers convert to the shortest decimal that round-trips, so `12.5`
+    /// becomes `12.5` rather than its binary approximation.
+    pub fn as_decimal(&self) -> Result<Decimal, TypedValueError> {
+        let coercion_error = || TypedValueError::CoercionError {
+            from: self.type_name().to_string(),
+            to: "Decimal".to_string(),
+        };
+        match self {
+            TypedValue::Number(n) => Decimal::try_from(*n).map_err(|_| coercion_error()),
+            TypedValue::Decimal(d) => Ok(*d),
+            TypedValue::Boolean(b) => Ok(if *b { Decimal::ONE } else { Decimal::ZERO }),
+            TypedValue::String(s) => s.parse::<Decimal>().map_err(|_| coercion_error()),
+            TypedValue::Bytes(_) => Err(coercion_error()),
+            TypedValue::Timestamp(t) => Ok(Decimal::new(t.timestamp_millis(), 3)),
+            TypedValue::Null => Ok(Decimal::ZERO),
+        }
+    }
+
+    /// Both operands as decimals, if either of them is a decimal
+    ///
+    /// Arithmetic and comparisons involving a decimal stay exact: the other
+    /// operand is converted, rather than the decimal being widened to `f64`.
+    fn decimal_operands(
+        &self,
+        other: &TypedValue,
+    ) -> Option<Result<(Decimal, Decimal), TypedValueError>> {
+        match (self, other) {
+            (TypedValue::Decimal(_), _) | (_, TypedValue::Decimal(_)) => {
+                Some(self.as_decimal().and_then(|a| Ok((a, other.as_decimal()?))))
+            }
+            _ => None,
+        }
+    }
+
     /// Try to convert the value to a boolean
     pub fn as_boolean(&self) -> Result<bool, TypedValueError> {
         match self {
             TypedValue::Number(n) => Ok(*n != 0.0),
+            TypedValue::Decimal(d) => Ok(!d.is_zero()),
             TypedValue::Boolean(b) => Ok(*b),
             TypedValue::String(s) => Ok(!s.is_empty()),
+            TypedValue::Bytes(b) => Ok(!b.is_empty()),
+            TypedValue::Timestamp(_) => Ok(true),
             TypedValue::Null => Ok(false),
         }
     }
@@ -82,16 +157,145 @@ impl TypedValue {
     pub fn as_string(&self) -> Result<String, TypedValueError> {
         match self {
             TypedValue::Number(n) => Ok(n.to_string()),
+            TypedValue::Decimal(d) => Ok(d.to_string()),
             TypedValue::Boolean(b) => Ok(b.to_string()),
             TypedValue::String(s) => Ok(s.clone()),
+            TypedValue::Bytes(b) => Ok(format!("0x{}", hex::encode(b))),
+            TypedValue::Timestamp(t) => Ok(format_timestamp(t)),
             TypedValue::Null => Ok("null".to_string()),
         }
     }
 
+    /// Try to convert the value to bytes; strings give their UTF-8 encoding
+    pub fn as_bytes(&self) -> Result<Vec<u8>, TypedValueError> {
+        match self {
+            TypedValue::Bytes(b) => Ok(b.clone()),
+            TypedValue::String(s) => Ok(s.as_bytes().to_vec()),
+            _ => Err(TypedValueError::CoercionError {
+                from: self.type_name().to_string(),
+                to: "Bytes".to_string(),
+            }),
+        }
+    }
+
+    /// The text of a string value, for ops that only accept strings
+    fn expect_string(&self) -> Result<&str, TypedValueError> {
+        match self {
+            TypedValue::String(s) => Ok(s),
+            _ => Err(TypedValueError::TypeMismatch {
+                expected: "String".to_string(),
+                found: self.type_name().to_string(),
+            }),
+        }
+    }
+
+    /// Encode bytes, or a string's UTF-8, as a lowercase hex string
+    pub fn hex_encode(&self) -> Result<TypedValue, TypedValueError> {
+        Ok(TypedValue::String(hex::encode(self.as_bytes()?)))
+    }
+
+    /// Decode a hex string, with or without a `0x` prefix, to bytes
+    pub fn hex_decode(&self) -> Result<TypedValue, TypedValueError> {
+        let text = self.expect_string()?;
+        hex::decode(text.strip_prefix("0x").unwrap_or(text))
+            .map(TypedValue::Bytes)
+            .map_err(|e| TypedValueError::InvalidEncoding {
+                encoding: "hex".to_string(),
+                details: e.to_string(),
+            })
+    }
+
+    /// Encode bytes, or a string's UTF-8, as standard padded base64
+    pub fn base64_encode(&self) -> Result<TypedValue, TypedValueError> {
+        Ok(TypedValue::String(BASE64.encode(self.as_bytes()?)))
+    }
+
+    /// Decode a standard padded base64 string to bytes
+    pub fn base64_decode(&self) -> Result<TypedValue, TypedValueError> {
+        BASE64
+            .decode(self.expect_string()?)
+            .map(TypedValue::Bytes)
+            .map_err(|e| TypedValueError::InvalidEncoding {
+                encoding: "base64".to_string(),
+                details: e.to_string(),
+            })
+    }
+
+    /// Length of bytes in bytes, or of a string in characters
+    pub fn length(&self) -> Result<TypedValue, TypedValueError> {
+        match self {
+            TypedValue::Bytes(b) => Ok(TypedValue::Number(b.len() as f64)),
+            TypedValue::String(s) => Ok(TypedValue::Number(s.chars().count() as f64)),
+            _ => Err(TypedValueError::TypeMismatch {
+                expected: "Bytes or String".to_string(),
+                found: self.type_name().to_string(),
+            }),
+        }
+    }
+
+    /// Parse an RFC 3339 string, a `YYYY-MM-DD` date (midnight UTC) or a
+    /// number of Unix seconds as a timestamp
+    pub fn parse_time(&self) -> Result<TypedValue, TypedValueError> {
+        match self {
+            TypedValue::Timestamp(t) => Ok(TypedValue::Timestamp(*t)),
+            TypedValue::Number(_) | TypedValue::Decimal(_) => {
+                shift_time(&DateTime::UNIX_EPOCH, self.as_number()?).map(TypedValue::Timestamp)
+            }
+            _ => {
+                let text = self.expect_string()?;
+                DateTime::parse_from_rfc3339(text)
+                    .map(|t| t.with_timezone(&Utc))
+                    .or_else(|_| {
+                        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                            .map(|d| d.and_time(Default::default()).and_utc())
+                    })
+                    .map(TypedValue::Timestamp)
+                    .map_err(|_| TypedValueError::InvalidEncoding {
+                        encoding: "timestamp".to_string(),
+                        details: format!("'{}' is not an RFC 3339 time or YYYY-MM-DD date", text),
+                    })
+            }
+        }
+    }
+
+    /// Format a timestamp as an RFC 3339 string in UTC
+    pub fn format_time(&self) -> Result<TypedValue, TypedValueError> {
+        match self {
+            TypedValue::Timestamp(t) => Ok(TypedValue::String(format_timestamp(t))),
+            _ => Err(TypedValueError::TypeMismatch {
+                expected: "Timestamp".to_string(),
+                found: self.type_name().to_string(),
+            }),
+        }
+    }
+
+    /// Join two values as text, converting any that is not a string
+    pub fn concat(&self, other: &TypedValue) -> Result<TypedValue, TypedValueError> {
+        Ok(TypedValue::String(format!(
+            "{}{}",
+            self.as_string()?,
+            other.as_string()?
+        )))
+    }
+
     /// Add two values, with type coercion
     pub fn add(&self, other: &TypedValue) -> Result<TypedValue, TypedValueError> {
         match (self, other) {
             (TypedValue::Number(a), TypedValue::Number(b)) => Ok(TypedValue::Number(a + b)),
+            (TypedValue::Timestamp(_), TypedValue::Timestamp(_)) => {
+                Err(TypedValueError::InvalidOperationForType {
+                    op: "add".to_string(),
+                    types: "Timestamp and Timestamp".to_string(),
+                })
+            }
+            (TypedValue::Timestamp(t), seconds) | (seconds, TypedValue::Timestamp(t))
+                if !matches!(seconds, TypedValue::String(_)) =>
+            {
+                shift_time(t, seconds.as_number()?).map(TypedValue::Timestamp)
+            }
+            (TypedValue::Bytes(a), TypedValue::Bytes(b)) => {
+                Ok(TypedValue::Bytes([a.as_slice(), b].concat()))
+            }
             (TypedValue::String(a), TypedValue::String(b)) => {
                 Ok(TypedValue::String(format!("{}{}", a, b)))
             }
@@ -104,6 +308,13 @@ impl TypedValue {
                 Ok(TypedValue::String(format!("{}{}", a_str, b)))
             }
             _ => {
+                if let Some(operands) = self.decimal_operands(other) {
+                    let (a, b) = operands?;
+                    return a
+                        .checked_add(b)
+                        .map(TypedValue::Decimal)
+                        .ok_or(TypedValueError::ValueOutOfBounds);
+                }
                 // Try numeric coercion for other combinations
                 let a_num = self.as_number()?;
                 let b_num = other.as_number()?;
@@ -113,7 +324,30 @@ impl TypedValue {
     }
 
     /// Subtract two values, with type coercion
+    ///
+    /// Subtracting two timestamps gives the seconds between them, and
+    /// subtracting a number from a timestamp moves it back that many seconds.
     pub fn sub(&self, other: &TypedValue) -> Result<TypedValue, TypedValueError> {
+        match (self, other) {
+            (TypedValue::Timestamp(a), TypedValue::Timestamp(b)) => {
+                return Ok(TypedValue::Number(
+                    (*a - *b).num_milliseconds() as f64 / 1000.0,
+                ));
+            }
+            (TypedValue::Timestamp(t), seconds) => {
+                return shift_time(t, -seconds.as_number()?).map(TypedValue::Timestamp);
+            }
+            _ => {}
+        }
+
+        if let Some(operands) = self.decimal_operands(other) {
+            let (a, b) = operands?;
+            return a
+                .checked_sub(b)
+                .map(TypedValue::Decimal)
+                .ok_or(TypedValueError::ValueOutOfBounds);
+        }
+
         // Subtraction requires numeric coercion
         let a_num = self.as_number()?;
         let b_num = other.as_number()?;
@@ -135,6 +369,13 @@ impl TypedValue {
                 Ok(TypedValue::String(s.repeat(repeat)))
             }
             _ => {
+                if let Some(operands) = self.decimal_operands(other) {
+                    let (a, b) = operands?;
+                    return a
+                        .checked_mul(b)
+                        .map(TypedValue::Decimal)
+                        .ok_or(TypedValueError::ValueOutOfBounds);
+                }
                 // Try numeric coercion for other combinations
                 let a_num = self.as_number()?;
                 let b_num = other.as_number()?;
@@ -145,6 +386,20 @@ impl TypedValue {
 
     /// Divide two values, with type coercion
     pub fn div(&self, other: &TypedValue) -> Result<TypedValue, TypedValueError> {
+        if let Some(operands) = self.decimal_operands(other) {
+            let (a, b) = operands?;
+            if b.is_zero() {
+                return Err(TypedValueError::InvalidOperationForType {
+                    op: "division".to_string(),
+                    types: "by zero".to_string(),
+                });
+            }
+            return a
+                .checked_div(b)
+                .map(TypedValue::Decimal)
+                .ok_or(TypedValueError::ValueOutOfBounds);
+        }
+
         // Division requires numeric coercion
         let a_num = self.as_number()?;
         let b_num = other.as_number()?;
@@ -161,6 +416,20 @@ impl TypedValue {
 
     /// Modulo operation, with type coercion
     pub fn modulo(&self, other: &TypedValue) -> Result<TypedValue, TypedValueError> {
+        if let Some(operands) = self.decimal_operands(other) {
+            let (a, b) = operands?;
+            if b.is_zero() {
+                return Err(TypedValueError::InvalidOperationForType {
+                    op: "modulo".to_string(),
+                    types: "by zero".to_string(),
+                });
+            }
+            return a
+                .checked_rem(b)
+                .map(TypedValue::Decimal)
+                .ok_or(TypedValueError::ValueOutOfBounds);
+        }
+
         // Modulo requires numeric coercion
         let a_num = self.as_number()?;
         let b_num = other.as_number()?;
@@ -183,9 +452,14 @@ impl TypedValue {
             }
             (TypedValue::Boolean(a), TypedValue::Boolean(b)) => Ok(TypedValue::Boolean(a == b)),
             (TypedValue::String(a), TypedValue::String(b)) => Ok(TypedValue::Boolean(a == b)),
+            (TypedValue::Bytes(a), TypedValue::Bytes(b)) => Ok(TypedValue::Boolean(a == b)),
+            (TypedValue::Timestamp(a), TypedValue::Timestamp(b)) => Ok(TypedValue::Boolean(a == b)),
             (TypedValue::Null, TypedValue::Null) => Ok(TypedValue::Boolean(true)),
             (TypedValue::Null, _) | (_, TypedValue::Null) => Ok(TypedValue::Boolean(false)),
             _ => {
+                if let Some(operands) = self.decimal_operands(other) {
+                    return Ok(TypedValue::Boolean(operands.is_ok_and(|(a, b)| a == b)));
+                }
                 // For mixed types, try string comparison as a last resort
                 let a_str = self.as_string()?;
                 let b_str = other.as_string()?;
@@ -199,7 +473,12 @@ impl TypedValue {
         match (self, other) {
             (TypedValue::Number(a), TypedValue::Number(b)) => Ok(TypedValue::Boolean(a > b)),
             (TypedValue::String(a), TypedValue::String(b)) => Ok(TypedValue::Boolean(a > b)),
+            (TypedValue::Timestamp(a), TypedValue::Timestamp(b)) => Ok(TypedValue::Boolean(a > b)),
             _ => {
+                if let Some(operands) = self.decimal_operands(other) {
+                    let (a, b) = operands?;
+                    return Ok(TypedValue::Boolean(a > b));
+                }
                 // For mixed types, try numeric comparison
                 let a_num = self.as_number()?;
                 let b_num = other.as_number()?;
@@ -213,7 +492,12 @@ impl TypedValue {
         match (self, other) {
             (TypedValue::Number(a), TypedValue::Number(b)) => Ok(TypedValue::Boolean(a < b)),
             (TypedValue::String(a), TypedValue::String(b)) => Ok(TypedValue::Boolean(a < b)),
+            (TypedValue::Timestamp(a), TypedValue::Timestamp(b)) => Ok(TypedValue::Boolean(a < b)),
             _ => {
+                if let Some(operands) = self.decimal_operands(other) {
+                    let (a, b) = operands?;
+                    return Ok(TypedValue::Boolean(a < b));
+                }
                 // For mixed types, try numeric comparison
                 let a_num = self.as_number()?;
                 let b_num = other.as_number()?;
@@ -246,8 +530,11 @@ impl TypedValue {
     pub fn describe(&self) -> String {
         match self {
             TypedValue::Number(n) => format!("Number({})", n),
+            TypedValue::Decimal(d) => format!("Decimal({})", d),
             TypedValue::Boolean(b) => format!("Boolean({})", b),
             TypedValue::String(s) => format!("String(\"{}\")", s),
+            TypedValue::Bytes(b) => format!("Bytes(0x{})", hex::encode(b)),
+            TypedValue::Timestamp(t) => format!("Timestamp({})", format_timestamp(t)),
             TypedValue::Null => "Null".into(),
         }
     }
@@ -276,13 +563,130 @@ impl fmt::Display for TypedValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             TypedValue::Number(n) => write!(f, "{}", n),
+            TypedValue::Decimal(d) => write!(f, "{}", d),
             TypedValue::Boolean(b) => write!(f, "{}", b),
             TypedValue::String(s) => write!(f, "\"{}\"", s),
+            TypedValue::Bytes(b) => write!(f, "0x{}", hex::encode(b)),
+            TypedValue::Timestamp(t) => write!(f, "{}", format_timestamp(t)),
             TypedValue::Null => write!(f, "null"),
         }
     }
 }
 
+/// How strictly operations check the types of their operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TypingMode {
+    /// Coerce operands between numbers, booleans and strings as needed
+    #[default]
+    Permissive,
+    /// Reject operands that would need an implicit coercion
+    Strict,
+}
+
+impl TypingMode {
+    /// Check that `op` can be applied to `operands` under this mode
+    ///
+    /// In strict mode, arithmetic takes numbers and decimals, with `add`
+    /// also joining two strings or two byte strings and timestamps moving
+    /// by a number of seconds. Comparisons take two values of the same
+    /// type (numbers and decimals counting as one), `eq` also accepting
+    /// Null against anything. Logical operations take booleans only.
+    pub fn check(self, op: &str, operands: &[&TypedValue]) -> Result<(), TypedValueError> {
+        if self == TypingMode::Permissive {
+            return Ok(());
+        }
+        let numeric = |v: &TypedValue| matches!(v, TypedValue::Number(_) | TypedValue::Decimal(_));
+        let same_type = |a: &TypedValue, b: &TypedValue| {
+            (numeric(a) && numeric(b)) || a.type_name() == b.type_name()
+        };
+
+        let (allowed, expected) = match (op, operands) {
+            ("add" | "sub" | "mul" | "div" | "mod", [a, b]) => {
+                let allowed = (numeric(a) && numeric(b))
+                    || matches!(
+                        (op, a, b),
+                        ("add", TypedValue::String(_), TypedValue::String(_))
+                            | ("add", TypedValue::Bytes(_), TypedValue::Bytes(_))
+                            | ("add", TypedValue::Timestamp(_), TypedValue::Number(_))
+                            | ("add", TypedValue::Number(_), TypedValue::Timestamp(_))
+                            | ("sub", TypedValue::Timestamp(_), TypedValue::Number(_))
+                            | ("sub", TypedValue::Timestamp(_), TypedValue::Timestamp(_))
+                    );
+                (allowed, "numbers")
+            }
+            ("eq", [a, b]) => (
+                same_type(a, b) || a.is_null() || b.is_null(),
+                "values of the same type",
+            ),
+            ("gt" | "lt", [a, b]) => (
+                same_type(a, b)
+                    && matches!(
+                        a,
+                        TypedValue::Number(_)
+                            | TypedValue::Decimal(_)
+                            | TypedValue::String(_)
+                            | TypedValue::Timestamp(_)
+                    ),
+                "two numbers, strings or timestamps",
+            ),
+            ("not" | "and" | "or", operands) => (
+                operands.iter().all(|v| matches!(v, TypedValue::Boolean(_))),
+                "booleans",
+            ),
+            _ => (true, ""),
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(TypedValueError::TypeMismatch {
+                expected: expected.to_string(),
+                found: operands
+                    .iter()
+                    .map(|v| v.type_name())
+                    .collect::<Vec<_>>()
+                    .join(" and "),
+            })
+        }
+    }
+}
+
+impl fmt::Display for TypingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypingMode::Permissive => write!(f, "permissive"),
+            TypingMode::Strict => write!(f, "strict"),
+        }
+    }
+}
+
+impl std::str::FromStr for TypingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "permissive" => Ok(TypingMode::Permissive),
+            "strict" => Ok(TypingMode::Strict),
+            _ => Err(format!("unknown typing mode: {}", s)),
+        }
+    }
+}
+
+/// RFC 3339 in UTC, with fractional seconds only when there are any
+fn format_timestamp(t: &DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Move a timestamp by a number of seconds, to the millisecond
+fn shift_time(t: &DateTime<Utc>, seconds: f64) -> Result<DateTime<Utc>, TypedValueError> {
+    if !seconds.is_finite() {
+        return Err(TypedValueError::ValueOutOfBounds);
+    }
+    Duration::try_milliseconds((seconds * 1000.0).round() as i64)
+        .and_then(|offset| t.checked_add_signed(offset))
+        .ok_or(TypedValueError::ValueOutOfBounds)
+}
+
 /// Extended VM with typed values
 #[derive(Debug)]
 pub struct TypedVM {
@@ -436,6 +840,11 @@ impl TypedVM {
             TypedValueError::ValueOutOfBounds => {
                 crate::vm::VMError::ParameterError("Value out of bounds".to_string())
             }
+            TypedValueError::InvalidEncoding { encoding, details } => {
+                crate::vm::VMError::InvalidFormat {
+                    reason: format!("invalid {} data: {}", encoding, details),
+                }
+            }
         }
     }
 }
@@ -474,6 +883,142 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_typed_decimal_arithmetic() {
+        let dec = |s: &str| TypedValue::Decimal(s.parse().unwrap());
+
+        // Exact where f64 is not
+        assert_eq!(dec("0.1").add(&dec("0.2")).unwrap(), dec("0.3"));
+        assert_eq!(dec("12.50").sub(&dec("0.01")).unwrap(), dec("12.49"));
+
+        // Numbers are converted to decimals, not the other way round
+        assert_eq!(
+            dec("0.1").add(&TypedValue::Number(0.2)).unwrap(),
+            dec("0.3")
+        );
+        assert_eq!(dec("10").div(&dec("4")).unwrap(), dec("2.5"));
+        assert!(dec("1").div(&dec("0")).is_err());
+
+        assert_eq!(
+            dec("12.50").equals(&TypedValue::Number(12.5)).unwrap(),
+            TypedValue::Boolean(true)
+        );
+        assert_eq!(
+            dec("0.3").greater_than(&dec("0.29")).unwrap(),
+            TypedValue::Boolean(true)
+        );
+        assert_eq!(
+            TypedValue::String("12.50".to_string())
+                .as_decimal()
+                .unwrap(),
+            "12.5".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_typed_bytes_encoding() {
+        let digest = TypedValue::Bytes(vec![0xde, 0xad, 0xbe, 0xef]);
+
+        let hex = digest.hex_encode().unwrap();
+        assert_eq!(hex, TypedValue::String("deadbeef".to_string()));
+        assert_eq!(hex.hex_decode().unwrap(), digest);
+        assert_eq!(
+            TypedValue::String("0xDEADBEEF".to_string())
+                .hex_decode()
+                .unwrap(),
+            digest
+        );
+
+        let base64 = digest.base64_encode().unwrap();
+        assert_eq!(base64, TypedValue::String("3q2+7w==".to_string()));
+        assert_eq!(base64.base64_decode().unwrap(), digest);
+
+        assert_eq!(digest.length().unwrap(), TypedValue::Number(4.0));
+        assert_eq!(
+            TypedValue::String("héllo".to_string()).length().unwrap(),
+            TypedValue::Number(5.0)
+        );
+
+        assert!(matches!(
+            TypedValue::String("xyz".to_string()).hex_decode(),
+            Err(TypedValueError::InvalidEncoding { .. })
+        ));
+        assert!(digest.hex_decode().is_err());
+        assert!(TypedValue::Number(1.0).length().is_err());
+    }
+
+    #[test]
+    fn test_typed_timestamps() {
+        let opened = TypedValue::String("2025-03-01T12:00:00Z".to_string())
+            .parse_time()
+            .unwrap();
+        let week = TypedValue::Number(7.0 * 86400.0);
+        let deadline = opened.add(&week).unwrap();
+        assert_eq!(
+            deadline.format_time().unwrap(),
+            TypedValue::String("2025-03-08T12:00:00Z".to_string())
+        );
+
+        // Comparisons are chronological
+        assert_eq!(
+            deadline.greater_than(&opened).unwrap(),
+            TypedValue::Boolean(true)
+        );
+        assert_eq!(deadline.sub(&opened).unwrap(), week);
+        assert_eq!(deadline.sub(&week).unwrap(), opened);
+
+        // Dates, offsets and Unix seconds name the same instants
+        let midnight = TypedValue::String("2025-03-01".to_string())
+            .parse_time()
+            .unwrap();
+        assert_eq!(
+            TypedValue::String("2025-03-01T02:00:00+02:00".to_string())
+                .parse_time()
+                .unwrap(),
+            midnight
+        );
+        assert_eq!(
+            TypedValue::Number(1_740_787_200.0).parse_time().unwrap(),
+            midnight
+        );
+        assert_eq!(midnight.as_number().unwrap(), 1_740_787_200.0);
+
+        assert!(matches!(
+            TypedValue::String("next tuesday".to_string()).parse_time(),
+            Err(TypedValueError::InvalidEncoding { .. })
+        ));
+        assert!(opened.add(&opened).is_err());
+    }
+
+    #[test]
+    fn test_strict_typing() {
+        let one = TypedValue::Number(1.0);
+        let yes = TypedValue::Boolean(true);
+        let text = TypedValue::String("1".to_string());
+        let amount = TypedValue::Decimal(Decimal::from(5));
+
+        // Permissive mode allows everything the coercion rules do
+        assert!(TypingMode::Permissive.check("add", &[&one, &yes]).is_ok());
+
+        let strict = TypingMode::Strict;
+        assert!(strict.check("add", &[&one, &amount]).is_ok());
+        assert!(strict.check("add", &[&text, &text]).is_ok());
+        assert!(strict.check("eq", &[&yes, &TypedValue::Null]).is_ok());
+        assert!(strict.check("and", &[&yes, &yes]).is_ok());
+
+        assert_eq!(
+            strict.check("add", &[&one, &yes]),
+            Err(TypedValueError::TypeMismatch {
+                expected: "numbers".to_string(),
+                found: "Number and Boolean".to_string(),
+            })
+        );
+        assert!(strict.check("add", &[&text, &one]).is_err());
+        assert!(strict.check("eq", &[&one, &text]).is_err());
+        assert!(strict.check("gt", &[&yes, &yes]).is_err());
+        assert!(strict.check("not", &[&one]).is_err());
+    }
+
     #[test]
     fn test_typed_boolean_operations() {
         let t = TypedValue::Boolean(true);
@@ -0,0 +1,359 @@
+//! Stepping through a program one op at a time
+//!
+//! [`VM::start_debugging`] loads a program without running it. Each
+//! [`VM::step`] then runs its next top-level op, and [`VM::resume`] runs ops
+//! until the next breakpoint or the end of the program. The ops nested in a
+//! top-level op, such as a loop body or the functions it calls, run as part
+//! of it.
+//!
+//! A breakpoint names either the index of a top-level op, or a function: the
+//! VM pauses before any top-level op that calls the function, directly, from
+//! a nested block, or through other functions the program defines.
+//!
+//! Whenever the VM pauses, the callbacks registered with [`VM::on_pause`] are
+//! given an [`Inspection`] of the stack and memory, which is how `run
+//! --debug` prints them.
+
+use crate::coverage;
+use crate::storage::traits::Storage;
+use crate::typed::TypedValue;
+use crate::vm::errors::VMError;
+use crate::vm::memory::MemoryScope;
+use crate::vm::stack::StackOps;
+use crate::vm::types::Op;
+use crate::vm::VM;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+
+/// Where execution pauses
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Breakpoint {
+    /// Before the top-level op at this index
+    Op(usize),
+    /// Before any top-level op that calls this function
+    Function(String),
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Op(index) => write!(f, "op {}", index),
+            Breakpoint::Function(name) => write!(f, "function {}", name),
+        }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    /// An op index, or otherwise a function name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("A breakpoint needs an op index or a function name".to_string());
+        }
+        Ok(s.parse()
+            .map(Breakpoint::Op)
+            .unwrap_or_else(|_| Breakpoint::Function(s.to_string())))
+    }
+}
+
+/// Where a debugged program stands after a step
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugState {
+    /// Paused before the op at `next`, on `breakpoint` if one was hit
+    Paused {
+        next: usize,
+        breakpoint: Option<Breakpoint>,
+    },
+    /// Every op has run
+    Finished,
+}
+
+/// The VM as it stands when paused
+#[derive(Debug, Clone)]
+pub struct Inspection {
+    /// Index of the op to run next
+    pub next: usize,
+    /// The op to run next, `None` once the program has finished
+    pub op: Option<Op>,
+    /// The breakpoint execution paused on, if any
+    pub breakpoint: Option<Breakpoint>,
+    /// The stack, bottom first
+    pub stack: Vec<TypedValue>,
+    pub memory: HashMap<String, TypedValue>,
+}
+
+type PauseCallback = Box<dyn FnMut(&Inspection) + Send>;
+
+/// The program being debugged, with its breakpoints and pause callbacks
+#[derive(Default)]
+pub struct Debugger {
+    program: Vec<Op>,
+    next: usize,
+    breakpoints: BTreeSet<Breakpoint>,
+    callbacks: Vec<PauseCallback>,
+}
+
+impl Debug for Debugger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debugger")
+            .field("ops", &self.program.len())
+            .field("next", &self.next)
+            .field("breakpoints", &self.breakpoints)
+            .field("callbacks", &self.callbacks.len())
+            .finish()
+    }
+}
+
+impl<S> VM<S>
+where
+    S: Storage + Send + Sync + Clone + Debug + 'static,
+{
+    fn debugger_mut(&mut self) -> &mut Debugger {
+        self.debugger.get_or_insert_with(Debugger::default)
+    }
+
+    /// Load `ops` to be stepped through, keeping any breakpoints and
+    /// callbacks already registered
+    pub fn start_debugging(&mut self, ops: &[Op]) {
+        self.executor.reset_read_cache();
+        let debugger = self.debugger_mut();
+        debugger.program = ops.to_vec();
+        debugger.next = 0;
+    }
+
+    /// Pause before `breakpoint` is reached
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> &mut Self {
+        self.debugger_mut().breakpoints.insert(breakpoint);
+        self
+    }
+
+    /// Remove a breakpoint, returning whether it was set
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) -> bool {
+        self.debugger_mut().breakpoints.remove(breakpoint)
+    }
+
+    /// Breakpoints currently set
+    pub fn breakpoints(&self) -> Vec<Breakpoint> {
+        self.debugger
+            .as_ref()
+            .map(|debugger| debugger.breakpoints.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Call `callback` with the stack and memory whenever execution pauses
+    pub fn on_pause<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: FnMut(&Inspection) + Send + 'static,
+    {
+        self.debugger_mut().callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// The stack and memory before the next op
+    pub fn inspect(&self) -> Inspection {
+        let (next, op) = match &self.debugger {
+            Some(debugger) => (debugger.next, debugger.program.get(debugger.next).cloned()),
+            None => (0, None),
+        };
+        Inspection {
+            next,
+            op,
+            breakpoint: None,
+            stack: self.stack.get_stack(),
+            memory: self.memory.get_memory_map(),
+        }
+    }
+
+    /// Run the next top-level op and pause
+    ///
+    /// An op that fails ends the program, so stepping again finds it
+    /// finished.
+    pub fn step(&mut self) -> Result<DebugState, VMError> {
+        self.run_next()?;
+        Ok(self.pause(None))
+    }
+
+    /// Run ops until one with a breakpoint is next, or the program ends
+    ///
+    /// The next op always runs, so resuming from a breakpoint moves past it.
+    pub fn resume(&mut self) -> Result<DebugState, VMError> {
+        loop {
+            if !self.run_next()? {
+                return Ok(self.pause(None));
+            }
+            if let Some(breakpoint) = self.breakpoint_hit() {
+                return Ok(self.pause(Some(breakpoint)));
+            }
+        }
+    }
+
+    /// Run the next op, returning whether any are left after it
+    fn run_next(&mut self) -> Result<bool, VMError> {
+        let debugger = self
+            .debugger
+            .as_mut()
+            .ok_or_else(|| VMError::UndefinedState("No program is being debugged".into()))?;
+        let Some(op) = debugger.program.get(debugger.next).cloned() else {
+            return Ok(false);
+        };
+        debugger.next += 1;
+        if let Err(e) = self.execute_inner(vec![op]) {
+            let debugger = self.debugger_mut();
+            debugger.next = debugger.program.len();
+            return Err(e);
+        }
+        let debugger = self.debugger_mut();
+        Ok(debugger.next < debugger.program.len())
+    }
+
+    /// The breakpoint on the next op, if any
+    fn breakpoint_hit(&self) -> Option<Breakpoint> {
+        let debugger = self.debugger.as_ref()?;
+        let op = debugger.program.get(debugger.next)?;
+        debugger
+            .breakpoints
+            .iter()
+            .find(|breakpoint| match breakpoint {
+                Breakpoint::Op(index) => *index == debugger.next,
+                Breakpoint::Function(name) => {
+                    self.calls(op, name, &debugger.program, &mut HashSet::new())
+                }
+            })
+            .cloned()
+    }
+
+    /// Whether running `op` may call `function`
+    ///
+    /// Functions are looked up among the definitions in `program`, then in
+    /// memory; `seen` keeps recursive functions from being searched twice.
+    fn calls(&self, op: &Op, function: &str, program: &[Op], seen: &mut HashSet<String>) -> bool {
+        match op {
+            Op::Call(name) if name == function => true,
+            Op::Call(name) => {
+                if !seen.insert(name.clone()) {
+                    return false;
+                }
+                let defined = program.iter().find_map(|op| match op {
+                    Op::Def {
+                        name: def, body, ..
+                    } if def == name => Some(body.clone()),
+                    _ => None,
+                });
+                let body = defined.or_else(|| self.memory.get_function(name).ok().map(|f| f.1));
+                body.unwrap_or_default()
+                    .iter()
+                    .any(|op| self.calls(op, function, program, seen))
+            }
+            // Defining a function does not run it
+            Op::Def { .. } => false,
+            _ => coverage::blocks(op).into_iter().any(|(_, block, _)| {
+                block
+                    .iter()
+                    .any(|op| self.calls(op, function, program, seen))
+            }),
+        }
+    }
+
+    /// Tell the callbacks execution paused, and report where
+    fn pause(&mut self, breakpoint: Option<Breakpoint>) -> DebugState {
+        let mut inspection = self.inspect();
+        inspection.breakpoint = breakpoint.clone();
+        if let Some(debugger) = self.debugger.as_mut() {
+            for callback in &mut debugger.callbacks {
+                callback(&inspection);
+            }
+        }
+        match inspection.op {
+            Some(_) => DebugState::Paused {
+                next: inspection.next,
+                breakpoint,
+            },
+            None => DebugState::Finished,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_step_and_resume_to_breakpoints() {
+        let program = vec![
+            Op::Def {
+                name: "double".to_string(),
+                params: vec!["x".to_string()],
+                body: vec![
+                    Op::Load("x".to_string()),
+                    Op::Push(TypedValue::Number(2.0)),
+                    Op::Mul,
+                    Op::Return,
+                ],
+            },
+            Op::Push(TypedValue::Number(3.0)),
+            Op::Store("n".to_string()),
+            Op::Push(TypedValue::Number(4.0)),
+            Op::If {
+                condition: vec![Op::Push(TypedValue::Boolean(true))],
+                then: vec![Op::Call("double".to_string())],
+                else_: None,
+            },
+            Op::Push(TypedValue::Number(1.0)),
+        ];
+        let mut vm = VM::<InMemoryStorage>::new();
+        let pauses = Arc::new(Mutex::new(Vec::new()));
+        let seen = pauses.clone();
+        vm.on_pause(move |inspection| {
+            seen.lock()
+                .unwrap()
+                .push((inspection.next, inspection.stack.clone()))
+        });
+        vm.add_breakpoint("double".parse().unwrap())
+            .add_breakpoint("2".parse().unwrap());
+        vm.start_debugging(&program);
+
+        assert_eq!(
+            vm.step().unwrap(),
+            DebugState::Paused {
+                next: 1,
+                breakpoint: None
+            }
+        );
+        assert_eq!(
+            vm.resume().unwrap(),
+            DebugState::Paused {
+                next: 2,
+                breakpoint: Some(Breakpoint::Op(2))
+            }
+        );
+        assert_eq!(vm.inspect().stack, vec![TypedValue::Number(3.0)]);
+
+        // The call is nested in the conditional
+        assert_eq!(
+            vm.resume().unwrap(),
+            DebugState::Paused {
+                next: 4,
+                breakpoint: Some(Breakpoint::Function("double".to_string()))
+            }
+        );
+        assert_eq!(vm.inspect().memory["n"], TypedValue::Number(3.0));
+        assert!(vm.remove_breakpoint(&Breakpoint::Op(2)));
+        assert_eq!(vm.resume().unwrap(), DebugState::Finished);
+        assert_eq!(
+            vm.get_stack(),
+            vec![TypedValue::Number(8.0), TypedValue::Number(1.0)]
+        );
+        assert_eq!(pauses.lock().unwrap().len(), 4);
+
+        // A failing op ends the program
+        vm.start_debugging(&[Op::Add, Op::Add, Op::Add]);
+        vm.stack.clear();
+        assert!(vm.step().is_err());
+        assert_eq!(vm.step().unwrap(), DebugState::Finished);
+    }
+}
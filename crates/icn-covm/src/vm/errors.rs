@@ -84,6 +84,15 @@ pub enum VMError {
     #[error("Step limit exceeded: {0} steps")]
     StepLimitExceeded(usize),
 
+    /// Error when an op costs more gas than remains of the budget
+    #[error("Out of gas: {op} costs {cost} but only {remaining} of {limit} remains")]
+    OutOfGas {
+        op: String,
+        cost: u64,
+        remaining: u64,
+        limit: u64,
+    },
+
     /// Error when VM execution reaches the maximum stack depth
     #[error("Stack overflow at depth {0}")]
     StackOverflow(usize),
@@ -96,10 +105,22 @@ pub enum VMError {
     #[error("Authorization error: {0}")]
     AuthorizationError(String),
 
+    /// Error when an op falls in a category the sandbox profile does not allow
+    #[error("Sandbox profile {profile} does not allow {category} op {op}")]
+    SandboxViolation {
+        op: String,
+        category: String,
+        profile: String,
+    },
+
     /// Error when a governance operation fails
     #[error("Governance error: {0}")]
     GovernanceError(String),
 
+    /// Error when a WASM module fails to load or traps
+    #[error("WASM error: {0}")]
+    WasmError(String),
+
     /// Error when a parsing operation fails
     #[error("Parse error: {0}")]
     ParseError(String),
@@ -251,7 +272,7 @@ pub enum VMError {
     TypedValueError(String),
 
     /// Type error in VM operations
-    /// 
+    ///
     /// Deprecated: Use TypeMismatch instead
     #[error("Type error in {op_name}: expected {expected}, found {found}")]
     #[deprecated(since = "0.2.0", note = "Use TypeMismatch instead")]
@@ -424,6 +445,11 @@ impl From<crate::typed::TypedValueError> for VMError {
             crate::typed::TypedValueError::ValueOutOfBounds => {
                 VMError::InvalidAmount { amount: -1.0 } // placeholder for out of bounds
             }
+            crate::typed::TypedValueError::InvalidEncoding { encoding, details } => {
+                VMError::InvalidFormat {
+                    reason: format!("invalid {} data: {}", encoding, details),
+                }
+            }
         }
     }
 }
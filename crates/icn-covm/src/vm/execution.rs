@@ -19,15 +19,48 @@
 //! The module defines an `ExecutorOps` trait that encapsulates operation execution,
 //! enabling alternative implementations for different execution models.
 
+use crate::clock::{self, SharedClock};
+use crate::events::Severity;
+use crate::governance::membership;
 use crate::storage::auth::AuthContext;
 use crate::storage::errors::{StorageError, StorageResult};
-use crate::storage::traits::Storage;
+use crate::storage::resource::{
+    Bounty, BountyVerification, Distribution, EscrowOutcome, ExchangeRate, ResourcePolicy,
+    SpendingAction, SpendingLimit, SpendingWindow,
+};
+use crate::storage::traits::{proposal_escrow_outcome, Storage};
 use crate::vm::errors::VMError;
-use crate::vm::types::VMEvent;
+use crate::vm::gas::{GasMeter, GasSchedule};
+use crate::vm::random::{SeededRandom, MAX_RANGE};
+use crate::vm::types::{Op, VMEvent};
+use crate::vm::wasm::{self, WasmCapability, WasmModuleApproval};
 use crate::vm::MissingKeyBehavior;
-use crate::typed::{TypedValue, TypedValueError};
+use crate::typed::{TypedValue, TypedValueError, TypingMode};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::marker::{Send, Sync};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Counts of `LoadP` reads served by the storage backend and by the
+/// per-execution read cache
+/// A live listener for output and events
+#[derive(Debug, Clone)]
+pub(crate) enum EventListener {
+    /// Receives the events alone
+    Events(Sender<VMEvent>),
+    /// Receives each event with the namespace it was emitted in
+    Namespaced(Sender<(String, VMEvent)>),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageReadStats {
+    /// Reads that went to the storage backend
+    pub backend_reads: u64,
+    /// Reads answered from values already read in this execution
+    pub cache_hits: u64,
+}
 
 /// Defines operations for VM execution logic
 pub trait ExecutorOps<S>
@@ -47,7 +80,11 @@ where
     fn get_auth_context(&self) -> Option<&AuthContext>;
 
     /// Execute a resource creation operation
-    fn execute_create_resource(&mut self, resource: &str) -> Result<(), VMError>;
+    fn execute_create_resource(
+        &mut self,
+        resource: &str,
+        policy: &ResourcePolicy,
+    ) -> Result<(), VMError>;
 
     /// Execute a minting operation
     fn execute_mint(
@@ -59,6 +96,9 @@ where
     ) -> Result<(), VMError>;
 
     /// Execute a transfer operation
+    ///
+    /// A transfer out of another identity's account spends an allowance
+    /// that identity gave the caller, its role or the running proposal.
     fn execute_transfer(
         &mut self,
         resource: &str,
@@ -68,6 +108,86 @@ where
         reason: &Option<String>,
     ) -> Result<(), VMError>;
 
+    /// Let a spender transfer up to `limit` out of the caller's account
+    fn execute_approve(
+        &mut self,
+        resource: &str,
+        spender: &str,
+        limit: &TypedValue,
+    ) -> Result<(), VMError>;
+
+    /// Execute an allowance query operation
+    fn execute_allowance(
+        &mut self,
+        resource: &str,
+        owner: &str,
+        spender: &str,
+    ) -> Result<TypedValue, VMError>;
+
+    /// Execute a spending limit change, which only proposal logic may make
+    fn execute_set_spending_limit(
+        &mut self,
+        resource: &str,
+        subject: &str,
+        action: SpendingAction,
+        window: SpendingWindow,
+        limit: &TypedValue,
+    ) -> Result<(), VMError>;
+
+    /// Execute an exchange rate change, which only proposal logic may make
+    fn execute_set_exchange_rate(
+        &mut self,
+        from_resource: &str,
+        to_resource: &str,
+        rate: &TypedValue,
+        reserve: &str,
+        max_amount: Option<&TypedValue>,
+    ) -> Result<(), VMError>;
+
+    /// Convert units of one resource in an account into another, returning
+    /// the amount received
+    fn execute_exchange(
+        &mut self,
+        from_resource: &str,
+        to_resource: &str,
+        account: &str,
+        amount: &TypedValue,
+        min_received: &TypedValue,
+    ) -> Result<TypedValue, VMError>;
+
+    /// Execute an exchange rate query operation
+    fn execute_exchange_rate(
+        &mut self,
+        from_resource: &str,
+        to_resource: &str,
+    ) -> Result<TypedValue, VMError>;
+
+    /// Approve a WASM module for calls, which only proposal logic may do
+    fn execute_approve_wasm_module(
+        &mut self,
+        hash: &str,
+        capabilities: &[WasmCapability],
+    ) -> Result<(), VMError>;
+
+    /// Admit a member to the cooperative, which only proposal logic may do
+    fn execute_onboard_member(
+        &mut self,
+        did: &str,
+        username: &str,
+        roles: &[String],
+    ) -> Result<(), VMError>;
+
+    /// Retire a member of the cooperative, which only proposal logic may do
+    fn execute_offboard_member(&mut self, did: &str) -> Result<(), VMError>;
+
+    /// Call a function of an approved WASM module, returning its result
+    fn execute_call_wasm(
+        &mut self,
+        hash: &str,
+        function: &str,
+        args: &[f64],
+    ) -> Result<TypedValue, VMError>;
+
     /// Execute a burn operation
     fn execute_burn(
         &mut self,
@@ -80,6 +200,67 @@ where
     /// Execute a balance query operation
     fn execute_balance(&mut self, resource: &str, account: &str) -> Result<TypedValue, VMError>;
 
+    /// Execute a credit limit change, which only proposal logic may make
+    fn execute_set_credit_limit(
+        &mut self,
+        resource: &str,
+        creditor: &str,
+        debtor: &str,
+        limit: &TypedValue,
+    ) -> Result<(), VMError>;
+
+    /// Execute a credit limit query operation
+    fn execute_credit_limit(&mut self, resource: &str, account: &str)
+        -> Result<TypedValue, VMError>;
+
+    /// Execute an escrow lock under a proposal
+    fn execute_escrow_lock(
+        &mut self,
+        proposal_id: &str,
+        resource: &str,
+        from: &str,
+        to: &str,
+        amount: &TypedValue,
+    ) -> Result<(), VMError>;
+
+    /// Settle a proposal's locked escrows, if its outcome allows `outcome`
+    fn execute_escrow_settle(
+        &mut self,
+        proposal_id: &str,
+        outcome: EscrowOutcome,
+    ) -> Result<(), VMError>;
+
+    /// Create a bounty funded from an account, returning its record
+    fn execute_create_bounty(
+        &mut self,
+        id: &str,
+        resource: &str,
+        funder: &str,
+        amount: &TypedValue,
+        verification: &BountyVerification,
+        description: &str,
+    ) -> Result<Bounty, VMError>;
+
+    /// Claim a bounty as the calling identity, returning its record
+    fn execute_claim_bounty(&mut self, id: &str, evidence: &str) -> Result<Bounty, VMError>;
+
+    /// Verify the claim on a bounty as the calling identity, returning its
+    /// record
+    fn execute_verify_bounty(&mut self, id: &str, approve: bool) -> Result<Bounty, VMError>;
+
+    /// Cancel an unclaimed bounty, returning its record
+    fn execute_cancel_bounty(&mut self, id: &str) -> Result<Bounty, VMError>;
+
+    /// Split an amount from a pool account across the members weighted
+    /// under `weight_key`, returning the shares paid
+    fn execute_distribute(
+        &mut self,
+        resource: &str,
+        pool: &str,
+        weight_key: &str,
+        amount: &TypedValue,
+    ) -> Result<Distribution, VMError>;
+
     /// Execute increment reputation for an identity
     fn execute_increment_reputation(
         &mut self,
@@ -111,8 +292,13 @@ where
     /// Emit a message to the output
     fn emit(&mut self, message: &str);
 
-    /// Emit an event with the given category and message
-    fn emit_event(&mut self, category: &str, message: &str);
+    /// Emit an info event with the given category and message
+    fn emit_event(&mut self, category: &str, message: &str) {
+        self.emit_event_with_severity(category, message, Severity::Info);
+    }
+
+    /// Emit an event with the given category, message and severity
+    fn emit_event_with_severity(&mut self, category: &str, message: &str, severity: Severity);
 
     /// Get the current output buffer
     fn get_output(&self) -> &str;
@@ -157,8 +343,38 @@ where
     /// Event log
     pub(crate) events: Vec<VMEvent>,
 
+    /// Live listeners for output and events as they are produced
+    pub(crate) event_listeners: Vec<EventListener>,
+
     /// Transaction state tracking
     pub(crate) transaction_active: bool,
+
+    /// Whether arithmetic, comparison and logic coerce their operands
+    pub(crate) typing_mode: TypingMode,
+
+    /// Proposal whose logic is being executed, if any; operations that only
+    /// governance may perform require one
+    pub(crate) executing_proposal: Option<String>,
+
+    /// Random sequence of the executing proposal, seeded when it was set
+    pub(crate) random: Option<SeededRandom>,
+
+    /// Where `now` and event timestamps come from
+    pub(crate) clock: SharedClock,
+
+    /// Values read with `LoadP` during the current execution, keyed by
+    /// namespace and key; `None` records a key that was not found. Any other
+    /// storage operation may write, so it empties the cache.
+    pub(crate) read_cache: HashMap<(String, String), Option<Vec<u8>>>,
+
+    /// Reads served by the backend and by `read_cache` this execution
+    pub(crate) read_stats: StorageReadStats,
+
+    /// Budget ops are charged against in metered mode, `None` otherwise
+    pub(crate) gas: Option<GasMeter>,
+
+    /// Gas each op costs in metered mode
+    pub(crate) gas_schedule: GasSchedule,
 }
 
 impl<S> VMExecution<S>
@@ -173,10 +389,96 @@ where
             namespace: "default".to_string(),
             output: String::new(),
             events: Vec::new(),
+            event_listeners: Vec::new(),
             transaction_active: false,
+            typing_mode: TypingMode::default(),
+            executing_proposal: None,
+            random: None,
+            clock: clock::system(),
+            read_cache: HashMap::new(),
+            read_stats: StorageReadStats::default(),
+            gas: None,
+            gas_schedule: GasSchedule::default(),
+        }
+    }
+
+    /// Forget cached reads, for when storage may have changed underneath them
+    pub(crate) fn invalidate_reads(&mut self) {
+        self.read_cache.clear();
+    }
+
+    /// Start a new execution with an empty read cache and zeroed counters
+    pub(crate) fn reset_read_cache(&mut self) {
+        self.read_cache.clear();
+        self.read_stats = StorageReadStats::default();
+    }
+
+    /// `LoadP` reads served by the backend and by the read cache since the
+    /// current execution started
+    pub fn storage_read_stats(&self) -> StorageReadStats {
+        self.read_stats
+    }
+
+    /// Meter execution against a new budget of `limit` gas, or stop metering
+    /// with `None`
+    pub fn set_gas_limit(&mut self, limit: Option<u64>) {
+        self.gas = limit.map(GasMeter::new);
+    }
+
+    /// Set the gas each op costs in metered mode
+    pub fn set_gas_schedule(&mut self, schedule: GasSchedule) {
+        self.gas_schedule = schedule;
+    }
+
+    /// The current gas budget, if execution is metered
+    pub fn gas_meter(&self) -> Option<&GasMeter> {
+        self.gas.as_ref()
+    }
+
+    /// Start a new, full gas budget for the next execution, if execution is
+    /// metered
+    pub(crate) fn reset_gas(&mut self) {
+        if let Some(meter) = &mut self.gas {
+            *meter = GasMeter::new(meter.limit());
+        }
+    }
+
+    /// Charge the cost of `op` to the gas budget, if execution is metered
+    pub(crate) fn charge_gas(&self, op: &Op) -> Result<(), VMError> {
+        match &self.gas {
+            Some(meter) => meter.consume(op, self.gas_schedule.cost(op)),
+            None => Ok(()),
         }
     }
 
+    /// Subscribe to `Emit` output and `EmitEvent` events as they happen
+    ///
+    /// `Emit` output is delivered with the category `"output"`. Forks share
+    /// their parent's listeners, so output from a transaction that is later
+    /// rolled back has still been streamed.
+    pub fn subscribe_events(&mut self) -> Receiver<VMEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.event_listeners.push(EventListener::Events(tx));
+        rx
+    }
+
+    /// Subscribe to output and events like `subscribe_events`, each paired
+    /// with the namespace the VM was executing in when it was emitted
+    pub fn subscribe_namespaced_events(&mut self) -> Receiver<(String, VMEvent)> {
+        let (tx, rx) = mpsc::channel();
+        self.event_listeners.push(EventListener::Namespaced(tx));
+        rx
+    }
+
+    /// Send an event to live listeners, dropping any that have gone away
+    fn notify_listeners(&mut self, event: &VMEvent) {
+        let namespace = &self.namespace;
+        self.event_listeners.retain(|listener| match listener {
+            EventListener::Events(tx) => tx.send(event.clone()).is_ok(),
+            EventListener::Namespaced(tx) => tx.send((namespace.clone(), event.clone())).is_ok(),
+        });
+    }
+
     /// Execute a storage operation with proper error handling
     pub(crate) fn storage_operation<F, T>(
         &mut self,
@@ -186,6 +488,9 @@ where
     where
         F: FnMut(&mut S, Option<&AuthContext>, &str) -> StorageResult<T>,
     {
+        if operation_name != "load_p" {
+            self.invalidate_reads();
+        }
         match &mut self.storage_backend {
             Some(backend) => {
                 let auth_context = self.auth_context.as_ref();
@@ -227,6 +532,138 @@ where
         }
     }
 
+    /// Read a key from the backend, bypassing the read cache
+    fn load_uncached(&mut self, key: &str) -> Result<(Vec<u8>, Option<VMEvent>), VMError> {
+        self.storage_operation("load_p", |backend, auth, namespace| {
+            backend.load(auth, namespace, key).map(|(data, event_opt)| {
+                // Log any event generated
+                if let Some(storage_event) = event_opt {
+                    // Create VM event
+                    let vm_event = VMEvent {
+                        category: "storage".to_string(),
+                        message: format!("load: {}", storage_event.details),
+                        timestamp: storage_event.timestamp,
+                        severity: Severity::Info,
+                    };
+                    // Return the data and event
+                    (data, Some(vm_event))
+                } else {
+                    (data, None)
+                }
+            })
+        })
+    }
+
+    /// A token amount as an exact, non-negative decimal
+    fn token_amount(amount: &TypedValue, operation: &str) -> Result<Decimal, VMError> {
+        let decimal = amount.as_decimal().map_err(|_| VMError::TypeMismatch {
+            expected: "Decimal".to_string(),
+            found: amount.type_name().to_string(),
+            operation: operation.to_string(),
+        })?;
+        if decimal.is_sign_negative() {
+            return Err(VMError::InvalidAmount {
+                amount: decimal.to_f64().unwrap_or(f64::NAN),
+            });
+        }
+        Ok(decimal)
+    }
+
+    /// Check that spending `amount` keeps the caller within its spending
+    /// limits, reporting each limit it would go over
+    ///
+    /// Proposal logic may go over a limit; anything else fails.
+    fn enforce_spending_limits(
+        &mut self,
+        resource: &str,
+        action: SpendingAction,
+        amount: Decimal,
+    ) -> Result<(), VMError> {
+        let breached =
+            self.storage_operation("check_spending_limits", |backend, auth, namespace| {
+                backend.check_spending_limits(auth, namespace, resource, action, amount)
+            })?;
+        if breached.is_empty() {
+            return Ok(());
+        }
+
+        let caller = self
+            .auth_context
+            .as_ref()
+            .map(|auth| auth.user_id_string())
+            .unwrap_or_else(|| "anonymous".to_string());
+        for limit in &breached {
+            let outcome = match &self.executing_proposal {
+                Some(proposal_id) => format!("allowed by proposal {}", proposal_id),
+                None => "refused".to_string(),
+            };
+            let message = format!(
+                "spending_limit: {} of {} by {} goes over the limit of {} a {} set for {}, {}",
+                amount,
+                resource,
+                caller,
+                limit.limit,
+                limit.window.as_str(),
+                limit.subject,
+                outcome
+            );
+            self.emit_event_with_severity("economic", &message, Severity::Warn);
+        }
+
+        if self.executing_proposal.is_some() {
+            return Ok(());
+        }
+        Err(VMError::GovernanceError(format!(
+            "{} of {} by {} goes over its spending limit; a proposal is needed to override it",
+            amount, resource, caller
+        )))
+    }
+
+    /// Set whether arithmetic, comparison and logic coerce their operands
+    pub fn set_typing_mode(&mut self, mode: TypingMode) {
+        self.typing_mode = mode;
+    }
+
+    /// Mark the code being executed as the logic of `proposal_id`, or as
+    /// ordinary code with `None`
+    ///
+    /// `tip_hash` identifies the ledger state the proposal's random sequence
+    /// is seeded with; see [`SeededRandom::tip_hash`].
+    pub fn set_executing_proposal(&mut self, proposal_id: Option<String>, tip_hash: &str) {
+        self.random = proposal_id
+            .as_deref()
+            .map(|id| SeededRandom::new(id, tip_hash));
+        self.executing_proposal = proposal_id;
+    }
+
+    /// Draw the next number from 0 up to, but not including, `max` from the
+    /// executing proposal's random sequence
+    pub fn draw_random(&mut self, max: u64) -> Result<u64, VMError> {
+        if !(1..=MAX_RANGE).contains(&max) {
+            return Err(VMError::InvalidOperation {
+                operation: format!("random {}: the range must be 1 to {}", max, MAX_RANGE),
+            });
+        }
+        let random = self.random.as_mut().ok_or_else(|| {
+            VMError::GovernanceError(
+                "random can only be used by proposal logic, whose id seeds it".to_string(),
+            )
+        })?;
+        Ok(random.next_below(max))
+    }
+
+    /// Reject operands the typing mode does not allow for `op`
+    fn check_typing(&self, op: &str, operands: &[&TypedValue]) -> Result<(), VMError> {
+        self.typing_mode.check(op, operands).map_err(|err| match err {
+            TypedValueError::TypeMismatch { expected, found } => VMError::TypeMismatch {
+                expected,
+                found,
+                operation: op.to_string(),
+            },
+            other => other.into(),
+        })
+    }
+
     /// Convert a storage event to a VM event
     fn storage_event_to_vm_event(
         &self,
@@ -237,6 +674,7 @@ where
             category: category.to_string(),
             message: format!("{}: {}", storage_event.event_type, storage_event.details),
             timestamp: storage_event.timestamp,
+            severity: Severity::Info,
         }
     }
 }
@@ -248,11 +686,13 @@ where
     /// Set the storage backend
     fn set_storage_backend(&mut self, backend: S) {
         self.storage_backend = Some(backend);
+        self.invalidate_reads();
     }
 
     /// Set the authentication context
     fn set_auth_context(&mut self, auth: AuthContext) {
         self.auth_context = Some(auth);
+        self.invalidate_reads();
     }
 
     /// Set the namespace
@@ -266,20 +706,22 @@ where
     }
 
     /// Execute a resource creation operation
-    fn execute_create_resource(&mut self, resource: &str) -> Result<(), VMError> {
+    fn execute_create_resource(
+        &mut self,
+        resource: &str,
+        policy: &ResourcePolicy,
+    ) -> Result<(), VMError> {
         // Create the resource and emit event
         self.storage_operation("create_resource", |backend, auth, namespace| {
-            backend.create_resource(auth, namespace, resource)
+            backend.create_resource(auth, namespace, resource, policy)
         })?;
 
         // Create and log an event for resource creation
         let event = VMEvent {
             category: "economic".to_string(),
             message: format!("Resource created: {}", resource),
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
+            timestamp: self.clock.unix_seconds(),
+            severity: Severity::Info,
         };
         self.events.push(event);
 
@@ -297,6 +739,8 @@ where
         let reason_str = reason
             .clone()
             .unwrap_or_else(|| "No reason provided".to_string());
+        let amount = Self::token_amount(amount, "mint")?;
+        self.enforce_spending_limits(resource, SpendingAction::Mint, amount)?;
 
         self.storage_operation("mint", |backend, auth, namespace| {
             backend
@@ -305,7 +749,7 @@ where
                     namespace,
                     resource,
                     account,
-                    amount.as_number().unwrap() as u64,
+                    amount,
                     &reason_str,
                 )
                 .map(|(_, event_opt)| {
@@ -316,6 +760,7 @@ where
                             category: "economic".to_string(),
                             message: format!("mint: {}", storage_event.details),
                             timestamp: storage_event.timestamp,
+                            severity: Severity::Info,
                         };
                         // Return VMEvent for logging outside this closure
                         Some(vm_event)
@@ -329,6 +774,10 @@ where
             if let Some(event) = event_opt {
                 self.events.push(event);
             }
+        })?;
+
+        self.storage_operation("record_spending", |backend, auth, namespace| {
+            backend.record_spending(auth, namespace, resource, SpendingAction::Mint, amount)
         })
     }
 
@@ -344,42 +793,338 @@ where
         let reason_str = reason
             .clone()
             .unwrap_or_else(|| "No reason provided".to_string());
+        let amount = Self::token_amount(amount, "transfer")?;
+        self.enforce_spending_limits(resource, SpendingAction::Transfer, amount)?;
+
+        // An account named by an identity belongs to it; anyone else must
+        // spend an allowance it has given them
+        let on_behalf = from.starts_with("did:")
+            && self.auth_context.as_ref().map(|auth| auth.user_id()) != Some(from);
+        let proposal = self.executing_proposal.clone();
 
         self.storage_operation("transfer", |backend, auth, namespace| {
-            backend
-                .transfer(
-                    auth,
-                    namespace,
-                    resource,
-                    from,
-                    to,
-                    amount.as_number().unwrap() as u64,
-                    &reason_str,
-                )
-                .map(|(_, event_opt)| {
-                    // Log any event generated
-                    if let Some(storage_event) = event_opt {
-                        // Create VM event
-                        let vm_event = VMEvent {
-                            category: "economic".to_string(),
-                            message: format!("transfer: {}", storage_event.details),
-                            timestamp: storage_event.timestamp,
-                        };
-                        // Return VMEvent for logging outside this closure
-                        Some(vm_event)
-                    } else {
-                        None
-                    }
-                })
+            let result = if on_behalf {
+                backend
+                    .transfer_from(
+                        auth,
+                        namespace,
+                        resource,
+                        from,
+                        to,
+                        amount,
+                        &reason_str,
+                        proposal.as_deref(),
+                    )
+                    .map(|(_, event_opt)| event_opt)
+            } else {
+                backend
+                    .transfer(
+                        auth,
+                        namespace,
+                        resource,
+                        from,
+                        to,
+                        amount,
+                        &reason_str,
+                    )
+                    .map(|(_, event_opt)| event_opt)
+            };
+            result.map(|event_opt| {
+                // Log any event generated
+                if let Some(storage_event) = event_opt {
+                    // Create VM event
+                    let vm_event = VMEvent {
+                        category: "economic".to_string(),
+                        message: format!(
+                            "{}: {}",
+                            storage_event.event_type, storage_event.details
+                        ),
+                        timestamp: storage_event.timestamp,
+                        severity: Severity::Info,
+                    };
+                    // Return VMEvent for logging outside this closure
+                    Some(vm_event)
+                } else {
+                    None
+                }
+            })
         })
         .map(|event_opt| {
             // Log the event if one was generated
             if let Some(event) = event_opt {
                 self.events.push(event);
             }
+        })?;
+
+        self.storage_operation("record_spending", |backend, auth, namespace| {
+            backend.record_spending(auth, namespace, resource, SpendingAction::Transfer, amount)
         })
     }
 
+    fn execute_approve(
+        &mut self,
+        resource: &str,
+        spender: &str,
+        limit: &TypedValue,
+    ) -> Result<(), VMError> {
+        let limit = Self::token_amount(limit, "approve")?;
+
+        let (_, event) = self.storage_operation("approve", |backend, auth, namespace| {
+            backend.approve(auth, namespace, resource, spender, limit)
+        })?;
+        if let Some(storage_event) = event {
+            let vm_event = self.storage_event_to_vm_event(&storage_event, "economic");
+            self.events.push(vm_event);
+        }
+        Ok(())
+    }
+
+    fn execute_allowance(
+        &mut self,
+        resource: &str,
+        owner: &str,
+        spender: &str,
+    ) -> Result<TypedValue, VMError> {
+        self.storage_operation("get_allowance", |backend, auth, namespace| {
+            backend.get_allowance(auth, namespace, resource, owner, spender)
+        })
+        .map(TypedValue::Decimal)
+    }
+
+    /// Execute a spending limit change, which only proposal logic may make
+    fn execute_set_spending_limit(
+        &mut self,
+        resource: &str,
+        subject: &str,
+        action: SpendingAction,
+        window: SpendingWindow,
+        limit: &TypedValue,
+    ) -> Result<(), VMError> {
+        let Some(proposal_id) = self.executing_proposal.clone() else {
+            return Err(VMError::GovernanceError(
+                "Spending limits can only be changed by executing a proposal".to_string(),
+            ));
+        };
+        let limit = SpendingLimit {
+            resource: resource.to_string(),
+            subject: subject.to_string(),
+            action,
+            window,
+            limit: Self::token_amount(limit, "set_spending_limit")?,
+            proposal_id: Some(proposal_id),
+        };
+
+        let (_, event) = self
+            .storage_operation("set_spending_limit", |backend, auth, namespace| {
+                backend.set_spending_limit(auth, namespace, &limit)
+            })?;
+        if let Some(storage_event) = event {
+            let vm_event = self.storage_event_to_vm_event(&storage_event, "economic");
+            self.events.push(vm_event);
+        }
+        Ok(())
+    }
+
+    /// Execute an exchange rate change, which only proposal logic may make
+    fn execute_set_exchange_rate(
+        &mut self,
+        from_resource: &str,
+        to_resource: &str,
+        rate: &TypedValue,
+        reserve: &str,
+        max_amount: Option<&TypedValue>,
+    ) -> Result<(), VMError> {
+        let Some(proposal_id) = self.executing_proposal.clone() else {
+            return Err(VMError::GovernanceError(
+                "Exchange rates can only be changed by executing a proposal".to_string(),
+            ));
+        };
+        let rate = ExchangeRate {
+            from_resource: from_resource.to_string(),
+            to_resource: to_resource.to_string(),
+            rate: Self::token_amount(rate, "set_exchange_rate")?,
+            reserve: reserve.to_string(),
+            max_amount: max_amount
+                .map(|max| Self::token_amount(max, "set_exchange_rate"))
+                .transpose()?,
+            proposal_id: Some(proposal_id),
+        };
+
+        let (_, event) = self
+            .storage_operation("set_exchange_rate", |backend, auth, namespace| {
+                backend.set_exchange_rate(auth, namespace, &rate)
+            })?;
+        if let Some(storage_event) = event {
+            let vm_event = self.storage_event_to_vm_event(&storage_event, "economic");
+            self.events.push(vm_event);
+        }
+        Ok(())
+    }
+
+    fn execute_exchange(
+        &mut self,
+        from_resource: &str,
+        to_resource: &str,
+        account: &str,
+        amount: &TypedValue,
+        min_received: &TypedValue,
+    ) -> Result<TypedValue, VMError> {
+        let amount = Self::token_amount(amount, "exchange")?;
+        let min_received = Self::token_amount(min_received, "exchange")?;
+
+        // Only an identity may exchange what its own account holds
+        let caller = self.auth_context.as_ref().map(|auth| auth.user_id());
+        if account.starts_with("did:") && caller != Some(account) {
+            return Err(VMError::PermissionDenied {
+                user: caller.unwrap_or("anonymous").to_string(),
+                action: "exchange".to_string(),
+                resource: format!("resources/{}/accounts/{}", from_resource, account),
+            });
+        }
+
+        let (received, event) =
+            self.storage_operation("exchange", |backend, auth, namespace| {
+                backend.exchange(
+                    auth,
+                    namespace,
+                    from_resource,
+                    to_resource,
+                    account,
+                    amount,
+                    min_received,
+                )
+            })?;
+        if let Some(storage_event) = event {
+            let vm_event = self.storage_event_to_vm_event(&storage_event, "economic");
+            self.events.push(vm_event);
+        }
+        Ok(TypedValue::Decimal(received))
+    }
+
+    fn execute_exchange_rate(
+        &mut self,
+        from_resource: &str,
+        to_resource: &str,
+    ) -> Result<TypedValue, VMError> {
+        self.storage_operation("get_exchange_rate", |backend, auth, namespace| {
+            backend.get_exchange_rate(auth, namespace, from_resource, to_resource)
+        })
+        .map(|rate| TypedValue::Decimal(rate.map_or(Decimal::ZERO, |rate| rate.rate)))
+    }
+
+    fn execute_approve_wasm_module(
+        &mut self,
+        hash: &str,
+        capabilities: &[WasmCapability],
+    ) -> Result<(), VMError> {
+        let Some(proposal_id) = self.executing_proposal.clone() else {
+            return Err(VMError::GovernanceError(
+                "WASM modules can only be approved by executing a proposal".to_string(),
+            ));
+        };
+        let approval = WasmModuleApproval {
+            hash: hash.to_string(),
+            capabilities: capabilities.to_vec(),
+            proposal_id: proposal_id.clone(),
+            approved_at: self.clock.unix_seconds(),
+        };
+
+        self.storage_operation("approve_wasm_module", |backend, auth, namespace| {
+            wasm::approve_module(backend, auth, namespace, &approval)
+        })?;
+        self.emit_event(
+            "wasm",
+            &format!(
+                "Proposal {} approved module {} with [{}]",
+                proposal_id,
+                hash,
+                capabilities
+                    .iter()
+                    .map(WasmCapability::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        );
+        Ok(())
+    }
+
+    fn execute_onboard_member(
+        &mut self,
+        did: &str,
+        username: &str,
+        roles: &[String],
+    ) -> Result<(), VMError> {
+        let Some(proposal_id) = self.executing_proposal.clone() else {
+            return Err(VMError::GovernanceError(
+                "Members can only be onboarded by executing a proposal".to_string(),
+            ));
+        };
+        let record = self.storage_operation("onboard_member", |backend, auth, namespace| {
+            membership::onboard_member(backend, auth, namespace, &proposal_id, did, username, roles)
+        })?;
+        self.emit_event(
+            "membership",
+            &format!(
+                "Proposal {} onboarded {} ({}) with [{}]",
+                proposal_id,
+                did,
+                username,
+                record.roles.join(", ")
+            ),
+        );
+        Ok(())
+    }
+
+    fn execute_offboard_member(&mut self, did: &str) -> Result<(), VMError> {
+        let Some(proposal_id) = self.executing_proposal.clone() else {
+            return Err(VMError::GovernanceError(
+                "Members can only be offboarded by executing a proposal".to_string(),
+            ));
+        };
+        self.storage_operation("offboard_member", |backend, auth, namespace| {
+            membership::offboard_member(backend, auth, namespace, &proposal_id, did)
+        })?;
+        self.emit_event(
+            "membership",
+            &format!("Proposal {} offboarded {}", proposal_id, did),
+        );
+        Ok(())
+    }
+
+    fn execute_call_wasm(
+        &mut self,
+        hash: &str,
+        function: &str,
+        args: &[f64],
+    ) -> Result<TypedValue, VMError> {
+        self.invalidate_reads();
+        let backend = self
+            .storage_backend
+            .as_mut()
+            .ok_or(VMError::StorageUnavailable)?;
+        let outcome = wasm::call_module(
+            backend,
+            self.auth_context.as_ref(),
+            &self.namespace,
+            self.executing_proposal.as_deref(),
+            hash,
+            function,
+            args,
+        )?;
+
+        for message in &outcome.logs {
+            self.emit_event("wasm", &format!("{}: {}", hash, message));
+        }
+        self.emit_event(
+            "wasm",
+            &format!(
+                "Called {}::{} using {} fuel",
+                hash, function, outcome.fuel_used
+            ),
+        );
+        Ok(TypedValue::Number(outcome.result))
+    }
+
     /// Execute a burn operation
     fn execute_burn(
         &mut self,
@@ -391,6 +1136,7 @@ where
         let reason_str = reason
             .clone()
             .unwrap_or_else(|| "No reason provided".to_string());
+        let amount = Self::token_amount(amount, "burn")?;
 
         self.storage_operation("burn", |backend, auth, namespace| {
             backend
@@ -399,7 +1145,7 @@ where
                     namespace,
                     resource,
                     account,
-                    amount.as_number().unwrap() as u64,
+                    amount,
                     &reason_str,
                 )
                 .map(|(_, event_opt)| {
@@ -410,6 +1156,7 @@ where
                             category: "economic".to_string(),
                             message: format!("burn: {}", storage_event.details),
                             timestamp: storage_event.timestamp,
+                            severity: Severity::Info,
                         };
                         // Return VMEvent for logging outside this closure
                         Some(vm_event)
@@ -439,11 +1186,12 @@ where
                             category: "economic".to_string(),
                             message: format!("balance: {}", storage_event.details),
                             timestamp: storage_event.timestamp,
+                            severity: Severity::Info,
                         };
                         // Push the event to the VM event log
-                        (balance as f64, Some(vm_event))
+                        (balance, Some(vm_event))
                     } else {
-                        (balance as f64, None)
+                        (balance, None)
                     }
                 })
         })
@@ -453,8 +1201,216 @@ where
                 self.events.push(event);
             }
             // Return the balance as a TypedValue
-            TypedValue::Number(balance)
+            TypedValue::Decimal(balance)
+        })
+    }
+
+    /// Execute a credit limit change, which only proposal logic may make
+    fn execute_set_credit_limit(
+        &mut self,
+        resource: &str,
+        creditor: &str,
+        debtor: &str,
+        limit: &TypedValue,
+    ) -> Result<(), VMError> {
+        let Some(proposal_id) = self.executing_proposal.clone() else {
+            return Err(VMError::GovernanceError(
+                "Credit limits can only be changed by executing a proposal".to_string(),
+            ));
+        };
+        let limit = Self::token_amount(limit, "set_credit_limit")?;
+
+        let event = self.storage_operation("set_credit_limit", |backend, auth, namespace| {
+            backend
+                .set_credit_limit(
+                    auth,
+                    namespace,
+                    resource,
+                    creditor,
+                    debtor,
+                    limit,
+                    Some(&proposal_id),
+                )
+                .map(|(_, event_opt)| event_opt)
+        })?;
+        if let Some(storage_event) = event {
+            self.events.push(VMEvent {
+                category: "economic".to_string(),
+                message: format!("set_credit_limit: {}", storage_event.details),
+                timestamp: storage_event.timestamp,
+                severity: Severity::Info,
+            });
+        }
+        Ok(())
+    }
+
+    /// Execute a credit limit query operation
+    fn execute_credit_limit(
+        &mut self,
+        resource: &str,
+        account: &str,
+    ) -> Result<TypedValue, VMError> {
+        self.storage_operation("get_credit_limit", |backend, auth, namespace| {
+            backend.get_credit_limit(auth, namespace, resource, account)
         })
+        .map(TypedValue::Decimal)
+    }
+
+    /// Execute an escrow lock under a proposal
+    fn execute_escrow_lock(
+        &mut self,
+        proposal_id: &str,
+        resource: &str,
+        from: &str,
+        to: &str,
+        amount: &TypedValue,
+    ) -> Result<(), VMError> {
+        let amount = Self::token_amount(amount, "escrow_lock")?;
+
+        let event = self.storage_operation("escrow_lock", |backend, auth, namespace| {
+            backend
+                .lock_escrow(auth, namespace, proposal_id, resource, from, to, amount)
+                .map(|(_, event_opt)| event_opt)
+        })?;
+        if let Some(storage_event) = event {
+            self.events.push(VMEvent {
+                category: "economic".to_string(),
+                message: format!("escrow_lock: {}", storage_event.details),
+                timestamp: storage_event.timestamp,
+                severity: Severity::Info,
+            });
+        }
+        Ok(())
+    }
+
+    /// Settle a proposal's locked escrows, if its outcome allows `outcome`
+    fn execute_escrow_settle(
+        &mut self,
+        proposal_id: &str,
+        outcome: EscrowOutcome,
+    ) -> Result<(), VMError> {
+        let decided = self.storage_operation("escrow_settle", |backend, auth, namespace| {
+            proposal_escrow_outcome(backend, auth, namespace, proposal_id)
+        })?;
+        if decided != Some(outcome) {
+            return Err(VMError::GovernanceError(match outcome {
+                EscrowOutcome::Release => format!(
+                    "Escrow for proposal '{}' can only be released once it is executed",
+                    proposal_id
+                ),
+                EscrowOutcome::Refund => format!(
+                    "Escrow for proposal '{}' can only be refunded once it is rejected or expired",
+                    proposal_id
+                ),
+            }));
+        }
+
+        let event = self.storage_operation("escrow_settle", |backend, auth, namespace| {
+            backend
+                .settle_escrows(auth, namespace, proposal_id, outcome)
+                .map(|(_, event_opt)| event_opt)
+        })?;
+        if let Some(storage_event) = event {
+            self.events.push(VMEvent {
+                category: "economic".to_string(),
+                message: format!("{}: {}", storage_event.event_type, storage_event.details),
+                timestamp: storage_event.timestamp,
+                severity: Severity::Info,
+            });
+        }
+        Ok(())
+    }
+
+    /// Create a bounty funded from an account, returning its record
+    fn execute_create_bounty(
+        &mut self,
+        id: &str,
+        resource: &str,
+        funder: &str,
+        amount: &TypedValue,
+        verification: &BountyVerification,
+        description: &str,
+    ) -> Result<Bounty, VMError> {
+        let amount = Self::token_amount(amount, "create_bounty")?;
+
+        let (bounty, event) =
+            self.storage_operation("create_bounty", |backend, auth, namespace| {
+                backend.create_bounty(
+                    auth,
+                    namespace,
+                    id,
+                    resource,
+                    funder,
+                    amount,
+                    verification.clone(),
+                    description,
+                )
+            })?;
+        if let Some(storage_event) = event {
+            let vm_event = self.storage_event_to_vm_event(&storage_event, "economic");
+            self.events.push(vm_event);
+        }
+        Ok(bounty)
+    }
+
+    /// Claim a bounty as the calling identity, returning its record
+    fn execute_claim_bounty(&mut self, id: &str, evidence: &str) -> Result<Bounty, VMError> {
+        let (bounty, event) = self
+            .storage_operation("claim_bounty", |backend, auth, namespace| {
+                backend.claim_bounty(auth, namespace, id, evidence)
+            })?;
+        if let Some(storage_event) = event {
+            let vm_event = self.storage_event_to_vm_event(&storage_event, "economic");
+            self.events.push(vm_event);
+        }
+        Ok(bounty)
+    }
+
+    /// Verify the claim on a bounty as the calling identity, returning its
+    /// record
+    fn execute_verify_bounty(&mut self, id: &str, approve: bool) -> Result<Bounty, VMError> {
+        let (bounty, event) = self
+            .storage_operation("verify_bounty", |backend, auth, namespace| {
+                backend.verify_bounty(auth, namespace, id, approve)
+            })?;
+        if let Some(storage_event) = event {
+            let vm_event = self.storage_event_to_vm_event(&storage_event, "economic");
+            self.events.push(vm_event);
+        }
+        Ok(bounty)
+    }
+
+    /// Cancel an unclaimed bounty, returning its record
+    fn execute_cancel_bounty(&mut self, id: &str) -> Result<Bounty, VMError> {
+        let (bounty, event) = self
+            .storage_operation("cancel_bounty", |backend, auth, namespace| {
+                backend.cancel_bounty(auth, namespace, id)
+            })?;
+        if let Some(storage_event) = event {
+            let vm_event = self.storage_event_to_vm_event(&storage_event, "economic");
+            self.events.push(vm_event);
+        }
+        Ok(bounty)
+    }
+
+    fn execute_distribute(
+        &mut self,
+        resource: &str,
+        pool: &str,
+        weight_key: &str,
+        amount: &TypedValue,
+    ) -> Result<Distribution, VMError> {
+        let amount = Self::token_amount(amount, "distribute")?;
+
+        let (distribution, event) =
+            self.storage_operation("distribute", |backend, auth, namespace| {
+                backend.distribute(auth, namespace, resource, pool, weight_key, amount)
+            })?;
+        if let Some(storage_event) = event {
+            let vm_event = self.storage_event_to_vm_event(&storage_event, "economic");
+            self.events.push(vm_event);
+        }
+        Ok(distribution)
     }
 
     /// Execute increment reputation for an identity
@@ -516,6 +1472,7 @@ where
                                     category: "reputation".to_string(),
                                     message: format!("get_reputation: {}", storage_event.details),
                                     timestamp: storage_event.timestamp,
+                                    severity: Severity::Info,
                                 };
                                 // Return current reputation and event
                                 (current_rep, Some(vm_event))
@@ -546,6 +1503,7 @@ where
                                 category: "reputation".to_string(),
                                 message: format!("set_reputation: {}", storage_event.details),
                                 timestamp: storage_event.timestamp,
+                                severity: Severity::Info,
                             };
                             // Return VMEvent for logging outside this closure
                             Some(vm_event)
@@ -578,6 +1536,7 @@ where
                             category: "storage".to_string(),
                             message: format!("store: {}", storage_event.details),
                             timestamp: storage_event.timestamp,
+                            severity: Severity::Info,
                         };
                         // Return the event
                         Some(vm_event)
@@ -600,23 +1559,35 @@ where
         key: &str,
         missing_key_behavior: MissingKeyBehavior,
     ) -> Result<TypedValue, VMError> {
-        match self.storage_operation("load_p", |backend, auth, namespace| {
-            backend.load(auth, namespace, key).map(|(data, event_opt)| {
-                // Log any event generated
-                if let Some(storage_event) = event_opt {
-                    // Create VM event
-                    let vm_event = VMEvent {
-                        category: "storage".to_string(),
-                        message: format!("load: {}", storage_event.details),
-                        timestamp: storage_event.timestamp,
-                    };
-                    // Return the data and event
-                    (data, Some(vm_event))
-                } else {
-                    (data, None)
+        let cache_key = (self.namespace.clone(), key.to_string());
+        let cached = self.read_cache.get(&cache_key).cloned();
+        let loaded = match cached {
+            Some(Some(data)) => {
+                self.read_stats.cache_hits += 1;
+                Ok((data, None))
+            }
+            Some(None) => {
+                self.read_stats.cache_hits += 1;
+                Err(VMError::StorageError {
+                    details: format!("Key '{}' not found during load_p", key),
+                })
+            }
+            None => {
+                self.read_stats.backend_reads += 1;
+                let loaded = self.load_uncached(key);
+                match &loaded {
+                    Ok((data, _)) => {
+                        self.read_cache.insert(cache_key, Some(data.clone()));
+                    }
+                    Err(VMError::StorageError { details }) if details.contains("not found") => {
+                        self.read_cache.insert(cache_key, None);
+                    }
+                    Err(_) => {}
                 }
-            })
-        }) {
+                loaded
+            }
+        };
+        match loaded {
             Ok(result) => {
                 // Process any events that were returned
                 if let Some(event) = result.1 {
@@ -648,6 +1619,7 @@ where
             Err(VMError::StorageError { details: ref err_msg }) if err_msg.contains("not found") => {
                 match missing_key_behavior {
                     MissingKeyBehavior::Default => Ok(TypedValue::Number(0.0)),
+                    MissingKeyBehavior::Null => Ok(TypedValue::Null),
                     MissingKeyBehavior::Error => Err(VMError::StorageError {
                         details: format!(
                             "Key '{}' not found during load_p",
@@ -673,7 +1645,16 @@ where
                     namespace: self.namespace.clone(),
                     output: self.output.clone(),
                     events: Vec::new(), // Start with empty events, we'll merge later if committed
+                    event_listeners: self.event_listeners.clone(),
                     transaction_active: true,
+                    typing_mode: self.typing_mode,
+                    executing_proposal: self.executing_proposal.clone(),
+                    random: self.random.clone(),
+                    clock: self.clock.clone(),
+                    read_cache: HashMap::new(),
+                    read_stats: StorageReadStats::default(),
+                    gas: self.gas.clone(),
+                    gas_schedule: self.gas_schedule,
                 };
 
                 if let Some(backend) = &mut forked.storage_backend {
@@ -723,6 +1704,7 @@ where
             });
         }
 
+        self.invalidate_reads();
         if let Some(backend) = &mut self.storage_backend {
             backend.rollback_transaction().map_err(|e| {
                 VMError::StorageError {
@@ -739,21 +1721,31 @@ where
     fn emit(&mut self, message: &str) {
         self.output.push_str(message);
         self.output.push('\n');
+
+        if !self.event_listeners.is_empty() {
+            let event = VMEvent {
+                category: "output".to_string(),
+                message: message.to_string(),
+                timestamp: self.clock.unix_seconds(),
+                severity: Severity::Info,
+            };
+            self.notify_listeners(&event);
+        }
     }
 
-    /// Emit an event with the given category and message
-    fn emit_event(&mut self, category: &str, message: &str) {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+    /// Emit an event with the given category, message and severity
+    fn emit_event_with_severity(&mut self, category: &str, message: &str, severity: Severity) {
+        let now = self.clock.unix_seconds();
 
         let event = VMEvent {
             category: category.to_string(),
             message: message.to_string(),
             timestamp: now,
+            severity,
         };
 
+        self.notify_listeners(&event);
+        crate::events::dispatch_vm_event(&event);
         self.events.push(event);
     }
 
@@ -774,6 +1766,7 @@ where
 
     /// Execute arithmetic operations
     fn execute_arithmetic(&self, a: &TypedValue, b: &TypedValue, op: &str) -> Result<TypedValue, VMError> {
+        self.check_typing(op, &[a, b])?;
         // Use TypedValue methods directly instead of extracting f64 values
         match op {
             "add" => a.add(b).map_err(|err| match err {
@@ -870,6 +1863,7 @@ where
 
     /// Execute comparison operations
     fn execute_comparison(&self, a: &TypedValue, b: &TypedValue, op: &str) -> Result<TypedValue, VMError> {
+        self.check_typing(op, &[a, b])?;
         match op {
             "eq" => a.equals(b).map_err(|err| match err {
                 TypedValueError::CoercionError { from, to } => VMError::TypeMismatch {
@@ -918,6 +1912,7 @@ where
 
     /// Execute logical operations
     fn execute_logical(&self, a: &TypedValue, op: &str) -> Result<TypedValue, VMError> {
+        self.check_typing(op, &[a])?;
         match op {
             "not" => a.logical_not().map_err(|err| match err {
                 TypedValueError::CoercionError { from, to } => VMError::TypeMismatch {
@@ -942,6 +1937,7 @@ where
 
     /// Execute binary logical operations
     fn execute_binary_logical(&self, a: &TypedValue, b: &TypedValue, op: &str) -> Result<TypedValue, VMError> {
+        self.check_typing(op, &[a, b])?;
         match op {
             "and" => a.logical_and(b).map_err(|err| match err {
                 TypedValueError::CoercionError { from, to } => VMError::TypeMismatch {
@@ -0,0 +1,202 @@
+//! Gas metering for VM execution
+//!
+//! In metered mode every op the VM runs, including each op of a loop body on
+//! every pass, costs gas according to a [`GasSchedule`]. The VM stops with
+//! [`VMError::OutOfGas`] before running an op that would take the total past
+//! the budget set with `VM::set_gas_limit`, so a runaway governance program
+//! fails instead of holding the VM. Every execution starts with the full
+//! budget, and the gas it used and had left can be read after the run,
+//! whether it finished or not.
+//!
+//! Costs follow the op's side effects, as [`crate::vm::sandbox::categories`]
+//! classifies them: economic ops cost most, then identity changes and
+//! storage writes, then storage reads, and everything else costs the base
+//! amount.
+
+use crate::vm::errors::VMError;
+use crate::vm::sandbox::{self, OpCategory};
+use crate::vm::types::Op;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Gas each kind of op costs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GasSchedule {
+    /// Ops without side effects, such as arithmetic and control flow
+    pub base: u64,
+    /// Reading storage, balances or identities
+    pub storage_read: u64,
+    /// Writing storage or approving WASM modules
+    pub storage_write: u64,
+    /// Adding, removing or rewarding members
+    pub identity: u64,
+    /// Creating, moving or limiting resources and bounties
+    pub economic: u64,
+    /// Calling a WASM module, whatever the module then does
+    pub wasm: u64,
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        Self {
+            base: 1,
+            storage_read: 5,
+            storage_write: 20,
+            identity: 20,
+            economic: 50,
+            wasm: 100,
+        }
+    }
+}
+
+impl GasSchedule {
+    /// The gas `op` costs
+    pub fn cost(&self, op: &Op) -> u64 {
+        if let Op::CallWasm { .. } = op {
+            return self.wasm;
+        }
+        let categories = sandbox::categories(op);
+        if categories.contains(&OpCategory::Economic) {
+            self.economic
+        } else if categories.contains(&OpCategory::Identity) {
+            self.identity
+        } else if categories.contains(&OpCategory::StorageWrite) {
+            self.storage_write
+        } else if reads_storage(op) {
+            self.storage_read
+        } else {
+            self.base
+        }
+    }
+}
+
+/// Whether `op` reads persistent storage without writing it
+fn reads_storage(op: &Op) -> bool {
+    matches!(
+        op,
+        Op::LoadP(_)
+            | Op::LoadVersionP { .. }
+            | Op::ListVersionsP(_)
+            | Op::DiffVersionsP { .. }
+            | Op::Balance { .. }
+            | Op::CreditLimit { .. }
+            | Op::Allowance { .. }
+            | Op::ExchangeRate { .. }
+            | Op::GetIdentity(_)
+            | Op::VerifyIdentity { .. }
+            | Op::CheckMembership { .. }
+            | Op::CheckDelegation { .. }
+    )
+}
+
+/// A gas budget and how much of it has been used
+///
+/// Clones share the gas used, so a forked VM draws from the same budget as
+/// the VM it was forked from until either starts a new execution.
+#[derive(Debug, Clone)]
+pub struct GasMeter {
+    limit: u64,
+    used: Arc<AtomicU64>,
+}
+
+impl GasMeter {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used())
+    }
+
+    /// Use `cost` gas to run `op`, or fail without using any if that would
+    /// exceed the limit
+    pub fn consume(&self, op: &Op, cost: u64) -> Result<(), VMError> {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(cost).filter(|&total| total <= self.limit)
+            })
+            .map(|_| ())
+            .map_err(|used| VMError::OutOfGas {
+                op: op.to_string(),
+                cost,
+                remaining: self.limit.saturating_sub(used),
+                limit: self.limit,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::implementations::in_memory::InMemoryStorage;
+    use crate::typed::TypedValue;
+    use crate::vm::VM;
+
+    #[test]
+    fn test_runaway_loop_runs_out_of_gas() {
+        let schedule = GasSchedule::default();
+        assert_eq!(schedule.cost(&Op::Push(TypedValue::Number(1.0))), 1);
+        assert_eq!(schedule.cost(&Op::LoadP("x".to_string())), 5);
+        assert_eq!(schedule.cost(&Op::StoreP("x".to_string())), 20);
+
+        let mut vm = VM::<InMemoryStorage>::new();
+        vm.set_gas_limit(Some(100));
+        let ops = vec![
+            Op::Push(TypedValue::Number(1.0)),
+            Op::While {
+                condition: vec![Op::Dup],
+                body: vec![Op::Push(TypedValue::Number(1.0)), Op::Pop],
+            },
+        ];
+        let result = vm.execute(&ops);
+        assert!(matches!(result, Err(VMError::OutOfGas { limit: 100, .. })));
+        assert!(vm.gas_used().unwrap() <= 100);
+        assert_eq!(vm.gas_remaining(), Some(100 - vm.gas_used().unwrap()));
+
+        // A new limit starts a new budget
+        vm.set_gas_limit(Some(10));
+        vm.execute(&[
+            Op::Push(TypedValue::Number(2.0)),
+            Op::Push(TypedValue::Number(3.0)),
+            Op::Add,
+        ])
+        .unwrap();
+        assert_eq!(vm.gas_used(), Some(3));
+        vm.set_gas_limit(None);
+        assert_eq!(vm.gas_used(), None);
+    }
+
+    #[test]
+    fn test_each_execution_starts_with_the_full_budget() {
+        let mut vm = VM::<InMemoryStorage>::new();
+        vm.set_gas_limit(Some(5));
+        let program = [
+            Op::Push(TypedValue::Number(2.0)),
+            Op::Push(TypedValue::Number(3.0)),
+            Op::Add,
+            Op::Pop,
+        ];
+
+        vm.execute(&program).unwrap();
+        assert_eq!(vm.gas_used(), Some(4));
+        assert_eq!(vm.gas_remaining(), Some(1));
+
+        // Would run out of gas if the first program's gas still counted
+        vm.execute(&program).unwrap();
+        assert_eq!(vm.gas_used(), Some(4));
+        assert_eq!(vm.gas_remaining(), Some(1));
+    }
+}
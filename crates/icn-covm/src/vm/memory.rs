@@ -144,6 +144,9 @@ impl VMMemory {
         // Convert TypedValue to string representation for parameters
         let string_value = match &value {
             TypedValue::Number(n) => n.to_string(),
+            TypedValue::Decimal(d) => d.to_string(),
+            TypedValue::Bytes(b) => format!("0x{}", hex::encode(b)),
+            TypedValue::Timestamp(_) => value.to_string(),
             TypedValue::Boolean(b) => b.to_string(),
             TypedValue::String(s) => s.clone(),
             TypedValue::Null => "null".to_string(),
@@ -25,6 +25,20 @@
 //!
 //! - **typed_trace.rs**: Provides utilities for tracing and debugging VM execution.
 //!
+//! - **wasm.rs**: Runs governance-approved WASM modules for `CallWasm`, with host functions
+//!   limited to the capabilities their approval grants.
+//!
+//! - **random.rs**: Seeds the numbers `Random` draws from the executing proposal and the DAG
+//!   ledger's tips, so every federation node draws the same ones.
+//!
+//! - **debugger.rs**: Steps through a program op by op, pausing on breakpoints to let callers
+//!   inspect the stack and memory.
+//!
+//! - **gas.rs**: Meters execution, charging each op gas by its side effects against a budget.
+//!
+//! - **sandbox.rs**: Names the categories of side effects, such as economic ops and storage
+//!   writes, that a sandboxed execution may have.
+//!
 //! ## Benefits of Modular Design
 //!
 //! This modular design provides significant benefits:
@@ -49,19 +63,27 @@
 //! For more detailed information, see the documentation for each component.
 
 // Module declarations
+pub mod debugger;
 pub mod errors;
 pub mod execution;
+pub mod gas;
 pub mod memory;
 pub mod ops;
+pub mod random;
+pub mod sandbox;
 pub mod stack;
 pub mod types;
 mod vm;
 pub mod typed_trace;
+pub mod wasm;
 
 // Re-export main VM types and components
+pub use debugger::{Breakpoint, DebugState, Inspection};
 pub use errors::VMError;
-pub use execution::{ExecutorOps, VMExecution};
+pub use execution::{ExecutorOps, StorageReadStats, VMExecution};
+pub use gas::{GasMeter, GasSchedule};
 pub use memory::{MemoryScope, VMMemory};
+pub use sandbox::{OpCategory, SandboxProfile};
 pub use stack::{StackOps, VMStack};
 pub use types::{CallFrame, LoopControl, Op, VMEvent};
 pub use vm::VM;
@@ -84,4 +106,7 @@ pub enum MissingKeyBehavior {
     
     /// Return an error when a key is not found
     Error,
+
+    /// Return Null when a key is not found
+    Null,
 }
@@ -6,15 +6,19 @@
 //! - Resource creation and management
 //! - Minting, transferring, and burning resource units
 //! - Querying resource balances
+//! - Spending limits on transfers and mints
 //! - Authorization and validation of governance actions
 
 use crate::storage::auth::AuthContext;
 use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::resource::{ResourcePolicy, SpendingAction};
 use crate::storage::traits::Storage;
 use crate::typed::TypedValue;
 use crate::vm::errors::VMError;
 use crate::vm::ops::GovernanceOpHandler;
 use crate::vm::ops::storage::StorageOpImpl;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 
 use std::fmt::Debug;
 use std::marker::{Send, Sync};
@@ -48,19 +52,49 @@ where
         }
     }
 
-    /// Extract a numeric value from a TypedValue, with validation
-    fn extract_numeric_amount(&self, amount: &TypedValue) -> Result<f64, VMError> {
-        match amount.as_number() {
-            Ok(num) if num >= 0.0 => Ok(num),
-            Ok(num) => Err(VMError::InvalidAmount { amount: num }),
+    /// Extract an exact token amount from a TypedValue, with validation
+    fn extract_numeric_amount(&self, amount: &TypedValue) -> Result<Decimal, VMError> {
+        match amount.as_decimal() {
+            Ok(num) if !num.is_sign_negative() => Ok(num),
+            Ok(num) => Err(VMError::InvalidAmount {
+                amount: num.to_f64().unwrap_or(f64::NAN),
+            }),
             Err(_) => Err(VMError::TypeMismatch {
-                expected: "Number".to_string(),
+                expected: "Decimal".to_string(),
                 found: amount.type_name().to_string(),
                 operation: "resource operation".to_string(),
             }),
         }
     }
 
+    /// Refuse spending that takes the caller over one of its spending
+    /// limits
+    ///
+    /// This handler never runs proposal logic, so it cannot override them.
+    fn enforce_spending_limits(
+        &mut self,
+        resource: &str,
+        action: SpendingAction,
+        amount: Decimal,
+    ) -> Result<(), VMError> {
+        let breached =
+            self.storage_operation("check_spending_limits", |storage, auth, namespace| {
+                storage.check_spending_limits(auth, namespace, resource, action, amount)
+            })?;
+        match breached.first() {
+            Some(limit) => Err(VMError::GovernanceError(format!(
+                "{} {} of {} goes over the limit of {} a {} set for {}; a proposal is needed to override it",
+                action.as_str(),
+                amount,
+                resource,
+                limit.limit,
+                limit.window.as_str(),
+                limit.subject
+            ))),
+            None => Ok(()),
+        }
+    }
+
     /// Execute a storage operation with proper error handling
     pub(crate) fn storage_operation<F, T>(
         &mut self,
@@ -87,9 +121,13 @@ impl<S> GovernanceOpHandler<S> for GovernanceOpImpl<S>
 where
     S: Storage + Send + Sync + Clone + Debug + 'static,
 {
-    fn execute_create_resource(&mut self, resource: &str) -> Result<(), VMError> {
+    fn execute_create_resource(
+        &mut self,
+        resource: &str,
+        policy: &ResourcePolicy,
+    ) -> Result<(), VMError> {
         self.storage_operation("create_resource", |storage, auth, namespace| {
-            storage.create_resource(resource, auth, namespace)
+            storage.create_resource(auth, namespace, resource, policy)
         })
     }
 
@@ -103,6 +141,8 @@ where
         // Extract and validate numeric amount
         let numeric_amount = self.extract_numeric_amount(amount)?;
 
+        self.enforce_spending_limits(resource, SpendingAction::Mint, numeric_amount)?;
+
         // Execute the mint operation
         self.storage_operation("mint", |storage, auth, namespace| {
             storage.mint(
@@ -113,6 +153,16 @@ where
                 auth,
                 namespace,
             )
+        })?;
+
+        self.storage_operation("record_spending", |storage, auth, namespace| {
+            storage.record_spending(
+                auth,
+                namespace,
+                resource,
+                SpendingAction::Mint,
+                numeric_amount,
+            )
         })
     }
 
@@ -127,6 +177,8 @@ where
         // Extract and validate numeric amount
         let numeric_amount = self.extract_numeric_amount(amount)?;
 
+        self.enforce_spending_limits(resource, SpendingAction::Transfer, numeric_amount)?;
+
         // Execute the transfer operation
         self.storage_operation("transfer", |storage, auth, namespace| {
             storage.transfer(
@@ -138,6 +190,16 @@ where
                 auth,
                 namespace,
             )
+        })?;
+
+        self.storage_operation("record_spending", |storage, auth, namespace| {
+            storage.record_spending(
+                auth,
+                namespace,
+                resource,
+                SpendingAction::Transfer,
+                numeric_amount,
+            )
         })
     }
 
@@ -169,7 +231,7 @@ where
             storage.balance(resource, account, auth, namespace)
         })?;
         
-        Ok(TypedValue::Number(balance))
+        Ok(TypedValue::Decimal(balance))
     }
 }
 
@@ -185,10 +247,10 @@ mod tests {
         gov_impl.storage_backend = Some(backend);
 
         // Create a resource
-        gov_impl.execute_create_resource("test_resource").unwrap();
+        gov_impl.execute_create_resource("test_resource", &ResourcePolicy::default()).unwrap();
 
         // Creating the same resource should fail
-        let result = gov_impl.execute_create_resource("test_resource");
+        let result = gov_impl.execute_create_resource("test_resource", &ResourcePolicy::default());
         assert!(matches!(result, Err(VMError::ResourceAlreadyExists { .. })));
     }
 
@@ -199,7 +261,7 @@ mod tests {
         gov_impl.storage_backend = Some(backend);
 
         // Create a resource
-        gov_impl.execute_create_resource("test_resource").unwrap();
+        gov_impl.execute_create_resource("test_resource", &ResourcePolicy::default()).unwrap();
 
         // Mint some units
         gov_impl
@@ -218,7 +280,7 @@ mod tests {
         gov_impl.storage_backend = Some(backend);
 
         // Create a resource
-        gov_impl.execute_create_resource("test_resource").unwrap();
+        gov_impl.execute_create_resource("test_resource", &ResourcePolicy::default()).unwrap();
 
         // Try to mint negative amount
         let result = gov_impl.execute_mint(
@@ -246,7 +308,7 @@ mod tests {
         gov_impl.storage_backend = Some(backend);
 
         // Create a resource
-        gov_impl.execute_create_resource("test_resource").unwrap();
+        gov_impl.execute_create_resource("test_resource", &ResourcePolicy::default()).unwrap();
 
         // Mint some units
         gov_impl
@@ -279,7 +341,7 @@ mod tests {
         gov_impl.storage_backend = Some(backend);
 
         // Create a resource
-        gov_impl.execute_create_resource("test_resource").unwrap();
+        gov_impl.execute_create_resource("test_resource", &ResourcePolicy::default()).unwrap();
 
         // Mint some units
         gov_impl
@@ -305,7 +367,7 @@ mod tests {
         gov_impl.storage_backend = Some(backend);
 
         // Create a resource
-        gov_impl.execute_create_resource("test_resource").unwrap();
+        gov_impl.execute_create_resource("test_resource", &ResourcePolicy::default()).unwrap();
 
         // Mint some units
         gov_impl
@@ -350,4 +412,4 @@ mod tests {
         let result = gov_impl.execute_balance("nonexistent_resource", "user1");
         assert!(matches!(result, Err(VMError::ResourceNotFound { .. })));
     }
-} 
\ No newline at end of file
+}
@@ -17,6 +17,7 @@
 use crate::identity::Identity;
 use crate::storage::auth::AuthContext;
 use crate::storage::errors::StorageResult;
+use crate::storage::resource::ResourcePolicy;
 use crate::storage::traits::Storage;
 use crate::typed::TypedValue;
 use crate::vm::errors::VMError;
@@ -55,7 +56,11 @@ where
     S: Storage + Send + Sync + Clone + Debug + 'static,
 {
     /// Execute a resource creation operation
-    fn execute_create_resource(&mut self, resource: &str) -> Result<(), VMError>;
+    fn execute_create_resource(
+        &mut self,
+        resource: &str,
+        policy: &ResourcePolicy,
+    ) -> Result<(), VMError>;
 
     /// Execute a minting operation
     fn execute_mint(
@@ -191,6 +196,11 @@ pub mod typed_builders {
         Op::Push(TypedValue::String(value.into()))
     }
 
+    /// Create a Push operation with an exact decimal value
+    pub fn push_decimal(value: rust_decimal::Decimal) -> Op {
+        Op::Push(TypedValue::Decimal(value))
+    }
+
     /// Create a Push operation with a null value
     pub fn push_null() -> Op {
         Op::Push(TypedValue::Null)
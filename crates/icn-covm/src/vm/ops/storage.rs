@@ -216,6 +216,7 @@ where
                     Err(VMError::ResourceNotFound { .. }) => match missing_key_behavior {
                         MissingKeyBehavior::ReturnZero => Ok(TypedValue::Number(0.0)),
                         MissingKeyBehavior::ReturnNaN => Ok(TypedValue::Number(f64::NAN)),
+                        MissingKeyBehavior::Null => Ok(TypedValue::Null),
                         MissingKeyBehavior::Error => Err(VMError::ResourceNotFound {
                             resource: key.to_string(),
                             namespace: self.namespace.clone(),
@@ -0,0 +1,99 @@
+//! Deterministic random numbers for proposal logic
+//!
+//! `Op::Random` draws from a sequence seeded with the id of the executing
+//! proposal and a hash of the DAG ledger's tips when the proposal started
+//! executing. Nothing else goes into it, so every federation node that
+//! executes the proposal against the same ledger draws the same numbers,
+//! which makes them fit for sortition and tie-breaking.
+//!
+//! The seed is the SHA-256 of [`DOMAIN`], the proposal id and the tip hash,
+//! each followed by a zero byte. The n-th draw, counting from zero, takes the
+//! first eight bytes of the SHA-256 of the seed and n (as eight big-endian
+//! bytes) as a big-endian integer, and redraws with the next n when that
+//! integer would bias the result towards small numbers.
+
+use sha2::{Digest, Sha256};
+
+/// Separates these seeds from other hashes of the same inputs
+pub const DOMAIN: &str = "icn-covm/random/v1";
+
+/// Largest `max` a draw accepts, so results are exact as numbers
+pub const MAX_RANGE: u64 = 1 << 53;
+
+/// The random sequence of one proposal execution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededRandom {
+    seed: [u8; 32],
+    draws: u64,
+}
+
+impl SeededRandom {
+    pub fn new(proposal_id: &str, tip_hash: &str) -> Self {
+        let mut hasher = Sha256::new();
+        for part in [DOMAIN, proposal_id, tip_hash] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        Self {
+            seed: hasher.finalize().into(),
+            draws: 0,
+        }
+    }
+
+    /// Hex SHA-256 of the ledger tips, sorted and joined by newlines, so the
+    /// order a node happens to hold them in does not matter
+    pub fn tip_hash(tips: &[String]) -> String {
+        let mut tips = tips.to_vec();
+        tips.sort();
+        hex::encode(Sha256::digest(tips.join("\n").as_bytes()))
+    }
+
+    /// The next number from 0 up to, but not including, `max`, which must be
+    /// at least 1
+    pub fn next_below(&mut self, max: u64) -> u64 {
+        // Values from `zone` up would make the low results more likely
+        let zone = u64::MAX - u64::MAX % max;
+        loop {
+            let mut hasher = Sha256::new();
+            hasher.update(self.seed);
+            hasher.update(self.draws.to_be_bytes());
+            self.draws += 1;
+
+            let digest = hasher.finalize();
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&digest[..8]);
+            let value = u64::from_be_bytes(bytes);
+            if value < zone {
+                return value % max;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_depends_only_on_proposal_and_tips() {
+        let tips = vec!["b".to_string(), "a".to_string()];
+        let reordered = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(
+            SeededRandom::tip_hash(&tips),
+            SeededRandom::tip_hash(&reordered)
+        );
+
+        let draw = |proposal: &str, tips: &[String]| {
+            let mut random = SeededRandom::new(proposal, &SeededRandom::tip_hash(tips));
+            (0..20).map(|_| random.next_below(10)).collect::<Vec<_>>()
+        };
+        let sequence = draw("prop-1", &tips);
+        assert_eq!(sequence, draw("prop-1", &reordered));
+        assert!(sequence.iter().all(|&n| n < 10));
+        assert_ne!(sequence, draw("prop-2", &tips));
+        assert_ne!(sequence, draw("prop-1", &["c".to_string()]));
+
+        let mut random = SeededRandom::new("prop-1", "");
+        assert_eq!(random.next_below(1), 0);
+    }
+}
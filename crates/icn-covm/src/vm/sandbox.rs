@@ -0,0 +1,194 @@
+//! Sandbox profiles for programs that are not trusted
+//!
+//! A [`SandboxProfile`] names the categories of side effects an execution may
+//! have. Ops outside every category (arithmetic, control flow, reads, events)
+//! are always allowed; an op in a category the profile does not allow stops
+//! the execution with [`VMError::SandboxViolation`] before it runs. The check
+//! covers the bodies of functions, loops and conditionals too, as they run
+//! through the same loop.
+//!
+//! Two profiles are built in:
+//!
+//! - `deliberation-preview` allows no category, so a program can compute,
+//!   read storage and identities, and emit output, but change nothing
+//! - `full-governance` allows every category, as approved proposal logic
+//!   needs
+//!
+//! The API runs submitted programs under `deliberation-preview` unless a
+//! global admin asks for another profile, and proposal logic under
+//! `full-governance`.
+
+use crate::vm::errors::VMError;
+use crate::vm::types::Op;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Profile submitted programs run under unless another is chosen
+pub const DEFAULT_PROFILE: &str = "deliberation-preview";
+
+/// A kind of side effect an op can have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OpCategory {
+    /// Creating, moving or limiting resources and bounties
+    Economic,
+    /// Writing to persistent storage, including approving WASM modules
+    StorageWrite,
+    /// Changing who is a member and their reputation
+    Identity,
+    /// Recording nodes on the DAG ledger, which federation peers replicate
+    Federation,
+}
+
+impl OpCategory {
+    pub const ALL: [OpCategory; 4] = [
+        OpCategory::Economic,
+        OpCategory::StorageWrite,
+        OpCategory::Identity,
+        OpCategory::Federation,
+    ];
+}
+
+impl fmt::Display for OpCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OpCategory::Economic => "economic",
+            OpCategory::StorageWrite => "storage-write",
+            OpCategory::Identity => "identity",
+            OpCategory::Federation => "federation",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The categories `op` falls in, empty when it has no side effects a
+/// sandbox restricts
+///
+/// `CallWasm` falls in every category its module could be granted, as the
+/// grant is only known once the module is loaded.
+pub fn categories(op: &Op) -> &'static [OpCategory] {
+    match op {
+        Op::StoreP(_) | Op::ApproveWasmModule { .. } => &[OpCategory::StorageWrite],
+        Op::CallWasm { .. } => &[OpCategory::StorageWrite, OpCategory::Economic],
+        Op::CreateResource { .. }
+        | Op::Mint { .. }
+        | Op::Transfer { .. }
+        | Op::Burn { .. }
+        | Op::SetCreditLimit { .. }
+        | Op::Approve { .. }
+        | Op::SetSpendingLimit { .. }
+        | Op::SetExchangeRate { .. }
+        | Op::Exchange { .. }
+        | Op::EscrowLock { .. }
+        | Op::EscrowRelease(_)
+        | Op::EscrowRefund(_) => &[OpCategory::Economic],
+        // Bounty steps and distributions are also recorded on the ledger
+        Op::CreateBounty { .. }
+        | Op::ClaimBounty { .. }
+        | Op::VerifyBounty { .. }
+        | Op::CancelBounty(_)
+        | Op::Distribute { .. } => &[OpCategory::Economic, OpCategory::Federation],
+        Op::OnboardMember { .. } | Op::OffboardMember(_) | Op::IncrementReputation { .. } => {
+            &[OpCategory::Identity]
+        }
+        _ => &[],
+    }
+}
+
+/// A named set of op categories an execution may use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxProfile {
+    pub name: String,
+    pub allowed: Vec<OpCategory>,
+}
+
+impl SandboxProfile {
+    pub fn new(name: impl Into<String>, allowed: Vec<OpCategory>) -> Self {
+        Self {
+            name: name.into(),
+            allowed,
+        }
+    }
+
+    /// Computation, reads and output only
+    pub fn deliberation_preview() -> Self {
+        Self::new("deliberation-preview", Vec::new())
+    }
+
+    /// Everything proposal logic may do
+    pub fn full_governance() -> Self {
+        Self::new("full-governance", OpCategory::ALL.to_vec())
+    }
+
+    /// The built-in profile called `name`
+    pub fn named(name: &str) -> Result<Self, String> {
+        match name {
+            "deliberation-preview" => Ok(Self::deliberation_preview()),
+            "full-governance" => Ok(Self::full_governance()),
+            _ => Err(format!(
+                "Unknown sandbox profile: {}. Expected deliberation-preview or full-governance",
+                name
+            )),
+        }
+    }
+
+    pub fn allows(&self, category: OpCategory) -> bool {
+        self.allowed.contains(&category)
+    }
+
+    /// Whether this profile allows nothing `other` does not
+    pub fn is_within(&self, other: &SandboxProfile) -> bool {
+        self.allowed.iter().all(|&category| other.allows(category))
+    }
+
+    /// Fail with [`VMError::SandboxViolation`] if `op` falls in a category
+    /// this profile does not allow
+    pub fn check(&self, op: &Op) -> Result<(), VMError> {
+        match categories(op)
+            .iter()
+            .find(|&&category| !self.allows(category))
+        {
+            Some(&category) => Err(VMError::SandboxViolation {
+                op: op.to_string(),
+                category: category.to_string(),
+                profile: self.name.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_profile_rejects_side_effects() {
+        let preview = SandboxProfile::named(DEFAULT_PROFILE).unwrap();
+        let full = SandboxProfile::named("full-governance").unwrap();
+        let store = Op::StoreP("votes/alice".to_string());
+        let distribute = Op::Distribute {
+            resource: "credits".to_string(),
+            pool_key: "pool".to_string(),
+            weight_key: "patronage".to_string(),
+        };
+
+        assert!(preview.check(&Op::LoadP("votes/alice".to_string())).is_ok());
+        assert!(preview.check(&Op::Emit("hello".to_string())).is_ok());
+        assert!(matches!(
+            preview.check(&store),
+            Err(VMError::SandboxViolation { ref category, .. }) if category == "storage-write"
+        ));
+        assert!(full.check(&store).is_ok());
+        assert!(full.check(&distribute).is_ok());
+
+        let economic_only = SandboxProfile::new("economic", vec![OpCategory::Economic]);
+        assert!(matches!(
+            economic_only.check(&distribute),
+            Err(VMError::SandboxViolation { ref category, .. }) if category == "federation"
+        ));
+        assert!(preview.is_within(&economic_only));
+        assert!(!full.is_within(&preview));
+        assert!(SandboxProfile::named("root").is_err());
+    }
+}
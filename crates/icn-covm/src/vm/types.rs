@@ -16,12 +16,25 @@
 //! - `LoopControl`: Loop control flow signals
 //! - `VMEvent`: Event structure for tracking VM activity
 
-use crate::typed::TypedValue;
+use crate::events::Severity;
+use crate::storage::resource::{BountyVerification, ResourcePolicy, SpendingAction, SpendingWindow};
+use crate::typed::{TypedValue, TypingMode};
+use crate::vm::wasm::WasmCapability;
 use chrono::Duration;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 
+/// A step of a bounty's lifecycle, recorded in the DAG ledger once it succeeds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BountyStep {
+    Created,
+    Claimed,
+    Verified { approved: bool },
+    Cancelled,
+}
+
 /// Operation types for the virtual machine
 ///
 /// The VM executes these operations in sequence, manipulating the stack,
@@ -71,9 +84,63 @@ pub enum Op {
     /// Emit a message to the output
     Emit(String),
 
+    /// Pop the top value and emit it as text, strings without their quotes
+    EmitTop,
+
+    /// Pop two values and push the second followed by the top as one string
+    ///
+    /// Values that are not strings are converted to text first, so numbers,
+    /// booleans and timestamps can be joined into messages and keys in strict
+    /// typing mode too.
+    Concat,
+
     /// Negate the top value on the stack
     Negate,
 
+    /// Replace the top value (bytes, or a string's UTF-8) with its hex encoding
+    HexEncode,
+
+    /// Replace the top value, a hex string, with the bytes it encodes
+    HexDecode,
+
+    /// Replace the top value (bytes, or a string's UTF-8) with its base64 encoding
+    Base64Encode,
+
+    /// Replace the top value, a base64 string, with the bytes it encodes
+    Base64Decode,
+
+    /// Replace the top value with its length: bytes for bytes, characters for strings
+    Length,
+
+    /// Replace the top value with whether it is Null
+    IsNull,
+
+    /// Pop a fallback, then replace the top value with it if the top value is Null
+    Coalesce,
+
+    /// Push the current time as a timestamp
+    Now,
+
+    /// Push the next number from 0 up to, but not including, `max` from the
+    /// executing proposal's random sequence
+    ///
+    /// The sequence is seeded with the proposal id and the DAG ledger's tips,
+    /// so every federation node draws the same numbers, for sortition and
+    /// tie-breaking. Only proposal logic may draw; see [`crate::vm::random`].
+    Random { max: u64 },
+
+    /// Parse the RFC 3339 string, date or Unix seconds on top of the stack as a timestamp
+    ParseTime,
+
+    /// Format the timestamp on top of the stack as an RFC 3339 string
+    FormatTime,
+
+    /// Set how strictly the operations that follow check operand types
+    ///
+    /// In strict mode, arithmetic, comparison and logic fail with a type
+    /// mismatch instead of coercing between numbers, booleans and strings.
+    Typing(TypingMode),
+
     /// Assert that the top value on the stack equals the expected value
     AssertTop(TypedValue),
 
@@ -149,8 +216,13 @@ pub enum Op {
     /// Continue to the next iteration of the innermost loop
     Continue,
 
-    /// Emit an event with a category and message
-    EmitEvent { category: String, message: String },
+    /// Emit an event with a category, message and severity
+    EmitEvent {
+        category: String,
+        message: String,
+        #[serde(default)]
+        severity: Severity,
+    },
 
     /// Assert that all values in a depth of the stack are equal
     AssertEqualStack { depth: usize },
@@ -165,6 +237,10 @@ pub enum Op {
     /// The winner is determined using instant-runoff voting.
     /// The result is pushed back onto the stack.
     ///
+    /// When `delegated` is set, each ballot is topped by its voter's identity,
+    /// and counts with the weight of the `LiquidDelegate` delegations that
+    /// reach the voter.
+    ///
     /// The number of candidates must be at least 2.
     /// The number of ballots must be at least 1.
     RankedVote {
@@ -173,13 +249,39 @@ pub enum Op {
 
         /// Number of ballots to process
         ballots: usize,
+
+        /// Whether ballots name their voter and carry delegated weight
+        #[serde(default)]
+        delegated: bool,
+    },
+
+    /// Execute a quadratic vote with candidates and ballots
+    ///
+    /// Pops a series of ballots from the stack, each holding the voice
+    /// credits the voter spends on every candidate, candidate 0 first.
+    /// Casting `n` votes costs `n²` credits, so each candidate receives the
+    /// square root of the credits spent on it; the candidate with the most
+    /// votes wins, ties going to the lowest-numbered one.
+    /// The winner is pushed back onto the stack.
+    ///
+    /// The number of candidates must be at least 2.
+    /// The number of ballots must be at least 1.
+    QuadraticVote {
+        /// Number of candidates in the election
+        candidates: usize,
+
+        /// Number of ballots to process
+        ballots: usize,
     },
 
     /// Delegate voting power from one member to another
     ///
     /// This operation creates a delegation relationship where the 'from' member
     /// delegates their voting rights to the 'to' member. The VM maintains a
-    /// delegation graph and ensures there are no cycles.
+    /// delegation graph, persisted in storage when it has a backend and an
+    /// identity, and rejects delegations that would create a cycle.
+    /// Delegations chain: a vote passes along the chain to the first member
+    /// who votes in a delegated `RankedVote`.
     ///
     /// The delegation can be revoked by calling with an empty 'to' string.
     LiquidDelegate {
@@ -336,9 +438,17 @@ pub enum Op {
 
     /// Create a new economic resource
     ///
-    /// This operation creates a new economic resource with the specified identifier.
-    /// The resource details should be stored in persistent storage.
-    CreateResource(String),
+    /// This operation registers a new economic resource with the specified
+    /// identifier. Its policy (symbol, decimals, whether it is transferable,
+    /// its issuance policy and authorized minters) is stored with it, and
+    /// every later mint, transfer and burn of the resource is checked
+    /// against it.
+    CreateResource {
+        /// Resource identifier
+        resource: String,
+        /// Terms the resource is created with
+        policy: ResourcePolicy,
+    },
 
     /// Mint new units of a resource and assign to an account
     ///
@@ -352,8 +462,8 @@ pub enum Op {
         /// Account identifier
         account: String,
 
-        /// Amount to mint
-        amount: f64,
+        /// Amount to mint, kept exact
+        amount: Decimal,
 
         /// Optional reason for minting
         reason: Option<String>,
@@ -373,8 +483,8 @@ pub enum Op {
         /// Destination account
         to: String,
 
-        /// Amount to transfer
-        amount: f64,
+        /// Amount to transfer, kept exact
+        amount: Decimal,
 
         /// Optional reason for transfer
         reason: Option<String>,
@@ -391,8 +501,8 @@ pub enum Op {
         /// Account to burn from
         account: String,
 
-        /// Amount to burn
-        amount: f64,
+        /// Amount to burn, kept exact
+        amount: Decimal,
 
         /// Optional reason for burning
         reason: Option<String>,
@@ -410,6 +520,288 @@ pub enum Op {
         account: String,
     },
 
+    /// Set the credit one account, or the commons, extends to another
+    ///
+    /// Transfers may take the debtor below zero, down to minus the sum of
+    /// the credit extended to it. Only a proposal's logic may change credit
+    /// limits; a limit of zero removes the line.
+    SetCreditLimit {
+        /// Resource identifier
+        resource: String,
+
+        /// Account extending the credit, or `commons`
+        creditor: String,
+
+        /// Account the credit is extended to
+        debtor: String,
+
+        /// New limit, kept exact
+        limit: Decimal,
+    },
+
+    /// Push how far below zero an account may go in a resource
+    CreditLimit {
+        /// Resource identifier
+        resource: String,
+
+        /// Account to check
+        account: String,
+    },
+
+    /// Let a spender transfer up to a limit out of the calling identity's
+    /// account on its behalf
+    ///
+    /// The spender is an identity's DID, `role:<role>` for anyone holding
+    /// the role in the namespace, or `proposal:<id>` for a proposal's
+    /// logic. A limit of zero revokes the allowance.
+    Approve {
+        /// Resource identifier
+        resource: String,
+
+        /// Identity, role or proposal allowed to spend
+        spender: String,
+
+        /// Units the spender may transfer, kept exact
+        limit: Decimal,
+    },
+
+    /// Push how much a spender may still transfer out of an identity's
+    /// account
+    Allowance {
+        /// Resource identifier
+        resource: String,
+
+        /// Identity that gave the allowance
+        owner: String,
+
+        /// Identity, role or proposal it was given to
+        spender: String,
+    },
+
+    /// Cap what an identity, or each holder of a role, may transfer or mint
+    /// of a resource within a rolling day or week
+    ///
+    /// Going over a limit fails unless the transfer or mint is run by a
+    /// proposal's logic, and is reported as an event either way. Only a
+    /// proposal's logic may set limits; a limit of zero removes the cap.
+    SetSpendingLimit {
+        /// Resource identifier
+        resource: String,
+
+        /// Identity's DID, or `role:<role>`
+        subject: String,
+
+        /// Whether transfers or mints are capped
+        action: SpendingAction,
+
+        /// Window spending is counted over
+        window: SpendingWindow,
+
+        /// Most that may be spent within the window, kept exact
+        limit: Decimal,
+    },
+
+    /// Set the rate at which one resource exchanges into another
+    ///
+    /// Exchanges pay into and out of the reserve account, so neither
+    /// resource's supply changes. Only a proposal's logic may set rates; a
+    /// rate of zero stops exchanges between the two.
+    SetExchangeRate {
+        /// Resource paid in
+        from_resource: String,
+
+        /// Resource paid out
+        to_resource: String,
+
+        /// Units of `to_resource` paid for one of `from_resource`, kept exact
+        rate: Decimal,
+
+        /// Account holding both resources that exchanges go through
+        reserve: String,
+
+        /// Most of `from_resource` one exchange may convert; any if unset
+        max_amount: Option<Decimal>,
+    },
+
+    /// Convert units of one resource in an account into another at the
+    /// governed rate, pushing the amount received
+    Exchange {
+        /// Resource paid in
+        from_resource: String,
+
+        /// Resource paid out
+        to_resource: String,
+
+        /// Account exchanging
+        account: String,
+
+        /// Units of `from_resource` to convert, kept exact
+        amount: Decimal,
+
+        /// Fewest units of `to_resource` to accept
+        min_received: Decimal,
+    },
+
+    /// Push the rate at which one resource exchanges into another, zero if
+    /// none has been set
+    ExchangeRate {
+        /// Resource paid in
+        from_resource: String,
+
+        /// Resource paid out
+        to_resource: String,
+    },
+
+    /// Hold resource units under a proposal until it is decided
+    ///
+    /// The amount leaves the source account at once. It is paid to the
+    /// destination account once the proposal is executed, or returned to
+    /// the source account if the proposal is rejected or expires. The
+    /// proposal must exist and not yet be decided.
+    EscrowLock {
+        /// Proposal the amount is held under
+        proposal_id: String,
+
+        /// Resource identifier
+        resource: String,
+
+        /// Account the amount is taken from
+        from: String,
+
+        /// Account the amount is released to
+        to: String,
+
+        /// Amount to hold, kept exact
+        amount: Decimal,
+    },
+
+    /// Pay a proposal's locked escrows to their beneficiaries
+    ///
+    /// Fails unless the proposal has been executed.
+    EscrowRelease(String),
+
+    /// Return a proposal's locked escrows to their depositors
+    ///
+    /// Fails unless the proposal has been rejected or has expired.
+    EscrowRefund(String),
+
+    /// Create a bounty for a piece of work
+    ///
+    /// The reward leaves the funding account at once and is held until a
+    /// claim on the bounty is accepted, then paid to the claimant.
+    CreateBounty {
+        /// Bounty identifier
+        id: String,
+
+        /// Resource the reward is paid in
+        resource: String,
+
+        /// Account the reward is taken from, such as the treasury
+        funder: String,
+
+        /// Reward, kept exact
+        amount: Decimal,
+
+        /// Whether a verifier role or a vote accepts claims
+        verification: BountyVerification,
+
+        /// The work the bounty is for
+        description: String,
+    },
+
+    /// Claim an open bounty as the calling identity
+    ClaimBounty {
+        /// Bounty identifier
+        id: String,
+
+        /// Link to, or description of, the work done
+        evidence: String,
+    },
+
+    /// Approve or reject the pending claim on a bounty as the calling
+    /// identity
+    ///
+    /// An accepted claim is paid out at once; a rejected one reopens the
+    /// bounty for claims.
+    VerifyBounty {
+        /// Bounty identifier
+        id: String,
+
+        /// Whether the claim is approved
+        approve: bool,
+    },
+
+    /// Withdraw an unclaimed bounty and refund its reward
+    CancelBounty(String),
+
+    /// Pop an amount and split it from a pool account across members in
+    /// proportion to their stored weights, such as hours worked or
+    /// patronage points
+    ///
+    /// Every share is paid or none are, and the payout is recorded in the
+    /// DAG ledger as a single node.
+    Distribute {
+        /// Resource to distribute
+        resource: String,
+
+        /// Account the amount is paid from, such as a surplus account
+        pool_key: String,
+
+        /// Storage key prefix under which each member's weight is kept,
+        /// as `{weight_key}/{account}`
+        weight_key: String,
+    },
+
+    /// Approve a WASM module stored as a blob for `CallWasm`
+    ///
+    /// Only a proposal's logic may approve modules. Approving a module
+    /// again replaces the capabilities granted before; granting none still
+    /// lets it compute and log.
+    ApproveWasmModule {
+        /// SHA-256 hash of the module's blob
+        hash: String,
+
+        /// Host functions the module may call
+        capabilities: Vec<WasmCapability>,
+    },
+
+    /// Pop `args` numbers and call a function of an approved WASM module
+    /// with them, pushing the number it returns
+    ///
+    /// The last number pushed is the last argument.
+    CallWasm {
+        /// SHA-256 hash of the module's blob
+        hash: String,
+
+        /// Exported function to call
+        function: String,
+
+        /// Number of arguments to pop
+        args: usize,
+    },
+
+    /// Admit a `did:key` identity to the cooperative in the current
+    /// namespace, registering it and granting its roles
+    ///
+    /// Only a proposal's logic may change membership; see
+    /// `governance::membership`.
+    OnboardMember {
+        /// DID of the new member
+        did: String,
+
+        /// Public username recorded with the identity
+        username: String,
+
+        /// Roles granted in the namespace; `member` if empty
+        roles: Vec<String>,
+    },
+
+    /// Retire a member of the cooperative in the current namespace,
+    /// revoking their roles there
+    ///
+    /// Only a proposal's logic may change membership.
+    OffboardMember(String),
+
     /// Get an identity from storage by its ID
     ///
     /// This operation retrieves an identity from storage using its ID.
@@ -485,7 +877,21 @@ impl fmt::Display for Op {
             Op::Loop { count, .. } => write!(f, "Loop({})", count),
             Op::While { .. } => write!(f, "While"),
             Op::Emit(msg) => write!(f, "Emit({})", msg),
+            Op::EmitTop => write!(f, "EmitTop"),
+            Op::Concat => write!(f, "Concat"),
             Op::Negate => write!(f, "Negate"),
+            Op::HexEncode => write!(f, "HexEncode"),
+            Op::HexDecode => write!(f, "HexDecode"),
+            Op::Base64Encode => write!(f, "Base64Encode"),
+            Op::Base64Decode => write!(f, "Base64Decode"),
+            Op::Length => write!(f, "Length"),
+            Op::IsNull => write!(f, "IsNull"),
+            Op::Coalesce => write!(f, "Coalesce"),
+            Op::Now => write!(f, "Now"),
+            Op::Random { max } => write!(f, "Random({})", max),
+            Op::ParseTime => write!(f, "ParseTime"),
+            Op::FormatTime => write!(f, "FormatTime"),
+            Op::Typing(mode) => write!(f, "Typing({})", mode),
             Op::AssertTop(val) => write!(f, "AssertTop({})", val),
             Op::DumpStack => write!(f, "DumpStack"),
             Op::DumpMemory => write!(f, "DumpMemory"),
@@ -507,18 +913,35 @@ impl fmt::Display for Op {
             Op::Match { .. } => write!(f, "Match"),
             Op::Break => write!(f, "Break"),
             Op::Continue => write!(f, "Continue"),
-            Op::EmitEvent { category, message } => {
-                write!(f, "EmitEvent({}, {})", category, message)
+            Op::EmitEvent {
+                category,
+                message,
+                severity,
+            } => {
+                write!(f, "EmitEvent({}, {}, {})", category, message, severity)
             }
             Op::AssertEqualStack { depth } => write!(f, "AssertEqualStack({})", depth),
             Op::DumpState => write!(f, "DumpState"),
             Op::RankedVote {
                 candidates,
                 ballots,
+                delegated,
             } => {
                 write!(
                     f,
-                    "RankedVote({} candidates, {} ballots)",
+                    "RankedVote({} candidates, {} ballots{})",
+                    candidates,
+                    ballots,
+                    if *delegated { ", delegated" } else { "" }
+                )
+            }
+            Op::QuadraticVote {
+                candidates,
+                ballots,
+            } => {
+                write!(
+                    f,
+                    "QuadraticVote({} candidates, {} ballots)",
                     candidates, ballots
                 )
             }
@@ -549,7 +972,7 @@ impl fmt::Display for Op {
                 write!(f, "CheckDelegation({} -> {})", delegator_id, delegate_id)
             }
             Op::VerifySignature => write!(f, "VerifySignature"),
-            Op::CreateResource(resource) => write!(f, "CreateResource({})", resource),
+            Op::CreateResource { resource, .. } => write!(f, "CreateResource({})", resource),
             Op::Mint {
                 resource,
                 account,
@@ -580,6 +1003,144 @@ impl fmt::Display for Op {
                 write!(f, "Burn({} of {} from {})", amount, resource, account)
             }
             Op::Balance { resource, account } => write!(f, "Balance({} for {})", resource, account),
+            Op::SetCreditLimit {
+                resource,
+                creditor,
+                debtor,
+                limit,
+            } => {
+                write!(
+                    f,
+                    "SetCreditLimit({} of {} from {} to {})",
+                    limit, resource, creditor, debtor
+                )
+            }
+            Op::CreditLimit { resource, account } => {
+                write!(f, "CreditLimit({} for {})", resource, account)
+            }
+            Op::Approve {
+                resource,
+                spender,
+                limit,
+            } => write!(f, "Approve({} of {} for {})", limit, resource, spender),
+            Op::Allowance {
+                resource,
+                owner,
+                spender,
+            } => write!(f, "Allowance({} from {} for {})", resource, owner, spender),
+            Op::SetSpendingLimit {
+                resource,
+                subject,
+                action,
+                window,
+                limit,
+            } => write!(
+                f,
+                "SetSpendingLimit({} {} of {} a {} for {})",
+                action.as_str(),
+                limit,
+                resource,
+                window.as_str(),
+                subject
+            ),
+            Op::SetExchangeRate {
+                from_resource,
+                to_resource,
+                rate,
+                reserve,
+                ..
+            } => write!(
+                f,
+                "SetExchangeRate({} to {} at {} through {})",
+                from_resource, to_resource, rate, reserve
+            ),
+            Op::Exchange {
+                from_resource,
+                to_resource,
+                account,
+                amount,
+                ..
+            } => write!(
+                f,
+                "Exchange({} of {} to {} for {})",
+                amount, from_resource, to_resource, account
+            ),
+            Op::ExchangeRate {
+                from_resource,
+                to_resource,
+            } => write!(f, "ExchangeRate({} to {})", from_resource, to_resource),
+            Op::EscrowLock {
+                proposal_id,
+                resource,
+                from,
+                amount,
+                ..
+            } => {
+                write!(
+                    f,
+                    "EscrowLock({} of {} from {} under {})",
+                    amount, resource, from, proposal_id
+                )
+            }
+            Op::EscrowRelease(proposal_id) => write!(f, "EscrowRelease({})", proposal_id),
+            Op::EscrowRefund(proposal_id) => write!(f, "EscrowRefund({})", proposal_id),
+            Op::CreateBounty {
+                id,
+                resource,
+                funder,
+                amount,
+                ..
+            } => {
+                write!(
+                    f,
+                    "CreateBounty({}: {} of {} from {})",
+                    id, amount, resource, funder
+                )
+            }
+            Op::ClaimBounty { id, .. } => write!(f, "ClaimBounty({})", id),
+            Op::VerifyBounty { id, approve } => {
+                let decision = if *approve { "approve" } else { "reject" };
+                write!(f, "VerifyBounty({}, {})", id, decision)
+            }
+            Op::CancelBounty(id) => write!(f, "CancelBounty({})", id),
+            Op::Distribute {
+                resource,
+                pool_key,
+                weight_key,
+            } => {
+                write!(
+                    f,
+                    "Distribute({} from {} by {})",
+                    resource, pool_key, weight_key
+                )
+            }
+            Op::ApproveWasmModule { hash, capabilities } => write!(
+                f,
+                "ApproveWasmModule({} with [{}])",
+                hash,
+                capabilities
+                    .iter()
+                    .map(WasmCapability::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Op::CallWasm {
+                hash,
+                function,
+                args,
+            } => write!(f, "CallWasm({}::{} with {} args)", hash, function, args),
+            Op::OnboardMember {
+                did,
+                username,
+                roles,
+            } => write!(
+                f,
+                "OnboardMember({} as {} with [{}])",
+                did,
+                username,
+                roles.join(", ")
+            ),
+            Op::OffboardMember(did) => write!(f, "OffboardMember({})", did),
             Op::GetIdentity(id) => write!(f, "GetIdentity({})", id),
             Op::RequireValidSignature { voter, .. } => {
                 write!(f, "RequireValidSignature({})", voter)
@@ -628,7 +1189,7 @@ pub enum LoopControl {
 }
 
 /// An event emitted by the VM during execution
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct VMEvent {
     /// Category of the event
     pub category: String,
@@ -636,6 +1197,11 @@ pub struct VMEvent {
     /// Event message or payload
     pub message: String,
 
+    /// How much the event matters; events from before severities were
+    /// recorded read as info
+    #[serde(default)]
+    pub severity: Severity,
+
     /// Timestamp when the event occurred
     pub timestamp: u64,
 }
@@ -16,21 +16,32 @@
 //! - Provides a solid foundation for extending VM capabilities
 //! - Facilitates both AST interpretation and bytecode execution
 
+use crate::clock::{Clock, SharedClock};
+use crate::coverage::Coverage;
+use crate::perf::Profiler;
 use crate::storage::auth::AuthContext;
+use crate::storage::resource::{Bounty, BountyStatus, Distribution, EscrowOutcome};
 use crate::storage::traits::Storage;
-use crate::typed::TypedValue;
+use crate::telemetry::metrics;
+use crate::typed::{TypedValue, TypedValueError, TypingMode};
+use crate::vm::debugger::Debugger;
 use crate::vm::errors::VMError;
-use crate::vm::execution::{ExecutorOps, VMExecution};
+use crate::vm::execution::{ExecutorOps, StorageReadStats, VMExecution};
+use crate::vm::gas::{GasMeter, GasSchedule};
 use crate::vm::memory::{MemoryScope, VMMemory};
+use crate::vm::random::SeededRandom;
+use crate::vm::sandbox::SandboxProfile;
 use crate::vm::stack::{StackOps, VMStack};
-use crate::vm::types::{LoopControl, Op, VMEvent};
+use crate::vm::types::{BountyStep, LoopControl, Op, VMEvent};
 use crate::vm::typed_trace::VMTracer;
-use icn_ledger::DagLedger;
+use icn_ledger::{DagLedger, DagNode, NodeData};
+use rust_decimal::Decimal;
 
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::marker::{Send, Sync};
 use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
 
 /// Defines behavior when a key is not found in storage operations
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -39,6 +50,8 @@ pub enum MissingKeyBehavior {
     Default,
     /// Return an error when a key is not found
     Error,
+    /// Return Null when a key is not found
+    Null,
 }
 
 /// The Virtual Machine for cooperative value networks
@@ -76,9 +89,21 @@ where
 
     /// Whether to enable verbose tracing of storage operations
     pub verbose_storage_trace: bool,
-    
+
     /// Execution tracer for recording operation history
     pub tracer: Option<VMTracer>,
+
+    /// Profiler recording the time spent in each op
+    pub profiler: Option<Profiler>,
+
+    /// Coverage recording which ops and branches run
+    pub coverage: Option<Coverage>,
+
+    /// Categories of ops the execution may use, or `None` for all of them
+    pub sandbox: Option<SandboxProfile>,
+
+    /// Program being stepped through, with its breakpoints
+    pub debugger: Option<Debugger>,
 }
 
 impl<S> VM<S>
@@ -98,6 +123,10 @@ where
             simulation_mode: false,
             verbose_storage_trace: false,
             tracer: None,
+            profiler: None,
+            coverage: None,
+            sandbox: None,
+            debugger: None,
         }
     }
 
@@ -129,6 +158,38 @@ where
         self.dag.as_ref()
     }
 
+    /// Subscribe to governance events appended to the DAG ledger
+    ///
+    /// Returns `None` when the VM has no ledger. Events recorded by forks of
+    /// this VM are not delivered.
+    pub fn subscribe_dag(&mut self) -> Option<Receiver<DagNode>> {
+        self.dag.as_mut().map(|dag| dag.subscribe())
+    }
+
+    /// Subscribe to `Emit` output and `EmitEvent` events as they are produced
+    pub fn subscribe_events(&mut self) -> Receiver<VMEvent> {
+        self.executor.subscribe_events()
+    }
+
+    /// Subscribe to output and events paired with the namespace they were
+    /// emitted in
+    pub fn subscribe_namespaced_events(&mut self) -> Receiver<(String, VMEvent)> {
+        self.executor.subscribe_namespaced_events()
+    }
+
+    /// Run `f` as `auth`, restoring the previous authentication context afterwards
+    pub fn with_auth_context<F, R>(&mut self, auth: AuthContext, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let previous = self.executor.auth_context.replace(auth);
+        self.executor.invalidate_reads();
+        let result = f(self);
+        self.executor.auth_context = previous;
+        self.executor.invalidate_reads();
+        result
+    }
+
     /// Set the storage backend
     pub fn set_storage_backend(&mut self, backend: S) {
         self.executor.set_storage_backend(backend);
@@ -161,6 +222,7 @@ where
 
     /// Get the mutable storage backend
     pub fn get_storage_backend_mut(&mut self) -> Option<&mut S> {
+        self.executor.invalidate_reads();
         self.executor.storage_backend.as_mut()
     }
 
@@ -186,6 +248,12 @@ where
         }
     }
 
+    /// `LoadP` reads served by the storage backend and by the read cache
+    /// during the most recent execution
+    pub fn storage_read_stats(&self) -> StorageReadStats {
+        self.executor.storage_read_stats()
+    }
+
     /// Get the namespace
     pub fn get_namespace(&self) -> Option<&str> {
         Some(&self.executor.namespace)
@@ -206,6 +274,10 @@ where
             simulation_mode: self.simulation_mode,
             verbose_storage_trace: self.verbose_storage_trace,
             tracer: self.tracer.clone(),
+            profiler: self.profiler.clone(),
+            coverage: self.coverage.clone(),
+            sandbox: self.sandbox.clone(),
+            debugger: None,
         })
     }
 
@@ -281,20 +353,59 @@ where
             simulation_mode: self.simulation_mode,
             verbose_storage_trace: self.verbose_storage_trace,
             tracer: self.tracer.clone(),
+            profiler: self.profiler.clone(),
+            coverage: self.coverage.clone(),
+            sandbox: self.sandbox.clone(),
+            debugger: None,
         })
     }
 
     /// Execute a sequence of operations
     pub fn execute(&mut self, ops: &[Op]) -> Result<(), VMError> {
+        let span = tracing::info_span!(
+            "vm_execute",
+            namespace = %self.executor.namespace,
+            identity = self.executor.auth_context.as_ref().map(|auth| auth.identity_did()),
+            ops = ops.len(),
+        );
+        let _entered = span.enter();
+
+        // Reads are cached for one execution only; storage may have been
+        // written through other handles since the last one
+        self.executor.reset_read_cache();
+        self.executor.reset_gas();
+
         // Use internal execution implementation
-        self.execute_inner(ops.to_vec())
+        let started = std::time::Instant::now();
+        let _covered = self.coverage.as_ref().map(Coverage::enter_program);
+        let result = self.execute_inner(ops.to_vec());
+        metrics::record_vm_execution(ops.len(), result.is_ok(), started.elapsed());
+        if let Err(e) = &result {
+            tracing::debug!(error = %e, "Execution failed");
+        }
+        result
+    }
+
+    /// Execute a block nested in the op being executed, where `block` is its
+    /// index among the op's blocks as given by [`crate::coverage::blocks`]
+    fn execute_block(&mut self, block: usize, ops: Vec<Op>) -> Result<(), VMError> {
+        let _covered = self
+            .coverage
+            .as_ref()
+            .map(|coverage| coverage.enter_block(block));
+        self.execute_inner(ops)
     }
 
     /// Internal implementation of execute that takes ownership of the ops vector
-    fn execute_inner(&mut self, ops: Vec<Op>) -> Result<(), VMError> {
+    pub(super) fn execute_inner(&mut self, ops: Vec<Op>) -> Result<(), VMError> {
         let mut loop_control = LoopControl::None;
 
         for op in ops {
+            tracing::trace!(op = ?op, "Executing op");
+            let _profiled = self.profiler.as_ref().map(|profiler| profiler.enter(&op));
+            if let Some(coverage) = &self.coverage {
+                coverage.enter_op();
+            }
             if self.trace_enabled {
                 self.log_trace(&op);
             }
@@ -303,6 +414,11 @@ where
                 self.log_explanation(&op);
             }
 
+            if let Some(sandbox) = &self.sandbox {
+                sandbox.check(&op)?;
+            }
+            self.executor.charge_gas(&op)?;
+
             // Check for simulation mode with storage operations
             match &op {
                 Op::StoreP(_)
@@ -310,11 +426,31 @@ where
                 | Op::LoadVersionP { .. }
                 | Op::ListVersionsP(_)
                 | Op::DiffVersionsP { .. }
-                | Op::CreateResource(_)
+                | Op::CreateResource { .. }
                 | Op::Mint { .. }
                 | Op::Transfer { .. }
                 | Op::Burn { .. }
                 | Op::Balance { .. }
+                | Op::SetCreditLimit { .. }
+                | Op::CreditLimit { .. }
+                | Op::Approve { .. }
+                | Op::Allowance { .. }
+                | Op::SetSpendingLimit { .. }
+                | Op::SetExchangeRate { .. }
+                | Op::Exchange { .. }
+                | Op::ExchangeRate { .. }
+                | Op::EscrowLock { .. }
+                | Op::EscrowRelease(_)
+                | Op::EscrowRefund(_)
+                | Op::CreateBounty { .. }
+                | Op::ClaimBounty { .. }
+                | Op::VerifyBounty { .. }
+                | Op::CancelBounty(_)
+                | Op::Distribute { .. }
+                | Op::ApproveWasmModule { .. }
+                | Op::CallWasm { .. }
+                | Op::OnboardMember { .. }
+                | Op::OffboardMember(_)
                     if self.simulation_mode =>
                 {
                     // In simulation mode, log the operation but don't execute storage modifications
@@ -323,11 +459,27 @@ where
 
                     // For operations that would push a value to the stack, push a placeholder
                     match &op {
-                        Op::LoadP(_) | Op::LoadVersionP { .. } | Op::Balance { .. } => {
+                        Op::LoadP(_) | Op::LoadVersionP { .. } => {
                             // Push a simulated value (0.0 for numbers)
                             // In a real implementation, you might want to be smarter about the type
                             self.stack.push(TypedValue::Number(0.0));
                         }
+                        Op::Balance { .. }
+                        | Op::CreditLimit { .. }
+                        | Op::Allowance { .. }
+                        | Op::Exchange { .. }
+                        | Op::ExchangeRate { .. } => {
+                            self.stack.push(TypedValue::Decimal(Decimal::ZERO));
+                        }
+                        Op::Distribute { .. } => {
+                            self.stack.pop("Distribute")?;
+                        }
+                        Op::CallWasm { args, .. } => {
+                            for _ in 0..*args {
+                                self.stack.pop("CallWasm")?;
+                            }
+                            self.stack.push(TypedValue::Number(0.0));
+                        }
                         _ => {}
                     }
 
@@ -381,24 +533,23 @@ where
                     else_,
                 } => {
                     // Execute the condition
-                    self.execute_inner(condition)?;
+                    self.execute_block(0, condition)?;
 
                     // Check the result
                     let cond_result = self.stack.pop("If")?;
 
                     if cond_result.is_falsey() {
-                        // Condition is false, execute 'else' branch if available
-                        if let Some(else_branch) = else_ {
-                            self.execute_inner(else_branch)?;
-                        }
+                        // Condition is false, execute 'else' branch, which
+                        // is empty if there is none
+                        self.execute_block(2, else_.unwrap_or_default())?;
                     } else {
                         // Condition is true, execute 'then' branch
-                        self.execute_inner(then)?;
+                        self.execute_block(1, then)?;
                     }
                 }
                 Op::Loop { count, body } => {
                     for _ in 0..count {
-                        self.execute_inner(body.clone())?;
+                        self.execute_block(0, body.clone())?;
 
                         // Check for loop control signals
                         match loop_control {
@@ -417,7 +568,7 @@ where
                 Op::While { condition, body } => {
                     loop {
                         // Evaluate condition
-                        self.execute_inner(condition.clone())?;
+                        self.execute_block(0, condition.clone())?;
                         let cond_result = self.stack.pop("While")?;
 
                         if cond_result.is_falsey() {
@@ -426,7 +577,7 @@ where
                         }
 
                         // Execute body
-                        self.execute_inner(body.clone())?;
+                        self.execute_block(1, body.clone())?;
 
                         // Check for loop control signals
                         match loop_control {
@@ -445,6 +596,14 @@ where
                 Op::Emit(message) => {
                     self.executor.emit(&message);
                 }
+                Op::EmitTop => {
+                    let value = self.stack.pop("EmitTop")?;
+                    self.executor.emit(&value.as_string()?);
+                }
+                Op::Concat => {
+                    let (a, b) = self.stack.pop_two("Concat")?;
+                    self.stack.push(a.concat(&b)?);
+                }
                 Op::Negate => {
                     let value = self.stack.pop("Negate")?;
                     if let TypedValue::Number(num) = value {
@@ -457,6 +616,27 @@ where
                         });
                     }
                 }
+                Op::HexEncode => self.replace_top("HexEncode", TypedValue::hex_encode)?,
+                Op::HexDecode => self.replace_top("HexDecode", TypedValue::hex_decode)?,
+                Op::Base64Encode => self.replace_top("Base64Encode", TypedValue::base64_encode)?,
+                Op::Base64Decode => self.replace_top("Base64Decode", TypedValue::base64_decode)?,
+                Op::Length => self.replace_top("Length", TypedValue::length)?,
+                Op::IsNull => {
+                    let value = self.stack.pop("IsNull")?;
+                    self.stack.push(TypedValue::Boolean(value.is_null()));
+                }
+                Op::Coalesce => {
+                    let (value, fallback) = self.stack.pop_two("Coalesce")?;
+                    self.stack.push(if value.is_null() { fallback } else { value });
+                }
+                Op::Now => self.stack.push(TypedValue::Timestamp(self.executor.clock.now())),
+                Op::Random { max } => {
+                    let value = self.executor.draw_random(max)?;
+                    self.stack.push(TypedValue::Number(value as f64));
+                }
+                Op::ParseTime => self.replace_top("ParseTime", TypedValue::parse_time)?,
+                Op::FormatTime => self.replace_top("FormatTime", TypedValue::format_time)?,
+                Op::Typing(mode) => self.executor.set_typing_mode(mode),
                 Op::AssertTop(expected) => {
                     let actual = self.stack.pop("AssertTop")?;
                     if !actual.equals(&expected).unwrap_or(TypedValue::Boolean(false)).as_boolean().unwrap_or(false) {
@@ -550,26 +730,26 @@ where
                     default,
                 } => {
                     // Evaluate the value to match on
-                    self.execute_inner(value)?;
+                    self.execute_block(0, value)?;
                     let match_value = self.stack.pop("Match")?;
 
                     let mut matched = false;
+                    let default_block = cases.len() + 1;
 
                     // Check each case
-                    for (case_value, case_body) in cases {
+                    for (index, (case_value, case_body)) in cases.into_iter().enumerate() {
                         if match_value.equals(&case_value).unwrap_or(TypedValue::Boolean(false)) == TypedValue::Boolean(true) {
                             // Found a match, execute the corresponding body
-                            self.execute_inner(case_body)?;
+                            self.execute_block(index + 1, case_body)?;
                             matched = true;
                             break;
                         }
                     }
 
-                    // If no match was found and there's a default case, execute it
+                    // If no match was found, execute the default case, which
+                    // is empty if there is none
                     if !matched {
-                        if let Some(default_body) = default {
-                            self.execute_inner(default_body)?;
-                        }
+                        self.execute_block(default_block, default.unwrap_or_default())?;
                     }
                 }
                 Op::Break => {
@@ -580,8 +760,13 @@ where
                     loop_control = LoopControl::Continue;
                     break;
                 }
-                Op::EmitEvent { category, message } => {
-                    self.executor.emit_event(&category, &message);
+                Op::EmitEvent {
+                    category,
+                    message,
+                    severity,
+                } => {
+                    self.executor
+                        .emit_event_with_severity(&category, &message, severity);
                 }
                 Op::AssertEqualStack { depth } => {
                     if !self.stack.assert_equal_stack(depth, "AssertEqualStack")? {
@@ -596,8 +781,8 @@ where
                     let memory_str = format!("{}", self.memory);
                     self.executor.emit(&memory_str);
                 }
-                Op::CreateResource(resource) => {
-                    self.executor.execute_create_resource(&resource)?;
+                Op::CreateResource { resource, policy } => {
+                    self.executor.execute_create_resource(&resource, &policy)?;
                 }
                 Op::Mint {
                     resource,
@@ -605,7 +790,7 @@ where
                     amount,
                     reason,
                 } => {
-                    let amount_value = TypedValue::Number(amount);
+                    let amount_value = TypedValue::Decimal(amount);
                     self.executor
                         .execute_mint(&resource, &account, &amount_value, &reason)?;
                 }
@@ -616,7 +801,7 @@ where
                     amount,
                     reason,
                 } => {
-                    let amount_value = TypedValue::Number(amount);
+                    let amount_value = TypedValue::Decimal(amount);
                     self.executor
                         .execute_transfer(&resource, &from, &to, &amount_value, &reason)?;
                 }
@@ -626,7 +811,7 @@ where
                     amount,
                     reason,
                 } => {
-                    let amount_value = TypedValue::Number(amount);
+                    let amount_value = TypedValue::Decimal(amount);
                     self.executor
                         .execute_burn(&resource, &account, &amount_value, &reason)?;
                 }
@@ -634,6 +819,198 @@ where
                     let balance = self.executor.execute_balance(&resource, &account)?;
                     self.stack.push(balance);
                 }
+                Op::SetCreditLimit {
+                    resource,
+                    creditor,
+                    debtor,
+                    limit,
+                } => {
+                    let limit_value = TypedValue::Decimal(limit);
+                    self.executor.execute_set_credit_limit(
+                        &resource,
+                        &creditor,
+                        &debtor,
+                        &limit_value,
+                    )?;
+                }
+                Op::CreditLimit { resource, account } => {
+                    let limit = self.executor.execute_credit_limit(&resource, &account)?;
+                    self.stack.push(limit);
+                }
+                Op::Approve {
+                    resource,
+                    spender,
+                    limit,
+                } => {
+                    let limit_value = TypedValue::Decimal(limit);
+                    self.executor
+                        .execute_approve(&resource, &spender, &limit_value)?;
+                }
+                Op::Allowance {
+                    resource,
+                    owner,
+                    spender,
+                } => {
+                    let allowance = self.executor.execute_allowance(&resource, &owner, &spender)?;
+                    self.stack.push(allowance);
+                }
+                Op::SetSpendingLimit {
+                    resource,
+                    subject,
+                    action,
+                    window,
+                    limit,
+                } => {
+                    let limit_value = TypedValue::Decimal(limit);
+                    self.executor.execute_set_spending_limit(
+                        &resource,
+                        &subject,
+                        action,
+                        window,
+                        &limit_value,
+                    )?;
+                }
+                Op::SetExchangeRate {
+                    from_resource,
+                    to_resource,
+                    rate,
+                    reserve,
+                    max_amount,
+                } => {
+                    let rate_value = TypedValue::Decimal(rate);
+                    let max_value = max_amount.map(TypedValue::Decimal);
+                    self.executor.execute_set_exchange_rate(
+                        &from_resource,
+                        &to_resource,
+                        &rate_value,
+                        &reserve,
+                        max_value.as_ref(),
+                    )?;
+                }
+                Op::Exchange {
+                    from_resource,
+                    to_resource,
+                    account,
+                    amount,
+                    min_received,
+                } => {
+                    let amount_value = TypedValue::Decimal(amount);
+                    let min_value = TypedValue::Decimal(min_received);
+                    let received = self.executor.execute_exchange(
+                        &from_resource,
+                        &to_resource,
+                        &account,
+                        &amount_value,
+                        &min_value,
+                    )?;
+                    self.stack.push(received);
+                }
+                Op::ExchangeRate {
+                    from_resource,
+                    to_resource,
+                } => {
+                    let rate = self
+                        .executor
+                        .execute_exchange_rate(&from_resource, &to_resource)?;
+                    self.stack.push(rate);
+                }
+                Op::EscrowLock {
+                    proposal_id,
+                    resource,
+                    from,
+                    to,
+                    amount,
+                } => {
+                    let amount_value = TypedValue::Decimal(amount);
+                    self.executor.execute_escrow_lock(
+                        &proposal_id,
+                        &resource,
+                        &from,
+                        &to,
+                        &amount_value,
+                    )?;
+                }
+                Op::EscrowRelease(proposal_id) => {
+                    self.executor
+                        .execute_escrow_settle(&proposal_id, EscrowOutcome::Release)?;
+                }
+                Op::EscrowRefund(proposal_id) => {
+                    self.executor
+                        .execute_escrow_settle(&proposal_id, EscrowOutcome::Refund)?;
+                }
+                Op::CreateBounty {
+                    id,
+                    resource,
+                    funder,
+                    amount,
+                    verification,
+                    description,
+                } => {
+                    let amount_value = TypedValue::Decimal(amount);
+                    let bounty = self.executor.execute_create_bounty(
+                        &id,
+                        &resource,
+                        &funder,
+                        &amount_value,
+                        &verification,
+                        &description,
+                    )?;
+                    self.record_bounty_step(BountyStep::Created, &bounty)?;
+                }
+                Op::ClaimBounty { id, evidence } => {
+                    let bounty = self.executor.execute_claim_bounty(&id, &evidence)?;
+                    self.record_bounty_step(BountyStep::Claimed, &bounty)?;
+                }
+                Op::VerifyBounty { id, approve } => {
+                    let bounty = self.executor.execute_verify_bounty(&id, approve)?;
+                    self.record_bounty_step(BountyStep::Verified { approved: approve }, &bounty)?;
+                }
+                Op::CancelBounty(id) => {
+                    let bounty = self.executor.execute_cancel_bounty(&id)?;
+                    self.record_bounty_step(BountyStep::Cancelled, &bounty)?;
+                }
+                Op::Distribute {
+                    resource,
+                    pool_key,
+                    weight_key,
+                } => {
+                    let amount = self.stack.pop("Distribute")?;
+                    let distribution = self.executor.execute_distribute(
+                        &resource,
+                        &pool_key,
+                        &weight_key,
+                        &amount,
+                    )?;
+                    self.record_distribution(&distribution)?;
+                }
+                Op::ApproveWasmModule { hash, capabilities } => {
+                    self.executor
+                        .execute_approve_wasm_module(&hash, &capabilities)?;
+                }
+                Op::CallWasm {
+                    hash,
+                    function,
+                    args,
+                } => {
+                    let mut values = Vec::with_capacity(args);
+                    for _ in 0..args {
+                        values.push(self.stack.pop_number("CallWasm")?);
+                    }
+                    values.reverse();
+                    let result = self.executor.execute_call_wasm(&hash, &function, &values)?;
+                    self.stack.push(result);
+                }
+                Op::OnboardMember {
+                    did,
+                    username,
+                    roles,
+                } => {
+                    self.executor
+                        .execute_onboard_member(&did, &username, &roles)?;
+                }
+                Op::OffboardMember(did) => {
+                    self.executor.execute_offboard_member(&did)?;
+                }
                 Op::IncrementReputation {
                     identity_id,
                     amount,
@@ -654,6 +1031,8 @@ where
                             self.executor.execute_load_p(&key, crate::vm::MissingKeyBehavior::Default)?,
                         MissingKeyBehavior::Error =>
                             self.executor.execute_load_p(&key, crate::vm::MissingKeyBehavior::Error)?,
+                        MissingKeyBehavior::Null =>
+                            self.executor.execute_load_p(&key, crate::vm::MissingKeyBehavior::Null)?,
                     };
                     self.log_storage_operation("LoadP", &key, &value);
                     self.stack.push(value);
@@ -700,6 +1079,10 @@ where
         self.memory.push_call_frame(name, param_values);
 
         // Execute the function body
+        let _covered = self
+            .coverage
+            .as_ref()
+            .map(|coverage| coverage.enter_function(name));
         self.execute_inner(body)?;
 
         // Pop the call frame
@@ -746,6 +1129,12 @@ where
         self
     }
 
+    /// Reject implicit coercions in arithmetic, comparison and logic
+    pub fn with_strict_typing(mut self) -> Self {
+        self.executor.set_typing_mode(TypingMode::Strict);
+        self
+    }
+
     /// Enable verbose storage tracing
     pub fn with_verbose_storage_trace(mut self) -> Self {
         self.verbose_storage_trace = true;
@@ -775,12 +1164,186 @@ where
         self
     }
 
+    /// Set how strictly arithmetic, comparison and logic check operand types
+    pub fn set_typing_mode(&mut self, mode: TypingMode) -> &mut Self {
+        self.executor.set_typing_mode(mode);
+        self
+    }
+
+    /// Mark the code being executed as the logic of `proposal_id`, allowing
+    /// operations only governance may perform, such as changing credit limits
+    ///
+    /// `Random` draws are seeded with the proposal id and the tips of the
+    /// namespace's DAG ledger as they are now.
+    pub fn set_executing_proposal(&mut self, proposal_id: Option<String>) -> &mut Self {
+        let tips = self
+            .dag
+            .as_ref()
+            .map(|dag| dag.current_tips(&self.executor.namespace))
+            .unwrap_or_default();
+        self.executor
+            .set_executing_proposal(proposal_id, &SeededRandom::tip_hash(&tips));
+        self
+    }
+
+    /// Record a bounty step in the DAG ledger, if the VM keeps one
+    ///
+    /// A verification that accepts the claim is followed by the payout.
+    pub(crate) fn record_bounty_step(
+        &mut self,
+        step: BountyStep,
+        bounty: &Bounty,
+    ) -> Result<(), VMError> {
+        let bounty_id = bounty.id.clone();
+        let claimant = bounty.claim.as_ref().map(|claim| claim.claimant.clone());
+        let mut steps = vec![match step {
+            BountyStep::Created => NodeData::BountyCreated {
+                bounty_id: bounty_id.clone(),
+                resource: bounty.resource.clone(),
+                funder: bounty.funder.clone(),
+                amount: bounty.amount.to_string(),
+            },
+            BountyStep::Claimed => NodeData::BountyClaimed {
+                bounty_id: bounty_id.clone(),
+                claimant: claimant.clone().unwrap_or_default(),
+            },
+            BountyStep::Verified { approved } => NodeData::BountyVerified {
+                bounty_id: bounty_id.clone(),
+                verifier: self
+                    .executor
+                    .auth_context
+                    .as_ref()
+                    .map(|auth| auth.user_id_string())
+                    .unwrap_or_default(),
+                approved,
+            },
+            BountyStep::Cancelled => NodeData::BountyCancelled {
+                bounty_id: bounty_id.clone(),
+            },
+        }];
+        if matches!(step, BountyStep::Verified { .. }) && bounty.status == BountyStatus::Paid {
+            steps.push(NodeData::BountyPaid {
+                bounty_id,
+                recipient: claimant.unwrap_or_default(),
+                amount: bounty.amount.to_string(),
+            });
+        }
+
+        self.record_economic_nodes(steps, "bounty")
+    }
+
+    /// Record a distribution's payout in the DAG ledger, if the VM keeps one
+    pub(crate) fn record_distribution(
+        &mut self,
+        distribution: &Distribution,
+    ) -> Result<(), VMError> {
+        let shares = distribution
+            .shares
+            .iter()
+            .map(|share| (share.account.clone(), share.amount.to_string()))
+            .collect();
+        let data = NodeData::Distributed {
+            resource: distribution.resource.clone(),
+            pool: distribution.pool.clone(),
+            weight_key: distribution.weight_key.clone(),
+            amount: distribution.paid().to_string(),
+            shares,
+        };
+        self.record_economic_nodes(vec![data], "distribution")
+    }
+
+    /// Append nodes on the DAG ledger's tips, if the VM keeps one
+    fn record_economic_nodes(&mut self, nodes: Vec<NodeData>, what: &str) -> Result<(), VMError> {
+        let Some(dag) = &mut self.dag else {
+            return Ok(());
+        };
+        let timestamp = crate::storage::utils::now_with_default();
+        for data in nodes {
+            let node =
+                DagNode::with_namespace(vec![], data, timestamp, self.executor.namespace.clone());
+            dag.append_on_tips(node).map_err(|e| {
+                VMError::Other(format!("Failed to record {} in the DAG: {}", what, e))
+            })?;
+        }
+        Ok(())
+    }
+
     /// Enable or disable verbose storage tracing
     pub fn set_verbose_storage_trace(&mut self, enabled: bool) -> &mut Self {
         self.verbose_storage_trace = enabled;
         self
     }
 
+    /// Attach a profiler that records the time spent in each op, or detach
+    /// it with `None`
+    pub fn set_profiler(&mut self, profiler: Option<Profiler>) -> &mut Self {
+        self.profiler = profiler;
+        self
+    }
+
+    /// Meter execution against a new budget of `limit` gas, or stop metering
+    /// with `None`
+    ///
+    /// Every execution starts with the full budget; the gas it used can be
+    /// read until the next one starts.
+    pub fn set_gas_limit(&mut self, limit: Option<u64>) -> &mut Self {
+        self.executor.set_gas_limit(limit);
+        self
+    }
+
+    /// Set the gas each op costs in metered mode
+    pub fn set_gas_schedule(&mut self, schedule: GasSchedule) -> &mut Self {
+        self.executor.set_gas_schedule(schedule);
+        self
+    }
+
+    /// Gas used by the last execution, or `None` when execution is not metered
+    pub fn gas_used(&self) -> Option<u64> {
+        self.executor.gas_meter().map(GasMeter::used)
+    }
+
+    /// Gas the last execution had left, or `None` when execution is not metered
+    pub fn gas_remaining(&self) -> Option<u64> {
+        self.executor.gas_meter().map(GasMeter::remaining)
+    }
+
+    /// Restrict the ops executions may use to those `sandbox` allows, or
+    /// lift the restriction with `None`
+    pub fn set_sandbox(&mut self, sandbox: Option<SandboxProfile>) -> &mut Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Run `f` under `sandbox`, restoring the previous profile afterwards
+    pub fn with_sandbox<F, R>(&mut self, sandbox: SandboxProfile, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let previous = self.sandbox.replace(sandbox);
+        let result = f(self);
+        self.sandbox = previous;
+        result
+    }
+
+    /// Attach a coverage that records which ops and branches run, or detach
+    /// it with `None`
+    pub fn set_coverage(&mut self, coverage: Option<Coverage>) -> &mut Self {
+        self.coverage = coverage;
+        self
+    }
+
+    /// Replace the clock that `now`, event timestamps and proposal lifecycle
+    /// checks read; forks share it
+    pub fn set_clock(&mut self, clock: SharedClock) -> &mut Self {
+        self.executor.clock = clock;
+        self
+    }
+
+    /// The clock this VM reads the time from
+    pub fn clock(&self) -> &dyn Clock {
+        self.executor.clock.as_ref()
+    }
+
     /// Check if verbose storage tracing is enabled
     pub fn is_verbose_storage_tracing(&self) -> bool {
         self.verbose_storage_trace
@@ -838,11 +1401,35 @@ where
         }
     }
 
+    /// Replace the top of the stack with `f` applied to it
+    fn replace_top(
+        &mut self,
+        op_name: &str,
+        f: fn(&TypedValue) -> Result<TypedValue, TypedValueError>,
+    ) -> Result<(), VMError> {
+        let value = self.stack.pop(op_name)?;
+        let result = f(&value).map_err(|err| match VMError::from(err) {
+            VMError::TypeMismatch {
+                expected, found, ..
+            } => VMError::TypeMismatch {
+                expected,
+                found,
+                operation: op_name.to_string(),
+            },
+            other => other,
+        })?;
+        self.stack.push(result);
+        Ok(())
+    }
+
     /// Log a storage operation with tracing information
     fn log_storage_operation(&mut self, operation: &str, key: &str, value: &TypedValue) {
         if self.verbose_storage_trace {
             let value_str = match value {
                 TypedValue::Number(n) => n.to_string(),
+                TypedValue::Decimal(d) => d.to_string(),
+                TypedValue::Bytes(b) => format!("0x{}", hex::encode(b)),
+                TypedValue::Timestamp(_) => value.to_string(),
                 TypedValue::Boolean(b) => b.to_string(),
                 TypedValue::String(s) => format!("\"{}\"", s),
                 TypedValue::Null => "null".to_string(),
@@ -875,7 +1462,24 @@ where
             Op::Loop { count, .. } => format!("Execute a block of code {} times", count),
             Op::While { .. } => "Execute a block of code while a condition is true".into(),
             Op::Emit(msg) => format!("Output the message: {}", msg),
+            Op::EmitTop => "Output the top value on the stack".into(),
+            Op::Concat => "Join the top two values into one string".into(),
             Op::Negate => "Negate the top value on the stack".into(),
+            Op::HexEncode => "Encode the top value as a hex string".into(),
+            Op::HexDecode => "Decode the hex string on top of the stack to bytes".into(),
+            Op::Base64Encode => "Encode the top value as a base64 string".into(),
+            Op::Base64Decode => "Decode the base64 string on top of the stack to bytes".into(),
+            Op::Length => "Replace the top value with its length".into(),
+            Op::IsNull => "Check if the top value is null".into(),
+            Op::Coalesce => "Use the top value as a fallback if the value below it is null".into(),
+            Op::Now => "Push the current time".into(),
+            Op::Random { max } => format!(
+                "Push the proposal's next deterministic random number below {}",
+                max
+            ),
+            Op::ParseTime => "Parse the top value as a timestamp".into(),
+            Op::FormatTime => "Format the top timestamp as an RFC 3339 string".into(),
+            Op::Typing(mode) => format!("Switch to {} typing", mode),
             Op::AssertTop(val) => format!("Assert that the top value equals {:?}", val),
             Op::DumpStack => "Display the current stack contents".into(),
             Op::DumpMemory => "Display the current memory contents".into(),
@@ -899,9 +1503,13 @@ where
             Op::Match { .. } => "Match a value against several cases".into(),
             Op::Break => "Break out of the innermost loop".into(),
             Op::Continue => "Continue to the next iteration of the innermost loop".into(),
-            Op::EmitEvent { category, message } => format!(
-                "Emit an event with category '{}' and message '{}'",
-                category, message
+            Op::EmitEvent {
+                category,
+                message,
+                severity,
+            } => format!(
+                "Emit a {} event with category '{}' and message '{}'",
+                severity, category, message
             ),
             Op::AssertEqualStack { depth } => format!(
                 "Assert that the top {} values on the stack are equal",
@@ -921,11 +1529,20 @@ where
     }
 }
 
+#[cfg(test)]
 pub mod tests {
     use super::*;
     use crate::identity::Identity;
-    use crate::storage::auth::AuthContext;
+    use crate::governance::membership::{self, MemberStatus};
+    use crate::storage::auth::{memberships_key, roles_key, AuthContext, RoleAssignment};
+    use crate::storage::blobs::put_blob;
     use crate::storage::implementations::in_memory::InMemoryStorage;
+    use crate::storage::resource::{
+        BountyVerification, DemurragePolicy, DemurrageState, IssuancePolicy, ResourcePolicy,
+        SpendingAction, SpendingWindow,
+    };
+    use crate::storage::traits::{EconomicOperations, StorageBackend, StorageExtensions};
+    use crate::vm::wasm::{self, WasmCapability};
 
     // This implementation conflicts with one in the actual InMemoryStorage module
     // Removing to avoid the conflict
@@ -1073,11 +1690,14 @@ pub mod tests {
 
         // Test creating a resource and minting some units
         let program = vec![
-            Op::CreateResource("token".to_string()),
+            Op::CreateResource {
+                resource: "token".to_string(),
+                policy: ResourcePolicy::default(),
+            },
             Op::Mint {
                 resource: "token".to_string(),
                 account: "user1".to_string(),
-                amount: 100.0,
+                amount: Decimal::from(100),
                 reason: Some("Initial allocation".to_string()),
             },
             Op::Balance {
@@ -1089,6 +1709,1050 @@ pub mod tests {
         vm.execute(&program).unwrap();
 
         // Check that balance query pushed the correct amount to the stack
-        assert_eq!(vm.stack.top(), Some(&TypedValue::Number(100.0)));
+        assert_eq!(
+            vm.stack.top(),
+            Some(&TypedValue::Decimal(Decimal::from(100)))
+        );
+    }
+
+    #[test]
+    fn test_token_amounts_are_exact() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(setup_identity_context());
+        vm.set_namespace("test_namespace");
+
+        let amount = |s: &str| s.parse::<Decimal>().unwrap();
+        let mut program = vec![
+            Op::CreateResource {
+                resource: "token".to_string(),
+                policy: ResourcePolicy::default(),
+            },
+            Op::Mint {
+                resource: "token".to_string(),
+                account: "user1".to_string(),
+                amount: amount("0.30"),
+                reason: None,
+            },
+        ];
+        // 0.1 three times over is not 0.3 in f64
+        for _ in 0..3 {
+            program.push(Op::Transfer {
+                resource: "token".to_string(),
+                from: "user1".to_string(),
+                to: "user2".to_string(),
+                amount: amount("0.1"),
+                reason: None,
+            });
+        }
+        program.push(Op::Balance {
+            resource: "token".to_string(),
+            account: "user1".to_string(),
+        });
+
+        vm.execute(&program).unwrap();
+        assert_eq!(vm.stack.top(), Some(&TypedValue::Decimal(Decimal::ZERO)));
+    }
+
+    #[test]
+    fn test_resource_policy_enforced() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        let auth = setup_identity_context();
+        let caller = auth.identity_did().to_string();
+        vm.set_auth_context(auth);
+        vm.set_namespace("test_namespace");
+
+        let mint = |resource: &str, amount: i64| Op::Mint {
+            resource: resource.to_string(),
+            account: "user1".to_string(),
+            amount: Decimal::from(amount),
+            reason: None,
+        };
+        vm.execute(&[
+            Op::CreateResource {
+                resource: "badge".to_string(),
+                policy: ResourcePolicy {
+                    transferable: false,
+                    issuance: IssuancePolicy::Capped {
+                        max_supply: Decimal::from(10),
+                    },
+                    minters: vec![caller],
+                    ..ResourcePolicy::default()
+                },
+            },
+            Op::CreateResource {
+                resource: "guarded".to_string(),
+                policy: ResourcePolicy {
+                    minters: vec!["did:key:someone-else".to_string()],
+                    ..ResourcePolicy::default()
+                },
+            },
+            mint("badge", 6),
+        ])
+        .unwrap();
+
+        // The cap counts units already minted
+        assert!(vm.execute(&[mint("badge", 5)]).is_err());
+        assert!(vm
+            .execute(&[Op::Transfer {
+                resource: "badge".to_string(),
+                from: "user1".to_string(),
+                to: "user2".to_string(),
+                amount: Decimal::from(1),
+                reason: None,
+            }])
+            .is_err());
+        assert!(matches!(
+            vm.execute(&[mint("guarded", 1)]),
+            Err(VMError::StorageError { ref details }) if details.starts_with("Permission denied")
+        ));
+    }
+
+    #[test]
+    fn test_escrow_follows_proposal_outcome() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        let auth = setup_identity_context();
+        vm.set_auth_context(auth.clone());
+        vm.set_namespace("test_namespace");
+
+        let set_state = |vm: &mut VM<InMemoryStorage>, proposal: &str, state: &str| {
+            vm.get_storage_backend_mut()
+                .unwrap()
+                .set(
+                    Some(&auth),
+                    "test_namespace",
+                    &format!("governance_proposals/{}/lifecycle", proposal),
+                    format!("{{\"state\":\"{}\"}}", state).into_bytes(),
+                )
+                .unwrap();
+        };
+        let lock = |proposal: &str| Op::EscrowLock {
+            proposal_id: proposal.to_string(),
+            resource: "token".to_string(),
+            from: "treasury".to_string(),
+            to: "project".to_string(),
+            amount: Decimal::from(40),
+        };
+        let balance = |vm: &mut VM<InMemoryStorage>, account: &str| {
+            vm.execute(&[Op::Balance {
+                resource: "token".to_string(),
+                account: account.to_string(),
+            }])
+            .unwrap();
+            vm.stack.top().cloned().unwrap()
+        };
+
+        set_state(&mut vm, "p1", "Voting");
+        set_state(&mut vm, "p2", "Voting");
+        vm.execute(&[
+            Op::CreateResource {
+                resource: "token".to_string(),
+                policy: ResourcePolicy::default(),
+            },
+            Op::Mint {
+                resource: "token".to_string(),
+                account: "treasury".to_string(),
+                amount: Decimal::from(100),
+                reason: None,
+            },
+            lock("p1"),
+            lock("p2"),
+        ])
+        .unwrap();
+        assert_eq!(balance(&mut vm, "treasury"), TypedValue::Decimal(Decimal::from(20)));
+
+        // Nothing moves while the proposals are undecided
+        assert!(vm.execute(&[Op::EscrowRelease("p1".to_string())]).is_err());
+        assert!(vm.execute(&[Op::EscrowRefund("p2".to_string())]).is_err());
+
+        set_state(&mut vm, "p1", "Executed");
+        set_state(&mut vm, "p2", "Rejected");
+        assert!(vm.execute(&[Op::EscrowRefund("p1".to_string())]).is_err());
+        assert!(vm.execute(&[Op::EscrowRelease("p2".to_string())]).is_err());
+        assert!(vm.execute(&[lock("p1")]).is_err());
+
+        // Settling twice pays out once
+        vm.execute(&[
+            Op::EscrowRelease("p1".to_string()),
+            Op::EscrowRelease("p1".to_string()),
+            Op::EscrowRefund("p2".to_string()),
+        ])
+        .unwrap();
+        assert_eq!(balance(&mut vm, "project"), TypedValue::Decimal(Decimal::from(40)));
+        assert_eq!(balance(&mut vm, "treasury"), TypedValue::Decimal(Decimal::from(60)));
+    }
+
+    #[test]
+    fn test_credit_limits_set_by_proposals() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(setup_identity_context());
+        vm.set_namespace("test_namespace");
+
+        let extend_credit = Op::SetCreditLimit {
+            resource: "hours".to_string(),
+            creditor: "commons".to_string(),
+            debtor: "alice".to_string(),
+            limit: Decimal::from(10),
+        };
+        let transfer = |amount: i64| Op::Transfer {
+            resource: "hours".to_string(),
+            from: "alice".to_string(),
+            to: "bob".to_string(),
+            amount: Decimal::from(amount),
+            reason: None,
+        };
+        vm.execute(&[Op::CreateResource {
+            resource: "hours".to_string(),
+            policy: ResourcePolicy::default(),
+        }])
+        .unwrap();
+
+        // Outside a proposal's logic, limits cannot change
+        assert!(matches!(
+            vm.execute(&[extend_credit.clone()]),
+            Err(VMError::GovernanceError(_))
+        ));
+        assert!(vm.execute(&[transfer(1)]).is_err());
+
+        vm.set_executing_proposal(Some("p1".to_string()));
+        vm.execute(&[extend_credit]).unwrap();
+        vm.set_executing_proposal(None);
+
+        vm.execute(&[
+            transfer(8),
+            Op::CreditLimit {
+                resource: "hours".to_string(),
+                account: "alice".to_string(),
+            },
+            Op::Balance {
+                resource: "hours".to_string(),
+                account: "alice".to_string(),
+            },
+        ])
+        .unwrap();
+        assert_eq!(
+            vm.stack.pop("test").unwrap(),
+            TypedValue::Decimal(Decimal::from(-8))
+        );
+        assert_eq!(
+            vm.stack.pop("test").unwrap(),
+            TypedValue::Decimal(Decimal::from(10))
+        );
+        assert!(vm.execute(&[transfer(3)]).is_err());
+    }
+
+    #[test]
+    fn test_allowances_limit_transfers_from_member_accounts() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(setup_identity_context());
+        vm.set_namespace("test_namespace");
+
+        let member = |did: &str, roles: &[&str]| {
+            let mut auth = AuthContext::new(did);
+            auth.add_role("global", "admin");
+            for role in roles {
+                auth.add_role("test_namespace", role);
+            }
+            auth
+        };
+        let spend = |amount: i64| Op::Transfer {
+            resource: "token".to_string(),
+            from: "did:key:alice".to_string(),
+            to: "project".to_string(),
+            amount: Decimal::from(amount),
+            reason: None,
+        };
+        let approve = |spender: &str, limit: i64| Op::Approve {
+            resource: "token".to_string(),
+            spender: spender.to_string(),
+            limit: Decimal::from(limit),
+        };
+        let allowance = |vm: &mut VM<InMemoryStorage>, spender: &str| {
+            vm.execute(&[Op::Allowance {
+                resource: "token".to_string(),
+                owner: "did:key:alice".to_string(),
+                spender: spender.to_string(),
+            }])
+            .unwrap();
+            vm.stack.pop("test").unwrap()
+        };
+
+        vm.execute(&[
+            Op::CreateResource {
+                resource: "token".to_string(),
+                policy: ResourcePolicy::default(),
+            },
+            Op::Mint {
+                resource: "token".to_string(),
+                account: "did:key:alice".to_string(),
+                amount: Decimal::from(100),
+                reason: None,
+            },
+        ])
+        .unwrap();
+        // Even an admin needs an allowance to spend a member's units
+        assert!(vm.execute(&[spend(1)]).is_err());
+
+        vm.set_auth_context(member("did:key:alice", &[]));
+        vm.execute(&[
+            spend(10),
+            approve("did:key:bob", 30),
+            approve("role:treasurer", 10),
+            approve("proposal:p1", 5),
+        ])
+        .unwrap();
+
+        vm.set_auth_context(member("did:key:bob", &[]));
+        vm.execute(&[spend(20)]).unwrap();
+        assert!(vm.execute(&[spend(15)]).is_err());
+        assert_eq!(
+            allowance(&mut vm, "did:key:bob"),
+            TypedValue::Decimal(Decimal::from(10))
+        );
+
+        vm.set_auth_context(member("did:key:carol", &["treasurer"]));
+        vm.execute(&[spend(10)]).unwrap();
+        assert_eq!(
+            allowance(&mut vm, "role:treasurer"),
+            TypedValue::Decimal(Decimal::ZERO)
+        );
+
+        vm.set_auth_context(member("did:key:dave", &[]));
+        assert!(vm.execute(&[spend(5)]).is_err());
+        vm.set_executing_proposal(Some("p1".to_string()));
+        vm.execute(&[spend(5)]).unwrap();
+        vm.set_executing_proposal(None);
+
+        // A zero limit revokes the allowance
+        vm.set_auth_context(member("did:key:alice", &[]));
+        vm.execute(&[approve("did:key:bob", 0)]).unwrap();
+        vm.set_auth_context(member("did:key:bob", &[]));
+        assert!(vm.execute(&[spend(1)]).is_err());
+
+        vm.execute(&[Op::Balance {
+            resource: "token".to_string(),
+            account: "project".to_string(),
+        }])
+        .unwrap();
+        assert_eq!(
+            vm.stack.pop("test").unwrap(),
+            TypedValue::Decimal(Decimal::from(45))
+        );
+    }
+
+    #[test]
+    fn test_spending_limits_need_a_proposal_to_exceed() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(setup_identity_context());
+        vm.set_namespace("test_namespace");
+
+        let member = |did: &str, roles: &[&str]| {
+            let mut auth = AuthContext::new(did);
+            auth.add_role("global", "admin");
+            for role in roles {
+                auth.add_role("test_namespace", role);
+            }
+            auth
+        };
+        let limit = |subject: &str, action: SpendingAction, amount: i64| Op::SetSpendingLimit {
+            resource: "token".to_string(),
+            subject: subject.to_string(),
+            action,
+            window: SpendingWindow::Day,
+            limit: Decimal::from(amount),
+        };
+        let pay = |amount: i64| Op::Transfer {
+            resource: "token".to_string(),
+            from: "treasury".to_string(),
+            to: "supplier".to_string(),
+            amount: Decimal::from(amount),
+            reason: None,
+        };
+        let mint = |amount: i64| Op::Mint {
+            resource: "token".to_string(),
+            account: "treasury".to_string(),
+            amount: Decimal::from(amount),
+            reason: None,
+        };
+
+        vm.execute(&[
+            Op::CreateResource {
+                resource: "token".to_string(),
+                policy: ResourcePolicy::default(),
+            },
+            mint(1000),
+        ])
+        .unwrap();
+
+        // Outside a proposal's logic, limits cannot change
+        assert!(matches!(
+            vm.execute(&[limit("role:treasurer", SpendingAction::Transfer, 100)]),
+            Err(VMError::GovernanceError(_))
+        ));
+        vm.set_executing_proposal(Some("p1".to_string()));
+        vm.execute(&[
+            limit("role:treasurer", SpendingAction::Transfer, 100),
+            limit("did:key:bob", SpendingAction::Transfer, 30),
+            limit("did:key:bob", SpendingAction::Mint, 10),
+        ])
+        .unwrap();
+        vm.set_executing_proposal(None);
+
+        // A role limit caps each holder separately
+        vm.set_auth_context(member("did:key:alice", &["treasurer"]));
+        vm.execute(&[pay(60), pay(40)]).unwrap();
+        assert!(matches!(
+            vm.execute(&[pay(1)]),
+            Err(VMError::GovernanceError(_))
+        ));
+        let warnings = vm
+            .get_events()
+            .iter()
+            .filter(|event| event.message.starts_with("spending_limit:"))
+            .count();
+        assert_eq!(warnings, 1);
+
+        // Every limit that covers the caller applies
+        vm.set_auth_context(member("did:key:bob", &["treasurer"]));
+        vm.execute(&[pay(30)]).unwrap();
+        assert!(vm.execute(&[pay(1)]).is_err());
+        assert!(vm.execute(&[mint(11)]).is_err());
+        vm.execute(&[mint(10)]).unwrap();
+
+        // Proposal logic goes over a limit, with a warning
+        vm.set_auth_context(member("did:key:alice", &["treasurer"]));
+        vm.set_executing_proposal(Some("p2".to_string()));
+        vm.execute(&[pay(500)]).unwrap();
+        vm.set_executing_proposal(None);
+        assert!(vm
+            .get_events()
+            .iter()
+            .any(|event| event.message.ends_with("allowed by proposal p2")));
+
+        // Members without a limit are not capped
+        vm.set_auth_context(member("did:key:carol", &[]));
+        vm.execute(&[pay(200)]).unwrap();
+    }
+
+    #[test]
+    fn test_exchange_at_governed_rate() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(setup_identity_context());
+        vm.set_namespace("test_namespace");
+
+        let set_rate = |rate: &str| Op::SetExchangeRate {
+            from_resource: "hours".to_string(),
+            to_resource: "dollars".to_string(),
+            rate: rate.parse().unwrap(),
+            reserve: "bridge".to_string(),
+            max_amount: Some(Decimal::from(10)),
+        };
+        let exchange = |account: &str, amount: i64, min_received: &str| Op::Exchange {
+            from_resource: "hours".to_string(),
+            to_resource: "dollars".to_string(),
+            account: account.to_string(),
+            amount: Decimal::from(amount),
+            min_received: min_received.parse().unwrap(),
+        };
+        let balance = |vm: &mut VM<InMemoryStorage>, resource: &str, account: &str| {
+            vm.execute(&[Op::Balance {
+                resource: resource.to_string(),
+                account: account.to_string(),
+            }])
+            .unwrap();
+            vm.stack.pop("test").unwrap()
+        };
+
+        vm.execute(&[
+            Op::CreateResource {
+                resource: "hours".to_string(),
+                policy: ResourcePolicy::default(),
+            },
+            Op::CreateResource {
+                resource: "dollars".to_string(),
+                policy: ResourcePolicy {
+                    decimals: Some(2),
+                    ..ResourcePolicy::default()
+                },
+            },
+            Op::Mint {
+                resource: "hours".to_string(),
+                account: "alice".to_string(),
+                amount: Decimal::from(20),
+                reason: None,
+            },
+            Op::Mint {
+                resource: "dollars".to_string(),
+                account: "bridge".to_string(),
+                amount: Decimal::from(100),
+                reason: None,
+            },
+        ])
+        .unwrap();
+
+        // Outside a proposal's logic, rates cannot change
+        assert!(matches!(
+            vm.execute(&[set_rate("12.505")]),
+            Err(VMError::GovernanceError(_))
+        ));
+        assert!(vm.execute(&[exchange("alice", 1, "0")]).is_err());
+
+        vm.set_executing_proposal(Some("p1".to_string()));
+        vm.execute(&[set_rate("12.505")]).unwrap();
+        vm.set_executing_proposal(None);
+
+        // Paid out rounded down to the cents dollars allow
+        vm.execute(&[exchange("alice", 3, "37.5")]).unwrap();
+        assert_eq!(
+            vm.stack.pop("test").unwrap(),
+            TypedValue::Decimal("37.51".parse().unwrap())
+        );
+
+        // Too little received, over the limit, or more than the reserve holds
+        assert!(vm.execute(&[exchange("alice", 1, "13")]).is_err());
+        assert!(vm.execute(&[exchange("alice", 11, "0")]).is_err());
+        assert!(vm.execute(&[exchange("alice", 6, "0")]).is_err());
+
+        // Nothing moves when an exchange fails
+        assert_eq!(
+            balance(&mut vm, "hours", "alice"),
+            TypedValue::Decimal(Decimal::from(17))
+        );
+        assert_eq!(
+            balance(&mut vm, "hours", "bridge"),
+            TypedValue::Decimal(Decimal::from(3))
+        );
+        assert_eq!(
+            balance(&mut vm, "dollars", "bridge"),
+            TypedValue::Decimal("62.49".parse().unwrap())
+        );
+
+        // Only its owner may exchange what an identity's account holds
+        assert!(matches!(
+            vm.execute(&[exchange("did:key:bob", 1, "0")]),
+            Err(VMError::PermissionDenied { .. })
+        ));
+
+        // A zero rate stops exchanges
+        vm.set_executing_proposal(Some("p2".to_string()));
+        vm.execute(&[set_rate("0")]).unwrap();
+        vm.set_executing_proposal(None);
+        vm.execute(&[Op::ExchangeRate {
+            from_resource: "hours".to_string(),
+            to_resource: "dollars".to_string(),
+        }])
+        .unwrap();
+        assert_eq!(
+            vm.stack.pop("test").unwrap(),
+            TypedValue::Decimal(Decimal::ZERO)
+        );
+        assert!(vm.execute(&[exchange("alice", 1, "0")]).is_err());
+    }
+
+    #[test]
+    fn test_wasm_modules_need_governance_approval() {
+        // (module (func (export "add") (param f64 f64) (result f64)
+        //   local.get 0 local.get 1 f64.add))
+        const ADD_MODULE: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x07, 0x01, 0x60, 0x02, 0x7c,
+            0x7c, 0x01, 0x7c, 0x03, 0x02, 0x01, 0x00, 0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64,
+            0x00, 0x00, 0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0xa0, 0x0b,
+        ];
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        let auth = setup_identity_context();
+        vm.set_auth_context(auth.clone());
+        vm.set_namespace("test_namespace");
+
+        let hash = put_blob(
+            vm.get_storage_backend_mut().unwrap(),
+            Some(&auth),
+            "test_namespace",
+            ADD_MODULE.to_vec(),
+        )
+        .unwrap();
+        let approve = |hash: &str| Op::ApproveWasmModule {
+            hash: hash.to_string(),
+            capabilities: vec![WasmCapability::StorageRead],
+        };
+        let call = [
+            Op::Push(TypedValue::Number(2.0)),
+            Op::Push(TypedValue::Number(3.5)),
+            Op::CallWasm {
+                hash: hash.clone(),
+                function: "add".to_string(),
+                args: 2,
+            },
+        ];
+
+        // Neither approved outside a proposal's logic nor called unapproved
+        assert!(matches!(
+            vm.execute(&[approve(&hash)]),
+            Err(VMError::GovernanceError(_))
+        ));
+        assert!(matches!(
+            vm.execute(&call),
+            Err(VMError::GovernanceError(_))
+        ));
+
+        // Only modules stored as blobs can be approved
+        vm.set_executing_proposal(Some("p1".to_string()));
+        assert!(vm.execute(&[approve(&"0".repeat(64))]).is_err());
+        vm.execute(&[approve(&hash)]).unwrap();
+        vm.set_executing_proposal(None);
+
+        let approval = wasm::load_approval(
+            vm.get_storage_backend().unwrap(),
+            Some(&auth),
+            "test_namespace",
+            &hash,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(approval.proposal_id, "p1");
+        assert!(approval.allows(WasmCapability::StorageRead));
+        assert!(!approval.allows(WasmCapability::Economic));
+
+        #[cfg(feature = "wasm")]
+        {
+            vm.stack.clear();
+            vm.execute(&call).unwrap();
+            assert_eq!(vm.stack.pop("test").unwrap(), TypedValue::Number(5.5));
+            assert!(matches!(
+                vm.execute(&[Op::CallWasm {
+                    hash: hash.clone(),
+                    function: "missing".to_string(),
+                    args: 0,
+                }]),
+                Err(VMError::WasmError(_))
+            ));
+        }
+        #[cfg(not(feature = "wasm"))]
+        assert!(matches!(vm.execute(&call), Err(VMError::NotImplemented(_))));
+    }
+
+    #[test]
+    fn test_membership_changes_need_governance() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        let auth = setup_identity_context();
+        vm.set_auth_context(auth.clone());
+        vm.set_namespace("test_namespace");
+
+        let did = create_test_identity("alice", "member").did;
+        let onboard = Op::OnboardMember {
+            did: did.clone(),
+            username: "alice".to_string(),
+            roles: vec!["member".to_string(), "treasurer".to_string()],
+        };
+        assert!(matches!(
+            vm.execute(&[onboard.clone()]),
+            Err(VMError::GovernanceError(_))
+        ));
+
+        vm.set_executing_proposal(Some("admit-alice".to_string()));
+        vm.execute(&[onboard.clone()]).unwrap();
+        // Admitting an active member again fails
+        assert!(vm.execute(&[onboard]).is_err());
+
+        let storage = vm.get_storage_backend().unwrap();
+        let identity: Identity = storage
+            .get_json(Some(&auth), "identity", &format!("identities/{}", did))
+            .unwrap();
+        assert_eq!(identity.public_username(), "alice");
+        let roles: Vec<RoleAssignment> = storage
+            .get_json(Some(&auth), "identity", &roles_key(&did))
+            .unwrap();
+        assert_eq!(roles.len(), 2);
+        assert!(roles.iter().all(|r| r.namespace == "test_namespace"));
+        let record = membership::load_member(storage, Some(&auth), "test_namespace", &did)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.status, MemberStatus::Active);
+        assert_eq!(record.admitted_by, "admit-alice");
+
+        vm.set_executing_proposal(Some("retire-alice".to_string()));
+        vm.execute(&[Op::OffboardMember(did.clone())]).unwrap();
+        assert!(vm.execute(&[Op::OffboardMember(did.clone())]).is_err());
+
+        let storage = vm.get_storage_backend().unwrap();
+        let roles: Vec<RoleAssignment> = storage
+            .get_json(Some(&auth), "identity", &roles_key(&did))
+            .unwrap();
+        assert!(roles.is_empty());
+        let memberships: Vec<String> = storage
+            .get_json(Some(&auth), "identity", &memberships_key(&did))
+            .unwrap();
+        assert!(memberships.is_empty());
+        let members = membership::list_members(storage, Some(&auth), "test_namespace").unwrap();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].status, MemberStatus::Retired);
+        assert_eq!(members[0].retired_by.as_deref(), Some("retire-alice"));
+        assert!(vm
+            .get_events()
+            .iter()
+            .any(|e| e.category == "membership" && e.message.contains("offboarded")));
+    }
+
+    #[test]
+    fn test_repeated_reads_are_served_from_cache() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(setup_identity_context());
+        vm.set_namespace("test_namespace");
+        vm.execute(&[
+            Op::Push(TypedValue::Number(1.0)),
+            Op::StoreP("votes/p1/alice".to_string()),
+            Op::Push(TypedValue::Number(1.0)),
+            Op::StoreP("votes/p1/bob".to_string()),
+            Op::Push(TypedValue::String("Voting".to_string())),
+            Op::StoreP("proposals/p1/lifecycle".to_string()),
+        ])
+        .unwrap();
+
+        // A tally that checks the lifecycle before each vote it counts
+        let mut tally = Vec::new();
+        for voter in ["alice", "bob", "alice", "bob"] {
+            tally.push(Op::LoadP("proposals/p1/lifecycle".to_string()));
+            tally.push(Op::Pop);
+            tally.push(Op::LoadP(format!("votes/p1/{}", voter)));
+        }
+        tally.extend([Op::Add, Op::Add, Op::Add]);
+        vm.execute(&tally).unwrap();
+        assert_eq!(vm.stack.pop("test").unwrap(), TypedValue::Number(4.0));
+        let stats = vm.storage_read_stats();
+        assert_eq!(stats.backend_reads, 3);
+        assert_eq!(stats.cache_hits, 5);
+
+        // A write empties the cache, so the next read sees the new value
+        vm.execute(&[
+            Op::LoadP("votes/p1/alice".to_string()),
+            Op::Push(TypedValue::Number(0.0)),
+            Op::StoreP("votes/p1/alice".to_string()),
+            Op::LoadP("votes/p1/alice".to_string()),
+        ])
+        .unwrap();
+        assert_eq!(vm.stack.pop("test").unwrap(), TypedValue::Number(0.0));
+        assert_eq!(vm.storage_read_stats().backend_reads, 2);
+        assert_eq!(vm.storage_read_stats().cache_hits, 0);
+    }
+
+    #[test]
+    fn test_distribute_splits_by_weight() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(setup_identity_context());
+        vm.set_namespace("test_namespace");
+
+        let balance = |vm: &mut VM<InMemoryStorage>, account: &str| {
+            vm.execute(&[Op::Balance {
+                resource: "hours".to_string(),
+                account: account.to_string(),
+            }])
+            .unwrap();
+            vm.stack.pop("test").unwrap()
+        };
+        let distribute = || Op::Distribute {
+            resource: "hours".to_string(),
+            pool_key: "surplus".to_string(),
+            weight_key: "patronage".to_string(),
+        };
+
+        let mut setup = vec![
+            Op::CreateResource {
+                resource: "hours".to_string(),
+                policy: ResourcePolicy {
+                    decimals: Some(2),
+                    ..ResourcePolicy::default()
+                },
+            },
+            Op::Mint {
+                resource: "hours".to_string(),
+                account: "surplus".to_string(),
+                amount: Decimal::from(100),
+                reason: None,
+            },
+        ];
+        for (member, weight) in [("alice", 3.0), ("bob", 1.0), ("carol", 2.0), ("dave", 0.0)] {
+            setup.push(Op::Push(TypedValue::Number(weight)));
+            setup.push(Op::StoreP(format!("patronage/{}", member)));
+        }
+        vm.execute(&setup).unwrap();
+
+        vm.execute(&[Op::Push(TypedValue::Decimal(Decimal::from(100))), distribute()])
+            .unwrap();
+        assert_eq!(balance(&mut vm, "alice"), TypedValue::Decimal(Decimal::from(50)));
+        assert_eq!(balance(&mut vm, "bob"), TypedValue::Decimal(Decimal::new(1666, 2)));
+        assert_eq!(balance(&mut vm, "carol"), TypedValue::Decimal(Decimal::new(3333, 2)));
+        assert_eq!(balance(&mut vm, "dave"), TypedValue::Decimal(Decimal::ZERO));
+        // Rounding leaves a remainder in the pool
+        assert_eq!(balance(&mut vm, "surplus"), TypedValue::Decimal(Decimal::new(1, 2)));
+
+        // A pool that cannot cover every share pays none of them
+        vm.execute(&[Op::Push(TypedValue::Decimal(Decimal::from(10))), distribute()])
+            .unwrap_err();
+        assert_eq!(balance(&mut vm, "alice"), TypedValue::Decimal(Decimal::from(50)));
+        assert_eq!(balance(&mut vm, "surplus"), TypedValue::Decimal(Decimal::new(1, 2)));
+
+        let dag = vm.get_dag().unwrap();
+        let distributions: Vec<_> = dag
+            .nodes()
+            .iter()
+            .filter(|node| node.data.type_name() == "Distributed")
+            .collect();
+        assert_eq!(distributions.len(), 1);
+        match &distributions[0].data {
+            NodeData::Distributed { amount, shares, .. } => {
+                assert_eq!(amount, "99.99");
+                assert_eq!(shares.len(), 3);
+            }
+            other => panic!("unexpected node {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bounty_claim_verification_and_payout() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(setup_identity_context());
+        vm.set_namespace("test_namespace");
+
+        let member = |did: &str, roles: &[&str]| {
+            let mut auth = AuthContext::new(did);
+            auth.add_role("global", "admin");
+            for role in roles {
+                auth.add_role("test_namespace", role);
+            }
+            auth
+        };
+        let balance = |vm: &mut VM<InMemoryStorage>, account: &str| {
+            vm.execute(&[Op::Balance {
+                resource: "token".to_string(),
+                account: account.to_string(),
+            }])
+            .unwrap();
+            vm.stack.pop("test").unwrap()
+        };
+        let create = |id: &str, verification: BountyVerification| Op::CreateBounty {
+            id: id.to_string(),
+            resource: "token".to_string(),
+            funder: "treasury".to_string(),
+            amount: Decimal::from(30),
+            verification,
+            description: format!("Work for {}", id),
+        };
+        let claim = |id: &str| Op::ClaimBounty {
+            id: id.to_string(),
+            evidence: "https://example.org/pr/12".to_string(),
+        };
+        let verify = |id: &str, approve: bool| Op::VerifyBounty {
+            id: id.to_string(),
+            approve,
+        };
+
+        vm.execute(&[
+            Op::CreateResource {
+                resource: "token".to_string(),
+                policy: ResourcePolicy::default(),
+            },
+            Op::Mint {
+                resource: "token".to_string(),
+                account: "treasury".to_string(),
+                amount: Decimal::from(100),
+                reason: None,
+            },
+            create(
+                "docs",
+                BountyVerification::Verifier {
+                    role: "reviewer".to_string(),
+                },
+            ),
+            create("audit", BountyVerification::Votes { threshold: 2 }),
+            create("spare", BountyVerification::Votes { threshold: 1 }),
+        ])
+        .unwrap();
+        assert_eq!(balance(&mut vm, "treasury"), TypedValue::Decimal(Decimal::from(10)));
+
+        // Only the verifier role decides a verifier-checked claim
+        vm.set_auth_context(member("did:key:alice", &[]));
+        vm.execute(&[claim("docs"), claim("audit")]).unwrap();
+        assert!(vm.execute(&[claim("docs")]).is_err());
+        assert!(vm.execute(&[verify("docs", true)]).is_err());
+        vm.set_auth_context(member("did:key:rita", &["reviewer"]));
+        vm.execute(&[verify("docs", true)]).unwrap();
+        assert_eq!(
+            balance(&mut vm, "did:key:alice"),
+            TypedValue::Decimal(Decimal::from(30))
+        );
+
+        // Voted claims need the threshold, and claimants cannot vote
+        vm.set_auth_context(member("did:key:alice", &[]));
+        assert!(vm.execute(&[verify("audit", true)]).is_err());
+        vm.set_auth_context(member("did:key:bob", &[]));
+        vm.execute(&[verify("audit", false)]).unwrap();
+        assert!(vm.execute(&[verify("audit", false)]).is_err());
+        vm.set_auth_context(member("did:key:carol", &[]));
+        vm.execute(&[verify("audit", false)]).unwrap();
+        let storage = vm.get_storage_backend().unwrap();
+        let audit = storage
+            .get_bounty(vm.executor.auth_context.as_ref(), "test_namespace", "audit")
+            .unwrap();
+        assert_eq!(audit.status, BountyStatus::Open);
+        assert!(audit.claim.is_none());
+
+        // Only an open bounty's creator may cancel it
+        assert!(vm.execute(&[Op::CancelBounty("spare".to_string())]).is_err());
+        vm.set_auth_context(setup_identity_context());
+        vm.execute(&[Op::CancelBounty("spare".to_string())]).unwrap();
+        assert_eq!(balance(&mut vm, "treasury"), TypedValue::Decimal(Decimal::from(40)));
+
+        let steps: Vec<&str> = vm
+            .get_dag()
+            .unwrap()
+            .nodes()
+            .iter()
+            .map(|node| node.data.type_name())
+            .filter(|name| name.starts_with("Bounty"))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                "BountyCreated",
+                "BountyCreated",
+                "BountyCreated",
+                "BountyClaimed",
+                "BountyClaimed",
+                "BountyVerified",
+                "BountyPaid",
+                "BountyVerified",
+                "BountyVerified",
+                "BountyCancelled",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_demurrage_decays_idle_balances() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        let auth = setup_identity_context();
+        vm.set_auth_context(auth.clone());
+        vm.set_namespace("test_namespace");
+
+        let balance = |vm: &mut VM<InMemoryStorage>, account: &str| {
+            vm.execute(&[Op::Balance {
+                resource: "credits".to_string(),
+                account: account.to_string(),
+            }])
+            .unwrap();
+            vm.stack.pop("test").unwrap()
+        };
+        vm.execute(&[
+            Op::CreateResource {
+                resource: "credits".to_string(),
+                policy: ResourcePolicy {
+                    demurrage: Some(DemurragePolicy {
+                        rate: Decimal::new(5, 1),
+                        period_secs: 100,
+                        grace_secs: 50,
+                    }),
+                    ..ResourcePolicy::default()
+                },
+            },
+            Op::Mint {
+                resource: "credits".to_string(),
+                account: "alice".to_string(),
+                amount: Decimal::from(100),
+                reason: None,
+            },
+        ])
+        .unwrap();
+        assert_eq!(balance(&mut vm, "alice"), TypedValue::Decimal(Decimal::from(100)));
+
+        // Idle through the grace period and two more periods
+        let since = crate::storage::utils::now_with_default() - 250;
+        vm.get_storage_backend_mut()
+            .unwrap()
+            .set(
+                Some(&auth),
+                "test_namespace",
+                "resources/credits/demurrage/alice",
+                serde_json::to_vec(&DemurrageState::new(since)).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(balance(&mut vm, "alice"), TypedValue::Decimal(Decimal::from(25)));
+
+        // Reads leave storage alone until the decay is materialized, once
+        let storage = vm.get_storage_backend_mut().unwrap();
+        let stored = storage
+            .get(Some(&auth), "test_namespace", "resources/credits/accounts/alice")
+            .unwrap();
+        assert_eq!(stored, b"100");
+        for _ in 0..2 {
+            storage
+                .apply_demurrage(Some(&auth), "test_namespace", "credits")
+                .unwrap();
+        }
+        let metadata = storage
+            .get_resource(Some(&auth), "test_namespace", "credits")
+            .unwrap();
+        assert_eq!(metadata.total_supply, Decimal::from(25));
+        assert_eq!(balance(&mut vm, "alice"), TypedValue::Decimal(Decimal::from(25)));
+
+        // Spending restarts the grace period for both accounts
+        vm.execute(&[Op::Transfer {
+            resource: "credits".to_string(),
+            from: "alice".to_string(),
+            to: "bob".to_string(),
+            amount: Decimal::from(20),
+            reason: None,
+        }])
+        .unwrap();
+        assert_eq!(balance(&mut vm, "alice"), TypedValue::Decimal(Decimal::from(5)));
+        assert_eq!(balance(&mut vm, "bob"), TypedValue::Decimal(Decimal::from(20)));
+    }
+
+    #[test]
+    fn test_missing_key_loads_null() {
+        let mut vm = VM::with_storage_backend(InMemoryStorage::new());
+        vm.set_auth_context(setup_identity_context());
+        vm.set_namespace("test_ns");
+        vm.set_missing_key_behavior(MissingKeyBehavior::Null);
+
+        let program = vec![
+            Op::LoadP("quorum".to_string()),
+            Op::Dup,
+            Op::IsNull,
+            Op::Store("missing".to_string()),
+            Op::Push(TypedValue::Number(0.5)),
+            Op::Coalesce,
+        ];
+
+        vm.execute(&program).unwrap();
+        assert_eq!(vm.memory.load("missing").unwrap(), TypedValue::Boolean(true));
+        assert_eq!(vm.stack.top(), Some(&TypedValue::Number(0.5)));
+
+        // A value that is present is kept
+        vm.execute(&[
+            Op::Push(TypedValue::Number(0.75)),
+            Op::Push(TypedValue::Number(0.5)),
+            Op::Coalesce,
+        ])
+        .unwrap();
+        assert_eq!(vm.stack.top(), Some(&TypedValue::Number(0.75)));
+    }
+
+    #[test]
+    fn test_strict_typing_rejects_coercion() {
+        let program = vec![
+            Op::Push(TypedValue::Number(1.0)),
+            Op::Push(TypedValue::Boolean(true)),
+            Op::Add,
+        ];
+
+        let mut vm = VM::<InMemoryStorage>::new();
+        vm.execute(&program).unwrap();
+        assert_eq!(vm.stack.top(), Some(&TypedValue::Number(2.0)));
+
+        let mut vm = VM::<InMemoryStorage>::new();
+        let mut strict = vec![Op::Typing(TypingMode::Strict)];
+        strict.extend(program);
+        assert!(matches!(
+            vm.execute(&strict),
+            Err(VMError::TypeMismatch { ref operation, .. }) if operation == "add"
+        ));
     }
 }
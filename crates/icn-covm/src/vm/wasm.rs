@@ -0,0 +1,486 @@
+//! Governance-approved WASM modules
+//!
+//! Logic too involved for the stack language can be compiled to WASM and
+//! called from a program rather than added to the VM as new operations. A
+//! module's bytes are stored as a content-addressed blob (see
+//! `storage::blobs`), for instance by attaching the file to the proposal
+//! that approves it. A proposal's logic approves the module by its hash with
+//! `ApproveWasmModule`, granting it a set of capabilities, and only then can
+//! `CallWasm` run it. Approvals are stored under `wasm/modules/{hash}`.
+//!
+//! Modules call back into the VM through functions imported from the `icn`
+//! module. Strings are passed as a pointer and length into the module's
+//! exported `memory`, and numbers as `f64`:
+//!
+//! - `log(ptr, len)`: add a `wasm` event; needs no capability
+//! - `storage_get(key_ptr, key_len) -> f64`: read a number from a key in the
+//!   namespace, zero if it is missing; needs `storage_read`
+//! - `storage_set(key_ptr, key_len, value)`: write a number to a key under
+//!   the module's own `wasm/state/{hash}/` prefix; needs `storage_write`
+//! - `balance(resource_ptr, resource_len, account_ptr, account_len) -> f64`:
+//!   needs `economic`
+//! - `transfer(resource_ptr, resource_len, from_ptr, from_len, to_ptr,
+//!   to_len, amount)`: needs `economic`; spends out of an identity's account
+//!   only when that identity is the caller, and within spending limits
+//!
+//! Calls run as the caller, so storage permissions apply. They run against
+//! a copy of the storage that replaces it only if the call returns without
+//! trapping, and with a fuel budget that traps when it runs out. Running
+//! modules needs the `wasm` feature.
+
+use crate::storage::auth::AuthContext;
+use crate::storage::blobs::get_blob;
+use crate::storage::errors::{StorageError, StorageResult};
+use crate::storage::traits::{Storage, StorageBackend, StorageExtensions};
+use crate::storage::utils::Timestamp;
+use crate::vm::errors::VMError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Key prefix under which module approvals are stored
+pub const MODULE_PREFIX: &str = "wasm/modules/";
+
+/// Fuel one call may use, roughly one unit per instruction
+pub const DEFAULT_FUEL: u64 = 10_000_000;
+
+/// Host functions an approved module may call beyond `log`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WasmCapability {
+    /// Read numbers from the namespace with `storage_get`
+    StorageRead,
+    /// Write numbers under the module's own prefix with `storage_set`
+    StorageWrite,
+    /// Read balances and make transfers
+    Economic,
+}
+
+impl WasmCapability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WasmCapability::StorageRead => "storage_read",
+            WasmCapability::StorageWrite => "storage_write",
+            WasmCapability::Economic => "economic",
+        }
+    }
+}
+
+impl fmt::Display for WasmCapability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WasmCapability {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "storage_read" => Ok(WasmCapability::StorageRead),
+            "storage_write" => Ok(WasmCapability::StorageWrite),
+            "economic" => Ok(WasmCapability::Economic),
+            other => Err(format!(
+                "Unknown WASM capability '{}', expected storage_read, storage_write or economic",
+                other
+            )),
+        }
+    }
+}
+
+/// A module a proposal approved for `CallWasm`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WasmModuleApproval {
+    /// SHA-256 hash of the module, addressing its blob
+    pub hash: String,
+    pub capabilities: Vec<WasmCapability>,
+    /// Proposal whose execution approved the module
+    pub proposal_id: String,
+    pub approved_at: Timestamp,
+}
+
+impl WasmModuleApproval {
+    pub fn allows(&self, capability: WasmCapability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+/// What a module call returned
+#[derive(Debug, Clone, PartialEq)]
+pub struct WasmOutcome {
+    pub result: f64,
+    /// Messages the module passed to `log`
+    pub logs: Vec<String>,
+    pub fuel_used: u64,
+}
+
+/// Everything a call needs besides the storage
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+struct Call {
+    auth: Option<AuthContext>,
+    namespace: String,
+    approval: WasmModuleApproval,
+    executing_proposal: Option<String>,
+}
+
+/// Storage key of a module's approval
+pub fn module_key(hash: &str) -> String {
+    format!("{}{}", MODULE_PREFIX, hash)
+}
+
+/// Storage key a module's `storage_set` writes for `key`
+pub fn state_key(hash: &str, key: &str) -> String {
+    format!("wasm/state/{}/{}", hash, key)
+}
+
+/// Approve the module stored as the blob `approval.hash`
+///
+/// Approving again replaces the capabilities granted before.
+pub fn approve_module<S>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    approval: &WasmModuleApproval,
+) -> StorageResult<()>
+where
+    S: StorageBackend + ?Sized,
+{
+    let bytes = get_blob(storage, auth, namespace, &approval.hash)?;
+    runtime::validate(&bytes)?;
+    let json = serde_json::to_vec(approval).map_err(|e| StorageError::SerializationError {
+        data_type: "WasmModuleApproval".to_string(),
+        details: e.to_string(),
+    })?;
+    storage.set(auth, namespace, &module_key(&approval.hash), json)
+}
+
+/// The approval of a module, if it has one
+pub fn load_approval<S>(
+    storage: &S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    hash: &str,
+) -> StorageResult<Option<WasmModuleApproval>>
+where
+    S: StorageBackend + StorageExtensions,
+{
+    let key = module_key(hash);
+    if !storage.contains(auth, namespace, &key)? {
+        return Ok(None);
+    }
+    storage.get_json(auth, namespace, &key).map(Some)
+}
+
+/// Call `function` of an approved module with `args`
+///
+/// The storage is replaced by the call's copy only if the call succeeds.
+pub fn call_module<S>(
+    storage: &mut S,
+    auth: Option<&AuthContext>,
+    namespace: &str,
+    executing_proposal: Option<&str>,
+    hash: &str,
+    function: &str,
+    args: &[f64],
+) -> Result<WasmOutcome, VMError>
+where
+    S: Storage + 'static,
+{
+    let approval = load_approval(storage, auth, namespace, hash)?.ok_or_else(|| {
+        VMError::GovernanceError(format!("WASM module {} has not been approved", hash))
+    })?;
+    let bytes = get_blob(storage, auth, namespace, hash)?;
+    let call = Call {
+        auth: auth.cloned(),
+        namespace: namespace.to_string(),
+        approval,
+        executing_proposal: executing_proposal.map(str::to_string),
+    };
+    let (outcome, updated) = runtime::run(&bytes, storage.clone(), call, function, args)?;
+    *storage = updated;
+    Ok(outcome)
+}
+
+#[cfg(feature = "wasm")]
+mod runtime {
+    use super::*;
+    use crate::storage::resource::SpendingAction;
+    use crate::storage::traits::EconomicOperations;
+    use rust_decimal::prelude::ToPrimitive;
+    use rust_decimal::Decimal;
+    use wasmi::{Caller, Config, Engine, Extern, Linker, Module, Store, Val};
+
+    /// Longest string a module may pass to a host function
+    const MAX_STRING: usize = 64 * 1024;
+
+    struct Host<S> {
+        storage: S,
+        call: Call,
+        logs: Vec<String>,
+    }
+
+    fn trap(message: impl fmt::Display) -> wasmi::Error {
+        wasmi::Error::new(message.to_string())
+    }
+
+    fn wasm_error(e: impl fmt::Display) -> VMError {
+        VMError::WasmError(e.to_string())
+    }
+
+    fn engine() -> Engine {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        Engine::new(&config)
+    }
+
+    pub(super) fn validate(bytes: &[u8]) -> StorageResult<()> {
+        Module::new(&engine(), bytes)
+            .map(|_| ())
+            .map_err(|e| StorageError::InvalidDataFormat {
+                expected: "WASM module".to_string(),
+                received: format!("{} bytes", bytes.len()),
+                details: e.to_string(),
+            })
+    }
+
+    fn read_str<S>(
+        caller: &Caller<'_, Host<S>>,
+        ptr: i32,
+        len: i32,
+    ) -> Result<String, wasmi::Error> {
+        let len = usize::try_from(len).map_err(|_| trap("negative string length"))?;
+        if len > MAX_STRING {
+            return Err(trap(format!("strings are limited to {} bytes", MAX_STRING)));
+        }
+        let memory = caller
+            .get_export("memory")
+            .and_then(Extern::into_memory)
+            .ok_or_else(|| trap("module exports no memory"))?;
+        let mut buffer = vec![0; len];
+        memory
+            .read(caller, ptr as u32 as usize, &mut buffer)
+            .map_err(trap)?;
+        String::from_utf8(buffer).map_err(|_| trap("string is not UTF-8"))
+    }
+
+    fn require<S>(
+        caller: &Caller<'_, Host<S>>,
+        capability: WasmCapability,
+    ) -> Result<(), wasmi::Error> {
+        if caller.data().call.approval.allows(capability) {
+            Ok(())
+        } else {
+            Err(trap(format!("module was not granted {}", capability)))
+        }
+    }
+
+    fn link<S: Storage + 'static>(linker: &mut Linker<Host<S>>) -> Result<(), wasmi::Error> {
+        linker.func_wrap(
+            "icn",
+            "log",
+            |caller: Caller<'_, Host<S>>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+                let message = read_str(&caller, ptr, len)?;
+                let mut caller = caller;
+                caller.data_mut().logs.push(message);
+                Ok(())
+            },
+        )?;
+        linker.func_wrap(
+            "icn",
+            "storage_get",
+            |caller: Caller<'_, Host<S>>, ptr: i32, len: i32| -> Result<f64, wasmi::Error> {
+                require(&caller, WasmCapability::StorageRead)?;
+                let key = read_str(&caller, ptr, len)?;
+                let host = caller.data();
+                let auth = host.call.auth.as_ref();
+                match host.storage.get(auth, &host.call.namespace, &key) {
+                    Ok(bytes) => String::from_utf8_lossy(&bytes)
+                        .trim()
+                        .parse()
+                        .map_err(|_| trap(format!("{} does not hold a number", key))),
+                    Err(StorageError::NotFound { .. }) => Ok(0.0),
+                    Err(e) => Err(trap(e)),
+                }
+            },
+        )?;
+        linker.func_wrap(
+            "icn",
+            "storage_set",
+            |caller: Caller<'_, Host<S>>,
+             ptr: i32,
+             len: i32,
+             value: f64|
+             -> Result<(), wasmi::Error> {
+                require(&caller, WasmCapability::StorageWrite)?;
+                let key = read_str(&caller, ptr, len)?;
+                let mut caller = caller;
+                let host = caller.data_mut();
+                let key = state_key(&host.call.approval.hash, &key);
+                let auth = host.call.auth.as_ref();
+                host.storage
+                    .set(
+                        auth,
+                        &host.call.namespace,
+                        &key,
+                        value.to_string().into_bytes(),
+                    )
+                    .map_err(trap)
+            },
+        )?;
+        linker.func_wrap(
+            "icn",
+            "balance",
+            |caller: Caller<'_, Host<S>>,
+             resource_ptr: i32,
+             resource_len: i32,
+             account_ptr: i32,
+             account_len: i32|
+             -> Result<f64, wasmi::Error> {
+                require(&caller, WasmCapability::Economic)?;
+                let resource = read_str(&caller, resource_ptr, resource_len)?;
+                let account = read_str(&caller, account_ptr, account_len)?;
+                let host = caller.data();
+                let auth = host.call.auth.as_ref();
+                let (balance, _) = host
+                    .storage
+                    .get_balance(auth, &host.call.namespace, &resource, &account)
+                    .map_err(trap)?;
+                Ok(balance.to_f64().unwrap_or(0.0))
+            },
+        )?;
+        linker.func_wrap(
+            "icn",
+            "transfer",
+            |caller: Caller<'_, Host<S>>,
+             resource_ptr: i32,
+             resource_len: i32,
+             from_ptr: i32,
+             from_len: i32,
+             to_ptr: i32,
+             to_len: i32,
+             amount: f64|
+             -> Result<(), wasmi::Error> {
+                require(&caller, WasmCapability::Economic)?;
+                let resource = read_str(&caller, resource_ptr, resource_len)?;
+                let from = read_str(&caller, from_ptr, from_len)?;
+                let to = read_str(&caller, to_ptr, to_len)?;
+                let amount = Decimal::try_from(amount)
+                    .map_err(|_| trap(format!("invalid amount {}", amount)))?;
+
+                let mut caller = caller;
+                let Host { storage, call, .. } = caller.data_mut();
+                let auth = call.auth.as_ref();
+                let namespace = call.namespace.as_str();
+                // Modules spend only what the caller could spend directly
+                let caller_id = auth.map(|auth| auth.user_id());
+                if from.starts_with("did:") && caller_id != Some(from.as_str()) {
+                    return Err(trap(format!(
+                        "{} may not transfer out of {}",
+                        caller_id.unwrap_or("anonymous"),
+                        from
+                    )));
+                }
+                let breached = storage
+                    .check_spending_limits(
+                        auth,
+                        namespace,
+                        &resource,
+                        SpendingAction::Transfer,
+                        amount,
+                    )
+                    .map_err(trap)?;
+                if !breached.is_empty() && call.executing_proposal.is_none() {
+                    return Err(trap(format!(
+                        "transfer of {} {} goes over a spending limit",
+                        amount, resource
+                    )));
+                }
+                storage
+                    .transfer(
+                        auth,
+                        namespace,
+                        &resource,
+                        &from,
+                        &to,
+                        amount,
+                        "WASM module transfer",
+                    )
+                    .map_err(trap)?;
+                storage
+                    .record_spending(auth, namespace, &resource, SpendingAction::Transfer, amount)
+                    .map_err(trap)
+            },
+        )?;
+        Ok(())
+    }
+
+    pub(super) fn run<S: Storage + 'static>(
+        bytes: &[u8],
+        storage: S,
+        call: Call,
+        function: &str,
+        args: &[f64],
+    ) -> Result<(WasmOutcome, S), VMError> {
+        let engine = engine();
+        let module = Module::new(&engine, bytes).map_err(wasm_error)?;
+        let mut store = Store::new(
+            &engine,
+            Host {
+                storage,
+                call,
+                logs: Vec::new(),
+            },
+        );
+        store.set_fuel(DEFAULT_FUEL).map_err(wasm_error)?;
+        let mut linker = <Linker<Host<S>>>::new(&engine);
+        link(&mut linker).map_err(wasm_error)?;
+
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(wasm_error)?;
+        let func = instance.get_func(&store, function).ok_or_else(|| {
+            VMError::WasmError(format!("module exports no function {}", function))
+        })?;
+        let params: Vec<Val> = args.iter().map(|arg| Val::F64((*arg).into())).collect();
+        let mut results = [Val::F64(0.0.into())];
+        func.call(&mut store, &params, &mut results)
+            .map_err(wasm_error)?;
+        let result = results[0]
+            .f64()
+            .map(f64::from)
+            .ok_or_else(|| VMError::WasmError(format!("{} must return one f64", function)))?;
+
+        let fuel_used = DEFAULT_FUEL - store.get_fuel().unwrap_or(0);
+        let host = store.into_data();
+        Ok((
+            WasmOutcome {
+                result,
+                logs: host.logs,
+                fuel_used,
+            },
+            host.storage,
+        ))
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+mod runtime {
+    use super::*;
+
+    pub(super) fn validate(_bytes: &[u8]) -> StorageResult<()> {
+        Ok(())
+    }
+
+    pub(super) fn run<S: Storage + 'static>(
+        _bytes: &[u8],
+        _storage: S,
+        _call: Call,
+        _function: &str,
+        _args: &[f64],
+    ) -> Result<(WasmOutcome, S), VMError> {
+        Err(VMError::NotImplemented(
+            "Calling WASM modules requires building with --features wasm".to_string(),
+        ))
+    }
+}
@@ -105,7 +105,10 @@ fn setup_vm() -> VM<InMemoryStorage> {
 
 // Helper functions to run economic operations
 fn create_resource(vm: &mut VM<InMemoryStorage>, resource_id: &str) -> Result<(), VMError> {
-    let op = Op::CreateResource(resource_id.to_string());
+    let op = Op::CreateResource {
+        resource: resource_id.to_string(),
+        policy: Default::default(),
+    };
     println!("Creating resource: {}", resource_id);
 
     // Check resources directory before operation
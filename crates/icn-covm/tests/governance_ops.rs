@@ -1,9 +1,11 @@
 use icn_covm::governance::try_handle_governance_op;
+use icn_covm::storage::auth::AuthContext;
 use icn_covm::storage::implementations::in_memory::InMemoryStorage;
+use icn_covm::typed::TypedValue;
 use icn_covm::vm::memory::MemoryScope;
 use icn_covm::vm::stack::StackOps;
 use icn_covm::vm::types::Op;
-use icn_covm::vm::VM;
+use icn_covm::vm::{VMError, VM};
 use std::fmt::Debug;
 
 // Helper function to create a VM for testing
@@ -20,6 +22,7 @@ fn test_ranked_vote_success() {
     let op = Op::RankedVote {
         candidates: 3,
         ballots: 2,
+        delegated: false,
     };
 
     // Push ballots (2 ballots x 3 candidates)
@@ -43,6 +46,7 @@ fn test_ranked_vote_tie_breaking() {
     let op = Op::RankedVote {
         candidates: 3,
         ballots: 3,
+        delegated: false,
     };
 
     // Push ballots (3 ballots x 3 candidates)
@@ -75,6 +79,7 @@ fn test_ranked_vote_invalid_input() {
     let op = Op::RankedVote {
         candidates: 1,
         ballots: 2,
+        delegated: false,
     };
     let result = try_handle_governance_op(&mut vm, &op);
     assert!(result.is_err());
@@ -83,6 +88,7 @@ fn test_ranked_vote_invalid_input() {
     let op = Op::RankedVote {
         candidates: 3,
         ballots: 0,
+        delegated: false,
     };
     let result = try_handle_governance_op(&mut vm, &op);
     assert!(result.is_err());
@@ -92,12 +98,56 @@ fn test_ranked_vote_invalid_input() {
     let op = Op::RankedVote {
         candidates: 3,
         ballots: 2,
+        delegated: false,
     };
     vm.stack.push(1.0); // Only one value, need 6 for 2 ballots with 3 candidates each
     let result = try_handle_governance_op(&mut vm, &op);
     assert!(result.is_err());
 }
 
+// ========== QuadraticVote Tests ==========
+
+#[test]
+fn test_quadratic_vote_favours_broad_support() {
+    let mut vm = create_test_vm();
+    let op = Op::QuadraticVote {
+        candidates: 2,
+        ballots: 3,
+    };
+
+    // Ballots 2 and 3: 16 credits on candidate 1, 4 votes each
+    for _ in 0..2 {
+        vm.stack.push(TypedValue::Number(16.0));
+        vm.stack.push(TypedValue::Number(0.0));
+    }
+    // Ballot 1: 144 credits on candidate 0, 12 votes
+    vm.stack.push(TypedValue::Number(0.0));
+    vm.stack.push(TypedValue::Number(144.0));
+
+    // 12 votes beat the 8 votes candidate 1 received
+    assert!(try_handle_governance_op(&mut vm, &op).is_ok());
+    assert_eq!(vm.stack.pop_number("test").unwrap(), 0.0);
+
+    // 49 credits buy only 7 votes, which lose to 8
+    vm.stack.push(TypedValue::Number(16.0));
+    vm.stack.push(TypedValue::Number(0.0));
+    vm.stack.push(TypedValue::Number(16.0));
+    vm.stack.push(TypedValue::Number(0.0));
+    vm.stack.push(TypedValue::Number(0.0));
+    vm.stack.push(TypedValue::Number(49.0));
+    assert!(try_handle_governance_op(&mut vm, &op).is_ok());
+    assert_eq!(vm.stack.pop_number("test").unwrap(), 1.0);
+
+    // Negative credits are rejected
+    vm.stack.push(TypedValue::Number(-4.0));
+    vm.stack.push(TypedValue::Number(1.0));
+    let op = Op::QuadraticVote {
+        candidates: 2,
+        ballots: 1,
+    };
+    assert!(try_handle_governance_op(&mut vm, &op).is_err());
+}
+
 // ========== LiquidDelegate Tests ==========
 
 #[test]
@@ -234,6 +284,66 @@ fn test_liquid_delegate_empty_from() {
     assert!(result.is_err());
 }
 
+/// Push a delegated ranked ballot: choices from last to first, then the voter
+fn push_delegated_ballot(vm: &mut VM<InMemoryStorage>, voter: &str, choices: &[f64]) {
+    for &choice in choices.iter().rev() {
+        vm.stack.push(TypedValue::Number(choice));
+    }
+    vm.stack.push(TypedValue::String(voter.to_string()));
+}
+
+fn delegate(vm: &mut VM<InMemoryStorage>, from: &str, to: &str) -> Result<(), VMError> {
+    let op = Op::LiquidDelegate {
+        from: from.to_string(),
+        to: to.to_string(),
+    };
+    try_handle_governance_op(vm, &op).map(|_| ())
+}
+
+#[test]
+fn test_liquid_delegate_chains_weigh_delegated_ranked_votes() {
+    let mut vm = create_test_vm();
+    let mut auth = AuthContext::new("did:key:admin");
+    auth.add_role("global", "admin");
+    vm.set_auth_context(auth.clone());
+
+    // alice -> bob -> carol, and dave -> bob
+    delegate(&mut vm, "alice", "bob").unwrap();
+    delegate(&mut vm, "bob", "carol").unwrap();
+    delegate(&mut vm, "dave", "bob").unwrap();
+    assert!(delegate(&mut vm, "carol", "alice").is_err());
+
+    // The graph is persisted, so another VM on the same storage tallies with it
+    let storage = vm.get_storage_backend().unwrap().clone();
+    let mut vm = VM::with_storage_backend(storage);
+    vm.set_auth_context(auth);
+    let op = Op::RankedVote {
+        candidates: 2,
+        ballots: 3,
+        delegated: true,
+    };
+    push_delegated_ballot(&mut vm, "frank", &[1.0, 0.0]);
+    push_delegated_ballot(&mut vm, "erin", &[1.0, 0.0]);
+    push_delegated_ballot(&mut vm, "carol", &[0.0, 1.0]);
+
+    // Carol's ballot carries alice, bob and dave: 4 votes to 2
+    assert!(try_handle_governance_op(&mut vm, &op).is_ok());
+    assert_eq!(vm.stack.pop_number("test").unwrap(), 0.0);
+
+    // Once bob votes himself, alice's and dave's votes stop with him
+    let op = Op::RankedVote {
+        candidates: 2,
+        ballots: 4,
+        delegated: true,
+    };
+    push_delegated_ballot(&mut vm, "frank", &[1.0, 0.0]);
+    push_delegated_ballot(&mut vm, "erin", &[1.0, 0.0]);
+    push_delegated_ballot(&mut vm, "bob", &[1.0, 0.0]);
+    push_delegated_ballot(&mut vm, "carol", &[0.0, 1.0]);
+    assert!(try_handle_governance_op(&mut vm, &op).is_ok());
+    assert_eq!(vm.stack.pop_number("test").unwrap(), 1.0);
+}
+
 // ========== QuorumThreshold Tests ==========
 
 #[test]
@@ -392,6 +502,7 @@ fn test_governance_ops_integration() {
     let vote_op = Op::RankedVote {
         candidates: 3,
         ballots: 1,
+        delegated: false,
     };
 
     // Push ballot values
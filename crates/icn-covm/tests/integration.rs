@@ -1,3 +1,4 @@
+use icn_covm::events::Severity;
 use icn_covm::{Op, VM};
 use icn_covm::typed::TypedValue;
 use std::fs;
@@ -47,6 +48,7 @@ fn test_governance_operations() -> Result<(), Box<dyn std::error::Error>> {
         Op::EmitEvent {
             category: "test".to_string(),
             message: "governance operations test".to_string(),
+            severity: Severity::Info,
         },
         // Test Break in Loop
         Op::Push(TypedValue::Number(0.0)),
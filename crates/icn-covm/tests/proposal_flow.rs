@@ -113,7 +113,7 @@ fn test_full_proposal_lifecycle() -> Result<(), Box<dyn std::error::Error>> {
         // If publish doesn't auto-start voting, do it manually:
         if prop.state != ProposalState::Voting {
             println!("Manually starting voting period for test...");
-            prop.start_voting(chrono::Duration::days(1)); // 1 day voting period
+            prop.start_voting(chrono::Duration::days(1), vm.clock()); // 1 day voting period
             let proposal_bytes = serde_json::to_vec(&prop)?;
             vm.storage_backend.as_mut().ok_or("Storage missing")?.set(
                 Some(&alice_auth),
@@ -516,7 +516,7 @@ fn publish_and_start_voting(
     let mut prop = load_proposal(vm, proposal_id)?;
     if prop.state != ProposalState::Voting {
         println!("Manually starting voting period for test...");
-        prop.start_voting(chrono::Duration::days(1));
+        prop.start_voting(chrono::Duration::days(1), vm.clock());
         let proposal_bytes = serde_json::to_vec(&prop)?;
         let lifecycle_key = format!("proposals/{}/lifecycle", proposal_id);
         vm.storage_backend.as_mut().ok_or("Storage missing")?.set(
@@ -276,3 +276,31 @@ fn test_comparison_operations() {
     assert_eq!(lines[4], "true"); // "hello" == "hello"
     assert_eq!(lines[5], "true"); // true == 1.0
 }
+
+#[test]
+fn test_string_literals_concat_and_emit() {
+    let dsl = r#"
+        push "Votes for \"garden\": "
+        push 3.0
+        concat
+        emit
+
+        # Keys can be built the same way, and compared
+        push "proposals/"
+        push "garden"
+        concat
+        store key
+        load key
+        push "proposals/garden"
+        eq
+        emit
+    "#;
+
+    let program = parse_dsl_with_stdlib(dsl).unwrap();
+    let mut vm = VM::new();
+
+    vm.execute(&program).unwrap();
+
+    let lines: Vec<&str> = vm.get_output().lines().collect();
+    assert_eq!(lines, vec!["Votes for \"garden\": 3", "true"]);
+}
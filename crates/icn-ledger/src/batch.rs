@@ -0,0 +1,239 @@
+//! Appending several nodes as one all-or-nothing batch
+//!
+//! `DagLedger::append_batch` checks every node before changing anything,
+//! then persists the batch through a write-ahead file: the new lines, headed
+//! by the length the ledger file had before them, are written to
+//! `<path>.wal` and synced before the ledger file is touched. The write-ahead
+//! file is staged under another name and renamed into place, so it only
+//! exists once complete. If appending to the ledger file fails the file is
+//! truncated back; if the process dies instead, loading the ledger finds the
+//! write-ahead file and finishes the batch, so the file never keeps half of
+//! one.
+
+use crate::{append_lines, is_sqlite_path, DagLedger, DagNode, SqliteStore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// First line of a write-ahead file
+#[derive(Debug, Serialize, Deserialize)]
+struct WalHeader {
+    /// Length of the ledger file before the batch, in bytes
+    ledger_len: u64,
+}
+
+/// Sibling of `path` with `suffix` appended to its file name
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Write-ahead file of the JSONL ledger at `path`
+fn wal_path(path: &Path) -> PathBuf {
+    sibling(path, ".wal")
+}
+
+impl DagLedger {
+    /// Append several nodes, keeping all of them or none
+    ///
+    /// IDs are computed (after sealing, as `append` does) and every parent
+    /// must already be in the ledger or be another node of the batch;
+    /// otherwise nothing is appended. A ledger with a file persists the
+    /// batch, with any nodes appended earlier and not yet written, before
+    /// any of it is added in memory or sent to subscribers. Returns the IDs
+    /// in the order the nodes were given.
+    pub fn append_batch(&mut self, nodes: Vec<DagNode>) -> Result<Vec<String>, String> {
+        let mut batch = Vec::with_capacity(nodes.len());
+        for mut node in nodes {
+            if let Some(key) = self.keys.get(&node.namespace) {
+                if !node.is_anchor() {
+                    node.seal(key)?;
+                }
+            }
+            node.id = node.compute_id();
+            batch.push(node);
+        }
+
+        let known: HashSet<&str> = self
+            .nodes
+            .iter()
+            .chain(&batch)
+            .map(|node| node.id.as_str())
+            .collect();
+        for node in &batch {
+            if let Some(parent) = node
+                .parent_ids
+                .iter()
+                .find(|parent| !known.contains(parent.as_str()))
+            {
+                return Err(format!(
+                    "Node {} has parent {}, which is neither in the ledger nor in the batch",
+                    node.id, parent
+                ));
+            }
+        }
+
+        if let Some(path) = self.file_path.clone() {
+            let unwritten = &self.nodes[self.persisted.min(self.nodes.len())..];
+            let pending: Vec<&DagNode> = unwritten.iter().chain(&batch).collect();
+            if is_sqlite_path(&path) {
+                // One transaction already keeps the batch whole
+                SqliteStore::open(&path).and_then(|mut store| store.insert_all(pending))?;
+            } else {
+                let jsonl = pending
+                    .iter()
+                    .map(|node| serde_json::to_string(node).map(|line| line + "\n"))
+                    .collect::<Result<String, _>>()
+                    .map_err(|e| format!("Failed to serialize batch: {}", e))?;
+                write_through_wal(&path, &jsonl)
+                    .map_err(|e| format!("Failed to persist batch to {}: {}", path.display(), e))?;
+            }
+        }
+
+        let ids = batch.iter().map(|node| node.id.clone()).collect();
+        for node in batch {
+            self.push_node(node);
+        }
+        if self.file_path.is_some() {
+            self.persisted = self.nodes.len();
+        }
+        Ok(ids)
+    }
+}
+
+/// Append `jsonl` to the ledger file at `path` through its write-ahead file
+fn write_through_wal(path: &Path, jsonl: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let ledger_len = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(e),
+    };
+    let header = serde_json::to_string(&WalHeader { ledger_len }).map_err(io::Error::other)?;
+
+    let wal = wal_path(path);
+    let staging = sibling(path, ".wal.tmp");
+    let mut file = File::create(&staging)?;
+    file.write_all(format!("{}\n{}", header, jsonl).as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&staging, &wal)?;
+
+    if let Err(e) = append_lines(path, jsonl) {
+        // Undo whatever part of the batch reached the file
+        OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(ledger_len)?;
+        fs::remove_file(&wal)?;
+        return Err(e);
+    }
+    fs::remove_file(&wal)
+}
+
+/// Finish a batch whose write-ahead file outlived the process writing it
+///
+/// The ledger file is cut back to its length before the batch and the
+/// batch's lines are appended again, so a partly written batch is neither
+/// duplicated nor left half-written.
+pub(crate) fn recover(path: &Path) -> io::Result<()> {
+    let wal = wal_path(path);
+    if !wal.exists() {
+        return Ok(());
+    }
+    let text = fs::read_to_string(&wal)?;
+    let (header, jsonl) = text.split_once('\n').unwrap_or((&text, ""));
+    let header: WalHeader = serde_json::from_str(header).map_err(io::Error::other)?;
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    if file.metadata()?.len() < header.ledger_len {
+        return Err(io::Error::other(format!(
+            "{} is shorter than before the batch in {}",
+            path.display(),
+            wal.display()
+        )));
+    }
+    file.set_len(header.ledger_len)?;
+    drop(file);
+
+    append_lines(path, jsonl)?;
+    tracing::warn!(path = %path.display(), "Finished an interrupted DAG ledger batch");
+    fs::remove_file(&wal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NodeData;
+
+    fn vote(voter: &str, parent_ids: Vec<String>) -> DagNode {
+        DagNode::with_namespace(
+            parent_ids,
+            NodeData::VoteCast {
+                proposal_id: "p1".to_string(),
+                voter: voter.to_string(),
+                vote: 1.0,
+            },
+            10,
+            "coop".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_batches_are_all_or_nothing_and_interrupted_ones_recover() {
+        let path = std::env::temp_dir().join(format!("icn-batch-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut ledger = DagLedger::with_path(path.clone());
+        let first = ledger.append_and_persist(vote("alice", vec![])).unwrap();
+        let parent = vote("bob", vec![first.clone()]);
+        let child = vote("carol", vec![parent.compute_id()]);
+        let ids = ledger.append_batch(vec![child, parent]).unwrap();
+        assert_eq!(ledger.nodes().len(), 3);
+
+        // An unknown parent rejects the whole batch
+        let orphan = vote("dave", vec!["missing".to_string()]);
+        assert!(ledger
+            .append_batch(vec![vote("erin", vec![]), orphan])
+            .is_err());
+        assert_eq!(ledger.nodes().len(), 3);
+        let reloaded = DagLedger::load_from_file(&path).unwrap();
+        assert_eq!(reloaded.all_node_ids(), [vec![first], ids].concat());
+
+        // A crash after the write-ahead file, halfway through the ledger
+        let before = fs::read(&path).unwrap();
+        let late: Vec<DagNode> = ["frank", "grace"]
+            .iter()
+            .map(|voter| {
+                let mut node = vote(voter, vec![]);
+                node.id = node.compute_id();
+                node
+            })
+            .collect();
+        let jsonl = DagLedger::to_jsonl(&late).unwrap();
+        let header = serde_json::to_string(&WalHeader {
+            ledger_len: before.len() as u64,
+        })
+        .unwrap();
+        fs::write(wal_path(&path), format!("{}\n{}", header, jsonl)).unwrap();
+        let mut torn = before.clone();
+        torn.extend_from_slice(&jsonl.as_bytes()[..jsonl.len() / 2]);
+        fs::write(&path, torn).unwrap();
+
+        let recovered = DagLedger::load_from_file(&path).unwrap();
+        assert_eq!(recovered.nodes().len(), 5);
+        assert!(!wal_path(&path).exists());
+        assert!(recovered.verify().is_valid());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+//! Compacting a ledger's nodes and file
+//!
+//! Persisted appends only ever add lines to a JSONL ledger, so a file that
+//! has been merged or imported into, or edited by hand, can hold the same
+//! node more than once or a node before one of its parents.
+//! `DagLedger::compact` keeps the first copy of each node, orders the nodes
+//! so every node follows its parents, and rewrites the file in that order.
+
+use crate::{is_sqlite_path, DagLedger, DagNode, SqliteStore};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+
+/// What compacting a ledger changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactReport {
+    /// Nodes kept
+    pub nodes: usize,
+    /// Repeated copies of a node that were dropped
+    pub duplicates_removed: usize,
+    /// Kept nodes that moved to a different position
+    pub reordered: usize,
+}
+
+impl DagLedger {
+    /// Drop repeated nodes and order the rest parents first, then rewrite
+    /// the ledger's file, if it has one
+    ///
+    /// Nodes whose parents are all placed come out oldest first, ties kept
+    /// in their current order, so a compacted ledger reads chronologically
+    /// wherever the DAG allows. Parents missing from the ledger do not hold
+    /// a node back. The JSONL file is written beside the old one and renamed
+    /// over it, so a failed compaction leaves the old file intact; lines that
+    /// are not nodes are dropped.
+    pub fn compact(&mut self) -> io::Result<CompactReport> {
+        let mut seen = HashSet::new();
+        let unique: Vec<DagNode> = self
+            .nodes
+            .iter()
+            .filter(|node| seen.insert(node.id.clone()))
+            .cloned()
+            .collect();
+        let duplicates_removed = self.nodes.len() - unique.len();
+
+        let order = parents_first(&unique);
+        let reordered = order
+            .iter()
+            .enumerate()
+            .filter(|(position, &index)| *position != index)
+            .count();
+        let mut slots: Vec<Option<DagNode>> = unique.into_iter().map(Some).collect();
+        self.nodes = order
+            .into_iter()
+            .filter_map(|index| slots[index].take())
+            .collect();
+
+        if let Some(path) = self.file_path.clone() {
+            if is_sqlite_path(&path) {
+                SqliteStore::open(&path)
+                    .and_then(|mut store| store.replace_all(&self.nodes))
+                    .map_err(io::Error::other)?;
+            } else {
+                let mut staging = OsString::from(path.as_os_str());
+                staging.push(".compacting");
+                fs::write(
+                    &staging,
+                    Self::to_jsonl(&self.nodes).map_err(io::Error::other)?,
+                )?;
+                fs::rename(&staging, &path)?;
+            }
+            self.persisted = self.nodes.len();
+        }
+
+        Ok(CompactReport {
+            nodes: self.nodes.len(),
+            duplicates_removed,
+            reordered,
+        })
+    }
+}
+
+/// Indexes of `nodes` in an order that puts every node after its parents
+///
+/// A node becomes ready once all its parents present in `nodes` are placed,
+/// and the oldest ready node is placed next. Nodes caught in a cycle, which
+/// only a tampered ledger can contain, follow in their current order.
+fn parents_first(nodes: &[DagNode]) -> Vec<usize> {
+    let index_of: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| (node.id.as_str(), index))
+        .collect();
+    let mut waiting_on = vec![0usize; nodes.len()];
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        let parents: HashSet<usize> = node
+            .parent_ids
+            .iter()
+            .filter_map(|id| index_of.get(id.as_str()).copied())
+            .filter(|&parent| parent != index)
+            .collect();
+        waiting_on[index] = parents.len();
+        for parent in parents {
+            children[parent].push(index);
+        }
+    }
+
+    let mut ready: BinaryHeap<Reverse<(u64, usize)>> = (0..nodes.len())
+        .filter(|&index| waiting_on[index] == 0)
+        .map(|index| Reverse((nodes[index].timestamp, index)))
+        .collect();
+    let mut order = Vec::with_capacity(nodes.len());
+    let mut placed = vec![false; nodes.len()];
+    while let Some(Reverse((_, index))) = ready.pop() {
+        order.push(index);
+        placed[index] = true;
+        for &child in &children[index] {
+            waiting_on[child] -= 1;
+            if waiting_on[child] == 0 {
+                ready.push(Reverse((nodes[child].timestamp, child)));
+            }
+        }
+    }
+    order.extend((0..nodes.len()).filter(|&index| !placed[index]));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NodeData;
+
+    fn vote(voter: &str, timestamp: u64) -> DagNode {
+        DagNode::with_namespace(
+            vec![],
+            NodeData::VoteCast {
+                proposal_id: "p1".to_string(),
+                voter: voter.to_string(),
+                vote: 1.0,
+            },
+            timestamp,
+            "coop".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_appends_are_written_in_place_and_compaction_restores_order() {
+        let path = std::env::temp_dir().join(format!("icn-compact-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut ledger = DagLedger::with_path(path.clone());
+        let first = ledger.append_and_persist(vote("alice", 10)).unwrap();
+        let mut second = vote("bob", 20);
+        second.parent_ids = vec![first.clone()];
+        let second = ledger.append_and_persist(second).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        // A merge that wrote the child first, then the parent twice
+        let nodes = ledger.export_all();
+        let jsonl = DagLedger::to_jsonl(&[nodes[1].clone(), nodes[0].clone(), nodes[0].clone()]);
+        fs::write(&path, jsonl.unwrap()).unwrap();
+
+        let mut ledger = DagLedger::with_path(path.clone());
+        let third = ledger.append_and_persist(vote("carol", 30)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 4);
+
+        let report = ledger.compact().unwrap();
+        assert_eq!(
+            report,
+            CompactReport {
+                nodes: 3,
+                duplicates_removed: 1,
+                reordered: 2,
+            }
+        );
+        let reloaded = DagLedger::load_from_file(&path).unwrap();
+        assert_eq!(reloaded.all_node_ids(), vec![first, second, third]);
+        assert!(reloaded.verify().is_valid());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+//! Per-namespace encryption of node payloads
+//!
+//! Private cooperatives can seal the `NodeData` of their nodes with a
+//! namespace key. The node's id, parents, timestamp, and namespace stay in
+//! the clear so the DAG can still be synced and verified by any peer, while
+//! only members holding the key can read what was decided.
+
+use crate::{DagNode, NodeData};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+/// Length in bytes of a namespace key
+pub const NAMESPACE_KEY_LEN: usize = 32;
+
+/// Symmetric key used to seal the payloads of one namespace
+#[derive(Clone, PartialEq, Eq)]
+pub struct NamespaceKey([u8; NAMESPACE_KEY_LEN]);
+
+impl NamespaceKey {
+    /// Generate a new random key
+    pub fn generate() -> Self {
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        Self(key.into())
+    }
+
+    /// Wrap existing key material
+    pub fn from_bytes(bytes: [u8; NAMESPACE_KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Parse a hex-encoded key, as stored in key files
+    pub fn from_hex(hex_key: &str) -> Result<Self, String> {
+        let bytes = hex::decode(hex_key.trim()).map_err(|e| format!("Invalid key hex: {}", e))?;
+        let bytes: [u8; NAMESPACE_KEY_LEN] = bytes
+            .try_into()
+            .map_err(|_| format!("Namespace key must be {} bytes", NAMESPACE_KEY_LEN))?;
+        Ok(Self(bytes))
+    }
+
+    /// Hex encoding of the key
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.0))
+    }
+}
+
+// Never print key material
+impl std::fmt::Debug for NamespaceKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NamespaceKey(..)")
+    }
+}
+
+/// Associated data binding a ciphertext to the public fields of its node
+///
+/// This stops a sealed payload from being transplanted onto another node.
+fn associated_data(node: &DagNode) -> Vec<u8> {
+    format!(
+        "{}|{}|{}",
+        node.namespace,
+        node.timestamp,
+        node.parent_ids.join(",")
+    )
+    .into_bytes()
+}
+
+impl DagNode {
+    /// Whether the node's payload is sealed
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self.data, NodeData::Encrypted { .. })
+    }
+
+    /// Replace the payload with its ciphertext under `key`
+    ///
+    /// Must be called before the id is computed, so the id commits to the
+    /// ciphertext that peers without the key will see. Sealing an already
+    /// sealed node is a no-op.
+    pub fn seal(&mut self, key: &NamespaceKey) -> Result<(), String> {
+        if self.is_encrypted() {
+            return Ok(());
+        }
+
+        let plaintext = serde_json::to_vec(&self.data)
+            .map_err(|e| format!("Failed to serialize node data: {}", e))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = associated_data(self);
+        let ciphertext = key
+            .cipher()
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| "Failed to encrypt node data".to_string())?;
+
+        self.data = NodeData::Encrypted {
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+        Ok(())
+    }
+
+    /// Decrypt the payload of a sealed node
+    ///
+    /// Returns the payload unchanged for nodes that are not sealed.
+    pub fn open(&self, key: &NamespaceKey) -> Result<NodeData, String> {
+        let (nonce, ciphertext) = match &self.data {
+            NodeData::Encrypted { nonce, ciphertext } => (nonce, ciphertext),
+            data => return Ok(data.clone()),
+        };
+
+        let nonce = hex::decode(nonce).map_err(|e| format!("Invalid nonce: {}", e))?;
+        if nonce.len() != 12 {
+            return Err("Invalid nonce length".to_string());
+        }
+        let ciphertext =
+            hex::decode(ciphertext).map_err(|e| format!("Invalid ciphertext: {}", e))?;
+        let aad = associated_data(self);
+        let plaintext = key
+            .cipher()
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| format!("Failed to decrypt node {}", self.id))?;
+
+        serde_json::from_slice(&plaintext).map_err(|e| format!("Invalid node data: {}", e))
+    }
+}
@@ -0,0 +1,138 @@
+//! Graphviz rendering of a ledger
+
+use crate::{DagLedger, DagNode, NodeData};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// Characters of a node ID shown in its label
+const SHORT_ID_LEN: usize = 8;
+
+/// Escape text for use inside a quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn label(node: &DagNode) -> String {
+    let short_id = &node.id[..node.id.len().min(SHORT_ID_LEN)];
+    let detail = match &node.data {
+        NodeData::ProposalCreated { proposal_id, .. }
+        | NodeData::ProposalExecuted { proposal_id, .. } => proposal_id.clone(),
+        NodeData::VoteCast { voter, .. } => voter.clone(),
+        NodeData::TokenMinted {
+            resource, amount, ..
+        } => format!("{} {}", amount, resource),
+        NodeData::EpochMarker { epoch, .. } => epoch.clone(),
+        NodeData::Genesis { founder, .. } => founder.clone(),
+        NodeData::Encrypted { .. } => "sealed".to_string(),
+        NodeData::BountyCreated {
+            bounty_id,
+            amount,
+            resource,
+            ..
+        } => format!("{}: {} {}", bounty_id, amount, resource),
+        NodeData::BountyClaimed { claimant, .. } => claimant.clone(),
+        NodeData::BountyVerified {
+            verifier, approved, ..
+        } => format!(
+            "{} {}",
+            verifier,
+            if *approved { "approved" } else { "rejected" }
+        ),
+        NodeData::BountyPaid {
+            recipient, amount, ..
+        } => format!("{} to {}", amount, recipient),
+        NodeData::BountyCancelled { bounty_id } => bounty_id.clone(),
+        NodeData::Distributed {
+            resource,
+            amount,
+            shares,
+            ..
+        } => format!("{} {} to {} accounts", amount, resource, shares.len()),
+        NodeData::ExecutionAck { coop_id, .. } => coop_id.clone(),
+    };
+    // `\n` is a line break in DOT labels, so the parts are escaped first
+    format!(
+        "{}\\n{}\\n{}",
+        node.data.type_name(),
+        escape(&detail),
+        short_id
+    )
+}
+
+impl DagLedger {
+    /// Render the ledger, or one namespace of it, in Graphviz DOT format
+    ///
+    /// Each namespace is drawn as a cluster, with edges from parents to
+    /// children. Sealed payloads are shown as such, without decrypting.
+    pub fn to_dot(&self, namespace: Option<&str>) -> String {
+        let nodes: Vec<&DagNode> = self
+            .nodes
+            .iter()
+            .filter(|n| namespace.is_none_or(|ns| n.namespace == ns))
+            .collect();
+        let ids: HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+
+        let mut namespaces: Vec<&str> = nodes.iter().map(|n| n.namespace.as_str()).collect();
+        namespaces.sort();
+        namespaces.dedup();
+
+        let mut out = String::from("digraph ledger {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (i, ns) in namespaces.iter().enumerate() {
+            let _ = writeln!(out, "    subgraph cluster_{} {{", i);
+            let _ = writeln!(out, "        label=\"{}\";", escape(ns));
+            for node in nodes.iter().filter(|n| n.namespace == *ns) {
+                let _ = writeln!(out, "        \"{}\" [label=\"{}\"];", node.id, label(node));
+            }
+            out.push_str("    }\n");
+        }
+        for node in &nodes {
+            // Parents outside the rendered namespace are left out
+            for parent_id in node.parent_ids.iter().filter(|p| ids.contains(p.as_str())) {
+                let _ = writeln!(out, "    \"{}\" -> \"{}\";", parent_id, node.id);
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DagLedger, DagNode, NodeData};
+
+    #[test]
+    fn test_to_dot_draws_nodes_and_edges() {
+        let mut ledger = DagLedger::new();
+        let created = ledger
+            .append_on_tips(DagNode::with_namespace(
+                vec![],
+                NodeData::ProposalCreated {
+                    proposal_id: "p1".to_string(),
+                    title: "Budget".to_string(),
+                },
+                1,
+                "coop".to_string(),
+            ))
+            .unwrap();
+        let voted = ledger
+            .append_on_tips(DagNode::with_namespace(
+                vec![],
+                NodeData::VoteCast {
+                    proposal_id: "p1".to_string(),
+                    voter: "alice".to_string(),
+                    vote: 1.0,
+                },
+                2,
+                "coop".to_string(),
+            ))
+            .unwrap();
+
+        let dot = ledger.to_dot(None);
+        assert!(dot.starts_with("digraph ledger {"));
+        assert!(dot.contains("label=\"coop\""));
+        assert!(dot.contains(&format!("\"{}\" -> \"{}\";", created, voted)));
+        assert!(dot.contains("VoteCast\\nalice"));
+
+        assert!(!ledger.to_dot(Some("other")).contains("->"));
+    }
+}
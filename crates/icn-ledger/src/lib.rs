@@ -2,9 +2,23 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::fs::{self, File};
-use std::io::{self, BufRead, BufReader, Write};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+mod batch;
+mod compact;
+mod encryption;
+mod graph;
+mod sqlite;
+mod stats;
+mod verify;
+pub use compact::CompactReport;
+pub use encryption::{NamespaceKey, NAMESPACE_KEY_LEN};
+pub use sqlite::{is_sqlite_path, SqliteStore};
+pub use stats::LedgerStats;
+pub use verify::{IssueKind, VerifyIssue, VerifyReport};
 // Only include OS-specific imports when needed
 #[cfg(target_os = "windows")]
 use std::os::windows::prelude::OsStrExt;
@@ -39,6 +53,97 @@ pub enum NodeData {
         recipient: String,
         amount: f64,
     },
+    /// First node of a namespace, signed by the founding identity
+    Genesis {
+        founder: String,
+        public_key: String,
+        signature: String,
+    },
+    /// Periodic checkpoint (e.g. yearly) over everything before it
+    EpochMarker {
+        epoch: String,
+        node_count: usize,
+    },
+    /// Payload sealed with a namespace key; see `DagNode::seal`
+    Encrypted {
+        nonce: String,
+        ciphertext: String,
+    },
+    /// Bounty opened, with its reward held from the funding account
+    BountyCreated {
+        bounty_id: String,
+        resource: String,
+        funder: String,
+        /// Exact decimal amount
+        amount: String,
+    },
+    /// Work submitted against an open bounty
+    BountyClaimed {
+        bounty_id: String,
+        claimant: String,
+    },
+    /// A verifier's vote on, or decision about, the pending claim
+    BountyVerified {
+        bounty_id: String,
+        verifier: String,
+        approved: bool,
+    },
+    /// Reward paid to the claimant once the claim was accepted
+    BountyPaid {
+        bounty_id: String,
+        recipient: String,
+        /// Exact decimal amount
+        amount: String,
+    },
+    /// Bounty withdrawn and its reward returned to the funding account
+    BountyCancelled {
+        bounty_id: String,
+    },
+    /// Amount split from a pool account in proportion to stored weights,
+    /// paid as one batch
+    Distributed {
+        resource: String,
+        pool: String,
+        /// Storage key prefix the weights were read from
+        weight_key: String,
+        /// Exact decimal amount paid out
+        amount: String,
+        /// `(account, exact decimal amount)` paid to each member
+        shares: Vec<(String, String)>,
+    },
+    /// A cooperative's signed confirmation that it applied the result of a
+    /// federated proposal
+    ExecutionAck {
+        proposal_id: String,
+        coop_id: String,
+        /// DID of the member who signed for the cooperative
+        signer: String,
+        /// Result the cooperative applied
+        outcome: String,
+        signature: String,
+    },
+}
+
+impl NodeData {
+    /// Name of the event type, as used in summaries and indexes
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            NodeData::ProposalCreated { .. } => "ProposalCreated",
+            NodeData::VoteCast { .. } => "VoteCast",
+            NodeData::ProposalExecuted { .. } => "ProposalExecuted",
+            NodeData::TokenMinted { .. } => "TokenMinted",
+            NodeData::Genesis { .. } => "Genesis",
+            NodeData::EpochMarker { .. } => "EpochMarker",
+            NodeData::Encrypted { .. } => "Encrypted",
+            NodeData::BountyCreated { .. } => "BountyCreated",
+            NodeData::BountyClaimed { .. } => "BountyClaimed",
+            NodeData::BountyVerified { .. } => "BountyVerified",
+            NodeData::BountyPaid { .. } => "BountyPaid",
+            NodeData::BountyCancelled { .. } => "BountyCancelled",
+            NodeData::Distributed { .. } => "Distributed",
+            NodeData::ExecutionAck { .. } => "ExecutionAck",
+        }
+    }
 }
 
 impl DagNode {
@@ -74,13 +179,68 @@ impl DagNode {
             data,
         }
     }
+
+    /// Whether the node is a genesis or epoch marker used as a trust anchor
+    pub fn is_anchor(&self) -> bool {
+        matches!(
+            self.data,
+            NodeData::Genesis { .. } | NodeData::EpochMarker { .. }
+        )
+    }
+}
+
+/// Bytes the founder signs when creating a namespace's genesis node
+pub fn genesis_signing_bytes(namespace: &str, founder: &str, timestamp: u64) -> Vec<u8> {
+    format!("icn-genesis|{}|{}|{}", namespace, founder, timestamp).into_bytes()
+}
+
+/// Append JSONL lines to the file at `path`, creating it if needed
+///
+/// Writing starts on a new line if an earlier write was cut short, and the
+/// data is synced before returning.
+pub(crate) fn append_lines(path: &Path, jsonl: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut out = String::new();
+    if file.metadata()?.len() > 0 {
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            out.push('\n');
+        }
+    }
+    out.push_str(jsonl);
+    file.write_all(out.as_bytes())?;
+    file.sync_data()
 }
 
 /// The DagLedger stores and manages a collection of DagNodes
-#[derive(Clone)]
 pub struct DagLedger {
     nodes: Vec<DagNode>,
     file_path: Option<PathBuf>,
+    /// Number of leading nodes already written to `file_path`, so persisting
+    /// an append writes only the nodes after them
+    persisted: usize,
+    keys: HashMap<String, NamespaceKey>,
+    subscribers: Vec<Sender<DagNode>>,
+}
+
+// Subscriptions belong to the instance they were made on, so appends to a
+// clone (e.g. a forked VM that may be rolled back) are not broadcast
+impl Clone for DagLedger {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            file_path: self.file_path.clone(),
+            persisted: self.persisted,
+            keys: self.keys.clone(),
+            subscribers: Vec::new(),
+        }
+    }
 }
 
 // Implement Debug for DagLedger
@@ -89,24 +249,68 @@ impl fmt::Debug for DagLedger {
         f.debug_struct("DagLedger")
             .field("nodes_count", &self.nodes.len())
             .field("path", &self.file_path)
+            .field(
+                "encrypted_namespaces",
+                &self.keys.keys().collect::<Vec<_>>(),
+            )
             .finish()
     }
 }
 
 /// Result of a diff operation between two DAG ledgers
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DagDiff {
     pub added: Vec<DagNode>,
     pub removed: Vec<DagNode>,
     pub common: Vec<String>, // IDs of nodes in both DAGs
 }
 
+/// A pair of nodes that record the same logical event with different contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeConflict {
+    /// Logical event both nodes claim, e.g. `coop/ProposalExecuted/prop-1`
+    pub event: String,
+    /// ID of the node already present in this ledger
+    pub local_id: String,
+    /// ID of the incoming node that was rejected
+    pub incoming_id: String,
+}
+
+/// Result of merging another ledger into this one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MergeReport {
+    /// IDs of nodes imported from the other ledger
+    pub imported: Vec<String>,
+    /// Number of incoming nodes that were already present
+    pub already_present: usize,
+    /// Incoming nodes rejected because they conflict with a local node
+    pub conflicts: Vec<MergeConflict>,
+    /// IDs of incoming nodes held back because an ancestor was rejected or missing
+    pub blocked: Vec<String>,
+}
+
+impl MergeReport {
+    /// Whether the merge completed without conflicts or blocked nodes
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty() && self.blocked.is_empty()
+    }
+}
+
+impl Default for DagLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DagLedger {
     /// Create a new empty DAG ledger
     pub fn new() -> Self {
         Self {
             nodes: Vec::new(),
             file_path: None,
+            persisted: 0,
+            keys: HashMap::new(),
+            subscribers: Vec::new(),
         }
     }
 
@@ -118,32 +322,144 @@ impl DagLedger {
                 ledger
             }
             Err(e) => {
-                eprintln!("Failed to load DAG ledger: {}, using empty DAG", e);
+                tracing::warn!(
+                    path = %path.display(),
+                    error = %e,
+                    "Failed to load DAG ledger, using empty DAG"
+                );
                 DagLedger {
                     nodes: Vec::new(),
                     file_path: Some(path),
+                    persisted: 0,
+                    keys: HashMap::new(),
+                    subscribers: Vec::new(),
                 }
             }
         }
     }
 
     /// Set or update the path for this ledger
+    ///
+    /// Nodes loaded from `path` count as written to it; moving to another
+    /// path counts none as written, so the next export writes them all.
     pub fn set_path(&mut self, path: PathBuf) {
+        if self
+            .file_path
+            .as_ref()
+            .is_some_and(|current| *current != path)
+        {
+            self.persisted = 0;
+        }
         self.file_path = Some(path);
     }
 
+    /// File this ledger is persisted to, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+
+    /// Register the key for a private namespace
+    ///
+    /// Nodes appended to the namespace afterwards are sealed with the key,
+    /// and existing sealed nodes become readable through `readable_nodes`.
+    pub fn set_namespace_key(&mut self, namespace: &str, key: NamespaceKey) {
+        self.keys.insert(namespace.to_string(), key);
+    }
+
+    /// Whether payloads in a namespace are sealed on append
+    pub fn is_namespace_encrypted(&self, namespace: &str) -> bool {
+        self.keys.contains_key(namespace)
+    }
+
     /// Append a new node to the DAG
     pub fn append(&mut self, mut node: DagNode) -> Result<String, String> {
+        // Seal private payloads before the ID commits to them; anchors stay
+        // public so non-members can verify the namespace
+        if let Some(key) = self.keys.get(&node.namespace) {
+            if !node.is_anchor() {
+                node.seal(key)?;
+            }
+        }
+
         // Auto-generate ID
         node.id = node.compute_id();
-        self.nodes.push(node.clone());
-        Ok(node.id)
+        let id = node.id.clone();
+        self.push_node(node);
+        Ok(id)
+    }
+
+    /// Subscribe to nodes added to this ledger
+    ///
+    /// Every node appended, imported, or merged afterwards is sent on the
+    /// returned channel as stored (sealed payloads stay sealed). Dropping the
+    /// receiver ends the subscription.
+    pub fn subscribe(&mut self) -> Receiver<DagNode> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Store a node and notify subscribers, dropping any that have gone away
+    fn push_node(&mut self, node: DagNode) {
+        self.subscribers.retain(|tx| tx.send(node.clone()).is_ok());
+        self.nodes.push(node);
+    }
+
+    /// Append a new node parented on the current tips of its namespace
+    ///
+    /// Any parents already set on the node are kept; the namespace tips are
+    /// added after them so every new event follows all prior ones.
+    pub fn append_on_tips(&mut self, mut node: DagNode) -> Result<String, String> {
+        for tip in self.current_tips(&node.namespace) {
+            if !node.parent_ids.contains(&tip) {
+                node.parent_ids.push(tip);
+            }
+        }
+        self.append(node)
+    }
+
+    /// Return the IDs of nodes in a namespace that no other node references as a parent
+    pub fn current_tips(&self, namespace: &str) -> Vec<String> {
+        let referenced: HashSet<&str> = self
+            .nodes
+            .iter()
+            .filter(|n| n.namespace == namespace)
+            .flat_map(|n| n.parent_ids.iter().map(|p| p.as_str()))
+            .collect();
+
+        self.nodes
+            .iter()
+            .filter(|n| n.namespace == namespace && !referenced.contains(n.id.as_str()))
+            .map(|n| n.id.clone())
+            .collect()
     }
 
     pub fn nodes(&self) -> &Vec<DagNode> {
         &self.nodes
     }
 
+    /// All nodes, with sealed payloads decrypted where this ledger holds the key
+    ///
+    /// Nodes from namespaces without a registered key, or that fail to
+    /// decrypt, are returned still sealed.
+    pub fn readable_nodes(&self) -> Vec<DagNode> {
+        self.nodes.iter().map(|node| self.readable(node)).collect()
+    }
+
+    /// A copy of the node with its payload decrypted, if this ledger holds the key
+    pub fn readable(&self, node: &DagNode) -> DagNode {
+        match self.keys.get(&node.namespace) {
+            Some(key) if node.is_encrypted() => match node.open(key) {
+                Ok(data) => DagNode {
+                    data,
+                    ..node.clone()
+                },
+                Err(_) => node.clone(),
+            },
+            _ => node.clone(),
+        }
+    }
+
     pub fn find_by_id(&self, id: &str) -> Option<&DagNode> {
         self.nodes.iter().find(|n| n.id == id)
     }
@@ -184,6 +500,9 @@ impl DagLedger {
     }
 
     /// Load a ledger from a JSONL file, one DagNode per line
+    ///
+    /// Paths with an SQLite extension (see `is_sqlite_path`) are loaded from
+    /// the database instead.
     pub fn load_from_file(path: &Path) -> std::io::Result<Self> {
         let mut ledger = DagLedger::new();
 
@@ -192,11 +511,24 @@ impl DagLedger {
             std::fs::create_dir_all(parent)?;
         }
 
+        // Finish a batch a crash interrupted before reading the file
+        if !is_sqlite_path(path) {
+            batch::recover(path)?;
+        }
+
         // If file doesn't exist, return empty ledger
         if !path.exists() {
             return Ok(ledger);
         }
 
+        if is_sqlite_path(path) {
+            ledger.nodes = SqliteStore::open(path)
+                .and_then(|store| store.load_all())
+                .map_err(io::Error::other)?;
+            ledger.persisted = ledger.nodes.len();
+            return Ok(ledger);
+        }
+
         let file = File::open(path)?;
         let reader = BufReader::new(file);
 
@@ -211,28 +543,101 @@ impl DagLedger {
                     ledger.nodes.push(node);
                 }
                 Err(e) => {
-                    eprintln!("Error parsing DAG node: {}", e);
+                    tracing::warn!(error = %e, "Skipping unparsable DAG node");
                 }
             }
         }
 
+        ledger.persisted = ledger.nodes.len();
+        Ok(ledger)
+    }
+
+    /// Parse a ledger from JSONL text, one DagNode per line
+    ///
+    /// Unlike `load_from_file`, malformed lines are an error, since the text
+    /// usually comes from an untrusted upload.
+    pub fn from_jsonl(text: &str) -> Result<Self, String> {
+        let mut ledger = DagLedger::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let node = serde_json::from_str::<DagNode>(line)
+                .map_err(|e| format!("Invalid DAG node on line {}: {}", index + 1, e))?;
+            ledger.nodes.push(node);
+        }
         Ok(ledger)
     }
 
+    /// Serialize nodes as JSONL, one DagNode per line
+    pub fn to_jsonl(nodes: &[DagNode]) -> Result<String, String> {
+        let mut out = String::new();
+        for node in nodes {
+            let line = serde_json::to_string(node)
+                .map_err(|e| format!("Failed to serialize node {}: {}", node.id, e))?;
+            out.push_str(&line);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
     /// Append a node and immediately persist it to disk
+    ///
+    /// Only the new node is written, along with any appended earlier
+    /// without being persisted; see `persist_appended`.
     pub fn append_and_persist(&mut self, node: DagNode) -> Result<String, String> {
         if self.file_path.is_none() {
             return Err("File path is not set".to_string());
         }
 
         let node_id = self.append(node)?;
-        self.export_to_file().map_err(|e| e.to_string())?;
+        self.persist_appended().map_err(|e| e.to_string())?;
         Ok(node_id)
     }
 
+    /// Write the nodes appended since the file was last written
+    ///
+    /// JSONL files are opened in append mode and only the new lines are
+    /// written, so persisting an append costs the size of the node rather
+    /// than of the whole ledger; SQLite ledgers insert only the new rows.
+    /// Returns the number of nodes written.
+    pub fn persist_appended(&mut self) -> io::Result<usize> {
+        let path = self
+            .file_path
+            .as_ref()
+            .ok_or_else(|| io::Error::other("File path is not set"))?;
+        let pending = &self.nodes[self.persisted.min(self.nodes.len())..];
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        if is_sqlite_path(path) {
+            SqliteStore::open(path)
+                .and_then(|mut store| store.insert_all(pending))
+                .map_err(io::Error::other)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            append_lines(path, &Self::to_jsonl(pending).map_err(io::Error::other)?)?;
+        }
+
+        let written = pending.len();
+        self.persisted = self.nodes.len();
+        Ok(written)
+    }
+
     /// Export the entire ledger to a file
-    pub fn export_to_file(&self) -> std::io::Result<()> {
+    pub fn export_to_file(&mut self) -> std::io::Result<()> {
         if let Some(path) = &self.file_path {
+            if is_sqlite_path(path) {
+                SqliteStore::open(path)
+                    .and_then(|mut store| store.insert_all(&self.nodes))
+                    .map_err(io::Error::other)?;
+                self.persisted = self.nodes.len();
+                return Ok(());
+            }
+
             let mut file = File::create(path)?;
             let nodes = self.nodes.iter();
 
@@ -242,9 +647,10 @@ impl DagLedger {
                 file.write_all(b"\n")?;
             }
 
+            self.persisted = self.nodes.len();
             Ok(())
         } else {
-            Err(io::Error::new(io::ErrorKind::Other, "File path is not set"))
+            Err(io::Error::other("File path is not set"))
         }
     }
 
@@ -262,11 +668,8 @@ impl DagLedger {
     pub fn find_vote_nodes_for(&self, proposal_id: &str) -> Vec<DagNode> {
         self.nodes
             .iter()
-            .filter(|node| match &node.data {
-                NodeData::VoteCast {
-                    proposal_id: id, ..
-                } if id == proposal_id => true,
-                _ => false,
+            .filter(|node| {
+                matches!(&node.data, NodeData::VoteCast { proposal_id: id, .. } if id == proposal_id)
             })
             .cloned()
             .collect()
@@ -329,6 +732,21 @@ impl DagLedger {
             return Ok(0);
         }
 
+        if is_sqlite_path(path) {
+            let other = Self::load_from_file(path)?;
+            let known: HashSet<String> = self.nodes.iter().map(|n| n.id.clone()).collect();
+            let missing: Vec<DagNode> = other
+                .nodes
+                .into_iter()
+                .filter(|node| !known.contains(&node.id))
+                .collect();
+            let added = missing.len();
+            for node in missing {
+                self.push_node(node);
+            }
+            return Ok(added);
+        }
+
         let file = File::open(path)?;
         let reader = BufReader::new(file);
 
@@ -344,12 +762,12 @@ impl DagLedger {
                 Ok(node) => {
                     // Check if this node is already in our collection
                     if !self.nodes.iter().any(|existing| existing.id == node.id) {
-                        self.nodes.push(node);
+                        self.push_node(node);
                         added += 1;
                     }
                 }
                 Err(e) => {
-                    eprintln!("Error parsing DAG node: {}", e);
+                    tracing::warn!(error = %e, "Skipping unparsable DAG node");
                 }
             }
         }
@@ -504,14 +922,9 @@ impl DagLedger {
         let mut summary = HashMap::new();
 
         for node in &self.nodes {
-            let type_name = match &node.data {
-                NodeData::ProposalCreated { .. } => "ProposalCreated",
-                NodeData::VoteCast { .. } => "VoteCast",
-                NodeData::ProposalExecuted { .. } => "ProposalExecuted",
-                NodeData::TokenMinted { .. } => "TokenMinted",
-            };
-
-            *summary.entry(type_name.to_string()).or_insert(0) += 1;
+            *summary
+                .entry(node.data.type_name().to_string())
+                .or_insert(0) += 1;
         }
 
         summary
@@ -557,4 +970,459 @@ impl DagLedger {
 
         Ok(())
     }
+
+    /// Create the genesis node of a new namespace
+    ///
+    /// `signature` must be the founder's signature over
+    /// `genesis_signing_bytes(namespace, founder, timestamp)`; the ledger
+    /// stores it without checking, since key schemes live with identities.
+    pub fn create_genesis(
+        &mut self,
+        namespace: &str,
+        founder: &str,
+        public_key: &str,
+        signature: &str,
+        timestamp: u64,
+    ) -> Result<String, String> {
+        if self.nodes.iter().any(|n| n.namespace == namespace) {
+            return Err(format!("Namespace '{}' already has nodes", namespace));
+        }
+
+        self.append(DagNode::with_namespace(
+            vec![],
+            NodeData::Genesis {
+                founder: founder.to_string(),
+                public_key: public_key.to_string(),
+                signature: signature.to_string(),
+            },
+            timestamp,
+            namespace.to_string(),
+        ))
+    }
+
+    /// The genesis node of a namespace, if one was created
+    pub fn genesis(&self, namespace: &str) -> Option<&DagNode> {
+        self.nodes
+            .iter()
+            .find(|n| n.namespace == namespace && matches!(n.data, NodeData::Genesis { .. }))
+    }
+
+    /// Append an epoch marker closing over every node of the namespace so far
+    pub fn append_epoch_marker(
+        &mut self,
+        namespace: &str,
+        epoch: &str,
+        timestamp: u64,
+    ) -> Result<String, String> {
+        if self.genesis(namespace).is_none() {
+            return Err(format!("Namespace '{}' has no genesis node", namespace));
+        }
+        if self
+            .epoch_markers(namespace)
+            .iter()
+            .any(|n| matches!(&n.data, NodeData::EpochMarker { epoch: e, .. } if e == epoch))
+        {
+            return Err(format!(
+                "Epoch '{}' already marked in '{}'",
+                epoch, namespace
+            ));
+        }
+
+        let node_count = self.nodes_by_namespace(namespace).len();
+        self.append_on_tips(DagNode::with_namespace(
+            vec![],
+            NodeData::EpochMarker {
+                epoch: epoch.to_string(),
+                node_count,
+            },
+            timestamp,
+            namespace.to_string(),
+        ))
+    }
+
+    /// Epoch markers of a namespace, oldest first
+    pub fn epoch_markers(&self, namespace: &str) -> Vec<&DagNode> {
+        self.nodes
+            .iter()
+            .filter(|n| n.namespace == namespace && matches!(n.data, NodeData::EpochMarker { .. }))
+            .collect()
+    }
+
+    /// The most recent trust anchor (epoch marker or genesis) of a namespace
+    ///
+    /// Sync, pruning, and verification can start from this node instead of
+    /// walking the namespace back to its first event.
+    pub fn latest_anchor(&self, namespace: &str) -> Option<&DagNode> {
+        self.nodes
+            .iter()
+            .rev()
+            .find(|n| n.namespace == namespace && n.is_anchor())
+    }
+
+    /// Merge nodes from a JSONL file into this ledger
+    pub fn merge_from_file(&mut self, path: &Path) -> std::io::Result<MergeReport> {
+        if !path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("File not found: {}", path.display()),
+            ));
+        }
+        let other = Self::load_from_file(path)?;
+        Ok(self.merge_from(&other))
+    }
+
+    /// Merge the nodes of another ledger into this one
+    ///
+    /// Missing nodes are imported in the other ledger's order. An incoming node
+    /// that claims the same logical event as a local node (a second creation or
+    /// execution of the same proposal) is rejected as a conflict, and any of its
+    /// descendants are held back rather than silently unioned. Repeated votes
+    /// are not conflicts, since voters may change their vote. Sealed nodes are
+    /// only checked for conflicts in namespaces whose key this ledger holds.
+    pub fn merge_from(&mut self, other: &DagLedger) -> MergeReport {
+        let mut report = MergeReport::default();
+        let mut known: HashSet<String> = self.nodes.iter().map(|n| n.id.clone()).collect();
+        let mut events: HashMap<String, String> = self
+            .nodes
+            .iter()
+            .filter_map(|n| self.readable(n).event_key().map(|key| (key, n.id.clone())))
+            .collect();
+
+        for node in &other.nodes {
+            if known.contains(&node.id) {
+                report.already_present += 1;
+                continue;
+            }
+
+            if node.parent_ids.iter().any(|p| !known.contains(p)) {
+                report.blocked.push(node.id.clone());
+                continue;
+            }
+
+            if let Some(key) = self.readable(node).event_key() {
+                if let Some(local_id) = events.get(&key) {
+                    report.conflicts.push(MergeConflict {
+                        event: key,
+                        local_id: local_id.clone(),
+                        incoming_id: node.id.clone(),
+                    });
+                    continue;
+                }
+                events.insert(key, node.id.clone());
+            }
+
+            known.insert(node.id.clone());
+            self.push_node(node.clone());
+            report.imported.push(node.id.clone());
+        }
+
+        report
+    }
+}
+
+impl DagNode {
+    /// Key of the logical event this node records, if it must be unique
+    fn event_key(&self) -> Option<String> {
+        match &self.data {
+            NodeData::ProposalCreated { proposal_id, .. } => Some(format!(
+                "{}/ProposalCreated/{}",
+                self.namespace, proposal_id
+            )),
+            NodeData::ProposalExecuted { proposal_id, .. } => Some(format!(
+                "{}/ProposalExecuted/{}",
+                self.namespace, proposal_id
+            )),
+            NodeData::Genesis { .. } => Some(format!("{}/Genesis", self.namespace)),
+            NodeData::EpochMarker { epoch, .. } => {
+                Some(format!("{}/EpochMarker/{}", self.namespace, epoch))
+            }
+            NodeData::BountyCreated { bounty_id, .. } => {
+                Some(format!("{}/BountyCreated/{}", self.namespace, bounty_id))
+            }
+            NodeData::BountyPaid { bounty_id, .. } => {
+                Some(format!("{}/BountyPaid/{}", self.namespace, bounty_id))
+            }
+            NodeData::BountyCancelled { bounty_id } => {
+                Some(format!("{}/BountyCancelled/{}", self.namespace, bounty_id))
+            }
+            NodeData::ExecutionAck {
+                proposal_id,
+                coop_id,
+                ..
+            } => Some(format!(
+                "{}/ExecutionAck/{}/{}",
+                self.namespace, proposal_id, coop_id
+            )),
+            NodeData::VoteCast { .. }
+            | NodeData::TokenMinted { .. }
+            | NodeData::Encrypted { .. }
+            | NodeData::BountyClaimed { .. }
+            | NodeData::BountyVerified { .. }
+            | NodeData::Distributed { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal_node(namespace: &str, proposal_id: &str) -> DagNode {
+        DagNode::with_namespace(
+            vec![],
+            NodeData::ProposalCreated {
+                proposal_id: proposal_id.to_string(),
+                title: format!("Proposal {}", proposal_id),
+            },
+            1,
+            namespace.to_string(),
+        )
+    }
+
+    fn vote_node(namespace: &str, proposal_id: &str, voter: &str) -> DagNode {
+        DagNode::with_namespace(
+            vec![],
+            NodeData::VoteCast {
+                proposal_id: proposal_id.to_string(),
+                voter: voter.to_string(),
+                vote: 1.0,
+            },
+            2,
+            namespace.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_tips_follow_appends() {
+        let mut ledger = DagLedger::new();
+        assert!(ledger.current_tips("coop").is_empty());
+
+        let root = ledger.append_on_tips(proposal_node("coop", "p1")).unwrap();
+        assert_eq!(ledger.current_tips("coop"), vec![root.clone()]);
+
+        let vote = ledger
+            .append_on_tips(vote_node("coop", "p1", "alice"))
+            .unwrap();
+        assert_eq!(ledger.find_by_id(&vote).unwrap().parent_ids, vec![root]);
+        assert_eq!(ledger.current_tips("coop"), vec![vote]);
+    }
+
+    #[test]
+    fn test_tips_are_scoped_to_namespace() {
+        let mut ledger = DagLedger::new();
+        let a = ledger.append_on_tips(proposal_node("a", "p1")).unwrap();
+        let b = ledger.append_on_tips(proposal_node("b", "p2")).unwrap();
+
+        assert!(ledger.find_by_id(&b).unwrap().parent_ids.is_empty());
+        assert_eq!(ledger.current_tips("a"), vec![a]);
+        assert_eq!(ledger.current_tips("b"), vec![b]);
+    }
+
+    #[test]
+    fn test_concurrent_branches_merge_on_next_append() {
+        let mut ledger = DagLedger::new();
+        let root = ledger.append_on_tips(proposal_node("coop", "p1")).unwrap();
+        let v1 = ledger
+            .append(DagNode {
+                parent_ids: vec![root.clone()],
+                ..vote_node("coop", "p1", "alice")
+            })
+            .unwrap();
+        let v2 = ledger
+            .append(DagNode {
+                parent_ids: vec![root],
+                ..vote_node("coop", "p1", "bob")
+            })
+            .unwrap();
+
+        let tips = ledger.current_tips("coop");
+        assert_eq!(tips, vec![v1.clone(), v2.clone()]);
+
+        let merged = ledger
+            .append_on_tips(vote_node("coop", "p1", "carol"))
+            .unwrap();
+        assert_eq!(ledger.find_by_id(&merged).unwrap().parent_ids, vec![v1, v2]);
+    }
+
+    fn execution_node(namespace: &str, proposal_id: &str, success: bool) -> DagNode {
+        DagNode::with_namespace(
+            vec![],
+            NodeData::ProposalExecuted {
+                proposal_id: proposal_id.to_string(),
+                success,
+            },
+            3,
+            namespace.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_merge_imports_missing_nodes() {
+        let mut local = DagLedger::new();
+        local.append_on_tips(proposal_node("coop", "p1")).unwrap();
+        let mut other = local.clone();
+        let vote = other
+            .append_on_tips(vote_node("coop", "p1", "alice"))
+            .unwrap();
+
+        let report = local.merge_from(&other);
+        assert!(report.is_clean());
+        assert_eq!(report.imported, vec![vote]);
+        assert_eq!(report.already_present, 1);
+        assert_eq!(local.nodes().len(), 2);
+    }
+
+    #[test]
+    fn test_merge_reports_conflicting_executions() {
+        let mut local = DagLedger::new();
+        local.append_on_tips(proposal_node("coop", "p1")).unwrap();
+        let mut other = local.clone();
+        let local_exec = local
+            .append_on_tips(execution_node("coop", "p1", true))
+            .unwrap();
+        let other_exec = other
+            .append_on_tips(execution_node("coop", "p1", false))
+            .unwrap();
+        let descendant = other
+            .append_on_tips(vote_node("coop", "p1", "bob"))
+            .unwrap();
+
+        let report = local.merge_from(&other);
+        assert!(report.imported.is_empty());
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].event, "coop/ProposalExecuted/p1");
+        assert_eq!(report.conflicts[0].local_id, local_exec);
+        assert_eq!(report.conflicts[0].incoming_id, other_exec);
+        assert_eq!(report.blocked, vec![descendant]);
+        assert_eq!(local.nodes().len(), 2);
+    }
+
+    #[test]
+    fn test_private_namespace_payloads_are_sealed() {
+        let key = NamespaceKey::generate();
+        let mut ledger = DagLedger::new();
+        ledger.set_namespace_key("private", key.clone());
+
+        let sealed = ledger
+            .append_on_tips(proposal_node("private", "p1"))
+            .unwrap();
+        let public = ledger.append_on_tips(proposal_node("open", "p2")).unwrap();
+
+        let node = ledger.find_by_id(&sealed).unwrap();
+        assert!(node.is_encrypted());
+        assert!(!ledger.find_by_id(&public).unwrap().is_encrypted());
+
+        match node.open(&key).unwrap() {
+            NodeData::ProposalCreated { proposal_id, .. } => assert_eq!(proposal_id, "p1"),
+            other => panic!("unexpected payload: {:?}", other),
+        }
+        assert!(node.open(&NamespaceKey::generate()).is_err());
+
+        let readable = ledger.readable_nodes();
+        assert!(readable.iter().all(|n| !n.is_encrypted()));
+    }
+
+    #[test]
+    fn test_sealed_payload_is_bound_to_node() {
+        let key = NamespaceKey::generate();
+        let mut node = proposal_node("private", "p1");
+        node.seal(&key).unwrap();
+
+        let moved = DagNode {
+            timestamp: node.timestamp + 1,
+            ..node.clone()
+        };
+        assert!(node.open(&key).is_ok());
+        assert!(moved.open(&key).is_err());
+    }
+
+    #[test]
+    fn test_sqlite_path_round_trips_nodes() {
+        let path = std::env::temp_dir().join(format!("icn-ledger-test-{}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut ledger = DagLedger::with_path(path.clone());
+        let proposal = ledger.append_on_tips(proposal_node("coop", "p1")).unwrap();
+        ledger.export_to_file().unwrap();
+        let mut vote = vote_node("coop", "p1", "alice");
+        vote.parent_ids = vec![proposal.clone()];
+        let vote = ledger.append_and_persist(vote).unwrap();
+
+        let reloaded = DagLedger::load_from_file(&path).unwrap();
+        assert_eq!(reloaded.all_node_ids(), vec![proposal, vote.clone()]);
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.count().unwrap(), 2);
+        assert_eq!(store.find_by_proposal("p1").unwrap().len(), 2);
+        assert_eq!(store.find_by_id(&vote).unwrap().unwrap().id, vote);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_genesis_and_epoch_anchors() {
+        let mut ledger = DagLedger::new();
+        let genesis = ledger
+            .create_genesis("coop", "did:key:founder", "zkey", "zsig", 0)
+            .unwrap();
+        assert!(ledger
+            .create_genesis("coop", "did:key:other", "zkey", "zsig", 0)
+            .is_err());
+
+        ledger.append_on_tips(proposal_node("coop", "p1")).unwrap();
+        assert_eq!(ledger.latest_anchor("coop").unwrap().id, genesis);
+
+        let epoch = ledger.append_epoch_marker("coop", "2026", 10).unwrap();
+        assert!(ledger.append_epoch_marker("coop", "2026", 11).is_err());
+        assert!(ledger.append_epoch_marker("other", "2026", 11).is_err());
+
+        let marker = ledger.latest_anchor("coop").unwrap();
+        assert_eq!(marker.id, epoch);
+        match &marker.data {
+            NodeData::EpochMarker { node_count, .. } => assert_eq!(*node_count, 2),
+            other => panic!("unexpected payload: {:?}", other),
+        }
+        assert_eq!(ledger.current_tips("coop"), vec![epoch]);
+    }
+
+    #[test]
+    fn test_subscribers_receive_appended_nodes() {
+        let mut ledger = DagLedger::new();
+        let rx = ledger.subscribe();
+        let id = ledger.append_on_tips(proposal_node("coop", "p1")).unwrap();
+        assert_eq!(rx.try_recv().unwrap().id, id);
+
+        // Appends to a clone are not broadcast
+        let mut fork = ledger.clone();
+        fork.append_on_tips(vote_node("coop", "p1", "alice"))
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+
+        let mut other = DagLedger::new();
+        let merged = other.append_on_tips(proposal_node("coop2", "p2")).unwrap();
+        ledger.merge_from(&other);
+        assert_eq!(rx.try_recv().unwrap().id, merged);
+
+        drop(rx);
+        ledger
+            .append_on_tips(vote_node("coop", "p1", "bob"))
+            .unwrap();
+        assert!(ledger.subscribers.is_empty());
+    }
+
+    #[test]
+    fn test_jsonl_round_trip_rejects_bad_lines() {
+        let mut ledger = DagLedger::new();
+        ledger.append_on_tips(proposal_node("coop", "p1")).unwrap();
+        ledger
+            .append_on_tips(vote_node("coop", "p1", "alice"))
+            .unwrap();
+
+        let text = DagLedger::to_jsonl(ledger.nodes()).unwrap();
+        let parsed = DagLedger::from_jsonl(&text).unwrap();
+        assert_eq!(parsed.all_node_ids(), ledger.all_node_ids());
+
+        let err = DagLedger::from_jsonl(&format!("{}not json\n", text)).unwrap_err();
+        assert!(err.contains("line 3"));
+    }
 }
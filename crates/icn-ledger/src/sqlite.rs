@@ -0,0 +1,212 @@
+//! SQLite persistence for large ledgers
+//!
+//! JSONL ledgers have to be read in full to answer any query, which becomes
+//! slow once a cooperative has hundreds of thousands of nodes. A ledger
+//! whose path ends in `.db`, `.sqlite`, or `.sqlite3` is instead kept in an
+//! SQLite database: appends insert a single row, and the namespace, node
+//! type, proposal ID, and timestamp columns are indexed for queries that
+//! should not have to scan the whole DAG.
+
+use crate::{DagNode, NodeData};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+/// File extensions that select the SQLite store
+const SQLITE_EXTENSIONS: [&str; 3] = ["db", "sqlite", "sqlite3"];
+
+/// Whether a ledger path should be stored in SQLite rather than JSONL
+pub fn is_sqlite_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| SQLITE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Ledger nodes stored in an SQLite database
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Open or create the database at `path`, creating the schema if needed
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn =
+            Connection::open(path).map_err(|e| format!("Failed to open ledger database: {}", e))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS nodes (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                id TEXT NOT NULL UNIQUE,
+                namespace TEXT NOT NULL,
+                node_type TEXT NOT NULL,
+                proposal_id TEXT,
+                timestamp INTEGER NOT NULL,
+                body TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_nodes_namespace ON nodes(namespace);
+            CREATE INDEX IF NOT EXISTS idx_nodes_type ON nodes(node_type);
+            CREATE INDEX IF NOT EXISTS idx_nodes_proposal ON nodes(proposal_id);
+            CREATE INDEX IF NOT EXISTS idx_nodes_timestamp ON nodes(timestamp);",
+        )
+        .map_err(|e| format!("Failed to create ledger schema: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    /// Insert a node, ignoring it if a node with the same ID is already stored
+    ///
+    /// Returns whether the node was newly inserted.
+    pub fn insert(&self, node: &DagNode) -> Result<bool, String> {
+        insert_node(&self.conn, node)
+    }
+
+    /// Insert many nodes in a single transaction, returning how many were new
+    pub fn insert_all<'a>(
+        &mut self,
+        nodes: impl IntoIterator<Item = &'a DagNode>,
+    ) -> Result<usize, String> {
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        let mut added = 0;
+        for node in nodes {
+            if insert_node(&tx, node)? {
+                added += 1;
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        Ok(added)
+    }
+
+    /// Replace every stored node with `nodes`, in a single transaction
+    ///
+    /// Used by compaction, so the rows keep the ledger's new order.
+    pub fn replace_all<'a>(
+        &mut self,
+        nodes: impl IntoIterator<Item = &'a DagNode>,
+    ) -> Result<usize, String> {
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        tx.execute("DELETE FROM nodes", params![])
+            .map_err(|e| format!("Failed to clear ledger database: {}", e))?;
+        let mut added = 0;
+        for node in nodes {
+            if insert_node(&tx, node)? {
+                added += 1;
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        Ok(added)
+    }
+
+    /// Load every node in insertion order
+    pub fn load_all(&self) -> Result<Vec<DagNode>, String> {
+        self.query("SELECT body FROM nodes ORDER BY seq", params![])
+    }
+
+    /// Find a node by its ID
+    pub fn find_by_id(&self, id: &str) -> Result<Option<DagNode>, String> {
+        let body: Option<String> = self
+            .conn
+            .query_row("SELECT body FROM nodes WHERE id = ?1", params![id], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(|e| format!("Failed to query node {}: {}", id, e))?;
+        body.map(|b| parse_body(&b)).transpose()
+    }
+
+    /// All nodes in a namespace, in insertion order
+    pub fn find_by_namespace(&self, namespace: &str) -> Result<Vec<DagNode>, String> {
+        self.query(
+            "SELECT body FROM nodes WHERE namespace = ?1 ORDER BY seq",
+            params![namespace],
+        )
+    }
+
+    /// All nodes recording events for a proposal, in insertion order
+    pub fn find_by_proposal(&self, proposal_id: &str) -> Result<Vec<DagNode>, String> {
+        self.query(
+            "SELECT body FROM nodes WHERE proposal_id = ?1 ORDER BY seq",
+            params![proposal_id],
+        )
+    }
+
+    /// Nodes whose timestamp falls within `[from, to]`, in insertion order
+    pub fn find_in_time_range(&self, from: u64, to: u64) -> Result<Vec<DagNode>, String> {
+        self.query(
+            "SELECT body FROM nodes WHERE timestamp BETWEEN ?1 AND ?2 ORDER BY seq",
+            params![from as i64, to as i64],
+        )
+    }
+
+    /// Number of stored nodes
+    pub fn count(&self) -> Result<usize, String> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM nodes", [], |row| row.get::<_, i64>(0))
+            .map(|n| n as usize)
+            .map_err(|e| format!("Failed to count nodes: {}", e))
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<DagNode>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        let bodies = stmt
+            .query_map(params, |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to query nodes: {}", e))?;
+
+        let mut nodes = Vec::new();
+        for body in bodies {
+            let body = body.map_err(|e| format!("Failed to read node: {}", e))?;
+            nodes.push(parse_body(&body)?);
+        }
+        Ok(nodes)
+    }
+}
+
+fn insert_node(conn: &Connection, node: &DagNode) -> Result<bool, String> {
+    let body = serde_json::to_string(node).map_err(|e| format!("Failed to serialize: {}", e))?;
+    let inserted = conn
+        .execute(
+            "INSERT OR IGNORE INTO nodes (id, namespace, node_type, proposal_id, timestamp, body)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                node.id,
+                node.namespace,
+                node.data.type_name(),
+                proposal_id(&node.data),
+                node.timestamp as i64,
+                body
+            ],
+        )
+        .map_err(|e| format!("Failed to insert node {}: {}", node.id, e))?;
+    Ok(inserted > 0)
+}
+
+fn parse_body(body: &str) -> Result<DagNode, String> {
+    serde_json::from_str(body).map_err(|e| format!("Error parsing DAG node: {}", e))
+}
+
+fn proposal_id(data: &NodeData) -> Option<&str> {
+    match data {
+        NodeData::ProposalCreated { proposal_id, .. }
+        | NodeData::VoteCast { proposal_id, .. }
+        | NodeData::ProposalExecuted { proposal_id, .. }
+        | NodeData::ExecutionAck { proposal_id, .. } => Some(proposal_id),
+        NodeData::TokenMinted { .. }
+        | NodeData::Genesis { .. }
+        | NodeData::EpochMarker { .. }
+        | NodeData::Encrypted { .. }
+        | NodeData::BountyCreated { .. }
+        | NodeData::BountyClaimed { .. }
+        | NodeData::BountyVerified { .. }
+        | NodeData::BountyPaid { .. }
+        | NodeData::BountyCancelled { .. }
+        | NodeData::Distributed { .. } => None,
+    }
+}
@@ -0,0 +1,95 @@
+//! Activity statistics over a ledger
+
+use crate::{DagLedger, NodeData};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Aggregate counts describing a ledger's contents and activity
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LedgerStats {
+    /// Total number of nodes
+    pub total_nodes: usize,
+    /// Node counts per event type
+    pub by_type: BTreeMap<String, usize>,
+    /// Node counts per namespace
+    pub by_namespace: BTreeMap<String, usize>,
+    /// Width in seconds of each activity bucket
+    pub bucket_secs: u64,
+    /// Node counts per time bucket, keyed by the bucket's start timestamp
+    pub activity: BTreeMap<u64, usize>,
+    /// Voters with the most votes cast, most active first
+    pub top_voters: Vec<(String, usize)>,
+}
+
+impl DagLedger {
+    /// Compute statistics over the ledger
+    ///
+    /// Activity is grouped into buckets of `bucket_secs` seconds, and at most
+    /// `top_voters` voters are listed. Votes in sealed namespaces are only
+    /// counted when this ledger holds the namespace key.
+    pub fn stats(&self, bucket_secs: u64, top_voters: usize) -> LedgerStats {
+        let bucket_secs = bucket_secs.max(1);
+        let mut stats = LedgerStats {
+            total_nodes: self.nodes.len(),
+            by_type: self.get_node_type_summary().into_iter().collect(),
+            bucket_secs,
+            ..Default::default()
+        };
+        let mut votes: HashMap<String, usize> = HashMap::new();
+
+        for node in self.readable_nodes() {
+            *stats
+                .by_namespace
+                .entry(node.namespace.clone())
+                .or_insert(0) += 1;
+            *stats
+                .activity
+                .entry(node.timestamp - node.timestamp % bucket_secs)
+                .or_insert(0) += 1;
+            if let NodeData::VoteCast { voter, .. } = &node.data {
+                *votes.entry(voter.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let mut voters: Vec<(String, usize)> = votes.into_iter().collect();
+        voters.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        voters.truncate(top_voters);
+        stats.top_voters = voters;
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DagLedger, DagNode, NodeData};
+
+    fn vote(voter: &str, timestamp: u64) -> DagNode {
+        DagNode::with_namespace(
+            vec![],
+            NodeData::VoteCast {
+                proposal_id: "p1".to_string(),
+                voter: voter.to_string(),
+                vote: 1.0,
+            },
+            timestamp,
+            "coop".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_stats_counts_and_buckets() {
+        let mut ledger = DagLedger::new();
+        for (voter, ts) in [("alice", 10), ("bob", 20), ("alice", 130)] {
+            ledger.append_on_tips(vote(voter, ts)).unwrap();
+        }
+
+        let stats = ledger.stats(100, 1);
+        assert_eq!(stats.total_nodes, 3);
+        assert_eq!(stats.by_type["VoteCast"], 3);
+        assert_eq!(stats.by_namespace["coop"], 3);
+        assert_eq!(stats.activity[&0], 2);
+        assert_eq!(stats.activity[&100], 1);
+        assert_eq!(stats.top_voters, vec![("alice".to_string(), 2)]);
+    }
+}
@@ -0,0 +1,399 @@
+//! Structural integrity checks over a ledger
+//!
+//! `DagLedger::verify` checks the nodes already loaded. `verify_file_integrity`
+//! also reports lines of a JSONL file that are not nodes at all, which
+//! `DagLedger::load_from_file` skips, and gives each problem its line so the
+//! first corrupted node can be found. Both reports carry the ledger's Merkle
+//! root, to compare against a root recorded earlier or held by a peer.
+
+use crate::{is_sqlite_path, DagLedger, DagNode, NodeData};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Hash prefixes keeping Merkle leaves and branches apart
+const LEAF_PREFIX: u8 = 0;
+const BRANCH_PREFIX: u8 = 1;
+
+/// Kind of problem found while verifying a ledger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// The node's ID is not the hash of its contents
+    HashMismatch,
+    /// Another node has the same ID
+    DuplicateId,
+    /// A parent ID does not refer to any node
+    MissingParent,
+    /// A parent is timestamped after the node itself
+    ParentAfterChild,
+    /// A genesis node has parents or is not its namespace's first node
+    MisplacedGenesis,
+    /// A genesis signature does not verify against the founder's key; not
+    /// raised by `DagLedger::verify`, for callers that check signatures
+    InvalidSignature,
+    /// A line of the ledger file is not a node
+    Unreadable,
+}
+
+/// A problem with one node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyIssue {
+    /// Empty for unreadable lines
+    pub node_id: String,
+    pub kind: IssueKind,
+    pub message: String,
+    /// Line of the ledger file, when verifying a JSONL file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+/// Result of verifying a ledger
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerifyReport {
+    /// Number of nodes checked
+    pub checked: usize,
+    /// Problems found, in ledger order
+    pub issues: Vec<VerifyIssue>,
+    /// Merkle root of the ledger, see `DagLedger::compute_merkle_root`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merkle_root: Option<String>,
+}
+
+impl VerifyReport {
+    /// Whether no problems were found
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The problem closest to the start of the ledger
+    pub fn first_issue(&self) -> Option<&VerifyIssue> {
+        self.issues.first()
+    }
+}
+
+/// Problems found, with the index of the node each is about
+struct Issues(Vec<(usize, VerifyIssue)>);
+
+impl Issues {
+    fn push(&mut self, index: usize, node: &DagNode, kind: IssueKind, message: String) {
+        self.0.push((
+            index,
+            VerifyIssue {
+                node_id: node.id.clone(),
+                kind,
+                message,
+                line: None,
+            },
+        ));
+    }
+}
+
+impl DagNode {
+    /// Hash of the node's contents, which its ID should be
+    fn content_hash(&self) -> String {
+        // IDs are computed before they are set, so hash with the ID cleared
+        let mut unsigned = self.clone();
+        unsigned.id = String::new();
+        unsigned.compute_id()
+    }
+
+    /// Whether the node's ID is the hash of its contents
+    pub fn has_valid_id(&self) -> bool {
+        self.content_hash() == self.id
+    }
+}
+
+impl DagLedger {
+    /// Check that every node is intact and linked into the DAG
+    ///
+    /// Verifies content hashes, ID uniqueness, that parents exist and
+    /// precede their children, and that genesis nodes open their namespace.
+    /// Sealed payloads are checked as stored, so no keys are needed. Genesis
+    /// signatures are not checked here, since key schemes live with identities.
+    pub fn verify(&self) -> VerifyReport {
+        VerifyReport {
+            checked: self.nodes.len(),
+            issues: self
+                .check_nodes()
+                .0
+                .into_iter()
+                .map(|(_, issue)| issue)
+                .collect(),
+            merkle_root: None,
+        }
+    }
+
+    /// `verify`, along with the ledger's Merkle root
+    pub fn verify_integrity(&self) -> VerifyReport {
+        VerifyReport {
+            merkle_root: self.compute_merkle_root(),
+            ..self.verify()
+        }
+    }
+
+    /// Verify the ledger file at `path` line by line
+    ///
+    /// Unlike loading the file, lines that are not nodes are reported as
+    /// `Unreadable` rather than skipped. `check` runs on every node for
+    /// problems `verify` does not look for, such as genesis signatures.
+    /// Issues come in file order, each with its line; SQLite ledgers have no
+    /// lines and are verified as loaded. A missing file is an empty ledger,
+    /// as `load_from_file` treats it.
+    pub fn verify_file_integrity<F>(path: &Path, check: F) -> io::Result<VerifyReport>
+    where
+        F: Fn(&DagNode) -> Option<VerifyIssue>,
+    {
+        if is_sqlite_path(path) || !path.exists() {
+            let ledger = Self::load_from_file(path)?;
+            let mut report = ledger.verify_integrity();
+            report.issues.extend(ledger.nodes.iter().filter_map(check));
+            return Ok(report);
+        }
+
+        let text = fs::read_to_string(path)?;
+        let mut ledger = DagLedger::new();
+        let mut node_lines = Vec::new();
+        let mut issues = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<DagNode>(line) {
+                Ok(node) => {
+                    ledger.nodes.push(node);
+                    node_lines.push(index + 1);
+                }
+                Err(e) => issues.push(VerifyIssue {
+                    node_id: String::new(),
+                    kind: IssueKind::Unreadable,
+                    message: format!("Not a DAG node: {}", e),
+                    line: Some(index + 1),
+                }),
+            }
+        }
+
+        let mut found = ledger.check_nodes();
+        for (index, node) in ledger.nodes.iter().enumerate() {
+            if let Some(issue) = check(node) {
+                found.0.push((index, issue));
+            }
+        }
+        issues.extend(found.0.into_iter().map(|(index, issue)| VerifyIssue {
+            line: Some(node_lines[index]),
+            ..issue
+        }));
+        // Stable, so a node's own issues keep their order
+        issues.sort_by_key(|issue| issue.line);
+
+        Ok(VerifyReport {
+            checked: ledger.nodes.len(),
+            issues,
+            merkle_root: ledger.compute_merkle_root(),
+        })
+    }
+
+    /// Root of a Merkle tree over the nodes' content hashes, in ledger order
+    ///
+    /// Hashes are recomputed rather than read from the IDs, so the root
+    /// changes if any node is altered, added, removed or reordered, and two
+    /// ledgers with the same root hold the same nodes. Leaves and branches
+    /// are hashed with different prefixes, and an unpaired hash is carried
+    /// up to the next level as is. `None` for an empty ledger.
+    pub fn compute_merkle_root(&self) -> Option<String> {
+        let mut level: Vec<[u8; 32]> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut hasher = Sha256::new();
+                hasher.update([LEAF_PREFIX]);
+                hasher.update(node.content_hash().as_bytes());
+                hasher.finalize().into()
+            })
+            .collect();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => {
+                        let mut hasher = Sha256::new();
+                        hasher.update([BRANCH_PREFIX]);
+                        hasher.update(left);
+                        hasher.update(right);
+                        hasher.finalize().into()
+                    }
+                    _ => pair[0],
+                })
+                .collect();
+        }
+        level.first().map(hex::encode)
+    }
+
+    /// The checks behind `verify`, with the index of each issue's node
+    fn check_nodes(&self) -> Issues {
+        let mut issues = Issues(Vec::new());
+        let by_id: HashMap<&str, &DagNode> =
+            self.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+        let mut seen = HashSet::new();
+        let mut opened = HashSet::new();
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if !node.has_valid_id() {
+                issues.push(
+                    index,
+                    node,
+                    IssueKind::HashMismatch,
+                    "ID does not match the node's contents".to_string(),
+                );
+            }
+            if !seen.insert(node.id.as_str()) {
+                issues.push(
+                    index,
+                    node,
+                    IssueKind::DuplicateId,
+                    "ID appears more than once".to_string(),
+                );
+            }
+
+            for parent_id in &node.parent_ids {
+                match by_id.get(parent_id.as_str()) {
+                    None => issues.push(
+                        index,
+                        node,
+                        IssueKind::MissingParent,
+                        format!("Parent {} not found", parent_id),
+                    ),
+                    Some(parent) if parent.timestamp > node.timestamp => issues.push(
+                        index,
+                        node,
+                        IssueKind::ParentAfterChild,
+                        format!(
+                            "Parent {} is timestamped {} after the node",
+                            parent_id,
+                            parent.timestamp - node.timestamp
+                        ),
+                    ),
+                    Some(_) => {}
+                }
+            }
+
+            let first_in_namespace = opened.insert(node.namespace.as_str());
+            if matches!(node.data, NodeData::Genesis { .. })
+                && (!node.parent_ids.is_empty() || !first_in_namespace)
+            {
+                issues.push(
+                    index,
+                    node,
+                    IssueKind::MisplacedGenesis,
+                    format!(
+                        "Genesis of '{}' must be the namespace's first node and have no parents",
+                        node.namespace
+                    ),
+                );
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote(voter: &str, timestamp: u64) -> DagNode {
+        DagNode::with_namespace(
+            vec![],
+            NodeData::VoteCast {
+                proposal_id: "p1".to_string(),
+                voter: voter.to_string(),
+                vote: 1.0,
+            },
+            timestamp,
+            "coop".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_verify_accepts_appended_nodes() {
+        let mut ledger = DagLedger::new();
+        ledger
+            .create_genesis("coop", "did:key:founder", "zKey", "zSig", 1)
+            .unwrap();
+        ledger.append_on_tips(vote("alice", 10)).unwrap();
+        ledger.append_on_tips(vote("bob", 20)).unwrap();
+
+        let report = ledger.verify();
+        assert_eq!(report.checked, 3);
+        assert!(report.is_valid(), "{:?}", report.issues);
+    }
+
+    #[test]
+    fn test_verify_reports_tampering_and_broken_links() {
+        let mut ledger = DagLedger::new();
+        let first = ledger.append_on_tips(vote("alice", 10)).unwrap();
+        ledger.append_on_tips(vote("bob", 5)).unwrap();
+
+        let mut nodes = ledger.export_all();
+        if let NodeData::VoteCast { vote, .. } = &mut nodes[0].data {
+            *vote = 0.0;
+        }
+        let mut orphan = vote("carol", 30);
+        orphan.parent_ids.push("missing".to_string());
+        orphan.id = orphan.compute_id();
+        nodes.push(orphan);
+
+        let tampered = DagLedger::from_jsonl(&DagLedger::to_jsonl(&nodes).unwrap()).unwrap();
+        let kinds: Vec<(IssueKind, bool)> = tampered
+            .verify()
+            .issues
+            .iter()
+            .map(|issue| (issue.kind, issue.node_id == first))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (IssueKind::HashMismatch, true),
+                (IssueKind::ParentAfterChild, false),
+                (IssueKind::MissingParent, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_integrity_finds_first_corrupted_line() {
+        let mut ledger = DagLedger::new();
+        ledger.append_on_tips(vote("alice", 10)).unwrap();
+        ledger.append_on_tips(vote("bob", 20)).unwrap();
+        ledger.append_on_tips(vote("carol", 30)).unwrap();
+        let root = ledger.compute_merkle_root().unwrap();
+        assert_eq!(ledger.verify_integrity().merkle_root, Some(root.clone()));
+        assert_eq!(DagLedger::new().compute_merkle_root(), None);
+
+        let path = std::env::temp_dir().join(format!("icn-verify-{}.jsonl", std::process::id()));
+        let jsonl = DagLedger::to_jsonl(ledger.nodes()).unwrap();
+        fs::write(&path, &jsonl).unwrap();
+        let report = DagLedger::verify_file_integrity(&path, |_| None).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.merkle_root, Some(root.clone()));
+
+        // Tamper with the second node and break the third line
+        let lines: Vec<&str> = jsonl.lines().collect();
+        let tampered = format!(
+            "{}\n{}\n{{\"id\": \n",
+            lines[0],
+            lines[1].replace("bob", "mallory")
+        );
+        fs::write(&path, tampered).unwrap();
+        let report = DagLedger::verify_file_integrity(&path, |_| None).unwrap();
+        let first = report.first_issue().unwrap();
+        assert_eq!((first.kind, first.line), (IssueKind::HashMismatch, Some(2)));
+        assert_eq!(report.issues[1].kind, IssueKind::Unreadable);
+        assert_eq!(report.issues[1].line, Some(3));
+        assert_ne!(report.merkle_root, Some(root));
+        fs::remove_file(&path).unwrap();
+    }
+}